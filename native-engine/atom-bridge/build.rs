@@ -1,11 +1,18 @@
 use std::env;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Set to 1 to have this script configure and build `cpp/` with CMake,
+/// instead of only looking for a library built by hand.
+const BUILD_CPP_ENV: &str = "ATOM_BRIDGE_BUILD_CPP";
 
 fn main() {
     println!("cargo:rerun-if-changed=src/lib.rs");
     println!("cargo:rerun-if-changed=src/ffi.rs");
     println!("cargo:rerun-if-changed=cpp/");
     println!("cargo:rerun-if-changed=include/");
+    println!("cargo:rerun-if-env-changed={}", BUILD_CPP_ENV);
+    println!("cargo:rerun-if-env-changed=VULKAN_SDK");
     
     // Register custom cfg for -Zcheck-cfg compatibility
     println!("cargo:rustc-check-cfg=cfg(atom_cpp_linked)");
@@ -35,17 +42,28 @@ fn main() {
 
     let lib_name = if target_os == "windows" { "atom_bridge.lib" } else { "libatom_bridge.a" };
     let mut found_lib = false;
-    
-    for search_path in &lib_search_paths {
-        let lib_path = search_path.join(lib_name);
+
+    if env::var(BUILD_CPP_ENV).is_ok_and(|v| v == "1") {
+        let lib_dir = build_cpp(Path::new(&manifest_dir), &target_os, lib_name);
+        println!("cargo:warning=Built C++ library in: {:?}", lib_dir);
+        println!("cargo:rustc-link-search=native={}", lib_dir.display());
+        println!("cargo:rustc-link-lib=static=atom_bridge");
+        println!("cargo:rustc-cfg=atom_cpp_linked");
+        found_lib = true;
+    }
+
+    if !found_lib {
+        for search_path in &lib_search_paths {
+            let lib_path = search_path.join(lib_name);
         
-        if lib_path.exists() {
-            println!("cargo:warning=Found pre-built C++ library at: {:?}", lib_path);
-            println!("cargo:rustc-link-search=native={}", search_path.display());
-            println!("cargo:rustc-link-lib=static=atom_bridge");
-            println!("cargo:rustc-cfg=atom_cpp_linked");
-            found_lib = true;
-            break;
+            if lib_path.exists() {
+                println!("cargo:warning=Found pre-built C++ library at: {:?}", lib_path);
+                println!("cargo:rustc-link-search=native={}", search_path.display());
+                println!("cargo:rustc-link-lib=static=atom_bridge");
+                println!("cargo:rustc-cfg=atom_cpp_linked");
+                found_lib = true;
+                break;
+            }
         }
     }
 
@@ -78,9 +96,233 @@ fn main() {
         println!("cargo:warning=");
         println!("cargo:warning=To enable the custom Vulkan renderer later:");
         println!("cargo:warning=  1. Install Vulkan SDK");
-        println!("cargo:warning=  2. Run: cmake -B cpp/build -G Ninja && cmake --build cpp/build");
-        println!("cargo:warning=  3. Rebuild the game");
+        println!("cargo:warning=  2. Rebuild with {}=1 to build the C++ library as part of cargo build", BUILD_CPP_ENV);
+        println!("cargo:warning=     (or run: cmake -B cpp/build -G Ninja && cmake --build cpp/build, then rebuild)");
     }
 
     println!("cargo:warning=atom-bridge build.rs completed");
 }
+
+
+/// Configures and builds `cpp/` with CMake and returns the directory with
+/// the library. The build tree is kept in OUT_DIR, so each profile and
+/// target has its own and later builds only recompile what changed. Stops
+/// the build with what's missing if the toolchain isn't there.
+fn build_cpp(manifest_dir: &Path, target_os: &str, lib_name: &str) -> PathBuf {
+    let source_dir = manifest_dir.join("cpp");
+    if !source_dir.join("CMakeLists.txt").exists() {
+        fail(&[
+            &format!("{}=1 but there is no {}", BUILD_CPP_ENV, source_dir.join("CMakeLists.txt").display()),
+            "The C++ renderer sources are synced with the engine; sync again or unset the variable.",
+        ]);
+    }
+
+    let cmake_version = match Command::new("cmake").arg("--version").output() {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .to_string(),
+        _ => fail(&[
+            &format!("{}=1 but CMake wasn't found on PATH.", BUILD_CPP_ENV),
+            "Install CMake 3.20 or newer (https://cmake.org/download/, or the \"C++ CMake tools\"",
+            "component of Visual Studio) and make sure `cmake --version` works in this shell.",
+        ]),
+    };
+    println!("cargo:warning=Using {}", cmake_version);
+
+    let vulkan_sdk = find_vulkan_sdk(target_os);
+    match &vulkan_sdk {
+        Some(sdk) => println!("cargo:warning=Building against Vulkan SDK: {}", sdk.display()),
+        // Linux and macOS packages put the headers where CMake looks anyway
+        None if target_os != "windows" => println!("cargo:warning=VULKAN_SDK not set - using the system Vulkan"),
+        None => fail(&[
+            &format!("{}=1 but no Vulkan SDK was found.", BUILD_CPP_ENV),
+            "Install it from https://vulkan.lunarg.com/sdk/home#windows (the launcher does this too)",
+            "and set VULKAN_SDK to its directory, e.g. C:\\VulkanSDK\\1.3.290.0.",
+        ]),
+    }
+
+    // main() only links the SDK named by VULKAN_SDK
+    if let Some(sdk) = vulkan_sdk.as_ref().filter(|_| target_os == "windows" && env::var_os("VULKAN_SDK").is_none()) {
+        println!("cargo:rustc-link-search=native={}", sdk.join("Lib").display());
+        println!("cargo:rustc-link-lib=vulkan-1");
+    }
+
+    // Rust links the release C runtime on MSVC, so a Debug library wouldn't link
+    let build_type = if env::var("PROFILE").as_deref() == Ok("release") {
+        "Release"
+    } else {
+        "RelWithDebInfo"
+    };
+    let out_dir = PathBuf::from(env::var("OUT_DIR").expect("cargo sets OUT_DIR"));
+    let build_dir = out_dir.join("cpp-build");
+    std::fs::create_dir_all(&build_dir).expect("failed to create the C++ build directory");
+
+    // A changed SDK or build type needs a fresh configure, not CMake's cached paths
+    let settings = format!(
+        "{}\n{}\n",
+        build_type,
+        vulkan_sdk
+            .as_deref()
+            .map(Path::display)
+            .map(|d| d.to_string())
+            .unwrap_or_default()
+    );
+    let settings_path = build_dir.join("atom-bridge-settings.txt");
+    if std::fs::read_to_string(&settings_path).ok().as_deref() != Some(settings.as_str()) {
+        let _ = std::fs::remove_file(build_dir.join("CMakeCache.txt"));
+    }
+
+    if !build_dir.join("CMakeCache.txt").exists() {
+        let mut configure = Command::new("cmake");
+        configure
+            .arg("-S")
+            .arg(&source_dir)
+            .arg("-B")
+            .arg(&build_dir)
+            .arg(format!("-DCMAKE_BUILD_TYPE={}", build_type))
+            .arg("-DCMAKE_MSVC_RUNTIME_LIBRARY=MultiThreadedDLL");
+        // Ninja needs the compiler on PATH; otherwise CMake picks Visual Studio itself
+        let has_compiler = target_os != "windows" || which("cl.exe");
+        if has_compiler
+            && which(if target_os == "windows" {
+                "ninja.exe"
+            } else {
+                "ninja"
+            })
+        {
+            configure.args(["-G", "Ninja"]);
+        }
+        if let Some(sdk) = &vulkan_sdk {
+            configure.env("VULKAN_SDK", sdk);
+        }
+        println!("cargo:warning=Configuring C++ renderer ({})...", build_type);
+        run_cmake(&mut configure, "configure");
+        std::fs::write(&settings_path, &settings).expect("failed to record the C++ build settings");
+    }
+
+    let jobs = env::var("NUM_JOBS").unwrap_or_else(|_| "1".to_string());
+    let mut build = Command::new("cmake");
+    build
+        .arg("--build")
+        .arg(&build_dir)
+        .args(["--config", build_type, "--parallel", &jobs]);
+    if let Some(sdk) = &vulkan_sdk {
+        build.env("VULKAN_SDK", sdk);
+    }
+    println!("cargo:warning=Building C++ renderer...");
+    run_cmake(&mut build, "build");
+
+    // Single-config generators put it in lib/, Visual Studio in lib/<config>/
+    let candidates = [
+        build_dir.join("lib").join(build_type),
+        build_dir.join("lib"),
+        build_dir.join(build_type),
+        build_dir.clone(),
+    ];
+    match candidates
+        .into_iter()
+        .find(|dir| dir.join(lib_name).exists())
+    {
+        Some(dir) => dir,
+        None => fail(&[
+            &format!(
+                "The CMake build succeeded but {} isn't in {}.",
+                lib_name,
+                build_dir.display()
+            ),
+            "Check that cpp/CMakeLists.txt still builds the static atom_bridge target.",
+        ]),
+    }
+}
+
+/// Runs a CMake step, showing its output and what's likely missing if it fails.
+fn run_cmake(command: &mut Command, step: &str) {
+    let output = match command.output() {
+        Ok(output) => output,
+        Err(e) => fail(&[&format!("Failed to run CMake {}: {}", step, e)]),
+    };
+    if output.status.success() {
+        return;
+    }
+
+    let log = format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    for line in log.lines() {
+        eprintln!("{}", line);
+    }
+    let hint = if log.contains("No CMAKE_CXX_COMPILER could be found")
+        || log.contains("could not find any instance of Visual Studio")
+    {
+        "No C++ compiler was found. On Windows install Visual Studio 2022 Build Tools with \"Desktop \
+         development with C++\" (the launcher does this), or build from a Developer Command Prompt."
+    } else if log.contains("Could NOT find Vulkan") {
+        "CMake couldn't find Vulkan. Check VULKAN_SDK points at an installed SDK \
+         (it should contain Include/vulkan/vulkan.h)."
+    } else if log.contains("CMake 3.") && log.contains("or higher is required") {
+        "This CMake is too old for cpp/CMakeLists.txt; install a newer one."
+    } else {
+        "See the CMake output above."
+    };
+    fail(&[
+        &format!(
+            "CMake {} of the C++ renderer failed (exit code {:?}).",
+            step,
+            output.status.code()
+        ),
+        hint,
+    ]);
+}
+
+/// `VULKAN_SDK`, or on Windows the newest SDK in C:\VulkanSDK.
+fn find_vulkan_sdk(target_os: &str) -> Option<PathBuf> {
+    if let Some(sdk) = env::var_os("VULKAN_SDK")
+        .map(PathBuf::from)
+        .filter(|sdk| sdk.exists())
+    {
+        return Some(sdk);
+    }
+    if target_os != "windows" {
+        return None;
+    }
+    let mut versions: Vec<PathBuf> = std::fs::read_dir(r"C:\VulkanSDK")
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .collect();
+    // Version directories like 1.3.290.0 sort correctly by their numbers
+    versions.sort_by_key(|path| {
+        let name = path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        name.split('.')
+            .map(|n| n.parse::<u32>().unwrap_or(0))
+            .collect::<Vec<_>>()
+    });
+    versions.pop()
+}
+
+fn which(program: &str) -> bool {
+    env::var_os("PATH")
+        .is_some_and(|path| env::split_paths(&path).any(|dir| dir.join(program).is_file()))
+}
+
+/// Stops the build script with `lines` as the error.
+fn fail(lines: &[&str]) -> ! {
+    eprintln!();
+    eprintln!("atom-bridge: the C++ renderer can't be built");
+    for line in lines {
+        eprintln!("  {}", line);
+    }
+    eprintln!(
+        "  Unset {} to use a prebuilt library or Bevy's wgpu renderer instead.",
+        BUILD_CPP_ENV
+    );
+    std::process::exit(1);
+}
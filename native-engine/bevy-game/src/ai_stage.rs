@@ -0,0 +1,566 @@
+//! The NPC AI stage, built to keep 5-10k active agents inside the frame
+//! budget. Each frame runs five ordered sets:
+//!
+//! - `Index`: hostile targets go into a spatial hash.
+//! - `Sense`: agents look for targets with a parallel query. Agents far from
+//!   every player sense only every few frames, and `Perception` is written
+//!   only when what the agent knows actually changed.
+//! - `Decide`: runs only for agents whose perception changed (`Changed`
+//!   filter), plus a slow periodic re-think.
+//! - `Path`: path requests from the parallel systems are deduplicated and
+//!   solved in batches on the compute pool, within a per-frame budget.
+//! - `Act`: agents follow their paths and attack in parallel.
+//!
+//! Agents opt in with [`AiAgent`]; the rest of the state comes along as
+//! required components.
+
+use bevy::ecs::entity::EntityHashMap;
+use bevy::prelude::*;
+use bevy::tasks::ComputeTaskPool;
+use bevy::utils::Parallel;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::{Character, Player};
+
+/// Faction players are on; agents of any other faction treat them as hostile.
+pub const PLAYER_FACTION: u8 = 0;
+
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AiStage {
+    Index,
+    Sense,
+    Decide,
+    Path,
+    Act,
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct AiStageConfig {
+    /// Spatial hash cell size; about the longest sight range works best.
+    pub cell_size: f32,
+    /// Agents within this of a player sense every frame.
+    pub near_distance: f32,
+    /// Frames between senses for agents beyond `near_distance`.
+    pub far_sense_interval: u32,
+    /// Seconds after which an agent re-decides even if nothing changed.
+    pub rethink_seconds: f32,
+    /// A chased target that moves this far from the path's goal gets a new path.
+    pub repath_distance: f32,
+    /// Paths solved per frame; the rest wait, oldest first.
+    pub path_budget: usize,
+    /// Requests per compute task.
+    pub path_batch_size: usize,
+    /// Sight range gained per level an agent is above the player, and lost
+    /// per level below.
+    pub aggro_range_per_level: f32,
+    /// Sight range never drops below this, however far the levels differ.
+    pub min_aggro_range: f32,
+}
+
+impl Default for AiStageConfig {
+    fn default() -> Self {
+        Self {
+            cell_size: 32.0,
+            near_distance: 80.0,
+            far_sense_interval: 8,
+            rethink_seconds: 2.0,
+            repath_distance: 4.0,
+            path_budget: 256,
+            path_batch_size: 32,
+            aggro_range_per_level: 1.0,
+            min_aggro_range: 5.0,
+        }
+    }
+}
+
+impl AiStageConfig {
+    /// How close a player of `player_level` can get before `agent` notices
+    /// them. Agents without a level, or facing a player without one, use
+    /// their plain sight range.
+    pub fn aggro_range(&self, agent: &AiAgent, player_level: Option<u32>) -> f32 {
+        match player_level {
+            Some(player_level) if agent.level > 0 => {
+                let difference = agent.level as f32 - player_level as f32;
+                (agent.sight_range + difference * self.aggro_range_per_level)
+                    .clamp(self.min_aggro_range.min(agent.sight_range), agent.sight_range * 2.0)
+            }
+            _ => agent.sight_range,
+        }
+    }
+}
+
+/// An NPC driven by this stage.
+#[derive(Component, Debug, Clone)]
+#[require(Perception, AiTimers, AiIntent, AiPath)]
+pub struct AiAgent {
+    pub faction: u8,
+    /// 0 for agents whose sight doesn't depend on the player's level.
+    pub level: u32,
+    /// Sight range against a player of the same level.
+    pub sight_range: f32,
+    pub attack_range: f32,
+    /// Agents give up a chase this far from `home`.
+    pub leash_range: f32,
+    pub home: Vec3,
+    pub speed: f32,
+    /// Seconds between attacks.
+    pub attack_interval: f32,
+}
+
+impl Default for AiAgent {
+    fn default() -> Self {
+        Self {
+            faction: 1,
+            level: 0,
+            sight_range: 25.0,
+            attack_range: 2.5,
+            leash_range: 60.0,
+            home: Vec3::ZERO,
+            speed: 4.0,
+            attack_interval: 1.5,
+        }
+    }
+}
+
+/// Something agents of other factions attack. Players get one automatically.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct AiTarget {
+    pub faction: u8,
+}
+
+/// What an agent knows. Only discrete facts live here, so the component
+/// changes (and decisions re-run) when the situation does, not every frame
+/// something moves.
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq)]
+pub struct Perception {
+    pub target: Option<Entity>,
+    pub in_attack_range: bool,
+    pub beyond_leash: bool,
+}
+
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct AiTimers {
+    /// Elapsed seconds at the last decision.
+    last_decision: f32,
+    /// Elapsed seconds before which the agent can't attack again.
+    ready_at: f32,
+}
+
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq)]
+pub enum AiIntent {
+    #[default]
+    Idle,
+    Chase(Entity),
+    Attack(Entity),
+    ReturnHome,
+}
+
+#[derive(Component, Debug, Clone, Default)]
+pub struct AiPath {
+    pub waypoints: Vec<Vec3>,
+    pub next: usize,
+    /// Where the path was asked to go.
+    pub goal: Option<Vec3>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PathRequest {
+    pub agent: Entity,
+    pub from: Vec3,
+    pub to: Vec3,
+}
+
+/// Finds waypoints from one point to another. The navigation mesh installs
+/// one; the default walks straight at the goal.
+pub type PathSolver = Arc<dyn Fn(Vec3, Vec3) -> Option<Vec<Vec3>> + Send + Sync>;
+
+/// Rejects a solved path (start, waypoints), e.g. one through a closed door.
+pub type PathFilter = Arc<dyn Fn(Vec3, &[Vec3]) -> bool + Send + Sync>;
+
+#[derive(Resource)]
+pub struct AiPathQueue {
+    /// Agents in the order they first asked.
+    order: VecDeque<Entity>,
+    latest: EntityHashMap<PathRequest>,
+    solver: PathSolver,
+    filters: Vec<PathFilter>,
+}
+
+impl Default for AiPathQueue {
+    fn default() -> Self {
+        Self {
+            order: VecDeque::new(),
+            latest: EntityHashMap::default(),
+            solver: Arc::new(|_, to| Some(vec![to])),
+            filters: Vec::new(),
+        }
+    }
+}
+
+impl AiPathQueue {
+    pub fn set_solver(&mut self, solver: PathSolver) {
+        self.solver = solver;
+    }
+
+    /// Paths must pass every filter; one that doesn't counts as no path.
+    pub fn add_filter(&mut self, filter: PathFilter) {
+        self.filters.push(filter);
+    }
+
+    /// A newer request for the same agent replaces the queued one but keeps
+    /// its place in line.
+    pub fn push(&mut self, request: PathRequest) {
+        if self.latest.insert(request.agent, request).is_none() {
+            self.order.push_back(request.agent);
+        }
+    }
+
+    fn pop(&mut self) -> Option<PathRequest> {
+        let agent = self.order.pop_front()?;
+        self.latest.remove(&agent)
+    }
+
+    pub fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+}
+
+/// An agent swung at its target; combat applies the damage.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct AiAttackEvent {
+    pub attacker: Entity,
+    pub target: Entity,
+}
+
+#[derive(Resource, Debug, Clone, Default)]
+pub struct AiStageStats {
+    pub agents: usize,
+    pub sensed: usize,
+    pub perception_changes: usize,
+    pub decisions: usize,
+    pub paths_solved: usize,
+    pub path_queue: usize,
+    pub ms_last_frame: f32,
+    stage_started: Option<Instant>,
+}
+
+/// Hostile targets by cell.
+#[derive(Resource, Debug, Default)]
+pub struct AiSpatialIndex {
+    cell_size: f32,
+    cells: HashMap<IVec2, Vec<(Entity, Vec3, u8)>>,
+    players: Vec<Vec3>,
+    /// The local player's level, for level-scaled sight.
+    player_level: Option<u32>,
+}
+
+impl AiSpatialIndex {
+    fn cell(&self, position: Vec3) -> IVec2 {
+        (position.xz() / self.cell_size).floor().as_ivec2()
+    }
+
+    /// Nearest target within `range` not of `faction`.
+    pub fn nearest_hostile(&self, position: Vec3, range: f32, faction: u8) -> Option<(Entity, Vec3, f32)> {
+        let min = self.cell(position - Vec3::splat(range));
+        let max = self.cell(position + Vec3::splat(range));
+        let mut best: Option<(Entity, Vec3, f32)> = None;
+        for x in min.x..=max.x {
+            for z in min.y..=max.y {
+                let Some(targets) = self.cells.get(&IVec2::new(x, z)) else {
+                    continue;
+                };
+                for (entity, target, target_faction) in targets {
+                    if *target_faction == faction {
+                        continue;
+                    }
+                    let distance = target.distance(position);
+                    if distance <= range && best.is_none_or(|(_, _, d)| distance < d) {
+                        best = Some((*entity, *target, distance));
+                    }
+                }
+            }
+        }
+        best
+    }
+
+    pub fn player_level(&self) -> Option<u32> {
+        self.player_level
+    }
+
+    fn near_player(&self, position: Vec3, distance: f32) -> bool {
+        let squared = distance * distance;
+        self.players.iter().any(|p| p.distance_squared(position) <= squared)
+    }
+}
+
+pub struct AiStagePlugin;
+
+impl Plugin for AiStagePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AiStageConfig>()
+            .init_resource::<AiSpatialIndex>()
+            .init_resource::<AiPathQueue>()
+            .init_resource::<AiStageStats>()
+            .add_event::<AiAttackEvent>()
+            .configure_sets(
+                Update,
+                (AiStage::Index, AiStage::Sense, AiStage::Decide, AiStage::Path, AiStage::Act).chain(),
+            )
+            .add_systems(
+                Update,
+                (
+                    (tag_player_targets, build_spatial_index).chain().in_set(AiStage::Index),
+                    sense.in_set(AiStage::Sense),
+                    decide.in_set(AiStage::Decide),
+                    solve_paths.in_set(AiStage::Path),
+                    act.in_set(AiStage::Act),
+                ),
+            );
+    }
+}
+
+fn tag_player_targets(mut commands: Commands, players: Query<Entity, (With<Player>, Without<AiTarget>)>) {
+    for player in &players {
+        commands.entity(player).insert(AiTarget {
+            faction: PLAYER_FACTION,
+        });
+    }
+}
+
+fn build_spatial_index(
+    config: Res<AiStageConfig>,
+    targets: Query<(Entity, &Transform, &AiTarget)>,
+    players: Query<(&Transform, Option<&Character>), With<Player>>,
+    mut index: ResMut<AiSpatialIndex>,
+    mut stats: ResMut<AiStageStats>,
+) {
+    stats.stage_started = Some(Instant::now());
+    index.cell_size = config.cell_size.max(1.0);
+    // Keep the cell vectors' capacity from frame to frame
+    for cell in index.cells.values_mut() {
+        cell.clear();
+    }
+    for (entity, transform, target) in &targets {
+        let cell = index.cell(transform.translation);
+        index
+            .cells
+            .entry(cell)
+            .or_default()
+            .push((entity, transform.translation, target.faction));
+    }
+    index.cells.retain(|_, cell| !cell.is_empty() || cell.capacity() <= 64);
+    index.players = players.iter().map(|(t, _)| t.translation).collect();
+    index.player_level = players.iter().find_map(|(_, character)| character.map(|c| c.level));
+}
+
+fn sense(
+    time: Res<Time>,
+    config: Res<AiStageConfig>,
+    index: Res<AiSpatialIndex>,
+    mut frame: Local<u32>,
+    mut agents: Query<(Entity, &AiAgent, &Transform, &mut Perception, &mut AiTimers)>,
+    mut stats: ResMut<AiStageStats>,
+) {
+    *frame = frame.wrapping_add(1);
+    let frame = *frame;
+    let now = time.elapsed_secs();
+    let sensed = AtomicUsize::new(0);
+    let changed = AtomicUsize::new(0);
+
+    agents
+        .par_iter_mut()
+        .for_each(|(entity, agent, transform, mut perception, mut timers)| {
+            let position = transform.translation;
+            // Far agents take turns, spread by entity so they don't all go at once
+            let interval = config.far_sense_interval.max(1);
+            let turn = entity.index().wrapping_add(frame).is_multiple_of(interval);
+            if !turn && !index.near_player(position, config.near_distance) {
+                return;
+            }
+            sensed.fetch_add(1, Ordering::Relaxed);
+
+            let beyond_leash = position.distance(agent.home) > agent.leash_range;
+            let target = if beyond_leash {
+                None
+            } else {
+                let range = config.aggro_range(agent, index.player_level);
+                index.nearest_hostile(position, range, agent.faction)
+            };
+            let next = Perception {
+                target: target.map(|(e, _, _)| e),
+                in_attack_range: target.is_some_and(|(_, _, d)| d <= agent.attack_range),
+                beyond_leash,
+            };
+            if perception.set_if_neq(next) {
+                changed.fetch_add(1, Ordering::Relaxed);
+            } else if now - timers.last_decision >= config.rethink_seconds {
+                perception.set_changed();
+            } else {
+                return;
+            }
+            timers.last_decision = now;
+        });
+
+    stats.sensed = sensed.into_inner();
+    stats.perception_changes = changed.into_inner();
+}
+
+fn decide(
+    mut agents: Query<(Entity, &AiAgent, &Transform, &Perception, &mut AiIntent, &AiPath), Changed<Perception>>,
+    targets: Query<&Transform, With<AiTarget>>,
+    mut requests: Local<Parallel<Vec<PathRequest>>>,
+    mut queue: ResMut<AiPathQueue>,
+    mut stats: ResMut<AiStageStats>,
+) {
+    let decisions = AtomicUsize::new(0);
+    agents
+        .par_iter_mut()
+        .for_each(|(entity, agent, transform, perception, mut intent, path)| {
+            decisions.fetch_add(1, Ordering::Relaxed);
+            let next = match perception.target {
+                Some(target) if perception.in_attack_range => AiIntent::Attack(target),
+                Some(target) => AiIntent::Chase(target),
+                None if transform.translation.distance(agent.home) > agent.attack_range => AiIntent::ReturnHome,
+                None => AiIntent::Idle,
+            };
+            let goal = match next {
+                AiIntent::Chase(target) => targets.get(target).ok().map(|t| t.translation),
+                AiIntent::ReturnHome => Some(agent.home),
+                _ => None,
+            };
+            intent.set_if_neq(next);
+            if let Some(goal) = goal.filter(|g| path.goal != Some(*g)) {
+                requests.borrow_local_mut().push(PathRequest {
+                    agent: entity,
+                    from: transform.translation,
+                    to: goal,
+                });
+            }
+        });
+    for request in requests.drain() {
+        queue.push(request);
+    }
+    stats.decisions = decisions.into_inner();
+}
+
+fn solve_paths(
+    config: Res<AiStageConfig>,
+    mut queue: ResMut<AiPathQueue>,
+    mut paths: Query<&mut AiPath>,
+    mut stats: ResMut<AiStageStats>,
+) {
+    let take = config.path_budget.min(queue.len());
+    let batch: Vec<PathRequest> = (0..take).filter_map(|_| queue.pop()).collect();
+    let solver = queue.solver.clone();
+    let filters = queue.filters.clone();
+    let batch_size = config.path_batch_size.max(1);
+
+    let solved: Vec<(PathRequest, Option<Vec<Vec3>>)> = ComputeTaskPool::get()
+        .scope(|scope| {
+            for requests in batch.chunks(batch_size) {
+                let (solver, filters) = (&solver, &filters);
+                scope.spawn(async move {
+                    requests
+                        .iter()
+                        .map(|r| {
+                            let waypoints = solver(r.from, r.to).filter(|w| filters.iter().all(|f| f(r.from, w)));
+                            (*r, waypoints)
+                        })
+                        .collect::<Vec<_>>()
+                });
+            }
+        })
+        .into_iter()
+        .flatten()
+        .collect();
+
+    stats.paths_solved = solved.len();
+    for (request, waypoints) in solved {
+        let Ok(mut path) = paths.get_mut(request.agent) else {
+            continue;
+        };
+        // No path: remember the goal anyway so the agent doesn't re-ask every frame
+        path.waypoints = waypoints.unwrap_or_default();
+        path.next = 0;
+        path.goal = Some(request.to);
+    }
+    stats.path_queue = queue.len();
+}
+
+fn act(
+    time: Res<Time>,
+    config: Res<AiStageConfig>,
+    mut agents: Query<(Entity, &AiAgent, &AiIntent, &mut AiPath, &mut AiTimers, &mut Transform), Without<AiTarget>>,
+    targets: Query<&Transform, With<AiTarget>>,
+    mut attacks: Local<Parallel<Vec<AiAttackEvent>>>,
+    mut repaths: Local<Parallel<Vec<PathRequest>>>,
+    mut events: EventWriter<AiAttackEvent>,
+    mut queue: ResMut<AiPathQueue>,
+    mut stats: ResMut<AiStageStats>,
+) {
+    let dt = time.delta_secs();
+    let now = time.elapsed_secs();
+    agents
+        .par_iter_mut()
+        .for_each(|(entity, agent, intent, mut path, mut timers, mut transform)| {
+            match *intent {
+                AiIntent::Attack(target) => {
+                    if let Ok(target_transform) = targets.get(target) {
+                        let to = target_transform.translation.with_y(transform.translation.y);
+                        if to.distance_squared(transform.translation) > 0.01 {
+                            transform.look_at(to, Vec3::Y);
+                        }
+                    }
+                    if now >= timers.ready_at {
+                        timers.ready_at = now + agent.attack_interval;
+                        attacks.borrow_local_mut().push(AiAttackEvent { attacker: entity, target });
+                    }
+                    return;
+                }
+                AiIntent::Chase(target) => {
+                    let moved = targets.get(target).ok().map(|t| t.translation).filter(|goal| {
+                        path.goal.is_none_or(|g| g.distance(*goal) > config.repath_distance)
+                    });
+                    if let Some(goal) = moved {
+                        // Mark it asked for, so the next frames don't pile up requests
+                        path.goal = Some(goal);
+                        repaths.borrow_local_mut().push(PathRequest {
+                            agent: entity,
+                            from: transform.translation,
+                            to: goal,
+                        });
+                    }
+                }
+                AiIntent::Idle => return,
+                AiIntent::ReturnHome => {}
+            }
+
+            let Some(waypoint) = path.waypoints.get(path.next).copied() else {
+                return;
+            };
+            let offset = (waypoint - transform.translation).with_y(0.0);
+            let step = agent.speed * dt;
+            if offset.length() <= step {
+                transform.translation = waypoint.with_y(transform.translation.y);
+                path.next += 1;
+            } else {
+                let direction = offset.normalize();
+                transform.translation += direction * step;
+                transform.rotation = Quat::from_rotation_arc(Vec3::NEG_Z, direction);
+            }
+        });
+
+    events.send_batch(attacks.drain());
+    for request in repaths.drain() {
+        queue.push(request);
+    }
+    stats.agents = agents.iter().len();
+    stats.ms_last_frame = stats.stage_started.map_or(0.0, |t| t.elapsed().as_secs_f32() * 1000.0);
+}
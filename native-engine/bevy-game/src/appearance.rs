@@ -0,0 +1,469 @@
+use bevy::gltf::GltfMaterialName;
+use bevy::prelude::*;
+use bevy::render::mesh::skinning::SkinnedMesh;
+use bevy::scene::SceneInstanceReady;
+use std::collections::HashMap;
+
+/// Body slots a modular character is assembled from. Armor pieces replace the
+/// base body part in the same slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BodySlot {
+    Head,
+    Hair,
+    Torso,
+    Hands,
+    Legs,
+    Feet,
+    Cloak,
+}
+
+impl BodySlot {
+    pub const ALL: [BodySlot; 7] = [
+        BodySlot::Head,
+        BodySlot::Hair,
+        BodySlot::Torso,
+        BodySlot::Hands,
+        BodySlot::Legs,
+        BodySlot::Feet,
+        BodySlot::Cloak,
+    ];
+
+    /// Small slots that are hidden beyond the detail distance to save draw calls.
+    pub fn is_detail(&self) -> bool {
+        matches!(self, BodySlot::Hands | BodySlot::Feet | BodySlot::Cloak)
+    }
+}
+
+/// Choices made at character creation.
+#[derive(Component, Debug, Clone)]
+pub struct CharacterAppearance {
+    pub body_type: String,
+    pub head: String,
+    pub hair_style: Option<String>,
+    pub skin_color: Color,
+    pub hair_color: Color,
+    pub dye_color: Color,
+}
+
+impl Default for CharacterAppearance {
+    fn default() -> Self {
+        Self {
+            body_type: "human_male".to_string(),
+            head: "head_01".to_string(),
+            hair_style: Some("hair_short".to_string()),
+            skin_color: Color::srgb(0.87, 0.72, 0.6),
+            hair_color: Color::srgb(0.25, 0.17, 0.1),
+            dye_color: Color::WHITE,
+        }
+    }
+}
+
+/// Armor/clothing part ids per slot, written by the inventory when equipment changes.
+#[derive(Component, Debug, Clone, Default)]
+pub struct EquipmentVisuals {
+    pub parts: HashMap<BodySlot, String>,
+}
+
+impl EquipmentVisuals {
+    pub fn equip(&mut self, slot: BodySlot, part_id: impl Into<String>) {
+        self.parts.insert(slot, part_id.into());
+    }
+
+    pub fn unequip(&mut self, slot: BodySlot) {
+        self.parts.remove(&slot);
+    }
+}
+
+/// Registry of modular part ids to the glTF scene they are loaded from.
+#[derive(Resource, Debug, Clone)]
+pub struct ModularPartLibrary {
+    pub skeleton_scene: String,
+    pub parts: HashMap<String, String>,
+    pub base_torso: String,
+    pub base_hands: String,
+    pub base_legs: String,
+    pub base_feet: String,
+}
+
+impl Default for ModularPartLibrary {
+    fn default() -> Self {
+        Self {
+            skeleton_scene: "models/characters/{body}/skeleton.glb".to_string(),
+            parts: HashMap::new(),
+            base_torso: "torso_base".to_string(),
+            base_hands: "hands_base".to_string(),
+            base_legs: "legs_base".to_string(),
+            base_feet: "feet_base".to_string(),
+        }
+    }
+}
+
+impl ModularPartLibrary {
+    /// Resolve a part id to a scene path. Unregistered ids fall back to the
+    /// conventional `models/characters/<body>/<part>.glb` layout.
+    pub fn scene_path(&self, body_type: &str, part_id: &str) -> String {
+        let path = self
+            .parts
+            .get(part_id)
+            .cloned()
+            .unwrap_or_else(|| format!("models/characters/{{body}}/{}.glb", part_id));
+        format!("{}#Scene0", path.replace("{body}", body_type))
+    }
+
+    pub fn skeleton_path(&self, body_type: &str) -> String {
+        format!("{}#Scene0", self.skeleton_scene.replace("{body}", body_type))
+    }
+
+    fn resolve_slot(
+        &self,
+        slot: BodySlot,
+        appearance: &CharacterAppearance,
+        equipment: Option<&EquipmentVisuals>,
+    ) -> Option<String> {
+        if let Some(armor) = equipment.and_then(|e| e.parts.get(&slot)) {
+            return Some(armor.clone());
+        }
+        match slot {
+            BodySlot::Head => Some(appearance.head.clone()),
+            BodySlot::Hair => appearance.hair_style.clone(),
+            BodySlot::Torso => Some(self.base_torso.clone()),
+            BodySlot::Hands => Some(self.base_hands.clone()),
+            BodySlot::Legs => Some(self.base_legs.clone()),
+            BodySlot::Feet => Some(self.base_feet.clone()),
+            BodySlot::Cloak => None,
+        }
+    }
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct AppearanceLodConfig {
+    /// Beyond this distance detail slots (hands, feet, cloak) are hidden.
+    pub detail_distance: f32,
+}
+
+impl Default for AppearanceLodConfig {
+    fn default() -> Self {
+        Self {
+            detail_distance: 40.0,
+        }
+    }
+}
+
+/// Tinted material variants shared between characters. Players with the same
+/// skin or dye color end up on the same material handle, which keeps them in
+/// the same draw batch.
+#[derive(Resource, Default)]
+pub struct TintedMaterialCache {
+    materials: HashMap<(AssetId<StandardMaterial>, [u8; 4]), Handle<StandardMaterial>>,
+}
+
+impl TintedMaterialCache {
+    pub fn len(&self) -> usize {
+        self.materials.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.materials.is_empty()
+    }
+
+    fn get_or_create(
+        &mut self,
+        base: &Handle<StandardMaterial>,
+        tint: Color,
+        materials: &mut Assets<StandardMaterial>,
+    ) -> Option<Handle<StandardMaterial>> {
+        let key = (base.id(), tint.to_srgba().to_u8_array());
+        if let Some(handle) = self.materials.get(&key) {
+            return Some(handle.clone());
+        }
+        let mut tinted = materials.get(base)?.clone();
+        tinted.base_color = tint;
+        let handle = materials.add(tinted);
+        self.materials.insert(key, handle.clone());
+        Some(handle)
+    }
+}
+
+#[derive(Component, Debug, Clone)]
+pub struct ModularBodyPart {
+    pub slot: BodySlot,
+    pub part_id: String,
+}
+
+#[derive(Component)]
+pub struct ModularSkeleton {
+    pub body_type: String,
+}
+
+/// Joint name -> entity map of the shared skeleton, stored on the character root.
+#[derive(Component, Default)]
+pub struct SkeletonJoints {
+    pub joints: HashMap<String, Entity>,
+}
+
+/// Marks a part (or skeleton) whose scene has finished spawning and still
+/// needs skin rebinding and tinting.
+#[derive(Component)]
+struct PartSceneReady;
+
+#[derive(Component)]
+struct PartTintDirty;
+
+pub struct CharacterAppearancePlugin;
+
+impl Plugin for CharacterAppearancePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ModularPartLibrary>()
+            .init_resource::<AppearanceLodConfig>()
+            .init_resource::<TintedMaterialCache>()
+            .add_systems(
+                Update,
+                (
+                    sync_modular_parts,
+                    collect_skeleton_joints,
+                    rebind_part_skins,
+                    apply_part_tints,
+                    cull_detail_parts,
+                )
+                    .chain(),
+            );
+
+        info!("CharacterAppearancePlugin initialized (modular parts, shared tint materials)");
+    }
+}
+
+fn mark_scene_ready(trigger: Trigger<SceneInstanceReady>, mut commands: Commands) {
+    commands.entity(trigger.entity()).insert(PartSceneReady);
+}
+
+fn sync_modular_parts(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    library: Res<ModularPartLibrary>,
+    characters: Query<
+        (Entity, &CharacterAppearance, Option<&EquipmentVisuals>, Option<&Children>),
+        Or<(Changed<CharacterAppearance>, Changed<EquipmentVisuals>)>,
+    >,
+    parts: Query<&ModularBodyPart>,
+    skeletons: Query<&ModularSkeleton>,
+) {
+    for (entity, appearance, equipment, children) in characters.iter() {
+        let mut existing: HashMap<BodySlot, (Entity, &ModularBodyPart)> = HashMap::new();
+        let mut skeleton: Option<(Entity, &ModularSkeleton)> = None;
+
+        for child in children.into_iter().flatten() {
+            if let Ok(part) = parts.get(*child) {
+                existing.insert(part.slot, (*child, part));
+            } else if let Ok(s) = skeletons.get(*child) {
+                skeleton = Some((*child, s));
+            }
+        }
+
+        // A body type change invalidates the skeleton and every part on it
+        let body_changed = skeleton
+            .map(|(_, s)| s.body_type != appearance.body_type)
+            .unwrap_or(true);
+        if body_changed {
+            if let Some((skeleton_entity, _)) = skeleton {
+                commands.entity(skeleton_entity).despawn_recursive();
+            }
+            for (part_entity, _) in existing.values() {
+                commands.entity(*part_entity).despawn_recursive();
+            }
+            existing.clear();
+
+            let scene = asset_server.load(library.skeleton_path(&appearance.body_type));
+            commands.entity(entity).insert(SkeletonJoints::default());
+            commands.entity(entity).with_children(|parent| {
+                parent
+                    .spawn((
+                        SceneRoot(scene),
+                        ModularSkeleton {
+                            body_type: appearance.body_type.clone(),
+                        },
+                        Name::new("ModularSkeleton"),
+                    ))
+                    .observe(mark_scene_ready);
+            });
+        }
+
+        for slot in BodySlot::ALL {
+            let wanted = library.resolve_slot(slot, appearance, equipment);
+            let current = existing.get(&slot);
+
+            if let (Some((part_entity, part)), Some(id)) = (current, wanted.as_ref()) {
+                if &part.part_id == id {
+                    // Same mesh, but skin/hair/dye colors may have changed
+                    commands.entity(*part_entity).insert(PartTintDirty);
+                    continue;
+                }
+            }
+
+            if let Some((part_entity, _)) = current {
+                commands.entity(*part_entity).despawn_recursive();
+            }
+            if let Some(part_id) = wanted {
+                let scene = asset_server.load(library.scene_path(&appearance.body_type, &part_id));
+                commands.entity(entity).with_children(|parent| {
+                    parent
+                        .spawn((
+                            SceneRoot(scene),
+                            ModularBodyPart { slot, part_id },
+                            Name::new(format!("Part_{:?}", slot)),
+                        ))
+                        .observe(mark_scene_ready);
+                });
+            }
+        }
+    }
+}
+
+fn collect_skeleton_joints(
+    mut commands: Commands,
+    ready_skeletons: Query<(Entity, &Parent), (With<ModularSkeleton>, With<PartSceneReady>)>,
+    children_query: Query<&Children>,
+    names: Query<&Name>,
+    mut joints_query: Query<&mut SkeletonJoints>,
+) {
+    for (skeleton_entity, parent) in ready_skeletons.iter() {
+        let Ok(mut joints) = joints_query.get_mut(parent.get()) else {
+            continue;
+        };
+        joints.joints.clear();
+        for descendant in children_query.iter_descendants(skeleton_entity) {
+            if let Ok(name) = names.get(descendant) {
+                joints.joints.insert(name.as_str().to_string(), descendant);
+            }
+        }
+        debug!("Modular skeleton ready with {} joints", joints.joints.len());
+        commands.entity(skeleton_entity).remove::<PartSceneReady>();
+    }
+}
+
+/// Point each part's skinned meshes at the shared skeleton's joints, so every
+/// part is driven by one animated armature instead of one per part.
+fn rebind_part_skins(
+    mut commands: Commands,
+    ready_parts: Query<(Entity, &Parent), (With<ModularBodyPart>, With<PartSceneReady>)>,
+    children_query: Query<&Children>,
+    names: Query<&Name>,
+    skeletons: Query<&SkeletonJoints>,
+    mut skinned: Query<&mut SkinnedMesh>,
+) {
+    for (part_entity, parent) in ready_parts.iter() {
+        let Ok(skeleton) = skeletons.get(parent.get()) else {
+            continue;
+        };
+        if skeleton.joints.is_empty() {
+            // Skeleton scene hasn't finished spawning yet - retry next frame
+            continue;
+        }
+
+        let mut rebound = 0;
+        for descendant in children_query.iter_descendants(part_entity) {
+            let Ok(mut skin) = skinned.get_mut(descendant) else {
+                continue;
+            };
+            let remapped: Option<Vec<Entity>> = skin
+                .joints
+                .iter()
+                .map(|joint| {
+                    names
+                        .get(*joint)
+                        .ok()
+                        .and_then(|name| skeleton.joints.get(name.as_str()).copied())
+                })
+                .collect();
+            match remapped {
+                Some(joints) => {
+                    skin.joints = joints;
+                    rebound += 1;
+                }
+                None => warn!("Modular part {:?} has joints missing from the shared skeleton", part_entity),
+            }
+        }
+
+        debug!("Rebound {} skinned meshes on part {:?}", rebound, part_entity);
+        commands
+            .entity(part_entity)
+            .remove::<PartSceneReady>()
+            .insert(PartTintDirty);
+    }
+}
+
+fn apply_part_tints(
+    mut commands: Commands,
+    dirty_parts: Query<(Entity, &Parent), (With<ModularBodyPart>, With<PartTintDirty>, Without<PartSceneReady>)>,
+    appearances: Query<&CharacterAppearance>,
+    children_query: Query<&Children>,
+    mut mesh_materials: Query<(&mut MeshMaterial3d<StandardMaterial>, &GltfMaterialName)>,
+    mut cache: ResMut<TintedMaterialCache>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut base_materials: Local<HashMap<Entity, Handle<StandardMaterial>>>,
+) {
+    for (part_entity, parent) in dirty_parts.iter() {
+        let Ok(appearance) = appearances.get(parent.get()) else {
+            continue;
+        };
+
+        for descendant in children_query.iter_descendants(part_entity) {
+            let Ok((mut material, material_name)) = mesh_materials.get_mut(descendant) else {
+                continue;
+            };
+            let name = material_name.0.to_lowercase();
+            let tint = if name.contains("skin") {
+                appearance.skin_color
+            } else if name.contains("hair") {
+                appearance.hair_color
+            } else if name.contains("dye") || name.contains("armor") {
+                appearance.dye_color
+            } else {
+                continue;
+            };
+
+            // Always tint from the untinted glTF material so repeated
+            // changes don't compound
+            let base = base_materials
+                .entry(descendant)
+                .or_insert_with(|| material.0.clone())
+                .clone();
+            if let Some(tinted) = cache.get_or_create(&base, tint, &mut materials) {
+                material.0 = tinted;
+            }
+        }
+
+        commands.entity(part_entity).remove::<PartTintDirty>();
+    }
+
+    base_materials.retain(|entity, _| mesh_materials.contains(*entity));
+}
+
+fn cull_detail_parts(
+    config: Res<AppearanceLodConfig>,
+    cameras: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+    mut parts: Query<(&ModularBodyPart, &GlobalTransform, &mut Visibility)>,
+) {
+    let Some(camera_pos) = cameras
+        .iter()
+        .filter(|(camera, _)| camera.is_active && camera.order >= 0)
+        .map(|(_, transform)| transform.translation())
+        .next()
+    else {
+        return;
+    };
+
+    let max_dist_sq = config.detail_distance * config.detail_distance;
+    for (part, transform, mut visibility) in parts.iter_mut() {
+        if !part.slot.is_detail() {
+            continue;
+        }
+        let wanted = if transform.translation().distance_squared(camera_pos) > max_dist_sq {
+            Visibility::Hidden
+        } else {
+            Visibility::Inherited
+        };
+        if *visibility != wanted {
+            *visibility = wanted;
+        }
+    }
+}
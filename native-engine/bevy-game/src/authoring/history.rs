@@ -0,0 +1,598 @@
+use bevy::ecs::entity::EntityHashMap;
+use bevy::prelude::*;
+use bevy::reflect::{GetPath, PartialReflect};
+use bevy::scene::{DynamicScene, DynamicSceneBuilder};
+use std::any::Any;
+use std::collections::{HashMap, VecDeque};
+
+use super::AuthoringMode;
+use crate::input_map::{Action, ActionState};
+
+/// An undoable editor operation. `apply` is also used for redo, so it must
+/// work on a world where the command was previously undone.
+pub trait EditorCommand: Send + Sync + 'static {
+    fn label(&self) -> String;
+    fn apply(&mut self, world: &mut World);
+    fn undo(&mut self, world: &mut World);
+
+    /// Fold a newer command into this one (e.g. consecutive nudges of the
+    /// same entity). Return `true` if `newer` was absorbed.
+    fn try_merge(&mut self, _newer: &dyn Any) -> bool {
+        false
+    }
+
+    fn as_any(&self) -> &dyn Any;
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct EditHistoryConfig {
+    pub max_depth: usize,
+    /// Edits to the same target closer together than this become one undo step.
+    pub merge_window_seconds: f32,
+    pub show_panel: bool,
+    pub panel_entries: usize,
+}
+
+impl Default for EditHistoryConfig {
+    fn default() -> Self {
+        Self {
+            max_depth: 200,
+            merge_window_seconds: 0.5,
+            show_panel: true,
+            panel_entries: 20,
+        }
+    }
+}
+
+enum HistoryRequest {
+    Execute(Box<dyn EditorCommand>),
+    /// Already applied by the caller (e.g. a finished gizmo drag).
+    Record(Box<dyn EditorCommand>),
+    Undo,
+    Redo,
+    Clear,
+}
+
+/// Undo/redo stacks. Editor systems queue work here; it runs in an exclusive
+/// system because commands need the whole world.
+#[derive(Resource, Default)]
+pub struct EditHistory {
+    undo_stack: VecDeque<(Box<dyn EditorCommand>, f32)>,
+    redo_stack: Vec<Box<dyn EditorCommand>>,
+    requests: Vec<HistoryRequest>,
+    /// Bumped whenever the stacks change, so UI can redraw lazily.
+    pub revision: u64,
+}
+
+impl EditHistory {
+    pub fn execute(&mut self, command: impl EditorCommand) {
+        self.requests.push(HistoryRequest::Execute(Box::new(command)));
+    }
+
+    pub fn record(&mut self, command: impl EditorCommand) {
+        self.requests.push(HistoryRequest::Record(Box::new(command)));
+    }
+
+    pub fn undo(&mut self) {
+        self.requests.push(HistoryRequest::Undo);
+    }
+
+    pub fn redo(&mut self) {
+        self.requests.push(HistoryRequest::Redo);
+    }
+
+    pub fn clear(&mut self) {
+        self.requests.push(HistoryRequest::Clear);
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    /// Labels oldest-first, plus how many of them are applied (the rest are redoable).
+    pub fn labels(&self) -> (Vec<String>, usize) {
+        let mut labels: Vec<String> = self.undo_stack.iter().map(|(c, _)| c.label()).collect();
+        let applied = labels.len();
+        labels.extend(self.redo_stack.iter().rev().map(|c| c.label()));
+        (labels, applied)
+    }
+}
+
+/// Entities deleted and restored by undo come back with new ids. Commands
+/// resolve the ids they captured through this map.
+#[derive(Resource, Debug, Default)]
+pub struct EditorEntityMap(HashMap<Entity, Entity>);
+
+impl EditorEntityMap {
+    pub fn resolve(&self, mut entity: Entity) -> Entity {
+        // Bounded in case a bad remap ever forms a cycle
+        for _ in 0..32 {
+            match self.0.get(&entity) {
+                Some(next) if *next != entity => entity = *next,
+                _ => break,
+            }
+        }
+        entity
+    }
+}
+
+fn resolve(world: &World, entity: Entity) -> Entity {
+    world
+        .get_resource::<EditorEntityMap>()
+        .map_or(entity, |map| map.resolve(entity))
+}
+
+pub struct EditHistoryPlugin;
+
+impl Plugin for EditHistoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EditHistoryConfig>()
+            .init_resource::<EditHistory>()
+            .init_resource::<EditorEntityMap>()
+            .add_systems(Startup, setup_history_panel)
+            .add_systems(
+                Update,
+                (
+                    undo_redo_shortcuts.run_if(|mode: Res<AuthoringMode>| mode.active),
+                    process_edit_history,
+                    update_history_panel,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// Undo and redo chords are exact, so Ctrl+Alt+axis stays with the
+/// alignment tools.
+fn undo_redo_shortcuts(actions: Res<ActionState>, mut history: ResMut<EditHistory>) {
+    if actions.just_pressed(Action::Undo) {
+        history.undo();
+    } else if actions.just_pressed(Action::Redo) {
+        history.redo();
+    }
+}
+
+fn process_edit_history(world: &mut World) {
+    if world.resource::<EditHistory>().requests.is_empty() {
+        return;
+    }
+    let requests = std::mem::take(&mut world.resource_mut::<EditHistory>().requests);
+    let config = world.resource::<EditHistoryConfig>().clone();
+    let now = world.resource::<Time>().elapsed_secs();
+
+    world.resource_scope(|world, mut history: Mut<EditHistory>| {
+        for request in requests {
+            match request {
+                HistoryRequest::Execute(mut command) => {
+                    command.apply(world);
+                    push(&mut history, command, now, &config);
+                }
+                HistoryRequest::Record(command) => push(&mut history, command, now, &config),
+                HistoryRequest::Undo => {
+                    if let Some((mut command, _)) = history.undo_stack.pop_back() {
+                        command.undo(world);
+                        debug!("Undo: {}", command.label());
+                        history.redo_stack.push(command);
+                    }
+                }
+                HistoryRequest::Redo => {
+                    if let Some(mut command) = history.redo_stack.pop() {
+                        command.apply(world);
+                        debug!("Redo: {}", command.label());
+                        // Never merge a redone step into its neighbour
+                        history.undo_stack.push_back((command, f32::NEG_INFINITY));
+                    }
+                }
+                HistoryRequest::Clear => {
+                    history.undo_stack.clear();
+                    history.redo_stack.clear();
+                }
+            }
+            history.revision += 1;
+        }
+    });
+}
+
+fn push(history: &mut EditHistory, command: Box<dyn EditorCommand>, now: f32, config: &EditHistoryConfig) {
+    history.redo_stack.clear();
+
+    if let Some((top, recorded_at)) = history.undo_stack.back_mut() {
+        if now - *recorded_at <= config.merge_window_seconds && top.try_merge(command.as_any()) {
+            *recorded_at = now;
+            return;
+        }
+    }
+
+    history.undo_stack.push_back((command, now));
+    while history.undo_stack.len() > config.max_depth.max(1) {
+        history.undo_stack.pop_front();
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Built-in commands
+// ---------------------------------------------------------------------------
+
+pub struct SetTransformCommand {
+    pub entity: Entity,
+    pub before: Transform,
+    pub after: Transform,
+}
+
+impl EditorCommand for SetTransformCommand {
+    fn label(&self) -> String {
+        format!("Move {}", self.entity)
+    }
+
+    fn apply(&mut self, world: &mut World) {
+        let entity = resolve(world, self.entity);
+        if let Some(mut transform) = world.get_mut::<Transform>(entity) {
+            *transform = self.after;
+        }
+    }
+
+    fn undo(&mut self, world: &mut World) {
+        let entity = resolve(world, self.entity);
+        if let Some(mut transform) = world.get_mut::<Transform>(entity) {
+            *transform = self.before;
+        }
+    }
+
+    fn try_merge(&mut self, newer: &dyn Any) -> bool {
+        match newer.downcast_ref::<SetTransformCommand>() {
+            Some(newer) if newer.entity == self.entity => {
+                self.after = newer.after;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Several entities moved together (multi-select drag, align).
+pub struct SetTransformsCommand {
+    pub label: String,
+    pub changes: Vec<(Entity, Transform, Transform)>,
+}
+
+impl EditorCommand for SetTransformsCommand {
+    fn label(&self) -> String {
+        self.label.clone()
+    }
+
+    fn apply(&mut self, world: &mut World) {
+        for (entity, _, after) in &self.changes {
+            let entity = resolve(world, *entity);
+            if let Some(mut transform) = world.get_mut::<Transform>(entity) {
+                *transform = *after;
+            }
+        }
+    }
+
+    fn undo(&mut self, world: &mut World) {
+        for (entity, before, _) in &self.changes {
+            let entity = resolve(world, *entity);
+            if let Some(mut transform) = world.get_mut::<Transform>(entity) {
+                *transform = *before;
+            }
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Write a reflected value into one field of a component. `field` is a
+/// reflect path such as `intensity` or `color.0`; empty replaces the whole component.
+pub fn set_reflected_field(
+    world: &mut World,
+    entity: Entity,
+    component: &str,
+    field: &str,
+    value: &dyn PartialReflect,
+) -> Result<(), String> {
+    let registry = world.resource::<AppTypeRegistry>().clone();
+    let registry = registry.read();
+    let registration = registry
+        .get_with_type_path(component)
+        .ok_or_else(|| format!("unknown component type {}", component))?;
+    let reflect_component = registration
+        .data::<ReflectComponent>()
+        .ok_or_else(|| format!("{} is not a reflected component", component))?;
+
+    let mut entity_mut = world
+        .get_entity_mut(entity)
+        .map_err(|_| format!("entity {} no longer exists", entity))?;
+    let mut reflected = reflect_component
+        .reflect_mut(&mut entity_mut)
+        .ok_or_else(|| format!("entity {} has no {}", entity, component))?;
+
+    let target: &mut dyn PartialReflect = if field.is_empty() {
+        reflected.as_partial_reflect_mut()
+    } else {
+        reflected
+            .reflect_path_mut(field)
+            .map_err(|e| format!("{}.{}: {}", component, field, e))?
+    };
+    target.try_apply(value).map_err(|e| e.to_string())
+}
+
+pub struct SetPropertyCommand {
+    pub entity: Entity,
+    /// Full type path of the component, as in the type registry.
+    pub component: String,
+    pub field: String,
+    pub before: Box<dyn PartialReflect>,
+    pub after: Box<dyn PartialReflect>,
+}
+
+impl SetPropertyCommand {
+    fn write(&self, world: &mut World, value: &dyn PartialReflect) {
+        let entity = resolve(world, self.entity);
+        if let Err(e) = set_reflected_field(world, entity, &self.component, &self.field, value) {
+            warn!("Editor: failed to set property: {}", e);
+        }
+    }
+}
+
+impl EditorCommand for SetPropertyCommand {
+    fn label(&self) -> String {
+        let short = self.component.rsplit("::").next().unwrap_or(&self.component);
+        if self.field.is_empty() {
+            format!("Edit {}", short)
+        } else {
+            format!("Edit {}.{}", short, self.field)
+        }
+    }
+
+    fn apply(&mut self, world: &mut World) {
+        let value = self.after.clone_value();
+        self.write(world, value.as_ref());
+    }
+
+    fn undo(&mut self, world: &mut World) {
+        let value = self.before.clone_value();
+        self.write(world, value.as_ref());
+    }
+
+    fn try_merge(&mut self, newer: &dyn Any) -> bool {
+        match newer.downcast_ref::<SetPropertyCommand>() {
+            Some(newer)
+                if newer.entity == self.entity && newer.component == self.component && newer.field == self.field =>
+            {
+                self.after = newer.after.clone_value();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Entities and all their descendants captured as a scene. Only reflected,
+/// registered components survive a delete/restore round trip.
+struct EntitySnapshot {
+    scene: DynamicScene,
+    /// Root entity (as captured) and the parent it was attached to.
+    roots: Vec<(Entity, Option<Entity>)>,
+}
+
+pub(super) fn collect_descendants(world: &World, entity: Entity, out: &mut Vec<Entity>) {
+    out.push(entity);
+    if let Some(children) = world.get::<Children>(entity) {
+        for child in children.iter() {
+            collect_descendants(world, *child, out);
+        }
+    }
+}
+
+fn capture_and_despawn(world: &mut World, roots: &[Entity]) -> EntitySnapshot {
+    let roots: Vec<Entity> = roots
+        .iter()
+        .map(|e| resolve(world, *e))
+        .filter(|e| world.get_entity(*e).is_ok())
+        .collect();
+
+    let mut all = Vec::new();
+    for root in &roots {
+        collect_descendants(world, *root, &mut all);
+    }
+
+    let scene = DynamicSceneBuilder::from_world(world)
+        .extract_entities(all.into_iter())
+        .build();
+    let roots: Vec<(Entity, Option<Entity>)> = roots
+        .into_iter()
+        .map(|root| (root, world.get::<Parent>(root).map(|p| p.get())))
+        .collect();
+
+    for (root, _) in &roots {
+        world.entity_mut(*root).despawn_recursive();
+    }
+
+    EntitySnapshot { scene, roots }
+}
+
+fn restore(world: &mut World, snapshot: &EntitySnapshot) {
+    let mut entity_map = EntityHashMap::default();
+    if let Err(e) = snapshot.scene.write_to_world(world, &mut entity_map) {
+        warn!("Editor: failed to restore entities: {}", e);
+        return;
+    }
+
+    world.resource_mut::<EditorEntityMap>().0.extend(entity_map.iter().map(|(old, new)| (*old, *new)));
+
+    // Parents outside the snapshot were not remapped by the scene; reattach by hand
+    for (root, parent) in &snapshot.roots {
+        let Some(new_root) = entity_map.get(root).copied() else {
+            continue;
+        };
+        world.entity_mut(new_root).remove::<Parent>();
+        if let Some(parent) = parent.map(|p| resolve(world, p)) {
+            if let Ok(mut parent) = world.get_entity_mut(parent) {
+                parent.add_child(new_root);
+            }
+        }
+    }
+}
+
+pub struct DeleteEntitiesCommand {
+    pub entities: Vec<Entity>,
+    snapshot: Option<EntitySnapshot>,
+}
+
+impl DeleteEntitiesCommand {
+    pub fn new(entities: Vec<Entity>) -> Self {
+        Self {
+            entities,
+            snapshot: None,
+        }
+    }
+}
+
+impl EditorCommand for DeleteEntitiesCommand {
+    fn label(&self) -> String {
+        format!("Delete {} entities", self.entities.len())
+    }
+
+    fn apply(&mut self, world: &mut World) {
+        self.snapshot = Some(capture_and_despawn(world, &self.entities));
+    }
+
+    fn undo(&mut self, world: &mut World) {
+        if let Some(snapshot) = self.snapshot.take() {
+            restore(world, &snapshot);
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Records entities the editor just spawned (placement, duplication,
+/// prefab instancing). Use with [`EditHistory::record`].
+pub struct CreateEntitiesCommand {
+    pub label: String,
+    pub entities: Vec<Entity>,
+    snapshot: Option<EntitySnapshot>,
+}
+
+impl CreateEntitiesCommand {
+    pub fn new(label: impl Into<String>, entities: Vec<Entity>) -> Self {
+        Self {
+            label: label.into(),
+            entities,
+            snapshot: None,
+        }
+    }
+}
+
+impl EditorCommand for CreateEntitiesCommand {
+    fn label(&self) -> String {
+        self.label.clone()
+    }
+
+    fn apply(&mut self, world: &mut World) {
+        if let Some(snapshot) = self.snapshot.take() {
+            restore(world, &snapshot);
+        }
+    }
+
+    fn undo(&mut self, world: &mut World) {
+        self.snapshot = Some(capture_and_despawn(world, &self.entities));
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+// ---------------------------------------------------------------------------
+// History panel
+// ---------------------------------------------------------------------------
+
+#[derive(Component)]
+struct HistoryPanel;
+
+#[derive(Component)]
+struct HistoryPanelText;
+
+fn setup_history_panel(mut commands: Commands) {
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                left: Val::Px(10.0),
+                bottom: Val::Px(10.0),
+                min_width: Val::Px(220.0),
+                padding: UiRect::all(Val::Px(6.0)),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.05, 0.05, 0.08, 0.85)),
+            Visibility::Hidden,
+            HistoryPanel,
+            Name::new("Edit History Panel"),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new("History"),
+                TextFont {
+                    font_size: 13.0,
+                    ..default()
+                },
+                HistoryPanelText,
+            ));
+        });
+}
+
+fn update_history_panel(
+    mode: Res<AuthoringMode>,
+    config: Res<EditHistoryConfig>,
+    history: Res<EditHistory>,
+    mut panels: Query<&mut Visibility, With<HistoryPanel>>,
+    mut texts: Query<&mut Text, With<HistoryPanelText>>,
+    mut shown_revision: Local<Option<u64>>,
+) {
+    let visible = mode.active && config.show_panel;
+    for mut visibility in panels.iter_mut() {
+        let target = if visible { Visibility::Inherited } else { Visibility::Hidden };
+        if *visibility != target {
+            *visibility = target;
+        }
+    }
+    if !visible || (*shown_revision == Some(history.revision) && !mode.is_changed()) {
+        return;
+    }
+    *shown_revision = Some(history.revision);
+
+    let (labels, applied) = history.labels();
+    let start = applied.saturating_sub(config.panel_entries / 2);
+    let mut lines = vec![format!("History ({}/{})", applied, labels.len())];
+    for (i, label) in labels.iter().enumerate().skip(start).take(config.panel_entries) {
+        let marker = if i + 1 == applied {
+            ">"
+        } else if i >= applied {
+            " ~"
+        } else {
+            " "
+        };
+        lines.push(format!("{} {}", marker, label));
+    }
+
+    for mut text in texts.iter_mut() {
+        text.0 = lines.join("\n");
+    }
+}
@@ -0,0 +1,378 @@
+use bevy::prelude::*;
+use bevy::reflect::{PartialReflect, ReflectMut};
+use bevy::window::PrimaryWindow;
+use bevy_egui::{egui, EguiContext, EguiPlugin};
+
+use super::history::{EditHistory, SetPropertyCommand, SetTransformCommand};
+use super::transform_tools::EditorSelection;
+use super::AuthoringMode;
+
+/// Entity inspector. Available in the editor and, in debug builds, in the
+/// running game; edits go through [`EditHistory`] so they can be undone.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct InspectorState {
+    pub open: bool,
+    /// Matches entity names and ids.
+    pub name_filter: String,
+    /// Matches component type names, e.g. `Health` or `Transform`.
+    pub component_filter: String,
+    pub selected: Option<Entity>,
+    /// Editor selection last mirrored into `selected`.
+    synced_selection: Option<Entity>,
+}
+
+pub struct InspectorPlugin;
+
+impl Plugin for InspectorPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugins(EguiPlugin);
+        }
+        app.init_resource::<InspectorState>().add_systems(
+            Update,
+            (
+                toggle_inspector,
+                inspector_ui.run_if(|state: Res<InspectorState>| state.open),
+            )
+                .chain(),
+        );
+    }
+}
+
+/// F9 opens the inspector: always while authoring, otherwise only in debug builds.
+fn toggle_inspector(keyboard: Res<ButtonInput<KeyCode>>, mode: Res<AuthoringMode>, mut state: ResMut<InspectorState>) {
+    if keyboard.just_pressed(KeyCode::F9) && (mode.active || cfg!(debug_assertions)) {
+        state.open = !state.open;
+    }
+}
+
+fn short_type_name(path: &str) -> &str {
+    // Keep generics readable: only strip the module path before the first `<`
+    let head = path.split('<').next().unwrap_or(path);
+    let start = head.rfind("::").map_or(0, |i| i + 2);
+    &path[start..]
+}
+
+fn join_path(parent: &str, field: &str) -> String {
+    if parent.is_empty() {
+        field.to_string()
+    } else {
+        format!("{}.{}", parent, field)
+    }
+}
+
+/// One field edited this frame: reflect path within the component, and its new value.
+type FieldChange = (String, Box<dyn PartialReflect>);
+
+fn edit_leaf(ui: &mut egui::Ui, label: &str, value: &mut dyn PartialReflect) -> Option<bool> {
+    macro_rules! drag {
+        ($($ty:ty),*) => {
+            $(
+                if let Some(v) = value.try_downcast_mut::<$ty>() {
+                    return Some(ui.add(egui::DragValue::new(v).prefix(format!("{}: ", label)).speed(0.1)).changed());
+                }
+            )*
+        };
+    }
+    drag!(f32, f64, i8, i16, i32, i64, u8, u16, u32, u64, usize);
+
+    if let Some(v) = value.try_downcast_mut::<bool>() {
+        return Some(ui.checkbox(v, label).changed());
+    }
+    if let Some(v) = value.try_downcast_mut::<String>() {
+        return Some(
+            ui.horizontal(|ui| {
+                ui.label(label);
+                ui.text_edit_singleline(v).changed()
+            })
+            .inner,
+        );
+    }
+    None
+}
+
+fn edit_reflect(
+    ui: &mut egui::Ui,
+    label: &str,
+    path: &str,
+    value: &mut dyn PartialReflect,
+    changes: &mut Vec<FieldChange>,
+) {
+    if let Some(changed) = edit_leaf(ui, label, value) {
+        if changed {
+            changes.push((path.to_string(), value.clone_value()));
+        }
+        return;
+    }
+
+    match value.reflect_mut() {
+        ReflectMut::Struct(s) => {
+            ui.collapsing(label, |ui| {
+                for i in 0..s.field_len() {
+                    let name = s.name_at(i).unwrap_or_default().to_string();
+                    if let Some(field) = s.field_at_mut(i) {
+                        edit_reflect(ui, &name, &join_path(path, &name), field, changes);
+                    }
+                }
+            });
+        }
+        ReflectMut::TupleStruct(s) => {
+            ui.collapsing(label, |ui| {
+                for i in 0..s.field_len() {
+                    if let Some(field) = s.field_mut(i) {
+                        edit_reflect(ui, &i.to_string(), &format!("{}.{}", path, i), field, changes);
+                    }
+                }
+            });
+        }
+        ReflectMut::Tuple(t) => {
+            ui.collapsing(label, |ui| {
+                for i in 0..t.field_len() {
+                    if let Some(field) = t.field_mut(i) {
+                        edit_reflect(ui, &i.to_string(), &format!("{}.{}", path, i), field, changes);
+                    }
+                }
+            });
+        }
+        ReflectMut::List(list) => {
+            ui.collapsing(format!("{} [{}]", label, list.len()), |ui| {
+                for i in 0..list.len() {
+                    if let Some(item) = list.get_mut(i) {
+                        edit_reflect(ui, &format!("[{}]", i), &format!("{}[{}]", path, i), item, changes);
+                    }
+                }
+            });
+        }
+        ReflectMut::Array(array) => {
+            ui.collapsing(format!("{} [{}]", label, array.len()), |ui| {
+                for i in 0..array.len() {
+                    if let Some(item) = array.get_mut(i) {
+                        edit_reflect(ui, &format!("[{}]", i), &format!("{}[{}]", path, i), item, changes);
+                    }
+                }
+            });
+        }
+        ReflectMut::Enum(e) => {
+            let variant = e.variant_name().to_string();
+            if e.field_len() == 0 {
+                ui.label(format!("{}: {}", label, variant));
+                return;
+            }
+            ui.collapsing(format!("{}: {}", label, variant), |ui| {
+                for i in 0..e.field_len() {
+                    let name = e.name_at(i).map_or_else(|| i.to_string(), str::to_string);
+                    let field_path = if e.name_at(i).is_some() {
+                        join_path(path, &name)
+                    } else {
+                        format!("{}.{}", path, i)
+                    };
+                    if let Some(field) = e.field_at_mut(i) {
+                        edit_reflect(ui, &name, &field_path, field, changes);
+                    }
+                }
+            });
+        }
+        ReflectMut::Map(map) => {
+            ui.label(format!("{}: {} entries", label, map.len()));
+        }
+        ReflectMut::Set(set) => {
+            ui.label(format!("{}: {} entries", label, set.len()));
+        }
+        _ => {
+            ui.label(format!("{}: {:?}", label, value));
+        }
+    }
+}
+
+/// Translation, Euler rotation (degrees, YXZ like prefabs) and scale.
+fn edit_transform(ui: &mut egui::Ui, transform: &mut Transform) -> bool {
+    let mut changed = false;
+    let mut row = |ui: &mut egui::Ui, label: &str, values: &mut [f32; 3], speed: f32| {
+        ui.horizontal(|ui| {
+            ui.label(label);
+            for v in values.iter_mut() {
+                changed |= ui.add(egui::DragValue::new(v).speed(speed)).changed();
+            }
+        });
+    };
+
+    let mut translation = transform.translation.to_array();
+    row(ui, "Position", &mut translation, 0.1);
+    let (y, x, z) = transform.rotation.to_euler(EulerRot::YXZ);
+    let mut rotation = [x.to_degrees(), y.to_degrees(), z.to_degrees()];
+    row(ui, "Rotation", &mut rotation, 1.0);
+    let mut scale = transform.scale.to_array();
+    row(ui, "Scale", &mut scale, 0.05);
+
+    if changed {
+        transform.translation = Vec3::from_array(translation);
+        transform.rotation = Quat::from_euler(
+            EulerRot::YXZ,
+            rotation[1].to_radians(),
+            rotation[0].to_radians(),
+            rotation[2].to_radians(),
+        );
+        transform.scale = Vec3::from_array(scale);
+    }
+    changed
+}
+
+fn entity_label(world: &World, entity: Entity) -> String {
+    match world.get::<Name>(entity) {
+        Some(name) => format!("{} ({})", name.as_str(), entity),
+        None => format!("Entity {}", entity),
+    }
+}
+
+fn inspector_ui(world: &mut World) {
+    let Ok(mut context) = world
+        .query_filtered::<&mut EguiContext, With<PrimaryWindow>>()
+        .get_single_mut(world)
+    else {
+        return;
+    };
+    let ctx = context.get_mut().clone();
+
+    world.resource_scope(|world, mut state: Mut<InspectorState>| {
+        // Follow the editor's active selection when it changes
+        let editor_active = world.get_resource::<EditorSelection>().and_then(|s| s.active());
+        if editor_active != state.synced_selection {
+            state.synced_selection = editor_active;
+            if editor_active.is_some() {
+                state.selected = editor_active;
+            }
+        }
+        if state.selected.is_some_and(|e| world.get_entity(e).is_err()) {
+            state.selected = None;
+        }
+
+        let name_filter = state.name_filter.to_lowercase();
+        let component_filter = state.component_filter.to_lowercase();
+        let mut entities: Vec<(Entity, String)> = world
+            .iter_entities()
+            .map(|e| e.id())
+            .filter(|e| {
+                component_filter.is_empty()
+                    || world
+                        .inspect_entity(*e)
+                        .any(|info| short_type_name(info.name()).to_lowercase().contains(&component_filter))
+            })
+            .map(|e| (e, entity_label(world, e)))
+            .filter(|(_, label)| name_filter.is_empty() || label.to_lowercase().contains(&name_filter))
+            .collect();
+        entities.sort_by_key(|(e, _)| *e);
+
+        let mut clicked = None;
+        let mut transform_edit = None;
+        let mut changes: Vec<(String, Vec<FieldChange>)> = Vec::new();
+
+        egui::SidePanel::right("entity_inspector")
+            .default_width(340.0)
+            .show(&ctx, |ui| {
+                ui.heading("Inspector");
+                ui.horizontal(|ui| {
+                    ui.label("Name");
+                    ui.text_edit_singleline(&mut state.name_filter);
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Component");
+                    ui.text_edit_singleline(&mut state.component_filter);
+                });
+                ui.label(format!("{} entities", entities.len()));
+
+                let row_height = ui.text_style_height(&egui::TextStyle::Body);
+                egui::ScrollArea::vertical()
+                    .id_salt("inspector_entities")
+                    .max_height(240.0)
+                    .show_rows(ui, row_height, entities.len(), |ui, rows| {
+                        for (entity, label) in &entities[rows] {
+                            if ui.selectable_label(state.selected == Some(*entity), label).clicked() {
+                                clicked = Some(*entity);
+                            }
+                        }
+                    });
+                ui.separator();
+
+                let Some(entity) = state.selected else {
+                    ui.label("No entity selected");
+                    return;
+                };
+                ui.strong(entity_label(world, entity));
+
+                egui::ScrollArea::vertical().id_salt("inspector_components").show(ui, |ui| {
+                    if let Some(transform) = world.get::<Transform>(entity) {
+                        let mut edited = *transform;
+                        egui::CollapsingHeader::new("Transform").default_open(true).show(ui, |ui| {
+                            if edit_transform(ui, &mut edited) {
+                                transform_edit = Some((*transform, edited));
+                            }
+                        });
+                    }
+
+                    let registry = world.resource::<AppTypeRegistry>().clone();
+                    let registry = registry.read();
+                    let Ok(entity_ref) = world.get_entity(entity) else {
+                        return;
+                    };
+                    let mut components: Vec<(&str, Box<dyn PartialReflect>)> = registry
+                        .iter()
+                        .filter(|registration| registration.type_id() != std::any::TypeId::of::<Transform>())
+                        .filter_map(|registration| {
+                            let reflected = registration.data::<ReflectComponent>()?.reflect(entity_ref)?;
+                            Some((registration.type_info().type_path(), reflected.clone_value()))
+                        })
+                        .collect();
+                    components.sort_by_key(|(path, _)| short_type_name(path));
+
+                    for (path, mut value) in components {
+                        let mut component_changes = Vec::new();
+                        edit_reflect(ui, short_type_name(path), "", value.as_mut(), &mut component_changes);
+                        if !component_changes.is_empty() {
+                            changes.push((path.to_string(), component_changes));
+                        }
+                    }
+                });
+            });
+
+        if let Some(entity) = clicked {
+            state.selected = Some(entity);
+            if let Some(mut selection) = world.get_resource_mut::<EditorSelection>() {
+                selection.entities = vec![entity];
+                state.synced_selection = Some(entity);
+            }
+        }
+
+        let Some(entity) = state.selected else {
+            return;
+        };
+        if let Some((before, after)) = transform_edit {
+            world.resource_mut::<EditHistory>().execute(SetTransformCommand { entity, before, after });
+        }
+        for (component, fields) in changes {
+            for (field, after) in fields {
+                let Some(before) = current_field_value(world, entity, &component, &field) else {
+                    continue;
+                };
+                world.resource_mut::<EditHistory>().execute(SetPropertyCommand {
+                    entity,
+                    component: component.clone(),
+                    field,
+                    before,
+                    after,
+                });
+            }
+        }
+    });
+}
+
+fn current_field_value(world: &World, entity: Entity, component: &str, field: &str) -> Option<Box<dyn PartialReflect>> {
+    let registry = world.resource::<AppTypeRegistry>().read();
+    let reflected = registry
+        .get_with_type_path(component)?
+        .data::<ReflectComponent>()?
+        .reflect(world.get_entity(entity).ok()?)?;
+    if field.is_empty() {
+        return Some(reflected.clone_value());
+    }
+    reflected.reflect_path(field).ok().map(|value| value.clone_value())
+}
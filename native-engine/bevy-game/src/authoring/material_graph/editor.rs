@@ -0,0 +1,327 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts, EguiPlugin};
+
+use super::super::node_canvas::{node_canvas, CanvasAction, CanvasLink, CanvasNode, NodeCanvasState};
+use super::super::transform_tools::EditorSelection;
+use super::super::AuthoringMode;
+use super::{
+    register_material_graph, ApplyMaterialGraphEvent, BlendMode, GraphMaterial, MaterialGraphConfig,
+    MaterialGraphDef, MaterialGraphLibrary, MaterialGraphPreview, MaterialNodeDef, MaterialNodeKind,
+    RevertMaterialGraphEvent, SaveMaterialGraphEvent,
+};
+
+#[derive(Resource, Debug, Default)]
+pub struct MaterialGraphEditorState {
+    pub open: bool,
+    pub graph: Option<String>,
+    pub selected_node: Option<String>,
+    canvas: NodeCanvasState,
+    new_graph_name: String,
+}
+
+pub struct MaterialGraphEditorPlugin;
+
+impl Plugin for MaterialGraphEditorPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugins(EguiPlugin);
+        }
+        app.init_resource::<MaterialGraphEditorState>().add_systems(
+            Update,
+            (
+                toggle_material_graph_editor,
+                material_graph_editor_ui.run_if(|state: Res<MaterialGraphEditorState>| state.open),
+            )
+                .chain(),
+        );
+    }
+}
+
+/// F8 opens the graph editor while authoring.
+fn toggle_material_graph_editor(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mode: Res<AuthoringMode>,
+    mut state: ResMut<MaterialGraphEditorState>,
+    mut preview: ResMut<MaterialGraphPreview>,
+) {
+    if mode.active && keyboard.just_pressed(KeyCode::F8) {
+        state.open = !state.open;
+    }
+    if !mode.active && state.open {
+        state.open = false;
+    }
+    let wanted = state.graph.clone().filter(|_| state.open);
+    if preview.graph != wanted {
+        preview.graph = wanted;
+    }
+}
+
+fn new_node_kinds() -> Vec<MaterialNodeKind> {
+    vec![
+        MaterialNodeKind::Texture {
+            path: String::new(),
+            uv_scale: [1.0, 1.0],
+        },
+        MaterialNodeKind::Color {
+            value: [1.0, 1.0, 1.0, 1.0],
+        },
+        MaterialNodeKind::Scalar { value: 0.5 },
+        MaterialNodeKind::Uv,
+        MaterialNodeKind::Time,
+        MaterialNodeKind::Add,
+        MaterialNodeKind::Subtract,
+        MaterialNodeKind::Multiply,
+        MaterialNodeKind::Lerp,
+        MaterialNodeKind::OneMinus,
+        MaterialNodeKind::Power,
+        MaterialNodeKind::Saturate,
+        MaterialNodeKind::Sine,
+        MaterialNodeKind::Blend { mode: BlendMode::Mix },
+    ]
+}
+
+/// Edits the selected node's constants. Returns true if anything changed.
+fn node_properties_ui(ui: &mut egui::Ui, node: &mut MaterialNodeDef) -> bool {
+    let mut changed = false;
+    match &mut node.kind {
+        MaterialNodeKind::Texture { path, uv_scale } => {
+            ui.horizontal(|ui| {
+                ui.label("Path");
+                changed |= ui.text_edit_singleline(path).lost_focus();
+            });
+            ui.horizontal(|ui| {
+                ui.label("UV scale");
+                changed |= ui.add(egui::DragValue::new(&mut uv_scale[0]).speed(0.05)).changed();
+                changed |= ui.add(egui::DragValue::new(&mut uv_scale[1]).speed(0.05)).changed();
+            });
+        }
+        MaterialNodeKind::Color { value } => {
+            ui.horizontal(|ui| {
+                ui.label("Color");
+                changed |= ui.color_edit_button_rgba_unmultiplied(value).changed();
+            });
+        }
+        MaterialNodeKind::Scalar { value } => {
+            changed |= ui.add(egui::DragValue::new(value).speed(0.01).prefix("Value: ")).changed();
+        }
+        MaterialNodeKind::Blend { mode } => {
+            egui::ComboBox::from_label("Mode")
+                .selected_text(format!("{:?}", mode))
+                .show_ui(ui, |ui| {
+                    for option in [BlendMode::Mix, BlendMode::Multiply, BlendMode::Screen, BlendMode::Overlay] {
+                        changed |= ui.selectable_value(mode, option, format!("{:?}", option)).changed();
+                    }
+                });
+        }
+        _ => {
+            ui.label("No properties");
+        }
+    }
+    changed
+}
+
+fn material_graph_editor_ui(
+    mut contexts: EguiContexts,
+    mut state: ResMut<MaterialGraphEditorState>,
+    mut library: ResMut<MaterialGraphLibrary>,
+    config: Res<MaterialGraphConfig>,
+    shaders: Res<Assets<Shader>>,
+    mut materials: ResMut<Assets<GraphMaterial>>,
+    selection: Res<EditorSelection>,
+    mut apply: EventWriter<ApplyMaterialGraphEvent>,
+    mut revert: EventWriter<RevertMaterialGraphEvent>,
+    mut save: EventWriter<SaveMaterialGraphEvent>,
+) {
+    let ctx = contexts.ctx_mut().clone();
+    let state = &mut *state;
+    let mut open = state.open;
+    let mut recompile = false;
+
+    egui::Window::new("Material Graph")
+        .open(&mut open)
+        .default_size([900.0, 560.0])
+        .show(&ctx, |ui| {
+            // Graph picker and actions
+            ui.horizontal(|ui| {
+                let mut ids: Vec<&String> = library.graphs.keys().collect();
+                ids.sort();
+                egui::ComboBox::from_id_salt("material_graph_picker")
+                    .selected_text(state.graph.clone().unwrap_or_else(|| "Select graph".into()))
+                    .show_ui(ui, |ui| {
+                        for id in ids {
+                            if ui.selectable_label(state.graph.as_ref() == Some(id), id.as_str()).clicked() {
+                                state.graph = Some(id.clone());
+                                state.selected_node = None;
+                            }
+                        }
+                    });
+
+                ui.text_edit_singleline(&mut state.new_graph_name);
+                let name = state.new_graph_name.trim().to_string();
+                if ui.button("New").clicked() && !name.is_empty() && !library.graphs.contains_key(&name) {
+                    let def = MaterialGraphDef {
+                        id: name.clone(),
+                        nodes: vec![MaterialNodeDef {
+                            id: "output".into(),
+                            kind: MaterialNodeKind::Output,
+                            position: [420.0, 120.0],
+                            inputs: Default::default(),
+                        }],
+                    };
+                    let source = config.graph_directory.join(format!("{}.toml", name));
+                    register_material_graph(&mut library, &shaders, &mut materials, def, source);
+                    state.graph = Some(name);
+                    state.new_graph_name.clear();
+                }
+
+                let Some(graph) = state.graph.clone() else {
+                    return;
+                };
+                if ui.button("Save").clicked() {
+                    save.send(SaveMaterialGraphEvent { graph: graph.clone() });
+                }
+                let targets = selection.entities.clone();
+                if ui
+                    .add_enabled(!targets.is_empty(), egui::Button::new("Apply to selection"))
+                    .clicked()
+                {
+                    apply.send(ApplyMaterialGraphEvent { graph, entities: targets.clone() });
+                }
+                if ui.add_enabled(!targets.is_empty(), egui::Button::new("Revert selection")).clicked() {
+                    revert.send(RevertMaterialGraphEvent { entities: targets });
+                }
+            });
+
+            let Some(entry) = state.graph.as_ref().and_then(|id| library.graphs.get_mut(id)) else {
+                ui.label("Pick or create a graph. Right-click the canvas to add nodes.");
+                return;
+            };
+            if let Some(error) = &entry.error {
+                ui.colored_label(egui::Color32::from_rgb(230, 90, 80), error);
+            }
+            let def = &mut entry.def;
+
+            ui.columns(2, |columns| {
+                // Selected node properties
+                let side = &mut columns[1];
+                side.set_max_width(220.0);
+                match state.selected_node.clone().and_then(|id| def.nodes.iter().position(|n| n.id == id)) {
+                    Some(index) => {
+                        side.strong(format!("{} ({})", def.nodes[index].id, def.nodes[index].kind.label()));
+                        recompile |= node_properties_ui(side, &mut def.nodes[index]);
+                        if def.nodes[index].kind != MaterialNodeKind::Output && side.button("Delete node").clicked() {
+                            let removed = def.nodes.remove(index).id;
+                            for node in def.nodes.iter_mut() {
+                                node.inputs.retain(|_, source| *source != removed);
+                            }
+                            state.selected_node = None;
+                            recompile = true;
+                        }
+                    }
+                    None => {
+                        side.label("Click a node to edit it");
+                    }
+                }
+
+                // Node canvas
+                let nodes: Vec<CanvasNode> = def
+                    .nodes
+                    .iter()
+                    .map(|node| CanvasNode {
+                        id: node.id.clone(),
+                        title: format!("{}  {}", node.kind.label(), node.id),
+                        position: node.position,
+                        inputs: node.kind.inputs().iter().map(|(slot, _)| slot.to_string()).collect(),
+                        outputs: if node.kind.has_output() { vec!["out".into()] } else { Vec::new() },
+                        flagged: false,
+                    })
+                    .collect();
+                let links: Vec<CanvasLink> = def
+                    .nodes
+                    .iter()
+                    .flat_map(|node| {
+                        node.kind.inputs().iter().enumerate().filter_map(|(index, (slot, _))| {
+                            node.inputs.get(*slot).map(|source| CanvasLink {
+                                from: source.clone(),
+                                output: 0,
+                                to: node.id.clone(),
+                                input: index,
+                            })
+                        })
+                        .collect::<Vec<_>>()
+                    })
+                    .collect();
+
+                let mut added = None;
+                let actions = node_canvas(
+                    &mut columns[0],
+                    &mut state.canvas,
+                    &nodes,
+                    &links,
+                    state.selected_node.as_deref(),
+                    480.0,
+                    |ui, at| {
+                        // Right-click the background to add nodes
+                        for kind in new_node_kinds() {
+                            if ui.button(kind.label()).clicked() {
+                                added = Some((kind, at));
+                                ui.close_menu();
+                            }
+                        }
+                    },
+                );
+                if let Some((kind, position)) = added {
+                    let id = def.next_node_id(&kind);
+                    def.nodes.push(MaterialNodeDef {
+                        id: id.clone(),
+                        kind,
+                        position,
+                        inputs: Default::default(),
+                    });
+                    state.selected_node = Some(id);
+                    recompile = true;
+                }
+
+                for action in actions {
+                    match action {
+                        CanvasAction::Select(id) => state.selected_node = Some(id),
+                        CanvasAction::Move { node, delta } => {
+                            if let Some(node) = def.nodes.iter_mut().find(|n| n.id == node) {
+                                node.position[0] += delta[0];
+                                node.position[1] += delta[1];
+                            }
+                        }
+                        CanvasAction::Connect { from, to, input, .. } => {
+                            if let Some(node) = def.nodes.iter_mut().find(|n| n.id == to) {
+                                if let Some((slot, _)) = node.kind.inputs().get(input) {
+                                    node.inputs.insert(slot.to_string(), from);
+                                    recompile = true;
+                                }
+                            }
+                        }
+                        CanvasAction::DisconnectInput { node, input } => {
+                            if let Some(node) = def.nodes.iter_mut().find(|n| n.id == node) {
+                                if let Some((slot, _)) = node.kind.inputs().get(input) {
+                                    recompile |= node.inputs.remove(*slot).is_some();
+                                }
+                            }
+                        }
+                        CanvasAction::DisconnectOutput { node, .. } => {
+                            for target in def.nodes.iter_mut() {
+                                let before = target.inputs.len();
+                                target.inputs.retain(|_, source| *source != node);
+                                recompile |= target.inputs.len() != before;
+                            }
+                        }
+                    }
+                }
+            });
+        });
+
+    state.open = open;
+    if recompile {
+        if let Some(graph) = state.graph.clone() {
+            library.mark_changed(&graph);
+        }
+    }
+}
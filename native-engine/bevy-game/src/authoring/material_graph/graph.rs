@@ -0,0 +1,335 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+/// Texture slots on [`super::GraphMaterial`]; graphs sampling more files fail to compile.
+pub const MAX_GRAPH_TEXTURES: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BlendMode {
+    Mix,
+    Multiply,
+    Screen,
+    Overlay,
+}
+
+/// Every node produces a `vec4<f32>`; scalars are splatted.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MaterialNodeKind {
+    Texture {
+        path: String,
+        #[serde(default = "default_uv_scale")]
+        uv_scale: [f32; 2],
+    },
+    Color {
+        value: [f32; 4],
+    },
+    Scalar {
+        value: f32,
+    },
+    Uv,
+    Time,
+    Add,
+    Subtract,
+    Multiply,
+    Lerp,
+    OneMinus,
+    Power,
+    Saturate,
+    Sine,
+    Blend {
+        mode: BlendMode,
+    },
+    /// Graph result; exactly one per graph.
+    Output,
+}
+
+fn default_uv_scale() -> [f32; 2] {
+    [1.0, 1.0]
+}
+
+impl MaterialNodeKind {
+    /// Input slots and the value used when a slot is left unconnected.
+    pub fn inputs(&self) -> &'static [(&'static str, f32)] {
+        match self {
+            MaterialNodeKind::Texture { .. } => &[("uv", 0.0)],
+            MaterialNodeKind::Add | MaterialNodeKind::Subtract => &[("a", 0.0), ("b", 0.0)],
+            MaterialNodeKind::Multiply | MaterialNodeKind::Power => &[("a", 1.0), ("b", 1.0)],
+            MaterialNodeKind::Lerp => &[("a", 0.0), ("b", 1.0), ("t", 0.5)],
+            MaterialNodeKind::OneMinus | MaterialNodeKind::Saturate | MaterialNodeKind::Sine => &[("a", 0.0)],
+            MaterialNodeKind::Blend { .. } => &[("base", 1.0), ("layer", 1.0), ("opacity", 1.0)],
+            MaterialNodeKind::Output => &[("base_color", 1.0), ("roughness", 0.5), ("metallic", 0.0), ("emissive", 0.0)],
+            MaterialNodeKind::Color { .. }
+            | MaterialNodeKind::Scalar { .. }
+            | MaterialNodeKind::Uv
+            | MaterialNodeKind::Time => &[],
+        }
+    }
+
+    pub fn has_output(&self) -> bool {
+        !matches!(self, MaterialNodeKind::Output)
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            MaterialNodeKind::Texture { .. } => "Texture",
+            MaterialNodeKind::Color { .. } => "Color",
+            MaterialNodeKind::Scalar { .. } => "Scalar",
+            MaterialNodeKind::Uv => "UV",
+            MaterialNodeKind::Time => "Time",
+            MaterialNodeKind::Add => "Add",
+            MaterialNodeKind::Subtract => "Subtract",
+            MaterialNodeKind::Multiply => "Multiply",
+            MaterialNodeKind::Lerp => "Lerp",
+            MaterialNodeKind::OneMinus => "One Minus",
+            MaterialNodeKind::Power => "Power",
+            MaterialNodeKind::Saturate => "Saturate",
+            MaterialNodeKind::Sine => "Sine",
+            MaterialNodeKind::Blend { .. } => "Blend",
+            MaterialNodeKind::Output => "Output",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MaterialNodeDef {
+    pub id: String,
+    #[serde(flatten)]
+    pub kind: MaterialNodeKind,
+    /// Canvas position in the graph editor.
+    #[serde(default)]
+    pub position: [f32; 2],
+    /// Input slot -> id of the node feeding it.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub inputs: BTreeMap<String, String>,
+}
+
+/// `content/materials/graphs/<id>.toml`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MaterialGraphDef {
+    pub id: String,
+    #[serde(default, rename = "node")]
+    pub nodes: Vec<MaterialNodeDef>,
+}
+
+impl MaterialGraphDef {
+    pub fn node(&self, id: &str) -> Option<&MaterialNodeDef> {
+        self.nodes.iter().find(|n| n.id == id)
+    }
+
+    pub fn output(&self) -> Option<&MaterialNodeDef> {
+        self.nodes.iter().find(|n| n.kind == MaterialNodeKind::Output)
+    }
+
+    /// Fresh node id such as `multiply_3`.
+    pub fn next_node_id(&self, kind: &MaterialNodeKind) -> String {
+        let prefix = kind.label().to_lowercase().replace(' ', "_");
+        (1..)
+            .map(|n| format!("{}_{}", prefix, n))
+            .find(|id| self.node(id).is_none())
+            .unwrap_or(prefix)
+    }
+}
+
+/// WGSL for one graph plus the texture files bound to its slots, in slot order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompiledMaterialGraph {
+    pub wgsl: String,
+    pub textures: Vec<String>,
+}
+
+fn splat(value: f32) -> String {
+    format!("vec4<f32>({:?})", value)
+}
+
+/// Emit `let` bindings for `id` and everything it depends on, in dependency order.
+fn emit_node(
+    graph: &MaterialGraphDef,
+    id: &str,
+    emitted: &mut HashMap<String, String>,
+    visiting: &mut Vec<String>,
+    textures: &mut Vec<String>,
+    body: &mut String,
+) -> Result<String, String> {
+    if let Some(var) = emitted.get(id) {
+        return Ok(var.clone());
+    }
+    if visiting.iter().any(|v| v == id) {
+        return Err(format!("cycle through node '{}'", id));
+    }
+    let node = graph.node(id).ok_or_else(|| format!("missing node '{}'", id))?;
+    if !node.kind.has_output() {
+        return Err(format!("node '{}' has no output", id));
+    }
+
+    visiting.push(id.to_string());
+    let mut args = Vec::new();
+    for (slot, default) in node.kind.inputs() {
+        match node.inputs.get(*slot) {
+            Some(source) => args.push(Some(emit_node(graph, source, emitted, visiting, textures, body)?)),
+            None if *slot == "uv" => args.push(None),
+            None => args.push(Some(splat(*default))),
+        }
+    }
+    visiting.pop();
+
+    let arg = |i: usize| args[i].clone().unwrap_or_default();
+    let expr = match &node.kind {
+        MaterialNodeKind::Texture { path, uv_scale } => {
+            let slot = match textures.iter().position(|t| t == path) {
+                Some(slot) => slot,
+                None if textures.len() < MAX_GRAPH_TEXTURES => {
+                    textures.push(path.clone());
+                    textures.len() - 1
+                }
+                None => return Err(format!("more than {} textures", MAX_GRAPH_TEXTURES)),
+            };
+            let uv = args[0].as_ref().map_or_else(|| "in.uv".to_string(), |v| format!("{}.xy", v));
+            format!(
+                "textureSample(texture_{slot}, sampler_{slot}, {uv} * vec2<f32>({:?}, {:?}))",
+                uv_scale[0], uv_scale[1]
+            )
+        }
+        MaterialNodeKind::Color { value } => {
+            format!("vec4<f32>({:?}, {:?}, {:?}, {:?})", value[0], value[1], value[2], value[3])
+        }
+        MaterialNodeKind::Scalar { value } => splat(*value),
+        MaterialNodeKind::Uv => "vec4<f32>(in.uv, 0.0, 1.0)".to_string(),
+        MaterialNodeKind::Time => "vec4<f32>(globals.time)".to_string(),
+        MaterialNodeKind::Add => format!("{} + {}", arg(0), arg(1)),
+        MaterialNodeKind::Subtract => format!("{} - {}", arg(0), arg(1)),
+        MaterialNodeKind::Multiply => format!("{} * {}", arg(0), arg(1)),
+        MaterialNodeKind::Lerp => format!("mix({}, {}, {})", arg(0), arg(1), arg(2)),
+        MaterialNodeKind::OneMinus => format!("vec4<f32>(1.0) - {}", arg(0)),
+        MaterialNodeKind::Power => format!("pow(max({}, vec4<f32>(0.0)), {})", arg(0), arg(1)),
+        MaterialNodeKind::Saturate => format!("saturate({})", arg(0)),
+        MaterialNodeKind::Sine => format!("sin({})", arg(0)),
+        MaterialNodeKind::Blend { mode } => {
+            let (base, layer, opacity) = (arg(0), arg(1), arg(2));
+            let blended = match mode {
+                BlendMode::Mix => layer.clone(),
+                BlendMode::Multiply => format!("{} * {}", base, layer),
+                BlendMode::Screen => format!("vec4<f32>(1.0) - (vec4<f32>(1.0) - {}) * (vec4<f32>(1.0) - {})", base, layer),
+                BlendMode::Overlay => format!(
+                    "select(vec4<f32>(1.0) - 2.0 * (vec4<f32>(1.0) - {b}) * (vec4<f32>(1.0) - {l}), 2.0 * {b} * {l}, {b} < vec4<f32>(0.5))",
+                    b = base,
+                    l = layer
+                ),
+            };
+            format!("mix({}, {}, {}.x)", base, blended, opacity)
+        }
+        MaterialNodeKind::Output => unreachable!("outputs are rejected above"),
+    };
+
+    let var = format!("n{}", emitted.len());
+    let _ = writeln!(body, "    let {} = {}; // {}", var, expr, node.id);
+    emitted.insert(id.to_string(), var.clone());
+    Ok(var)
+}
+
+/// Lower a graph to a PBR fragment shader for [`super::GraphMaterial`].
+pub fn compile_material_graph(graph: &MaterialGraphDef) -> Result<CompiledMaterialGraph, String> {
+    let outputs = graph.nodes.iter().filter(|n| n.kind == MaterialNodeKind::Output).count();
+    if outputs != 1 {
+        return Err(format!("graph '{}' needs exactly one output node, found {}", graph.id, outputs));
+    }
+    let output = graph.output().expect("counted above");
+
+    let mut emitted = HashMap::new();
+    let mut visiting = Vec::new();
+    let mut textures = Vec::new();
+    let mut body = String::new();
+    let mut results = Vec::new();
+    for (slot, default) in output.kind.inputs() {
+        let value = match output.inputs.get(*slot) {
+            Some(source) => emit_node(graph, source, &mut emitted, &mut visiting, &mut textures, &mut body)?,
+            None => splat(*default),
+        };
+        results.push(value);
+    }
+
+    let mut wgsl = String::new();
+    let _ = writeln!(wgsl, "// Generated from material graph '{}'. Do not edit.", graph.id);
+    wgsl.push_str(
+        "#import bevy_pbr::forward_io::VertexOutput\n\
+         #import bevy_pbr::mesh_view_bindings::globals\n\
+         #import bevy_pbr::pbr_types::{PbrInput, pbr_input_new}\n\
+         #import bevy_pbr::pbr_functions as fns\n\n",
+    );
+    for slot in 0..MAX_GRAPH_TEXTURES {
+        let _ = writeln!(wgsl, "@group(2) @binding({}) var texture_{}: texture_2d<f32>;", slot * 2, slot);
+        let _ = writeln!(wgsl, "@group(2) @binding({}) var sampler_{}: sampler;", slot * 2 + 1, slot);
+    }
+    wgsl.push_str(
+        "\n@fragment\nfn fragment(in: VertexOutput, @builtin(front_facing) is_front: bool) -> @location(0) vec4<f32> {\n",
+    );
+    wgsl.push_str(&body);
+    let _ = writeln!(
+        wgsl,
+        "    var pbr_input: PbrInput = pbr_input_new();\n    \
+         pbr_input.material.base_color = {};\n    \
+         pbr_input.material.perceptual_roughness = clamp({}.x, 0.089, 1.0);\n    \
+         pbr_input.material.metallic = saturate({}.x);\n    \
+         pbr_input.material.emissive = vec4<f32>({}.rgb, 1.0);\n    \
+         pbr_input.frag_coord = in.position;\n    \
+         pbr_input.world_position = in.world_position;\n    \
+         pbr_input.world_normal = fns::prepare_world_normal(in.world_normal, false, is_front);\n    \
+         pbr_input.N = normalize(pbr_input.world_normal);\n    \
+         pbr_input.V = fns::calculate_view(in.world_position, false);\n    \
+         let color = fns::apply_pbr_lighting(pbr_input);\n    \
+         return fns::main_pass_post_lighting_processing(pbr_input, color);\n}}",
+        results[0], results[1], results[2], results[3]
+    );
+
+    Ok(CompiledMaterialGraph { wgsl, textures })
+}
+
+/// Best-effort Atom `StandardPBR` material for a graph. Only constant and
+/// directly-sampled inputs translate; anything computed falls back to defaults.
+pub fn atom_material_definition(graph: &MaterialGraphDef) -> serde_json::Value {
+    let mut properties = serde_json::Map::new();
+    let Some(output) = graph.output() else {
+        return serde_json::json!({});
+    };
+    let source = |slot: &str| output.inputs.get(slot).and_then(|id| graph.node(id)).map(|n| &n.kind);
+
+    match source("base_color") {
+        Some(MaterialNodeKind::Color { value }) => {
+            properties.insert("baseColor.color".into(), serde_json::json!(value));
+        }
+        Some(MaterialNodeKind::Texture { path, .. }) => {
+            properties.insert("baseColor.textureMap".into(), serde_json::json!(path));
+        }
+        _ => {}
+    }
+    for (slot, property) in [("roughness", "roughness"), ("metallic", "metallic")] {
+        match source(slot) {
+            Some(MaterialNodeKind::Scalar { value }) => {
+                properties.insert(format!("{}.factor", property), serde_json::json!(value));
+            }
+            Some(MaterialNodeKind::Texture { path, .. }) => {
+                properties.insert(format!("{}.textureMap", property), serde_json::json!(path));
+            }
+            _ => {}
+        }
+    }
+    match source("emissive") {
+        Some(MaterialNodeKind::Color { value }) => {
+            properties.insert("emissive.enable".into(), serde_json::json!(true));
+            properties.insert("emissive.color".into(), serde_json::json!(value));
+        }
+        Some(MaterialNodeKind::Texture { path, .. }) => {
+            properties.insert("emissive.enable".into(), serde_json::json!(true));
+            properties.insert("emissive.textureMap".into(), serde_json::json!(path));
+        }
+        _ => {}
+    }
+
+    serde_json::json!({
+        "materialType": "Materials/Types/StandardPBR.materialtype",
+        "materialTypeVersion": 5,
+        "propertyValues": properties,
+    })
+}
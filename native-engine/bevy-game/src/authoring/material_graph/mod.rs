@@ -0,0 +1,476 @@
+//! Node-graph materials: graphs of texture, math and blend nodes compiled to
+//! WGSL, previewed live and hot-applied to world meshes.
+
+use bevy::pbr::{MaterialPipeline, MaterialPipelineKey};
+use bevy::prelude::*;
+use bevy::render::mesh::MeshVertexBufferLayoutRef;
+use bevy::render::render_resource::{
+    AsBindGroup, RenderPipelineDescriptor, SpecializedMeshPipelineError,
+};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::content_packs::ContentPacks;
+use crate::game_settings::RendererChoice;
+use crate::renderer_backend::ActiveRenderer;
+
+#[cfg(feature = "inspector")]
+mod editor;
+pub mod graph;
+
+#[cfg(feature = "inspector")]
+pub use editor::*;
+pub use graph::*;
+
+/// Material whose fragment shader is generated from a [`MaterialGraphDef`].
+/// The shader handle is part of the pipeline key, so each graph gets its own
+/// pipeline and replacing the shader asset recompiles it in place.
+#[derive(Asset, TypePath, AsBindGroup, Debug, Clone)]
+#[bind_group_data(GraphMaterialKey)]
+pub struct GraphMaterial {
+    #[texture(0)]
+    #[sampler(1)]
+    pub texture_0: Option<Handle<Image>>,
+    #[texture(2)]
+    #[sampler(3)]
+    pub texture_1: Option<Handle<Image>>,
+    #[texture(4)]
+    #[sampler(5)]
+    pub texture_2: Option<Handle<Image>>,
+    #[texture(6)]
+    #[sampler(7)]
+    pub texture_3: Option<Handle<Image>>,
+    pub shader: Handle<Shader>,
+}
+
+impl GraphMaterial {
+    fn texture_slot(&mut self, slot: usize) -> &mut Option<Handle<Image>> {
+        match slot {
+            0 => &mut self.texture_0,
+            1 => &mut self.texture_1,
+            2 => &mut self.texture_2,
+            _ => &mut self.texture_3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GraphMaterialKey {
+    shader: Handle<Shader>,
+}
+
+impl From<&GraphMaterial> for GraphMaterialKey {
+    fn from(material: &GraphMaterial) -> Self {
+        Self {
+            shader: material.shader.clone(),
+        }
+    }
+}
+
+impl Material for GraphMaterial {
+    fn specialize(
+        _pipeline: &MaterialPipeline<Self>,
+        descriptor: &mut RenderPipelineDescriptor,
+        _layout: &MeshVertexBufferLayoutRef,
+        key: MaterialPipelineKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        if let Some(fragment) = descriptor.fragment.as_mut() {
+            fragment.shader = key.bind_group_data.shader;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct MaterialGraphConfig {
+    pub graph_directory: PathBuf,
+    /// Atom `.material` files are written here when the Atom renderer is linked.
+    pub atom_directory: PathBuf,
+    /// How often graph files are checked for outside edits.
+    pub poll_seconds: f32,
+    /// Distance of the preview mesh in front of the camera.
+    pub preview_distance: f32,
+}
+
+impl Default for MaterialGraphConfig {
+    fn default() -> Self {
+        Self {
+            graph_directory: PathBuf::from("content").join("materials").join("graphs"),
+            atom_directory: PathBuf::from("content").join("materials").join("atom"),
+            poll_seconds: 1.0,
+            preview_distance: 4.0,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct MaterialGraphEntry {
+    pub def: MaterialGraphDef,
+    pub source: PathBuf,
+    pub modified: Option<SystemTime>,
+    pub shader: Handle<Shader>,
+    pub material: Handle<GraphMaterial>,
+    /// Last compile error; the previous good shader stays bound meanwhile.
+    pub error: Option<String>,
+    pub wgsl: String,
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct MaterialGraphLibrary {
+    pub graphs: HashMap<String, MaterialGraphEntry>,
+    dirty: Vec<String>,
+}
+
+impl MaterialGraphLibrary {
+    /// Queue a graph for recompilation after an in-memory edit.
+    pub fn mark_changed(&mut self, graph: &str) {
+        if !self.dirty.iter().any(|g| g == graph) {
+            self.dirty.push(graph.to_string());
+        }
+    }
+}
+
+/// Swap the meshes under `entities` (and their descendants) to a graph material.
+#[derive(Event, Debug, Clone)]
+pub struct ApplyMaterialGraphEvent {
+    pub graph: String,
+    pub entities: Vec<Entity>,
+}
+
+/// Put back the materials replaced by [`ApplyMaterialGraphEvent`].
+#[derive(Event, Debug, Clone)]
+pub struct RevertMaterialGraphEvent {
+    pub entities: Vec<Entity>,
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct SaveMaterialGraphEvent {
+    pub graph: String,
+}
+
+/// Marks a mesh whose standard material was replaced by a graph material.
+#[derive(Component, Debug, Clone)]
+pub struct GraphMaterialOverride {
+    pub graph: String,
+    pub original: Option<Handle<StandardMaterial>>,
+}
+
+/// Graph shown on the preview sphere; `None` hides it.
+#[derive(Resource, Debug, Default)]
+pub struct MaterialGraphPreview {
+    pub graph: Option<String>,
+    entity: Option<Entity>,
+}
+
+#[derive(Component)]
+struct MaterialPreviewMesh;
+
+pub struct MaterialGraphPlugin;
+
+impl Plugin for MaterialGraphPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(MaterialPlugin::<GraphMaterial>::default())
+            .init_resource::<MaterialGraphConfig>()
+            .init_resource::<ContentPacks>()
+            .init_resource::<MaterialGraphLibrary>()
+            .init_resource::<MaterialGraphPreview>()
+            .add_event::<ApplyMaterialGraphEvent>()
+            .add_event::<RevertMaterialGraphEvent>()
+            .add_event::<SaveMaterialGraphEvent>()
+            .add_systems(Startup, load_material_graphs)
+            .add_systems(
+                Update,
+                (
+                    watch_material_graph_files,
+                    compile_material_graphs,
+                    update_material_preview,
+                    apply_material_graphs,
+                    save_material_graphs,
+                )
+                    .chain(),
+            );
+
+        #[cfg(feature = "inspector")]
+        app.add_plugins(MaterialGraphEditorPlugin);
+    }
+}
+
+fn read_graph(path: &Path) -> Result<MaterialGraphDef, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    toml::from_str(&text).map_err(|e| e.to_string())
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Adds a graph to the library with a reserved shader and a material bound to it.
+pub fn register_material_graph(
+    library: &mut MaterialGraphLibrary,
+    shaders: &Assets<Shader>,
+    materials: &mut Assets<GraphMaterial>,
+    def: MaterialGraphDef,
+    source: PathBuf,
+) {
+    let shader = shaders.reserve_handle();
+    let material = materials.add(GraphMaterial {
+        texture_0: None,
+        texture_1: None,
+        texture_2: None,
+        texture_3: None,
+        shader: shader.clone(),
+    });
+    let id = def.id.clone();
+    library.graphs.insert(
+        id.clone(),
+        MaterialGraphEntry {
+            def,
+            modified: modified_time(&source),
+            source,
+            shader,
+            material,
+            error: None,
+            wgsl: String::new(),
+        },
+    );
+    library.mark_changed(&id);
+}
+
+fn load_material_graphs(
+    config: Res<MaterialGraphConfig>,
+    packs: Res<ContentPacks>,
+    shaders: Res<Assets<Shader>>,
+    mut materials: ResMut<Assets<GraphMaterial>>,
+    mut library: ResMut<MaterialGraphLibrary>,
+) {
+    for path in packs.files(&config.graph_directory, "toml") {
+        match read_graph(&path) {
+            Ok(def) => register_material_graph(&mut library, &shaders, &mut materials, def, path),
+            Err(e) => warn!("Invalid material graph {:?}: {}", path, e),
+        }
+    }
+    info!("Loaded {} material graphs from {:?}", library.graphs.len(), config.graph_directory);
+}
+
+/// Picks up graph files edited outside the game (text editor, version control).
+fn watch_material_graph_files(
+    time: Res<Time>,
+    config: Res<MaterialGraphConfig>,
+    mut library: ResMut<MaterialGraphLibrary>,
+    mut elapsed: Local<f32>,
+) {
+    *elapsed += time.delta_secs();
+    if *elapsed < config.poll_seconds {
+        return;
+    }
+    *elapsed = 0.0;
+
+    let mut changed = Vec::new();
+    for (id, entry) in library.graphs.iter_mut() {
+        let modified = modified_time(&entry.source);
+        if modified.is_none() || modified == entry.modified {
+            continue;
+        }
+        entry.modified = modified;
+        match read_graph(&entry.source) {
+            Ok(def) if def != entry.def => {
+                info!("Material graph '{}' changed on disk, reloading", id);
+                entry.def = def;
+                changed.push(id.clone());
+            }
+            Ok(_) => {}
+            Err(e) => warn!("Invalid material graph {:?}: {}", entry.source, e),
+        }
+    }
+    for id in changed {
+        library.mark_changed(&id);
+    }
+}
+
+fn compile_material_graphs(
+    config: Res<MaterialGraphConfig>,
+    asset_server: Res<AssetServer>,
+    mut shaders: ResMut<Assets<Shader>>,
+    mut materials: ResMut<Assets<GraphMaterial>>,
+    mut library: ResMut<MaterialGraphLibrary>,
+    renderer: Option<Res<ActiveRenderer>>,
+) {
+    let dirty = std::mem::take(&mut library.dirty);
+    for id in dirty {
+        let Some(entry) = library.graphs.get_mut(&id) else {
+            continue;
+        };
+        crate::profile_zone!("material_graph::compile");
+        let compiled = match compile_material_graph(&entry.def) {
+            Ok(compiled) => compiled,
+            Err(e) => {
+                warn!("Material graph '{}' failed to compile: {}", id, e);
+                entry.error = Some(e);
+                continue;
+            }
+        };
+        entry.error = None;
+        if compiled.wgsl != entry.wgsl {
+            shaders.insert(
+                &entry.shader,
+                Shader::from_wgsl(compiled.wgsl.clone(), format!("material_graph/{}.wgsl", id)),
+            );
+            entry.wgsl = compiled.wgsl;
+        }
+        if let Some(material) = materials.get_mut(&entry.material) {
+            for slot in 0..MAX_GRAPH_TEXTURES {
+                *material.texture_slot(slot) = compiled.textures.get(slot).map(|path| asset_server.load(path.clone()));
+            }
+        }
+        debug!("Compiled material graph '{}' ({} textures)", id, compiled.textures.len());
+
+        if renderer.as_ref().is_some_and(|renderer| renderer.kind() == RendererChoice::Atom) {
+            write_atom_material(&config, &entry.def);
+        }
+    }
+}
+
+fn write_atom_material(config: &MaterialGraphConfig, def: &MaterialGraphDef) {
+    let path = config.atom_directory.join(format!("{}.material", def.id));
+    let result = std::fs::create_dir_all(&config.atom_directory)
+        .map_err(|e| e.to_string())
+        .and_then(|_| serde_json::to_string_pretty(&atom_material_definition(def)).map_err(|e| e.to_string()))
+        .and_then(|text| std::fs::write(&path, text).map_err(|e| e.to_string()));
+    if let Err(e) = result {
+        warn!("Failed to write Atom material {:?}: {}", path, e);
+    }
+}
+
+/// Keeps a sphere with the previewed graph floating in front of the camera.
+fn update_material_preview(
+    mut commands: Commands,
+    config: Res<MaterialGraphConfig>,
+    library: Res<MaterialGraphLibrary>,
+    mut preview: ResMut<MaterialGraphPreview>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+    mut previews: Query<(&mut Transform, &mut MeshMaterial3d<GraphMaterial>), With<MaterialPreviewMesh>>,
+) {
+    let material = preview
+        .graph
+        .as_ref()
+        .and_then(|id| library.graphs.get(id))
+        .map(|entry| entry.material.clone());
+    let Some(material) = material else {
+        if let Some(entity) = preview.entity.take() {
+            commands.entity(entity).despawn_recursive();
+        }
+        return;
+    };
+    let Some((_, camera_transform)) = cameras.iter().find(|(c, _)| c.is_active) else {
+        return;
+    };
+    let position = camera_transform.translation() + camera_transform.forward() * config.preview_distance;
+
+    match preview.entity.and_then(|e| previews.get_mut(e).ok()) {
+        Some((mut transform, mut current)) => {
+            transform.translation = position;
+            if current.0 != material {
+                current.0 = material;
+            }
+        }
+        None => {
+            let entity = commands
+                .spawn((
+                    Mesh3d(meshes.add(Sphere::new(0.75).mesh().uv(48, 24))),
+                    MeshMaterial3d(material),
+                    Transform::from_translation(position),
+                    MaterialPreviewMesh,
+                    Name::new("Material Graph Preview"),
+                ))
+                .id();
+            preview.entity = Some(entity);
+        }
+    }
+}
+
+fn apply_material_graphs(
+    mut commands: Commands,
+    library: Res<MaterialGraphLibrary>,
+    mut apply_events: EventReader<ApplyMaterialGraphEvent>,
+    mut revert_events: EventReader<RevertMaterialGraphEvent>,
+    children: Query<&Children>,
+    meshes: Query<(Option<&MeshMaterial3d<StandardMaterial>>, Option<&GraphMaterialOverride>), With<Mesh3d>>,
+) {
+    let with_descendants = |roots: &[Entity]| {
+        let mut all = Vec::new();
+        for root in roots {
+            all.push(*root);
+            all.extend(children.iter_descendants(*root));
+        }
+        all
+    };
+
+    for event in apply_events.read() {
+        let Some(entry) = library.graphs.get(&event.graph) else {
+            warn!("Cannot apply unknown material graph '{}'", event.graph);
+            continue;
+        };
+        let mut count = 0;
+        for entity in with_descendants(&event.entities) {
+            let Ok((standard, existing)) = meshes.get(entity) else {
+                continue;
+            };
+            // Re-applying keeps the material from before the first graph
+            let original = existing.map_or_else(|| standard.map(|m| m.0.clone()), |o| o.original.clone());
+            commands
+                .entity(entity)
+                .remove::<MeshMaterial3d<StandardMaterial>>()
+                .insert((
+                    MeshMaterial3d(entry.material.clone()),
+                    GraphMaterialOverride {
+                        graph: event.graph.clone(),
+                        original,
+                    },
+                ));
+            count += 1;
+        }
+        info!("Applied material graph '{}' to {} meshes", event.graph, count);
+    }
+
+    for event in revert_events.read() {
+        for entity in with_descendants(&event.entities) {
+            let Ok((_, Some(applied))) = meshes.get(entity) else {
+                continue;
+            };
+            let mut entity = commands.entity(entity);
+            entity.remove::<(MeshMaterial3d<GraphMaterial>, GraphMaterialOverride)>();
+            if let Some(original) = &applied.original {
+                entity.insert(MeshMaterial3d(original.clone()));
+            }
+        }
+    }
+}
+
+fn save_material_graphs(
+    mut events: EventReader<SaveMaterialGraphEvent>,
+    config: Res<MaterialGraphConfig>,
+    mut library: ResMut<MaterialGraphLibrary>,
+) {
+    for event in events.read() {
+        let Some(entry) = library.graphs.get_mut(&event.graph) else {
+            continue;
+        };
+        if let Err(e) = std::fs::create_dir_all(&config.graph_directory) {
+            error!("Failed to create material graph directory {:?}: {}", config.graph_directory, e);
+            return;
+        }
+        match toml::to_string_pretty(&entry.def) {
+            Ok(text) => match std::fs::write(&entry.source, text) {
+                Ok(()) => {
+                    // Our own write shouldn't trigger a reload
+                    entry.modified = modified_time(&entry.source);
+                    info!("Saved {:?}", entry.source);
+                }
+                Err(e) => error!("Failed to write {:?}: {}", entry.source, e),
+            },
+            Err(e) => error!("Failed to serialize material graph '{}': {}", event.graph, e),
+        }
+    }
+}
@@ -0,0 +1,106 @@
+//! Level-authoring tools: prefabs and the editor-side workflows built on them.
+
+use bevy::prelude::*;
+
+use crate::input_map::{Action, ActionState, InputContext, InputContexts};
+
+pub mod history;
+#[cfg(feature = "inspector")]
+pub mod inspector;
+pub mod material_graph;
+pub mod narrative;
+#[cfg(feature = "inspector")]
+pub mod node_canvas;
+pub mod prefab;
+pub mod spawn_painting;
+pub mod terrain_sculpt;
+pub mod transform_tools;
+
+pub use history::*;
+#[cfg(feature = "inspector")]
+pub use inspector::*;
+pub use material_graph::*;
+pub use narrative::*;
+pub use prefab::*;
+pub use spawn_painting::*;
+pub use terrain_sculpt::*;
+pub use transform_tools::*;
+
+/// Which tool owns the mouse in the viewport. F5 cycles through them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AuthoringTool {
+    #[default]
+    Transform,
+    TerrainBrush,
+    SpawnPaint,
+}
+
+impl AuthoringTool {
+    pub fn next(self) -> Self {
+        match self {
+            AuthoringTool::Transform => AuthoringTool::TerrainBrush,
+            AuthoringTool::TerrainBrush => AuthoringTool::SpawnPaint,
+            AuthoringTool::SpawnPaint => AuthoringTool::Transform,
+        }
+    }
+}
+
+/// Whether authoring tools (shortcuts, panels, gizmos) are live. Toggled with F10.
+#[derive(Resource, Debug, Default)]
+pub struct AuthoringMode {
+    pub active: bool,
+    pub tool: AuthoringTool,
+}
+
+impl AuthoringMode {
+    pub fn using(&self, tool: AuthoringTool) -> bool {
+        self.active && self.tool == tool
+    }
+}
+
+pub struct AuthoringPlugin;
+
+impl Plugin for AuthoringPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AuthoringMode>()
+            .add_plugins((PrefabPlugin, EditHistoryPlugin, TransformToolsPlugin, TerrainSculptPlugin, SpawnPaintingPlugin))
+            .add_plugins((MaterialGraphPlugin, NarrativeEditorPlugin))
+            .add_systems(Update, (toggle_authoring_mode, save_scene_shortcut));
+
+        #[cfg(feature = "inspector")]
+        app.add_plugins(InspectorPlugin);
+    }
+}
+
+fn toggle_authoring_mode(
+    actions: Res<ActionState>,
+    mut contexts: ResMut<InputContexts>,
+    mut mode: ResMut<AuthoringMode>,
+) {
+    if actions.just_pressed(Action::ToggleAuthoring) {
+        mode.active = !mode.active;
+        contexts.set(InputContext::Editor, mode.active);
+        info!("Authoring mode {}", if mode.active { "enabled" } else { "disabled" });
+    }
+    if mode.active && actions.just_pressed(Action::NextTool) {
+        mode.tool = mode.tool.next();
+        info!("Authoring tool: {:?}", mode.tool);
+    }
+}
+
+/// Ctrl+S (by default) writes everything the editor persists with the level.
+fn save_scene_shortcut(
+    actions: Res<ActionState>,
+    mode: Res<AuthoringMode>,
+    mut prefabs: EventWriter<SavePrefabsEvent>,
+    mut terrain: EventWriter<SaveTerrainEditsEvent>,
+    mut spawns: EventWriter<SaveSpawnContentEvent>,
+    mut narrative: EventWriter<ExportNarrativeEvent>,
+) {
+    if mode.active && actions.just_pressed(Action::Save) {
+        prefabs.send(SavePrefabsEvent);
+        terrain.send(SaveTerrainEditsEvent);
+        spawns.send(SaveSpawnContentEvent);
+        narrative.send(ExportNarrativeEvent { force: false });
+    }
+}
@@ -0,0 +1,697 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts, EguiPlugin};
+
+use super::super::node_canvas::{node_canvas, CanvasAction, CanvasLink, CanvasNode, NodeCanvasState};
+use super::super::AuthoringMode;
+use super::{
+    DialogChoiceDef, DialogNodeDef, DialogTreeDef, ExportNarrativeEvent, ItemStack, NarrativeConfig, NarrativeGraph,
+    NarrativeIssueSeverity, NarrativeKind, NarrativeLibrary, QuestBranchOption, QuestDef, QuestNodeDef, QuestNodeKind,
+    QuestObjective,
+};
+
+#[derive(Resource, Debug)]
+pub struct NarrativeEditorState {
+    pub open: bool,
+    pub kind: NarrativeKind,
+    pub graph: Option<String>,
+    pub selected_node: Option<String>,
+    canvas: NodeCanvasState,
+    new_graph_name: String,
+}
+
+impl Default for NarrativeEditorState {
+    fn default() -> Self {
+        Self {
+            open: false,
+            kind: NarrativeKind::Quest,
+            graph: None,
+            selected_node: None,
+            canvas: NodeCanvasState::default(),
+            new_graph_name: String::new(),
+        }
+    }
+}
+
+pub struct NarrativeGraphEditorPlugin;
+
+impl Plugin for NarrativeGraphEditorPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugins(EguiPlugin);
+        }
+        app.init_resource::<NarrativeEditorState>().add_systems(
+            Update,
+            (
+                toggle_narrative_editor,
+                narrative_editor_ui.run_if(|state: Res<NarrativeEditorState>| state.open),
+            )
+                .chain(),
+        );
+    }
+}
+
+/// F11 opens the quest and dialog editor while authoring.
+fn toggle_narrative_editor(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mode: Res<AuthoringMode>,
+    mut state: ResMut<NarrativeEditorState>,
+) {
+    if mode.active && keyboard.just_pressed(KeyCode::F11) {
+        state.open = !state.open;
+    }
+    if !mode.active && state.open {
+        state.open = false;
+    }
+}
+
+fn unused_id(prefix: &str, taken: &[&str]) -> String {
+    (1..)
+        .map(|n| format!("{}_{}", prefix, n))
+        .find(|id| !taken.contains(&id.as_str()))
+        .unwrap_or_default()
+}
+
+fn short(text: &str) -> String {
+    if text.chars().count() > 18 {
+        format!("{}…", text.chars().take(17).collect::<String>())
+    } else {
+        text.to_string()
+    }
+}
+
+fn optional_text(ui: &mut egui::Ui, label: &str, value: &mut Option<String>) -> bool {
+    let mut text = value.clone().unwrap_or_default();
+    let changed = ui
+        .horizontal(|ui| {
+            ui.label(label);
+            ui.text_edit_singleline(&mut text).changed()
+        })
+        .inner;
+    if changed {
+        *value = Some(text.trim().to_string()).filter(|t| !t.is_empty());
+    }
+    changed
+}
+
+fn text_row(ui: &mut egui::Ui, label: &str, value: &mut String) -> bool {
+    ui.horizontal(|ui| {
+        ui.label(label);
+        ui.text_edit_singleline(value).changed()
+    })
+    .inner
+}
+
+fn new_quest_nodes() -> Vec<QuestNodeKind> {
+    let objective = |objective| QuestNodeKind::Objective {
+        objective,
+        text: String::new(),
+        next: None,
+    };
+    vec![
+        objective(QuestObjective::Kill {
+            target: String::new(),
+            count: 1,
+        }),
+        objective(QuestObjective::Collect {
+            item: String::new(),
+            count: 1,
+        }),
+        objective(QuestObjective::Talk { npc: String::new() }),
+        objective(QuestObjective::Reach {
+            position: [0.0; 3],
+            radius: 10.0,
+        }),
+        QuestNodeKind::Branch {
+            options: vec![QuestBranchOption {
+                label: "Option".into(),
+                next: None,
+                requires_item: None,
+            }],
+        },
+        QuestNodeKind::Reward {
+            xp: 0,
+            gold: 0,
+            items: Vec::new(),
+            next: None,
+        },
+        QuestNodeKind::Complete,
+    ]
+}
+
+fn quest_node_ui(ui: &mut egui::Ui, node: &mut QuestNodeDef) -> bool {
+    let mut changed = false;
+    match &mut node.kind {
+        QuestNodeKind::Objective { objective, text, .. } => {
+            changed |= text_row(ui, "Text", text);
+            match objective {
+                QuestObjective::Kill { target, count } => {
+                    changed |= text_row(ui, "Target", target);
+                    changed |= ui.add(egui::DragValue::new(count).range(1..=999).prefix("Count: ")).changed();
+                }
+                QuestObjective::Collect { item, count } => {
+                    changed |= text_row(ui, "Item", item);
+                    changed |= ui.add(egui::DragValue::new(count).range(1..=999).prefix("Count: ")).changed();
+                }
+                QuestObjective::Talk { npc } => changed |= text_row(ui, "NPC", npc),
+                QuestObjective::Reach { position, radius } => {
+                    ui.horizontal(|ui| {
+                        ui.label("Position");
+                        for axis in position.iter_mut() {
+                            changed |= ui.add(egui::DragValue::new(axis).speed(0.5)).changed();
+                        }
+                    });
+                    changed |= ui.add(egui::DragValue::new(radius).speed(0.5).prefix("Radius: ")).changed();
+                }
+            }
+        }
+        QuestNodeKind::Branch { options } => {
+            let mut remove = None;
+            for (index, option) in options.iter_mut().enumerate() {
+                ui.separator();
+                changed |= text_row(ui, "Label", &mut option.label);
+                changed |= optional_text(ui, "Requires item", &mut option.requires_item);
+                if ui.small_button("Remove option").clicked() {
+                    remove = Some(index);
+                }
+            }
+            if let Some(index) = remove {
+                options.remove(index);
+                changed = true;
+            }
+            if ui.button("Add option").clicked() {
+                options.push(QuestBranchOption {
+                    label: format!("Option {}", options.len() + 1),
+                    next: None,
+                    requires_item: None,
+                });
+                changed = true;
+            }
+        }
+        QuestNodeKind::Reward { xp, gold, items, .. } => {
+            changed |= ui.add(egui::DragValue::new(xp).prefix("XP: ")).changed();
+            changed |= ui.add(egui::DragValue::new(gold).prefix("Gold: ")).changed();
+            let mut remove = None;
+            for (index, stack) in items.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    changed |= ui.text_edit_singleline(&mut stack.item).changed();
+                    changed |= ui.add(egui::DragValue::new(&mut stack.count).range(1..=999)).changed();
+                    if ui.small_button("x").clicked() {
+                        remove = Some(index);
+                    }
+                });
+            }
+            if let Some(index) = remove {
+                items.remove(index);
+                changed = true;
+            }
+            if ui.button("Add item").clicked() {
+                items.push(ItemStack {
+                    item: String::new(),
+                    count: 1,
+                });
+                changed = true;
+            }
+        }
+        QuestNodeKind::Complete => {
+            ui.label("Ends the quest");
+        }
+    }
+    changed
+}
+
+fn dialog_node_ui(ui: &mut egui::Ui, node: &mut DialogNodeDef) -> bool {
+    let mut changed = text_row(ui, "Speaker", &mut node.speaker);
+    ui.label("Text");
+    changed |= ui.text_edit_multiline(&mut node.text).changed();
+    changed |= optional_text(ui, "Voice line", &mut node.voice_line);
+
+    let mut remove = None;
+    for (index, choice) in node.choices.iter_mut().enumerate() {
+        ui.separator();
+        changed |= text_row(ui, "Choice", &mut choice.text);
+        changed |= optional_text(ui, "Accept quest", &mut choice.accept_quest);
+        changed |= optional_text(ui, "Requires item", &mut choice.requires_item);
+        if ui.small_button("Remove choice").clicked() {
+            remove = Some(index);
+        }
+    }
+    if let Some(index) = remove {
+        node.choices.remove(index);
+        changed = true;
+    }
+    if ui.button("Add choice").clicked() {
+        node.choices.push(DialogChoiceDef {
+            text: format!("Choice {}", node.choices.len() + 1),
+            ..default()
+        });
+        changed = true;
+    }
+    changed
+}
+
+fn quest_canvas(def: &QuestDef) -> (Vec<CanvasNode>, Vec<CanvasLink>) {
+    let nodes = def
+        .nodes
+        .iter()
+        .map(|node| CanvasNode {
+            id: node.id.clone(),
+            title: format!("{}{}  {}", if node.id == def.start { "▶ " } else { "" }, node.kind.label(), node.id),
+            position: node.position,
+            inputs: vec!["in".into()],
+            outputs: match &node.kind {
+                QuestNodeKind::Branch { options } => options.iter().map(|o| short(&o.label)).collect(),
+                QuestNodeKind::Objective { objective, .. } => vec![format!("{} done", objective.label())],
+                QuestNodeKind::Reward { .. } => vec!["next".into()],
+                QuestNodeKind::Complete => Vec::new(),
+            },
+            flagged: false,
+        })
+        .collect();
+    let links = def
+        .nodes
+        .iter()
+        .flat_map(|node| {
+            node.kind
+                .links()
+                .into_iter()
+                .enumerate()
+                .filter_map(|(output, target)| {
+                    target.map(|to| CanvasLink {
+                        from: node.id.clone(),
+                        output,
+                        to: to.to_string(),
+                        input: 0,
+                    })
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    (nodes, links)
+}
+
+fn dialog_canvas(def: &DialogTreeDef) -> (Vec<CanvasNode>, Vec<CanvasLink>) {
+    let nodes = def
+        .nodes
+        .iter()
+        .map(|node| CanvasNode {
+            id: node.id.clone(),
+            title: format!("{}{}  {}", if node.id == def.start { "▶ " } else { "" }, node.speaker, node.id),
+            position: node.position,
+            inputs: vec![short(&node.text)],
+            outputs: if node.choices.is_empty() {
+                vec!["next".into()]
+            } else {
+                node.choices.iter().map(|c| short(&c.text)).collect()
+            },
+            flagged: false,
+        })
+        .collect();
+    let links = def
+        .nodes
+        .iter()
+        .flat_map(|node| {
+            node.links()
+                .into_iter()
+                .enumerate()
+                .filter_map(|(output, target)| {
+                    target.map(|to| CanvasLink {
+                        from: node.id.clone(),
+                        output,
+                        to: to.to_string(),
+                        input: 0,
+                    })
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    (nodes, links)
+}
+
+/// What the canvas context menu asked to add.
+enum NewNode {
+    Quest(QuestNodeKind),
+    DialogLine,
+}
+
+fn narrative_editor_ui(
+    mut contexts: EguiContexts,
+    mut state: ResMut<NarrativeEditorState>,
+    mut library: ResMut<NarrativeLibrary>,
+    config: Res<NarrativeConfig>,
+    mut export: EventWriter<ExportNarrativeEvent>,
+) {
+    let ctx = contexts.ctx_mut().clone();
+    let state = &mut *state;
+    let mut open = state.open;
+    let mut changed = false;
+
+    egui::Window::new("Quests & Dialog")
+        .open(&mut open)
+        .default_size([960.0, 580.0])
+        .show(&ctx, |ui| {
+            ui.horizontal(|ui| {
+                for (kind, label) in [(NarrativeKind::Quest, "Quests"), (NarrativeKind::Dialog, "Dialog")] {
+                    if ui.selectable_label(state.kind == kind, label).clicked() && state.kind != kind {
+                        state.kind = kind;
+                        state.graph = None;
+                        state.selected_node = None;
+                    }
+                }
+                ui.separator();
+
+                egui::ComboBox::from_id_salt("narrative_graph_picker")
+                    .selected_text(state.graph.clone().unwrap_or_else(|| "Select graph".into()))
+                    .show_ui(ui, |ui| {
+                        for ((kind, id), entry) in &library.graphs {
+                            if *kind != state.kind {
+                                continue;
+                            }
+                            let label = if entry.dirty { format!("{} *", id) } else { id.clone() };
+                            if ui.selectable_label(state.graph.as_ref() == Some(id), label).clicked() {
+                                state.graph = Some(id.clone());
+                                state.selected_node = None;
+                            }
+                        }
+                    });
+
+                ui.text_edit_singleline(&mut state.new_graph_name);
+                let name = state.new_graph_name.trim().to_string();
+                if ui.button("New").clicked() && !name.is_empty() && !library.graphs.contains_key(&(state.kind, name.clone())) {
+                    let (graph, source) = match state.kind {
+                        NarrativeKind::Quest => (
+                            NarrativeGraph::Quest(QuestDef {
+                                id: name.clone(),
+                                title: name.clone(),
+                                description: String::new(),
+                                level: 1,
+                                start: "start".into(),
+                                nodes: vec![
+                                    QuestNodeDef {
+                                        id: "start".into(),
+                                        kind: QuestNodeKind::Objective {
+                                            objective: QuestObjective::Talk { npc: String::new() },
+                                            text: String::new(),
+                                            next: Some("complete".into()),
+                                        },
+                                        position: [40.0, 80.0],
+                                    },
+                                    QuestNodeDef {
+                                        id: "complete".into(),
+                                        kind: QuestNodeKind::Complete,
+                                        position: [320.0, 80.0],
+                                    },
+                                ],
+                            }),
+                            config.quest_directory.join(format!("{}.toml", name)),
+                        ),
+                        NarrativeKind::Dialog => (
+                            NarrativeGraph::Dialog(DialogTreeDef {
+                                id: name.clone(),
+                                start: "greeting".into(),
+                                nodes: vec![DialogNodeDef {
+                                    id: "greeting".into(),
+                                    text: "Hello there.".into(),
+                                    position: [40.0, 80.0],
+                                    ..default()
+                                }],
+                            }),
+                            config.dialog_directory.join(format!("{}.json", name)),
+                        ),
+                    };
+                    library.insert(graph, source);
+                    library.mark_changed(&(state.kind, name.clone()));
+                    state.graph = Some(name);
+                    state.selected_node = None;
+                    state.new_graph_name.clear();
+                }
+
+                ui.separator();
+                let dirty: Vec<_> = library.graphs.values().filter(|e| e.dirty).collect();
+                let blocked = dirty.iter().filter(|e| e.error_count() > 0).count();
+                if ui.add_enabled(!dirty.is_empty() && blocked == 0, egui::Button::new("Export")).clicked() {
+                    export.send(ExportNarrativeEvent { force: false });
+                }
+                if blocked > 0 {
+                    ui.colored_label(egui::Color32::from_rgb(230, 90, 80), format!("{} graph(s) have errors", blocked));
+                    if ui.small_button("Export anyway").clicked() {
+                        export.send(ExportNarrativeEvent { force: true });
+                    }
+                }
+            });
+
+            let Some(key) = state.graph.clone().map(|id| (state.kind, id)) else {
+                ui.label("Pick or create a graph. Right-click the canvas to add nodes, drag outputs onto nodes to link them.");
+                return;
+            };
+            let Some(entry) = library.graphs.get_mut(&key) else {
+                state.graph = None;
+                return;
+            };
+
+            let (mut nodes, links) = match &entry.graph {
+                NarrativeGraph::Quest(def) => quest_canvas(def),
+                NarrativeGraph::Dialog(def) => dialog_canvas(def),
+            };
+            for node in nodes.iter_mut() {
+                node.flagged = entry
+                    .issues
+                    .iter()
+                    .any(|i| i.severity == NarrativeIssueSeverity::Error && i.node.as_ref() == Some(&node.id));
+            }
+
+            ui.columns(2, |columns| {
+                let side = &mut columns[1];
+                side.set_max_width(260.0);
+                egui::ScrollArea::vertical().max_height(500.0).show(side, |ui| {
+                    match &mut entry.graph {
+                        NarrativeGraph::Quest(def) => {
+                            changed |= text_row(ui, "Title", &mut def.title);
+                            changed |= ui.add(egui::DragValue::new(&mut def.level).range(1..=100).prefix("Level: ")).changed();
+                            ui.label("Description");
+                            changed |= ui.text_edit_multiline(&mut def.description).changed();
+                        }
+                        NarrativeGraph::Dialog(def) => {
+                            ui.label(format!("Start: {}", def.start));
+                        }
+                    }
+                    ui.separator();
+
+                    let selected = state.selected_node.clone();
+                    let mut delete = false;
+                    let mut make_start = false;
+                    match (&mut entry.graph, selected.as_deref()) {
+                        (NarrativeGraph::Quest(def), Some(id)) => {
+                            if let Some(node) = def.nodes.iter_mut().find(|n| n.id == id) {
+                                ui.strong(format!("{} ({})", node.id, node.kind.label()));
+                                changed |= quest_node_ui(ui, node);
+                                make_start = def.start != id && ui.button("Make start node").clicked();
+                                delete = ui.button("Delete node").clicked();
+                            }
+                        }
+                        (NarrativeGraph::Dialog(def), Some(id)) => {
+                            if let Some(node) = def.nodes.iter_mut().find(|n| n.id == id) {
+                                ui.strong(node.id.clone());
+                                changed |= dialog_node_ui(ui, node);
+                                make_start = def.start != id && ui.button("Make start node").clicked();
+                                delete = ui.button("Delete node").clicked();
+                            }
+                        }
+                        (_, None) => {
+                            ui.label("Click a node to edit it");
+                        }
+                    }
+                    if let Some(id) = selected.filter(|_| make_start || delete) {
+                        match &mut entry.graph {
+                            NarrativeGraph::Quest(def) if make_start => def.start = id,
+                            NarrativeGraph::Dialog(def) if make_start => def.start = id,
+                            NarrativeGraph::Quest(def) => {
+                                def.nodes.retain(|n| n.id != id);
+                                for node in def.nodes.iter_mut() {
+                                    for port in 0..node.kind.links().len() {
+                                        if let Some(link) = node.kind.link_mut(port).filter(|l| l.as_deref() == Some(&id)) {
+                                            *link = None;
+                                        }
+                                    }
+                                }
+                                state.selected_node = None;
+                            }
+                            NarrativeGraph::Dialog(def) => {
+                                def.nodes.retain(|n| n.id != id);
+                                for node in def.nodes.iter_mut() {
+                                    for port in 0..node.links().len() {
+                                        if let Some(link) = node.link_mut(port).filter(|l| l.as_deref() == Some(&id)) {
+                                            *link = None;
+                                        }
+                                    }
+                                }
+                                state.selected_node = None;
+                            }
+                        }
+                        changed = true;
+                    }
+
+                    ui.separator();
+                    if entry.issues.is_empty() {
+                        ui.label("No issues");
+                    }
+                    for issue in &entry.issues {
+                        let color = match issue.severity {
+                            NarrativeIssueSeverity::Error => egui::Color32::from_rgb(230, 90, 80),
+                            NarrativeIssueSeverity::Warning => egui::Color32::from_rgb(230, 190, 80),
+                        };
+                        let label = ui.add(egui::Label::new(egui::RichText::new(&issue.message).color(color)).sense(egui::Sense::click()));
+                        if label.clicked() {
+                            if let Some(node) = &issue.node {
+                                state.selected_node = Some(node.clone());
+                            }
+                        }
+                    }
+                });
+
+                let mut added = None;
+                let kind = state.kind;
+                let actions = node_canvas(
+                    &mut columns[0],
+                    &mut state.canvas,
+                    &nodes,
+                    &links,
+                    state.selected_node.as_deref(),
+                    500.0,
+                    |ui, at| match kind {
+                        NarrativeKind::Quest => {
+                            for node in new_quest_nodes() {
+                                let label = match &node {
+                                    QuestNodeKind::Objective { objective, .. } => format!("Objective: {}", objective.label()),
+                                    other => other.label().to_string(),
+                                };
+                                if ui.button(label).clicked() {
+                                    added = Some((NewNode::Quest(node), at));
+                                    ui.close_menu();
+                                }
+                            }
+                        }
+                        NarrativeKind::Dialog => {
+                            if ui.button("Line").clicked() {
+                                added = Some((NewNode::DialogLine, at));
+                                ui.close_menu();
+                            }
+                        }
+                    },
+                );
+
+                if let Some((node, position)) = added {
+                    let taken: Vec<String> = nodes.iter().map(|n| n.id.clone()).collect();
+                    let taken: Vec<&str> = taken.iter().map(String::as_str).collect();
+                    let id = match (&mut entry.graph, node) {
+                        (NarrativeGraph::Quest(def), NewNode::Quest(kind)) => {
+                            let id = unused_id(&kind.label().to_lowercase(), &taken);
+                            def.nodes.push(QuestNodeDef {
+                                id: id.clone(),
+                                kind,
+                                position,
+                            });
+                            id
+                        }
+                        (NarrativeGraph::Dialog(def), NewNode::DialogLine) => {
+                            let id = unused_id("line", &taken);
+                            def.nodes.push(DialogNodeDef {
+                                id: id.clone(),
+                                position,
+                                ..default()
+                            });
+                            id
+                        }
+                        _ => return,
+                    };
+                    state.selected_node = Some(id);
+                    changed = true;
+                }
+
+                for action in actions {
+                    changed |= apply_canvas_action(&mut entry.graph, &mut state.selected_node, action);
+                }
+            });
+        });
+
+    state.open = open;
+    if changed {
+        if let Some(id) = state.graph.clone() {
+            library.mark_changed(&(state.kind, id));
+        }
+    }
+}
+
+/// Applies a canvas edit to the graph. Returns true if the graph changed.
+fn apply_canvas_action(graph: &mut NarrativeGraph, selected: &mut Option<String>, action: CanvasAction) -> bool {
+    let set_link = |graph: &mut NarrativeGraph, node: &str, port: usize, target: Option<String>| {
+        let link = match graph {
+            NarrativeGraph::Quest(def) => def
+                .nodes
+                .iter_mut()
+                .find(|n| n.id == node)
+                .and_then(|n| n.kind.link_mut(port)),
+            NarrativeGraph::Dialog(def) => def.nodes.iter_mut().find(|n| n.id == node).and_then(|n| n.link_mut(port)),
+        };
+        match link {
+            Some(link) if *link != target => {
+                *link = target;
+                true
+            }
+            _ => false,
+        }
+    };
+
+    match action {
+        CanvasAction::Select(id) => {
+            *selected = Some(id);
+            false
+        }
+        CanvasAction::Move { node, delta } => {
+            let position = match graph {
+                NarrativeGraph::Quest(def) => def.nodes.iter_mut().find(|n| n.id == node).map(|n| &mut n.position),
+                NarrativeGraph::Dialog(def) => def.nodes.iter_mut().find(|n| n.id == node).map(|n| &mut n.position),
+            };
+            if let Some(position) = position {
+                position[0] += delta[0];
+                position[1] += delta[1];
+            }
+            true
+        }
+        CanvasAction::Connect { from, output, to, .. } => set_link(graph, &from, output, Some(to)),
+        CanvasAction::DisconnectOutput { node, output } => set_link(graph, &node, output, None),
+        CanvasAction::DisconnectInput { node, .. } => {
+            let sources: Vec<(String, usize)> = match graph {
+                NarrativeGraph::Quest(def) => def
+                    .nodes
+                    .iter()
+                    .flat_map(|n| {
+                        n.kind
+                            .links()
+                            .into_iter()
+                            .enumerate()
+                            .filter(|(_, l)| *l == Some(node.as_str()))
+                            .map(|(port, _)| (n.id.clone(), port))
+                            .collect::<Vec<_>>()
+                    })
+                    .collect(),
+                NarrativeGraph::Dialog(def) => def
+                    .nodes
+                    .iter()
+                    .flat_map(|n| {
+                        n.links()
+                            .into_iter()
+                            .enumerate()
+                            .filter(|(_, l)| *l == Some(node.as_str()))
+                            .map(|(port, _)| (n.id.clone(), port))
+                            .collect::<Vec<_>>()
+                    })
+                    .collect(),
+            };
+            let mut changed = false;
+            for (source, port) in sources {
+                changed |= set_link(graph, &source, port, None);
+            }
+            changed
+        }
+    }
+}
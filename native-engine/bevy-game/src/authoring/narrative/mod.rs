@@ -0,0 +1,704 @@
+//! Quest and dialog graphs: the content formats, loading, validation and
+//! export. Quests are `content/quests/*.toml`, dialog trees are
+//! `content/dialog/*.json`; both are lists of nodes linked by id from a start node.
+
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::content_packs::ContentPacks;
+use crate::hot_reload::{ContentChangedEvent, ContentReloadedEvent};
+
+#[cfg(feature = "inspector")]
+mod editor;
+#[cfg(feature = "inspector")]
+pub use editor::*;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ItemStack {
+    pub item: String,
+    #[serde(default = "default_count")]
+    pub count: u32,
+}
+
+fn default_count() -> u32 {
+    1
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum QuestObjective {
+    Kill { target: String, count: u32 },
+    Collect { item: String, count: u32 },
+    Talk { npc: String },
+    Reach { position: [f32; 3], radius: f32 },
+}
+
+impl QuestObjective {
+    pub fn label(&self) -> &'static str {
+        match self {
+            QuestObjective::Kill { .. } => "Kill",
+            QuestObjective::Collect { .. } => "Collect",
+            QuestObjective::Talk { .. } => "Talk",
+            QuestObjective::Reach { .. } => "Reach",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct QuestBranchOption {
+    pub label: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next: Option<String>,
+    /// Only offered while the player carries this item.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requires_item: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum QuestNodeKind {
+    Objective {
+        objective: QuestObjective,
+        #[serde(default)]
+        text: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        next: Option<String>,
+    },
+    Branch {
+        #[serde(default)]
+        options: Vec<QuestBranchOption>,
+    },
+    Reward {
+        #[serde(default)]
+        xp: u32,
+        #[serde(default)]
+        gold: u32,
+        #[serde(default)]
+        items: Vec<ItemStack>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        next: Option<String>,
+    },
+    Complete,
+}
+
+impl QuestNodeKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            QuestNodeKind::Objective { .. } => "Objective",
+            QuestNodeKind::Branch { .. } => "Branch",
+            QuestNodeKind::Reward { .. } => "Reward",
+            QuestNodeKind::Complete => "Complete",
+        }
+    }
+
+    /// Outgoing links in port order; `None` is an unconnected port.
+    pub fn links(&self) -> Vec<Option<&str>> {
+        match self {
+            QuestNodeKind::Objective { next, .. } | QuestNodeKind::Reward { next, .. } => vec![next.as_deref()],
+            QuestNodeKind::Branch { options } => options.iter().map(|o| o.next.as_deref()).collect(),
+            QuestNodeKind::Complete => Vec::new(),
+        }
+    }
+
+    pub fn link_mut(&mut self, port: usize) -> Option<&mut Option<String>> {
+        match self {
+            QuestNodeKind::Objective { next, .. } | QuestNodeKind::Reward { next, .. } => (port == 0).then_some(next),
+            QuestNodeKind::Branch { options } => options.get_mut(port).map(|o| &mut o.next),
+            QuestNodeKind::Complete => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct QuestNodeDef {
+    pub id: String,
+    #[serde(flatten)]
+    pub kind: QuestNodeKind,
+    /// Editor canvas position.
+    #[serde(default)]
+    pub position: [f32; 2],
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct QuestDef {
+    pub id: String,
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub level: u32,
+    pub start: String,
+    #[serde(default, rename = "node")]
+    pub nodes: Vec<QuestNodeDef>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct DialogChoiceDef {
+    pub text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accept_quest: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requires_item: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct DialogNodeDef {
+    pub id: String,
+    #[serde(default)]
+    pub speaker: String,
+    pub text: String,
+    /// Voice-over line played with the text, see `PlayDialogVoiceEvent`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub voice_line: Option<String>,
+    #[serde(default)]
+    pub choices: Vec<DialogChoiceDef>,
+    /// Followed when there are no choices; a node with neither ends the conversation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next: Option<String>,
+    #[serde(default)]
+    pub position: [f32; 2],
+}
+
+impl DialogNodeDef {
+    pub fn links(&self) -> Vec<Option<&str>> {
+        if self.choices.is_empty() {
+            vec![self.next.as_deref()]
+        } else {
+            self.choices.iter().map(|c| c.next.as_deref()).collect()
+        }
+    }
+
+    pub fn link_mut(&mut self, port: usize) -> Option<&mut Option<String>> {
+        if self.choices.is_empty() {
+            (port == 0).then_some(&mut self.next)
+        } else {
+            self.choices.get_mut(port).map(|c| &mut c.next)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DialogTreeDef {
+    pub id: String,
+    pub start: String,
+    #[serde(default)]
+    pub nodes: Vec<DialogNodeDef>,
+}
+
+/// Which kind of graph a library entry holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum NarrativeKind {
+    Quest,
+    Dialog,
+}
+
+#[derive(Debug, Clone)]
+pub enum NarrativeGraph {
+    Quest(QuestDef),
+    Dialog(DialogTreeDef),
+}
+
+impl NarrativeGraph {
+    pub fn kind(&self) -> NarrativeKind {
+        match self {
+            NarrativeGraph::Quest(_) => NarrativeKind::Quest,
+            NarrativeGraph::Dialog(_) => NarrativeKind::Dialog,
+        }
+    }
+
+    fn serialize(&self) -> Result<String, String> {
+        match self {
+            NarrativeGraph::Quest(def) => toml::to_string_pretty(def).map_err(|e| e.to_string()),
+            NarrativeGraph::Dialog(def) => serde_json::to_string_pretty(def).map_err(|e| e.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NarrativeIssueSeverity {
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone)]
+pub struct NarrativeIssue {
+    pub severity: NarrativeIssueSeverity,
+    /// Node the issue points at, if any.
+    pub node: Option<String>,
+    pub message: String,
+}
+
+#[derive(Debug)]
+pub struct NarrativeEntry {
+    pub graph: NarrativeGraph,
+    pub source: PathBuf,
+    /// Edited since the last load or export.
+    pub dirty: bool,
+    pub issues: Vec<NarrativeIssue>,
+}
+
+impl NarrativeEntry {
+    pub fn error_count(&self) -> usize {
+        self.issues.iter().filter(|i| i.severity == NarrativeIssueSeverity::Error).count()
+    }
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct NarrativeConfig {
+    pub quest_directory: PathBuf,
+    pub dialog_directory: PathBuf,
+    /// Item content scanned for `[[item]] id = ...` to check item references.
+    pub item_directory: PathBuf,
+}
+
+impl Default for NarrativeConfig {
+    fn default() -> Self {
+        let content = PathBuf::from("content");
+        Self {
+            quest_directory: content.join("quests"),
+            dialog_directory: content.join("dialog"),
+            item_directory: content.join("items"),
+        }
+    }
+}
+
+/// Every quest and dialog tree in the content directories, keyed by kind and id.
+#[derive(Resource, Debug, Default)]
+pub struct NarrativeLibrary {
+    pub graphs: BTreeMap<(NarrativeKind, String), NarrativeEntry>,
+    pub known_items: HashSet<String>,
+    /// Bumped on every edit; validation reruns when it changes.
+    pub revision: u64,
+}
+
+impl NarrativeLibrary {
+    pub fn mark_changed(&mut self, key: &(NarrativeKind, String)) {
+        if let Some(entry) = self.graphs.get_mut(key) {
+            entry.dirty = true;
+        }
+        self.revision += 1;
+    }
+
+    pub fn insert(&mut self, graph: NarrativeGraph, source: PathBuf) {
+        let id = match &graph {
+            NarrativeGraph::Quest(def) => def.id.clone(),
+            NarrativeGraph::Dialog(def) => def.id.clone(),
+        };
+        self.graphs.insert(
+            (graph.kind(), id),
+            NarrativeEntry {
+                graph,
+                source,
+                dirty: false,
+                issues: Vec::new(),
+            },
+        );
+        self.revision += 1;
+    }
+}
+
+/// Writes dirty graphs back to their source files. Graphs with validation
+/// errors are skipped unless `force` is set.
+#[derive(Event, Debug, Clone, Copy, Default)]
+pub struct ExportNarrativeEvent {
+    pub force: bool,
+}
+
+pub struct NarrativeEditorPlugin;
+
+impl Plugin for NarrativeEditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<NarrativeConfig>()
+            .init_resource::<ContentPacks>()
+            .init_resource::<NarrativeLibrary>()
+            .add_event::<ExportNarrativeEvent>()
+            .add_event::<ContentChangedEvent>()
+            .add_event::<ContentReloadedEvent>()
+            .add_systems(Startup, load_narrative_content)
+            .add_systems(Update, (reload_narrative_content, validate_narrative, export_narrative).chain());
+
+        #[cfg(feature = "inspector")]
+        app.add_plugins(NarrativeGraphEditorPlugin);
+    }
+}
+
+fn content_files(directory: &Path, extension: &str) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(directory) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().and_then(|e| e.to_str()) == Some(extension))
+        .collect();
+    paths.sort();
+    paths
+}
+
+/// Item ids from `[[item]]` tables in the item content. Other keys are ignored
+/// so this keeps working as the item format grows.
+pub fn load_known_items(directory: &Path) -> HashSet<String> {
+    known_items(&content_files(directory, "toml"))
+}
+
+fn known_items(paths: &[PathBuf]) -> HashSet<String> {
+    let mut items = HashSet::new();
+    for path in paths {
+        let parsed = std::fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| text.parse::<toml::Value>().map_err(|e| e.to_string()));
+        match parsed {
+            Ok(value) => {
+                let ids = value
+                    .get("item")
+                    .and_then(|v| v.as_array())
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|item| item.get("id").and_then(|id| id.as_str()));
+                items.extend(ids.map(str::to_string));
+            }
+            Err(e) => warn!("Invalid item content {:?}: {}", path, e),
+        }
+    }
+    items
+}
+
+fn read_quest(path: &Path) -> Result<NarrativeGraph, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    toml::from_str::<QuestDef>(&text).map(NarrativeGraph::Quest).map_err(|e| e.to_string())
+}
+
+fn read_dialog(path: &Path) -> Result<NarrativeGraph, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    serde_json::from_str::<DialogTreeDef>(&text).map(NarrativeGraph::Dialog).map_err(|e| e.to_string())
+}
+
+fn load_narrative_content(config: Res<NarrativeConfig>, packs: Res<ContentPacks>, mut library: ResMut<NarrativeLibrary>) {
+    library.known_items = known_items(&packs.files(&config.item_directory, "toml"));
+
+    for path in packs.files(&config.quest_directory, "toml") {
+        match read_quest(&path) {
+            Ok(graph) => library.insert(graph, path),
+            Err(e) => warn!("Invalid quest {:?}: {}", path, e),
+        }
+    }
+    for path in packs.files(&config.dialog_directory, "json") {
+        match read_dialog(&path) {
+            Ok(graph) => library.insert(graph, path),
+            Err(e) => warn!("Invalid dialog tree {:?}: {}", path, e),
+        }
+    }
+    info!(
+        "Loaded {} quest and dialog graphs, {} known items",
+        library.graphs.len(),
+        library.known_items.len()
+    );
+}
+
+/// Picks up quest, dialog and item files edited outside the game. Graphs with
+/// unsaved edits in the editor are left alone rather than overwritten.
+fn reload_narrative_content(
+    mut changed: EventReader<ContentChangedEvent>,
+    mut reloaded: EventWriter<ContentReloadedEvent>,
+    config: Res<NarrativeConfig>,
+    packs: Res<ContentPacks>,
+    mut library: ResMut<NarrativeLibrary>,
+) {
+    for event in changed.read() {
+        let path = &event.path;
+        let extension = path.extension().and_then(|e| e.to_str());
+        if path.starts_with(&config.item_directory) && extension == Some("toml") {
+            library.known_items = known_items(&packs.files(&config.item_directory, "toml"));
+            library.revision += 1;
+            let summary = format!("{} known items", library.known_items.len());
+            reloaded.send(ContentReloadedEvent::applied(path, summary));
+            continue;
+        }
+        let read: fn(&Path) -> Result<NarrativeGraph, String> =
+            if path.starts_with(&config.quest_directory) && extension == Some("toml") {
+                read_quest
+            } else if path.starts_with(&config.dialog_directory) && extension == Some("json") {
+                read_dialog
+            } else {
+                continue;
+            };
+
+        if library.graphs.values().any(|entry| &entry.source == path && entry.dirty) {
+            reloaded.send(ContentReloadedEvent::failed(
+                path,
+                "kept the editor's unsaved changes; export or reopen to pick up the file",
+            ));
+            continue;
+        }
+        let graph = if event.removed {
+            None
+        } else {
+            match read(path) {
+                Ok(graph) => Some(graph),
+                Err(e) => {
+                    reloaded.send(ContentReloadedEvent::failed(path, e));
+                    continue;
+                }
+            }
+        };
+
+        library.graphs.retain(|_, entry| &entry.source != path);
+        library.revision += 1;
+        let summary = match graph {
+            Some(graph) => {
+                let summary = match &graph {
+                    NarrativeGraph::Quest(def) => format!("quest {} ({} nodes)", def.id, def.nodes.len()),
+                    NarrativeGraph::Dialog(def) => format!("dialog {} ({} nodes)", def.id, def.nodes.len()),
+                };
+                library.insert(graph, path.clone());
+                summary
+            }
+            None => "removed".to_string(),
+        };
+        reloaded.send(ContentReloadedEvent::applied(path, summary));
+    }
+}
+
+/// Node ids reachable from `start`, following `links`.
+fn reachable<'a>(start: &'a str, links: &HashMap<&'a str, Vec<&'a str>>) -> HashSet<&'a str> {
+    let mut seen = HashSet::new();
+    let mut queue = VecDeque::from([start]);
+    while let Some(id) = queue.pop_front() {
+        if !seen.insert(id) {
+            continue;
+        }
+        if let Some(next) = links.get(id) {
+            queue.extend(next.iter().copied());
+        }
+    }
+    seen
+}
+
+/// Checks shared by both graph kinds: duplicate ids, a missing start node,
+/// links to missing nodes and nodes the start can't reach.
+fn validate_links(start: &str, nodes: &[(&str, Vec<Option<&str>>)], issues: &mut Vec<NarrativeIssue>) {
+    let mut ids = HashSet::new();
+    for (id, _) in nodes {
+        if !ids.insert(*id) {
+            issues.push(NarrativeIssue {
+                severity: NarrativeIssueSeverity::Error,
+                node: Some(id.to_string()),
+                message: format!("duplicate node id {}", id),
+            });
+        }
+    }
+    if !ids.contains(start) {
+        issues.push(NarrativeIssue {
+            severity: NarrativeIssueSeverity::Error,
+            node: None,
+            message: format!("start node {} does not exist", start),
+        });
+        return;
+    }
+
+    let mut links: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (id, targets) in nodes {
+        for target in targets.iter().flatten() {
+            if ids.contains(target) {
+                links.entry(id).or_default().push(target);
+            } else {
+                issues.push(NarrativeIssue {
+                    severity: NarrativeIssueSeverity::Error,
+                    node: Some(id.to_string()),
+                    message: format!("{} links to missing node {}", id, target),
+                });
+            }
+        }
+    }
+
+    let seen = reachable(start, &links);
+    for (id, _) in nodes {
+        if !seen.contains(id) {
+            issues.push(NarrativeIssue {
+                severity: NarrativeIssueSeverity::Warning,
+                node: Some(id.to_string()),
+                message: format!("{} is unreachable from {}", id, start),
+            });
+        }
+    }
+}
+
+fn check_item(item: &str, node: &str, known_items: &HashSet<String>, issues: &mut Vec<NarrativeIssue>) {
+    if !known_items.contains(item) {
+        issues.push(NarrativeIssue {
+            severity: NarrativeIssueSeverity::Error,
+            node: Some(node.to_string()),
+            message: format!("{} references unknown item {}", node, item),
+        });
+    }
+}
+
+pub fn validate_quest(def: &QuestDef, known_items: &HashSet<String>) -> Vec<NarrativeIssue> {
+    let mut issues = Vec::new();
+    let nodes: Vec<(&str, Vec<Option<&str>>)> = def.nodes.iter().map(|n| (n.id.as_str(), n.kind.links())).collect();
+    validate_links(&def.start, &nodes, &mut issues);
+
+    for node in &def.nodes {
+        match &node.kind {
+            QuestNodeKind::Objective { objective, .. } => match objective {
+                QuestObjective::Collect { item, count } => {
+                    check_item(item, &node.id, known_items, &mut issues);
+                    if *count == 0 {
+                        issues.push(NarrativeIssue {
+                            severity: NarrativeIssueSeverity::Error,
+                            node: Some(node.id.clone()),
+                            message: format!("{} collects zero {}", node.id, item),
+                        });
+                    }
+                }
+                QuestObjective::Kill { target, count: 0 } => issues.push(NarrativeIssue {
+                    severity: NarrativeIssueSeverity::Error,
+                    node: Some(node.id.clone()),
+                    message: format!("{} kills zero {}", node.id, target),
+                }),
+                _ => {}
+            },
+            QuestNodeKind::Branch { options } => {
+                if options.is_empty() {
+                    issues.push(NarrativeIssue {
+                        severity: NarrativeIssueSeverity::Error,
+                        node: Some(node.id.clone()),
+                        message: format!("{} has no options", node.id),
+                    });
+                }
+                for item in options.iter().filter_map(|o| o.requires_item.as_deref()) {
+                    check_item(item, &node.id, known_items, &mut issues);
+                }
+            }
+            QuestNodeKind::Reward { items, .. } => {
+                for stack in items {
+                    check_item(&stack.item, &node.id, known_items, &mut issues);
+                }
+            }
+            QuestNodeKind::Complete => {}
+        }
+
+        let open_ports = node.kind.links().iter().filter(|l| l.is_none()).count();
+        if open_ports > 0 {
+            issues.push(NarrativeIssue {
+                severity: NarrativeIssueSeverity::Error,
+                node: Some(node.id.clone()),
+                message: format!("{} has {} unconnected output(s); end paths with a Complete node", node.id, open_ports),
+            });
+        }
+    }
+
+    if !def.nodes.iter().any(|n| n.kind == QuestNodeKind::Complete) {
+        issues.push(NarrativeIssue {
+            severity: NarrativeIssueSeverity::Error,
+            node: None,
+            message: "quest has no Complete node".into(),
+        });
+    }
+    issues
+}
+
+pub fn validate_dialog(def: &DialogTreeDef, known_items: &HashSet<String>, known_quests: &HashSet<&str>) -> Vec<NarrativeIssue> {
+    let mut issues = Vec::new();
+    let nodes: Vec<(&str, Vec<Option<&str>>)> = def.nodes.iter().map(|n| (n.id.as_str(), n.links())).collect();
+    validate_links(&def.start, &nodes, &mut issues);
+
+    for node in &def.nodes {
+        if node.text.trim().is_empty() {
+            issues.push(NarrativeIssue {
+                severity: NarrativeIssueSeverity::Warning,
+                node: Some(node.id.clone()),
+                message: format!("{} has no text", node.id),
+            });
+        }
+        if !node.choices.is_empty() && node.next.is_some() {
+            issues.push(NarrativeIssue {
+                severity: NarrativeIssueSeverity::Warning,
+                node: Some(node.id.clone()),
+                message: format!("{} has choices, so its next link is ignored", node.id),
+            });
+        }
+        for choice in &node.choices {
+            if let Some(item) = &choice.requires_item {
+                check_item(item, &node.id, known_items, &mut issues);
+            }
+            if let Some(quest) = choice.accept_quest.as_deref().filter(|q| !known_quests.contains(q)) {
+                issues.push(NarrativeIssue {
+                    severity: NarrativeIssueSeverity::Error,
+                    node: Some(node.id.clone()),
+                    message: format!("{} offers unknown quest {}", node.id, quest),
+                });
+            }
+        }
+    }
+    issues
+}
+
+fn validate_narrative(mut library: ResMut<NarrativeLibrary>, mut validated_revision: Local<Option<u64>>) {
+    if *validated_revision == Some(library.revision) {
+        return;
+    }
+    *validated_revision = Some(library.revision);
+
+    let library = &mut *library;
+    let known_quests: HashSet<&str> = library
+        .graphs
+        .keys()
+        .filter(|(kind, _)| *kind == NarrativeKind::Quest)
+        .map(|(_, id)| id.as_str())
+        .collect();
+    let results: Vec<_> = library
+        .graphs
+        .iter()
+        .map(|(key, entry)| {
+            let issues = match &entry.graph {
+                NarrativeGraph::Quest(def) => validate_quest(def, &library.known_items),
+                NarrativeGraph::Dialog(def) => validate_dialog(def, &library.known_items, &known_quests),
+            };
+            (key.clone(), issues)
+        })
+        .collect();
+    for (key, issues) in results {
+        if let Some(entry) = library.graphs.get_mut(&key) {
+            entry.issues = issues;
+        }
+    }
+}
+
+fn export_narrative(
+    mut events: EventReader<ExportNarrativeEvent>,
+    mut library: ResMut<NarrativeLibrary>,
+) {
+    let Some(force) = events.read().map(|e| e.force).reduce(|a, b| a || b) else {
+        return;
+    };
+
+    for ((_, id), entry) in library.graphs.iter_mut().filter(|(_, e)| e.dirty) {
+        let errors = entry.error_count();
+        if errors > 0 && !force {
+            warn!("Not exporting {}: {} validation errors", id, errors);
+            continue;
+        }
+        if let Some(parent) = entry.source.parent() {
+            if let Err(e) = std::fs::create_dir_all(parent) {
+                error!("Failed to create {:?}: {}", parent, e);
+                continue;
+            }
+        }
+        match entry.graph.serialize() {
+            Ok(text) => match std::fs::write(&entry.source, text) {
+                Ok(()) => {
+                    entry.dirty = false;
+                    info!("Exported {:?}", entry.source);
+                }
+                Err(e) => error!("Failed to write {:?}: {}", entry.source, e),
+            },
+            Err(e) => error!("Failed to serialize {}: {}", id, e),
+        }
+    }
+}
@@ -0,0 +1,202 @@
+//! Pannable egui canvas of boxes, ports and wires shared by the graph editors.
+//! The canvas only reports what the user did; callers own the graph data.
+
+use bevy_egui::egui;
+
+const NODE_WIDTH: f32 = 170.0;
+const HEADER_HEIGHT: f32 = 22.0;
+const ROW_HEIGHT: f32 = 18.0;
+const PORT_RADIUS: f32 = 5.0;
+
+pub struct CanvasNode {
+    pub id: String,
+    pub title: String,
+    pub position: [f32; 2],
+    pub inputs: Vec<String>,
+    pub outputs: Vec<String>,
+    /// Outline the node in red (validation errors).
+    pub flagged: bool,
+}
+
+/// Wire from an output port to an input port, by node id and port index.
+pub struct CanvasLink {
+    pub from: String,
+    pub output: usize,
+    pub to: String,
+    pub input: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CanvasAction {
+    Select(String),
+    Move { node: String, delta: [f32; 2] },
+    Connect { from: String, output: usize, to: String, input: usize },
+    /// Right-click on an input port.
+    DisconnectInput { node: String, input: usize },
+    /// Right-click on an output port.
+    DisconnectOutput { node: String, output: usize },
+}
+
+#[derive(Debug, Default)]
+pub struct NodeCanvasState {
+    pub pan: egui::Vec2,
+    connecting: Option<(String, usize)>,
+    menu_at: [f32; 2],
+}
+
+fn node_rect(node: &CanvasNode, origin: egui::Vec2) -> egui::Rect {
+    let rows = node.inputs.len().max(node.outputs.len()).max(1);
+    egui::Rect::from_min_size(
+        egui::pos2(node.position[0], node.position[1]) + origin,
+        egui::vec2(NODE_WIDTH, HEADER_HEIGHT + rows as f32 * ROW_HEIGHT + 4.0),
+    )
+}
+
+fn port_y(rect: egui::Rect, index: usize) -> f32 {
+    rect.top() + HEADER_HEIGHT + (index as f32 + 0.5) * ROW_HEIGHT
+}
+
+fn input_port(rect: egui::Rect, index: usize) -> egui::Pos2 {
+    egui::pos2(rect.left(), port_y(rect, index))
+}
+
+fn output_port(rect: egui::Rect, index: usize) -> egui::Pos2 {
+    egui::pos2(rect.right(), port_y(rect, index))
+}
+
+fn wire(painter: &egui::Painter, from: egui::Pos2, to: egui::Pos2, color: egui::Color32) {
+    let bend = ((to.x - from.x).abs() * 0.5).max(30.0);
+    painter.add(egui::epaint::CubicBezierShape::from_points_stroke(
+        [from, from + egui::vec2(bend, 0.0), to - egui::vec2(bend, 0.0), to],
+        false,
+        egui::Color32::TRANSPARENT,
+        egui::Stroke::new(2.0, color),
+    ));
+}
+
+fn port_rect(center: egui::Pos2) -> egui::Rect {
+    egui::Rect::from_center_size(center, egui::vec2(PORT_RADIUS * 3.0, PORT_RADIUS * 3.0))
+}
+
+/// Draws the canvas. Middle-drag pans, header-drag moves nodes, dragging from
+/// an output to an input connects them. `add_menu` fills the background
+/// right-click menu and receives the canvas position that was clicked.
+pub fn node_canvas(
+    ui: &mut egui::Ui,
+    state: &mut NodeCanvasState,
+    nodes: &[CanvasNode],
+    links: &[CanvasLink],
+    selected: Option<&str>,
+    height: f32,
+    add_menu: impl FnOnce(&mut egui::Ui, [f32; 2]),
+) -> Vec<CanvasAction> {
+    let mut actions = Vec::new();
+    let (response, painter) = ui.allocate_painter(egui::vec2(ui.available_width(), height), egui::Sense::click_and_drag());
+    painter.rect_filled(response.rect, 4.0, egui::Color32::from_gray(24));
+    if response.dragged_by(egui::PointerButton::Middle) {
+        state.pan += response.drag_delta();
+    }
+    let origin = response.rect.min.to_vec2() + state.pan;
+    let rect_by_id = |id: &str| nodes.iter().find(|n| n.id == id).map(|n| node_rect(n, origin));
+
+    for link in links {
+        if let (Some(from), Some(to)) = (rect_by_id(&link.from), rect_by_id(&link.to)) {
+            wire(&painter, output_port(from, link.output), input_port(to, link.input), egui::Color32::LIGHT_GRAY);
+        }
+    }
+
+    let pointer = ui.input(|i| i.pointer.interact_pos());
+    let released = ui.input(|i| i.pointer.any_released());
+    let mut drop_target = None;
+    let small = egui::FontId::proportional(11.0);
+
+    for node in nodes {
+        let rect = node_rect(node, origin);
+        let is_selected = selected == Some(node.id.as_str());
+        let outline = if node.flagged {
+            egui::Color32::from_rgb(220, 70, 60)
+        } else if is_selected {
+            egui::Color32::from_gray(220)
+        } else {
+            egui::Color32::from_gray(90)
+        };
+        painter.rect_filled(rect, 4.0, egui::Color32::from_gray(48));
+        painter.rect_stroke(rect, 4.0, egui::Stroke::new(if is_selected { 2.0 } else { 1.0 }, outline));
+        painter.text(
+            rect.left_top() + egui::vec2(6.0, 4.0),
+            egui::Align2::LEFT_TOP,
+            &node.title,
+            egui::FontId::proportional(12.0),
+            egui::Color32::WHITE,
+        );
+
+        let header = egui::Rect::from_min_size(rect.min, egui::vec2(rect.width(), HEADER_HEIGHT));
+        let header_response = ui.interact(header, ui.id().with(("node", &node.id)), egui::Sense::click_and_drag());
+        if header_response.clicked() || header_response.drag_started() {
+            actions.push(CanvasAction::Select(node.id.clone()));
+        }
+        if header_response.dragged() {
+            let delta = header_response.drag_delta();
+            actions.push(CanvasAction::Move {
+                node: node.id.clone(),
+                delta: [delta.x, delta.y],
+            });
+        }
+
+        for (index, label) in node.inputs.iter().enumerate() {
+            let port = input_port(rect, index);
+            painter.circle_filled(port, PORT_RADIUS, egui::Color32::from_rgb(120, 170, 255));
+            painter.text(port + egui::vec2(8.0, 0.0), egui::Align2::LEFT_CENTER, label, small.clone(), egui::Color32::LIGHT_GRAY);
+            let area = port_rect(port);
+            if ui.interact(area, ui.id().with(("in", &node.id, index)), egui::Sense::click()).secondary_clicked() {
+                actions.push(CanvasAction::DisconnectInput {
+                    node: node.id.clone(),
+                    input: index,
+                });
+            }
+            if released && state.connecting.is_some() && pointer.is_some_and(|p| area.contains(p)) {
+                drop_target = Some((node.id.clone(), index));
+            }
+        }
+
+        for (index, label) in node.outputs.iter().enumerate() {
+            let port = output_port(rect, index);
+            painter.circle_filled(port, PORT_RADIUS, egui::Color32::from_rgb(255, 190, 90));
+            painter.text(port - egui::vec2(8.0, 0.0), egui::Align2::RIGHT_CENTER, label, small.clone(), egui::Color32::LIGHT_GRAY);
+            let port_response = ui.interact(port_rect(port), ui.id().with(("out", &node.id, index)), egui::Sense::click_and_drag());
+            if port_response.drag_started() {
+                state.connecting = Some((node.id.clone(), index));
+            }
+            if port_response.secondary_clicked() {
+                actions.push(CanvasAction::DisconnectOutput {
+                    node: node.id.clone(),
+                    output: index,
+                });
+            }
+        }
+    }
+
+    if let (Some((from, output)), Some(pointer)) = (&state.connecting, pointer) {
+        if let Some(rect) = rect_by_id(from) {
+            wire(&painter, output_port(rect, *output), pointer, egui::Color32::YELLOW);
+        }
+    }
+    if released {
+        if let (Some((from, output)), Some((to, input))) = (state.connecting.take(), drop_target) {
+            if from != to {
+                actions.push(CanvasAction::Connect { from, output, to, input });
+            }
+        }
+    }
+
+    if response.secondary_clicked() {
+        if let Some(pos) = response.interact_pointer_pos() {
+            let local = pos - origin;
+            state.menu_at = [local.x, local.y];
+        }
+    }
+    let menu_at = state.menu_at;
+    response.context_menu(|ui| add_menu(ui, menu_at));
+
+    actions
+}
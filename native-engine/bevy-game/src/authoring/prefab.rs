@@ -0,0 +1,565 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use crate::content_packs::ContentPacks;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PrefabCollider {
+    Box { half_extents: [f32; 3] },
+    Sphere { radius: f32 },
+    Capsule { half_height: f32, radius: f32 },
+}
+
+impl PrefabCollider {
+    pub fn to_collider(&self) -> Collider {
+        match self {
+            PrefabCollider::Box { half_extents } => Collider::cuboid(half_extents[0], half_extents[1], half_extents[2]),
+            PrefabCollider::Sphere { radius } => Collider::ball(*radius),
+            PrefabCollider::Capsule { half_height, radius } => Collider::capsule_y(*half_height, *radius),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PrefabLight {
+    #[serde(default = "default_light_color")]
+    pub color: [f32; 3],
+    pub intensity: f32,
+    pub range: f32,
+    #[serde(default)]
+    pub shadows: bool,
+}
+
+fn default_light_color() -> [f32; 3] {
+    [1.0, 1.0, 1.0]
+}
+
+/// One entity in a prefab hierarchy. Nodes are addressed by `name`, which is
+/// also what instance overrides key on, so renaming a node drops its overrides.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PrefabNodeDef {
+    pub name: String,
+    /// Name of the parent node; root nodes attach to the instance entity.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+    #[serde(default)]
+    pub translation: [f32; 3],
+    /// Euler angles in degrees, applied Y then X then Z.
+    #[serde(default)]
+    pub rotation_degrees: [f32; 3],
+    #[serde(default = "default_scale")]
+    pub scale: [f32; 3],
+    /// glTF file whose first scene is instanced on this node.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scene: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub collider: Option<PrefabCollider>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub light: Option<PrefabLight>,
+    /// Marks a spawn point (e.g. `town_guard`) for the NPC spawner.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spawn_tag: Option<String>,
+}
+
+fn default_scale() -> [f32; 3] {
+    [1.0, 1.0, 1.0]
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrefabDef {
+    pub id: String,
+    #[serde(default)]
+    pub nodes: Vec<PrefabNodeDef>,
+}
+
+/// Per-instance changes to one prefab node. Unset fields follow the prefab,
+/// so edits to the prefab still reach every instance.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Reflect)]
+pub struct NodeOverride {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub translation: Option<[f32; 3]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rotation_degrees: Option<[f32; 3]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scale: Option<[f32; 3]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scene: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spawn_tag: Option<String>,
+    /// Remove the node (and its children) from this instance.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub removed: bool,
+}
+
+impl NodeOverride {
+    /// Layer `other` on top of `self`; set fields in `other` win.
+    pub fn merge(&mut self, other: NodeOverride) {
+        self.translation = other.translation.or(self.translation);
+        self.rotation_degrees = other.rotation_degrees.or(self.rotation_degrees);
+        self.scale = other.scale.or(self.scale);
+        self.scene = other.scene.or(self.scene.take());
+        self.spawn_tag = other.spawn_tag.or(self.spawn_tag.take());
+        self.removed = other.removed;
+    }
+
+    pub fn apply(&self, node: &PrefabNodeDef) -> PrefabNodeDef {
+        PrefabNodeDef {
+            translation: self.translation.unwrap_or(node.translation),
+            rotation_degrees: self.rotation_degrees.unwrap_or(node.rotation_degrees),
+            scale: self.scale.unwrap_or(node.scale),
+            scene: self.scene.clone().or_else(|| node.scene.clone()),
+            spawn_tag: self.spawn_tag.clone().or_else(|| node.spawn_tag.clone()),
+            ..node.clone()
+        }
+    }
+}
+
+/// Placement of a prefab in a level content file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrefabInstanceDef {
+    pub id: String,
+    pub prefab: String,
+    pub position: [f32; 3],
+    #[serde(default)]
+    pub yaw_degrees: f32,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub overrides: BTreeMap<String, NodeOverride>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PrefabInstanceFile {
+    #[serde(default)]
+    pub instances: Vec<PrefabInstanceDef>,
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct PrefabConfig {
+    /// One prefab per file.
+    pub prefab_directory: PathBuf,
+    pub instance_directory: PathBuf,
+}
+
+impl Default for PrefabConfig {
+    fn default() -> Self {
+        Self {
+            prefab_directory: PathBuf::from("content").join("prefabs"),
+            instance_directory: PathBuf::from("content").join("prefab_instances"),
+        }
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct PrefabLibrary {
+    pub prefabs: HashMap<String, PrefabDef>,
+    pub sources: HashMap<String, PathBuf>,
+}
+
+/// Root of a placed prefab. Its children are rebuilt from the prefab plus
+/// `overrides` whenever either changes.
+#[derive(Component, Debug, Clone, Reflect)]
+#[reflect(Component)]
+pub struct PrefabInstance {
+    pub id: String,
+    pub prefab: String,
+    pub overrides: BTreeMap<String, NodeOverride>,
+    pub source: Option<PathBuf>,
+}
+
+#[derive(Component, Debug, Clone, Reflect)]
+#[reflect(Component)]
+pub struct PrefabNode {
+    pub instance: Entity,
+    pub name: String,
+}
+
+#[derive(Component, Debug, Clone, Reflect)]
+#[reflect(Component)]
+pub struct PrefabSpawnPoint {
+    pub tag: String,
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct SpawnPrefabEvent {
+    pub prefab: String,
+    /// Defaults to `<prefab>_<n>`.
+    pub instance_id: Option<String>,
+    pub transform: Transform,
+    pub overrides: BTreeMap<String, NodeOverride>,
+}
+
+/// Editor edit to one node of one instance; merged into the existing override.
+#[derive(Event, Debug, Clone)]
+pub struct SetPrefabOverrideEvent {
+    pub instance: Entity,
+    pub node: String,
+    pub value: NodeOverride,
+}
+
+/// Drop an instance's override for `node`, or all overrides when `node` is `None`.
+#[derive(Event, Debug, Clone)]
+pub struct RevertPrefabOverrideEvent {
+    pub instance: Entity,
+    pub node: Option<String>,
+}
+
+/// A prefab definition changed: replace it (or reload it from disk when
+/// `def` is `None`) and rebuild every instance.
+#[derive(Event, Debug, Clone)]
+pub struct PrefabChangedEvent {
+    pub prefab: String,
+    pub def: Option<PrefabDef>,
+}
+
+#[derive(Event, Debug, Clone, Default)]
+pub struct SavePrefabsEvent;
+
+/// Instance roots whose children need rebuilding this frame.
+#[derive(Resource, Debug, Default)]
+struct DirtyPrefabInstances(Vec<Entity>);
+
+pub struct PrefabPlugin;
+
+impl Plugin for PrefabPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PrefabConfig>()
+            .init_resource::<ContentPacks>()
+            .init_resource::<PrefabLibrary>()
+            .init_resource::<DirtyPrefabInstances>()
+            .register_type::<PrefabInstance>()
+            .register_type::<PrefabNode>()
+            .register_type::<PrefabSpawnPoint>()
+            .add_event::<SpawnPrefabEvent>()
+            .add_event::<SetPrefabOverrideEvent>()
+            .add_event::<RevertPrefabOverrideEvent>()
+            .add_event::<PrefabChangedEvent>()
+            .add_event::<SavePrefabsEvent>()
+            .add_systems(Startup, (load_prefab_library, load_prefab_instances).chain())
+            .add_systems(
+                Update,
+                (
+                    handle_spawn_prefab,
+                    handle_override_events,
+                    handle_prefab_changes,
+                    rebuild_dirty_instances,
+                    save_prefabs,
+                )
+                    .chain(),
+            );
+    }
+}
+
+fn read_toml<T: serde::de::DeserializeOwned>(path: &Path) -> Option<T> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) => {
+            warn!("Failed to read {:?}: {}", path, e);
+            return None;
+        }
+    };
+    match toml::from_str(&text) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            warn!("Invalid prefab content {:?}: {}", path, e);
+            None
+        }
+    }
+}
+
+fn load_prefab_library(config: Res<PrefabConfig>, packs: Res<ContentPacks>, mut library: ResMut<PrefabLibrary>) {
+    for path in packs.files(&config.prefab_directory, "toml") {
+        if let Some(def) = read_toml::<PrefabDef>(&path) {
+            library.sources.insert(def.id.clone(), path);
+            library.prefabs.insert(def.id.clone(), def);
+        }
+    }
+    info!("Loaded {} prefabs from {:?}", library.prefabs.len(), config.prefab_directory);
+}
+
+pub fn spawn_prefab_instance(
+    commands: &mut Commands,
+    def: &PrefabInstanceDef,
+    source: Option<PathBuf>,
+) -> Entity {
+    let transform = Transform::from_translation(Vec3::from_array(def.position))
+        .with_rotation(Quat::from_rotation_y(def.yaw_degrees.to_radians()));
+
+    commands
+        .spawn((
+            PrefabInstance {
+                id: def.id.clone(),
+                prefab: def.prefab.clone(),
+                overrides: def.overrides.clone(),
+                source,
+            },
+            transform,
+            Visibility::default(),
+            Name::new(format!("Prefab: {} ({})", def.id, def.prefab)),
+        ))
+        .id()
+}
+
+fn load_prefab_instances(mut commands: Commands, config: Res<PrefabConfig>, packs: Res<ContentPacks>) {
+    let mut count = 0;
+    for path in packs.files(&config.instance_directory, "toml") {
+        let Some(file) = read_toml::<PrefabInstanceFile>(&path) else {
+            continue;
+        };
+        for def in &file.instances {
+            spawn_prefab_instance(&mut commands, def, Some(path.clone()));
+            count += 1;
+        }
+    }
+    info!("Placed {} prefab instances from {:?}", count, config.instance_directory);
+}
+
+fn handle_spawn_prefab(
+    mut commands: Commands,
+    mut events: EventReader<SpawnPrefabEvent>,
+    instances: Query<&PrefabInstance>,
+) {
+    for event in events.read() {
+        let id = event.instance_id.clone().unwrap_or_else(|| {
+            let n = instances.iter().filter(|i| i.prefab == event.prefab).count() + 1;
+            format!("{}_{}", event.prefab, n)
+        });
+        commands.spawn((
+            PrefabInstance {
+                id: id.clone(),
+                prefab: event.prefab.clone(),
+                overrides: event.overrides.clone(),
+                source: None,
+            },
+            event.transform,
+            Visibility::default(),
+            Name::new(format!("Prefab: {} ({})", id, event.prefab)),
+        ));
+    }
+}
+
+fn handle_override_events(
+    mut set_events: EventReader<SetPrefabOverrideEvent>,
+    mut revert_events: EventReader<RevertPrefabOverrideEvent>,
+    mut instances: Query<&mut PrefabInstance>,
+    mut dirty: ResMut<DirtyPrefabInstances>,
+) {
+    for event in set_events.read() {
+        let Ok(mut instance) = instances.get_mut(event.instance) else {
+            continue;
+        };
+        instance
+            .overrides
+            .entry(event.node.clone())
+            .or_default()
+            .merge(event.value.clone());
+        dirty.0.push(event.instance);
+    }
+
+    for event in revert_events.read() {
+        let Ok(mut instance) = instances.get_mut(event.instance) else {
+            continue;
+        };
+        match &event.node {
+            Some(node) => {
+                instance.overrides.remove(node);
+            }
+            None => instance.overrides.clear(),
+        }
+        dirty.0.push(event.instance);
+    }
+}
+
+/// Propagates prefab edits: every instance of a changed prefab is rebuilt,
+/// keeping its overrides.
+fn handle_prefab_changes(
+    mut events: EventReader<PrefabChangedEvent>,
+    mut library: ResMut<PrefabLibrary>,
+    instances: Query<(Entity, &PrefabInstance)>,
+    mut dirty: ResMut<DirtyPrefabInstances>,
+) {
+    for event in events.read() {
+        let def = match &event.def {
+            Some(def) => Some(def.clone()),
+            None => library
+                .sources
+                .get(&event.prefab)
+                .and_then(|path| read_toml::<PrefabDef>(path)),
+        };
+        let Some(def) = def else {
+            warn!("Prefab '{}' changed but could not be loaded", event.prefab);
+            continue;
+        };
+        library.prefabs.insert(event.prefab.clone(), def);
+
+        let mut count = 0;
+        for (entity, instance) in instances.iter() {
+            if instance.prefab == event.prefab {
+                dirty.0.push(entity);
+                count += 1;
+            }
+        }
+        info!("Prefab '{}' changed, rebuilding {} instances", event.prefab, count);
+    }
+}
+
+fn node_transform(node: &PrefabNodeDef) -> Transform {
+    let [y, x, z] = [
+        node.rotation_degrees[1].to_radians(),
+        node.rotation_degrees[0].to_radians(),
+        node.rotation_degrees[2].to_radians(),
+    ];
+    Transform::from_translation(Vec3::from_array(node.translation))
+        .with_rotation(Quat::from_euler(EulerRot::YXZ, y, x, z))
+        .with_scale(Vec3::from_array(node.scale))
+}
+
+fn rebuild_dirty_instances(
+    mut commands: Commands,
+    asset_server: Option<Res<AssetServer>>,
+    library: Res<PrefabLibrary>,
+    mut dirty: ResMut<DirtyPrefabInstances>,
+    instances: Query<&PrefabInstance>,
+    added: Query<Entity, Added<PrefabInstance>>,
+) {
+    // New instances (content, editor placement, duplication, undo) build themselves
+    let mut pending = std::mem::take(&mut dirty.0);
+    pending.extend(added.iter());
+    pending.sort();
+    pending.dedup();
+
+    for root in pending {
+        let Ok(instance) = instances.get(root) else {
+            continue;
+        };
+        let Some(def) = library.prefabs.get(&instance.prefab) else {
+            warn!("Prefab instance '{}' uses unknown prefab '{}'", instance.id, instance.prefab);
+            continue;
+        };
+
+        commands.entity(root).despawn_descendants();
+
+        // Nodes may list children before parents; keep passing until all are placed
+        let mut spawned: HashMap<&str, Entity> = HashMap::new();
+        let mut remaining: Vec<&PrefabNodeDef> = def.nodes.iter().collect();
+        while !remaining.is_empty() {
+            let before = remaining.len();
+            remaining.retain(|node| {
+                let original: &PrefabNodeDef = node;
+                let parent = match node.parent.as_deref() {
+                    None => root,
+                    Some(parent) => match spawned.get(parent) {
+                        Some(entity) => *entity,
+                        None => return true,
+                    },
+                };
+
+                let node_override = instance.overrides.get(&node.name);
+                if node_override.is_some_and(|o| o.removed) {
+                    return false;
+                }
+                let node = node_override.map_or_else(|| original.clone(), |o| o.apply(original));
+
+                let mut entity = commands.spawn((
+                    node_transform(&node),
+                    Visibility::default(),
+                    PrefabNode {
+                        instance: root,
+                        name: node.name.clone(),
+                    },
+                    Name::new(node.name.clone()),
+                ));
+                if let (Some(scene), Some(asset_server)) = (&node.scene, &asset_server) {
+                    entity.insert(SceneRoot(asset_server.load(GltfAssetLabel::Scene(0).from_asset(scene.clone()))));
+                }
+                if let Some(collider) = &node.collider {
+                    entity.insert((RigidBody::Fixed, collider.to_collider()));
+                }
+                if let Some(light) = &node.light {
+                    entity.insert(PointLight {
+                        color: Color::srgb(light.color[0], light.color[1], light.color[2]),
+                        intensity: light.intensity,
+                        range: light.range,
+                        shadows_enabled: light.shadows,
+                        ..default()
+                    });
+                }
+                if let Some(tag) = &node.spawn_tag {
+                    entity.insert(PrefabSpawnPoint { tag: tag.clone() });
+                }
+                let entity = entity.set_parent(parent).id();
+                spawned.insert(original.name.as_str(), entity);
+                false
+            });
+
+            if remaining.len() == before {
+                // Children of removed or missing nodes are dropped with them
+                for node in &remaining {
+                    debug!("Prefab '{}': skipping node '{}' (parent not placed)", def.id, node.name);
+                }
+                break;
+            }
+        }
+    }
+}
+
+fn save_prefabs(
+    mut events: EventReader<SavePrefabsEvent>,
+    config: Res<PrefabConfig>,
+    library: Res<PrefabLibrary>,
+    instances: Query<(&PrefabInstance, &Transform)>,
+) {
+    if events.read().count() == 0 {
+        return;
+    }
+
+    for dir in [&config.prefab_directory, &config.instance_directory] {
+        if let Err(e) = std::fs::create_dir_all(dir) {
+            error!("Failed to create prefab directory {:?}: {}", dir, e);
+            return;
+        }
+    }
+
+    for (id, def) in &library.prefabs {
+        let path = library
+            .sources
+            .get(id)
+            .cloned()
+            .unwrap_or_else(|| config.prefab_directory.join(format!("{}.toml", id)));
+        write_toml(&path, def);
+    }
+
+    let default_path = config.instance_directory.join("editor.toml");
+    let mut files: HashMap<PathBuf, PrefabInstanceFile> = HashMap::new();
+    for (instance, transform) in instances.iter() {
+        let (yaw, _, _) = transform.rotation.to_euler(EulerRot::YXZ);
+        let path = instance.source.clone().unwrap_or_else(|| default_path.clone());
+        files.entry(path).or_default().instances.push(PrefabInstanceDef {
+            id: instance.id.clone(),
+            prefab: instance.prefab.clone(),
+            position: transform.translation.to_array(),
+            yaw_degrees: yaw.to_degrees(),
+            overrides: instance.overrides.clone(),
+        });
+    }
+
+    for (path, mut file) in files {
+        file.instances.sort_by(|a, b| a.id.cmp(&b.id));
+        write_toml(&path, &file);
+    }
+}
+
+fn write_toml<T: Serialize>(path: &Path, value: &T) {
+    match toml::to_string_pretty(value) {
+        Ok(text) => {
+            if let Err(e) = std::fs::write(path, text) {
+                error!("Failed to write {:?}: {}", path, e);
+            } else {
+                info!("Saved {:?}", path);
+            }
+        }
+        Err(e) => error!("Failed to serialize {:?}: {}", path, e),
+    }
+}
@@ -0,0 +1,943 @@
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use bevy_rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+use super::history::{CreateEntitiesCommand, EditHistory, EditorCommand};
+use super::terrain_sculpt::TerrainEdits;
+use super::transform_tools::EditorSelectable;
+use super::{AuthoringMode, AuthoringTool};
+use crate::content_packs::ContentPacks;
+use crate::database::GameDatabase;
+use crate::hot_reload::{ContentChangedEvent, ContentReloadedEvent};
+use crate::worldgen::{WorldSeed, WorldStream};
+use crate::{LandmarkRegistry, TerrainConfig};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SpawnEntryDef {
+    pub monster: String,
+    #[serde(default = "default_weight")]
+    pub weight: f32,
+    /// Narrower level range for this monster; must sit inside the zone's range.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub level_range: Option<[u32; 2]>,
+}
+
+fn default_weight() -> f32 {
+    1.0
+}
+
+/// A painted spawn zone: a set of grid cells sharing one spawn table.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SpawnZoneDef {
+    pub id: String,
+    /// Inclusive `[min, max]` monster level.
+    pub level_range: [u32; 2],
+    /// Target live spawns per 1000 m².
+    #[serde(default = "default_density")]
+    pub density: f32,
+    #[serde(default = "default_respawn_seconds")]
+    pub respawn_seconds: f32,
+    #[serde(default)]
+    pub spawns: Vec<SpawnEntryDef>,
+    /// Painted cells as `[x, z]` on the file's grid.
+    #[serde(default)]
+    pub cells: Vec<[i32; 2]>,
+}
+
+fn default_density() -> f32 {
+    4.0
+}
+
+fn default_respawn_seconds() -> f32 {
+    90.0
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Reflect)]
+pub struct EncounterMemberDef {
+    pub monster: String,
+    /// Offset from the group position, in the group's local frame.
+    #[serde(default)]
+    pub offset: [f32; 3],
+    /// Defaults to the group level.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub level: Option<u32>,
+}
+
+/// A hand-placed group of monsters that spawn and pull together.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EncounterGroupDef {
+    pub id: String,
+    pub position: [f32; 3],
+    #[serde(default)]
+    pub yaw_degrees: f32,
+    pub level: u32,
+    #[serde(default = "default_respawn_seconds")]
+    pub respawn_seconds: f32,
+    #[serde(default)]
+    pub members: Vec<EncounterMemberDef>,
+}
+
+/// `content/spawns/*.toml`, read by the content loader when populating the world.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpawnContentFile {
+    #[serde(default = "default_cell_size")]
+    pub cell_size: f32,
+    #[serde(default, rename = "zone")]
+    pub zones: Vec<SpawnZoneDef>,
+    #[serde(default, rename = "encounter")]
+    pub encounters: Vec<EncounterGroupDef>,
+}
+
+fn default_cell_size() -> f32 {
+    8.0
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct SpawnPaintConfig {
+    pub content_directory: PathBuf,
+    /// Painting grid spacing in metres. Files on a different grid are skipped.
+    pub cell_size: f32,
+    /// Cells further than this from the camera aren't drawn.
+    pub preview_distance: f32,
+    /// Members placed for a new encounter group when the zone has no spawn table.
+    pub default_group_size: usize,
+}
+
+impl Default for SpawnPaintConfig {
+    fn default() -> Self {
+        Self {
+            content_directory: PathBuf::from("content").join("spawns"),
+            cell_size: default_cell_size(),
+            preview_distance: 250.0,
+            default_group_size: 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SpawnZone {
+    pub def: SpawnZoneDef,
+    pub source: PathBuf,
+}
+
+impl SpawnZone {
+    pub fn area(&self, cell_size: f32) -> f32 {
+        self.def.cells.len() as f32 * cell_size * cell_size
+    }
+
+    pub fn expected_spawns(&self, cell_size: f32) -> f32 {
+        self.area(cell_size) * self.def.density / 1000.0
+    }
+}
+
+/// Spawn zones being edited, in load order. Encounter groups live as entities
+/// so the transform tools can move them.
+#[derive(Resource, Debug, Default)]
+pub struct SpawnZoneLayout {
+    pub zones: Vec<SpawnZone>,
+    /// Index into `zones` that painting writes to.
+    pub active: usize,
+    pub revision: u64,
+}
+
+impl SpawnZoneLayout {
+    pub fn zone_at_cell(&self, cell: [i32; 2]) -> Option<&SpawnZone> {
+        self.zones.iter().find(|z| z.def.cells.contains(&cell))
+    }
+}
+
+#[derive(Component, Debug, Clone, Reflect)]
+#[reflect(Component)]
+pub struct EncounterGroup {
+    pub id: String,
+    pub level: u32,
+    pub respawn_seconds: f32,
+    pub members: Vec<EncounterMemberDef>,
+    pub source: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SpawnIssueSeverity {
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone)]
+pub struct SpawnValidationIssue {
+    pub severity: SpawnIssueSeverity,
+    pub message: String,
+}
+
+/// Result of the last validation pass; refreshed whenever zones or groups change.
+#[derive(Resource, Debug, Default)]
+pub struct SpawnValidation {
+    pub issues: Vec<SpawnValidationIssue>,
+}
+
+impl SpawnValidation {
+    pub fn error_count(&self) -> usize {
+        self.issues.iter().filter(|i| i.severity == SpawnIssueSeverity::Error).count()
+    }
+}
+
+#[derive(Event, Debug, Clone, Copy, Default)]
+pub struct SaveSpawnContentEvent;
+
+#[derive(Resource, Debug, Clone)]
+pub struct SpawnBrushSettings {
+    pub radius: f32,
+}
+
+impl Default for SpawnBrushSettings {
+    fn default() -> Self {
+        Self { radius: 12.0 }
+    }
+}
+
+/// Cells of every zone before the current stroke.
+#[derive(Resource, Default)]
+struct ActiveSpawnStroke {
+    before: Option<Vec<Vec<[i32; 2]>>>,
+}
+
+#[derive(Resource, Default)]
+struct SpawnBrushCursor {
+    hit: Option<Vec3>,
+}
+
+/// One paint or erase stroke. Painting a cell takes it from any other zone,
+/// so every zone's cells are captured.
+pub struct SpawnZoneStrokeCommand {
+    pub before: Vec<Vec<[i32; 2]>>,
+    pub after: Vec<Vec<[i32; 2]>>,
+}
+
+impl SpawnZoneStrokeCommand {
+    fn write(world: &mut World, cells: &[Vec<[i32; 2]>]) {
+        let mut layout = world.resource_mut::<SpawnZoneLayout>();
+        for (zone, cells) in layout.zones.iter_mut().zip(cells) {
+            zone.def.cells = cells.clone();
+        }
+        layout.revision += 1;
+    }
+}
+
+impl EditorCommand for SpawnZoneStrokeCommand {
+    fn label(&self) -> String {
+        "Paint spawn zone".to_string()
+    }
+
+    fn apply(&mut self, world: &mut World) {
+        Self::write(world, &self.after);
+    }
+
+    fn undo(&mut self, world: &mut World) {
+        Self::write(world, &self.before);
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+#[derive(Component)]
+struct SpawnPanel;
+
+#[derive(Component)]
+struct SpawnPanelText;
+
+pub struct SpawnPaintingPlugin;
+
+impl Plugin for SpawnPaintingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SpawnPaintConfig>()
+            .init_resource::<ContentPacks>()
+            .init_resource::<SpawnZoneLayout>()
+            .init_resource::<SpawnValidation>()
+            .init_resource::<SpawnBrushSettings>()
+            .init_resource::<ActiveSpawnStroke>()
+            .init_resource::<SpawnBrushCursor>()
+            .register_type::<EncounterGroup>()
+            .add_event::<SaveSpawnContentEvent>()
+            .add_event::<ContentChangedEvent>()
+            .add_event::<ContentReloadedEvent>()
+            .add_systems(Startup, (load_spawn_content, setup_spawn_panel))
+            .add_systems(
+                Update,
+                (
+                    (
+                        spawn_paint_shortcuts,
+                        update_spawn_cursor,
+                        paint_spawn_zones,
+                        place_encounter_group,
+                    )
+                        .chain()
+                        .run_if(|mode: Res<AuthoringMode>| mode.using(AuthoringTool::SpawnPaint)),
+                    reload_spawn_content,
+                    validate_spawn_content,
+                    draw_spawn_previews.run_if(|mode: Res<AuthoringMode>| mode.active),
+                    update_spawn_panel,
+                    save_spawn_content,
+                )
+                    .chain(),
+            );
+    }
+}
+
+fn load_spawn_content(
+    mut commands: Commands,
+    config: Res<SpawnPaintConfig>,
+    packs: Res<ContentPacks>,
+    mut layout: ResMut<SpawnZoneLayout>,
+) {
+    let mut groups = 0;
+    for path in packs.files(&config.content_directory, "toml") {
+        let file = match read_spawn_file(&path, &config) {
+            Ok(file) => file,
+            Err(e) => {
+                warn!("Spawn content {:?}: {}; skipping", path, e);
+                continue;
+            }
+        };
+
+        for def in file.zones {
+            layout.zones.push(SpawnZone {
+                def,
+                source: path.clone(),
+            });
+        }
+        for def in &file.encounters {
+            spawn_encounter_group(&mut commands, def, Some(path.clone()));
+            groups += 1;
+        }
+    }
+    layout.revision += 1;
+    info!("Loaded {} spawn zones and {} encounter groups", layout.zones.len(), groups);
+}
+
+fn read_spawn_file(path: &Path, config: &SpawnPaintConfig) -> Result<SpawnContentFile, String> {
+    let file = std::fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|text| toml::from_str::<SpawnContentFile>(&text).map_err(|e| e.to_string()))?;
+    if file.cell_size != config.cell_size {
+        return Err(format!("uses a {} m grid, expected {} m", file.cell_size, config.cell_size));
+    }
+    Ok(file)
+}
+
+/// Swaps a changed spawn file's zones into the layout and updates its
+/// encounter groups in place, so selections and undo history keep pointing
+/// at the same entities.
+fn reload_spawn_content(
+    mut commands: Commands,
+    mut changed: EventReader<ContentChangedEvent>,
+    mut reloaded: EventWriter<ContentReloadedEvent>,
+    config: Res<SpawnPaintConfig>,
+    mut layout: ResMut<SpawnZoneLayout>,
+    mut groups: Query<(Entity, &mut EncounterGroup, &mut Transform)>,
+) {
+    for event in changed.read() {
+        let is_spawn_file = event.path.starts_with(&config.content_directory)
+            && event.path.extension().and_then(|e| e.to_str()) == Some("toml");
+        if !is_spawn_file {
+            continue;
+        }
+        let file = if event.removed {
+            empty_content_file(config.cell_size)
+        } else {
+            match read_spawn_file(&event.path, &config) {
+                Ok(file) => file,
+                Err(e) => {
+                    reloaded.send(ContentReloadedEvent::failed(&event.path, e));
+                    continue;
+                }
+            }
+        };
+
+        let current: Vec<&SpawnZoneDef> =
+            layout.zones.iter().filter(|z| z.source == event.path).map(|z| &z.def).collect();
+        if current.len() != file.zones.len() || current.iter().zip(&file.zones).any(|(a, b)| *a != b) {
+            layout.zones.retain(|z| z.source != event.path);
+            layout.zones.extend(file.zones.iter().map(|def| SpawnZone {
+                def: def.clone(),
+                source: event.path.clone(),
+            }));
+            layout.active = layout.active.min(layout.zones.len().saturating_sub(1));
+            layout.revision += 1;
+        }
+
+        let mut remaining: HashMap<&str, &EncounterGroupDef> =
+            file.encounters.iter().map(|def| (def.id.as_str(), def)).collect();
+        let mut removed = 0;
+        for (entity, mut group, mut transform) in groups.iter_mut() {
+            if group.source.as_ref() != Some(&event.path) {
+                continue;
+            }
+            let Some(def) = remaining.remove(group.id.as_str()) else {
+                commands.entity(entity).despawn_recursive();
+                removed += 1;
+                continue;
+            };
+            group.level = def.level;
+            group.respawn_seconds = def.respawn_seconds;
+            group.members = def.members.clone();
+            *transform = Transform::from_translation(Vec3::from_array(def.position))
+                .with_rotation(Quat::from_rotation_y(def.yaw_degrees.to_radians()));
+        }
+        for def in remaining.values() {
+            spawn_encounter_group(&mut commands, def, Some(event.path.clone()));
+        }
+        reloaded.send(ContentReloadedEvent::applied(
+            &event.path,
+            format!(
+                "{} zones, {} encounter groups ({} added, {} removed)",
+                file.zones.len(),
+                file.encounters.len(),
+                remaining.len(),
+                removed
+            ),
+        ));
+    }
+}
+
+pub fn spawn_encounter_group(commands: &mut Commands, def: &EncounterGroupDef, source: Option<PathBuf>) -> Entity {
+    commands
+        .spawn((
+            EncounterGroup {
+                id: def.id.clone(),
+                level: def.level,
+                respawn_seconds: def.respawn_seconds,
+                members: def.members.clone(),
+                source,
+            },
+            Transform::from_translation(Vec3::from_array(def.position))
+                .with_rotation(Quat::from_rotation_y(def.yaw_degrees.to_radians())),
+            Visibility::default(),
+            EditorSelectable,
+            Name::new(format!("Encounter: {}", def.id)),
+        ))
+        .id()
+}
+
+fn cell_of(position: Vec3, cell_size: f32) -> [i32; 2] {
+    [(position.x / cell_size).floor() as i32, (position.z / cell_size).floor() as i32]
+}
+
+fn cell_center(cell: [i32; 2], cell_size: f32) -> Vec2 {
+    (Vec2::new(cell[0] as f32, cell[1] as f32) + 0.5) * cell_size
+}
+
+/// F6 cycles the active zone, F7 adds a zone next to it, `[`/`]` resize the brush.
+fn spawn_paint_shortcuts(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    config: Res<SpawnPaintConfig>,
+    mut layout: ResMut<SpawnZoneLayout>,
+    mut settings: ResMut<SpawnBrushSettings>,
+) {
+    if keyboard.just_pressed(KeyCode::F6) && !layout.zones.is_empty() {
+        layout.active = (layout.active + 1) % layout.zones.len();
+        info!("Active spawn zone: {}", layout.zones[layout.active].def.id);
+    }
+    if keyboard.just_pressed(KeyCode::F7) {
+        let template = layout.zones.get(layout.active).cloned();
+        let n = layout.zones.len() + 1;
+        let zone = SpawnZone {
+            def: SpawnZoneDef {
+                id: format!("zone_{}", n),
+                level_range: template.as_ref().map_or([1, 5], |t| t.def.level_range),
+                density: template.as_ref().map_or(default_density(), |t| t.def.density),
+                respawn_seconds: template.as_ref().map_or(default_respawn_seconds(), |t| t.def.respawn_seconds),
+                spawns: template.as_ref().map(|t| t.def.spawns.clone()).unwrap_or_default(),
+                cells: Vec::new(),
+            },
+            source: template.map_or_else(|| config.content_directory.join("editor.toml"), |t| t.source),
+        };
+        info!("Added spawn zone {}", zone.def.id);
+        layout.zones.push(zone);
+        layout.active = layout.zones.len() - 1;
+        layout.revision += 1;
+    }
+    if keyboard.just_pressed(KeyCode::BracketLeft) {
+        settings.radius = (settings.radius * 0.8).max(config.cell_size * 0.5);
+    }
+    if keyboard.just_pressed(KeyCode::BracketRight) {
+        settings.radius = (settings.radius * 1.25).min(200.0);
+    }
+}
+
+fn update_spawn_cursor(
+    mut cursor: ResMut<SpawnBrushCursor>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+    rapier_context: ReadRapierContext,
+) {
+    cursor.hit = None;
+    let Some(position) = windows.iter().next().and_then(|w| w.cursor_position()) else {
+        return;
+    };
+    let Some((camera, camera_transform)) = cameras.iter().find(|(c, _)| c.is_active) else {
+        return;
+    };
+    let Ok(ray) = camera.viewport_to_world(camera_transform, position) else {
+        return;
+    };
+    let Ok(rapier_context) = rapier_context.single() else {
+        return;
+    };
+    let filter = QueryFilter::default().exclude_sensors().exclude_dynamic();
+    cursor.hit = rapier_context
+        .cast_ray(ray.origin, *ray.direction, 2000.0, true, filter)
+        .map(|(_, toi)| ray.origin + *ray.direction * toi);
+}
+
+/// Left drag paints the active zone; Shift erases. Ctrl is left for placing groups.
+fn paint_spawn_zones(
+    mouse: Res<ButtonInput<MouseButton>>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    cursor: Res<SpawnBrushCursor>,
+    config: Res<SpawnPaintConfig>,
+    settings: Res<SpawnBrushSettings>,
+    mut layout: ResMut<SpawnZoneLayout>,
+    mut stroke: ResMut<ActiveSpawnStroke>,
+    mut history: ResMut<EditHistory>,
+) {
+    if mouse.just_released(MouseButton::Left) {
+        if let Some(before) = stroke.before.take() {
+            let after: Vec<Vec<[i32; 2]>> = layout.zones.iter().map(|z| z.def.cells.clone()).collect();
+            if after != before {
+                history.record(SpawnZoneStrokeCommand { before, after });
+            }
+        }
+        return;
+    }
+
+    let ctrl = keyboard.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    if ctrl || layout.zones.is_empty() {
+        return;
+    }
+    let Some(hit) = cursor.hit else {
+        return;
+    };
+    if mouse.just_pressed(MouseButton::Left) {
+        stroke.before = Some(layout.zones.iter().map(|z| z.def.cells.clone()).collect());
+    }
+    if !mouse.pressed(MouseButton::Left) || stroke.before.is_none() {
+        return;
+    }
+
+    let erase = keyboard.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    let cell_size = config.cell_size;
+    let center = cell_of(hit, cell_size);
+    let reach = (settings.radius / cell_size).ceil() as i32;
+    let mut brushed = BTreeSet::new();
+    for dz in -reach..=reach {
+        for dx in -reach..=reach {
+            let cell = [center[0] + dx, center[1] + dz];
+            if cell_center(cell, cell_size).distance(hit.xz()) <= settings.radius {
+                brushed.insert(cell);
+            }
+        }
+    }
+
+    let active = layout.active.min(layout.zones.len() - 1);
+    let mut changed = false;
+    for (index, zone) in layout.zones.iter_mut().enumerate() {
+        let before = zone.def.cells.len();
+        // A cell belongs to at most one zone
+        zone.def.cells.retain(|c| !brushed.contains(c) || (index == active && !erase));
+        changed |= zone.def.cells.len() != before;
+        if index == active && !erase {
+            let existing: BTreeSet<[i32; 2]> = zone.def.cells.iter().copied().collect();
+            let added: Vec<[i32; 2]> = brushed.difference(&existing).copied().collect();
+            changed |= !added.is_empty();
+            zone.def.cells.extend(added);
+        }
+    }
+    if changed {
+        layout.revision += 1;
+    }
+}
+
+/// Ctrl+click drops an encounter group at the cursor, levelled to the zone under it.
+fn place_encounter_group(
+    mut commands: Commands,
+    mouse: Res<ButtonInput<MouseButton>>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    cursor: Res<SpawnBrushCursor>,
+    config: Res<SpawnPaintConfig>,
+    layout: Res<SpawnZoneLayout>,
+    groups: Query<&EncounterGroup>,
+    mut history: ResMut<EditHistory>,
+) {
+    let ctrl = keyboard.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    if !ctrl || !mouse.just_pressed(MouseButton::Left) {
+        return;
+    }
+    let Some(hit) = cursor.hit else {
+        return;
+    };
+
+    let zone = layout.zone_at_cell(cell_of(hit, config.cell_size));
+    let level = zone.map_or(1, |z| (z.def.level_range[0] + z.def.level_range[1]) / 2);
+    let monsters: Vec<String> = zone
+        .map(|z| z.def.spawns.iter().map(|s| s.monster.clone()).collect())
+        .unwrap_or_default();
+    let members = (0..config.default_group_size)
+        .map(|i| {
+            let angle = i as f32 / config.default_group_size as f32 * std::f32::consts::TAU;
+            EncounterMemberDef {
+                monster: monsters
+                    .get(i % monsters.len().max(1))
+                    .cloned()
+                    .unwrap_or_else(|| "unassigned".to_string()),
+                offset: [angle.cos() * 2.0, 0.0, angle.sin() * 2.0],
+                level: None,
+            }
+        })
+        .collect();
+
+    let existing: Vec<&str> = groups.iter().map(|g| g.id.as_str()).collect();
+    let prefix = zone.map_or("encounter", |z| z.def.id.as_str());
+    let id = (1..)
+        .map(|n| format!("{}_group_{}", prefix, n))
+        .find(|id| !existing.contains(&id.as_str()))
+        .unwrap_or_default();
+
+    let def = EncounterGroupDef {
+        id,
+        position: hit.to_array(),
+        yaw_degrees: 0.0,
+        level,
+        respawn_seconds: default_respawn_seconds(),
+        members,
+    };
+    let source = zone.map(|z| z.source.clone());
+    let entity = spawn_encounter_group(&mut commands, &def, source);
+    history.record(CreateEntitiesCommand::new(format!("Place {}", def.id), vec![entity]));
+}
+
+fn in_range(level: u32, range: [u32; 2]) -> bool {
+    level >= range[0] && level <= range[1]
+}
+
+fn validate_spawn_content(
+    config: Res<SpawnPaintConfig>,
+    layout: Res<SpawnZoneLayout>,
+    groups: Query<(Ref<EncounterGroup>, Ref<Transform>)>,
+    database: Option<Res<GameDatabase>>,
+    mut validation: ResMut<SpawnValidation>,
+    mut validated_revision: Local<Option<u64>>,
+) {
+    let groups_changed = groups.iter().any(|(g, t)| g.is_changed() || t.is_changed());
+    let database_changed = database.as_ref().is_some_and(|db| db.is_changed());
+    if *validated_revision == Some(layout.revision) && !groups_changed && !database_changed {
+        return;
+    }
+    *validated_revision = Some(layout.revision);
+
+    let mut issues = Vec::new();
+    let mut error = |message: String| {
+        issues.push(SpawnValidationIssue {
+            severity: SpawnIssueSeverity::Error,
+            message,
+        })
+    };
+    let mut cell_owners: HashMap<[i32; 2], &str> = HashMap::new();
+    let mut overlaps = Vec::new();
+
+    for zone in &layout.zones {
+        let def = &zone.def;
+        if def.level_range[0] > def.level_range[1] {
+            error(format!("{}: level range {:?} is inverted", def.id, def.level_range));
+        }
+        if def.spawns.is_empty() {
+            error(format!("{}: no spawn entries", def.id));
+        }
+        for entry in &def.spawns {
+            // Checked only once the database has monsters, so layouts can be
+            // painted before the monster content exists
+            if let Some(monsters) = database.as_ref().map(|db| &db.monsters).filter(|m| !m.is_empty()) {
+                match monsters.by_key(&entry.monster) {
+                    None => error(format!("{}: unknown monster '{}'", def.id, entry.monster)),
+                    Some(monster) => {
+                        let range = entry.level_range.unwrap_or(def.level_range);
+                        if range[1] < monster.level_range[0] || range[0] > monster.level_range[1] {
+                            error(format!(
+                                "{}: {} spawns at levels {:?}, outside its {:?}",
+                                def.id, entry.monster, range, monster.level_range
+                            ));
+                        }
+                    }
+                }
+            }
+            if let Some(range) = entry.level_range {
+                if !in_range(range[0], def.level_range) || !in_range(range[1], def.level_range) {
+                    error(format!(
+                        "{}: {} levels {:?} outside zone range {:?}",
+                        def.id, entry.monster, range, def.level_range
+                    ));
+                }
+            }
+        }
+        for cell in &def.cells {
+            if let Some(owner) = cell_owners.insert(*cell, def.id.as_str()) {
+                overlaps.push(format!("{} overlaps {} at cell {:?}", def.id, owner, cell));
+            }
+        }
+    }
+
+    for (group, transform) in groups.iter() {
+        let cell = cell_of(transform.translation, config.cell_size);
+        let Some(zone) = layout.zone_at_cell(cell) else {
+            issues.push(SpawnValidationIssue {
+                severity: SpawnIssueSeverity::Warning,
+                message: format!("{}: outside every spawn zone, level range unchecked", group.id),
+            });
+            continue;
+        };
+        let range = zone.def.level_range;
+        if !in_range(group.level, range) {
+            issues.push(SpawnValidationIssue {
+                severity: SpawnIssueSeverity::Error,
+                message: format!("{}: level {} outside {} range {:?}", group.id, group.level, zone.def.id, range),
+            });
+        }
+        for member in &group.members {
+            if let Some(level) = member.level.filter(|l| !in_range(*l, range)) {
+                issues.push(SpawnValidationIssue {
+                    severity: SpawnIssueSeverity::Error,
+                    message: format!("{}: {} level {} outside {} range {:?}", group.id, member.monster, level, zone.def.id, range),
+                });
+            }
+        }
+    }
+
+    for overlap in overlaps {
+        issues.push(SpawnValidationIssue {
+            severity: SpawnIssueSeverity::Warning,
+            message: overlap,
+        });
+    }
+    validation.issues = issues;
+}
+
+fn zone_color(index: usize) -> Color {
+    Color::hsl((index as f32 * 67.0) % 360.0, 0.75, 0.55)
+}
+
+/// Painted cells plus a scatter of preview markers at the zone's density,
+/// drawn from the world seed's spawn stream.
+fn draw_spawn_previews(
+    mut gizmos: Gizmos,
+    config: Res<SpawnPaintConfig>,
+    layout: Res<SpawnZoneLayout>,
+    settings: Res<SpawnBrushSettings>,
+    mode: Res<AuthoringMode>,
+    cursor: Res<SpawnBrushCursor>,
+    terrain_config: Res<TerrainConfig>,
+    terrain_edits: Res<TerrainEdits>,
+    seed: Res<WorldSeed>,
+    mut landmarks: ResMut<LandmarkRegistry>,
+    cameras: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+    groups: Query<(&EncounterGroup, &GlobalTransform)>,
+) {
+    let Some((_, camera_transform)) = cameras.iter().find(|(c, _)| c.is_active) else {
+        return;
+    };
+    let eye = camera_transform.translation().xz();
+    let cell_size = config.cell_size;
+    let mut ground = |p: Vec2| {
+        crate::systems::terrain::terrain_height_at_with_features(p.x, p.y, &terrain_config, &mut landmarks)
+            + terrain_edits.height_delta(p.x, p.y)
+    };
+    let flat = Quat::from_rotation_x(-std::f32::consts::FRAC_PI_2);
+
+    for (index, zone) in layout.zones.iter().enumerate() {
+        let color = zone_color(index);
+        let alpha = if index == layout.active { 0.9 } else { 0.45 };
+        let per_cell = cell_size * cell_size * zone.def.density / 1000.0;
+
+        for cell in &zone.def.cells {
+            let center = cell_center(*cell, cell_size);
+            if center.distance(eye) > config.preview_distance {
+                continue;
+            }
+            let y = ground(center) + 0.1;
+            gizmos.rect(
+                Isometry3d::new(center.extend(y).xzy(), flat),
+                Vec2::splat(cell_size * 0.95),
+                color.with_alpha(alpha),
+            );
+
+            let scatter = |index: u32| seed.cell_value(WorldStream::Spawns, *cell, index);
+            let markers = (per_cell + scatter(0)).floor() as u32;
+            for m in 0..markers {
+                let offset = Vec2::new(scatter(m * 2 + 1), scatter(m * 2 + 2)) - 0.5;
+                let p = center + offset * cell_size;
+                gizmos.sphere(Isometry3d::from_translation(p.extend(ground(p) + 0.5).xzy()), 0.4, color);
+            }
+        }
+    }
+
+    for (group, transform) in groups.iter() {
+        let position = transform.translation();
+        gizmos.circle(Isometry3d::new(position + Vec3::Y * 0.1, flat), 2.5, Color::srgb(1.0, 0.3, 0.2));
+        for member in &group.members {
+            let p = transform.transform_point(Vec3::from_array(member.offset));
+            gizmos.sphere(Isometry3d::from_translation(p + Vec3::Y * 0.5), 0.5, Color::srgb(1.0, 0.5, 0.2));
+        }
+    }
+
+    if mode.tool == AuthoringTool::SpawnPaint {
+        if let Some(hit) = cursor.hit {
+            gizmos.circle(Isometry3d::new(hit + Vec3::Y * 0.15, flat), settings.radius, Color::WHITE);
+        }
+    }
+}
+
+fn setup_spawn_panel(mut commands: Commands) {
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                right: Val::Px(10.0),
+                bottom: Val::Px(10.0),
+                min_width: Val::Px(260.0),
+                padding: UiRect::all(Val::Px(6.0)),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.05, 0.05, 0.08, 0.85)),
+            Visibility::Hidden,
+            SpawnPanel,
+            Name::new("Spawn Zone Panel"),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new("Spawn zones"),
+                TextFont {
+                    font_size: 13.0,
+                    ..default()
+                },
+                SpawnPanelText,
+            ));
+        });
+}
+
+fn update_spawn_panel(
+    mode: Res<AuthoringMode>,
+    config: Res<SpawnPaintConfig>,
+    layout: Res<SpawnZoneLayout>,
+    validation: Res<SpawnValidation>,
+    mut panels: Query<&mut Visibility, With<SpawnPanel>>,
+    mut texts: Query<&mut Text, With<SpawnPanelText>>,
+) {
+    let visible = mode.using(AuthoringTool::SpawnPaint);
+    for mut visibility in panels.iter_mut() {
+        let target = if visible { Visibility::Inherited } else { Visibility::Hidden };
+        if *visibility != target {
+            *visibility = target;
+        }
+    }
+    if !visible || !(layout.is_changed() || validation.is_changed() || mode.is_changed()) {
+        return;
+    }
+
+    let mut lines = vec![format!("Spawn zones ({})", layout.zones.len())];
+    for (index, zone) in layout.zones.iter().enumerate() {
+        let marker = if index == layout.active { ">" } else { " " };
+        lines.push(format!(
+            "{} {}  L{}-{}  {} cells  ~{:.0} spawns",
+            marker,
+            zone.def.id,
+            zone.def.level_range[0],
+            zone.def.level_range[1],
+            zone.def.cells.len(),
+            zone.expected_spawns(config.cell_size),
+        ));
+    }
+    if !validation.issues.is_empty() {
+        lines.push(format!("{} issues ({} errors)", validation.issues.len(), validation.error_count()));
+        for issue in validation.issues.iter().take(8) {
+            let tag = match issue.severity {
+                SpawnIssueSeverity::Error => "E",
+                SpawnIssueSeverity::Warning => "W",
+            };
+            lines.push(format!("{} {}", tag, issue.message));
+        }
+    }
+
+    for mut text in texts.iter_mut() {
+        text.0 = lines.join("\n");
+    }
+}
+
+fn save_spawn_content(
+    mut events: EventReader<SaveSpawnContentEvent>,
+    config: Res<SpawnPaintConfig>,
+    layout: Res<SpawnZoneLayout>,
+    validation: Res<SpawnValidation>,
+    groups: Query<(&EncounterGroup, &Transform)>,
+) {
+    if events.read().count() == 0 {
+        return;
+    }
+    if let Err(e) = std::fs::create_dir_all(&config.content_directory) {
+        error!("Failed to create spawn content directory {:?}: {}", config.content_directory, e);
+        return;
+    }
+    if validation.error_count() > 0 {
+        warn!("Saving spawn content with {} validation errors", validation.error_count());
+    }
+
+    let default_path = config.content_directory.join("editor.toml");
+    let mut files: HashMap<PathBuf, SpawnContentFile> = HashMap::new();
+
+    for zone in &layout.zones {
+        let mut def = zone.def.clone();
+        def.cells.sort();
+        files
+            .entry(zone.source.clone())
+            .or_insert_with(|| empty_content_file(config.cell_size))
+            .zones
+            .push(def);
+    }
+    for (group, transform) in groups.iter() {
+        let (yaw, _, _) = transform.rotation.to_euler(EulerRot::YXZ);
+        let path = group.source.clone().unwrap_or_else(|| default_path.clone());
+        files
+            .entry(path)
+            .or_insert_with(|| empty_content_file(config.cell_size))
+            .encounters
+            .push(EncounterGroupDef {
+                id: group.id.clone(),
+                position: transform.translation.to_array(),
+                yaw_degrees: yaw.to_degrees(),
+                level: group.level,
+                respawn_seconds: group.respawn_seconds,
+                members: group.members.clone(),
+            });
+    }
+
+    for (path, mut file) in files {
+        file.encounters.sort_by(|a, b| a.id.cmp(&b.id));
+        match toml::to_string_pretty(&file) {
+            Ok(text) => match std::fs::write(&path, text) {
+                Ok(()) => info!("Saved {:?}", path),
+                Err(e) => error!("Failed to write {:?}: {}", path, e),
+            },
+            Err(e) => error!("Failed to serialize {:?}: {}", path, e),
+        }
+    }
+}
+
+fn empty_content_file(cell_size: f32) -> SpawnContentFile {
+    SpawnContentFile {
+        cell_size,
+        zones: Vec::new(),
+        encounters: Vec::new(),
+    }
+}
@@ -0,0 +1,627 @@
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use bevy_rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use super::history::{EditHistory, EditorCommand};
+use super::{AuthoringMode, AuthoringTool};
+use crate::{LandmarkRegistry, TerrainConfig};
+
+/// Splat layers per cell, matching the RGBA splat map the terrain shader blends.
+pub const SPLAT_LAYERS: usize = 4;
+
+/// Hand edits for one chunk of the edit grid, layered over the procedural
+/// terrain. Samples are row-major (`z * chunk_cells + x`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TerrainChunkDelta {
+    /// Metres added to the procedural height.
+    pub height: Vec<f32>,
+    /// Painted splat weights (0..255). All zero means "use the procedural splat";
+    /// otherwise the painted mix covers it in proportion to the weight sum.
+    pub splat: Vec<[u8; SPLAT_LAYERS]>,
+    /// Multiplier on the forest density at this cell (1.0 = unchanged).
+    pub vegetation: Vec<f32>,
+}
+
+impl TerrainChunkDelta {
+    fn new(samples: usize) -> Self {
+        Self {
+            height: vec![0.0; samples],
+            splat: vec![[0; SPLAT_LAYERS]; samples],
+            vegetation: vec![1.0; samples],
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.height.iter().all(|h| *h == 0.0)
+            && self.splat.iter().all(|s| s.iter().all(|w| *w == 0))
+            && self.vegetation.iter().all(|v| *v == 1.0)
+    }
+}
+
+/// On-disk form of [`TerrainEdits`]. The grid layout is stored with the data so
+/// changing the config defaults doesn't misplace existing edits.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TerrainEditFile {
+    pub cell_size: f32,
+    pub chunk_cells: i32,
+    pub chunks: Vec<([i32; 2], TerrainChunkDelta)>,
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct TerrainEditConfig {
+    /// Saved alongside the level's prefab instances.
+    pub path: PathBuf,
+    /// Spacing of edit samples in metres, for new edit files.
+    pub cell_size: f32,
+    /// Samples per edit-chunk side, for new edit files.
+    pub chunk_cells: i32,
+    /// Upper bound for painted vegetation density.
+    pub max_vegetation_density: f32,
+}
+
+impl Default for TerrainEditConfig {
+    fn default() -> Self {
+        Self {
+            path: PathBuf::from("content").join("terrain").join("edits.bin"),
+            cell_size: 1.0,
+            chunk_cells: 32,
+            max_vegetation_density: 2.0,
+        }
+    }
+}
+
+/// Sparse terrain edits. Terrain meshing adds [`TerrainEdits::height_delta`] to
+/// the procedural height and blends [`TerrainEdits::splat_weights`] over the
+/// procedural splat; forest placement scales its `ForestConfig` density by
+/// [`TerrainEdits::vegetation_density`].
+#[derive(Resource, Debug, Clone)]
+pub struct TerrainEdits {
+    pub cell_size: f32,
+    pub chunk_cells: i32,
+    pub chunks: HashMap<IVec2, TerrainChunkDelta>,
+}
+
+impl Default for TerrainEdits {
+    fn default() -> Self {
+        let config = TerrainEditConfig::default();
+        Self {
+            cell_size: config.cell_size,
+            chunk_cells: config.chunk_cells,
+            chunks: HashMap::new(),
+        }
+    }
+}
+
+impl TerrainEdits {
+    pub fn chunk_of_cell(&self, cell: IVec2) -> IVec2 {
+        IVec2::new(cell.x.div_euclid(self.chunk_cells), cell.y.div_euclid(self.chunk_cells))
+    }
+
+    fn sample_index(&self, cell: IVec2) -> usize {
+        let local = IVec2::new(cell.x.rem_euclid(self.chunk_cells), cell.y.rem_euclid(self.chunk_cells));
+        (local.y * self.chunk_cells + local.x) as usize
+    }
+
+    fn cell(&self, cell: IVec2) -> Option<(&TerrainChunkDelta, usize)> {
+        let index = self.sample_index(cell);
+        self.chunks.get(&self.chunk_of_cell(cell)).map(|chunk| (chunk, index))
+    }
+
+    fn cell_mut(&mut self, cell: IVec2) -> (&mut TerrainChunkDelta, usize) {
+        let index = self.sample_index(cell);
+        let samples = (self.chunk_cells * self.chunk_cells) as usize;
+        let chunk = self
+            .chunks
+            .entry(self.chunk_of_cell(cell))
+            .or_insert_with(|| TerrainChunkDelta::new(samples));
+        (chunk, index)
+    }
+
+    pub fn cell_position(&self, cell: IVec2) -> Vec2 {
+        cell.as_vec2() * self.cell_size
+    }
+
+    fn nearest_cell(&self, x: f32, z: f32) -> IVec2 {
+        IVec2::new((x / self.cell_size).round() as i32, (z / self.cell_size).round() as i32)
+    }
+
+    fn height_at_cell(&self, cell: IVec2) -> f32 {
+        self.cell(cell).map_or(0.0, |(chunk, i)| chunk.height[i])
+    }
+
+    /// Bilinearly interpolated height offset at a world position.
+    pub fn height_delta(&self, x: f32, z: f32) -> f32 {
+        if self.chunks.is_empty() {
+            return 0.0;
+        }
+        let gx = x / self.cell_size;
+        let gz = z / self.cell_size;
+        let base = IVec2::new(gx.floor() as i32, gz.floor() as i32);
+        let (tx, tz) = (gx - gx.floor(), gz - gz.floor());
+
+        let h00 = self.height_at_cell(base);
+        let h10 = self.height_at_cell(base + IVec2::X);
+        let h01 = self.height_at_cell(base + IVec2::Y);
+        let h11 = self.height_at_cell(base + IVec2::ONE);
+        let near = h00 + (h10 - h00) * tx;
+        let far = h01 + (h11 - h01) * tx;
+        near + (far - near) * tz
+    }
+
+    /// Painted splat mix (0..1 per layer) at a world position, if any was painted.
+    pub fn splat_weights(&self, x: f32, z: f32) -> Option<[f32; SPLAT_LAYERS]> {
+        let (chunk, i) = self.cell(self.nearest_cell(x, z))?;
+        let weights = chunk.splat[i];
+        if weights.iter().all(|w| *w == 0) {
+            return None;
+        }
+        Some(weights.map(|w| w as f32 / 255.0))
+    }
+
+    pub fn vegetation_density(&self, x: f32, z: f32) -> f32 {
+        self.cell(self.nearest_cell(x, z))
+            .map_or(1.0, |(chunk, i)| chunk.vegetation[i])
+    }
+
+    fn to_file(&self) -> TerrainEditFile {
+        let mut chunks: Vec<([i32; 2], TerrainChunkDelta)> = self
+            .chunks
+            .iter()
+            .filter(|(_, chunk)| !chunk.is_empty())
+            .map(|(coord, chunk)| (coord.to_array(), chunk.clone()))
+            .collect();
+        chunks.sort_by_key(|(coord, _)| *coord);
+        TerrainEditFile {
+            cell_size: self.cell_size,
+            chunk_cells: self.chunk_cells,
+            chunks,
+        }
+    }
+}
+
+/// Edit-grid chunks whose deltas changed; terrain remeshes the world chunks
+/// they overlap and forest placement resyncs them when `vegetation` is set.
+#[derive(Event, Debug, Clone)]
+pub struct TerrainEditedEvent {
+    pub chunks: Vec<IVec2>,
+    pub height: bool,
+    pub splat: bool,
+    pub vegetation: bool,
+}
+
+#[derive(Event, Debug, Clone, Copy, Default)]
+pub struct SaveTerrainEditsEvent;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TerrainBrushKind {
+    #[default]
+    Raise,
+    Lower,
+    Smooth,
+    /// Pull towards the height under the cursor when the stroke started.
+    Flatten,
+    Splat,
+    Vegetation,
+}
+
+impl TerrainBrushKind {
+    const ALL: [TerrainBrushKind; 6] = [
+        TerrainBrushKind::Raise,
+        TerrainBrushKind::Lower,
+        TerrainBrushKind::Smooth,
+        TerrainBrushKind::Flatten,
+        TerrainBrushKind::Splat,
+        TerrainBrushKind::Vegetation,
+    ];
+
+    fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|k| *k == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            TerrainBrushKind::Raise => "raise",
+            TerrainBrushKind::Lower => "lower",
+            TerrainBrushKind::Smooth => "smooth",
+            TerrainBrushKind::Flatten => "flatten",
+            TerrainBrushKind::Splat => "paint splat",
+            TerrainBrushKind::Vegetation => "paint vegetation",
+        }
+    }
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct TerrainBrushSettings {
+    pub kind: TerrainBrushKind,
+    pub radius: f32,
+    /// Per second: metres for raise/lower, blend rate for smooth/flatten,
+    /// weight (0..1) for splat and density for vegetation.
+    pub strength: f32,
+    /// Fraction of the radius that fades out; 0 is a hard edge.
+    pub falloff: f32,
+    pub splat_layer: usize,
+}
+
+impl Default for TerrainBrushSettings {
+    fn default() -> Self {
+        Self {
+            kind: TerrainBrushKind::Raise,
+            radius: 6.0,
+            strength: 1.5,
+            falloff: 0.5,
+            splat_layer: 0,
+        }
+    }
+}
+
+impl TerrainBrushSettings {
+    fn weight(&self, distance: f32) -> f32 {
+        let t = distance / self.radius.max(f32::EPSILON);
+        if t >= 1.0 {
+            return 0.0;
+        }
+        let inner = 1.0 - self.falloff.clamp(0.0, 1.0);
+        if t <= inner {
+            return 1.0;
+        }
+        let s = (1.0 - (t - inner) / (1.0 - inner)).clamp(0.0, 1.0);
+        s * s * (3.0 - 2.0 * s)
+    }
+}
+
+/// Chunks as they were before the stroke began (`None` = not yet edited).
+#[derive(Resource, Default)]
+struct ActiveStroke {
+    before: Option<HashMap<IVec2, Option<TerrainChunkDelta>>>,
+    flatten_height: f32,
+    kind: TerrainBrushKind,
+}
+
+#[derive(Resource, Default)]
+struct BrushCursor {
+    hit: Option<Vec3>,
+}
+
+/// One brush stroke, from press to release.
+pub struct TerrainStrokeCommand {
+    pub label: String,
+    pub before: HashMap<IVec2, Option<TerrainChunkDelta>>,
+    pub after: HashMap<IVec2, Option<TerrainChunkDelta>>,
+}
+
+impl TerrainStrokeCommand {
+    fn write(world: &mut World, chunks: &HashMap<IVec2, Option<TerrainChunkDelta>>) {
+        let mut edits = world.resource_mut::<TerrainEdits>();
+        for (coord, chunk) in chunks {
+            match chunk {
+                Some(chunk) => edits.chunks.insert(*coord, chunk.clone()),
+                None => edits.chunks.remove(coord),
+            };
+        }
+        world.send_event(TerrainEditedEvent {
+            chunks: chunks.keys().copied().collect(),
+            height: true,
+            splat: true,
+            vegetation: true,
+        });
+    }
+}
+
+impl EditorCommand for TerrainStrokeCommand {
+    fn label(&self) -> String {
+        self.label.clone()
+    }
+
+    fn apply(&mut self, world: &mut World) {
+        Self::write(world, &self.after);
+    }
+
+    fn undo(&mut self, world: &mut World) {
+        Self::write(world, &self.before);
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+pub struct TerrainSculptPlugin;
+
+impl Plugin for TerrainSculptPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TerrainEditConfig>()
+            .init_resource::<TerrainEdits>()
+            .init_resource::<TerrainBrushSettings>()
+            .init_resource::<ActiveStroke>()
+            .init_resource::<BrushCursor>()
+            .add_event::<TerrainEditedEvent>()
+            .add_event::<SaveTerrainEditsEvent>()
+            .add_systems(Startup, load_terrain_edits)
+            .add_systems(
+                Update,
+                (
+                    (terrain_brush_shortcuts, update_brush_cursor, apply_terrain_brush, draw_brush_gizmo)
+                        .chain()
+                        .run_if(|mode: Res<AuthoringMode>| mode.using(AuthoringTool::TerrainBrush)),
+                    save_terrain_edits,
+                )
+                    .chain(),
+            );
+    }
+}
+
+fn load_terrain_edits(config: Res<TerrainEditConfig>, mut edits: ResMut<TerrainEdits>) {
+    *edits = TerrainEdits {
+        cell_size: config.cell_size,
+        chunk_cells: config.chunk_cells,
+        chunks: HashMap::new(),
+    };
+
+    let Ok(bytes) = std::fs::read(&config.path) else {
+        info!("No terrain edits at {:?}", config.path);
+        return;
+    };
+    match bincode::deserialize::<TerrainEditFile>(&bytes) {
+        Ok(file) => {
+            edits.cell_size = file.cell_size;
+            edits.chunk_cells = file.chunk_cells;
+            edits.chunks = file
+                .chunks
+                .into_iter()
+                .map(|(coord, chunk)| (IVec2::from_array(coord), chunk))
+                .collect();
+            info!("Loaded {} edited terrain chunks from {:?}", edits.chunks.len(), config.path);
+        }
+        Err(e) => warn!("Invalid terrain edits {:?}: {}", config.path, e),
+    }
+}
+
+fn save_terrain_edits(
+    mut events: EventReader<SaveTerrainEditsEvent>,
+    config: Res<TerrainEditConfig>,
+    edits: Res<TerrainEdits>,
+) {
+    if events.read().count() == 0 {
+        return;
+    }
+
+    if let Some(dir) = config.path.parent() {
+        if let Err(e) = std::fs::create_dir_all(dir) {
+            error!("Failed to create terrain edit directory {:?}: {}", dir, e);
+            return;
+        }
+    }
+    let file = edits.to_file();
+    match bincode::serialize(&file) {
+        Ok(bytes) => match std::fs::write(&config.path, bytes) {
+            Ok(()) => info!("Saved {} edited terrain chunks to {:?}", file.chunks.len(), config.path),
+            Err(e) => error!("Failed to write {:?}: {}", config.path, e),
+        },
+        Err(e) => error!("Failed to serialize terrain edits: {}", e),
+    }
+}
+
+/// F6 cycles brushes, F7 cycles splat layers and `[`/`]` resize the brush.
+fn terrain_brush_shortcuts(keyboard: Res<ButtonInput<KeyCode>>, mut settings: ResMut<TerrainBrushSettings>) {
+    if keyboard.just_pressed(KeyCode::F6) {
+        settings.kind = settings.kind.next();
+        info!("Terrain brush: {}", settings.kind.label());
+    }
+    if keyboard.just_pressed(KeyCode::F7) {
+        settings.splat_layer = (settings.splat_layer + 1) % SPLAT_LAYERS;
+        info!("Splat layer: {}", settings.splat_layer);
+    }
+    if keyboard.just_pressed(KeyCode::BracketLeft) {
+        settings.radius = (settings.radius * 0.8).max(0.5);
+    }
+    if keyboard.just_pressed(KeyCode::BracketRight) {
+        settings.radius = (settings.radius * 1.25).min(100.0);
+    }
+}
+
+fn update_brush_cursor(
+    mut cursor: ResMut<BrushCursor>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+    rapier_context: ReadRapierContext,
+) {
+    cursor.hit = None;
+    let Some(position) = windows.iter().next().and_then(|w| w.cursor_position()) else {
+        return;
+    };
+    let Some((camera, camera_transform)) = cameras.iter().find(|(c, _)| c.is_active) else {
+        return;
+    };
+    let Ok(ray) = camera.viewport_to_world(camera_transform, position) else {
+        return;
+    };
+    let Ok(rapier_context) = rapier_context.single() else {
+        return;
+    };
+    let filter = QueryFilter::default().exclude_sensors().exclude_dynamic();
+    cursor.hit = rapier_context
+        .cast_ray(ray.origin, *ray.direction, 2000.0, true, filter)
+        .map(|(_, toi)| ray.origin + *ray.direction * toi);
+}
+
+fn apply_terrain_brush(
+    time: Res<Time>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    cursor: Res<BrushCursor>,
+    settings: Res<TerrainBrushSettings>,
+    edit_config: Res<TerrainEditConfig>,
+    terrain_config: Res<TerrainConfig>,
+    mut landmarks: ResMut<LandmarkRegistry>,
+    mut edits: ResMut<TerrainEdits>,
+    mut stroke: ResMut<ActiveStroke>,
+    mut history: ResMut<EditHistory>,
+    mut edited: EventWriter<TerrainEditedEvent>,
+) {
+    if mouse.just_released(MouseButton::Left) {
+        if let Some(before) = stroke.before.take() {
+            let after = before
+                .keys()
+                .map(|coord| (*coord, edits.chunks.get(coord).cloned()))
+                .collect();
+            history.record(TerrainStrokeCommand {
+                label: format!("Terrain {}", stroke.kind.label()),
+                before,
+                after,
+            });
+        }
+        return;
+    }
+
+    let Some(hit) = cursor.hit else {
+        return;
+    };
+    if mouse.just_pressed(MouseButton::Left) {
+        stroke.before = Some(HashMap::new());
+        stroke.flatten_height = hit.y;
+        stroke.kind = settings.kind;
+    }
+    if !mouse.pressed(MouseButton::Left) || stroke.before.is_none() {
+        return;
+    }
+
+    crate::profile_zone!("terrain::sculpt_brush");
+
+    // Shift inverts: raise lowers, paint erases
+    let invert = keyboard.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    let amount = settings.strength * time.delta_secs();
+    let cell_size = edits.cell_size;
+    let reach = (settings.radius / cell_size).ceil() as i32;
+    let center = edits.nearest_cell(hit.x, hit.z);
+
+    let mut cells = Vec::new();
+    for dz in -reach..=reach {
+        for dx in -reach..=reach {
+            let cell = center + IVec2::new(dx, dz);
+            let weight = settings.weight(edits.cell_position(cell).distance(hit.xz()));
+            if weight > 0.0 {
+                cells.push((cell, weight));
+            }
+        }
+    }
+    if cells.is_empty() {
+        return;
+    }
+
+    // Snapshot chunks the first time this stroke touches them
+    let touched: Vec<IVec2> = {
+        let mut touched: Vec<IVec2> = cells.iter().map(|(cell, _)| edits.chunk_of_cell(*cell)).collect();
+        touched.sort_by_key(|c| (c.x, c.y));
+        touched.dedup();
+        touched
+    };
+    if let Some(before) = stroke.before.as_mut() {
+        for coord in &touched {
+            before
+                .entry(*coord)
+                .or_insert_with(|| edits.chunks.get(coord).cloned());
+        }
+    }
+
+    let mut total_height = |edits: &TerrainEdits, cell: IVec2| {
+        let p = edits.cell_position(cell);
+        crate::systems::terrain::terrain_height_at_with_features(p.x, p.y, &terrain_config, &mut landmarks)
+            + edits.height_at_cell(cell)
+    };
+
+    let kind = settings.kind;
+    match kind {
+        TerrainBrushKind::Raise | TerrainBrushKind::Lower => {
+            let sign = if (kind == TerrainBrushKind::Raise) != invert { 1.0 } else { -1.0 };
+            for (cell, weight) in &cells {
+                let (chunk, i) = edits.cell_mut(*cell);
+                chunk.height[i] += sign * amount * weight;
+            }
+        }
+        TerrainBrushKind::Smooth | TerrainBrushKind::Flatten => {
+            // Targets are computed from the unmodified field, then written
+            let mut targets = Vec::with_capacity(cells.len());
+            for (cell, weight) in &cells {
+                let current = total_height(&edits, *cell);
+                let target = if kind == TerrainBrushKind::Flatten {
+                    stroke.flatten_height
+                } else {
+                    let mut sum = 0.0;
+                    for dz in -1..=1 {
+                        for dx in -1..=1 {
+                            sum += total_height(&edits, *cell + IVec2::new(dx, dz));
+                        }
+                    }
+                    sum / 9.0
+                };
+                targets.push((*cell, (target - current) * (amount * weight).min(1.0)));
+            }
+            for (cell, change) in targets {
+                let (chunk, i) = edits.cell_mut(cell);
+                chunk.height[i] += change;
+            }
+        }
+        TerrainBrushKind::Splat => {
+            let layer = settings.splat_layer.min(SPLAT_LAYERS - 1);
+            for (cell, weight) in &cells {
+                let (chunk, i) = edits.cell_mut(*cell);
+                let weights = &mut chunk.splat[i];
+                let step = (amount * weight * 255.0).round().max(1.0) as i32;
+                if invert {
+                    weights[layer] = (weights[layer] as i32 - step).max(0) as u8;
+                    continue;
+                }
+                weights[layer] = (weights[layer] as i32 + step).min(255) as u8;
+                // Other layers give way so the mix never exceeds full coverage
+                let others: i32 = (0..SPLAT_LAYERS).filter(|l| *l != layer).map(|l| weights[l] as i32).sum();
+                let room = 255 - weights[layer] as i32;
+                if others > room {
+                    for l in (0..SPLAT_LAYERS).filter(|l| *l != layer) {
+                        weights[l] = (weights[l] as i32 * room / others) as u8;
+                    }
+                }
+            }
+        }
+        TerrainBrushKind::Vegetation => {
+            let sign = if invert { -1.0 } else { 1.0 };
+            for (cell, weight) in &cells {
+                let (chunk, i) = edits.cell_mut(*cell);
+                chunk.vegetation[i] =
+                    (chunk.vegetation[i] + sign * amount * weight).clamp(0.0, edit_config.max_vegetation_density);
+            }
+        }
+    }
+
+    edited.send(TerrainEditedEvent {
+        chunks: touched,
+        height: matches!(
+            kind,
+            TerrainBrushKind::Raise | TerrainBrushKind::Lower | TerrainBrushKind::Smooth | TerrainBrushKind::Flatten
+        ),
+        splat: kind == TerrainBrushKind::Splat,
+        vegetation: kind == TerrainBrushKind::Vegetation,
+    });
+}
+
+fn draw_brush_gizmo(mut gizmos: Gizmos, cursor: Res<BrushCursor>, settings: Res<TerrainBrushSettings>) {
+    let Some(hit) = cursor.hit else {
+        return;
+    };
+    let color = match settings.kind {
+        TerrainBrushKind::Raise | TerrainBrushKind::Lower => Color::srgb(1.0, 0.8, 0.2),
+        TerrainBrushKind::Smooth | TerrainBrushKind::Flatten => Color::srgb(0.3, 0.8, 1.0),
+        TerrainBrushKind::Splat => Color::srgb(0.9, 0.4, 0.9),
+        TerrainBrushKind::Vegetation => Color::srgb(0.3, 0.9, 0.3),
+    };
+    let rotation = Quat::from_rotation_x(-std::f32::consts::FRAC_PI_2);
+    gizmos.circle(Isometry3d::new(hit + Vec3::Y * 0.05, rotation), settings.radius, color);
+    let inner = settings.radius * (1.0 - settings.falloff.clamp(0.0, 1.0));
+    if inner > 0.0 && inner < settings.radius {
+        gizmos.circle(Isometry3d::new(hit + Vec3::Y * 0.05, rotation), inner, color.with_alpha(0.5));
+    }
+}
@@ -0,0 +1,714 @@
+use bevy::ecs::entity::EntityHashMap;
+use bevy::prelude::*;
+use bevy::scene::DynamicSceneBuilder;
+use bevy::window::PrimaryWindow;
+use bevy_rapier3d::prelude::*;
+use std::collections::HashSet;
+
+use super::history::{collect_descendants, CreateEntitiesCommand, DeleteEntitiesCommand, EditHistory, SetTransformsCommand};
+use super::prefab::PrefabInstance;
+use super::{AuthoringMode, AuthoringTool};
+use crate::triggers::{TriggerOccupants, TriggerVolume};
+
+/// Opt-in for entities the level editor can pick. Prefab instances and
+/// trigger volumes are always pickable.
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct EditorSelectable;
+
+/// Selected entities in pick order; the last one is the active entity
+/// that alignment tools line up against.
+#[derive(Resource, Debug, Default)]
+pub struct EditorSelection {
+    pub entities: Vec<Entity>,
+}
+
+impl EditorSelection {
+    pub fn active(&self) -> Option<Entity> {
+        self.entities.last().copied()
+    }
+
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.entities.contains(&entity)
+    }
+
+    pub fn toggle(&mut self, entity: Entity) {
+        if let Some(index) = self.entities.iter().position(|e| *e == entity) {
+            self.entities.remove(index);
+        } else {
+            self.entities.push(entity);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GizmoMode {
+    #[default]
+    Translate,
+    Rotate,
+    Scale,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GizmoAxis {
+    X,
+    Y,
+    Z,
+}
+
+impl GizmoAxis {
+    pub const ALL: [GizmoAxis; 3] = [GizmoAxis::X, GizmoAxis::Y, GizmoAxis::Z];
+
+    pub fn vector(self) -> Vec3 {
+        match self {
+            GizmoAxis::X => Vec3::X,
+            GizmoAxis::Y => Vec3::Y,
+            GizmoAxis::Z => Vec3::Z,
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    fn color(self) -> Color {
+        match self {
+            GizmoAxis::X => Color::srgb(0.9, 0.2, 0.2),
+            GizmoAxis::Y => Color::srgb(0.2, 0.9, 0.2),
+            GizmoAxis::Z => Color::srgb(0.2, 0.4, 1.0),
+        }
+    }
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct TransformToolSettings {
+    pub mode: GizmoMode,
+    pub grid_snap: bool,
+    pub grid_size: f32,
+    pub rotation_snap_degrees: f32,
+    pub scale_snap: f32,
+    /// Keep entities on the ground while dragging them horizontally.
+    pub snap_to_surface: bool,
+    pub duplicate_offset: Vec3,
+    /// Handle length in world units.
+    pub handle_length: f32,
+    /// How close (in pixels) the cursor must be to grab a handle or click-select.
+    pub pick_radius_px: f32,
+}
+
+impl Default for TransformToolSettings {
+    fn default() -> Self {
+        Self {
+            mode: GizmoMode::Translate,
+            grid_snap: false,
+            grid_size: 0.5,
+            rotation_snap_degrees: 15.0,
+            scale_snap: 0.1,
+            snap_to_surface: false,
+            duplicate_offset: Vec3::new(1.0, 0.0, 1.0),
+            handle_length: 2.0,
+            pick_radius_px: 12.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlignTarget {
+    /// Match the active (last selected) entity.
+    Active,
+    Min,
+    Max,
+    Center,
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct AlignSelectionEvent {
+    pub axis: GizmoAxis,
+    pub target: AlignTarget,
+}
+
+#[derive(Event, Debug, Clone, Copy, Default)]
+pub struct DuplicateSelectionEvent;
+
+#[derive(Event, Debug, Clone, Copy, Default)]
+pub struct DropSelectionToSurfaceEvent;
+
+#[derive(Event, Debug, Clone, Copy, Default)]
+pub struct DeleteSelectionEvent;
+
+struct HandleDrag {
+    axis: GizmoAxis,
+    start_cursor: Vec2,
+    pivot: Vec3,
+    /// Screen-space pixels per world unit along the axis at drag start.
+    axis_screen: Vec2,
+    starts: Vec<(Entity, Transform)>,
+}
+
+#[derive(Resource, Default)]
+struct PointerState {
+    drag: Option<HandleDrag>,
+    box_start: Option<Vec2>,
+    hovered_axis: Option<GizmoAxis>,
+}
+
+#[derive(Component)]
+struct BoxSelectRect;
+
+type PickableFilter = Or<(With<EditorSelectable>, With<PrefabInstance>, With<TriggerVolume>)>;
+
+pub struct TransformToolsPlugin;
+
+impl Plugin for TransformToolsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EditorSelection>()
+            .init_resource::<TransformToolSettings>()
+            .init_resource::<PointerState>()
+            .add_event::<AlignSelectionEvent>()
+            .add_event::<DuplicateSelectionEvent>()
+            .add_event::<DropSelectionToSurfaceEvent>()
+            .add_event::<DeleteSelectionEvent>()
+            .add_systems(Startup, setup_box_select_ui)
+            .add_systems(
+                Update,
+                (
+                    prune_selection,
+                    transform_tool_shortcuts,
+                    handle_pointer.run_if(|mode: Res<AuthoringMode>| mode.tool == AuthoringTool::Transform),
+                    duplicate_selection,
+                    drop_selection_to_surface,
+                    align_selection,
+                    delete_selection,
+                    draw_transform_gizmos,
+                )
+                    .chain()
+                    .run_if(|mode: Res<AuthoringMode>| mode.active),
+            )
+            .add_systems(Update, update_box_select_ui);
+    }
+}
+
+fn prune_selection(mut selection: ResMut<EditorSelection>, entities: Query<()>) {
+    if selection.entities.iter().any(|e| !entities.contains(*e)) {
+        selection.entities.retain(|e| entities.contains(*e));
+    }
+}
+
+fn transform_tool_shortcuts(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut settings: ResMut<TransformToolSettings>,
+    mut selection: ResMut<EditorSelection>,
+    mut duplicate: EventWriter<DuplicateSelectionEvent>,
+    mut drop: EventWriter<DropSelectionToSurfaceEvent>,
+    mut delete: EventWriter<DeleteSelectionEvent>,
+    mut align: EventWriter<AlignSelectionEvent>,
+) {
+    let ctrl = keyboard.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    let alt = keyboard.any_pressed([KeyCode::AltLeft, KeyCode::AltRight]);
+
+    if keyboard.just_pressed(KeyCode::F1) {
+        settings.mode = GizmoMode::Translate;
+    } else if keyboard.just_pressed(KeyCode::F2) {
+        settings.mode = GizmoMode::Rotate;
+    } else if keyboard.just_pressed(KeyCode::F3) {
+        settings.mode = GizmoMode::Scale;
+    } else if keyboard.just_pressed(KeyCode::F4) {
+        settings.grid_snap = !settings.grid_snap;
+        info!("Grid snap {}", if settings.grid_snap { "on" } else { "off" });
+    }
+
+    if ctrl && keyboard.just_pressed(KeyCode::KeyD) {
+        duplicate.send(DuplicateSelectionEvent);
+    }
+    if keyboard.just_pressed(KeyCode::End) {
+        drop.send(DropSelectionToSurfaceEvent);
+    }
+    if keyboard.just_pressed(KeyCode::Delete) {
+        delete.send(DeleteSelectionEvent);
+    }
+    if keyboard.just_pressed(KeyCode::Escape) {
+        selection.entities.clear();
+    }
+    if ctrl && alt {
+        for (key, axis) in [(KeyCode::KeyX, GizmoAxis::X), (KeyCode::KeyY, GizmoAxis::Y), (KeyCode::KeyZ, GizmoAxis::Z)] {
+            if keyboard.just_pressed(key) {
+                align.send(AlignSelectionEvent {
+                    axis,
+                    target: AlignTarget::Active,
+                });
+            }
+        }
+    }
+}
+
+fn selection_pivot(selection: &EditorSelection, transforms: &Query<&GlobalTransform>) -> Option<Vec3> {
+    let positions: Vec<Vec3> = selection
+        .entities
+        .iter()
+        .filter_map(|e| transforms.get(*e).ok())
+        .map(|t| t.translation())
+        .collect();
+    if positions.is_empty() {
+        return None;
+    }
+    Some(positions.iter().sum::<Vec3>() / positions.len() as f32)
+}
+
+fn distance_to_segment(p: Vec2, a: Vec2, b: Vec2) -> f32 {
+    let ab = b - a;
+    let t = ((p - a).dot(ab) / ab.length_squared().max(f32::EPSILON)).clamp(0.0, 1.0);
+    p.distance(a + ab * t)
+}
+
+fn snap(value: f32, step: f32) -> f32 {
+    if step <= 0.0 {
+        value
+    } else {
+        (value / step).round() * step
+    }
+}
+
+fn ground_height(rapier_context: &RapierContext, position: Vec3, ignore: &HashSet<Entity>) -> Option<f32> {
+    let predicate = |entity: Entity| !ignore.contains(&entity);
+    let filter = QueryFilter::default().exclude_sensors().predicate(&predicate);
+    rapier_context
+        .cast_ray(position + Vec3::Y * 50.0, Vec3::NEG_Y, 500.0, true, filter)
+        .map(|(_, toi)| position.y + 50.0 - toi)
+}
+
+fn handle_pointer(
+    mouse: Res<ButtonInput<MouseButton>>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    settings: Res<TransformToolSettings>,
+    mut state: ResMut<PointerState>,
+    mut selection: ResMut<EditorSelection>,
+    mut history: ResMut<EditHistory>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+    pickables: Query<(Entity, &GlobalTransform), PickableFilter>,
+    global_transforms: Query<&GlobalTransform>,
+    mut transforms: Query<&mut Transform>,
+    children: Query<&Children>,
+    rapier_context: ReadRapierContext,
+) {
+    let Some(cursor) = windows.iter().next().and_then(|w| w.cursor_position()) else {
+        return;
+    };
+    let Some((camera, camera_transform)) = cameras.iter().find(|(c, _)| c.is_active) else {
+        return;
+    };
+    let to_screen = |p: Vec3| camera.world_to_viewport(camera_transform, p).ok();
+
+    // Which handle is under the cursor
+    let pivot = selection_pivot(&selection, &global_transforms);
+    state.hovered_axis = None;
+    if state.drag.is_none() {
+        if let Some((pivot, origin)) = pivot.and_then(|p| to_screen(p).map(|o| (p, o))) {
+            let mut best = settings.pick_radius_px;
+            for axis in GizmoAxis::ALL {
+                let Some(tip) = to_screen(pivot + axis.vector() * settings.handle_length) else {
+                    continue;
+                };
+                let distance = distance_to_segment(cursor, origin, tip);
+                if distance < best {
+                    best = distance;
+                    state.hovered_axis = Some(axis);
+                }
+            }
+        }
+    }
+
+    if mouse.just_pressed(MouseButton::Left) {
+        match (state.hovered_axis, pivot) {
+            (Some(axis), Some(pivot)) => {
+                let (Some(origin), Some(tip)) = (to_screen(pivot), to_screen(pivot + axis.vector() * settings.handle_length))
+                else {
+                    return;
+                };
+                let starts = selection
+                    .entities
+                    .iter()
+                    .filter_map(|e| transforms.get(*e).ok().map(|t| (*e, *t)))
+                    .collect();
+                state.drag = Some(HandleDrag {
+                    axis,
+                    start_cursor: cursor,
+                    pivot,
+                    axis_screen: (tip - origin) / settings.handle_length,
+                    starts,
+                });
+            }
+            _ => state.box_start = Some(cursor),
+        }
+    }
+
+    if let Some(drag) = &state.drag {
+        let delta = cursor - drag.start_cursor;
+        let axis_vector = drag.axis.vector();
+        let axis_len_sq = drag.axis_screen.length_squared().max(f32::EPSILON);
+        let amount = delta.dot(drag.axis_screen) / axis_len_sq;
+
+        let ignore: HashSet<Entity> = if settings.snap_to_surface {
+            let mut all = HashSet::new();
+            for (entity, _) in &drag.starts {
+                all.insert(*entity);
+                all.extend(children.iter_descendants(*entity));
+            }
+            all
+        } else {
+            HashSet::new()
+        };
+        let rapier_context = rapier_context.single().ok();
+
+        for (entity, start) in &drag.starts {
+            let Ok(mut transform) = transforms.get_mut(*entity) else {
+                continue;
+            };
+            match settings.mode {
+                GizmoMode::Translate => {
+                    let mut translation = start.translation + axis_vector * amount;
+                    if settings.grid_snap {
+                        let i = drag.axis.index();
+                        translation[i] = snap(translation[i], settings.grid_size);
+                    }
+                    if settings.snap_to_surface && drag.axis != GizmoAxis::Y {
+                        if let Some(y) = rapier_context
+                            .as_ref()
+                            .and_then(|ctx| ground_height(ctx, translation, &ignore))
+                        {
+                            translation.y = y;
+                        }
+                    }
+                    transform.translation = translation;
+                }
+                GizmoMode::Rotate => {
+                    let mut angle = (delta.x - delta.y) * 0.01;
+                    if settings.grid_snap {
+                        angle = snap(angle, settings.rotation_snap_degrees.to_radians());
+                    }
+                    let rotation = Quat::from_axis_angle(axis_vector, angle);
+                    transform.rotation = rotation * start.rotation;
+                    transform.translation = drag.pivot + rotation * (start.translation - drag.pivot);
+                }
+                GizmoMode::Scale => {
+                    let mut factor = 1.0 + amount / settings.handle_length;
+                    if settings.grid_snap {
+                        factor = snap(factor, settings.scale_snap);
+                    }
+                    let i = drag.axis.index();
+                    transform.scale[i] = (start.scale[i] * factor).max(0.01);
+                }
+            }
+        }
+    }
+
+    if !mouse.just_released(MouseButton::Left) {
+        return;
+    }
+
+    if let Some(drag) = state.drag.take() {
+        let changes: Vec<(Entity, Transform, Transform)> = drag
+            .starts
+            .iter()
+            .filter_map(|(e, before)| transforms.get(*e).ok().map(|after| (*e, *before, *after)))
+            .filter(|(_, before, after)| before != after)
+            .collect();
+        if !changes.is_empty() {
+            let verb = match settings.mode {
+                GizmoMode::Translate => "Move",
+                GizmoMode::Rotate => "Rotate",
+                GizmoMode::Scale => "Scale",
+            };
+            history.record(SetTransformsCommand {
+                label: format!("{} {} entities", verb, changes.len()),
+                changes,
+            });
+        }
+        return;
+    }
+
+    let Some(start) = state.box_start.take() else {
+        return;
+    };
+    let additive = keyboard.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    if !additive {
+        selection.entities.clear();
+    }
+
+    let min = start.min(cursor);
+    let max = start.max(cursor);
+    if (max - min).length() < 4.0 {
+        // Click: pick the closest entity near the cursor
+        let picked = pickables
+            .iter()
+            .filter_map(|(e, t)| to_screen(t.translation()).map(|p| (e, p.distance(cursor))))
+            .filter(|(_, d)| *d <= settings.pick_radius_px * 2.0)
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((entity, _)) = picked {
+            selection.toggle(entity);
+        }
+    } else {
+        for (entity, transform) in pickables.iter() {
+            let Some(p) = to_screen(transform.translation()) else {
+                continue;
+            };
+            if p.cmpge(min).all() && p.cmple(max).all() && !selection.contains(entity) {
+                selection.entities.push(entity);
+            }
+        }
+    }
+}
+
+/// Copies reflected components of each selected hierarchy. Prefab instances
+/// copy only their root and rebuild their children from the prefab.
+fn duplicate_selection(world: &mut World) {
+    let requested = world.resource_mut::<Events<DuplicateSelectionEvent>>().drain().count() > 0;
+    if !requested {
+        return;
+    }
+    let sources = world.resource::<EditorSelection>().entities.clone();
+    let offset = world.resource::<TransformToolSettings>().duplicate_offset;
+    let mut created = Vec::new();
+
+    for source in sources {
+        if world.get_entity(source).is_err() {
+            continue;
+        }
+        let instance = world.get::<PrefabInstance>(source).cloned();
+        let mut entities = Vec::new();
+        if instance.is_some() {
+            entities.push(source);
+        } else {
+            collect_descendants(world, source, &mut entities);
+        }
+
+        let scene = DynamicSceneBuilder::from_world(world)
+            .extract_entities(entities.into_iter())
+            .build();
+        let mut entity_map = EntityHashMap::default();
+        if let Err(e) = scene.write_to_world(world, &mut entity_map) {
+            warn!("Editor: failed to duplicate {}: {}", source, e);
+            continue;
+        }
+        let Some(copy) = entity_map.get(&source).copied() else {
+            continue;
+        };
+
+        let parent = world.get::<Parent>(source).map(|p| p.get());
+        let transform = world.get::<Transform>(source).copied().unwrap_or_default();
+        let volume = world.get::<TriggerVolume>(source).cloned();
+
+        let mut copy_mut = world.entity_mut(copy);
+        copy_mut.remove::<Parent>();
+        copy_mut.insert(Transform {
+            translation: transform.translation + offset,
+            ..transform
+        });
+        if let Some(mut volume) = volume {
+            volume.id = format!("{}_copy", volume.id);
+            copy_mut.insert((volume, TriggerOccupants::default()));
+        }
+        if let Some(mut instance) = instance {
+            instance.id = format!("{}_copy", instance.id);
+            copy_mut.insert(instance);
+        }
+        if let Some(parent) = parent {
+            world.entity_mut(parent).add_child(copy);
+        }
+        created.push(copy);
+    }
+
+    if created.is_empty() {
+        return;
+    }
+    info!("Editor: duplicated {} entities", created.len());
+    world.resource_mut::<EditorSelection>().entities = created.clone();
+    world
+        .resource_mut::<EditHistory>()
+        .record(CreateEntitiesCommand::new(format!("Duplicate {} entities", created.len()), created));
+}
+
+fn drop_selection_to_surface(
+    mut events: EventReader<DropSelectionToSurfaceEvent>,
+    selection: Res<EditorSelection>,
+    mut history: ResMut<EditHistory>,
+    mut transforms: Query<&mut Transform>,
+    children: Query<&Children>,
+    rapier_context: ReadRapierContext,
+) {
+    if events.read().count() == 0 {
+        return;
+    }
+    let Ok(rapier_context) = rapier_context.single() else {
+        return;
+    };
+
+    let mut ignore = HashSet::new();
+    for entity in &selection.entities {
+        ignore.insert(*entity);
+        ignore.extend(children.iter_descendants(*entity));
+    }
+
+    let mut changes = Vec::new();
+    for entity in &selection.entities {
+        let Ok(mut transform) = transforms.get_mut(*entity) else {
+            continue;
+        };
+        let before = *transform;
+        if let Some(y) = ground_height(&rapier_context, transform.translation, &ignore) {
+            transform.translation.y = y;
+            changes.push((*entity, before, *transform));
+        }
+    }
+
+    if !changes.is_empty() {
+        history.record(SetTransformsCommand {
+            label: format!("Drop {} entities to surface", changes.len()),
+            changes,
+        });
+    }
+}
+
+fn align_selection(
+    mut events: EventReader<AlignSelectionEvent>,
+    selection: Res<EditorSelection>,
+    mut history: ResMut<EditHistory>,
+    mut transforms: Query<&mut Transform>,
+) {
+    for event in events.read() {
+        let i = event.axis.index();
+        let values: Vec<f32> = selection
+            .entities
+            .iter()
+            .filter_map(|e| transforms.get(*e).ok())
+            .map(|t| t.translation[i])
+            .collect();
+        if values.len() < 2 {
+            continue;
+        }
+
+        let target = match event.target {
+            AlignTarget::Active => selection
+                .active()
+                .and_then(|e| transforms.get(e).ok())
+                .map(|t| t.translation[i]),
+            AlignTarget::Min => values.iter().copied().reduce(f32::min),
+            AlignTarget::Max => values.iter().copied().reduce(f32::max),
+            AlignTarget::Center => Some(values.iter().sum::<f32>() / values.len() as f32),
+        };
+        let Some(target) = target else {
+            continue;
+        };
+
+        let mut changes = Vec::new();
+        for entity in &selection.entities {
+            let Ok(mut transform) = transforms.get_mut(*entity) else {
+                continue;
+            };
+            if transform.translation[i] == target {
+                continue;
+            }
+            let before = *transform;
+            transform.translation[i] = target;
+            changes.push((*entity, before, *transform));
+        }
+
+        if !changes.is_empty() {
+            history.record(SetTransformsCommand {
+                label: format!("Align {} entities on {:?}", changes.len(), event.axis),
+                changes,
+            });
+        }
+    }
+}
+
+fn delete_selection(
+    mut events: EventReader<DeleteSelectionEvent>,
+    mut selection: ResMut<EditorSelection>,
+    mut history: ResMut<EditHistory>,
+) {
+    if events.read().count() == 0 || selection.entities.is_empty() {
+        return;
+    }
+    history.execute(DeleteEntitiesCommand::new(std::mem::take(&mut selection.entities)));
+}
+
+fn draw_transform_gizmos(
+    settings: Res<TransformToolSettings>,
+    state: Res<PointerState>,
+    selection: Res<EditorSelection>,
+    transforms: Query<&GlobalTransform>,
+    mut gizmos: Gizmos,
+) {
+    for entity in &selection.entities {
+        if let Ok(transform) = transforms.get(*entity) {
+            gizmos.cuboid(
+                Transform::from_translation(transform.translation()).with_scale(Vec3::splat(0.6)),
+                Color::srgb(1.0, 0.8, 0.2),
+            );
+        }
+    }
+
+    let Some(pivot) = selection_pivot(&selection, &transforms) else {
+        return;
+    };
+    let active_axis = state.drag.as_ref().map(|d| d.axis).or(state.hovered_axis);
+
+    for axis in GizmoAxis::ALL {
+        let color = if active_axis == Some(axis) {
+            Color::srgb(1.0, 1.0, 0.3)
+        } else {
+            axis.color()
+        };
+        let tip = pivot + axis.vector() * settings.handle_length;
+        gizmos.line(pivot, tip, color);
+
+        match settings.mode {
+            GizmoMode::Translate => {
+                gizmos.sphere(Isometry3d::from_translation(tip), 0.08, color);
+            }
+            GizmoMode::Rotate => {
+                let rotation = Quat::from_rotation_arc(Vec3::Z, axis.vector());
+                gizmos.circle(Isometry3d::new(pivot, rotation), settings.handle_length * 0.8, color);
+            }
+            GizmoMode::Scale => {
+                gizmos.cuboid(Transform::from_translation(tip).with_scale(Vec3::splat(0.15)), color);
+            }
+        }
+    }
+}
+
+fn setup_box_select_ui(mut commands: Commands) {
+    commands.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            border: UiRect::all(Val::Px(1.0)),
+            ..default()
+        },
+        BorderColor(Color::srgba(0.4, 0.7, 1.0, 0.9)),
+        BackgroundColor(Color::srgba(0.4, 0.7, 1.0, 0.15)),
+        Visibility::Hidden,
+        BoxSelectRect,
+    ));
+}
+
+fn update_box_select_ui(
+    state: Res<PointerState>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut rects: Query<(&mut Node, &mut Visibility), With<BoxSelectRect>>,
+) {
+    let cursor = windows.iter().next().and_then(|w| w.cursor_position());
+    let area = state.box_start.zip(cursor).map(|(a, b)| (a.min(b), a.max(b)));
+
+    for (mut node, mut visibility) in rects.iter_mut() {
+        match area {
+            Some((min, max)) if (max - min).length() >= 4.0 => {
+                node.left = Val::Px(min.x);
+                node.top = Val::Px(min.y);
+                node.width = Val::Px(max.x - min.x);
+                node.height = Val::Px(max.y - min.y);
+                *visibility = Visibility::Inherited;
+            }
+            _ => *visibility = Visibility::Hidden,
+        }
+    }
+}
@@ -0,0 +1,410 @@
+//! Camera modes layered over the orbit camera in `systems::camera`.
+//!
+//! Zooming all the way in (or [`Action::ToggleCameraMode`]) blends the view into
+//! first person at the player's eyes; scrolling back out blends it back.
+//! First person hides the player's body, draws arms and the wielded weapon
+//! from a second camera on [`VIEW_MODEL_LAYER`] (so they never clip into
+//! walls and keep their own field of view) and bobs the view while walking.
+//! Field of view and look speed per mode come from [`CameraSettings`].
+
+use bevy::hierarchy::HierarchyQueryExt;
+use bevy::input::mouse::{AccumulatedMouseMotion, AccumulatedMouseScroll};
+use bevy::pbr::NotShadowCaster;
+use bevy::render::view::RenderLayers;
+use std::f32::consts::{FRAC_PI_2, TAU};
+
+use bevy::prelude::*;
+
+use crate::input_map::{Action, ActionState};
+use crate::settings::{CameraSettings, UserSettings};
+use crate::Player;
+
+/// Render layer for first-person arms and weapons.
+pub const VIEW_MODEL_LAYER: usize = 1;
+
+/// Radians of look per pixel of mouse motion at sensitivity 1.
+const LOOK_RADIANS_PER_PIXEL: f32 = 0.003;
+
+/// Radians per second of look with a stick fully over, at sensitivity 1.
+const STICK_LOOK_RADIANS_PER_SECOND: f32 = 2.5;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CameraMode {
+    #[default]
+    ThirdPerson,
+    FirstPerson,
+    /// Detached free flight, see [`crate::spectator`].
+    Spectator,
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct CameraModeConfig {
+    /// Eye position above the player's origin.
+    pub eye_height: f32,
+    /// Scrolling in with the orbit camera this close enters first person.
+    pub enter_distance: f32,
+    pub transition_seconds: f32,
+    /// Field of view (degrees) of the arms camera, independent of the
+    /// player's setting so arms don't stretch at wide angles.
+    pub view_model_fov: f32,
+    /// glTF for the first-person arms; simple placeholder arms when `None`.
+    pub arms_model: Option<String>,
+    /// Bob cycles per second at `bob_reference_speed`.
+    pub bob_frequency: f32,
+    /// Ground speed (m/s) of a normal walk.
+    pub bob_reference_speed: f32,
+}
+
+impl Default for CameraModeConfig {
+    fn default() -> Self {
+        Self {
+            eye_height: 1.6,
+            enter_distance: 2.0,
+            transition_seconds: 0.3,
+            view_model_fov: 70.0,
+            arms_model: None,
+            bob_frequency: 1.8,
+            bob_reference_speed: 5.0,
+        }
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct CameraModeState {
+    pub mode: CameraMode,
+    /// 0 third person .. 1 first person, eased when applied.
+    pub blend: f32,
+    /// Orbit camera distance from the eyes last frame.
+    pub orbit_distance: f32,
+    pub(crate) yaw: f32,
+    pub(crate) pitch: f32,
+    bob_phase: f32,
+    bob_weight: f32,
+    last_player_position: Option<Vec3>,
+    body_hidden: bool,
+}
+
+impl CameraModeState {
+    /// Look speed for the current mode.
+    pub fn sensitivity(&self, settings: &CameraSettings) -> f32 {
+        match self.mode {
+            CameraMode::ThirdPerson => settings.third_person_sensitivity,
+            CameraMode::FirstPerson | CameraMode::Spectator => settings.first_person_sensitivity,
+        }
+    }
+
+    fn eased_blend(&self) -> f32 {
+        self.blend * self.blend * (3.0 - 2.0 * self.blend)
+    }
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct SetCameraModeRequest(pub CameraMode);
+
+#[derive(Component, Debug)]
+pub struct ViewModelCamera;
+
+/// Root of the first-person arms; everything under it renders on
+/// [`VIEW_MODEL_LAYER`].
+#[derive(Component, Debug)]
+pub struct FirstPersonViewModel;
+
+/// Right-hand attachment point; equipment parents the wielded weapon here.
+#[derive(Component, Debug)]
+pub struct ViewModelSocket;
+
+pub struct CameraModePlugin;
+
+impl Plugin for CameraModePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CameraModeConfig>()
+            .init_resource::<CameraModeState>()
+            .add_event::<SetCameraModeRequest>()
+            .add_systems(Startup, spawn_view_model)
+            .add_systems(
+                Update,
+                (
+                    (switch_camera_mode, first_person_look).chain(),
+                    (apply_camera_mode, update_view_model)
+                        .chain()
+                        .after(crate::systems::camera::update_camera)
+                        .after(crate::systems::mount::mount_camera_system),
+                    (light_view_model_layer, propagate_view_model_layer),
+                ),
+            );
+    }
+}
+
+fn spawn_view_model(
+    mut commands: Commands,
+    config: Res<CameraModeConfig>,
+    asset_server: Res<AssetServer>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let layer = RenderLayers::layer(VIEW_MODEL_LAYER);
+    commands
+        .spawn((
+            Camera3d::default(),
+            Camera {
+                // Drawn over the world camera with its own depth buffer
+                order: 1,
+                is_active: false,
+                clear_color: ClearColorConfig::None,
+                ..default()
+            },
+            Projection::Perspective(PerspectiveProjection {
+                fov: config.view_model_fov.to_radians(),
+                near: 0.01,
+                ..default()
+            }),
+            layer.clone(),
+            ViewModelCamera,
+            Name::new("ViewModelCamera"),
+        ))
+        .with_children(|camera| {
+            camera
+                .spawn((
+                    FirstPersonViewModel,
+                    Transform::default(),
+                    Visibility::default(),
+                    layer.clone(),
+                    Name::new("FirstPersonViewModel"),
+                ))
+                .with_children(|view_model| {
+                    if let Some(path) = &config.arms_model {
+                        view_model.spawn(SceneRoot(
+                            asset_server.load(GltfAssetLabel::Scene(0).from_asset(path.clone())),
+                        ));
+                    } else {
+                        let arm = meshes.add(Cuboid::new(0.08, 0.08, 0.5));
+                        let skin = materials.add(Color::srgb(0.76, 0.6, 0.5));
+                        for side in [-1.0, 1.0] {
+                            view_model.spawn((
+                                Mesh3d(arm.clone()),
+                                MeshMaterial3d(skin.clone()),
+                                Transform::from_xyz(0.22 * side, -0.25, -0.45),
+                                layer.clone(),
+                                NotShadowCaster,
+                            ));
+                        }
+                    }
+                    view_model.spawn((
+                        ViewModelSocket,
+                        Transform::from_xyz(0.22, -0.22, -0.72),
+                        Visibility::default(),
+                        Name::new("ViewModelSocket"),
+                    ));
+                });
+        });
+}
+
+fn switch_camera_mode(
+    config: Res<CameraModeConfig>,
+    actions: Res<ActionState>,
+    scroll: Option<Res<AccumulatedMouseScroll>>,
+    mut requests: EventReader<SetCameraModeRequest>,
+    mut state: ResMut<CameraModeState>,
+    cameras: Query<(&Camera, &Transform), (With<Camera3d>, Without<ViewModelCamera>)>,
+) {
+    // Entering and leaving spectator mode is gated in `spectator`
+    let mut next = requests
+        .read()
+        .map(|request| request.0)
+        .filter(|mode| *mode != CameraMode::Spectator)
+        .last();
+    if state.mode == CameraMode::Spectator {
+        return;
+    }
+    if actions.just_pressed(Action::ToggleCameraMode) {
+        next = Some(match state.mode {
+            CameraMode::FirstPerson => CameraMode::ThirdPerson,
+            _ => CameraMode::FirstPerson,
+        });
+    }
+    // Positive scroll zooms in
+    let scrolled = scroll.map_or(0.0, |s| s.delta.y);
+    match state.mode {
+        CameraMode::ThirdPerson if scrolled > 0.0 && state.orbit_distance <= config.enter_distance => {
+            next = Some(CameraMode::FirstPerson);
+        }
+        CameraMode::FirstPerson if scrolled < 0.0 => next = Some(CameraMode::ThirdPerson),
+        _ => {}
+    }
+
+    let Some(mode) = next.filter(|mode| *mode != state.mode) else {
+        return;
+    };
+    if mode == CameraMode::FirstPerson {
+        // Start looking where the orbit camera was looking
+        if let Some((_, transform)) = cameras.iter().find(|(camera, _)| camera.order == 0) {
+            let (yaw, pitch, _) = transform.rotation.to_euler(EulerRot::YXZ);
+            state.yaw = yaw;
+            state.pitch = pitch;
+        }
+    }
+    state.mode = mode;
+}
+
+/// Mouse look for the modes that don't use the orbit camera's rotation.
+fn first_person_look(
+    time: Res<Time>,
+    settings: Option<Res<UserSettings>>,
+    actions: Res<ActionState>,
+    motion: Option<Res<AccumulatedMouseMotion>>,
+    mut state: ResMut<CameraModeState>,
+) {
+    if state.mode == CameraMode::ThirdPerson {
+        return;
+    }
+    let mouse = match motion {
+        Some(motion) if actions.pressed(Action::MouseLook) => motion.delta * LOOK_RADIANS_PER_PIXEL,
+        _ => Vec2::ZERO,
+    };
+    let stick =
+        Vec2::new(actions.axis(Action::LookLeft, Action::LookRight), actions.axis(Action::LookUp, Action::LookDown))
+            * STICK_LOOK_RADIANS_PER_SECOND
+            * time.delta_secs();
+    let sensitivity = settings.map_or(1.0, |s| s.camera.first_person_sensitivity);
+    let delta = (mouse + stick) * sensitivity;
+    if delta == Vec2::ZERO {
+        return;
+    }
+    state.yaw = (state.yaw - delta.x).rem_euclid(TAU);
+    state.pitch = (state.pitch - delta.y).clamp(-FRAC_PI_2 + 0.05, FRAC_PI_2 - 0.05);
+}
+
+/// Blends the orbit camera's pose toward the eyes and sets the field of
+/// view for the blend.
+fn apply_camera_mode(
+    time: Res<Time>,
+    config: Res<CameraModeConfig>,
+    settings: Option<Res<UserSettings>>,
+    mut state: ResMut<CameraModeState>,
+    players: Query<&Transform, With<Player>>,
+    mut cameras: Query<
+        (&Camera, &mut Transform, &mut Projection),
+        (With<Camera3d>, Without<ViewModelCamera>, Without<Player>),
+    >,
+) {
+    let Ok(player) = players.get_single() else {
+        return;
+    };
+    let Some((_, mut transform, mut projection)) = cameras.iter_mut().find(|(camera, _, _)| camera.order == 0) else {
+        return;
+    };
+    let dt = time.delta_secs();
+    let defaults = CameraSettings::default();
+    let camera_settings = settings.as_ref().map_or(&defaults, |s| &s.camera);
+
+    let eye = player.translation + Vec3::Y * config.eye_height;
+    state.orbit_distance = transform.translation.distance(eye);
+
+    let target = if state.mode == CameraMode::FirstPerson { 1.0 } else { 0.0 };
+    let step = dt / config.transition_seconds.max(0.01);
+    state.blend = if target > state.blend {
+        (state.blend + step).min(target)
+    } else {
+        (state.blend - step).max(target)
+    };
+    let eased = state.eased_blend();
+    if state.mode == CameraMode::Spectator {
+        return;
+    }
+
+    // Head bob follows ground speed so it fades in and out with walking
+    let ground_speed = state
+        .last_player_position
+        .filter(|_| dt > 0.0)
+        .map_or(0.0, |last| (player.translation - last).xz().length() / dt);
+    state.last_player_position = Some(player.translation);
+    let stride = (ground_speed / config.bob_reference_speed.max(0.1)).min(1.5);
+    let bob_target = if camera_settings.head_bob { stride.min(1.0) } else { 0.0 };
+    state.bob_weight += (bob_target - state.bob_weight) * (dt * 8.0).min(1.0);
+    state.bob_phase = (state.bob_phase + dt * config.bob_frequency * stride * TAU).rem_euclid(TAU);
+    let amplitude = 0.05 * camera_settings.head_bob_intensity * state.bob_weight;
+    let bob = Vec3::new(state.bob_phase.cos() * amplitude * 0.5, (state.bob_phase * 2.0).sin() * amplitude, 0.0);
+
+    if eased > 0.0 {
+        let look = Quat::from_euler(EulerRot::YXZ, state.yaw, state.pitch, 0.0);
+        let eye_pose = eye + look * bob;
+        transform.translation = transform.translation.lerp(eye_pose, eased);
+        transform.rotation = transform.rotation.slerp(look, eased);
+    }
+
+    if let Projection::Perspective(perspective) = projection.as_mut() {
+        let fov = camera_settings.third_person_fov.lerp(camera_settings.first_person_fov, eased);
+        perspective.fov = fov.to_radians();
+    }
+}
+
+/// Shows the arms once the view is (nearly) at the eyes and hides the body
+/// while it is. Visibility only changes on the switch so mount systems can
+/// still hide the player.
+fn update_view_model(
+    mut state: ResMut<CameraModeState>,
+    main_cameras: Query<(&Camera, &Transform), (With<Camera3d>, Without<ViewModelCamera>)>,
+    mut view_model_cameras: Query<(&mut Camera, &mut Transform), With<ViewModelCamera>>,
+    mut players: Query<&mut Visibility, With<Player>>,
+) {
+    let Some((_, main_transform)) = main_cameras.iter().find(|(camera, _)| camera.order == 0) else {
+        return;
+    };
+    let in_first_person = state.mode != CameraMode::Spectator && state.eased_blend() > 0.9;
+    for (mut camera, mut transform) in &mut view_model_cameras {
+        camera.is_active = in_first_person;
+        *transform = *main_transform;
+    }
+
+    if in_first_person != state.body_hidden {
+        state.body_hidden = in_first_person;
+        for mut visibility in &mut players {
+            *visibility = if in_first_person { Visibility::Hidden } else { Visibility::Inherited };
+        }
+    }
+}
+
+/// Lights only reach meshes on their own layers, so world lights also
+/// light the arms.
+fn light_view_model_layer(
+    mut commands: Commands,
+    lights: Query<
+        Entity,
+        (
+            Or<(Added<DirectionalLight>, Added<PointLight>, Added<SpotLight>)>,
+            Without<RenderLayers>,
+        ),
+    >,
+) {
+    for light in &lights {
+        commands
+            .entity(light)
+            .insert(RenderLayers::from_layers(&[0, VIEW_MODEL_LAYER]));
+    }
+}
+
+/// Render layers don't inherit, so meshes that arrive under the view model
+/// (the arms scene, a weapon parented to the socket) are moved onto its
+/// layer.
+fn propagate_view_model_layer(
+    mut commands: Commands,
+    arrived: Query<Entity, Or<(Added<Mesh3d>, Changed<Parent>)>>,
+    view_models: Query<(), With<FirstPersonViewModel>>,
+    parents: Query<&Parent>,
+    children: Query<&Children>,
+    meshes: Query<(), (With<Mesh3d>, Without<NotShadowCaster>)>,
+) {
+    for entity in &arrived {
+        if !parents.iter_ancestors(entity).any(|ancestor| view_models.contains(ancestor)) {
+            continue;
+        }
+        for mesh in std::iter::once(entity)
+            .chain(children.iter_descendants(entity))
+            .filter(|e| meshes.contains(*e))
+        {
+            commands
+                .entity(mesh)
+                .insert((RenderLayers::layer(VIEW_MODEL_LAYER), NotShadowCaster));
+        }
+    }
+}
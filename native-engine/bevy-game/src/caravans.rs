@@ -0,0 +1,513 @@
+//! Supply caravans: a repeatable world event. Routes between towns live in
+//! `content/caravans/*.toml`:
+//!
+//! ```toml
+//! [[town]]
+//! id = "camelot"
+//! position = [0.0, 12.0, 0.0]
+//!
+//! [[route]]
+//! id = "camelot_supply"
+//! name = "Camelot Supply Train"
+//! realm = "albion"
+//! from = "camelot"
+//! to = "caer_ulfwych"
+//! interval_seconds = 1800
+//! defender_reward = { xp = 500, copper = 2000 }
+//! attacker_reward = { xp = 400, copper = 1500, items = ["stolen_supplies"] }
+//! ```
+//!
+//! Each route sends a caravan every `interval_seconds`. Caravans ask the AI
+//! stage's path queue for a route, so they follow whatever solver the
+//! navigation mesh installed, and they are an `AiTarget` on the players'
+//! faction, so monsters attack them like they would a player. Players of
+//! other realms damage them through [`CaravanDamageEvent`]. A caravan under
+//! attack halts until it has been left alone for `halt_seconds`.
+//!
+//! Players of the caravan's realm who escorted it for long enough get the
+//! defender reward when it arrives; if it's destroyed, the players who did
+//! enough of the damage get the attacker reward instead.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::ai_stage::{AiAttackEvent, AiPath, AiPathQueue, AiTarget, PathRequest, PLAYER_FACTION};
+use crate::console::{
+    CommandPermission, ConsoleAppExt, ConsoleCommand, ConsoleCommandEvent, ConsoleOutputEvent,
+};
+use crate::content_packs::ContentPacks;
+use crate::{Character, Player};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TownDef {
+    pub id: String,
+    pub position: Vec3,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RewardDef {
+    #[serde(default)]
+    pub xp: u32,
+    #[serde(default)]
+    pub copper: u64,
+    #[serde(default)]
+    pub items: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaravanRouteDef {
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    /// Realm that owns the caravan; its players defend, the others raid.
+    pub realm: String,
+    pub from: String,
+    pub to: String,
+    #[serde(default = "default_interval_seconds")]
+    pub interval_seconds: f32,
+    /// Seconds after startup before the first departure; defaults to the interval.
+    #[serde(default)]
+    pub first_departure_seconds: Option<f32>,
+    #[serde(default = "default_caravan_speed")]
+    pub speed: f32,
+    #[serde(default = "default_caravan_health")]
+    pub health: f32,
+    #[serde(default)]
+    pub defender_reward: RewardDef,
+    #[serde(default)]
+    pub attacker_reward: RewardDef,
+}
+
+fn default_interval_seconds() -> f32 {
+    1800.0
+}
+
+fn default_caravan_speed() -> f32 {
+    3.5
+}
+
+fn default_caravan_health() -> f32 {
+    5000.0
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CaravanContentFile {
+    #[serde(default, rename = "town")]
+    pub towns: Vec<TownDef>,
+    #[serde(default, rename = "route")]
+    pub routes: Vec<CaravanRouteDef>,
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct CaravanConfig {
+    pub content_directory: PathBuf,
+    /// Players within this of a caravan count as escorting it.
+    pub escort_radius: f32,
+    /// Share of the trip a defender must have escorted to be rewarded.
+    pub min_escort_share: f32,
+    /// Share of the damage an attacker must have dealt to be rewarded.
+    pub min_damage_share: f32,
+    /// Damage per monster swing.
+    pub monster_hit_damage: f32,
+    /// A caravan moves on once it hasn't been hit for this long.
+    pub halt_seconds: f32,
+    /// Distance from the destination that counts as arrived.
+    pub arrival_distance: f32,
+}
+
+impl Default for CaravanConfig {
+    fn default() -> Self {
+        Self {
+            content_directory: PathBuf::from("content").join("caravans"),
+            escort_radius: 40.0,
+            min_escort_share: 0.5,
+            min_damage_share: 0.05,
+            monster_hit_damage: 25.0,
+            halt_seconds: 5.0,
+            arrival_distance: 4.0,
+        }
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct CaravanContent {
+    pub towns: HashMap<String, TownDef>,
+    pub routes: Vec<CaravanRouteDef>,
+}
+
+/// Elapsed seconds of each route's next departure.
+#[derive(Resource, Debug, Default)]
+pub struct CaravanSchedule {
+    pub next_departure: HashMap<String, f32>,
+}
+
+#[derive(Component, Debug, Clone)]
+#[require(AiPath)]
+pub struct Caravan {
+    pub route: String,
+    pub destination: Vec3,
+    pub speed: f32,
+    pub health: f32,
+    pub max_health: f32,
+    pub departed_at: f32,
+    /// Elapsed seconds of the last hit.
+    pub last_hit_at: Option<f32>,
+    /// Seconds each player has spent escorting it.
+    pub escorts: HashMap<Entity, f32>,
+    /// Damage each player has dealt to it.
+    pub attackers: HashMap<Entity, f32>,
+}
+
+impl Caravan {
+    pub fn is_halted(&self, now: f32, config: &CaravanConfig) -> bool {
+        self.last_hit_at.is_some_and(|at| now - at < config.halt_seconds)
+    }
+}
+
+/// Damage to a caravan from a player or ability; monster swings are
+/// picked up from the AI stage directly.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct CaravanDamageEvent {
+    pub caravan: Entity,
+    pub attacker: Option<Entity>,
+    pub amount: f32,
+}
+
+/// A caravan set out; the server announces it to the realm.
+#[derive(Event, Debug, Clone)]
+pub struct CaravanDepartedEvent {
+    pub caravan: Entity,
+    pub route: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaravanOutcome {
+    Arrived,
+    Destroyed,
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct CaravanFinishedEvent {
+    pub route: String,
+    pub outcome: CaravanOutcome,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaravanRole {
+    Defender,
+    Attacker,
+}
+
+/// Progression and inventory hand out `reward`.
+#[derive(Event, Debug, Clone)]
+pub struct CaravanRewardEvent {
+    pub character: Entity,
+    pub route: String,
+    pub role: CaravanRole,
+    pub reward: RewardDef,
+}
+
+pub struct CaravanPlugin;
+
+impl Plugin for CaravanPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CaravanConfig>()
+            .init_resource::<CaravanContent>()
+            .init_resource::<CaravanSchedule>()
+            .init_resource::<AiPathQueue>()
+            .add_event::<AiAttackEvent>()
+            .add_event::<CaravanDamageEvent>()
+            .add_event::<CaravanDepartedEvent>()
+            .add_event::<CaravanFinishedEvent>()
+            .add_event::<CaravanRewardEvent>()
+            .add_console_command(
+                ConsoleCommand::new("caravan", "Lists caravans on the road, or sends one out now")
+                    .usage("[route id]")
+                    .permission(CommandPermission::Dev),
+            )
+            .add_systems(Startup, load_caravans)
+            .add_systems(
+                Update,
+                (
+                    dispatch_caravans,
+                    move_caravans,
+                    track_escorts,
+                    damage_caravans,
+                    finish_caravans,
+                    caravan_command,
+                )
+                    .chain(),
+            );
+    }
+}
+
+fn load_caravans(config: Res<CaravanConfig>, packs: Res<ContentPacks>, mut content: ResMut<CaravanContent>) {
+    for path in packs.files(&config.content_directory, "toml") {
+        let parsed = std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| toml::from_str::<CaravanContentFile>(&text).map_err(|e| e.to_string()));
+
+        match parsed {
+            Ok(file) => {
+                for town in file.towns {
+                    content.towns.insert(town.id.clone(), town);
+                }
+                content.routes.extend(file.routes);
+            }
+            Err(e) => warn!("Invalid caravan file {:?}: {}", path, e),
+        }
+    }
+    let towns = &content.towns;
+    for route in content.routes.iter().filter(|r| !towns.contains_key(&r.from) || !towns.contains_key(&r.to)) {
+        warn!("Caravan route '{}' runs between unknown towns '{}' and '{}'", route.id, route.from, route.to);
+    }
+
+    info!("Caravans: {} routes between {} towns loaded", content.routes.len(), content.towns.len());
+}
+
+/// Spawns a caravan for `route` at its starting town and asks for a path.
+fn send_caravan(
+    commands: &mut Commands,
+    queue: &mut AiPathQueue,
+    content: &CaravanContent,
+    route: &CaravanRouteDef,
+    now: f32,
+) -> Option<Entity> {
+    let from = content.towns.get(&route.from)?.position;
+    let to = content.towns.get(&route.to)?.position;
+    let caravan = commands
+        .spawn((
+            Caravan {
+                route: route.id.clone(),
+                destination: to,
+                speed: route.speed,
+                health: route.health,
+                max_health: route.health,
+                departed_at: now,
+                last_hit_at: None,
+                escorts: HashMap::new(),
+                attackers: HashMap::new(),
+            },
+            AiTarget { faction: PLAYER_FACTION },
+            Transform::from_translation(from),
+            Name::new(if route.name.is_empty() { route.id.clone() } else { route.name.clone() }),
+        ))
+        .id();
+    queue.push(PathRequest { agent: caravan, from, to });
+    Some(caravan)
+}
+
+fn dispatch_caravans(
+    mut commands: Commands,
+    time: Res<Time>,
+    content: Res<CaravanContent>,
+    mut schedule: ResMut<CaravanSchedule>,
+    mut queue: ResMut<AiPathQueue>,
+    caravans: Query<&Caravan>,
+    mut departed: EventWriter<CaravanDepartedEvent>,
+) {
+    let now = time.elapsed_secs();
+    for route in &content.routes {
+        let next = *schedule
+            .next_departure
+            .entry(route.id.clone())
+            .or_insert_with(|| now + route.first_departure_seconds.unwrap_or(route.interval_seconds));
+        if now < next {
+            continue;
+        }
+        schedule.next_departure.insert(route.id.clone(), now + route.interval_seconds.max(1.0));
+        // One caravan per route on the road at a time
+        if caravans.iter().any(|c| c.route == route.id) {
+            continue;
+        }
+        if let Some(caravan) = send_caravan(&mut commands, &mut queue, &content, route, now) {
+            info!("Caravan {} departed {} for {}", route.id, route.from, route.to);
+            departed.send(CaravanDepartedEvent { caravan, route: route.id.clone() });
+        }
+    }
+}
+
+fn move_caravans(
+    time: Res<Time>,
+    config: Res<CaravanConfig>,
+    mut caravans: Query<(&Caravan, &mut AiPath, &mut Transform)>,
+) {
+    let now = time.elapsed_secs();
+    let dt = time.delta_secs();
+    for (caravan, mut path, mut transform) in &mut caravans {
+        if caravan.is_halted(now, &config) {
+            continue;
+        }
+        // No path found: head straight for the destination
+        if path.goal.is_some() && path.waypoints.is_empty() {
+            path.waypoints.push(caravan.destination);
+        }
+        let Some(waypoint) = path.waypoints.get(path.next).copied() else {
+            continue;
+        };
+        let offset = waypoint - transform.translation;
+        let step = caravan.speed * dt;
+        if offset.length() <= step {
+            transform.translation = waypoint;
+            path.next += 1;
+        } else {
+            let direction = offset.normalize();
+            transform.translation += direction * step;
+            let heading = direction.with_y(0.0).normalize_or_zero();
+            if heading != Vec3::ZERO {
+                transform.rotation = Quat::from_rotation_arc(Vec3::NEG_Z, heading);
+            }
+        }
+    }
+}
+
+fn realm_name(character: &Character) -> String {
+    format!("{:?}", character.realm).to_lowercase()
+}
+
+fn track_escorts(
+    time: Res<Time>,
+    config: Res<CaravanConfig>,
+    content: Res<CaravanContent>,
+    players: Query<(Entity, &GlobalTransform, &Character), With<Player>>,
+    mut caravans: Query<(&mut Caravan, &Transform)>,
+) {
+    let dt = time.delta_secs();
+    for (mut caravan, transform) in &mut caravans {
+        let Some(route) = content.routes.iter().find(|r| r.id == caravan.route) else {
+            continue;
+        };
+        for (player, player_transform, character) in &players {
+            let near = player_transform.translation().distance(transform.translation) <= config.escort_radius;
+            if near && realm_name(character) == route.realm.to_lowercase() {
+                *caravan.escorts.entry(player).or_default() += dt;
+            }
+        }
+    }
+}
+
+fn damage_caravans(
+    time: Res<Time>,
+    config: Res<CaravanConfig>,
+    mut caravans: Query<&mut Caravan>,
+    mut monster_hits: EventReader<AiAttackEvent>,
+    mut hits: EventReader<CaravanDamageEvent>,
+) {
+    let now = time.elapsed_secs();
+    let monster_damage = monster_hits.read().map(|hit| CaravanDamageEvent {
+        caravan: hit.target,
+        attacker: None,
+        amount: config.monster_hit_damage,
+    });
+    for hit in monster_damage.chain(hits.read().copied()) {
+        let Ok(mut caravan) = caravans.get_mut(hit.caravan) else {
+            continue;
+        };
+        caravan.health = (caravan.health - hit.amount).max(0.0);
+        caravan.last_hit_at = Some(now);
+        if let Some(attacker) = hit.attacker {
+            *caravan.attackers.entry(attacker).or_default() += hit.amount;
+        }
+    }
+}
+
+fn finish_caravans(
+    mut commands: Commands,
+    time: Res<Time>,
+    config: Res<CaravanConfig>,
+    content: Res<CaravanContent>,
+    caravans: Query<(Entity, &Caravan, &Transform)>,
+    mut finished: EventWriter<CaravanFinishedEvent>,
+    mut rewards: EventWriter<CaravanRewardEvent>,
+) {
+    let now = time.elapsed_secs();
+    for (entity, caravan, transform) in &caravans {
+        let outcome = if caravan.health <= 0.0 {
+            CaravanOutcome::Destroyed
+        } else if transform.translation.distance(caravan.destination) <= config.arrival_distance {
+            CaravanOutcome::Arrived
+        } else {
+            continue;
+        };
+        commands.entity(entity).despawn_recursive();
+        finished.send(CaravanFinishedEvent { route: caravan.route.clone(), outcome });
+        let Some(route) = content.routes.iter().find(|r| r.id == caravan.route) else {
+            continue;
+        };
+
+        let (role, reward, earned): (CaravanRole, &RewardDef, Vec<Entity>) = match outcome {
+            CaravanOutcome::Arrived => {
+                let trip = (now - caravan.departed_at).max(1.0);
+                let escorts = caravan.escorts.iter().filter(|(_, seconds)| **seconds / trip >= config.min_escort_share);
+                (CaravanRole::Defender, &route.defender_reward, escorts.map(|(e, _)| *e).collect())
+            }
+            CaravanOutcome::Destroyed => {
+                let total: f32 = caravan.attackers.values().sum();
+                let attackers = caravan
+                    .attackers
+                    .iter()
+                    .filter(|(_, damage)| total > 0.0 && **damage / total >= config.min_damage_share);
+                (CaravanRole::Attacker, &route.attacker_reward, attackers.map(|(e, _)| *e).collect())
+            }
+        };
+        info!("Caravan {} {:?}; rewarding {} {:?}s", route.id, outcome, earned.len(), role);
+        for character in earned {
+            rewards.send(CaravanRewardEvent {
+                character,
+                route: route.id.clone(),
+                role,
+                reward: reward.clone(),
+            });
+        }
+    }
+}
+
+fn caravan_command(
+    mut commands: Commands,
+    time: Res<Time>,
+    content: Res<CaravanContent>,
+    mut queue: ResMut<AiPathQueue>,
+    caravans: Query<(&Caravan, &Transform)>,
+    mut events: EventReader<ConsoleCommandEvent>,
+    (mut output, mut departed): (EventWriter<ConsoleOutputEvent>, EventWriter<CaravanDepartedEvent>),
+) {
+    for event in events.read() {
+        if event.name != "caravan" {
+            continue;
+        }
+        let result = match event.args.first() {
+            None => {
+                let on_road = caravans.iter().len();
+                let mut lines = vec![format!("{} routes, {} caravans on the road", content.routes.len(), on_road)];
+                for (caravan, transform) in &caravans {
+                    lines.push(format!(
+                        "  {}: {:.0}/{:.0} health, {:.0}m to go, {} escorts, {} attackers",
+                        caravan.route,
+                        caravan.health,
+                        caravan.max_health,
+                        transform.translation.distance(caravan.destination),
+                        caravan.escorts.len(),
+                        caravan.attackers.len()
+                    ));
+                }
+                Ok(lines.join("\n"))
+            }
+            Some(id) => match content.routes.iter().find(|r| &r.id == id) {
+                Some(route) => match send_caravan(&mut commands, &mut queue, &content, route, time.elapsed_secs()) {
+                    Some(caravan) => {
+                        departed.send(CaravanDepartedEvent { caravan, route: route.id.clone() });
+                        Ok(format!("Sent {} from {} to {}", route.id, route.from, route.to))
+                    }
+                    None => Err(format!("route '{}' has unknown towns", id)),
+                },
+                None => Err(format!("unknown route '{}'", id)),
+            },
+        };
+        output.send(match result {
+            Ok(text) => event.reply(text),
+            Err(text) => event.error(format!("{}: {}", event.name, text)),
+        });
+    }
+}
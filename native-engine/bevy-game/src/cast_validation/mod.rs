@@ -0,0 +1,342 @@
+//! Ability casts decided by the server. The client no longer says "I cast
+//! Fireball and it hit": it asks.
+//!
+//! On the client a [`CastAttemptEvent`] becomes a [`CastRequest`] in
+//! [`CastRequestOutbox`], for the match socket to send with
+//! [`CAST_OP_CODE`]. The ability bar shows the cooldown at once
+//! ([`PredictedCooldowns`]) so the button doesn't feel laggy, but nothing
+//! happens until the server's [`CastReply`] comes back. The reply accepts or
+//! denies the cast and carries the caster's real cooldowns, which replace
+//! the predicted ones, so a denied cast gives its cooldown back and a client
+//! that ran its timers fast is pulled back in line.
+//!
+//! The server side is in [`server`]: it checks cooldowns, range, resources
+//! and line of sight against its own state and only then applies the
+//! ability.
+
+mod server;
+
+pub use server::*;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::database::{AbilityResource, GameDatabase};
+use crate::world_clock::WorldClock;
+
+/// Match data op code for [`CastRequest`] and [`CastReply`].
+pub const CAST_OP_CODE: i64 = 23;
+
+/// Asks the server to cast an ability.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CastRequest {
+    /// Numbers the caster's requests so replies can be matched up.
+    pub sequence: u32,
+    pub ability: String,
+    /// Network id of the target; `None` targets the caster.
+    #[serde(default)]
+    pub target: Option<String>,
+}
+
+/// Why the server refused a cast.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum CastDenial {
+    UnknownAbility,
+    NotLearned,
+    OnCooldown { remaining: f32 },
+    GlobalCooldown { remaining: f32 },
+    NotEnoughResource { resource: AbilityResource, have: f32, cost: f32 },
+    NoTarget,
+    OutOfRange { distance: f32, range: f32 },
+    NoLineOfSight,
+    TooManyRequests,
+}
+
+impl CastDenial {
+    /// What the player is told.
+    pub fn message(&self) -> String {
+        match self {
+            CastDenial::UnknownAbility => "That ability doesn't exist".to_string(),
+            CastDenial::NotLearned => "You haven't learned that ability".to_string(),
+            CastDenial::OnCooldown { remaining } => format!("Not ready yet ({:.1}s)", remaining),
+            CastDenial::GlobalCooldown { .. } => "Not ready yet".to_string(),
+            CastDenial::NotEnoughResource { resource, .. } => match resource {
+                AbilityResource::None => "Not enough resources".to_string(),
+                AbilityResource::Mana => "Not enough mana".to_string(),
+                AbilityResource::Rage => "Not enough rage".to_string(),
+                AbilityResource::Energy => "Not enough energy".to_string(),
+            },
+            CastDenial::NoTarget => "You have no target".to_string(),
+            CastDenial::OutOfRange { .. } => "Out of range".to_string(),
+            CastDenial::NoLineOfSight => "Target not in line of sight".to_string(),
+            CastDenial::TooManyRequests => "You can't do that yet".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CooldownState {
+    pub ability: String,
+    /// Seconds left when the reply was sent.
+    pub remaining: f32,
+}
+
+/// The server's answer to a [`CastRequest`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CastReply {
+    pub sequence: u32,
+    pub ability: String,
+    /// `None` when the cast went through.
+    #[serde(default)]
+    pub denial: Option<CastDenial>,
+    /// Every cooldown the caster has running.
+    #[serde(default)]
+    pub cooldowns: Vec<CooldownState>,
+    #[serde(default)]
+    pub global_cooldown: f32,
+}
+
+/// The player wants to cast `ability`.
+#[derive(Event, Debug, Clone)]
+pub struct CastAttemptEvent {
+    pub ability: String,
+    /// Network id of the target; `None` targets the player.
+    pub target: Option<String>,
+}
+
+/// A [`CastReply`] arrived.
+#[derive(Event, Debug, Clone)]
+pub struct CastReplyReceived(pub CastReply);
+
+/// The server accepted a cast of ours.
+#[derive(Event, Debug, Clone)]
+pub struct CastConfirmedEvent {
+    pub sequence: u32,
+    pub ability: String,
+}
+
+/// A cast of ours was refused, by the server or already by the prediction.
+#[derive(Event, Debug, Clone)]
+pub struct CastDeniedEvent {
+    pub ability: String,
+    pub denial: CastDenial,
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct CastRequestOutbox {
+    pending: Vec<CastRequest>,
+}
+
+impl CastRequestOutbox {
+    pub fn drain(&mut self) -> Vec<CastRequest> {
+        std::mem::take(&mut self.pending)
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct CastValidationConfig {
+    /// Seconds of the global cooldown.
+    pub global_cooldown: f32,
+    /// Range of abilities with `range = 0`.
+    pub melee_range: f32,
+    /// Extra meters allowed, since the target moved while the request was
+    /// on its way.
+    pub range_tolerance: f32,
+    /// A cooldown this close to done counts as done, for the same reason.
+    pub cooldown_tolerance: f32,
+    pub line_of_sight: bool,
+    /// Height above the feet that line of sight is checked from and to.
+    pub eye_height: f32,
+    /// Requests per second a caster may send before the rest are refused.
+    pub max_requests_per_second: usize,
+    /// Seconds the client waits for a reply before giving up on a request.
+    pub reply_timeout_seconds: f32,
+}
+
+impl Default for CastValidationConfig {
+    fn default() -> Self {
+        Self {
+            global_cooldown: 1.5,
+            melee_range: 5.0,
+            range_tolerance: 1.5,
+            cooldown_tolerance: 0.15,
+            line_of_sight: true,
+            eye_height: 1.6,
+            max_requests_per_second: 10,
+            reply_timeout_seconds: 5.0,
+        }
+    }
+}
+
+struct PendingCast {
+    ability: String,
+    sent_at: f64,
+    /// Predicted cooldowns as they were before this request.
+    previous_ready_at: Option<f64>,
+    previous_global_ready_at: f64,
+}
+
+/// The client's view of its cooldowns: predicted when a request goes out,
+/// replaced by the server's numbers when the reply comes in. Times are
+/// world clock seconds.
+#[derive(Resource, Default)]
+pub struct PredictedCooldowns {
+    ready_at: HashMap<String, f64>,
+    global_ready_at: f64,
+    next_sequence: u32,
+    pending: BTreeMap<u32, PendingCast>,
+}
+
+impl PredictedCooldowns {
+    pub fn remaining(&self, ability: &str, now: f64) -> f32 {
+        self.ready_at.get(ability).map_or(0.0, |ready_at| (ready_at - now).max(0.0) as f32)
+    }
+
+    pub fn global_remaining(&self, now: f64) -> f32 {
+        (self.global_ready_at - now).max(0.0) as f32
+    }
+
+    /// Requests still waiting for the server.
+    pub fn awaiting_reply(&self) -> usize {
+        self.pending.len()
+    }
+}
+
+pub struct CastValidationPlugin;
+
+impl Plugin for CastValidationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CastValidationConfig>()
+            .init_resource::<CastRequestOutbox>()
+            .init_resource::<PredictedCooldowns>()
+            .init_resource::<WorldClock>()
+            .add_event::<CastAttemptEvent>()
+            .add_event::<CastReplyReceived>()
+            .add_event::<CastConfirmedEvent>()
+            .add_event::<CastDeniedEvent>()
+            .add_systems(Update, (request_casts, apply_cast_replies).chain());
+    }
+}
+
+/// Sends attempts the prediction doesn't already rule out. Only cooldowns
+/// are predicted; range, resources and line of sight are the server's call.
+fn request_casts(
+    config: Res<CastValidationConfig>,
+    clock: Res<WorldClock>,
+    database: Res<GameDatabase>,
+    mut attempts: EventReader<CastAttemptEvent>,
+    mut cooldowns: ResMut<PredictedCooldowns>,
+    mut outbox: ResMut<CastRequestOutbox>,
+    mut denied: EventWriter<CastDeniedEvent>,
+) {
+    let now = clock.now();
+    for attempt in attempts.read() {
+        let Some(def) = database.abilities.by_key(&attempt.ability) else {
+            denied.send(CastDeniedEvent {
+                ability: attempt.ability.clone(),
+                denial: CastDenial::UnknownAbility,
+            });
+            continue;
+        };
+        let remaining = cooldowns.remaining(&def.id, now);
+        let global = cooldowns.global_remaining(now);
+        let denial = if remaining > 0.0 {
+            Some(CastDenial::OnCooldown { remaining })
+        } else if def.triggers_global_cooldown && global > 0.0 {
+            Some(CastDenial::GlobalCooldown { remaining: global })
+        } else {
+            None
+        };
+        if let Some(denial) = denial {
+            denied.send(CastDeniedEvent { ability: def.id.clone(), denial });
+            continue;
+        }
+
+        let sequence = cooldowns.next_sequence;
+        cooldowns.next_sequence = sequence.wrapping_add(1);
+        let pending = PendingCast {
+            ability: def.id.clone(),
+            sent_at: now,
+            previous_ready_at: cooldowns.ready_at.get(&def.id).copied(),
+            previous_global_ready_at: cooldowns.global_ready_at,
+        };
+        cooldowns.pending.insert(sequence, pending);
+        if def.cooldown > 0.0 {
+            cooldowns.ready_at.insert(def.id.clone(), now + def.cooldown as f64);
+        }
+        if def.triggers_global_cooldown {
+            cooldowns.global_ready_at = now + config.global_cooldown as f64;
+        }
+        outbox.pending.push(CastRequest {
+            sequence,
+            ability: def.id.clone(),
+            target: attempt.target.clone(),
+        });
+    }
+}
+
+/// Takes the server's cooldowns over the predicted ones. Requests that
+/// never got an answer are forgotten and their predicted cooldowns undone.
+fn apply_cast_replies(
+    config: Res<CastValidationConfig>,
+    clock: Res<WorldClock>,
+    mut replies: EventReader<CastReplyReceived>,
+    mut cooldowns: ResMut<PredictedCooldowns>,
+    mut confirmed: EventWriter<CastConfirmedEvent>,
+    mut denied: EventWriter<CastDeniedEvent>,
+) {
+    let now = clock.now();
+    for CastReplyReceived(reply) in replies.read() {
+        cooldowns.pending.remove(&reply.sequence);
+        cooldowns.ready_at = reply
+            .cooldowns
+            .iter()
+            .map(|c| (c.ability.clone(), now + c.remaining as f64))
+            .collect();
+        cooldowns.global_ready_at = now + reply.global_cooldown as f64;
+        match &reply.denial {
+            None => {
+                confirmed.send(CastConfirmedEvent {
+                    sequence: reply.sequence,
+                    ability: reply.ability.clone(),
+                });
+            }
+            Some(denial) => {
+                info!("Cast of {} denied: {:?}", reply.ability, denial);
+                denied.send(CastDeniedEvent {
+                    ability: reply.ability.clone(),
+                    denial: denial.clone(),
+                });
+            }
+        }
+    }
+
+    let timeout = config.reply_timeout_seconds as f64;
+    let expired: Vec<u32> = cooldowns
+        .pending
+        .iter()
+        .filter(|(_, pending)| now - pending.sent_at > timeout)
+        .map(|(sequence, _)| *sequence)
+        .collect();
+    for sequence in expired {
+        let Some(pending) = cooldowns.pending.remove(&sequence) else {
+            continue;
+        };
+        warn!("No reply to cast {} of {}; dropping it", sequence, pending.ability);
+        match pending.previous_ready_at {
+            Some(ready_at) => cooldowns.ready_at.insert(pending.ability, ready_at),
+            None => cooldowns.ready_at.remove(&pending.ability),
+        };
+        cooldowns.global_ready_at = cooldowns.global_ready_at.min(pending.previous_global_ready_at);
+    }
+}
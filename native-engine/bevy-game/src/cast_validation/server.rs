@@ -0,0 +1,278 @@
+//! The server's half: every cast request is checked against the server's
+//! own state before anything happens.
+//!
+//! The match socket hands requests over as [`CastRequestReceived`], with the
+//! caster and target already resolved to entities. Each character gets a
+//! [`CasterState`] holding its authoritative cooldowns and resource pools
+//! (the combat systems keep the pools in step with regeneration). Checks
+//! run in order: request rate, ability, learned, cooldown, global cooldown,
+//! resource, target, range and line of sight, with a little slack on
+//! cooldowns and range for latency.
+//!
+//! An accepted cast starts its cooldowns, spends its cost and goes out as a
+//! [`CastAcceptedEvent`] for the combat systems to apply; nothing else deals
+//! damage or heals on a client's word. Either way a [`CastReply`] with the
+//! caster's real cooldowns goes into [`CastReplyOutbox`], and denials are
+//! also sent as [`CastRejectedEvent`] so repeat offenders can be flagged.
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use super::{CastDenial, CastReply, CastRequest, CastValidationConfig, CooldownState};
+use crate::database::{AbilityDef, AbilityResource, GameDatabase};
+use crate::world_clock::WorldClock;
+use crate::Character;
+
+/// A client asked to cast.
+#[derive(Event, Debug, Clone)]
+pub struct CastRequestReceived {
+    pub caster: Entity,
+    /// `None` when the request named no target, or one that isn't here.
+    pub target: Option<Entity>,
+    pub request: CastRequest,
+}
+
+/// What the server knows about a character's casting. Times are world
+/// clock seconds.
+#[derive(Component, Debug, Clone, Default)]
+pub struct CasterState {
+    /// Ability id to when it's ready again.
+    pub ready_at: HashMap<String, f64>,
+    pub global_ready_at: f64,
+    pub resources: HashMap<AbilityResource, f32>,
+    /// Abilities the character may use; `None` allows any.
+    pub known: Option<HashSet<String>>,
+    /// Casts refused so far.
+    pub denials: u32,
+    recent_requests: VecDeque<f64>,
+}
+
+impl CasterState {
+    pub fn resource(&self, resource: AbilityResource) -> f32 {
+        match resource {
+            AbilityResource::None => f32::INFINITY,
+            resource => self.resources.get(&resource).copied().unwrap_or(0.0),
+        }
+    }
+
+    pub fn remaining(&self, ability: &str, now: f64) -> f64 {
+        self.ready_at.get(ability).map_or(0.0, |ready_at| (ready_at - now).max(0.0))
+    }
+
+    /// Running cooldowns, by ability id.
+    pub fn cooldowns(&self, now: f64) -> Vec<CooldownState> {
+        let mut cooldowns: Vec<CooldownState> = self
+            .ready_at
+            .iter()
+            .filter(|(_, ready_at)| **ready_at > now)
+            .map(|(ability, ready_at)| CooldownState {
+                ability: ability.clone(),
+                remaining: (ready_at - now) as f32,
+            })
+            .collect();
+        cooldowns.sort_by(|a, b| a.ability.cmp(&b.ability));
+        cooldowns
+    }
+}
+
+/// The server let a cast through; the combat systems apply it.
+#[derive(Event, Debug, Clone)]
+pub struct CastAcceptedEvent {
+    pub caster: Entity,
+    pub target: Entity,
+    pub ability: String,
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct CastRejectedEvent {
+    pub caster: Entity,
+    pub ability: String,
+    pub denial: CastDenial,
+}
+
+/// Replies for the match socket to send to each caster's client.
+#[derive(Resource, Debug, Default)]
+pub struct CastReplyOutbox {
+    pending: Vec<(Entity, CastReply)>,
+}
+
+impl CastReplyOutbox {
+    pub fn drain(&mut self) -> Vec<(Entity, CastReply)> {
+        std::mem::take(&mut self.pending)
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+/// Validates casts on the server. Clients use [`super::CastValidationPlugin`].
+pub struct CastAuthorityPlugin;
+
+impl Plugin for CastAuthorityPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CastValidationConfig>()
+            .init_resource::<CastReplyOutbox>()
+            .init_resource::<WorldClock>()
+            .add_event::<CastRequestReceived>()
+            .add_event::<CastAcceptedEvent>()
+            .add_event::<CastRejectedEvent>()
+            .add_systems(Update, (add_caster_state, validate_casts).chain());
+    }
+}
+
+fn add_caster_state(mut commands: Commands, characters: Query<Entity, (With<Character>, Without<CasterState>)>) {
+    for entity in &characters {
+        commands.entity(entity).insert(CasterState::default());
+    }
+}
+
+fn clear_line(rapier_context: &ReadRapierContext, (caster, target): (Entity, Entity), from: Vec3, to: Vec3) -> bool {
+    let Ok(context) = rapier_context.single() else {
+        return true;
+    };
+    let offset = to - from;
+    let distance = offset.length();
+    if distance < 1e-3 {
+        return true;
+    }
+    let filter = QueryFilter::default()
+        .exclude_sensors()
+        .exclude_dynamic()
+        .exclude_rigid_body(caster)
+        .exclude_rigid_body(target);
+    context.cast_ray(from, offset / distance, distance, true, filter).is_none()
+}
+
+/// `positions` are the caster's and target's eyes, `None` when the target
+/// is gone.
+fn check_cast<'a>(
+    config: &CastValidationConfig,
+    database: &'a GameDatabase,
+    state: &mut CasterState,
+    now: f64,
+    request: &CastRequest,
+    positions: Option<(Vec3, Vec3)>,
+    line_of_sight: impl FnOnce(Vec3, Vec3) -> bool,
+) -> Result<&'a AbilityDef, CastDenial> {
+    while state.recent_requests.front().is_some_and(|at| now - at > 1.0) {
+        state.recent_requests.pop_front();
+    }
+    state.recent_requests.push_back(now);
+    if state.recent_requests.len() > config.max_requests_per_second {
+        return Err(CastDenial::TooManyRequests);
+    }
+
+    let def = database.abilities.by_key(&request.ability).ok_or(CastDenial::UnknownAbility)?;
+    if state.known.as_ref().is_some_and(|known| !known.contains(&def.id)) {
+        return Err(CastDenial::NotLearned);
+    }
+    let tolerance = config.cooldown_tolerance as f64;
+    let remaining = state.remaining(&def.id, now);
+    if remaining > tolerance {
+        return Err(CastDenial::OnCooldown { remaining: remaining as f32 });
+    }
+    let global = state.global_ready_at - now;
+    if def.triggers_global_cooldown && global > tolerance {
+        return Err(CastDenial::GlobalCooldown { remaining: global as f32 });
+    }
+    let have = state.resource(def.resource);
+    if have < def.cost {
+        return Err(CastDenial::NotEnoughResource {
+            resource: def.resource,
+            have,
+            cost: def.cost,
+        });
+    }
+
+    let Some((from, to)) = positions else {
+        return Err(CastDenial::NoTarget);
+    };
+    if request.target.is_none() && def.damage[1] > 0.0 {
+        return Err(CastDenial::NoTarget);
+    }
+    let range = if def.range > 0.0 { def.range } else { config.melee_range };
+    let distance = from.distance(to);
+    if distance > range + config.range_tolerance {
+        return Err(CastDenial::OutOfRange { distance, range });
+    }
+    if config.line_of_sight && !line_of_sight(from, to) {
+        return Err(CastDenial::NoLineOfSight);
+    }
+    Ok(def)
+}
+
+fn validate_casts(
+    (config, clock, database): (Res<CastValidationConfig>, Res<WorldClock>, Res<GameDatabase>),
+    rapier_context: ReadRapierContext,
+    mut requests: EventReader<CastRequestReceived>,
+    mut casters: Query<(&mut CasterState, &GlobalTransform)>,
+    transforms: Query<&GlobalTransform>,
+    mut outbox: ResMut<CastReplyOutbox>,
+    (mut accepted, mut rejected): (EventWriter<CastAcceptedEvent>, EventWriter<CastRejectedEvent>),
+) {
+    let now = clock.now();
+    let eye = Vec3::Y * config.eye_height;
+    for received in requests.read() {
+        let Ok((mut state, caster_transform)) = casters.get_mut(received.caster) else {
+            continue;
+        };
+        let request = &received.request;
+        let from = caster_transform.translation() + eye;
+        let target = received.target.unwrap_or(received.caster);
+        let positions = match (received.target, &request.target) {
+            // Named a target that isn't here
+            (None, Some(_)) => None,
+            (None, None) => Some((from, from)),
+            (Some(target), _) => transforms.get(target).ok().map(|t| (from, t.translation() + eye)),
+        };
+
+        let verdict = check_cast(&config, &database, &mut state, now, request, positions, |from, to| {
+            clear_line(&rapier_context, (received.caster, target), from, to)
+        });
+        let denial = match verdict {
+            Ok(def) => {
+                if def.cooldown > 0.0 {
+                    state.ready_at.insert(def.id.clone(), now + def.cooldown as f64);
+                }
+                if def.triggers_global_cooldown {
+                    state.global_ready_at = now + config.global_cooldown as f64;
+                }
+                if def.resource != AbilityResource::None {
+                    *state.resources.entry(def.resource).or_default() -= def.cost;
+                }
+                accepted.send(CastAcceptedEvent {
+                    caster: received.caster,
+                    target,
+                    ability: def.id.clone(),
+                });
+                None
+            }
+            Err(denial) => {
+                state.denials += 1;
+                rejected.send(CastRejectedEvent {
+                    caster: received.caster,
+                    ability: request.ability.clone(),
+                    denial: denial.clone(),
+                });
+                Some(denial)
+            }
+        };
+
+        outbox.pending.push((
+            received.caster,
+            CastReply {
+                sequence: request.sequence,
+                ability: request.ability.clone(),
+                denial,
+                cooldowns: state.cooldowns(now),
+                global_cooldown: (state.global_ready_at - now).max(0.0) as f32,
+            },
+        ));
+    }
+}
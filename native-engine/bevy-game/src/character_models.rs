@@ -0,0 +1,439 @@
+//! glTF character spawning. Callers ask [`CharacterModelSpawner`] for a
+//! model by library id (or asset path) and a position; once the model is
+//! loaded it is spawned standing on the terrain, with its collider and idle
+//! animation set up, and a [`CharacterModelSpawnedEvent`] reports the entity.
+//!
+//! Models are described in `content/characters/*.toml`:
+//!
+//! ```toml
+//! [[model]]
+//! id = "mutant"
+//! path = "models/mutant.glb"
+//! scale = 3.0
+//! idle_animation = "Idle"
+//! collider = { radius = 0.6, half_height = 0.9 }
+//! ```
+
+use bevy::gltf::Gltf;
+use bevy::prelude::*;
+use bevy::scene::SceneInstanceReady;
+use bevy_rapier3d::prelude::*;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::authoring::terrain_sculpt::TerrainEdits;
+use crate::content_packs::ContentPacks;
+use crate::preload::{PreloadCache, PreloadStatus};
+use crate::{LandmarkRegistry, TerrainChunkCache, TerrainConfig};
+
+/// Upright capsule from the model's feet.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct CapsuleDef {
+    pub radius: f32,
+    pub half_height: f32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CharacterModelDef {
+    pub id: String,
+    pub path: String,
+    #[serde(default = "default_model_scale")]
+    pub scale: f32,
+    /// Named glTF animation looped once the scene is ready; the first
+    /// animation in the file when omitted.
+    #[serde(default)]
+    pub idle_animation: Option<String>,
+    #[serde(default)]
+    pub collider: Option<CapsuleDef>,
+}
+
+fn default_model_scale() -> f32 {
+    1.0
+}
+
+impl CharacterModelDef {
+    /// A model that isn't in the library, loaded straight from `path`.
+    pub fn from_path(path: &str) -> Self {
+        Self {
+            id: path.to_string(),
+            path: path.to_string(),
+            scale: default_model_scale(),
+            idle_animation: None,
+            collider: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct CharacterModelContentFile {
+    #[serde(default, rename = "model")]
+    pub models: Vec<CharacterModelDef>,
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct CharacterModelConfig {
+    pub content_directory: PathBuf,
+    /// Frames between attempts to snap models that spawned before their
+    /// terrain chunk was cached.
+    pub resync_interval_frames: u32,
+}
+
+impl Default for CharacterModelConfig {
+    fn default() -> Self {
+        Self {
+            content_directory: PathBuf::from("content").join("characters"),
+            resync_interval_frames: 30,
+        }
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct CharacterModelLibrary {
+    pub models: HashMap<String, CharacterModelDef>,
+}
+
+impl Default for CharacterModelLibrary {
+    fn default() -> Self {
+        let mutant = CharacterModelDef {
+            id: "mutant".to_string(),
+            path: "models/mutant.glb".to_string(),
+            scale: 3.0,
+            idle_animation: None,
+            collider: Some(CapsuleDef {
+                radius: 0.6,
+                half_height: 0.9,
+            }),
+        };
+        Self {
+            models: HashMap::from([(mutant.id.clone(), mutant)]),
+        }
+    }
+}
+
+impl CharacterModelLibrary {
+    /// The library entry for `model`, or a plain model at that path.
+    pub fn resolve(&self, model: &str) -> CharacterModelDef {
+        self.models
+            .get(model)
+            .cloned()
+            .unwrap_or_else(|| CharacterModelDef::from_path(model))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CharacterModelRequest {
+    /// Library id, or an asset path for models without an entry.
+    pub model: String,
+    /// Where to stand. With `snap_to_terrain` only x and z are used.
+    pub position: Vec3,
+    pub yaw: f32,
+    pub snap_to_terrain: bool,
+    pub name: Option<String>,
+}
+
+impl CharacterModelRequest {
+    pub fn new(model: impl Into<String>, position: Vec3) -> Self {
+        Self {
+            model: model.into(),
+            position,
+            yaw: 0.0,
+            snap_to_terrain: true,
+            name: None,
+        }
+    }
+
+    pub fn with_yaw(mut self, yaw: f32) -> Self {
+        self.yaw = yaw;
+        self
+    }
+
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Spawn at `position` as given.
+    pub fn unsnapped(mut self) -> Self {
+        self.snap_to_terrain = false;
+        self
+    }
+}
+
+/// Identifies a request, for matching its completion event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CharacterTicket(pub u64);
+
+/// Requests waiting for their model to load.
+#[derive(Resource, Default)]
+pub struct CharacterModelSpawner {
+    next_ticket: u64,
+    pending: Vec<(CharacterTicket, CharacterModelRequest)>,
+}
+
+impl CharacterModelSpawner {
+    pub fn spawn(&mut self, request: CharacterModelRequest) -> CharacterTicket {
+        self.next_ticket += 1;
+        let ticket = CharacterTicket(self.next_ticket);
+        self.pending.push((ticket, request));
+        ticket
+    }
+
+    pub fn cancel(&mut self, ticket: CharacterTicket) -> bool {
+        let before = self.pending.len();
+        self.pending.retain(|(t, _)| *t != ticket);
+        self.pending.len() != before
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+/// On the root of a spawned character. The scene is a child, scaled by the
+/// model definition, so the root's transform stays at the feet.
+#[derive(Component, Debug, Clone)]
+pub struct CharacterModel {
+    pub model: String,
+    pub ticket: CharacterTicket,
+}
+
+/// Spawned on a fallback height; moved onto the cached terrain chunk once
+/// it loads.
+#[derive(Component, Debug)]
+pub struct AwaitingTerrainSnap;
+
+#[derive(Component)]
+struct CharacterScene {
+    gltf: Handle<Gltf>,
+    idle_animation: Option<String>,
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct CharacterModelSpawnedEvent {
+    pub ticket: CharacterTicket,
+    pub entity: Entity,
+    pub model: String,
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct CharacterModelFailedEvent {
+    pub ticket: CharacterTicket,
+    pub model: String,
+    pub error: String,
+}
+
+pub struct CharacterModelPlugin;
+
+impl Plugin for CharacterModelPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CharacterModelConfig>()
+            .init_resource::<CharacterModelLibrary>()
+            .init_resource::<CharacterModelSpawner>()
+            .add_event::<CharacterModelSpawnedEvent>()
+            .add_event::<CharacterModelFailedEvent>()
+            .add_systems(Startup, load_character_models)
+            .add_systems(Update, (spawn_loaded_characters, snap_characters_to_terrain).chain());
+    }
+}
+
+fn load_character_models(
+    config: Res<CharacterModelConfig>,
+    packs: Res<ContentPacks>,
+    mut library: ResMut<CharacterModelLibrary>,
+) {
+    for path in packs.files(&config.content_directory, "toml") {
+        let parsed = std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| toml::from_str::<CharacterModelContentFile>(&text).map_err(|e| e.to_string()));
+
+        match parsed {
+            Ok(file) => {
+                for model in file.models {
+                    library.models.insert(model.id.clone(), model);
+                }
+            }
+            Err(e) => warn!("Invalid character model file {:?}: {}", path, e),
+        }
+    }
+
+    info!("Character models: {} in library", library.models.len());
+}
+
+fn spawn_loaded_characters(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    gltf_assets: Res<Assets<Gltf>>,
+    library: Res<CharacterModelLibrary>,
+    mut preload: ResMut<PreloadCache>,
+    mut spawner: ResMut<CharacterModelSpawner>,
+    terrain_config: Res<TerrainConfig>,
+    chunk_cache: Res<TerrainChunkCache>,
+    terrain_edits: Res<TerrainEdits>,
+    mut landmarks: ResMut<LandmarkRegistry>,
+    mut spawned_events: EventWriter<CharacterModelSpawnedEvent>,
+    mut failed_events: EventWriter<CharacterModelFailedEvent>,
+) {
+    if spawner.pending.is_empty() {
+        return;
+    }
+
+    let mut failed = |ticket: CharacterTicket, model: &str, error: String| {
+        warn!("Character model '{}' not spawned: {}", model, error);
+        failed_events.send(CharacterModelFailedEvent {
+            ticket,
+            model: model.to_string(),
+            error,
+        });
+    };
+
+    let pending = std::mem::take(&mut spawner.pending);
+    for (ticket, request) in pending {
+        let def = library.resolve(&request.model);
+        let gltf_handle: Handle<Gltf> = preload.load(&asset_server, &def.path);
+        match preload.status(&asset_server, &def.path) {
+            Some(PreloadStatus::Loaded) => {}
+            Some(PreloadStatus::Failed) => {
+                failed(ticket, &request.model, format!("{} failed to load", def.path));
+                continue;
+            }
+            _ => {
+                spawner.pending.push((ticket, request));
+                continue;
+            }
+        }
+        let Some(scene) = gltf_assets.get(&gltf_handle).and_then(|gltf| gltf.scenes.first()) else {
+            failed(ticket, &request.model, format!("{} has no scenes", def.path));
+            continue;
+        };
+
+        let mut position = request.position;
+        let mut awaiting_snap = false;
+        if request.snap_to_terrain {
+            // The chunk cache matches the rendered mesh; without it, use the
+            // height function and correct once the chunk is cached
+            position.y = crate::systems::terrain::terrain_height_at_point(
+                position.x,
+                position.z,
+                &terrain_config,
+                &chunk_cache,
+            )
+            .unwrap_or_else(|| {
+                awaiting_snap = true;
+                crate::systems::terrain::terrain_height_at_with_features(
+                    position.x,
+                    position.z,
+                    &terrain_config,
+                    &mut landmarks,
+                ) + terrain_edits.height_delta(position.x, position.z)
+            });
+        }
+
+        let name = request.name.clone().unwrap_or_else(|| def.id.clone());
+        let mut root = commands.spawn((
+            Transform::from_translation(position).with_rotation(Quat::from_rotation_y(request.yaw)),
+            Visibility::default(),
+            Name::new(name),
+            CharacterModel {
+                model: def.id.clone(),
+                ticket,
+            },
+        ));
+        if awaiting_snap {
+            root.insert(AwaitingTerrainSnap);
+        }
+        if def.collider.is_some() {
+            root.insert(RigidBody::KinematicPositionBased);
+        }
+        root.with_children(|parent| {
+            parent
+                .spawn((
+                    SceneRoot(scene.clone()),
+                    Transform::from_scale(Vec3::splat(def.scale)),
+                    CharacterScene {
+                        gltf: gltf_handle.clone(),
+                        idle_animation: def.idle_animation.clone(),
+                    },
+                ))
+                .observe(start_idle_animation);
+            if let Some(capsule) = def.collider {
+                parent.spawn((
+                    Collider::capsule_y(capsule.half_height, capsule.radius),
+                    Transform::from_xyz(0.0, capsule.half_height + capsule.radius, 0.0),
+                ));
+            }
+        });
+
+        let entity = root.id();
+        info!("Spawned character '{}' at {:?}", def.id, position);
+        spawned_events.send(CharacterModelSpawnedEvent {
+            ticket,
+            entity,
+            model: def.id,
+        });
+    }
+}
+
+fn snap_characters_to_terrain(
+    mut commands: Commands,
+    config: Res<CharacterModelConfig>,
+    terrain_config: Res<TerrainConfig>,
+    chunk_cache: Res<TerrainChunkCache>,
+    mut characters: Query<(Entity, &mut Transform), With<AwaitingTerrainSnap>>,
+    mut frame: Local<u32>,
+) {
+    *frame = frame.wrapping_add(1);
+    if !frame.is_multiple_of(config.resync_interval_frames.max(1)) {
+        return;
+    }
+    for (entity, mut transform) in &mut characters {
+        let (x, z) = (transform.translation.x, transform.translation.z);
+        if let Some(height) = crate::systems::terrain::terrain_height_at_point(x, z, &terrain_config, &chunk_cache) {
+            if (height - transform.translation.y).abs() > 0.1 {
+                transform.translation.y = height;
+            }
+            commands.entity(entity).remove::<AwaitingTerrainSnap>();
+        }
+    }
+}
+
+/// Loops the idle clip on every `AnimationPlayer` the scene spawned.
+fn start_idle_animation(
+    trigger: Trigger<SceneInstanceReady>,
+    mut commands: Commands,
+    scenes: Query<&CharacterScene>,
+    children: Query<&Children>,
+    mut players: Query<&mut AnimationPlayer>,
+    gltf_assets: Res<Assets<Gltf>>,
+    mut graphs: ResMut<Assets<AnimationGraph>>,
+) {
+    let Ok(scene) = scenes.get(trigger.entity()) else {
+        return;
+    };
+    let Some(gltf) = gltf_assets.get(&scene.gltf) else {
+        return;
+    };
+    let clip = match &scene.idle_animation {
+        Some(name) => gltf.named_animations.get(name.as_str()),
+        None => gltf.animations.first(),
+    };
+    let Some(clip) = clip else {
+        if let Some(name) = &scene.idle_animation {
+            warn!("Character scene has no animation named '{}'", name);
+        }
+        return;
+    };
+
+    let (graph, index) = AnimationGraph::from_clip(clip.clone());
+    let graph = graphs.add(graph);
+    for entity in children.iter_descendants(trigger.entity()) {
+        if let Ok(mut player) = players.get_mut(entity) {
+            player.play(index).repeat();
+            commands.entity(entity).insert(AnimationGraphHandle(graph.clone()));
+        }
+    }
+}
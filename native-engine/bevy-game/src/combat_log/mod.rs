@@ -0,0 +1,297 @@
+//! Combat log. Combat sends a [`CombatLogEvent`] for every hit, heal, buff
+//! and death (environmental damage is picked up from the hazards); each is
+//! resolved to names, kept in [`CombatLog`] for the window, re-sent as a
+//! [`CombatRecordEvent`] for in-game parsers like the damage meter, and
+//! appended to `combat_logs/combat-<time>.jsonl`.
+//!
+//! The file is JSON lines. The first line is a header,
+//! `{"format":"combat-log","version":1,"started_at":<unix seconds>}`, and
+//! every later line is one [`CombatRecord`]:
+//!
+//! ```json
+//! {"t":1760601234.125,"kind":"damage","source":"Aelric","source_id":4294967301,
+//!  "target":"Dire Wolf","target_id":4294967342,"ability":"Fireball",
+//!  "amount":412.0,"overkill":0.0,"school":"fire","critical":true}
+//! ```
+//!
+//! `t` is unix seconds with millisecond precision, ids tell apart entities
+//! with the same name, and `source` is absent for the environment.
+
+mod ui;
+
+pub use ui::*;
+
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+
+use crate::hazards::{DamageSchool, HazardCause, HazardDamageEvent};
+use crate::Character;
+
+const FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CombatEventKind {
+    Damage,
+    Heal,
+    BuffApplied,
+    BuffRemoved,
+    Death,
+}
+
+impl CombatEventKind {
+    pub const ALL: [CombatEventKind; 5] = [
+        CombatEventKind::Damage,
+        CombatEventKind::Heal,
+        CombatEventKind::BuffApplied,
+        CombatEventKind::BuffRemoved,
+        CombatEventKind::Death,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            CombatEventKind::Damage => "Damage",
+            CombatEventKind::Heal => "Healing",
+            CombatEventKind::BuffApplied => "Buffs gained",
+            CombatEventKind::BuffRemoved => "Buffs faded",
+            CombatEventKind::Death => "Deaths",
+        }
+    }
+}
+
+/// Something that happened in combat, as combat systems report it.
+/// `amount` is damage or healing done; 0 for buffs and deaths.
+#[derive(Event, Debug, Clone)]
+pub struct CombatLogEvent {
+    pub kind: CombatEventKind,
+    /// `None` for the environment.
+    pub source: Option<Entity>,
+    pub target: Entity,
+    pub ability: String,
+    pub amount: f32,
+    /// Damage past what killed the target.
+    pub overkill: f32,
+    pub school: DamageSchool,
+    pub critical: bool,
+}
+
+impl CombatLogEvent {
+    pub fn damage(source: Option<Entity>, target: Entity, ability: impl Into<String>, amount: f32) -> Self {
+        Self {
+            kind: CombatEventKind::Damage,
+            source,
+            target,
+            ability: ability.into(),
+            amount,
+            overkill: 0.0,
+            school: DamageSchool::Physical,
+            critical: false,
+        }
+    }
+}
+
+/// One resolved line of the log, as written to disk.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CombatRecord {
+    /// Unix seconds.
+    pub t: f64,
+    pub kind: CombatEventKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_id: Option<u64>,
+    pub target: String,
+    pub target_id: u64,
+    pub ability: String,
+    pub amount: f32,
+    #[serde(default)]
+    pub overkill: f32,
+    pub school: DamageSchool,
+    #[serde(default)]
+    pub critical: bool,
+}
+
+/// A record was added to the log; parsers read these instead of the
+/// file.
+#[derive(Event, Debug, Clone)]
+pub struct CombatRecordEvent {
+    pub record: CombatRecord,
+    pub source: Option<Entity>,
+    pub target: Entity,
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct CombatLogConfig {
+    pub directory: PathBuf,
+    pub write_to_disk: bool,
+    /// Records kept in memory for the window.
+    pub capacity: usize,
+    pub flush_seconds: f32,
+}
+
+impl Default for CombatLogConfig {
+    fn default() -> Self {
+        Self {
+            directory: PathBuf::from("combat_logs"),
+            write_to_disk: true,
+            capacity: 5000,
+            flush_seconds: 2.0,
+        }
+    }
+}
+
+/// The most recent records, oldest first.
+#[derive(Resource, Debug, Default)]
+pub struct CombatLog {
+    pub records: VecDeque<CombatRecord>,
+    /// Records ever added, so readers can tell what's new after trimming.
+    pub total: u64,
+}
+
+/// The open log file; opened on the first record.
+#[derive(Resource, Default)]
+struct CombatLogFile {
+    writer: Option<BufWriter<File>>,
+    failed: bool,
+    since_flush: f32,
+}
+
+impl CombatLogFile {
+    fn open(&mut self, config: &CombatLogConfig, now: f64) -> Option<&mut BufWriter<File>> {
+        if self.writer.is_none() && !self.failed {
+            let path = config.directory.join(format!("combat-{}.jsonl", now as u64));
+            let opened = std::fs::create_dir_all(&config.directory).and_then(|_| File::create(&path));
+            match opened {
+                Ok(file) => {
+                    let mut writer = BufWriter::new(file);
+                    let header = serde_json::json!({
+                        "format": "combat-log",
+                        "version": FORMAT_VERSION,
+                        "started_at": now as u64,
+                    });
+                    let _ = writeln!(writer, "{}", header);
+                    info!("Combat log writing to {:?}", path);
+                    self.writer = Some(writer);
+                }
+                Err(e) => {
+                    warn!("Combat log off: failed to create {:?}: {}", path, e);
+                    self.failed = true;
+                }
+            }
+        }
+        self.writer.as_mut()
+    }
+}
+
+pub struct CombatLogPlugin;
+
+impl Plugin for CombatLogPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CombatLogConfig>()
+            .init_resource::<CombatLog>()
+            .init_resource::<CombatLogFile>()
+            .add_event::<CombatLogEvent>()
+            .add_event::<CombatRecordEvent>()
+            .add_event::<HazardDamageEvent>()
+            .add_systems(Update, (log_hazard_damage, record_combat_events, flush_combat_log).chain());
+    }
+}
+
+fn log_hazard_damage(mut hazards: EventReader<HazardDamageEvent>, mut events: EventWriter<CombatLogEvent>) {
+    for hazard in hazards.read() {
+        let ability = match &hazard.cause {
+            HazardCause::Falling { .. } => "Falling".to_string(),
+            HazardCause::Volume { trigger_id } => trigger_id.clone(),
+            HazardCause::Drowning => "Drowning".to_string(),
+        };
+        events.send(CombatLogEvent {
+            school: hazard.school,
+            ..CombatLogEvent::damage(None, hazard.target, ability, hazard.amount)
+        });
+    }
+}
+
+fn unix_now() -> f64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs_f64())
+        .unwrap_or(0.0)
+}
+
+fn record_combat_events(
+    config: Res<CombatLogConfig>,
+    mut log: ResMut<CombatLog>,
+    mut file: ResMut<CombatLogFile>,
+    names: Query<(Option<&Character>, Option<&Name>)>,
+    mut events: EventReader<CombatLogEvent>,
+    mut recorded: EventWriter<CombatRecordEvent>,
+) {
+    if events.is_empty() {
+        return;
+    }
+    let name_of = |entity: Entity| match names.get(entity) {
+        Ok((Some(character), _)) => character.name.clone(),
+        Ok((None, Some(name))) => name.as_str().to_string(),
+        _ => format!("{}", entity),
+    };
+    let now = unix_now();
+    // Millisecond precision is plenty for parsers and keeps lines short
+    let t = (now * 1000.0).round() / 1000.0;
+
+    for event in events.read() {
+        let record = CombatRecord {
+            t,
+            kind: event.kind,
+            source: event.source.map(name_of),
+            source_id: event.source.map(Entity::to_bits),
+            target: name_of(event.target),
+            target_id: event.target.to_bits(),
+            ability: event.ability.clone(),
+            amount: event.amount,
+            overkill: event.overkill,
+            school: event.school,
+            critical: event.critical,
+        };
+        if config.write_to_disk {
+            if let Some(writer) = file.open(&config, now) {
+                if let Ok(line) = serde_json::to_string(&record) {
+                    let _ = writeln!(writer, "{}", line);
+                }
+            }
+        }
+        log.records.push_back(record.clone());
+        log.total += 1;
+        recorded.send(CombatRecordEvent {
+            record,
+            source: event.source,
+            target: event.target,
+        });
+    }
+    while log.records.len() > config.capacity {
+        log.records.pop_front();
+    }
+}
+
+fn flush_combat_log(
+    time: Res<Time>,
+    config: Res<CombatLogConfig>,
+    mut file: ResMut<CombatLogFile>,
+    mut exits: EventReader<AppExit>,
+) {
+    let exiting = exits.read().count() > 0;
+    file.since_flush += time.delta_secs();
+    if file.since_flush < config.flush_seconds && !exiting {
+        return;
+    }
+    file.since_flush = 0.0;
+    if let Some(writer) = file.writer.as_mut() {
+        if let Err(e) = writer.flush() {
+            warn!("Failed to flush the combat log: {}", e);
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts, EguiPlugin};
+use std::collections::HashSet;
+
+use super::{CombatEventKind, CombatLog, CombatRecord};
+use crate::hazards::DamageSchool;
+use crate::input_map::{Action, ActionState};
+use crate::Player;
+
+/// Lines drawn at most; older matches are in the file.
+const MAX_SHOWN: usize = 500;
+
+#[derive(Resource, Debug)]
+pub struct CombatLogWindow {
+    pub open: bool,
+    pub kinds: HashSet<CombatEventKind>,
+    /// Matches source, target or ability, ignoring case.
+    pub search: String,
+    /// Only lines the local player is the source or target of.
+    pub mine_only: bool,
+}
+
+impl Default for CombatLogWindow {
+    fn default() -> Self {
+        Self {
+            open: false,
+            kinds: CombatEventKind::ALL.into_iter().collect(),
+            search: String::new(),
+            mine_only: true,
+        }
+    }
+}
+
+impl CombatLogWindow {
+    pub fn matches(&self, record: &CombatRecord, player: Option<u64>) -> bool {
+        if !self.kinds.contains(&record.kind) {
+            return false;
+        }
+        if self.mine_only && player.is_some_and(|p| record.source_id != Some(p) && record.target_id != p) {
+            return false;
+        }
+        let search = self.search.trim().to_lowercase();
+        search.is_empty()
+            || record.ability.to_lowercase().contains(&search)
+            || record.target.to_lowercase().contains(&search)
+            || record.source.as_ref().is_some_and(|s| s.to_lowercase().contains(&search))
+    }
+}
+
+/// The combat log window, toggled with L by default.
+pub struct CombatLogUiPlugin;
+
+impl Plugin for CombatLogUiPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugins(EguiPlugin);
+        }
+        app.init_resource::<CombatLogWindow>().add_systems(
+            Update,
+            (toggle_combat_log, combat_log_ui.run_if(|window: Res<CombatLogWindow>| window.open)).chain(),
+        );
+    }
+}
+
+fn toggle_combat_log(actions: Res<ActionState>, mut window: ResMut<CombatLogWindow>) {
+    if actions.just_pressed(Action::ToggleCombatLog) {
+        window.open = !window.open;
+    }
+}
+
+fn school_color(school: DamageSchool) -> egui::Color32 {
+    match school {
+        DamageSchool::Physical => egui::Color32::from_rgb(230, 230, 230),
+        DamageSchool::Fire => egui::Color32::from_rgb(255, 128, 64),
+        DamageSchool::Frost => egui::Color32::from_rgb(128, 200, 255),
+        DamageSchool::Nature => egui::Color32::from_rgb(120, 220, 100),
+        DamageSchool::Shadow => egui::Color32::from_rgb(170, 120, 230),
+        DamageSchool::Arcane => egui::Color32::from_rgb(240, 140, 240),
+        DamageSchool::Holy => egui::Color32::from_rgb(255, 230, 130),
+    }
+}
+
+fn describe(record: &CombatRecord) -> String {
+    let source = record.source.as_deref().unwrap_or("The environment");
+    let crit = if record.critical { " (critical)" } else { "" };
+    match record.kind {
+        CombatEventKind::Damage if record.overkill > 0.0 => format!(
+            "{}'s {} hits {} for {:.0}{} ({:.0} overkill)",
+            source, record.ability, record.target, record.amount, crit, record.overkill
+        ),
+        CombatEventKind::Damage => {
+            format!("{}'s {} hits {} for {:.0}{}", source, record.ability, record.target, record.amount, crit)
+        }
+        CombatEventKind::Heal => {
+            format!("{}'s {} heals {} for {:.0}{}", source, record.ability, record.target, record.amount, crit)
+        }
+        CombatEventKind::BuffApplied => format!("{} gains {} from {}", record.target, record.ability, source),
+        CombatEventKind::BuffRemoved => format!("{} fades from {}", record.ability, record.target),
+        CombatEventKind::Death => format!("{} dies", record.target),
+    }
+}
+
+fn combat_log_ui(
+    mut contexts: EguiContexts,
+    mut window: ResMut<CombatLogWindow>,
+    log: Res<CombatLog>,
+    player: Query<Entity, With<Player>>,
+) {
+    let ctx = contexts.ctx_mut().clone();
+    let player = player.get_single().ok().map(Entity::to_bits);
+    let window = &mut *window;
+    let mut open = window.open;
+
+    egui::Window::new("Combat Log")
+        .open(&mut open)
+        .default_size([460.0, 320.0])
+        .show(&ctx, |ui| {
+            ui.horizontal_wrapped(|ui| {
+                for kind in CombatEventKind::ALL {
+                    let mut shown = window.kinds.contains(&kind);
+                    if ui.checkbox(&mut shown, kind.label()).changed() {
+                        if shown {
+                            window.kinds.insert(kind);
+                        } else {
+                            window.kinds.remove(&kind);
+                        }
+                    }
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Filter");
+                ui.text_edit_singleline(&mut window.search);
+                ui.checkbox(&mut window.mine_only, "Mine only");
+            });
+            ui.separator();
+
+            let matching: Vec<&CombatRecord> = log.records.iter().filter(|r| window.matches(r, player)).collect();
+            let shown = &matching[matching.len().saturating_sub(MAX_SHOWN)..];
+            egui::ScrollArea::vertical().stick_to_bottom(true).auto_shrink([false, false]).show(ui, |ui| {
+                for record in shown {
+                    let seconds = record.t as u64;
+                    let stamp = format!(
+                        "{:02}:{:02}:{:02}",
+                        seconds % 86_400 / 3600,
+                        seconds % 3600 / 60,
+                        seconds % 60
+                    );
+                    let color = match record.kind {
+                        CombatEventKind::Damage => school_color(record.school),
+                        CombatEventKind::Heal => egui::Color32::from_rgb(100, 230, 120),
+                        CombatEventKind::BuffApplied | CombatEventKind::BuffRemoved => egui::Color32::LIGHT_BLUE,
+                        CombatEventKind::Death => egui::Color32::from_rgb(230, 80, 80),
+                    };
+                    ui.horizontal(|ui| {
+                        ui.weak(stamp);
+                        ui.colored_label(color, describe(record));
+                    });
+                }
+            });
+        });
+
+    window.open = open;
+}
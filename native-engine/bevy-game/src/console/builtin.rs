@@ -0,0 +1,202 @@
+//! Commands every build has. Cheats only reach the world through request
+//! events, so the systems that own spawning, inventory and the sky decide
+//! how to apply them.
+
+use bevy::prelude::*;
+
+use super::{
+    ArgCompletion, CommandPermission, ConsoleAppExt, ConsoleCommand, ConsoleCommandEvent, ConsoleCommands, ConsoleLog,
+    ConsoleOutputEvent, ConsolePermissions, CommandSource,
+};
+use crate::database::GameDatabase;
+use crate::hazards::Invulnerable;
+use crate::Player;
+
+#[derive(Event, Debug, Clone)]
+pub struct ConsoleSpawnRequest {
+    pub monster: String,
+    pub level: Option<u32>,
+    pub position: Vec3,
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct ConsoleGiveItemRequest {
+    pub target: Entity,
+    pub item: String,
+    pub count: u32,
+}
+
+/// Jumps the world clock to `hour` (0..24).
+#[derive(Event, Debug, Clone)]
+pub struct SetTimeOfDayRequest {
+    pub hour: f32,
+}
+
+pub struct BuiltinCommandsPlugin;
+
+impl Plugin for BuiltinCommandsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ConsoleSpawnRequest>()
+            .add_event::<ConsoleGiveItemRequest>()
+            .add_event::<SetTimeOfDayRequest>()
+            .add_console_command(
+                ConsoleCommand::new("help", "Lists commands, or describes one")
+                    .usage("[command]")
+                    .complete(vec![ArgCompletion::Commands]),
+            )
+            .add_console_command(ConsoleCommand::new("clear", "Clears the console"))
+            .add_console_command(
+                ConsoleCommand::new("teleport", "Moves the player")
+                    .alias("tp")
+                    .usage("<x> <y> <z>")
+                    .permission(CommandPermission::Dev),
+            )
+            .add_console_command(
+                ConsoleCommand::new("spawn", "Spawns monsters in front of the player")
+                    .usage("<monster> [level] [count]")
+                    .permission(CommandPermission::Dev)
+                    .complete(vec![ArgCompletion::Monsters]),
+            )
+            .add_console_command(
+                ConsoleCommand::new("give", "Puts items in the player's bags")
+                    .usage("<item> [count]")
+                    .permission(CommandPermission::Dev)
+                    .complete(vec![ArgCompletion::Items]),
+            )
+            .add_console_command(
+                ConsoleCommand::new("time", "Sets the time of day")
+                    .usage("<hour 0-24>")
+                    .permission(CommandPermission::Dev),
+            )
+            .add_console_command(
+                ConsoleCommand::new("god", "Toggles invulnerability for the player").permission(CommandPermission::Dev),
+            )
+            .add_systems(Update, run_builtin_commands);
+    }
+}
+
+/// Largest stack `give` and `spawn` hand out in one go.
+const MAX_COUNT: u32 = 100;
+
+fn run_builtin_commands(
+    mut commands: Commands,
+    registry: Res<ConsoleCommands>,
+    permissions: Res<ConsolePermissions>,
+    database: Option<Res<GameDatabase>>,
+    mut log: ResMut<ConsoleLog>,
+    mut players: Query<(Entity, &mut Transform, Has<Invulnerable>), With<Player>>,
+    mut events: EventReader<ConsoleCommandEvent>,
+    mut output: EventWriter<ConsoleOutputEvent>,
+    mut spawn_requests: EventWriter<ConsoleSpawnRequest>,
+    mut give_requests: EventWriter<ConsoleGiveItemRequest>,
+    mut time_requests: EventWriter<SetTimeOfDayRequest>,
+) {
+    for event in events.read() {
+        let result: Result<String, String> = match event.name.as_str() {
+            "help" => Ok(help(&registry, permissions.of(event.source), event.args.first())),
+            "clear" => {
+                if event.source == CommandSource::Local {
+                    log.lines.clear();
+                }
+                continue;
+            }
+            "teleport" => (|| {
+                let target = Vec3::new(event.arg(0, "x")?, event.arg(1, "y")?, event.arg(2, "z")?);
+                let (_, mut transform, _) = players.get_single_mut().map_err(|_| "no player".to_string())?;
+                transform.translation = target;
+                Ok(format!("Teleported to {:.1} {:.1} {:.1}", target.x, target.y, target.z))
+            })(),
+            "spawn" => (|| {
+                let monster: String = event.arg(0, "monster")?;
+                let level = event.args.get(1).map(|_| event.arg::<u32>(1, "level")).transpose()?;
+                let count = event.args.get(2).map(|_| event.arg::<u32>(2, "count")).transpose()?.unwrap_or(1);
+                let known = |db: &GameDatabase| db.monsters.is_empty() || db.monsters.contains(&monster);
+                check_known(database.as_deref(), known, "monster", &monster)?;
+                let (_, transform, _) = players.get_single().map_err(|_| "no player".to_string())?;
+                let ahead = transform.translation + transform.forward() * 5.0;
+                for i in 0..count.min(MAX_COUNT) {
+                    // A loose row across the player's view so spawns don't stack
+                    let offset = transform.right() * (i as f32 - (count.min(MAX_COUNT) - 1) as f32 * 0.5) * 2.0;
+                    spawn_requests.send(ConsoleSpawnRequest {
+                        monster: monster.clone(),
+                        level,
+                        position: ahead + offset,
+                    });
+                }
+                Ok(format!("Spawning {} x{}", monster, count.min(MAX_COUNT)))
+            })(),
+            "give" => (|| {
+                let item: String = event.arg(0, "item")?;
+                let count = event.args.get(1).map(|_| event.arg::<u32>(1, "count")).transpose()?.unwrap_or(1);
+                let known = |db: &GameDatabase| db.items.is_empty() || db.items.contains(&item);
+                check_known(database.as_deref(), known, "item", &item)?;
+                let (target, _, _) = players.get_single().map_err(|_| "no player".to_string())?;
+                let count = count.clamp(1, MAX_COUNT);
+                give_requests.send(ConsoleGiveItemRequest {
+                    target,
+                    item: item.clone(),
+                    count,
+                });
+                Ok(format!("Gave {} x{}", item, count))
+            })(),
+            "time" => event.arg::<f32>(0, "hour").and_then(|hour| {
+                if !(0.0..=24.0).contains(&hour) {
+                    return Err(format!("hour {} outside 0-24", hour));
+                }
+                time_requests.send(SetTimeOfDayRequest { hour: hour % 24.0 });
+                Ok(format!("Time set to {:02}:{:02}", hour as u32 % 24, (hour.fract() * 60.0) as u32))
+            }),
+            "god" => players
+                .get_single()
+                .map_err(|_| "no player".to_string())
+                .map(|(player, _, invulnerable)| {
+                    if invulnerable {
+                        commands.entity(player).remove::<Invulnerable>();
+                        "God mode off".to_string()
+                    } else {
+                        commands.entity(player).insert(Invulnerable);
+                        "God mode on".to_string()
+                    }
+                }),
+            _ => continue,
+        };
+
+        output.send(match result {
+            Ok(text) => event.reply(text),
+            Err(text) => event.error(format!("{}: {}", event.name, text)),
+        });
+    }
+}
+
+fn check_known(
+    database: Option<&GameDatabase>,
+    known: impl Fn(&GameDatabase) -> bool,
+    kind: &str,
+    id: &str,
+) -> Result<(), String> {
+    match database {
+        Some(db) if !known(db) => Err(format!("unknown {} '{}'", kind, id)),
+        _ => Ok(()),
+    }
+}
+
+fn help(registry: &ConsoleCommands, permission: CommandPermission, command: Option<&String>) -> String {
+    if let Some(name) = command {
+        return match registry.find(name).filter(|c| c.permission <= permission) {
+            Some(c) => {
+                let aliases = if c.aliases.is_empty() {
+                    String::new()
+                } else {
+                    format!(" (also {})", c.aliases.join(", "))
+                };
+                format!("{} {}{}\n  {}", c.name, c.usage, aliases, c.description)
+            }
+            None => format!("No command '{}'", name),
+        };
+    }
+    registry
+        .available(permission)
+        .map(|c| format!("{:<12} {}", c.name, c.description))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
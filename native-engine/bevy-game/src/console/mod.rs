@@ -0,0 +1,358 @@
+//! In-game console. Systems register commands with
+//! [`ConsoleAppExt::add_console_command`] and handle them by reading
+//! [`ConsoleCommandEvent`]s with their name, answering through
+//! [`ConsoleOutputEvent`]s. Lines come from the backtick overlay
+//! (`ConsoleUiPlugin`) or, for server admins, from the remote console.
+//!
+//! Every command carries a permission level. The local player gets `Dev`
+//! in debug builds or with `--dev`, and only `Player` commands otherwise;
+//! authenticated remote sessions run as `Admin`.
+
+use bevy::prelude::*;
+use std::collections::{BTreeMap, VecDeque};
+
+mod builtin;
+mod remote;
+mod ui;
+
+pub use builtin::*;
+pub use remote::*;
+pub use ui::*;
+
+use crate::database::GameDatabase;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CommandPermission {
+    Player,
+    /// Cheats and debugging aids.
+    Dev,
+    /// Server administration; includes everything `Dev` can run.
+    Admin,
+}
+
+/// Where a line was typed, and so where its output goes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CommandSource {
+    Local,
+    Remote { session: u64 },
+}
+
+/// What Tab offers for an argument.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArgCompletion {
+    None,
+    Commands,
+    Items,
+    Abilities,
+    Monsters,
+    Values(Vec<String>),
+}
+
+#[derive(Debug, Clone)]
+pub struct ConsoleCommand {
+    pub name: String,
+    pub aliases: Vec<String>,
+    /// Argument synopsis for `help`, e.g. `<item> [count]`.
+    pub usage: String,
+    pub description: String,
+    pub permission: CommandPermission,
+    /// Completion per argument position.
+    pub completions: Vec<ArgCompletion>,
+}
+
+impl ConsoleCommand {
+    pub fn new(name: &str, description: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            aliases: Vec::new(),
+            usage: String::new(),
+            description: description.to_string(),
+            permission: CommandPermission::Player,
+            completions: Vec::new(),
+        }
+    }
+
+    pub fn alias(mut self, alias: &str) -> Self {
+        self.aliases.push(alias.to_string());
+        self
+    }
+
+    pub fn usage(mut self, usage: &str) -> Self {
+        self.usage = usage.to_string();
+        self
+    }
+
+    pub fn permission(mut self, permission: CommandPermission) -> Self {
+        self.permission = permission;
+        self
+    }
+
+    pub fn complete(mut self, completions: Vec<ArgCompletion>) -> Self {
+        self.completions = completions;
+        self
+    }
+}
+
+/// Registered commands by name.
+#[derive(Resource, Debug, Default)]
+pub struct ConsoleCommands {
+    commands: BTreeMap<String, ConsoleCommand>,
+}
+
+impl ConsoleCommands {
+    pub fn register(&mut self, command: ConsoleCommand) {
+        if self.commands.insert(command.name.clone(), command.clone()).is_some() {
+            warn!("Console command '{}' registered twice; keeping the last", command.name);
+        }
+    }
+
+    /// Looks a command up by name or alias.
+    pub fn find(&self, name: &str) -> Option<&ConsoleCommand> {
+        self.commands
+            .get(name)
+            .or_else(|| self.commands.values().find(|c| c.aliases.iter().any(|a| a == name)))
+    }
+
+    pub fn available(&self, permission: CommandPermission) -> impl Iterator<Item = &ConsoleCommand> {
+        self.commands.values().filter(move |c| c.permission <= permission)
+    }
+}
+
+pub trait ConsoleAppExt {
+    fn add_console_command(&mut self, command: ConsoleCommand) -> &mut Self;
+}
+
+impl ConsoleAppExt for App {
+    fn add_console_command(&mut self, command: ConsoleCommand) -> &mut Self {
+        self.init_resource::<ConsoleCommands>();
+        self.world_mut().resource_mut::<ConsoleCommands>().register(command);
+        self
+    }
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct ConsolePermissions {
+    pub local: CommandPermission,
+}
+
+impl Default for ConsolePermissions {
+    fn default() -> Self {
+        let dev = cfg!(debug_assertions) || std::env::args().any(|arg| arg == "--dev");
+        Self {
+            local: if dev { CommandPermission::Dev } else { CommandPermission::Player },
+        }
+    }
+}
+
+impl ConsolePermissions {
+    pub fn of(&self, source: CommandSource) -> CommandPermission {
+        match source {
+            CommandSource::Local => self.local,
+            CommandSource::Remote { .. } => CommandPermission::Admin,
+        }
+    }
+}
+
+/// A line to run.
+#[derive(Event, Debug, Clone)]
+pub struct ConsoleInputEvent {
+    pub line: String,
+    pub source: CommandSource,
+}
+
+/// A parsed, permitted command. `name` is the registered name even when an
+/// alias was typed.
+#[derive(Event, Debug, Clone)]
+pub struct ConsoleCommandEvent {
+    pub name: String,
+    pub args: Vec<String>,
+    pub source: CommandSource,
+}
+
+impl ConsoleCommandEvent {
+    pub fn reply(&self, text: impl Into<String>) -> ConsoleOutputEvent {
+        ConsoleOutputEvent {
+            source: self.source,
+            text: text.into(),
+            error: false,
+        }
+    }
+
+    pub fn error(&self, text: impl Into<String>) -> ConsoleOutputEvent {
+        ConsoleOutputEvent {
+            source: self.source,
+            text: text.into(),
+            error: true,
+        }
+    }
+
+    /// Argument `index` parsed as `T`, with an error message naming it.
+    pub fn arg<T: std::str::FromStr>(&self, index: usize, name: &str) -> Result<T, String> {
+        let text = self.args.get(index).ok_or_else(|| format!("missing <{}>", name))?;
+        text.parse().map_err(|_| format!("invalid <{}>: '{}'", name, text))
+    }
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct ConsoleOutputEvent {
+    pub source: CommandSource,
+    pub text: String,
+    pub error: bool,
+}
+
+/// Output shown in the local console.
+#[derive(Resource, Debug)]
+pub struct ConsoleLog {
+    pub lines: VecDeque<(String, bool)>,
+    pub max_lines: usize,
+}
+
+impl Default for ConsoleLog {
+    fn default() -> Self {
+        Self {
+            lines: VecDeque::new(),
+            max_lines: 200,
+        }
+    }
+}
+
+impl ConsoleLog {
+    pub fn push(&mut self, text: impl Into<String>, error: bool) {
+        for line in text.into().lines() {
+            self.lines.push_back((line.to_string(), error));
+        }
+        while self.lines.len() > self.max_lines {
+            self.lines.pop_front();
+        }
+    }
+}
+
+/// Splits a line into words; double quotes group words with spaces.
+pub fn tokenize(line: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut in_token = false;
+    for c in line.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                in_token = true;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if in_token {
+                    tokens.push(std::mem::take(&mut current));
+                    in_token = false;
+                }
+            }
+            c => {
+                current.push(c);
+                in_token = true;
+            }
+        }
+    }
+    if in_token {
+        tokens.push(current);
+    }
+    tokens
+}
+
+/// Candidates for the word being typed at the end of `line`.
+pub fn completions(
+    line: &str,
+    commands: &ConsoleCommands,
+    permission: CommandPermission,
+    database: Option<&GameDatabase>,
+) -> Vec<String> {
+    let mut tokens = tokenize(line);
+    if line.is_empty() || line.ends_with(char::is_whitespace) {
+        tokens.push(String::new());
+    }
+    let Some(partial) = tokens.last() else {
+        return Vec::new();
+    };
+
+    let source = if tokens.len() == 1 {
+        ArgCompletion::Commands
+    } else {
+        match commands.find(&tokens[0]) {
+            Some(command) => command.completions.get(tokens.len() - 2).cloned().unwrap_or(ArgCompletion::None),
+            None => ArgCompletion::None,
+        }
+    };
+    let candidates: Vec<String> = match source {
+        ArgCompletion::None => Vec::new(),
+        ArgCompletion::Commands => commands.available(permission).map(|c| c.name.clone()).collect(),
+        ArgCompletion::Items => database.map(|db| db.items.iter().map(|r| r.id.clone()).collect()).unwrap_or_default(),
+        ArgCompletion::Abilities => database
+            .map(|db| db.abilities.iter().map(|r| r.id.clone()).collect())
+            .unwrap_or_default(),
+        ArgCompletion::Monsters => database
+            .map(|db| db.monsters.iter().map(|r| r.id.clone()).collect())
+            .unwrap_or_default(),
+        ArgCompletion::Values(values) => values,
+    };
+    let mut matches: Vec<String> = candidates.into_iter().filter(|c| c.starts_with(partial.as_str())).collect();
+    matches.sort();
+    matches
+}
+
+pub struct ConsolePlugin;
+
+impl Plugin for ConsolePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ConsoleCommands>()
+            .init_resource::<ConsolePermissions>()
+            .init_resource::<ConsoleLog>()
+            .add_event::<ConsoleInputEvent>()
+            .add_event::<ConsoleCommandEvent>()
+            .add_event::<ConsoleOutputEvent>()
+            .add_plugins((BuiltinCommandsPlugin, RemoteConsolePlugin))
+            .add_systems(PreUpdate, dispatch_console_input)
+            .add_systems(Last, collect_local_output);
+    }
+}
+
+fn dispatch_console_input(
+    commands: Res<ConsoleCommands>,
+    permissions: Res<ConsolePermissions>,
+    mut input: EventReader<ConsoleInputEvent>,
+    mut parsed: EventWriter<ConsoleCommandEvent>,
+    mut output: EventWriter<ConsoleOutputEvent>,
+) {
+    for event in input.read() {
+        let mut tokens = tokenize(&event.line);
+        if tokens.is_empty() {
+            continue;
+        }
+        let name = tokens.remove(0);
+        let error = |text: String| ConsoleOutputEvent {
+            source: event.source,
+            text,
+            error: true,
+        };
+
+        let Some(command) = commands.find(&name) else {
+            output.send(error(format!("Unknown command '{}'; try 'help'", name)));
+            continue;
+        };
+        if command.permission > permissions.of(event.source) {
+            output.send(error(format!("'{}' needs {:?} permission", command.name, command.permission)));
+            continue;
+        }
+        if let CommandSource::Remote { session } = event.source {
+            info!("Remote console session {}: {}", session, event.line);
+        }
+        parsed.send(ConsoleCommandEvent {
+            name: command.name.clone(),
+            args: tokens,
+            source: event.source,
+        });
+    }
+}
+
+fn collect_local_output(mut output: EventReader<ConsoleOutputEvent>, mut log: ResMut<ConsoleLog>) {
+    for event in output.read().filter(|e| e.source == CommandSource::Local) {
+        log.push(event.text.clone(), event.error);
+    }
+}
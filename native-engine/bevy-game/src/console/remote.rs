@@ -0,0 +1,160 @@
+//! Remote console for server admins: a line-based TCP endpoint, off unless
+//! `MMO_ADMIN_CONSOLE` names an address to listen on. The first line a
+//! client sends must be the `MMO_ADMIN_TOKEN`; every later line runs as a
+//! command with `Admin` permission and its output is written back, errors
+//! prefixed with `! `. Bind it to localhost or a private interface: the
+//! token is the only protection and the connection isn't encrypted.
+
+use bevy::prelude::*;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use super::{CommandSource, ConsoleInputEvent, ConsoleOutputEvent};
+
+#[derive(Resource, Debug, Clone)]
+pub struct RemoteConsoleConfig {
+    /// Listen address, e.g. `127.0.0.1:7878`.
+    pub bind: Option<String>,
+    pub token: Option<String>,
+}
+
+impl Default for RemoteConsoleConfig {
+    fn default() -> Self {
+        Self {
+            bind: std::env::var("MMO_ADMIN_CONSOLE").ok().filter(|v| !v.is_empty()),
+            token: std::env::var("MMO_ADMIN_TOKEN").ok().filter(|v| !v.is_empty()),
+        }
+    }
+}
+
+type Sessions = Arc<Mutex<HashMap<u64, TcpStream>>>;
+
+#[derive(Resource)]
+struct RemoteConsole {
+    lines: Mutex<Receiver<(u64, String)>>,
+    sessions: Sessions,
+}
+
+pub struct RemoteConsolePlugin;
+
+impl Plugin for RemoteConsolePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RemoteConsoleConfig>()
+            .add_systems(Startup, start_remote_console)
+            .add_systems(First, poll_remote_console.run_if(resource_exists::<RemoteConsole>))
+            .add_systems(Last, send_remote_output.run_if(resource_exists::<RemoteConsole>));
+    }
+}
+
+/// Compares without stopping at the first difference, so response timing
+/// doesn't reveal how much of a guess was right.
+fn token_matches(given: &str, expected: &str) -> bool {
+    let (given, expected) = (given.as_bytes(), expected.as_bytes());
+    let mut difference = given.len() ^ expected.len();
+    for (i, byte) in expected.iter().enumerate() {
+        difference |= (given.get(i).copied().unwrap_or(0) ^ byte) as usize;
+    }
+    difference == 0
+}
+
+fn start_remote_console(mut commands: Commands, config: Res<RemoteConsoleConfig>) {
+    let Some(bind) = config.bind.clone() else {
+        return;
+    };
+    let Some(token) = config.token.clone() else {
+        warn!("Remote console off: MMO_ADMIN_CONSOLE is set but MMO_ADMIN_TOKEN isn't");
+        return;
+    };
+    let listener = match TcpListener::bind(&bind) {
+        Ok(listener) => listener,
+        Err(e) => {
+            warn!("Remote console off: failed to listen on {}: {}", bind, e);
+            return;
+        }
+    };
+    info!("Remote console listening on {}", bind);
+
+    let (sender, receiver) = channel();
+    let sessions: Sessions = Arc::default();
+    let accept_sessions = sessions.clone();
+    std::thread::spawn(move || {
+        for (session, stream) in (1u64..).zip(listener.incoming().flatten()) {
+            let sender = sender.clone();
+            let sessions = accept_sessions.clone();
+            let token = token.clone();
+            std::thread::spawn(move || run_session(session, stream, &token, sender, sessions));
+        }
+    });
+
+    commands.insert_resource(RemoteConsole {
+        lines: Mutex::new(receiver),
+        sessions,
+    });
+}
+
+fn run_session(session: u64, mut stream: TcpStream, token: &str, sender: Sender<(u64, String)>, sessions: Sessions) {
+    let peer = stream.peer_addr().map(|a| a.to_string()).unwrap_or_default();
+    let Ok(reader) = stream.try_clone() else {
+        return;
+    };
+    let mut lines = BufReader::new(reader).lines();
+
+    let authenticated = matches!(lines.next(), Some(Ok(line)) if token_matches(line.trim(), token));
+    if !authenticated {
+        warn!("Remote console: rejected {} (bad token)", peer);
+        // Slows down guessing
+        std::thread::sleep(Duration::from_secs(1));
+        let _ = stream.write_all(b"! denied\n");
+        return;
+    }
+    info!("Remote console: session {} opened from {}", session, peer);
+    let _ = stream.write_all(b"ok\n");
+    if let (Ok(writer), Ok(mut sessions)) = (stream.try_clone(), sessions.lock()) {
+        sessions.insert(session, writer);
+    }
+
+    for line in lines.map_while(Result::ok) {
+        if sender.send((session, line)).is_err() {
+            break;
+        }
+    }
+    if let Ok(mut sessions) = sessions.lock() {
+        sessions.remove(&session);
+    }
+    info!("Remote console: session {} closed", session);
+}
+
+fn poll_remote_console(remote: Res<RemoteConsole>, mut input: EventWriter<ConsoleInputEvent>) {
+    let Ok(lines) = remote.lines.lock() else {
+        return;
+    };
+    for (session, line) in lines.try_iter() {
+        input.send(ConsoleInputEvent {
+            line,
+            source: CommandSource::Remote { session },
+        });
+    }
+}
+
+fn send_remote_output(remote: Res<RemoteConsole>, mut output: EventReader<ConsoleOutputEvent>) {
+    let Ok(mut sessions) = remote.sessions.lock() else {
+        return;
+    };
+    for event in output.read() {
+        let CommandSource::Remote { session } = event.source else {
+            continue;
+        };
+        let Some(stream) = sessions.get_mut(&session) else {
+            continue;
+        };
+        let prefix = if event.error { "! " } else { "" };
+        let text: String = event.text.lines().map(|line| format!("{}{}\n", prefix, line)).collect();
+        if stream.write_all(text.as_bytes()).is_err() {
+            sessions.remove(&session);
+        }
+    }
+}
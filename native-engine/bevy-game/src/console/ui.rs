@@ -0,0 +1,238 @@
+//! The backtick console overlay: output, an input line, history on Up/Down
+//! and Tab completion.
+
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::input::ButtonState;
+use bevy::prelude::*;
+
+use super::{completions, CommandSource, ConsoleCommands, ConsoleInputEvent, ConsoleLog, ConsolePermissions};
+use crate::database::GameDatabase;
+use crate::input_map::{InputContext, InputContexts};
+
+/// Whether the console has the keyboard. While `open` is set the `Ui` input
+/// context is pushed, which hides gameplay actions.
+#[derive(Resource, Debug, Default)]
+pub struct ConsoleState {
+    pub open: bool,
+    pub input: String,
+    pub history: Vec<String>,
+    /// Position while browsing history; `None` is the line being typed.
+    history_cursor: Option<usize>,
+}
+
+const MAX_HISTORY: usize = 100;
+/// Output lines shown above the input line.
+const VISIBLE_LINES: usize = 18;
+
+#[derive(Component)]
+struct ConsoleOverlay;
+
+#[derive(Component)]
+struct ConsoleOutputText;
+
+#[derive(Component)]
+struct ConsoleInputText;
+
+pub struct ConsoleUiPlugin;
+
+impl Plugin for ConsoleUiPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ConsoleState>()
+            .add_systems(Startup, setup_console_overlay)
+            .add_systems(Update, (handle_console_keys, update_console_overlay).chain());
+    }
+}
+
+fn setup_console_overlay(mut commands: Commands) {
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(0.0),
+                left: Val::Px(0.0),
+                width: Val::Percent(100.0),
+                height: Val::Percent(40.0),
+                padding: UiRect::all(Val::Px(8.0)),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::FlexEnd,
+                overflow: Overflow::clip(),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.02, 0.02, 0.05, 0.9)),
+            GlobalZIndex(100),
+            Visibility::Hidden,
+            ConsoleOverlay,
+            Name::new("Console"),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new(""),
+                TextFont {
+                    font_size: 14.0,
+                    ..default()
+                },
+                TextColor(Color::srgb(0.85, 0.85, 0.85)),
+                ConsoleOutputText,
+            ));
+            parent.spawn((
+                Text::new("> "),
+                TextFont {
+                    font_size: 15.0,
+                    ..default()
+                },
+                TextColor(Color::srgb(1.0, 0.9, 0.4)),
+                ConsoleInputText,
+            ));
+        });
+}
+
+/// Replaces the word being typed with `completion`.
+fn complete_last_word(input: &mut String, completion: &str, finished: bool) {
+    let start = input.rfind(char::is_whitespace).map_or(0, |i| i + 1);
+    input.truncate(start);
+    input.push_str(completion);
+    if finished {
+        input.push(' ');
+    }
+}
+
+fn common_prefix(candidates: &[String]) -> String {
+    let Some(first) = candidates.first() else {
+        return String::new();
+    };
+    let mut length = first.len();
+    for candidate in &candidates[1..] {
+        length = first
+            .char_indices()
+            .zip(candidate.chars())
+            .take_while(|((_, a), b)| a == b)
+            .map(|((i, a), _)| i + a.len_utf8())
+            .last()
+            .unwrap_or(0)
+            .min(length);
+    }
+    first[..length].to_string()
+}
+
+fn handle_console_keys(
+    mut keys: EventReader<KeyboardInput>,
+    mut state: ResMut<ConsoleState>,
+    mut log: ResMut<ConsoleLog>,
+    commands: Res<ConsoleCommands>,
+    permissions: Res<ConsolePermissions>,
+    database: Option<Res<GameDatabase>>,
+    contexts: Option<ResMut<InputContexts>>,
+    mut input: EventWriter<ConsoleInputEvent>,
+) {
+    for event in keys.read() {
+        if event.state != ButtonState::Pressed {
+            continue;
+        }
+        if event.key_code == KeyCode::Backquote {
+            state.open = !state.open;
+            continue;
+        }
+        if !state.open {
+            continue;
+        }
+
+        match &event.logical_key {
+            Key::Enter => {
+                let line = std::mem::take(&mut state.input).trim().to_string();
+                state.history_cursor = None;
+                if line.is_empty() {
+                    continue;
+                }
+                log.push(format!("> {}", line), false);
+                if state.history.last() != Some(&line) {
+                    state.history.push(line.clone());
+                    if state.history.len() > MAX_HISTORY {
+                        state.history.remove(0);
+                    }
+                }
+                input.send(ConsoleInputEvent {
+                    line,
+                    source: CommandSource::Local,
+                });
+            }
+            Key::Escape => state.open = false,
+            Key::Backspace => {
+                state.input.pop();
+            }
+            Key::ArrowUp if !state.history.is_empty() => {
+                let cursor = state.history_cursor.map_or(state.history.len() - 1, |c| c.saturating_sub(1));
+                state.history_cursor = Some(cursor);
+                state.input = state.history[cursor].clone();
+            }
+            Key::ArrowDown => match state.history_cursor {
+                Some(cursor) if cursor + 1 < state.history.len() => {
+                    state.history_cursor = Some(cursor + 1);
+                    state.input = state.history[cursor + 1].clone();
+                }
+                Some(_) => {
+                    state.history_cursor = None;
+                    state.input.clear();
+                }
+                None => {}
+            },
+            Key::Tab => {
+                let candidates = completions(
+                    &state.input,
+                    &commands,
+                    permissions.of(CommandSource::Local),
+                    database.as_deref(),
+                );
+                match candidates.len() {
+                    0 => {}
+                    1 => complete_last_word(&mut state.input, &candidates[0], true),
+                    _ => {
+                        let prefix = common_prefix(&candidates);
+                        let typed = state.input.rsplit(char::is_whitespace).next().unwrap_or("").len();
+                        if prefix.len() > typed {
+                            complete_last_word(&mut state.input, &prefix, false);
+                        } else {
+                            log.push(candidates.join("  "), false);
+                        }
+                    }
+                }
+            }
+            Key::Space => state.input.push(' '),
+            Key::Character(text) => {
+                state.input.extend(text.chars().filter(|c| !c.is_control() && *c != '`'));
+            }
+            _ => {}
+        }
+    }
+    if let Some(mut contexts) = contexts {
+        contexts.set(InputContext::Ui, state.open);
+    }
+}
+
+fn update_console_overlay(
+    state: Res<ConsoleState>,
+    log: Res<ConsoleLog>,
+    mut overlay: Query<&mut Visibility, With<ConsoleOverlay>>,
+    mut output_text: Query<&mut Text, (With<ConsoleOutputText>, Without<ConsoleInputText>)>,
+    mut input_text: Query<&mut Text, (With<ConsoleInputText>, Without<ConsoleOutputText>)>,
+) {
+    if state.is_changed() {
+        for mut visibility in overlay.iter_mut() {
+            *visibility = if state.open { Visibility::Visible } else { Visibility::Hidden };
+        }
+        for mut text in input_text.iter_mut() {
+            text.0 = format!("> {}_", state.input);
+        }
+    }
+    if log.is_changed() {
+        let skip = log.lines.len().saturating_sub(VISIBLE_LINES);
+        let shown: Vec<String> = log
+            .lines
+            .iter()
+            .skip(skip)
+            .map(|(line, error)| if *error { format!("! {}", line) } else { line.clone() })
+            .collect();
+        for mut text in output_text.iter_mut() {
+            text.0 = shown.join("\n");
+        }
+    }
+}
@@ -0,0 +1,416 @@
+//! Content packs: the core `content/` directory plus mods installed under
+//! `packs/<id>/`, each with a `pack.toml` manifest, a `content/` tree laid out
+//! like the core one and optionally an `assets/` directory.
+//!
+//! Packs load in the order given by `packs/load_order.toml` (the launcher's
+//! `--enable-pack`/`--disable-pack` edit it); packs missing from it load after
+//! the listed ones, enabled. A file in a later pack replaces the file with the
+//! same path in earlier packs, and a manifest's `remove` list hides earlier
+//! files outright. Packs whose dependencies, conflicts or engine version don't
+//! check out are disabled, along with anything depending on them.
+//!
+//! Loaders ask [`ContentPacks::files`] for a content directory instead of
+//! reading it directly. Pack assets are registered as an asset source named
+//! after the pack, so pack content references them as `<pack id>://path`.
+
+use bevy::asset::io::{AssetSourceBuilder, AssetSourceId};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+
+pub const CORE_PACK: &str = "core";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackDependency {
+    pub id: String,
+    /// Version requirement, e.g. `">=1.2"`, `"^1.0"` or `"*"`.
+    #[serde(default = "any_version")]
+    pub version: String,
+}
+
+fn any_version() -> String {
+    "*".to_string()
+}
+
+/// `packs/<id>/pack.toml`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackManifest {
+    pub id: String,
+    pub name: String,
+    pub version: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub authors: Vec<String>,
+    /// Engine versions the pack works with.
+    #[serde(default = "any_version")]
+    pub engine: String,
+    #[serde(default, rename = "dependency")]
+    pub dependencies: Vec<PackDependency>,
+    /// Packs that can't be enabled alongside this one.
+    #[serde(default)]
+    pub conflicts: Vec<String>,
+    /// Paths relative to `content/` that earlier packs provide and this pack hides.
+    #[serde(default)]
+    pub remove: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoadOrderEntry {
+    pub id: String,
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+/// `packs/load_order.toml`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LoadOrderFile {
+    #[serde(default, rename = "pack")]
+    pub packs: Vec<LoadOrderEntry>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ContentPack {
+    pub manifest: PackManifest,
+    pub root: PathBuf,
+    /// The pack's `content/` directory (the core pack's is `content/` itself).
+    pub content_root: PathBuf,
+    /// Wanted by the load order; `active` is whether it actually loads.
+    pub enabled: bool,
+    pub active: bool,
+    pub errors: Vec<String>,
+}
+
+impl ContentPack {
+    pub fn assets_root(&self) -> PathBuf {
+        self.root.join("assets")
+    }
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct ContentPacks {
+    /// The core content root that loader configs' paths start with.
+    pub content_root: PathBuf,
+    /// Every discovered pack in load order, core first.
+    pub packs: Vec<ContentPack>,
+}
+
+impl ContentPacks {
+    pub fn discover(content_root: &Path, packs_dir: &Path) -> Self {
+        let core = ContentPack {
+            manifest: PackManifest {
+                id: CORE_PACK.to_string(),
+                name: "Core".to_string(),
+                version: env!("CARGO_PKG_VERSION").to_string(),
+                description: String::new(),
+                authors: Vec::new(),
+                engine: any_version(),
+                dependencies: Vec::new(),
+                conflicts: Vec::new(),
+                remove: Vec::new(),
+            },
+            root: content_root.parent().map(Path::to_path_buf).unwrap_or_default(),
+            content_root: content_root.to_path_buf(),
+            enabled: true,
+            active: true,
+            errors: Vec::new(),
+        };
+
+        let mut found: BTreeMap<String, ContentPack> = BTreeMap::new();
+        let mut entries: Vec<PathBuf> = std::fs::read_dir(packs_dir)
+            .map(|entries| entries.flatten().map(|e| e.path()).filter(|p| p.is_dir()).collect())
+            .unwrap_or_default();
+        entries.sort();
+        for root in entries {
+            let manifest_path = root.join("pack.toml");
+            let parsed = std::fs::read_to_string(&manifest_path)
+                .map_err(|e| e.to_string())
+                .and_then(|text| toml::from_str::<PackManifest>(&text).map_err(|e| e.to_string()));
+            let manifest = match parsed {
+                Ok(manifest) => manifest,
+                Err(e) => {
+                    warn!("Skipping pack {:?}: bad pack.toml: {}", root, e);
+                    continue;
+                }
+            };
+            if manifest.id == CORE_PACK || found.contains_key(&manifest.id) {
+                warn!("Skipping pack {:?}: duplicate id {}", root, manifest.id);
+                continue;
+            }
+            found.insert(
+                manifest.id.clone(),
+                ContentPack {
+                    content_root: root.join("content"),
+                    root,
+                    manifest,
+                    enabled: true,
+                    active: true,
+                    errors: Vec::new(),
+                },
+            );
+        }
+
+        let load_order_path = packs_dir.join("load_order.toml");
+        let load_order = match std::fs::read_to_string(&load_order_path) {
+            Ok(text) => toml::from_str::<LoadOrderFile>(&text).unwrap_or_else(|e| {
+                warn!("Ignoring {:?}: {}", load_order_path, e);
+                LoadOrderFile::default()
+            }),
+            Err(_) => LoadOrderFile::default(),
+        };
+
+        let mut ordered = vec![core];
+        for entry in &load_order.packs {
+            if let Some(mut pack) = found.remove(&entry.id) {
+                pack.enabled = entry.enabled;
+                ordered.push(pack);
+            } else if entry.id != CORE_PACK {
+                warn!("Load order lists pack {} which isn't installed", entry.id);
+            }
+        }
+        ordered.extend(found.into_values());
+
+        let mut packs = Self {
+            content_root: content_root.to_path_buf(),
+            packs: ordered,
+        };
+        packs.check();
+        packs.sort_by_dependencies();
+        packs
+    }
+
+    /// Disables packs whose requirements fail, repeating until nothing changes
+    /// so dependents of a disabled pack go too.
+    fn check(&mut self) {
+        for pack in &mut self.packs {
+            pack.active = pack.enabled;
+            pack.errors.clear();
+            if !version_matches(&pack.manifest.engine, env!("CARGO_PKG_VERSION")) {
+                pack.errors.push(format!(
+                    "needs engine {}, this is {}",
+                    pack.manifest.engine,
+                    env!("CARGO_PKG_VERSION")
+                ));
+                pack.active = false;
+            }
+        }
+        loop {
+            let active: BTreeMap<String, String> = self
+                .packs
+                .iter()
+                .filter(|p| p.active)
+                .map(|p| (p.manifest.id.clone(), p.manifest.version.clone()))
+                .collect();
+            let mut changed = false;
+            for pack in self.packs.iter_mut().filter(|p| p.active) {
+                let mut errors = Vec::new();
+                for dependency in &pack.manifest.dependencies {
+                    match active.get(&dependency.id) {
+                        None => errors.push(format!("needs pack {} which is missing or disabled", dependency.id)),
+                        Some(version) if !version_matches(&dependency.version, version) => errors.push(format!(
+                            "needs {} {}, found {}",
+                            dependency.id, dependency.version, version
+                        )),
+                        Some(_) => {}
+                    }
+                }
+                for conflict in pack.manifest.conflicts.iter().filter(|c| active.contains_key(*c)) {
+                    errors.push(format!("conflicts with enabled pack {}", conflict));
+                }
+                if !errors.is_empty() {
+                    pack.errors.extend(errors);
+                    pack.active = false;
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+    }
+
+    /// Keeps the load order, except that a pack always loads after the packs
+    /// it depends on.
+    fn sort_by_dependencies(&mut self) {
+        let mut remaining = std::mem::take(&mut self.packs);
+        let mut placed: HashSet<String> = HashSet::new();
+        while !remaining.is_empty() {
+            let next = remaining
+                .iter()
+                .position(|pack| {
+                    pack.manifest
+                        .dependencies
+                        .iter()
+                        .all(|d| placed.contains(&d.id) || !remaining.iter().any(|p| p.manifest.id == d.id))
+                })
+                // Dependency cycle: fall back to the listed order
+                .unwrap_or(0);
+            let pack = remaining.remove(next);
+            placed.insert(pack.manifest.id.clone());
+            self.packs.push(pack);
+        }
+    }
+
+    pub fn active(&self) -> impl Iterator<Item = &ContentPack> {
+        self.packs.iter().filter(|p| p.active)
+    }
+
+    /// Path of `path` relative to the core content root, if it's inside it.
+    fn relative<'a>(&self, path: &'a Path) -> Option<&'a Path> {
+        path.strip_prefix(&self.content_root).ok()
+    }
+
+    /// `directory` (a core content path such as `content/spawns`) in every
+    /// active pack that has it, in load order.
+    pub fn directories(&self, directory: &Path) -> Vec<PathBuf> {
+        let Some(relative) = self.relative(directory) else {
+            return vec![directory.to_path_buf()];
+        };
+        self.active()
+            .map(|pack| pack.content_root.join(relative))
+            .filter(|dir| dir.is_dir())
+            .collect()
+    }
+
+    /// Files with `extension` directly inside `directory` across the active
+    /// packs, after overrides and removals, sorted by file name.
+    pub fn files(&self, directory: &Path, extension: &str) -> Vec<PathBuf> {
+        let Some(relative) = self.relative(directory) else {
+            return plain_files(directory, extension);
+        };
+        let mut files: BTreeMap<std::ffi::OsString, PathBuf> = BTreeMap::new();
+        for pack in self.active() {
+            for removed in &pack.manifest.remove {
+                let removed = Path::new(removed);
+                if removed.parent() == Some(relative) {
+                    if let Some(name) = removed.file_name() {
+                        files.remove(name);
+                    }
+                }
+            }
+            for path in plain_files(&pack.content_root.join(relative), extension) {
+                if let Some(name) = path.file_name() {
+                    files.insert(name.to_os_string(), path);
+                }
+            }
+        }
+        files.into_values().collect()
+    }
+
+    /// The winning copy of a single content file (e.g. `content/audio/foley.toml`).
+    pub fn resolve(&self, path: &Path) -> Option<PathBuf> {
+        let Some(relative) = self.relative(path) else {
+            return path.exists().then(|| path.to_path_buf());
+        };
+        let mut resolved = None;
+        for pack in self.active() {
+            if pack.manifest.remove.iter().any(|r| Path::new(r) == relative) {
+                resolved = None;
+            }
+            let candidate = pack.content_root.join(relative);
+            if candidate.is_file() {
+                resolved = Some(candidate);
+            }
+        }
+        resolved
+    }
+}
+
+impl Default for ContentPacks {
+    fn default() -> Self {
+        Self::discover(Path::new("content"), Path::new("packs"))
+    }
+}
+
+fn plain_files(directory: &Path, extension: &str) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(directory) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.is_file() && p.extension().and_then(|e| e.to_str()) == Some(extension))
+        .collect();
+    paths.sort();
+    paths
+}
+
+fn parse_version(text: &str) -> Option<[u64; 3]> {
+    let mut parts = [0; 3];
+    let mut count = 0;
+    for (slot, part) in parts.iter_mut().zip(text.trim().split('.')) {
+        // Ignore pre-release/build suffixes: "1.2.0-beta" compares as 1.2.0
+        let digits: String = part.chars().take_while(char::is_ascii_digit).collect();
+        *slot = digits.parse().ok()?;
+        count += 1;
+    }
+    (count > 0).then_some(parts)
+}
+
+/// Checks `version` against a requirement: `*`, `=x`, `>x`, `>=x`, `<x`,
+/// `<=x`, `~x` (same minor) or `^x`/bare `x` (same major, at least x).
+/// Comma-separated requirements must all hold.
+pub fn version_matches(requirement: &str, version: &str) -> bool {
+    let Some(version) = parse_version(version) else {
+        return false;
+    };
+    requirement.split(',').map(str::trim).all(|requirement| {
+        if requirement.is_empty() || requirement == "*" {
+            return true;
+        }
+        let (op, rest) = ["<=", ">=", "<", ">", "=", "~", "^"]
+            .iter()
+            .find_map(|op| requirement.strip_prefix(op).map(|rest| (*op, rest)))
+            .unwrap_or(("^", requirement));
+        let Some(wanted) = parse_version(rest) else {
+            return false;
+        };
+        match op {
+            "<=" => version <= wanted,
+            ">=" => version >= wanted,
+            "<" => version < wanted,
+            ">" => version > wanted,
+            "=" => version == wanted,
+            "~" => version[..2] == wanted[..2] && version >= wanted,
+            _ => version[0] == wanted[0] && version >= wanted,
+        }
+    })
+}
+
+/// Discovers packs and registers their asset sources. Must be added before
+/// `DefaultPlugins`, since asset sources can't be added once the asset
+/// server exists.
+pub struct ContentPacksPlugin;
+
+impl Plugin for ContentPacksPlugin {
+    fn build(&self, app: &mut App) {
+        let packs = ContentPacks::default();
+        for pack in &packs.packs {
+            let state = if pack.active {
+                "loaded"
+            } else if pack.enabled {
+                "failed"
+            } else {
+                "disabled"
+            };
+            info!("Content pack {} {} ({}): {}", pack.manifest.id, pack.manifest.version, pack.manifest.name, state);
+            for error in &pack.errors {
+                warn!("Content pack {}: {}", pack.manifest.id, error);
+            }
+            let assets = pack.assets_root();
+            if pack.active && pack.manifest.id != CORE_PACK && assets.is_dir() {
+                let path = assets.to_string_lossy().into_owned();
+                app.register_asset_source(
+                    AssetSourceId::Name(pack.manifest.id.clone().into()),
+                    AssetSourceBuilder::platform_default(&path, None),
+                );
+            }
+        }
+        app.insert_resource(packs);
+    }
+}
@@ -0,0 +1,409 @@
+//! `--validate-content`: a headless pass over `content/` for CI and the
+//! launcher. Every TOML/JSON/RON file is parsed, files in directories with a
+//! known format are checked against it, asset paths must exist under
+//! `assets/`, and id references (items, abilities, monsters, loot tables,
+//! behavior trees, quests, prefabs, voice lines) must resolve. Exits nonzero on errors.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::authoring::{
+    validate_dialog, validate_quest, DialogTreeDef, MaterialGraphDef, NarrativeIssueSeverity, PrefabDef,
+    PrefabInstanceFile, QuestDef, SpawnContentFile,
+};
+use crate::caravans::CaravanContentFile;
+use crate::database::{ContentMigrations, GameDatabase};
+use crate::dungeons::DungeonContentFile;
+use crate::economy::EconomyContentFile;
+use crate::gathering::GatheringContentFile;
+use crate::interactables::LootContentFile;
+use crate::races::RaceContentFile;
+use crate::sound::{AmbienceContentFile, FoleyContentFile, MusicContentFile, SoundCueFile, VoiceContentFile};
+use crate::territory::TerritoryContentFile;
+use crate::triggers::TriggerVolumeFile;
+use crate::tutorials::TutorialContentFile;
+use crate::world_clock::WorldScheduleFile;
+
+type SchemaCheck = fn(&str) -> Result<(), String>;
+
+fn toml_schema<T: DeserializeOwned>(text: &str) -> Result<(), String> {
+    toml::from_str::<T>(text).map(|_| ()).map_err(|e| e.to_string())
+}
+
+fn json_schema<T: DeserializeOwned>(text: &str) -> Result<(), String> {
+    serde_json::from_str::<T>(text).map(|_| ()).map_err(|e| e.to_string())
+}
+
+/// Typed formats by path under `content/`. Directories without an entry here
+/// (behavior trees, loot tables, ...) only get syntax and reference checks.
+const SCHEMAS: &[(&str, SchemaCheck)] = &[
+    ("spawns/", toml_schema::<SpawnContentFile>),
+    ("quests/", toml_schema::<QuestDef>),
+    ("dialog/", json_schema::<DialogTreeDef>),
+    ("materials/graphs/", toml_schema::<MaterialGraphDef>),
+    ("prefabs/", toml_schema::<PrefabDef>),
+    ("prefab_instances/", toml_schema::<PrefabInstanceFile>),
+    ("triggers/", toml_schema::<TriggerVolumeFile>),
+    ("audio/voice/", toml_schema::<VoiceContentFile>),
+    ("audio/events/", toml_schema::<SoundCueFile>),
+    ("audio/ambience/", toml_schema::<AmbienceContentFile>),
+    ("audio/foley.toml", toml_schema::<FoleyContentFile>),
+    ("music/", toml_schema::<MusicContentFile>),
+    ("loot/", toml_schema::<LootContentFile>),
+    ("races/", toml_schema::<RaceContentFile>),
+    ("economy/", toml_schema::<EconomyContentFile>),
+    ("dungeons/", toml_schema::<DungeonContentFile>),
+    ("caravans/", toml_schema::<CaravanContentFile>),
+    ("gathering/", toml_schema::<GatheringContentFile>),
+    ("tutorials/", toml_schema::<TutorialContentFile>),
+    ("territory/", toml_schema::<TerritoryContentFile>),
+    ("world_schedule/", toml_schema::<WorldScheduleFile>),
+];
+
+/// Where each kind of referenced id is defined.
+const CATEGORIES: &[(&str, &str)] = &[
+    ("items/", "item"),
+    ("abilities/", "ability"),
+    ("monsters/", "monster"),
+    ("loot/", "loot table"),
+    ("loot_tables/", "loot table"),
+    ("behavior_trees/", "behavior tree"),
+    ("ai/behavior_trees/", "behavior tree"),
+    ("quests/", "quest"),
+    ("prefabs/", "prefab"),
+    ("audio/voice/", "voice line"),
+];
+
+/// Keys whose string values are ids of the given kind.
+const REFERENCE_KEYS: &[(&str, &str)] = &[
+    ("item", "item"),
+    ("item_id", "item"),
+    ("requires_item", "item"),
+    ("ability", "ability"),
+    ("ability_id", "ability"),
+    ("abilities", "ability"),
+    ("monster", "monster"),
+    ("monster_id", "monster"),
+    ("loot_table", "loot table"),
+    ("behavior_tree", "behavior tree"),
+    ("accept_quest", "quest"),
+    ("quest", "quest"),
+    ("prefab", "prefab"),
+    ("voice_line", "voice line"),
+];
+
+/// Extensions that mark a string as a path under `assets/`.
+const ASSET_EXTENSIONS: &[&str] = &[
+    "glb", "gltf", "png", "jpg", "jpeg", "ktx2", "dds", "tga", "hdr", "ogg", "wav", "mp3", "flac", "wgsl",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ContentIssueSeverity {
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone)]
+pub struct ContentIssue {
+    pub severity: ContentIssueSeverity,
+    pub path: PathBuf,
+    pub message: String,
+}
+
+#[derive(Debug, Default)]
+pub struct ContentReport {
+    pub files_checked: usize,
+    pub issues: Vec<ContentIssue>,
+}
+
+impl ContentReport {
+    pub fn count(&self, severity: ContentIssueSeverity) -> usize {
+        self.issues.iter().filter(|i| i.severity == severity).count()
+    }
+
+    fn push(&mut self, severity: ContentIssueSeverity, path: &Path, message: impl Into<String>) {
+        self.issues.push(ContentIssue {
+            severity,
+            path: path.to_path_buf(),
+            message: message.into(),
+        });
+    }
+}
+
+struct ParsedFile {
+    path: PathBuf,
+    /// Relative to the content root with `/` separators.
+    relative: String,
+    text: String,
+    value: Value,
+}
+
+fn content_files(directory: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(directory) else {
+        return;
+    };
+    for path in entries.flatten().map(|e| e.path()) {
+        if path.is_dir() {
+            content_files(&path, files);
+        } else if matches!(path.extension().and_then(|e| e.to_str()), Some("toml" | "json" | "ron")) {
+            files.push(path);
+        }
+    }
+}
+
+fn parse(path: &Path, text: &str) -> Result<Value, String> {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("toml") => {
+            let value: toml::Value = toml::from_str(text).map_err(|e| e.to_string())?;
+            serde_json::to_value(value).map_err(|e| e.to_string())
+        }
+        Some("json") => serde_json::from_str(text).map_err(|e| e.to_string()),
+        _ => {
+            let value: ron::Value = ron::from_str(text).map_err(|e| e.to_string())?;
+            // RON maps can have non-string keys; those files still pass the syntax check
+            Ok(serde_json::to_value(value).unwrap_or(Value::Null))
+        }
+    }
+}
+
+fn category_of(relative: &str) -> Option<&'static str> {
+    CATEGORIES
+        .iter()
+        .find(|(prefix, _)| relative.starts_with(prefix))
+        .map(|(_, category)| *category)
+}
+
+/// Ids a file defines: a top-level `id`, ids of tables in top-level arrays
+/// (`[[item]] id = ...`), or failing both the file stem (behavior trees are
+/// usually referenced by file name).
+fn defined_ids(file: &ParsedFile) -> Vec<String> {
+    let mut ids = Vec::new();
+    if let Some(object) = file.value.as_object() {
+        if let Some(id) = object.get("id").and_then(Value::as_str) {
+            ids.push(id.to_string());
+        }
+        for entry in object.values().filter_map(Value::as_array).flatten() {
+            if let Some(id) = entry.get("id").and_then(Value::as_str) {
+                ids.push(id.to_string());
+            }
+        }
+    }
+    if ids.is_empty() {
+        if let Some(stem) = file.path.file_stem().and_then(|s| s.to_str()) {
+            ids.push(stem.to_string());
+        }
+    }
+    ids
+}
+
+/// Visits every string in `value` with the key it sits under.
+fn visit_strings<'a>(value: &'a Value, key: Option<&'a str>, visit: &mut impl FnMut(Option<&'a str>, &'a str)) {
+    match value {
+        Value::String(text) => visit(key, text),
+        Value::Array(items) => items.iter().for_each(|item| visit_strings(item, key, visit)),
+        Value::Object(map) => map.iter().for_each(|(k, v)| visit_strings(v, Some(k.as_str()), visit)),
+        _ => {}
+    }
+}
+
+fn asset_path(text: &str) -> Option<&str> {
+    if text.contains("://") {
+        return None;
+    }
+    let path = text.split('#').next().unwrap_or(text);
+    let extension = Path::new(path).extension()?.to_str()?.to_ascii_lowercase();
+    ASSET_EXTENSIONS.contains(&extension.as_str()).then_some(path)
+}
+
+pub fn validate_content(content_root: &Path, assets_root: &Path) -> ContentReport {
+    let mut report = ContentReport::default();
+    let mut paths = Vec::new();
+    content_files(content_root, &mut paths);
+    paths.sort();
+
+    let mut files = Vec::new();
+    for path in paths {
+        report.files_checked += 1;
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) => {
+                report.push(ContentIssueSeverity::Error, &path, format!("unreadable: {}", e));
+                continue;
+            }
+        };
+        let value = match parse(&path, &text) {
+            Ok(value) => value,
+            Err(e) => {
+                report.push(ContentIssueSeverity::Error, &path, format!("syntax error: {}", e));
+                continue;
+            }
+        };
+        let relative = path
+            .strip_prefix(content_root)
+            .unwrap_or(&path)
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        files.push(ParsedFile {
+            path,
+            relative,
+            text,
+            value,
+        });
+    }
+
+    // Typed formats
+    for file in &files {
+        if let Some((_, check)) = SCHEMAS.iter().find(|(prefix, _)| file.relative.starts_with(prefix)) {
+            if let Err(e) = check(&file.text) {
+                report.push(ContentIssueSeverity::Error, &file.path, format!("schema error: {}", e));
+            }
+        }
+    }
+
+    // Database records: formats, numbers and overrides
+    let records_in = |prefix: &str| -> Vec<PathBuf> {
+        files
+            .iter()
+            .filter(|f| f.relative.starts_with(prefix) && f.relative.ends_with(".toml"))
+            .map(|f| f.path.clone())
+            .collect()
+    };
+    let (_, problems) = GameDatabase::from_files(&records_in("items/"), &records_in("abilities/"), &records_in("monsters/"));
+    for (path, problem) in problems {
+        report.push(ContentIssueSeverity::Error, &path, problem);
+    }
+    let migrations = content_root.join("database").join("migrations.toml");
+    if migrations.exists() {
+        if let Err(e) = ContentMigrations::load(&migrations) {
+            report.push(ContentIssueSeverity::Error, &migrations, e);
+        }
+    }
+
+    // Definitions by kind, then every reference against them
+    let mut defined: HashMap<&str, HashSet<String>> = HashMap::new();
+    for file in &files {
+        if let Some(category) = category_of(&file.relative) {
+            defined.entry(category).or_default().extend(defined_ids(file));
+        }
+    }
+    let mut unchecked: BTreeMap<&str, usize> = BTreeMap::new();
+    for file in &files {
+        let mut missing_assets = Vec::new();
+        let mut dangling = Vec::new();
+        visit_strings(&file.value, None, &mut |key, text| {
+            if let Some(path) = asset_path(text) {
+                if !assets_root.join(path).exists() {
+                    missing_assets.push(path.to_string());
+                }
+                return;
+            }
+            let Some((_, kind)) = REFERENCE_KEYS.iter().find(|(k, _)| Some(*k) == key) else {
+                return;
+            };
+            if text.is_empty() {
+                return;
+            }
+            match defined.get(kind) {
+                Some(ids) if !ids.contains(text) => dangling.push(format!("unknown {} '{}'", kind, text)),
+                Some(_) => {}
+                None => *unchecked.entry(kind).or_default() += 1,
+            }
+        });
+        for path in missing_assets {
+            report.push(
+                ContentIssueSeverity::Error,
+                &file.path,
+                format!("missing file {}", assets_root.join(path).display()),
+            );
+        }
+        for message in dangling {
+            report.push(ContentIssueSeverity::Error, &file.path, message);
+        }
+    }
+    for (kind, count) in unchecked {
+        report.push(
+            ContentIssueSeverity::Warning,
+            content_root,
+            format!("{} {} reference(s) unchecked: no {} content found", count, kind, kind),
+        );
+    }
+
+    // Quest and dialog graph structure
+    let items = defined.get("item").cloned().unwrap_or_default();
+    let quests: HashSet<&str> = defined.get("quest").map(|ids| ids.iter().map(String::as_str).collect()).unwrap_or_default();
+    for file in &files {
+        let issues = if file.relative.starts_with("quests/") {
+            match toml::from_str::<QuestDef>(&file.text) {
+                Ok(def) => validate_quest(&def, &items),
+                Err(_) => continue,
+            }
+        } else if file.relative.starts_with("dialog/") {
+            match serde_json::from_str::<DialogTreeDef>(&file.text) {
+                Ok(def) => validate_dialog(&def, &items, &quests),
+                Err(_) => continue,
+            }
+        } else {
+            continue;
+        };
+        for issue in issues {
+            // Item and quest references were already checked against the same ids
+            if issue.message.contains("unknown item") || issue.message.contains("unknown quest") {
+                continue;
+            }
+            let severity = match issue.severity {
+                NarrativeIssueSeverity::Error => ContentIssueSeverity::Error,
+                NarrativeIssueSeverity::Warning => ContentIssueSeverity::Warning,
+            };
+            report.push(severity, &file.path, issue.message);
+        }
+    }
+
+    report.issues.sort_by(|a, b| b.severity.cmp(&a.severity).then_with(|| a.path.cmp(&b.path)));
+    report
+}
+
+fn arg_value(args: &[String], name: &str) -> Option<String> {
+    args.iter().position(|a| a == name).and_then(|i| args.get(i + 1)).cloned()
+}
+
+/// Runs the check from the command line and returns the process exit code:
+/// 0 clean, 1 errors (or warnings with `--strict`), 2 no content directory.
+///
+/// `--content-dir <path>` and `--assets-dir <path>` override `content` and `assets`.
+pub fn run_cli() -> i32 {
+    let args: Vec<String> = std::env::args().collect();
+    let content_root = PathBuf::from(arg_value(&args, "--content-dir").unwrap_or_else(|| "content".into()));
+    let assets_root = PathBuf::from(arg_value(&args, "--assets-dir").unwrap_or_else(|| "assets".into()));
+    let strict = args.iter().any(|a| a == "--strict");
+
+    if !content_root.is_dir() {
+        eprintln!("Content directory {} not found", content_root.display());
+        return 2;
+    }
+
+    let report = validate_content(&content_root, &assets_root);
+    for issue in &report.issues {
+        let label = match issue.severity {
+            ContentIssueSeverity::Error => "error",
+            ContentIssueSeverity::Warning => "warning",
+        };
+        println!("{}: {}: {}", label, issue.path.display(), issue.message);
+    }
+    let errors = report.count(ContentIssueSeverity::Error);
+    let warnings = report.count(ContentIssueSeverity::Warning);
+    println!(
+        "Checked {} content files: {} errors, {} warnings",
+        report.files_checked, errors, warnings
+    );
+
+    if errors > 0 || (strict && warnings > 0) {
+        1
+    } else {
+        0
+    }
+}
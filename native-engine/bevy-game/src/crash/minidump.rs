@@ -0,0 +1,159 @@
+//! Native crash capture. The game starts a copy of itself with
+//! `--crash-monitor <socket> <pid>`; when the game faults, the signal or
+//! exception handler asks the monitor to write a minidump, since the
+//! crashed process can't be trusted to do it. The monitor also writes a
+//! JSON report next to the dump so the launcher treats both kinds of crash
+//! alike. Symbolicate dumps offline with `minidump-stackwalk` and the
+//! build's symbol files.
+
+use std::fs::File;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::atomic::AtomicBool;
+use std::time::Duration;
+
+use super::{crash_directory, unix_time, CrashReport, SystemInfo};
+
+/// Keeps the handler attached for the life of the process.
+pub struct MinidumpGuard {
+    _handler: crash_handler::CrashHandler,
+}
+
+/// Starts the monitor and attaches the crash handler. `None` (with a
+/// message) when either fails; panics still get a report.
+pub fn attach() -> Option<MinidumpGuard> {
+    let socket = format!("mmo-crash-{}", std::process::id());
+    let executable = std::env::current_exe().ok()?;
+    if let Err(e) = Command::new(executable)
+        .arg("--crash-monitor")
+        .arg(&socket)
+        .arg(std::process::id().to_string())
+        .spawn()
+    {
+        eprintln!("Crash monitor not started: {}", e);
+        return None;
+    }
+
+    // The monitor needs a moment to create its socket
+    let mut client = None;
+    for _ in 0..50 {
+        if let Ok(c) = minidumper::Client::with_name(socket.as_str()) {
+            client = Some(c);
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    let Some(client) = client else {
+        eprintln!("Crash monitor not reachable; native crashes won't produce minidumps");
+        return None;
+    };
+
+    // SAFETY: the closure only asks the monitor for a dump over an already
+    // open socket; it doesn't allocate or take locks.
+    let handler = crash_handler::CrashHandler::attach(unsafe {
+        crash_handler::make_crash_event(move |context: &crash_handler::CrashContext| {
+            crash_handler::CrashEventResult::Handled(client.request_dump(context).is_ok())
+        })
+    });
+    match handler {
+        Ok(handler) => Some(MinidumpGuard { _handler: handler }),
+        Err(e) => {
+            eprintln!("Crash handler not attached: {}", e);
+            None
+        }
+    }
+}
+
+struct MonitorHandler {
+    game_pid: String,
+    stem: std::sync::Mutex<Option<String>>,
+}
+
+impl minidumper::ServerHandler for MonitorHandler {
+    fn create_minidump_file(&self) -> Result<(File, PathBuf), std::io::Error> {
+        let directory = crash_directory();
+        std::fs::create_dir_all(&directory)?;
+        let stem = format!("crash-{}-{}", unix_time(), self.game_pid);
+        let path = directory.join(format!("{}.dmp", stem));
+        if let Ok(mut current) = self.stem.lock() {
+            *current = Some(stem);
+        }
+        Ok((File::create(&path)?, path))
+    }
+
+    fn on_minidump_created(
+        &self,
+        result: Result<minidumper::MinidumpBinary, minidumper::Error>,
+    ) -> minidumper::LoopAction {
+        let stem = self.stem.lock().ok().and_then(|s| s.clone());
+        match (result, stem) {
+            (Ok(binary), Some(stem)) => {
+                let mut report = CrashReport {
+                    version: env!("CARGO_PKG_VERSION").to_string(),
+                    debug_build: cfg!(debug_assertions),
+                    time: unix_time(),
+                    message: "native crash".to_string(),
+                    location: None,
+                    thread: String::new(),
+                    backtrace: String::new(),
+                    system: SystemInfo::current(),
+                    context: Default::default(),
+                    log: Vec::new(),
+                    minidump: binary.path.file_name().map(|n| n.to_string_lossy().to_string()),
+                };
+                report.system.args.clear();
+                let path = crash_directory().join(format!("{}.json", stem));
+                if let Ok(text) = serde_json::to_string_pretty(&report) {
+                    let _ = std::fs::write(&path, text);
+                }
+                eprintln!("Minidump written to {}", binary.path.display());
+            }
+            (Ok(binary), None) => eprintln!("Minidump written to {}", binary.path.display()),
+            (Err(e), _) => eprintln!("Minidump failed: {}", e),
+        }
+        minidumper::LoopAction::Exit
+    }
+
+    fn on_message(&self, _kind: u32, _buffer: Vec<u8>) {}
+
+    fn on_client_disconnected(&self, clients: usize) -> minidumper::LoopAction {
+        // The game exited normally
+        if clients == 0 {
+            minidumper::LoopAction::Exit
+        } else {
+            minidumper::LoopAction::Continue
+        }
+    }
+}
+
+/// `--crash-monitor <socket> <pid>`: serves dump requests until the game
+/// disconnects or crashes.
+pub fn run_monitor() -> i32 {
+    let args: Vec<String> = std::env::args().collect();
+    let Some(index) = args.iter().position(|a| a == "--crash-monitor") else {
+        return 2;
+    };
+    let (Some(socket), Some(pid)) = (args.get(index + 1), args.get(index + 2)) else {
+        eprintln!("usage: --crash-monitor <socket> <pid>");
+        return 2;
+    };
+    let mut server = match minidumper::Server::with_name(socket.as_str()) {
+        Ok(server) => server,
+        Err(e) => {
+            eprintln!("Crash monitor failed to listen: {}", e);
+            return 1;
+        }
+    };
+    let handler = MonitorHandler {
+        game_pid: pid.clone(),
+        stem: Default::default(),
+    };
+    let shutdown = AtomicBool::new(false);
+    match server.run(Box::new(handler), &shutdown, None) {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("Crash monitor stopped: {}", e);
+            1
+        }
+    }
+}
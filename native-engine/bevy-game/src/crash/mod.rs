@@ -0,0 +1,287 @@
+//! Crash reports. A panic writes `crashes/crash-<time>-<pid>.json` with the
+//! message, a symbolicated backtrace, system info, recent log overlay lines
+//! and whatever context systems recorded (GPU, world seed, ...). Builds with
+//! the `minidump` feature also run a monitor process that writes a `.dmp`
+//! for native crashes (access violations, aborts) the panic hook never sees.
+//!
+//! The launcher picks reports up from the crash directory (`MMO_CRASH_DIR`,
+//! default `crashes/`) and offers to send them on its next run. When the
+//! player has opted in, the game also uploads unsent reports to
+//! `MMO_CRASH_UPLOAD_URL` on the next start.
+
+use ::tracing::{Event, Subscriber};
+use bevy::log::BoxedLayer;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+#[cfg(feature = "minidump")]
+pub mod minidump;
+
+use crate::launcher_link::{self, MessageVisitor};
+use crate::settings::UserSettings;
+
+/// Log lines kept for the next crash report.
+const RECENT_LOG_LINES: usize = 200;
+
+static RECENT_LOG: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+static CONTEXT: Mutex<BTreeMap<String, String>> = Mutex::new(BTreeMap::new());
+
+pub fn crash_directory() -> PathBuf {
+    std::env::var("MMO_CRASH_DIR")
+        .ok()
+        .filter(|v| !v.is_empty())
+        .map_or_else(|| PathBuf::from("crashes"), PathBuf::from)
+}
+
+/// Keeps a line for the next crash report. Called from the log overlay and
+/// [`crash_log_layer`].
+pub fn record_log_line(level: &str, text: &str) {
+    if let Ok(mut lines) = RECENT_LOG.lock() {
+        lines.push_back(format!("[{}] {}", level, text));
+        while lines.len() > RECENT_LOG_LINES {
+            lines.pop_front();
+        }
+    }
+}
+
+struct CrashLogLayer;
+
+impl<S: Subscriber> tracing_subscriber::Layer<S> for CrashLogLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: tracing_subscriber::layer::Context<'_, S>) {
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+        let metadata = event.metadata();
+        record_log_line(metadata.level().as_str(), &format!("{}: {}", metadata.target(), visitor.0));
+    }
+}
+
+/// `LogPlugin::custom_layer` part that keeps the game's log for crash reports.
+pub fn crash_log_layer() -> BoxedLayer {
+    Box::new(CrashLogLayer)
+}
+
+/// Sets a key shown in the next crash report, e.g. `gpu` or `zone`.
+pub fn set_crash_context(key: &str, value: impl Into<String>) {
+    if let Ok(mut context) = CONTEXT.lock() {
+        context.insert(key.to_string(), value.into());
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SystemInfo {
+    pub os: String,
+    pub arch: String,
+    pub cpus: usize,
+    pub executable: String,
+    pub args: Vec<String>,
+}
+
+impl SystemInfo {
+    pub fn current() -> Self {
+        Self {
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            cpus: std::thread::available_parallelism().map_or(0, |n| n.get()),
+            executable: std::env::current_exe().map(|p| p.display().to_string()).unwrap_or_default(),
+            args: std::env::args().collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrashReport {
+    pub version: String,
+    pub debug_build: bool,
+    /// Unix seconds.
+    pub time: u64,
+    pub message: String,
+    pub location: Option<String>,
+    pub thread: String,
+    pub backtrace: String,
+    pub system: SystemInfo,
+    pub context: BTreeMap<String, String>,
+    pub log: Vec<String>,
+    /// Companion `.dmp` in the same directory, for native crashes.
+    #[serde(default)]
+    pub minidump: Option<String>,
+}
+
+impl CrashReport {
+    pub fn capture(message: String, location: Option<String>) -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            debug_build: cfg!(debug_assertions),
+            time: unix_time(),
+            message,
+            location,
+            thread: std::thread::current().name().unwrap_or("<unnamed>").to_string(),
+            // Resolved in-process, so frames have names wherever debug info shipped
+            backtrace: std::backtrace::Backtrace::force_capture().to_string(),
+            system: SystemInfo::current(),
+            context: CONTEXT.lock().map(|c| c.clone()).unwrap_or_default(),
+            log: RECENT_LOG.lock().map(|l| l.iter().cloned().collect()).unwrap_or_default(),
+            minidump: None,
+        }
+    }
+
+    pub fn write(&self) -> std::io::Result<PathBuf> {
+        let directory = crash_directory();
+        std::fs::create_dir_all(&directory)?;
+        let path = directory.join(format!("crash-{}-{}.json", self.time, std::process::id()));
+        let text = serde_json::to_string_pretty(self).map_err(std::io::Error::other)?;
+        std::fs::write(&path, text)?;
+        Ok(path)
+    }
+}
+
+fn unix_time() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Replaces the default panic output: writes a crash report and prints
+/// where it went. Started by the launcher, the game tells it about the crash
+/// and exits; otherwise it waits for Enter so a console window doesn't vanish.
+pub fn install_panic_hook() {
+    std::panic::set_hook(Box::new(|panic_info| {
+        let message = panic_info
+            .payload()
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| panic_info.payload().downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "<non-string panic payload>".to_string());
+        let location = panic_info
+            .location()
+            .map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column()));
+
+        eprintln!("================================================================");
+        eprintln!("  GAME CRASHED!");
+        eprintln!("================================================================");
+        eprintln!("{}", panic_info);
+        if let Some(location) = &location {
+            eprintln!("  Location: {}", location);
+        }
+        let report = CrashReport::capture(message.clone(), location);
+        match report.write() {
+            Ok(path) => eprintln!("  Crash report: {}", path.display()),
+            Err(e) => eprintln!("  Failed to write crash report: {}", e),
+        }
+        eprintln!("================================================================");
+        if let Some(launcher) = launcher_link::connected() {
+            launcher.fatal("crashed", &message);
+            return;
+        }
+        eprintln!("Press Enter to exit...");
+        let mut input = String::new();
+        let _ = std::io::stdin().read_line(&mut input);
+    }));
+}
+
+pub struct CrashReporterPlugin;
+
+impl Plugin for CrashReporterPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, upload_pending_reports)
+            .add_systems(PostStartup, record_startup_context);
+    }
+}
+
+fn record_startup_context(
+    seed: Option<Res<crate::worldgen::WorldSeed>>,
+    database: Option<Res<crate::database::GameDatabase>>,
+    adapter: Option<Res<bevy::render::renderer::RenderAdapterInfo>>,
+) {
+    if let Some(seed) = seed {
+        set_crash_context("world_seed", seed.seed.to_string());
+    }
+    if let Some(database) = database {
+        set_crash_context("content_version", database.migrations.current_version().to_string());
+    }
+    match adapter {
+        Some(adapter) => {
+            set_crash_context("gpu", format!("{} ({:?})", adapter.name, adapter.backend));
+            set_crash_context("gpu_driver", format!("{} {}", adapter.driver, adapter.driver_info));
+        }
+        None => set_crash_context("gpu", "headless"),
+    }
+}
+
+/// Reports not yet uploaded. Uploaded ones are renamed to `.json.sent`.
+pub fn pending_reports() -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(crash_directory()) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().and_then(|e| e.to_str()) == Some("json"))
+        .collect();
+    paths.sort();
+    paths
+}
+
+fn upload_pending_reports(settings: Option<Res<UserSettings>>) {
+    let pending = pending_reports();
+    if pending.is_empty() {
+        return;
+    }
+    let opted_in = settings.is_some_and(|s| s.privacy.send_crash_reports);
+    let url = std::env::var("MMO_CRASH_UPLOAD_URL").ok().filter(|v| !v.is_empty());
+    let (true, Some(url)) = (opted_in, url) else {
+        info!("{} crash report(s) in {:?}, not uploaded", pending.len(), crash_directory());
+        return;
+    };
+    // Off the main thread: a slow endpoint shouldn't hold up startup
+    std::thread::spawn(move || {
+        for path in pending {
+            match upload_report(&url, &path) {
+                Ok(()) => {
+                    let _ = std::fs::rename(&path, path.with_extension("json.sent"));
+                    info!("Uploaded crash report {:?}", path);
+                }
+                Err(e) => {
+                    warn!("Crash report upload failed, will retry next start: {}", e);
+                    break;
+                }
+            }
+        }
+    });
+}
+
+#[cfg(feature = "networking")]
+fn upload_report(url: &str, path: &std::path::Path) -> Result<(), String> {
+    let client = reqwest::blocking::Client::new();
+    let post = |url: String, content_type: &str, body: Vec<u8>| -> Result<(), String> {
+        let response = client
+            .post(url)
+            .header("Content-Type", content_type)
+            .body(body)
+            .send()
+            .map_err(|e| e.to_string())?;
+        if !response.status().is_success() {
+            return Err(format!("server answered {}", response.status()));
+        }
+        Ok(())
+    };
+
+    let report = std::fs::read(path).map_err(|e| e.to_string())?;
+    post(url.to_string(), "application/json", report)?;
+    let minidump = path.with_extension("dmp");
+    if minidump.exists() {
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        let dump = std::fs::read(&minidump).map_err(|e| e.to_string())?;
+        post(format!("{}/minidump?report={}", url.trim_end_matches('/'), stem), "application/octet-stream", dump)?;
+        let _ = std::fs::rename(&minidump, minidump.with_extension("dmp.sent"));
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "networking"))]
+fn upload_report(_url: &str, _path: &std::path::Path) -> Result<(), String> {
+    Err("this build has no networking feature".to_string())
+}
@@ -0,0 +1,244 @@
+//! Content versions and save migration. `content/database/migrations.toml`
+//! lists, per content version, the record ids that were renamed or removed:
+//!
+//! ```toml
+//! [[migration]]
+//! version = 2
+//! rename = [{ kind = "item", from = "old_sword", to = "iron_sword" }]
+//! remove = [{ kind = "ability", id = "test_bolt" }]
+//! ```
+//!
+//! Saves record the content version they were written with. Loading an older
+//! save replays the later migrations on it: renamed ids are rewritten and
+//! entries holding a removed id are dropped.
+
+use serde::Deserialize;
+use serde_json::Value;
+use std::path::Path;
+
+use super::DatabaseConfig;
+use crate::worldgen::WorldSeed;
+
+/// Save files keep their content version under this key.
+pub const SAVE_VERSION_KEY: &str = "content_version";
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RenameDef {
+    pub kind: String,
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RemoveDef {
+    pub kind: String,
+    pub id: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MigrationDef {
+    pub version: u32,
+    #[serde(default)]
+    pub note: String,
+    #[serde(default)]
+    pub rename: Vec<RenameDef>,
+    #[serde(default)]
+    pub remove: Vec<RemoveDef>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct MigrationsFile {
+    #[serde(default, rename = "migration")]
+    migrations: Vec<MigrationDef>,
+}
+
+/// Keys whose values are ids of a record kind, in saves as in content.
+const REFERENCE_KEYS: &[(&str, &str)] = &[
+    ("item", "item"),
+    ("item_id", "item"),
+    ("items", "item"),
+    ("ability", "ability"),
+    ("ability_id", "ability"),
+    ("abilities", "ability"),
+    ("monster", "monster"),
+    ("monster_id", "monster"),
+    ("monsters", "monster"),
+];
+
+fn kind_of(key: &str) -> Option<&'static str> {
+    REFERENCE_KEYS.iter().find(|(k, _)| *k == key).map(|(_, kind)| *kind)
+}
+
+/// What migrating one save changed.
+#[derive(Debug, Clone, Default)]
+pub struct SaveMigrationReport {
+    pub from_version: u32,
+    pub to_version: u32,
+    pub renamed: usize,
+    pub removed: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ContentMigrations {
+    /// Sorted by version.
+    migrations: Vec<MigrationDef>,
+}
+
+impl ContentMigrations {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let file: MigrationsFile = toml::from_str(&text).map_err(|e| e.to_string())?;
+        let mut migrations = file.migrations;
+        migrations.sort_by_key(|m| m.version);
+        if let Some(pair) = migrations.windows(2).find(|pair| pair[0].version == pair[1].version) {
+            return Err(format!("version {} is listed twice", pair[0].version));
+        }
+        if let Some(migration) = migrations.iter().find(|m| m.version < 2) {
+            return Err(format!(
+                "version {} is invalid; content starts at 1, so migrations start at 2",
+                migration.version
+            ));
+        }
+        Ok(Self { migrations })
+    }
+
+    /// Version of the loaded content: 1 until the first migration.
+    pub fn current_version(&self) -> u32 {
+        self.migrations.last().map_or(1, |m| m.version)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &MigrationDef> {
+        self.migrations.iter()
+    }
+
+    /// Brings a save (JSON) up to the current content version in place.
+    pub fn migrate_save(&self, save: &mut Value) -> Result<SaveMigrationReport, String> {
+        let from_version = save.get(SAVE_VERSION_KEY).and_then(Value::as_u64).unwrap_or(1) as u32;
+        let to_version = self.current_version();
+        if from_version > to_version {
+            return Err(format!(
+                "save is from content version {}, newer than the installed {}",
+                from_version, to_version
+            ));
+        }
+
+        let mut report = SaveMigrationReport {
+            from_version,
+            to_version,
+            ..Default::default()
+        };
+        for migration in self.migrations.iter().filter(|m| m.version > from_version) {
+            apply(migration, save, None, &mut report);
+        }
+        if let Some(object) = save.as_object_mut() {
+            object.insert(SAVE_VERSION_KEY.to_string(), Value::from(to_version));
+        }
+        // Saves from before seeds were recorded were all on the default world
+        if WorldSeed::from_save(save).is_none() {
+            WorldSeed::default().write_to_save(save);
+        }
+        Ok(report)
+    }
+}
+
+fn removes(migration: &MigrationDef, kind: &str, id: &str) -> bool {
+    migration.remove.iter().any(|r| r.kind == kind && r.id == id)
+}
+
+/// Rewrites renamed ids under reference keys and drops array entries that
+/// hold a removed id, either directly (`"items": ["x"]`) or as a reference
+/// field of an object (`"inventory": [{ "item": "x", "count": 2 }]`).
+fn apply(migration: &MigrationDef, value: &mut Value, key: Option<&str>, report: &mut SaveMigrationReport) {
+    let kind = key.and_then(kind_of);
+    match value {
+        Value::String(text) => {
+            let Some(kind) = kind else {
+                return;
+            };
+            if let Some(rename) = migration.rename.iter().find(|r| r.kind == kind && r.from == *text) {
+                *text = rename.to.clone();
+                report.renamed += 1;
+            }
+        }
+        Value::Array(entries) => {
+            let before = entries.len();
+            entries.retain(|entry| match entry {
+                Value::String(id) => !kind.is_some_and(|kind| removes(migration, kind, id)),
+                Value::Object(fields) => !fields.iter().any(|(field, v)| {
+                    matches!((kind_of(field), v.as_str()), (Some(kind), Some(id)) if removes(migration, kind, id))
+                }),
+                _ => true,
+            });
+            report.removed += before - entries.len();
+            for entry in entries.iter_mut() {
+                apply(migration, entry, key, report);
+            }
+        }
+        Value::Object(fields) => {
+            for (field, v) in fields.iter_mut() {
+                apply(migration, v, Some(field.as_str()), report);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// `--migrate-save <file>`: upgrades a JSON save to the installed content
+/// version, keeping the original as `<file>.bak`.
+pub fn run_migrate_cli() -> i32 {
+    let args: Vec<String> = std::env::args().collect();
+    let Some(path) = args.iter().position(|a| a == "--migrate-save").and_then(|i| args.get(i + 1)) else {
+        eprintln!("usage: --migrate-save <save.json>");
+        return 2;
+    };
+    let path = Path::new(path);
+
+    let config = DatabaseConfig::default();
+    let migrations = if config.migrations.exists() {
+        match ContentMigrations::load(&config.migrations) {
+            Ok(migrations) => migrations,
+            Err(e) => {
+                eprintln!("Invalid {}: {}", config.migrations.display(), e);
+                return 1;
+            }
+        }
+    } else {
+        ContentMigrations::default()
+    };
+
+    let result = std::fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|text| serde_json::from_str::<Value>(&text).map_err(|e| e.to_string()))
+        .and_then(|mut save| {
+            let report = migrations.migrate_save(&mut save)?;
+            if report.from_version != report.to_version {
+                let backup = path.with_extension("json.bak");
+                std::fs::copy(path, &backup).map_err(|e| format!("backup failed: {}", e))?;
+                let text = serde_json::to_string_pretty(&save).map_err(|e| e.to_string())?;
+                std::fs::write(path, text).map_err(|e| e.to_string())?;
+            }
+            Ok(report)
+        });
+
+    match result {
+        Ok(report) if report.from_version == report.to_version => {
+            println!("{} is already at content version {}", path.display(), report.to_version);
+            0
+        }
+        Ok(report) => {
+            println!(
+                "Migrated {} from content version {} to {}: {} renamed, {} removed",
+                path.display(),
+                report.from_version,
+                report.to_version,
+                report.renamed,
+                report.removed
+            );
+            0
+        }
+        Err(e) => {
+            eprintln!("Failed to migrate {}: {}", path.display(), e);
+            1
+        }
+    }
+}
@@ -0,0 +1,679 @@
+//! Items, abilities and monsters in one place. Records come from
+//! `content/items`, `content/abilities` and `content/monsters` in every
+//! active pack (`[[item]]`, `[[ability]]` and `[[monster]]` tables). Each
+//! record has a string id that content refers to and a stable number that
+//! saves and the network use; a later pack may redefine a record by id but
+//! must keep its number.
+//!
+//! Code reads records through typed handles: a [`DbId<ItemDef>`] can't be
+//! used to look up an ability, and ids the engine itself depends on are
+//! declared as [`DbKey`] constants instead of string literals.
+
+use bevy::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashMap;
+use std::fmt;
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+
+use crate::content_packs::ContentPacks;
+use crate::hazards::DamageSchool;
+use crate::hot_reload::{ContentChangedEvent, ContentReloadedEvent};
+
+mod migration;
+pub use migration::*;
+
+/// Version of the record formats below. Bump it when a field changes
+/// meaning, alongside a migration for content and saves that need one.
+pub const DATABASE_SCHEMA_VERSION: u32 = 1;
+
+/// A kind of record stored in a [`Table`].
+pub trait Record: DeserializeOwned + Send + Sync + 'static {
+    /// Name used in messages and migration files, e.g. `item`.
+    const KIND: &'static str;
+    /// Array-of-tables key in content files, e.g. `item` for `[[item]]`.
+    const TABLE: &'static str;
+
+    fn key(&self) -> &str;
+    fn number(&self) -> u32;
+}
+
+/// Stable numeric id of a record of type `T`.
+pub struct DbId<T> {
+    number: u32,
+    _kind: PhantomData<fn() -> T>,
+}
+
+impl<T> DbId<T> {
+    pub const fn new(number: u32) -> Self {
+        Self {
+            number,
+            _kind: PhantomData,
+        }
+    }
+
+    pub fn number(self) -> u32 {
+        self.number
+    }
+}
+
+impl<T> Clone for DbId<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for DbId<T> {}
+
+impl<T> PartialEq for DbId<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.number == other.number
+    }
+}
+
+impl<T> Eq for DbId<T> {}
+
+impl<T> std::hash::Hash for DbId<T> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.number.hash(state);
+    }
+}
+
+impl<T> fmt::Debug for DbId<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "DbId({})", self.number)
+    }
+}
+
+impl<T> Serialize for DbId<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.number.serialize(serializer)
+    }
+}
+
+impl<'de, T> Deserialize<'de> for DbId<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        u32::deserialize(deserializer).map(Self::new)
+    }
+}
+
+/// A record the engine refers to by id, declared once as a constant:
+/// `const HEARTHSTONE: DbKey<ItemDef> = DbKey::new("hearthstone");`.
+pub struct DbKey<T> {
+    key: &'static str,
+    _kind: PhantomData<fn() -> T>,
+}
+
+impl<T> DbKey<T> {
+    pub const fn new(key: &'static str) -> Self {
+        Self { key, _kind: PhantomData }
+    }
+
+    pub fn key(&self) -> &'static str {
+        self.key
+    }
+}
+
+impl<T> Clone for DbKey<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for DbKey<T> {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemQuality {
+    Poor,
+    #[default]
+    Common,
+    Uncommon,
+    Rare,
+    Epic,
+    Legendary,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EquipSlot {
+    Head,
+    Neck,
+    Shoulders,
+    Back,
+    Chest,
+    Wrists,
+    Hands,
+    Waist,
+    Legs,
+    Feet,
+    Finger,
+    Trinket,
+    MainHand,
+    OffHand,
+    TwoHand,
+    Ranged,
+}
+
+/// Stat bonuses while equipped.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ItemStats {
+    pub armor: f32,
+    pub strength: f32,
+    pub agility: f32,
+    pub intellect: f32,
+    pub stamina: f32,
+    pub spirit: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WeaponStats {
+    pub damage_min: f32,
+    pub damage_max: f32,
+    /// Seconds between swings.
+    pub attack_speed: f32,
+    #[serde(default)]
+    pub school: DamageSchool,
+}
+
+impl WeaponStats {
+    pub fn dps(&self) -> f32 {
+        (self.damage_min + self.damage_max) * 0.5 / self.attack_speed.max(0.1)
+    }
+}
+
+/// Market group for vendor pricing; items without one are grouped by
+/// their equipment data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemCategory {
+    Weapon,
+    Armor,
+    Consumable,
+    Reagent,
+    TradeGood,
+    Misc,
+}
+
+impl ItemCategory {
+    pub const ALL: [ItemCategory; 6] =
+        [Self::Weapon, Self::Armor, Self::Consumable, Self::Reagent, Self::TradeGood, Self::Misc];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ItemCategory::Weapon => "weapon",
+            ItemCategory::Armor => "armor",
+            ItemCategory::Consumable => "consumable",
+            ItemCategory::Reagent => "reagent",
+            ItemCategory::TradeGood => "trade_good",
+            ItemCategory::Misc => "misc",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ItemDef {
+    pub id: String,
+    /// Stable number for saves and the network; never reused.
+    pub number: u32,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub quality: ItemQuality,
+    #[serde(default)]
+    pub item_level: u32,
+    #[serde(default)]
+    pub required_level: u32,
+    #[serde(default = "default_stack")]
+    pub max_stack: u32,
+    /// Vendor price in copper.
+    #[serde(default)]
+    pub sell_price: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<ItemCategory>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slot: Option<EquipSlot>,
+    #[serde(default)]
+    pub stats: ItemStats,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weapon: Option<WeaponStats>,
+    #[serde(default)]
+    pub icon: String,
+}
+
+fn default_stack() -> u32 {
+    1
+}
+
+impl ItemDef {
+    /// The explicit category, else weapon or armor by equipment data.
+    pub fn market_category(&self) -> ItemCategory {
+        match (self.category, &self.weapon, self.slot) {
+            (Some(category), _, _) => category,
+            (None, Some(_), _) => ItemCategory::Weapon,
+            (None, None, Some(_)) => ItemCategory::Armor,
+            (None, None, None) => ItemCategory::Misc,
+        }
+    }
+}
+
+impl Record for ItemDef {
+    const KIND: &'static str = "item";
+    const TABLE: &'static str = "item";
+
+    fn key(&self) -> &str {
+        &self.id
+    }
+
+    fn number(&self) -> u32 {
+        self.number
+    }
+}
+
+/// What an ability spends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AbilityResource {
+    None,
+    #[default]
+    Mana,
+    Rage,
+    Energy,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AbilityDef {
+    pub id: String,
+    pub number: u32,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub school: DamageSchool,
+    #[serde(default)]
+    pub resource: AbilityResource,
+    #[serde(default)]
+    pub cost: f32,
+    /// Seconds; zero is instant.
+    #[serde(default)]
+    pub cast_time: f32,
+    #[serde(default)]
+    pub cooldown: f32,
+    /// Meters; zero is melee range.
+    #[serde(default)]
+    pub range: f32,
+    /// `[min, max]` damage per use.
+    #[serde(default)]
+    pub damage: [f32; 2],
+    #[serde(default)]
+    pub healing: [f32; 2],
+    #[serde(default = "default_true")]
+    pub triggers_global_cooldown: bool,
+    #[serde(default)]
+    pub icon: String,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Record for AbilityDef {
+    const KIND: &'static str = "ability";
+    const TABLE: &'static str = "ability";
+
+    fn key(&self) -> &str {
+        &self.id
+    }
+
+    fn number(&self) -> u32 {
+        self.number
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MonsterDef {
+    pub id: String,
+    pub number: u32,
+    pub name: String,
+    /// Inclusive `[min, max]` level it spawns at.
+    pub level_range: [u32; 2],
+    /// Health at the lowest level...
+    pub health: f32,
+    /// ...plus this much per level above it.
+    #[serde(default)]
+    pub health_per_level: f32,
+    #[serde(default)]
+    pub mana: f32,
+    #[serde(default)]
+    pub armor: f32,
+    /// `[min, max]` melee damage per swing.
+    #[serde(default)]
+    pub damage: [f32; 2],
+    #[serde(default = "default_attack_speed")]
+    pub attack_speed: f32,
+    #[serde(default)]
+    pub school: DamageSchool,
+    #[serde(default)]
+    pub abilities: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loot_table: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub behavior_tree: Option<String>,
+    #[serde(default)]
+    pub model: String,
+    #[serde(default)]
+    pub xp: u32,
+}
+
+fn default_attack_speed() -> f32 {
+    2.0
+}
+
+impl MonsterDef {
+    pub fn health_at(&self, level: u32) -> f32 {
+        let above = level.saturating_sub(self.level_range[0]) as f32;
+        self.health + self.health_per_level * above
+    }
+}
+
+impl Record for MonsterDef {
+    const KIND: &'static str = "monster";
+    const TABLE: &'static str = "monster";
+
+    fn key(&self) -> &str {
+        &self.id
+    }
+
+    fn number(&self) -> u32 {
+        self.number
+    }
+}
+
+/// All records of one kind, by string id and by number.
+pub struct Table<T> {
+    records: Vec<T>,
+    sources: Vec<PathBuf>,
+    by_key: HashMap<String, usize>,
+    by_number: HashMap<u32, usize>,
+}
+
+impl<T> Default for Table<T> {
+    fn default() -> Self {
+        Self {
+            records: Vec::new(),
+            sources: Vec::new(),
+            by_key: HashMap::new(),
+            by_number: HashMap::new(),
+        }
+    }
+}
+
+impl<T: Record> Table<T> {
+    pub fn get(&self, id: DbId<T>) -> Option<&T> {
+        self.by_number.get(&id.number).map(|&i| &self.records[i])
+    }
+
+    pub fn by_key(&self, key: &str) -> Option<&T> {
+        self.by_key.get(key).map(|&i| &self.records[i])
+    }
+
+    pub fn lookup(&self, key: DbKey<T>) -> Option<&T> {
+        self.by_key(key.key)
+    }
+
+    pub fn id(&self, key: &str) -> Option<DbId<T>> {
+        self.by_key(key).map(|record| DbId::new(record.number()))
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.by_key.contains_key(key)
+    }
+
+    /// File the record was last defined in.
+    pub fn source(&self, key: &str) -> Option<&Path> {
+        self.by_key.get(key).map(|&i| self.sources[i].as_path())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.records.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Adds or, for an id already present, replaces a record. Replacements
+    /// must keep the number, and a number belongs to one id only.
+    fn insert(&mut self, record: T, source: &Path) -> Result<(), String> {
+        let key = record.key().to_string();
+        let number = record.number();
+        if let Some(&existing) = self.by_number.get(&number) {
+            if self.records[existing].key() != key {
+                return Err(format!(
+                    "{} '{}' reuses number {} of '{}' ({})",
+                    T::KIND,
+                    key,
+                    number,
+                    self.records[existing].key(),
+                    self.sources[existing].display()
+                ));
+            }
+        }
+        match self.by_key.get(&key) {
+            Some(&index) => {
+                let previous = self.records[index].number();
+                if previous != number {
+                    return Err(format!(
+                        "{} '{}' overrides number {} with {}; numbers are permanent",
+                        T::KIND,
+                        key,
+                        previous,
+                        number
+                    ));
+                }
+                self.records[index] = record;
+                self.sources[index] = source.to_path_buf();
+            }
+            None => {
+                self.by_key.insert(key, self.records.len());
+                self.by_number.insert(number, self.records.len());
+                self.records.push(record);
+                self.sources.push(source.to_path_buf());
+            }
+        }
+        Ok(())
+    }
+
+    /// Loads every `[[T::TABLE]]` entry of `paths` in order. Bad records are
+    /// skipped; the problems are returned with their file.
+    fn load(&mut self, paths: &[PathBuf], problems: &mut Vec<(PathBuf, String)>) {
+        for path in paths {
+            let parsed = std::fs::read_to_string(path)
+                .map_err(|e| e.to_string())
+                .and_then(|text| text.parse::<toml::Value>().map_err(|e| e.to_string()));
+            let value = match parsed {
+                Ok(value) => value,
+                Err(e) => {
+                    problems.push((path.clone(), e));
+                    continue;
+                }
+            };
+            let entries = value.get(T::TABLE).and_then(|v| v.as_array()).cloned().unwrap_or_default();
+            for (index, entry) in entries.into_iter().enumerate() {
+                let result = entry
+                    .try_into::<T>()
+                    .map_err(|e| format!("{} #{}: {}", T::KIND, index + 1, e))
+                    .and_then(|record| self.insert(record, path));
+                if let Err(e) = result {
+                    problems.push((path.clone(), e));
+                }
+            }
+        }
+    }
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct DatabaseConfig {
+    pub items: PathBuf,
+    pub abilities: PathBuf,
+    pub monsters: PathBuf,
+    pub migrations: PathBuf,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        let content = PathBuf::from("content");
+        Self {
+            items: content.join("items"),
+            abilities: content.join("abilities"),
+            monsters: content.join("monsters"),
+            migrations: content.join("database").join("migrations.toml"),
+        }
+    }
+}
+
+impl DatabaseConfig {
+    fn owns(&self, path: &Path) -> bool {
+        let is_toml = path.extension().and_then(|e| e.to_str()) == Some("toml");
+        let directories = [&self.items, &self.abilities, &self.monsters];
+        path == self.migrations || (is_toml && directories.iter().any(|dir| path.parent() == Some(dir.as_path())))
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct GameDatabase {
+    pub items: Table<ItemDef>,
+    pub abilities: Table<AbilityDef>,
+    pub monsters: Table<MonsterDef>,
+    pub migrations: ContentMigrations,
+}
+
+impl GameDatabase {
+    /// Builds the tables from explicit file lists, in load order.
+    pub fn from_files(
+        items: &[PathBuf],
+        abilities: &[PathBuf],
+        monsters: &[PathBuf],
+    ) -> (Self, Vec<(PathBuf, String)>) {
+        let mut database = Self::default();
+        let mut problems = Vec::new();
+        database.items.load(items, &mut problems);
+        database.abilities.load(abilities, &mut problems);
+        database.monsters.load(monsters, &mut problems);
+        problems.extend(database.check_references());
+        (database, problems)
+    }
+
+    fn load(config: &DatabaseConfig, packs: &ContentPacks) -> (Self, Vec<(PathBuf, String)>) {
+        let (mut database, mut problems) = Self::from_files(
+            &packs.files(&config.items, "toml"),
+            &packs.files(&config.abilities, "toml"),
+            &packs.files(&config.monsters, "toml"),
+        );
+        if let Some(path) = packs.resolve(&config.migrations) {
+            match ContentMigrations::load(&path) {
+                Ok(migrations) => database.migrations = migrations,
+                Err(e) => problems.push((path, e)),
+            }
+        }
+        (database, problems)
+    }
+
+    /// References between records: monster abilities must exist.
+    fn check_references(&self) -> Vec<(PathBuf, String)> {
+        let mut problems = Vec::new();
+        for monster in self.monsters.iter() {
+            for ability in monster.abilities.iter().filter(|a| !self.abilities.contains(a)) {
+                let source = self.monsters.source(&monster.id).unwrap_or(Path::new("")).to_path_buf();
+                problems.push((source, format!("monster '{}': unknown ability '{}'", monster.id, ability)));
+            }
+        }
+        problems
+    }
+
+    pub fn item(&self, key: DbKey<ItemDef>) -> Option<&ItemDef> {
+        self.items.lookup(key)
+    }
+
+    pub fn ability(&self, key: DbKey<AbilityDef>) -> Option<&AbilityDef> {
+        self.abilities.lookup(key)
+    }
+
+    pub fn monster(&self, key: DbKey<MonsterDef>) -> Option<&MonsterDef> {
+        self.monsters.lookup(key)
+    }
+}
+
+pub struct GameDatabasePlugin;
+
+impl Plugin for GameDatabasePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DatabaseConfig>()
+            .init_resource::<ContentPacks>()
+            .init_resource::<GameDatabase>()
+            .add_event::<ContentChangedEvent>()
+            .add_event::<ContentReloadedEvent>()
+            .add_systems(PreStartup, load_game_database)
+            .add_systems(Update, reload_game_database);
+    }
+}
+
+fn log_problems(problems: &[(PathBuf, String)]) {
+    for (path, problem) in problems {
+        warn!("Database: {}: {}", path.display(), problem);
+    }
+}
+
+/// Runs in `PreStartup` so spawning systems in `Startup` can read it.
+fn load_game_database(config: Res<DatabaseConfig>, packs: Res<ContentPacks>, mut database: ResMut<GameDatabase>) {
+    let (loaded, problems) = GameDatabase::load(&config, &packs);
+    log_problems(&problems);
+    *database = loaded;
+    info!(
+        "Database: {} items, {} abilities, {} monsters (content version {})",
+        database.items.len(),
+        database.abilities.len(),
+        database.monsters.len(),
+        database.migrations.current_version()
+    );
+}
+
+/// Rebuilds the whole database when any of its files change; it's small
+/// enough that tracking per-file contributions isn't worth it.
+fn reload_game_database(
+    config: Res<DatabaseConfig>,
+    packs: Res<ContentPacks>,
+    mut database: ResMut<GameDatabase>,
+    mut changed: EventReader<ContentChangedEvent>,
+    mut reloaded: EventWriter<ContentReloadedEvent>,
+) {
+    let paths: Vec<PathBuf> = changed.read().filter(|e| config.owns(&e.path)).map(|e| e.path.clone()).collect();
+    if paths.is_empty() {
+        return;
+    }
+
+    let (loaded, problems) = GameDatabase::load(&config, &packs);
+    log_problems(&problems);
+    *database = loaded;
+    for path in paths {
+        let errors: Vec<&str> = problems.iter().filter(|(p, _)| *p == path).map(|(_, e)| e.as_str()).collect();
+        if errors.is_empty() {
+            let summary = format!(
+                "{} items, {} abilities, {} monsters",
+                database.items.len(),
+                database.abilities.len(),
+                database.monsters.len()
+            );
+            reloaded.send(ContentReloadedEvent::applied(&path, summary));
+        } else {
+            reloaded.send(ContentReloadedEvent::failed(&path, errors.join("; ")));
+        }
+    }
+}
@@ -0,0 +1,355 @@
+//! Day/night lighting. A [`DayNightClock`] advances the hour; the sun's path
+//! for the configured latitude and date is baked into a table of lighting
+//! samples (light direction, color temperature, ambient and fog), and each
+//! frame the sample for the current hour is applied to the [`SunLight`],
+//! `AmbientLight` and camera `DistanceFog`. Below the horizon the same light
+//! becomes moonlight.
+//!
+//! The world clock sets the hour from server time, so every client shares
+//! the sky. `time <hour>` shifts the cycle, and `timescale <multiplier>` and
+//! `timepause` take the clock over locally for testing.
+
+use bevy::pbr::{DistanceFog, FogFalloff};
+use bevy::prelude::*;
+use std::f32::consts::{PI, TAU};
+
+use crate::console::{
+    CommandPermission, ConsoleAppExt, ConsoleCommand, ConsoleCommandEvent, ConsoleOutputEvent, SetTimeOfDayRequest,
+};
+use crate::sound::ambience::AmbienceConditions;
+
+/// The directional light the cycle animates.
+#[derive(Component, Debug, Default)]
+pub struct SunLight;
+
+#[derive(Resource, Debug, Clone)]
+pub struct DayNightClock {
+    /// Hour of day, 0..24.
+    pub hour: f32,
+    /// Real minutes per game day at speed 1.
+    pub day_length_minutes: f32,
+    /// Multiplier for testing; the `timescale` command sets it.
+    pub speed: f32,
+    pub paused: bool,
+}
+
+impl Default for DayNightClock {
+    fn default() -> Self {
+        Self {
+            hour: 9.0,
+            day_length_minutes: 48.0,
+            speed: 1.0,
+            paused: false,
+        }
+    }
+}
+
+impl DayNightClock {
+    pub fn advance(&mut self, seconds: f32) {
+        if self.paused || self.day_length_minutes <= 0.0 {
+            return;
+        }
+        let hours = seconds * self.speed * 24.0 / (self.day_length_minutes * 60.0);
+        self.hour = (self.hour + hours).rem_euclid(24.0);
+    }
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct DayNightConfig {
+    pub latitude_degrees: f32,
+    /// 1..365; sets the sun's declination, so summer days run long.
+    pub day_of_year: u32,
+    /// Baked samples per day; the hour is interpolated between them.
+    pub samples_per_day: usize,
+    /// Lux at zenith.
+    pub sun_illuminance: f32,
+    pub moon_illuminance: f32,
+    /// Kelvin at the horizon and high in the sky.
+    pub sunrise_temperature: f32,
+    pub noon_temperature: f32,
+    pub moon_color: Color,
+    pub day_ambient: Color,
+    pub night_ambient: Color,
+    pub day_ambient_brightness: f32,
+    pub night_ambient_brightness: f32,
+    pub day_fog: Color,
+    pub night_fog: Color,
+    /// Linear fog end distance by day and night.
+    pub day_visibility: f32,
+    pub night_visibility: f32,
+}
+
+impl Default for DayNightConfig {
+    fn default() -> Self {
+        Self {
+            latitude_degrees: 45.0,
+            day_of_year: 172,
+            samples_per_day: 288,
+            sun_illuminance: 10000.0,
+            moon_illuminance: 250.0,
+            sunrise_temperature: 2200.0,
+            noon_temperature: 5800.0,
+            moon_color: Color::srgb(0.62, 0.72, 1.0),
+            day_ambient: Color::srgb(0.5, 0.5, 0.6),
+            night_ambient: Color::srgb(0.25, 0.3, 0.5),
+            day_ambient_brightness: 200.0,
+            night_ambient_brightness: 30.0,
+            day_fog: Color::srgb(0.62, 0.7, 0.8),
+            night_fog: Color::srgb(0.03, 0.04, 0.08),
+            day_visibility: 900.0,
+            night_visibility: 350.0,
+        }
+    }
+}
+
+/// Lighting at one moment of the day.
+#[derive(Debug, Clone, Copy)]
+pub struct LightingSample {
+    /// Unit vector toward the sun.
+    pub sun_direction: Vec3,
+    /// Unit vector toward whichever of sun or moon lights the scene.
+    pub light_direction: Vec3,
+    pub light_color: LinearRgba,
+    pub illuminance: f32,
+    pub ambient_color: LinearRgba,
+    pub ambient_brightness: f32,
+    pub fog_color: LinearRgba,
+    pub visibility: f32,
+}
+
+impl LightingSample {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        Self {
+            sun_direction: self.sun_direction.lerp(other.sun_direction, t).normalize_or(Vec3::Y),
+            light_direction: self.light_direction.lerp(other.light_direction, t).normalize_or(Vec3::Y),
+            light_color: self.light_color.mix(&other.light_color, t),
+            illuminance: self.illuminance.lerp(other.illuminance, t),
+            ambient_color: self.ambient_color.mix(&other.ambient_color, t),
+            ambient_brightness: self.ambient_brightness.lerp(other.ambient_brightness, t),
+            fog_color: self.fog_color.mix(&other.fog_color, t),
+            visibility: self.visibility.lerp(other.visibility, t),
+        }
+    }
+}
+
+/// The day's lighting, baked from [`DayNightConfig`].
+#[derive(Resource, Debug, Clone, Default)]
+pub struct SunPath {
+    pub samples: Vec<LightingSample>,
+}
+
+impl SunPath {
+    pub fn bake(config: &DayNightConfig) -> Self {
+        let count = config.samples_per_day.max(24);
+        let samples = (0..count)
+            .map(|i| bake_sample(config, i as f32 * 24.0 / count as f32))
+            .collect();
+        Self { samples }
+    }
+
+    pub fn sample(&self, hour: f32) -> Option<LightingSample> {
+        let count = self.samples.len();
+        if count == 0 {
+            return None;
+        }
+        let position = hour.rem_euclid(24.0) / 24.0 * count as f32;
+        let index = position.floor() as usize % count;
+        let next = (index + 1) % count;
+        Some(self.samples[index].lerp(&self.samples[next], position.fract()))
+    }
+}
+
+/// Direction toward the sun in world space (x east, y up, -z north).
+pub fn sun_direction(latitude_degrees: f32, day_of_year: u32, hour: f32) -> Vec3 {
+    let latitude = latitude_degrees.to_radians();
+    let declination = 23.44_f32.to_radians() * (TAU * (284.0 + day_of_year as f32) / 365.0).sin();
+    let hour_angle = (hour - 12.0) * PI / 12.0;
+    let east = -declination.cos() * hour_angle.sin();
+    let north = declination.sin() * latitude.cos() - declination.cos() * hour_angle.cos() * latitude.sin();
+    let up = declination.sin() * latitude.sin() + declination.cos() * hour_angle.cos() * latitude.cos();
+    Vec3::new(east, up, -north).normalize_or(Vec3::Y)
+}
+
+/// Approximate RGB of a black body at `kelvin`, 1000..40000.
+pub fn color_temperature(kelvin: f32) -> Color {
+    let t = kelvin.clamp(1000.0, 40000.0) / 100.0;
+    let red = if t <= 66.0 {
+        255.0
+    } else {
+        329.698_73 * (t - 60.0).powf(-0.133_204_76)
+    };
+    let green = if t <= 66.0 {
+        99.470_8 * t.ln() - 161.119_57
+    } else {
+        288.122_16 * (t - 60.0).powf(-0.075_514_846)
+    };
+    let blue = if t >= 66.0 {
+        255.0
+    } else if t <= 19.0 {
+        0.0
+    } else {
+        138.517_73 * (t - 10.0).ln() - 305.044_8
+    };
+    Color::srgb(
+        red.clamp(0.0, 255.0) / 255.0,
+        green.clamp(0.0, 255.0) / 255.0,
+        blue.clamp(0.0, 255.0) / 255.0,
+    )
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+fn bake_sample(config: &DayNightConfig, hour: f32) -> LightingSample {
+    let sun = sun_direction(config.latitude_degrees, config.day_of_year, hour);
+    let elevation = sun.y;
+    // 0 at night, 1 in daylight, easing through civil twilight
+    let daylight = smoothstep(-0.1, 0.15, elevation);
+
+    let sun_strength = smoothstep(-0.02, 0.35, elevation);
+    let sun_illuminance = config.sun_illuminance * sun_strength;
+    let temperature = config
+        .sunrise_temperature
+        .lerp(config.noon_temperature, smoothstep(0.0, 0.5, elevation));
+    // A full moon opposite the sun
+    let moon = -sun;
+    let moon_illuminance = config.moon_illuminance * (1.0 - daylight) * smoothstep(-0.05, 0.2, moon.y);
+
+    let (light_direction, light_color, illuminance) = if sun_illuminance >= moon_illuminance {
+        (sun, color_temperature(temperature), sun_illuminance)
+    } else {
+        (moon, config.moon_color, moon_illuminance)
+    };
+
+    // Low sun tints the horizon haze
+    let dusk = smoothstep(0.25, 0.0, elevation.abs()) * daylight;
+    let fog = config
+        .night_fog
+        .mix(&config.day_fog, daylight)
+        .mix(&color_temperature(config.sunrise_temperature), dusk * 0.35);
+
+    LightingSample {
+        sun_direction: sun,
+        light_direction,
+        light_color: light_color.to_linear(),
+        illuminance,
+        ambient_color: config.night_ambient.mix(&config.day_ambient, daylight).to_linear(),
+        ambient_brightness: config.night_ambient_brightness.lerp(config.day_ambient_brightness, daylight),
+        fog_color: fog.to_linear(),
+        visibility: config.night_visibility.lerp(config.day_visibility, daylight),
+    }
+}
+
+pub struct DayNightPlugin;
+
+impl Plugin for DayNightPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DayNightClock>()
+            .init_resource::<DayNightConfig>()
+            .init_resource::<SunPath>()
+            .add_console_command(
+                ConsoleCommand::new("timescale", "Speeds up or slows down the day/night cycle")
+                    .usage("<multiplier>")
+                    .permission(CommandPermission::Dev),
+            )
+            .add_console_command(
+                ConsoleCommand::new("timepause", "Pauses or resumes the day/night cycle")
+                    .permission(CommandPermission::Dev),
+            )
+            .add_systems(
+                Update,
+                (
+                    run_day_night_commands,
+                    advance_clock,
+                    bake_sun_path,
+                    apply_lighting.after(crate::systems::sky::update_sky_visuals),
+                )
+                    .chain(),
+            );
+    }
+}
+
+fn run_day_night_commands(
+    mut clock: ResMut<DayNightClock>,
+    mut time_requests: EventReader<SetTimeOfDayRequest>,
+    mut events: EventReader<ConsoleCommandEvent>,
+    mut output: EventWriter<ConsoleOutputEvent>,
+) {
+    for request in time_requests.read() {
+        clock.hour = request.hour.rem_euclid(24.0);
+    }
+    for event in events.read() {
+        let result: Result<String, String> = match event.name.as_str() {
+            "timescale" => event.arg::<f32>(0, "multiplier").and_then(|speed| {
+                if !(0.0..=10000.0).contains(&speed) {
+                    return Err(format!("multiplier {} outside 0-10000", speed));
+                }
+                clock.speed = speed;
+                let day_seconds = clock.day_length_minutes * 60.0 / speed.max(f32::EPSILON);
+                Ok(format!("Time scale x{} (a day takes {:.0}s)", speed, day_seconds))
+            }),
+            "timepause" => {
+                clock.paused = !clock.paused;
+                Ok(if clock.paused { "Time paused" } else { "Time resumed" }.to_string())
+            }
+            _ => continue,
+        };
+        output.send(match result {
+            Ok(text) => event.reply(text),
+            Err(text) => event.error(format!("{}: {}", event.name, text)),
+        });
+    }
+}
+
+pub(crate) fn advance_clock(time: Res<Time>, mut clock: ResMut<DayNightClock>, ambience: Option<ResMut<AmbienceConditions>>) {
+    clock.advance(time.delta_secs());
+    if let Some(mut ambience) = ambience {
+        if (ambience.hour - clock.hour).abs() > f32::EPSILON {
+            ambience.hour = clock.hour;
+        }
+    }
+}
+
+fn bake_sun_path(config: Res<DayNightConfig>, mut path: ResMut<SunPath>) {
+    if config.is_changed() || path.samples.is_empty() {
+        *path = SunPath::bake(&config);
+    }
+}
+
+pub(crate) fn apply_lighting(
+    mut commands: Commands,
+    clock: Res<DayNightClock>,
+    path: Res<SunPath>,
+    mut ambient: ResMut<AmbientLight>,
+    mut suns: Query<(&mut DirectionalLight, &mut Transform), With<SunLight>>,
+    mut fogs: Query<&mut DistanceFog>,
+    unfogged: Query<Entity, (With<Camera3d>, Without<DistanceFog>)>,
+) {
+    let Some(sample) = path.sample(clock.hour) else {
+        return;
+    };
+
+    let up = if sample.light_direction.y.abs() > 0.999 { Vec3::Z } else { Vec3::Y };
+    for (mut light, mut transform) in &mut suns {
+        light.color = sample.light_color.into();
+        light.illuminance = sample.illuminance;
+        transform.look_to(-sample.light_direction, up);
+    }
+
+    ambient.color = sample.ambient_color.into();
+    ambient.brightness = sample.ambient_brightness;
+
+    for entity in &unfogged {
+        commands.entity(entity).insert(DistanceFog::default());
+    }
+    for mut fog in &mut fogs {
+        fog.color = sample.fog_color.into();
+        fog.directional_light_color = sample.light_color.with_alpha(0.5).into();
+        fog.falloff = FogFalloff::Linear {
+            start: sample.visibility * 0.25,
+            end: sample.visibility,
+        };
+    }
+}
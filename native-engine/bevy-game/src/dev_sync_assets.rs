@@ -0,0 +1,223 @@
+//! Asset and shader pushes from the dev server. Alongside code sync, the dev
+//! server can push models, textures, content TOML and WGSL over the same
+//! `DEV_SYNC_URL` WebSocket; files land in `assets/` or `content/` and are
+//! reloaded in place, so artists see their changes in a few seconds.
+
+use base64::Engine;
+use bevy::prelude::*;
+use serde::Deserialize;
+use std::path::{Component, Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::GameLogOverlay;
+
+/// Messages the dev server sends besides code sync; anything else is ignored here.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum DevSyncMessage {
+    /// Any file under `assets/` or `content/`, base64 encoded.
+    AssetPush { path: String, data: String },
+    /// WGSL source for `assets/shaders/<path>`.
+    ShaderPush { path: String, source: String },
+    AssetDelete { path: String },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug)]
+enum DevAssetChange {
+    Write { path: String, bytes: Vec<u8> },
+    Delete { path: String },
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct DevAssetSyncConfig {
+    pub url: Option<String>,
+    pub assets_root: PathBuf,
+    pub content_root: PathBuf,
+    /// Larger pushes are rejected rather than written.
+    pub max_file_bytes: usize,
+    pub reconnect_delay: Duration,
+}
+
+impl Default for DevAssetSyncConfig {
+    fn default() -> Self {
+        Self {
+            url: std::env::var("DEV_SYNC_URL").ok().filter(|url| !url.is_empty()),
+            assets_root: PathBuf::from("assets"),
+            content_root: PathBuf::from("content"),
+            max_file_bytes: 256 * 1024 * 1024,
+            reconnect_delay: Duration::from_secs(5),
+        }
+    }
+}
+
+/// A pushed file was written (or removed) and its asset reload requested.
+#[derive(Event, Debug, Clone)]
+pub struct DevAssetPushedEvent {
+    pub path: PathBuf,
+    pub removed: bool,
+}
+
+#[derive(Resource)]
+struct DevAssetSyncChannel {
+    changes: Mutex<Receiver<DevAssetChange>>,
+}
+
+pub struct DevAssetSyncPlugin;
+
+impl Plugin for DevAssetSyncPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DevAssetSyncConfig>()
+            .add_event::<DevAssetPushedEvent>()
+            .add_systems(Startup, start_dev_asset_sync)
+            .add_systems(Update, apply_dev_asset_changes.run_if(resource_exists::<DevAssetSyncChannel>));
+    }
+}
+
+fn start_dev_asset_sync(mut commands: Commands, config: Res<DevAssetSyncConfig>) {
+    let Some(url) = config.url.clone() else {
+        return;
+    };
+    let (sender, receiver) = channel();
+    let reconnect_delay = config.reconnect_delay;
+    let spawned = std::thread::Builder::new()
+        .name("dev-asset-sync".into())
+        .spawn(move || run_dev_asset_socket(&url, reconnect_delay, sender));
+    match spawned {
+        Ok(_) => commands.insert_resource(DevAssetSyncChannel {
+            changes: Mutex::new(receiver),
+        }),
+        Err(e) => warn!("Dev asset sync: failed to start thread: {}", e),
+    }
+}
+
+/// Socket loop on its own thread; reconnects until the game exits.
+fn run_dev_asset_socket(url: &str, reconnect_delay: Duration, sender: Sender<DevAssetChange>) {
+    loop {
+        match tungstenite::connect(url) {
+            Ok((mut socket, _)) => {
+                info!("Dev asset sync connected to {}", url);
+                let subscribe = serde_json::json!({ "type": "subscribe", "channels": ["assets", "shaders"] });
+                if let Err(e) = socket.send(tungstenite::Message::text(subscribe.to_string())) {
+                    warn!("Dev asset sync: subscribe failed: {}", e);
+                }
+                loop {
+                    let text = match socket.read() {
+                        Ok(tungstenite::Message::Text(text)) => text,
+                        Ok(tungstenite::Message::Close(_)) => break,
+                        Ok(_) => continue,
+                        Err(e) => {
+                            warn!("Dev asset sync connection lost: {}", e);
+                            break;
+                        }
+                    };
+                    let change = match serde_json::from_str::<DevSyncMessage>(&text) {
+                        Ok(DevSyncMessage::AssetPush { path, data }) => {
+                            match base64::engine::general_purpose::STANDARD.decode(data) {
+                                Ok(bytes) => DevAssetChange::Write { path, bytes },
+                                Err(e) => {
+                                    warn!("Dev asset sync: bad data for {}: {}", path, e);
+                                    continue;
+                                }
+                            }
+                        }
+                        Ok(DevSyncMessage::ShaderPush { path, source }) => DevAssetChange::Write {
+                            path: format!("assets/shaders/{}", path),
+                            bytes: source.into_bytes(),
+                        },
+                        Ok(DevSyncMessage::AssetDelete { path }) => DevAssetChange::Delete { path },
+                        Ok(DevSyncMessage::Other) | Err(_) => continue,
+                    };
+                    if sender.send(change).is_err() {
+                        // The app is gone
+                        return;
+                    }
+                }
+            }
+            Err(e) => debug!("Dev asset sync: cannot reach {}: {}", url, e),
+        }
+        std::thread::sleep(reconnect_delay);
+    }
+}
+
+/// Maps a pushed path onto `assets/` or `content/`. Returns the file path and,
+/// for assets, the path the asset server knows it by.
+fn resolve_push_path(config: &DevAssetSyncConfig, pushed: &str) -> Result<(PathBuf, Option<PathBuf>), String> {
+    let pushed = Path::new(pushed);
+    if pushed.components().any(|c| !matches!(c, Component::Normal(_))) {
+        return Err("path must be relative and stay inside the project".into());
+    }
+    if let Ok(relative) = pushed.strip_prefix("assets") {
+        Ok((config.assets_root.join(relative), Some(relative.to_path_buf())))
+    } else if let Ok(relative) = pushed.strip_prefix("content") {
+        Ok((config.content_root.join(relative), None))
+    } else {
+        Err("only assets/ and content/ can be pushed".into())
+    }
+}
+
+fn apply_dev_asset_changes(
+    channel: Res<DevAssetSyncChannel>,
+    config: Res<DevAssetSyncConfig>,
+    asset_server: Res<AssetServer>,
+    time: Res<Time>,
+    mut overlay: Option<ResMut<GameLogOverlay>>,
+    mut pushed: EventWriter<DevAssetPushedEvent>,
+) {
+    let changes: Vec<DevAssetChange> = match channel.changes.lock() {
+        Ok(changes) => changes.try_iter().collect(),
+        Err(_) => return,
+    };
+
+    for change in changes {
+        let (path, removed, result) = match change {
+            DevAssetChange::Write { path, bytes } => {
+                let result = if bytes.len() > config.max_file_bytes {
+                    Err(format!("{} bytes is over the {} byte limit", bytes.len(), config.max_file_bytes))
+                } else {
+                    resolve_push_path(&config, &path).and_then(|(file, asset)| {
+                        if let Some(parent) = file.parent() {
+                            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+                        }
+                        std::fs::write(&file, &bytes).map_err(|e| e.to_string())?;
+                        Ok((file, asset))
+                    })
+                };
+                (path, false, result)
+            }
+            DevAssetChange::Delete { path } => {
+                let result = resolve_push_path(&config, &path).and_then(|(file, asset)| {
+                    std::fs::remove_file(&file).map_err(|e| e.to_string())?;
+                    Ok((file, asset))
+                });
+                (path, true, result)
+            }
+        };
+
+        let now = time.elapsed_secs_f64();
+        match result {
+            Ok((file, asset)) => {
+                // Content files are picked up by the content hot reload watcher
+                if let (Some(asset), false) = (&asset, removed) {
+                    asset_server.reload(asset.clone());
+                }
+                let message = format!("Dev sync: {} {}", if removed { "removed" } else { "updated" }, path);
+                info!("{}", message);
+                if let Some(overlay) = overlay.as_mut() {
+                    overlay.info(message, now);
+                }
+                pushed.send(DevAssetPushedEvent { path: file, removed });
+            }
+            Err(e) => {
+                let message = format!("Dev sync: rejected {}: {}", path, e);
+                warn!("{}", message);
+                if let Some(overlay) = overlay.as_mut() {
+                    overlay.warn(message, now);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,127 @@
+//! Per-character instance lockouts. A character is saved to an instance the
+//! first time a boss dies with them in it, and stays saved to it (and to the
+//! bosses already killed) until the weekly reset.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use super::Difficulty;
+use crate::persistence;
+use crate::territory::Weekday;
+
+const DAY_SECONDS: u64 = 86_400;
+const WEEK_SECONDS: u64 = 7 * DAY_SECONDS;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lockout {
+    pub dungeon: String,
+    pub difficulty: Difficulty,
+    /// Instance the character is saved to.
+    pub instance: u64,
+    pub bosses_killed: Vec<String>,
+    /// Unix seconds of the reset that clears it.
+    pub expires_at: u64,
+}
+
+#[derive(Resource, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Lockouts {
+    /// Character name to its lockouts.
+    #[serde(default)]
+    pub characters: BTreeMap<String, Vec<Lockout>>,
+    /// Changed since the last save.
+    #[serde(skip)]
+    pub dirty: bool,
+}
+
+impl Lockouts {
+    pub fn get(&self, character: &str, dungeon: &str, difficulty: Difficulty) -> Option<&Lockout> {
+        self.characters
+            .get(character)?
+            .iter()
+            .find(|l| l.dungeon == dungeon && l.difficulty == difficulty)
+    }
+
+    /// Saves `character` to `instance` and records the kill.
+    pub fn record_kill(
+        &mut self,
+        character: &str,
+        dungeon: &str,
+        difficulty: Difficulty,
+        instance: u64,
+        boss: &str,
+        expires_at: u64,
+    ) {
+        let lockouts = self.characters.entry(character.to_string()).or_default();
+        let index = match lockouts.iter().position(|l| l.dungeon == dungeon && l.difficulty == difficulty) {
+            Some(index) => index,
+            None => {
+                lockouts.push(Lockout {
+                    dungeon: dungeon.to_string(),
+                    difficulty,
+                    instance,
+                    bosses_killed: Vec::new(),
+                    expires_at,
+                });
+                lockouts.len() - 1
+            }
+        };
+        let lockout = &mut lockouts[index];
+        if !lockout.bosses_killed.iter().any(|b| b == boss) {
+            lockout.bosses_killed.push(boss.to_string());
+        }
+        self.dirty = true;
+    }
+
+    /// Drops lockouts whose reset has passed; returns how many.
+    pub fn expire(&mut self, now: u64) -> usize {
+        let mut expired = 0;
+        for lockouts in self.characters.values_mut() {
+            let before = lockouts.len();
+            lockouts.retain(|l| l.expires_at > now);
+            expired += before - lockouts.len();
+        }
+        self.characters.retain(|_, lockouts| !lockouts.is_empty());
+        self.dirty |= expired > 0;
+        expired
+    }
+
+    pub fn clear(&mut self) {
+        self.characters.clear();
+        self.dirty = true;
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        persistence::load_json(path)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        persistence::save_json(self, path)
+    }
+}
+
+/// Unix seconds of the first weekly reset (`day` at `hour` UTC) after `now`.
+pub fn next_weekly_reset(day: Weekday, hour: u32, now: u64) -> u64 {
+    // 1970-01-01 was a Thursday
+    let week_start = now - (now + 3 * DAY_SECONDS) % WEEK_SECONDS;
+    let reset = week_start + day as u64 * DAY_SECONDS + hour as u64 * 3600;
+    if reset > now {
+        reset
+    } else {
+        reset + WEEK_SECONDS
+    }
+}
+
+/// "3d 4h" style time until `at`.
+pub fn time_until(at: u64, now: u64) -> String {
+    let seconds = at.saturating_sub(now);
+    let (days, hours, minutes) = (seconds / DAY_SECONDS, seconds % DAY_SECONDS / 3600, seconds % 3600 / 60);
+    if days > 0 {
+        format!("{}d {}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else {
+        format!("{}m", minutes.max(1))
+    }
+}
@@ -0,0 +1,615 @@
+//! Dungeon and raid instances with difficulty tiers and weekly lockouts.
+//! Dungeons live in `content/dungeons/*.toml`:
+//!
+//! ```toml
+//! [[dungeon]]
+//! id = "ragnar_keep"
+//! name = "Ragnar's Keep"
+//! kind = "raid"
+//! script = "bosses/ragnar"
+//! bosses = ["jarl_skadi", "ragnar"]
+//!
+//! [dungeon.tiers.heroic]
+//! health = 2.0
+//! damage = 1.5
+//! min_level = 60
+//! ```
+//!
+//! Tiers a dungeon doesn't list use the defaults in [`DungeonConfig`]:
+//! heroic monsters are tougher and hit harder, and heroic dungeons (every
+//! raid tier) lock characters out after the first boss kill until the weekly
+//! reset. The spawner scales monster stats with [`scaled_monster`] for the
+//! instance's tier. Boss scripts get `on_instance_start(instance, difficulty)`
+//! when an instance opens, and turn on their extra heroic mechanics there.
+
+pub mod lockouts;
+pub mod ui;
+
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use rhai::Dynamic;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::PathBuf;
+
+use crate::console::{
+    ArgCompletion, CommandPermission, ConsoleAppExt, ConsoleCommand, ConsoleCommandEvent, ConsoleOutputEvent,
+};
+use crate::content_packs::ContentPacks;
+use crate::database::MonsterDef;
+use crate::scripting::ScriptCallEvent;
+use crate::territory::Weekday;
+use crate::world_clock::WorldClock;
+use crate::Character;
+
+pub use lockouts::{next_weekly_reset, time_until, Lockout, Lockouts};
+pub use ui::LockoutUiPlugin;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Difficulty {
+    #[default]
+    Normal,
+    Heroic,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 2] = [Difficulty::Normal, Difficulty::Heroic];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Difficulty::Normal => "normal",
+            Difficulty::Heroic => "heroic",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|d| d.name() == name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DungeonKind {
+    #[default]
+    Dungeon,
+    Raid,
+}
+
+/// Stat multipliers and rules for one difficulty.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TierDef {
+    #[serde(default = "one")]
+    pub health: f32,
+    #[serde(default = "one")]
+    pub damage: f32,
+    #[serde(default = "one")]
+    pub armor: f32,
+    /// Experience multiplier.
+    #[serde(default = "one")]
+    pub xp: f32,
+    #[serde(default)]
+    pub min_level: u32,
+    /// Boss kills save characters to the instance until the weekly reset.
+    #[serde(default)]
+    pub lockout: bool,
+}
+
+fn one() -> f32 {
+    1.0
+}
+
+impl Default for TierDef {
+    fn default() -> Self {
+        Self {
+            health: 1.0,
+            damage: 1.0,
+            armor: 1.0,
+            xp: 1.0,
+            min_level: 0,
+            lockout: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DungeonDef {
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub kind: DungeonKind,
+    /// Boss script (name under the script directory) told about new instances.
+    #[serde(default)]
+    pub script: Option<String>,
+    /// Monster ids whose deaths count towards lockouts.
+    #[serde(default)]
+    pub bosses: Vec<String>,
+    #[serde(default)]
+    pub tiers: BTreeMap<Difficulty, TierDef>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DungeonContentFile {
+    #[serde(default, rename = "dungeon")]
+    pub dungeons: Vec<DungeonDef>,
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct DungeonConfig {
+    pub content_directory: PathBuf,
+    /// Where lockouts are saved; nothing is saved when `None`.
+    pub lockouts_path: Option<PathBuf>,
+    pub save_interval_seconds: f32,
+    /// Weekly reset, UTC.
+    pub reset_day: Weekday,
+    pub reset_hour: u32,
+    pub heroic: TierDef,
+}
+
+impl Default for DungeonConfig {
+    fn default() -> Self {
+        Self {
+            content_directory: PathBuf::from("content").join("dungeons"),
+            lockouts_path: Some(PathBuf::from("lockouts.json")),
+            save_interval_seconds: 60.0,
+            reset_day: Weekday::Tuesday,
+            reset_hour: 15,
+            heroic: TierDef {
+                health: 1.6,
+                damage: 1.3,
+                armor: 1.2,
+                xp: 1.5,
+                min_level: 0,
+                lockout: true,
+            },
+        }
+    }
+}
+
+impl DungeonConfig {
+    /// The dungeon's tier, falling back to the defaults. Raids always lock.
+    pub fn tier(&self, dungeon: &DungeonDef, difficulty: Difficulty) -> TierDef {
+        let mut tier = dungeon.tiers.get(&difficulty).cloned().unwrap_or_else(|| match difficulty {
+            Difficulty::Normal => TierDef::default(),
+            Difficulty::Heroic => self.heroic.clone(),
+        });
+        tier.lockout |= dungeon.kind == DungeonKind::Raid;
+        tier
+    }
+}
+
+/// `monster` with its stats scaled for `tier`.
+pub fn scaled_monster(monster: &MonsterDef, tier: &TierDef) -> MonsterDef {
+    MonsterDef {
+        health: monster.health * tier.health,
+        health_per_level: monster.health_per_level * tier.health,
+        armor: monster.armor * tier.armor,
+        damage: monster.damage.map(|d| d * tier.damage),
+        xp: (monster.xp as f32 * tier.xp).round() as u32,
+        ..monster.clone()
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct DungeonContent {
+    pub dungeons: HashMap<String, DungeonDef>,
+}
+
+#[derive(Debug, Clone)]
+pub struct DungeonInstance {
+    pub dungeon: String,
+    pub difficulty: Difficulty,
+    pub killed: BTreeSet<String>,
+    pub members: Vec<Entity>,
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct DungeonInstances {
+    next_id: u64,
+    pub instances: HashMap<u64, DungeonInstance>,
+}
+
+/// Monsters spawned into an instance; the spawner adds it alongside the
+/// scaled stats.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct InstanceMonster {
+    pub instance: u64,
+    pub difficulty: Difficulty,
+}
+
+/// A character asks to enter a dungeon, joining `instance` (a group
+/// member's) when given and opening a new one otherwise.
+#[derive(Event, Debug, Clone)]
+pub struct EnterDungeonRequest {
+    pub character: Entity,
+    pub dungeon: String,
+    pub difficulty: Difficulty,
+    pub instance: Option<u64>,
+}
+
+/// The zone system moves the character into the instance.
+#[derive(Event, Debug, Clone)]
+pub struct DungeonEnteredEvent {
+    pub character: Entity,
+    pub instance: u64,
+    pub dungeon: String,
+    pub difficulty: Difficulty,
+    /// Bosses already dead for this character's lockout; don't spawn them.
+    pub killed: Vec<String>,
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct DungeonEntryDeniedEvent {
+    pub character: Entity,
+    pub dungeon: String,
+    pub reason: String,
+}
+
+/// Combat sends this when an [`InstanceMonster`] dies, with everyone who
+/// gets credit for the kill.
+#[derive(Event, Debug, Clone)]
+pub struct BossKilledEvent {
+    pub instance: u64,
+    pub boss: String,
+    pub participants: Vec<Entity>,
+}
+
+/// A character left; instances close once everyone has.
+#[derive(Event, Debug, Clone)]
+pub struct LeaveDungeonRequest {
+    pub character: Entity,
+    pub instance: u64,
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct DungeonInstanceClosedEvent {
+    pub instance: u64,
+}
+
+/// The weekly reset cleared lockouts.
+#[derive(Event, Debug, Clone)]
+pub struct LockoutsResetEvent {
+    pub expired: usize,
+}
+
+pub struct DungeonPlugin;
+
+impl Plugin for DungeonPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DungeonConfig>()
+            .init_resource::<DungeonContent>()
+            .init_resource::<DungeonInstances>()
+            .init_resource::<Lockouts>()
+            .init_resource::<WorldClock>()
+            .add_event::<EnterDungeonRequest>()
+            .add_event::<DungeonEnteredEvent>()
+            .add_event::<DungeonEntryDeniedEvent>()
+            .add_event::<LeaveDungeonRequest>()
+            .add_event::<DungeonInstanceClosedEvent>()
+            .add_event::<BossKilledEvent>()
+            .add_event::<LockoutsResetEvent>()
+            .add_event::<ScriptCallEvent>()
+            .add_console_command(
+                ConsoleCommand::new("lockouts", "Lists a character's lockouts, or clears everyone's")
+                    .usage("[character|reset]")
+                    .permission(CommandPermission::Admin)
+                    .complete(vec![ArgCompletion::Values(vec!["reset".to_string()])]),
+            )
+            .add_systems(Startup, load_dungeons)
+            .add_systems(
+                Update,
+                (
+                    enter_dungeons,
+                    leave_dungeons,
+                    record_boss_kills,
+                    weekly_reset,
+                    lockouts_command,
+                    save_lockouts,
+                )
+                    .chain(),
+            );
+    }
+}
+
+fn load_dungeons(
+    config: Res<DungeonConfig>,
+    packs: Res<ContentPacks>,
+    mut content: ResMut<DungeonContent>,
+    mut lockouts: ResMut<Lockouts>,
+    mut instances: ResMut<DungeonInstances>,
+    clock: Res<WorldClock>,
+) {
+    for path in packs.files(&config.content_directory, "toml") {
+        let parsed = std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| toml::from_str::<DungeonContentFile>(&text).map_err(|e| e.to_string()));
+
+        match parsed {
+            Ok(file) => {
+                for dungeon in file.dungeons {
+                    content.dungeons.insert(dungeon.id.clone(), dungeon);
+                }
+            }
+            Err(e) => warn!("Invalid dungeon file {:?}: {}", path, e),
+        }
+    }
+
+    if let Some(path) = config.lockouts_path.as_ref().filter(|p| p.exists()) {
+        match Lockouts::load(path) {
+            Ok(loaded) => *lockouts = loaded,
+            Err(e) => warn!("Invalid lockouts {:?}: {}; starting fresh", path, e),
+        }
+    }
+    lockouts.expire(clock.now_secs());
+    // New instance ids start past the ones characters are saved to
+    let saved = lockouts.characters.values().flatten().map(|l| l.instance).max();
+    instances.next_id = saved.unwrap_or(0);
+
+    info!("Dungeons: {} loaded, {} characters locked out", content.dungeons.len(), lockouts.characters.len());
+}
+
+fn enter_dungeons(
+    config: Res<DungeonConfig>,
+    content: Res<DungeonContent>,
+    lockouts: Res<Lockouts>,
+    mut instances: ResMut<DungeonInstances>,
+    characters: Query<&Character>,
+    mut requests: EventReader<EnterDungeonRequest>,
+    (mut entered, mut denied, mut scripts): (
+        EventWriter<DungeonEnteredEvent>,
+        EventWriter<DungeonEntryDeniedEvent>,
+        EventWriter<ScriptCallEvent>,
+    ),
+) {
+    for request in requests.read() {
+        let result = enter(&config, &content, &lockouts, &mut instances, &characters, request);
+        match result {
+            Ok((instance, opened)) => {
+                let state = &instances.instances[&instance];
+                if opened {
+                    let script = content.dungeons.get(&request.dungeon).and_then(|d| d.script.clone());
+                    if script.is_some() {
+                        scripts.send(ScriptCallEvent {
+                            script,
+                            function: "on_instance_start".to_string(),
+                            args: vec![Dynamic::from(instance as i64), Dynamic::from(request.difficulty.name())],
+                        });
+                    }
+                }
+                entered.send(DungeonEnteredEvent {
+                    character: request.character,
+                    instance,
+                    dungeon: state.dungeon.clone(),
+                    difficulty: state.difficulty,
+                    killed: state.killed.iter().cloned().collect(),
+                });
+            }
+            Err(reason) => {
+                denied.send(DungeonEntryDeniedEvent {
+                    character: request.character,
+                    dungeon: request.dungeon.clone(),
+                    reason,
+                });
+            }
+        }
+    }
+}
+
+/// Places the character in an instance, returning its id and whether it
+/// was opened for them.
+fn enter(
+    config: &DungeonConfig,
+    content: &DungeonContent,
+    lockouts: &Lockouts,
+    instances: &mut DungeonInstances,
+    characters: &Query<&Character>,
+    request: &EnterDungeonRequest,
+) -> Result<(u64, bool), String> {
+    let dungeon = content
+        .dungeons
+        .get(&request.dungeon)
+        .ok_or_else(|| format!("unknown dungeon '{}'", request.dungeon))?;
+    let character = characters.get(request.character).map_err(|_| "not a character".to_string())?;
+    let tier = config.tier(dungeon, request.difficulty);
+    if character.level < tier.min_level {
+        return Err(format!("requires level {}", tier.min_level));
+    }
+    let saved = lockouts.get(&character.name, &dungeon.id, request.difficulty);
+
+    if let Some(id) = request.instance {
+        let instance = instances.instances.get_mut(&id).ok_or_else(|| "that instance has closed".to_string())?;
+        if instance.dungeon != dungeon.id || instance.difficulty != request.difficulty {
+            return Err("that instance is a different dungeon or difficulty".to_string());
+        }
+        if saved.is_some_and(|s| s.instance != id) {
+            return Err("you are saved to a different instance".to_string());
+        }
+        if !instance.members.contains(&request.character) {
+            instance.members.push(request.character);
+        }
+        return Ok((id, false));
+    }
+
+    // Saved characters go back to their instance, or a fresh one with their
+    // kills still dead when it has closed
+    if let Some(saved) = saved {
+        if dungeon.bosses.iter().all(|b| saved.bosses_killed.contains(b)) {
+            return Err(format!("already cleared this week on {}", request.difficulty.name()));
+        }
+        if let Some(instance) = instances.instances.get_mut(&saved.instance) {
+            if !instance.members.contains(&request.character) {
+                instance.members.push(request.character);
+            }
+            return Ok((saved.instance, false));
+        }
+    }
+
+    let id = match saved {
+        Some(saved) => saved.instance,
+        None => {
+            instances.next_id += 1;
+            instances.next_id
+        }
+    };
+    instances.instances.insert(
+        id,
+        DungeonInstance {
+            dungeon: dungeon.id.clone(),
+            difficulty: request.difficulty,
+            killed: saved.map(|s| s.bosses_killed.iter().cloned().collect()).unwrap_or_default(),
+            members: vec![request.character],
+        },
+    );
+    Ok((id, true))
+}
+
+fn leave_dungeons(
+    mut instances: ResMut<DungeonInstances>,
+    mut requests: EventReader<LeaveDungeonRequest>,
+    mut closed: EventWriter<DungeonInstanceClosedEvent>,
+) {
+    for request in requests.read() {
+        let Some(instance) = instances.instances.get_mut(&request.instance) else {
+            continue;
+        };
+        instance.members.retain(|&m| m != request.character);
+        if instance.members.is_empty() {
+            instances.instances.remove(&request.instance);
+            closed.send(DungeonInstanceClosedEvent { instance: request.instance });
+        }
+    }
+}
+
+fn record_boss_kills(
+    (config, clock): (Res<DungeonConfig>, Res<WorldClock>),
+    content: Res<DungeonContent>,
+    mut instances: ResMut<DungeonInstances>,
+    mut lockouts: ResMut<Lockouts>,
+    characters: Query<&Character>,
+    mut kills: EventReader<BossKilledEvent>,
+    mut scripts: EventWriter<ScriptCallEvent>,
+) {
+    let expires_at = next_weekly_reset(config.reset_day, config.reset_hour, clock.now_secs());
+    for kill in kills.read() {
+        let Some(instance) = instances.instances.get_mut(&kill.instance) else {
+            continue;
+        };
+        let Some(dungeon) = content.dungeons.get(&instance.dungeon) else {
+            continue;
+        };
+        if !dungeon.bosses.contains(&kill.boss) || !instance.killed.insert(kill.boss.clone()) {
+            continue;
+        }
+        if dungeon.script.is_some() {
+            scripts.send(ScriptCallEvent {
+                script: dungeon.script.clone(),
+                function: "on_boss_killed".to_string(),
+                args: vec![Dynamic::from(kill.instance as i64), Dynamic::from(kill.boss.clone())],
+            });
+        }
+        if !config.tier(dungeon, instance.difficulty).lockout {
+            continue;
+        }
+        for character in kill.participants.iter().filter_map(|&e| characters.get(e).ok()) {
+            lockouts.record_kill(
+                &character.name,
+                &dungeon.id,
+                instance.difficulty,
+                kill.instance,
+                &kill.boss,
+                expires_at,
+            );
+        }
+    }
+}
+
+fn weekly_reset(
+    time: Res<Time>,
+    clock: Res<WorldClock>,
+    mut lockouts: ResMut<Lockouts>,
+    mut resets: EventWriter<LockoutsResetEvent>,
+    mut since_check: Local<f32>,
+) {
+    *since_check += time.delta_secs();
+    if *since_check < 1.0 {
+        return;
+    }
+    *since_check = 0.0;
+    let expired = lockouts.expire(clock.now_secs());
+    if expired > 0 {
+        info!("Weekly reset cleared {} lockouts", expired);
+        resets.send(LockoutsResetEvent { expired });
+    }
+}
+
+fn lockouts_command(
+    content: Res<DungeonContent>,
+    clock: Res<WorldClock>,
+    mut lockouts: ResMut<Lockouts>,
+    mut events: EventReader<ConsoleCommandEvent>,
+    mut output: EventWriter<ConsoleOutputEvent>,
+) {
+    for event in events.read() {
+        if event.name != "lockouts" {
+            continue;
+        }
+        let now = clock.now_secs();
+        let result: Result<String, String> = match event.args.first().map(String::as_str) {
+            Some("reset") => {
+                let count = lockouts.characters.len();
+                lockouts.clear();
+                Ok(format!("Cleared lockouts for {} characters", count))
+            }
+            Some(name) => match lockouts.characters.get(name) {
+                Some(saved) => {
+                    let mut lines = vec![format!("{}:", name)];
+                    for lockout in saved {
+                        let total = content.dungeons.get(&lockout.dungeon).map_or(0, |d| d.bosses.len());
+                        lines.push(format!(
+                            "  {} ({}) instance {}: {}/{} bosses, resets in {}",
+                            lockout.dungeon,
+                            lockout.difficulty.name(),
+                            lockout.instance,
+                            lockout.bosses_killed.len(),
+                            total,
+                            time_until(lockout.expires_at, now)
+                        ));
+                    }
+                    Ok(lines.join("\n"))
+                }
+                None => Err(format!("'{}' has no lockouts", name)),
+            },
+            None => {
+                let saved: usize = lockouts.characters.values().map(Vec::len).sum();
+                Ok(format!("{} lockouts across {} characters", saved, lockouts.characters.len()))
+            }
+        };
+        output.send(match result {
+            Ok(text) => event.reply(text),
+            Err(text) => event.error(format!("{}: {}", event.name, text)),
+        });
+    }
+}
+
+fn save_lockouts(
+    time: Res<Time>,
+    config: Res<DungeonConfig>,
+    mut lockouts: ResMut<Lockouts>,
+    mut exits: EventReader<AppExit>,
+    mut since_save: Local<f32>,
+) {
+    let Some(path) = &config.lockouts_path else {
+        return;
+    };
+    *since_save += time.delta_secs();
+    let exiting = exits.read().count() > 0;
+    if !lockouts.dirty || (*since_save < config.save_interval_seconds && !exiting) {
+        return;
+    }
+    *since_save = 0.0;
+    match lockouts.save(path) {
+        Ok(()) => lockouts.dirty = false,
+        Err(e) => warn!("Failed to save lockouts {:?}: {}", path, e),
+    }
+}
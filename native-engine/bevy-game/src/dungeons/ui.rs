@@ -0,0 +1,76 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts, EguiPlugin};
+
+use super::{time_until, DungeonContent, Lockouts};
+use crate::input_map::{Action, ActionState};
+use crate::world_clock::WorldClock;
+use crate::{Character, Player};
+
+#[derive(Resource, Debug, Default)]
+pub struct LockoutWindow {
+    pub open: bool,
+}
+
+/// The local player's lockouts, toggled with O by default.
+pub struct LockoutUiPlugin;
+
+impl Plugin for LockoutUiPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugins(EguiPlugin);
+        }
+        app.init_resource::<LockoutWindow>().add_systems(
+            Update,
+            (toggle_lockout_window, lockout_window_ui.run_if(|window: Res<LockoutWindow>| window.open)).chain(),
+        );
+    }
+}
+
+fn toggle_lockout_window(actions: Res<ActionState>, mut window: ResMut<LockoutWindow>) {
+    if actions.just_pressed(Action::ToggleLockouts) {
+        window.open = !window.open;
+    }
+}
+
+fn lockout_window_ui(
+    mut contexts: EguiContexts,
+    mut window: ResMut<LockoutWindow>,
+    content: Res<DungeonContent>,
+    lockouts: Res<Lockouts>,
+    clock: Res<WorldClock>,
+    player: Query<&Character, With<Player>>,
+) {
+    let ctx = contexts.ctx_mut().clone();
+    let saved = player.get_single().ok().and_then(|c| lockouts.characters.get(&c.name));
+    let now = clock.now_secs();
+
+    egui::Window::new("Instance Lockouts")
+        .open(&mut window.open)
+        .resizable(false)
+        .show(&ctx, |ui| {
+            let Some(saved) = saved.filter(|s| !s.is_empty()) else {
+                ui.label("You are not saved to any instances.");
+                return;
+            };
+            egui::Grid::new("lockouts").striped(true).show(ui, |ui| {
+                ui.strong("Instance");
+                ui.strong("Difficulty");
+                ui.strong("Bosses");
+                ui.strong("Resets in");
+                ui.end_row();
+                for lockout in saved {
+                    let dungeon = content.dungeons.get(&lockout.dungeon);
+                    let name = dungeon.map_or(lockout.dungeon.as_str(), |d| {
+                        if d.name.is_empty() { d.id.as_str() } else { d.name.as_str() }
+                    });
+                    let total = dungeon.map_or(lockout.bosses_killed.len(), |d| d.bosses.len());
+                    ui.label(name);
+                    ui.label(lockout.difficulty.name());
+                    ui.label(format!("{}/{}", lockout.bosses_killed.len(), total))
+                        .on_hover_text(lockout.bosses_killed.join(", "));
+                    ui.label(time_until(lockout.expires_at, now));
+                    ui.end_row();
+                }
+            });
+        });
+}
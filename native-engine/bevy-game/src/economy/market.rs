@@ -0,0 +1,90 @@
+//! Price and stock drift. Each region keeps a pressure per item category:
+//! buying from its vendors pushes it up, selling to them pushes it down, and
+//! it decays back to zero over time. Prices and vendor stock follow the
+//! pressure within a band. The whole state is saved as JSON so it survives
+//! server restarts.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use super::EconomyConfig;
+use crate::database::ItemCategory;
+use crate::persistence;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct CategoryMarket {
+    /// -1 (flooded by sales) to 1 (bought out).
+    pub pressure: f32,
+    /// Lifetime units bought from and sold to vendors.
+    pub bought: u64,
+    pub sold: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct VendorStock {
+    pub count: u32,
+    /// Fraction of the next unit restocked.
+    #[serde(default)]
+    pub restock_progress: f32,
+}
+
+#[derive(Resource, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MarketState {
+    /// Region id, then category.
+    #[serde(default)]
+    pub regions: BTreeMap<String, BTreeMap<ItemCategory, CategoryMarket>>,
+    /// Vendor id, then item id.
+    #[serde(default)]
+    pub stock: BTreeMap<String, BTreeMap<String, VendorStock>>,
+    /// Changed since the last save.
+    #[serde(skip)]
+    pub dirty: bool,
+}
+
+impl MarketState {
+    pub fn market(&self, region: &str, category: ItemCategory) -> CategoryMarket {
+        self.regions.get(region).and_then(|r| r.get(&category)).copied().unwrap_or_default()
+    }
+
+    /// Price multiplier from trading volume alone, before regional pricing.
+    pub fn drift(&self, region: &str, category: ItemCategory, config: &EconomyConfig) -> f32 {
+        1.0 + self.market(region, category).pressure * config.max_drift
+    }
+
+    /// Records `units` bought from (positive) or sold to (negative) a
+    /// vendor in `region`.
+    pub fn record(&mut self, region: &str, category: ItemCategory, units: i64, config: &EconomyConfig) {
+        let market = self.regions.entry(region.to_string()).or_default().entry(category).or_default();
+        market.pressure = (market.pressure + units as f32 / config.reference_volume.max(1.0)).clamp(-1.0, 1.0);
+        if units >= 0 {
+            market.bought += units as u64;
+        } else {
+            market.sold += units.unsigned_abs();
+        }
+        self.dirty = true;
+    }
+
+    /// Eases every pressure back towards zero.
+    pub fn decay(&mut self, seconds: f32, config: &EconomyConfig) {
+        let keep = (-seconds / config.recovery_seconds.max(1.0)).exp();
+        for market in self.regions.values_mut().flat_map(|r| r.values_mut()) {
+            if market.pressure != 0.0 {
+                market.pressure *= keep;
+                if market.pressure.abs() < 0.001 {
+                    market.pressure = 0.0;
+                }
+                self.dirty = true;
+            }
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        persistence::load_json(path)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        persistence::save_json(self, path)
+    }
+}
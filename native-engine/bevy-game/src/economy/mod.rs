@@ -0,0 +1,477 @@
+//! NPC vendors and a light economy simulation. Regions and vendors live in
+//! `content/economy/*.toml`:
+//!
+//! ```toml
+//! [[region]]
+//! id = "westmarch"
+//! min = [-800.0, -800.0]
+//! max = [0.0, 0.0]
+//! # Produced here, so cheap; silk comes from far away
+//! prices = { reagent = 0.8, trade_good = 1.4 }
+//!
+//! [[vendor]]
+//! id = "westmarch_general"
+//! name = "Mara the Trader"
+//! region = "westmarch"
+//! restock_seconds = 600
+//!
+//! [[vendor.stock]]
+//! item = "linen_cloth"
+//! quantity = 40
+//! ```
+//!
+//! Prices start from an item's `sell_price`, scaled by the region's price
+//! for its category (the difference between regions is what makes trade
+//! routes pay) and by the trading volume drift in [`market`]. Vendors sell
+//! at a markup, restock over time towards a level that follows demand, and
+//! keep what players sell them. The authoritative instance saves the market
+//! state every `save_interval_seconds` and on exit.
+
+pub mod market;
+
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::console::{CommandPermission, ConsoleAppExt, ConsoleCommand, ConsoleCommandEvent, ConsoleOutputEvent};
+use crate::content_packs::ContentPacks;
+use crate::database::{GameDatabase, ItemCategory, ItemDef};
+
+pub use market::{CategoryMarket, MarketState, VendorStock};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegionDef {
+    pub id: String,
+    /// `[x, z]` corners of the area the region covers.
+    pub min: [f32; 2],
+    pub max: [f32; 2],
+    /// Price multiplier per category; unlisted categories are 1.
+    #[serde(default)]
+    pub prices: HashMap<ItemCategory, f32>,
+}
+
+impl RegionDef {
+    pub fn contains(&self, position: Vec3) -> bool {
+        (self.min[0]..=self.max[0]).contains(&position.x) && (self.min[1]..=self.max[1]).contains(&position.z)
+    }
+
+    pub fn price(&self, category: ItemCategory) -> f32 {
+        self.prices.get(&category).copied().unwrap_or(1.0)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VendorStockDef {
+    pub item: String,
+    /// Units on hand at normal demand.
+    pub quantity: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VendorDef {
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    pub region: String,
+    /// Seconds to restock from empty to the full quantity.
+    #[serde(default = "default_restock_seconds")]
+    pub restock_seconds: f32,
+    #[serde(default)]
+    pub stock: Vec<VendorStockDef>,
+}
+
+fn default_restock_seconds() -> f32 {
+    600.0
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EconomyContentFile {
+    #[serde(default, rename = "region")]
+    pub regions: Vec<RegionDef>,
+    #[serde(default, rename = "vendor")]
+    pub vendors: Vec<VendorDef>,
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct EconomyConfig {
+    pub content_directory: PathBuf,
+    /// Where the market state is saved; nothing is saved when `None`.
+    pub state_path: Option<PathBuf>,
+    pub save_interval_seconds: f32,
+    /// Vendors sell at this multiple of what they pay.
+    pub buy_markup: f32,
+    /// Trade goods sell near cost so carrying them between regions pays.
+    pub trade_good_markup: f32,
+    /// Largest price change from trading volume, as a fraction.
+    pub max_drift: f32,
+    /// Units traded in one direction that move pressure all the way.
+    pub reference_volume: f32,
+    /// Seconds for pressure to decay to about a third.
+    pub recovery_seconds: f32,
+    /// Vendors keep at most this multiple of their normal quantity.
+    pub max_stock_multiplier: f32,
+}
+
+impl Default for EconomyConfig {
+    fn default() -> Self {
+        Self {
+            content_directory: PathBuf::from("content").join("economy"),
+            state_path: Some(PathBuf::from("economy_state.json")),
+            save_interval_seconds: 60.0,
+            buy_markup: 4.0,
+            trade_good_markup: 1.25,
+            max_drift: 0.5,
+            reference_volume: 200.0,
+            recovery_seconds: 6.0 * 3600.0,
+            max_stock_multiplier: 2.0,
+        }
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct EconomyContent {
+    pub regions: Vec<RegionDef>,
+    pub vendors: HashMap<String, VendorDef>,
+}
+
+impl EconomyContent {
+    pub fn region(&self, id: &str) -> Option<&RegionDef> {
+        self.regions.iter().find(|r| r.id == id)
+    }
+
+    pub fn region_at(&self, position: Vec3) -> Option<&RegionDef> {
+        self.regions.iter().find(|r| r.contains(position))
+    }
+}
+
+/// Copper per unit for an item at a vendor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VendorPrice {
+    /// What the vendor charges.
+    pub buy: u64,
+    /// What the vendor pays.
+    pub sell: u64,
+}
+
+/// Prices for `item` in `region` right now.
+pub fn vendor_price(
+    item: &ItemDef,
+    region: Option<&RegionDef>,
+    market: &MarketState,
+    config: &EconomyConfig,
+) -> VendorPrice {
+    let category = item.market_category();
+    let regional = region.map_or(1.0, |r| r.price(category));
+    let drift = region.map_or(1.0, |r| market.drift(&r.id, category, config));
+    let value = item.sell_price as f32 * regional * drift;
+    let markup = if category == ItemCategory::TradeGood { config.trade_good_markup } else { config.buy_markup };
+    VendorPrice { buy: (value * markup).ceil().max(1.0) as u64, sell: value.floor() as u64 }
+}
+
+/// Marks an NPC as the vendor with this content id.
+#[derive(Component, Debug, Clone)]
+pub struct Vendor {
+    pub id: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TradeKind {
+    Buy,
+    Sell,
+}
+
+/// A character asks to buy from or sell to a vendor. The inventory sends
+/// these once it has checked the character can pay or holds the items.
+#[derive(Event, Debug, Clone)]
+pub struct VendorTradeRequest {
+    pub character: Entity,
+    pub vendor: String,
+    pub item: String,
+    pub count: u32,
+    pub kind: TradeKind,
+}
+
+/// A trade went through; the inventory moves the items and `copper`.
+#[derive(Event, Debug, Clone)]
+pub struct VendorTradeEvent {
+    pub character: Entity,
+    pub vendor: String,
+    pub item: String,
+    pub count: u32,
+    pub kind: TradeKind,
+    /// Total paid (buy) or received (sell).
+    pub copper: u64,
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct VendorTradeFailedEvent {
+    pub character: Entity,
+    pub vendor: String,
+    pub item: String,
+    pub reason: String,
+}
+
+pub struct EconomyPlugin;
+
+impl Plugin for EconomyPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EconomyConfig>()
+            .init_resource::<EconomyContent>()
+            .init_resource::<MarketState>()
+            .add_event::<VendorTradeRequest>()
+            .add_event::<VendorTradeEvent>()
+            .add_event::<VendorTradeFailedEvent>()
+            .add_console_command(
+                ConsoleCommand::new("market", "Shows regional price multipliers, or a vendor's stock and prices")
+                    .usage("[vendor id]")
+                    .permission(CommandPermission::Dev),
+            )
+            .add_systems(Startup, load_economy)
+            .add_systems(Update, (process_trades, simulate_market, market_command, save_market_state).chain());
+    }
+}
+
+fn load_economy(
+    config: Res<EconomyConfig>,
+    packs: Res<ContentPacks>,
+    mut content: ResMut<EconomyContent>,
+    mut market: ResMut<MarketState>,
+) {
+    for path in packs.files(&config.content_directory, "toml") {
+        let parsed = std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| toml::from_str::<EconomyContentFile>(&text).map_err(|e| e.to_string()));
+
+        match parsed {
+            Ok(file) => {
+                content.regions.extend(file.regions);
+                for vendor in file.vendors {
+                    content.vendors.insert(vendor.id.clone(), vendor);
+                }
+            }
+            Err(e) => warn!("Invalid economy file {:?}: {}", path, e),
+        }
+    }
+    for vendor in content.vendors.values().filter(|v| content.region(&v.region).is_none()) {
+        warn!("Vendor '{}' is in unknown region '{}'", vendor.id, vendor.region);
+    }
+
+    if let Some(path) = config.state_path.as_ref().filter(|p| p.exists()) {
+        match MarketState::load(path) {
+            Ok(state) => *market = state,
+            Err(e) => warn!("Invalid economy state {:?}: {}; starting fresh", path, e),
+        }
+    }
+    // New vendors and items start fully stocked
+    for vendor in content.vendors.values() {
+        let stock = market.stock.entry(vendor.id.clone()).or_default();
+        for def in &vendor.stock {
+            stock.entry(def.item.clone()).or_insert(VendorStock { count: def.quantity, restock_progress: 0.0 });
+        }
+    }
+
+    info!("Economy: {} regions, {} vendors loaded", content.regions.len(), content.vendors.len());
+}
+
+fn process_trades(
+    config: Res<EconomyConfig>,
+    content: Res<EconomyContent>,
+    database: Res<GameDatabase>,
+    mut market: ResMut<MarketState>,
+    mut requests: EventReader<VendorTradeRequest>,
+    mut trades: EventWriter<VendorTradeEvent>,
+    mut failures: EventWriter<VendorTradeFailedEvent>,
+) {
+    for request in requests.read() {
+        let result = trade(&config, &content, &database, &mut market, request);
+        match result {
+            Ok(copper) => {
+                trades.send(VendorTradeEvent {
+                    character: request.character,
+                    vendor: request.vendor.clone(),
+                    item: request.item.clone(),
+                    count: request.count,
+                    kind: request.kind,
+                    copper,
+                });
+            }
+            Err(reason) => {
+                failures.send(VendorTradeFailedEvent {
+                    character: request.character,
+                    vendor: request.vendor.clone(),
+                    item: request.item.clone(),
+                    reason,
+                });
+            }
+        }
+    }
+}
+
+/// Applies one trade to the market, returning its total in copper.
+fn trade(
+    config: &EconomyConfig,
+    content: &EconomyContent,
+    database: &GameDatabase,
+    market: &mut MarketState,
+    request: &VendorTradeRequest,
+) -> Result<u64, String> {
+    let vendor = content.vendors.get(&request.vendor).ok_or_else(|| format!("unknown vendor '{}'", request.vendor))?;
+    let item = database.items.by_key(&request.item).ok_or_else(|| format!("unknown item '{}'", request.item))?;
+    if request.count == 0 {
+        return Err("nothing to trade".to_string());
+    }
+    let region = content.region(&vendor.region);
+    let price = vendor_price(item, region, market, config);
+    let stocked = vendor.stock.iter().find(|s| s.item == item.id);
+
+    let (copper, units) = match request.kind {
+        TradeKind::Buy => {
+            let Some(stocked) = stocked else {
+                return Err(format!("{} doesn't sell {}", vendor.id, item.id));
+            };
+            let entry = market.stock.entry(vendor.id.clone()).or_default().entry(item.id.clone()).or_default();
+            if entry.count < request.count {
+                return Err(format!("only {} of {} left", entry.count, stocked.item));
+            }
+            entry.count -= request.count;
+            (price.buy * request.count as u64, request.count as i64)
+        }
+        TradeKind::Sell => {
+            if price.sell == 0 {
+                return Err(format!("{} won't buy {}", vendor.id, item.id));
+            }
+            // Vendors resell what they stock, up to their cap
+            if let Some(stocked) = stocked {
+                let cap = (stocked.quantity as f32 * config.max_stock_multiplier) as u32;
+                let entry = market.stock.entry(vendor.id.clone()).or_default().entry(item.id.clone()).or_default();
+                entry.count = (entry.count + request.count).min(cap.max(entry.count));
+            }
+            (price.sell * request.count as u64, -(request.count as i64))
+        }
+    };
+    market.record(&vendor.region, item.market_category(), units, config);
+    Ok(copper)
+}
+
+/// Eases prices back and restocks vendors towards a level that follows
+/// demand: more stock where the category sells out, less where it floods.
+fn simulate_market(
+    time: Res<Time>,
+    config: Res<EconomyConfig>,
+    content: Res<EconomyContent>,
+    database: Res<GameDatabase>,
+    mut market: ResMut<MarketState>,
+) {
+    let dt = time.delta_secs();
+    if dt <= 0.0 {
+        return;
+    }
+    market.decay(dt, &config);
+
+    let MarketState { regions, stock, dirty } = &mut *market;
+    for vendor in content.vendors.values() {
+        let Some(stock) = stock.get_mut(&vendor.id) else {
+            continue;
+        };
+        let region = regions.get(&vendor.region);
+        for def in &vendor.stock {
+            let Some(entry) = stock.get_mut(&def.item) else {
+                continue;
+            };
+            let category = database.items.by_key(&def.item).map(|item| item.market_category());
+            let pressure = category.and_then(|c| region?.get(&c)).map_or(0.0, |m| m.pressure);
+            let target = (def.quantity as f32 * (1.0 + pressure)).round() as u32;
+            if entry.count >= target {
+                entry.restock_progress = 0.0;
+                continue;
+            }
+            entry.restock_progress += dt * def.quantity as f32 / vendor.restock_seconds.max(1.0);
+            let restocked = entry.restock_progress.floor();
+            if restocked >= 1.0 {
+                entry.count = (entry.count + restocked as u32).min(target);
+                entry.restock_progress -= restocked;
+                *dirty = true;
+            }
+        }
+    }
+}
+
+fn save_market_state(
+    time: Res<Time>,
+    config: Res<EconomyConfig>,
+    mut market: ResMut<MarketState>,
+    mut exits: EventReader<AppExit>,
+    mut since_save: Local<f32>,
+) {
+    let Some(path) = &config.state_path else {
+        return;
+    };
+    *since_save += time.delta_secs();
+    let exiting = exits.read().count() > 0;
+    if !market.dirty || (*since_save < config.save_interval_seconds && !exiting) {
+        return;
+    }
+    *since_save = 0.0;
+    match market.save(path) {
+        Ok(()) => market.dirty = false,
+        Err(e) => warn!("Failed to save economy state {:?}: {}", path, e),
+    }
+}
+
+fn market_command(
+    config: Res<EconomyConfig>,
+    content: Res<EconomyContent>,
+    database: Res<GameDatabase>,
+    market: Res<MarketState>,
+    mut events: EventReader<ConsoleCommandEvent>,
+    mut output: EventWriter<ConsoleOutputEvent>,
+) {
+    for event in events.read() {
+        if event.name != "market" {
+            continue;
+        }
+        let result: Result<String, String> = match event.args.first() {
+            None => {
+                let mut lines = Vec::new();
+                for region in &content.regions {
+                    let prices: Vec<String> = ItemCategory::ALL
+                        .iter()
+                        .map(|&category| {
+                            let price = region.price(category) * market.drift(&region.id, category, &config);
+                            format!("{} x{:.2}", category.name(), price)
+                        })
+                        .collect();
+                    lines.push(format!("{}: {}", region.id, prices.join(", ")));
+                }
+                Ok(if lines.is_empty() { "No regions loaded".to_string() } else { lines.join("\n") })
+            }
+            Some(id) => match content.vendors.get(id) {
+                Some(vendor) => {
+                    let region = content.region(&vendor.region);
+                    let mut lines = vec![format!("{} ({})", vendor.id, vendor.region)];
+                    for def in &vendor.stock {
+                        let count = market.stock.get(&vendor.id).and_then(|s| s.get(&def.item)).map_or(0, |s| s.count);
+                        match database.items.by_key(&def.item) {
+                            Some(item) => {
+                                let price = vendor_price(item, region, &market, &config);
+                                lines.push(format!(
+                                    "  {} {}/{}: buy {}c, sell {}c",
+                                    def.item, count, def.quantity, price.buy, price.sell
+                                ));
+                            }
+                            None => lines.push(format!("  {}: unknown item", def.item)),
+                        }
+                    }
+                    Ok(lines.join("\n"))
+                }
+                None => Err(format!("unknown vendor '{}'", id)),
+            },
+        };
+        output.send(match result {
+            Ok(text) => event.reply(text),
+            Err(text) => event.error(format!("{}: {}", event.name, text)),
+        });
+    }
+}
@@ -0,0 +1,409 @@
+//! Encounter statistics from the combat log: damage, healing and threat
+//! per participant for each fight, the damage meter widget ([`MeterUiPlugin`])
+//! and JSON reports.
+//!
+//! A fight starts with the first damage the local player deals or takes.
+//! Anyone who then hits, heals or is hit by someone already in the fight
+//! joins it, so group members and pets are counted. It ends after
+//! `idle_seconds` without damage, when the local player dies (a wipe) or
+//! when a dungeon boss dies (a kill, named after the boss).
+//!
+//! Threat is estimated from the log as weighted damage and healing; the
+//! AI's own threat tables aren't visible here.
+
+mod ui;
+
+pub use ui::*;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+
+use crate::combat_log::{CombatEventKind, CombatRecord, CombatRecordEvent};
+use crate::console::{ConsoleAppExt, ConsoleCommand, ConsoleCommandEvent, ConsoleOutputEvent};
+use crate::dungeons::BossKilledEvent;
+use crate::Player;
+
+#[derive(Resource, Debug, Clone)]
+pub struct EncounterConfig {
+    /// Seconds without damage that end a fight.
+    pub idle_seconds: f64,
+    /// Finished fights kept for the meter.
+    pub history: usize,
+    /// Threat per point of damage and healing.
+    pub damage_threat: f32,
+    pub heal_threat: f32,
+    pub report_directory: PathBuf,
+    /// Write a report for every boss kill or wipe.
+    pub export_boss_encounters: bool,
+}
+
+impl Default for EncounterConfig {
+    fn default() -> Self {
+        Self {
+            idle_seconds: 5.0,
+            history: 20,
+            damage_threat: 1.0,
+            heal_threat: 0.5,
+            report_directory: PathBuf::from("encounter_reports"),
+            export_boss_encounters: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AbilityStats {
+    pub hits: u32,
+    pub crits: u32,
+    pub total: f32,
+    pub largest: f32,
+}
+
+impl AbilityStats {
+    fn add(&mut self, record: &CombatRecord) {
+        self.hits += 1;
+        self.crits += u32::from(record.critical);
+        self.total += record.amount;
+        self.largest = self.largest.max(record.amount);
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Participant {
+    pub name: String,
+    pub damage: f32,
+    pub healing: f32,
+    pub threat: f32,
+    pub damage_taken: f32,
+    pub deaths: u32,
+    /// Damage abilities by name.
+    pub damage_abilities: BTreeMap<String, AbilityStats>,
+    /// Healing abilities by name.
+    pub heal_abilities: BTreeMap<String, AbilityStats>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EncounterOutcome {
+    InProgress,
+    /// Combat stopped without a boss kill or a wipe.
+    Ended,
+    Kill,
+    Wipe,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Encounter {
+    /// Named after the boss, or whatever took the most damage.
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub boss: Option<String>,
+    /// Unix seconds of the first and last damage.
+    pub started_at: f64,
+    pub last_damage_at: f64,
+    pub outcome: EncounterOutcome,
+    /// Participants by entity id (as in the combat log file).
+    pub participants: BTreeMap<u64, Participant>,
+    #[serde(skip)]
+    members: HashSet<u64>,
+}
+
+impl Encounter {
+    fn new(started_at: f64) -> Self {
+        Self {
+            name: String::new(),
+            boss: None,
+            started_at,
+            last_damage_at: started_at,
+            outcome: EncounterOutcome::InProgress,
+            participants: BTreeMap::new(),
+            members: HashSet::new(),
+        }
+    }
+
+    /// Seconds from the first to the last damage, at least one.
+    pub fn duration(&self) -> f64 {
+        (self.last_damage_at - self.started_at).max(1.0)
+    }
+
+    pub fn dps(&self, participant: &Participant) -> f32 {
+        participant.damage / self.duration() as f32
+    }
+
+    pub fn hps(&self, participant: &Participant) -> f32 {
+        participant.healing / self.duration() as f32
+    }
+
+    /// Whether `record` involves someone already in the fight.
+    fn involves(&self, record: &CombatRecord) -> bool {
+        self.members.contains(&record.target_id) || record.source_id.is_some_and(|id| self.members.contains(&id))
+    }
+
+    fn add(&mut self, record: &CombatRecord, config: &EncounterConfig) {
+        self.members.insert(record.target_id);
+        let source = record.source_id.zip(record.source.as_ref()).map(|(id, name)| {
+            self.members.insert(id);
+            let participant = self.participants.entry(id).or_default();
+            participant.name.clone_from(name);
+            id
+        });
+        let entry = |participants: &mut BTreeMap<u64, Participant>, id: u64, name: &str| {
+            let participant = participants.entry(id).or_default();
+            if participant.name.is_empty() {
+                participant.name = name.to_string();
+            }
+        };
+
+        match record.kind {
+            CombatEventKind::Damage => {
+                self.last_damage_at = record.t;
+                if let Some(source) = source.and_then(|id| self.participants.get_mut(&id)) {
+                    source.damage += record.amount;
+                    source.threat += record.amount * config.damage_threat;
+                    source.damage_abilities.entry(record.ability.clone()).or_default().add(record);
+                }
+                entry(&mut self.participants, record.target_id, &record.target);
+                if let Some(target) = self.participants.get_mut(&record.target_id) {
+                    target.damage_taken += record.amount;
+                }
+            }
+            CombatEventKind::Heal => {
+                if let Some(source) = source.and_then(|id| self.participants.get_mut(&id)) {
+                    // Overhealing isn't in the log, so it counts in full
+                    source.healing += record.amount;
+                    source.threat += record.amount * config.heal_threat;
+                    source.heal_abilities.entry(record.ability.clone()).or_default().add(record);
+                }
+            }
+            CombatEventKind::Death => {
+                entry(&mut self.participants, record.target_id, &record.target);
+                if let Some(target) = self.participants.get_mut(&record.target_id) {
+                    target.deaths += 1;
+                }
+            }
+            CombatEventKind::BuffApplied | CombatEventKind::BuffRemoved => {}
+        }
+    }
+
+    /// Names it after whatever took the most damage, unless a boss died.
+    fn finish(&mut self, outcome: EncounterOutcome, player: Option<u64>) {
+        self.outcome = outcome;
+        if self.boss.is_none() {
+            let most_hit = self
+                .participants
+                .iter()
+                .filter(|(id, _)| Some(**id) != player)
+                .max_by(|a, b| a.1.damage_taken.total_cmp(&b.1.damage_taken));
+            self.name = most_hit.map_or("Unknown".to_string(), |(_, p)| p.name.clone());
+        }
+    }
+
+    /// Participants who did damage or healing, most first by `key`.
+    pub fn ranked(&self, key: impl Fn(&Participant) -> f32) -> Vec<(u64, &Participant)> {
+        let mut ranked: Vec<(u64, &Participant)> =
+            self.participants.iter().map(|(id, p)| (*id, p)).filter(|(_, p)| key(p) > 0.0).collect();
+        ranked.sort_by(|a, b| key(b.1).total_cmp(&key(a.1)));
+        ranked
+    }
+
+    /// Writes the encounter as pretty JSON under `directory`; returns the path.
+    pub fn export(&self, directory: &Path) -> Result<PathBuf, String> {
+        let safe_name: String = self
+            .name
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '_' })
+            .collect();
+        let path = directory.join(format!("{}-{}.json", self.started_at as u64, safe_name));
+        let text = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::create_dir_all(directory).map_err(|e| e.to_string())?;
+        std::fs::write(&path, text).map_err(|e| e.to_string())?;
+        Ok(path)
+    }
+}
+
+/// The fight in progress and recent ones, newest last.
+#[derive(Resource, Debug, Default)]
+pub struct Encounters {
+    pub current: Option<Encounter>,
+    pub history: VecDeque<Encounter>,
+}
+
+impl Encounters {
+    /// The fight in progress, or the last one.
+    pub fn latest(&self) -> Option<&Encounter> {
+        self.current.as_ref().or(self.history.back())
+    }
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct EncounterEndedEvent {
+    pub name: String,
+    pub outcome: EncounterOutcome,
+}
+
+pub struct EncounterPlugin;
+
+impl Plugin for EncounterPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EncounterConfig>()
+            .init_resource::<Encounters>()
+            .add_event::<CombatRecordEvent>()
+            .add_event::<BossKilledEvent>()
+            .add_event::<EncounterEndedEvent>()
+            .add_console_command(
+                ConsoleCommand::new("encounter", "Summarizes a recent fight, or exports it to JSON")
+                    .usage("[export] [fights ago]"),
+            )
+            .add_systems(Update, (track_encounters, encounter_command).chain());
+    }
+}
+
+fn end_encounter(
+    encounters: &mut Encounters,
+    config: &EncounterConfig,
+    outcome: EncounterOutcome,
+    player: Option<u64>,
+    ended: &mut EventWriter<EncounterEndedEvent>,
+) {
+    let Some(mut encounter) = encounters.current.take() else {
+        return;
+    };
+    encounter.finish(outcome, player);
+    info!(
+        "Encounter {} {:?} after {:.0}s",
+        encounter.name,
+        encounter.outcome,
+        encounter.duration()
+    );
+    if config.export_boss_encounters && encounter.boss.is_some() {
+        if let Err(e) = encounter.export(&config.report_directory) {
+            warn!("Failed to export encounter {}: {}", encounter.name, e);
+        }
+    }
+    ended.send(EncounterEndedEvent {
+        name: encounter.name.clone(),
+        outcome: encounter.outcome,
+    });
+    encounters.history.push_back(encounter);
+    while encounters.history.len() > config.history {
+        encounters.history.pop_front();
+    }
+}
+
+fn track_encounters(
+    config: Res<EncounterConfig>,
+    mut encounters: ResMut<Encounters>,
+    players: Query<Entity, With<Player>>,
+    mut records: EventReader<CombatRecordEvent>,
+    mut bosses: EventReader<BossKilledEvent>,
+    mut ended: EventWriter<EncounterEndedEvent>,
+) {
+    let player = players.get_single().ok().map(Entity::to_bits);
+
+    for event in records.read() {
+        let record = &event.record;
+        if let Some(current) = &encounters.current {
+            if record.t - current.last_damage_at > config.idle_seconds {
+                end_encounter(&mut encounters, &config, EncounterOutcome::Ended, player, &mut ended);
+            }
+        }
+        let Some(player) = player else {
+            continue;
+        };
+        let starts = record.kind == CombatEventKind::Damage
+            && (record.target_id == player || record.source_id == Some(player));
+        if encounters.current.is_none() && starts {
+            let mut encounter = Encounter::new(record.t);
+            encounter.members.insert(player);
+            encounters.current = Some(encounter);
+        }
+        let Some(current) = encounters.current.as_mut() else {
+            continue;
+        };
+        if current.involves(record) {
+            current.add(record, &config);
+        }
+        if record.kind == CombatEventKind::Death && record.target_id == player {
+            end_encounter(&mut encounters, &config, EncounterOutcome::Wipe, Some(player), &mut ended);
+        }
+    }
+
+    for kill in bosses.read() {
+        if let Some(current) = encounters.current.as_mut() {
+            current.boss = Some(kill.boss.clone());
+            current.name = kill.boss.clone();
+            end_encounter(&mut encounters, &config, EncounterOutcome::Kill, player, &mut ended);
+        }
+    }
+
+    // Ends a quiet fight even when nothing else is logged
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs_f64())
+        .unwrap_or(0.0);
+    if encounters.current.as_ref().is_some_and(|c| now - c.last_damage_at > config.idle_seconds) {
+        end_encounter(&mut encounters, &config, EncounterOutcome::Ended, player, &mut ended);
+    }
+}
+
+fn summary(encounter: &Encounter) -> String {
+    let mut lines = vec![format!(
+        "{} ({:?}, {:.0}s)",
+        if encounter.name.is_empty() { "Current fight" } else { encounter.name.as_str() },
+        encounter.outcome,
+        encounter.duration()
+    )];
+    for (_, participant) in encounter.ranked(|p| p.damage.max(p.healing)) {
+        lines.push(format!(
+            "  {}: {:.0} dps, {:.0} hps, {:.0} threat",
+            participant.name,
+            encounter.dps(participant),
+            encounter.hps(participant),
+            participant.threat
+        ));
+    }
+    lines.join("\n")
+}
+
+fn encounter_command(
+    config: Res<EncounterConfig>,
+    encounters: Res<Encounters>,
+    mut events: EventReader<ConsoleCommandEvent>,
+    mut output: EventWriter<ConsoleOutputEvent>,
+) {
+    for event in events.read() {
+        if event.name != "encounter" {
+            continue;
+        }
+        let export = event.args.first().is_some_and(|a| a == "export");
+        let index = usize::from(export);
+        let result = event
+            .args
+            .get(index)
+            .map(|_| event.arg::<usize>(index, "fights ago"))
+            .transpose()
+            .and_then(|ago| {
+                let ago = ago.unwrap_or(0);
+                let finished = encounters.history.len();
+                let encounter = match (&encounters.current, ago) {
+                    (Some(current), 0) => Some(current),
+                    (Some(_), ago) => finished.checked_sub(ago).and_then(|i| encounters.history.get(i)),
+                    (None, ago) => finished.checked_sub(ago + 1).and_then(|i| encounters.history.get(i)),
+                };
+                encounter.ok_or_else(|| "no such fight".to_string())
+            })
+            .and_then(|encounter| {
+                if export {
+                    let path = encounter.export(&config.report_directory)?;
+                    Ok(format!("Exported to {}", path.display()))
+                } else {
+                    Ok(summary(encounter))
+                }
+            });
+        output.send(match result {
+            Ok(text) => event.reply(text),
+            Err(text) => event.error(format!("{}: {}", event.name, text)),
+        });
+    }
+}
@@ -0,0 +1,161 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts, EguiPlugin};
+
+use super::{Encounter, EncounterConfig, Encounters, Participant};
+use crate::input_map::{Action, ActionState};
+use crate::Player;
+
+/// Bars drawn at most.
+const MAX_ROWS: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MeterMode {
+    #[default]
+    Damage,
+    Healing,
+    Threat,
+    DamageTaken,
+}
+
+impl MeterMode {
+    pub const ALL: [MeterMode; 4] = [MeterMode::Damage, MeterMode::Healing, MeterMode::Threat, MeterMode::DamageTaken];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            MeterMode::Damage => "Damage",
+            MeterMode::Healing => "Healing",
+            MeterMode::Threat => "Threat",
+            MeterMode::DamageTaken => "Taken",
+        }
+    }
+
+    fn value(self, participant: &Participant) -> f32 {
+        match self {
+            MeterMode::Damage => participant.damage,
+            MeterMode::Healing => participant.healing,
+            MeterMode::Threat => participant.threat,
+            MeterMode::DamageTaken => participant.damage_taken,
+        }
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct DamageMeter {
+    pub open: bool,
+    pub mode: MeterMode,
+    /// Fights back from the latest; 0 follows the current one.
+    pub fights_ago: usize,
+    /// Result of the last export, shown under the bars.
+    pub status: Option<String>,
+}
+
+/// The damage meter, toggled with K by default.
+pub struct MeterUiPlugin;
+
+impl Plugin for MeterUiPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugins(EguiPlugin);
+        }
+        app.init_resource::<DamageMeter>().add_systems(
+            Update,
+            (toggle_damage_meter, damage_meter_ui.run_if(|meter: Res<DamageMeter>| meter.open)).chain(),
+        );
+    }
+}
+
+fn toggle_damage_meter(actions: Res<ActionState>, mut meter: ResMut<DamageMeter>) {
+    if actions.just_pressed(Action::ToggleDamageMeter) {
+        meter.open = !meter.open;
+    }
+}
+
+fn title(encounter: &Encounter) -> String {
+    if encounter.name.is_empty() {
+        "Current fight".to_string()
+    } else {
+        encounter.name.clone()
+    }
+}
+
+fn damage_meter_ui(
+    mut contexts: EguiContexts,
+    mut meter: ResMut<DamageMeter>,
+    config: Res<EncounterConfig>,
+    encounters: Res<Encounters>,
+    player: Query<Entity, With<Player>>,
+) {
+    let ctx = contexts.ctx_mut().clone();
+    let player = player.get_single().ok().map(Entity::to_bits);
+    let meter = &mut *meter;
+    let mut open = meter.open;
+
+    // Newest first: the current fight, then finished ones
+    let fights: Vec<&Encounter> = encounters.current.iter().chain(encounters.history.iter().rev()).collect();
+    meter.fights_ago = meter.fights_ago.min(fights.len().saturating_sub(1));
+
+    egui::Window::new("Damage Meter")
+        .open(&mut open)
+        .default_width(260.0)
+        .resizable(false)
+        .show(&ctx, |ui| {
+            let Some(encounter) = fights.get(meter.fights_ago).copied() else {
+                ui.weak("No fights yet.");
+                return;
+            };
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_id_salt("meter_fight")
+                    .selected_text(title(encounter))
+                    .show_ui(ui, |ui| {
+                        for (i, fight) in fights.iter().enumerate() {
+                            ui.selectable_value(&mut meter.fights_ago, i, title(fight));
+                        }
+                    });
+                ui.weak(format!("{:.0}s", encounter.duration()));
+            });
+            ui.horizontal(|ui| {
+                for mode in MeterMode::ALL {
+                    ui.selectable_value(&mut meter.mode, mode, mode.label());
+                }
+            });
+            ui.separator();
+
+            let mode = meter.mode;
+            let ranked = encounter.ranked(|p| mode.value(p));
+            let top = ranked.first().map_or(1.0, |(_, p)| mode.value(p)).max(1.0);
+            if ranked.is_empty() {
+                ui.weak("Nothing yet.");
+            }
+            for (id, participant) in ranked.into_iter().take(MAX_ROWS) {
+                let value = mode.value(participant);
+                let text = match mode {
+                    MeterMode::Threat => format!("{}  {:.0}", participant.name, value),
+                    _ => format!(
+                        "{}  {:.0} ({:.0}/s)",
+                        participant.name,
+                        value,
+                        value / encounter.duration() as f32
+                    ),
+                };
+                let color = if Some(id) == player {
+                    egui::Color32::from_rgb(70, 130, 200)
+                } else {
+                    egui::Color32::from_rgb(90, 90, 110)
+                };
+                ui.add(egui::ProgressBar::new(value / top).text(text).fill(color));
+            }
+
+            ui.separator();
+            if ui.small_button("Export report").clicked() {
+                meter.status = Some(match encounter.export(&config.report_directory) {
+                    Ok(path) => format!("Saved {}", path.display()),
+                    Err(e) => format!("Export failed: {}", e),
+                });
+            }
+            if let Some(status) = &meter.status {
+                ui.weak(status);
+            }
+        });
+
+    meter.open = open;
+}
@@ -71,6 +71,7 @@ pub struct CharacterMovementConfig {
     pub snap_to_ground: f32,
     pub push_power: f32,
     pub mass: f32,
+    pub climb_speed: f32,
 }
 
 impl Default for CharacterMovementConfig {
@@ -89,6 +90,7 @@ impl Default for CharacterMovementConfig {
             snap_to_ground: 0.3,
             push_power: 2.0,
             mass: 80.0,
+            climb_speed: 3.0,
         }
     }
 }
@@ -109,6 +111,7 @@ impl CharacterMovementConfig {
             snap_to_ground: 0.4,
             push_power: 1.5,
             mass: 75.0,
+            climb_speed: 2.5,
         }
     }
 
@@ -127,6 +130,7 @@ impl CharacterMovementConfig {
             snap_to_ground: 0.3,
             push_power: 0.5,
             mass: 70.0,
+            climb_speed: 2.0,
         }
     }
 
@@ -154,10 +158,13 @@ pub struct CharacterController {
     pub is_sprinting: bool,
     pub is_climbing: bool,
     pub is_swimming: bool,
+    pub climb_normal: Vec3,
     
     pub external_velocity: Vec3,
     pub platform_velocity: Vec3,
     pub last_ground_position: Vec3,
+    landing_speed: Option<f32>,
+    landed_this_step: Option<f32>,
     
     enabled: bool,
 }
@@ -182,9 +189,12 @@ impl Default for CharacterController {
             is_sprinting: false,
             is_climbing: false,
             is_swimming: false,
+            climb_normal: Vec3::ZERO,
             external_velocity: Vec3::ZERO,
             platform_velocity: Vec3::ZERO,
             last_ground_position: Vec3::ZERO,
+            landing_speed: None,
+            landed_this_step: None,
             enabled: true,
         }
     }
@@ -221,6 +231,16 @@ impl CharacterController {
     }
 
     pub fn jump(&mut self, gravity: f32) {
+        if self.is_climbing && self.enabled {
+            // Jumping off a wall pushes away from it
+            let push_off = self.climb_normal * self.config.max_speed * 0.5;
+            self.stop_climbing();
+            self.velocity = push_off;
+            self.velocity.y = self.config.calculate_jump_velocity(gravity) * 0.7;
+            self.jump_count_remaining = self.jump_count_remaining.saturating_sub(1);
+            return;
+        }
+
         if self.can_jump() {
             let jump_vel = self.config.calculate_jump_velocity(gravity);
             self.velocity.y = jump_vel;
@@ -247,6 +267,23 @@ impl CharacterController {
             || self.jump_count_remaining > 0
     }
 
+    pub fn start_climbing(&mut self, wall_normal: Vec3) {
+        self.is_climbing = true;
+        self.climb_normal = Vec3::new(wall_normal.x, 0.0, wall_normal.z).normalize_or_zero();
+        self.velocity = Vec3::ZERO;
+        self.ground_info.state = GroundState::Climbing;
+        self.jump_count_remaining = self.config.jump_count;
+    }
+
+    pub fn stop_climbing(&mut self) {
+        if !self.is_climbing {
+            return;
+        }
+        self.is_climbing = false;
+        self.climb_normal = Vec3::ZERO;
+        self.ground_info.state = GroundState::Airborne;
+    }
+
     pub fn set_crouching(&mut self, crouching: bool) {
         self.is_crouching = crouching;
     }
@@ -267,6 +304,17 @@ impl CharacterController {
         self.enabled
     }
 
+    /// Vertical speed of the most recent landing, cleared once read. Used for fall damage.
+    pub fn take_landing_speed(&mut self) -> Option<f32> {
+        self.landing_speed.take()
+    }
+
+    /// Landing speed if the character touched down during the last controller
+    /// step. Unlike `take_landing_speed` this is not consumed; used by foley.
+    pub fn landed_this_step(&self) -> Option<f32> {
+        self.landed_this_step
+    }
+
     pub fn get_current_speed(&self) -> f32 {
         Vec3::new(self.velocity.x, 0.0, self.velocity.z).length()
     }
@@ -289,6 +337,7 @@ impl CharacterController {
         current_position: Vec3,
     ) {
         let was_grounded = self.ground_info.is_grounded();
+        self.landed_this_step = None;
         
         if output.grounded {
             self.ground_info.state = GroundState::Grounded;
@@ -297,7 +346,15 @@ impl CharacterController {
             self.coyote_time = 0.15;
             self.last_ground_position = current_position;
 
-            if let Some(collision) = output.collisions.first() {
+            // Pick the most upward-facing contact so walls touched this frame
+            // don't get mistaken for the surface we're standing on
+            let ground_collision = output
+                .collisions
+                .iter()
+                .max_by(|a, b| a.hit.normal.y.total_cmp(&b.hit.normal.y));
+
+            if let Some(collision) = ground_collision {
+                self.ground_info.ground_entity = Some(collision.entity);
                 self.ground_info.ground_normal = collision.hit.normal;
                 self.ground_info.slope_angle = collision.hit.normal.dot(Vec3::Y).acos().to_degrees();
                 
@@ -310,9 +367,16 @@ impl CharacterController {
                 self.jump_buffer_time = 0.0;
             }
 
-            if !was_grounded {
+            // Ignore the frame a jump starts on: the output still reports grounded
+            if !was_grounded && self.velocity.y <= 0.0 {
+                self.landing_speed = Some(-self.velocity.y);
+                self.landed_this_step = self.landing_speed;
+                self.velocity.y = 0.0;
                 log::debug!("CharacterController: Landed");
             }
+        } else if self.is_climbing {
+            self.ground_info.state = GroundState::Climbing;
+            self.ground_info.ground_entity = None;
         } else {
             self.ground_info.state = GroundState::Airborne;
             self.ground_info.ground_entity = None;
@@ -336,6 +400,10 @@ impl CharacterController {
             self.jump_buffer_time -= dt;
         }
 
+        if self.is_climbing {
+            return self.compute_climb_movement(dt);
+        }
+
         let is_grounded = self.ground_info.is_grounded();
         let control = if is_grounded { 1.0 } else { self.config.air_control };
         let max_speed = self.get_effective_max_speed();
@@ -390,6 +458,22 @@ impl CharacterController {
         movement
     }
 
+    /// Wall-relative movement: pushing into the wall climbs up, pulling away
+    /// climbs down, sideways input shimmies along the surface.
+    fn compute_climb_movement(&mut self, dt: f32) -> Vec3 {
+        let into_wall = -self.climb_normal;
+        let right = Vec3::Y.cross(self.climb_normal).normalize_or_zero();
+
+        let vertical = self.input_direction.dot(into_wall);
+        let lateral = self.input_direction.dot(right);
+
+        self.velocity = (Vec3::Y * vertical + right * lateral).clamp_length_max(1.0) * self.config.climb_speed;
+
+        // Small pull toward the wall keeps the controller in contact with it
+        let stick = into_wall * 0.5;
+        (self.velocity + stick + self.platform_velocity) * dt
+    }
+
     pub fn handle_step(
         &mut self,
         rapier_context: &RapierContext,
@@ -0,0 +1,403 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use super::{update_character_controllers, CharacterController, PhysicsFabric};
+
+/// Name suffix content authors use to mark climbable meshes in glTF scenes.
+pub const CLIMBABLE_NAME_SUFFIX: &str = "_climbable";
+
+/// Marks a collider as climbable. Added directly by gameplay code or picked up
+/// from content via [`CLIMBABLE_NAME_SUFFIX`].
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Climbable {
+    pub stamina_multiplier: f32,
+    pub allow_ledge_grab: bool,
+}
+
+impl Default for Climbable {
+    fn default() -> Self {
+        Self {
+            stamina_multiplier: 1.0,
+            allow_ledge_grab: true,
+        }
+    }
+}
+
+#[derive(Component, Debug, Clone, Copy)]
+pub struct ClimbingConfig {
+    pub detect_distance: f32,
+    pub probe_radius: f32,
+    /// Height of the chest probe above the character origin.
+    pub chest_height: f32,
+    /// How far above the chest a ledge top may be and still be grabbed.
+    pub ledge_reach: f32,
+    pub mantle_duration: f32,
+    pub max_wall_tilt: f32,
+    pub stamina_per_second: f32,
+    pub ledge_grab_stamina: f32,
+    pub mantle_stamina: f32,
+    pub character_half_height: f32,
+}
+
+impl Default for ClimbingConfig {
+    fn default() -> Self {
+        Self {
+            detect_distance: 0.6,
+            probe_radius: 0.25,
+            chest_height: 0.4,
+            ledge_reach: 0.9,
+            mantle_duration: 0.45,
+            max_wall_tilt: 30.0,
+            stamina_per_second: 8.0,
+            ledge_grab_stamina: 5.0,
+            mantle_stamina: 10.0,
+            character_half_height: 0.9,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ClimbMode {
+    #[default]
+    None,
+    Climbing {
+        surface: Entity,
+    },
+    Hanging {
+        ledge_top: Vec3,
+        wall_normal: Vec3,
+    },
+    Mantling {
+        from: Vec3,
+        to: Vec3,
+        elapsed: f32,
+    },
+}
+
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct ClimbingState {
+    pub mode: ClimbMode,
+    /// Set by input code while the climb key is held.
+    pub wants_climb: bool,
+    /// Set by input code to pull up from a hanging position.
+    pub wants_mantle: bool,
+}
+
+impl ClimbingState {
+    pub fn is_active(&self) -> bool {
+        self.mode != ClimbMode::None
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClimbAction {
+    Climb,
+    LedgeGrab,
+    Mantle,
+}
+
+/// Emitted whenever climbing should cost stamina. The stats system owns the
+/// stamina pool and answers with [`ClimbStaminaExhausted`] when it runs dry.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct ClimbStaminaCost {
+    pub entity: Entity,
+    pub action: ClimbAction,
+    pub amount: f32,
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct ClimbStaminaExhausted {
+    pub entity: Entity,
+}
+
+pub struct ClimbingPlugin;
+
+impl Plugin for ClimbingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ClimbStaminaCost>()
+            .add_event::<ClimbStaminaExhausted>()
+            .add_systems(Update, tag_climbable_content)
+            .add_systems(
+                PostUpdate,
+                (handle_stamina_exhaustion, update_climbing)
+                    .chain()
+                    .before(update_character_controllers),
+            );
+    }
+}
+
+fn tag_climbable_content(
+    mut commands: Commands,
+    named: Query<(Entity, &Name), (Added<Name>, Without<Climbable>)>,
+) {
+    for (entity, name) in named.iter() {
+        if name.as_str().ends_with(CLIMBABLE_NAME_SUFFIX) {
+            commands.entity(entity).insert(Climbable::default());
+        }
+    }
+}
+
+fn handle_stamina_exhaustion(
+    mut events: EventReader<ClimbStaminaExhausted>,
+    mut climbers: Query<(&mut CharacterController, &mut ClimbingState)>,
+) {
+    for event in events.read() {
+        let Ok((mut controller, mut state)) = climbers.get_mut(event.entity) else {
+            continue;
+        };
+        // Mantling is committed; everything else lets go
+        if matches!(state.mode, ClimbMode::Mantling { .. }) {
+            continue;
+        }
+        state.mode = ClimbMode::None;
+        controller.stop_climbing();
+        controller.set_enabled(true);
+        log::debug!("Climbing: {:?} ran out of stamina", event.entity);
+    }
+}
+
+struct WallHit {
+    surface: Entity,
+    point: Vec3,
+    normal: Vec3,
+}
+
+fn probe_wall(
+    rapier_context: &RapierContext,
+    origin: Vec3,
+    direction: Vec3,
+    config: &ClimbingConfig,
+    filter: QueryFilter,
+) -> Option<WallHit> {
+    let probe = Collider::ball(config.probe_radius);
+    let options = ShapeCastOptions::with_max_time_of_impact(config.detect_distance);
+    let (surface, _) = rapier_context.cast_shape(origin, Quat::IDENTITY, direction, &probe, options, filter)?;
+
+    // Shape cast tells us something is there, a ray gives a reliable world-space normal
+    let (_, hit) = rapier_context.cast_ray_and_get_normal(
+        origin,
+        direction,
+        config.detect_distance + config.probe_radius,
+        true,
+        filter,
+    )?;
+
+    Some(WallHit {
+        surface,
+        point: hit.point,
+        normal: hit.normal,
+    })
+}
+
+fn find_ledge_top(
+    rapier_context: &RapierContext,
+    wall: &WallHit,
+    chest: Vec3,
+    config: &ClimbingConfig,
+    filter: QueryFilter,
+) -> Option<Vec3> {
+    let over_wall = wall.point - wall.normal * (config.probe_radius + 0.1);
+    let origin = Vec3::new(over_wall.x, chest.y + config.ledge_reach, over_wall.z);
+    let (_, hit) = rapier_context.cast_ray_and_get_normal(origin, Vec3::NEG_Y, config.ledge_reach, true, filter)?;
+
+    // Must be a walkable top, not another stretch of wall
+    (hit.normal.y > 0.7 && hit.time_of_impact > 0.01).then_some(hit.point)
+}
+
+fn update_climbing(
+    time: Res<Time>,
+    physics: Res<PhysicsFabric>,
+    rapier_context: ReadRapierContext,
+    mut climbers: Query<(
+        Entity,
+        &mut CharacterController,
+        &mut ClimbingState,
+        &ClimbingConfig,
+        &mut Transform,
+    )>,
+    climbables: Query<&Climbable>,
+    parents: Query<&Parent>,
+    mut stamina_events: EventWriter<ClimbStaminaCost>,
+) {
+    if !physics.is_enabled() || physics.is_paused() {
+        return;
+    }
+
+    let Ok(rapier_context) = rapier_context.single() else {
+        return;
+    };
+
+    let dt = time.delta_secs() * physics.time_scale();
+
+    let climbable_of = |entity: Entity| -> Option<Climbable> {
+        climbables
+            .get(entity)
+            .ok()
+            .or_else(|| parents.get(entity).ok().and_then(|p| climbables.get(p.get()).ok()))
+            .copied()
+    };
+
+    for (entity, mut controller, mut state, config, mut transform) in climbers.iter_mut() {
+        let filter = QueryFilter::default().exclude_collider(entity).exclude_rigid_body(entity);
+        let chest = transform.translation + Vec3::Y * config.chest_height;
+
+        // Jumping off clears `is_climbing` on the controller; follow it
+        if matches!(state.mode, ClimbMode::Climbing { .. }) && !controller.is_climbing {
+            state.mode = ClimbMode::None;
+        }
+
+        match state.mode {
+            ClimbMode::None => {
+                if !state.wants_climb || !controller.is_enabled() {
+                    continue;
+                }
+                let input = controller.input_direction;
+                let direction = if input.length_squared() > 0.01 {
+                    input
+                } else {
+                    controller.look_direction
+                };
+
+                let Some(wall) = probe_wall(&rapier_context, chest, direction, config, filter) else {
+                    continue;
+                };
+                if climbable_of(wall.surface).is_none() {
+                    continue;
+                }
+                let tilt = wall.normal.y.abs().asin().to_degrees();
+                if tilt > config.max_wall_tilt || direction.dot(-wall.normal) < 0.5 {
+                    continue;
+                }
+
+                controller.start_climbing(wall.normal);
+                state.mode = ClimbMode::Climbing {
+                    surface: wall.surface,
+                };
+                log::debug!("Climbing: {:?} attached to {:?}", entity, wall.surface);
+            }
+
+            ClimbMode::Climbing { .. } => {
+                let into_wall = -controller.climb_normal;
+                let wall = probe_wall(&rapier_context, chest, into_wall, config, filter);
+                let climbable = wall.as_ref().and_then(|w| climbable_of(w.surface));
+
+                let (Some(wall), Some(climbable)) = (wall, climbable) else {
+                    // Lost the wall while moving up: look for something to hang from
+                    let last_hit = WallHit {
+                        surface: entity,
+                        point: chest + into_wall * config.detect_distance,
+                        normal: controller.climb_normal,
+                    };
+                    let ledge = (controller.velocity.y > 0.0)
+                        .then(|| find_ledge_top(&rapier_context, &last_hit, chest, config, filter))
+                        .flatten();
+
+                    if let Some(ledge_top) = ledge {
+                        state.mode = ClimbMode::Hanging {
+                            ledge_top,
+                            wall_normal: controller.climb_normal,
+                        };
+                        controller.velocity = Vec3::ZERO;
+                        stamina_events.send(ClimbStaminaCost {
+                            entity,
+                            action: ClimbAction::LedgeGrab,
+                            amount: config.ledge_grab_stamina,
+                        });
+                    } else {
+                        state.mode = ClimbMode::None;
+                        controller.stop_climbing();
+                    }
+                    continue;
+                };
+
+                // Reached the floor while climbing down
+                if controller.ground_info.is_grounded() && controller.velocity.y < 0.0 {
+                    state.mode = ClimbMode::None;
+                    controller.stop_climbing();
+                    continue;
+                }
+
+                if climbable.allow_ledge_grab && controller.velocity.y > 0.0 {
+                    if let Some(ledge_top) = find_ledge_top(&rapier_context, &wall, chest, config, filter) {
+                        if ledge_top.y - chest.y < config.ledge_reach * 0.5 {
+                            state.mode = ClimbMode::Hanging {
+                                ledge_top,
+                                wall_normal: wall.normal,
+                            };
+                            controller.velocity = Vec3::ZERO;
+                            stamina_events.send(ClimbStaminaCost {
+                                entity,
+                                action: ClimbAction::LedgeGrab,
+                                amount: config.ledge_grab_stamina * climbable.stamina_multiplier,
+                            });
+                            continue;
+                        }
+                    }
+                }
+
+                // Follow curved or angled walls
+                controller.climb_normal = Vec3::new(wall.normal.x, 0.0, wall.normal.z).normalize_or_zero();
+                state.mode = ClimbMode::Climbing {
+                    surface: wall.surface,
+                };
+
+                if controller.velocity.length_squared() > 0.01 {
+                    stamina_events.send(ClimbStaminaCost {
+                        entity,
+                        action: ClimbAction::Climb,
+                        amount: config.stamina_per_second * climbable.stamina_multiplier * dt,
+                    });
+                }
+            }
+
+            ClimbMode::Hanging { ledge_top, wall_normal } => {
+                controller.velocity = Vec3::ZERO;
+                controller.input_direction = Vec3::ZERO;
+
+                if state.wants_mantle {
+                    let to = ledge_top - wall_normal * config.probe_radius * 2.0
+                        + Vec3::Y * config.character_half_height;
+                    state.mode = ClimbMode::Mantling {
+                        from: transform.translation,
+                        to,
+                        elapsed: 0.0,
+                    };
+                    state.wants_mantle = false;
+                    controller.set_enabled(false);
+                    stamina_events.send(ClimbStaminaCost {
+                        entity,
+                        action: ClimbAction::Mantle,
+                        amount: config.mantle_stamina,
+                    });
+                } else if !state.wants_climb {
+                    state.mode = ClimbMode::None;
+                    controller.stop_climbing();
+                }
+            }
+
+            ClimbMode::Mantling { from, to, elapsed } => {
+                let elapsed = elapsed + dt;
+                let t = (elapsed / config.mantle_duration.max(0.01)).min(1.0);
+
+                // Up first, then over the lip
+                let vertical_t = (t * 2.0).min(1.0);
+                let horizontal_t = ((t - 0.3) / 0.7).clamp(0.0, 1.0);
+                transform.translation = Vec3::new(
+                    from.x + (to.x - from.x) * horizontal_t,
+                    from.y + (to.y - from.y) * vertical_t,
+                    from.z + (to.z - from.z) * horizontal_t,
+                );
+
+                if t >= 1.0 {
+                    state.mode = ClimbMode::None;
+                    controller.stop_climbing();
+                    controller.set_enabled(true);
+                    controller.teleport(to);
+                    log::debug!("Climbing: {:?} mantled onto ledge", entity);
+                } else {
+                    state.mode = ClimbMode::Mantling { from, to, elapsed };
+                }
+            }
+        }
+    }
+}
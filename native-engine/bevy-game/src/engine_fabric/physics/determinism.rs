@@ -0,0 +1,260 @@
+use bevy::ecs::schedule::ScheduleLabel;
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::collections::VecDeque;
+
+/// Schedule run a whole number of times per frame, once per simulation tick.
+/// Everything that must replay identically on server, client prediction and
+/// replays goes in here instead of `Update`.
+#[derive(ScheduleLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DeterministicStep;
+
+/// Explicit ordering inside [`DeterministicStep`]. Systems in the same set
+/// still need `.chain()` or explicit ordering between each other; the set
+/// order alone only pins down coarse phases.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum DeterministicSet {
+    Input,
+    Gameplay,
+    Checksum,
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct DeterministicClock {
+    pub tick: u64,
+    pub step_dt: f32,
+    pub accumulator: f32,
+    pub max_steps_per_frame: u32,
+    /// Fraction of a step left in the accumulator, for render interpolation.
+    pub alpha: f32,
+    pub dropped_time: f32,
+}
+
+impl DeterministicClock {
+    pub fn new(tick_rate: u32) -> Self {
+        Self {
+            tick: 0,
+            step_dt: 1.0 / tick_rate.max(1) as f32,
+            accumulator: 0.0,
+            max_steps_per_frame: 5,
+            alpha: 0.0,
+            dropped_time: 0.0,
+        }
+    }
+
+    /// Jump to a known tick (server snapshot, replay seek). Pending time is discarded.
+    pub fn reset_to_tick(&mut self, tick: u64) {
+        self.tick = tick;
+        self.accumulator = 0.0;
+        self.alpha = 0.0;
+    }
+}
+
+/// Seeded RNG for simulation code. Reseeded from `(seed, tick)` at the start
+/// of every step so a tick replayed in isolation draws the same numbers.
+#[derive(Resource)]
+pub struct SimulationRng {
+    pub seed: u64,
+    rng: StdRng,
+}
+
+impl SimulationRng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn reseed_for_tick(&mut self, tick: u64) {
+        // SplitMix64 finalizer so consecutive ticks get unrelated streams
+        let mut z = self.seed ^ tick.wrapping_mul(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        self.rng = StdRng::seed_from_u64(z);
+    }
+
+    pub fn rng(&mut self) -> &mut StdRng {
+        &mut self.rng
+    }
+}
+
+/// Stable identity for bodies included in the state checksum. Entity ids are
+/// allocation-order dependent, so they can't be used across processes.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DeterministicId(pub u64);
+
+#[derive(Resource, Debug, Default)]
+pub struct PhysicsChecksums {
+    pub interval: u64,
+    pub history: VecDeque<(u64, u64)>,
+    pub history_limit: usize,
+    pub mismatches: u64,
+}
+
+impl PhysicsChecksums {
+    pub fn get(&self, tick: u64) -> Option<u64> {
+        self.history.iter().find(|(t, _)| *t == tick).map(|(_, c)| *c)
+    }
+
+    /// Compare against a checksum computed elsewhere (server, recorded replay).
+    /// Returns `None` when the tick has already left the history window.
+    pub fn verify(&mut self, tick: u64, remote: u64) -> Option<bool> {
+        let local = self.get(tick)?;
+        let matches = local == remote;
+        if !matches {
+            self.mismatches += 1;
+            log::warn!(
+                "Deterministic: checksum mismatch at tick {} (local {:016x}, remote {:016x})",
+                tick,
+                local,
+                remote
+            );
+        }
+        Some(matches)
+    }
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct PhysicsChecksumEvent {
+    pub tick: u64,
+    pub checksum: u64,
+}
+
+pub struct DeterministicPhysicsPlugin {
+    pub tick_rate: u32,
+    pub seed: u64,
+    pub checksum_interval: u64,
+}
+
+impl Default for DeterministicPhysicsPlugin {
+    fn default() -> Self {
+        Self {
+            tick_rate: 60,
+            seed: 0,
+            checksum_interval: 30,
+        }
+    }
+}
+
+impl Plugin for DeterministicPhysicsPlugin {
+    fn build(&self, app: &mut App) {
+        let mut schedule = Schedule::new(DeterministicStep);
+        // Single-threaded executor: parallel system order is not stable run to run
+        schedule.set_executor_kind(bevy::ecs::schedule::ExecutorKind::SingleThreaded);
+        app.add_schedule(schedule);
+
+        app.insert_resource(DeterministicClock::new(self.tick_rate))
+            .insert_resource(SimulationRng::new(self.seed))
+            .insert_resource(PhysicsChecksums {
+                interval: self.checksum_interval.max(1),
+                history_limit: 256,
+                ..Default::default()
+            })
+            .add_event::<PhysicsChecksumEvent>()
+            .configure_sets(
+                DeterministicStep,
+                (
+                    DeterministicSet::Input,
+                    DeterministicSet::Gameplay.before(PhysicsSet::SyncBackend),
+                    DeterministicSet::Checksum.after(PhysicsSet::Writeback),
+                )
+                    .chain(),
+            )
+            .add_systems(
+                DeterministicStep,
+                record_physics_checksum.in_set(DeterministicSet::Checksum),
+            )
+            .add_systems(PreUpdate, run_deterministic_steps);
+
+        log::info!(
+            "Deterministic physics enabled: {} Hz, seed {}",
+            self.tick_rate,
+            self.seed
+        );
+    }
+}
+
+fn run_deterministic_steps(world: &mut World) {
+    let delta = world.resource::<Time>().delta_secs();
+
+    let (steps, step_dt) = {
+        let mut clock = world.resource_mut::<DeterministicClock>();
+        clock.accumulator += delta;
+        let mut steps = (clock.accumulator / clock.step_dt) as u32;
+        if steps > clock.max_steps_per_frame {
+            // Spiral-of-death guard: drop the backlog instead of stalling the frame
+            let dropped = (steps - clock.max_steps_per_frame) as f32 * clock.step_dt;
+            clock.accumulator -= dropped;
+            clock.dropped_time += dropped;
+            steps = clock.max_steps_per_frame;
+        }
+        (steps, clock.step_dt)
+    };
+
+    for _ in 0..steps {
+        let tick = world.resource::<DeterministicClock>().tick;
+        world.resource_mut::<SimulationRng>().reseed_for_tick(tick);
+
+        world.run_schedule(DeterministicStep);
+
+        let mut clock = world.resource_mut::<DeterministicClock>();
+        clock.accumulator -= step_dt;
+        clock.tick += 1;
+    }
+
+    let mut clock = world.resource_mut::<DeterministicClock>();
+    clock.alpha = (clock.accumulator / clock.step_dt).clamp(0.0, 1.0);
+}
+
+fn fnv1a(hash: &mut u64, bytes: &[u8]) {
+    for byte in bytes {
+        *hash ^= *byte as u64;
+        *hash = hash.wrapping_mul(0x0000_0100_0000_01B3);
+    }
+}
+
+fn record_physics_checksum(
+    clock: Res<DeterministicClock>,
+    mut checksums: ResMut<PhysicsChecksums>,
+    bodies: Query<(&DeterministicId, &Transform, Option<&Velocity>)>,
+    mut events: EventWriter<PhysicsChecksumEvent>,
+) {
+    if clock.tick % checksums.interval != 0 {
+        return;
+    }
+
+    let mut sorted: Vec<_> = bodies.iter().collect();
+    sorted.sort_unstable_by_key(|(id, _, _)| **id);
+
+    let mut hash = 0xCBF2_9CE4_8422_2325_u64;
+    for (id, transform, velocity) in sorted {
+        fnv1a(&mut hash, &id.0.to_le_bytes());
+        for value in transform
+            .translation
+            .to_array()
+            .into_iter()
+            .chain(transform.rotation.to_array())
+        {
+            fnv1a(&mut hash, &value.to_bits().to_le_bytes());
+        }
+        if let Some(velocity) = velocity {
+            for value in velocity.linvel.to_array().into_iter().chain(velocity.angvel.to_array()) {
+                fnv1a(&mut hash, &value.to_bits().to_le_bytes());
+            }
+        }
+    }
+
+    checksums.history.push_back((clock.tick, hash));
+    while checksums.history.len() > checksums.history_limit {
+        checksums.history.pop_front();
+    }
+
+    events.send(PhysicsChecksumEvent {
+        tick: clock.tick,
+        checksum: hash,
+    });
+}
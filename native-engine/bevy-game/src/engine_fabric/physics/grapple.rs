@@ -0,0 +1,374 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use super::{update_character_controllers, CharacterController, PhysicsFabric};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GrappleMode {
+    /// Anchor to the world and swing from the rope.
+    Swing,
+    /// Hook a rigidbody and reel it in toward the caster.
+    Pull,
+}
+
+#[derive(Component, Debug, Clone, Copy)]
+pub struct GrappleConfig {
+    pub projectile_speed: f32,
+    pub max_range: f32,
+    pub min_rope_length: f32,
+    pub reel_speed: f32,
+    pub swing_damping: f32,
+    pub pull_reel_speed: f32,
+    pub pull_impulse: f32,
+    pub pull_stop_distance: f32,
+    pub pull_max_duration: f32,
+}
+
+impl Default for GrappleConfig {
+    fn default() -> Self {
+        Self {
+            projectile_speed: 60.0,
+            max_range: 35.0,
+            min_rope_length: 1.5,
+            reel_speed: 8.0,
+            swing_damping: 0.02,
+            pull_reel_speed: 18.0,
+            pull_impulse: 400.0,
+            pull_stop_distance: 2.0,
+            pull_max_duration: 1.5,
+        }
+    }
+}
+
+/// Marks entities the pull ability may yank. Bosses and anything anchored
+/// in place simply don't get this component.
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct Pullable;
+
+#[derive(Component, Debug, Clone, Copy)]
+pub struct GrappleProjectile {
+    pub owner: Entity,
+    pub mode: GrappleMode,
+    pub velocity: Vec3,
+    pub traveled: f32,
+    pub max_range: f32,
+}
+
+/// Active swing rope on a character. Anchored to `anchor_entity` when the hook
+/// lands on something that can move, otherwise to a fixed world point.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct GrappleRope {
+    pub anchor_entity: Option<Entity>,
+    pub local_anchor: Vec3,
+    pub length: f32,
+    /// -1 reels in, +1 pays out. Set by input code.
+    pub reel_input: f32,
+}
+
+/// Active pull on a target rigidbody. Lives on the target alongside the rope
+/// `ImpulseJoint` linking it to the caster.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct GrapplePull {
+    pub caster: Entity,
+    pub elapsed: f32,
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct FireGrappleEvent {
+    pub caster: Entity,
+    pub direction: Vec3,
+    pub mode: GrappleMode,
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct ReleaseGrappleEvent {
+    pub caster: Entity,
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct GrappleAttachedEvent {
+    pub caster: Entity,
+    pub target: Option<Entity>,
+    pub point: Vec3,
+    pub mode: GrappleMode,
+}
+
+pub struct GrapplePlugin;
+
+impl Plugin for GrapplePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<FireGrappleEvent>()
+            .add_event::<ReleaseGrappleEvent>()
+            .add_event::<GrappleAttachedEvent>()
+            .add_systems(
+                Update,
+                (
+                    fire_grapples,
+                    release_grapples,
+                    update_grapple_projectiles,
+                    update_pulls,
+                )
+                    .chain(),
+            )
+            .add_systems(PostUpdate, apply_swing_constraint.before(update_character_controllers));
+    }
+}
+
+fn fire_grapples(
+    mut commands: Commands,
+    mut events: EventReader<FireGrappleEvent>,
+    casters: Query<(&Transform, Option<&GrappleConfig>), Without<GrappleRope>>,
+    projectiles: Query<&GrappleProjectile>,
+) {
+    for event in events.read() {
+        let Ok((transform, config)) = casters.get(event.caster) else {
+            continue;
+        };
+        if projectiles.iter().any(|p| p.owner == event.caster) {
+            continue;
+        }
+        let config = config.copied().unwrap_or_default();
+        let direction = event.direction.normalize_or_zero();
+        if direction == Vec3::ZERO {
+            continue;
+        }
+
+        commands.spawn((
+            GrappleProjectile {
+                owner: event.caster,
+                mode: event.mode,
+                velocity: direction * config.projectile_speed,
+                traveled: 0.0,
+                max_range: config.max_range,
+            },
+            Transform::from_translation(transform.translation + Vec3::Y * 0.5),
+            GlobalTransform::default(),
+        ));
+    }
+}
+
+fn release_grapples(
+    mut commands: Commands,
+    mut events: EventReader<ReleaseGrappleEvent>,
+    ropes: Query<(), With<GrappleRope>>,
+    pulls: Query<(Entity, &GrapplePull)>,
+    projectiles: Query<(Entity, &GrappleProjectile)>,
+) {
+    for event in events.read() {
+        if ropes.contains(event.caster) {
+            commands.entity(event.caster).remove::<GrappleRope>();
+        }
+        for (entity, pull) in pulls.iter() {
+            if pull.caster == event.caster {
+                commands.entity(entity).remove::<(GrapplePull, ImpulseJoint)>();
+            }
+        }
+        for (entity, projectile) in projectiles.iter() {
+            if projectile.owner == event.caster {
+                commands.entity(entity).despawn();
+            }
+        }
+    }
+}
+
+fn update_grapple_projectiles(
+    mut commands: Commands,
+    time: Res<Time>,
+    physics: Res<PhysicsFabric>,
+    rapier_context: ReadRapierContext,
+    mut projectiles: Query<(Entity, &mut GrappleProjectile, &mut Transform)>,
+    casters: Query<(&Transform, Option<&GrappleConfig>), Without<GrappleProjectile>>,
+    targets: Query<(&Transform, Option<&RigidBody>, Has<Pullable>), Without<GrappleProjectile>>,
+    mut attached_events: EventWriter<GrappleAttachedEvent>,
+) {
+    if !physics.is_enabled() || physics.is_paused() {
+        return;
+    }
+
+    let Ok(rapier_context) = rapier_context.single() else {
+        return;
+    };
+
+    let dt = time.delta_secs() * physics.time_scale();
+
+    for (entity, mut projectile, mut transform) in projectiles.iter_mut() {
+        let Ok((caster_transform, config)) = casters.get(projectile.owner) else {
+            commands.entity(entity).despawn();
+            continue;
+        };
+        let config = config.copied().unwrap_or_default();
+
+        let step = projectile.velocity * dt;
+        let step_length = step.length();
+        let filter = QueryFilter::default()
+            .exclude_collider(projectile.owner)
+            .exclude_rigid_body(projectile.owner);
+
+        let hit = rapier_context.cast_ray(transform.translation, step / step_length.max(1e-5), step_length, true, filter);
+
+        let Some((target, toi)) = hit else {
+            transform.translation += step;
+            projectile.traveled += step_length;
+            if projectile.traveled >= projectile.max_range {
+                commands.entity(entity).despawn();
+            }
+            continue;
+        };
+
+        let point = transform.translation + step.normalize_or_zero() * toi;
+        commands.entity(entity).despawn();
+
+        let (target_transform, body, pullable) = match targets.get(target) {
+            Ok(found) => found,
+            Err(_) => continue,
+        };
+        let is_dynamic = matches!(body, Some(RigidBody::Dynamic));
+
+        match projectile.mode {
+            GrappleMode::Pull if is_dynamic && pullable => {
+                let distance = caster_transform.translation.distance(target_transform.translation);
+                let to_caster = (caster_transform.translation - target_transform.translation).normalize_or_zero();
+
+                let rope = RopeJointBuilder::new(distance).local_anchor2(Vec3::ZERO);
+                commands.entity(target).insert((
+                    ImpulseJoint::new(projectile.owner, rope),
+                    GrapplePull {
+                        caster: projectile.owner,
+                        elapsed: 0.0,
+                    },
+                    ExternalImpulse {
+                        impulse: (to_caster + Vec3::Y * 0.3) * config.pull_impulse,
+                        torque_impulse: Vec3::ZERO,
+                    },
+                ));
+                attached_events.send(GrappleAttachedEvent {
+                    caster: projectile.owner,
+                    target: Some(target),
+                    point,
+                    mode: GrappleMode::Pull,
+                });
+            }
+            GrappleMode::Pull => {
+                // Nothing to pull; the hook just bounces off
+            }
+            GrappleMode::Swing => {
+                // Hooks on moving bodies follow them; static geometry stores the world point
+                let (anchor_entity, local_anchor) = if body.is_some_and(|b| *b != RigidBody::Fixed) {
+                    (
+                        Some(target),
+                        target_transform.compute_affine().inverse().transform_point3(point),
+                    )
+                } else {
+                    (None, point)
+                };
+                let length = caster_transform
+                    .translation
+                    .distance(point)
+                    .max(config.min_rope_length);
+
+                commands.entity(projectile.owner).insert(GrappleRope {
+                    anchor_entity,
+                    local_anchor,
+                    length,
+                    reel_input: 0.0,
+                });
+                attached_events.send(GrappleAttachedEvent {
+                    caster: projectile.owner,
+                    target: anchor_entity,
+                    point,
+                    mode: GrappleMode::Swing,
+                });
+            }
+        }
+    }
+}
+
+fn update_pulls(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut pulls: Query<(Entity, &mut GrapplePull, &mut ImpulseJoint, &Transform)>,
+    casters: Query<(&Transform, Option<&GrappleConfig>), Without<GrapplePull>>,
+) {
+    let dt = time.delta_secs();
+
+    for (entity, mut pull, mut joint, transform) in pulls.iter_mut() {
+        let Ok((caster_transform, config)) = casters.get(pull.caster) else {
+            commands.entity(entity).remove::<(GrapplePull, ImpulseJoint)>();
+            continue;
+        };
+        let config = config.copied().unwrap_or_default();
+
+        pull.elapsed += dt;
+        let distance = caster_transform.translation.distance(transform.translation);
+
+        if distance <= config.pull_stop_distance || pull.elapsed >= config.pull_max_duration {
+            commands.entity(entity).remove::<(GrapplePull, ImpulseJoint)>();
+            log::debug!("Grapple: pull on {:?} finished at {:.1}m", entity, distance);
+            continue;
+        }
+
+        // Reel the rope in; the joint drags the target after it
+        if let TypedJoint::RopeJoint(rope) = &mut joint.data {
+            let length = (rope.max_distance() - config.pull_reel_speed * dt).max(config.pull_stop_distance);
+            rope.set_max_distance(length.min(distance));
+        }
+    }
+}
+
+/// The player is a kinematic character, so rapier joints can't act on it.
+/// The rope is enforced here instead by stripping outward radial velocity and
+/// pulling the character back onto the rope sphere.
+fn apply_swing_constraint(
+    time: Res<Time>,
+    physics: Res<PhysicsFabric>,
+    mut swingers: Query<(
+        &mut CharacterController,
+        &mut GrappleRope,
+        &Transform,
+        Option<&GrappleConfig>,
+    )>,
+    anchors: Query<&Transform, Without<GrappleRope>>,
+) {
+    if !physics.is_enabled() || physics.is_paused() {
+        return;
+    }
+
+    let dt = time.delta_secs() * physics.time_scale();
+    if dt <= 0.0 {
+        return;
+    }
+
+    for (mut controller, mut rope, transform, config) in swingers.iter_mut() {
+        let config = config.copied().unwrap_or_default();
+
+        let anchor = match rope.anchor_entity {
+            Some(entity) => match anchors.get(entity) {
+                Ok(anchor_transform) => anchor_transform.transform_point(rope.local_anchor),
+                Err(_) => continue,
+            },
+            None => rope.local_anchor,
+        };
+
+        if rope.reel_input != 0.0 {
+            rope.length = (rope.length + rope.reel_input * config.reel_speed * dt)
+                .clamp(config.min_rope_length, config.max_range);
+        }
+
+        let offset = transform.translation - anchor;
+        let distance = offset.length();
+        if distance <= rope.length || distance < 1e-4 {
+            continue;
+        }
+
+        let radial = offset / distance;
+        let outward_speed = controller.velocity.dot(radial);
+        if outward_speed > 0.0 {
+            controller.velocity -= radial * outward_speed;
+        }
+
+        // Positional correction back onto the rope, spread over a frame
+        let overshoot = distance - rope.length;
+        controller.velocity -= radial * (overshoot * 0.5 / dt).min(30.0);
+        controller.velocity *= 1.0 - config.swing_damping;
+    }
+}
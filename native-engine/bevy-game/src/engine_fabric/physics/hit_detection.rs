@@ -0,0 +1,402 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use super::PhysicsFabric;
+
+/// Surface type reported with hits so effects can pick sparks, blood, splinters.
+/// Also drives footstep foley.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SurfaceMaterial {
+    #[default]
+    Stone,
+    Flesh,
+    Metal,
+    Wood,
+    Dirt,
+    Water,
+    Grass,
+    Sand,
+    Snow,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HitLocation {
+    Head,
+    Body,
+    Limb,
+}
+
+impl HitLocation {
+    pub fn damage_multiplier(self) -> f32 {
+        match self {
+            HitLocation::Head => 1.5,
+            HitLocation::Body => 1.0,
+            HitLocation::Limb => 0.75,
+        }
+    }
+}
+
+/// Put on child colliders of a character to get exact hit locations. Without
+/// zones the location is estimated from the hit height.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct HitZone {
+    pub location: HitLocation,
+}
+
+/// Marks the top-level entity that owns hit zones and receives damage.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Hurtbox {
+    pub height: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HitSource {
+    Melee,
+    Projectile,
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct PhysicsHitEvent {
+    pub attacker: Entity,
+    /// Entity owning the hurtbox when one was found, otherwise the collider hit.
+    pub target: Entity,
+    pub collider: Entity,
+    pub point: Vec3,
+    pub normal: Vec3,
+    pub material: SurfaceMaterial,
+    pub location: HitLocation,
+    pub source: HitSource,
+    pub base_damage: f32,
+}
+
+impl PhysicsHitEvent {
+    pub fn damage(&self) -> f32 {
+        self.base_damage * self.location.damage_multiplier()
+    }
+}
+
+/// Weapon arc swept in the attacker's local space. Yaw 0 is straight ahead,
+/// positive yaw sweeps to the attacker's right.
+#[derive(Component, Debug, Clone)]
+pub struct MeleeSwing {
+    pub start_yaw: f32,
+    pub end_yaw: f32,
+    pub pitch: f32,
+    pub pivot: Vec3,
+    pub inner_radius: f32,
+    pub reach: f32,
+    pub blade_radius: f32,
+    pub duration: f32,
+    pub elapsed: f32,
+    pub base_damage: f32,
+    pub hit_entities: HashSet<Entity>,
+    pub max_targets: usize,
+}
+
+impl MeleeSwing {
+    pub fn horizontal(arc_degrees: f32, reach: f32, duration: f32, base_damage: f32) -> Self {
+        Self {
+            start_yaw: -arc_degrees * 0.5,
+            end_yaw: arc_degrees * 0.5,
+            pitch: 0.0,
+            pivot: Vec3::new(0.0, 1.2, 0.0),
+            inner_radius: 0.3,
+            reach,
+            blade_radius: 0.08,
+            duration,
+            elapsed: 0.0,
+            base_damage,
+            hit_entities: HashSet::new(),
+            max_targets: 3,
+        }
+    }
+
+    /// Blade segment (hilt, tip) in world space at normalized time `t`.
+    fn blade_at(&self, t: f32, attacker: &Transform) -> (Vec3, Vec3) {
+        let yaw = (self.start_yaw + (self.end_yaw - self.start_yaw) * t).to_radians();
+        let local_dir = Quat::from_euler(EulerRot::YXZ, -yaw, self.pitch.to_radians(), 0.0) * Vec3::NEG_Z;
+        let pivot = attacker.transform_point(self.pivot);
+        let dir = attacker.rotation * local_dir;
+        (pivot + dir * self.inner_radius, pivot + dir * self.reach)
+    }
+}
+
+#[derive(Component, Debug, Clone, Copy)]
+pub struct PhysicsProjectile {
+    pub owner: Entity,
+    pub velocity: Vec3,
+    pub gravity_scale: f32,
+    /// 0 uses a plain ray; larger values sweep a sphere.
+    pub radius: f32,
+    pub max_distance: f32,
+    pub traveled: f32,
+    pub base_damage: f32,
+}
+
+pub struct HitDetectionPlugin;
+
+impl Plugin for HitDetectionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<PhysicsHitEvent>()
+            .add_systems(Update, (sweep_melee_swings, sweep_projectiles));
+    }
+}
+
+/// Sub-steps per frame for melee arcs, so fast swings don't skip thin targets.
+const MELEE_SUBSTEPS: u32 = 4;
+
+struct ResolvedHit {
+    target: Entity,
+    material: SurfaceMaterial,
+    location: HitLocation,
+}
+
+fn resolve_hit(
+    collider: Entity,
+    point: Vec3,
+    zones: &Query<&HitZone>,
+    hurtboxes: &Query<(&Hurtbox, &GlobalTransform)>,
+    materials: &Query<&SurfaceMaterial>,
+    parents: &Query<&Parent>,
+) -> ResolvedHit {
+    let zone = zones.get(collider).ok().map(|z| z.location);
+
+    // Walk up to the hurtbox owner: zone colliders are usually children of the rig
+    let mut owner = collider;
+    let mut current = collider;
+    for _ in 0..8 {
+        if hurtboxes.contains(current) {
+            owner = current;
+            break;
+        }
+        match parents.get(current) {
+            Ok(parent) => current = parent.get(),
+            Err(_) => break,
+        }
+    }
+
+    let location = zone.unwrap_or_else(|| match hurtboxes.get(owner) {
+        Ok((hurtbox, transform)) => {
+            let relative = (point.y - transform.translation().y) / hurtbox.height.max(0.01);
+            if relative > 0.85 {
+                HitLocation::Head
+            } else if relative < 0.35 {
+                HitLocation::Limb
+            } else {
+                HitLocation::Body
+            }
+        }
+        Err(_) => HitLocation::Body,
+    });
+
+    let material = materials
+        .get(collider)
+        .or_else(|_| materials.get(owner))
+        .copied()
+        .unwrap_or_else(|_| {
+            if hurtboxes.contains(owner) {
+                SurfaceMaterial::Flesh
+            } else {
+                SurfaceMaterial::default()
+            }
+        });
+
+    ResolvedHit {
+        target: owner,
+        material,
+        location,
+    }
+}
+
+fn sweep_melee_swings(
+    mut commands: Commands,
+    time: Res<Time>,
+    physics: Res<PhysicsFabric>,
+    rapier_context: ReadRapierContext,
+    mut swings: Query<(Entity, &mut MeleeSwing, &Transform)>,
+    zones: Query<&HitZone>,
+    hurtboxes: Query<(&Hurtbox, &GlobalTransform)>,
+    materials: Query<&SurfaceMaterial>,
+    parents: Query<&Parent>,
+    mut hits: EventWriter<PhysicsHitEvent>,
+) {
+    if !physics.is_enabled() || physics.is_paused() {
+        return;
+    }
+
+    let Ok(rapier_context) = rapier_context.single() else {
+        return;
+    };
+
+    let dt = time.delta_secs() * physics.time_scale();
+
+    for (attacker, mut swing, transform) in swings.iter_mut() {
+        let t0 = (swing.elapsed / swing.duration.max(0.01)).min(1.0);
+        swing.elapsed += dt;
+        let t1 = (swing.elapsed / swing.duration.max(0.01)).min(1.0);
+
+        for step in 0..MELEE_SUBSTEPS {
+            if swing.hit_entities.len() >= swing.max_targets {
+                break;
+            }
+
+            let a = t0 + (t1 - t0) * step as f32 / MELEE_SUBSTEPS as f32;
+            let b = t0 + (t1 - t0) * (step + 1) as f32 / MELEE_SUBSTEPS as f32;
+            let (hilt_a, tip_a) = swing.blade_at(a, transform);
+            let (hilt_b, tip_b) = swing.blade_at(b, transform);
+
+            let center_a = (hilt_a + tip_a) * 0.5;
+            let center_b = (hilt_b + tip_b) * 0.5;
+            let blade_dir = (tip_a - hilt_a).normalize_or_zero();
+            let half_length = hilt_a.distance(tip_a) * 0.5;
+
+            let capsule = Collider::capsule_y(half_length, swing.blade_radius);
+            let rotation = Quat::from_rotation_arc(Vec3::Y, blade_dir);
+            let options = ShapeCastOptions {
+                max_time_of_impact: 1.0,
+                target_distance: 0.0,
+                stop_at_penetration: true,
+                compute_impact_geometry_on_penetration: true,
+            };
+
+            // Each cast reports one collider; repeat while excluding what we've hit
+            loop {
+                if swing.hit_entities.len() >= swing.max_targets {
+                    break;
+                }
+                let already_hit = swing.hit_entities.clone();
+                let predicate = |entity: Entity| entity != attacker && !already_hit.contains(&entity);
+                let filter = QueryFilter::default()
+                    .exclude_rigid_body(attacker)
+                    .exclude_sensors()
+                    .predicate(&predicate);
+
+                let Some((collider, hit)) =
+                    rapier_context.cast_shape(center_a, rotation, center_b - center_a, &capsule, options, filter)
+                else {
+                    break;
+                };
+
+                let (point, normal) = hit
+                    .details
+                    .map(|d| (d.witness1, d.normal1))
+                    .unwrap_or((center_a.lerp(center_b, hit.time_of_impact), -blade_dir));
+
+                let resolved = resolve_hit(collider, point, &zones, &hurtboxes, &materials, &parents);
+                swing.hit_entities.insert(collider);
+                if resolved.target != collider {
+                    swing.hit_entities.insert(resolved.target);
+                }
+
+                hits.send(PhysicsHitEvent {
+                    attacker,
+                    target: resolved.target,
+                    collider,
+                    point,
+                    normal,
+                    material: resolved.material,
+                    location: resolved.location,
+                    source: HitSource::Melee,
+                    base_damage: swing.base_damage,
+                });
+            }
+        }
+
+        if t1 >= 1.0 {
+            commands.entity(attacker).remove::<MeleeSwing>();
+        }
+    }
+}
+
+fn sweep_projectiles(
+    mut commands: Commands,
+    time: Res<Time>,
+    physics: Res<PhysicsFabric>,
+    rapier_context: ReadRapierContext,
+    mut projectiles: Query<(Entity, &mut PhysicsProjectile, &mut Transform)>,
+    zones: Query<&HitZone>,
+    hurtboxes: Query<(&Hurtbox, &GlobalTransform)>,
+    materials: Query<&SurfaceMaterial>,
+    parents: Query<&Parent>,
+    mut hits: EventWriter<PhysicsHitEvent>,
+) {
+    if !physics.is_enabled() || physics.is_paused() {
+        return;
+    }
+
+    let Ok(rapier_context) = rapier_context.single() else {
+        return;
+    };
+
+    let dt = time.delta_secs() * physics.time_scale();
+    let gravity = physics.settings.gravity;
+
+    for (entity, mut projectile, mut transform) in projectiles.iter_mut() {
+        let gravity_scale = projectile.gravity_scale;
+        projectile.velocity += gravity * gravity_scale * dt;
+        let motion = projectile.velocity * dt;
+        let distance = motion.length();
+        if distance <= f32::EPSILON {
+            continue;
+        }
+        let direction = motion / distance;
+
+        let owner = projectile.owner;
+        let predicate = |e: Entity| e != entity;
+        let filter = QueryFilter::default()
+            .exclude_rigid_body(owner)
+            .exclude_collider(owner)
+            .exclude_sensors()
+            .predicate(&predicate);
+
+        // Continuous test over the whole tick: nothing tunnels regardless of speed
+        let hit = if projectile.radius > 0.0 {
+            let ball = Collider::ball(projectile.radius);
+            let options = ShapeCastOptions {
+                max_time_of_impact: 1.0,
+                target_distance: 0.0,
+                stop_at_penetration: true,
+                compute_impact_geometry_on_penetration: true,
+            };
+            rapier_context
+                .cast_shape(transform.translation, Quat::IDENTITY, motion, &ball, options, filter)
+                .map(|(collider, hit)| {
+                    let fallback = transform.translation + motion * hit.time_of_impact;
+                    let (point, normal) = hit.details.map(|d| (d.witness1, d.normal1)).unwrap_or((fallback, -direction));
+                    (collider, point, normal)
+                })
+        } else {
+            rapier_context
+                .cast_ray_and_get_normal(transform.translation, direction, distance, true, filter)
+                .map(|(collider, hit)| (collider, hit.point, hit.normal))
+        };
+
+        let Some((collider, point, normal)) = hit else {
+            transform.translation += motion;
+            transform.look_to(direction, Vec3::Y);
+            projectile.traveled += distance;
+            if projectile.traveled >= projectile.max_distance {
+                commands.entity(entity).despawn_recursive();
+            }
+            continue;
+        };
+
+        let resolved = resolve_hit(collider, point, &zones, &hurtboxes, &materials, &parents);
+        hits.send(PhysicsHitEvent {
+            attacker: owner,
+            target: resolved.target,
+            collider,
+            point,
+            normal,
+            material: resolved.material,
+            location: resolved.location,
+            source: HitSource::Projectile,
+            base_damage: projectile.base_damage,
+        });
+
+        commands.entity(entity).despawn_recursive();
+    }
+}
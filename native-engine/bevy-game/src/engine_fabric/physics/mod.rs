@@ -1,14 +1,28 @@
 pub mod character;
+pub mod climbing;
 pub mod collision;
+pub mod determinism;
+pub mod grapple;
+pub mod hit_detection;
 pub mod joints;
+pub mod platform;
+pub mod pooling;
 pub mod queries;
 pub mod rigidbody;
+pub mod vehicle;
 
 pub use character::*;
+pub use climbing::*;
 pub use collision::*;
+pub use determinism::*;
+pub use grapple::*;
+pub use hit_detection::*;
 pub use joints::*;
+pub use platform::*;
+pub use pooling::*;
 pub use queries::*;
 pub use rigidbody::*;
+pub use vehicle::*;
 
 use bevy::prelude::*;
 use bevy_rapier3d::prelude::*;
@@ -315,14 +329,18 @@ pub struct PhysicsSettings {
     pub max_stabilization_iterations: usize,
     pub prediction_distance: f32,
     pub length_unit: f32,
+    pub simulation_seed: u64,
 }
 
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub enum TimestepMode {
     #[default]
     Fixed,
     Variable,
     Interpolated,
+    /// Rapier steps inside `DeterministicStep`, driven by an accumulator at a
+    /// fixed tick rate. Required for lag compensation and replays.
+    Deterministic,
 }
 
 impl Default for PhysicsSettings {
@@ -335,6 +353,7 @@ impl Default for PhysicsSettings {
             max_stabilization_iterations: 1,
             prediction_distance: 0.002,
             length_unit: 1.0,
+            simulation_seed: 0,
         }
     }
 }
@@ -349,6 +368,7 @@ impl PhysicsSettings {
             max_stabilization_iterations: 2,
             prediction_distance: 0.002,
             length_unit: 1.0,
+            simulation_seed: 0,
         }
     }
 }
@@ -735,7 +755,22 @@ impl Plugin for PhysicsPlugin {
             force_update_from_transform_changes: false,
         };
 
-        app.add_plugins(RapierPhysicsPlugin::<NoUserData>::default())
+        let deterministic = self.settings.timestep_mode == TimestepMode::Deterministic;
+        let rapier_plugin = if deterministic {
+            RapierPhysicsPlugin::<NoUserData>::default().in_schedule(DeterministicStep)
+        } else {
+            RapierPhysicsPlugin::<NoUserData>::default()
+        };
+
+        if deterministic {
+            app.add_plugins(DeterministicPhysicsPlugin {
+                tick_rate: 60,
+                seed: self.settings.simulation_seed,
+                ..Default::default()
+            });
+        }
+
+        app.add_plugins(rapier_plugin)
             .insert_resource(rapier_config)
             .insert_resource(PhysicsFabric::with_settings(self.settings))
             .add_event::<PhysicsEvent>()
@@ -748,7 +783,15 @@ impl Plugin for PhysicsPlugin {
                 )
                     .chain(),
             )
-            .add_systems(PostUpdate, update_character_controllers);
+            .add_systems(PostUpdate, update_character_controllers)
+            .add_plugins((
+                VehiclePlugin,
+                MovingPlatformPlugin,
+                ClimbingPlugin,
+                GrapplePlugin,
+                PhysicsPoolingPlugin,
+                HitDetectionPlugin,
+            ));
 
         log::info!(
             "PhysicsPlugin initialized with gravity {:?}",
@@ -0,0 +1,372 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{update_character_controllers, CharacterController, GroundState, PhysicsFabric};
+
+/// Upper bound on velocity handed to riders. A platform that snaps (network
+/// correction, editor move, teleport) must not fling everyone standing on it.
+const MAX_PLATFORM_CARRY_SPEED: f32 = 30.0;
+
+/// Platform displacement in a single frame beyond which we treat the move as a
+/// teleport and relocate riders instead of carrying them.
+const PLATFORM_SNAP_DISTANCE: f32 = 4.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PlatformPathMode {
+    Loop,
+    PingPong,
+    Once,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PlatformWaypoint {
+    pub position: Vec3,
+    pub wait_time: f32,
+}
+
+impl PlatformWaypoint {
+    pub fn new(position: Vec3, wait_time: f32) -> Self {
+        Self { position, wait_time }
+    }
+}
+
+#[derive(Component, Debug, Clone)]
+pub struct MovingPlatform {
+    pub waypoints: Vec<PlatformWaypoint>,
+    pub speed: f32,
+    pub mode: PlatformPathMode,
+    /// Constant angular velocity (rad/s), e.g. a turning ferry or carousel.
+    pub spin: Vec3,
+    pub active: bool,
+    pub network_id: Option<u64>,
+    /// Elevator call target. When set the platform travels to this waypoint and stops.
+    pub call_target: Option<usize>,
+    pub current: usize,
+    pub direction: i32,
+    pub wait_remaining: f32,
+    pub linear_velocity: Vec3,
+    pub angular_velocity: Vec3,
+    pub last_delta: Vec3,
+}
+
+impl MovingPlatform {
+    pub fn new(waypoints: Vec<PlatformWaypoint>, speed: f32, mode: PlatformPathMode) -> Self {
+        Self {
+            waypoints,
+            speed,
+            mode,
+            spin: Vec3::ZERO,
+            active: true,
+            network_id: None,
+            call_target: None,
+            current: 0,
+            direction: 1,
+            wait_remaining: 0.0,
+            linear_velocity: Vec3::ZERO,
+            angular_velocity: Vec3::ZERO,
+            last_delta: Vec3::ZERO,
+        }
+    }
+
+    /// Elevator that idles at its first floor until called.
+    pub fn elevator(floors: Vec<Vec3>, speed: f32) -> Self {
+        let waypoints = floors
+            .into_iter()
+            .map(|p| PlatformWaypoint::new(p, 2.0))
+            .collect();
+        let mut platform = Self::new(waypoints, speed, PlatformPathMode::Once);
+        platform.active = false;
+        platform
+    }
+
+    pub fn ferry(stops: Vec<Vec3>, speed: f32, dock_time: f32) -> Self {
+        let waypoints = stops
+            .into_iter()
+            .map(|p| PlatformWaypoint::new(p, dock_time))
+            .collect();
+        Self::new(waypoints, speed, PlatformPathMode::PingPong)
+    }
+
+    pub fn with_spin(mut self, spin: Vec3) -> Self {
+        self.spin = spin;
+        self
+    }
+
+    pub fn with_network_id(mut self, id: u64) -> Self {
+        self.network_id = Some(id);
+        self
+    }
+
+    /// Velocity of the platform surface at a world-space point.
+    pub fn velocity_at(&self, platform_origin: Vec3, point: Vec3) -> Vec3 {
+        self.linear_velocity + self.angular_velocity.cross(point - platform_origin)
+    }
+
+    fn next_index(&self) -> Option<usize> {
+        let count = self.waypoints.len();
+        if count < 2 {
+            return None;
+        }
+
+        if let Some(target) = self.call_target {
+            return match target.cmp(&self.current) {
+                std::cmp::Ordering::Greater => Some(self.current + 1),
+                std::cmp::Ordering::Less => Some(self.current - 1),
+                std::cmp::Ordering::Equal => None,
+            };
+        }
+
+        match self.mode {
+            PlatformPathMode::Loop => Some((self.current + 1) % count),
+            PlatformPathMode::PingPong => {
+                let next = self.current as i32 + self.direction;
+                if next < 0 || next >= count as i32 {
+                    Some((self.current as i32 - self.direction) as usize)
+                } else {
+                    Some(next as usize)
+                }
+            }
+            PlatformPathMode::Once => (self.current + 1 < count).then_some(self.current + 1),
+        }
+    }
+}
+
+/// Tracks which platform a character is standing on, in platform-local space.
+/// Network code replicates `local_offset` for players on platforms so remote
+/// peers don't see them jitter against the platform's own interpolation.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct PlatformAttachment {
+    pub platform: Entity,
+    pub local_offset: Vec3,
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct PlatformCallEvent {
+    pub platform: Entity,
+    pub waypoint: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlatformNetState {
+    pub network_id: u64,
+    pub position: [f32; 3],
+    pub rotation: [f32; 4],
+    pub current: usize,
+    pub direction: i32,
+    pub wait_remaining: f32,
+    pub call_target: Option<usize>,
+}
+
+/// Authoritative platform state received from the server.
+#[derive(Event, Debug, Clone)]
+pub struct PlatformStateUpdate {
+    pub state: PlatformNetState,
+}
+
+pub struct MovingPlatformPlugin;
+
+impl Plugin for MovingPlatformPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<PlatformCallEvent>()
+            .add_event::<PlatformStateUpdate>()
+            .add_systems(
+                Update,
+                (handle_platform_calls, apply_platform_state_updates, move_platforms).chain(),
+            )
+            .add_systems(
+                PostUpdate,
+                carry_characters_on_platforms.before(update_character_controllers),
+            );
+    }
+}
+
+fn handle_platform_calls(
+    mut events: EventReader<PlatformCallEvent>,
+    mut platforms: Query<&mut MovingPlatform>,
+) {
+    for event in events.read() {
+        let Ok(mut platform) = platforms.get_mut(event.platform) else {
+            continue;
+        };
+        if event.waypoint >= platform.waypoints.len() {
+            log::warn!(
+                "Platform {:?}: call to missing waypoint {}",
+                event.platform,
+                event.waypoint
+            );
+            continue;
+        }
+        platform.call_target = Some(event.waypoint);
+        platform.active = true;
+    }
+}
+
+fn apply_platform_state_updates(
+    mut events: EventReader<PlatformStateUpdate>,
+    mut platforms: Query<(&mut MovingPlatform, &mut Transform)>,
+) {
+    for event in events.read() {
+        let state = &event.state;
+        for (mut platform, mut transform) in platforms.iter_mut() {
+            if platform.network_id != Some(state.network_id) {
+                continue;
+            }
+            // Movement this frame is recorded in `last_delta` so the carry
+            // system can detect large corrections and relocate riders.
+            let target = Vec3::from_array(state.position);
+            platform.last_delta += target - transform.translation;
+            transform.translation = target;
+            transform.rotation = Quat::from_array(state.rotation);
+            platform.current = state.current.min(platform.waypoints.len().saturating_sub(1));
+            platform.direction = state.direction;
+            platform.wait_remaining = state.wait_remaining;
+            platform.call_target = state.call_target;
+        }
+    }
+}
+
+fn move_platforms(
+    time: Res<Time>,
+    physics: Res<PhysicsFabric>,
+    mut platforms: Query<(&mut MovingPlatform, &mut Transform)>,
+) {
+    if !physics.is_enabled() || physics.is_paused() {
+        return;
+    }
+
+    let dt = time.delta_secs() * physics.time_scale();
+    if dt <= 0.0 {
+        return;
+    }
+
+    for (mut platform, mut transform) in platforms.iter_mut() {
+        let start = transform.translation;
+
+        if platform.spin != Vec3::ZERO {
+            let spin = platform.spin;
+            transform.rotate(Quat::from_scaled_axis(spin * dt));
+        }
+        platform.angular_velocity = platform.spin;
+
+        if platform.active && !platform.waypoints.is_empty() {
+            if platform.wait_remaining > 0.0 {
+                platform.wait_remaining -= dt;
+            } else if let Some(next) = platform.next_index() {
+                let target = platform.waypoints[next].position;
+                let to_target = target - transform.translation;
+                let step = platform.speed * dt;
+
+                if to_target.length() <= step {
+                    transform.translation = target;
+                    if platform.mode == PlatformPathMode::PingPong && platform.call_target.is_none() {
+                        platform.direction = if next > platform.current { 1 } else { -1 };
+                    }
+                    platform.current = next;
+                    platform.wait_remaining = platform.waypoints[next].wait_time;
+
+                    if platform.call_target == Some(next) {
+                        platform.call_target = None;
+                        platform.active = false;
+                    } else if platform.mode == PlatformPathMode::Once
+                        && platform.call_target.is_none()
+                        && next + 1 >= platform.waypoints.len()
+                    {
+                        platform.active = false;
+                    }
+                } else {
+                    transform.translation += to_target.normalize() * step;
+                }
+            } else if platform.call_target.is_some() {
+                platform.call_target = None;
+                platform.active = false;
+            }
+        }
+
+        let delta = transform.translation - start;
+        platform.linear_velocity = delta / dt;
+        platform.last_delta += delta;
+    }
+}
+
+fn carry_characters_on_platforms(
+    mut commands: Commands,
+    mut platforms: Query<(&mut MovingPlatform, &Transform)>,
+    mut characters: Query<
+        (
+            Entity,
+            &mut CharacterController,
+            &mut Transform,
+            Option<&PlatformAttachment>,
+        ),
+        Without<MovingPlatform>,
+    >,
+    parents: Query<&Parent>,
+) {
+    for (entity, mut controller, mut transform, attachment) in characters.iter_mut() {
+        let ground = controller.ground_info.ground_entity;
+        // Platform colliders may live on child entities
+        let ground_platform = ground.map(|g| {
+            if platforms.contains(g) {
+                g
+            } else {
+                parents.get(g).map(|p| p.get()).unwrap_or(g)
+            }
+        });
+
+        match (controller.ground_info.state, ground_platform) {
+            (GroundState::Grounded | GroundState::Sliding, Some(ground)) if platforms.contains(ground) => {
+                let Ok((platform, platform_transform)) = platforms.get(ground) else {
+                    continue;
+                };
+
+                if platform.last_delta.length() > PLATFORM_SNAP_DISTANCE {
+                    // Platform was corrected or teleported: keep the rider at
+                    // the same spot on the deck instead of applying a huge velocity
+                    if let Some(attached) = attachment.filter(|a| a.platform == ground) {
+                        let position = platform_transform.transform_point(attached.local_offset);
+                        transform.translation = position;
+                        controller.teleport(position);
+                    }
+                    continue;
+                }
+
+                let velocity = platform
+                    .velocity_at(platform_transform.translation, transform.translation)
+                    .clamp_length_max(MAX_PLATFORM_CARRY_SPEED);
+                controller.platform_velocity = velocity;
+
+                let local_offset = platform_transform
+                    .compute_affine()
+                    .inverse()
+                    .transform_point3(transform.translation);
+                commands.entity(entity).insert(PlatformAttachment {
+                    platform: ground,
+                    local_offset,
+                });
+            }
+            (GroundState::Airborne, _) => {
+                // Keep horizontal momentum when jumping off a ferry, but never
+                // launch players upward from a rising elevator
+                controller.platform_velocity.y = controller.platform_velocity.y.min(0.0);
+                controller.platform_velocity *= 0.98;
+                if controller.platform_velocity.length_squared() < 0.0001 {
+                    controller.platform_velocity = Vec3::ZERO;
+                }
+                if attachment.is_some() {
+                    commands.entity(entity).remove::<PlatformAttachment>();
+                }
+            }
+            _ => {
+                controller.platform_velocity = Vec3::ZERO;
+                if attachment.is_some() {
+                    commands.entity(entity).remove::<PlatformAttachment>();
+                }
+            }
+        }
+    }
+
+    for (mut platform, _) in platforms.iter_mut() {
+        platform.last_delta = Vec3::ZERO;
+    }
+}
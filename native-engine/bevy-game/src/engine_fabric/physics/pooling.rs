@@ -0,0 +1,242 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PooledBodyKind {
+    LootDrop,
+    Debris,
+    Projectile,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PoolStats {
+    pub created: u64,
+    pub reused: u64,
+    pub released: u64,
+    pub parked: usize,
+}
+
+/// Free lists of parked physics bodies. Parked entities keep their rapier
+/// handles alive with the body and collider disabled, which is far cheaper
+/// than despawning and re-inserting thousands of bodies into the broad phase.
+#[derive(Resource, Debug)]
+pub struct PhysicsBodyPool {
+    free: HashMap<PooledBodyKind, Vec<Entity>>,
+    capacity: HashMap<PooledBodyKind, usize>,
+    stats: HashMap<PooledBodyKind, PoolStats>,
+}
+
+impl Default for PhysicsBodyPool {
+    fn default() -> Self {
+        let mut capacity = HashMap::new();
+        capacity.insert(PooledBodyKind::LootDrop, 512);
+        capacity.insert(PooledBodyKind::Debris, 2048);
+        capacity.insert(PooledBodyKind::Projectile, 256);
+
+        Self {
+            free: HashMap::new(),
+            capacity,
+            stats: HashMap::new(),
+        }
+    }
+}
+
+impl PhysicsBodyPool {
+    pub fn set_capacity(&mut self, kind: PooledBodyKind, capacity: usize) {
+        self.capacity.insert(kind, capacity);
+    }
+
+    pub fn stats(&self, kind: PooledBodyKind) -> PoolStats {
+        let mut stats = self.stats.get(&kind).copied().unwrap_or_default();
+        stats.parked = self.free.get(&kind).map_or(0, Vec::len);
+        stats
+    }
+
+    /// Spawn a dynamic body, reusing a parked entity of the same kind if one is available.
+    pub fn spawn(
+        &mut self,
+        commands: &mut Commands,
+        kind: PooledBodyKind,
+        collider: Collider,
+        transform: Transform,
+        linvel: Vec3,
+        settings: &DebrisSettings,
+    ) -> Entity {
+        let stats = self.stats.entry(kind).or_default();
+        let components = (
+            RigidBody::Dynamic,
+            collider,
+            Velocity::linear(linvel),
+            transform,
+            Visibility::Inherited,
+            settings.sleeping(),
+            PooledPhysicsBody { kind },
+            Debris::default(),
+        );
+
+        if let Some(entity) = self.free.get_mut(&kind).and_then(Vec::pop) {
+            stats.reused += 1;
+            commands
+                .entity(entity)
+                .remove::<(RigidBodyDisabled, ColliderDisabled, SettledDebris)>()
+                .insert(components);
+            entity
+        } else {
+            stats.created += 1;
+            commands.spawn(components).id()
+        }
+    }
+
+    /// Park a body for reuse. Past the per-kind capacity the entity is despawned instead.
+    pub fn release(&mut self, commands: &mut Commands, entity: Entity, kind: PooledBodyKind) {
+        self.stats.entry(kind).or_default().released += 1;
+
+        let capacity = self.capacity.get(&kind).copied().unwrap_or(0);
+        let free = self.free.entry(kind).or_default();
+        if free.len() >= capacity {
+            commands.entity(entity).despawn_recursive();
+            return;
+        }
+
+        commands.entity(entity).insert((
+            RigidBodyDisabled,
+            ColliderDisabled,
+            Visibility::Hidden,
+            Velocity::zero(),
+        ));
+        free.push(entity);
+    }
+}
+
+#[derive(Component, Debug, Clone, Copy)]
+pub struct PooledPhysicsBody {
+    pub kind: PooledBodyKind,
+}
+
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct Debris {
+    pub age: f32,
+    pub resting_time: f32,
+}
+
+/// Debris that came to rest and was converted to a fixed collider.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct SettledDebris;
+
+/// Entity whose position keeps nearby debris simulated (usually the local player).
+/// Without any anchors, active cameras are used instead.
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct PhysicsAnchor;
+
+#[derive(Resource, Debug, Clone)]
+pub struct DebrisSettings {
+    pub sleep_linear_threshold: f32,
+    pub sleep_angular_threshold: f32,
+    /// Seconds a body must stay below the thresholds before it's frozen.
+    pub settle_time: f32,
+    pub convert_settled_to_fixed: bool,
+    pub despawn_distance: f32,
+    pub max_lifetime: f32,
+}
+
+impl Default for DebrisSettings {
+    fn default() -> Self {
+        Self {
+            sleep_linear_threshold: 0.15,
+            sleep_angular_threshold: 0.2,
+            settle_time: 0.75,
+            convert_settled_to_fixed: true,
+            despawn_distance: 120.0,
+            max_lifetime: 300.0,
+        }
+    }
+}
+
+impl DebrisSettings {
+    /// Sleep thresholds well above rapier's defaults so piles go to sleep quickly.
+    pub fn sleeping(&self) -> Sleeping {
+        Sleeping {
+            normalized_linear_threshold: self.sleep_linear_threshold,
+            angular_threshold: self.sleep_angular_threshold,
+            sleeping: false,
+        }
+    }
+}
+
+pub struct PhysicsPoolingPlugin;
+
+impl Plugin for PhysicsPoolingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PhysicsBodyPool>()
+            .init_resource::<DebrisSettings>()
+            .add_systems(Update, (settle_debris, despawn_distant_debris).chain());
+    }
+}
+
+fn settle_debris(
+    mut commands: Commands,
+    time: Res<Time>,
+    settings: Res<DebrisSettings>,
+    mut debris: Query<
+        (Entity, &mut Debris, &Velocity, Option<&Sleeping>),
+        (Without<SettledDebris>, Without<RigidBodyDisabled>),
+    >,
+) {
+    let dt = time.delta_secs();
+
+    for (entity, mut state, velocity, sleeping) in debris.iter_mut() {
+        state.age += dt;
+
+        let resting = sleeping.is_some_and(|s| s.sleeping)
+            || (velocity.linvel.length() < settings.sleep_linear_threshold
+                && velocity.angvel.length() < settings.sleep_angular_threshold);
+
+        if !resting {
+            state.resting_time = 0.0;
+            continue;
+        }
+
+        state.resting_time += dt;
+        if settings.convert_settled_to_fixed && state.resting_time >= settings.settle_time {
+            // Fixed colliders cost nothing in the solver and never wake up
+            commands
+                .entity(entity)
+                .insert((RigidBody::Fixed, Velocity::zero(), SettledDebris));
+        }
+    }
+}
+
+fn despawn_distant_debris(
+    mut commands: Commands,
+    settings: Res<DebrisSettings>,
+    mut pool: ResMut<PhysicsBodyPool>,
+    anchors: Query<&GlobalTransform, With<PhysicsAnchor>>,
+    cameras: Query<(&GlobalTransform, &Camera), With<Camera3d>>,
+    debris: Query<(Entity, &GlobalTransform, &Debris, &PooledPhysicsBody), Without<RigidBodyDisabled>>,
+) {
+    let mut positions: Vec<Vec3> = anchors.iter().map(|t| t.translation()).collect();
+    if positions.is_empty() {
+        positions.extend(
+            cameras
+                .iter()
+                .filter(|(_, camera)| camera.is_active)
+                .map(|(t, _)| t.translation()),
+        );
+    }
+
+    let max_distance_sq = settings.despawn_distance * settings.despawn_distance;
+
+    for (entity, transform, state, pooled) in debris.iter() {
+        let position = transform.translation();
+        let too_far = !positions.is_empty()
+            && positions
+                .iter()
+                .all(|anchor| anchor.distance_squared(position) > max_distance_sq);
+        let expired = state.age > settings.max_lifetime;
+
+        if too_far || expired {
+            pool.release(&mut commands, entity, pooled.kind);
+        }
+    }
+}
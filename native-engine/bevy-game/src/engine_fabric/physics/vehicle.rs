@@ -0,0 +1,697 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{CharacterController, PhysicsFabric};
+
+#[derive(Debug, Clone, Copy)]
+pub struct WheelConfig {
+    pub local_anchor: Vec3,
+    pub radius: f32,
+    pub suspension_rest_length: f32,
+    pub suspension_stiffness: f32,
+    pub suspension_damping: f32,
+    pub max_suspension_force: f32,
+    pub is_driven: bool,
+    pub is_steered: bool,
+    pub is_braked: bool,
+}
+
+impl WheelConfig {
+    pub fn new(local_anchor: Vec3, radius: f32) -> Self {
+        Self {
+            local_anchor,
+            radius,
+            suspension_rest_length: 0.4,
+            suspension_stiffness: 35_000.0,
+            suspension_damping: 4_500.0,
+            max_suspension_force: 60_000.0,
+            is_driven: false,
+            is_steered: false,
+            is_braked: true,
+        }
+    }
+
+    pub fn driven(mut self) -> Self {
+        self.is_driven = true;
+        self
+    }
+
+    pub fn steered(mut self) -> Self {
+        self.is_steered = true;
+        self
+    }
+
+    pub fn with_suspension(mut self, rest_length: f32, stiffness: f32, damping: f32) -> Self {
+        self.suspension_rest_length = rest_length;
+        self.suspension_stiffness = stiffness;
+        self.suspension_damping = damping;
+        self
+    }
+}
+
+/// Piecewise-linear curve sampled by normalized speed (0 = standstill,
+/// 1 = max speed). Used for engine torque and brake force falloff.
+#[derive(Debug, Clone)]
+pub struct ForceCurve {
+    pub points: Vec<(f32, f32)>,
+}
+
+impl ForceCurve {
+    pub fn new(points: Vec<(f32, f32)>) -> Self {
+        Self { points }
+    }
+
+    pub fn flat(value: f32) -> Self {
+        Self::new(vec![(0.0, value), (1.0, value)])
+    }
+
+    pub fn sample(&self, t: f32) -> f32 {
+        let Some(first) = self.points.first() else {
+            return 0.0;
+        };
+        if t <= first.0 {
+            return first.1;
+        }
+        for window in self.points.windows(2) {
+            let (t0, v0) = window[0];
+            let (t1, v1) = window[1];
+            if t <= t1 {
+                let span = (t1 - t0).max(f32::EPSILON);
+                return v0 + (v1 - v0) * ((t - t0) / span);
+            }
+        }
+        self.points.last().map(|p| p.1).unwrap_or(0.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SeatRole {
+    Driver,
+    Passenger,
+    Gunner,
+}
+
+#[derive(Debug, Clone)]
+pub struct VehicleSeat {
+    pub role: SeatRole,
+    pub local_offset: Vec3,
+    pub exit_offset: Vec3,
+    pub occupant: Option<Entity>,
+}
+
+impl VehicleSeat {
+    pub fn new(role: SeatRole, local_offset: Vec3) -> Self {
+        Self {
+            role,
+            local_offset,
+            exit_offset: Vec3::new(2.0, 0.0, 0.0),
+            occupant: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct VehicleConfig {
+    pub wheels: Vec<WheelConfig>,
+    pub mass: f32,
+    pub max_speed: f32,
+    pub reverse_speed_ratio: f32,
+    pub max_steer_angle: f32,
+    /// Total drive force (N) at full throttle, sampled by normalized speed.
+    pub engine_curve: ForceCurve,
+    /// Total brake force (N), sampled by normalized speed.
+    pub brake_curve: ForceCurve,
+    /// Slope (degrees) beyond which drive force fades out and the vehicle
+    /// starts rolling back down.
+    pub max_climb_angle: f32,
+    pub lateral_grip: f32,
+    pub rolling_resistance: f32,
+    pub seats: Vec<VehicleSeat>,
+}
+
+impl VehicleConfig {
+    pub fn wagon() -> Self {
+        Self {
+            wheels: vec![
+                WheelConfig::new(Vec3::new(-0.9, -0.3, -1.4), 0.5).steered(),
+                WheelConfig::new(Vec3::new(0.9, -0.3, -1.4), 0.5).steered(),
+                WheelConfig::new(Vec3::new(-0.9, -0.3, 1.4), 0.55).driven(),
+                WheelConfig::new(Vec3::new(0.9, -0.3, 1.4), 0.55).driven(),
+            ],
+            mass: 900.0,
+            max_speed: 9.0,
+            reverse_speed_ratio: 0.3,
+            max_steer_angle: 30.0,
+            engine_curve: ForceCurve::new(vec![(0.0, 7_000.0), (0.6, 5_000.0), (1.0, 0.0)]),
+            brake_curve: ForceCurve::new(vec![(0.0, 6_000.0), (1.0, 9_000.0)]),
+            max_climb_angle: 28.0,
+            lateral_grip: 0.85,
+            rolling_resistance: 0.02,
+            seats: vec![
+                VehicleSeat::new(SeatRole::Driver, Vec3::new(0.0, 0.9, -1.6)),
+                VehicleSeat::new(SeatRole::Passenger, Vec3::new(-0.5, 0.9, 0.6)),
+                VehicleSeat::new(SeatRole::Passenger, Vec3::new(0.5, 0.9, 0.6)),
+            ],
+        }
+    }
+
+    pub fn siege_ram() -> Self {
+        Self {
+            wheels: vec![
+                WheelConfig::new(Vec3::new(-1.2, -0.6, -2.0), 0.7).steered().driven(),
+                WheelConfig::new(Vec3::new(1.2, -0.6, -2.0), 0.7).steered().driven(),
+                WheelConfig::new(Vec3::new(-1.2, -0.6, 2.0), 0.7).driven(),
+                WheelConfig::new(Vec3::new(1.2, -0.6, 2.0), 0.7).driven(),
+            ]
+            .into_iter()
+            .map(|w| w.with_suspension(0.3, 90_000.0, 12_000.0))
+            .collect(),
+            mass: 4_000.0,
+            max_speed: 3.5,
+            reverse_speed_ratio: 0.5,
+            max_steer_angle: 18.0,
+            engine_curve: ForceCurve::new(vec![(0.0, 30_000.0), (1.0, 0.0)]),
+            brake_curve: ForceCurve::flat(40_000.0),
+            max_climb_angle: 15.0,
+            lateral_grip: 0.95,
+            rolling_resistance: 0.05,
+            seats: vec![
+                VehicleSeat::new(SeatRole::Driver, Vec3::new(0.0, 0.5, 2.6)),
+                VehicleSeat::new(SeatRole::Gunner, Vec3::new(-0.8, 0.5, 0.0)),
+                VehicleSeat::new(SeatRole::Gunner, Vec3::new(0.8, 0.5, 0.0)),
+            ],
+        }
+    }
+
+    pub fn catapult() -> Self {
+        let mut config = Self::siege_ram();
+        config.mass = 2_500.0;
+        config.max_speed = 2.5;
+        config.seats = vec![
+            VehicleSeat::new(SeatRole::Driver, Vec3::new(0.0, 0.5, 2.4)),
+            VehicleSeat::new(SeatRole::Gunner, Vec3::new(0.0, 1.0, -1.0)),
+        ];
+        config
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct VehicleInput {
+    pub throttle: f32,
+    pub steer: f32,
+    pub brake: f32,
+    pub handbrake: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WheelState {
+    pub grounded: bool,
+    pub compression: f32,
+    pub contact_point: Vec3,
+    pub contact_normal: Vec3,
+    pub ground_entity: Option<Entity>,
+}
+
+#[derive(Component, Debug, Clone)]
+pub struct VehicleController {
+    pub config: VehicleConfig,
+    pub input: VehicleInput,
+    pub wheels: Vec<WheelState>,
+    pub forward_speed: f32,
+    pub steer_angle: f32,
+    pub slope_angle: f32,
+}
+
+impl VehicleController {
+    pub fn new(config: VehicleConfig) -> Self {
+        let wheels = vec![WheelState::default(); config.wheels.len()];
+        Self {
+            config,
+            input: VehicleInput::default(),
+            wheels,
+            forward_speed: 0.0,
+            steer_angle: 0.0,
+            slope_angle: 0.0,
+        }
+    }
+
+    pub fn grounded_wheels(&self) -> usize {
+        self.wheels.iter().filter(|w| w.grounded).count()
+    }
+
+    pub fn driver(&self) -> Option<Entity> {
+        self.config
+            .seats
+            .iter()
+            .find(|s| s.role == SeatRole::Driver)
+            .and_then(|s| s.occupant)
+    }
+
+    pub fn free_seat(&self, preferred: Option<usize>) -> Option<usize> {
+        if let Some(index) = preferred {
+            return self
+                .config
+                .seats
+                .get(index)
+                .filter(|s| s.occupant.is_none())
+                .map(|_| index);
+        }
+        self.config.seats.iter().position(|s| s.occupant.is_none())
+    }
+}
+
+/// Placed on a character while it occupies a vehicle seat.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct SeatedIn {
+    pub vehicle: Entity,
+    pub seat: usize,
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct EnterVehicleEvent {
+    pub vehicle: Entity,
+    pub rider: Entity,
+    pub seat: Option<usize>,
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct ExitVehicleEvent {
+    pub rider: Entity,
+}
+
+/// Replicated vehicle state. The owner (server or driving client) produces
+/// snapshots, other peers apply them to a kinematic proxy.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VehicleNetState {
+    pub tick: u64,
+    pub position: [f32; 3],
+    pub rotation: [f32; 4],
+    pub linear_velocity: [f32; 3],
+    pub angular_velocity: [f32; 3],
+    pub input: VehicleInput,
+    pub wheel_compression: Vec<f32>,
+    pub occupants: Vec<Option<u64>>,
+}
+
+#[derive(Component, Debug, Clone)]
+pub struct VehicleNetSync {
+    pub network_id: u64,
+    pub is_authority: bool,
+    pub send_interval: f32,
+    pub time_since_send: f32,
+    pub latest_remote: Option<VehicleNetState>,
+}
+
+impl VehicleNetSync {
+    pub fn authority(network_id: u64) -> Self {
+        Self {
+            network_id,
+            is_authority: true,
+            send_interval: 0.1,
+            time_since_send: 0.0,
+            latest_remote: None,
+        }
+    }
+
+    pub fn remote(network_id: u64) -> Self {
+        Self {
+            is_authority: false,
+            ..Self::authority(network_id)
+        }
+    }
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct VehicleStateSnapshot {
+    pub entity: Entity,
+    pub network_id: u64,
+    pub state: VehicleNetState,
+}
+
+#[derive(Bundle)]
+pub struct VehicleBundle {
+    pub controller: VehicleController,
+    pub rigidbody: RigidBody,
+    pub collider: Collider,
+    pub mass: AdditionalMassProperties,
+    pub velocity: Velocity,
+    pub external_force: ExternalForce,
+    pub damping: Damping,
+    pub collision_groups: CollisionGroups,
+    pub transform: Transform,
+    pub global_transform: GlobalTransform,
+}
+
+impl VehicleBundle {
+    pub fn new(config: VehicleConfig, chassis_half_extents: Vec3, position: Vec3) -> Self {
+        let mass = config.mass;
+        Self {
+            controller: VehicleController::new(config),
+            rigidbody: RigidBody::Dynamic,
+            collider: Collider::cuboid(
+                chassis_half_extents.x,
+                chassis_half_extents.y,
+                chassis_half_extents.z,
+            ),
+            mass: AdditionalMassProperties::Mass(mass),
+            velocity: Velocity::default(),
+            external_force: ExternalForce::default(),
+            damping: Damping {
+                linear_damping: 0.1,
+                angular_damping: 0.8,
+            },
+            collision_groups: CollisionGroups::default(),
+            transform: Transform::from_translation(position),
+            global_transform: GlobalTransform::default(),
+        }
+    }
+}
+
+pub struct VehiclePlugin;
+
+impl Plugin for VehiclePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<EnterVehicleEvent>()
+            .add_event::<ExitVehicleEvent>()
+            .add_event::<VehicleStateSnapshot>()
+            .add_systems(
+                Update,
+                (
+                    handle_vehicle_seating,
+                    update_vehicle_physics,
+                    sync_seated_riders,
+                    produce_vehicle_snapshots,
+                    apply_remote_vehicle_state,
+                )
+                    .chain(),
+            );
+    }
+}
+
+fn handle_vehicle_seating(
+    mut commands: Commands,
+    mut enter_events: EventReader<EnterVehicleEvent>,
+    mut exit_events: EventReader<ExitVehicleEvent>,
+    mut vehicles: Query<(&mut VehicleController, &Transform)>,
+    mut riders: Query<(Option<&SeatedIn>, Option<&mut CharacterController>, &mut Transform), Without<VehicleController>>,
+) {
+    for event in exit_events.read() {
+        let Ok((Some(seated), controller, mut rider_transform)) = riders.get_mut(event.rider) else {
+            continue;
+        };
+        let seated = *seated;
+        if let Ok((mut vehicle, vehicle_transform)) = vehicles.get_mut(seated.vehicle) {
+            if let Some(seat) = vehicle.config.seats.get_mut(seated.seat) {
+                seat.occupant = None;
+                rider_transform.translation = vehicle_transform.transform_point(seat.local_offset + seat.exit_offset);
+            }
+            if vehicle.driver().is_none() {
+                vehicle.input = VehicleInput::default();
+            }
+        }
+        if let Some(mut controller) = controller {
+            controller.set_enabled(true);
+            controller.teleport(rider_transform.translation);
+        }
+        commands.entity(event.rider).remove::<SeatedIn>();
+        log::debug!("Vehicle: rider {:?} exited {:?}", event.rider, seated.vehicle);
+    }
+
+    for event in enter_events.read() {
+        let Ok((already_seated, controller, _)) = riders.get_mut(event.rider) else {
+            continue;
+        };
+        if already_seated.is_some() {
+            continue;
+        }
+        let Ok((mut vehicle, _)) = vehicles.get_mut(event.vehicle) else {
+            continue;
+        };
+        let Some(seat_index) = vehicle.free_seat(event.seat) else {
+            log::debug!("Vehicle {:?}: no free seat for {:?}", event.vehicle, event.rider);
+            continue;
+        };
+
+        vehicle.config.seats[seat_index].occupant = Some(event.rider);
+        if let Some(mut controller) = controller {
+            controller.set_enabled(false);
+        }
+        commands.entity(event.rider).insert(SeatedIn {
+            vehicle: event.vehicle,
+            seat: seat_index,
+        });
+        log::debug!(
+            "Vehicle: rider {:?} entered {:?} seat {}",
+            event.rider,
+            event.vehicle,
+            seat_index
+        );
+    }
+}
+
+fn update_vehicle_physics(
+    time: Res<Time>,
+    physics: Res<PhysicsFabric>,
+    rapier_context: ReadRapierContext,
+    mut vehicles: Query<(
+        Entity,
+        &mut VehicleController,
+        &Transform,
+        &Velocity,
+        &mut ExternalForce,
+        Option<&VehicleNetSync>,
+    )>,
+) {
+    if !physics.is_enabled() || physics.is_paused() {
+        return;
+    }
+
+    let Ok(rapier_context) = rapier_context.single() else {
+        return;
+    };
+
+    let dt = (time.delta_secs() * physics.time_scale()).max(1e-4);
+    let gravity = physics.settings.gravity;
+
+    for (entity, mut vehicle, transform, velocity, mut external_force, net_sync) in vehicles.iter_mut() {
+        // Remote proxies are driven by snapshots, not simulated locally
+        if net_sync.map(|s| !s.is_authority).unwrap_or(false) {
+            continue;
+        }
+
+        let up = transform.up().as_vec3();
+        let forward = transform.forward().as_vec3();
+        let com = transform.translation;
+        let filter = QueryFilter::default().exclude_rigid_body(entity).exclude_collider(entity);
+
+        let mass = vehicle.config.mass;
+        let max_speed = vehicle.config.max_speed;
+        let forward_speed = velocity.linvel.dot(forward);
+        vehicle.forward_speed = forward_speed;
+
+        let target_steer = vehicle.input.steer.clamp(-1.0, 1.0) * vehicle.config.max_steer_angle;
+        // Steering rate limit so siege engines can't snap around
+        let steer_step = 90.0 * dt;
+        vehicle.steer_angle += (target_steer - vehicle.steer_angle).clamp(-steer_step, steer_step);
+
+        let speed_ratio = (forward_speed.abs() / max_speed.max(0.1)).clamp(0.0, 1.0);
+        let throttle = vehicle.input.throttle.clamp(-1.0, 1.0);
+        let reversing = throttle < 0.0;
+        let speed_limit = if reversing {
+            max_speed * vehicle.config.reverse_speed_ratio
+        } else {
+            max_speed
+        };
+
+        let wheel_count = vehicle.config.wheels.len().max(1) as f32;
+        let driven_count = vehicle.config.wheels.iter().filter(|w| w.is_driven).count().max(1) as f32;
+        let braked_count = vehicle.config.wheels.iter().filter(|w| w.is_braked).count().max(1) as f32;
+
+        let mut total_force = ExternalForce::default();
+        let mut ground_normal_sum = Vec3::ZERO;
+
+        for i in 0..vehicle.wheels.len() {
+            let wheel = vehicle.config.wheels[i];
+            let anchor = transform.transform_point(wheel.local_anchor);
+            let ray_length = wheel.suspension_rest_length + wheel.radius;
+
+            let hit = rapier_context.cast_ray_and_get_normal(anchor, -up, ray_length, true, filter);
+
+            let previous_compression = vehicle.wheels[i].compression;
+            let Some((ground_entity, intersection)) = hit else {
+                vehicle.wheels[i] = WheelState::default();
+                continue;
+            };
+
+            let compression = (ray_length - intersection.time_of_impact).max(0.0);
+            let compression_velocity = (compression - previous_compression) / dt;
+            let suspension = (wheel.suspension_stiffness * compression
+                + wheel.suspension_damping * compression_velocity)
+                .clamp(0.0, wheel.max_suspension_force);
+
+            vehicle.wheels[i] = WheelState {
+                grounded: true,
+                compression,
+                contact_point: intersection.point,
+                contact_normal: intersection.normal,
+                ground_entity: Some(ground_entity),
+            };
+            ground_normal_sum += intersection.normal;
+
+            let contact = intersection.point;
+            total_force += ExternalForce::at_point(up * suspension, contact, com);
+
+            // Wheel heading projected onto the ground plane
+            let wheel_forward = if wheel.is_steered {
+                Quat::from_axis_angle(up, -vehicle.steer_angle.to_radians()) * forward
+            } else {
+                forward
+            };
+            let wheel_forward = (wheel_forward - intersection.normal * wheel_forward.dot(intersection.normal))
+                .normalize_or_zero();
+            let wheel_right = wheel_forward.cross(intersection.normal).normalize_or_zero();
+
+            let point_velocity = velocity.linvel + velocity.angvel.cross(contact - com);
+
+            if wheel.is_driven && throttle.abs() > 0.01 && forward_speed.abs() < speed_limit {
+                let drive = vehicle.config.engine_curve.sample(speed_ratio) * throttle / driven_count;
+                total_force += ExternalForce::at_point(wheel_forward * drive, contact, com);
+            }
+
+            let brake_input = if vehicle.input.handbrake {
+                1.0
+            } else {
+                vehicle.input.brake.clamp(0.0, 1.0)
+            };
+            if wheel.is_braked && brake_input > 0.0 {
+                let along = point_velocity.dot(wheel_forward);
+                let brake = vehicle.config.brake_curve.sample(speed_ratio) * brake_input / braked_count;
+                // Never apply more than enough to stop the wheel this frame
+                let max_brake = along.abs() * mass / (wheel_count * dt);
+                let brake = brake.min(max_brake) * -along.signum();
+                total_force += ExternalForce::at_point(wheel_forward * brake, contact, com);
+            }
+
+            // Lateral grip: cancel sideways sliding proportional to grip
+            let lateral_velocity = point_velocity.dot(wheel_right);
+            let lateral = -lateral_velocity * vehicle.config.lateral_grip * mass / (wheel_count * dt);
+            let lateral = lateral.clamp(-suspension * 1.5, suspension * 1.5);
+            total_force += ExternalForce::at_point(wheel_right * lateral, contact, com);
+
+            let rolling = -point_velocity.dot(wheel_forward) * vehicle.config.rolling_resistance * suspension;
+            total_force += ExternalForce::at_point(wheel_forward * rolling, contact, com);
+        }
+
+        // Slope handling: past the climb limit the drive fades out and the
+        // along-slope part of gravity takes over so heavy wagons roll back
+        if ground_normal_sum != Vec3::ZERO {
+            let ground_normal = ground_normal_sum.normalize();
+            vehicle.slope_angle = ground_normal.dot(Vec3::Y).clamp(-1.0, 1.0).acos().to_degrees();
+            let over_limit = vehicle.slope_angle - vehicle.config.max_climb_angle;
+            if over_limit > 0.0 {
+                let fade = (over_limit / 10.0).clamp(0.0, 1.0);
+                let downhill = (gravity - ground_normal * gravity.dot(ground_normal)) * mass * fade;
+                let climbing = total_force.force.dot(downhill.normalize_or_zero()) < 0.0;
+                if climbing {
+                    total_force.force += downhill;
+                }
+            }
+        } else {
+            vehicle.slope_angle = 0.0;
+        }
+
+        *external_force = total_force;
+    }
+}
+
+fn sync_seated_riders(
+    vehicles: Query<(&VehicleController, &Transform)>,
+    mut riders: Query<(&SeatedIn, &mut Transform), Without<VehicleController>>,
+) {
+    for (seated, mut rider_transform) in riders.iter_mut() {
+        let Ok((vehicle, vehicle_transform)) = vehicles.get(seated.vehicle) else {
+            continue;
+        };
+        let Some(seat) = vehicle.config.seats.get(seated.seat) else {
+            continue;
+        };
+        rider_transform.translation = vehicle_transform.transform_point(seat.local_offset);
+        rider_transform.rotation = vehicle_transform.rotation;
+    }
+}
+
+fn produce_vehicle_snapshots(
+    time: Res<Time>,
+    physics: Res<PhysicsFabric>,
+    mut vehicles: Query<(Entity, &VehicleController, &Transform, &Velocity, &mut VehicleNetSync)>,
+    mut snapshots: EventWriter<VehicleStateSnapshot>,
+) {
+    for (entity, vehicle, transform, velocity, mut sync) in vehicles.iter_mut() {
+        if !sync.is_authority {
+            continue;
+        }
+        sync.time_since_send += time.delta_secs();
+        if sync.time_since_send < sync.send_interval {
+            continue;
+        }
+        sync.time_since_send = 0.0;
+
+        let state = VehicleNetState {
+            tick: physics.step_count(),
+            position: transform.translation.to_array(),
+            rotation: transform.rotation.to_array(),
+            linear_velocity: velocity.linvel.to_array(),
+            angular_velocity: velocity.angvel.to_array(),
+            input: vehicle.input,
+            wheel_compression: vehicle.wheels.iter().map(|w| w.compression).collect(),
+            occupants: vehicle
+                .config
+                .seats
+                .iter()
+                .map(|s| s.occupant.map(|e| e.to_bits()))
+                .collect(),
+        };
+
+        snapshots.send(VehicleStateSnapshot {
+            entity,
+            network_id: sync.network_id,
+            state,
+        });
+    }
+}
+
+fn apply_remote_vehicle_state(
+    time: Res<Time>,
+    mut vehicles: Query<(&mut VehicleController, &mut Transform, &mut Velocity, &VehicleNetSync)>,
+) {
+    let blend = (time.delta_secs() * 10.0).clamp(0.0, 1.0);
+
+    for (mut vehicle, mut transform, mut velocity, sync) in vehicles.iter_mut() {
+        if sync.is_authority {
+            continue;
+        }
+        let Some(state) = sync.latest_remote.as_ref() else {
+            continue;
+        };
+
+        let target_pos = Vec3::from_array(state.position);
+        let target_rot = Quat::from_array(state.rotation);
+        // Snap when far off (teleport, late join), otherwise smooth toward the snapshot
+        if transform.translation.distance_squared(target_pos) > 25.0 {
+            transform.translation = target_pos;
+            transform.rotation = target_rot;
+        } else {
+            transform.translation = transform.translation.lerp(target_pos, blend);
+            transform.rotation = transform.rotation.slerp(target_rot, blend);
+        }
+        velocity.linvel = Vec3::from_array(state.linear_velocity);
+        velocity.angvel = Vec3::from_array(state.angular_velocity);
+
+        vehicle.input = state.input;
+        for (wheel, compression) in vehicle.wheels.iter_mut().zip(&state.wheel_compression) {
+            wheel.compression = *compression;
+            wheel.grounded = *compression > 0.0;
+        }
+    }
+}
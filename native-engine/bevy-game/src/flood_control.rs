@@ -0,0 +1,398 @@
+//! Per-client message budgets on the server, so one client can't flood the
+//! match with chat, position updates or RPCs.
+//!
+//! Each client has a token bucket per [`MessageKind`]. The match socket asks
+//! [`FloodControl::admit`] before handling a message and drops it when the
+//! bucket is empty. Too many dropped messages in a short window squelch the
+//! client: its chat and RPCs are ignored for a while, though positions keep
+//! flowing within their own budget so the character doesn't freeze. A
+//! client squelched too often is kicked through [`FloodKickRequest`].
+//!
+//! Clients are keyed by account name, the same name GM actions take.
+//! Offenders show up in the GM panel, in the `floods` console command and
+//! in the `mmo_net_*` metrics.
+
+use bevy::prelude::*;
+use std::collections::{HashMap, VecDeque};
+
+use crate::console::{
+    ArgCompletion, CommandPermission, ConsoleAppExt, ConsoleCommand, ConsoleCommandEvent, ConsoleOutputEvent,
+};
+use crate::metrics::Metrics;
+
+/// Client messages dropped by flood control, by kind.
+pub const NET_MESSAGES_LIMITED_TOTAL: &str = "mmo_net_messages_limited_total";
+pub const NET_CLIENTS_SQUELCHED: &str = "mmo_net_clients_squelched";
+pub const NET_FLOOD_KICKS_TOTAL: &str = "mmo_net_flood_kicks_total";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MessageKind {
+    Chat,
+    Position,
+    Rpc,
+}
+
+impl MessageKind {
+    pub const ALL: [MessageKind; 3] = [MessageKind::Chat, MessageKind::Position, MessageKind::Rpc];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MessageKind::Chat => "chat",
+            MessageKind::Position => "position",
+            MessageKind::Rpc => "rpc",
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct BucketConfig {
+    /// Messages a client may send in a burst.
+    pub burst: f32,
+    /// Messages per second the budget refills at.
+    pub per_second: f32,
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct FloodControlConfig {
+    pub chat: BucketConfig,
+    pub position: BucketConfig,
+    pub rpc: BucketConfig,
+    /// Dropped messages within `violation_window_seconds` that squelch a client.
+    pub squelch_after: usize,
+    pub violation_window_seconds: f32,
+    pub squelch_seconds: f32,
+    /// Squelches in one session that get a client kicked; 0 never kicks.
+    pub kick_after_squelches: u32,
+}
+
+impl Default for FloodControlConfig {
+    fn default() -> Self {
+        Self {
+            chat: BucketConfig {
+                burst: 5.0,
+                per_second: 1.0,
+            },
+            // Clients send ten a second; leave room for jitter
+            position: BucketConfig {
+                burst: 30.0,
+                per_second: 20.0,
+            },
+            rpc: BucketConfig {
+                burst: 20.0,
+                per_second: 10.0,
+            },
+            squelch_after: 20,
+            violation_window_seconds: 10.0,
+            squelch_seconds: 30.0,
+            kick_after_squelches: 3,
+        }
+    }
+}
+
+impl FloodControlConfig {
+    pub fn bucket(&self, kind: MessageKind) -> BucketConfig {
+        match kind {
+            MessageKind::Chat => self.chat,
+            MessageKind::Position => self.position,
+            MessageKind::Rpc => self.rpc,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct TokenBucket {
+    tokens: f32,
+    updated_at: f64,
+}
+
+impl TokenBucket {
+    fn full(config: BucketConfig, now: f64) -> Self {
+        Self {
+            tokens: config.burst,
+            updated_at: now,
+        }
+    }
+
+    fn take(&mut self, config: BucketConfig, now: f64) -> bool {
+        let elapsed = (now - self.updated_at).max(0.0) as f32;
+        self.tokens = (self.tokens + elapsed * config.per_second).min(config.burst);
+        self.updated_at = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// What to do with a client's message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Admission {
+    Allow,
+    /// Over budget; drop it.
+    Drop,
+    /// The client is squelched; drop it.
+    Squelched,
+    /// The client has been kicked; drop it and everything after.
+    Kicked,
+}
+
+#[derive(Debug, Clone)]
+struct ClientBudget {
+    buckets: [TokenBucket; 3],
+    limited: [u64; 3],
+    violations: VecDeque<f64>,
+    squelched_until: f64,
+    squelches: u32,
+    kicked: bool,
+}
+
+/// One client's record, for GM tools.
+#[derive(Debug, Clone)]
+pub struct FloodReport {
+    pub client: String,
+    /// Dropped messages by kind, in [`MessageKind::ALL`] order.
+    pub limited: [u64; 3],
+    pub squelches: u32,
+    /// Seconds of squelch left.
+    pub squelched_for: f32,
+    pub kicked: bool,
+}
+
+impl FloodReport {
+    pub fn total_limited(&self) -> u64 {
+        self.limited.iter().sum()
+    }
+
+    pub fn describe(&self) -> String {
+        let mut text = format!(
+            "{}: {} dropped ({} chat, {} position, {} rpc), squelched {}x",
+            self.client, self.total_limited(), self.limited[0], self.limited[1], self.limited[2], self.squelches
+        );
+        if self.kicked {
+            text.push_str(", kicked");
+        } else if self.squelched_for > 0.0 {
+            text.push_str(&format!(", squelched for {:.0}s", self.squelched_for));
+        }
+        text
+    }
+}
+
+/// A client went over its budget often enough to be silenced.
+#[derive(Event, Debug, Clone)]
+pub struct FloodSquelchEvent {
+    pub client: String,
+    pub seconds: f32,
+}
+
+/// The match socket should disconnect `client`.
+#[derive(Event, Debug, Clone)]
+pub struct FloodKickRequest {
+    pub client: String,
+    pub reason: String,
+}
+
+#[derive(Debug)]
+enum FloodAction {
+    Limited(MessageKind),
+    Squelch(String, f32),
+    Kick(String),
+}
+
+/// Every connected client's message budget.
+#[derive(Resource, Debug, Default)]
+pub struct FloodControl {
+    clients: HashMap<String, ClientBudget>,
+    actions: Vec<FloodAction>,
+}
+
+impl FloodControl {
+    /// Charges `client` one `kind` message at `now` (seconds, any
+    /// monotonic clock) and says whether to handle it.
+    pub fn admit(&mut self, config: &FloodControlConfig, client: &str, kind: MessageKind, now: f64) -> Admission {
+        let budget = self.clients.entry(client.to_string()).or_insert_with(|| ClientBudget {
+            buckets: MessageKind::ALL.map(|kind| TokenBucket::full(config.bucket(kind), now)),
+            limited: [0; 3],
+            violations: VecDeque::new(),
+            squelched_until: 0.0,
+            squelches: 0,
+            kicked: false,
+        });
+        if budget.kicked {
+            return Admission::Kicked;
+        }
+        if kind != MessageKind::Position && now < budget.squelched_until {
+            return Admission::Squelched;
+        }
+        if budget.buckets[kind.index()].take(config.bucket(kind), now) {
+            return Admission::Allow;
+        }
+
+        budget.limited[kind.index()] += 1;
+        self.actions.push(FloodAction::Limited(kind));
+        let window = config.violation_window_seconds as f64;
+        while budget.violations.front().is_some_and(|at| now - at > window) {
+            budget.violations.pop_front();
+        }
+        budget.violations.push_back(now);
+        if budget.violations.len() < config.squelch_after.max(1) {
+            return Admission::Drop;
+        }
+
+        budget.violations.clear();
+        budget.squelches += 1;
+        if config.kick_after_squelches > 0 && budget.squelches >= config.kick_after_squelches {
+            budget.kicked = true;
+            self.actions.push(FloodAction::Kick(client.to_string()));
+            return Admission::Kicked;
+        }
+        budget.squelched_until = now + config.squelch_seconds as f64;
+        self.actions.push(FloodAction::Squelch(client.to_string(), config.squelch_seconds));
+        Admission::Squelched
+    }
+
+    /// Forgets a client that left.
+    pub fn remove(&mut self, client: &str) {
+        self.clients.remove(client);
+    }
+
+    /// Lifts a squelch and clears the client's record. `false` when the
+    /// client isn't known.
+    pub fn pardon(&mut self, client: &str) -> bool {
+        self.clients.remove(client).is_some()
+    }
+
+    /// Clients that have had messages dropped, worst first.
+    pub fn offenders(&self, now: f64) -> Vec<FloodReport> {
+        let mut reports: Vec<FloodReport> = self
+            .clients
+            .iter()
+            .filter(|(_, budget)| budget.limited.iter().any(|n| *n > 0) || budget.kicked)
+            .map(|(client, budget)| FloodReport {
+                client: client.clone(),
+                limited: budget.limited,
+                squelches: budget.squelches,
+                squelched_for: (budget.squelched_until - now).max(0.0) as f32,
+                kicked: budget.kicked,
+            })
+            .collect();
+        reports.sort_by(|a, b| {
+            (b.kicked, b.squelches, b.total_limited())
+                .cmp(&(a.kicked, a.squelches, a.total_limited()))
+                .then_with(|| a.client.cmp(&b.client))
+        });
+        reports
+    }
+
+    pub fn squelched(&self, now: f64) -> usize {
+        self.clients
+            .values()
+            .filter(|budget| !budget.kicked && budget.squelched_until > now)
+            .count()
+    }
+}
+
+/// Flood control for servers.
+pub struct FloodControlPlugin;
+
+impl Plugin for FloodControlPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FloodControlConfig>()
+            .init_resource::<FloodControl>()
+            .add_event::<FloodSquelchEvent>()
+            .add_event::<FloodKickRequest>()
+            .add_console_command(
+                ConsoleCommand::new("floods", "Clients over their message budget, or clear one's record")
+                    .usage("[clear <player>]")
+                    .permission(CommandPermission::Admin)
+                    .complete(vec![ArgCompletion::Values(vec!["clear".to_string()])]),
+            )
+            .add_systems(Startup, describe_flood_metrics)
+            .add_systems(Update, (publish_flood_actions, floods_command));
+    }
+}
+
+fn describe_flood_metrics(metrics: Option<Res<Metrics>>) {
+    let Some(metrics) = metrics else {
+        return;
+    };
+    metrics.describe_counter(NET_MESSAGES_LIMITED_TOTAL, "Client messages dropped by flood control, by kind");
+    metrics.describe_gauge(NET_CLIENTS_SQUELCHED, "Clients squelched for flooding");
+    metrics.describe_counter(NET_FLOOD_KICKS_TOTAL, "Clients kicked for flooding");
+}
+
+fn publish_flood_actions(
+    time: Res<Time>,
+    metrics: Option<Res<Metrics>>,
+    mut flood: ResMut<FloodControl>,
+    mut squelches: EventWriter<FloodSquelchEvent>,
+    mut kicks: EventWriter<FloodKickRequest>,
+) {
+    for action in std::mem::take(&mut flood.actions) {
+        match action {
+            FloodAction::Limited(kind) => {
+                if let Some(metrics) = &metrics {
+                    metrics.increment(NET_MESSAGES_LIMITED_TOTAL, &[("kind", kind.as_str())], 1.0);
+                }
+            }
+            FloodAction::Squelch(client, seconds) => {
+                warn!("Squelched {} for {:.0}s for flooding", client, seconds);
+                squelches.send(FloodSquelchEvent { client, seconds });
+            }
+            FloodAction::Kick(client) => {
+                warn!("Kicking {} for flooding", client);
+                if let Some(metrics) = &metrics {
+                    metrics.increment(NET_FLOOD_KICKS_TOTAL, &[], 1.0);
+                }
+                kicks.send(FloodKickRequest {
+                    client,
+                    reason: "Too many messages".to_string(),
+                });
+            }
+        }
+    }
+    if let Some(metrics) = &metrics {
+        metrics.set_gauge(NET_CLIENTS_SQUELCHED, &[], flood.squelched(time.elapsed_secs_f64()) as f64);
+    }
+}
+
+fn floods_command(
+    time: Res<Time>,
+    mut flood: ResMut<FloodControl>,
+    mut events: EventReader<ConsoleCommandEvent>,
+    mut output: EventWriter<ConsoleOutputEvent>,
+) {
+    for event in events.read() {
+        if event.name != "floods" {
+            continue;
+        }
+        let result: Result<String, String> = match event.args.first().map(String::as_str) {
+            None => {
+                let offenders = flood.offenders(time.elapsed_secs_f64());
+                if offenders.is_empty() {
+                    Ok("No clients over budget".to_string())
+                } else {
+                    let lines: Vec<String> = offenders.iter().take(10).map(FloodReport::describe).collect();
+                    Ok(lines.join("\n"))
+                }
+            }
+            Some("clear") => event.arg::<String>(1, "player").and_then(|player| {
+                if flood.pardon(&player) {
+                    Ok(format!("Cleared {}'s record", player))
+                } else {
+                    Err(format!("no record for '{}'", player))
+                }
+            }),
+            Some(other) => Err(format!("unknown action '{}'", other)),
+        };
+        output.send(match result {
+            Ok(text) => event.reply(text),
+            Err(text) => event.error(format!("{}: {}", event.name, text)),
+        });
+    }
+}
@@ -0,0 +1,45 @@
+// Instanced trees. Instances carry world positions, so the mesh transform
+// isn't used; the view bind group supplies the camera.
+#import bevy_pbr::view_transformations::position_world_to_clip
+
+struct Vertex {
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(5) color: vec4<f32>,
+    // xyz: trunk base, w: scale
+    @location(8) position_scale: vec4<f32>,
+    // x: yaw, yzw: linear tint
+    @location(9) yaw_tint: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+    @location(1) normal: vec3<f32>,
+};
+
+fn rotate_y(v: vec3<f32>, yaw: f32) -> vec3<f32> {
+    let c = cos(yaw);
+    let s = sin(yaw);
+    return vec3<f32>(c * v.x + s * v.z, v.y, c * v.z - s * v.x);
+}
+
+@vertex
+fn vertex(vertex: Vertex) -> VertexOutput {
+    let yaw = vertex.yaw_tint.x;
+    let world = rotate_y(vertex.position, yaw) * vertex.position_scale.w + vertex.position_scale.xyz;
+    var out: VertexOutput;
+    out.clip_position = position_world_to_clip(world);
+    out.color = vec4<f32>(vertex.color.rgb * vertex.yaw_tint.yzw, 1.0);
+    out.normal = rotate_y(vertex.normal, yaw);
+    return out;
+}
+
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Fixed sun and ambient; trees are small on screen and don't need the
+    // full PBR path
+    let sun = normalize(vec3<f32>(0.4, 0.8, 0.3));
+    let light = 0.35 + 0.65 * max(dot(normalize(in.normal), sun), 0.0);
+    return vec4<f32>(in.color.rgb * light, 1.0);
+}
@@ -0,0 +1,494 @@
+//! Forests as per-chunk instance buffers. Trees are placed when a terrain
+//! chunk's heights are built and kept only in [`ForestGrid`], which answers
+//! collision and gameplay queries. Each chunk then gets one entity per
+//! species whose [`ForestInstances`] are drawn with a single instanced draw
+//! (see `render`). A forest costs a handful of entities per chunk instead
+//! of one per tree.
+//!
+//! Placement is seeded per cell from the world seed's forest stream. Chunks
+//! re-place on every remesh, so LOD changes and sculpting keep trees on the
+//! drawn ground, and painted vegetation density remeshes the chunks it
+//! touches.
+
+use bevy::prelude::*;
+use bevy::render::primitives::Aabb;
+use std::collections::{HashMap, HashSet};
+
+mod render;
+
+use crate::authoring::terrain_sculpt::{TerrainEditedEvent, TerrainEdits};
+use crate::terrain_meshing::{
+    world_chunks_for_edits, TerrainChunkMeshedEvent, TerrainChunkReleasedEvent, TerrainMeshJobs,
+};
+use crate::worldgen::{WorldSeed, WorldStream};
+use crate::{Player, TerrainConfig};
+
+pub use render::TreeGpuInstance;
+
+#[derive(Debug, Clone)]
+pub struct TreeSpecies {
+    pub name: String,
+    /// Relative share of placed trees.
+    pub weight: f32,
+    pub tint: Color,
+    pub trunk_height: f32,
+    pub trunk_radius: f32,
+    pub crown_radius: f32,
+    /// Cone crown (conifers) rather than a round one.
+    pub conifer: bool,
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct ForestPlacementConfig {
+    /// Metres between candidate tree positions.
+    pub spacing: f32,
+    /// Chance a candidate gets a tree, before painted density.
+    pub density: f32,
+    /// Steepest ground (rise over run) trees grow on.
+    pub max_slope: f32,
+    pub min_height: f32,
+    pub max_height: f32,
+    pub min_scale: f32,
+    pub max_scale: f32,
+    /// Beyond this, chunks draw the low-detail meshes.
+    pub lod_distance: f32,
+    /// Beyond this, chunks draw no trees at all.
+    pub draw_distance: f32,
+    pub species: Vec<TreeSpecies>,
+}
+
+impl Default for ForestPlacementConfig {
+    fn default() -> Self {
+        Self {
+            spacing: 7.0,
+            density: 0.45,
+            max_slope: 0.7,
+            min_height: 1.5,
+            max_height: 180.0,
+            min_scale: 0.8,
+            max_scale: 1.3,
+            lod_distance: 120.0,
+            draw_distance: 600.0,
+            species: vec![
+                TreeSpecies {
+                    name: "pine".to_string(),
+                    weight: 0.6,
+                    tint: Color::srgb(0.16, 0.32, 0.18),
+                    trunk_height: 3.0,
+                    trunk_radius: 0.25,
+                    crown_radius: 1.8,
+                    conifer: true,
+                },
+                TreeSpecies {
+                    name: "oak".to_string(),
+                    weight: 0.4,
+                    tint: Color::srgb(0.24, 0.40, 0.16),
+                    trunk_height: 2.5,
+                    trunk_radius: 0.35,
+                    crown_radius: 2.4,
+                    conifer: false,
+                },
+            ],
+        }
+    }
+}
+
+impl ForestPlacementConfig {
+    fn pick_species(&self, roll: f32) -> usize {
+        let total: f32 = self.species.iter().map(|s| s.weight.max(0.0)).sum();
+        let mut remaining = roll * total;
+        for (index, species) in self.species.iter().enumerate() {
+            remaining -= species.weight.max(0.0);
+            if remaining <= 0.0 {
+                return index;
+            }
+        }
+        self.species.len().saturating_sub(1)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TreeInstance {
+    /// Base of the trunk.
+    pub position: Vec3,
+    pub yaw: f32,
+    pub scale: f32,
+    pub species: u16,
+    /// Collision radius of the trunk, already scaled.
+    pub trunk_radius: f32,
+}
+
+/// Every placed tree, bucketed by terrain chunk. This is the only CPU copy;
+/// the instance buffers are rebuilt from it when a chunk changes.
+#[derive(Resource, Debug, Default)]
+pub struct ForestGrid {
+    chunk_size: f32,
+    chunks: HashMap<IVec2, Vec<TreeInstance>>,
+    /// Chunks whose instance buffers need rebuilding.
+    dirty: HashSet<IVec2>,
+}
+
+impl ForestGrid {
+    fn chunk_of(&self, position: Vec3) -> IVec2 {
+        (position.xz() / self.chunk_size.max(1.0)).floor().as_ivec2()
+    }
+
+    pub fn len(&self) -> usize {
+        self.chunks.values().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chunks.values().all(Vec::is_empty)
+    }
+
+    pub fn chunk(&self, coord: IVec2) -> &[TreeInstance] {
+        self.chunks.get(&coord).map_or(&[], Vec::as_slice)
+    }
+
+    /// Trees whose trunk base is within `radius` of `center` (ignoring height).
+    pub fn trees_in_radius(&self, center: Vec3, radius: f32) -> impl Iterator<Item = &TreeInstance> {
+        let min = self.chunk_of(center - Vec3::splat(radius));
+        let max = self.chunk_of(center + Vec3::splat(radius));
+        let radius_squared = radius * radius;
+        (min.x..=max.x)
+            .flat_map(move |x| (min.y..=max.y).map(move |z| IVec2::new(x, z)))
+            .flat_map(|coord| self.chunk(coord))
+            .filter(move |tree| tree.position.xz().distance_squared(center.xz()) <= radius_squared)
+    }
+
+    pub fn nearest(&self, center: Vec3, max_distance: f32) -> Option<&TreeInstance> {
+        self.trees_in_radius(center, max_distance)
+            .min_by(|a, b| {
+                let da = a.position.xz().distance_squared(center.xz());
+                let db = b.position.xz().distance_squared(center.xz());
+                da.total_cmp(&db)
+            })
+    }
+
+    /// Whether a circle of `radius` at `position` overlaps a trunk.
+    pub fn blocked(&self, position: Vec3, radius: f32) -> bool {
+        // Trunks are thin; 2 m covers the largest scaled one
+        self.trees_in_radius(position, radius + 2.0).any(|tree| {
+            tree.position.xz().distance(position.xz()) < radius + tree.trunk_radius
+        })
+    }
+
+    /// Removes the tree nearest `position` (felled, burnt, harvested).
+    pub fn remove_nearest(&mut self, position: Vec3, max_distance: f32) -> Option<TreeInstance> {
+        let tree = *self.nearest(position, max_distance)?;
+        let coord = self.chunk_of(tree.position);
+        let trees = self.chunks.get_mut(&coord)?;
+        let index = trees.iter().position(|t| *t == tree)?;
+        self.dirty.insert(coord);
+        Some(trees.swap_remove(index))
+    }
+
+    fn set_chunk(&mut self, coord: IVec2, trees: Vec<TreeInstance>) {
+        self.chunks.insert(coord, trees);
+        self.dirty.insert(coord);
+    }
+
+    fn remove_chunk(&mut self, coord: IVec2) {
+        if self.chunks.remove(&coord).is_some() {
+            self.dirty.insert(coord);
+        }
+    }
+}
+
+/// Near and far meshes per species, indexed like `ForestPlacementConfig::species`.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct ForestMeshes {
+    pub near: Vec<Handle<Mesh>>,
+    pub far: Vec<Handle<Mesh>>,
+}
+
+/// One species' trees in one chunk, drawn in a single instanced call. The
+/// entity sits at the origin; instances carry world positions.
+#[derive(Component, Debug, Clone)]
+pub struct ForestChunk {
+    pub coord: IVec2,
+    pub species: usize,
+    pub far: bool,
+}
+
+#[derive(Component, Debug, Clone, Default)]
+pub struct ForestInstances(pub Vec<TreeGpuInstance>);
+
+pub struct ForestPlugin;
+
+impl Plugin for ForestPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ForestPlacementConfig>()
+            .init_resource::<ForestGrid>()
+            .add_plugins(render::ForestRenderPlugin)
+            .add_systems(Startup, build_tree_meshes)
+            .add_systems(
+                Update,
+                (
+                    remesh_vegetation_edits,
+                    place_chunk_trees,
+                    sync_forest_chunks,
+                    update_forest_lod,
+                )
+                    .chain()
+                    .after(crate::terrain_meshing::TerrainMeshingSet),
+            );
+    }
+}
+
+fn tree_mesh(species: &TreeSpecies, far: bool) -> Mesh {
+    let (sides, crown_detail) = if far { (4, 0) } else { (8, 1) };
+    let trunk_color = LinearRgba::new(0.45, 0.32, 0.22, 1.0).to_f32_array();
+    let crown_color = LinearRgba::WHITE.to_f32_array();
+
+    let crown_center = species.trunk_height + species.crown_radius * if species.conifer { 1.0 } else { 0.6 };
+    let mut crown = if species.conifer {
+        Cone {
+            radius: species.crown_radius,
+            height: species.crown_radius * 2.5,
+        }
+        .mesh()
+        .resolution(sides)
+        .build()
+    } else {
+        Sphere::new(species.crown_radius)
+            .mesh()
+            .ico(crown_detail)
+            .unwrap_or_else(|_| Sphere::new(species.crown_radius).mesh().uv(sides, sides / 2))
+    }
+    .translated_by(Vec3::Y * crown_center);
+    let crown_vertices = crown.count_vertices();
+    crown.insert_attribute(Mesh::ATTRIBUTE_COLOR, vec![crown_color; crown_vertices]);
+
+    // Far trees are all crown; the trunk is under a pixel at that range
+    if far {
+        return crown;
+    }
+    let mut trunk = Cylinder::new(species.trunk_radius, species.trunk_height)
+        .mesh()
+        .resolution(sides)
+        .build()
+        .translated_by(Vec3::Y * species.trunk_height * 0.5);
+    let trunk_vertices = trunk.count_vertices();
+    // Instance tint multiplies vertex colour; a dark trunk stays dark
+    trunk.insert_attribute(Mesh::ATTRIBUTE_COLOR, vec![trunk_color; trunk_vertices]);
+    trunk.merge(&crown);
+    trunk
+}
+
+fn build_tree_meshes(mut commands: Commands, config: Res<ForestPlacementConfig>, mut meshes: ResMut<Assets<Mesh>>) {
+    let forest = ForestMeshes {
+        near: config.species.iter().map(|s| meshes.add(tree_mesh(s, false))).collect(),
+        far: config.species.iter().map(|s| meshes.add(tree_mesh(s, true))).collect(),
+    };
+    commands.insert_resource(forest);
+}
+
+fn remesh_vegetation_edits(
+    mut events: EventReader<TerrainEditedEvent>,
+    terrain: Res<TerrainConfig>,
+    edits: Option<Res<TerrainEdits>>,
+    mut jobs: ResMut<TerrainMeshJobs>,
+) {
+    let Some(edits) = edits else {
+        events.clear();
+        return;
+    };
+    // Height edits remesh already; placement follows from the new heights
+    let mut touched = HashSet::new();
+    for event in events.read().filter(|e| e.vegetation && !e.height) {
+        touched.extend(world_chunks_for_edits(&edits, terrain.chunk_size, &event.chunks));
+    }
+    for coord in touched {
+        jobs.remesh(coord);
+    }
+}
+
+/// Bilinear height and slope (rise over run) at `local`, metres from the
+/// chunk's minimum corner.
+fn sample_chunk(event: &TerrainChunkMeshedEvent, local: Vec2) -> (f32, f32) {
+    let n = event.resolution as usize + 1;
+    let step = event.chunk_size / event.resolution as f32;
+    let grid = (local / step).clamp(Vec2::ZERO, Vec2::splat(event.resolution as f32 - 0.001));
+    let (x, z) = (grid.x as usize, grid.y as usize);
+    let (tx, tz) = (grid.x.fract(), grid.y.fract());
+    let h = |x: usize, z: usize| event.heights[z * n + x];
+    let (h00, h10, h01, h11) = (h(x, z), h(x + 1, z), h(x, z + 1), h(x + 1, z + 1));
+    let near = h00 + (h10 - h00) * tx;
+    let far = h01 + (h11 - h01) * tx;
+    let slope = Vec2::new(h10 - h00, h01 - h00).length() / step;
+    (near + (far - near) * tz, slope)
+}
+
+fn place_chunk_trees(
+    mut meshed: EventReader<TerrainChunkMeshedEvent>,
+    mut released: EventReader<TerrainChunkReleasedEvent>,
+    config: Res<ForestPlacementConfig>,
+    seed: Option<Res<WorldSeed>>,
+    edits: Option<Res<TerrainEdits>>,
+    mut grid: ResMut<ForestGrid>,
+) {
+    let seed = seed.map_or_else(WorldSeed::default, |s| *s);
+    for event in meshed.read() {
+        grid.chunk_size = event.chunk_size;
+        let origin = event.coord.as_vec2() * event.chunk_size;
+        let spacing = config.spacing.max(0.5);
+        let first = (origin / spacing).ceil().as_ivec2();
+        let end = ((origin + Vec2::splat(event.chunk_size)) / spacing).ceil().as_ivec2();
+
+        let mut trees = Vec::new();
+        for cz in first.y..end.y {
+            for cx in first.x..end.x {
+                let cell = [cx, cz];
+                let value = |index| seed.cell_value(WorldStream::Forests, cell, index);
+                let jitter = Vec2::new(value(1), value(2)) * spacing;
+                let position = IVec2::new(cx, cz).as_vec2() * spacing + jitter;
+                let painted = edits.as_ref().map_or(1.0, |e| e.vegetation_density(position.x, position.y));
+                if value(0) > config.density * painted {
+                    continue;
+                }
+                let (height, slope) = sample_chunk(event, position - origin);
+                if slope > config.max_slope || height < config.min_height || height > config.max_height {
+                    continue;
+                }
+                let species = config.pick_species(value(3));
+                let scale = config.min_scale + (config.max_scale - config.min_scale) * value(5);
+                trees.push(TreeInstance {
+                    position: Vec3::new(position.x, height, position.y),
+                    yaw: value(4) * std::f32::consts::TAU,
+                    scale,
+                    species: species as u16,
+                    trunk_radius: config.species.get(species).map_or(0.3, |s| s.trunk_radius) * scale,
+                });
+            }
+        }
+        grid.set_chunk(event.coord, trees);
+    }
+    for event in released.read() {
+        grid.remove_chunk(event.coord);
+    }
+}
+
+/// Rebuilds instance buffers for chunks whose trees changed.
+fn sync_forest_chunks(
+    mut commands: Commands,
+    config: Res<ForestPlacementConfig>,
+    forest_meshes: Option<Res<ForestMeshes>>,
+    mut grid: ResMut<ForestGrid>,
+    mut chunks: Query<(Entity, &ForestChunk, &mut ForestInstances, &mut Aabb)>,
+) {
+    let Some(forest_meshes) = forest_meshes else {
+        return;
+    };
+    if grid.dirty.is_empty() {
+        return;
+    }
+    let dirty = std::mem::take(&mut grid.dirty);
+
+    let mut by_species: HashMap<(IVec2, usize), Vec<TreeGpuInstance>> = HashMap::new();
+    for coord in &dirty {
+        for tree in grid.chunk(*coord) {
+            let tint = config
+                .species
+                .get(tree.species as usize)
+                .map_or(LinearRgba::WHITE, |s| s.tint.to_linear());
+            by_species
+                .entry((*coord, tree.species as usize))
+                .or_default()
+                .push(TreeGpuInstance::new(tree, tint));
+        }
+    }
+
+    // Update or despawn existing entities, then spawn what's new
+    for (entity, chunk, mut instances, mut aabb) in &mut chunks {
+        if !dirty.contains(&chunk.coord) {
+            continue;
+        }
+        match by_species.remove(&(chunk.coord, chunk.species)) {
+            Some(data) => {
+                *aabb = instances_aabb(&data, &config.species[chunk.species]);
+                instances.0 = data;
+            }
+            None => commands.entity(entity).despawn_recursive(),
+        }
+    }
+    for ((coord, species), data) in by_species {
+        let Some(mesh) = forest_meshes.near.get(species) else {
+            continue;
+        };
+        commands.spawn((
+            Name::new(format!("Forest {},{} {}", coord.x, coord.y, config.species[species].name)),
+            ForestChunk {
+                coord,
+                species,
+                far: false,
+            },
+            Mesh3d(mesh.clone()),
+            instances_aabb(&data, &config.species[species]),
+            ForestInstances(data),
+            Transform::IDENTITY,
+            Visibility::default(),
+        ));
+    }
+}
+
+/// Bounds of every tree in the buffer, for frustum culling.
+fn instances_aabb(instances: &[TreeGpuInstance], species: &TreeSpecies) -> Aabb {
+    let mut min = Vec3::splat(f32::MAX);
+    let mut max = Vec3::splat(f32::MIN);
+    for instance in instances {
+        let base = Vec3::from_slice(&instance.position_scale[..3]);
+        let scale = instance.position_scale[3];
+        let reach = species.crown_radius * scale;
+        let top = (species.trunk_height + species.crown_radius * 3.0) * scale;
+        min = min.min(base - Vec3::new(reach, 0.0, reach));
+        max = max.max(base + Vec3::new(reach, top, reach));
+    }
+    Aabb::from_min_max(min, max)
+}
+
+/// Swaps chunks between near and far meshes and hides them past the draw
+/// distance.
+fn update_forest_lod(
+    config: Res<ForestPlacementConfig>,
+    forest_meshes: Option<Res<ForestMeshes>>,
+    cameras: Query<&GlobalTransform, With<Camera3d>>,
+    players: Query<&Transform, With<Player>>,
+    grid: Res<ForestGrid>,
+    mut chunks: Query<(&mut ForestChunk, &mut Mesh3d, &mut Visibility)>,
+) {
+    let Some(forest_meshes) = forest_meshes else {
+        return;
+    };
+    let Some(viewer) = cameras
+        .iter()
+        .next()
+        .map(|t| t.translation())
+        .or_else(|| players.iter().next().map(|t| t.translation))
+    else {
+        return;
+    };
+    let chunk_size = grid.chunk_size.max(1.0);
+    for (mut chunk, mut mesh, mut visibility) in &mut chunks {
+        let center = (chunk.coord.as_vec2() + Vec2::splat(0.5)) * chunk_size;
+        // Nearest point of the chunk, so a large chunk doesn't pop when the
+        // viewer stands at its edge
+        let offset = (viewer.xz() - center).abs() - Vec2::splat(chunk_size * 0.5);
+        let distance = offset.max(Vec2::ZERO).length();
+
+        let hidden = distance > config.draw_distance;
+        let wanted = if hidden { Visibility::Hidden } else { Visibility::Inherited };
+        if *visibility != wanted {
+            *visibility = wanted;
+        }
+        let far = distance > config.lod_distance;
+        if hidden || chunk.far == far {
+            continue;
+        }
+        let meshes = if far { &forest_meshes.far } else { &forest_meshes.near };
+        if let Some(handle) = meshes.get(chunk.species) {
+            mesh.0 = handle.clone();
+            chunk.far = far;
+        }
+    }
+}
@@ -0,0 +1,268 @@
+//! Instanced draw for [`ForestInstances`]: each chunk entity's trees go to
+//! the GPU as one vertex buffer of [`TreeGpuInstance`]s and draw with one
+//! `draw_indexed` call. Culling is per chunk, through the entity's `Aabb`.
+
+use bevy::asset::load_internal_asset;
+use bevy::core_pipeline::core_3d::Transparent3d;
+use bevy::ecs::query::QueryItem;
+use bevy::ecs::system::lifetimeless::{Read, SRes};
+use bevy::ecs::system::SystemParamItem;
+use bevy::pbr::{MeshPipeline, MeshPipelineKey, RenderMeshInstances, SetMeshBindGroup, SetMeshViewBindGroup};
+use bevy::prelude::*;
+use bevy::render::extract_component::{ExtractComponent, ExtractComponentPlugin};
+use bevy::render::mesh::allocator::MeshAllocator;
+use bevy::render::mesh::{MeshVertexBufferLayoutRef, RenderMesh, RenderMeshBufferInfo};
+use bevy::render::render_asset::RenderAssets;
+use bevy::render::render_phase::{
+    AddRenderCommand, DrawFunctions, PhaseItem, PhaseItemExtraIndex, RenderCommand, RenderCommandResult,
+    SetItemPipeline, TrackedRenderPass, ViewSortedRenderPhases,
+};
+use bevy::render::render_resource::{
+    Buffer, BufferInitDescriptor, BufferUsages, PipelineCache, RenderPipelineDescriptor, SpecializedMeshPipeline,
+    SpecializedMeshPipelineError, SpecializedMeshPipelines, VertexAttribute, VertexBufferLayout, VertexFormat,
+    VertexStepMode,
+};
+use bevy::render::renderer::RenderDevice;
+use bevy::render::sync_world::MainEntity;
+use bevy::render::view::ExtractedView;
+use bevy::render::{Render, RenderApp, RenderSet};
+
+use super::{ForestInstances, TreeInstance};
+
+const INSTANCING_SHADER: Handle<Shader> = Handle::weak_from_u128(0x6a1f_37c2_58d4_4b0e_9c71_2e5a_f0b3_d814);
+
+/// Per-tree vertex data, matching `instancing.wgsl` locations 8 and 9.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[repr(C)]
+pub struct TreeGpuInstance {
+    /// Trunk base in world space, and scale.
+    pub position_scale: [f32; 4],
+    /// Yaw in radians, then the linear species tint.
+    pub yaw_tint: [f32; 4],
+}
+
+impl TreeGpuInstance {
+    const SIZE: u64 = std::mem::size_of::<Self>() as u64;
+
+    pub fn new(tree: &TreeInstance, tint: LinearRgba) -> Self {
+        Self {
+            position_scale: [tree.position.x, tree.position.y, tree.position.z, tree.scale],
+            yaw_tint: [tree.yaw, tint.red, tint.green, tint.blue],
+        }
+    }
+}
+
+/// Render entities are retained, so only changed chunks are re-extracted and
+/// re-uploaded; the rest keep last frame's buffer.
+impl ExtractComponent for ForestInstances {
+    type QueryData = &'static ForestInstances;
+    type QueryFilter = Changed<ForestInstances>;
+    type Out = Self;
+
+    fn extract_component(item: QueryItem<'_, Self::QueryData>) -> Option<Self> {
+        Some(item.clone())
+    }
+}
+
+pub(super) struct ForestRenderPlugin;
+
+impl Plugin for ForestRenderPlugin {
+    fn build(&self, app: &mut App) {
+        load_internal_asset!(app, INSTANCING_SHADER, "instancing.wgsl", Shader::from_wgsl);
+        app.add_plugins(ExtractComponentPlugin::<ForestInstances>::default());
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app
+            .add_render_command::<Transparent3d, DrawForest>()
+            .init_resource::<SpecializedMeshPipelines<ForestPipeline>>()
+            .add_systems(
+                Render,
+                (
+                    queue_forest.in_set(RenderSet::QueueMeshes),
+                    prepare_instance_buffers.in_set(RenderSet::PrepareResources),
+                ),
+            );
+    }
+
+    fn finish(&self, app: &mut App) {
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app.init_resource::<ForestPipeline>();
+        }
+    }
+}
+
+#[derive(Component)]
+struct InstanceBuffer {
+    buffer: Buffer,
+    length: u32,
+}
+
+fn prepare_instance_buffers(
+    mut commands: Commands,
+    chunks: Query<(Entity, &ForestInstances), Changed<ForestInstances>>,
+    render_device: Res<RenderDevice>,
+) {
+    for (entity, instances) in &chunks {
+        let bytes: Vec<u8> = instances
+            .0
+            .iter()
+            .flat_map(|i| i.position_scale.iter().chain(&i.yaw_tint))
+            .flat_map(|f| f.to_ne_bytes())
+            .collect();
+        let buffer = render_device.create_buffer_with_data(&BufferInitDescriptor {
+            label: Some("forest instance buffer"),
+            contents: &bytes,
+            usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+        });
+        commands.entity(entity).insert(InstanceBuffer {
+            buffer,
+            length: instances.0.len() as u32,
+        });
+    }
+}
+
+fn queue_forest(
+    draw_functions: Res<DrawFunctions<Transparent3d>>,
+    forest_pipeline: Res<ForestPipeline>,
+    mut pipelines: ResMut<SpecializedMeshPipelines<ForestPipeline>>,
+    pipeline_cache: Res<PipelineCache>,
+    meshes: Res<RenderAssets<RenderMesh>>,
+    render_mesh_instances: Res<RenderMeshInstances>,
+    chunks: Query<(Entity, &MainEntity), With<ForestInstances>>,
+    mut phases: ResMut<ViewSortedRenderPhases<Transparent3d>>,
+    views: Query<(Entity, &ExtractedView, &Msaa)>,
+) {
+    let draw_forest = draw_functions.read().id::<DrawForest>();
+    for (view_entity, view, msaa) in &views {
+        let Some(phase) = phases.get_mut(&view_entity) else {
+            continue;
+        };
+        let view_key = MeshPipelineKey::from_msaa_samples(msaa.samples()) | MeshPipelineKey::from_hdr(view.hdr);
+        let rangefinder = view.rangefinder3d();
+        // Only visible chunks have mesh instances, so this is the cull
+        for (entity, main_entity) in &chunks {
+            let Some(mesh_instance) = render_mesh_instances.render_mesh_queue_data(*main_entity) else {
+                continue;
+            };
+            let Some(mesh) = meshes.get(mesh_instance.mesh_asset_id) else {
+                continue;
+            };
+            let key = view_key | MeshPipelineKey::from_primitive_topology(mesh.primitive_topology());
+            let pipeline = match pipelines.specialize(&pipeline_cache, &forest_pipeline, key, &mesh.layout) {
+                Ok(pipeline) => pipeline,
+                Err(e) => {
+                    error_once!("Forest pipeline failed to specialize: {}", e);
+                    continue;
+                }
+            };
+            phase.add(Transparent3d {
+                entity: (entity, *main_entity),
+                pipeline,
+                draw_function: draw_forest,
+                distance: rangefinder.distance_translation(&mesh_instance.translation),
+                batch_range: 0..1,
+                extra_index: PhaseItemExtraIndex::NONE,
+            });
+        }
+    }
+}
+
+#[derive(Resource)]
+struct ForestPipeline {
+    mesh_pipeline: MeshPipeline,
+}
+
+impl FromWorld for ForestPipeline {
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            mesh_pipeline: world.resource::<MeshPipeline>().clone(),
+        }
+    }
+}
+
+impl SpecializedMeshPipeline for ForestPipeline {
+    type Key = MeshPipelineKey;
+
+    fn specialize(
+        &self,
+        key: Self::Key,
+        layout: &MeshVertexBufferLayoutRef,
+    ) -> Result<RenderPipelineDescriptor, SpecializedMeshPipelineError> {
+        let mut descriptor = self.mesh_pipeline.specialize(key, layout)?;
+        descriptor.label = Some("forest_instanced_pipeline".into());
+        descriptor.vertex.shader = INSTANCING_SHADER;
+        descriptor.vertex.buffers.push(VertexBufferLayout {
+            array_stride: TreeGpuInstance::SIZE,
+            step_mode: VertexStepMode::Instance,
+            attributes: vec![
+                VertexAttribute {
+                    format: VertexFormat::Float32x4,
+                    offset: 0,
+                    shader_location: 8,
+                },
+                VertexAttribute {
+                    format: VertexFormat::Float32x4,
+                    offset: VertexFormat::Float32x4.size(),
+                    shader_location: 9,
+                },
+            ],
+        });
+        if let Some(fragment) = descriptor.fragment.as_mut() {
+            fragment.shader = INSTANCING_SHADER;
+        }
+        Ok(descriptor)
+    }
+}
+
+type DrawForest = (SetItemPipeline, SetMeshViewBindGroup<0>, SetMeshBindGroup<1>, DrawMeshInstanced);
+
+struct DrawMeshInstanced;
+
+impl<P: PhaseItem> RenderCommand<P> for DrawMeshInstanced {
+    type Param = (SRes<RenderAssets<RenderMesh>>, SRes<RenderMeshInstances>, SRes<MeshAllocator>);
+    type ViewQuery = ();
+    type ItemQuery = Read<InstanceBuffer>;
+
+    fn render<'w>(
+        item: &P,
+        _view: (),
+        instance_buffer: Option<&'w InstanceBuffer>,
+        (meshes, render_mesh_instances, mesh_allocator): SystemParamItem<'w, '_, Self::Param>,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let mesh_allocator = mesh_allocator.into_inner();
+        let Some(mesh_instance) = render_mesh_instances.render_mesh_queue_data(item.main_entity()) else {
+            return RenderCommandResult::Skip;
+        };
+        let Some(gpu_mesh) = meshes.into_inner().get(mesh_instance.mesh_asset_id) else {
+            return RenderCommandResult::Skip;
+        };
+        let Some(instance_buffer) = instance_buffer else {
+            return RenderCommandResult::Skip;
+        };
+        let Some(vertices) = mesh_allocator.mesh_vertex_slice(&mesh_instance.mesh_asset_id) else {
+            return RenderCommandResult::Skip;
+        };
+
+        pass.set_vertex_buffer(0, vertices.buffer.slice(..));
+        pass.set_vertex_buffer(1, instance_buffer.buffer.slice(..));
+        match &gpu_mesh.buffer_info {
+            RenderMeshBufferInfo::Indexed { index_format, count } => {
+                let Some(indices) = mesh_allocator.mesh_index_slice(&mesh_instance.mesh_asset_id) else {
+                    return RenderCommandResult::Skip;
+                };
+                pass.set_index_buffer(indices.buffer.slice(..), 0, *index_format);
+                pass.draw_indexed(
+                    indices.range.start..(indices.range.start + count),
+                    vertices.range.start as i32,
+                    0..instance_buffer.length,
+                );
+            }
+            RenderMeshBufferInfo::NonIndexed => {
+                pass.draw(vertices.range, 0..instance_buffer.length);
+            }
+        }
+        RenderCommandResult::Success
+    }
+}
@@ -0,0 +1,501 @@
+//! The renderer's frame graph for debugging: which passes run in what order,
+//! the resources they read and write and the barriers between them. The
+//! active [`RendererBackend`](crate::renderer_backend::RendererBackend)
+//! describes its graph, [`FrameGraph::validate`] checks it for missing or
+//! wrong barriers, and `framegraph dump` writes a captured frame as a
+//! Graphviz file (`dot -Tsvg frame_graph_<frame>.dot -o graph.svg`).
+
+use std::fmt::Write as _;
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+
+use crate::console::{
+    ArgCompletion, CommandPermission, ConsoleAppExt, ConsoleCommand, ConsoleCommandEvent, ConsoleOutputEvent,
+};
+use crate::renderer_backend::ActiveRenderer;
+
+#[cfg(feature = "inspector")]
+mod window;
+#[cfg(feature = "inspector")]
+pub use window::*;
+
+/// What a pass needs a resource to be in, or a barrier moves it between.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ResourceState {
+    /// Nothing in it worth keeping, as at the start of a frame.
+    Undefined,
+    ColorTarget,
+    DepthTarget,
+    DepthRead,
+    ShaderRead,
+    Storage,
+    CopySrc,
+    CopyDst,
+    Present,
+    /// Read or written, for renderers that don't say how.
+    Read,
+    Write,
+}
+
+impl ResourceState {
+    pub fn writes(self) -> bool {
+        matches!(
+            self,
+            ResourceState::ColorTarget
+                | ResourceState::DepthTarget
+                | ResourceState::Storage
+                | ResourceState::CopyDst
+                | ResourceState::Write
+        )
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ResourceState::Undefined => "undefined",
+            ResourceState::ColorTarget => "color target",
+            ResourceState::DepthTarget => "depth target",
+            ResourceState::DepthRead => "depth read",
+            ResourceState::ShaderRead => "shader read",
+            ResourceState::Storage => "storage",
+            ResourceState::CopySrc => "copy source",
+            ResourceState::CopyDst => "copy destination",
+            ResourceState::Present => "present",
+            ResourceState::Read => "read",
+            ResourceState::Write => "write",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FrameResource {
+    pub name: String,
+    /// Texture, buffer, ... as the renderer calls it.
+    pub kind: String,
+    /// What it's in when the frame starts: `Undefined` for transient
+    /// resources, e.g. `Present` for the swapchain image.
+    pub initial_state: ResourceState,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ResourceUse {
+    /// Index into [`FrameGraph::resources`].
+    pub resource: usize,
+    pub state: ResourceState,
+}
+
+#[derive(Debug, Clone)]
+pub struct FramePass {
+    pub name: String,
+    /// The (sub)graph it belongs to, e.g. `Core3d`.
+    pub graph: String,
+    pub uses: Vec<ResourceUse>,
+    /// Passes that have to run first, as indices into [`FrameGraph::passes`].
+    pub after: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Barrier {
+    pub resource: usize,
+    /// Recorded just before this pass.
+    pub before_pass: usize,
+    pub from: ResourceState,
+    pub to: ResourceState,
+}
+
+/// One frame's graph, passes in the order they run.
+#[derive(Debug, Clone, Default)]
+pub struct FrameGraph {
+    pub frame: u64,
+    pub backend: String,
+    /// Whether the renderer records its own barriers. wgpu inserts them
+    /// itself, so its graph has none and only the pass order is checked.
+    pub explicit_barriers: bool,
+    pub passes: Vec<FramePass>,
+    pub resources: Vec<FrameResource>,
+    pub barriers: Vec<Barrier>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FrameGraphWarning {
+    pub pass: Option<usize>,
+    pub resource: Option<usize>,
+    pub message: String,
+}
+
+impl FrameGraph {
+    pub fn pass_name(&self, index: usize) -> &str {
+        self.passes.get(index).map_or("?", |pass| pass.name.as_str())
+    }
+
+    pub fn resource_name(&self, index: usize) -> &str {
+        self.resources.get(index).map_or("?", |resource| resource.name.as_str())
+    }
+
+    /// Barriers recorded just before `pass`.
+    pub fn barriers_before(&self, pass: usize) -> impl Iterator<Item = &Barrier> {
+        self.barriers.iter().filter(move |barrier| barrier.before_pass == pass)
+    }
+
+    /// Walks the passes in order, following each resource's state through
+    /// the barriers, and reports every use that doesn't match.
+    pub fn validate(&self) -> Vec<FrameGraphWarning> {
+        let mut warnings = Vec::new();
+        let mut warn = |pass: Option<usize>, resource: Option<usize>, message: String| {
+            warnings.push(FrameGraphWarning { pass, resource, message });
+        };
+
+        for (index, pass) in self.passes.iter().enumerate() {
+            for &dependency in &pass.after {
+                if dependency >= index {
+                    warn(
+                        Some(index),
+                        None,
+                        format!("{} runs before {}, which it depends on", pass.name, self.pass_name(dependency)),
+                    );
+                }
+            }
+            for resource_use in &pass.uses {
+                if resource_use.resource >= self.resources.len() {
+                    let message =
+                        format!("{} uses resource #{}, which isn't in the graph", pass.name, resource_use.resource);
+                    warn(Some(index), None, message);
+                }
+            }
+        }
+        for barrier in &self.barriers {
+            if barrier.resource >= self.resources.len() || barrier.before_pass >= self.passes.len() {
+                let message = format!(
+                    "barrier on resource #{} before pass #{} is out of range",
+                    barrier.resource, barrier.before_pass
+                );
+                warn(None, None, message);
+            }
+        }
+
+        let mut states: Vec<ResourceState> = self.resources.iter().map(|resource| resource.initial_state).collect();
+        // The pass that last wrote each resource, until a barrier makes the write visible
+        let mut unsynced_writer: Vec<Option<usize>> = vec![None; self.resources.len()];
+
+        for (index, pass) in self.passes.iter().enumerate() {
+            if self.explicit_barriers {
+                for barrier in self.barriers_before(index) {
+                    let Some(state) = states.get_mut(barrier.resource) else {
+                        continue;
+                    };
+                    let name = self.resource_name(barrier.resource);
+                    // Transitions from undefined throw the contents away, whatever they were
+                    if barrier.from != ResourceState::Undefined && barrier.from != *state {
+                        warn(
+                            Some(index),
+                            Some(barrier.resource),
+                            format!(
+                                "barrier before {} moves {} from {}, but it's in {}",
+                                pass.name,
+                                name,
+                                barrier.from.label(),
+                                state.label()
+                            ),
+                        );
+                    } else if barrier.from == barrier.to && !barrier.to.writes() {
+                        warn(
+                            Some(index),
+                            Some(barrier.resource),
+                            format!("barrier before {} on {} doesn't change anything", pass.name, name),
+                        );
+                    }
+                    *state = barrier.to;
+                    unsynced_writer[barrier.resource] = None;
+                }
+            }
+
+            for resource_use in &pass.uses {
+                let Some(&state) = states.get(resource_use.resource) else {
+                    continue;
+                };
+                let name = self.resource_name(resource_use.resource);
+                if state == ResourceState::Undefined && !resource_use.state.writes() {
+                    warn(
+                        Some(index),
+                        Some(resource_use.resource),
+                        format!("{} reads {} before anything writes it", pass.name, name),
+                    );
+                } else if self.explicit_barriers && state != resource_use.state {
+                    warn(
+                        Some(index),
+                        Some(resource_use.resource),
+                        format!(
+                            "missing barrier: {} uses {} as {}, but it's in {}",
+                            pass.name,
+                            name,
+                            resource_use.state.label(),
+                            state.label()
+                        ),
+                    );
+                } else if let Some(writer) = unsynced_writer[resource_use.resource].filter(|&writer| writer != index) {
+                    if self.explicit_barriers && state == ResourceState::Storage {
+                        warn(
+                            Some(index),
+                            Some(resource_use.resource),
+                            format!(
+                                "no barrier between {} writing {} and {} using it",
+                                self.pass_name(writer),
+                                name,
+                                pass.name
+                            ),
+                        );
+                    }
+                }
+
+                if self.explicit_barriers {
+                    states[resource_use.resource] = resource_use.state;
+                } else if resource_use.state.writes() {
+                    // Without barriers only "has it been written" is tracked
+                    states[resource_use.resource] = resource_use.state;
+                }
+                if resource_use.state.writes() {
+                    unsynced_writer[resource_use.resource] = Some(index);
+                }
+            }
+        }
+        warnings
+    }
+
+    /// The graph in Graphviz's dot language: passes as boxes in their
+    /// (sub)graph's cluster, resources as ellipses, and warnings in red.
+    pub fn to_dot(&self, warnings: &[FrameGraphWarning]) -> String {
+        let flagged_pass = |index: usize| warnings.iter().any(|warning| warning.pass == Some(index));
+        let flagged_resource = |index: usize| warnings.iter().any(|warning| warning.resource == Some(index));
+
+        let mut dot = String::new();
+        let _ = writeln!(dot, "digraph frame_graph {{");
+        let _ = writeln!(dot, "  label={};", quote(&format!("Frame {} ({})", self.frame, self.backend)));
+        let _ = writeln!(dot, "  rankdir=LR;");
+        let _ = writeln!(dot, "  node [fontname=\"Helvetica\", fontsize=10];");
+        let _ = writeln!(dot, "  edge [fontname=\"Helvetica\", fontsize=9];");
+
+        let mut graphs: Vec<&str> = Vec::new();
+        for pass in &self.passes {
+            if !graphs.contains(&pass.graph.as_str()) {
+                graphs.push(&pass.graph);
+            }
+        }
+        for (cluster, graph) in graphs.iter().enumerate() {
+            let _ = writeln!(dot, "  subgraph cluster_{} {{", cluster);
+            let _ = writeln!(dot, "    label={};", quote(graph));
+            for (index, pass) in self.passes.iter().enumerate().filter(|(_, pass)| pass.graph == *graph) {
+                let color = if flagged_pass(index) { "red" } else { "black" };
+                let _ = writeln!(dot, "    p{} [shape=box, color={}, label={}];", index, color, quote(&pass.name));
+            }
+            let _ = writeln!(dot, "  }}");
+        }
+        for (index, resource) in self.resources.iter().enumerate() {
+            let color = if flagged_resource(index) { "red" } else { "gray40" };
+            let label = format!("{}\n{}", resource.name, resource.kind);
+            let _ = writeln!(dot, "  r{} [shape=ellipse, color={}, label={}];", index, color, quote(&label));
+        }
+
+        for (index, pass) in self.passes.iter().enumerate() {
+            for &dependency in &pass.after {
+                let _ = writeln!(dot, "  p{} -> p{} [style=dashed, color=gray60];", dependency, index);
+            }
+            for resource_use in &pass.uses {
+                let label = quote(resource_use.state.label());
+                if resource_use.state.writes() {
+                    let _ = writeln!(dot, "  p{} -> r{} [label={}];", index, resource_use.resource, label);
+                } else {
+                    let _ = writeln!(dot, "  r{} -> p{} [label={}];", resource_use.resource, index, label);
+                }
+            }
+        }
+        for barrier in &self.barriers {
+            let label = format!("{} -> {}", barrier.from.label(), barrier.to.label());
+            let _ = writeln!(
+                dot,
+                "  r{} -> p{} [style=dotted, color=blue, label={}];",
+                barrier.resource,
+                barrier.before_pass,
+                quote(&label)
+            );
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n"))
+}
+
+/// A frame graph and what's wrong with it.
+#[derive(Debug, Clone)]
+pub struct FrameGraphCapture {
+    pub graph: FrameGraph,
+    pub warnings: Vec<FrameGraphWarning>,
+}
+
+impl FrameGraphCapture {
+    pub fn new(graph: FrameGraph) -> Self {
+        let warnings = graph.validate();
+        Self { graph, warnings }
+    }
+
+    pub fn summary(&self) -> String {
+        format!(
+            "Frame {} ({}): {} passes, {} resources, {} barriers, {} warning{}",
+            self.graph.frame,
+            self.graph.backend,
+            self.graph.passes.len(),
+            self.graph.resources.len(),
+            self.graph.barriers.len(),
+            self.warnings.len(),
+            if self.warnings.len() == 1 { "" } else { "s" }
+        )
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct FrameGraphDebug {
+    /// Refresh `latest` every frame; set while the window is open.
+    pub live: bool,
+    pub latest: Option<FrameGraphCapture>,
+    /// The frame kept for inspection and dumping.
+    pub captured: Option<FrameGraphCapture>,
+    pub export_directory: PathBuf,
+}
+
+impl Default for FrameGraphDebug {
+    fn default() -> Self {
+        Self {
+            live: false,
+            latest: None,
+            captured: None,
+            export_directory: PathBuf::from("profiling"),
+        }
+    }
+}
+
+/// Keeps the next frame's graph in [`FrameGraphDebug::captured`].
+#[derive(Event, Debug, Clone, Copy, Default)]
+pub struct CaptureFrameGraphEvent;
+
+/// Writes the captured frame to `frame_graph_<frame>.dot` in the export directory.
+#[derive(Event, Debug, Clone, Copy, Default)]
+pub struct DumpFrameGraphEvent;
+
+pub struct FrameGraphPlugin;
+
+impl Plugin for FrameGraphPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FrameGraphDebug>()
+            .add_event::<CaptureFrameGraphEvent>()
+            .add_event::<DumpFrameGraphEvent>()
+            .add_console_command(
+                ConsoleCommand::new("framegraph", "Captures the renderer's frame graph and checks its barriers")
+                    .usage("[capture|dump|show]")
+                    .permission(CommandPermission::Dev)
+                    .complete(vec![ArgCompletion::Values(vec![
+                        "capture".to_string(),
+                        "dump".to_string(),
+                        "show".to_string(),
+                    ])]),
+            )
+            .add_systems(Update, frame_graph_command)
+            .add_systems(Last, (capture_frame_graph, dump_frame_graph).chain());
+
+        #[cfg(feature = "inspector")]
+        app.add_plugins(FrameGraphWindowPlugin);
+    }
+}
+
+fn frame_graph_command(
+    mut debug: ResMut<FrameGraphDebug>,
+    mut events: EventReader<ConsoleCommandEvent>,
+    mut output: EventWriter<ConsoleOutputEvent>,
+    mut capture: EventWriter<CaptureFrameGraphEvent>,
+    mut dump: EventWriter<DumpFrameGraphEvent>,
+) {
+    for event in events.read() {
+        if event.name != "framegraph" {
+            continue;
+        }
+        let result: Result<String, String> = match event.args.first().map(String::as_str) {
+            None => Ok(match &debug.captured {
+                Some(captured) => captured.summary(),
+                None => "No frame captured yet; try 'framegraph capture'".to_string(),
+            }),
+            Some("capture") => {
+                capture.send(CaptureFrameGraphEvent);
+                Ok("Capturing this frame's graph; the result goes to the log".to_string())
+            }
+            Some("dump") => {
+                capture.send(CaptureFrameGraphEvent);
+                dump.send(DumpFrameGraphEvent);
+                Ok(format!("Writing this frame's graph to {:?}", debug.export_directory))
+            }
+            Some("show") => show_window(&mut debug),
+            Some(other) => Err(format!("unknown option '{}'", other)),
+        };
+        output.send(match result {
+            Ok(text) => event.reply(text),
+            Err(text) => event.error(format!("{}: {}", event.name, text)),
+        });
+    }
+}
+
+#[cfg(feature = "inspector")]
+fn show_window(debug: &mut FrameGraphDebug) -> Result<String, String> {
+    debug.live = !debug.live;
+    Ok(if debug.live { "Frame graph window open" } else { "Frame graph window closed" }.to_string())
+}
+
+#[cfg(not(feature = "inspector"))]
+fn show_window(_debug: &mut FrameGraphDebug) -> Result<String, String> {
+    Err("this build has no debug windows (--features inspector)".to_string())
+}
+
+fn capture_frame_graph(world: &mut World) {
+    let requested = world.resource_mut::<Events<CaptureFrameGraphEvent>>().drain().count() > 0;
+    if !requested && !world.resource::<FrameGraphDebug>().live {
+        return;
+    }
+
+    let graph = world.get_resource::<ActiveRenderer>().and_then(|renderer| renderer.frame_graph(world));
+    let capture = graph.map(FrameGraphCapture::new);
+    if requested {
+        match &capture {
+            Some(capture) => {
+                info!("{}", capture.summary());
+                for warning in &capture.warnings {
+                    warn!("Frame graph: {}", warning.message);
+                }
+            }
+            None => warn!("The renderer didn't report a frame graph this frame"),
+        }
+    }
+
+    let mut debug = world.resource_mut::<FrameGraphDebug>();
+    if requested && capture.is_some() {
+        debug.captured = capture.clone();
+    }
+    debug.latest = capture;
+}
+
+fn dump_frame_graph(mut events: EventReader<DumpFrameGraphEvent>, frame_graph: Res<FrameGraphDebug>) {
+    if events.read().count() == 0 {
+        return;
+    }
+    let Some(captured) = &frame_graph.captured else {
+        warn!("No frame graph captured to dump");
+        return;
+    };
+    if let Err(e) = std::fs::create_dir_all(&frame_graph.export_directory) {
+        error!("Failed to create {:?}: {}", frame_graph.export_directory, e);
+        return;
+    }
+    let path = frame_graph.export_directory.join(format!("frame_graph_{}.dot", captured.graph.frame));
+    match std::fs::write(&path, captured.graph.to_dot(&captured.warnings)) {
+        Ok(()) => info!("Wrote the frame graph to {:?}", path),
+        Err(e) => error!("Failed to write {:?}: {}", path, e),
+    }
+}
@@ -0,0 +1,180 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts, EguiPlugin};
+
+use super::{CaptureFrameGraphEvent, DumpFrameGraphEvent, FrameGraphCapture, FrameGraphDebug};
+
+const WARNING_COLOR: egui::Color32 = egui::Color32::from_rgb(230, 90, 80);
+const BARRIER_COLOR: egui::Color32 = egui::Color32::from_rgb(110, 160, 230);
+
+#[derive(Resource, Debug, Default)]
+pub struct FrameGraphWindow {
+    /// Show the captured frame rather than the current one.
+    pub show_captured: bool,
+    /// Only list passes with warnings.
+    pub warnings_only: bool,
+}
+
+pub struct FrameGraphWindowPlugin;
+
+impl Plugin for FrameGraphWindowPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugins(EguiPlugin);
+        }
+        app.init_resource::<FrameGraphWindow>()
+            .add_systems(Update, frame_graph_ui.run_if(|debug: Res<FrameGraphDebug>| debug.live));
+    }
+}
+
+fn frame_graph_ui(
+    mut contexts: EguiContexts,
+    mut window: ResMut<FrameGraphWindow>,
+    mut debug: ResMut<FrameGraphDebug>,
+    mut capture: EventWriter<CaptureFrameGraphEvent>,
+    mut dump: EventWriter<DumpFrameGraphEvent>,
+) {
+    let ctx = contexts.ctx_mut().clone();
+    let window = &mut *window;
+    let mut open = debug.live;
+
+    egui::Window::new("Frame Graph")
+        .open(&mut open)
+        .default_size([640.0, 560.0])
+        .show(&ctx, |ui| {
+            ui.horizontal(|ui| {
+                if ui.button("Capture").clicked() {
+                    capture.send(CaptureFrameGraphEvent);
+                    window.show_captured = true;
+                }
+                let has_capture = debug.captured.is_some();
+                if ui.add_enabled(has_capture, egui::Button::new("Dump .dot")).clicked() {
+                    dump.send(DumpFrameGraphEvent);
+                }
+                ui.separator();
+                ui.selectable_value(&mut window.show_captured, false, "Live");
+                ui.add_enabled_ui(has_capture, |ui| {
+                    ui.selectable_value(&mut window.show_captured, true, "Captured");
+                });
+                ui.separator();
+                ui.checkbox(&mut window.warnings_only, "Warnings only");
+            });
+
+            let shown = if window.show_captured { &debug.captured } else { &debug.latest };
+            let Some(capture) = shown else {
+                ui.label("The renderer hasn't reported a frame graph; Atom doesn't hand its graph to Rust yet.");
+                return;
+            };
+            ui.label(capture.summary());
+            if !capture.graph.explicit_barriers {
+                ui.weak("This renderer places its own barriers; only pass order and reads are checked.");
+            }
+            ui.separator();
+
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                warnings_section(ui, capture);
+                passes_section(ui, capture, window.warnings_only);
+                resources_section(ui, capture);
+            });
+        });
+
+    debug.live = open;
+}
+
+fn warnings_section(ui: &mut egui::Ui, capture: &FrameGraphCapture) {
+    if capture.warnings.is_empty() {
+        ui.colored_label(egui::Color32::from_rgb(110, 200, 120), "No validation warnings");
+        return;
+    }
+    egui::CollapsingHeader::new(format!("Warnings ({})", capture.warnings.len()))
+        .id_salt("frame_graph_warnings")
+        .default_open(true)
+        .show(ui, |ui| {
+            for warning in &capture.warnings {
+                ui.colored_label(WARNING_COLOR, &warning.message);
+            }
+        });
+}
+
+fn passes_section(ui: &mut egui::Ui, capture: &FrameGraphCapture, warnings_only: bool) {
+    let graph = &capture.graph;
+    egui::CollapsingHeader::new(format!("Passes ({})", graph.passes.len()))
+        .id_salt("frame_graph_passes")
+        .default_open(true)
+        .show(ui, |ui| {
+            for (index, pass) in graph.passes.iter().enumerate() {
+                let flagged = capture.warnings.iter().any(|warning| warning.pass == Some(index));
+                if warnings_only && !flagged {
+                    continue;
+                }
+                let title = format!("{:3}  {}  [{}]", index, pass.name, pass.graph);
+                let title = if flagged {
+                    egui::RichText::new(title).color(WARNING_COLOR)
+                } else {
+                    egui::RichText::new(title)
+                };
+                egui::CollapsingHeader::new(title).id_salt(("frame_graph_pass", index)).show(ui, |ui| {
+                    for barrier in graph.barriers_before(index) {
+                        ui.colored_label(
+                            BARRIER_COLOR,
+                            format!(
+                                "barrier {}: {} -> {}",
+                                graph.resource_name(barrier.resource),
+                                barrier.from.label(),
+                                barrier.to.label()
+                            ),
+                        );
+                    }
+                    for resource_use in &pass.uses {
+                        let verb = if resource_use.state.writes() { "writes" } else { "reads" };
+                        ui.label(format!(
+                            "{} {} as {}",
+                            verb,
+                            graph.resource_name(resource_use.resource),
+                            resource_use.state.label()
+                        ));
+                    }
+                    if !pass.after.is_empty() {
+                        let after: Vec<&str> = pass.after.iter().map(|&before| graph.pass_name(before)).collect();
+                        ui.weak(format!("after {}", after.join(", ")));
+                    }
+                    for warning in capture.warnings.iter().filter(|warning| warning.pass == Some(index)) {
+                        ui.colored_label(WARNING_COLOR, &warning.message);
+                    }
+                });
+            }
+        });
+}
+
+fn resources_section(ui: &mut egui::Ui, capture: &FrameGraphCapture) {
+    let graph = &capture.graph;
+    if graph.resources.is_empty() {
+        return;
+    }
+    egui::CollapsingHeader::new(format!("Resources ({})", graph.resources.len()))
+        .id_salt("frame_graph_resources")
+        .show(ui, |ui| {
+            egui::Grid::new("frame_graph_resource_table").striped(true).show(ui, |ui| {
+                for header in ["Resource", "Kind", "Starts as", "Used by"] {
+                    ui.strong(header);
+                }
+                ui.end_row();
+                for (index, resource) in graph.resources.iter().enumerate() {
+                    let flagged = capture.warnings.iter().any(|warning| warning.resource == Some(index));
+                    if flagged {
+                        ui.colored_label(WARNING_COLOR, &resource.name);
+                    } else {
+                        ui.label(&resource.name);
+                    }
+                    ui.label(&resource.kind);
+                    ui.label(resource.initial_state.label());
+                    let users = graph
+                        .passes
+                        .iter()
+                        .filter(|pass| pass.uses.iter().any(|resource_use| resource_use.resource == index))
+                        .count();
+                    ui.label(users.to_string());
+                    ui.end_row();
+                }
+            });
+        });
+}
@@ -0,0 +1,151 @@
+//! `game_settings.toml`: the display and renderer options the launcher
+//! writes, read once before the window opens. They're the ones a player may
+//! need to change before the game can show anything at all (a black screen
+//! at a resolution the monitor won't take, a renderer the GPU can't run),
+//! so they live outside the in-game settings menu.
+//!
+//! Command-line flags and environment variables still win over the file.
+
+use bevy::pbr::DirectionalLightShadowMap;
+use bevy::prelude::*;
+use bevy::window::{MonitorSelection, PresentMode, WindowMode};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+pub const GAME_SETTINGS_FILE: &str = "game_settings.toml";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DisplayMode {
+    #[default]
+    Windowed,
+    Borderless,
+    Fullscreen,
+}
+
+/// Which renderer to start. `Auto` is Atom when the game was built with it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RendererChoice {
+    #[default]
+    Auto,
+    Atom,
+    Wgpu,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QualityPreset {
+    Low,
+    Medium,
+    #[default]
+    High,
+    Ultra,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DisplaySettings {
+    pub width: u32,
+    pub height: u32,
+    pub mode: DisplayMode,
+    pub vsync: bool,
+}
+
+impl Default for DisplaySettings {
+    fn default() -> Self {
+        Self {
+            width: 1920,
+            height: 1080,
+            mode: DisplayMode::Windowed,
+            vsync: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GraphicsSettings {
+    pub renderer: RendererChoice,
+    pub quality: QualityPreset,
+}
+
+/// Start without a window, as `--headless --ticks <ticks>` does.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HeadlessSettings {
+    pub enabled: bool,
+    pub ticks: u32,
+}
+
+impl Default for HeadlessSettings {
+    fn default() -> Self {
+        Self { enabled: false, ticks: 100 }
+    }
+}
+
+#[derive(Resource, Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameSettings {
+    pub display: DisplaySettings,
+    pub graphics: GraphicsSettings,
+    pub headless: HeadlessSettings,
+}
+
+impl GameSettings {
+    /// Reads `game_settings.toml` from the working directory. A missing
+    /// file is the defaults; a broken one is reported and ignored.
+    pub fn load() -> Self {
+        Self::load_from(Path::new(GAME_SETTINGS_FILE))
+    }
+
+    fn load_from(path: &Path) -> Self {
+        let Ok(text) = std::fs::read_to_string(path) else {
+            return Self::default();
+        };
+        match toml::from_str(&text) {
+            Ok(settings) => {
+                println!("  Settings: {}", path.display());
+                settings
+            }
+            Err(e) => {
+                eprintln!("Invalid {}: {} - using defaults", path.display(), e);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn window(&self) -> Window {
+        let display = &self.display;
+        Window {
+            title: "MMO Engine - AAA MMORPG".into(),
+            resolution: (display.width.max(640) as f32, display.height.max(360) as f32).into(),
+            mode: match display.mode {
+                DisplayMode::Windowed => WindowMode::Windowed,
+                DisplayMode::Borderless => WindowMode::BorderlessFullscreen(MonitorSelection::Current),
+                DisplayMode::Fullscreen => WindowMode::Fullscreen(MonitorSelection::Current),
+            },
+            present_mode: if display.vsync { PresentMode::AutoVsync } else { PresentMode::AutoNoVsync },
+            ..default()
+        }
+    }
+
+    /// Whether to start the Atom renderer in a build that has it.
+    pub fn use_atom(&self) -> bool {
+        self.graphics.renderer != RendererChoice::Wgpu
+    }
+
+    pub fn shadow_map(&self) -> DirectionalLightShadowMap {
+        let size = match self.graphics.quality {
+            QualityPreset::Low => 1024,
+            QualityPreset::Medium => 2048,
+            QualityPreset::High => 4096,
+            QualityPreset::Ultra => 8192,
+        };
+        DirectionalLightShadowMap { size }
+    }
+
+    pub fn sun_shadows(&self) -> bool {
+        self.graphics.quality > QualityPreset::Low
+    }
+}
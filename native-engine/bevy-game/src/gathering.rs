@@ -0,0 +1,465 @@
+//! Gathering and fishing nodes, kept by a respawn director. Node types and
+//! the zones they grow in live in `content/gathering/*.toml`:
+//!
+//! ```toml
+//! [[node_type]]
+//! id = "copper_vein"
+//! item = "copper_ore"
+//! yield = [2, 4]
+//! charges = [1, 3]
+//! respawn_seconds = 300
+//! respawn_jitter = 0.35
+//! relocate_chance = 0.6
+//!
+//! [[zone]]
+//! id = "copper_hills"
+//! node_type = "copper_vein"
+//! center = [410.0, 30.0, -220.0]
+//! radius = 80.0
+//! count = 6
+//! ```
+//!
+//! A depleted node hides and comes back after its respawn time, stretched
+//! or shortened by up to `respawn_jitter`, and usually somewhere else in
+//! its zone, so a bot can't park on a spawn point and wait for a timer.
+//! Players who keep harvesting the same kind of node in the same spot get
+//! less out of it each time until they move on or the window passes.
+//!
+//! Nodes are plain [`Interactable`]s; fishing pools are node types like
+//! any other. Models are attached by whatever renders the node type.
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::console::{CommandPermission, ConsoleAppExt, ConsoleCommand, ConsoleCommandEvent, ConsoleOutputEvent};
+use crate::content_packs::ContentPacks;
+use crate::interactables::{Interactable, UseObjectEvent};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeTypeDef {
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    pub item: String,
+    /// Items per harvest, inclusive.
+    #[serde(rename = "yield", default = "default_yield")]
+    pub yield_range: [u32; 2],
+    /// Harvests before the node depletes, inclusive.
+    #[serde(default = "default_charges")]
+    pub charges: [u32; 2],
+    #[serde(default = "default_respawn_seconds")]
+    pub respawn_seconds: f32,
+    /// Respawn time varies by up to this fraction either way.
+    #[serde(default = "default_respawn_jitter")]
+    pub respawn_jitter: f32,
+    /// Chance a depleted node comes back somewhere else in its zone.
+    #[serde(default = "default_relocate_chance")]
+    pub relocate_chance: f32,
+    /// Harvests of this type in one spot before yields start dropping.
+    #[serde(default = "default_free_harvests")]
+    pub free_harvests: u32,
+    /// Yield multiplier per harvest past `free_harvests`.
+    #[serde(default = "default_diminish_factor")]
+    pub diminish_factor: f32,
+    /// Lowest the yield multiplier goes.
+    #[serde(default = "default_min_yield_multiplier")]
+    pub min_yield_multiplier: f32,
+    #[serde(default = "default_interact_range")]
+    pub range: f32,
+}
+
+fn default_yield() -> [u32; 2] {
+    [1, 1]
+}
+
+fn default_charges() -> [u32; 2] {
+    [1, 1]
+}
+
+fn default_respawn_seconds() -> f32 {
+    300.0
+}
+
+fn default_respawn_jitter() -> f32 {
+    0.3
+}
+
+fn default_relocate_chance() -> f32 {
+    0.5
+}
+
+fn default_free_harvests() -> u32 {
+    3
+}
+
+fn default_diminish_factor() -> f32 {
+    0.7
+}
+
+fn default_min_yield_multiplier() -> f32 {
+    0.1
+}
+
+fn default_interact_range() -> f32 {
+    3.0
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GatherZoneDef {
+    pub id: String,
+    pub node_type: String,
+    pub center: Vec3,
+    pub radius: f32,
+    /// Nodes kept in the zone.
+    pub count: u32,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GatheringContentFile {
+    #[serde(default, rename = "node_type")]
+    pub node_types: Vec<NodeTypeDef>,
+    #[serde(default, rename = "zone")]
+    pub zones: Vec<GatherZoneDef>,
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct GatheringConfig {
+    pub content_directory: PathBuf,
+    /// Harvests within this of each other count as the same spot.
+    pub spot_radius: f32,
+    /// Seconds a harvest counts towards diminishing yields.
+    pub diminish_window_seconds: f32,
+    /// A relocated node lands at least this far from where it was, when
+    /// the zone is big enough.
+    pub min_relocate_distance: f32,
+}
+
+impl Default for GatheringConfig {
+    fn default() -> Self {
+        Self {
+            content_directory: PathBuf::from("content").join("gathering"),
+            spot_radius: 25.0,
+            diminish_window_seconds: 1800.0,
+            min_relocate_distance: 15.0,
+        }
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct GatheringContent {
+    pub node_types: HashMap<String, NodeTypeDef>,
+    pub zones: Vec<GatherZoneDef>,
+}
+
+#[derive(Component, Debug, Clone)]
+pub struct GatherNode {
+    pub node_type: String,
+    pub zone: String,
+    pub charges: u32,
+    /// Elapsed seconds it respawns at, while depleted.
+    pub respawns_at: Option<f32>,
+}
+
+#[derive(Debug, Clone)]
+struct Harvest {
+    node_type: String,
+    position: Vec3,
+    at: f32,
+}
+
+/// Recent harvests per character, for diminishing yields.
+#[derive(Component, Debug, Clone, Default)]
+pub struct HarvestHistory {
+    harvests: Vec<Harvest>,
+}
+
+impl HarvestHistory {
+    /// Yield multiplier for harvesting `node_type` at `position` now.
+    pub fn multiplier(&self, node_type: &NodeTypeDef, position: Vec3, spot_radius: f32) -> f32 {
+        let repeats = self
+            .harvests
+            .iter()
+            .filter(|h| h.node_type == node_type.id && h.position.distance(position) <= spot_radius)
+            .count() as u32;
+        let over = repeats.saturating_sub(node_type.free_harvests);
+        node_type.diminish_factor.powi(over as i32).max(node_type.min_yield_multiplier)
+    }
+
+    fn record(&mut self, node_type: &str, position: Vec3, now: f32, window: f32) {
+        self.harvests.retain(|h| now - h.at < window);
+        self.harvests.push(Harvest {
+            node_type: node_type.to_string(),
+            position,
+            at: now,
+        });
+    }
+}
+
+/// Items from a harvest; the inventory adds them. `multiplier` is below 1
+/// when diminishing yields cut it down.
+#[derive(Event, Debug, Clone)]
+pub struct GatherYieldEvent {
+    pub actor: Entity,
+    pub node: Entity,
+    pub item: String,
+    pub count: u32,
+    pub multiplier: f32,
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct NodeDepletedEvent {
+    pub node: Entity,
+    pub node_type: String,
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct NodeRespawnedEvent {
+    pub node: Entity,
+    pub node_type: String,
+    pub relocated: bool,
+}
+
+pub struct GatheringPlugin;
+
+impl Plugin for GatheringPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GatheringConfig>()
+            .init_resource::<GatheringContent>()
+            .add_event::<UseObjectEvent>()
+            .add_event::<GatherYieldEvent>()
+            .add_event::<NodeDepletedEvent>()
+            .add_event::<NodeRespawnedEvent>()
+            .add_console_command(
+                ConsoleCommand::new("gathering", "Shows live and depleted gathering nodes per zone")
+                    .permission(CommandPermission::Dev),
+            )
+            .add_systems(Startup, (load_gathering, populate_zones).chain())
+            .add_systems(Update, (harvest_nodes, respawn_nodes, gathering_command).chain());
+    }
+}
+
+fn load_gathering(config: Res<GatheringConfig>, packs: Res<ContentPacks>, mut content: ResMut<GatheringContent>) {
+    for path in packs.files(&config.content_directory, "toml") {
+        let parsed = std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| toml::from_str::<GatheringContentFile>(&text).map_err(|e| e.to_string()));
+
+        match parsed {
+            Ok(file) => {
+                for node_type in file.node_types {
+                    content.node_types.insert(node_type.id.clone(), node_type);
+                }
+                content.zones.extend(file.zones);
+            }
+            Err(e) => warn!("Invalid gathering file {:?}: {}", path, e),
+        }
+    }
+    let node_types = &content.node_types;
+    for zone in content.zones.iter().filter(|z| !node_types.contains_key(&z.node_type)) {
+        warn!("Gathering zone '{}' uses unknown node type '{}'", zone.id, zone.node_type);
+    }
+
+    info!("Gathering: {} node types in {} zones loaded", content.node_types.len(), content.zones.len());
+}
+
+fn roll(rng: &mut impl Rng, range: [u32; 2]) -> u32 {
+    rng.gen_range(range[0].min(range[1])..=range[0].max(range[1]))
+}
+
+/// A random point in `zone`, at least `avoid_distance` from `avoid` when
+/// a few tries find one.
+fn zone_point(rng: &mut impl Rng, zone: &GatherZoneDef, avoid: Option<Vec3>, avoid_distance: f32) -> Vec3 {
+    let mut point = zone.center;
+    for _ in 0..8 {
+        let angle = rng.gen_range(0.0..std::f32::consts::TAU);
+        // sqrt keeps points even across the disc instead of bunched in the middle
+        let distance = zone.radius * rng.gen_range(0.0f32..1.0).sqrt();
+        point = zone.center + Vec3::new(angle.cos(), 0.0, angle.sin()) * distance;
+        if avoid.is_none_or(|avoid| avoid.with_y(0.0).distance(point.with_y(0.0)) >= avoid_distance) {
+            break;
+        }
+    }
+    point
+}
+
+/// Drops `point` onto whatever is below it, or leaves it at the zone's
+/// height when there's nothing to hit.
+fn on_ground(rapier_context: &ReadRapierContext, point: Vec3) -> Vec3 {
+    let Ok(context) = rapier_context.single() else {
+        return point;
+    };
+    let filter = QueryFilter::default().exclude_sensors().exclude_dynamic();
+    context
+        .cast_ray(point + Vec3::Y * 50.0, Vec3::NEG_Y, 500.0, true, filter)
+        .map_or(point, |(_, toi)| point.with_y(point.y + 50.0 - toi))
+}
+
+fn populate_zones(mut commands: Commands, content: Res<GatheringContent>, rapier_context: ReadRapierContext) {
+    let mut rng = rand::thread_rng();
+    let mut spawned = 0;
+    for zone in &content.zones {
+        let Some(node_type) = content.node_types.get(&zone.node_type) else {
+            continue;
+        };
+        for _ in 0..zone.count {
+            let position = on_ground(&rapier_context, zone_point(&mut rng, zone, None, 0.0));
+            commands.spawn((
+                GatherNode {
+                    node_type: node_type.id.clone(),
+                    zone: zone.id.clone(),
+                    charges: roll(&mut rng, node_type.charges),
+                    respawns_at: None,
+                },
+                Interactable { range: node_type.range },
+                Transform::from_translation(position),
+                Visibility::Inherited,
+                Name::new(if node_type.name.is_empty() { node_type.id.clone() } else { node_type.name.clone() }),
+            ));
+            spawned += 1;
+        }
+    }
+    info!("Gathering: {} nodes placed", spawned);
+}
+
+fn harvest_nodes(
+    mut commands: Commands,
+    time: Res<Time>,
+    (config, content): (Res<GatheringConfig>, Res<GatheringContent>),
+    mut uses: EventReader<UseObjectEvent>,
+    mut nodes: Query<(&mut GatherNode, &Transform, &mut Visibility)>,
+    mut histories: Query<&mut HarvestHistory>,
+    (mut yields, mut depleted): (EventWriter<GatherYieldEvent>, EventWriter<NodeDepletedEvent>),
+) {
+    let mut rng = rand::thread_rng();
+    let now = time.elapsed_secs();
+    for event in uses.read() {
+        let Ok((mut node, transform, mut visibility)) = nodes.get_mut(event.target) else {
+            continue;
+        };
+        let Some(node_type) = content.node_types.get(&node.node_type) else {
+            continue;
+        };
+        if node.respawns_at.is_some() || node.charges == 0 {
+            continue;
+        }
+
+        let position = transform.translation;
+        let multiplier = match histories.get_mut(event.actor) {
+            Ok(mut history) => {
+                let multiplier = history.multiplier(node_type, position, config.spot_radius);
+                history.record(&node_type.id, position, now, config.diminish_window_seconds);
+                multiplier
+            }
+            Err(_) => {
+                let mut history = HarvestHistory::default();
+                history.record(&node_type.id, position, now, config.diminish_window_seconds);
+                commands.entity(event.actor).insert(history);
+                1.0
+            }
+        };
+        // Round stochastically so small yields still diminish on average
+        let scaled = roll(&mut rng, node_type.yield_range) as f32 * multiplier;
+        let count = scaled.floor() as u32 + u32::from(rng.gen::<f32>() < scaled.fract());
+        if count > 0 {
+            yields.send(GatherYieldEvent {
+                actor: event.actor,
+                node: event.target,
+                item: node_type.item.clone(),
+                count,
+                multiplier,
+            });
+        }
+
+        node.charges -= 1;
+        if node.charges == 0 {
+            let jitter = node_type.respawn_jitter.clamp(0.0, 1.0);
+            let delay = node_type.respawn_seconds * (1.0 + rng.gen_range(-jitter..=jitter));
+            node.respawns_at = Some(now + delay.max(1.0));
+            *visibility = Visibility::Hidden;
+            // Out of reach too, so it doesn't shadow a live node next to it
+            commands.entity(event.target).remove::<Interactable>();
+            depleted.send(NodeDepletedEvent {
+                node: event.target,
+                node_type: node_type.id.clone(),
+            });
+        }
+    }
+}
+
+fn respawn_nodes(
+    mut commands: Commands,
+    time: Res<Time>,
+    config: Res<GatheringConfig>,
+    content: Res<GatheringContent>,
+    rapier_context: ReadRapierContext,
+    mut nodes: Query<(Entity, &mut GatherNode, &mut Transform, &mut Visibility)>,
+    mut respawned: EventWriter<NodeRespawnedEvent>,
+) {
+    let now = time.elapsed_secs();
+    let due = |node: &GatherNode| node.respawns_at.is_some_and(|at| now >= at);
+    if !nodes.iter().any(|(_, node, _, _)| due(node)) {
+        return;
+    }
+    let mut rng = rand::thread_rng();
+    for (entity, mut node, mut transform, mut visibility) in &mut nodes {
+        if !due(&node) {
+            continue;
+        }
+        let (Some(node_type), Some(zone)) = (
+            content.node_types.get(&node.node_type),
+            content.zones.iter().find(|z| z.id == node.zone),
+        ) else {
+            // Content for it is gone; don't bring it back
+            commands.entity(entity).despawn_recursive();
+            continue;
+        };
+        let relocated = rng.gen::<f32>() < node_type.relocate_chance;
+        if relocated {
+            let point = zone_point(&mut rng, zone, Some(transform.translation), config.min_relocate_distance);
+            transform.translation = on_ground(&rapier_context, point);
+        }
+        node.charges = roll(&mut rng, node_type.charges);
+        node.respawns_at = None;
+        *visibility = Visibility::Inherited;
+        commands.entity(entity).insert(Interactable { range: node_type.range });
+        respawned.send(NodeRespawnedEvent {
+            node: entity,
+            node_type: node_type.id.clone(),
+            relocated,
+        });
+    }
+}
+
+fn gathering_command(
+    time: Res<Time>,
+    content: Res<GatheringContent>,
+    nodes: Query<&GatherNode>,
+    mut events: EventReader<ConsoleCommandEvent>,
+    mut output: EventWriter<ConsoleOutputEvent>,
+) {
+    for event in events.read() {
+        if event.name != "gathering" {
+            continue;
+        }
+        let now = time.elapsed_secs();
+        let mut lines = vec![format!("{} zones", content.zones.len())];
+        for zone in &content.zones {
+            let in_zone: Vec<&GatherNode> = nodes.iter().filter(|n| n.zone == zone.id).collect();
+            let depleted: Vec<f32> = in_zone.iter().filter_map(|n| n.respawns_at).map(|at| at - now).collect();
+            let next = depleted.iter().copied().reduce(f32::min);
+            lines.push(format!(
+                "  {} ({}): {} live, {} depleted{}",
+                zone.id,
+                zone.node_type,
+                in_zone.len() - depleted.len(),
+                depleted.len(),
+                next.map_or(String::new(), |s| format!(", next back in {:.0}s", s.max(0.0)))
+            ));
+        }
+        output.send(event.reply(lines.join("\n")));
+    }
+}
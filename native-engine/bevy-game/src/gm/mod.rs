@@ -0,0 +1,311 @@
+//! GM tools for admin accounts: finding, visiting and summoning players,
+//! granting items, muting and kicking, spawning monsters and overriding the
+//! time and weather. The panel ([`GmUiPlugin`]) and the `gm` console
+//! command both send [`GmActionRequest`]s.
+//!
+//! The client applies what it can see (moving characters, spawn, item,
+//! time and wind requests) and records every action in [`GmAuditOutbox`],
+//! for the match socket to send with [`GM_AUDIT_OP_CODE`]. The server checks
+//! the account's admin flag, applies what only it can (mutes, kicks,
+//! moving players on other clients) and keeps the audit log. The panel only
+//! opens once the server has raised the local console permission to
+//! `Admin`.
+
+mod ui;
+
+pub use ui::*;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+use crate::console::{
+    ArgCompletion, CommandPermission, ConsoleAppExt, ConsoleCommand, ConsoleCommandEvent, ConsoleGiveItemRequest,
+    ConsoleOutputEvent, ConsoleSpawnRequest, SetTimeOfDayRequest,
+};
+use crate::mount_environment::WeatherWind;
+use crate::{Character, Player};
+
+/// Match data op code for [`GmAuditEntry`] messages.
+pub const GM_AUDIT_OP_CODE: i64 = 21;
+
+/// Largest stack or pack one action hands out.
+const MAX_COUNT: u32 = 100;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum GmAction {
+    /// Moves the GM to a player.
+    Goto { player: String },
+    /// Moves a player to the GM.
+    Summon { player: String },
+    GrantItem { player: String, item: String, count: u32 },
+    Mute { player: String, minutes: u32, reason: String },
+    Kick { player: String, reason: String },
+    Spawn { monster: String, level: Option<u32>, count: u32 },
+    SetTime { hour: f32 },
+    /// Wind towards `direction_degrees` (0 is +x, 90 is +z) at `speed` m/s.
+    SetWind { direction_degrees: f32, speed: f32 },
+}
+
+impl GmAction {
+    /// The player the action is aimed at, if any.
+    pub fn player(&self) -> Option<&str> {
+        match self {
+            GmAction::Goto { player }
+            | GmAction::Summon { player }
+            | GmAction::GrantItem { player, .. }
+            | GmAction::Mute { player, .. }
+            | GmAction::Kick { player, .. } => Some(player),
+            GmAction::Spawn { .. } | GmAction::SetTime { .. } | GmAction::SetWind { .. } => None,
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            GmAction::Goto { player } => format!("went to {}", player),
+            GmAction::Summon { player } => format!("summoned {}", player),
+            GmAction::GrantItem { player, item, count } => format!("gave {} {} x{}", player, item, count),
+            GmAction::Mute { player, minutes, reason } => format!("muted {} for {}m: {}", player, minutes, reason),
+            GmAction::Kick { player, reason } => format!("kicked {}: {}", player, reason),
+            GmAction::Spawn { monster, level, count } => match level {
+                Some(level) => format!("spawned {} x{} at level {}", monster, count, level),
+                None => format!("spawned {} x{}", monster, count),
+            },
+            GmAction::SetTime { hour } => format!("set the time to {:.1}h", hour),
+            GmAction::SetWind { direction_degrees, speed } => {
+                format!("set the wind to {:.1} m/s towards {:.0}°", speed, direction_degrees)
+            }
+        }
+    }
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct GmActionRequest(pub GmAction);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GmAuditEntry {
+    /// Unix seconds.
+    pub at: u64,
+    /// Character name of the GM.
+    pub gm: String,
+    pub action: GmAction,
+}
+
+/// Audit entries for the match socket to send, oldest first.
+#[derive(Resource, Debug, Default)]
+pub struct GmAuditOutbox {
+    pending: Vec<GmAuditEntry>,
+}
+
+impl GmAuditOutbox {
+    pub fn drain(&mut self) -> Vec<GmAuditEntry> {
+        std::mem::take(&mut self.pending)
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+/// This session's GM actions, newest last, for the panel.
+#[derive(Resource, Debug)]
+pub struct GmAuditLog {
+    pub entries: VecDeque<GmAuditEntry>,
+    pub capacity: usize,
+}
+
+impl Default for GmAuditLog {
+    fn default() -> Self {
+        Self {
+            entries: VecDeque::new(),
+            capacity: 200,
+        }
+    }
+}
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Characters whose name contains `text`, ignoring case; an exact match first.
+pub fn find_characters<'a>(
+    characters: impl Iterator<Item = (Entity, &'a Character)>,
+    text: &str,
+) -> Vec<(Entity, &'a Character)> {
+    let needle = text.to_lowercase();
+    let mut found: Vec<(Entity, &Character)> =
+        characters.filter(|(_, c)| c.name.to_lowercase().contains(&needle)).collect();
+    found.sort_by_key(|(_, c)| (c.name.to_lowercase() != needle, c.name.clone()));
+    found
+}
+
+pub struct GmPlugin;
+
+impl Plugin for GmPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GmAuditOutbox>()
+            .init_resource::<GmAuditLog>()
+            .add_event::<GmActionRequest>()
+            .add_event::<ConsoleSpawnRequest>()
+            .add_event::<ConsoleGiveItemRequest>()
+            .add_event::<SetTimeOfDayRequest>()
+            .add_console_command(
+                ConsoleCommand::new("gm", "GM actions on players, recorded in the audit log")
+                    .usage("<find|goto|summon|give|mute|kick> <player> [item [count]|minutes [reason]|reason]")
+                    .permission(CommandPermission::Admin)
+                    .complete(vec![ArgCompletion::Values(
+                        ["find", "goto", "summon", "give", "mute", "kick"].map(String::from).to_vec(),
+                    )]),
+            )
+            .add_systems(Update, (gm_command, apply_gm_actions).chain());
+    }
+}
+
+fn gm_command(
+    characters: Query<(Entity, &Character)>,
+    mut events: EventReader<ConsoleCommandEvent>,
+    mut output: EventWriter<ConsoleOutputEvent>,
+    mut actions: EventWriter<GmActionRequest>,
+) {
+    for event in events.read() {
+        if event.name != "gm" {
+            continue;
+        }
+        let result: Result<String, String> = (|| {
+            let verb: String = event.arg(0, "action")?;
+            let player: String = event.arg(1, "player")?;
+            let action = match verb.as_str() {
+                "find" => {
+                    let found = find_characters(characters.iter(), &player);
+                    if found.is_empty() {
+                        return Ok(format!("No characters match '{}'", player));
+                    }
+                    let names: Vec<&str> = found.iter().map(|(_, c)| c.name.as_str()).collect();
+                    return Ok(names.join(", "));
+                }
+                "goto" => GmAction::Goto { player },
+                "summon" => GmAction::Summon { player },
+                "give" => GmAction::GrantItem {
+                    player,
+                    item: event.arg(2, "item")?,
+                    count: event.args.get(3).map(|_| event.arg::<u32>(3, "count")).transpose()?.unwrap_or(1),
+                },
+                "mute" => GmAction::Mute {
+                    player,
+                    minutes: event.arg(2, "minutes")?,
+                    reason: event.args.get(3..).map(|words| words.join(" ")).unwrap_or_default(),
+                },
+                "kick" => GmAction::Kick {
+                    player,
+                    reason: event.args.get(2..).map(|words| words.join(" ")).unwrap_or_default(),
+                },
+                other => return Err(format!("unknown action '{}'", other)),
+            };
+            let text = action.describe();
+            actions.send(GmActionRequest(action));
+            Ok(text)
+        })();
+        output.send(match result {
+            Ok(text) => event.reply(text),
+            Err(text) => event.error(format!("{}: {}", event.name, text)),
+        });
+    }
+}
+
+fn apply_gm_actions(
+    mut requests: EventReader<GmActionRequest>,
+    mut characters: Query<(Entity, &Character, &mut Transform, Has<Player>)>,
+    mut wind: Option<ResMut<WeatherWind>>,
+    (mut outbox, mut log): (ResMut<GmAuditOutbox>, ResMut<GmAuditLog>),
+    (mut spawns, mut gives): (EventWriter<ConsoleSpawnRequest>, EventWriter<ConsoleGiveItemRequest>),
+    mut times: EventWriter<SetTimeOfDayRequest>,
+) {
+    for GmActionRequest(action) in requests.read() {
+        let gm = characters.iter().find(|(_, _, _, is_player)| *is_player);
+        let gm_name = gm.as_ref().map_or("unknown".to_string(), |(_, c, _, _)| c.name.clone());
+        let gm_transform = gm.map(|(_, _, transform, _)| *transform);
+        let target = action.player().and_then(|name| {
+            characters
+                .iter()
+                .find(|(_, c, _, _)| c.name.eq_ignore_ascii_case(name))
+                .map(|(entity, _, transform, _)| (entity, transform.translation))
+        });
+
+        match action {
+            GmAction::Goto { .. } => {
+                if let Some((_, position)) = target {
+                    for (_, _, mut transform, is_player) in &mut characters {
+                        if is_player {
+                            transform.translation = position + Vec3::X * 2.0;
+                        }
+                    }
+                }
+            }
+            GmAction::Summon { .. } => {
+                if let (Some((entity, _)), Some(gm_transform)) = (target, gm_transform) {
+                    if let Ok((_, _, mut transform, _)) = characters.get_mut(entity) {
+                        transform.translation = gm_transform.translation + gm_transform.forward() * 2.0;
+                    }
+                }
+            }
+            GmAction::GrantItem { item, count, .. } => {
+                if let Some((entity, _)) = target {
+                    gives.send(ConsoleGiveItemRequest {
+                        target: entity,
+                        item: item.clone(),
+                        count: (*count).clamp(1, MAX_COUNT),
+                    });
+                }
+            }
+            // Chat and sessions are the server's
+            GmAction::Mute { .. } | GmAction::Kick { .. } => {}
+            GmAction::Spawn { monster, level, count } => {
+                if let Some(gm_transform) = gm_transform {
+                    let count = (*count).clamp(1, MAX_COUNT);
+                    let ahead = gm_transform.translation + gm_transform.forward() * 5.0;
+                    for i in 0..count {
+                        let offset = gm_transform.right() * (i as f32 - (count - 1) as f32 * 0.5) * 2.0;
+                        spawns.send(ConsoleSpawnRequest {
+                            monster: monster.clone(),
+                            level: *level,
+                            position: ahead + offset,
+                        });
+                    }
+                }
+            }
+            GmAction::SetTime { hour } => {
+                times.send(SetTimeOfDayRequest { hour: hour.rem_euclid(24.0) });
+            }
+            GmAction::SetWind { direction_degrees, speed } => {
+                if let Some(wind) = wind.as_mut() {
+                    wind.direction = Vec2::from_angle(direction_degrees.to_radians());
+                    wind.speed = speed.max(0.0);
+                }
+            }
+        }
+        if action.player().is_some() && target.is_none() {
+            info!("GM {} {} (not on this client; left to the server)", gm_name, action.describe());
+        } else {
+            info!("GM {} {}", gm_name, action.describe());
+        }
+
+        let entry = GmAuditEntry {
+            at: unix_now(),
+            gm: gm_name,
+            action: action.clone(),
+        };
+        outbox.pending.push(entry.clone());
+        log.entries.push_back(entry);
+        while log.entries.len() > log.capacity {
+            log.entries.pop_front();
+        }
+    }
+}
@@ -0,0 +1,246 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts, EguiPlugin};
+
+use super::{find_characters, GmAction, GmActionRequest, GmAuditLog};
+use crate::console::{CommandPermission, ConsolePermissions};
+use crate::flood_control::FloodControl;
+use crate::input_map::{Action, ActionState};
+use crate::mount_environment::WeatherWind;
+use crate::Character;
+
+/// What's typed into the panel, kept while it's closed.
+#[derive(Resource, Debug)]
+pub struct GmPanel {
+    pub open: bool,
+    pub search: String,
+    pub selected: Option<String>,
+    pub item: String,
+    pub item_count: u32,
+    pub mute_minutes: u32,
+    pub reason: String,
+    pub monster: String,
+    pub level: u32,
+    pub spawn_count: u32,
+    pub hour: f32,
+    pub wind_degrees: f32,
+    pub wind_speed: f32,
+}
+
+impl Default for GmPanel {
+    fn default() -> Self {
+        Self {
+            open: false,
+            search: String::new(),
+            selected: None,
+            item: String::new(),
+            item_count: 1,
+            mute_minutes: 15,
+            reason: String::new(),
+            monster: String::new(),
+            level: 0,
+            spawn_count: 1,
+            hour: 12.0,
+            wind_degrees: 0.0,
+            wind_speed: 4.0,
+        }
+    }
+}
+
+fn is_admin(permissions: &ConsolePermissions) -> bool {
+    permissions.local >= CommandPermission::Admin
+}
+
+/// The GM panel, toggled with F8 by default on admin accounts.
+pub struct GmUiPlugin;
+
+impl Plugin for GmUiPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugins(EguiPlugin);
+        }
+        app.init_resource::<GmPanel>().add_systems(
+            Update,
+            (toggle_gm_panel, gm_panel_ui.run_if(|panel: Res<GmPanel>| panel.open)).chain(),
+        );
+    }
+}
+
+fn toggle_gm_panel(actions: Res<ActionState>, permissions: Res<ConsolePermissions>, mut panel: ResMut<GmPanel>) {
+    if !is_admin(&permissions) {
+        // Closes it if the server takes the flag away
+        if panel.open {
+            panel.open = false;
+        }
+        return;
+    }
+    if actions.just_pressed(Action::ToggleGmPanel) {
+        panel.open = !panel.open;
+    }
+}
+
+fn gm_panel_ui(
+    mut contexts: EguiContexts,
+    mut panel: ResMut<GmPanel>,
+    log: Res<GmAuditLog>,
+    wind: Option<Res<WeatherWind>>,
+    // Only on the server
+    (flood, time): (Option<Res<FloodControl>>, Res<Time>),
+    characters: Query<(Entity, &Character)>,
+    mut actions: EventWriter<GmActionRequest>,
+) {
+    let ctx = contexts.ctx_mut().clone();
+    let panel = &mut *panel;
+    let mut open = panel.open;
+    let mut requested = Vec::new();
+
+    egui::Window::new("GM Tools").open(&mut open).default_width(380.0).show(&ctx, |ui| {
+        ui.collapsing("Players", |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Search");
+                ui.text_edit_singleline(&mut panel.search);
+            });
+            if !panel.search.trim().is_empty() {
+                for (_, character) in find_characters(characters.iter(), panel.search.trim()).into_iter().take(10) {
+                    let selected = panel.selected.as_deref() == Some(character.name.as_str());
+                    if ui.selectable_label(selected, &character.name).clicked() {
+                        panel.selected = Some(character.name.clone());
+                    }
+                }
+            }
+            // Players on other clients can still be named directly
+            let unlisted = panel.selected.is_none() && !panel.search.trim().is_empty();
+            if unlisted && ui.small_button(format!("Use '{}'", panel.search.trim())).clicked() {
+                panel.selected = Some(panel.search.trim().to_string());
+            }
+            let Some(player) = panel.selected.clone() else {
+                return;
+            };
+            ui.separator();
+            ui.strong(&player);
+            ui.horizontal(|ui| {
+                if ui.button("Go to").clicked() {
+                    requested.push(GmAction::Goto { player: player.clone() });
+                }
+                if ui.button("Summon").clicked() {
+                    requested.push(GmAction::Summon { player: player.clone() });
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Item");
+                ui.text_edit_singleline(&mut panel.item);
+                ui.add(egui::DragValue::new(&mut panel.item_count).range(1..=100));
+                let can_grant = !panel.item.trim().is_empty();
+                if ui.add_enabled(can_grant, egui::Button::new("Grant")).clicked() {
+                    requested.push(GmAction::GrantItem {
+                        player: player.clone(),
+                        item: panel.item.trim().to_string(),
+                        count: panel.item_count,
+                    });
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Reason");
+                ui.text_edit_singleline(&mut panel.reason);
+            });
+            ui.horizontal(|ui| {
+                ui.add(egui::DragValue::new(&mut panel.mute_minutes).range(1..=10_080).suffix(" min"));
+                if ui.button("Mute").clicked() {
+                    requested.push(GmAction::Mute {
+                        player: player.clone(),
+                        minutes: panel.mute_minutes,
+                        reason: panel.reason.trim().to_string(),
+                    });
+                }
+                if ui.button("Kick").clicked() {
+                    requested.push(GmAction::Kick {
+                        player: player.clone(),
+                        reason: panel.reason.trim().to_string(),
+                    });
+                }
+            });
+        });
+
+        ui.collapsing("Spawn", |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Monster");
+                ui.text_edit_singleline(&mut panel.monster);
+            });
+            ui.horizontal(|ui| {
+                ui.label("Level");
+                ui.add(egui::DragValue::new(&mut panel.level).range(0..=100))
+                    .on_hover_text("0 uses the monster's own level");
+                ui.label("Count");
+                ui.add(egui::DragValue::new(&mut panel.spawn_count).range(1..=100));
+                let can_spawn = !panel.monster.trim().is_empty();
+                if ui.add_enabled(can_spawn, egui::Button::new("Spawn")).clicked() {
+                    requested.push(GmAction::Spawn {
+                        monster: panel.monster.trim().to_string(),
+                        level: (panel.level > 0).then_some(panel.level),
+                        count: panel.spawn_count,
+                    });
+                }
+            });
+        });
+
+        ui.collapsing("World", |ui| {
+            ui.horizontal(|ui| {
+                ui.add(egui::Slider::new(&mut panel.hour, 0.0..=24.0).text("hour"));
+                if ui.button("Set time").clicked() {
+                    requested.push(GmAction::SetTime { hour: panel.hour });
+                }
+            });
+            if let Some(wind) = &wind {
+                ui.label(format!(
+                    "Wind now {:.1} m/s towards {:.0}°",
+                    wind.speed,
+                    wind.direction.to_angle().to_degrees().rem_euclid(360.0)
+                ));
+            }
+            ui.add(egui::Slider::new(&mut panel.wind_degrees, 0.0..=360.0).text("towards °"));
+            ui.horizontal(|ui| {
+                ui.add(egui::Slider::new(&mut panel.wind_speed, 0.0..=40.0).text("m/s"));
+                if ui.button("Set wind").clicked() {
+                    requested.push(GmAction::SetWind {
+                        direction_degrees: panel.wind_degrees,
+                        speed: panel.wind_speed,
+                    });
+                }
+            });
+        });
+
+        if let Some(flood) = &flood {
+            ui.collapsing("Flood control", |ui| {
+                let offenders = flood.offenders(time.elapsed_secs_f64());
+                if offenders.is_empty() {
+                    ui.label("No clients over their message budget.");
+                }
+                for report in offenders.iter().take(10) {
+                    ui.horizontal(|ui| {
+                        ui.label(report.describe());
+                        if !report.kicked && ui.small_button("Kick").clicked() {
+                            requested.push(GmAction::Kick {
+                                player: report.client.clone(),
+                                reason: "Flooding".to_string(),
+                            });
+                        }
+                    });
+                }
+            });
+        }
+
+        ui.collapsing("Audit log", |ui| {
+            egui::ScrollArea::vertical().max_height(200.0).stick_to_bottom(true).show(ui, |ui| {
+                if log.entries.is_empty() {
+                    ui.label("No GM actions this session.");
+                }
+                for entry in &log.entries {
+                    let (hour, minute) = (entry.at % 86_400 / 3600, entry.at % 3600 / 60);
+                    ui.label(format!("{:02}:{:02} UTC  {} {}", hour, minute, entry.gm, entry.action.describe()));
+                }
+            });
+        });
+    });
+
+    panel.open = open;
+    actions.send_batch(requested.into_iter().map(GmActionRequest));
+}
@@ -0,0 +1,176 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::engine_fabric::physics::CharacterController;
+
+/// Damage school carried by environmental damage so resistances and combat
+/// log coloring work the same as for spell damage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DamageSchool {
+    #[default]
+    Physical,
+    Fire,
+    Frost,
+    Nature,
+    Shadow,
+    Arcane,
+    Holy,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HazardCause {
+    Falling { impact_speed: f32 },
+    Volume { trigger_id: String },
+    Drowning,
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct HazardDamageEvent {
+    pub target: Entity,
+    pub amount: f32,
+    pub school: DamageSchool,
+    pub cause: HazardCause,
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct HazardConfig {
+    /// Landing speed (m/s) that causes no damage.
+    pub safe_fall_speed: f32,
+    /// Damage per m/s above `safe_fall_speed`.
+    pub fall_damage_per_speed: f32,
+    /// Seconds between damage ticks inside damage volumes.
+    pub volume_tick_interval: f32,
+    pub drowning_tick_interval: f32,
+    pub drowning_damage: f32,
+}
+
+impl Default for HazardConfig {
+    fn default() -> Self {
+        Self {
+            safe_fall_speed: 14.0,
+            fall_damage_per_speed: 6.0,
+            volume_tick_interval: 1.0,
+            drowning_tick_interval: 2.0,
+            drowning_damage: 10.0,
+        }
+    }
+}
+
+/// Held breath while the head is underwater. Swim code sets `submerged`;
+/// characters without water logic fall back to `CharacterController::is_swimming`.
+#[derive(Component, Debug, Clone)]
+pub struct Breath {
+    pub current: f32,
+    pub max: f32,
+    pub regen_per_second: f32,
+    pub submerged: bool,
+    pub drowning_timer: f32,
+}
+
+impl Default for Breath {
+    fn default() -> Self {
+        Self {
+            current: 60.0,
+            max: 60.0,
+            regen_per_second: 20.0,
+            submerged: false,
+            drowning_timer: 0.0,
+        }
+    }
+}
+
+impl Breath {
+    pub fn fraction(&self) -> f32 {
+        if self.max <= 0.0 {
+            0.0
+        } else {
+            (self.current / self.max).clamp(0.0, 1.0)
+        }
+    }
+}
+
+/// Characters with this component take no falling damage (slow fall buffs, mounts).
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct FallDamageImmune;
+
+/// Takes no damage from any hazard (console god mode, cutscenes).
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct Invulnerable;
+
+pub struct HazardPlugin;
+
+impl Plugin for HazardPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HazardConfig>()
+            .add_event::<HazardDamageEvent>()
+            .add_systems(Update, (apply_fall_damage, update_breath));
+    }
+}
+
+fn apply_fall_damage(
+    config: Res<HazardConfig>,
+    mut characters: Query<(Entity, &mut CharacterController, Has<FallDamageImmune>, Has<Invulnerable>)>,
+    mut damage: EventWriter<HazardDamageEvent>,
+) {
+    for (entity, mut controller, immune, invulnerable) in characters.iter_mut() {
+        let Some(impact_speed) = controller.take_landing_speed() else {
+            continue;
+        };
+        // Landing in water or onto a climbable surface mid-climb never hurts
+        if immune || invulnerable || controller.is_swimming || controller.is_climbing {
+            continue;
+        }
+        let excess = impact_speed - config.safe_fall_speed;
+        if excess <= 0.0 {
+            continue;
+        }
+
+        let amount = excess * config.fall_damage_per_speed;
+        debug!("Fall damage: {:?} landed at {:.1} m/s for {:.0}", entity, impact_speed, amount);
+        damage.send(HazardDamageEvent {
+            target: entity,
+            amount,
+            school: DamageSchool::Physical,
+            cause: HazardCause::Falling { impact_speed },
+        });
+    }
+}
+
+fn update_breath(
+    time: Res<Time>,
+    config: Res<HazardConfig>,
+    mut characters: Query<(Entity, &mut Breath, Option<&CharacterController>, Has<Invulnerable>)>,
+    mut damage: EventWriter<HazardDamageEvent>,
+) {
+    let dt = time.delta_secs();
+
+    for (entity, mut breath, controller, invulnerable) in characters.iter_mut() {
+        let submerged = breath.submerged || controller.is_some_and(|c| c.is_swimming);
+
+        if !submerged {
+            breath.current = (breath.current + breath.regen_per_second * dt).min(breath.max);
+            breath.drowning_timer = 0.0;
+            continue;
+        }
+
+        if breath.current > 0.0 {
+            breath.current = (breath.current - dt).max(0.0);
+            continue;
+        }
+
+        breath.drowning_timer += dt;
+        if breath.drowning_timer >= config.drowning_tick_interval {
+            breath.drowning_timer -= config.drowning_tick_interval;
+            if invulnerable {
+                continue;
+            }
+            damage.send(HazardDamageEvent {
+                target: entity,
+                amount: config.drowning_damage,
+                school: DamageSchool::Physical,
+                cause: HazardCause::Drowning,
+            });
+        }
+    }
+}
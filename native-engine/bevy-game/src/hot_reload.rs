@@ -0,0 +1,214 @@
+//! Live reload of `content/` while the game runs. A file watcher turns saves
+//! into `ContentChangedEvent`s; the module that owns each kind of content
+//! (spawns, triggers, quests and dialog, scripts, ...) reloads its part and
+//! answers with a `ContentReloadedEvent`, which ends up in the log overlay.
+
+use bevy::prelude::*;
+use fluent::fluent_args;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
+use std::sync::Mutex;
+
+use crate::localization::Localization;
+use crate::GameLogOverlay;
+
+#[derive(Resource, Debug, Clone)]
+pub struct ContentHotReloadConfig {
+    pub enabled: bool,
+    pub root: PathBuf,
+    /// Quiet time after the last write before a file is reloaded; editors
+    /// often save in several steps.
+    pub debounce_seconds: f64,
+}
+
+impl Default for ContentHotReloadConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            root: PathBuf::from("content"),
+            debounce_seconds: 0.25,
+        }
+    }
+}
+
+/// A content file was written or removed. `path` starts with the configured
+/// root (`content/spawns/forest.toml`), the same form loaders keep as `source`.
+#[derive(Event, Debug, Clone)]
+pub struct ContentChangedEvent {
+    pub path: PathBuf,
+    pub removed: bool,
+}
+
+/// Result of reloading a changed file, for the reload report.
+#[derive(Event, Debug, Clone)]
+pub struct ContentReloadedEvent {
+    pub path: PathBuf,
+    pub outcome: Result<String, String>,
+}
+
+impl ContentReloadedEvent {
+    pub fn applied(path: &Path, summary: impl Into<String>) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            outcome: Ok(summary.into()),
+        }
+    }
+
+    pub fn failed(path: &Path, error: impl Into<String>) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            outcome: Err(error.into()),
+        }
+    }
+}
+
+#[derive(Resource)]
+struct ContentWatcher {
+    // Dropping the watcher stops the notifications
+    _watcher: Mutex<RecommendedWatcher>,
+    events: Mutex<Receiver<notify::Result<notify::Event>>>,
+    /// Canonical form of the root, which is what notify reports paths under.
+    watched_root: PathBuf,
+}
+
+/// Changed files waiting out the debounce, with the time of their last write.
+#[derive(Resource, Debug, Default)]
+struct PendingContentChanges {
+    files: HashMap<PathBuf, f64>,
+}
+
+pub struct ContentHotReloadPlugin;
+
+impl Plugin for ContentHotReloadPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ContentHotReloadConfig>()
+            .init_resource::<PendingContentChanges>()
+            .init_resource::<Localization>()
+            .add_event::<ContentChangedEvent>()
+            .add_event::<ContentReloadedEvent>()
+            .add_systems(Startup, start_content_watcher)
+            .add_systems(PreUpdate, poll_content_watcher.run_if(resource_exists::<ContentWatcher>))
+            .add_systems(Last, report_content_reloads);
+    }
+}
+
+fn start_content_watcher(mut commands: Commands, config: Res<ContentHotReloadConfig>) {
+    if !config.enabled {
+        return;
+    }
+    let Ok(watched_root) = config.root.canonicalize() else {
+        info!("Content hot reload off: no content directory at {:?}", config.root);
+        return;
+    };
+
+    let (sender, receiver) = channel();
+    let watcher = notify::recommended_watcher(move |event| {
+        let _ = sender.send(event);
+    })
+    .and_then(|mut watcher| {
+        watcher.watch(&watched_root, RecursiveMode::Recursive)?;
+        Ok(watcher)
+    });
+    match watcher {
+        Ok(watcher) => {
+            info!("Watching {:?} for content changes", config.root);
+            commands.insert_resource(ContentWatcher {
+                _watcher: Mutex::new(watcher),
+                events: Mutex::new(receiver),
+                watched_root,
+            });
+        }
+        Err(e) => warn!("Content hot reload off: failed to watch {:?}: {}", config.root, e),
+    }
+}
+
+fn is_content_file(path: &Path) -> bool {
+    matches!(path.extension().and_then(|e| e.to_str()), Some("toml" | "json" | "ron" | "rhai" | "ftl"))
+}
+
+fn poll_content_watcher(
+    watcher: Res<ContentWatcher>,
+    config: Res<ContentHotReloadConfig>,
+    time: Res<Time<Real>>,
+    mut pending: ResMut<PendingContentChanges>,
+    mut changed: EventWriter<ContentChangedEvent>,
+) {
+    let now = time.elapsed_secs_f64();
+    if let Ok(events) = watcher.events.lock() {
+        for event in events.try_iter() {
+            let event = match event {
+                Ok(event) => event,
+                Err(e) => {
+                    warn!("Content watcher error: {}", e);
+                    continue;
+                }
+            };
+            if event.kind.is_access() {
+                continue;
+            }
+            for path in event.paths.iter().filter(|p| is_content_file(p)) {
+                if let Ok(relative) = path.strip_prefix(&watcher.watched_root) {
+                    pending.files.insert(config.root.join(relative), now);
+                }
+            }
+        }
+    }
+
+    let ready: Vec<PathBuf> = pending
+        .files
+        .iter()
+        .filter(|(_, last_write)| now - **last_write >= config.debounce_seconds)
+        .map(|(path, _)| path.clone())
+        .collect();
+    for path in ready {
+        pending.files.remove(&path);
+        let removed = !path.exists();
+        changed.send(ContentChangedEvent { path, removed });
+    }
+}
+
+/// Logs each reload and lists changed files nothing reloaded, so it's clear
+/// when a restart is still needed.
+fn report_content_reloads(
+    mut changed: EventReader<ContentChangedEvent>,
+    mut reloaded: EventReader<ContentReloadedEvent>,
+    mut overlay: Option<ResMut<GameLogOverlay>>,
+    localization: Res<Localization>,
+    time: Res<Time>,
+) {
+    let mut unhandled: Vec<PathBuf> = changed.read().map(|e| e.path.clone()).collect();
+    let now = time.elapsed_secs_f64();
+
+    for event in reloaded.read() {
+        unhandled.retain(|path| path != &event.path);
+        match &event.outcome {
+            Ok(summary) => {
+                let args = fluent_args!["path" => event.path.display().to_string(), "summary" => summary.clone()];
+                let message = localization.text_with("system-reload-applied", &args);
+                info!("{}", message);
+                if let Some(overlay) = overlay.as_mut() {
+                    overlay.info(message, now);
+                }
+            }
+            Err(e) => {
+                let args = fluent_args!["path" => event.path.display().to_string(), "error" => e.clone()];
+                let message = localization.text_with("system-reload-failed", &args);
+                warn!("{}", message);
+                if let Some(overlay) = overlay.as_mut() {
+                    overlay.error(message, now);
+                }
+            }
+        }
+    }
+
+    for path in unhandled {
+        let args = fluent_args!["path" => path.display().to_string()];
+        let message = localization.text_with("system-reload-restart", &args);
+        info!("{}", message);
+        if let Some(overlay) = overlay.as_mut() {
+            overlay.warn(message, now);
+        }
+    }
+}
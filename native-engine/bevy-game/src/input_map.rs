@@ -0,0 +1,770 @@
+//! Action mapping. Systems ask [`ActionState`] whether an [`Action`] is
+//! pressed instead of reading keys. Every action has default keyboard/mouse
+//! and gamepad bindings; players rebind them at runtime and the overrides
+//! are saved in [`InputSettings`].
+//!
+//! A stack of [`InputContext`]s decides which actions are live. Gameplay is
+//! always at the bottom; opening the console pushes `Ui` and spectating
+//! pushes `FreeCamera`, both of which hide the contexts under them. Systems
+//! that still read `ButtonInput` directly don't see keys bound in a hidden
+//! context either, so they stay quiet until they move to actions.
+
+use bevy::input::gamepad::{Gamepad, GamepadAxis, GamepadButton};
+use bevy::input::keyboard::{KeyboardFocusLost, KeyboardInput};
+use bevy::input::mouse::MouseButtonInput;
+use bevy::input::{ButtonState, InputSystem};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+use crate::console::{ArgCompletion, ConsoleAppExt, ConsoleCommand, ConsoleCommandEvent, ConsoleOutputEvent};
+use crate::settings::{InputSettings, UserSettings};
+
+/// Gamepad axis travel needed to bind a stick direction while rebinding.
+const CAPTURE_AXIS_THRESHOLD: f32 = 0.7;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    MoveForward,
+    MoveBackward,
+    MoveLeft,
+    MoveRight,
+    Jump,
+    /// Run; flies faster in the free camera.
+    Sprint,
+    Interact,
+    /// Held to turn the camera with the mouse.
+    MouseLook,
+    LookLeft,
+    LookRight,
+    LookUp,
+    LookDown,
+    ToggleCameraMode,
+    /// Instance lockouts window.
+    ToggleLockouts,
+    ToggleCombatLog,
+    ToggleDamageMeter,
+    FlyUp,
+    FlyDown,
+    FlySlow,
+    ToggleNoClip,
+    ToggleFollow,
+    UiConfirm,
+    UiCancel,
+    ToggleSpectator,
+    ToggleLogOverlay,
+    ToggleProfiler,
+    ToggleAuthoring,
+    /// GM tools; only opens for admin accounts.
+    ToggleGmPanel,
+    NextTool,
+    Undo,
+    Redo,
+    Save,
+}
+
+impl Action {
+    pub const ALL: [Action; 32] = [
+        Action::MoveForward,
+        Action::MoveBackward,
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::Jump,
+        Action::Sprint,
+        Action::Interact,
+        Action::MouseLook,
+        Action::LookLeft,
+        Action::LookRight,
+        Action::LookUp,
+        Action::LookDown,
+        Action::ToggleCameraMode,
+        Action::ToggleLockouts,
+        Action::ToggleCombatLog,
+        Action::ToggleDamageMeter,
+        Action::FlyUp,
+        Action::FlyDown,
+        Action::FlySlow,
+        Action::ToggleNoClip,
+        Action::ToggleFollow,
+        Action::UiConfirm,
+        Action::UiCancel,
+        Action::ToggleSpectator,
+        Action::ToggleLogOverlay,
+        Action::ToggleProfiler,
+        Action::ToggleAuthoring,
+        Action::ToggleGmPanel,
+        Action::NextTool,
+        Action::Undo,
+        Action::Redo,
+        Action::Save,
+    ];
+
+    /// The name used in settings files and the `bind` command.
+    pub fn name(self) -> String {
+        serde_json::to_value(self).ok().and_then(|value| value.as_str().map(str::to_string)).unwrap_or_default()
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        serde_json::from_value(serde_json::Value::String(name.to_string())).ok()
+    }
+
+    /// Contexts the action is live in.
+    pub fn contexts(self) -> &'static [InputContext] {
+        use InputContext::{Editor, FreeCamera, Gameplay, Global, Ui};
+        match self {
+            Action::MoveForward
+            | Action::MoveBackward
+            | Action::MoveLeft
+            | Action::MoveRight
+            | Action::Sprint
+            | Action::MouseLook
+            | Action::LookLeft
+            | Action::LookRight
+            | Action::LookUp
+            | Action::LookDown => &[Gameplay, FreeCamera],
+            Action::Jump
+            | Action::Interact
+            | Action::ToggleCameraMode
+            | Action::ToggleLockouts
+            | Action::ToggleCombatLog
+            | Action::ToggleDamageMeter => &[Gameplay],
+            Action::FlyUp | Action::FlyDown | Action::FlySlow | Action::ToggleNoClip | Action::ToggleFollow => {
+                &[FreeCamera]
+            }
+            Action::UiConfirm | Action::UiCancel => &[Ui],
+            Action::NextTool | Action::Undo | Action::Redo | Action::Save => &[Editor],
+            Action::ToggleSpectator
+            | Action::ToggleLogOverlay
+            | Action::ToggleProfiler
+            | Action::ToggleAuthoring
+            | Action::ToggleGmPanel => &[Global],
+        }
+    }
+
+    pub fn default_bindings(self) -> Vec<Binding> {
+        use Binding::{Gamepad as Pad, Key, Mouse};
+        let axis = |axis, positive| Binding::GamepadAxis { axis, positive };
+        let chord = |modifiers: &[KeyCode], key| Binding::Chord { modifiers: modifiers.to_vec(), key };
+        match self {
+            Action::MoveForward => vec![Key(KeyCode::KeyW), axis(GamepadAxis::LeftStickY, true)],
+            Action::MoveBackward => vec![Key(KeyCode::KeyS), axis(GamepadAxis::LeftStickY, false)],
+            Action::MoveLeft => vec![Key(KeyCode::KeyA), axis(GamepadAxis::LeftStickX, false)],
+            Action::MoveRight => vec![Key(KeyCode::KeyD), axis(GamepadAxis::LeftStickX, true)],
+            Action::Jump => vec![Key(KeyCode::Space), Pad(GamepadButton::South)],
+            Action::Sprint => vec![Key(KeyCode::ShiftLeft), Pad(GamepadButton::LeftThumb)],
+            Action::Interact => vec![Key(KeyCode::KeyF), Pad(GamepadButton::West)],
+            Action::MouseLook => vec![Mouse(MouseButton::Right)],
+            Action::LookLeft => vec![axis(GamepadAxis::RightStickX, false)],
+            Action::LookRight => vec![axis(GamepadAxis::RightStickX, true)],
+            Action::LookUp => vec![axis(GamepadAxis::RightStickY, true)],
+            Action::LookDown => vec![axis(GamepadAxis::RightStickY, false)],
+            Action::ToggleCameraMode => vec![Key(KeyCode::KeyV), Pad(GamepadButton::RightThumb)],
+            Action::ToggleLockouts => vec![Key(KeyCode::KeyO)],
+            Action::ToggleCombatLog => vec![Key(KeyCode::KeyL)],
+            Action::ToggleDamageMeter => vec![Key(KeyCode::KeyK)],
+            Action::FlyUp => vec![Key(KeyCode::Space), Key(KeyCode::KeyE), Pad(GamepadButton::RightTrigger2)],
+            Action::FlyDown => vec![Key(KeyCode::KeyQ), Pad(GamepadButton::LeftTrigger2)],
+            Action::FlySlow => vec![Key(KeyCode::ControlLeft), Pad(GamepadButton::LeftTrigger)],
+            Action::ToggleNoClip => vec![Key(KeyCode::KeyN), Pad(GamepadButton::North)],
+            Action::ToggleFollow => vec![Key(KeyCode::KeyP), Pad(GamepadButton::West)],
+            Action::UiConfirm => vec![Key(KeyCode::Enter), Pad(GamepadButton::South)],
+            Action::UiCancel => vec![Key(KeyCode::Escape), Pad(GamepadButton::East)],
+            Action::ToggleSpectator => vec![Key(KeyCode::F9)],
+            // Exact chords, so Shift+F12 (the profiler) doesn't also toggle the log
+            Action::ToggleLogOverlay => vec![chord(&[], KeyCode::F12)],
+            Action::ToggleProfiler => vec![chord(&[KeyCode::ShiftLeft], KeyCode::F12)],
+            Action::ToggleAuthoring => vec![Key(KeyCode::F10)],
+            Action::ToggleGmPanel => vec![Key(KeyCode::F8)],
+            Action::NextTool => vec![Key(KeyCode::F5)],
+            Action::Undo => vec![chord(&[KeyCode::ControlLeft], KeyCode::KeyZ)],
+            Action::Redo => vec![
+                chord(&[KeyCode::ControlLeft], KeyCode::KeyY),
+                chord(&[KeyCode::ControlLeft, KeyCode::ShiftLeft], KeyCode::KeyZ),
+            ],
+            Action::Save => vec![chord(&[KeyCode::ControlLeft], KeyCode::KeyS)],
+        }
+    }
+}
+
+/// One way to trigger an action.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Binding {
+    /// The key, whatever modifiers are held (so Shift+W still walks).
+    Key(KeyCode),
+    /// The key with exactly these modifiers held; left and right count as
+    /// the same modifier.
+    Chord {
+        modifiers: Vec<KeyCode>,
+        key: KeyCode,
+    },
+    Mouse(MouseButton),
+    Gamepad(GamepadButton),
+    /// A stick or trigger pushed past the dead zone in one direction.
+    GamepadAxis {
+        axis: GamepadAxis,
+        positive: bool,
+    },
+}
+
+impl Binding {
+    pub fn is_gamepad(&self) -> bool {
+        matches!(self, Binding::Gamepad(_) | Binding::GamepadAxis { .. })
+    }
+
+    /// 0..1; digital inputs are 0 or 1.
+    fn value(&self, devices: &Devices) -> f32 {
+        let on = |held: bool| if held { 1.0 } else { 0.0 };
+        match self {
+            Binding::Key(key) => on(devices.keys.contains(key)),
+            Binding::Chord { modifiers, key } => {
+                let wanted: HashSet<KeyCode> = modifiers.iter().filter_map(|m| modifier_group(*m)).collect();
+                on(devices.modifiers == wanted && devices.keys.contains(key))
+            }
+            Binding::Mouse(button) => on(devices.mouse.contains(button)),
+            Binding::Gamepad(button) => devices
+                .gamepads
+                .iter()
+                .map(|pad| {
+                    if pad.pressed(*button) {
+                        1.0
+                    } else {
+                        past_dead_zone(pad.get(*button).unwrap_or(0.0), devices.dead_zone)
+                    }
+                })
+                .fold(0.0, f32::max),
+            Binding::GamepadAxis { axis, positive } => devices
+                .gamepads
+                .iter()
+                .map(|pad| pad.get(*axis).unwrap_or(0.0) * if *positive { 1.0 } else { -1.0 })
+                .map(|value| past_dead_zone(value, devices.dead_zone))
+                .fold(0.0, f32::max),
+        }
+    }
+
+    fn text(&self) -> String {
+        match self {
+            Binding::Key(key) => format!("{:?}", key),
+            Binding::Chord { modifiers, key } => {
+                modifiers.iter().map(|m| format!("{:?}+", m)).chain(std::iter::once(format!("{:?}", key))).collect()
+            }
+            Binding::Mouse(button) => format!("Mouse{:?}", button),
+            Binding::Gamepad(button) => format!("Pad{:?}", button),
+            Binding::GamepadAxis { axis, positive } => format!("Pad{:?}{}", axis, if *positive { "+" } else { "-" }),
+        }
+    }
+}
+
+/// Rescales `value` so the dead zone reads 0 and full travel 1.
+fn past_dead_zone(value: f32, dead_zone: f32) -> f32 {
+    if value <= dead_zone {
+        0.0
+    } else {
+        ((value - dead_zone) / (1.0 - dead_zone).max(f32::EPSILON)).min(1.0)
+    }
+}
+
+/// Modifier groups, in the order chords list them.
+const MODIFIERS: [KeyCode; 4] = [KeyCode::ControlLeft, KeyCode::ShiftLeft, KeyCode::AltLeft, KeyCode::SuperLeft];
+
+/// The modifier a key counts as, folding right-hand keys onto the left.
+fn modifier_group(key: KeyCode) -> Option<KeyCode> {
+    match key {
+        KeyCode::ControlLeft | KeyCode::ControlRight => Some(KeyCode::ControlLeft),
+        KeyCode::ShiftLeft | KeyCode::ShiftRight => Some(KeyCode::ShiftLeft),
+        KeyCode::AltLeft | KeyCode::AltRight => Some(KeyCode::AltLeft),
+        KeyCode::SuperLeft | KeyCode::SuperRight => Some(KeyCode::SuperLeft),
+        _ => None,
+    }
+}
+
+struct Devices<'a> {
+    keys: HashSet<KeyCode>,
+    modifiers: HashSet<KeyCode>,
+    mouse: HashSet<MouseButton>,
+    gamepads: Vec<&'a Gamepad>,
+    dead_zone: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InputContext {
+    /// Never on the stack; its actions are live everywhere.
+    Global,
+    Gameplay,
+    /// Spectator free flight.
+    FreeCamera,
+    Ui,
+    /// Authoring tools; gameplay input still reaches the world beneath.
+    Editor,
+}
+
+impl InputContext {
+    /// Whether contexts under this one stop receiving input.
+    fn blocks_below(self) -> bool {
+        matches!(self, InputContext::FreeCamera | InputContext::Ui)
+    }
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct InputContexts {
+    stack: Vec<InputContext>,
+}
+
+impl Default for InputContexts {
+    fn default() -> Self {
+        Self { stack: vec![InputContext::Gameplay] }
+    }
+}
+
+impl InputContexts {
+    /// Puts `context` on top (moving it there if already on the stack).
+    pub fn push(&mut self, context: InputContext) {
+        if context == InputContext::Global {
+            return;
+        }
+        self.stack.retain(|c| *c != context);
+        self.stack.push(context);
+    }
+
+    /// Takes `context` off the stack. Gameplay stays at the bottom.
+    pub fn remove(&mut self, context: InputContext) {
+        if context != InputContext::Gameplay {
+            self.stack.retain(|c| *c != context);
+        }
+    }
+
+    /// Pushes or removes `context` to match `active`, leaving the stack
+    /// alone when it already does.
+    pub fn set(&mut self, context: InputContext, active: bool) {
+        if active != self.stack.contains(&context) {
+            if active {
+                self.push(context);
+            } else {
+                self.remove(context);
+            }
+        }
+    }
+
+    pub fn top(&self) -> InputContext {
+        self.stack.last().copied().unwrap_or(InputContext::Gameplay)
+    }
+
+    pub fn contains(&self, context: InputContext) -> bool {
+        self.stack.contains(&context)
+    }
+
+    pub fn is_live(&self, context: InputContext) -> bool {
+        context == InputContext::Global || self.live().any(|c| c == context)
+    }
+
+    /// From the top down to (and including) the first blocking context.
+    fn live(&self) -> impl Iterator<Item = InputContext> + '_ {
+        let blocker = self.stack.iter().rposition(|c| c.blocks_below()).unwrap_or(0);
+        self.stack[blocker..].iter().rev().copied()
+    }
+
+    /// On the stack but under a blocking context.
+    fn hidden(&self) -> impl Iterator<Item = InputContext> + '_ {
+        let blocker = self.stack.iter().rposition(|c| c.blocks_below()).unwrap_or(0);
+        self.stack[..blocker].iter().copied()
+    }
+}
+
+/// Bindings in effect: defaults with the player's overrides on top.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct InputMap {
+    bindings: HashMap<Action, Vec<Binding>>,
+}
+
+impl InputMap {
+    pub fn from_settings(settings: &InputSettings) -> Self {
+        let bindings = Action::ALL
+            .iter()
+            .map(|action| {
+                let bindings = settings.bindings.get(action).cloned().unwrap_or_else(|| action.default_bindings());
+                (*action, bindings)
+            })
+            .collect();
+        Self { bindings }
+    }
+
+    pub fn bindings(&self, action: Action) -> &[Binding] {
+        self.bindings.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Binds `binding` to `action` in place of its other bindings on the
+    /// same device, and takes it off actions that share a context with
+    /// `action`. Returns those actions.
+    pub fn rebind(&mut self, action: Action, binding: Binding) -> Vec<Action> {
+        let mut unbound = Vec::new();
+        for (other, bindings) in self.bindings.iter_mut() {
+            let shares_context = other.contexts().iter().any(|c| action.contexts().contains(c));
+            if *other != action && shares_context && bindings.contains(&binding) {
+                bindings.retain(|b| *b != binding);
+                unbound.push(*other);
+            }
+        }
+        let bindings = self.bindings.entry(action).or_default();
+        bindings.retain(|b| b.is_gamepad() != binding.is_gamepad());
+        bindings.push(binding);
+        unbound
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct ActionState {
+    values: HashMap<Action, f32>,
+    pressed: HashSet<Action>,
+    just_pressed: HashSet<Action>,
+    just_released: HashSet<Action>,
+}
+
+impl ActionState {
+    pub fn pressed(&self, action: Action) -> bool {
+        self.pressed.contains(&action)
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.just_pressed.contains(&action)
+    }
+
+    pub fn just_released(&self, action: Action) -> bool {
+        self.just_released.contains(&action)
+    }
+
+    /// 0..1, analog for sticks and triggers.
+    pub fn value(&self, action: Action) -> f32 {
+        self.values.get(&action).copied().unwrap_or(0.0)
+    }
+
+    /// -1..1 from a pair of opposing actions.
+    pub fn axis(&self, negative: Action, positive: Action) -> f32 {
+        self.value(positive) - self.value(negative)
+    }
+}
+
+/// Binds the next key, button or stick direction to `action`.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct RebindActionRequest {
+    pub action: Action,
+}
+
+/// Restores the default bindings of one action, or of all when `None`.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct ResetBindingsRequest(pub Option<Action>);
+
+#[derive(Event, Debug, Clone)]
+pub struct ActionReboundEvent {
+    pub action: Action,
+    pub binding: Binding,
+    /// Actions that lost the binding because it now belongs to `action`.
+    pub unbound_from: Vec<Action>,
+}
+
+/// A rebind in progress; actions don't fire while one is.
+#[derive(Resource, Debug, Default)]
+pub struct RebindCapture {
+    action: Option<Action>,
+    /// Modifier pressed on its own, bound if released before another key.
+    pending_modifier: Option<KeyCode>,
+}
+
+impl RebindCapture {
+    pub fn capturing(&self) -> Option<Action> {
+        self.action
+    }
+}
+
+/// Keys and buttons held, from the raw events. `ButtonInput` loses keys
+/// hidden from a context until they are pressed again, so this is what
+/// actions read.
+#[derive(Resource, Debug, Default)]
+struct HeldInputs {
+    keys: HashSet<KeyCode>,
+    mouse: HashSet<MouseButton>,
+}
+
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ActionSet;
+
+pub struct InputMapPlugin;
+
+impl Plugin for InputMapPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<InputContexts>()
+            .init_resource::<InputMap>()
+            .init_resource::<ActionState>()
+            .init_resource::<RebindCapture>()
+            .init_resource::<HeldInputs>()
+            // Registered here too for headless apps without InputPlugin
+            .add_event::<KeyboardInput>()
+            .add_event::<KeyboardFocusLost>()
+            .add_event::<MouseButtonInput>()
+            .add_event::<RebindActionRequest>()
+            .add_event::<ResetBindingsRequest>()
+            .add_event::<ActionReboundEvent>()
+            .add_console_command(
+                ConsoleCommand::new("bind", "Rebinds an action to the next key or button pressed")
+                    .usage("<action> [reset]")
+                    .complete(vec![ArgCompletion::Values(Action::ALL.iter().map(|a| a.name()).collect())]),
+            )
+            .add_systems(
+                PreUpdate,
+                (sync_input_map, track_held_inputs, capture_rebind, update_action_state, hide_blocked_inputs)
+                    .chain()
+                    .in_set(ActionSet)
+                    .after(InputSystem),
+            )
+            .add_systems(Update, run_bind_command);
+    }
+}
+
+/// Rebuilds the map when the settings change; resets drop the player's
+/// overrides so the defaults show through.
+fn sync_input_map(
+    settings: Option<ResMut<UserSettings>>,
+    mut map: ResMut<InputMap>,
+    mut resets: EventReader<ResetBindingsRequest>,
+    mut initialized: Local<bool>,
+) {
+    let Some(mut settings) = settings else {
+        if !*initialized {
+            *map = InputMap::from_settings(&InputSettings::default());
+            *initialized = true;
+        }
+        for reset in resets.read() {
+            let actions = reset.0.map_or(Action::ALL.to_vec(), |action| vec![action]);
+            for action in actions {
+                map.bindings.insert(action, action.default_bindings());
+            }
+        }
+        return;
+    };
+    for reset in resets.read() {
+        match reset.0 {
+            Some(action) => settings.input.bindings.remove(&action),
+            None => {
+                settings.input.bindings.clear();
+                None
+            }
+        };
+    }
+    if !*initialized || settings.is_changed() {
+        *map = InputMap::from_settings(&settings.input);
+        *initialized = true;
+    }
+}
+
+fn track_held_inputs(
+    mut held: ResMut<HeldInputs>,
+    mut keys: EventReader<KeyboardInput>,
+    mut buttons: EventReader<MouseButtonInput>,
+    mut focus_lost: EventReader<KeyboardFocusLost>,
+) {
+    // Releases that happen in another window never arrive
+    if focus_lost.read().count() > 0 {
+        held.keys.clear();
+        held.mouse.clear();
+    }
+    for event in keys.read() {
+        match event.state {
+            ButtonState::Pressed => held.keys.insert(event.key_code),
+            ButtonState::Released => held.keys.remove(&event.key_code),
+        };
+    }
+    for event in buttons.read() {
+        match event.state {
+            ButtonState::Pressed => held.mouse.insert(event.button),
+            ButtonState::Released => held.mouse.remove(&event.button),
+        };
+    }
+}
+
+fn capture_rebind(
+    mut capture: ResMut<RebindCapture>,
+    mut map: ResMut<InputMap>,
+    held: Res<HeldInputs>,
+    settings: Option<ResMut<UserSettings>>,
+    mut requests: EventReader<RebindActionRequest>,
+    mut keys: EventReader<KeyboardInput>,
+    mut buttons: EventReader<MouseButtonInput>,
+    gamepads: Query<&Gamepad>,
+    mut rebound: EventWriter<ActionReboundEvent>,
+) {
+    if let Some(request) = requests.read().last() {
+        *capture = RebindCapture { action: Some(request.action), pending_modifier: None };
+        // Whatever started the rebind (a click, Enter) isn't the new binding
+        keys.clear();
+        buttons.clear();
+        return;
+    }
+    let Some(action) = capture.action else {
+        keys.clear();
+        buttons.clear();
+        return;
+    };
+
+    let mut binding = None;
+    for event in keys.read() {
+        let modifier = modifier_group(event.key_code);
+        match (event.state, modifier) {
+            (ButtonState::Pressed, Some(modifier)) => capture.pending_modifier = Some(modifier),
+            (ButtonState::Released, Some(modifier)) if capture.pending_modifier == Some(modifier) => {
+                binding = Some(Binding::Key(modifier));
+            }
+            (ButtonState::Pressed, None) if event.key_code == KeyCode::Escape => {
+                *capture = RebindCapture::default();
+                return;
+            }
+            (ButtonState::Pressed, None) => {
+                let held_modifiers: HashSet<KeyCode> = held.keys.iter().filter_map(|k| modifier_group(*k)).collect();
+                let modifiers: Vec<KeyCode> = MODIFIERS.into_iter().filter(|m| held_modifiers.contains(m)).collect();
+                binding = Some(if modifiers.is_empty() {
+                    Binding::Key(event.key_code)
+                } else {
+                    Binding::Chord { modifiers, key: event.key_code }
+                });
+            }
+            _ => {}
+        }
+    }
+    for event in buttons.read() {
+        if event.state == ButtonState::Pressed {
+            binding = Some(Binding::Mouse(event.button));
+        }
+    }
+    for pad in &gamepads {
+        if let Some(button) = pad.get_just_pressed().next() {
+            binding = Some(Binding::Gamepad(*button));
+        }
+        for axis in GamepadAxis::all() {
+            let value = pad.get(axis).unwrap_or(0.0);
+            if value.abs() >= CAPTURE_AXIS_THRESHOLD {
+                binding = Some(Binding::GamepadAxis { axis, positive: value > 0.0 });
+            }
+        }
+    }
+
+    let Some(binding) = binding else {
+        return;
+    };
+    let unbound_from = map.rebind(action, binding.clone());
+    if let Some(mut settings) = settings {
+        for changed in std::iter::once(action).chain(unbound_from.iter().copied()) {
+            settings.input.bindings.insert(changed, map.bindings(changed).to_vec());
+        }
+    }
+    info!("Bound {} to {}", action.name(), binding.text());
+    rebound.send(ActionReboundEvent { action, binding, unbound_from });
+    *capture = RebindCapture::default();
+}
+
+fn update_action_state(
+    settings: Option<Res<UserSettings>>,
+    contexts: Res<InputContexts>,
+    map: Res<InputMap>,
+    capture: Res<RebindCapture>,
+    held: Res<HeldInputs>,
+    keyboard: Option<Res<ButtonInput<KeyCode>>>,
+    mouse: Option<Res<ButtonInput<MouseButton>>>,
+    gamepads: Query<&Gamepad>,
+    mut state: ResMut<ActionState>,
+) {
+    // `ButtonInput` is what headless runs and scenarios drive
+    let mut keys = held.keys.clone();
+    keys.extend(keyboard.iter().flat_map(|k| k.get_pressed().copied()));
+    let mut buttons = held.mouse.clone();
+    buttons.extend(mouse.iter().flat_map(|m| m.get_pressed().copied()));
+    let devices = Devices {
+        modifiers: keys.iter().filter_map(|k| modifier_group(*k)).collect(),
+        keys,
+        mouse: buttons,
+        gamepads: gamepads.iter().collect(),
+        dead_zone: settings.map_or(InputSettings::default().gamepad_dead_zone, |s| s.input.gamepad_dead_zone),
+    };
+
+    let previous = std::mem::take(&mut state.pressed);
+    state.values.clear();
+    state.just_pressed.clear();
+    state.just_released.clear();
+    for action in Action::ALL {
+        let live = capture.action.is_none() && action.contexts().iter().any(|c| contexts.is_live(*c));
+        let value = if live { map.bindings(action).iter().map(|b| b.value(&devices)).fold(0.0, f32::max) } else { 0.0 };
+        if value > 0.0 {
+            state.values.insert(action, value);
+            state.pressed.insert(action);
+            if !previous.contains(&action) {
+                state.just_pressed.insert(action);
+            }
+        } else if previous.contains(&action) {
+            state.just_released.insert(action);
+        }
+    }
+}
+
+fn hide_blocked_inputs(
+    contexts: Res<InputContexts>,
+    map: Res<InputMap>,
+    keyboard: Option<ResMut<ButtonInput<KeyCode>>>,
+    mouse: Option<ResMut<ButtonInput<MouseButton>>>,
+) {
+    let hidden: Vec<InputContext> = contexts.hidden().collect();
+    if hidden.is_empty() {
+        return;
+    }
+    let (mut keyboard, mut mouse) = (keyboard, mouse);
+    let hidden_bindings = Action::ALL
+        .into_iter()
+        .filter(|action| action.contexts().iter().any(|c| hidden.contains(c)))
+        .flat_map(|action| map.bindings(action));
+    for binding in hidden_bindings {
+        match binding {
+            Binding::Key(key) | Binding::Chord { key, .. } => {
+                if let Some(keyboard) = keyboard.as_mut() {
+                    keyboard.reset(*key);
+                }
+            }
+            Binding::Mouse(button) => {
+                if let Some(mouse) = mouse.as_mut() {
+                    mouse.reset(*button);
+                }
+            }
+            Binding::Gamepad(_) | Binding::GamepadAxis { .. } => {}
+        }
+    }
+}
+
+fn run_bind_command(
+    mut events: EventReader<ConsoleCommandEvent>,
+    mut output: EventWriter<ConsoleOutputEvent>,
+    mut rebinds: EventWriter<RebindActionRequest>,
+    mut resets: EventWriter<ResetBindingsRequest>,
+    map: Res<InputMap>,
+) {
+    for event in events.read() {
+        if event.name != "bind" {
+            continue;
+        }
+        let result: Result<String, String> = (|| {
+            let name: String = event.arg(0, "action")?;
+            let action = Action::from_name(&name).ok_or_else(|| format!("unknown action '{}'", name))?;
+            match event.args.get(1).map(String::as_str) {
+                None => {
+                    rebinds.send(RebindActionRequest { action });
+                    let current: Vec<String> = map.bindings(action).iter().map(Binding::text).collect();
+                    Ok(format!("Press a key or button for {} (now {}), Escape to cancel", name, current.join(", ")))
+                }
+                Some("reset") => {
+                    resets.send(ResetBindingsRequest(Some(action)));
+                    Ok(format!("{} reset to its default bindings", name))
+                }
+                Some(other) => Err(format!("unknown option '{}'", other)),
+            }
+        })();
+        output.send(match result {
+            Ok(text) => event.reply(text),
+            Err(text) => event.error(format!("{}: {}", event.name, text)),
+        });
+    }
+}
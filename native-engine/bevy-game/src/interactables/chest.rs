@@ -0,0 +1,297 @@
+//! Lootable chests. Opening one rolls its loot table; who gets the items
+//! depends on the opener's group loot rule. Loot tables live in
+//! `content/loot/*.toml`:
+//!
+//! ```toml
+//! [[table]]
+//! id = "bandit_chest"
+//! rolls = 2
+//!
+//! [[table.entry]]
+//! item = "gold_coin"
+//! weight = 10
+//! count = [5, 20]
+//!
+//! [[table.entry]]
+//! item = "bandit_map"
+//! always = true
+//! ```
+
+use bevy::prelude::*;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+use super::UseObjectEvent;
+use crate::content_packs::ContentPacks;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LootEntryDef {
+    pub item: String,
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+    /// Inclusive `[min, max]` stack size.
+    #[serde(default = "default_count")]
+    pub count: [u32; 2],
+    /// Dropped on every open, on top of the weighted rolls.
+    #[serde(default)]
+    pub always: bool,
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+fn default_count() -> [u32; 2] {
+    [1, 1]
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LootTableDef {
+    pub id: String,
+    /// Weighted picks per open.
+    #[serde(default = "default_rolls")]
+    pub rolls: u32,
+    #[serde(default, rename = "entry")]
+    pub entries: Vec<LootEntryDef>,
+}
+
+fn default_rolls() -> u32 {
+    1
+}
+
+impl LootTableDef {
+    pub fn roll(&self, rng: &mut impl Rng) -> Vec<(String, u32)> {
+        let stack = |entry: &LootEntryDef, rng: &mut dyn rand::RngCore| {
+            let [min, max] = entry.count;
+            (entry.item.clone(), rng.gen_range(min.min(max)..=max.max(min)))
+        };
+        let mut drops: Vec<(String, u32)> = self.entries.iter().filter(|e| e.always).map(|e| stack(e, rng)).collect();
+
+        let weighted: Vec<&LootEntryDef> = self.entries.iter().filter(|e| !e.always && e.weight > 0).collect();
+        let total: u32 = weighted.iter().map(|e| e.weight).sum();
+        if total == 0 {
+            return drops;
+        }
+        for _ in 0..self.rolls {
+            let mut pick = rng.gen_range(0..total);
+            for entry in &weighted {
+                if pick < entry.weight {
+                    drops.push(stack(entry, rng));
+                    break;
+                }
+                pick -= entry.weight;
+            }
+        }
+        drops
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LootContentFile {
+    #[serde(default, rename = "table")]
+    pub tables: Vec<LootTableDef>,
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct ChestConfig {
+    pub content_directory: PathBuf,
+    /// Group members this close to the chest share in its loot.
+    pub share_radius: f32,
+}
+
+impl Default for ChestConfig {
+    fn default() -> Self {
+        Self {
+            content_directory: PathBuf::from("content").join("loot"),
+            share_radius: 40.0,
+        }
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct LootTables {
+    pub tables: HashMap<String, LootTableDef>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LootRule {
+    /// Whoever opens the chest takes everything.
+    #[default]
+    FreeForAll,
+    /// Members nearby take turns receiving whole chests.
+    RoundRobin,
+    /// Every member nearby gets their own roll, once.
+    Personal,
+}
+
+/// Group membership for loot sharing; the party system sets it. Characters
+/// without one loot alone.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct LootGroup {
+    pub group: u64,
+    pub rule: LootRule,
+}
+
+#[derive(Component, Debug, Clone)]
+pub struct Chest {
+    pub loot_table: String,
+    /// Emptied for everyone (free-for-all and round-robin).
+    pub looted: bool,
+    /// Characters that took their personal roll.
+    pub looted_by: HashSet<Entity>,
+    /// Refills this long after being emptied; never when `None`.
+    pub respawn_seconds: Option<f32>,
+    pub emptied_at: Option<f32>,
+}
+
+impl Chest {
+    pub fn new(loot_table: impl Into<String>) -> Self {
+        Self {
+            loot_table: loot_table.into(),
+            looted: false,
+            looted_by: HashSet::new(),
+            respawn_seconds: None,
+            emptied_at: None,
+        }
+    }
+}
+
+/// Items from a chest for one character; the inventory adds them.
+#[derive(Event, Debug, Clone)]
+pub struct LootAwardedEvent {
+    pub recipient: Entity,
+    pub chest: Entity,
+    pub item: String,
+    pub count: u32,
+}
+
+/// Next round-robin recipient per group.
+#[derive(Resource, Debug, Default)]
+struct RoundRobinTurns {
+    next: HashMap<u64, usize>,
+}
+
+pub(super) struct ChestPlugin;
+
+impl Plugin for ChestPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ChestConfig>()
+            .init_resource::<LootTables>()
+            .init_resource::<RoundRobinTurns>()
+            .add_event::<LootAwardedEvent>()
+            .add_systems(Startup, load_loot_tables)
+            .add_systems(Update, (open_chests, refill_chests));
+    }
+}
+
+fn load_loot_tables(config: Res<ChestConfig>, packs: Res<ContentPacks>, mut tables: ResMut<LootTables>) {
+    for path in packs.files(&config.content_directory, "toml") {
+        let parsed = std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| toml::from_str::<LootContentFile>(&text).map_err(|e| e.to_string()));
+
+        match parsed {
+            Ok(file) => {
+                for table in file.tables {
+                    tables.tables.insert(table.id.clone(), table);
+                }
+            }
+            Err(e) => warn!("Invalid loot file {:?}: {}", path, e),
+        }
+    }
+
+    info!("Loot: {} tables loaded", tables.tables.len());
+}
+
+fn open_chests(
+    time: Res<Time>,
+    config: Res<ChestConfig>,
+    tables: Res<LootTables>,
+    mut turns: ResMut<RoundRobinTurns>,
+    mut uses: EventReader<UseObjectEvent>,
+    mut chests: Query<(&mut Chest, &GlobalTransform)>,
+    members: Query<(Entity, &LootGroup, &GlobalTransform)>,
+    mut awarded: EventWriter<LootAwardedEvent>,
+) {
+    let mut rng = rand::thread_rng();
+    for event in uses.read() {
+        let Ok((mut chest, chest_transform)) = chests.get_mut(event.target) else {
+            continue;
+        };
+        if chest.looted {
+            continue;
+        }
+        let Some(table) = tables.tables.get(&chest.loot_table) else {
+            warn!("Chest uses unknown loot table '{}'", chest.loot_table);
+            continue;
+        };
+
+        let opener_group = members.get(event.actor).ok().map(|(_, group, _)| *group);
+        let rule = opener_group.map_or(LootRule::FreeForAll, |g| g.rule);
+        // The opener and group members near the chest, in a stable order
+        let mut nearby: Vec<Entity> = match opener_group {
+            Some(group) => members
+                .iter()
+                .filter(|(_, g, t)| {
+                    g.group == group.group
+                        && t.translation().distance(chest_transform.translation()) <= config.share_radius
+                })
+                .map(|(entity, _, _)| entity)
+                .collect(),
+            None => vec![event.actor],
+        };
+        if !nearby.contains(&event.actor) {
+            nearby.push(event.actor);
+        }
+        nearby.sort();
+
+        let recipients: Vec<Entity> = match (rule, opener_group) {
+            (LootRule::Personal, _) => nearby
+                .into_iter()
+                .filter(|entity| !chest.looted_by.contains(entity))
+                .collect(),
+            (LootRule::RoundRobin, Some(group)) => {
+                let turn = turns.next.entry(group.group).or_default();
+                let recipient = nearby[*turn % nearby.len()];
+                *turn = turn.wrapping_add(1);
+                vec![recipient]
+            }
+            _ => vec![event.actor],
+        };
+
+        for recipient in recipients {
+            for (item, count) in table.roll(&mut rng) {
+                awarded.send(LootAwardedEvent {
+                    recipient,
+                    chest: event.target,
+                    item,
+                    count,
+                });
+            }
+            if rule == LootRule::Personal {
+                chest.looted_by.insert(recipient);
+            }
+        }
+        if rule != LootRule::Personal {
+            chest.looted = true;
+            chest.emptied_at = Some(time.elapsed_secs());
+        }
+    }
+}
+
+fn refill_chests(time: Res<Time>, mut chests: Query<&mut Chest>) {
+    let now = time.elapsed_secs();
+    for mut chest in &mut chests {
+        let (Some(respawn), Some(emptied_at)) = (chest.respawn_seconds, chest.emptied_at) else {
+            continue;
+        };
+        if now - emptied_at >= respawn {
+            chest.looted = false;
+            chest.looted_by.clear();
+            chest.emptied_at = None;
+        }
+    }
+}
@@ -0,0 +1,167 @@
+//! Hinged doors. Using one toggles it; the leaf swings about its origin
+//! (the hinge) toward `open_angle`. A closed door blocks AI paths through
+//! its doorway until it opens again.
+
+use bevy::prelude::*;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use super::UseObjectEvent;
+use crate::ai_stage::AiPathQueue;
+
+#[derive(Component, Debug, Clone)]
+pub struct Door {
+    pub open: bool,
+    /// Radians about Y when fully open; negative swings the other way.
+    pub open_angle: f32,
+    /// Leaf width along its local +X from the hinge.
+    pub width: f32,
+    /// Seconds for a full swing.
+    pub swing_seconds: f32,
+    /// 0 closed .. 1 open.
+    pub progress: f32,
+    /// Rotation when closed, captured when the door is first seen.
+    pub closed_rotation: Option<Quat>,
+}
+
+impl Default for Door {
+    fn default() -> Self {
+        Self {
+            open: false,
+            open_angle: std::f32::consts::FRAC_PI_2,
+            width: 1.2,
+            swing_seconds: 0.8,
+            progress: 0.0,
+            closed_rotation: None,
+        }
+    }
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct DoorToggledEvent {
+    pub door: Entity,
+    pub actor: Option<Entity>,
+    pub open: bool,
+}
+
+/// Doorway segments (hinge to leaf end, in XZ) of closed doors. Shared with
+/// the AI path filter, which runs on the compute pool.
+#[derive(Resource, Clone, Default)]
+pub struct DoorNavLinks {
+    closed: Arc<RwLock<HashMap<Entity, (Vec2, Vec2)>>>,
+}
+
+impl DoorNavLinks {
+    fn set(&self, door: Entity, doorway: Option<(Vec2, Vec2)>) {
+        let Ok(mut closed) = self.closed.write() else {
+            return;
+        };
+        match doorway {
+            Some(segment) => closed.insert(door, segment),
+            None => closed.remove(&door),
+        };
+    }
+
+    /// Whether the path from `start` through `waypoints` crosses a closed
+    /// doorway.
+    pub fn blocks(&self, start: Vec3, waypoints: &[Vec3]) -> bool {
+        let Ok(closed) = self.closed.read() else {
+            return false;
+        };
+        if closed.is_empty() {
+            return false;
+        }
+        let mut from = start.xz();
+        for point in waypoints {
+            let to = point.xz();
+            if closed.values().any(|&(a, b)| segments_cross(from, to, a, b)) {
+                return true;
+            }
+            from = to;
+        }
+        false
+    }
+}
+
+fn segments_cross(p1: Vec2, p2: Vec2, q1: Vec2, q2: Vec2) -> bool {
+    let d1 = (p2 - p1).perp_dot(q1 - p1);
+    let d2 = (p2 - p1).perp_dot(q2 - p1);
+    let d3 = (q2 - q1).perp_dot(p1 - q1);
+    let d4 = (q2 - q1).perp_dot(p2 - q1);
+    d1 * d2 < 0.0 && d3 * d4 < 0.0
+}
+
+pub(super) struct DoorPlugin;
+
+impl Plugin for DoorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DoorNavLinks>()
+            .add_event::<DoorToggledEvent>()
+            .add_systems(Startup, install_door_path_filter)
+            .add_systems(Update, (toggle_doors, animate_doors, sync_door_nav_links).chain());
+    }
+}
+
+fn install_door_path_filter(links: Res<DoorNavLinks>, queue: Option<ResMut<AiPathQueue>>) {
+    if let Some(mut queue) = queue {
+        let links = links.clone();
+        queue.add_filter(Arc::new(move |start, waypoints| !links.blocks(start, waypoints)));
+    }
+}
+
+fn toggle_doors(
+    mut uses: EventReader<UseObjectEvent>,
+    mut doors: Query<&mut Door>,
+    mut toggled: EventWriter<DoorToggledEvent>,
+) {
+    for event in uses.read() {
+        if let Ok(mut door) = doors.get_mut(event.target) {
+            door.open = !door.open;
+            toggled.send(DoorToggledEvent {
+                door: event.target,
+                actor: Some(event.actor),
+                open: door.open,
+            });
+        }
+    }
+}
+
+fn animate_doors(time: Res<Time>, mut doors: Query<(&mut Door, &mut Transform)>) {
+    for (mut door, mut transform) in &mut doors {
+        let closed_rotation = *door.closed_rotation.get_or_insert(transform.rotation);
+        let target = if door.open { 1.0 } else { 0.0 };
+        if door.progress == target {
+            continue;
+        }
+        let step = time.delta_secs() / door.swing_seconds.max(0.01);
+        door.progress = if door.open {
+            (door.progress + step).min(1.0)
+        } else {
+            (door.progress - step).max(0.0)
+        };
+        // Ease in and out so the leaf doesn't snap at either end
+        let eased = door.progress * door.progress * (3.0 - 2.0 * door.progress);
+        transform.rotation = closed_rotation * Quat::from_rotation_y(door.open_angle * eased);
+    }
+}
+
+/// A door blocks paths once it starts closing and stops once it starts
+/// opening, so agents don't walk into a swinging leaf.
+fn sync_door_nav_links(
+    links: Res<DoorNavLinks>,
+    doors: Query<(Entity, &Door, &GlobalTransform), Changed<Door>>,
+    mut removed: RemovedComponents<Door>,
+) {
+    for (entity, door, transform) in &doors {
+        let doorway = (!door.open).then(|| {
+            let hinge = transform.translation();
+            let rotation = door.closed_rotation.unwrap_or(transform.rotation());
+            let end = hinge + rotation * Vec3::X * door.width;
+            (hinge.xz(), end.xz())
+        });
+        links.set(entity, doorway);
+    }
+    for entity in removed.read() {
+        links.set(entity, None);
+    }
+}
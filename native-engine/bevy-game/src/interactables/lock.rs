@@ -0,0 +1,161 @@
+//! Locks on doors and chests. A matching key opens one at once; without a
+//! key, an actor with [`Lockpicking`] can spend a few seconds picking it,
+//! with better odds the more their skill exceeds the lock's difficulty.
+
+use bevy::prelude::*;
+use rand::Rng;
+use std::collections::HashSet;
+
+use super::{Interactable, UseObjectEvent};
+
+/// Systems that unlock things; interaction resolves before them.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LockSet;
+
+#[derive(Component, Debug, Clone)]
+pub struct Lock {
+    pub locked: bool,
+    /// Item id of the key that opens it, if any.
+    pub key: Option<String>,
+    /// 0..=100; `None` can't be picked.
+    pub difficulty: Option<u32>,
+    /// Seconds a pick attempt takes.
+    pub pick_seconds: f32,
+}
+
+impl Default for Lock {
+    fn default() -> Self {
+        Self {
+            locked: true,
+            key: None,
+            difficulty: Some(25),
+            pick_seconds: 3.0,
+        }
+    }
+}
+
+impl Lock {
+    pub fn opens_with(&self, keys: Option<&KeyRing>) -> bool {
+        match (&self.key, keys) {
+            (Some(key), Some(ring)) => ring.keys.contains(key),
+            _ => false,
+        }
+    }
+
+    pub fn pickable(&self) -> bool {
+        self.difficulty.is_some()
+    }
+
+    /// Chance a pick by `skill` succeeds: even odds at equal skill, never
+    /// certain either way.
+    pub fn pick_chance(&self, skill: u32) -> f32 {
+        let Some(difficulty) = self.difficulty else {
+            return 0.0;
+        };
+        (0.5 + (skill as f32 - difficulty as f32) / 100.0).clamp(0.05, 0.95)
+    }
+}
+
+/// Key item ids the actor carries. The inventory keeps it in step with the
+/// key items in the bags.
+#[derive(Component, Debug, Clone, Default)]
+pub struct KeyRing {
+    pub keys: HashSet<String>,
+}
+
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Lockpicking {
+    pub skill: u32,
+}
+
+/// A pick in progress on the actor.
+#[derive(Component, Debug, Clone)]
+pub struct PickingLock {
+    pub target: Entity,
+    pub remaining: f32,
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct StartLockpickRequest {
+    pub actor: Entity,
+    pub target: Entity,
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct LockpickResultEvent {
+    pub actor: Entity,
+    pub target: Entity,
+    pub success: bool,
+}
+
+pub(super) struct LockPlugin;
+
+impl Plugin for LockPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<StartLockpickRequest>()
+            .add_event::<LockpickResultEvent>()
+            .add_systems(Update, (start_lockpicks, progress_lockpicks).chain().in_set(LockSet));
+    }
+}
+
+fn start_lockpicks(
+    mut commands: Commands,
+    mut requests: EventReader<StartLockpickRequest>,
+    locks: Query<&Lock>,
+    picking: Query<&PickingLock>,
+) {
+    for request in requests.read() {
+        let Ok(lock) = locks.get(request.target) else {
+            continue;
+        };
+        if picking.get(request.actor).is_ok_and(|p| p.target == request.target) {
+            continue;
+        }
+        commands.entity(request.actor).insert(PickingLock {
+            target: request.target,
+            remaining: lock.pick_seconds,
+        });
+    }
+}
+
+fn progress_lockpicks(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut actors: Query<(Entity, &mut PickingLock, &Lockpicking, &GlobalTransform)>,
+    mut locks: Query<(&mut Lock, &Interactable, &GlobalTransform)>,
+    mut results: EventWriter<LockpickResultEvent>,
+    mut uses: EventWriter<UseObjectEvent>,
+) {
+    let mut rng = rand::thread_rng();
+    for (actor, mut picking, skill, actor_transform) in &mut actors {
+        let Ok((mut lock, interactable, lock_transform)) = locks.get_mut(picking.target) else {
+            commands.entity(actor).remove::<PickingLock>();
+            continue;
+        };
+        // Walking away or someone else opening it ends the attempt
+        let distance = actor_transform.translation().distance(lock_transform.translation());
+        if distance > interactable.range || !lock.locked {
+            commands.entity(actor).remove::<PickingLock>();
+            continue;
+        }
+        picking.remaining -= time.delta_secs();
+        if picking.remaining > 0.0 {
+            continue;
+        }
+
+        let success = rng.gen::<f32>() < lock.pick_chance(skill.skill);
+        if success {
+            lock.locked = false;
+            uses.send(UseObjectEvent {
+                actor,
+                target: picking.target,
+            });
+        }
+        results.send(LockpickResultEvent {
+            actor,
+            target: picking.target,
+            success,
+        });
+        commands.entity(actor).remove::<PickingLock>();
+    }
+}
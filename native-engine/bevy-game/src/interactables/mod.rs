@@ -0,0 +1,157 @@
+//! Openable world objects: doors, chests and the locks on them.
+//!
+//! The player uses [`Action::Interact`] near an [`Interactable`]; that, or
+//! any other system (NPC scripts, quests), sends an [`InteractRequest`].
+//! A locked target is opened with a key from the actor's [`KeyRing`] or
+//! picked over a few seconds; anything else becomes a [`UseObjectEvent`]
+//! that the door and chest systems act on. Object state is replicated by
+//! [`WorldObjectId`], see [`replication`].
+
+pub mod chest;
+pub mod door;
+pub mod lock;
+pub mod replication;
+
+pub use chest::*;
+pub use door::*;
+pub use lock::*;
+pub use replication::*;
+
+use bevy::prelude::*;
+
+use crate::input_map::{Action, ActionState};
+use crate::Player;
+
+/// Something an actor can use from within `range`.
+#[derive(Component, Debug, Clone)]
+pub struct Interactable {
+    pub range: f32,
+}
+
+impl Default for Interactable {
+    fn default() -> Self {
+        Self { range: 3.0 }
+    }
+}
+
+/// `actor` wants to use `target`.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct InteractRequest {
+    pub actor: Entity,
+    pub target: Entity,
+}
+
+/// `actor` used `target`, which was in range and not locked.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct UseObjectEvent {
+    pub actor: Entity,
+    pub target: Entity,
+}
+
+/// Why an interaction didn't go through, for UI feedback.
+#[derive(Event, Debug, Clone)]
+pub struct InteractDeniedEvent {
+    pub actor: Entity,
+    pub target: Entity,
+    pub reason: String,
+}
+
+pub struct InteractablesPlugin;
+
+impl Plugin for InteractablesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<InteractRequest>()
+            .add_event::<UseObjectEvent>()
+            .add_event::<InteractDeniedEvent>()
+            .add_plugins((
+                lock::LockPlugin,
+                door::DoorPlugin,
+                chest::ChestPlugin,
+                replication::ReplicationPlugin,
+            ))
+            .add_systems(
+                Update,
+                (player_interact_input, resolve_interactions)
+                    .chain()
+                    .before(lock::LockSet),
+            );
+    }
+}
+
+fn player_interact_input(
+    actions: Option<Res<ActionState>>,
+    players: Query<(Entity, &GlobalTransform), With<Player>>,
+    interactables: Query<(Entity, &GlobalTransform, &Interactable)>,
+    mut requests: EventWriter<InteractRequest>,
+) {
+    if !actions.is_some_and(|a| a.just_pressed(Action::Interact)) {
+        return;
+    }
+    let Ok((player, player_transform)) = players.get_single() else {
+        return;
+    };
+    let origin = player_transform.translation();
+    let nearest = interactables
+        .iter()
+        .map(|(entity, transform, interactable)| {
+            (entity, transform.translation().distance(origin), interactable.range)
+        })
+        .filter(|(_, distance, range)| distance <= range)
+        .min_by(|a, b| a.1.total_cmp(&b.1));
+    if let Some((target, _, _)) = nearest {
+        requests.send(InteractRequest { actor: player, target });
+    }
+}
+
+fn resolve_interactions(
+    mut requests: EventReader<InteractRequest>,
+    transforms: Query<&GlobalTransform>,
+    interactables: Query<&Interactable>,
+    mut locks: Query<&mut Lock>,
+    key_rings: Query<&KeyRing>,
+    lockpickers: Query<&Lockpicking>,
+    mut picks: EventWriter<StartLockpickRequest>,
+    mut uses: EventWriter<UseObjectEvent>,
+    mut denied: EventWriter<InteractDeniedEvent>,
+) {
+    for request in requests.read() {
+        let (Ok(interactable), Ok(actor), Ok(target)) = (
+            interactables.get(request.target),
+            transforms.get(request.actor),
+            transforms.get(request.target),
+        ) else {
+            continue;
+        };
+        let mut deny = |reason: &str| {
+            denied.send(InteractDeniedEvent {
+                actor: request.actor,
+                target: request.target,
+                reason: reason.to_string(),
+            });
+        };
+        if actor.translation().distance(target.translation()) > interactable.range {
+            deny("Too far away");
+            continue;
+        }
+        if let Ok(mut lock) = locks.get_mut(request.target) {
+            if lock.locked {
+                if lock.opens_with(key_rings.get(request.actor).ok()) {
+                    lock.locked = false;
+                } else if lockpickers.contains(request.actor) && lock.pickable() {
+                    picks.send(StartLockpickRequest {
+                        actor: request.actor,
+                        target: request.target,
+                    });
+                    continue;
+                } else {
+                    deny("Locked");
+                    continue;
+                }
+            }
+        }
+        uses.send(UseObjectEvent {
+            actor: request.actor,
+            target: request.target,
+        });
+    }
+}
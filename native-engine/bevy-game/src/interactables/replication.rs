@@ -0,0 +1,124 @@
+//! Door, lock and chest state shared between clients. Objects carry a
+//! [`WorldObjectId`] agreed by content (the same id on every client).
+//! Local changes are collected in [`WorldObjectOutbox`], latest state per
+//! object, for the match socket to send with [`WORLD_OBJECT_OP_CODE`];
+//! states that arrive with that op code are applied as
+//! [`WorldObjectStateReceived`] events.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+use super::{Chest, Door, Lock};
+
+/// Match data op code for [`WorldObjectState`] messages.
+pub const WORLD_OBJECT_OP_CODE: i64 = 20;
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct WorldObjectId(pub u64);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorldObjectState {
+    pub id: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub open: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locked: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub looted: Option<bool>,
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct WorldObjectStateReceived(pub WorldObjectState);
+
+/// States to broadcast, replaced per object until sent.
+#[derive(Resource, Debug, Default)]
+pub struct WorldObjectOutbox {
+    pending: HashMap<u64, WorldObjectState>,
+}
+
+impl WorldObjectOutbox {
+    pub fn drain(&mut self) -> Vec<WorldObjectState> {
+        self.pending.drain().map(|(_, state)| state).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+/// Objects changed by the network this frame, so they aren't echoed back.
+#[derive(Resource, Debug, Default)]
+struct AppliedRemotely(HashSet<u64>);
+
+pub(super) struct ReplicationPlugin;
+
+impl Plugin for ReplicationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WorldObjectOutbox>()
+            .init_resource::<AppliedRemotely>()
+            .add_event::<WorldObjectStateReceived>()
+            .add_systems(PreUpdate, apply_remote_states)
+            .add_systems(PostUpdate, collect_local_changes);
+    }
+}
+
+fn apply_remote_states(
+    mut received: EventReader<WorldObjectStateReceived>,
+    mut applied: ResMut<AppliedRemotely>,
+    mut objects: Query<(&WorldObjectId, Option<&mut Door>, Option<&mut Lock>, Option<&mut Chest>)>,
+) {
+    applied.0.clear();
+    if received.is_empty() {
+        return;
+    }
+    let states: HashMap<u64, &WorldObjectState> = received.read().map(|e| (e.0.id, &e.0)).collect();
+    for (id, door, lock, chest) in &mut objects {
+        let Some(state) = states.get(&id.0) else {
+            continue;
+        };
+        if let (Some(mut door), Some(open)) = (door, state.open) {
+            door.open = open;
+        }
+        if let (Some(mut lock), Some(locked)) = (lock, state.locked) {
+            lock.locked = locked;
+        }
+        if let (Some(mut chest), Some(looted)) = (chest, state.looted) {
+            chest.looted = looted;
+        }
+        applied.0.insert(id.0);
+    }
+}
+
+/// Sends an object's full state when any part of it differs from what this
+/// client last saw, so a door's swing (which touches `Door` every frame)
+/// is sent once.
+fn collect_local_changes(
+    applied: Res<AppliedRemotely>,
+    mut outbox: ResMut<WorldObjectOutbox>,
+    mut known: Local<HashMap<u64, WorldObjectState>>,
+    objects: Query<
+        (&WorldObjectId, Option<&Door>, Option<&Lock>, Option<&Chest>),
+        Or<(Changed<Door>, Changed<Lock>, Changed<Chest>)>,
+    >,
+) {
+    for (id, door, lock, chest) in &objects {
+        let current = WorldObjectState {
+            id: id.0,
+            open: door.map(|d| d.open),
+            locked: lock.map(|l| l.locked),
+            looted: chest.map(|c| c.looted),
+        };
+        let previous = known.insert(id.0, current.clone());
+        // The first sighting is the content's initial state, which every
+        // client already has; remote changes came from the network
+        if previous.is_none() || previous.as_ref() == Some(&current) || applied.0.contains(&id.0) {
+            continue;
+        }
+        outbox.pending.insert(id.0, current);
+    }
+}
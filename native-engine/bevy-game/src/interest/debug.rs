@@ -0,0 +1,133 @@
+//! What a client knows, for checking the culling rules against reports of
+//! players popping in or of ESP still working. `interest <player>` prints a
+//! summary and focuses that player; on a server that renders, the focused
+//! player gets rings at the view and always-visible distances, green lines
+//! to what it's sent and red (walls) or purple (stealth) lines to what it
+//! isn't. `interest off` clears the focus.
+
+use bevy::prelude::*;
+use std::f32::consts::FRAC_PI_2;
+
+use super::{ClientInterest, CullReason, InterestConfig, InterestObserver};
+use crate::console::{
+    ArgCompletion, CommandPermission, ConsoleAppExt, ConsoleCommand, ConsoleCommandEvent, ConsoleOutputEvent,
+};
+
+/// The player whose interest is drawn.
+#[derive(Resource, Debug, Default)]
+pub struct InterestDebug {
+    pub focus: Option<String>,
+}
+
+pub struct InterestDebugPlugin;
+
+impl Plugin for InterestDebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<InterestDebug>()
+            .add_console_command(
+                ConsoleCommand::new("interest", "What a player's client is sent, and what's culled")
+                    .usage("[<player>|off]")
+                    .permission(CommandPermission::Admin)
+                    .complete(vec![ArgCompletion::Values(vec!["off".to_string()])]),
+            )
+            .add_systems(Update, interest_command);
+
+        // Gizmos only exist when rendering
+        if app.is_plugin_added::<bevy::gizmos::GizmoPlugin>() {
+            app.add_systems(Update, draw_interest_gizmos);
+        }
+    }
+}
+
+fn describe(client: &str, interest: &ClientInterest) -> String {
+    let count = |reason| interest.culled.iter().filter(|(_, r)| *r == reason).count();
+    format!(
+        "{} is sent {} of {} nearby characters; {} hidden by walls, {} by stealth",
+        client,
+        interest.visible_count(),
+        interest.visible_count() + interest.culled.len(),
+        count(CullReason::Occluded),
+        count(CullReason::Stealthed)
+    )
+}
+
+fn interest_command(
+    mut debug: ResMut<InterestDebug>,
+    observers: Query<(&InterestObserver, &ClientInterest)>,
+    mut events: EventReader<ConsoleCommandEvent>,
+    mut output: EventWriter<ConsoleOutputEvent>,
+) {
+    for event in events.read() {
+        if event.name != "interest" {
+            continue;
+        }
+        let result: Result<String, String> = match event.args.first().map(String::as_str) {
+            None => {
+                let lines: Vec<String> = observers
+                    .iter()
+                    .take(10)
+                    .map(|(observer, interest)| describe(&observer.client, interest))
+                    .collect();
+                if lines.is_empty() {
+                    Ok("No clients connected".to_string())
+                } else {
+                    Ok(lines.join("\n"))
+                }
+            }
+            Some("off") => {
+                debug.focus = None;
+                Ok("Interest display off".to_string())
+            }
+            Some(player) => {
+                let found = observers.iter().find(|(observer, _)| observer.client.eq_ignore_ascii_case(player));
+                match found {
+                    Some((observer, interest)) => {
+                        debug.focus = Some(observer.client.clone());
+                        Ok(describe(&observer.client, interest))
+                    }
+                    None => Err(format!("'{}' isn't connected", player)),
+                }
+            }
+        };
+        output.send(match result {
+            Ok(text) => event.reply(text),
+            Err(text) => event.error(format!("{}: {}", event.name, text)),
+        });
+    }
+}
+
+fn draw_interest_gizmos(
+    mut gizmos: Gizmos,
+    debug: Res<InterestDebug>,
+    config: Res<InterestConfig>,
+    observers: Query<(&InterestObserver, &ClientInterest, &GlobalTransform)>,
+    transforms: Query<&GlobalTransform>,
+) {
+    let Some(focus) = &debug.focus else {
+        return;
+    };
+    let Some((_, interest, transform)) = observers.iter().find(|(observer, ..)| &observer.client == focus) else {
+        return;
+    };
+    let eye = Vec3::Y * config.eye_height;
+    let position = transform.translation();
+    let flat = Quat::from_rotation_x(FRAC_PI_2);
+    let ring = Isometry3d::new(position + Vec3::Y * 0.1, flat);
+    gizmos.circle(ring, config.view_distance, Color::srgb(0.3, 0.6, 1.0));
+    gizmos.circle(ring, config.always_visible_range, Color::srgb(0.3, 0.9, 0.9));
+
+    for entity in interest.visible() {
+        if let Ok(target) = transforms.get(entity) {
+            gizmos.line(position + eye, target.translation() + eye, Color::srgb(0.3, 0.9, 0.3));
+        }
+    }
+    for &(entity, reason) in &interest.culled {
+        let color = match reason {
+            CullReason::Occluded => Color::srgb(0.95, 0.2, 0.15),
+            CullReason::Stealthed => Color::srgb(0.7, 0.3, 0.9),
+        };
+        if let Ok(target) = transforms.get(entity) {
+            gizmos.line(position + eye, target.translation() + eye, color);
+        }
+    }
+}
@@ -0,0 +1,232 @@
+//! Interest management on the server: each client is only sent the
+//! characters it could actually perceive, so a wallhack or ESP tool reading
+//! the client's memory has nothing extra to show.
+//!
+//! Every connected player has an [`InterestObserver`]; the match socket adds
+//! it on join. A few times a second each observer's [`ClientInterest`] is
+//! rebuilt from the characters within `view_distance`:
+//!
+//! - Anything within `always_visible_range` is known, walls or not, so
+//!   footsteps and melee behind a corner still work.
+//! - Beyond that a character must be in line of sight, checked with a ray
+//!   between the two characters' eyes.
+//! - A character with [`Stealth`] is only known within its detection range,
+//!   and even then only in line of sight.
+//!
+//! A character stays known for `linger_seconds` after it drops out of sight
+//! so it doesn't flicker at corners. The match socket sends state only for
+//! [`ClientInterest::visible`] entities, spawns [`ClientInterest::entered`]
+//! ones and despawns [`ClientInterest::left`] ones on that client.
+//!
+//! The `debug` module shows what a client knows, from the `interest` console command
+//! and as gizmos on a server that renders.
+
+mod debug;
+
+pub use debug::*;
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use std::collections::HashMap;
+
+use crate::Character;
+
+#[derive(Resource, Debug, Clone)]
+pub struct InterestConfig {
+    pub view_distance: f32,
+    /// Closer than this, characters are known through walls.
+    pub always_visible_range: f32,
+    pub line_of_sight: bool,
+    /// Height above the feet that line of sight is checked from and to.
+    pub eye_height: f32,
+    pub linger_seconds: f32,
+    /// Seconds between updates.
+    pub update_interval: f32,
+    /// Line of sight rays per update across all observers. Pairs past the
+    /// budget keep what they had until the next update.
+    pub max_rays_per_update: usize,
+}
+
+impl Default for InterestConfig {
+    fn default() -> Self {
+        Self {
+            view_distance: 120.0,
+            always_visible_range: 12.0,
+            line_of_sight: true,
+            eye_height: 1.6,
+            linger_seconds: 1.5,
+            update_interval: 0.2,
+            max_rays_per_update: 4000,
+        }
+    }
+}
+
+/// A connected client's character.
+#[derive(Component, Debug, Clone)]
+pub struct InterestObserver {
+    /// Account name, as GM tools and console commands take it.
+    pub client: String,
+}
+
+/// Hidden from observers further away than `detection_range`.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Stealth {
+    pub detection_range: f32,
+}
+
+/// Why a nearby character wasn't sent to a client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CullReason {
+    Occluded,
+    Stealthed,
+}
+
+/// What one client knows about, rebuilt every update.
+#[derive(Component, Debug, Clone, Default)]
+pub struct ClientInterest {
+    /// Known entities and when each was last seen, in elapsed seconds.
+    known: HashMap<Entity, f64>,
+    /// Became known in the last update.
+    pub entered: Vec<Entity>,
+    /// Stopped being known in the last update.
+    pub left: Vec<Entity>,
+    /// Characters in view distance that were held back, and why.
+    pub culled: Vec<(Entity, CullReason)>,
+}
+
+impl ClientInterest {
+    pub fn is_visible(&self, entity: Entity) -> bool {
+        self.known.contains_key(&entity)
+    }
+
+    pub fn visible(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.known.keys().copied()
+    }
+
+    pub fn visible_count(&self) -> usize {
+        self.known.len()
+    }
+}
+
+pub struct InterestPlugin;
+
+impl Plugin for InterestPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<InterestConfig>()
+            .add_systems(Update, (add_client_interest, update_interest).chain())
+            .add_plugins(InterestDebugPlugin);
+    }
+}
+
+fn add_client_interest(
+    mut commands: Commands,
+    observers: Query<Entity, (With<InterestObserver>, Without<ClientInterest>)>,
+) {
+    for entity in &observers {
+        commands.entity(entity).insert(ClientInterest::default());
+    }
+}
+
+fn clear_line(context: &RapierContext, (observer, target): (Entity, Entity), from: Vec3, to: Vec3) -> bool {
+    let offset = to - from;
+    let distance = offset.length();
+    if distance < 1e-3 {
+        return true;
+    }
+    let filter = QueryFilter::default()
+        .exclude_sensors()
+        .exclude_dynamic()
+        .exclude_rigid_body(observer)
+        .exclude_rigid_body(target);
+    context.cast_ray(from, offset / distance, distance, true, filter).is_none()
+}
+
+fn update_interest(
+    config: Res<InterestConfig>,
+    time: Res<Time>,
+    rapier_context: ReadRapierContext,
+    mut since_update: Local<f32>,
+    characters: Query<(Entity, &GlobalTransform, Option<&Stealth>), With<Character>>,
+    mut observers: Query<(Entity, &GlobalTransform, &mut ClientInterest), With<InterestObserver>>,
+) {
+    *since_update += time.delta_secs();
+    if *since_update < config.update_interval {
+        return;
+    }
+    *since_update = 0.0;
+    let now = time.elapsed_secs_f64();
+    let context = rapier_context.single().ok();
+    let eye = Vec3::Y * config.eye_height;
+    let cell_size = config.view_distance.max(1.0);
+    let cell = |position: Vec3| (position.xz() / cell_size).floor().as_ivec2();
+
+    let mut grid: HashMap<IVec2, Vec<(Entity, Vec3, Option<f32>)>> = HashMap::new();
+    for (entity, transform, stealth) in &characters {
+        let position = transform.translation();
+        grid.entry(cell(position))
+            .or_default()
+            .push((entity, position, stealth.map(|s| s.detection_range)));
+    }
+
+    let mut rays_left = config.max_rays_per_update;
+    for (observer, observer_transform, mut interest) in &mut observers {
+        let interest = &mut *interest;
+        let position = observer_transform.translation();
+        let center = cell(position);
+        let mut known = HashMap::with_capacity(interest.known.len());
+        interest.culled.clear();
+
+        let nearby = (-1..=1)
+            .flat_map(|x| (-1..=1).map(move |z| center + IVec2::new(x, z)))
+            .filter_map(|cell| grid.get(&cell))
+            .flatten();
+        for &(entity, target_position, detection_range) in nearby {
+            let distance = position.distance(target_position);
+            if entity == observer || distance > config.view_distance {
+                continue;
+            }
+            let previously = interest.known.get(&entity).copied();
+
+            let close = detection_range.is_none() && distance <= config.always_visible_range;
+            let verdict = if detection_range.is_some_and(|range| distance > range) {
+                Err(CullReason::Stealthed)
+            } else if close || !config.line_of_sight {
+                Ok(())
+            } else if let Some(context) = &context {
+                if rays_left == 0 {
+                    // Out of budget: keep the last answer
+                    previously.map(|_| ()).ok_or(CullReason::Occluded)
+                } else {
+                    rays_left -= 1;
+                    let line = (observer, entity);
+                    if clear_line(context, line, position + eye, target_position + eye) {
+                        Ok(())
+                    } else {
+                        Err(CullReason::Occluded)
+                    }
+                }
+            } else {
+                Ok(())
+            };
+
+            match verdict {
+                Ok(()) => {
+                    known.insert(entity, now);
+                }
+                Err(reason) => {
+                    let lingering = previously.filter(|seen| now - seen < config.linger_seconds as f64);
+                    match lingering {
+                        Some(seen) => {
+                            known.insert(entity, seen);
+                        }
+                        None => interest.culled.push((entity, reason)),
+                    }
+                }
+            }
+        }
+
+        interest.entered = known.keys().filter(|e| !interest.known.contains_key(e)).copied().collect();
+        interest.left = interest.known.keys().filter(|e| !known.contains_key(e)).copied().collect();
+        interest.known = known;
+    }
+}
@@ -0,0 +1,146 @@
+//! The game's end of the launcher's log channel. When the launcher starts
+//! the game it passes a named pipe (a Unix socket elsewhere) in
+//! `AAA_LAUNCHER_PIPE`; the game sends its log there as JSON lines so the
+//! launcher window can show it, and sends a `fatal` line before exiting on
+//! an error the launcher knows how to explain, like a failed Atom check.
+//!
+//! Started without the launcher, none of this does anything.
+
+use bevy::log::BoxedLayer;
+use bevy::prelude::*;
+use serde::Serialize;
+use std::io::Write;
+use std::sync::mpsc::{self, SyncSender};
+use std::sync::{Arc, Mutex, OnceLock};
+use ::tracing::field::{Field, Visit};
+use ::tracing::{Event, Subscriber};
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::Layer;
+
+pub const LAUNCHER_PIPE_ENV: &str = "AAA_LAUNCHER_PIPE";
+
+/// Log lines waiting to be written. Past this they're dropped rather than
+/// holding the game up on a launcher that stopped reading.
+const QUEUE_LENGTH: usize = 1024;
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum LauncherMessage {
+    Log { level: String, target: String, message: String },
+    Fatal { reason: String, message: String },
+}
+
+/// Set with the resource, for code outside the ECS like the panic hook.
+static CONNECTED: OnceLock<LauncherLink> = OnceLock::new();
+
+/// The connection to the launcher, present when it started the game.
+#[derive(Resource, Clone)]
+pub struct LauncherLink {
+    stream: Arc<Mutex<Box<dyn Write + Send>>>,
+    queue: SyncSender<LauncherMessage>,
+}
+
+impl LauncherLink {
+    fn connect() -> Option<Self> {
+        let name = std::env::var(LAUNCHER_PIPE_ENV).ok().filter(|name| !name.is_empty())?;
+        let stream = match open(&name) {
+            Ok(stream) => Arc::new(Mutex::new(stream)),
+            Err(e) => {
+                eprintln!("Couldn't connect to the launcher at {}: {}", name, e);
+                return None;
+            }
+        };
+
+        let (queue, messages) = mpsc::sync_channel(QUEUE_LENGTH);
+        let writer = stream.clone();
+        std::thread::spawn(move || {
+            for message in messages {
+                if write_message(&writer, &message).is_err() {
+                    break;
+                }
+            }
+        });
+        Some(Self { stream, queue })
+    }
+
+    fn log(&self, level: &::tracing::Level, target: &str, message: String) {
+        let _ = self.queue.try_send(LauncherMessage::Log {
+            level: level.to_string(),
+            target: target.to_string(),
+            message,
+        });
+    }
+
+    /// Tells the launcher why the game is about to exit. Written straight
+    /// away, since the game may be gone before the queue drains.
+    pub fn fatal(&self, reason: &str, message: &str) {
+        let message = LauncherMessage::Fatal {
+            reason: reason.to_string(),
+            message: message.to_string(),
+        };
+        let _ = write_message(&self.stream, &message);
+    }
+}
+
+fn write_message(stream: &Mutex<Box<dyn Write + Send>>, message: &LauncherMessage) -> std::io::Result<()> {
+    let mut line = serde_json::to_vec(message)?;
+    line.push(b'\n');
+    let mut stream = stream.lock().map_err(|_| std::io::Error::other("launcher link poisoned"))?;
+    stream.write_all(&line)?;
+    stream.flush()
+}
+
+#[cfg(windows)]
+fn open(name: &str) -> std::io::Result<Box<dyn Write + Send>> {
+    Ok(Box::new(std::fs::OpenOptions::new().write(true).open(name)?))
+}
+
+#[cfg(unix)]
+fn open(name: &str) -> std::io::Result<Box<dyn Write + Send>> {
+    Ok(Box::new(std::os::unix::net::UnixStream::connect(name)?))
+}
+
+/// The launcher link, if the launcher started the game.
+pub fn connected() -> Option<&'static LauncherLink> {
+    CONNECTED.get()
+}
+
+/// Collects an event's `message` field.
+#[derive(Default)]
+pub(crate) struct MessageVisitor(pub String);
+
+impl Visit for MessageVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.0 = value.to_string();
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            self.0 = format!("{:?}", value);
+        }
+    }
+}
+
+struct LauncherLogLayer {
+    link: LauncherLink,
+}
+
+impl<S: Subscriber> Layer<S> for LauncherLogLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+        let metadata = event.metadata();
+        self.link.log(metadata.level(), metadata.target(), visitor.0);
+    }
+}
+
+/// `LogPlugin::custom_layer` hook: forwards the log to the launcher and
+/// inserts [`LauncherLink`] if the launcher started the game.
+pub fn launcher_log_layer(app: &mut App) -> Option<BoxedLayer> {
+    let link = LauncherLink::connect()?;
+    app.insert_resource(link.clone());
+    let _ = CONNECTED.set(link.clone());
+    Some(Box::new(LauncherLogLayer { link }))
+}
@@ -0,0 +1,22 @@
+# Built-in English strings. Language files under content/locale/<language>/
+# override these message by message.
+
+## Log overlay
+
+ui-log-title = === GAME LOG (F12 to toggle) ===
+ui-log-title-open = === GAME LOG (F12 to hide) ===
+ui-log-empty = (No log messages yet)
+
+## Loading screen
+
+ui-loading-progress = Loading... { $loaded } / { $total }
+
+## Content hot reload
+
+system-reload-applied = Reloaded { $path }: { $summary }
+system-reload-failed = Reload of { $path } failed: { $error }
+system-reload-restart = { $path } changed; no live reload for it, restart to apply
+
+## Localization
+
+system-language-changed = Language set to { $language }
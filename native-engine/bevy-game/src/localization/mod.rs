@@ -0,0 +1,443 @@
+//! Player-facing text from Fluent string tables. Language files are
+//! `content/locale/<language>/*.ftl` in any content pack; a message missing
+//! from the chosen language falls back to its base language and then to the
+//! source language, whose UI and system strings are built in.
+//!
+//! Content keeps its text inline in the source language (quest titles, dialog
+//! lines, item names); translations address it by derived ids such as
+//! `quest-<id>-title` or `dialog-<tree>-<node>`, see the helpers on
+//! [`Localization`].
+//!
+//! Pseudo-localization (`--pseudo-localize`, or the `qps-ploc` language)
+//! accents, stretches and brackets every string that goes through this
+//! module, so hardcoded text stands out as the plain English left on screen.
+
+use bevy::prelude::*;
+use fluent::concurrent::FluentBundle;
+use fluent::{fluent_args, FluentArgs, FluentResource};
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use unic_langid::LanguageIdentifier;
+
+use crate::authoring::narrative::{DialogNodeDef, QuestDef};
+use crate::content_packs::ContentPacks;
+use crate::hot_reload::{ContentChangedEvent, ContentReloadedEvent};
+use crate::settings::UserSettings;
+use crate::GameLogOverlay;
+
+/// Language code that selects pseudo-localization from the settings.
+pub const PSEUDO_LANGUAGE: &str = "qps-ploc";
+
+const BUILTIN_STRINGS: &str = include_str!("en.ftl");
+
+#[derive(Resource, Debug, Clone)]
+pub struct LocalizationConfig {
+    /// Holds one directory per language: `content/locale/<language>/*.ftl`.
+    pub directory: PathBuf,
+    /// Language the built-in strings and inline content text are written in.
+    pub source_language: String,
+    /// Pseudo-localize whatever language is selected.
+    pub pseudo: bool,
+}
+
+impl Default for LocalizationConfig {
+    fn default() -> Self {
+        Self {
+            directory: PathBuf::from("content").join("locale"),
+            source_language: "en".to_string(),
+            pseudo: std::env::args().any(|arg| arg == "--pseudo-localize"),
+        }
+    }
+}
+
+/// The active language's string tables.
+#[derive(Resource)]
+pub struct Localization {
+    language: String,
+    pseudo: bool,
+    /// The requested language first, then its fallbacks down to the source language.
+    bundles: Vec<FluentBundle<FluentResource>>,
+    /// Languages with string tables in any active pack, plus the source language.
+    available: Vec<String>,
+    /// Ids already warned about, so a missing string in a UI redrawn every
+    /// frame is reported once.
+    reported_missing: Mutex<HashSet<String>>,
+}
+
+impl Default for Localization {
+    /// Only the built-in source strings; replaced once content is loaded.
+    fn default() -> Self {
+        let config = LocalizationConfig::default();
+        let no_packs = ContentPacks {
+            content_root: PathBuf::from("content"),
+            packs: Vec::new(),
+        };
+        let (localization, _) = build_localization(&config, &no_packs, &config.source_language);
+        localization
+    }
+}
+
+impl Localization {
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    pub fn is_pseudo(&self) -> bool {
+        self.pseudo
+    }
+
+    pub fn available_languages(&self) -> &[String] {
+        &self.available
+    }
+
+    pub fn has(&self, id: &str) -> bool {
+        self.bundles.iter().any(|bundle| bundle.has_message(id))
+    }
+
+    fn lookup(&self, id: &str, args: Option<&FluentArgs>) -> Option<String> {
+        self.bundles.iter().find_map(|bundle| {
+            let pattern = bundle.get_message(id)?.value()?;
+            let mut errors = Vec::new();
+            let text = bundle.format_pattern(pattern, args, &mut errors);
+            if !errors.is_empty() {
+                debug!("Localization: errors formatting '{}': {:?}", id, errors);
+            }
+            Some(text.into_owned())
+        })
+    }
+
+    fn decorate(&self, text: String) -> String {
+        if self.pseudo {
+            format!("[{}]", text)
+        } else {
+            text
+        }
+    }
+
+    fn report_missing(&self, id: &str) {
+        if let Ok(mut reported) = self.reported_missing.lock() {
+            if reported.insert(id.to_string()) {
+                warn!("Localization: no string '{}' for {}", id, self.language);
+            }
+        }
+    }
+
+    /// The message `id`, or the id itself when no table has it.
+    pub fn text(&self, id: &str) -> String {
+        self.text_with_args(id, None)
+    }
+
+    pub fn text_with(&self, id: &str, args: &FluentArgs) -> String {
+        self.text_with_args(id, Some(args))
+    }
+
+    fn text_with_args(&self, id: &str, args: Option<&FluentArgs>) -> String {
+        match self.lookup(id, args) {
+            Some(text) => self.decorate(text),
+            None => {
+                self.report_missing(id);
+                id.to_string()
+            }
+        }
+    }
+
+    /// The message `id`, or `fallback` (source-language text) when it isn't
+    /// translated. Content text goes through here; an untranslated line is
+    /// expected, so it isn't reported.
+    pub fn text_or(&self, id: &str, fallback: &str) -> String {
+        match self.lookup(id, None) {
+            Some(text) => self.decorate(text),
+            None if self.pseudo => self.decorate(pseudo_transform(fallback).into_owned()),
+            None => fallback.to_string(),
+        }
+    }
+
+    pub fn quest_title(&self, quest: &QuestDef) -> String {
+        self.text_or(&message_id(&["quest", &quest.id, "title"]), &quest.title)
+    }
+
+    pub fn quest_description(&self, quest: &QuestDef) -> String {
+        self.text_or(&message_id(&["quest", &quest.id, "description"]), &quest.description)
+    }
+
+    /// Objective text of a quest node.
+    pub fn quest_text(&self, quest_id: &str, node_id: &str, fallback: &str) -> String {
+        self.text_or(&message_id(&["quest", quest_id, node_id]), fallback)
+    }
+
+    /// Label of a branch option, `index` in the node's option order.
+    pub fn quest_option(&self, quest_id: &str, node_id: &str, index: usize, fallback: &str) -> String {
+        self.text_or(&message_id(&["quest", quest_id, node_id, "option", &index.to_string()]), fallback)
+    }
+
+    pub fn dialog_text(&self, tree_id: &str, node: &DialogNodeDef) -> String {
+        self.text_or(&message_id(&["dialog", tree_id, &node.id]), &node.text)
+    }
+
+    /// Text of a dialog choice, `index` in the node's choice order.
+    pub fn dialog_choice(&self, tree_id: &str, node_id: &str, index: usize, fallback: &str) -> String {
+        self.text_or(&message_id(&["dialog", tree_id, node_id, "choice", &index.to_string()]), fallback)
+    }
+
+    pub fn speaker_name(&self, speaker: &str) -> String {
+        self.text_or(&message_id(&["speaker", speaker]), speaker)
+    }
+
+    pub fn item_name(&self, item_id: &str, fallback: &str) -> String {
+        self.text_or(&message_id(&["item", item_id]), fallback)
+    }
+}
+
+/// Joins content ids into a Fluent message id. Fluent ids allow letters,
+/// digits, `-` and `_`, so anything else (dots, spaces) becomes `-`.
+pub fn message_id(parts: &[&str]) -> String {
+    parts
+        .iter()
+        .map(|part| {
+            part.chars()
+                .map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '-' })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Accents letters and doubles vowels. Doubling makes text about a third
+/// longer, roughly what German or Finnish need, so layouts that clip
+/// translated text show it in English builds already.
+fn pseudo_transform(text: &str) -> Cow<'_, str> {
+    let mut out = String::with_capacity(text.len() * 2);
+    for c in text.chars() {
+        let accented = match c {
+            'a' => 'á',
+            'e' => 'é',
+            'i' => 'í',
+            'o' => 'ó',
+            'u' => 'ú',
+            'A' => 'Á',
+            'E' => 'É',
+            'I' => 'Í',
+            'O' => 'Ó',
+            'U' => 'Ú',
+            'c' => 'ç',
+            'C' => 'Ç',
+            'n' => 'ñ',
+            'N' => 'Ñ',
+            's' => 'š',
+            'S' => 'Š',
+            'y' => 'ý',
+            'Y' => 'Ý',
+            'z' => 'ž',
+            'Z' => 'Ž',
+            other => other,
+        };
+        out.push(accented);
+        if matches!(c, 'a' | 'e' | 'i' | 'o' | 'u' | 'A' | 'E' | 'I' | 'O' | 'U') {
+            out.push(accented);
+        }
+    }
+    Cow::Owned(out)
+}
+
+/// `de-AT` falls back to `de`, then to the source language.
+fn language_chain(language: &str, source: &str) -> Vec<String> {
+    let mut chain = vec![language.to_string()];
+    if let Some((base, _)) = language.split_once('-') {
+        chain.push(base.to_string());
+    }
+    chain.push(source.to_string());
+    chain.dedup();
+    chain
+}
+
+fn read_string_table(path: &Path) -> Result<FluentResource, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    FluentResource::try_new(text).map_err(|(_, errors)| {
+        let first = errors.first().map(|e| format!("{:?}", e.kind)).unwrap_or_default();
+        format!("{} syntax error(s), first: {}", errors.len(), first)
+    })
+}
+
+/// Builds the bundles for `language`; the second value lists files that
+/// couldn't be read, which are skipped.
+fn build_localization(
+    config: &LocalizationConfig,
+    packs: &ContentPacks,
+    language: &str,
+) -> (Localization, Vec<String>) {
+    let pseudo = config.pseudo || language == PSEUDO_LANGUAGE;
+    let chain = if pseudo {
+        vec![config.source_language.clone()]
+    } else {
+        language_chain(language, &config.source_language)
+    };
+
+    let mut problems = Vec::new();
+    let mut bundles = Vec::new();
+    for code in &chain {
+        let langid: LanguageIdentifier = match code.parse() {
+            Ok(langid) => langid,
+            Err(e) => {
+                problems.push(format!("invalid language code '{}': {}", code, e));
+                continue;
+            }
+        };
+        let mut bundle = FluentBundle::new_concurrent(vec![langid]);
+        // Bevy's text renderer has no use for the bidi isolation marks
+        bundle.set_use_isolating(false);
+        if pseudo {
+            bundle.set_transform(Some(pseudo_transform));
+        }
+
+        let is_source = *code == config.source_language;
+        if is_source {
+            if let Ok(builtin) = FluentResource::try_new(BUILTIN_STRINGS.to_string()) {
+                bundle.add_resource_overriding(builtin);
+            }
+        }
+        let files = packs.files(&config.directory.join(code), "ftl");
+        if files.is_empty() && !is_source {
+            continue;
+        }
+        for path in files {
+            match read_string_table(&path) {
+                Ok(resource) => bundle.add_resource_overriding(resource),
+                Err(e) => problems.push(format!("{}: {}", path.display(), e)),
+            }
+        }
+        bundles.push(bundle);
+    }
+
+    let mut available: Vec<String> = packs
+        .directories(&config.directory)
+        .iter()
+        .filter_map(|dir| std::fs::read_dir(dir).ok())
+        .flat_map(|entries| entries.flatten())
+        .filter(|entry| entry.path().is_dir())
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .chain(std::iter::once(config.source_language.clone()))
+        .collect();
+    available.sort();
+    available.dedup();
+
+    let localization = Localization {
+        language: language.to_string(),
+        pseudo,
+        bundles,
+        available,
+        reported_missing: Mutex::new(HashSet::new()),
+    };
+    (localization, problems)
+}
+
+/// Sent after the string tables change, so UI that caches text can redraw.
+#[derive(Event, Debug, Clone)]
+pub struct LanguageChangedEvent {
+    pub language: String,
+}
+
+pub struct LocalizationPlugin;
+
+impl Plugin for LocalizationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LocalizationConfig>()
+            .init_resource::<ContentPacks>()
+            .init_resource::<Localization>()
+            .add_event::<LanguageChangedEvent>()
+            .add_event::<ContentChangedEvent>()
+            .add_event::<ContentReloadedEvent>()
+            .add_systems(Startup, load_string_tables)
+            .add_systems(Update, (apply_language_setting, reload_string_tables));
+    }
+}
+
+fn selected_language(config: &LocalizationConfig, settings: Option<&UserSettings>) -> String {
+    settings
+        .map(|s| s.interface.language.clone())
+        .filter(|language| !language.is_empty())
+        .unwrap_or_else(|| config.source_language.clone())
+}
+
+fn load_string_tables(
+    mut commands: Commands,
+    config: Res<LocalizationConfig>,
+    packs: Res<ContentPacks>,
+    settings: Option<Res<UserSettings>>,
+) {
+    let language = selected_language(&config, settings.as_deref());
+    let (localization, problems) = build_localization(&config, &packs, &language);
+    for problem in &problems {
+        warn!("Localization: {}", problem);
+    }
+    info!(
+        "Localization: {} ({} fallback tables, languages: {}){}",
+        language,
+        localization.bundles.len().saturating_sub(1),
+        localization.available.join(", "),
+        if localization.pseudo { ", pseudo-localized" } else { "" }
+    );
+    commands.insert_resource(localization);
+}
+
+/// Switches language when the settings change it.
+fn apply_language_setting(
+    config: Res<LocalizationConfig>,
+    packs: Res<ContentPacks>,
+    settings: Option<Res<UserSettings>>,
+    mut localization: ResMut<Localization>,
+    mut changed: EventWriter<LanguageChangedEvent>,
+    mut overlay: Option<ResMut<GameLogOverlay>>,
+    time: Res<Time>,
+) {
+    let Some(settings) = settings.filter(|s| s.is_changed()) else {
+        return;
+    };
+    let language = selected_language(&config, Some(&settings));
+    if language == localization.language {
+        return;
+    }
+
+    let (rebuilt, problems) = build_localization(&config, &packs, &language);
+    for problem in &problems {
+        warn!("Localization: {}", problem);
+    }
+    *localization = rebuilt;
+    let message = localization.text_with("system-language-changed", &fluent_args!["language" => language.clone()]);
+    info!("{}", message);
+    if let Some(overlay) = overlay.as_mut() {
+        overlay.info(message, time.elapsed_secs_f64());
+    }
+    changed.send(LanguageChangedEvent { language });
+}
+
+fn reload_string_tables(
+    config: Res<LocalizationConfig>,
+    packs: Res<ContentPacks>,
+    mut localization: ResMut<Localization>,
+    mut content_changed: EventReader<ContentChangedEvent>,
+    mut reloaded: EventWriter<ContentReloadedEvent>,
+    mut changed: EventWriter<LanguageChangedEvent>,
+) {
+    let paths: Vec<PathBuf> = content_changed
+        .read()
+        .filter(|e| e.path.starts_with(&config.directory) && e.path.extension().and_then(|x| x.to_str()) == Some("ftl"))
+        .map(|e| e.path.clone())
+        .collect();
+    if paths.is_empty() {
+        return;
+    }
+
+    let language = localization.language.clone();
+    let (rebuilt, problems) = build_localization(&config, &packs, &language);
+    *localization = rebuilt;
+    for path in paths {
+        let problem = problems.iter().find(|p| p.starts_with(&path.display().to_string()));
+        match problem {
+            Some(problem) => reloaded.send(ContentReloadedEvent::failed(&path, problem.clone())),
+            None => reloaded.send(ContentReloadedEvent::applied(&path, format!("strings for {}", language))),
+        };
+    }
+    changed.send(LanguageChangedEvent { language });
+}
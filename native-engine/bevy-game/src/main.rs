@@ -1,6 +1,5 @@
 use bevy::prelude::*;
-use bevy::gltf::{Gltf, GltfAssetLabel};
-use bevy::asset::LoadState;
+use bevy::gltf::GltfAssetLabel;
 use bevy_rapier3d::prelude::*;
 use std::env;
 
@@ -9,6 +8,9 @@ mod assets;
 mod audio;
 mod components;
 mod content;
+mod content_packs;
+mod hot_reload;
+mod scripting;
 mod dialog;
 mod editor;
 mod gameplay;
@@ -18,31 +20,84 @@ mod rendering;
 mod resources;
 mod systems;
 mod tracing;
+mod tracy;
+mod profiler;
 mod world;
 mod events;
 mod engine_fabric;
+mod minimap;
+mod appearance;
+mod triggers;
+mod hazards;
+mod sound;
+mod settings;
+mod input_map;
+mod authoring;
+mod content_validation;
+mod localization;
+mod database;
+mod console;
+mod worldgen;
+mod metrics;
+mod crash;
+mod memory;
+mod spawn_scheduler;
+mod spawn_density;
+mod terrain_meshing;
+mod forest;
+mod ai_stage;
+mod net_io;
+mod preload;
+mod character_models;
+mod day_night;
+mod interactables;
+mod camera_modes;
+mod spectator;
+mod mount_environment;
+mod races;
+mod economy;
+mod territory;
+mod dungeons;
+mod caravans;
+mod gathering;
+mod tutorials;
+mod gm;
+mod combat_log;
+mod encounters;
+mod threat_display;
+mod world_clock;
+mod persistence;
+mod cast_validation;
+mod net_security;
+mod flood_control;
+mod interest;
+mod launcher_link;
+mod game_settings;
+mod renderer_backend;
+mod frame_graph;
+mod scenario;
 
 #[cfg(test)]
 mod stress_tests;
 
 #[cfg(feature = "dev-sync")]
 mod dev_sync;
-
-#[cfg(feature = "atom")]
-use atom_bridge::{AtomRendererPlugin, RenderConfig as AtomRenderConfig, AtomRendererResource, is_real_atom_available, get_renderer_backend};
-
-#[cfg(feature = "atom")]
-use crate::rendering::atom::{AtomExtractionPlugin, AtomStatus};
+#[cfg(feature = "dev-sync")]
+mod dev_sync_assets;
 
 pub use components::*;
 pub use resources::*;
 pub use events::*;
+pub use memory::{EntityPool, FrameArena};
 
 #[derive(Resource)]
 pub struct HeadlessConfig {
     pub enabled: bool,
     pub max_ticks: u32,
     pub current_tick: u32,
+    /// Real seconds spent across all ticks, and in the slowest one.
+    pub tick_seconds: f64,
+    pub slowest_tick_seconds: f64,
 }
 
 impl Default for HeadlessConfig {
@@ -51,15 +106,16 @@ impl Default for HeadlessConfig {
             enabled: false,
             max_ticks: 100,
             current_tick: 0,
+            tick_seconds: 0.0,
+            slowest_tick_seconds: 0.0,
         }
     }
 }
 
-#[derive(Resource)]
+#[derive(Resource, Default)]
 pub struct MutantAsset {
-    pub gltf_handle: Handle<Gltf>,
+    pub ticket: Option<character_models::CharacterTicket>,
     pub spawned: bool,
-    pub load_check_count: u32,
 }
 
 #[derive(Component)]
@@ -87,6 +143,17 @@ pub enum LogLevel {
     Debug,
 }
 
+impl LogLevel {
+    pub fn label(self) -> &'static str {
+        match self {
+            LogLevel::Info => "INFO",
+            LogLevel::Warn => "WARN",
+            LogLevel::Error => "ERROR",
+            LogLevel::Debug => "DEBUG",
+        }
+    }
+}
+
 impl Default for GameLogOverlay {
     fn default() -> Self {
         Self {
@@ -99,8 +166,10 @@ impl Default for GameLogOverlay {
 
 impl GameLogOverlay {
     pub fn log(&mut self, level: LogLevel, message: impl Into<String>, time: f64) {
+        let text = message.into();
+        crash::record_log_line(level.label(), &text);
         self.messages.push(GameLogEntry {
-            text: message.into(),
+            text,
             level,
             timestamp: time,
         });
@@ -136,7 +205,7 @@ fn is_headless_mode() -> bool {
     env::args().any(|arg| arg == "--headless" || arg == "-h")
 }
 
-fn get_max_ticks() -> u32 {
+fn get_max_ticks(default: u32) -> u32 {
     if let Some(ticks_arg) = env::args().skip_while(|a| a != "--ticks").nth(1) {
         if let Ok(ticks) = ticks_arg.parse::<u32>() {
             return ticks;
@@ -149,24 +218,27 @@ fn get_max_ticks() -> u32 {
         }
     }
     
-    100
+    default
 }
 
 fn main() {
-    // Set up panic hook to show errors in console
-    std::panic::set_hook(Box::new(|panic_info| {
-        eprintln!("================================================================");
-        eprintln!("  GAME CRASHED!");
-        eprintln!("================================================================");
-        eprintln!("{}", panic_info);
-        if let Some(location) = panic_info.location() {
-            eprintln!("  Location: {}:{}:{}", location.file(), location.line(), location.column());
-        }
-        eprintln!("================================================================");
-        eprintln!("Press Enter to exit...");
-        let mut input = String::new();
-        let _ = std::io::stdin().read_line(&mut input);
-    }));
+    // CI / launcher content check: no window, no engine, just a report and exit code
+    if env::args().any(|arg| arg == "--validate-content") {
+        std::process::exit(content_validation::run_cli());
+    }
+    if env::args().any(|arg| arg == "--migrate-save") {
+        std::process::exit(database::run_migrate_cli());
+    }
+
+    #[cfg(feature = "minidump")]
+    if env::args().any(|arg| arg == "--crash-monitor") {
+        std::process::exit(crash::minidump::run_monitor());
+    }
+
+    // Panics print to the console and leave a report in crashes/
+    crash::install_panic_hook();
+    #[cfg(feature = "minidump")]
+    let _minidumps = crash::minidump::attach();
 
     // Force logging to be visible
     if env::var("RUST_LOG").is_err() {
@@ -181,10 +253,16 @@ fn main() {
     println!("  Working directory: {:?}", env::current_dir().unwrap_or_default());
     println!("  Args: {:?}", env::args().collect::<Vec<_>>());
     
-    let headless = is_headless_mode();
-    let max_ticks = get_max_ticks();
+    // Written by the launcher; flags and environment variables still win
+    let settings = game_settings::GameSettings::load();
+    let headless = is_headless_mode() || settings.headless.enabled;
+    let max_ticks = get_max_ticks(settings.headless.ticks);
     
     if headless {
+        if let Some(path) = env::args().skip_while(|a| a != "--scenario").nth(1) {
+            println!("  Mode: HEADLESS SCENARIO ({})", path);
+            std::process::exit(scenario::run_cli(scenario_app(), std::path::Path::new(&path)));
+        }
         println!("  Mode: HEADLESS ({} ticks)", max_ticks);
         info!("=== HEADLESS MODE ENABLED ===");
         info!("Running for {} ticks without GPU rendering", max_ticks);
@@ -193,66 +271,48 @@ fn main() {
         println!("  Mode: FULL RENDERING");
         println!("================================================================");
         info!("=== FULL RENDERING MODE ===");
-        run_with_rendering();
+        run_with_rendering(settings);
     }
 }
 
 fn run_headless(max_ticks: u32) {
     App::new()
         .add_plugins(MinimalPlugins)
+        .add_plugins(content_packs::ContentPacksPlugin)
         .add_plugins(HeadlessPlugin { max_ticks })
         .add_plugins(GameLogicPlugin)
+        // Only the server keeps state between runs; scenarios and clients don't
+        .add_plugins(persistence::PersistencePlugin)
+        // The server decides whether casts go through
+        .add_plugins(cast_validation::CastAuthorityPlugin)
+        .add_plugins(flood_control::FloodControlPlugin)
+        // Clients are only sent what their character could see
+        .add_plugins(interest::InterestPlugin)
         .run();
 }
 
-fn run_with_rendering() {
+/// The headless app scenarios run on: game logic without the test entities.
+pub(crate) fn scenario_app() -> App {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugins(content_packs::ContentPacksPlugin)
+        .add_plugins(GameLogicPlugin);
+    app
+}
+
+/// System timings, plus the launcher's log channel when it started the game.
+fn log_layers(app: &mut App) -> Option<bevy::log::BoxedLayer> {
+    use tracing_subscriber::Layer;
+
+    let timing = profiler::system_timing_layer(app);
+    let launcher = launcher_link::launcher_log_layer(app);
+    let crash = crash::crash_log_layer();
+    Some(Box::new(Layer::and_then(timing, launcher).and_then(crash)))
+}
+
+fn run_with_rendering(settings: game_settings::GameSettings) {
     println!(">>> run_with_rendering() called");
     
-    // =========================================================================
-    // ATOM RENDERER VERIFICATION - NO COMPROMISE
-    // =========================================================================
-    #[cfg(feature = "atom")]
-    {
-        println!(">>> Checking Atom renderer...");
-        println!("    Backend: {}", get_renderer_backend());
-        println!("    Atom C++ linked: {}", is_real_atom_available());
-        info!("=== RENDERER VERIFICATION ===");
-        info!("Backend: {}", get_renderer_backend());
-        info!("Atom C++ library linked: {}", is_real_atom_available());
-        
-        // On Windows, we REQUIRE the real Atom renderer - no fallback allowed
-        #[cfg(target_os = "windows")]
-        if !is_real_atom_available() {
-            error!("================================================================");
-            error!("  FATAL ERROR: ATOM RENDERER NOT AVAILABLE");
-            error!("================================================================");
-            error!("");
-            error!("  The O3DE Atom renderer C++ library was not linked.");
-            error!("  This game REQUIRES the Atom renderer on Windows.");
-            error!("");
-            error!("  Possible causes:");
-            error!("    1. C++ build failed - check cpp_build.log");
-            error!("    2. O3DE SDK not installed - run PlayGame.bat /DIAG");
-            error!("    3. atom_bridge.lib not found in expected location");
-            error!("");
-            error!("  Fix: Re-run PlayGame.bat to rebuild with O3DE SDK");
-            error!("================================================================");
-            panic!("Atom renderer not available - game cannot run without it");
-        }
-        
-        // On non-Windows (Linux/Replit), we allow stub mode for development
-        #[cfg(not(target_os = "windows"))]
-        if !is_real_atom_available() {
-            warn!("================================================================");
-            warn!("  WARNING: Running with STUB renderer (development mode)");
-            warn!("================================================================");
-            warn!("  The O3DE Atom renderer is not available on this platform.");
-            warn!("  Using Bevy wgpu fallback for development/testing.");
-            warn!("  For full AAA rendering, run on Windows with O3DE SDK.");
-            warn!("================================================================");
-        }
-    }
-    
     #[cfg(feature = "dev-sync")]
     {
         if dev_sync::is_dev_sync_enabled() {
@@ -269,16 +329,18 @@ fn run_with_rendering() {
     println!(">>> Creating Bevy app...");
     let mut app = App::new();
     
+    // Registers pack asset sources, which has to happen before the asset server exists
+    app.add_plugins(content_packs::ContentPacksPlugin);
+    
     println!(">>> Adding DefaultPlugins with window...");
     app.add_plugins(DefaultPlugins.set(WindowPlugin {
-        primary_window: Some(Window {
-            title: "MMO Engine - AAA MMORPG".into(),
-            resolution: (1920.0, 1080.0).into(),
-            present_mode: bevy::window::PresentMode::AutoVsync,
-            ..default()
-        }),
+        primary_window: Some(settings.window()),
+        ..default()
+    }).set(bevy::log::LogPlugin {
+        custom_layer: log_layers,
         ..default()
     }));
+    app.insert_resource(settings.shadow_map()).insert_resource(settings);
     
     println!(">>> Adding GamePlugin...");
     app.add_plugins(GamePlugin);
@@ -287,6 +349,7 @@ fn run_with_rendering() {
     {
         println!(">>> Adding DevSyncPlugin...");
         app.add_plugins(dev_sync::DevSyncPlugin);
+        app.add_plugins(dev_sync_assets::DevAssetSyncPlugin);
     }
     
     println!(">>> Starting app.run() - window should appear now!");
@@ -304,7 +367,7 @@ impl Plugin for HeadlessPlugin {
             .insert_resource(HeadlessConfig {
                 enabled: true,
                 max_ticks: self.max_ticks,
-                current_tick: 0,
+                ..default()
             })
             .add_systems(Startup, headless_setup)
             .add_systems(Update, (
@@ -352,9 +415,13 @@ fn headless_tick_system(
     mut config: ResMut<HeadlessConfig>,
     mut app_exit: EventWriter<AppExit>,
     time: Res<Time>,
+    real_time: Res<Time<Real>>,
     mut npc_query: Query<(&mut Transform, &TestNPC)>,
 ) {
     config.current_tick += 1;
+    let real_delta = real_time.delta_secs_f64();
+    config.tick_seconds += real_delta;
+    config.slowest_tick_seconds = config.slowest_tick_seconds.max(real_delta);
     
     let delta = time.delta_secs().max(0.016);
     for (mut transform, npc) in npc_query.iter_mut() {
@@ -393,6 +460,16 @@ fn headless_state_reporter(
         info!("");
         info!("=== HEADLESS TEST PASSED ===");
         info!("Game logic systems executed successfully without GPU!");
+        // On stdout whatever the log filter, for the launcher's --smoke-test
+        let ticks = config.current_tick.max(1) as f64;
+        println!(
+            "HEADLESS STATS ticks={} entities={} avg_tick_ms={:.3} max_tick_ms={:.3}",
+            config.current_tick,
+            entity_query.iter().count(),
+            config.tick_seconds / ticks * 1000.0,
+            config.slowest_tick_seconds * 1000.0
+        );
+        println!("=== HEADLESS TEST PASSED ===");
     }
 }
 
@@ -430,11 +507,41 @@ impl Plugin for GameLogicPlugin {
             .add_plugins(world::ProceduralGenerationPlugin)
             // Content loader (data-driven monsters, NPCs, spawn zones from TOML)
             .add_plugins(content::ContentLoaderPlugin)
+            // Item, ability and monster records
+            .add_plugins(database::GameDatabasePlugin)
+            // Console commands; the remote admin console when configured
+            .add_plugins(console::ConsolePlugin)
+            // Actions bound to keys, mouse and gamepad, with rebinding and input contexts
+            .add_plugins(input_map::InputMapPlugin)
+            // Prometheus endpoint when MMO_METRICS_ADDR is set
+            .add_plugins(metrics::MetricsPlugin)
+            // Crash report context and opt-in upload of earlier reports
+            .add_plugins(crash::CrashReporterPlugin)
+            // Frame arena and entity pool, reset and trimmed at the end of each frame
+            .add_plugins(memory::MemoryPlugin)
+            // Budgeted spawning, nearest the player first
+            .add_plugins(spawn_scheduler::SpawnSchedulerPlugin)
+            // Respawn rates, pack sizes and kill credit scaled by players per zone
+            .add_plugins(spawn_density::SpawnDensityPlugin)
+            // World seed shared by terrain, landmarks, forests and spawns
+            .add_plugins(worldgen::WorldSeedPlugin)
+            // String tables for system messages
+            .add_plugins(localization::LocalizationPlugin)
+            // Reload edited content files without a restart
+            .add_plugins(hot_reload::ContentHotReloadPlugin)
+            // Trigger volumes (zones, cutscenes, ambushes, hazards)
+            .add_plugins(triggers::TriggerVolumePlugin)
+            // Falling, drowning and damage-volume hazards
+            .add_plugins(hazards::HazardPlugin)
+            // Rhai gameplay scripts (quests, boss mechanics, NPC interactions)
+            .add_plugins(scripting::ScriptingPlugin)
             .insert_resource(TerrainConfig::default())
             .insert_resource(WaterConfig::default())
             .insert_resource(SpawnConfig::default())
             .insert_resource(TimeOfDay::default())
             .insert_resource(NetworkConfig::default())
+            .init_resource::<net_io::NetIoConfig>()
+            .init_resource::<net_security::SessionAuth>()
             .insert_resource(GameState::default())
             .insert_resource(PerformanceMetrics::default())
             .insert_resource(LandmarkRegistry::new())
@@ -449,8 +556,6 @@ impl Plugin for GameLogicPlugin {
             .insert_resource(SkyridingConfig::default())
             .insert_resource(SkyridingInput::default())
             .insert_resource(systems::spawning::SpawnTemplates::default())
-            .insert_resource(FrameArena::default())
-            .insert_resource(EntityPool::default())
             .insert_resource(systems::spawning::SpawnQueue::new(50))
             .add_event::<DamageEvent>()
             .add_event::<DeathEvent>()
@@ -503,15 +608,30 @@ impl Plugin for GameLogicPlugin {
                 systems::mount::skyward_ascent_system,
                 systems::mount::whirling_surge_system,
             ))
-            // AI systems (state machine)
-            .add_systems(Update, (
-                systems::ai::update_ai_spatial_grid,
-                systems::ai::ai_perception_system.after(systems::ai::update_ai_spatial_grid),
-                systems::ai::ai_decision_system,
-                systems::ai::ai_pathfinding_system,
-                systems::ai::ai_movement_system,
-                systems::ai::ai_combat_system,
-            ))
+            // Wind, cliff updrafts, water skimming and surface grip for mounts
+            .add_plugins(mount_environment::MountEnvironmentPlugin)
+            // AI stage: parallel sense, decide, path and act for thousands of NPCs
+            .add_plugins(ai_stage::AiStagePlugin)
+            // Doors, chests and locks, replicated by world object id
+            .add_plugins(interactables::InteractablesPlugin)
+            // Skyriding races: checkpoints, medals, tricks, ghosts and leaderboards
+            .add_plugins(races::RacePlugin)
+            // Vendors with regional prices and stock that drift with trading volume
+            .add_plugins(economy::EconomyPlugin)
+            // Guild territory: objectives, claims with upkeep, vulnerability windows and buffs
+            .add_plugins(territory::TerritoryPlugin)
+            // Dungeon difficulty tiers and weekly instance lockouts
+            .add_plugins(dungeons::DungeonPlugin)
+            // Supply caravans between towns, escorted or raided for rewards
+            .add_plugins(caravans::CaravanPlugin)
+            // Gathering nodes with jittered, relocating respawns and diminishing yields
+            .add_plugins(gathering::GatheringPlugin)
+            // GM actions from the admin console, with an audit log sent to the server
+            .add_plugins(gm::GmPlugin)
+            // Server-synced world clock: resets, scheduled world events and the day/night hour
+            .add_plugins(world_clock::WorldClockPlugin)
+            // Casts are requests the server accepts or denies; cooldowns are predicted until it answers
+            .add_plugins(cast_validation::CastValidationPlugin)
             // Note: BehaviorTreePlugin now handles ai::behavior_tree_update_system and ai::apply_behavior_tree_outputs
             // Combat and spawning systems
             .add_systems(Update, (
@@ -531,9 +651,10 @@ impl Plugin for GameLogicPlugin {
                 systems::character::experience_system,
                 systems::character::level_up_effects_system,
                 networking_update_system,
-            ))
-            // Frame arena reset (runs at end of frame)
-            .add_systems(Last, reset_frame_arena);
+            ));
+
+        #[cfg(feature = "tracy")]
+        app.add_plugins(tracy::TracyPlugin);
     }
 }
 
@@ -582,13 +703,64 @@ impl Plugin for GamePlugin {
             .add_plugins(editor::LevelEditorPlugin)
             .add_plugins(editor::MaterialEditorPlugin)
             .add_plugins(editor::ProfilerPlugin)
+            // Per-system timeline, budget alerts and CSV export
+            .add_plugins(profiler::SystemProfilerPlugin)
             // Navigation plugin (NavMesh pathfinding)
             .add_plugins(navigation::NavigationPlugin)
             // Navigation debug (conditional)
             #[cfg(debug_assertions)]
             .add_plugins(navigation::debug::NavigationDebugPlugin)
+            // Persisted player options (audio volumes, ...)
+            .add_plugins(settings::SettingsPlugin)
+            // Actions bound to keys, mouse and gamepad, with rebinding and input contexts
+            .add_plugins(input_map::InputMapPlugin)
+            // String tables for UI, quest, dialog and item text
+            .add_plugins(localization::LocalizationPlugin)
+            // Item, ability and monster records
+            .add_plugins(database::GameDatabasePlugin)
+            // Backtick console with dev cheats and admin commands
+            .add_plugins((console::ConsolePlugin, console::ConsoleUiPlugin))
+            // Prometheus endpoint when MMO_METRICS_ADDR is set
+            .add_plugins(metrics::MetricsPlugin)
+            // Crash report context and opt-in upload of earlier reports
+            .add_plugins(crash::CrashReporterPlugin)
+            // Frame arena and entity pool, reset and trimmed at the end of each frame
+            .add_plugins(memory::MemoryPlugin)
+            // Budgeted spawning, nearest the player first
+            .add_plugins(spawn_scheduler::SpawnSchedulerPlugin)
+            // Respawn rates, pack sizes and kill credit scaled by players per zone
+            .add_plugins(spawn_density::SpawnDensityPlugin)
+            // Zone asset manifests preloaded behind loading screens
+            .add_plugins(preload::PreloadPlugin)
+            // glTF characters spawned on the terrain with collider and idle animation
+            .add_plugins(character_models::CharacterModelPlugin)
+            .init_resource::<MutantAsset>()
+            // Sun and moon lighting, ambient and fog following the time of day
+            .add_plugins(day_night::DayNightPlugin)
+            // Terrain chunk meshes built on the async compute pool
+            .add_plugins(terrain_meshing::TerrainMeshingPlugin)
+            // Forests placed per terrain chunk and drawn as instance buffers
+            .add_plugins(forest::ForestPlugin)
+            // World seed shared by terrain, landmarks, forests and spawns
+            .add_plugins(worldgen::WorldSeedPlugin)
             // Audio plugin (3D spatial audio)
-            .add_plugins(audio::AudioPlugin);
+            .add_plugins(audio::AudioPlugin)
+            // Mixer buses, music director and content-driven audio
+            .add_plugins(sound::SoundPlugin)
+            // Minimap/world map tile capture
+            .add_plugins(minimap::MinimapPlugin)
+            // Modular character parts and armor
+            .add_plugins(appearance::CharacterAppearancePlugin)
+            // Trigger volumes (zones, cutscenes, ambushes, hazards)
+            .add_plugins(triggers::TriggerVolumePlugin)
+            // Falling, drowning and damage-volume hazards
+            .add_plugins(hazards::HazardPlugin)
+            // Rhai gameplay scripts (quests, boss mechanics, NPC interactions)
+            .add_plugins(scripting::ScriptingPlugin)
+            // Prefabs and level-authoring tools
+            .add_plugins(authoring::AuthoringPlugin)
+            // Reload edited content files without a restart
+            .add_plugins(hot_reload::ContentHotReloadPlugin);
         
         // Nakama multiplayer sync (when networking feature is enabled)
         #[cfg(feature = "networking")]
@@ -597,53 +769,19 @@ impl Plugin for GamePlugin {
             info!("NakamaSyncPlugin enabled for multiplayer synchronization");
         }
         
-        #[cfg(feature = "atom")]
-        {
-            info!("╔══════════════════════════════════════════════════════════════╗");
-            info!("║              ATOM RENDERER - REQUIRED MODE                    ║");
-            info!("╚══════════════════════════════════════════════════════════════╝");
-            info!("Atom renderer feature is ENABLED - this is REQUIRED, not optional");
-            
-            let atom_config = AtomRenderConfig {
-                width: 1920,
-                height: 1080,
-                enable_gi: true,
-                enable_ssr: true,
-                enable_shadows: true,
-                enable_ao: true,
-                shadow_cascade_count: 4,
-                lod_bias: 0.0,
-                max_draw_calls: 10000,
-            };
-            
-            info!("Atom render config: {:?}", atom_config);
-            info!("Adding AtomRendererPlugin...");
-            app.add_plugins(AtomRendererPlugin::with_config(atom_config));
-            
-            info!("Adding AtomExtractionPlugin...");
-            app.add_plugins(AtomExtractionPlugin);
-            
-            app.add_systems(PostStartup, verify_atom_initialized);
-            
-            info!("AtomRendererPlugin and AtomExtractionPlugin added with high-quality settings");
-            info!("Atom verification system scheduled for PostStartup");
-        }
-        
-        #[cfg(not(feature = "atom"))]
-        {
-            warn!("╔══════════════════════════════════════════════════════════════╗");
-            warn!("║  WARNING: ATOM FEATURE NOT ENABLED                            ║");
-            warn!("║  Running with default Bevy/wgpu renderer                      ║");
-            warn!("║  For AAA graphics, rebuild with: --features atom              ║");
-            warn!("╚══════════════════════════════════════════════════════════════╝");
-        }
-        
+        // Atom or wgpu, as the launcher's settings file and the build allow
+        app.add_plugins(renderer_backend::RendererBackendPlugin);
+        // Frame graph window, barrier validation and Graphviz dumps (`framegraph`)
+        app.add_plugins(frame_graph::FrameGraphPlugin);
+
         app
             .insert_resource(TerrainConfig::default())
             .insert_resource(WaterConfig::default())
             .insert_resource(SpawnConfig::default())
             .insert_resource(TimeOfDay::default())
             .insert_resource(NetworkConfig::default())
+            .init_resource::<net_io::NetIoConfig>()
+            .init_resource::<net_security::SessionAuth>()
             .insert_resource(GameState::default())
             .insert_resource(PerformanceMetrics::default())
             .insert_resource(GameLogOverlay::default())
@@ -659,8 +797,6 @@ impl Plugin for GamePlugin {
             .insert_resource(SkyridingConfig::default())
             .insert_resource(SkyridingInput::default())
             .insert_resource(systems::spawning::SpawnTemplates::default())
-            .insert_resource(FrameArena::default())
-            .insert_resource(EntityPool::default())
             .insert_resource(systems::spawning::SpawnQueue::new(50))
             .add_event::<DamageEvent>()
             .add_event::<DeathEvent>()
@@ -683,15 +819,13 @@ impl Plugin for GamePlugin {
                 systems::spawning::setup_spawn_points,
                 setup_lighting,
                 setup_gpu_smoke_test,
-                systems::vegetation::generate_forest,
                 systems::sky::setup_sky_system,
-                load_mutant_gltf,
                 setup_log_overlay,
                 networking::network_setup_system,
             ))
             .add_systems(PostStartup, systems::camera::setup_player_camera)
-            // World systems (terrain, water, vegetation, entities)
-            // CRITICAL: Use .chain() to guarantee terrain chunks update BEFORE trees/mutant spawn/resync
+            // World systems (terrain, water, entities); forests follow terrain meshing in ForestPlugin
+            // CRITICAL: Use .chain() to guarantee terrain chunks update BEFORE mutant spawn/resync
             // This ensures the chunk cache is populated before entities sample heights from it
             .add_systems(Update, (
                 // Stage 1: Terrain and water updates (populates chunk cache)
@@ -701,13 +835,10 @@ impl Plugin for GamePlugin {
                     systems::water::update_water_animation,
                     systems::water::update_water_lod,
                 ),
-                // Stage 2: Vegetation and entity systems (depends on chunk cache)
+                // Stage 2: Entity systems (depends on chunk cache)
                 (
-                    systems::vegetation::spawn_tree_instances,
-                    systems::vegetation::update_forest_lod,
-                    systems::vegetation::resync_tree_heights,
+                    request_mutant_spawn,
                     check_mutant_loading,
-                    resync_mutant_height,
                 ),
             ).chain())
             // Player and camera systems
@@ -729,15 +860,42 @@ impl Plugin for GamePlugin {
                 systems::mount::mount_camera_system,
                 systems::mount::hide_player_when_mounted_system,
             ))
-            // AI systems (state machine)
-            .add_systems(Update, (
-                systems::ai::update_ai_spatial_grid,
-                systems::ai::ai_perception_system.after(systems::ai::update_ai_spatial_grid),
-                systems::ai::ai_decision_system,
-                systems::ai::ai_pathfinding_system,
-                systems::ai::ai_movement_system,
-                systems::ai::ai_combat_system,
-            ))
+            // Wind, cliff updrafts, water skimming and surface grip for mounts
+            .add_plugins(mount_environment::MountEnvironmentPlugin)
+            // First-person mode with view model arms, blended from the orbit camera
+            .add_plugins(camera_modes::CameraModePlugin)
+            // Free-fly spectator camera for dev builds and admins
+            .add_plugins(spectator::SpectatorPlugin)
+            // AI stage: parallel sense, decide, path and act for thousands of NPCs
+            .add_plugins(ai_stage::AiStagePlugin)
+            // Doors, chests and locks, replicated by world object id
+            .add_plugins(interactables::InteractablesPlugin)
+            // Skyriding races: checkpoints, medals, tricks, ghosts and leaderboards
+            .add_plugins((races::RacePlugin, races::RaceHudPlugin))
+            // Vendors with regional prices and stock that drift with trading volume
+            .add_plugins(economy::EconomyPlugin)
+            // Guild territory: objectives, claims with upkeep, vulnerability windows and buffs
+            .add_plugins(territory::TerritoryPlugin)
+            // Dungeon difficulty tiers and weekly instance lockouts
+            .add_plugins((dungeons::DungeonPlugin, dungeons::LockoutUiPlugin))
+            // Supply caravans between towns, escorted or raided for rewards
+            .add_plugins(caravans::CaravanPlugin)
+            // Gathering nodes with jittered, relocating respawns and diminishing yields
+            .add_plugins(gathering::GatheringPlugin)
+            // First-time hint popups and starter-zone tutorial sequences
+            .add_plugins((tutorials::TutorialPlugin, tutorials::TutorialUiPlugin))
+            // GM tools for admin accounts, with an audit log sent to the server
+            .add_plugins((gm::GmPlugin, gm::GmUiPlugin))
+            // Combat log window and the JSON-lines log file for external parsers
+            .add_plugins((combat_log::CombatLogPlugin, combat_log::CombatLogUiPlugin))
+            // Per-fight damage, healing and threat, with the meter and JSON reports
+            .add_plugins((encounters::EncounterPlugin, encounters::MeterUiPlugin))
+            // Aggro rings, the tanking warning and NPC target lines
+            .add_plugins(threat_display::ThreatDisplayPlugin)
+            // Server-synced world clock: resets, scheduled world events and the day/night hour
+            .add_plugins(world_clock::WorldClockPlugin)
+            // Casts are requests the server accepts or denies; cooldowns are predicted until it answers
+            .add_plugins(cast_validation::CastValidationPlugin)
             // AI systems (behavior tree)
             .add_systems(Update, (
                 ai::behavior_tree_update_system,
@@ -781,14 +939,11 @@ impl Plugin for GamePlugin {
                 update_log_overlay_text,
                 log_mutant_status_to_overlay,
                 log_game_startup_to_overlay,
-            ))
-            // Frame arena reset (runs at end of frame)
-            .add_systems(Last, reset_frame_arena);
-    }
-}
+            ));
 
-fn reset_frame_arena(mut frame_arena: ResMut<FrameArena>) {
-    frame_arena.reset();
+        #[cfg(feature = "tracy")]
+        app.add_plugins(tracy::TracyPlugin);
+    }
 }
 
 fn setup_terrain(
@@ -885,11 +1040,11 @@ fn setup_player_headless(mut commands: Commands) {
 }
 
 
-fn setup_lighting(mut commands: Commands) {
+fn setup_lighting(mut commands: Commands, settings: Option<Res<game_settings::GameSettings>>) {
     commands.spawn((
         DirectionalLight {
             illuminance: 10000.0,
-            shadows_enabled: true,
+            shadows_enabled: settings.is_none_or(|settings| settings.sun_shadows()),
             ..default()
         },
         Transform::from_rotation(Quat::from_euler(
@@ -898,6 +1053,7 @@ fn setup_lighting(mut commands: Commands) {
             std::f32::consts::FRAC_PI_4,
             0.0,
         )),
+        day_night::SunLight,
     ));
     
     commands.insert_resource(AmbientLight {
@@ -908,138 +1064,39 @@ fn setup_lighting(mut commands: Commands) {
     info!("Lighting setup complete (camera spawned by camera system)");
 }
 
-fn load_mutant_gltf(
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
+fn request_mutant_spawn(
+    mut mutant: ResMut<MutantAsset>,
+    mut spawner: ResMut<character_models::CharacterModelSpawner>,
+    player_query: Query<&Transform, With<Player>>,
 ) {
-    info!("=== LOADING MUTANT GLTF FILE ===");
-    info!("Asset path: models/mutant.glb");
-    
-    let gltf_handle: Handle<Gltf> = asset_server.load("models/mutant.glb");
-    info!("GLTF handle created: {:?}", gltf_handle);
-    
-    commands.insert_resource(MutantAsset {
-        gltf_handle,
-        spawned: false,
-        load_check_count: 0,
-    });
-    
-    info!("MutantAsset resource inserted, waiting for load completion...");
+    if mutant.ticket.is_some() {
+        return;
+    }
+    // Wait until player exists before placing the mutant
+    let Ok(player_transform) = player_query.get_single() else { return; };
+
+    // Offset 15 units in front of player; the spawner snaps it to the terrain
+    let spawn_pos = player_transform.translation + Vec3::new(15.0, 0.0, 15.0);
+    let request = character_models::CharacterModelRequest::new("mutant", spawn_pos).with_name("TestMutant");
+    mutant.ticket = Some(spawner.spawn(request));
+    info!("Mutant model requested near player");
 }
 
 fn check_mutant_loading(
     mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    gltf_assets: Res<Assets<Gltf>>,
-    mut mutant_asset: Option<ResMut<MutantAsset>>,
-    player_query: Query<&Transform, With<Player>>,
-    terrain_config: Res<TerrainConfig>,
-    chunk_cache: Res<TerrainChunkCache>,
-    mut landmark_registry: ResMut<LandmarkRegistry>,
+    mut mutant: ResMut<MutantAsset>,
+    mut spawned_events: EventReader<character_models::CharacterModelSpawnedEvent>,
+    mut failed_events: EventReader<character_models::CharacterModelFailedEvent>,
 ) {
-    let Some(ref mut mutant) = mutant_asset else { return; };
-    if mutant.spawned { return; }
-    
-    mutant.load_check_count += 1;
-    
-    let is_fully_loaded = asset_server.is_loaded_with_dependencies(&mutant.gltf_handle);
-    
-    if mutant.load_check_count % 60 == 0 {
-        info!("Mutant load check #{}: fully_loaded_with_deps = {}", 
-            mutant.load_check_count, is_fully_loaded);
-    }
-    
-    if mutant.load_check_count > 600 {
-        error!("=== MUTANT LOADING TIMEOUT (10 seconds) ===");
-        error!("Asset may have failed to load or path is incorrect");
+    let Some(ticket) = mutant.ticket else { return; };
+    for event in spawned_events.read().filter(|e| e.ticket == ticket) {
+        commands.entity(event.entity).insert(MutantMarker);
+        info!("=== MUTANT SCENE SPAWNED ===");
         mutant.spawned = true;
-        return;
     }
-    
-    if is_fully_loaded {
-        info!("=== MUTANT GLTF FULLY LOADED WITH ALL DEPENDENCIES ===");
-        
-        if let Some(gltf) = gltf_assets.get(&mutant.gltf_handle) {
-            info!("GLTF contents:");
-            info!("  - Scenes: {}", gltf.scenes.len());
-            info!("  - Named scenes: {}", gltf.named_scenes.len());
-            info!("  - Meshes: {}", gltf.meshes.len());
-            info!("  - Materials: {}", gltf.materials.len());
-            info!("  - Nodes: {}", gltf.nodes.len());
-            
-            for (name, _) in gltf.named_scenes.iter() {
-                info!("  Named scene: '{}'", name);
-            }
-            
-            if let Some(scene_handle) = gltf.scenes.first() {
-                let scene_loaded = asset_server.is_loaded_with_dependencies(scene_handle);
-                info!("First scene handle loaded with deps: {}", scene_loaded);
-                
-                if scene_loaded {
-                    // Wait until player exists before spawning mutant
-                    let Ok(player_transform) = player_query.get_single() else {
-                        if mutant.load_check_count % 60 == 0 {
-                            info!("Waiting for player to spawn before placing mutant...");
-                        }
-                        return;
-                    };
-                    
-                    info!("=== SPAWNING MUTANT SCENE ===");
-                    
-                    // Spawn mutant near player at correct terrain height
-                    let player_pos = player_transform.translation;
-                    
-                    // Offset 15 units in front of player
-                    let spawn_x = player_pos.x + 15.0;
-                    let spawn_z = player_pos.z + 15.0;
-                    
-                    // CRITICAL FIX: Sample from chunk cache to match rendered terrain mesh exactly
-                    // Fallback to raw function only if chunk not loaded yet
-                    let terrain_y = systems::terrain::terrain_height_at_point(
-                        spawn_x, spawn_z, &terrain_config, &chunk_cache
-                    ).unwrap_or_else(|| {
-                        // Fallback to raw height function if chunk not available
-                        systems::terrain::terrain_height_at_with_features(
-                            spawn_x, spawn_z, &terrain_config, &mut landmark_registry
-                        )
-                    });
-                    let spawn_pos = Vec3::new(spawn_x, terrain_y, spawn_z);
-                    
-                    commands.spawn((
-                        SceneRoot(scene_handle.clone()),
-                        Transform::from_translation(spawn_pos)
-                            .with_scale(Vec3::splat(3.0)),
-                        GlobalTransform::default(),
-                        Visibility::Visible,
-                        InheritedVisibility::default(),
-                        ViewVisibility::default(),
-                        Name::new("TestMutant"),
-                        MutantMarker,
-                    ));
-                    
-                    info!("=== MUTANT SCENE SPAWNED near player at {:?} (terrain_y={}) with 3x SCALE ===", spawn_pos, terrain_y);
-                    mutant.spawned = true;
-                } else {
-                    if mutant.load_check_count % 60 == 0 {
-                        info!("Waiting for scene sub-assets to finish loading...");
-                    }
-                }
-            } else {
-                error!("=== NO SCENES FOUND IN GLTF ===");
-                mutant.spawned = true;
-            }
-        } else {
-            if mutant.load_check_count % 60 == 0 {
-                warn!("GLTF asset not accessible in Assets<Gltf> collection");
-            }
-        }
-    } else {
-        let load_state = asset_server.get_load_state(&mutant.gltf_handle);
-        if let Some(LoadState::Failed(err)) = load_state {
-            error!("=== MUTANT GLTF FAILED TO LOAD ===");
-            error!("Error: {:?}", err);
-            mutant.spawned = true;
-        }
+    for event in failed_events.read().filter(|e| e.ticket == ticket) {
+        error!("=== MUTANT FAILED TO SPAWN: {} ===", event.error);
+        mutant.spawned = true;
     }
 }
 
@@ -1067,51 +1124,6 @@ fn debug_mutant_entities(
     }
 }
 
-/// Resync mutant height from chunk cache after chunks load
-/// This corrects mutants that spawned with fallback heights
-/// Tracks per-entity sync status using Entity IDs
-fn resync_mutant_height(
-    terrain_config: Res<TerrainConfig>,
-    chunk_cache: Res<TerrainChunkCache>,
-    mut mutant_query: Query<(Entity, &mut Transform), With<MutantMarker>>,
-    mut synced_entities: Local<std::collections::HashSet<Entity>>,
-    mut frame_count: Local<u32>,
-) {
-    // Only run every 30 frames to reduce overhead
-    *frame_count += 1;
-    if *frame_count % 30 != 0 {
-        return;
-    }
-    
-    for (entity, mut transform) in mutant_query.iter_mut() {
-        // Skip if already synced
-        if synced_entities.contains(&entity) {
-            continue;
-        }
-        
-        let x = transform.translation.x;
-        let z = transform.translation.z;
-        
-        // Try to get height from chunk cache
-        if let Some(cached_height) = systems::terrain::terrain_height_at_point(
-            x, z, &terrain_config, &chunk_cache
-        ) {
-            let current_y = transform.translation.y;
-            let height_diff = (cached_height - current_y).abs();
-            
-            // Only update if there's a significant difference
-            if height_diff > 0.1 {
-                transform.translation.y = cached_height;
-                info!("Resynced mutant height from chunk cache: {} -> {}", current_y, cached_height);
-            }
-            
-            // Mark as synced (even if no update needed)
-            synced_entities.insert(entity);
-        }
-        // If chunk not available, we'll retry next time
-    }
-}
-
 #[derive(Component)]
 struct SpinningCube;
 
@@ -1148,146 +1160,246 @@ fn spin_cube_system(time: Res<Time>, mut query: Query<&mut Transform, With<Spinn
 fn networking_update_system(
     time: Res<Time>,
     config: Res<NetworkConfig>,
+    io_config: Res<net_io::NetIoConfig>,
+    metrics: Option<Res<metrics::Metrics>>,
     mut network_state: ResMut<networking::NetworkState>,
     mut network_events: EventWriter<NetworkEvent>,
+    (mut world_object_events, mut time_sync_events, mut cast_reply_events): (
+        EventWriter<interactables::WorldObjectStateReceived>,
+        EventWriter<world_clock::TimeSyncReceived>,
+        EventWriter<cast_validation::CastReplyReceived>,
+    ),
+    (mut session_auth, cast_outbox, time_sync_outbox): (
+        ResMut<net_security::SessionAuth>,
+        Option<ResMut<cast_validation::CastRequestOutbox>>,
+        Option<ResMut<world_clock::TimeSyncOutbox>>,
+    ),
     player_query: Query<&Transform, With<Player>>,
     mut remote_query: Query<(&mut Transform, &NetworkEntity), Without<Player>>,
+    mut io: Local<Option<net_io::NetIo>>,
 ) {
+    use net_io::{NetCommand, NetInbound};
     use networking::ConnectionState;
     
     if !config.auto_connect {
         return;
     }
-    
-    match network_state.connection_state {
-        ConnectionState::Disconnected => {
-            network_state.connection_state = ConnectionState::Authenticating;
-            
-            if let Some(ref mut client) = network_state.client {
-                match client.authenticate_device(&config.device_id) {
-                    Ok(session) => {
-                        info!("Connected as user: {} ({})", session.username, session.user_id);
-                        network_state.connection_state = ConnectionState::Connected;
-                        
-                        network_events.send(NetworkEvent {
-                            event_type: crate::events::NetworkEventType::Connected,
-                            data: session.user_id.into_bytes(),
-                        });
-                    }
-                    Err(e) => {
-                        warn!("Authentication failed: {}", e);
-                        network_state.connection_state = ConnectionState::Error;
-                        
-                        network_events.send(NetworkEvent {
-                            event_type: crate::events::NetworkEventType::Disconnected,
-                            data: e.to_string().into_bytes(),
-                        });
-                    }
-                }
-            }
+    crate::profile_zone!("networking::update");
+
+    // The client moves to a dedicated IO thread on first use, which serves
+    // commands, sends heartbeats and reads the socket. From then on this
+    // system only talks to it through channels, so blocking calls and
+    // match-state decoding never stall the frame.
+    if io.is_none() {
+        let Some(mut client) = network_state.client.take() else {
+            return;
+        };
+        let (channel, worker) = net_io::NetIo::channel(&io_config, metrics.as_deref().cloned());
+        let poll = std::time::Duration::from_millis(io_config.poll_interval_ms.max(1));
+        let heartbeat = std::time::Duration::from_secs_f32(io_config.heartbeat_seconds.max(0.1));
+        let server_key = net_security::server_key_from_env();
+        if let Err(e) = &server_key {
+            error!("{}; no match messages will be sent or accepted", e);
         }
-        
-        ConnectionState::Connected | ConnectionState::InMatch => {
+        let spawned = std::thread::Builder::new().name("network-io".into()).spawn(move || {
+            let mut last_heartbeat = std::time::Instant::now();
+            let mut connected = false;
             #[cfg(feature = "networking")]
-            {
-                if let Some(ref mut client) = network_state.client {
-                    let _ = client.send_heartbeat();
-                    
-                    let messages = client.receive_messages();
-                    for msg in messages {
-                        if let Some(match_data) = msg.get("match_data") {
-                            if let Some(data) = match_data.get("data") {
-                                if let Some(data_str) = data.as_str() {
-                                    use base64::Engine;
-                                    if let Ok(decoded) = base64::engine::general_purpose::STANDARD.decode(data_str) {
-                                        if let Ok(state) = serde_json::from_slice::<networking::StateSync>(&decoded) {
-                                            let current_time = std::time::SystemTime::now()
-                                                .duration_since(std::time::UNIX_EPOCH)
-                                                .map(|d| d.as_secs_f64())
-                                                .unwrap_or(0.0);
-                                            network_state.interpolation_buffer.add_state(current_time, state);
-                                        }
-                                    }
+            let mut session = net_security::ClientSession::new(server_key);
+            #[cfg(not(feature = "networking"))]
+            let _ = server_key;
+            while let Ok(command) = worker.next_command(poll) {
+                match command {
+                    Some(NetCommand::Authenticate { device_id }) => match client.authenticate_device(&device_id) {
+                        Ok(session) => {
+                            connected = true;
+                            worker.post(NetInbound::Authenticated {
+                                user_id: session.user_id,
+                                username: session.username,
+                            });
+                        }
+                        Err(e) => {
+                            worker.post(NetInbound::AuthFailed(e.to_string()));
+                        }
+                    },
+                    // Match messages only leave sealed, over the match socket
+                    Some(command) => {
+                        #[cfg(feature = "networking")]
+                        match net_io::seal_command(&mut session, &command) {
+                            Some((op_code, Ok(frame))) => {
+                                if let Err(e) = client.send_match_data(op_code, &frame) {
+                                    worker.post(NetInbound::SendFailed(e.to_string()));
                                 }
                             }
+                            Some((_, Err(reason))) => {
+                                worker.post(NetInbound::SendFailed(reason));
+                            }
+                            None => {}
                         }
+                        #[cfg(not(feature = "networking"))]
+                        let _ = command;
                     }
+                    None => {}
                 }
-                
-                let should_sync = network_state.last_position_sync
-                    .map(|t| t.elapsed().as_secs_f32() > 0.1)
-                    .unwrap_or(true);
-                
-                if should_sync {
-                    if let Ok(transform) = player_query.get_single() {
-                        if let Some(ref client) = network_state.client {
-                            if let Some(user_id) = client.get_user_id() {
-                                let request = networking::PositionUpdateRequest {
-                                    character_id: user_id.to_string(),
-                                    x: transform.translation.x,
-                                    y: transform.translation.y,
-                                    z: transform.translation.z,
-                                    rotation_y: transform.rotation.to_euler(EulerRot::YXZ).0,
-                                    velocity: [0.0, 0.0, 0.0],
-                                    timestamp: (time.elapsed_secs() * 1000.0) as u64,
-                                };
-                                
-                                match client.update_position(request) {
-                                    Ok(response) => {
-                                        if !response.approved {
-                                            warn!("Position update rejected by server");
-                                        }
-                                    }
-                                    Err(e) => {
-                                        warn!("Failed to sync position: {}", e);
-                                    }
-                                }
-                                
-                                network_state.last_position_sync = Some(std::time::Instant::now());
-                            }
+                if !connected {
+                    continue;
+                }
+
+                #[cfg(feature = "networking")]
+                {
+                    if last_heartbeat.elapsed() >= heartbeat {
+                        let _ = client.send_heartbeat();
+                        last_heartbeat = std::time::Instant::now();
+                    }
+                    for message in client.receive_messages() {
+                        if let Some(inbound) = net_io::decode_match_data(&message, &mut session) {
+                            worker.post(inbound);
                         }
                     }
-                }
-            }
-            
-            let current_time = time.elapsed_secs() as f64;
-            if let Some(state) = network_state.interpolation_buffer.get_interpolated_state(current_time) {
-                for entity_state in &state.entities {
-                    for (mut transform, network_entity) in remote_query.iter_mut() {
-                        if network_entity.network_id == entity_state.entity_id 
-                            && network_entity.is_remote {
-                            transform.translation = Vec3::new(
-                                entity_state.position[0],
-                                entity_state.position[1],
-                                entity_state.position[2],
-                            );
-                            transform.rotation = Quat::from_array(entity_state.rotation);
+                    for frame in session.take_outgoing() {
+                        if let Err(e) = client.send_match_data(net_security::HANDSHAKE_OP_CODE, &frame) {
+                            worker.post(NetInbound::SendFailed(e.to_string()));
                         }
                     }
                 }
-            }
-            
-            if let Some(ref client) = network_state.client {
+                #[cfg(not(feature = "networking"))]
+                let _ = (&mut last_heartbeat, heartbeat);
+
                 if !client.is_connected() {
-                    network_state.connection_state = ConnectionState::Disconnected;
-                    network_state.current_match_id = None;
-                    network_state.interpolation_buffer.clear();
-                    
-                    network_events.send(NetworkEvent {
-                        event_type: crate::events::NetworkEventType::Disconnected,
-                        data: Vec::new(),
-                    });
+                    connected = false;
+                    #[cfg(feature = "networking")]
+                    session.reset();
+                    if !worker.post(NetInbound::Disconnected) {
+                        break;
+                    }
                 }
             }
+        });
+        if let Err(e) = spawned {
+            error!("Failed to start the network IO thread: {}", e);
         }
-        
-        ConnectionState::Authenticating => {
+        *io = Some(channel);
+    }
+    let Some(io) = io.as_mut() else {
+        return;
+    };
+
+    if matches!(network_state.connection_state, ConnectionState::Disconnected) {
+        network_state.connection_state = ConnectionState::Authenticating;
+        io.send(NetCommand::Authenticate {
+            device_id: config.device_id.clone(),
+        });
+    }
+
+    for inbound in io.drain() {
+        match inbound {
+            NetInbound::Authenticated { user_id, username } => {
+                info!("Connected as user: {} ({})", username, user_id);
+                network_state.connection_state = ConnectionState::Connected;
+                io.user_id = Some(user_id.clone());
+                network_events.send(NetworkEvent {
+                    event_type: crate::events::NetworkEventType::Connected,
+                    data: user_id.into_bytes(),
+                });
+            }
+            NetInbound::AuthFailed(e) => {
+                warn!("Authentication failed: {}", e);
+                network_state.connection_state = ConnectionState::Error;
+                network_events.send(NetworkEvent {
+                    event_type: crate::events::NetworkEventType::Disconnected,
+                    data: e.into_bytes(),
+                });
+            }
+            NetInbound::State { received_at, state } => {
+                network_state.interpolation_buffer.add_state(received_at, state);
+            }
+            NetInbound::WorldObject(state) => {
+                world_object_events.send(interactables::WorldObjectStateReceived(state));
+            }
+            NetInbound::TimeSync { received_at, reply } => {
+                if let Some(metrics) = &metrics {
+                    metrics.observe(metrics::NETWORK_RTT_SECONDS, &[], received_at - reply.client_sent);
+                }
+                time_sync_events.send(world_clock::TimeSyncReceived { reply, received_at });
+            }
+            NetInbound::CastReply(reply) => {
+                cast_reply_events.send(cast_validation::CastReplyReceived(reply));
+            }
+            NetInbound::SessionSecured => {
+                info!("Match session secured; match messages are encrypted and signed from now on");
+                session_auth.set_secured(true);
+            }
+            NetInbound::Forged { op_code, reason } => {
+                io.stats.forged += 1;
+                warn!("Dropped match message with op code {}: {}", op_code, reason);
+            }
+            NetInbound::PositionRejected => warn!("Position update rejected by server"),
+            NetInbound::SendFailed(e) => warn!("Failed to send a match message: {}", e),
+            NetInbound::Disconnected => {
+                network_state.connection_state = ConnectionState::Disconnected;
+                network_state.current_match_id = None;
+                network_state.interpolation_buffer.clear();
+                io.user_id = None;
+                session_auth.set_secured(false);
+                network_events.send(NetworkEvent {
+                    event_type: crate::events::NetworkEventType::Disconnected,
+                    data: Vec::new(),
+                });
+            }
         }
-        
-        ConnectionState::Error => {
+    }
+
+    if !matches!(network_state.connection_state, ConnectionState::Connected | ConnectionState::InMatch) {
+        return;
+    }
+
+    // Nothing goes out before the handshake; an unsent cast times out and
+    // rolls back like any other unanswered one
+    let casts = cast_outbox.map(|mut outbox| outbox.drain()).unwrap_or_default();
+    let time_syncs = time_sync_outbox.map(|mut outbox| outbox.drain()).unwrap_or_default();
+    if !session_auth.is_secured() {
+        return;
+    }
+    for request in casts {
+        io.send(NetCommand::SendCast(request));
+    }
+    for request in time_syncs {
+        io.send(NetCommand::SendTimeSync(request));
+    }
+
+    let should_sync = network_state
+        .last_position_sync
+        .map(|t| t.elapsed().as_secs_f32() >= io_config.position_interval)
+        .unwrap_or(true);
+    if should_sync {
+        if let (Ok(transform), Some(user_id)) = (player_query.get_single(), io.user_id.clone()) {
+            io.send(NetCommand::SendPosition(networking::PositionUpdateRequest {
+                character_id: user_id,
+                x: transform.translation.x,
+                y: transform.translation.y,
+                z: transform.translation.z,
+                rotation_y: transform.rotation.to_euler(EulerRot::YXZ).0,
+                velocity: [0.0, 0.0, 0.0],
+                timestamp: (time.elapsed_secs() * 1000.0) as u64,
+            }));
+            network_state.last_position_sync = Some(std::time::Instant::now());
         }
-        
-        ConnectionState::Connecting => {
+    }
+
+    // States are stamped in wall-clock seconds as they arrive
+    if let Some(state) = network_state.interpolation_buffer.get_interpolated_state(net_io::unix_seconds()) {
+        for entity_state in &state.entities {
+            for (mut transform, network_entity) in remote_query.iter_mut() {
+                if network_entity.network_id == entity_state.entity_id 
+                    && network_entity.is_remote {
+                    transform.translation = Vec3::new(
+                        entity_state.position[0],
+                        entity_state.position[1],
+                        entity_state.position[2],
+                    );
+                    transform.rotation = Quat::from_array(entity_state.rotation);
+                }
+            }
         }
     }
 }
@@ -1297,7 +1409,7 @@ fn ui_update_system(
 ) {
 }
 
-fn setup_log_overlay(mut commands: Commands) {
+fn setup_log_overlay(mut commands: Commands, localization: Res<localization::Localization>) {
     commands.spawn((
         Node {
             position_type: PositionType::Absolute,
@@ -1315,7 +1427,7 @@ fn setup_log_overlay(mut commands: Commands) {
         LogOverlayUI,
     )).with_children(|parent| {
         parent.spawn((
-            Text::new("=== GAME LOG (F12 to toggle) ===\n"),
+            Text::new(format!("{}\n", localization.text("ui-log-title"))),
             TextFont {
                 font_size: 14.0,
                 ..default()
@@ -1329,11 +1441,11 @@ fn setup_log_overlay(mut commands: Commands) {
 }
 
 fn toggle_log_overlay(
-    keyboard: Res<ButtonInput<KeyCode>>,
+    actions: Res<input_map::ActionState>,
     mut log_overlay: ResMut<GameLogOverlay>,
     mut query: Query<&mut Visibility, With<LogOverlayUI>>,
 ) {
-    if keyboard.just_pressed(KeyCode::F12) {
+    if actions.just_pressed(input_map::Action::ToggleLogOverlay) {
         log_overlay.visible = !log_overlay.visible;
         for mut visibility in query.iter_mut() {
             *visibility = if log_overlay.visible {
@@ -1347,12 +1459,13 @@ fn toggle_log_overlay(
 
 fn update_log_overlay_text(
     log_overlay: Res<GameLogOverlay>,
+    localization: Res<localization::Localization>,
     mut query: Query<&mut Text, With<LogOverlayText>>,
 ) {
     if !log_overlay.visible { return; }
     
     for mut text in query.iter_mut() {
-        let mut content = String::from("=== GAME LOG (F12 to hide) ===\n\n");
+        let mut content = format!("{}\n\n", localization.text("ui-log-title-open"));
         
         let start_idx = if log_overlay.messages.len() > 20 {
             log_overlay.messages.len() - 20
@@ -1371,7 +1484,7 @@ fn update_log_overlay_text(
         }
         
         if log_overlay.messages.is_empty() {
-            content.push_str("(No log messages yet)\n");
+            content.push_str(&format!("{}\n", localization.text("ui-log-empty")));
         }
         
         *text = Text::new(content);
@@ -1381,22 +1494,26 @@ fn update_log_overlay_text(
 fn log_mutant_status_to_overlay(
     mut log_overlay: ResMut<GameLogOverlay>,
     mutant_asset: Option<Res<MutantAsset>>,
+    progress: Res<preload::PreloadProgress>,
     time: Res<Time>,
-    mut last_status: Local<Option<(bool, u32)>>,
+    mut last_status: Local<Option<(bool, usize)>>,
 ) {
     let Some(mutant) = mutant_asset else { return; };
     
-    let current_status = (mutant.spawned, mutant.load_check_count);
+    let current_status = (mutant.spawned, progress.loaded + progress.failed);
     
     if *last_status != Some(current_status) {
         let elapsed = time.elapsed_secs_f64();
         
         if mutant.spawned {
             log_overlay.info("Mutant model spawned successfully!", elapsed);
-        } else if mutant.load_check_count == 1 {
+        } else if last_status.is_none() {
             log_overlay.info("Loading mutant.glb...", elapsed);
-        } else if mutant.load_check_count % 60 == 0 {
-            log_overlay.info(format!("Still loading... (check #{})", mutant.load_check_count), elapsed);
+        } else if progress.is_active() {
+            log_overlay.info(
+                format!("Still loading... ({}/{} assets)", current_status.1, progress.total),
+                elapsed,
+            );
         }
         
         *last_status = Some(current_status);
@@ -1418,50 +1535,3 @@ fn log_game_startup_to_overlay(
     log_overlay.info("Mouse: Right-click+drag=Look, Scroll=Zoom", t);
     log_overlay.info("Loading world assets...", t);
 }
-
-#[cfg(feature = "atom")]
-fn verify_atom_initialized(
-    renderer: Res<AtomRendererResource>,
-    status: Res<AtomStatus>,
-    mut app_exit: EventWriter<AppExit>,
-) {
-    info!("╔══════════════════════════════════════════════════════════════╗");
-    info!("║         POST-STARTUP ATOM VERIFICATION                        ║");
-    info!("╚══════════════════════════════════════════════════════════════╝");
-    
-    let renderer_initialized = renderer.get().is_initialized();
-    let status_initialized = status.is_initialized;
-    let is_atom_active = status.is_atom_active();
-    
-    info!("Renderer initialized: {}", renderer_initialized);
-    info!("AtomStatus initialized: {}", status_initialized);
-    info!("Backend name: {}", status.backend_name);
-    info!("Is Atom active (not wgpu fallback): {}", is_atom_active);
-    
-    if renderer_initialized && status_initialized && is_atom_active {
-        info!("┌──────────────────────────────────────────────────────────────┐");
-        info!("│  ✓✓✓ ATOM RENDERER VERIFICATION PASSED ✓✓✓                   │");
-        info!("│                                                              │");
-        info!("│  Atom renderer is ACTIVE and rendering.                      │");
-        info!("│  NOT falling back to wgpu.                                   │");
-        info!("│  Backend: {}                           │", status.backend_name);
-        info!("│  Frame count: {}                                             │", status.frame_count);
-        info!("└──────────────────────────────────────────────────────────────┘");
-    } else {
-        error!("╔══════════════════════════════════════════════════════════════╗");
-        error!("║  ✗✗✗ ATOM RENDERER VERIFICATION FAILED ✗✗✗                   ║");
-        error!("╠══════════════════════════════════════════════════════════════╣");
-        error!("║  CRITICAL ERROR: Atom renderer is REQUIRED but not working  ║");
-        error!("║                                                              ║");
-        error!("║  Renderer initialized: {}                                    ║", renderer_initialized);
-        error!("║  Status initialized: {}                                      ║", status_initialized);
-        error!("║  Is Atom active: {}                                          ║", is_atom_active);
-        error!("║  Backend: {}                                                 ║", status.backend_name);
-        error!("║                                                              ║");
-        error!("║  The game CANNOT run without the Atom renderer.              ║");
-        error!("║  Exiting with error...                                       ║");
-        error!("╚══════════════════════════════════════════════════════════════╝");
-        
-        app_exit.send(AppExit::Error(std::num::NonZeroU8::new(1).unwrap()));
-    }
-}
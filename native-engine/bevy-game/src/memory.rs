@@ -0,0 +1,519 @@
+//! Per-frame scratch memory and entity recycling, with the numbers to tune
+//! them. `FrameArena` is a bump allocator emptied at the end of every frame;
+//! `EntityPool` keeps released entities per kind (projectiles, damage
+//! numbers, ...) for reuse instead of despawning them. Both record usage and
+//! high-water marks, shown in the system profiler and exported as metrics,
+//! and size themselves by the policies in `content/memory.toml`:
+//!
+//! ```toml
+//! [frame_arena]
+//! initial_kb = 256
+//! budget_kb = 4096
+//!
+//! [entity_pool]
+//! max_free = 256
+//! kinds.projectile = { max_free = 1024, min_free = 64 }
+//! ```
+
+use bevy::prelude::*;
+use bumpalo::Bump;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::marker::PhantomData;
+use std::path::PathBuf;
+use std::ptr::NonNull;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+
+use crate::metrics::Metrics;
+
+pub const ARENA_USED_BYTES: &str = "mmo_frame_arena_used_bytes";
+pub const ARENA_CAPACITY_BYTES: &str = "mmo_frame_arena_capacity_bytes";
+pub const POOL_FREE: &str = "mmo_entity_pool_free";
+pub const POOL_IN_USE: &str = "mmo_entity_pool_in_use";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ArenaPolicy {
+    /// Capacity reserved up front.
+    pub initial_kb: usize,
+    /// Frames using more than this log a warning (once per new peak).
+    pub budget_kb: usize,
+    /// After a frame spills into extra chunks, the next frame starts with
+    /// one chunk of `peak * headroom` so it doesn't spill again.
+    pub headroom: f32,
+    /// Capacity is cut back to `peak * headroom` once the peak over this many
+    /// frames stays below half of it. 0 never shrinks.
+    pub shrink_after_frames: usize,
+}
+
+impl Default for ArenaPolicy {
+    fn default() -> Self {
+        Self {
+            initial_kb: 256,
+            budget_kb: 4096,
+            headroom: 1.25,
+            shrink_after_frames: 600,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ArenaStats {
+    pub frame: u64,
+    /// Bytes handed out last frame.
+    pub used_bytes: usize,
+    pub capacity_bytes: usize,
+    pub high_water_bytes: usize,
+    /// Chunks last frame; more than one means it outgrew its capacity.
+    pub chunks: usize,
+    pub grows: u32,
+    pub shrinks: u32,
+    pub over_budget_frames: u32,
+}
+
+static NEXT_ARENA_ID: AtomicU32 = AtomicU32::new(1);
+
+/// Scratch memory valid until the end of the frame. Borrow the [`Bump`]
+/// for allocations used within one system, or allocate a [`FrameHandle`]
+/// to pass a value to later systems in the same frame. Values are never
+/// dropped, so store plain data.
+#[derive(Resource)]
+pub struct FrameArena {
+    // The mutex only makes the arena `Sync`; access goes through `get_mut`
+    bump: Mutex<Bump>,
+    id: u32,
+    frame: u64,
+    policy: ArenaPolicy,
+    stats: ArenaStats,
+    recent_peaks: VecDeque<usize>,
+}
+
+impl Default for FrameArena {
+    fn default() -> Self {
+        Self::with_policy(ArenaPolicy::default())
+    }
+}
+
+impl FrameArena {
+    pub fn with_policy(policy: ArenaPolicy) -> Self {
+        let bump = Bump::with_capacity(policy.initial_kb * 1024);
+        let stats = ArenaStats {
+            capacity_bytes: bump.allocated_bytes(),
+            ..Default::default()
+        };
+        Self {
+            bump: Mutex::new(bump),
+            id: NEXT_ARENA_ID.fetch_add(1, Ordering::Relaxed),
+            frame: 0,
+            policy,
+            stats,
+            recent_peaks: VecDeque::new(),
+        }
+    }
+
+    fn bump_mut(&mut self) -> &mut Bump {
+        self.bump.get_mut().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// The allocator itself, for scratch data that stays inside a system.
+    pub fn bump(&mut self) -> &Bump {
+        self.bump_mut()
+    }
+
+    pub fn alloc<T: Copy + Send + Sync>(&mut self, value: T) -> FrameHandle<T> {
+        let (id, frame) = (self.id, self.frame);
+        let pointer = NonNull::from(self.bump_mut().alloc(value));
+        FrameHandle {
+            pointer,
+            arena: id,
+            frame,
+            _marker: PhantomData,
+        }
+    }
+
+    fn check<T>(&self, handle: &FrameHandle<T>) -> bool {
+        let valid = handle.arena == self.id && handle.frame == self.frame;
+        debug_assert!(
+            valid,
+            "frame arena handle from arena {} frame {} used in arena {} frame {}: it outlived its frame",
+            handle.arena, handle.frame, self.id, self.frame
+        );
+        valid
+    }
+
+    /// `None` (and a debug assertion) for a handle kept past its frame.
+    pub fn get<T>(&self, handle: FrameHandle<T>) -> Option<&T> {
+        if !self.check(&handle) {
+            return None;
+        }
+        // SAFETY: same arena and frame means the value was allocated since the
+        // last reset, and `&self` keeps the arena from resetting meanwhile.
+        Some(unsafe { handle.pointer.as_ref() })
+    }
+
+    pub fn get_mut<T>(&mut self, handle: FrameHandle<T>) -> Option<&mut T> {
+        if !self.check(&handle) {
+            return None;
+        }
+        let mut pointer = handle.pointer;
+        // SAFETY: as in `get`, with `&mut self` for exclusive access.
+        Some(unsafe { pointer.as_mut() })
+    }
+
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    pub fn stats(&self) -> &ArenaStats {
+        &self.stats
+    }
+
+    pub fn policy(&self) -> &ArenaPolicy {
+        &self.policy
+    }
+
+    pub fn set_policy(&mut self, policy: ArenaPolicy) {
+        self.policy = policy;
+    }
+
+    /// Ends the frame: records usage, frees everything and resizes per the
+    /// policy. Handles from the ending frame stop resolving.
+    pub fn reset(&mut self) {
+        let bump = self.bump_mut();
+        let (used, chunks) = bump
+            .iter_allocated_chunks()
+            .fold((0, 0), |(used, chunks), chunk| (used + chunk.len(), chunks + 1));
+        let capacity = bump.allocated_bytes();
+
+        self.recent_peaks.push_back(used);
+        while self.recent_peaks.len() > self.policy.shrink_after_frames.max(1) {
+            self.recent_peaks.pop_front();
+        }
+        let stats = &mut self.stats;
+        stats.frame = self.frame;
+        stats.used_bytes = used;
+        stats.chunks = chunks;
+        if used > self.policy.budget_kb * 1024 {
+            stats.over_budget_frames += 1;
+            if used > stats.high_water_bytes {
+                warn!("Frame arena used {} KB, over its {} KB budget", used / 1024, self.policy.budget_kb);
+            }
+        }
+        stats.high_water_bytes = stats.high_water_bytes.max(used);
+
+        let target = ((used as f32 * self.policy.headroom) as usize).max(self.policy.initial_kb * 1024);
+        let window_peak = self.recent_peaks.iter().copied().max().unwrap_or(0);
+        let window_full =
+            self.policy.shrink_after_frames > 0 && self.recent_peaks.len() >= self.policy.shrink_after_frames;
+        if chunks > 1 {
+            // Spilled: one chunk big enough for this frame next time
+            *self.bump_mut() = Bump::with_capacity(target);
+            self.stats.grows += 1;
+        } else if window_full && window_peak * 2 < capacity && capacity > self.policy.initial_kb * 1024 {
+            let shrunk = ((window_peak as f32 * self.policy.headroom) as usize).max(self.policy.initial_kb * 1024);
+            *self.bump_mut() = Bump::with_capacity(shrunk);
+            self.stats.shrinks += 1;
+            self.recent_peaks.clear();
+        } else {
+            self.bump_mut().reset();
+        }
+        self.stats.capacity_bytes = self.bump_mut().allocated_bytes();
+        self.frame += 1;
+    }
+}
+
+/// A value in the [`FrameArena`], valid until the frame ends. Resolving it
+/// later trips a debug assertion, which is how values escaping the arena's
+/// lifetime (kept in a component, a `Local`, ...) get caught.
+pub struct FrameHandle<T> {
+    pointer: NonNull<T>,
+    arena: u32,
+    frame: u64,
+    _marker: PhantomData<T>,
+}
+
+impl<T> Clone for FrameHandle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for FrameHandle<T> {}
+
+impl<T> std::fmt::Debug for FrameHandle<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "FrameHandle(arena {}, frame {})", self.arena, self.frame)
+    }
+}
+
+// SAFETY: the pointer is only dereferenced through the arena, which checks
+// the handle is current; `alloc` requires `T: Send + Sync`.
+unsafe impl<T: Send + Sync> Send for FrameHandle<T> {}
+unsafe impl<T: Send + Sync> Sync for FrameHandle<T> {}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PoolPolicy {
+    /// Released entities kept beyond this are handed back for despawning.
+    pub max_free: usize,
+    /// Free entities kept however long a kind goes unused.
+    pub min_free: usize,
+    /// Frames without an acquire before the free list is trimmed to
+    /// `min_free`. 0 never trims.
+    pub shrink_after_frames: u32,
+}
+
+impl Default for PoolPolicy {
+    fn default() -> Self {
+        Self {
+            max_free: 256,
+            min_free: 8,
+            shrink_after_frames: 1800,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PoolStats {
+    pub free: usize,
+    pub in_use: usize,
+    pub high_water_in_use: usize,
+    pub high_water_free: usize,
+    /// Acquires served from the free list.
+    pub reused: u64,
+    /// Acquires that found the free list empty.
+    pub missed: u64,
+    /// Releases turned away because the free list was full.
+    pub rejected: u64,
+    pub trimmed: u64,
+}
+
+impl PoolStats {
+    /// Share of acquires served without spawning.
+    pub fn hit_rate(&self) -> f32 {
+        let total = self.reused + self.missed;
+        if total == 0 {
+            0.0
+        } else {
+            self.reused as f32 / total as f32
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct PoolKind {
+    free: Vec<Entity>,
+    stats: PoolStats,
+    idle_frames: u32,
+}
+
+/// Released entities by kind. The pool only tracks ids: callers hide and
+/// reset an entity before releasing it, and spawn one themselves when
+/// `acquire` comes back empty, registering it with `track`.
+#[derive(Resource, Debug, Default)]
+pub struct EntityPool {
+    kinds: HashMap<String, PoolKind>,
+    in_use: HashMap<Entity, String>,
+    free_set: HashSet<Entity>,
+    pub default_policy: PoolPolicy,
+    pub policies: HashMap<String, PoolPolicy>,
+}
+
+impl EntityPool {
+    pub fn policy(&self, kind: &str) -> &PoolPolicy {
+        self.policies.get(kind).unwrap_or(&self.default_policy)
+    }
+
+    pub fn acquire(&mut self, kind: &str) -> Option<Entity> {
+        let pool = self.kinds.entry(kind.to_string()).or_default();
+        pool.idle_frames = 0;
+        let Some(entity) = pool.free.pop() else {
+            pool.stats.missed += 1;
+            return None;
+        };
+        pool.stats.reused += 1;
+        self.free_set.remove(&entity);
+        self.track(kind, entity);
+        Some(entity)
+    }
+
+    /// Counts a freshly spawned entity as in use.
+    pub fn track(&mut self, kind: &str, entity: Entity) {
+        let previous = self.in_use.insert(entity, kind.to_string());
+        debug_assert!(previous.is_none(), "{:?} tracked twice by the entity pool", entity);
+        let pool = self.kinds.entry(kind.to_string()).or_default();
+        pool.stats.in_use += 1;
+        pool.stats.high_water_in_use = pool.stats.high_water_in_use.max(pool.stats.in_use);
+    }
+
+    /// Returns `entity` to its kind's free list. `false` means the list is
+    /// full (or the entity isn't from the pool) and the caller should despawn it.
+    pub fn release(&mut self, entity: Entity) -> bool {
+        debug_assert!(!self.free_set.contains(&entity), "{:?} released to the entity pool twice", entity);
+        let Some(kind) = self.in_use.remove(&entity) else {
+            debug_assert!(false, "{:?} released to the entity pool but never acquired from it", entity);
+            return false;
+        };
+        let max_free = self.policy(&kind).max_free;
+        let pool = self.kinds.entry(kind).or_default();
+        pool.stats.in_use -= 1;
+        if pool.free.len() >= max_free {
+            pool.stats.rejected += 1;
+            return false;
+        }
+        pool.free.push(entity);
+        pool.stats.high_water_free = pool.stats.high_water_free.max(pool.free.len());
+        self.free_set.insert(entity);
+        true
+    }
+
+    /// Forgets an entity despawned while in use.
+    pub fn forget(&mut self, entity: Entity) {
+        if let Some(kind) = self.in_use.remove(&entity) {
+            if let Some(pool) = self.kinds.get_mut(&kind) {
+                pool.stats.in_use -= 1;
+            }
+        }
+    }
+
+    pub fn stats(&self) -> impl Iterator<Item = (&str, PoolStats)> {
+        self.kinds.iter().map(|(kind, pool)| {
+            let mut stats = pool.stats.clone();
+            stats.free = pool.free.len();
+            (kind.as_str(), stats)
+        })
+    }
+
+    /// Advances idle counters and takes free entities past their policy,
+    /// for the caller to despawn.
+    pub fn trim(&mut self) -> Vec<Entity> {
+        let mut trimmed = Vec::new();
+        for (kind, pool) in self.kinds.iter_mut() {
+            let policy = self.policies.get(kind).unwrap_or(&self.default_policy);
+            pool.idle_frames = pool.idle_frames.saturating_add(1);
+            let idle = policy.shrink_after_frames > 0 && pool.idle_frames >= policy.shrink_after_frames;
+            let keep = if idle { policy.min_free } else { policy.max_free };
+            if pool.free.len() > keep {
+                let excess = pool.free.split_off(keep);
+                pool.stats.trimmed += excess.len() as u64;
+                trimmed.extend(excess);
+            }
+        }
+        for entity in &trimmed {
+            self.free_set.remove(entity);
+        }
+        trimmed
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct EntityPoolFile {
+    #[serde(flatten)]
+    default: PoolPolicy,
+    kinds: HashMap<String, PoolPolicy>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct MemoryFile {
+    frame_arena: Option<ArenaPolicy>,
+    entity_pool: Option<EntityPoolFile>,
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct MemoryConfig {
+    pub policy_file: PathBuf,
+}
+
+impl Default for MemoryConfig {
+    fn default() -> Self {
+        Self {
+            policy_file: PathBuf::from("content").join("memory.toml"),
+        }
+    }
+}
+
+/// Last frame's numbers, for the profiler window.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct MemoryStats {
+    pub arena: ArenaStats,
+    /// Sorted by kind.
+    pub pools: Vec<(String, PoolStats)>,
+}
+
+pub struct MemoryPlugin;
+
+impl Plugin for MemoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MemoryConfig>()
+            .init_resource::<FrameArena>()
+            .init_resource::<EntityPool>()
+            .init_resource::<MemoryStats>()
+            .add_systems(Startup, (load_memory_policies, describe_memory_metrics))
+            .add_systems(Last, (reset_frame_arena, trim_entity_pool, publish_memory_stats).chain());
+    }
+}
+
+fn load_memory_policies(config: Res<MemoryConfig>, mut arena: ResMut<FrameArena>, mut pool: ResMut<EntityPool>) {
+    let Ok(text) = std::fs::read_to_string(&config.policy_file) else {
+        return;
+    };
+    match toml::from_str::<MemoryFile>(&text) {
+        Ok(file) => {
+            if let Some(policy) = file.frame_arena {
+                *arena = FrameArena::with_policy(policy);
+            }
+            if let Some(pools) = file.entity_pool {
+                pool.default_policy = pools.default;
+                pool.policies = pools.kinds;
+            }
+            info!("Loaded memory policies from {:?}", config.policy_file);
+        }
+        Err(e) => warn!("Invalid memory policies {:?}: {}", config.policy_file, e),
+    }
+}
+
+fn describe_memory_metrics(metrics: Option<Res<Metrics>>) {
+    let Some(metrics) = metrics else {
+        return;
+    };
+    metrics.describe_gauge(ARENA_USED_BYTES, "Frame arena bytes used last frame");
+    metrics.describe_gauge(ARENA_CAPACITY_BYTES, "Frame arena capacity");
+    metrics.describe_gauge(POOL_FREE, "Pooled entities waiting for reuse, by kind");
+    metrics.describe_gauge(POOL_IN_USE, "Pooled entities in use, by kind");
+}
+
+fn reset_frame_arena(mut arena: ResMut<FrameArena>) {
+    arena.reset();
+}
+
+fn trim_entity_pool(mut commands: Commands, mut pool: ResMut<EntityPool>) {
+    for entity in pool.trim() {
+        if let Some(entity) = commands.get_entity(entity) {
+            entity.despawn_recursive();
+        }
+    }
+}
+
+fn publish_memory_stats(
+    arena: Res<FrameArena>,
+    pool: Res<EntityPool>,
+    metrics: Option<Res<Metrics>>,
+    mut stats: ResMut<MemoryStats>,
+) {
+    stats.arena = arena.stats().clone();
+    stats.pools = pool.stats().map(|(kind, s)| (kind.to_string(), s)).collect();
+    stats.pools.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let Some(metrics) = metrics else {
+        return;
+    };
+    metrics.set_gauge(ARENA_USED_BYTES, &[], stats.arena.used_bytes as f64);
+    metrics.set_gauge(ARENA_CAPACITY_BYTES, &[], stats.arena.capacity_bytes as f64);
+    for (kind, pool) in &stats.pools {
+        metrics.set_gauge(POOL_FREE, &[("kind", kind)], pool.free as f64);
+        metrics.set_gauge(POOL_IN_USE, &[("kind", kind)], pool.in_use as f64);
+    }
+}
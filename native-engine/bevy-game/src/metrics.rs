@@ -0,0 +1,332 @@
+//! Counters, gauges and histograms served in the Prometheus text format at
+//! `http://<MMO_METRICS_ADDR>/metrics`, off unless the variable (or
+//! `--metrics <addr>`) is set. Dedicated servers are the main user; an
+//! OpenTelemetry collector can scrape the same endpoint with its Prometheus
+//! receiver and forward over OTLP.
+//!
+//! Frame rate, frame time, entity count and profiled system time are
+//! sampled here. Code that owns other numbers reports them through
+//! [`Metrics`], using the names below so dashboards stay stable.
+
+use bevy::prelude::*;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+
+use crate::profiler::SystemTimeline;
+
+pub const FPS: &str = "mmo_fps";
+pub const FRAME_SECONDS: &str = "mmo_frame_seconds";
+pub const FRAMES_TOTAL: &str = "mmo_frames_total";
+pub const ENTITIES: &str = "mmo_entities";
+pub const SYSTEMS_SECONDS: &str = "mmo_profiled_systems_seconds";
+/// Round trip to the game server, observed by the network client.
+pub const NETWORK_RTT_SECONDS: &str = "mmo_network_rtt_seconds";
+/// Spawn requests waiting in the spawn queue, set by the spawner.
+pub const SPAWN_QUEUE_DEPTH: &str = "mmo_spawn_queue_depth";
+pub const CONNECTED_PLAYERS: &str = "mmo_connected_players";
+/// Commands waiting for the network IO thread.
+pub const NET_SEND_QUEUE_DEPTH: &str = "mmo_net_send_queue_depth";
+/// Commands dropped because the network send queue was full.
+pub const NET_SEND_DROPPED_TOTAL: &str = "mmo_net_send_dropped_total";
+
+/// Upper bounds for frame and tick durations, in seconds.
+const FRAME_BUCKETS: &[f64] = &[0.004, 0.008, 0.0167, 0.025, 0.0333, 0.05, 0.1, 0.25];
+const RTT_BUCKETS: &[f64] = &[0.01, 0.025, 0.05, 0.1, 0.15, 0.25, 0.5, 1.0];
+
+#[derive(Resource, Debug, Clone)]
+pub struct MetricsConfig {
+    /// Listen address, e.g. `0.0.0.0:9184`.
+    pub bind: Option<String>,
+    /// Seconds between gauge samples.
+    pub sample_interval: f32,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        let args: Vec<String> = std::env::args().collect();
+        let bind = args
+            .iter()
+            .position(|a| a == "--metrics")
+            .and_then(|i| args.get(i + 1).cloned())
+            .or_else(|| std::env::var("MMO_METRICS_ADDR").ok())
+            .filter(|v| !v.is_empty());
+        Self {
+            bind,
+            sample_interval: 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricKind {
+    Counter,
+    Gauge,
+    Histogram,
+}
+
+impl MetricKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Counter => "counter",
+            Self::Gauge => "gauge",
+            Self::Histogram => "histogram",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Series {
+    Value(f64),
+    Histogram { counts: Vec<u64>, sum: f64, count: u64 },
+}
+
+#[derive(Debug, Clone)]
+struct Family {
+    kind: MetricKind,
+    help: String,
+    buckets: Vec<f64>,
+    /// Keyed by rendered label set, e.g. `{zone="frostvale"}`.
+    series: BTreeMap<String, Series>,
+}
+
+/// Shared with the HTTP thread; cheap to clone and safe to use from any
+/// system or worker thread.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct Metrics(Arc<Mutex<BTreeMap<String, Family>>>);
+
+fn render_labels(labels: &[(&str, &str)]) -> String {
+    if labels.is_empty() {
+        return String::new();
+    }
+    let pairs: Vec<String> = labels
+        .iter()
+        .map(|(k, v)| format!("{}=\"{}\"", k, v.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")))
+        .collect();
+    format!("{{{}}}", pairs.join(","))
+}
+
+/// Adds `le` to an already rendered label set.
+fn with_bucket(labels: &str, le: &str) -> String {
+    match labels.strip_suffix('}') {
+        Some(open) => format!("{},le=\"{}\"}}", open, le),
+        None => format!("{{le=\"{}\"}}", le),
+    }
+}
+
+impl Metrics {
+    fn describe(&self, name: &str, kind: MetricKind, help: &str, buckets: &[f64]) {
+        let Ok(mut families) = self.0.lock() else {
+            return;
+        };
+        families.entry(name.to_string()).or_insert_with(|| Family {
+            kind,
+            help: help.to_string(),
+            buckets: buckets.to_vec(),
+            series: BTreeMap::new(),
+        });
+    }
+
+    pub fn describe_counter(&self, name: &str, help: &str) {
+        self.describe(name, MetricKind::Counter, help, &[]);
+    }
+
+    pub fn describe_gauge(&self, name: &str, help: &str) {
+        self.describe(name, MetricKind::Gauge, help, &[]);
+    }
+
+    /// `buckets` are upper bounds in ascending order; `+Inf` is implied.
+    pub fn describe_histogram(&self, name: &str, help: &str, buckets: &[f64]) {
+        self.describe(name, MetricKind::Histogram, help, buckets);
+    }
+
+    /// Runs `update` on a series, if `name` was described as `kind`.
+    fn update(&self, name: &str, kind: MetricKind, labels: &[(&str, &str)], update: impl FnOnce(&[f64], &mut Series)) {
+        let Ok(mut families) = self.0.lock() else {
+            return;
+        };
+        let Some(family) = families.get_mut(name) else {
+            warn_once!("Metric {} used before it was described", name);
+            return;
+        };
+        if family.kind != kind {
+            return;
+        }
+        let buckets = family.buckets.clone();
+        let series = family.series.entry(render_labels(labels)).or_insert_with(|| match kind {
+            MetricKind::Histogram => Series::Histogram {
+                counts: vec![0; buckets.len()],
+                sum: 0.0,
+                count: 0,
+            },
+            _ => Series::Value(0.0),
+        });
+        update(&buckets, series);
+    }
+
+    pub fn increment(&self, name: &str, labels: &[(&str, &str)], by: f64) {
+        self.update(name, MetricKind::Counter, labels, |_, series| {
+            if let Series::Value(value) = series {
+                *value += by.max(0.0);
+            }
+        });
+    }
+
+    pub fn set_gauge(&self, name: &str, labels: &[(&str, &str)], to: f64) {
+        self.update(name, MetricKind::Gauge, labels, |_, series| {
+            if let Series::Value(value) = series {
+                *value = to;
+            }
+        });
+    }
+
+    pub fn observe(&self, name: &str, labels: &[(&str, &str)], value: f64) {
+        self.update(name, MetricKind::Histogram, labels, |buckets, series| {
+            if let Series::Histogram { counts, sum, count } = series {
+                for (bound, bucket) in buckets.iter().zip(counts.iter_mut()) {
+                    if value <= *bound {
+                        *bucket += 1;
+                    }
+                }
+                *sum += value;
+                *count += 1;
+            }
+        });
+    }
+
+    /// Prometheus text exposition format, version 0.0.4.
+    pub fn render(&self) -> String {
+        let Ok(families) = self.0.lock() else {
+            return String::new();
+        };
+        let mut out = String::new();
+        for (name, family) in families.iter() {
+            let _ = writeln!(out, "# HELP {} {}", name, family.help);
+            let _ = writeln!(out, "# TYPE {} {}", name, family.kind.as_str());
+            for (labels, series) in &family.series {
+                match series {
+                    Series::Value(value) => {
+                        let _ = writeln!(out, "{}{} {}", name, labels, value);
+                    }
+                    Series::Histogram { counts, sum, count } => {
+                        for (bound, bucket) in family.buckets.iter().zip(counts) {
+                            let le = with_bucket(labels, &bound.to_string());
+                            let _ = writeln!(out, "{}_bucket{} {}", name, le, bucket);
+                        }
+                        let _ = writeln!(out, "{}_bucket{} {}", name, with_bucket(labels, "+Inf"), count);
+                        let _ = writeln!(out, "{}_sum{} {}", name, labels, sum);
+                        let _ = writeln!(out, "{}_count{} {}", name, labels, count);
+                    }
+                }
+            }
+        }
+        out
+    }
+}
+
+pub struct MetricsPlugin;
+
+impl Plugin for MetricsPlugin {
+    fn build(&self, app: &mut App) {
+        let metrics = Metrics::default();
+        metrics.describe_gauge(FPS, "Frames per second, averaged over the sample interval");
+        metrics.describe_histogram(FRAME_SECONDS, "Frame (server tick) duration", FRAME_BUCKETS);
+        metrics.describe_counter(FRAMES_TOTAL, "Frames (server ticks) run");
+        metrics.describe_gauge(ENTITIES, "Live ECS entities");
+        metrics.describe_gauge(SYSTEMS_SECONDS, "Time in profiled systems last frame, by system");
+        metrics.describe_histogram(NETWORK_RTT_SECONDS, "Round trip time to the game server", RTT_BUCKETS);
+        metrics.describe_gauge(SPAWN_QUEUE_DEPTH, "Spawn requests waiting in the spawn queue");
+        metrics.describe_gauge(CONNECTED_PLAYERS, "Players connected to this server");
+        metrics.describe_gauge(NET_SEND_QUEUE_DEPTH, "Commands waiting for the network IO thread");
+        metrics.describe_counter(NET_SEND_DROPPED_TOTAL, "Network commands dropped on a full send queue");
+
+        app.init_resource::<MetricsConfig>()
+            .insert_resource(metrics)
+            .add_systems(Startup, start_metrics_endpoint)
+            .add_systems(Last, sample_metrics);
+    }
+}
+
+fn start_metrics_endpoint(config: Res<MetricsConfig>, metrics: Res<Metrics>) {
+    let Some(bind) = config.bind.clone() else {
+        return;
+    };
+    let listener = match TcpListener::bind(&bind) {
+        Ok(listener) => listener,
+        Err(e) => {
+            warn!("Metrics endpoint off: failed to listen on {}: {}", bind, e);
+            return;
+        }
+    };
+    info!("Serving metrics on http://{}/metrics", bind);
+
+    let metrics = metrics.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            serve_scrape(stream, &metrics);
+        }
+    });
+}
+
+/// Answers one HTTP request. Scrapes are small and infrequent, so they're
+/// served one at a time on the listener thread.
+fn serve_scrape(mut stream: TcpStream, metrics: &Metrics) {
+    let _ = stream.set_read_timeout(Some(std::time::Duration::from_secs(5)));
+    let Ok(reader) = stream.try_clone() else {
+        return;
+    };
+    let mut lines = BufReader::new(reader).lines();
+    let request = lines.next().and_then(Result::ok).unwrap_or_default();
+    // Skip the headers; no request here has a body
+    for line in lines.map_while(Result::ok) {
+        if line.is_empty() {
+            break;
+        }
+    }
+
+    let path = request.split_whitespace().nth(1).unwrap_or("");
+    let response = if request.starts_with("GET ") && (path == "/metrics" || path.starts_with("/metrics?")) {
+        let body = metrics.render();
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )
+    } else {
+        "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+    };
+    let _ = stream.write_all(response.as_bytes());
+}
+
+fn sample_metrics(
+    time: Res<Time>,
+    config: Res<MetricsConfig>,
+    metrics: Res<Metrics>,
+    timeline: Option<Res<SystemTimeline>>,
+    entities: &bevy::ecs::entity::Entities,
+    mut window: Local<(f32, u32)>,
+) {
+    let delta = time.delta_secs();
+    metrics.observe(FRAME_SECONDS, &[], delta as f64);
+    metrics.increment(FRAMES_TOTAL, &[], 1.0);
+
+    let (elapsed, frames) = &mut *window;
+    *elapsed += delta;
+    *frames += 1;
+    if *elapsed < config.sample_interval {
+        return;
+    }
+    metrics.set_gauge(FPS, &[], (*frames as f32 / *elapsed) as f64);
+    *window = (0.0, 0);
+
+    metrics.set_gauge(ENTITIES, &[], entities.len() as f64);
+    if let Some(frame) = timeline.as_ref().and_then(|t| t.frames.back()) {
+        for (system, ms) in &frame.systems {
+            let system = crate::profiler::short_system_name(system);
+            metrics.set_gauge(SYSTEMS_SECONDS, &[("system", system)], *ms as f64 / 1000.0);
+        }
+    }
+}
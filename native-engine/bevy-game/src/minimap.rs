@@ -0,0 +1,576 @@
+use bevy::prelude::*;
+use bevy::render::camera::{RenderTarget, ScalingMode};
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages};
+use bevy::render::view::screenshot::{save_to_disk, Screenshot};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::PathBuf;
+
+use crate::console::{CommandPermission, ConsoleAppExt, ConsoleCommand, ConsoleCommandEvent, ConsoleOutputEvent};
+use crate::Player;
+
+/// Frames to wait after moving the capture camera before reading back the tile,
+/// so streamed terrain chunks under the camera have a chance to mesh.
+const CAPTURE_SETTLE_FRAMES: u32 = 3;
+
+/// Tiles around the player `bakemap` captures without a radius.
+const DEFAULT_BAKE_RADIUS: u32 = 8;
+
+#[derive(Resource, Clone, Debug)]
+pub struct MinimapConfig {
+    /// World units covered by one map tile (matches the terrain chunk footprint).
+    pub tile_world_size: f32,
+    pub tile_resolution: u32,
+    /// Height the orthographic capture camera is placed at above the tile.
+    pub capture_height: f32,
+    pub cache_dir: PathBuf,
+    /// Tiles around the player kept resident for the minimap.
+    pub stream_radius: i32,
+    pub max_resident_tiles: usize,
+    pub minimap_size_px: f32,
+}
+
+impl Default for MinimapConfig {
+    fn default() -> Self {
+        Self {
+            tile_world_size: 64.0,
+            tile_resolution: 256,
+            capture_height: 1000.0,
+            cache_dir: PathBuf::from("cache").join("map_tiles"),
+            stream_radius: 2,
+            max_resident_tiles: 256,
+            minimap_size_px: 240.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MapTileCoord {
+    pub x: i32,
+    pub z: i32,
+}
+
+impl MapTileCoord {
+    pub fn from_world(position: Vec3, tile_world_size: f32) -> Self {
+        Self {
+            x: (position.x / tile_world_size).floor() as i32,
+            z: (position.z / tile_world_size).floor() as i32,
+        }
+    }
+
+    pub fn center(&self, tile_world_size: f32) -> Vec3 {
+        Vec3::new(
+            (self.x as f32 + 0.5) * tile_world_size,
+            0.0,
+            (self.z as f32 + 0.5) * tile_world_size,
+        )
+    }
+
+    /// Every tile overlapping the world-space rectangle from `min` to `max`
+    /// on the XZ plane.
+    pub fn covering(min: Vec2, max: Vec2, tile_world_size: f32) -> impl Iterator<Item = MapTileCoord> {
+        let min = Self::from_world(Vec3::new(min.x, 0.0, min.y), tile_world_size);
+        // A rectangle ending on a tile edge doesn't reach into the next tile
+        let max = Self::from_world(Vec3::new(max.x, 0.0, max.y) - Vec3::splat(1e-3), tile_world_size);
+        (min.z..=max.z).flat_map(move |z| (min.x..=max.x).map(move |x| MapTileCoord { x, z }))
+    }
+
+    fn file_name(&self) -> String {
+        format!("tile_{}_{}.png", self.x, self.z)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MapTile {
+    pub image: Handle<Image>,
+    pub revision: u32,
+    pub last_used_frame: u64,
+}
+
+/// Captured minimap/world map tiles, keyed by tile coordinate.
+///
+/// The UI reads tile images from here; capture and disk caching are driven by
+/// `MinimapPlugin` systems.
+#[derive(Resource, Default)]
+pub struct MapTileCache {
+    tiles: HashMap<MapTileCoord, MapTile>,
+    capture_queue: VecDeque<MapTileCoord>,
+    queued: HashSet<MapTileCoord>,
+    revisions: HashMap<MapTileCoord, u32>,
+    frame: u64,
+    pub captures_completed: u64,
+    pub disk_hits: u64,
+}
+
+impl MapTileCache {
+    pub fn tile_image(&self, coord: MapTileCoord) -> Option<Handle<Image>> {
+        self.tiles.get(&coord).map(|tile| tile.image.clone())
+    }
+
+    pub fn contains(&self, coord: MapTileCoord) -> bool {
+        self.tiles.contains_key(&coord)
+    }
+
+    pub fn resident_count(&self) -> usize {
+        self.tiles.len()
+    }
+
+    pub fn pending_captures(&self) -> usize {
+        self.capture_queue.len()
+    }
+
+    pub fn queue_capture(&mut self, coord: MapTileCoord) {
+        if self.queued.insert(coord) {
+            self.capture_queue.push_back(coord);
+        }
+    }
+
+    /// Queue at the front so tiles the player is standing on are captured first.
+    pub fn queue_capture_urgent(&mut self, coord: MapTileCoord) {
+        if self.queued.insert(coord) {
+            self.capture_queue.push_front(coord);
+        } else if let Some(index) = self.capture_queue.iter().position(|c| *c == coord) {
+            self.capture_queue.remove(index);
+            self.capture_queue.push_front(coord);
+        }
+    }
+
+    fn insert(&mut self, coord: MapTileCoord, image: Handle<Image>) {
+        let revision = self.revisions.get(&coord).copied().unwrap_or(0);
+        self.tiles.insert(
+            coord,
+            MapTile {
+                image,
+                revision,
+                last_used_frame: self.frame,
+            },
+        );
+    }
+
+    fn invalidate(&mut self, coord: MapTileCoord) {
+        self.tiles.remove(&coord);
+        *self.revisions.entry(coord).or_insert(0) += 1;
+    }
+
+    fn touch(&mut self, coord: MapTileCoord) {
+        let frame = self.frame;
+        if let Some(tile) = self.tiles.get_mut(&coord) {
+            tile.last_used_frame = frame;
+        }
+    }
+
+    fn evict_over_budget(&mut self, max_resident: usize) {
+        if self.tiles.len() <= max_resident {
+            return;
+        }
+        let mut by_age: Vec<(MapTileCoord, u64)> = self
+            .tiles
+            .iter()
+            .map(|(coord, tile)| (*coord, tile.last_used_frame))
+            .collect();
+        by_age.sort_by_key(|(_, frame)| *frame);
+        let excess = self.tiles.len() - max_resident;
+        for (coord, _) in by_age.into_iter().take(excess) {
+            self.tiles.remove(&coord);
+        }
+    }
+}
+
+/// Sent by the terrain editor (or anything else that modifies a chunk) so the
+/// cached map tile covering that area is thrown away and re-captured.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct MapTileDirtyEvent {
+    pub coord: MapTileCoord,
+}
+
+/// Requests capture of every tile within `radius_tiles` of `center`, used to
+/// pre-bake the world map at build time or from a dev command.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct BakeWorldMapEvent {
+    pub center: MapTileCoord,
+    pub radius_tiles: i32,
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct MapTileReadyEvent {
+    pub coord: MapTileCoord,
+    pub revision: u32,
+}
+
+#[derive(Component)]
+pub struct MapCaptureCamera;
+
+#[derive(Component)]
+pub struct MinimapUI;
+
+#[derive(Component)]
+pub struct MinimapTileSlot {
+    pub offset_x: i32,
+    pub offset_z: i32,
+}
+
+#[derive(Resource, Default)]
+struct CaptureInProgress {
+    active: Option<ActiveCapture>,
+}
+
+struct ActiveCapture {
+    coord: MapTileCoord,
+    image: Handle<Image>,
+    frames_waited: u32,
+}
+
+pub struct MinimapPlugin;
+
+impl Plugin for MinimapPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MinimapConfig>()
+            .init_resource::<MapTileCache>()
+            .init_resource::<CaptureInProgress>()
+            .add_event::<MapTileDirtyEvent>()
+            .add_event::<BakeWorldMapEvent>()
+            .add_event::<MapTileReadyEvent>()
+            .add_console_command(
+                ConsoleCommand::new("bakemap", "Captures the world map tiles around the player")
+                    .usage("[radius]")
+                    .permission(CommandPermission::Dev),
+            )
+            .add_systems(Startup, (setup_capture_camera, setup_minimap_ui))
+            .add_systems(
+                Update,
+                (
+                    bake_map_command,
+                    handle_dirty_tiles,
+                    handle_bake_requests,
+                    stream_tiles_around_player,
+                    capture_map_tiles,
+                    update_minimap_ui,
+                )
+                    .chain(),
+            );
+
+        info!("MinimapPlugin initialized (orthographic tile capture + disk cache)");
+    }
+}
+
+fn setup_capture_camera(mut commands: Commands, config: Res<MinimapConfig>) {
+    if let Err(e) = std::fs::create_dir_all(&config.cache_dir) {
+        warn!("Could not create map tile cache dir {:?}: {}", config.cache_dir, e);
+    }
+
+    commands.spawn((
+        Camera3d::default(),
+        Camera {
+            is_active: false,
+            order: -10,
+            clear_color: ClearColorConfig::Custom(Color::srgb(0.05, 0.08, 0.12)),
+            ..default()
+        },
+        Projection::Orthographic(OrthographicProjection {
+            scaling_mode: ScalingMode::Fixed {
+                width: config.tile_world_size,
+                height: config.tile_world_size,
+            },
+            far: config.capture_height * 2.0,
+            ..OrthographicProjection::default_3d()
+        }),
+        Transform::from_xyz(0.0, config.capture_height, 0.0).looking_at(Vec3::ZERO, Vec3::NEG_Z),
+        MapCaptureCamera,
+        Name::new("MapCaptureCamera"),
+    ));
+}
+
+fn setup_minimap_ui(mut commands: Commands, config: Res<MinimapConfig>) {
+    let grid = config.stream_radius.max(1);
+    let cells = (grid * 2 + 1) as f32;
+    let cell_px = config.minimap_size_px / cells;
+
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                right: Val::Px(10.0),
+                top: Val::Px(10.0),
+                width: Val::Px(config.minimap_size_px),
+                height: Val::Px(config.minimap_size_px),
+                flex_direction: FlexDirection::Column,
+                overflow: Overflow::clip(),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+            MinimapUI,
+        ))
+        .with_children(|parent| {
+            // Rows go north (-Z) to south (+Z) so the map reads top-down
+            for dz in -grid..=grid {
+                parent
+                    .spawn(Node {
+                        flex_direction: FlexDirection::Row,
+                        ..default()
+                    })
+                    .with_children(|row| {
+                        for dx in -grid..=grid {
+                            row.spawn((
+                                ImageNode::default(),
+                                Node {
+                                    width: Val::Px(cell_px),
+                                    height: Val::Px(cell_px),
+                                    ..default()
+                                },
+                                MinimapTileSlot {
+                                    offset_x: dx,
+                                    offset_z: dz,
+                                },
+                            ));
+                        }
+                    });
+            }
+        });
+}
+
+fn handle_dirty_tiles(
+    mut events: EventReader<MapTileDirtyEvent>,
+    mut cache: ResMut<MapTileCache>,
+    config: Res<MinimapConfig>,
+) {
+    for event in events.read() {
+        cache.invalidate(event.coord);
+        let path = config.cache_dir.join(event.coord.file_name());
+        if path.exists() {
+            if let Err(e) = std::fs::remove_file(&path) {
+                warn!("Failed to remove stale map tile {:?}: {}", path, e);
+            }
+        }
+        cache.queue_capture_urgent(event.coord);
+        debug!("Map tile {:?} marked dirty - re-capturing", event.coord);
+    }
+}
+
+fn bake_map_command(
+    config: Res<MinimapConfig>,
+    player_query: Query<&Transform, With<Player>>,
+    mut events: EventReader<ConsoleCommandEvent>,
+    mut output: EventWriter<ConsoleOutputEvent>,
+    mut bake: EventWriter<BakeWorldMapEvent>,
+) {
+    for event in events.read() {
+        if event.name != "bakemap" {
+            continue;
+        }
+        let radius = match event.args.first().map(|_| event.arg::<u32>(0, "radius")).transpose() {
+            Ok(radius) => radius.unwrap_or(DEFAULT_BAKE_RADIUS).min(i32::MAX as u32) as i32,
+            Err(e) => {
+                output.send(event.error(e));
+                continue;
+            }
+        };
+        let position = player_query.get_single().map(|t| t.translation).unwrap_or(Vec3::ZERO);
+        let center = MapTileCoord::from_world(position, config.tile_world_size);
+        bake.send(BakeWorldMapEvent {
+            center,
+            radius_tiles: radius,
+        });
+        let side = 2 * radius as i64 + 1;
+        output.send(event.reply(format!(
+            "Baking {} map tiles around tile {},{} into {:?}",
+            side * side,
+            center.x,
+            center.z,
+            config.cache_dir
+        )));
+    }
+}
+
+fn handle_bake_requests(mut events: EventReader<BakeWorldMapEvent>, mut cache: ResMut<MapTileCache>) {
+    for event in events.read() {
+        let r = event.radius_tiles.max(0);
+        for z in -r..=r {
+            for x in -r..=r {
+                cache.queue_capture(MapTileCoord {
+                    x: event.center.x + x,
+                    z: event.center.z + z,
+                });
+            }
+        }
+        info!(
+            "World map bake queued: {} tiles around {:?}",
+            (2 * r + 1) * (2 * r + 1),
+            event.center
+        );
+    }
+}
+
+fn stream_tiles_around_player(
+    config: Res<MinimapConfig>,
+    mut cache: ResMut<MapTileCache>,
+    mut images: ResMut<Assets<Image>>,
+    player_query: Query<&Transform, With<Player>>,
+    mut ready_events: EventWriter<MapTileReadyEvent>,
+) {
+    cache.frame += 1;
+
+    let Ok(player_transform) = player_query.get_single() else {
+        return;
+    };
+    let center = MapTileCoord::from_world(player_transform.translation, config.tile_world_size);
+    let r = config.stream_radius;
+
+    for dz in -r..=r {
+        for dx in -r..=r {
+            let coord = MapTileCoord {
+                x: center.x + dx,
+                z: center.z + dz,
+            };
+            if cache.contains(coord) {
+                cache.touch(coord);
+                continue;
+            }
+
+            let path = config.cache_dir.join(coord.file_name());
+            match load_tile_from_disk(&path) {
+                Some(image) => {
+                    let handle = images.add(image);
+                    cache.insert(coord, handle);
+                    cache.disk_hits += 1;
+                    ready_events.send(MapTileReadyEvent {
+                        coord,
+                        revision: cache.revisions.get(&coord).copied().unwrap_or(0),
+                    });
+                }
+                None if dx == 0 && dz == 0 => cache.queue_capture_urgent(coord),
+                None => cache.queue_capture(coord),
+            }
+        }
+    }
+
+    let max_resident = config.max_resident_tiles;
+    cache.evict_over_budget(max_resident);
+}
+
+fn load_tile_from_disk(path: &std::path::Path) -> Option<Image> {
+    if !path.exists() {
+        return None;
+    }
+    match image::open(path) {
+        Ok(dynamic) => Some(Image::from_dynamic(
+            dynamic,
+            true,
+            RenderAssetUsages::RENDER_WORLD | RenderAssetUsages::MAIN_WORLD,
+        )),
+        Err(e) => {
+            warn!("Corrupt map tile {:?} ({}), will re-capture", path, e);
+            let _ = std::fs::remove_file(path);
+            None
+        }
+    }
+}
+
+fn new_capture_target(resolution: u32) -> Image {
+    let size = Extent3d {
+        width: resolution,
+        height: resolution,
+        depth_or_array_layers: 1,
+    };
+    let mut image = Image::new_fill(
+        size,
+        TextureDimension::D2,
+        &[0, 0, 0, 255],
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    image.texture_descriptor.usage = TextureUsages::TEXTURE_BINDING
+        | TextureUsages::COPY_DST
+        | TextureUsages::COPY_SRC
+        | TextureUsages::RENDER_ATTACHMENT;
+    image
+}
+
+fn capture_map_tiles(
+    mut commands: Commands,
+    config: Res<MinimapConfig>,
+    mut cache: ResMut<MapTileCache>,
+    mut in_progress: ResMut<CaptureInProgress>,
+    mut images: ResMut<Assets<Image>>,
+    mut camera_query: Query<(&mut Camera, &mut Transform), With<MapCaptureCamera>>,
+    mut ready_events: EventWriter<MapTileReadyEvent>,
+) {
+    let Ok((mut camera, mut transform)) = camera_query.get_single_mut() else {
+        return;
+    };
+
+    if let Some(active) = in_progress.active.as_mut() {
+        active.frames_waited += 1;
+        if active.frames_waited < CAPTURE_SETTLE_FRAMES {
+            return;
+        }
+
+        let coord = active.coord;
+        let image = active.image.clone();
+        let path = config.cache_dir.join(coord.file_name());
+        commands
+            .spawn(Screenshot::image(image.clone()))
+            .observe(save_to_disk(path));
+
+        camera.is_active = false;
+        cache.queued.remove(&coord);
+        cache.insert(coord, image);
+        cache.captures_completed += 1;
+        ready_events.send(MapTileReadyEvent {
+            coord,
+            revision: cache.revisions.get(&coord).copied().unwrap_or(0),
+        });
+        in_progress.active = None;
+        return;
+    }
+
+    // Only one tile in flight at a time keeps the capture cost to a single
+    // extra orthographic pass per frame
+    let Some(coord) = cache.capture_queue.pop_front() else {
+        return;
+    };
+    if cache.contains(coord) {
+        cache.queued.remove(&coord);
+        return;
+    }
+
+    let image = images.add(new_capture_target(config.tile_resolution));
+    let center = coord.center(config.tile_world_size);
+    *transform = Transform::from_translation(center + Vec3::Y * config.capture_height)
+        .looking_at(center, Vec3::NEG_Z);
+    camera.target = RenderTarget::Image(image.clone());
+    camera.is_active = true;
+
+    in_progress.active = Some(ActiveCapture {
+        coord,
+        image,
+        frames_waited: 0,
+    });
+}
+
+fn update_minimap_ui(
+    config: Res<MinimapConfig>,
+    cache: Res<MapTileCache>,
+    player_query: Query<&Transform, With<Player>>,
+    mut slot_query: Query<(&MinimapTileSlot, &mut ImageNode)>,
+    mut ready_events: EventReader<MapTileReadyEvent>,
+    mut last_center: Local<Option<MapTileCoord>>,
+) {
+    let Ok(player_transform) = player_query.get_single() else {
+        return;
+    };
+    let center = MapTileCoord::from_world(player_transform.translation, config.tile_world_size);
+
+    let tiles_changed = ready_events.read().count() > 0;
+    if *last_center == Some(center) && !tiles_changed {
+        return;
+    }
+    *last_center = Some(center);
+
+    for (slot, mut image_node) in slot_query.iter_mut() {
+        let coord = MapTileCoord {
+            x: center.x + slot.offset_x,
+            z: center.z + slot.offset_z,
+        };
+        image_node.image = cache.tile_image(coord).unwrap_or_default();
+    }
+}
@@ -0,0 +1,336 @@
+//! Weather and terrain effects on mounts. Every rider carrying a
+//! [`MountEnvironment`] is probed each frame for the wind at its position
+//! (the weather wind with gusts, plus updrafts where that wind meets a cliff
+//! face), whether it is skimming water, and the surface under a grounded
+//! mount.
+//!
+//! Flight uses the result through [`MountEnvironment::acceleration`], which
+//! the skyriding physics adds to its own thrust and drag: tailwinds carry the
+//! mount, headwinds hold it back, cliffs lift it and water spray drags it.
+//! Ground mounts get the surface directly: snow and sand lower the
+//! controller's grip and top speed while the mount stands on them.
+//!
+//! Mount code inserts the component when the rider mounts, after setting the
+//! mount's movement config, and removes it on dismount; removal restores the
+//! config the surface scaled.
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use std::collections::HashMap;
+
+use crate::console::{
+    ArgCompletion, CommandPermission, ConsoleAppExt, ConsoleCommand, ConsoleCommandEvent, ConsoleOutputEvent,
+};
+use crate::engine_fabric::physics::{CharacterController, SurfaceMaterial};
+use crate::sound::{AmbienceConditions, TerrainSurfaceMap};
+
+/// How a surface changes ground mount handling.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SurfaceHandling {
+    /// Multiplies acceleration and braking.
+    pub grip: f32,
+    /// Multiplies top speed.
+    pub speed: f32,
+}
+
+impl Default for SurfaceHandling {
+    fn default() -> Self {
+        Self { grip: 1.0, speed: 1.0 }
+    }
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct MountEnvironmentConfig {
+    /// Fraction of the wind speed that becomes acceleration each second. The
+    /// mount's own drag balances it, so steady wind shifts cruising speed.
+    pub wind_coupling: f32,
+    /// How far upwind a cliff face still lifts a rider.
+    pub updraft_range: f32,
+    /// Upward acceleration per m/s of wind right against a cliff face.
+    pub updraft_per_wind: f32,
+    /// Faces steeper than this (normal's y below it) count as cliffs.
+    pub cliff_max_normal_y: f32,
+    /// Flying lower than this over water counts as skimming it.
+    pub skim_height: f32,
+    /// Fraction of horizontal speed lost per second while skimming water.
+    pub water_drag: f32,
+    pub surfaces: HashMap<SurfaceMaterial, SurfaceHandling>,
+    /// Surface assumed in each biome where colliders and the terrain splat
+    /// map say nothing.
+    pub biome_surfaces: HashMap<String, SurfaceMaterial>,
+}
+
+impl Default for MountEnvironmentConfig {
+    fn default() -> Self {
+        Self {
+            wind_coupling: 0.15,
+            updraft_range: 40.0,
+            updraft_per_wind: 0.8,
+            cliff_max_normal_y: 0.5,
+            skim_height: 4.0,
+            water_drag: 0.35,
+            surfaces: HashMap::from([
+                (SurfaceMaterial::Snow, SurfaceHandling { grip: 0.55, speed: 0.8 }),
+                (SurfaceMaterial::Sand, SurfaceHandling { grip: 0.7, speed: 0.75 }),
+                (SurfaceMaterial::Water, SurfaceHandling { grip: 0.5, speed: 0.6 }),
+                (SurfaceMaterial::Dirt, SurfaceHandling { grip: 0.95, speed: 1.0 }),
+            ]),
+            biome_surfaces: HashMap::from([
+                ("tundra".to_string(), SurfaceMaterial::Snow),
+                ("snowfield".to_string(), SurfaceMaterial::Snow),
+                ("desert".to_string(), SurfaceMaterial::Sand),
+                ("dunes".to_string(), SurfaceMaterial::Sand),
+                ("beach".to_string(), SurfaceMaterial::Sand),
+            ]),
+        }
+    }
+}
+
+impl MountEnvironmentConfig {
+    pub fn handling(&self, surface: SurfaceMaterial) -> SurfaceHandling {
+        self.surfaces.get(&surface).copied().unwrap_or_default()
+    }
+}
+
+/// Prevailing wind. The weather system sets it along with the weather in
+/// `AmbienceConditions`.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct WeatherWind {
+    /// Horizontal direction the wind blows towards (x, z).
+    pub direction: Vec2,
+    /// Mean speed in m/s.
+    pub speed: f32,
+    /// 0 for steady wind, 1 for gusts that double or still it.
+    pub gustiness: f32,
+}
+
+impl Default for WeatherWind {
+    fn default() -> Self {
+        Self {
+            direction: Vec2::X,
+            speed: 0.0,
+            gustiness: 0.2,
+        }
+    }
+}
+
+impl WeatherWind {
+    /// Wind velocity at `elapsed` seconds, gusts included.
+    pub fn velocity(&self, elapsed: f32) -> Vec3 {
+        let gust = (elapsed * 0.7).sin() * 0.6 + (elapsed * 1.9 + 1.3).sin() * 0.4;
+        let speed = (self.speed * (1.0 + self.gustiness.clamp(0.0, 1.0) * gust)).max(0.0);
+        let direction = self.direction.normalize_or_zero();
+        Vec3::new(direction.x, 0.0, direction.y) * speed
+    }
+}
+
+/// Environment around a mounted rider, refreshed every frame.
+#[derive(Component, Debug, Clone, Default)]
+pub struct MountEnvironment {
+    /// Wind at the rider, gusts included.
+    pub wind: Vec3,
+    /// Upward acceleration from wind deflected up a cliff face.
+    pub updraft: f32,
+    pub grounded: bool,
+    /// Flying low over water, or swimming.
+    pub over_water: bool,
+    /// Surface under a grounded mount.
+    pub surface: Option<SurfaceMaterial>,
+    pub handling: SurfaceHandling,
+    /// Movement config before surface scaling: top speed, acceleration,
+    /// deceleration.
+    base_movement: Option<(f32, f32, f32)>,
+}
+
+impl MountEnvironment {
+    /// Acceleration the environment adds to a flying mount moving at
+    /// `velocity`.
+    pub fn acceleration(&self, velocity: Vec3, config: &MountEnvironmentConfig) -> Vec3 {
+        let mut acceleration = self.wind * config.wind_coupling + Vec3::Y * self.updraft;
+        if self.over_water {
+            acceleration -= Vec3::new(velocity.x, 0.0, velocity.z) * config.water_drag;
+        }
+        acceleration
+    }
+
+    /// Positive when flying into the wind, negative with it at the back.
+    pub fn headwind(&self, velocity: Vec3) -> f32 {
+        -self.wind.dot(Vec3::new(velocity.x, 0.0, velocity.z).normalize_or_zero())
+    }
+}
+
+pub struct MountEnvironmentPlugin;
+
+impl Plugin for MountEnvironmentPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MountEnvironmentConfig>()
+            .init_resource::<WeatherWind>()
+            .add_console_command(
+                ConsoleCommand::new("wind", "Shows or sets the weather wind and what mounted riders feel")
+                    .usage("wind [calm|<speed> [heading degrees]]")
+                    .permission(CommandPermission::Dev)
+                    .complete(vec![ArgCompletion::Values(vec!["calm".to_string()])]),
+            )
+            .add_observer(restore_movement)
+            .add_systems(
+                Update,
+                (sense_environment, apply_surface_handling, wind_command)
+                    .chain()
+                    .before(crate::systems::mount::skyriding_physics_system),
+            );
+    }
+}
+
+fn sense_environment(
+    time: Res<Time>,
+    config: Res<MountEnvironmentConfig>,
+    wind: Res<WeatherWind>,
+    // Biome layers: the terrain splat map, then the zone's biome
+    (terrain, conditions): (Option<Res<TerrainSurfaceMap>>, Option<Res<AmbienceConditions>>),
+    rapier_context: ReadRapierContext,
+    surfaces: Query<&SurfaceMaterial>,
+    mut riders: Query<(Entity, &GlobalTransform, &mut MountEnvironment, Option<&CharacterController>)>,
+) {
+    let Ok(context) = rapier_context.single() else {
+        return;
+    };
+    let wind_velocity = wind.velocity(time.elapsed_secs());
+    let wind_speed = wind_velocity.length();
+    let wind_direction = wind_velocity.normalize_or_zero();
+
+    for (entity, transform, mut environment, controller) in &mut riders {
+        let position = transform.translation();
+        let filter = QueryFilter::default().exclude_collider(entity).exclude_rigid_body(entity).exclude_sensors();
+        let below = context.cast_ray(position, Vec3::NEG_Y, config.skim_height, true, filter);
+        let grounded = controller.map_or(below.is_some_and(|(_, toi)| toi < 0.5), |c| c.ground_info.is_grounded());
+        let surface_below = below.and_then(|(collider, _)| surfaces.get(collider).ok().copied());
+
+        // Wind blowing into a cliff face ahead of the rider is pushed up it
+        let mut updraft = 0.0;
+        if !grounded && wind_speed > 0.0 {
+            let hit = context.cast_ray_and_get_normal(position, wind_direction, config.updraft_range, true, filter);
+            if let Some((_, hit)) = hit {
+                let facing_wind = hit.normal.dot(wind_direction) < -0.3;
+                if hit.normal.y.abs() < config.cliff_max_normal_y && facing_wind {
+                    let closeness = 1.0 - hit.time_of_impact / config.updraft_range.max(0.01);
+                    updraft = wind_speed * config.updraft_per_wind * closeness;
+                }
+            }
+        }
+
+        let surface = grounded.then(|| {
+            controller
+                .and_then(|c| c.ground_info.ground_entity)
+                .and_then(|ground| surfaces.get(ground).ok().copied())
+                .or(surface_below)
+                .or_else(|| terrain.as_ref().and_then(|t| t.sample(position)))
+                .or_else(|| {
+                    let biome = conditions.as_ref()?.biome.as_ref()?;
+                    config.biome_surfaces.get(biome).copied()
+                })
+                .unwrap_or(SurfaceMaterial::Dirt)
+        });
+
+        environment.wind = wind_velocity;
+        environment.updraft = updraft;
+        environment.grounded = grounded;
+        environment.over_water = controller.is_some_and(|c| c.is_swimming)
+            || (!grounded && surface_below == Some(SurfaceMaterial::Water));
+        environment.surface = surface;
+        environment.handling = surface.map(|s| config.handling(s)).unwrap_or_default();
+    }
+}
+
+/// Scales a grounded mount's controller by its surface; airborne mounts keep
+/// the grip of their last surface until they land.
+fn apply_surface_handling(mut riders: Query<(&mut MountEnvironment, &mut CharacterController)>) {
+    for (mut environment, mut controller) in &mut riders {
+        let movement = &mut controller.config;
+        let (max_speed, acceleration, deceleration) = *environment
+            .base_movement
+            .get_or_insert((movement.max_speed, movement.acceleration, movement.deceleration));
+        if !environment.grounded {
+            continue;
+        }
+        let handling = environment.handling;
+        movement.max_speed = max_speed * handling.speed;
+        movement.acceleration = acceleration * handling.grip;
+        movement.deceleration = deceleration * handling.grip;
+    }
+}
+
+fn restore_movement(
+    trigger: Trigger<OnRemove, MountEnvironment>,
+    mut riders: Query<(&MountEnvironment, &mut CharacterController)>,
+) {
+    let Ok((environment, mut controller)) = riders.get_mut(trigger.entity()) else {
+        return;
+    };
+    if let Some((max_speed, acceleration, deceleration)) = environment.base_movement {
+        controller.config.max_speed = max_speed;
+        controller.config.acceleration = acceleration;
+        controller.config.deceleration = deceleration;
+    }
+}
+
+fn wind_command(
+    config: Res<MountEnvironmentConfig>,
+    mut wind: ResMut<WeatherWind>,
+    riders: Query<(Entity, &MountEnvironment)>,
+    mut events: EventReader<ConsoleCommandEvent>,
+    mut output: EventWriter<ConsoleOutputEvent>,
+) {
+    for event in events.read() {
+        if event.name != "wind" {
+            continue;
+        }
+        let result = match event.args.first().map(String::as_str) {
+            None => {
+                let heading = wind.direction.x.atan2(wind.direction.y).to_degrees().rem_euclid(360.0);
+                let mut lines = vec![format!(
+                    "Wind {:.1} m/s towards {:.0} degrees, gustiness {:.2}",
+                    wind.speed, heading, wind.gustiness
+                )];
+                for (entity, environment) in &riders {
+                    let surface = match environment.surface {
+                        Some(surface) => format!("{:?} (grip x{:.2})", surface, environment.handling.grip),
+                        None => "airborne".to_string(),
+                    };
+                    lines.push(format!(
+                        "  {:?}: wind {:.1} m/s, updraft {:.1}, {}{}",
+                        entity,
+                        environment.wind.length(),
+                        environment.updraft,
+                        surface,
+                        if environment.over_water { ", skimming water" } else { "" }
+                    ));
+                }
+                Ok(lines.join("\n"))
+            }
+            Some("calm") => {
+                wind.speed = 0.0;
+                Ok("Wind calmed".to_string())
+            }
+            Some(speed) => match speed.parse::<f32>() {
+                Ok(speed) if speed >= 0.0 => {
+                    let heading = event.args.get(1).and_then(|h| h.parse::<f32>().ok());
+                    if let Some(heading) = heading {
+                        let radians = heading.to_radians();
+                        wind.direction = Vec2::new(radians.sin(), radians.cos());
+                    }
+                    wind.speed = speed;
+                    Ok(format!(
+                        "Wind set to {:.1} m/s; updrafts reach {:.1} m/s² at a cliff face",
+                        speed,
+                        speed * config.updraft_per_wind
+                    ))
+                }
+                _ => Err(format!("invalid speed '{}'", speed)),
+            },
+        };
+        output.send(match result {
+            Ok(text) => event.reply(text),
+            Err(text) => event.error(format!("{}: {}", event.name, text)),
+        });
+    }
+}
@@ -0,0 +1,283 @@
+//! Channels between the ECS and the network IO thread. The Nakama client
+//! lives on its own thread: authentication, heartbeats, position updates and
+//! socket reads block there, never in a system. Systems post [`NetCommand`]s
+//! and drain [`NetInbound`]s once per frame.
+//!
+//! The command queue is bounded. When the IO thread falls behind (slow HTTP,
+//! a stalled socket) new commands are dropped instead of piling up; position
+//! updates are sent ten times a second, so a dropped one is replaced soon.
+//! Depth and drops are reported through [`Metrics`].
+//!
+//! Match messages go through the IO thread's
+//! [`ClientSession`](crate::net_security::ClientSession): it seals positions,
+//! casts and time sync requests on the way out and opens everything on the
+//! way in.
+
+use bevy::prelude::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvError, RecvTimeoutError, Sender, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::cast_validation::{CastReply, CastRequest};
+use crate::interactables::WorldObjectState;
+use crate::metrics::{self, Metrics};
+#[cfg(feature = "networking")]
+use crate::net_security::{ClientSession, HANDSHAKE_OP_CODE};
+use crate::networking::{PositionUpdateRequest, StateSync};
+use crate::world_clock::{TimeSyncReply, TimeSyncRequest};
+
+/// Match data op code for [`PositionUpdateRequest`] and the server's
+/// [`PositionReply`].
+pub const POSITION_OP_CODE: i64 = 25;
+
+/// The server's answer to a position update.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct PositionReply {
+    pub approved: bool,
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct NetIoConfig {
+    /// Commands that may wait for the IO thread before new ones are dropped.
+    pub send_queue_capacity: usize,
+    /// How long the IO thread waits for a command before polling the socket.
+    pub poll_interval_ms: u64,
+    pub heartbeat_seconds: f32,
+    /// Seconds between position updates.
+    pub position_interval: f32,
+}
+
+impl Default for NetIoConfig {
+    fn default() -> Self {
+        Self {
+            send_queue_capacity: 64,
+            poll_interval_ms: 10,
+            heartbeat_seconds: 5.0,
+            position_interval: 0.1,
+        }
+    }
+}
+
+pub enum NetCommand {
+    Authenticate { device_id: String },
+    SendPosition(PositionUpdateRequest),
+    SendCast(CastRequest),
+    SendTimeSync(TimeSyncRequest),
+}
+
+pub enum NetInbound {
+    Authenticated { user_id: String, username: String },
+    AuthFailed(String),
+    /// Match state, stamped with the wall-clock second it arrived.
+    State { received_at: f64, state: StateSync },
+    /// A door, lock or chest changed on another client.
+    WorldObject(WorldObjectState),
+    /// The server's answer to a time sync request.
+    TimeSync { received_at: f64, reply: TimeSyncReply },
+    /// The server accepted or denied one of our casts.
+    CastReply(CastReply),
+    /// The handshake finished; match messages are sealed from now on.
+    SessionSecured,
+    /// A message failed its signature check and was dropped.
+    Forged { op_code: i64, reason: String },
+    PositionRejected,
+    SendFailed(String),
+    Disconnected,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct NetIoStats {
+    pub sent: u64,
+    pub dropped: u64,
+    pub received: u64,
+    /// Messages dropped for a bad signature or a replay.
+    pub forged: u64,
+}
+
+/// The game's end of the channels.
+pub struct NetIo {
+    commands: SyncSender<NetCommand>,
+    inbound: Mutex<Receiver<NetInbound>>,
+    depth: Arc<AtomicUsize>,
+    metrics: Option<Metrics>,
+    /// Set once the IO thread reports a session.
+    pub user_id: Option<String>,
+    pub stats: NetIoStats,
+}
+
+/// The IO thread's end of the channels. The thread should exit when
+/// [`NetIoWorker::next_command`] errors, which happens once `NetIo` drops.
+pub struct NetIoWorker {
+    commands: Receiver<NetCommand>,
+    inbound: Sender<NetInbound>,
+    depth: Arc<AtomicUsize>,
+    pub metrics: Option<Metrics>,
+}
+
+impl NetIo {
+    pub fn channel(config: &NetIoConfig, metrics: Option<Metrics>) -> (NetIo, NetIoWorker) {
+        let (command_tx, command_rx) = mpsc::sync_channel(config.send_queue_capacity.max(1));
+        let (inbound_tx, inbound_rx) = mpsc::channel();
+        let depth = Arc::new(AtomicUsize::new(0));
+        let io = NetIo {
+            commands: command_tx,
+            inbound: Mutex::new(inbound_rx),
+            depth: depth.clone(),
+            metrics: metrics.clone(),
+            user_id: None,
+            stats: NetIoStats::default(),
+        };
+        let worker = NetIoWorker {
+            commands: command_rx,
+            inbound: inbound_tx,
+            depth,
+            metrics,
+        };
+        (io, worker)
+    }
+
+    /// Queues `command` without blocking. `false` when the queue is full or
+    /// the IO thread has stopped.
+    pub fn send(&mut self, command: NetCommand) -> bool {
+        match self.commands.try_send(command) {
+            Ok(()) => {
+                self.depth.fetch_add(1, Ordering::Relaxed);
+                self.stats.sent += 1;
+                true
+            }
+            Err(TrySendError::Full(_)) => {
+                self.stats.dropped += 1;
+                if let Some(metrics) = &self.metrics {
+                    metrics.increment(metrics::NET_SEND_DROPPED_TOTAL, &[], 1.0);
+                }
+                false
+            }
+            Err(TrySendError::Disconnected(_)) => false,
+        }
+    }
+
+    pub fn drain(&mut self) -> Vec<NetInbound> {
+        let received: Vec<NetInbound> = self
+            .inbound
+            .lock()
+            .map(|inbound| inbound.try_iter().collect())
+            .unwrap_or_default();
+        self.stats.received += received.len() as u64;
+        if let Some(metrics) = &self.metrics {
+            metrics.set_gauge(metrics::NET_SEND_QUEUE_DEPTH, &[], self.queue_depth() as f64);
+        }
+        received
+    }
+
+    /// Commands the IO thread hasn't taken yet.
+    pub fn queue_depth(&self) -> usize {
+        self.depth.load(Ordering::Relaxed)
+    }
+}
+
+impl NetIoWorker {
+    /// The next command, waiting up to `timeout`. `Ok(None)` on timeout,
+    /// an error once the game side is gone.
+    pub fn next_command(&self, timeout: Duration) -> Result<Option<NetCommand>, RecvError> {
+        match self.commands.recv_timeout(timeout) {
+            Ok(command) => {
+                self.depth.fetch_sub(1, Ordering::Relaxed);
+                Ok(Some(command))
+            }
+            Err(RecvTimeoutError::Timeout) => Ok(None),
+            Err(RecvTimeoutError::Disconnected) => Err(RecvError),
+        }
+    }
+
+    /// `false` once the game side is gone.
+    pub fn post(&self, inbound: NetInbound) -> bool {
+        self.inbound.send(inbound).is_ok()
+    }
+}
+
+pub fn unix_seconds() -> f64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs_f64())
+        .unwrap_or(0.0)
+}
+
+/// `command`'s op code and sealed frame, for the commands that are match
+/// messages. Fails until `session` is secured; nothing goes out unsealed.
+#[cfg(feature = "networking")]
+pub fn seal_command(session: &mut ClientSession, command: &NetCommand) -> Option<(i64, Result<Vec<u8>, String>)> {
+    match command {
+        NetCommand::Authenticate { .. } => None,
+        NetCommand::SendPosition(request) => Some((POSITION_OP_CODE, session.seal(POSITION_OP_CODE, request))),
+        NetCommand::SendCast(request) => {
+            let op_code = crate::cast_validation::CAST_OP_CODE;
+            Some((op_code, session.seal(op_code, request)))
+        }
+        NetCommand::SendTimeSync(request) => {
+            let op_code = crate::world_clock::TIME_SYNC_OP_CODE;
+            Some((op_code, session.seal(op_code, request)))
+        }
+    }
+}
+
+/// Decodes a Nakama `match_data` message, whose `data` is base64 of a
+/// sealed frame. Decoded on the IO thread so large states don't cost a frame.
+///
+/// `session` answers the server's handshake messages, queueing its replies,
+/// and has to open every other message; until the handshake is done none
+/// is accepted. The IO thread resets it when the connection drops.
+#[cfg(feature = "networking")]
+pub fn decode_match_data(message: &serde_json::Value, session: &mut ClientSession) -> Option<NetInbound> {
+    use base64::Engine;
+    let match_data = message.get("match_data")?;
+    let data = match_data.get("data")?.as_str()?;
+    let decoded = base64::engine::general_purpose::STANDARD.decode(data).ok()?;
+    // Nakama sends the op code as a string; accept a number too
+    let op_code = match match_data.get("op_code") {
+        Some(serde_json::Value::String(code)) => code.parse().ok(),
+        Some(code) => code.as_i64(),
+        None => None,
+    };
+    if op_code == Some(HANDSHAKE_OP_CODE) {
+        return match session.handshake(&decoded) {
+            Ok(true) => Some(NetInbound::SessionSecured),
+            Ok(false) => None,
+            Err(reason) => Some(NetInbound::Forged {
+                op_code: HANDSHAKE_OP_CODE,
+                reason,
+            }),
+        };
+    }
+    let decoded = match session.open_body(op_code.unwrap_or_default(), &decoded) {
+        Ok(body) => body.into_bytes(),
+        Err(reason) => {
+            return Some(NetInbound::Forged {
+                op_code: op_code.unwrap_or_default(),
+                reason,
+            })
+        }
+    };
+
+    if op_code == Some(crate::interactables::WORLD_OBJECT_OP_CODE) {
+        return serde_json::from_slice(&decoded).ok().map(NetInbound::WorldObject);
+    }
+    if op_code == Some(crate::world_clock::TIME_SYNC_OP_CODE) {
+        // Stamped here, not when the frame drains it, to keep the round trip tight
+        return serde_json::from_slice(&decoded).ok().map(|reply| NetInbound::TimeSync {
+            received_at: unix_seconds(),
+            reply,
+        });
+    }
+    if op_code == Some(crate::cast_validation::CAST_OP_CODE) {
+        return serde_json::from_slice(&decoded).ok().map(NetInbound::CastReply);
+    }
+    if op_code == Some(POSITION_OP_CODE) {
+        let reply: PositionReply = serde_json::from_slice(&decoded).ok()?;
+        return (!reply.approved).then_some(NetInbound::PositionRejected);
+    }
+    serde_json::from_slice(&decoded).ok().map(|state| NetInbound::State {
+        received_at: unix_seconds(),
+        state,
+    })
+}
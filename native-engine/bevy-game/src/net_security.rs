@@ -0,0 +1,524 @@
+//! Encryption and message authentication for match data, keyed per session.
+//!
+//! After the match join the server sends an empty [`HANDSHAKE_OP_CODE`]
+//! message and the client answers with a Noise NK handshake
+//! ([`NOISE_PARAMS`]) against the server's static public key, which the
+//! client is built or configured with ([`SERVER_KEY_VAR`]). Only the holder
+//! of the matching private key can finish it, so a proxy in the middle can
+//! neither read the session's keys nor stand in for the server, and no key
+//! ever crosses the wire. Each direction gets its own keys from the
+//! handshake.
+//!
+//! From then on every match data message, positions and casts included,
+//! travels as a sealed frame: an 8-byte sequence number, then the
+//! ChaCha20-Poly1305 encryption of a [`SignedMessage`], the JSON body with an
+//! HMAC-SHA256 over direction, op code, sequence and body. A frame that
+//! doesn't decrypt, has a bad MAC, or a sequence number not above the last
+//! one seen is dropped. The direction is part of both, so a message can't be
+//! reflected back at its sender.
+//!
+//! The client keeps its [`ClientSession`] on the network IO thread. Until
+//! the handshake is done it sends nothing and drops every match message but
+//! the handshake's, so stripping the handshake only stops the session; it
+//! can't fall back to plain messages. The server does the mirror image with
+//! [`accept`].
+
+use bevy::prelude::*;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::ChaCha20Poly1305;
+use hmac::{Hmac, Mac};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+/// Match data op code for the handshake.
+pub const HANDSHAKE_OP_CODE: i64 = 24;
+
+/// The handshake pattern; the server has to use the same one.
+pub const NOISE_PARAMS: &str = "Noise_NK_25519_ChaChaPoly_SHA256";
+
+/// Environment variable with the server's static public key, 32 bytes hex.
+pub const SERVER_KEY_VAR: &str = "MMO_SERVER_PUBLIC_KEY";
+
+/// Bound into the handshake, so it can't be mistaken for another protocol's.
+const PROLOGUE: &[u8] = b"aaa-mmorpg match v1";
+
+/// Largest handshake message: two public keys and a tag, with room to spare.
+const HANDSHAKE_MESSAGE_LEN: usize = 256;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedMessage {
+    pub seq: u64,
+    /// The message, as JSON text, so the MAC covers exactly these bytes.
+    pub body: String,
+    /// HMAC-SHA256, hex.
+    pub mac: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    ClientToServer,
+    ServerToClient,
+}
+
+impl Direction {
+    fn label(self) -> &'static [u8] {
+        match self {
+            Direction::ClientToServer => b"c2s",
+            Direction::ServerToClient => b"s2c",
+        }
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// A 32-byte key written as hex, e.g. [`SERVER_KEY_VAR`].
+pub fn parse_key(text: &str) -> Result<[u8; 32], String> {
+    let bytes = from_hex(text.trim()).ok_or("key isn't hex")?;
+    bytes
+        .try_into()
+        .map_err(|bytes: Vec<u8>| format!("key is {} bytes, expected 32", bytes.len()))
+}
+
+/// The server's public key from [`SERVER_KEY_VAR`].
+pub fn server_key_from_env() -> Result<[u8; 32], String> {
+    let text = std::env::var(SERVER_KEY_VAR).map_err(|_| format!("{} isn't set", SERVER_KEY_VAR))?;
+    parse_key(&text).map_err(|e| format!("{}: {}", SERVER_KEY_VAR, e))
+}
+
+fn derive(key: &[u8; 32], label: &[u8]) -> [u8; 32] {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key).expect("HMAC takes keys of any length");
+    mac.update(label);
+    mac.finalize().into_bytes().into()
+}
+
+/// One direction's keys, from the handshake.
+#[derive(Clone)]
+struct DirectionKeys {
+    cipher: ChaCha20Poly1305,
+    mac: [u8; 32],
+}
+
+// Keeps the keys out of logs
+impl std::fmt::Debug for DirectionKeys {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("DirectionKeys(..)")
+    }
+}
+
+impl DirectionKeys {
+    fn new(split: &[u8; 32]) -> Self {
+        Self {
+            cipher: ChaCha20Poly1305::new(&derive(split, b"aaa-match-encrypt").into()),
+            mac: derive(split, b"aaa-match-mac"),
+        }
+    }
+
+    fn mac(&self, direction: Direction, op_code: i64, seq: u64, body: &[u8]) -> Hmac<Sha256> {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&self.mac).expect("HMAC takes keys of any length");
+        mac.update(direction.label());
+        mac.update(&op_code.to_be_bytes());
+        mac.update(&seq.to_be_bytes());
+        mac.update(body);
+        mac
+    }
+
+    fn nonce(seq: u64) -> [u8; 12] {
+        let mut nonce = [0; 12];
+        nonce[4..].copy_from_slice(&seq.to_be_bytes());
+        nonce
+    }
+
+    fn associated_data(direction: Direction, op_code: i64) -> Vec<u8> {
+        [direction.label(), &op_code.to_be_bytes()].concat()
+    }
+}
+
+/// Encrypts and signs outgoing messages in one direction.
+#[derive(Debug, Clone)]
+pub struct MessageSealer {
+    keys: DirectionKeys,
+    direction: Direction,
+    next_seq: u64,
+}
+
+impl MessageSealer {
+    /// `message` as a sealed frame, ready to send with `op_code`.
+    pub fn seal<T: Serialize>(&mut self, op_code: i64, message: &T) -> Result<Vec<u8>, String> {
+        let body = serde_json::to_string(message).map_err(|e| e.to_string())?;
+        self.seal_body(op_code, body)
+    }
+
+    /// Like [`MessageSealer::seal`], for a body that's already JSON.
+    pub fn seal_body(&mut self, op_code: i64, body: String) -> Result<Vec<u8>, String> {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        let mac = self.keys.mac(self.direction, op_code, seq, body.as_bytes()).finalize().into_bytes();
+        let signed = serde_json::to_vec(&SignedMessage {
+            seq,
+            body,
+            mac: to_hex(&mac),
+        })
+        .map_err(|e| e.to_string())?;
+        let aad = DirectionKeys::associated_data(self.direction, op_code);
+        let encrypted = self
+            .keys
+            .cipher
+            .encrypt(&DirectionKeys::nonce(seq).into(), Payload { msg: &signed, aad: &aad })
+            .map_err(|_| "encryption failed".to_string())?;
+        Ok([&seq.to_be_bytes()[..], &encrypted].concat())
+    }
+}
+
+/// Decrypts and checks incoming messages in one direction.
+#[derive(Debug, Clone)]
+pub struct MessageOpener {
+    keys: DirectionKeys,
+    direction: Direction,
+    last_seq: u64,
+}
+
+impl MessageOpener {
+    /// The message inside sealed frame `bytes`, if it was sealed for
+    /// `op_code` in this direction and hasn't been seen before.
+    pub fn open<T: DeserializeOwned>(&mut self, op_code: i64, bytes: &[u8]) -> Result<T, String> {
+        let body = self.open_body(op_code, bytes)?;
+        serde_json::from_str(&body).map_err(|e| e.to_string())
+    }
+
+    /// Like [`MessageOpener::open`], but leaves the JSON body unparsed.
+    pub fn open_body(&mut self, op_code: i64, bytes: &[u8]) -> Result<String, String> {
+        let (seq, encrypted) = bytes.split_first_chunk::<8>().ok_or("not a sealed message")?;
+        let seq = u64::from_be_bytes(*seq);
+        if seq <= self.last_seq {
+            return Err(format!("replayed message {} (last {})", seq, self.last_seq));
+        }
+        let aad = DirectionKeys::associated_data(self.direction, op_code);
+        let signed = self
+            .keys
+            .cipher
+            .decrypt(&DirectionKeys::nonce(seq).into(), Payload { msg: encrypted, aad: &aad })
+            .map_err(|_| "doesn't decrypt".to_string())?;
+        let signed: SignedMessage = serde_json::from_slice(&signed).map_err(|_| "not a signed message".to_string())?;
+        let tag = from_hex(&signed.mac).ok_or("MAC isn't hex")?;
+        // verify_slice compares in constant time
+        self.keys
+            .mac(self.direction, op_code, seq, signed.body.as_bytes())
+            .verify_slice(&tag)
+            .map_err(|_| "bad MAC".to_string())?;
+        if signed.seq != seq {
+            return Err(format!("sequence {} sealed as {}", signed.seq, seq));
+        }
+        self.last_seq = seq;
+        Ok(signed.body)
+    }
+}
+
+/// Both halves of a session, one for each direction.
+#[derive(Debug, Clone)]
+pub struct SecureChannel {
+    pub sealer: MessageSealer,
+    pub opener: MessageOpener,
+}
+
+impl SecureChannel {
+    fn new(handshake: &mut snow::HandshakeState, sending: Direction) -> Self {
+        // The first key is the initiator's (the client's) sending key
+        let (client_to_server, server_to_client) = handshake.dangerously_get_raw_split();
+        let (send, receive, receiving) = match sending {
+            Direction::ClientToServer => (client_to_server, server_to_client, Direction::ServerToClient),
+            Direction::ServerToClient => (server_to_client, client_to_server, Direction::ClientToServer),
+        };
+        Self {
+            sealer: MessageSealer {
+                keys: DirectionKeys::new(&send),
+                direction: sending,
+                next_seq: 1,
+            },
+            opener: MessageOpener {
+                keys: DirectionKeys::new(&receive),
+                direction: receiving,
+                last_seq: 0,
+            },
+        }
+    }
+}
+
+fn builder() -> snow::Builder<'static> {
+    snow::Builder::new(NOISE_PARAMS.parse().expect("valid Noise parameters")).prologue(PROLOGUE)
+}
+
+/// The client's half of a handshake in progress.
+pub struct Handshake(snow::HandshakeState);
+
+impl std::fmt::Debug for Handshake {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Handshake(..)")
+    }
+}
+
+impl Handshake {
+    /// Starts a handshake with the server holding `server_key`'s private
+    /// half. Send the message with [`HANDSHAKE_OP_CODE`].
+    pub fn initiate(server_key: &[u8; 32]) -> Result<(Self, Vec<u8>), String> {
+        let mut state = builder().remote_public_key(server_key).build_initiator().map_err(|e| e.to_string())?;
+        let mut message = vec![0; HANDSHAKE_MESSAGE_LEN];
+        let len = state.write_message(&[], &mut message).map_err(|e| e.to_string())?;
+        message.truncate(len);
+        Ok((Self(state), message))
+    }
+
+    /// Finishes the handshake with the server's reply.
+    pub fn complete(mut self, reply: &[u8]) -> Result<SecureChannel, String> {
+        let mut payload = vec![0; HANDSHAKE_MESSAGE_LEN];
+        self.0.read_message(reply, &mut payload).map_err(|_| "handshake reply doesn't verify".to_string())?;
+        if !self.0.is_handshake_finished() {
+            return Err("handshake isn't finished".to_string());
+        }
+        Ok(SecureChannel::new(&mut self.0, Direction::ClientToServer))
+    }
+}
+
+/// The server's side: answers a client's handshake `message` with the
+/// server's static `private_key`. Send the reply with [`HANDSHAKE_OP_CODE`].
+pub fn accept(private_key: &[u8; 32], message: &[u8]) -> Result<(Vec<u8>, SecureChannel), String> {
+    let mut state = builder().local_private_key(private_key).build_responder().map_err(|e| e.to_string())?;
+    let mut payload = vec![0; HANDSHAKE_MESSAGE_LEN];
+    state.read_message(message, &mut payload).map_err(|_| "handshake message doesn't verify".to_string())?;
+    let mut reply = vec![0; HANDSHAKE_MESSAGE_LEN];
+    let len = state.write_message(&[], &mut reply).map_err(|e| e.to_string())?;
+    reply.truncate(len);
+    Ok((reply, SecureChannel::new(&mut state, Direction::ServerToClient)))
+}
+
+#[derive(Debug, Default)]
+enum SessionState {
+    /// Waiting for the server to ask for a handshake.
+    #[default]
+    Waiting,
+    Handshaking(Box<Handshake>),
+    Secured(SecureChannel),
+}
+
+/// The client's end of the session, kept on the network IO thread.
+#[derive(Debug)]
+pub struct ClientSession {
+    /// `Err` when the client has no server key, which leaves the session
+    /// unable to start; it never falls back to plain messages.
+    server_key: Result<[u8; 32], String>,
+    state: SessionState,
+    /// Handshake messages to send with [`HANDSHAKE_OP_CODE`].
+    outgoing: Vec<Vec<u8>>,
+}
+
+impl ClientSession {
+    pub fn new(server_key: Result<[u8; 32], String>) -> Self {
+        Self {
+            server_key,
+            state: SessionState::Waiting,
+            outgoing: Vec::new(),
+        }
+    }
+
+    pub fn is_secured(&self) -> bool {
+        matches!(self.state, SessionState::Secured(_))
+    }
+
+    /// Forgets the session, after a disconnect.
+    pub fn reset(&mut self) {
+        self.state = SessionState::Waiting;
+        self.outgoing.clear();
+    }
+
+    /// A [`HANDSHAKE_OP_CODE`] message from the server. `Ok(true)` once the
+    /// session is secured.
+    pub fn handshake(&mut self, message: &[u8]) -> Result<bool, String> {
+        match std::mem::take(&mut self.state) {
+            SessionState::Waiting => {
+                let server_key = self.server_key.as_ref().map_err(Clone::clone)?;
+                let (handshake, first) = Handshake::initiate(server_key)?;
+                self.outgoing.push(first);
+                self.state = SessionState::Handshaking(Box::new(handshake));
+                Ok(false)
+            }
+            // A failed reply leaves the session waiting for the server to ask again
+            SessionState::Handshaking(handshake) => {
+                self.state = SessionState::Secured(handshake.complete(message)?);
+                Ok(true)
+            }
+            // Only one handshake per connection, so a later one can't swap the keys
+            secured @ SessionState::Secured(_) => {
+                self.state = secured;
+                Err("second handshake".to_string())
+            }
+        }
+    }
+
+    /// Handshake messages waiting to be sent.
+    pub fn take_outgoing(&mut self) -> Vec<Vec<u8>> {
+        std::mem::take(&mut self.outgoing)
+    }
+
+    pub fn seal<T: Serialize>(&mut self, op_code: i64, message: &T) -> Result<Vec<u8>, String> {
+        match &mut self.state {
+            SessionState::Secured(channel) => channel.sealer.seal(op_code, message),
+            _ => Err("no secure session yet".to_string()),
+        }
+    }
+
+    /// The body of a sealed match message; anything else is refused.
+    pub fn open_body(&mut self, op_code: i64, bytes: &[u8]) -> Result<String, String> {
+        match &mut self.state {
+            SessionState::Secured(channel) => channel.opener.open_body(op_code, bytes),
+            _ => Err("no secure session yet".to_string()),
+        }
+    }
+}
+
+/// Whether the session is secured, as the IO thread last reported; position
+/// and cast messages wait for it.
+#[derive(Resource, Debug, Default)]
+pub struct SessionAuth {
+    secured: bool,
+}
+
+impl SessionAuth {
+    pub fn set_secured(&mut self, secured: bool) {
+        self.secured = secured;
+    }
+
+    pub fn is_secured(&self) -> bool {
+        self.secured
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server_keys() -> ([u8; 32], [u8; 32]) {
+        let keypair = builder().generate_keypair().unwrap();
+        (keypair.private.try_into().unwrap(), keypair.public.try_into().unwrap())
+    }
+
+    /// A client session secured against a server with `private`, and the
+    /// server's channel.
+    fn secured(private: &[u8; 32], public: &[u8; 32]) -> (ClientSession, SecureChannel) {
+        let mut client = ClientSession::new(Ok(*public));
+        assert!(!client.handshake(&[]).unwrap());
+        let first = client.take_outgoing().pop().unwrap();
+        let (reply, server) = accept(private, &first).unwrap();
+        assert!(client.handshake(&reply).unwrap());
+        (client, server)
+    }
+
+    #[test]
+    fn seal_and_open_round_trip() {
+        let (private, public) = server_keys();
+        let (mut client, mut server) = secured(&private, &public);
+
+        let frame = client.seal(7, &vec![1, 2, 3]).unwrap();
+        assert_eq!(server.opener.open::<Vec<i32>>(7, &frame).unwrap(), vec![1, 2, 3]);
+        let frame = server.sealer.seal(9, &"hello").unwrap();
+        assert_eq!(client.open_body(9, &frame).unwrap(), "\"hello\"");
+    }
+
+    #[test]
+    fn frames_are_encrypted() {
+        let (private, public) = server_keys();
+        let (mut client, _) = secured(&private, &public);
+        let frame = client.seal(7, &"secret position").unwrap();
+        assert!(!frame.windows(6).any(|window| window == b"secret"));
+    }
+
+    #[test]
+    fn tampered_frames_are_dropped() {
+        let (private, public) = server_keys();
+        let (mut client, mut server) = secured(&private, &public);
+        let frame = client.seal(7, &"move").unwrap();
+        for index in [0, 8, frame.len() - 1] {
+            let mut tampered = frame.clone();
+            tampered[index] ^= 1;
+            assert!(server.opener.open_body(7, &tampered).is_err(), "byte {} changed", index);
+        }
+        // Another op code, or the same frame reflected back at its sender
+        assert!(server.opener.open_body(8, &frame).is_err());
+        assert!(client.open_body(7, &frame).is_err());
+        assert_eq!(server.opener.open_body(7, &frame).unwrap(), "\"move\"");
+    }
+
+    #[test]
+    fn replays_are_dropped() {
+        let (private, public) = server_keys();
+        let (mut client, mut server) = secured(&private, &public);
+        let first = client.seal(7, &1).unwrap();
+        let second = client.seal(7, &2).unwrap();
+        assert!(server.opener.open_body(7, &second).is_ok());
+        assert!(server.opener.open_body(7, &second).is_err());
+        // Older than the last one seen
+        assert!(server.opener.open_body(7, &first).is_err());
+    }
+
+    #[test]
+    fn unsealed_messages_are_never_accepted() {
+        let (private, public) = server_keys();
+        let mut client = ClientSession::new(Ok(public));
+        let plain = serde_json::to_vec(&"state").unwrap();
+        assert!(client.open_body(7, &plain).is_err());
+        assert!(client.seal(7, &"move").is_err());
+
+        // Stripping the server's reply leaves the session unsecured, not plain
+        client.handshake(&[]).unwrap();
+        assert!(client.open_body(7, &plain).is_err());
+        assert!(client.seal(7, &"move").is_err());
+
+        let first = client.take_outgoing().pop().unwrap();
+        let (reply, _) = accept(&private, &first).unwrap();
+        client.handshake(&reply).unwrap();
+        assert!(client.open_body(7, &plain).is_err());
+    }
+
+    #[test]
+    fn only_the_server_can_finish_the_handshake() {
+        let (_, public) = server_keys();
+        let (impostor, _) = server_keys();
+        let mut client = ClientSession::new(Ok(public));
+        client.handshake(&[]).unwrap();
+        let first = client.take_outgoing().pop().unwrap();
+        assert!(accept(&impostor, &first).is_err());
+
+        // A reply forged without the server's key doesn't secure anything
+        assert!(client.handshake(&[0; 48]).is_err());
+        assert!(!client.is_secured());
+    }
+
+    #[test]
+    fn a_second_handshake_is_refused() {
+        let (private, public) = server_keys();
+        let (mut client, mut server) = secured(&private, &public);
+        assert!(client.handshake(&[]).is_err());
+        assert!(client.is_secured());
+        let frame = server.sealer.seal(9, &"still here").unwrap();
+        assert!(client.open_body(9, &frame).is_ok());
+    }
+
+    #[test]
+    fn no_server_key_means_no_session() {
+        let mut client = ClientSession::new(Err("not set".to_string()));
+        assert!(client.handshake(&[]).is_err());
+        assert!(client.take_outgoing().is_empty());
+        assert!(!client.is_secured());
+    }
+}
@@ -0,0 +1,108 @@
+//! The write-ahead journal. Every write is appended here (and synced to
+//! disk once per burst) before it waits in the write-behind batch, so a
+//! crash loses at most the writes still in the channel. Once a batch is
+//! committed to storage the journal is truncated; whatever is left in it at
+//! startup is replayed. Replaying twice is harmless, since writes are
+//! whole-record upserts and deletes.
+//!
+//! Each line is `<checksum> <json>`, the checksum being 16 hex digits of
+//! FNV-1a over the JSON. A torn or corrupt line (a crash mid-append) fails
+//! the check and ends the replay there.
+
+use bevy::log::warn;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use super::StoredWrite;
+
+fn checksum(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+pub struct Journal {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    /// Lines written since the last sync.
+    unsynced: usize,
+    /// Lines since the last truncate.
+    pub entries: usize,
+}
+
+impl Journal {
+    /// Opens (creating if needed) the journal at `path` and returns the
+    /// writes left in it by a previous run.
+    pub fn open(path: &Path) -> Result<(Self, Vec<StoredWrite>), String> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let recovered = if path.exists() { Self::read(path)? } else { Vec::new() };
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        let journal = Self {
+            path: path.to_path_buf(),
+            writer: BufWriter::new(file),
+            unsynced: 0,
+            entries: recovered.len(),
+        };
+        Ok((journal, recovered))
+    }
+
+    fn read(path: &Path) -> Result<Vec<StoredWrite>, String> {
+        let file = File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let mut writes = Vec::new();
+        for (number, line) in BufReader::new(file).lines().enumerate() {
+            let line = line.map_err(|e| e.to_string())?;
+            let parsed = line.split_once(' ').and_then(|(sum, json)| {
+                let sum = u64::from_str_radix(sum, 16).ok()?;
+                if sum != checksum(json.as_bytes()) {
+                    return None;
+                }
+                serde_json::from_str::<StoredWrite>(json).ok()
+            });
+            match parsed {
+                Some(write) => writes.push(write),
+                None => {
+                    warn!("Journal {} is damaged at line {}; replaying up to it", path.display(), number + 1);
+                    break;
+                }
+            }
+        }
+        Ok(writes)
+    }
+
+    pub fn append(&mut self, write: &StoredWrite) -> Result<(), String> {
+        let json = serde_json::to_string(write).map_err(|e| e.to_string())?;
+        writeln!(self.writer, "{:016x} {}", checksum(json.as_bytes()), json).map_err(|e| e.to_string())?;
+        self.unsynced += 1;
+        self.entries += 1;
+        Ok(())
+    }
+
+    /// Flushes appended lines and waits for the disk.
+    pub fn sync(&mut self) -> Result<(), String> {
+        if self.unsynced == 0 {
+            return Ok(());
+        }
+        self.writer.flush().map_err(|e| e.to_string())?;
+        self.writer.get_ref().sync_data().map_err(|e| e.to_string())?;
+        self.unsynced = 0;
+        Ok(())
+    }
+
+    /// Empties the journal once everything in it is in storage.
+    pub fn truncate(&mut self) -> Result<(), String> {
+        self.writer.flush().map_err(|e| e.to_string())?;
+        let file = self.writer.get_ref();
+        file.set_len(0).map_err(|e| format!("{}: {}", self.path.display(), e))?;
+        file.sync_data().map_err(|e| e.to_string())?;
+        self.unsynced = 0;
+        self.entries = 0;
+        Ok(())
+    }
+}
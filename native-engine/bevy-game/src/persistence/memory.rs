@@ -0,0 +1,43 @@
+//! Storage that forgets everything on exit, for tests, scenarios and
+//! servers built without a database. The journal still replays into it, so
+//! it survives a crash but not a clean shutdown.
+
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use super::{Collection, Storage, StoredWrite};
+
+#[derive(Debug, Default)]
+pub struct MemoryStorage {
+    records: BTreeMap<(Collection, String), Value>,
+}
+
+impl Storage for MemoryStorage {
+    fn name(&self) -> &'static str {
+        "memory"
+    }
+
+    fn apply(&mut self, writes: &[StoredWrite]) -> Result<(), String> {
+        for write in writes {
+            let key = (write.collection, write.key.clone());
+            match &write.value {
+                Some(value) => self.records.insert(key, value.clone()),
+                None => self.records.remove(&key),
+            };
+        }
+        Ok(())
+    }
+
+    fn get(&mut self, collection: Collection, key: &str) -> Result<Option<Value>, String> {
+        Ok(self.records.get(&(collection, key.to_string())).cloned())
+    }
+
+    fn scan(&mut self, collection: Collection) -> Result<Vec<(String, Value)>, String> {
+        Ok(self
+            .records
+            .iter()
+            .filter(|((c, _), _)| *c == collection)
+            .map(|((_, key), value)| (key.clone(), value.clone()))
+            .collect())
+    }
+}
@@ -0,0 +1,553 @@
+//! Persistence for the dedicated server: characters, inventories, guilds,
+//! auctions and world flags kept in a database instead of only in memory.
+//!
+//! Records ([`records`]) are stored as JSON under a key in one of a few
+//! [`Collection`]s, behind the [`Storage`] trait: SQLite (`sqlite` feature),
+//! PostgreSQL (`postgres` feature) or, without either, [`MemoryStorage`].
+//!
+//! Writes are write-behind. [`Persistence::save`] hands the record to a
+//! writer thread and returns; the thread appends it to a journal, syncs the
+//! journal once per burst of writes, and commits to storage in batches
+//! every `batch_interval_seconds` (or `max_batch` records), several saves
+//! of the same record in one batch costing one row write. Once a batch is
+//! committed the journal is truncated, and on startup whatever is left in
+//! it is replayed, so a crash loses nothing that reached the journal. A
+//! failed commit keeps the batch and retries.
+//!
+//! Reads ([`Persistence::load`], [`Persistence::load_all`]) block on the
+//! writer thread, which answers from the pending batch before storage so a
+//! load always sees earlier saves. They're meant for startup and logins,
+//! not every frame.
+
+mod journal;
+mod json_file;
+mod memory;
+#[cfg(feature = "postgres")]
+mod postgres;
+mod records;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+pub use json_file::{load_json, save_json};
+pub use memory::MemoryStorage;
+#[cfg(feature = "postgres")]
+pub use postgres::PostgresStorage;
+pub use records::*;
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteStorage;
+
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::console::{
+    ArgCompletion, CommandPermission, ConsoleAppExt, ConsoleCommand, ConsoleCommandEvent, ConsoleOutputEvent,
+};
+use crate::scripting::{self, ScriptFlagChangedEvent, ScriptRuntime};
+use journal::Journal;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Collection {
+    Characters,
+    Inventories,
+    Guilds,
+    Auctions,
+    WorldFlags,
+}
+
+impl Collection {
+    pub const ALL: [Collection; 5] = [
+        Collection::Characters,
+        Collection::Inventories,
+        Collection::Guilds,
+        Collection::Auctions,
+        Collection::WorldFlags,
+    ];
+
+    /// Table name in SQL backends.
+    pub fn table(self) -> &'static str {
+        match self {
+            Collection::Characters => "characters",
+            Collection::Inventories => "inventories",
+            Collection::Guilds => "guilds",
+            Collection::Auctions => "auctions",
+            Collection::WorldFlags => "world_flags",
+        }
+    }
+}
+
+/// A record type the server stores.
+pub trait Persisted: Serialize + DeserializeOwned + Send + 'static {
+    const COLLECTION: Collection;
+
+    fn storage_key(&self) -> String;
+}
+
+/// One record write; `value: None` deletes it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoredWrite {
+    pub collection: Collection,
+    pub key: String,
+    pub value: Option<Value>,
+}
+
+/// A database. Only the writer thread touches it.
+pub trait Storage: Send + 'static {
+    fn name(&self) -> &'static str;
+
+    /// Applies `writes` in order, all or nothing.
+    fn apply(&mut self, writes: &[StoredWrite]) -> Result<(), String>;
+
+    fn get(&mut self, collection: Collection, key: &str) -> Result<Option<Value>, String>;
+
+    /// Every record in `collection`, in key order.
+    fn scan(&mut self, collection: Collection) -> Result<Vec<(String, Value)>, String>;
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StorageBackend {
+    Memory,
+    Sqlite { path: PathBuf },
+    /// e.g. `postgres://mmo:secret@db/mmo`
+    Postgres { url: String },
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct PersistenceConfig {
+    pub backend: StorageBackend,
+    pub journal_path: PathBuf,
+    pub batch_interval_seconds: f32,
+    /// Pending records that trigger a commit before the interval is up.
+    pub max_batch: usize,
+}
+
+impl Default for PersistenceConfig {
+    fn default() -> Self {
+        let backend = match std::env::var("MMO_DATABASE_URL").ok().filter(|v| !v.is_empty()) {
+            Some(url) if url.starts_with("postgres://") || url.starts_with("postgresql://") => {
+                StorageBackend::Postgres { url }
+            }
+            Some(path) => StorageBackend::Sqlite { path: PathBuf::from(path) },
+            None if cfg!(feature = "sqlite") => StorageBackend::Sqlite {
+                path: PathBuf::from("server_data").join("world.sqlite3"),
+            },
+            None => StorageBackend::Memory,
+        };
+        Self {
+            backend,
+            journal_path: PathBuf::from("server_data").join("persistence.journal"),
+            batch_interval_seconds: 2.0,
+            max_batch: 500,
+        }
+    }
+}
+
+fn open_storage(backend: &StorageBackend) -> Result<Box<dyn Storage>, String> {
+    match backend {
+        StorageBackend::Memory => Ok(Box::new(MemoryStorage::default())),
+        #[cfg(feature = "sqlite")]
+        StorageBackend::Sqlite { path } => Ok(Box::new(SqliteStorage::open(path)?)),
+        #[cfg(not(feature = "sqlite"))]
+        StorageBackend::Sqlite { .. } => Err("this build has no SQLite support (enable the `sqlite` feature)".into()),
+        #[cfg(feature = "postgres")]
+        StorageBackend::Postgres { url } => Ok(Box::new(PostgresStorage::connect(url)?)),
+        #[cfg(not(feature = "postgres"))]
+        StorageBackend::Postgres { .. } => {
+            Err("this build has no PostgreSQL support (enable the `postgres` feature)".into())
+        }
+    }
+}
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PersistenceStats {
+    /// Writes replayed from the journal at startup.
+    pub recovered: usize,
+    pub pending: usize,
+    pub journal_entries: usize,
+    pub batches_committed: u64,
+    pub records_committed: u64,
+    pub last_error: Option<String>,
+}
+
+enum WriterMessage {
+    Write(StoredWrite),
+    Get {
+        collection: Collection,
+        key: String,
+        reply: Sender<Result<Option<Value>, String>>,
+    },
+    Scan {
+        collection: Collection,
+        reply: Sender<Result<Vec<(String, Value)>, String>>,
+    },
+    Flush(Sender<Result<(), String>>),
+}
+
+/// The server's handle on storage. Cheap calls; the work happens on the
+/// writer thread.
+#[derive(Resource)]
+pub struct Persistence {
+    sender: Sender<WriterMessage>,
+    backend: &'static str,
+    stats: Arc<Mutex<PersistenceStats>>,
+}
+
+impl Persistence {
+    /// Opens storage, replays the journal into it and starts the writer
+    /// thread.
+    pub fn open(config: &PersistenceConfig) -> Result<Self, String> {
+        let mut storage = open_storage(&config.backend)?;
+        let (mut journal, recovered) = Journal::open(&config.journal_path)?;
+        if !recovered.is_empty() {
+            storage.apply(&recovered).map_err(|e| format!("replaying the journal: {}", e))?;
+            journal.truncate()?;
+            info!("Persistence: replayed {} journaled writes", recovered.len());
+        }
+
+        let backend = storage.name();
+        let stats = Arc::new(Mutex::new(PersistenceStats {
+            recovered: recovered.len(),
+            ..default()
+        }));
+        let (sender, receiver) = mpsc::channel();
+        let writer = Writer {
+            storage,
+            journal,
+            pending: BTreeMap::new(),
+            interval: Duration::from_secs_f32(config.batch_interval_seconds.max(0.01)),
+            max_batch: config.max_batch.max(1),
+            stats: stats.clone(),
+        };
+        std::thread::Builder::new()
+            .name("persistence".into())
+            .spawn(move || writer.run(receiver))
+            .map_err(|e| e.to_string())?;
+        Ok(Self { sender, backend, stats })
+    }
+
+    pub fn backend(&self) -> &'static str {
+        self.backend
+    }
+
+    pub fn stats(&self) -> PersistenceStats {
+        self.stats.lock().map(|stats| stats.clone()).unwrap_or_default()
+    }
+
+    fn send(&self, message: WriterMessage) -> Result<(), String> {
+        self.sender.send(message).map_err(|_| "the persistence writer has stopped".to_string())
+    }
+
+    /// Queues `record` to be written.
+    pub fn save<T: Persisted>(&self, record: &T) -> Result<(), String> {
+        let value = serde_json::to_value(record).map_err(|e| e.to_string())?;
+        self.send(WriterMessage::Write(StoredWrite {
+            collection: T::COLLECTION,
+            key: record.storage_key(),
+            value: Some(value),
+        }))
+    }
+
+    /// Queues the record of type `T` under `key` to be deleted.
+    pub fn delete<T: Persisted>(&self, key: &str) -> Result<(), String> {
+        self.send(WriterMessage::Write(StoredWrite {
+            collection: T::COLLECTION,
+            key: key.to_string(),
+            value: None,
+        }))
+    }
+
+    /// Reads one record, waiting for the writer thread.
+    pub fn load<T: Persisted>(&self, key: &str) -> Result<Option<T>, String> {
+        let (reply, answer) = mpsc::channel();
+        self.send(WriterMessage::Get {
+            collection: T::COLLECTION,
+            key: key.to_string(),
+            reply,
+        })?;
+        let value = answer.recv().map_err(|_| "the persistence writer has stopped".to_string())??;
+        value
+            .map(|value| {
+                serde_json::from_value(value).map_err(|e| format!("{} '{}': {}", T::COLLECTION.table(), key, e))
+            })
+            .transpose()
+    }
+
+    /// Reads every record of type `T`, waiting for the writer thread.
+    /// Records that no longer parse are skipped with a warning.
+    pub fn load_all<T: Persisted>(&self) -> Result<Vec<T>, String> {
+        let (reply, answer) = mpsc::channel();
+        self.send(WriterMessage::Scan {
+            collection: T::COLLECTION,
+            reply,
+        })?;
+        let records = answer.recv().map_err(|_| "the persistence writer has stopped".to_string())??;
+        Ok(records
+            .into_iter()
+            .filter_map(|(key, value)| match serde_json::from_value(value) {
+                Ok(record) => Some(record),
+                Err(e) => {
+                    warn!("Skipping unreadable {} record '{}': {}", T::COLLECTION.table(), key, e);
+                    None
+                }
+            })
+            .collect())
+    }
+
+    /// Commits everything queued so far and waits for it.
+    pub fn flush(&self) -> Result<(), String> {
+        let (reply, answer) = mpsc::channel();
+        self.send(WriterMessage::Flush(reply))?;
+        answer.recv().map_err(|_| "the persistence writer has stopped".to_string())?
+    }
+}
+
+struct Writer {
+    storage: Box<dyn Storage>,
+    journal: Journal,
+    /// Latest write per record since the last commit.
+    pending: BTreeMap<(Collection, String), Option<Value>>,
+    interval: Duration,
+    max_batch: usize,
+    stats: Arc<Mutex<PersistenceStats>>,
+}
+
+impl Writer {
+    fn run(mut self, receiver: Receiver<WriterMessage>) {
+        let mut last_commit = Instant::now();
+        loop {
+            let first = match receiver.recv_timeout(self.interval.saturating_sub(last_commit.elapsed())) {
+                Ok(message) => Some(message),
+                Err(RecvTimeoutError::Timeout) => None,
+                Err(RecvTimeoutError::Disconnected) => {
+                    // Whatever fails here is still in the journal for next time
+                    let _ = self.commit();
+                    return;
+                }
+            };
+
+            // Group commit: take the whole burst, then sync the journal once
+            let mut flushes = Vec::new();
+            for message in first.into_iter().chain(receiver.try_iter()) {
+                match message {
+                    WriterMessage::Write(write) => {
+                        if let Err(e) = self.journal.append(&write) {
+                            self.set_error(format!("journal: {}", e));
+                        }
+                        self.pending.insert((write.collection, write.key), write.value);
+                    }
+                    WriterMessage::Get { collection, key, reply } => {
+                        let _ = reply.send(match self.pending.get(&(collection, key.clone())) {
+                            Some(value) => Ok(value.clone()),
+                            None => self.storage.get(collection, &key),
+                        });
+                    }
+                    WriterMessage::Scan { collection, reply } => {
+                        let _ = reply.send(self.scan(collection));
+                    }
+                    WriterMessage::Flush(reply) => flushes.push(reply),
+                }
+            }
+            if let Err(e) = self.journal.sync() {
+                self.set_error(format!("journal: {}", e));
+            }
+
+            let due = last_commit.elapsed() >= self.interval || self.pending.len() >= self.max_batch;
+            if due || !flushes.is_empty() {
+                let result = self.commit();
+                // A failed commit retries after the next interval
+                last_commit = Instant::now();
+                for reply in flushes {
+                    let _ = reply.send(result.clone());
+                }
+            }
+            self.update_stats();
+        }
+    }
+
+    fn scan(&mut self, collection: Collection) -> Result<Vec<(String, Value)>, String> {
+        let mut records: BTreeMap<String, Value> = self.storage.scan(collection)?.into_iter().collect();
+        for ((c, key), value) in &self.pending {
+            if *c != collection {
+                continue;
+            }
+            match value {
+                Some(value) => records.insert(key.clone(), value.clone()),
+                None => records.remove(key),
+            };
+        }
+        Ok(records.into_iter().collect())
+    }
+
+    fn commit(&mut self) -> Result<(), String> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let writes: Vec<StoredWrite> = self
+            .pending
+            .iter()
+            .map(|((collection, key), value)| StoredWrite {
+                collection: *collection,
+                key: key.clone(),
+                value: value.clone(),
+            })
+            .collect();
+        if let Err(e) = self.storage.apply(&writes) {
+            warn!("Persistence: committing {} records failed: {}", writes.len(), e);
+            self.set_error(e.clone());
+            return Err(e);
+        }
+        self.pending.clear();
+        if let Err(e) = self.journal.truncate() {
+            self.set_error(format!("journal: {}", e));
+        }
+        if let Ok(mut stats) = self.stats.lock() {
+            stats.batches_committed += 1;
+            stats.records_committed += writes.len() as u64;
+            stats.last_error = None;
+        }
+        Ok(())
+    }
+
+    fn set_error(&self, error: String) {
+        if let Ok(mut stats) = self.stats.lock() {
+            stats.last_error = Some(error);
+        }
+    }
+
+    fn update_stats(&self) {
+        if let Ok(mut stats) = self.stats.lock() {
+            stats.pending = self.pending.len();
+            stats.journal_entries = self.journal.entries;
+        }
+    }
+}
+
+pub struct PersistencePlugin;
+
+impl Plugin for PersistencePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PersistenceConfig>()
+            .add_event::<ScriptFlagChangedEvent>()
+            .add_console_command(
+                ConsoleCommand::new("persistence", "Shows the storage backend and write-behind queue")
+                    .usage("[flush]")
+                    .permission(CommandPermission::Admin)
+                    .complete(vec![ArgCompletion::Values(vec!["flush".to_string()])]),
+            )
+            .add_systems(
+                Startup,
+                (open_persistence, restore_world_flags.run_if(resource_exists::<ScriptRuntime>))
+                    .chain()
+                    .before(scripting::load_scripts),
+            )
+            .add_systems(
+                Update,
+                (persist_world_flags, flush_on_exit, persistence_command).run_if(resource_exists::<Persistence>),
+            );
+    }
+}
+
+fn open_persistence(mut commands: Commands, config: Res<PersistenceConfig>) {
+    match Persistence::open(&config) {
+        Ok(persistence) => {
+            info!("Persistence: {} storage ready", persistence.backend());
+            commands.insert_resource(persistence);
+        }
+        Err(e) => error!("Failed to open persistent storage: {}; world state will not be saved", e),
+    }
+}
+
+/// Puts saved world flags back before any script's `on_load` runs.
+fn restore_world_flags(persistence: Option<Res<Persistence>>, runtime: Res<ScriptRuntime>) {
+    let Some(persistence) = persistence else {
+        return;
+    };
+    match persistence.load_all::<WorldFlagRecord>() {
+        Ok(flags) => {
+            for flag in &flags {
+                match rhai::serde::to_dynamic(&flag.value) {
+                    Ok(value) => runtime.set_flag(&flag.name, value),
+                    Err(e) => warn!("Skipping world flag '{}': {}", flag.name, e),
+                }
+            }
+            info!("Persistence: {} world flags restored", flags.len());
+        }
+        Err(e) => warn!("Failed to load world flags: {}", e),
+    }
+}
+
+fn persist_world_flags(persistence: Res<Persistence>, mut changes: EventReader<ScriptFlagChangedEvent>) {
+    for change in changes.read() {
+        let result = rhai::serde::from_dynamic::<Value>(&change.value)
+            .map_err(|e| e.to_string())
+            .and_then(|value| {
+                persistence.save(&WorldFlagRecord {
+                    name: change.name.clone(),
+                    value,
+                })
+            });
+        if let Err(e) = result {
+            warn!("Failed to save world flag '{}': {}", change.name, e);
+        }
+    }
+}
+
+fn flush_on_exit(persistence: Res<Persistence>, mut exits: EventReader<AppExit>) {
+    if exits.read().count() == 0 {
+        return;
+    }
+    if let Err(e) = persistence.flush() {
+        warn!("Persistence: final flush failed: {}; the journal will be replayed on next start", e);
+    }
+}
+
+fn persistence_command(
+    persistence: Res<Persistence>,
+    mut events: EventReader<ConsoleCommandEvent>,
+    mut output: EventWriter<ConsoleOutputEvent>,
+) {
+    for event in events.read() {
+        if event.name != "persistence" {
+            continue;
+        }
+        let result = match event.args.first().map(String::as_str) {
+            Some("flush") => persistence.flush().map(|_| "Flushed".to_string()),
+            Some(other) => Err(format!("unknown option '{}'", other)),
+            None => {
+                let stats = persistence.stats();
+                let mut text = format!(
+                    "Backend {}: {} pending, {} journaled, {} batches ({} records) committed, {} recovered at startup",
+                    persistence.backend(),
+                    stats.pending,
+                    stats.journal_entries,
+                    stats.batches_committed,
+                    stats.records_committed,
+                    stats.recovered
+                );
+                if let Some(error) = stats.last_error {
+                    text.push_str(&format!("\nLast error: {}", error));
+                }
+                Ok(text)
+            }
+        };
+        output.send(match result {
+            Ok(text) => event.reply(text),
+            Err(text) => event.error(format!("{}: {}", event.name, text)),
+        });
+    }
+}
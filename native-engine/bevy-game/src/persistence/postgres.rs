@@ -0,0 +1,74 @@
+//! PostgreSQL storage (`postgres` feature), for servers sharing one
+//! database: one table per collection with JSONB values.
+
+use postgres::types::Json;
+use postgres::{Client, NoTls};
+use serde_json::Value;
+
+use super::{unix_now, Collection, Storage, StoredWrite};
+
+pub struct PostgresStorage {
+    client: Client,
+}
+
+impl PostgresStorage {
+    pub fn connect(url: &str) -> Result<Self, String> {
+        let mut client = Client::connect(url, NoTls).map_err(|e| e.to_string())?;
+        for collection in Collection::ALL {
+            client
+                .batch_execute(&format!(
+                    "CREATE TABLE IF NOT EXISTS {} \
+                     (key TEXT PRIMARY KEY, value JSONB NOT NULL, updated_at BIGINT NOT NULL)",
+                    collection.table()
+                ))
+                .map_err(|e| e.to_string())?;
+        }
+        Ok(Self { client })
+    }
+}
+
+impl Storage for PostgresStorage {
+    fn name(&self) -> &'static str {
+        "postgres"
+    }
+
+    fn apply(&mut self, writes: &[StoredWrite]) -> Result<(), String> {
+        let mut transaction = self.client.transaction().map_err(|e| e.to_string())?;
+        let now = unix_now() as i64;
+        for write in writes {
+            let table = write.collection.table();
+            let result = match &write.value {
+                Some(value) => transaction.execute(
+                    &format!(
+                        "INSERT INTO {} (key, value, updated_at) VALUES ($1, $2, $3) \
+                         ON CONFLICT (key) DO UPDATE SET value = EXCLUDED.value, updated_at = EXCLUDED.updated_at",
+                        table
+                    ),
+                    &[&write.key, &Json(value), &now],
+                ),
+                None => transaction.execute(&format!("DELETE FROM {} WHERE key = $1", table), &[&write.key]),
+            };
+            result.map_err(|e| e.to_string())?;
+        }
+        transaction.commit().map_err(|e| e.to_string())
+    }
+
+    fn get(&mut self, collection: Collection, key: &str) -> Result<Option<Value>, String> {
+        let row = self
+            .client
+            .query_opt(&format!("SELECT value FROM {} WHERE key = $1", collection.table()), &[&key])
+            .map_err(|e| e.to_string())?;
+        Ok(row.map(|row| row.get::<_, Json<Value>>(0).0))
+    }
+
+    fn scan(&mut self, collection: Collection) -> Result<Vec<(String, Value)>, String> {
+        let rows = self
+            .client
+            .query(&format!("SELECT key, value FROM {} ORDER BY key", collection.table()), &[])
+            .map_err(|e| e.to_string())?;
+        Ok(rows
+            .iter()
+            .map(|row| (row.get::<_, String>(0), row.get::<_, Json<Value>>(1).0))
+            .collect())
+    }
+}
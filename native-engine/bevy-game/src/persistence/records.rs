@@ -0,0 +1,125 @@
+//! What the server stores. Each record type lives in one [`Collection`] and
+//! is stored as JSON under its key, so adding a field with a serde default
+//! needs no schema change.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use super::{Collection, Persisted};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ItemStack {
+    pub item: String,
+    pub count: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CharacterRecord {
+    pub name: String,
+    pub account: String,
+    pub realm: String,
+    pub level: u32,
+    #[serde(default)]
+    pub xp: u64,
+    #[serde(default)]
+    pub copper: u64,
+    pub position: Vec3,
+    #[serde(default)]
+    pub zone: Option<String>,
+    /// Unix seconds of the last logout.
+    #[serde(default)]
+    pub last_seen: u64,
+}
+
+impl Persisted for CharacterRecord {
+    const COLLECTION: Collection = Collection::Characters;
+
+    fn storage_key(&self) -> String {
+        self.name.clone()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct InventoryRecord {
+    pub character: String,
+    /// Bag slots in order; `None` is an empty slot.
+    #[serde(default)]
+    pub bags: Vec<Option<ItemStack>>,
+    /// Equipment slot name to what's in it.
+    #[serde(default)]
+    pub equipped: BTreeMap<String, ItemStack>,
+}
+
+impl Persisted for InventoryRecord {
+    const COLLECTION: Collection = Collection::Inventories;
+
+    fn storage_key(&self) -> String {
+        self.character.clone()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GuildRecord {
+    pub name: String,
+    pub leader: String,
+    /// Member name to rank.
+    #[serde(default)]
+    pub members: BTreeMap<String, String>,
+    #[serde(default)]
+    pub created_at: u64,
+}
+
+impl Persisted for GuildRecord {
+    const COLLECTION: Collection = Collection::Guilds;
+
+    fn storage_key(&self) -> String {
+        self.name.clone()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuctionRecord {
+    pub id: u64,
+    pub seller: String,
+    pub item: ItemStack,
+    pub starting_bid: u64,
+    #[serde(default)]
+    pub buyout: Option<u64>,
+    #[serde(default)]
+    pub highest_bidder: Option<String>,
+    #[serde(default)]
+    pub highest_bid: u64,
+    /// Unix seconds.
+    pub expires_at: u64,
+}
+
+impl AuctionRecord {
+    /// Storage key of auction `id`; zero-padded so keys sort in id order.
+    pub fn key_for(id: u64) -> String {
+        format!("{:020}", id)
+    }
+}
+
+impl Persisted for AuctionRecord {
+    const COLLECTION: Collection = Collection::Auctions;
+
+    fn storage_key(&self) -> String {
+        Self::key_for(self.id)
+    }
+}
+
+/// A script flag (`set_flag`), kept across restarts.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorldFlagRecord {
+    pub name: String,
+    pub value: serde_json::Value,
+}
+
+impl Persisted for WorldFlagRecord {
+    const COLLECTION: Collection = Collection::WorldFlags;
+
+    fn storage_key(&self) -> String {
+        self.name.clone()
+    }
+}
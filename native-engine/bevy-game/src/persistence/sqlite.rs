@@ -0,0 +1,92 @@
+//! SQLite storage (`sqlite` feature): one table per collection, JSON text
+//! values, WAL mode so readers never block the writer thread.
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde_json::Value;
+use std::path::Path;
+
+use super::{unix_now, Collection, Storage, StoredWrite};
+
+pub struct SqliteStorage {
+    connection: Connection,
+}
+
+impl SqliteStorage {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let connection = Connection::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        // Setting journal_mode answers with the new mode, so it needs the checked form
+        connection
+            .pragma_update_and_check(None, "journal_mode", "WAL", |_| Ok(()))
+            .and_then(|_| connection.pragma_update(None, "synchronous", "NORMAL"))
+            .map_err(|e| e.to_string())?;
+        for collection in Collection::ALL {
+            connection
+                .execute_batch(&format!(
+                    "CREATE TABLE IF NOT EXISTS {} \
+                     (key TEXT PRIMARY KEY, value TEXT NOT NULL, updated_at INTEGER NOT NULL)",
+                    collection.table()
+                ))
+                .map_err(|e| e.to_string())?;
+        }
+        Ok(Self { connection })
+    }
+}
+
+impl Storage for SqliteStorage {
+    fn name(&self) -> &'static str {
+        "sqlite"
+    }
+
+    fn apply(&mut self, writes: &[StoredWrite]) -> Result<(), String> {
+        let transaction = self.connection.transaction().map_err(|e| e.to_string())?;
+        let now = unix_now() as i64;
+        for write in writes {
+            let table = write.collection.table();
+            let result = match &write.value {
+                Some(value) => transaction.execute(
+                    &format!(
+                        "INSERT INTO {} (key, value, updated_at) VALUES (?1, ?2, ?3) \
+                         ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at",
+                        table
+                    ),
+                    params![write.key, value.to_string(), now],
+                ),
+                None => transaction.execute(&format!("DELETE FROM {} WHERE key = ?1", table), params![write.key]),
+            };
+            result.map_err(|e| e.to_string())?;
+        }
+        transaction.commit().map_err(|e| e.to_string())
+    }
+
+    fn get(&mut self, collection: Collection, key: &str) -> Result<Option<Value>, String> {
+        let text: Option<String> = self
+            .connection
+            .query_row(
+                &format!("SELECT value FROM {} WHERE key = ?1", collection.table()),
+                params![key],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| e.to_string())?;
+        text.map(|text| serde_json::from_str(&text).map_err(|e| e.to_string())).transpose()
+    }
+
+    fn scan(&mut self, collection: Collection) -> Result<Vec<(String, Value)>, String> {
+        let mut statement = self
+            .connection
+            .prepare(&format!("SELECT key, value FROM {} ORDER BY key", collection.table()))
+            .map_err(|e| e.to_string())?;
+        let rows = statement
+            .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+            .map_err(|e| e.to_string())?;
+        let mut records = Vec::new();
+        for row in rows {
+            let (key, text) = row.map_err(|e| e.to_string())?;
+            records.push((key, serde_json::from_str(&text).map_err(|e| e.to_string())?));
+        }
+        Ok(records)
+    }
+}
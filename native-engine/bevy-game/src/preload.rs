@@ -0,0 +1,541 @@
+//! Per-zone asset preloading. Zone manifests under `content/preload/` list
+//! the models, textures and audio a zone needs; entering the zone (or
+//! starting the game, for the `startup` zone) loads them all and tracks
+//! progress, with a loading screen for zones that ask for one.
+//!
+//! Handles live in [`PreloadCache`], so systems look assets up by path
+//! instead of holding their own handles and polling the asset server:
+//!
+//! ```toml
+//! [[zone]]
+//! id = "startup"
+//! loading_screen = true
+//! models = ["models/mutant.glb"]
+//! audio = ["audio/music/title.ogg"]
+//! ```
+
+use bevy::asset::{RecursiveDependencyLoadState, UntypedAssetId};
+use bevy::gltf::Gltf;
+use bevy::prelude::*;
+use fluent::fluent_args;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::PathBuf;
+
+use crate::content_packs::ContentPacks;
+use crate::localization::Localization;
+use crate::triggers::ZoneEnteredEvent;
+use crate::Player;
+
+/// Zone preloaded once at startup; its assets are never released.
+pub const STARTUP_ZONE: &str = "startup";
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ZoneAssetManifest {
+    pub id: String,
+    /// Cover the screen until every asset is loaded.
+    #[serde(default)]
+    pub loading_screen: bool,
+    /// glTF files, loaded with all their scenes, meshes and materials.
+    #[serde(default)]
+    pub models: Vec<String>,
+    #[serde(default)]
+    pub textures: Vec<String>,
+    #[serde(default)]
+    pub audio: Vec<String>,
+}
+
+impl ZoneAssetManifest {
+    pub fn len(&self) -> usize {
+        self.models.len() + self.textures.len() + self.audio.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PreloadContentFile {
+    #[serde(default, rename = "zone")]
+    pub zones: Vec<ZoneAssetManifest>,
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct PreloadConfig {
+    pub content_directory: PathBuf,
+    /// Seconds before a preload gives up on assets that are still loading.
+    pub timeout_seconds: f32,
+}
+
+impl Default for PreloadConfig {
+    fn default() -> Self {
+        Self {
+            content_directory: PathBuf::from("content").join("preload"),
+            timeout_seconds: 30.0,
+        }
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct ZoneManifests {
+    pub zones: HashMap<String, ZoneAssetManifest>,
+}
+
+impl ZoneManifests {
+    /// Used when no content file defines the startup zone.
+    fn builtin_startup() -> ZoneAssetManifest {
+        ZoneAssetManifest {
+            id: STARTUP_ZONE.to_string(),
+            loading_screen: true,
+            models: vec!["models/mutant.glb".to_string()],
+            ..default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PreloadStatus {
+    Loading,
+    Loaded,
+    Failed,
+}
+
+struct CachedAsset {
+    handle: UntypedHandle,
+    /// Zones whose manifests list this asset. Pinned assets are kept even
+    /// when this is empty.
+    zones: HashSet<String>,
+    pinned: bool,
+}
+
+/// Loaded (or loading) assets by path. Holding the handle here keeps the
+/// asset alive until every zone that listed it has been released.
+#[derive(Resource, Default)]
+pub struct PreloadCache {
+    assets: HashMap<String, CachedAsset>,
+}
+
+impl PreloadCache {
+    /// The handle for `path`, loading it on first use. Assets loaded this way
+    /// are pinned and never released with a zone.
+    pub fn load<A: Asset>(&mut self, asset_server: &AssetServer, path: &str) -> Handle<A> {
+        let entry = self.assets.entry(path.to_string()).or_insert_with(|| CachedAsset {
+            handle: asset_server.load::<A>(path.to_string()).untyped(),
+            zones: HashSet::new(),
+            pinned: false,
+        });
+        entry.pinned = true;
+        match entry.handle.clone().try_typed::<A>() {
+            Ok(handle) => handle,
+            Err(e) => {
+                warn!("Preloaded {} has another asset type: {}", path, e);
+                asset_server.load(path.to_string())
+            }
+        }
+    }
+
+    /// The cached handle for `path`, if it was preloaded as an `A`.
+    pub fn get<A: Asset>(&self, path: &str) -> Option<Handle<A>> {
+        self.assets.get(path)?.handle.clone().try_typed::<A>().ok()
+    }
+
+    pub fn status(&self, asset_server: &AssetServer, path: &str) -> Option<PreloadStatus> {
+        let asset = self.assets.get(path)?;
+        Some(load_status(asset_server, asset.handle.id()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.assets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.assets.is_empty()
+    }
+
+    fn retain_for_zone(&mut self, zone: &str, path: &str, handle: impl FnOnce() -> UntypedHandle) {
+        self.assets
+            .entry(path.to_string())
+            .or_insert_with(|| CachedAsset {
+                handle: handle(),
+                zones: HashSet::new(),
+                pinned: false,
+            })
+            .zones
+            .insert(zone.to_string());
+    }
+
+    /// Drops the zone's claim on its assets. Returns how many handles were
+    /// released.
+    pub fn release_zone(&mut self, zone: &str) -> usize {
+        let before = self.assets.len();
+        self.assets.retain(|_, asset| {
+            asset.zones.remove(zone);
+            asset.pinned || !asset.zones.is_empty()
+        });
+        before - self.assets.len()
+    }
+}
+
+fn load_status(asset_server: &AssetServer, id: UntypedAssetId) -> PreloadStatus {
+    match asset_server.get_recursive_dependency_load_state(id) {
+        Some(RecursiveDependencyLoadState::Loaded) => PreloadStatus::Loaded,
+        Some(RecursiveDependencyLoadState::Failed(_)) => PreloadStatus::Failed,
+        _ => PreloadStatus::Loading,
+    }
+}
+
+/// Preload a zone's manifest. Sent for the startup zone and whenever the
+/// player enters a zone; other systems may send it ahead of a teleport.
+#[derive(Event, Debug, Clone)]
+pub struct PreloadRequest {
+    pub zone: String,
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct PreloadCompleteEvent {
+    pub zone: String,
+    pub loaded: usize,
+    /// Paths that failed or timed out.
+    pub failed: Vec<String>,
+    pub seconds: f32,
+}
+
+/// The preload in progress, for loading screens and the log overlay.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct PreloadProgress {
+    pub zone: Option<String>,
+    pub total: usize,
+    pub loaded: usize,
+    pub failed: usize,
+    pub loading_screen: bool,
+    /// Preloads waiting behind this one.
+    pub queued: usize,
+}
+
+impl PreloadProgress {
+    pub fn is_active(&self) -> bool {
+        self.zone.is_some()
+    }
+
+    pub fn fraction(&self) -> f32 {
+        if self.total == 0 {
+            return 1.0;
+        }
+        (self.loaded + self.failed) as f32 / self.total as f32
+    }
+}
+
+struct PreloadJob {
+    zone: String,
+    pending: Vec<(String, UntypedAssetId)>,
+    total: usize,
+    loaded: usize,
+    failed: Vec<String>,
+    started: f32,
+    loading_screen: bool,
+}
+
+#[derive(Resource, Default)]
+struct PreloadQueue {
+    active: Option<PreloadJob>,
+    waiting: VecDeque<String>,
+    /// Zone the player is in, released when they enter another.
+    current_zone: Option<String>,
+}
+
+#[derive(Component)]
+struct LoadingScreen;
+
+#[derive(Component)]
+struct LoadingScreenBar;
+
+#[derive(Component)]
+struct LoadingScreenText;
+
+pub struct PreloadPlugin;
+
+impl Plugin for PreloadPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PreloadConfig>()
+            .init_resource::<ZoneManifests>()
+            .init_resource::<PreloadCache>()
+            .init_resource::<PreloadProgress>()
+            .init_resource::<PreloadQueue>()
+            .add_event::<PreloadRequest>()
+            .add_event::<PreloadCompleteEvent>()
+            .add_systems(Startup, (load_preload_manifests, spawn_loading_screen))
+            .add_systems(
+                Update,
+                (
+                    request_zone_preloads,
+                    start_preloads,
+                    track_preloads,
+                    update_loading_screen,
+                )
+                    .chain(),
+            );
+    }
+}
+
+fn load_preload_manifests(
+    config: Res<PreloadConfig>,
+    packs: Res<ContentPacks>,
+    mut manifests: ResMut<ZoneManifests>,
+    mut requests: EventWriter<PreloadRequest>,
+) {
+    for path in packs.files(&config.content_directory, "toml") {
+        let parsed = std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| toml::from_str::<PreloadContentFile>(&text).map_err(|e| e.to_string()));
+
+        match parsed {
+            Ok(file) => {
+                for zone in file.zones {
+                    manifests.zones.insert(zone.id.clone(), zone);
+                }
+            }
+            Err(e) => warn!("Invalid preload manifest {:?}: {}", path, e),
+        }
+    }
+    manifests
+        .zones
+        .entry(STARTUP_ZONE.to_string())
+        .or_insert_with(ZoneManifests::builtin_startup);
+
+    info!("Preload: {} zone manifests loaded", manifests.zones.len());
+    requests.send(PreloadRequest {
+        zone: STARTUP_ZONE.to_string(),
+    });
+}
+
+fn request_zone_preloads(
+    mut zone_events: EventReader<ZoneEnteredEvent>,
+    players: Query<(), With<Player>>,
+    manifests: Res<ZoneManifests>,
+    mut requests: EventWriter<PreloadRequest>,
+) {
+    for event in zone_events.read() {
+        if players.contains(event.entity) && manifests.zones.contains_key(&event.zone_id) {
+            requests.send(PreloadRequest {
+                zone: event.zone_id.clone(),
+            });
+        }
+    }
+}
+
+fn start_preloads(
+    time: Res<Time>,
+    asset_server: Res<AssetServer>,
+    manifests: Res<ZoneManifests>,
+    mut cache: ResMut<PreloadCache>,
+    mut queue: ResMut<PreloadQueue>,
+    mut requests: EventReader<PreloadRequest>,
+) {
+    for request in requests.read() {
+        let duplicate = queue.active.as_ref().is_some_and(|job| job.zone == request.zone)
+            || queue.waiting.contains(&request.zone);
+        if !duplicate {
+            queue.waiting.push_back(request.zone.clone());
+        }
+    }
+    if queue.active.is_some() {
+        return;
+    }
+    let Some(zone) = queue.waiting.pop_front() else {
+        return;
+    };
+    let Some(manifest) = manifests.zones.get(&zone) else {
+        warn!("Preload: no manifest for zone '{}'", zone);
+        return;
+    };
+
+    let mut pending = Vec::with_capacity(manifest.len());
+    let mut retain = |path: &String, load: &dyn Fn() -> UntypedHandle| {
+        cache.retain_for_zone(&zone, path, load);
+        if let Some(asset) = cache.assets.get(path) {
+            pending.push((path.clone(), asset.handle.id()));
+        }
+    };
+    for path in &manifest.models {
+        retain(path, &|| asset_server.load::<Gltf>(path.clone()).untyped());
+    }
+    for path in &manifest.textures {
+        retain(path, &|| asset_server.load::<Image>(path.clone()).untyped());
+    }
+    for path in &manifest.audio {
+        retain(path, &|| asset_server.load::<AudioSource>(path.clone()).untyped());
+    }
+
+    // Entering a zone releases the one the player left; assets both zones
+    // list keep the new zone's claim and stay loaded
+    if zone != STARTUP_ZONE {
+        if let Some(previous) = queue.current_zone.replace(zone.clone()) {
+            if previous != zone {
+                let released = cache.release_zone(&previous);
+                if released > 0 {
+                    info!("Preload: released {} assets of zone '{}'", released, previous);
+                }
+            }
+        }
+    }
+
+    info!("Preload: zone '{}' ({} assets)", zone, pending.len());
+    queue.active = Some(PreloadJob {
+        zone,
+        total: pending.len(),
+        pending,
+        loaded: 0,
+        failed: Vec::new(),
+        started: time.elapsed_secs(),
+        loading_screen: manifest.loading_screen,
+    });
+}
+
+fn track_preloads(
+    time: Res<Time>,
+    config: Res<PreloadConfig>,
+    asset_server: Res<AssetServer>,
+    mut queue: ResMut<PreloadQueue>,
+    mut progress: ResMut<PreloadProgress>,
+    mut complete: EventWriter<PreloadCompleteEvent>,
+) {
+    let queued = queue.waiting.len();
+    let Some(job) = queue.active.as_mut() else {
+        if progress.is_active() {
+            *progress = PreloadProgress::default();
+        }
+        return;
+    };
+
+    let mut newly_loaded = 0;
+    let mut newly_failed = Vec::new();
+    job.pending.retain(|(path, id)| match load_status(&asset_server, *id) {
+        PreloadStatus::Loading => true,
+        PreloadStatus::Loaded => {
+            newly_loaded += 1;
+            false
+        }
+        PreloadStatus::Failed => {
+            warn!("Preload: {} failed to load", path);
+            newly_failed.push(path.clone());
+            false
+        }
+    });
+    job.loaded += newly_loaded;
+    job.failed.append(&mut newly_failed);
+
+    let elapsed = time.elapsed_secs() - job.started;
+    if !job.pending.is_empty() && elapsed > config.timeout_seconds {
+        for (path, _) in job.pending.drain(..) {
+            warn!("Preload: {} still loading after {:.0}s, giving up", path, elapsed);
+            job.failed.push(path);
+        }
+    }
+
+    *progress = PreloadProgress {
+        zone: Some(job.zone.clone()),
+        total: job.total,
+        loaded: job.loaded,
+        failed: job.failed.len(),
+        loading_screen: job.loading_screen,
+        queued,
+    };
+
+    if job.pending.is_empty() {
+        info!(
+            "Preload: zone '{}' ready in {:.2}s ({} loaded, {} failed)",
+            job.zone,
+            elapsed,
+            job.loaded,
+            job.failed.len()
+        );
+        complete.send(PreloadCompleteEvent {
+            zone: job.zone.clone(),
+            loaded: job.loaded,
+            failed: std::mem::take(&mut job.failed),
+            seconds: elapsed,
+        });
+        queue.active = None;
+    }
+}
+
+fn spawn_loading_screen(mut commands: Commands) {
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(12.0),
+                ..default()
+            },
+            BackgroundColor(Color::srgb(0.02, 0.02, 0.03)),
+            GlobalZIndex(100),
+            Visibility::Hidden,
+            LoadingScreen,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new(""),
+                TextFont {
+                    font_size: 20.0,
+                    ..default()
+                },
+                TextColor(Color::srgb(0.85, 0.85, 0.85)),
+                LoadingScreenText,
+            ));
+            parent
+                .spawn((
+                    Node {
+                        width: Val::Px(420.0),
+                        height: Val::Px(10.0),
+                        ..default()
+                    },
+                    BackgroundColor(Color::srgb(0.15, 0.15, 0.18)),
+                ))
+                .with_children(|track| {
+                    track.spawn((
+                        Node {
+                            width: Val::Percent(0.0),
+                            height: Val::Percent(100.0),
+                            ..default()
+                        },
+                        BackgroundColor(Color::srgb(0.85, 0.65, 0.25)),
+                        LoadingScreenBar,
+                    ));
+                });
+        });
+}
+
+fn update_loading_screen(
+    progress: Res<PreloadProgress>,
+    localization: Res<Localization>,
+    mut screens: Query<&mut Visibility, With<LoadingScreen>>,
+    mut bars: Query<&mut Node, With<LoadingScreenBar>>,
+    mut texts: Query<&mut Text, With<LoadingScreenText>>,
+) {
+    if !progress.is_changed() {
+        return;
+    }
+    let shown = progress.is_active() && progress.loading_screen;
+    for mut visibility in &mut screens {
+        visibility.set_if_neq(if shown { Visibility::Visible } else { Visibility::Hidden });
+    }
+    if !shown {
+        return;
+    }
+    for mut node in &mut bars {
+        node.width = Val::Percent(progress.fraction() * 100.0);
+    }
+    let args = fluent_args![
+        "loaded" => progress.loaded + progress.failed,
+        "total" => progress.total,
+    ];
+    for mut text in &mut texts {
+        *text = Text::new(localization.text_with("ui-loading-progress", &args));
+    }
+}
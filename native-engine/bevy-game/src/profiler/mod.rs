@@ -0,0 +1,450 @@
+//! Per-system frame timings. A tracing layer times Bevy's `system` spans
+//! (present with the `trace` feature), and `SystemProfilerPlugin` folds them
+//! into a rolling timeline, checks budgets and exports CSV summaries.
+
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use bevy::log::BoxedLayer;
+use bevy::prelude::*;
+use serde::Deserialize;
+use ::tracing::field::{Field, Visit};
+use ::tracing::span::{Attributes, Id};
+use ::tracing::Subscriber;
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+#[cfg(feature = "inspector")]
+mod window;
+#[cfg(feature = "inspector")]
+pub use window::*;
+
+/// Time spent per system since the last drain, filled from any thread.
+#[derive(Resource, Clone, Default)]
+pub struct SystemTimingSink(Arc<Mutex<HashMap<String, Duration>>>);
+
+impl SystemTimingSink {
+    fn drain(&self) -> HashMap<String, Duration> {
+        self.0.lock().map(|mut timings| std::mem::take(&mut *timings)).unwrap_or_default()
+    }
+}
+
+struct SystemSpanName(String);
+struct SystemSpanStart(Instant);
+
+struct SystemNameVisitor(Option<String>);
+
+impl Visit for SystemNameVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "name" {
+            self.0 = Some(value.to_string());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "name" {
+            self.0 = Some(format!("{:?}", value).trim_matches('"').to_string());
+        }
+    }
+}
+
+struct SystemTimingLayer {
+    sink: SystemTimingSink,
+}
+
+impl<S> Layer<S> for SystemTimingLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        if attrs.metadata().name() != "system" {
+            return;
+        }
+        let mut visitor = SystemNameVisitor(None);
+        attrs.record(&mut visitor);
+        if let (Some(name), Some(span)) = (visitor.0, ctx.span(id)) {
+            span.extensions_mut().insert(SystemSpanName(name));
+        }
+    }
+
+    fn on_enter(&self, id: &Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            let mut extensions = span.extensions_mut();
+            if extensions.get_mut::<SystemSpanName>().is_some() {
+                extensions.replace(SystemSpanStart(Instant::now()));
+            }
+        }
+    }
+
+    fn on_exit(&self, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let extensions = span.extensions();
+        let (Some(name), Some(start)) = (extensions.get::<SystemSpanName>(), extensions.get::<SystemSpanStart>()) else {
+            return;
+        };
+        if let Ok(mut timings) = self.sink.0.lock() {
+            *timings.entry(name.0.clone()).or_default() += start.0.elapsed();
+        }
+    }
+}
+
+/// `LogPlugin::custom_layer` hook that installs the system timing layer.
+pub fn system_timing_layer(app: &mut App) -> Option<BoxedLayer> {
+    let sink = SystemTimingSink::default();
+    app.insert_resource(sink.clone());
+    Some(Box::new(SystemTimingLayer { sink }))
+}
+
+/// `content/profiler_budgets.toml`: `default_ms` plus per-system overrides
+/// matched by substring, e.g. `"terrain::update_terrain_chunks" = 4.0`.
+#[derive(Debug, Clone, Deserialize, Default)]
+struct BudgetFile {
+    default_ms: Option<f32>,
+    #[serde(default)]
+    budgets: HashMap<String, f32>,
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct SystemProfilerConfig {
+    /// Frames kept in the timeline.
+    pub history_frames: usize,
+    pub default_budget_ms: f32,
+    /// Substring of the system path and its budget; the longest match wins.
+    pub budgets: Vec<(String, f32)>,
+    pub budget_file: PathBuf,
+    pub export_directory: PathBuf,
+    /// How long an on-screen alert stays up after the last overrun.
+    pub alert_seconds: f32,
+}
+
+impl Default for SystemProfilerConfig {
+    fn default() -> Self {
+        Self {
+            history_frames: 300,
+            default_budget_ms: 2.0,
+            budgets: Vec::new(),
+            budget_file: PathBuf::from("content").join("profiler_budgets.toml"),
+            export_directory: PathBuf::from("profiling"),
+            alert_seconds: 3.0,
+        }
+    }
+}
+
+impl SystemProfilerConfig {
+    pub fn budget_for(&self, system: &str) -> f32 {
+        self.budgets
+            .iter()
+            .filter(|(pattern, _)| system.contains(pattern.as_str()))
+            .max_by_key(|(pattern, _)| pattern.len())
+            .map(|(_, budget)| *budget)
+            .unwrap_or(self.default_budget_ms)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FrameTimings {
+    pub frame: u32,
+    /// Milliseconds per system, slowest first.
+    pub systems: Vec<(String, f32)>,
+}
+
+impl FrameTimings {
+    pub fn total_ms(&self) -> f32 {
+        self.systems.iter().map(|(_, ms)| ms).sum()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SystemStats {
+    pub name: String,
+    pub last_ms: f32,
+    pub avg_ms: f32,
+    pub p95_ms: f32,
+    pub max_ms: f32,
+    pub budget_ms: f32,
+    /// Frames in the window where the system went over budget.
+    pub over_budget: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SystemStatsSort {
+    Name,
+    Last,
+    #[default]
+    Average,
+    P95,
+    Max,
+    OverBudget,
+}
+
+/// The last `history_frames` frames of system timings.
+#[derive(Resource, Debug, Default)]
+pub struct SystemTimeline {
+    pub frames: VecDeque<FrameTimings>,
+    /// Paused timelines keep their frames for inspection.
+    pub paused: bool,
+}
+
+impl SystemTimeline {
+    pub fn stats(&self, config: &SystemProfilerConfig) -> Vec<SystemStats> {
+        let mut samples: HashMap<&str, Vec<f32>> = HashMap::new();
+        for frame in &self.frames {
+            for (name, ms) in &frame.systems {
+                samples.entry(name.as_str()).or_default().push(*ms);
+            }
+        }
+        let last = self.frames.back();
+        samples
+            .into_iter()
+            .map(|(name, mut values)| {
+                let budget_ms = config.budget_for(name);
+                let over_budget = values.iter().filter(|ms| **ms > budget_ms).count();
+                let avg_ms = values.iter().sum::<f32>() / values.len() as f32;
+                values.sort_by(|a, b| a.total_cmp(b));
+                let p95_ms = values[((values.len() - 1) as f32 * 0.95).round() as usize];
+                SystemStats {
+                    name: name.to_string(),
+                    last_ms: last
+                        .and_then(|f| f.systems.iter().find(|(n, _)| n == name))
+                        .map_or(0.0, |(_, ms)| *ms),
+                    avg_ms,
+                    p95_ms,
+                    max_ms: values.last().copied().unwrap_or_default(),
+                    budget_ms,
+                    over_budget,
+                }
+            })
+            .collect()
+    }
+
+    pub fn sorted_stats(&self, config: &SystemProfilerConfig, sort: SystemStatsSort) -> Vec<SystemStats> {
+        let mut stats = self.stats(config);
+        match sort {
+            SystemStatsSort::Name => stats.sort_by(|a, b| a.name.cmp(&b.name)),
+            SystemStatsSort::Last => stats.sort_by(|a, b| b.last_ms.total_cmp(&a.last_ms)),
+            SystemStatsSort::Average => stats.sort_by(|a, b| b.avg_ms.total_cmp(&a.avg_ms)),
+            SystemStatsSort::P95 => stats.sort_by(|a, b| b.p95_ms.total_cmp(&a.p95_ms)),
+            SystemStatsSort::Max => stats.sort_by(|a, b| b.max_ms.total_cmp(&a.max_ms)),
+            SystemStatsSort::OverBudget => stats.sort_by_key(|s| std::cmp::Reverse(s.over_budget)),
+        }
+        stats
+    }
+}
+
+/// A system ran over its budget this frame.
+#[derive(Event, Debug, Clone)]
+pub struct BudgetExceededEvent {
+    pub system: String,
+    pub ms: f32,
+    pub budget_ms: f32,
+}
+
+#[derive(Debug, Clone)]
+pub struct BudgetAlert {
+    pub system: String,
+    pub worst_ms: f32,
+    pub budget_ms: f32,
+    pub expires_at: f64,
+}
+
+/// Alerts shown on screen, one per system, until they expire.
+#[derive(Resource, Debug, Default)]
+pub struct BudgetAlerts {
+    pub alerts: Vec<BudgetAlert>,
+}
+
+/// Writes `system_timings_<unix seconds>.csv` to the export directory.
+#[derive(Event, Debug, Clone, Copy, Default)]
+pub struct ExportSystemTimingsEvent;
+
+#[derive(Component)]
+struct BudgetAlertText;
+
+pub struct SystemProfilerPlugin;
+
+impl Plugin for SystemProfilerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SystemProfilerConfig>()
+            .init_resource::<SystemTimingSink>()
+            .init_resource::<SystemTimeline>()
+            .init_resource::<BudgetAlerts>()
+            .add_event::<BudgetExceededEvent>()
+            .add_event::<ExportSystemTimingsEvent>()
+            .add_systems(Startup, (load_budgets, setup_budget_alert_text))
+            .add_systems(Last, (collect_system_timings, update_budget_alerts, export_system_timings).chain());
+
+        #[cfg(feature = "inspector")]
+        app.add_plugins(SystemProfilerWindowPlugin);
+    }
+}
+
+fn load_budgets(mut config: ResMut<SystemProfilerConfig>) {
+    let Ok(text) = std::fs::read_to_string(&config.budget_file) else {
+        return;
+    };
+    match toml::from_str::<BudgetFile>(&text) {
+        Ok(file) => {
+            if let Some(default_ms) = file.default_ms {
+                config.default_budget_ms = default_ms;
+            }
+            config.budgets = file.budgets.into_iter().collect();
+            info!("Loaded {} system budgets from {:?}", config.budgets.len(), config.budget_file);
+        }
+        Err(e) => warn!("Invalid profiler budgets {:?}: {}", config.budget_file, e),
+    }
+}
+
+/// Short system name for display: the last two path segments.
+pub fn short_system_name(name: &str) -> &str {
+    let mut cut = name.len();
+    for _ in 0..2 {
+        match name[..cut].rfind("::") {
+            Some(index) => cut = index,
+            None => return name,
+        }
+    }
+    &name[cut + 2..]
+}
+
+fn collect_system_timings(
+    sink: Res<SystemTimingSink>,
+    config: Res<SystemProfilerConfig>,
+    mut timeline: ResMut<SystemTimeline>,
+    mut exceeded: EventWriter<BudgetExceededEvent>,
+    mut frame: Local<u32>,
+) {
+    let drained = sink.drain();
+    *frame += 1;
+    if timeline.paused || drained.is_empty() {
+        return;
+    }
+
+    let mut systems: Vec<(String, f32)> = drained
+        .into_iter()
+        .map(|(name, elapsed)| (name, elapsed.as_secs_f32() * 1000.0))
+        .collect();
+    systems.sort_by(|a, b| b.1.total_cmp(&a.1));
+    for (name, ms) in &systems {
+        let budget_ms = config.budget_for(name);
+        if *ms > budget_ms {
+            exceeded.send(BudgetExceededEvent {
+                system: name.clone(),
+                ms: *ms,
+                budget_ms,
+            });
+        }
+    }
+
+    timeline.frames.push_back(FrameTimings { frame: *frame, systems });
+    while timeline.frames.len() > config.history_frames {
+        timeline.frames.pop_front();
+    }
+}
+
+fn setup_budget_alert_text(mut commands: Commands) {
+    commands.spawn((
+        Text::new(""),
+        TextFont {
+            font_size: 14.0,
+            ..default()
+        },
+        TextColor(Color::srgb(1.0, 0.35, 0.3)),
+        Node {
+            position_type: PositionType::Absolute,
+            right: Val::Px(12.0),
+            top: Val::Px(12.0),
+            ..default()
+        },
+        BudgetAlertText,
+    ));
+}
+
+fn update_budget_alerts(
+    time: Res<Time>,
+    config: Res<SystemProfilerConfig>,
+    mut exceeded: EventReader<BudgetExceededEvent>,
+    mut alerts: ResMut<BudgetAlerts>,
+    mut text: Query<&mut Text, With<BudgetAlertText>>,
+) {
+    let now = time.elapsed_secs_f64();
+    let before = alerts.alerts.len();
+    let mut changed = false;
+    for event in exceeded.read() {
+        let expires_at = now + config.alert_seconds as f64;
+        match alerts.alerts.iter_mut().find(|a| a.system == event.system) {
+            Some(alert) => {
+                alert.worst_ms = alert.worst_ms.max(event.ms);
+                alert.expires_at = expires_at;
+            }
+            None => {
+                warn!("{} took {:.2} ms (budget {:.2} ms)", event.system, event.ms, event.budget_ms);
+                alerts.alerts.push(BudgetAlert {
+                    system: event.system.clone(),
+                    worst_ms: event.ms,
+                    budget_ms: event.budget_ms,
+                    expires_at,
+                });
+            }
+        }
+        changed = true;
+    }
+    alerts.alerts.retain(|a| a.expires_at > now);
+    if !changed && alerts.alerts.len() == before {
+        return;
+    }
+
+    alerts.alerts.sort_by(|a, b| (b.worst_ms / b.budget_ms).total_cmp(&(a.worst_ms / a.budget_ms)));
+    let lines: Vec<String> = alerts
+        .alerts
+        .iter()
+        .take(8)
+        .map(|a| format!("{}  {:.2} / {:.2} ms", short_system_name(&a.system), a.worst_ms, a.budget_ms))
+        .collect();
+    for mut text in text.iter_mut() {
+        text.0 = if lines.is_empty() {
+            String::new()
+        } else {
+            format!("Over budget\n{}", lines.join("\n"))
+        };
+    }
+}
+
+fn export_system_timings(
+    mut events: EventReader<ExportSystemTimingsEvent>,
+    config: Res<SystemProfilerConfig>,
+    timeline: Res<SystemTimeline>,
+) {
+    if events.read().count() == 0 {
+        return;
+    }
+    if let Err(e) = std::fs::create_dir_all(&config.export_directory) {
+        error!("Failed to create {:?}: {}", config.export_directory, e);
+        return;
+    }
+    let stamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let path = config.export_directory.join(format!("system_timings_{}.csv", stamp));
+
+    let mut csv = format!(
+        "# {} frames, build {}\nsystem,avg_ms,p95_ms,max_ms,budget_ms,over_budget_frames\n",
+        timeline.frames.len(),
+        env!("CARGO_PKG_VERSION")
+    );
+    for stats in timeline.sorted_stats(&config, SystemStatsSort::Name) {
+        csv.push_str(&format!(
+            "\"{}\",{:.4},{:.4},{:.4},{:.2},{}\n",
+            stats.name.replace('"', "\"\""), stats.avg_ms, stats.p95_ms, stats.max_ms, stats.budget_ms, stats.over_budget
+        ));
+    }
+    match std::fs::write(&path, csv) {
+        Ok(()) => info!("Exported system timings to {:?}", path),
+        Err(e) => error!("Failed to write {:?}: {}", path, e),
+    }
+}
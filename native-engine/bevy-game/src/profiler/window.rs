@@ -0,0 +1,244 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts, EguiPlugin};
+
+use super::{
+    short_system_name, ExportSystemTimingsEvent, SystemProfilerConfig, SystemStatsSort, SystemTimeline,
+};
+use crate::input_map::{Action, ActionState};
+use crate::memory::MemoryStats;
+
+const TIMELINE_HEIGHT: f32 = 120.0;
+/// Systems drawn as their own band in the timeline; the rest are lumped together.
+const TIMELINE_BANDS: usize = 6;
+
+#[derive(Resource, Debug, Default)]
+pub struct SystemProfilerWindow {
+    pub open: bool,
+    pub sort: SystemStatsSort,
+    pub filter: String,
+}
+
+pub struct SystemProfilerWindowPlugin;
+
+impl Plugin for SystemProfilerWindowPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugins(EguiPlugin);
+        }
+        app.init_resource::<SystemProfilerWindow>().add_systems(
+            Update,
+            (
+                toggle_system_profiler,
+                system_profiler_ui.run_if(|window: Res<SystemProfilerWindow>| window.open),
+            )
+                .chain(),
+        );
+    }
+}
+
+/// Shift+F12 by default (F12 alone is the log overlay).
+fn toggle_system_profiler(actions: Res<ActionState>, mut window: ResMut<SystemProfilerWindow>) {
+    if actions.just_pressed(Action::ToggleProfiler) {
+        window.open = !window.open;
+    }
+}
+
+fn band_color(index: usize) -> egui::Color32 {
+    const COLORS: [egui::Color32; TIMELINE_BANDS] = [
+        egui::Color32::from_rgb(230, 90, 80),
+        egui::Color32::from_rgb(240, 170, 60),
+        egui::Color32::from_rgb(220, 220, 90),
+        egui::Color32::from_rgb(110, 200, 120),
+        egui::Color32::from_rgb(90, 170, 230),
+        egui::Color32::from_rgb(170, 120, 230),
+    ];
+    COLORS.get(index).copied().unwrap_or(egui::Color32::from_gray(110))
+}
+
+fn system_profiler_ui(
+    mut contexts: EguiContexts,
+    mut window: ResMut<SystemProfilerWindow>,
+    mut timeline: ResMut<SystemTimeline>,
+    config: Res<SystemProfilerConfig>,
+    memory: Option<Res<MemoryStats>>,
+    mut export: EventWriter<ExportSystemTimingsEvent>,
+) {
+    let ctx = contexts.ctx_mut().clone();
+    let window = &mut *window;
+    let mut open = window.open;
+    let stats = timeline.sorted_stats(&config, window.sort);
+
+    egui::Window::new("System Profiler")
+        .open(&mut open)
+        .default_size([720.0, 520.0])
+        .show(&ctx, |ui| {
+            ui.horizontal(|ui| {
+                let label = if timeline.paused { "Resume" } else { "Pause" };
+                if ui.button(label).clicked() {
+                    timeline.paused = !timeline.paused;
+                }
+                if ui.button("Clear").clicked() {
+                    timeline.frames.clear();
+                }
+                if ui.add_enabled(!timeline.frames.is_empty(), egui::Button::new("Export CSV")).clicked() {
+                    export.send(ExportSystemTimingsEvent);
+                }
+                ui.label(format!("{} frames", timeline.frames.len()));
+                ui.separator();
+                ui.label("Filter");
+                ui.text_edit_singleline(&mut window.filter);
+            });
+
+            if let Some(memory) = &memory {
+                memory_section(ui, memory);
+            }
+
+            if timeline.frames.is_empty() {
+                ui.label("No system timings yet. Per-system spans need a build with --features trace.");
+                return;
+            }
+
+            // Stacked timeline: the slowest systems by average get their own band
+            let bands: Vec<String> = timeline
+                .sorted_stats(&config, SystemStatsSort::Average)
+                .into_iter()
+                .take(TIMELINE_BANDS)
+                .map(|s| s.name)
+                .collect();
+            let peak = timeline.frames.iter().map(|f| f.total_ms()).fold(1.0_f32, f32::max);
+            let (response, painter) =
+                ui.allocate_painter(egui::vec2(ui.available_width(), TIMELINE_HEIGHT), egui::Sense::hover());
+            let rect = response.rect;
+            painter.rect_filled(rect, 2.0, egui::Color32::from_gray(20));
+            let bar_width = rect.width() / config.history_frames.max(1) as f32;
+            let scale = rect.height() / peak;
+            for (column, frame) in timeline.frames.iter().enumerate() {
+                let x = rect.left() + column as f32 * bar_width;
+                let mut y = rect.bottom();
+                let mut rest = frame.total_ms();
+                for (band, name) in bands.iter().enumerate() {
+                    let Some((_, ms)) = frame.systems.iter().find(|(n, _)| n == name) else {
+                        continue;
+                    };
+                    rest -= ms;
+                    let top = y - ms * scale;
+                    painter.rect_filled(
+                        egui::Rect::from_min_max(egui::pos2(x, top), egui::pos2(x + bar_width.max(1.0), y)),
+                        0.0,
+                        band_color(band),
+                    );
+                    y = top;
+                }
+                painter.rect_filled(
+                    egui::Rect::from_min_max(egui::pos2(x, y - rest.max(0.0) * scale), egui::pos2(x + bar_width.max(1.0), y)),
+                    0.0,
+                    band_color(TIMELINE_BANDS),
+                );
+            }
+            painter.text(
+                rect.left_top() + egui::vec2(4.0, 2.0),
+                egui::Align2::LEFT_TOP,
+                format!("{:.2} ms peak", peak),
+                egui::FontId::proportional(11.0),
+                egui::Color32::LIGHT_GRAY,
+            );
+            ui.horizontal_wrapped(|ui| {
+                for (band, name) in bands.iter().enumerate() {
+                    ui.colored_label(band_color(band), short_system_name(name));
+                }
+                ui.colored_label(band_color(TIMELINE_BANDS), "other");
+            });
+            ui.separator();
+
+            // Worst offenders; click a header to sort by it
+            let filter = window.filter.to_lowercase();
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                egui::Grid::new("system_profiler_table").striped(true).show(ui, |ui| {
+                    for (label, sort) in [
+                        ("System", SystemStatsSort::Name),
+                        ("Last", SystemStatsSort::Last),
+                        ("Avg", SystemStatsSort::Average),
+                        ("P95", SystemStatsSort::P95),
+                        ("Max", SystemStatsSort::Max),
+                        ("Over budget", SystemStatsSort::OverBudget),
+                    ] {
+                        let text = if window.sort == sort { format!("{} ▼", label) } else { label.to_string() };
+                        if ui.selectable_label(window.sort == sort, text).clicked() {
+                            window.sort = sort;
+                        }
+                    }
+                    ui.label("Budget");
+                    ui.end_row();
+
+                    for stat in stats.iter().filter(|s| filter.is_empty() || s.name.to_lowercase().contains(&filter)) {
+                        let over = stat.p95_ms > stat.budget_ms;
+                        let color = if over { egui::Color32::from_rgb(230, 90, 80) } else { egui::Color32::LIGHT_GRAY };
+                        ui.label(short_system_name(&stat.name)).on_hover_text(&stat.name);
+                        ui.colored_label(color, format!("{:.3}", stat.last_ms));
+                        ui.colored_label(color, format!("{:.3}", stat.avg_ms));
+                        ui.colored_label(color, format!("{:.3}", stat.p95_ms));
+                        ui.colored_label(color, format!("{:.3}", stat.max_ms));
+                        ui.colored_label(color, stat.over_budget.to_string());
+                        ui.label(format!("{:.2}", stat.budget_ms));
+                        ui.end_row();
+                    }
+                });
+            });
+        });
+
+    window.open = open;
+}
+
+fn kb(bytes: usize) -> String {
+    format!("{:.1} KB", bytes as f32 / 1024.0)
+}
+
+/// Frame arena and entity pool usage against their high-water marks.
+fn memory_section(ui: &mut egui::Ui, memory: &MemoryStats) {
+    let arena = &memory.arena;
+    egui::CollapsingHeader::new(format!(
+        "Memory: arena {} / {}, {} pooled kinds",
+        kb(arena.used_bytes),
+        kb(arena.capacity_bytes),
+        memory.pools.len()
+    ))
+    .id_salt("profiler_memory")
+    .show(ui, |ui| {
+        let fill = arena.used_bytes as f32 / arena.capacity_bytes.max(1) as f32;
+        ui.add(egui::ProgressBar::new(fill.min(1.0)).text(format!(
+            "Frame arena {} of {} (peak {}, {} chunk{})",
+            kb(arena.used_bytes),
+            kb(arena.capacity_bytes),
+            kb(arena.high_water_bytes),
+            arena.chunks,
+            if arena.chunks == 1 { "" } else { "s" }
+        )));
+        ui.label(format!(
+            "Grew {} times, shrank {} times, {} frames over budget",
+            arena.grows, arena.shrinks, arena.over_budget_frames
+        ));
+
+        if memory.pools.is_empty() {
+            return;
+        }
+        egui::Grid::new("profiler_entity_pools").striped(true).show(ui, |ui| {
+            let headers = ["Pool", "In use", "Free", "Peak in use", "Peak free", "Hit rate", "Rejected", "Trimmed"];
+            for header in headers {
+                ui.strong(header);
+            }
+            ui.end_row();
+            for (kind, pool) in &memory.pools {
+                ui.label(kind);
+                ui.label(pool.in_use.to_string());
+                ui.label(pool.free.to_string());
+                ui.label(pool.high_water_in_use.to_string());
+                ui.label(pool.high_water_free.to_string());
+                ui.label(format!("{:.0}%", pool.hit_rate() * 100.0));
+                ui.label(pool.rejected.to_string());
+                ui.label(pool.trimmed.to_string());
+                ui.end_row();
+            }
+        });
+    });
+    ui.separator();
+}
@@ -0,0 +1,157 @@
+//! Personal bests and ghost replays. Each best is saved as
+//! `<records_directory>/<race id>.json` with its time, style, checkpoint
+//! splits and the rider's transform sampled through the run. Racing a
+//! course with a best spawns a [`RaceGhost`] that flies the best run
+//! alongside.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+use super::{Medal, RaceConfig, RaceState};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GhostSample {
+    /// Seconds into the run.
+    pub time: f32,
+    pub translation: [f32; 3],
+    pub rotation: [f32; 4],
+}
+
+impl GhostSample {
+    pub fn new(time: f32, transform: &GlobalTransform) -> Self {
+        let (_, rotation, translation) = transform.to_scale_rotation_translation();
+        Self { time, translation: translation.to_array(), rotation: rotation.to_array() }
+    }
+
+    fn transform(&self) -> Transform {
+        Transform::from_translation(Vec3::from(self.translation)).with_rotation(Quat::from_array(self.rotation))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RaceRecord {
+    pub race: String,
+    pub time: f32,
+    pub style: u32,
+    #[serde(default)]
+    pub medal: Option<Medal>,
+    /// Run time at each checkpoint.
+    #[serde(default)]
+    pub splits: Vec<f32>,
+    #[serde(default)]
+    pub samples: Vec<GhostSample>,
+}
+
+impl RaceRecord {
+    /// The run's pose `time` seconds in, between the nearest samples.
+    pub fn pose_at(&self, time: f32) -> Option<Transform> {
+        let next = self.samples.partition_point(|s| s.time <= time);
+        match (next.checked_sub(1).and_then(|i| self.samples.get(i)), self.samples.get(next)) {
+            (Some(a), Some(b)) => {
+                let t = ((time - a.time) / (b.time - a.time).max(f32::EPSILON)).clamp(0.0, 1.0);
+                let (a, b) = (a.transform(), b.transform());
+                Some(
+                    Transform::from_translation(a.translation.lerp(b.translation, t))
+                        .with_rotation(a.rotation.slerp(b.rotation, t)),
+                )
+            }
+            (Some(only), None) | (None, Some(only)) => Some(only.transform()),
+            (None, None) => None,
+        }
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct PersonalBests {
+    pub records: HashMap<String, RaceRecord>,
+}
+
+impl PersonalBests {
+    pub fn get(&self, race: &str) -> Option<&RaceRecord> {
+        self.records.get(race)
+    }
+
+    /// Keeps `record` if it beats the current best, saving it to
+    /// `directory`. Returns whether it did.
+    pub fn offer(&mut self, directory: &Path, record: RaceRecord) -> bool {
+        if self.records.get(&record.race).is_some_and(|best| best.time <= record.time) {
+            return false;
+        }
+        let path = directory.join(format!("{}.json", record.race));
+        let saved = std::fs::create_dir_all(directory)
+            .map_err(|e| e.to_string())
+            .and_then(|_| serde_json::to_string(&record).map_err(|e| e.to_string()))
+            .and_then(|text| std::fs::write(&path, text).map_err(|e| e.to_string()));
+        if let Err(e) = saved {
+            warn!("Failed to save race record {:?}: {}", path, e);
+        }
+        self.records.insert(record.race.clone(), record);
+        true
+    }
+}
+
+/// Replays the personal best of `race`.
+#[derive(Component, Debug, Clone)]
+pub struct RaceGhost {
+    pub race: String,
+}
+
+pub(super) struct GhostPlugin;
+
+impl Plugin for GhostPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PersonalBests>().add_systems(Startup, load_personal_bests);
+    }
+}
+
+fn load_personal_bests(config: Res<RaceConfig>, mut bests: ResMut<PersonalBests>) {
+    let Ok(entries) = std::fs::read_dir(&config.records_directory) else {
+        return;
+    };
+    for path in entries.flatten().map(|entry| entry.path()) {
+        if path.extension().and_then(|e| e.to_str()) != Some("json") {
+            continue;
+        }
+        let parsed = std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| serde_json::from_str::<RaceRecord>(&text).map_err(|e| e.to_string()));
+        match parsed {
+            Ok(record) => {
+                bests.records.insert(record.race.clone(), record);
+            }
+            Err(e) => warn!("Invalid race record {:?}: {}", path, e),
+        }
+    }
+
+    info!("Races: {} personal bests loaded", bests.records.len());
+}
+
+pub(super) fn play_ghost(
+    mut commands: Commands,
+    time: Res<Time>,
+    state: Res<RaceState>,
+    bests: Res<PersonalBests>,
+    mut ghosts: Query<(Entity, &RaceGhost, &mut Transform)>,
+) {
+    let run = state.run.as_ref();
+    let record = run.and_then(|run| bests.get(&run.race));
+    let mut shown = false;
+    for (entity, ghost, mut transform) in &mut ghosts {
+        match (run, record) {
+            (Some(run), Some(record)) if ghost.race == run.race && !shown => {
+                if let Some(pose) = record.pose_at(run.time(time.elapsed_secs())) {
+                    *transform = pose;
+                }
+                shown = true;
+            }
+            _ => commands.entity(entity).despawn_recursive(),
+        }
+    }
+    if let (false, Some(run), Some(record)) = (shown, run, record) {
+        if let Some(pose) = record.pose_at(run.time(time.elapsed_secs())) {
+            commands.spawn((RaceGhost { race: run.race.clone() }, pose, Name::new("Race Ghost")));
+        }
+    }
+}
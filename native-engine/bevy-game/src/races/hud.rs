@@ -0,0 +1,227 @@
+//! Race HUD: the countdown and clock, checkpoint count and split against
+//! the personal best, trick call-outs and the result, plus the checkpoint
+//! rings and the ghost's body in the world.
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use super::{
+    CheckpointDef, PersonalBests, RaceCourses, RaceDef, RaceFinishedEvent, RaceGhost, RaceState, RunPhase,
+    TrickPerformedEvent,
+};
+
+/// How long trick call-outs and the result stay up.
+const CALLOUT_SECONDS: f32 = 2.0;
+const RESULT_SECONDS: f32 = 8.0;
+
+#[derive(Component, Debug, Clone)]
+struct CheckpointRing {
+    index: usize,
+}
+
+#[derive(Resource, Debug, Default)]
+struct RaceHud {
+    /// Text and the elapsed time it hides at.
+    callouts: Vec<(String, f32)>,
+    result: Option<(RaceFinishedEvent, f32)>,
+}
+
+pub struct RaceHudPlugin;
+
+impl Plugin for RaceHudPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RaceHud>()
+            .add_systems(Update, (collect_callouts, race_hud_ui).chain())
+            .add_systems(Update, (show_checkpoint_rings, dress_ghosts));
+    }
+}
+
+fn format_time(seconds: f32) -> String {
+    let minutes = (seconds / 60.0).floor();
+    format!("{}:{:05.2}", minutes, seconds - minutes * 60.0)
+}
+
+fn collect_callouts(
+    time: Res<Time>,
+    mut hud: ResMut<RaceHud>,
+    mut tricks: EventReader<TrickPerformedEvent>,
+    mut finished: EventReader<RaceFinishedEvent>,
+) {
+    let now = time.elapsed_secs();
+    hud.callouts.retain(|(_, until)| *until > now);
+    for trick in tricks.read() {
+        let combo = if trick.combo > 1 { format!(" x{}", trick.combo) } else { String::new() };
+        hud.callouts.push((format!("{}{} +{}", trick.trick.label(), combo, trick.points), now + CALLOUT_SECONDS));
+    }
+    if let Some(result) = finished.read().last() {
+        hud.result = Some((result.clone(), now + RESULT_SECONDS));
+    }
+    if hud.result.as_ref().is_some_and(|(_, until)| *until <= now) {
+        hud.result = None;
+    }
+}
+
+fn race_hud_ui(
+    mut contexts: EguiContexts,
+    time: Res<Time>,
+    courses: Res<RaceCourses>,
+    state: Res<RaceState>,
+    bests: Res<PersonalBests>,
+    hud: Res<RaceHud>,
+) {
+    let run = state.run.as_ref().and_then(|run| courses.races.get(&run.race).map(|race| (run, race)));
+    if run.is_none() && hud.result.is_none() {
+        return;
+    }
+    let now = time.elapsed_secs();
+    let ctx = contexts.ctx_mut().clone();
+
+    egui::Area::new(egui::Id::new("race_hud"))
+        .anchor(egui::Align2::CENTER_TOP, [0.0, 24.0])
+        .show(&ctx, |ui| {
+            ui.vertical_centered(|ui| {
+                if let Some((run, race)) = run {
+                    let best = bests.get(&race.id);
+                    ui.label(egui::RichText::new(race.display_name()).strong());
+                    match run.phase {
+                        RunPhase::Countdown { go_at } => {
+                            ui.label(egui::RichText::new(format!("{}", (go_at - now).ceil().max(1.0))).size(40.0));
+                        }
+                        RunPhase::Running { .. } => {
+                            ui.label(egui::RichText::new(format_time(run.time(now))).monospace().size(28.0));
+                        }
+                    }
+                    ui.label(format!("Checkpoint {}/{}", run.next_checkpoint, race.checkpoint_count()));
+                    // Split against the best at the last checkpoint passed
+                    let last = run.splits.len().checked_sub(1);
+                    let split = last.and_then(|i| Some((run.splits[i], *best?.splits.get(i)?)));
+                    if let Some((split, best_split)) = split {
+                        let delta = split - best_split;
+                        let (ahead, behind) = (egui::Color32::from_rgb(90, 220, 110), egui::Color32::from_rgb(230, 90, 80));
+                        let color = if delta <= 0.0 { ahead } else { behind };
+                        ui.colored_label(color, format!("{:+.2}", delta));
+                    }
+                    ui.label(format!("Style {}", run.style));
+                    if let Some(best) = best {
+                        ui.label(format!("Best {}", format_time(best.time)));
+                    }
+                    ui.label(format!(
+                        "Gold {}  Silver {}  Bronze {}",
+                        format_time(race.medals.gold),
+                        format_time(race.medals.silver),
+                        format_time(race.medals.bronze)
+                    ));
+                }
+                for (text, _) in &hud.callouts {
+                    ui.label(egui::RichText::new(text).color(egui::Color32::from_rgb(240, 200, 80)));
+                }
+                if let Some((result, _)) = &hud.result {
+                    let mut text = format!("Finished {}  Style {}", format_time(result.time), result.style);
+                    if let Some(medal) = result.medal {
+                        text.push_str(&format!("  {}", medal.label()));
+                    }
+                    if result.personal_best {
+                        text.push_str("  New best!");
+                    }
+                    ui.label(egui::RichText::new(text).strong().size(20.0));
+                }
+            });
+        });
+}
+
+fn ring_color(checkpoint: &CheckpointDef, next: bool) -> Color {
+    match (next, checkpoint.boost) {
+        (true, _) => Color::srgba(1.0, 0.8, 0.2, 0.9),
+        (false, true) => Color::srgba(0.3, 0.8, 1.0, 0.5),
+        (false, false) => Color::srgba(1.0, 1.0, 1.0, 0.35),
+    }
+}
+
+/// Ring facing along the course, from the checkpoint before it.
+fn ring_transform(race: &RaceDef, index: usize) -> Transform {
+    let position = Vec3::from(race.checkpoints[index].position);
+    let from = match index {
+        0 if race.laps > 1 => Vec3::from(race.checkpoints[race.checkpoints.len() - 1].position),
+        0 => Vec3::from(race.start),
+        _ => Vec3::from(race.checkpoints[index - 1].position),
+    };
+    let direction = (position - from).normalize_or(Vec3::Z);
+    Transform::from_translation(position).with_rotation(Quat::from_rotation_arc(Vec3::Y, direction))
+}
+
+fn show_checkpoint_rings(
+    mut commands: Commands,
+    courses: Res<RaceCourses>,
+    state: Res<RaceState>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    rings: Query<(Entity, &CheckpointRing, &MeshMaterial3d<StandardMaterial>)>,
+    mut shown: Local<Option<(String, usize)>>,
+) {
+    let current = state.run.as_ref().map(|run| (run.race.clone(), run.next_checkpoint));
+    if *shown == current {
+        return;
+    }
+    let race = current.as_ref().and_then(|(id, _)| courses.races.get(id));
+    let next = current.as_ref().map(|(_, next)| *next);
+    let same_race = shown.as_ref().map(|(id, _)| id) == current.as_ref().map(|(id, _)| id);
+    *shown = current.clone();
+
+    let Some(race) = race else {
+        for (entity, _, _) in &rings {
+            commands.entity(entity).despawn_recursive();
+        }
+        return;
+    };
+    let is_next = |index: usize| {
+        next.is_some_and(|next| next < race.checkpoint_count() && next % race.checkpoints.len() == index)
+    };
+
+    if same_race {
+        for (_, ring, material) in &rings {
+            let checkpoint = race.checkpoints.get(ring.index);
+            if let (Some(material), Some(checkpoint)) = (materials.get_mut(&material.0), checkpoint) {
+                material.base_color = ring_color(checkpoint, is_next(ring.index));
+            }
+        }
+        return;
+    }
+
+    for (entity, _, _) in &rings {
+        commands.entity(entity).despawn_recursive();
+    }
+    for (index, checkpoint) in race.checkpoints.iter().enumerate() {
+        let radius = checkpoint.radius.max(1.0);
+        commands.spawn((
+            CheckpointRing { index },
+            Mesh3d(meshes.add(Torus::new(radius - 0.4, radius))),
+            MeshMaterial3d(materials.add(StandardMaterial {
+                base_color: ring_color(checkpoint, is_next(index)),
+                alpha_mode: AlphaMode::Blend,
+                unlit: true,
+                ..default()
+            })),
+            ring_transform(race, index),
+            Name::new(format!("Race Checkpoint {}", index + 1)),
+        ));
+    }
+}
+
+fn dress_ghosts(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    ghosts: Query<Entity, Added<RaceGhost>>,
+) {
+    for ghost in &ghosts {
+        commands.entity(ghost).insert((
+            Mesh3d(meshes.add(Capsule3d::new(0.4, 1.6))),
+            MeshMaterial3d(materials.add(StandardMaterial {
+                base_color: Color::srgba(0.5, 0.8, 1.0, 0.35),
+                alpha_mode: AlphaMode::Blend,
+                unlit: true,
+                ..default()
+            })),
+        ));
+    }
+}
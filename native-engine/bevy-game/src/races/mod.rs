@@ -0,0 +1,519 @@
+//! Skyriding races: checkpoint rings flown in order against the clock.
+//! Courses live in `content/races/*.toml`:
+//!
+//! ```toml
+//! [[race]]
+//! id = "highmoor_loop"
+//! name = "Highmoor Loop"
+//! start = [120.0, 64.0, -40.0]
+//! medals = { gold = 48.0, silver = 55.0, bronze = 65.0 }
+//!
+//! [[race.checkpoint]]
+//! position = [180.0, 80.0, -10.0]
+//! radius = 10.0
+//!
+//! [[race.checkpoint]]
+//! position = [240.0, 50.0, 60.0]
+//! boost = true
+//! ```
+//!
+//! A run starts near the course start with [`StartRaceRequest`] or
+//! `race <id>`, counts down, and ends at the last checkpoint. Tricks flown
+//! on the way add style points ([`tricks`]), personal bests are kept with a
+//! ghost of the run ([`ghost`]), and finished runs wait in
+//! [`LeaderboardOutbox`] for the network layer to submit.
+
+pub mod ghost;
+pub mod hud;
+pub mod tricks;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::console::{ArgCompletion, ConsoleAppExt, ConsoleCommand, ConsoleCommandEvent, ConsoleOutputEvent};
+use crate::content_packs::ContentPacks;
+use crate::Player;
+
+pub use ghost::{GhostSample, PersonalBests, RaceGhost, RaceRecord};
+pub use hud::RaceHudPlugin;
+pub use tricks::{Trick, TrickConfig, TrickPerformedEvent};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckpointDef {
+    pub position: [f32; 3],
+    #[serde(default = "default_radius")]
+    pub radius: f32,
+    /// Flying through gives a speed boost and style points.
+    #[serde(default)]
+    pub boost: bool,
+}
+
+fn default_radius() -> f32 {
+    8.0
+}
+
+/// Finish times (seconds) at or under which each medal is awarded.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct MedalTimes {
+    pub gold: f32,
+    pub silver: f32,
+    pub bronze: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RaceDef {
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    pub start: [f32; 3],
+    #[serde(default = "default_laps")]
+    pub laps: u32,
+    pub medals: MedalTimes,
+    /// Server leaderboard id; `race_<id>` when unset.
+    #[serde(default)]
+    pub leaderboard: Option<String>,
+    #[serde(default, rename = "checkpoint")]
+    pub checkpoints: Vec<CheckpointDef>,
+}
+
+fn default_laps() -> u32 {
+    1
+}
+
+impl RaceDef {
+    pub fn display_name(&self) -> &str {
+        if self.name.is_empty() {
+            &self.id
+        } else {
+            &self.name
+        }
+    }
+
+    /// Checkpoints over all laps.
+    pub fn checkpoint_count(&self) -> usize {
+        self.checkpoints.len() * self.laps.max(1) as usize
+    }
+
+    /// The `index`th checkpoint of the run, counting across laps.
+    pub fn checkpoint(&self, index: usize) -> Option<&CheckpointDef> {
+        if self.checkpoints.is_empty() || index >= self.checkpoint_count() {
+            return None;
+        }
+        self.checkpoints.get(index % self.checkpoints.len())
+    }
+
+    pub fn medal(&self, time: f32) -> Option<Medal> {
+        if time <= self.medals.gold {
+            Some(Medal::Gold)
+        } else if time <= self.medals.silver {
+            Some(Medal::Silver)
+        } else if time <= self.medals.bronze {
+            Some(Medal::Bronze)
+        } else {
+            None
+        }
+    }
+
+    pub fn leaderboard_id(&self) -> String {
+        self.leaderboard.clone().unwrap_or_else(|| format!("race_{}", self.id))
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RaceContentFile {
+    #[serde(default, rename = "race")]
+    pub races: Vec<RaceDef>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Medal {
+    Gold,
+    Silver,
+    Bronze,
+}
+
+impl Medal {
+    pub fn label(self) -> &'static str {
+        match self {
+            Medal::Gold => "Gold",
+            Medal::Silver => "Silver",
+            Medal::Bronze => "Bronze",
+        }
+    }
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct RaceConfig {
+    pub content_directory: PathBuf,
+    /// Personal bests and their ghosts, one JSON file per race.
+    pub records_directory: PathBuf,
+    /// How close to the course start a run may begin.
+    pub start_distance: f32,
+    pub countdown_seconds: f32,
+    /// Ghost samples per second.
+    pub ghost_sample_rate: f32,
+    pub boost_points: u32,
+}
+
+impl Default for RaceConfig {
+    fn default() -> Self {
+        Self {
+            content_directory: PathBuf::from("content").join("races"),
+            records_directory: PathBuf::from("race_records"),
+            start_distance: 30.0,
+            countdown_seconds: 3.0,
+            ghost_sample_rate: 10.0,
+            boost_points: 50,
+        }
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct RaceCourses {
+    pub races: HashMap<String, RaceDef>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RunPhase {
+    /// Counting down to the given elapsed time.
+    Countdown { go_at: f32 },
+    /// Timed from the given elapsed time.
+    Running { started_at: f32 },
+}
+
+#[derive(Debug, Clone)]
+pub struct ActiveRun {
+    pub race: String,
+    pub rider: Entity,
+    pub phase: RunPhase,
+    /// Index of the next checkpoint, counting across laps.
+    pub next_checkpoint: usize,
+    /// Run time at each checkpoint passed.
+    pub splits: Vec<f32>,
+    pub style: u32,
+    samples: Vec<GhostSample>,
+    last_position: Option<Vec3>,
+}
+
+impl ActiveRun {
+    /// Seconds since the start signal; zero during the countdown.
+    pub fn time(&self, now: f32) -> f32 {
+        match self.phase {
+            RunPhase::Countdown { .. } => 0.0,
+            RunPhase::Running { started_at } => (now - started_at).max(0.0),
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        matches!(self.phase, RunPhase::Running { .. })
+    }
+}
+
+/// The local player's run, if any.
+#[derive(Resource, Debug, Default)]
+pub struct RaceState {
+    pub run: Option<ActiveRun>,
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct StartRaceRequest {
+    pub race: String,
+}
+
+#[derive(Event, Debug, Clone, Default)]
+pub struct AbandonRaceRequest;
+
+#[derive(Event, Debug, Clone)]
+pub struct CheckpointReachedEvent {
+    pub race: String,
+    pub index: usize,
+    pub time: f32,
+}
+
+/// The mount's skyriding physics applies the surge.
+#[derive(Event, Debug, Clone)]
+pub struct RaceBoostEvent {
+    pub rider: Entity,
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct RaceFinishedEvent {
+    pub race: String,
+    pub rider: Entity,
+    pub time: f32,
+    pub style: u32,
+    pub medal: Option<Medal>,
+    pub personal_best: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LeaderboardSubmission {
+    pub leaderboard: String,
+    /// Finish time in milliseconds; lower is better.
+    pub score: i64,
+    /// Style points, the tie breaker.
+    pub subscore: i64,
+}
+
+/// Finished runs to submit, in order.
+#[derive(Resource, Debug, Default)]
+pub struct LeaderboardOutbox {
+    pending: Vec<LeaderboardSubmission>,
+}
+
+impl LeaderboardOutbox {
+    pub fn drain(&mut self) -> Vec<LeaderboardSubmission> {
+        std::mem::take(&mut self.pending)
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+pub struct RacePlugin;
+
+impl Plugin for RacePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RaceConfig>()
+            .init_resource::<RaceCourses>()
+            .init_resource::<RaceState>()
+            .init_resource::<LeaderboardOutbox>()
+            .add_event::<StartRaceRequest>()
+            .add_event::<AbandonRaceRequest>()
+            .add_event::<CheckpointReachedEvent>()
+            .add_event::<RaceBoostEvent>()
+            .add_event::<RaceFinishedEvent>()
+            .add_console_command(
+                ConsoleCommand::new("race", "Starts a skyriding race near you, or abandons the current one")
+                    .usage("<race id>|abandon|list")
+                    .complete(vec![ArgCompletion::Values(vec!["abandon".to_string(), "list".to_string()])]),
+            )
+            .add_plugins((tricks::TrickPlugin, ghost::GhostPlugin))
+            .add_systems(Startup, load_races)
+            .add_systems(
+                Update,
+                (
+                    handle_race_commands,
+                    start_races,
+                    tricks::detect_tricks,
+                    advance_runs,
+                    ghost::play_ghost,
+                )
+                    .chain(),
+            );
+    }
+}
+
+fn load_races(config: Res<RaceConfig>, packs: Res<ContentPacks>, mut courses: ResMut<RaceCourses>) {
+    for path in packs.files(&config.content_directory, "toml") {
+        let parsed = std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| toml::from_str::<RaceContentFile>(&text).map_err(|e| e.to_string()));
+
+        match parsed {
+            Ok(file) => {
+                for race in file.races {
+                    if race.checkpoints.is_empty() {
+                        warn!("Race '{}' in {:?} has no checkpoints", race.id, path);
+                        continue;
+                    }
+                    courses.races.insert(race.id.clone(), race);
+                }
+            }
+            Err(e) => warn!("Invalid race file {:?}: {}", path, e),
+        }
+    }
+
+    info!("Races: {} courses loaded", courses.races.len());
+}
+
+fn handle_race_commands(
+    courses: Res<RaceCourses>,
+    mut events: EventReader<ConsoleCommandEvent>,
+    mut output: EventWriter<ConsoleOutputEvent>,
+    mut starts: EventWriter<StartRaceRequest>,
+    mut abandons: EventWriter<AbandonRaceRequest>,
+) {
+    for event in events.read() {
+        if event.name != "race" {
+            continue;
+        }
+        let result: Result<String, String> = match event.args.first().map(String::as_str) {
+            None => Err("expected a race id, abandon or list".to_string()),
+            Some("abandon") => {
+                abandons.send(AbandonRaceRequest);
+                Ok("Race abandoned".to_string())
+            }
+            Some("list") => {
+                let mut ids: Vec<&str> = courses.races.keys().map(String::as_str).collect();
+                ids.sort_unstable();
+                Ok(if ids.is_empty() { "No races loaded".to_string() } else { ids.join(", ") })
+            }
+            Some(id) if courses.races.contains_key(id) => {
+                starts.send(StartRaceRequest { race: id.to_string() });
+                Ok(format!("Starting {}", id))
+            }
+            Some(other) => Err(format!("unknown race '{}'", other)),
+        };
+        output.send(match result {
+            Ok(text) => event.reply(text),
+            Err(text) => event.error(format!("{}: {}", event.name, text)),
+        });
+    }
+}
+
+fn start_races(
+    time: Res<Time>,
+    config: Res<RaceConfig>,
+    courses: Res<RaceCourses>,
+    mut state: ResMut<RaceState>,
+    mut starts: EventReader<StartRaceRequest>,
+    mut abandons: EventReader<AbandonRaceRequest>,
+    players: Query<(Entity, &GlobalTransform), With<Player>>,
+) {
+    if abandons.read().count() > 0 {
+        if let Some(run) = state.run.take() {
+            info!("Race '{}' abandoned", run.race);
+        }
+    }
+    let Some(request) = starts.read().last() else {
+        return;
+    };
+    let Some(race) = courses.races.get(&request.race) else {
+        warn!("Unknown race '{}'", request.race);
+        return;
+    };
+    let Ok((rider, transform)) = players.get_single() else {
+        return;
+    };
+    let distance = transform.translation().distance(Vec3::from(race.start));
+    if distance > config.start_distance {
+        warn!("Too far from the start of '{}' ({:.0} m)", race.id, distance);
+        return;
+    }
+
+    state.run = Some(ActiveRun {
+        race: race.id.clone(),
+        rider,
+        phase: RunPhase::Countdown { go_at: time.elapsed_secs() + config.countdown_seconds },
+        next_checkpoint: 0,
+        splits: Vec::new(),
+        style: 0,
+        samples: Vec::new(),
+        last_position: None,
+    });
+    info!("Race '{}' starting", race.id);
+}
+
+/// Closest distance from `point` to the segment `a`..`b`, so a fast rider
+/// can't skip through a ring between frames.
+fn segment_distance(a: Vec3, b: Vec3, point: Vec3) -> f32 {
+    let ab = b - a;
+    let length_squared = ab.length_squared();
+    let t = if length_squared > f32::EPSILON { ((point - a).dot(ab) / length_squared).clamp(0.0, 1.0) } else { 0.0 };
+    (a + ab * t).distance(point)
+}
+
+fn advance_runs(
+    time: Res<Time>,
+    config: Res<RaceConfig>,
+    courses: Res<RaceCourses>,
+    mut state: ResMut<RaceState>,
+    mut bests: ResMut<PersonalBests>,
+    mut outbox: ResMut<LeaderboardOutbox>,
+    mut tricks: EventReader<TrickPerformedEvent>,
+    mut reached: EventWriter<CheckpointReachedEvent>,
+    mut boosts: EventWriter<RaceBoostEvent>,
+    mut finished: EventWriter<RaceFinishedEvent>,
+    riders: Query<&GlobalTransform>,
+) {
+    let now = time.elapsed_secs();
+    let Some(run) = state.run.as_mut() else {
+        tricks.clear();
+        return;
+    };
+    let (Some(race), Ok(transform)) = (courses.races.get(&run.race), riders.get(run.rider)) else {
+        state.run = None;
+        return;
+    };
+    if let RunPhase::Countdown { go_at } = run.phase {
+        if now >= go_at {
+            run.phase = RunPhase::Running { started_at: go_at };
+        } else {
+            return;
+        }
+    }
+
+    for trick in tricks.read() {
+        if trick.rider == run.rider {
+            run.style += trick.points;
+        }
+    }
+
+    let position = transform.translation();
+    let elapsed = run.time(now);
+    let interval = 1.0 / config.ghost_sample_rate.max(1.0);
+    if run.samples.last().is_none_or(|s| elapsed - s.time >= interval) {
+        run.samples.push(GhostSample::new(elapsed, transform));
+    }
+
+    let previous = run.last_position.replace(position).unwrap_or(position);
+    while let Some(checkpoint) = race.checkpoint(run.next_checkpoint) {
+        if segment_distance(previous, position, Vec3::from(checkpoint.position)) > checkpoint.radius {
+            break;
+        }
+        if checkpoint.boost {
+            run.style += config.boost_points;
+            boosts.send(RaceBoostEvent { rider: run.rider });
+        }
+        run.splits.push(elapsed);
+        reached.send(CheckpointReachedEvent { race: race.id.clone(), index: run.next_checkpoint, time: elapsed });
+        run.next_checkpoint += 1;
+    }
+    if run.next_checkpoint < race.checkpoint_count() {
+        return;
+    }
+
+    let Some(run) = state.run.take() else {
+        return;
+    };
+    let medal = race.medal(elapsed);
+    let record = RaceRecord {
+        race: race.id.clone(),
+        time: elapsed,
+        style: run.style,
+        medal,
+        splits: run.splits,
+        samples: run.samples,
+    };
+    let personal_best = bests.offer(&config.records_directory, record);
+    outbox.pending.push(LeaderboardSubmission {
+        leaderboard: race.leaderboard_id(),
+        score: (elapsed * 1000.0).round() as i64,
+        subscore: run.style as i64,
+    });
+    info!(
+        "Race '{}' finished in {:.2}s, {} style{}{}",
+        race.id,
+        elapsed,
+        run.style,
+        medal.map_or(String::new(), |m| format!(", {}", m.label())),
+        if personal_best { ", personal best" } else { "" }
+    );
+    finished.send(RaceFinishedEvent {
+        race: race.id.clone(),
+        rider: run.rider,
+        time: elapsed,
+        style: run.style,
+        medal,
+        personal_best,
+    });
+}
@@ -0,0 +1,238 @@
+//! Style scoring for race runs. Barrel rolls and loops are read from the
+//! rider's rotation, dives and skims from its motion, and surges from
+//! sudden speed gains (the mount's surge abilities). Tricks landed within
+//! `combo_window` of each other multiply their points.
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::f32::consts::TAU;
+
+use super::RaceState;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Trick {
+    BarrelRoll,
+    Loop,
+    /// A long steep dive, scored on pulling out.
+    Dive,
+    /// Fast, low flight over the ground.
+    Skim,
+    Surge,
+}
+
+impl Trick {
+    pub fn label(self) -> &'static str {
+        match self {
+            Trick::BarrelRoll => "Barrel Roll",
+            Trick::Loop => "Loop",
+            Trick::Dive => "Dive",
+            Trick::Skim => "Skim",
+            Trick::Surge => "Surge",
+        }
+    }
+
+    pub fn points(self) -> u32 {
+        match self {
+            Trick::BarrelRoll => 100,
+            Trick::Loop => 150,
+            Trick::Dive => 75,
+            Trick::Skim => 120,
+            Trick::Surge => 40,
+        }
+    }
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct TrickConfig {
+    /// A full roll must be completed within this many seconds.
+    pub roll_window: f32,
+    pub loop_window: f32,
+    /// Downward speed (m/s) that counts as diving.
+    pub dive_speed: f32,
+    pub dive_seconds: f32,
+    /// Height above ground that counts as skimming.
+    pub skim_height: f32,
+    pub skim_speed: f32,
+    pub skim_seconds: f32,
+    /// Speed gained (m/s) within `surge_seconds` that counts as a surge.
+    pub surge_gain: f32,
+    pub surge_seconds: f32,
+    pub combo_window: f32,
+    pub max_combo: u32,
+}
+
+impl Default for TrickConfig {
+    fn default() -> Self {
+        Self {
+            roll_window: 2.5,
+            loop_window: 3.5,
+            dive_speed: 30.0,
+            dive_seconds: 1.5,
+            skim_height: 4.0,
+            skim_speed: 20.0,
+            skim_seconds: 1.0,
+            surge_gain: 10.0,
+            surge_seconds: 0.3,
+            combo_window: 3.0,
+            max_combo: 5,
+        }
+    }
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct TrickPerformedEvent {
+    pub rider: Entity,
+    pub trick: Trick,
+    /// Points after the combo multiplier.
+    pub points: u32,
+    pub combo: u32,
+}
+
+/// Rotation turned in one direction since `started`.
+#[derive(Debug, Default)]
+struct Spin {
+    angle: f32,
+    started: f32,
+}
+
+impl Spin {
+    /// Adds this frame's turn; true once a full turn completes in the window.
+    fn turn(&mut self, delta: f32, now: f32, window: f32) -> bool {
+        if now - self.started > window || delta * self.angle < 0.0 {
+            self.angle = 0.0;
+            self.started = now;
+        }
+        self.angle += delta;
+        if self.angle.abs() < TAU {
+            return false;
+        }
+        self.angle = 0.0;
+        self.started = now;
+        true
+    }
+}
+
+/// Motion history of the racing rider.
+#[derive(Resource, Debug, Default)]
+pub(super) struct TrickTracker {
+    rider: Option<Entity>,
+    rotation: Quat,
+    position: Vec3,
+    roll: Spin,
+    pitch: Spin,
+    dive_time: f32,
+    skim_time: f32,
+    skimmed: bool,
+    /// `(elapsed, speed)` over the last `surge_seconds`.
+    speeds: VecDeque<(f32, f32)>,
+    combo: u32,
+    last_trick_at: f32,
+}
+
+pub(super) struct TrickPlugin;
+
+impl Plugin for TrickPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TrickConfig>()
+            .init_resource::<TrickTracker>()
+            .add_event::<TrickPerformedEvent>();
+    }
+}
+
+pub(super) fn detect_tricks(
+    time: Res<Time>,
+    config: Res<TrickConfig>,
+    state: Res<RaceState>,
+    mut tracker: ResMut<TrickTracker>,
+    rapier_context: ReadRapierContext,
+    riders: Query<&GlobalTransform>,
+    mut performed: EventWriter<TrickPerformedEvent>,
+) {
+    let Some(run) = state.run.as_ref().filter(|run| run.is_running()) else {
+        tracker.rider = None;
+        return;
+    };
+    let Ok(transform) = riders.get(run.rider) else {
+        return;
+    };
+    let (_, rotation, position) = transform.to_scale_rotation_translation();
+    let now = time.elapsed_secs();
+    let dt = time.delta_secs();
+    if tracker.rider != Some(run.rider) || dt <= 0.0 {
+        *tracker = TrickTracker {
+            rider: Some(run.rider),
+            rotation,
+            position,
+            ..default()
+        };
+        return;
+    }
+
+    let mut landed = Vec::new();
+
+    // Turn this frame in the rider's own frame: x pitches, z rolls
+    let turn = (tracker.rotation.inverse() * rotation).to_scaled_axis();
+    if tracker.roll.turn(turn.z, now, config.roll_window) {
+        landed.push(Trick::BarrelRoll);
+    }
+    if tracker.pitch.turn(turn.x, now, config.loop_window) {
+        landed.push(Trick::Loop);
+    }
+
+    let velocity = (position - tracker.position) / dt;
+    if velocity.y <= -config.dive_speed {
+        tracker.dive_time += dt;
+    } else {
+        if tracker.dive_time >= config.dive_seconds {
+            landed.push(Trick::Dive);
+        }
+        tracker.dive_time = 0.0;
+    }
+
+    let ground = rapier_context.single().ok().and_then(|context| {
+        let filter = QueryFilter::default().exclude_sensors().exclude_collider(run.rider);
+        context.cast_ray(position, Vec3::NEG_Y, config.skim_height, true, filter)
+    });
+    if ground.is_some() && velocity.xz().length() >= config.skim_speed {
+        tracker.skim_time += dt;
+        if tracker.skim_time >= config.skim_seconds && !tracker.skimmed {
+            tracker.skimmed = true;
+            landed.push(Trick::Skim);
+        }
+    } else {
+        tracker.skim_time = 0.0;
+        tracker.skimmed = false;
+    }
+
+    let speed = velocity.length();
+    while tracker.speeds.front().is_some_and(|(at, _)| now - at > config.surge_seconds) {
+        tracker.speeds.pop_front();
+    }
+    let slowest = tracker.speeds.iter().map(|(_, s)| *s).fold(speed, f32::min);
+    if speed - slowest >= config.surge_gain {
+        tracker.speeds.clear();
+        landed.push(Trick::Surge);
+    }
+    tracker.speeds.push_back((now, speed));
+
+    tracker.rotation = rotation;
+    tracker.position = position;
+
+    for trick in landed {
+        tracker.combo = if now - tracker.last_trick_at <= config.combo_window {
+            (tracker.combo + 1).min(config.max_combo.max(1))
+        } else {
+            1
+        };
+        tracker.last_trick_at = now;
+        performed.send(TrickPerformedEvent {
+            rider: run.rider,
+            trick,
+            points: trick.points() * tracker.combo,
+            combo: tracker.combo,
+        });
+    }
+}
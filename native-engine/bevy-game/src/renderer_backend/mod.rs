@@ -0,0 +1,129 @@
+//! One API for whichever renderer the game is running: Atom, the O3DE
+//! renderer behind the `atom` feature, or Bevy's own wgpu renderer.
+//!
+//! [`RendererBackendPlugin`] picks the backend at startup from
+//! `game_settings.toml` and what the build has, lets it add its plugins,
+//! and keeps it in [`ActiveRenderer`]. Gameplay and settings code ask that
+//! resource instead of branching on the `atom` feature themselves.
+
+use bevy::prelude::*;
+
+use crate::frame_graph::FrameGraph;
+use crate::game_settings::{GameSettings, RendererChoice};
+
+#[cfg(feature = "atom")]
+mod atom;
+mod wgpu;
+
+#[cfg(feature = "atom")]
+pub use atom::AtomBackend;
+pub use wgpu::WgpuBackend;
+
+/// What the game needs from a renderer.
+pub trait RendererBackend: Send + Sync + 'static {
+    /// Which renderer this is, in `game_settings.toml` terms.
+    fn kind(&self) -> RendererChoice;
+
+    fn name(&self) -> &'static str;
+
+    /// Adds the renderer's plugins and startup checks. Called once, after
+    /// the first [`RendererBackend::configure`].
+    fn init(&mut self, app: &mut App);
+
+    /// Applies the graphics settings; called again whenever [`GameSettings`]
+    /// changes.
+    fn configure(&mut self, settings: &GameSettings, world: &mut World);
+
+    /// Adds whatever copies the scene from the game world to the renderer.
+    fn extract(&self, app: &mut App);
+
+    fn stats(&self, world: &World) -> RendererStats;
+
+    /// The passes, resources and barriers of a recent frame, if the
+    /// renderer reports them. Asking may start the reporting, so the first
+    /// call can come back empty.
+    fn frame_graph(&self, world: &World) -> Option<FrameGraph>;
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RendererStats {
+    /// What the renderer says it's running on.
+    pub backend: String,
+    /// Whether it's drawing frames: for Atom, the C++ renderer rather than
+    /// its wgpu stand-in.
+    pub active: bool,
+    pub frame_count: u64,
+}
+
+/// The backend the game started with.
+#[derive(Resource)]
+pub struct ActiveRenderer(Box<dyn RendererBackend>);
+
+impl ActiveRenderer {
+    pub fn kind(&self) -> RendererChoice {
+        self.0.kind()
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.0.name()
+    }
+
+    pub fn stats(&self, world: &World) -> RendererStats {
+        self.0.stats(world)
+    }
+
+    pub fn frame_graph(&self, world: &World) -> Option<FrameGraph> {
+        self.0.frame_graph(world)
+    }
+}
+
+/// The backend `settings` ask for, out of those this build has.
+pub fn select(settings: &GameSettings) -> Box<dyn RendererBackend> {
+    #[cfg(feature = "atom")]
+    {
+        if settings.use_atom() {
+            return Box::new(AtomBackend::default());
+        }
+        warn!("Atom renderer turned off in {} - using wgpu", crate::game_settings::GAME_SETTINGS_FILE);
+    }
+
+    #[cfg(not(feature = "atom"))]
+    {
+        if settings.graphics.renderer == RendererChoice::Atom {
+            warn!("{} asks for Atom, but this build doesn't have it", crate::game_settings::GAME_SETTINGS_FILE);
+        }
+        warn!("╔══════════════════════════════════════════════════════════════╗");
+        warn!("║  WARNING: ATOM FEATURE NOT ENABLED                            ║");
+        warn!("║  Running with default Bevy/wgpu renderer                      ║");
+        warn!("║  For AAA graphics, rebuild with: --features atom              ║");
+        warn!("╚══════════════════════════════════════════════════════════════╝");
+    }
+
+    Box::new(WgpuBackend::default())
+}
+
+pub struct RendererBackendPlugin;
+
+impl Plugin for RendererBackendPlugin {
+    fn build(&self, app: &mut App) {
+        let settings = app.world().get_resource::<GameSettings>().cloned().unwrap_or_default();
+        let mut backend = select(&settings);
+        info!("Renderer backend: {}", backend.name());
+
+        backend.configure(&settings, app.world_mut());
+        backend.init(app);
+        backend.extract(app);
+
+        app.insert_resource(ActiveRenderer(backend))
+            .add_systems(Update, reconfigure.run_if(resource_exists_and_changed::<GameSettings>));
+    }
+}
+
+fn reconfigure(world: &mut World) {
+    let Some(settings) = world.get_resource::<GameSettings>().cloned() else {
+        return;
+    };
+    world.resource_scope(|world, mut renderer: Mut<ActiveRenderer>| {
+        renderer.0.configure(&settings, world);
+    });
+}
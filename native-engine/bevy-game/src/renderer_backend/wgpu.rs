@@ -0,0 +1,173 @@
+//! Bevy's built-in renderer. `DefaultPlugins` already has it, so there's
+//! nothing to add; settings go straight to the resources and lights it reads.
+//! Its frame graph is Bevy's render graph, copied out of the render world
+//! when asked for.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use bevy::core::FrameCount;
+use bevy::prelude::*;
+use bevy::render::render_graph::{Edge, InternedRenderLabel, NodeState, RenderGraph};
+use bevy::render::{Render, RenderApp, RenderSet};
+
+use super::{RendererBackend, RendererStats};
+use crate::frame_graph::{FrameGraph, FramePass, FrameResource, ResourceState, ResourceUse};
+use crate::game_settings::{GameSettings, RendererChoice};
+
+/// The render graph, shared with the render world.
+#[derive(Resource, Clone, Default)]
+struct RenderGraphSnapshot(Arc<RenderGraphSnapshotInner>);
+
+#[derive(Default)]
+struct RenderGraphSnapshotInner {
+    /// Set by the main world, cleared by the render world once it has copied the graph.
+    wanted: AtomicBool,
+    graph: Mutex<Option<FrameGraph>>,
+}
+
+#[derive(Default)]
+pub struct WgpuBackend {
+    snapshot: RenderGraphSnapshot,
+}
+
+impl RendererBackend for WgpuBackend {
+    fn kind(&self) -> RendererChoice {
+        RendererChoice::Wgpu
+    }
+
+    fn name(&self) -> &'static str {
+        "wgpu"
+    }
+
+    fn init(&mut self, app: &mut App) {
+        // Headless runs have no render world
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app
+                .insert_resource(self.snapshot.clone())
+                .add_systems(Render, snapshot_render_graph.in_set(RenderSet::Cleanup));
+        }
+    }
+
+    fn configure(&mut self, settings: &GameSettings, world: &mut World) {
+        world.insert_resource(settings.shadow_map());
+        let shadows = settings.sun_shadows();
+        for mut light in world.query::<&mut DirectionalLight>().iter_mut(world) {
+            light.shadows_enabled = shadows;
+        }
+    }
+
+    fn extract(&self, _app: &mut App) {}
+
+    fn stats(&self, world: &World) -> RendererStats {
+        RendererStats {
+            backend: self.name().to_string(),
+            active: true,
+            frame_count: world.get_resource::<FrameCount>().map_or(0, |frames| u64::from(frames.0)),
+        }
+    }
+
+    fn frame_graph(&self, world: &World) -> Option<FrameGraph> {
+        self.snapshot.0.wanted.store(true, Ordering::Relaxed);
+        let mut graph = self.snapshot.0.graph.lock().ok()?.clone()?;
+        graph.frame = world.get_resource::<FrameCount>().map_or(0, |frames| u64::from(frames.0));
+        Some(graph)
+    }
+}
+
+fn snapshot_render_graph(graph: Res<RenderGraph>, snapshot: Res<RenderGraphSnapshot>) {
+    if !snapshot.0.wanted.swap(false, Ordering::Relaxed) {
+        return;
+    }
+    let mut frame_graph = FrameGraph {
+        backend: "wgpu".to_string(),
+        ..default()
+    };
+    add_render_graph(&mut frame_graph, "main", &graph);
+    if let Ok(mut shared) = snapshot.0.graph.lock() {
+        *shared = Some(frame_graph);
+    }
+}
+
+/// Adds `graph`'s nodes as passes in an order Bevy could run them in, its
+/// slot edges as resources, then its sub-graphs.
+fn add_render_graph(frame_graph: &mut FrameGraph, name: &str, graph: &RenderGraph) {
+    let mut nodes: Vec<&NodeState> = graph.iter_nodes().collect();
+    nodes.sort_by_cached_key(|node| format!("{:?}", node.label));
+
+    // A node runs once everything with an edge into it has
+    let mut ordered: Vec<&NodeState> = Vec::with_capacity(nodes.len());
+    let mut placed: HashSet<InternedRenderLabel> = HashSet::new();
+    while ordered.len() < nodes.len() {
+        let ready: Vec<&NodeState> = nodes
+            .iter()
+            .copied()
+            .filter(|node| !placed.contains(&node.label))
+            .filter(|node| node.edges.input_edges().iter().all(|edge| placed.contains(&edge.get_output_node())))
+            .collect();
+        if ready.is_empty() {
+            // A cycle, which Bevy won't run; validation points at it
+            ordered.extend(nodes.iter().copied().filter(|node| !placed.contains(&node.label)));
+            break;
+        }
+        for node in ready {
+            placed.insert(node.label);
+            ordered.push(node);
+        }
+    }
+
+    let first = frame_graph.passes.len();
+    let index_of: HashMap<InternedRenderLabel, usize> =
+        ordered.iter().enumerate().map(|(offset, node)| (node.label, first + offset)).collect();
+    for node in &ordered {
+        frame_graph.passes.push(FramePass {
+            name: format!("{:?}", node.label),
+            graph: name.to_string(),
+            uses: Vec::new(),
+            after: node
+                .edges
+                .input_edges()
+                .iter()
+                .filter_map(|edge| index_of.get(&edge.get_output_node()).copied())
+                .collect(),
+        });
+    }
+
+    // One resource per connected output slot, written by its node and read by each node it feeds
+    let mut slots: HashMap<(InternedRenderLabel, usize), usize> = HashMap::new();
+    for node in &ordered {
+        for edge in node.edges.output_edges() {
+            let Edge::SlotEdge { input_node, output_index, .. } = edge else {
+                continue;
+            };
+            let (Some(&writer), Some(&reader)) = (index_of.get(&node.label), index_of.get(input_node)) else {
+                continue;
+            };
+            let resource = *slots.entry((node.label, *output_index)).or_insert_with(|| {
+                let slot = node.output_slots.get_slot(*output_index);
+                frame_graph.resources.push(FrameResource {
+                    name: format!("{:?}.{}", node.label, slot.map_or("?", |slot| &*slot.name)),
+                    kind: slot.map_or_else(|| "?".to_string(), |slot| format!("{:?}", slot.slot_type)),
+                    initial_state: ResourceState::Undefined,
+                });
+                frame_graph.passes[writer].uses.push(ResourceUse {
+                    resource: frame_graph.resources.len() - 1,
+                    state: ResourceState::Write,
+                });
+                frame_graph.resources.len() - 1
+            });
+            frame_graph.passes[reader].uses.push(ResourceUse {
+                resource,
+                state: ResourceState::Read,
+            });
+        }
+    }
+
+    let mut sub_graphs: Vec<(String, &RenderGraph)> =
+        graph.iter_sub_graphs().map(|(label, sub_graph)| (format!("{:?}", label), sub_graph)).collect();
+    sub_graphs.sort_by(|a, b| a.0.cmp(&b.0));
+    for (label, sub_graph) in sub_graphs {
+        add_render_graph(frame_graph, &label, sub_graph);
+    }
+}
@@ -0,0 +1,446 @@
+//! Scenario harness for headless runs. A scenario file (`scenarios/*.toml`)
+//! spawns entities, feeds console commands and key presses at given ticks,
+//! and checks assertions on entity counts, positions and reflected component
+//! fields. Ticks use a fixed time step, so a scenario runs the same on every
+//! machine.
+//!
+//! Run one with `--headless --scenario scenarios/console_teleport.toml`;
+//! `cargo test` runs every file in `scenarios/`.
+
+use bevy::ecs::component::ComponentId;
+use bevy::prelude::*;
+use bevy::reflect::{GetPath, PartialReflect};
+use bevy::time::TimeUpdateStrategy;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::console::{CommandPermission, CommandSource, ConsoleInputEvent, ConsoleOutputEvent, ConsolePermissions};
+use crate::worldgen::WorldSeed;
+use crate::Player;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScenarioSpawn {
+    pub name: String,
+    #[serde(default)]
+    pub position: [f32; 3],
+    /// More than one spawns `name_0`, `name_1`, ... spaced along +X.
+    #[serde(default = "default_count")]
+    pub count: u32,
+    #[serde(default = "default_spacing")]
+    pub spacing: f32,
+    /// Adds the `Player` marker, for commands that act on the player.
+    #[serde(default)]
+    pub player: bool,
+}
+
+fn default_count() -> u32 {
+    1
+}
+
+fn default_spacing() -> f32 {
+    2.0
+}
+
+/// Something done at a tick: a console command, or a key held for
+/// `hold_ticks`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScenarioInput {
+    pub tick: u32,
+    #[serde(default)]
+    pub command: Option<String>,
+    #[serde(default)]
+    pub key: Option<KeyCode>,
+    #[serde(default = "default_hold_ticks")]
+    pub hold_ticks: u32,
+}
+
+fn default_hold_ticks() -> u32 {
+    1
+}
+
+/// A check on the entities an assertion selects: those whose `Name` matches
+/// `entity` (exact, or a prefix ending in `*`) and that have the component
+/// `with`. Either selector may be left out.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScenarioAssertion {
+    /// Tick to check at; defaults to the last one.
+    #[serde(default)]
+    pub tick: Option<u32>,
+    #[serde(default)]
+    pub entity: Option<String>,
+    #[serde(default)]
+    pub with: Option<String>,
+
+    #[serde(default)]
+    pub count: Option<usize>,
+    #[serde(default)]
+    pub min_count: Option<usize>,
+    #[serde(default)]
+    pub max_count: Option<usize>,
+
+    #[serde(default)]
+    pub position: Option<[f32; 3]>,
+    #[serde(default = "default_tolerance")]
+    pub tolerance: f32,
+
+    /// Reflected component and field path, e.g. `Health` / `current`.
+    #[serde(default)]
+    pub component: Option<String>,
+    #[serde(default)]
+    pub field: Option<String>,
+    #[serde(default)]
+    pub min: Option<f64>,
+    #[serde(default)]
+    pub max: Option<f64>,
+    #[serde(default)]
+    pub equals: Option<f64>,
+}
+
+fn default_tolerance() -> f32 {
+    0.05
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Scenario {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub ticks: u32,
+    #[serde(default = "default_tick_seconds")]
+    pub tick_seconds: f64,
+    /// World seed; the default world when left out, whatever `--seed` says.
+    #[serde(default)]
+    pub seed: Option<u64>,
+    #[serde(default, rename = "spawn")]
+    pub spawns: Vec<ScenarioSpawn>,
+    #[serde(default, rename = "input")]
+    pub inputs: Vec<ScenarioInput>,
+    #[serde(default, rename = "assert")]
+    pub assertions: Vec<ScenarioAssertion>,
+}
+
+fn default_tick_seconds() -> f64 {
+    1.0 / 60.0
+}
+
+impl Scenario {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let scenario: Scenario = toml::from_str(&text).map_err(|e| e.to_string())?;
+        for input in &scenario.inputs {
+            if input.command.is_none() && input.key.is_none() {
+                return Err(format!("input at tick {} has neither command nor key", input.tick));
+            }
+        }
+        Ok(scenario)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ScenarioReport {
+    pub name: String,
+    pub passed: usize,
+    pub failures: Vec<String>,
+}
+
+impl ScenarioReport {
+    pub fn success(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+#[derive(Resource)]
+struct ScenarioRun {
+    scenario: Scenario,
+    tick: u32,
+    /// Keys to release and the tick to release them at.
+    held: Vec<(KeyCode, u32)>,
+    report: ScenarioReport,
+}
+
+/// Runs `scenario` on `app`, which should hold the game plugins under test
+/// but not a runner: ticks are driven here with `App::update`.
+pub fn run_scenario(mut app: App, scenario: Scenario) -> ScenarioReport {
+    info!("Scenario '{}': {} ticks", scenario.name, scenario.ticks);
+    let step = Duration::from_secs_f64(scenario.tick_seconds);
+    let ticks = scenario.ticks;
+
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(step))
+        .insert_resource(ConsolePermissions {
+            local: CommandPermission::Dev,
+        })
+        .init_resource::<ButtonInput<KeyCode>>()
+        .insert_resource(scenario.seed.map_or_else(WorldSeed::default, WorldSeed::new))
+        .insert_resource(ScenarioRun {
+            report: ScenarioReport {
+                name: scenario.name.clone(),
+                ..Default::default()
+            },
+            scenario,
+            tick: 0,
+            held: Vec::new(),
+        })
+        .add_systems(Startup, spawn_scenario_entities)
+        .add_systems(First, feed_scenario_inputs)
+        .add_systems(Last, (log_scenario_output, check_scenario_assertions));
+
+    app.finish();
+    app.cleanup();
+    for _ in 0..ticks {
+        app.update();
+    }
+
+    app.world_mut()
+        .remove_resource::<ScenarioRun>()
+        .map(|run| run.report)
+        .unwrap_or_default()
+}
+
+fn spawn_scenario_entities(mut commands: Commands, run: Res<ScenarioRun>) {
+    for spawn in &run.scenario.spawns {
+        for i in 0..spawn.count {
+            let name = if spawn.count > 1 {
+                format!("{}_{}", spawn.name, i)
+            } else {
+                spawn.name.clone()
+            };
+            let position = Vec3::from(spawn.position) + Vec3::X * spawn.spacing * i as f32;
+            let mut entity = commands.spawn((Transform::from_translation(position), Name::new(name)));
+            if spawn.player {
+                entity.insert(Player);
+            }
+        }
+    }
+}
+
+fn feed_scenario_inputs(
+    mut run: ResMut<ScenarioRun>,
+    mut keys: ResMut<ButtonInput<KeyCode>>,
+    mut console: EventWriter<ConsoleInputEvent>,
+) {
+    run.tick += 1;
+    let tick = run.tick;
+    // No InputPlugin headless, so presses are cleared here instead
+    keys.clear();
+
+    let (release, keep): (Vec<_>, Vec<_>) = run.held.drain(..).partition(|(_, until)| *until <= tick);
+    run.held = keep;
+    for (key, _) in release {
+        keys.release(key);
+    }
+
+    let due: Vec<ScenarioInput> = run.scenario.inputs.iter().filter(|i| i.tick == tick).cloned().collect();
+    for input in due {
+        if let Some(line) = input.command {
+            console.send(ConsoleInputEvent {
+                line,
+                source: CommandSource::Local,
+            });
+        }
+        if let Some(key) = input.key {
+            keys.press(key);
+            run.held.push((key, tick + input.hold_ticks.max(1)));
+        }
+    }
+}
+
+fn log_scenario_output(mut output: EventReader<ConsoleOutputEvent>) {
+    for event in output.read() {
+        if event.error {
+            warn!("[scenario console] {}", event.text);
+        } else {
+            info!("[scenario console] {}", event.text);
+        }
+    }
+}
+
+fn short_type_name(path: &str) -> &str {
+    let head = path.split('<').next().unwrap_or(path);
+    let start = head.rfind("::").map_or(0, |i| i + 2);
+    &path[start..]
+}
+
+fn component_id(world: &World, name: &str) -> Option<ComponentId> {
+    world.components().iter().find(|info| short_type_name(info.name()) == name).map(|info| info.id())
+}
+
+fn name_matches(pattern: &str, name: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => name == pattern,
+    }
+}
+
+fn as_number(value: &dyn PartialReflect) -> Option<f64> {
+    macro_rules! number {
+        ($($ty:ty),*) => {
+            $(
+                if let Some(v) = value.try_downcast_ref::<$ty>() {
+                    return Some(*v as f64);
+                }
+            )*
+        };
+    }
+    number!(f32, f64, i8, i16, i32, i64, u8, u16, u32, u64, usize);
+    value.try_downcast_ref::<bool>().map(|b| if *b { 1.0 } else { 0.0 })
+}
+
+/// Reads `component.field` of `entity` through reflection.
+fn read_field(world: &World, entity: Entity, component: &str, field: &str) -> Result<f64, String> {
+    let registry = world.resource::<AppTypeRegistry>().read();
+    let registration = registry
+        .get_with_short_type_path(component)
+        .ok_or_else(|| format!("component {} isn't registered for reflection", component))?;
+    let reflect_component = registration
+        .data::<ReflectComponent>()
+        .ok_or_else(|| format!("{} doesn't reflect as a component", component))?;
+    let value = reflect_component
+        .reflect(world.entity(entity))
+        .ok_or_else(|| format!("no {}", component))?;
+    let field_value = if field.is_empty() {
+        value.as_partial_reflect()
+    } else {
+        value.reflect_path(field).map_err(|e| format!("{}.{}: {}", component, field, e))?
+    };
+    as_number(field_value).ok_or_else(|| format!("{}.{} isn't a number", component, field))
+}
+
+/// Checks one assertion; `Err` describes the first thing that failed.
+fn check(world: &mut World, assertion: &ScenarioAssertion) -> Result<(), String> {
+    let with = match &assertion.with {
+        Some(name) => Some(component_id(world, name).ok_or_else(|| format!("no component type {}", name))?),
+        None => None,
+    };
+    let mut query = world.query::<(Entity, Option<&Name>, Option<&Transform>)>();
+    let selected: Vec<(Entity, String, Option<Vec3>)> = query
+        .iter(world)
+        .filter(|(_, name, _)| match (&assertion.entity, name) {
+            (Some(pattern), Some(name)) => name_matches(pattern, name.as_str()),
+            (Some(_), None) => false,
+            (None, _) => true,
+        })
+        .map(|(entity, name, transform)| {
+            let name = name.map(|n| n.to_string()).unwrap_or_else(|| format!("{:?}", entity));
+            (entity, name, transform.map(|t| t.translation))
+        })
+        .collect();
+    let selected: Vec<_> = selected
+        .into_iter()
+        .filter(|(entity, _, _)| with.is_none_or(|id| world.entity(*entity).contains_id(id)))
+        .collect();
+
+    let count = selected.len();
+    if let Some(expected) = assertion.count.filter(|c| *c != count) {
+        return Err(format!("expected {} entities, found {}", expected, count));
+    }
+    if let Some(min) = assertion.min_count.filter(|m| count < *m) {
+        return Err(format!("expected at least {} entities, found {}", min, count));
+    }
+    if let Some(max) = assertion.max_count.filter(|m| count > *m) {
+        return Err(format!("expected at most {} entities, found {}", max, count));
+    }
+
+    let checks_entities = assertion.position.is_some() || assertion.component.is_some();
+    if checks_entities && selected.is_empty() {
+        return Err("no entities selected".to_string());
+    }
+    for (entity, name, translation) in &selected {
+        if let Some(expected) = assertion.position.map(Vec3::from) {
+            let actual = translation.ok_or_else(|| format!("{} has no Transform", name))?;
+            if actual.distance(expected) > assertion.tolerance {
+                return Err(format!("{} at {:.2?}, expected {:.2?}", name, actual, expected));
+            }
+        }
+        if let Some(component) = &assertion.component {
+            let field = assertion.field.as_deref().unwrap_or("");
+            let value = read_field(world, *entity, component, field).map_err(|e| format!("{}: {}", name, e))?;
+            let label = format!("{} {}.{} = {}", name, component, field, value);
+            if assertion.equals.is_some_and(|v| (value - v).abs() > assertion.tolerance as f64) {
+                return Err(format!("{}, expected {}", label, assertion.equals.unwrap_or_default()));
+            }
+            if assertion.min.is_some_and(|v| value < v) {
+                return Err(format!("{}, expected at least {}", label, assertion.min.unwrap_or_default()));
+            }
+            if assertion.max.is_some_and(|v| value > v) {
+                return Err(format!("{}, expected at most {}", label, assertion.max.unwrap_or_default()));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn check_scenario_assertions(world: &mut World) {
+    let Some(mut run) = world.remove_resource::<ScenarioRun>() else {
+        return;
+    };
+    let tick = run.tick;
+    let last = run.scenario.ticks;
+    for (index, assertion) in run.scenario.assertions.iter().enumerate() {
+        if assertion.tick.unwrap_or(last) != tick {
+            continue;
+        }
+        match check(world, assertion) {
+            Ok(()) => run.report.passed += 1,
+            Err(e) => run.report.failures.push(format!("assert #{} (tick {}): {}", index + 1, tick, e)),
+        }
+    }
+    world.insert_resource(run);
+}
+
+/// `--scenario <file>` in headless mode. `app` holds the game plugins.
+pub fn run_cli(app: App, path: &Path) -> i32 {
+    let scenario = match Scenario::load(path) {
+        Ok(scenario) => scenario,
+        Err(e) => {
+            eprintln!("Invalid scenario {}: {}", path.display(), e);
+            return 2;
+        }
+    };
+    let report = run_scenario(app, scenario);
+    for failure in &report.failures {
+        eprintln!("  FAIL {}", failure);
+    }
+    println!(
+        "Scenario '{}': {} passed, {} failed",
+        report.name,
+        report.passed,
+        report.failures.len()
+    );
+    if report.success() {
+        0
+    } else {
+        1
+    }
+}
+
+pub fn scenario_files(directory: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(directory) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().and_then(|e| e.to_str()) == Some("toml"))
+        .collect();
+    paths.sort();
+    paths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scenarios_pass() {
+        let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("scenarios");
+        let mut failures = Vec::new();
+        for path in scenario_files(&directory) {
+            let scenario = Scenario::load(&path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+            let report = run_scenario(crate::scenario_app(), scenario);
+            println!("{}: {} passed, {} failed", path.display(), report.passed, report.failures.len());
+            failures.extend(report.failures.into_iter().map(|f| format!("{}: {}", path.display(), f)));
+        }
+        assert!(failures.is_empty(), "scenario failures:\n{}", failures.join("\n"));
+    }
+}
@@ -0,0 +1,141 @@
+//! The functions scripts can call. Scripts never touch the ECS world: every
+//! call records a [`ScriptCommand`] that `apply_script_commands` turns into
+//! events after the script returns. Flags are the one piece of state scripts
+//! can read back.
+
+use bevy::prelude::*;
+use rhai::{Dynamic, Engine};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use super::ScriptConfig;
+use crate::hazards::DamageSchool;
+
+#[derive(Debug, Clone)]
+pub enum ScriptCommand {
+    Spawn { template: String, position: Vec3 },
+    Damage { target: Entity, amount: f32, school: DamageSchool },
+    StartDialog { tree: String, target: Entity },
+    SetFlag { name: String, value: Dynamic },
+}
+
+/// State shared between the engine's registered functions and the runtime.
+#[derive(Debug, Default)]
+pub struct ScriptShared {
+    /// Script currently running, for attributing commands and log lines.
+    pub current: String,
+    pub commands: Vec<(String, ScriptCommand)>,
+    pub flags: HashMap<String, Dynamic>,
+}
+
+pub type SharedState = Arc<Mutex<ScriptShared>>;
+
+pub fn entity_to_script(entity: Entity) -> i64 {
+    entity.to_bits() as i64
+}
+
+fn entity_from_script(value: i64) -> Option<Entity> {
+    Entity::try_from_bits(value as u64).ok()
+}
+
+fn school_from_name(name: &str) -> DamageSchool {
+    match name.to_ascii_lowercase().as_str() {
+        "fire" => DamageSchool::Fire,
+        "frost" => DamageSchool::Frost,
+        "nature" => DamageSchool::Nature,
+        "shadow" => DamageSchool::Shadow,
+        "arcane" => DamageSchool::Arcane,
+        "holy" => DamageSchool::Holy,
+        _ => DamageSchool::Physical,
+    }
+}
+
+fn push(shared: &SharedState, command: ScriptCommand) {
+    if let Ok(mut shared) = shared.lock() {
+        let script = shared.current.clone();
+        shared.commands.push((script, command));
+    }
+}
+
+/// A sandboxed engine: no `eval`, no module imports, and hard limits on work
+/// and memory so a runaway script stalls nothing but itself.
+pub fn build_engine(config: &ScriptConfig, shared: &SharedState) -> Engine {
+    let mut engine = Engine::new();
+    engine
+        .set_max_operations(config.max_operations)
+        .set_max_call_levels(32)
+        .set_max_expr_depths(64, 32)
+        .set_max_string_size(64 * 1024)
+        .set_max_array_size(10_000)
+        .set_max_map_size(10_000)
+        .set_module_resolver(rhai::module_resolvers::DummyModuleResolver::new())
+        .disable_symbol("eval");
+
+    let log_state = shared.clone();
+    engine.on_print(move |text| {
+        let script = log_state.lock().map(|s| s.current.clone()).unwrap_or_default();
+        info!("[script {}] {}", script, text);
+    });
+    let debug_state = shared.clone();
+    engine.on_debug(move |text, _, position| {
+        let script = debug_state.lock().map(|s| s.current.clone()).unwrap_or_default();
+        debug!("[script {} {}] {}", script, position, text);
+    });
+
+    let state = shared.clone();
+    engine.register_fn("spawn", move |template: &str, x: f64, y: f64, z: f64| {
+        push(
+            &state,
+            ScriptCommand::Spawn {
+                template: template.to_string(),
+                position: Vec3::new(x as f32, y as f32, z as f32),
+            },
+        );
+    });
+
+    let state = shared.clone();
+    engine.register_fn("damage", move |target: i64, amount: f64| {
+        if let Some(target) = entity_from_script(target) {
+            let school = DamageSchool::Physical;
+            push(&state, ScriptCommand::Damage { target, amount: amount as f32, school });
+        }
+    });
+    let state = shared.clone();
+    engine.register_fn("damage", move |target: i64, amount: f64, school: &str| {
+        if let Some(target) = entity_from_script(target) {
+            let school = school_from_name(school);
+            push(&state, ScriptCommand::Damage { target, amount: amount as f32, school });
+        }
+    });
+
+    let state = shared.clone();
+    engine.register_fn("start_dialog", move |tree: &str, target: i64| {
+        if let Some(target) = entity_from_script(target) {
+            push(&state, ScriptCommand::StartDialog { tree: tree.to_string(), target });
+        }
+    });
+
+    // Flags are written immediately so a script sees its own writes
+    let state = shared.clone();
+    engine.register_fn("set_flag", move |name: &str, value: Dynamic| {
+        if let Ok(mut shared) = state.lock() {
+            shared.flags.insert(name.to_string(), value.clone());
+            let script = shared.current.clone();
+            shared.commands.push((script, ScriptCommand::SetFlag { name: name.to_string(), value }));
+        }
+    });
+    let state = shared.clone();
+    engine.register_fn("get_flag", move |name: &str| -> Dynamic {
+        state
+            .lock()
+            .ok()
+            .and_then(|shared| shared.flags.get(name).cloned())
+            .unwrap_or(Dynamic::UNIT)
+    });
+    let state = shared.clone();
+    engine.register_fn("has_flag", move |name: &str| -> bool {
+        state.lock().map(|shared| shared.flags.contains_key(name)).unwrap_or(false)
+    });
+
+    engine
+}
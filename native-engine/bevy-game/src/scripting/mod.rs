@@ -0,0 +1,363 @@
+//! Gameplay scripts: quests, boss mechanics and NPC interactions written in
+//! Rhai under `content/scripts/` (or the same directory in a content pack),
+//! reloaded when the files change.
+//!
+//! A script is a set of hook functions, all optional:
+//!
+//! ```rhai
+//! fn on_load() { set_flag("ragnar_phase", 1); }
+//! fn on_update(dt) { ... }
+//! fn on_trigger_enter(trigger_id, entity) { ... }
+//! fn on_trigger_exit(trigger_id, entity) { ... }
+//! fn on_zone_enter(zone_id, entity) { ... }
+//! ```
+//!
+//! Other systems call arbitrary functions with [`ScriptCallEvent`]. Scripts
+//! act on the world only through the API in [`api`]: `spawn`, `damage`,
+//! `start_dialog` and `set_flag`/`get_flag`/`has_flag`.
+
+use bevy::prelude::*;
+use rhai::{CallFnOptions, Dynamic, Engine, Scope, AST};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+mod api;
+
+pub use api::{entity_to_script, ScriptCommand};
+use api::{build_engine, SharedState};
+
+use crate::content_packs::ContentPacks;
+use crate::hazards::DamageSchool;
+use crate::hot_reload::{ContentChangedEvent, ContentReloadedEvent};
+use crate::triggers::{TriggerEnteredEvent, TriggerExitedEvent, ZoneEnteredEvent};
+
+#[derive(Resource, Debug, Clone)]
+pub struct ScriptConfig {
+    pub directories: Vec<PathBuf>,
+    /// Rhai operations one hook call may run before it's aborted.
+    pub max_operations: u64,
+}
+
+impl Default for ScriptConfig {
+    fn default() -> Self {
+        Self {
+            directories: vec![PathBuf::from("content").join("scripts")],
+            max_operations: 200_000,
+        }
+    }
+}
+
+pub struct LoadedScript {
+    pub source: PathBuf,
+    ast: AST,
+    scope: Scope<'static>,
+    /// Set when `on_update` fails, so a broken script doesn't error every frame.
+    update_failed: bool,
+}
+
+impl LoadedScript {
+    fn defines(&self, function: &str, arity: usize) -> bool {
+        self.ast.iter_functions().any(|f| f.name == function && f.params.len() == arity)
+    }
+}
+
+/// Compiled scripts by name (path under the script directory, without the
+/// extension: `bosses/ragnar`).
+#[derive(Resource)]
+pub struct ScriptRuntime {
+    engine: Engine,
+    shared: SharedState,
+    pub scripts: BTreeMap<String, LoadedScript>,
+}
+
+impl ScriptRuntime {
+    fn new(config: &ScriptConfig) -> Self {
+        let shared = SharedState::default();
+        Self {
+            engine: build_engine(config, &shared),
+            shared,
+            scripts: BTreeMap::new(),
+        }
+    }
+
+    pub fn flag(&self, name: &str) -> Option<Dynamic> {
+        self.shared.lock().ok().and_then(|shared| shared.flags.get(name).cloned())
+    }
+
+    /// Sets a flag from outside the scripts, without a
+    /// [`ScriptFlagChangedEvent`].
+    pub fn set_flag(&self, name: &str, value: Dynamic) {
+        if let Ok(mut shared) = self.shared.lock() {
+            shared.flags.insert(name.to_string(), value);
+        }
+    }
+
+    fn set_current(&self, script: &str) {
+        if let Ok(mut shared) = self.shared.lock() {
+            shared.current = script.to_string();
+        }
+    }
+
+    /// Compiles `text`, runs its top level and `on_load`, and replaces any
+    /// script of the same name. On error the old version keeps running.
+    fn load(&mut self, name: &str, source: &Path, text: &str) -> Result<(), String> {
+        let ast = self.engine.compile(text).map_err(|e| e.to_string())?;
+        let mut script = LoadedScript {
+            source: source.to_path_buf(),
+            ast,
+            scope: Scope::new(),
+            update_failed: false,
+        };
+        self.set_current(name);
+        self.engine
+            .run_ast_with_scope(&mut script.scope, &script.ast)
+            .map_err(|e| e.to_string())?;
+        if script.defines("on_load", 0) {
+            let _ = call(&self.engine, &mut script, "on_load", Vec::new())?;
+        }
+        self.scripts.insert(name.to_string(), script);
+        Ok(())
+    }
+
+    /// Calls `function` in every script that defines it with this many arguments.
+    pub fn broadcast(&mut self, function: &str, args: Vec<Dynamic>) {
+        let names: Vec<String> = self.scripts.keys().cloned().collect();
+        for name in names {
+            self.call(&name, function, args.clone());
+        }
+    }
+
+    /// Calls `function` in one script; missing scripts and functions are skipped.
+    pub fn call(&mut self, script: &str, function: &str, args: Vec<Dynamic>) -> Option<Dynamic> {
+        self.set_current(script);
+        let loaded = self.scripts.get_mut(script)?;
+        if !loaded.defines(function, args.len()) {
+            return None;
+        }
+        match call(&self.engine, loaded, function, args) {
+            Ok(value) => Some(value),
+            Err(e) => {
+                warn!("Script {} failed in {}: {}", script, function, e);
+                None
+            }
+        }
+    }
+
+    fn take_commands(&self) -> Vec<(String, ScriptCommand)> {
+        self.shared
+            .lock()
+            .map(|mut shared| std::mem::take(&mut shared.commands))
+            .unwrap_or_default()
+    }
+}
+
+fn call(engine: &Engine, script: &mut LoadedScript, function: &str, args: Vec<Dynamic>) -> Result<Dynamic, String> {
+    let options = CallFnOptions::new().eval_ast(false);
+    engine
+        .call_fn_with_options::<Dynamic>(options, &mut script.scope, &script.ast, function, args)
+        .map_err(|e| e.to_string())
+}
+
+/// Calls a script function from gameplay code, e.g. a quest step or an NPC
+/// interaction. Without `script` every script defining the function runs.
+#[derive(Event, Debug, Clone)]
+pub struct ScriptCallEvent {
+    pub script: Option<String>,
+    pub function: String,
+    pub args: Vec<Dynamic>,
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct ScriptSpawnRequest {
+    pub script: String,
+    pub template: String,
+    pub position: Vec3,
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct ScriptDamageRequest {
+    pub script: String,
+    pub target: Entity,
+    pub amount: f32,
+    pub school: DamageSchool,
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct ScriptDialogRequest {
+    pub script: String,
+    pub tree: String,
+    pub target: Entity,
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct ScriptFlagChangedEvent {
+    pub script: String,
+    pub name: String,
+    pub value: Dynamic,
+}
+
+pub struct ScriptingPlugin;
+
+impl Plugin for ScriptingPlugin {
+    fn build(&self, app: &mut App) {
+        let config = app.world().get_resource::<ScriptConfig>().cloned().unwrap_or_default();
+        app.insert_resource(ScriptRuntime::new(&config))
+            .insert_resource(config)
+            .init_resource::<ContentPacks>()
+            .add_event::<ScriptCallEvent>()
+            .add_event::<ScriptSpawnRequest>()
+            .add_event::<ScriptDamageRequest>()
+            .add_event::<ScriptDialogRequest>()
+            .add_event::<ScriptFlagChangedEvent>()
+            .add_event::<TriggerEnteredEvent>()
+            .add_event::<TriggerExitedEvent>()
+            .add_event::<ZoneEnteredEvent>()
+            .add_event::<ContentChangedEvent>()
+            .add_event::<ContentReloadedEvent>()
+            .add_systems(Startup, load_scripts)
+            .add_systems(Update, (reload_scripts, run_script_hooks, apply_script_commands).chain());
+    }
+}
+
+fn script_files(directory: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(directory) else {
+        return;
+    };
+    for path in entries.flatten().map(|e| e.path()) {
+        if path.is_dir() {
+            script_files(&path, files);
+        } else if path.extension().and_then(|e| e.to_str()) == Some("rhai") {
+            files.push(path);
+        }
+    }
+}
+
+fn script_name(directory: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(directory).ok()?.with_extension("");
+    let parts: Vec<_> = relative.components().map(|c| c.as_os_str().to_string_lossy()).collect();
+    Some(parts.join("/"))
+}
+
+/// Later packs' scripts replace earlier ones with the same name.
+pub(crate) fn load_scripts(config: Res<ScriptConfig>, packs: Res<ContentPacks>, mut runtime: ResMut<ScriptRuntime>) {
+    for directory in config.directories.iter().flat_map(|dir| packs.directories(dir)) {
+        let mut paths = Vec::new();
+        script_files(&directory, &mut paths);
+        paths.sort();
+        for path in paths {
+            let Some(name) = script_name(&directory, &path) else {
+                continue;
+            };
+            let loaded = std::fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|text| runtime.load(&name, &path, &text));
+            if let Err(e) = loaded {
+                warn!("Script {} ({:?}) failed to load: {}", name, path, e);
+            }
+        }
+    }
+    info!("Loaded {} gameplay scripts", runtime.scripts.len());
+}
+
+fn reload_scripts(
+    mut changed: EventReader<ContentChangedEvent>,
+    mut reloaded: EventWriter<ContentReloadedEvent>,
+    config: Res<ScriptConfig>,
+    mut runtime: ResMut<ScriptRuntime>,
+) {
+    for event in changed.read() {
+        if event.path.extension().and_then(|e| e.to_str()) != Some("rhai") {
+            continue;
+        }
+        let Some(name) = config.directories.iter().find_map(|dir| script_name(dir, &event.path)) else {
+            continue;
+        };
+        if event.removed {
+            runtime.scripts.remove(&name);
+            reloaded.send(ContentReloadedEvent::applied(&event.path, format!("script {} unloaded", name)));
+            continue;
+        }
+        let result = std::fs::read_to_string(&event.path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| runtime.load(&name, &event.path, &text));
+        reloaded.send(match result {
+            Ok(()) => ContentReloadedEvent::applied(&event.path, format!("script {}", name)),
+            Err(e) => ContentReloadedEvent::failed(&event.path, format!("{} (previous version kept)", e)),
+        });
+    }
+}
+
+fn run_script_hooks(
+    mut runtime: ResMut<ScriptRuntime>,
+    time: Res<Time>,
+    mut calls: EventReader<ScriptCallEvent>,
+    mut entered: EventReader<TriggerEnteredEvent>,
+    mut exited: EventReader<TriggerExitedEvent>,
+    mut zones: EventReader<ZoneEnteredEvent>,
+) {
+    crate::profile_zone!("scripting::hooks");
+    if runtime.scripts.is_empty() {
+        return;
+    }
+
+    for event in entered.read() {
+        let args = vec![Dynamic::from(event.trigger_id.clone()), Dynamic::from(entity_to_script(event.entity))];
+        runtime.broadcast("on_trigger_enter", args);
+    }
+    for event in exited.read() {
+        let args = vec![Dynamic::from(event.trigger_id.clone()), Dynamic::from(entity_to_script(event.entity))];
+        runtime.broadcast("on_trigger_exit", args);
+    }
+    for event in zones.read() {
+        let args = vec![Dynamic::from(event.zone_id.clone()), Dynamic::from(entity_to_script(event.entity))];
+        runtime.broadcast("on_zone_enter", args);
+    }
+    for event in calls.read() {
+        match &event.script {
+            Some(script) => {
+                runtime.call(script, &event.function, event.args.clone());
+            }
+            None => runtime.broadcast(&event.function, event.args.clone()),
+        }
+    }
+
+    let dt = Dynamic::from(time.delta_secs() as f64);
+    let runtime = &mut *runtime;
+    for (name, script) in runtime.scripts.iter_mut() {
+        if script.update_failed || !script.defines("on_update", 1) {
+            continue;
+        }
+        if let Ok(mut shared) = runtime.shared.lock() {
+            shared.current = name.clone();
+        }
+        if let Err(e) = call(&runtime.engine, script, "on_update", vec![dt.clone()]) {
+            warn!("Script {} failed in on_update, disabled until reloaded: {}", name, e);
+            script.update_failed = true;
+        }
+    }
+}
+
+fn apply_script_commands(
+    runtime: Res<ScriptRuntime>,
+    mut spawns: EventWriter<ScriptSpawnRequest>,
+    mut damage: EventWriter<ScriptDamageRequest>,
+    mut dialogs: EventWriter<ScriptDialogRequest>,
+    mut flags: EventWriter<ScriptFlagChangedEvent>,
+) {
+    for (script, command) in runtime.take_commands() {
+        match command {
+            ScriptCommand::Spawn { template, position } => {
+                spawns.send(ScriptSpawnRequest { script, template, position });
+            }
+            ScriptCommand::Damage { target, amount, school } => {
+                damage.send(ScriptDamageRequest { script, target, amount, school });
+            }
+            ScriptCommand::StartDialog { tree, target } => {
+                dialogs.send(ScriptDialogRequest { script, tree, target });
+            }
+            ScriptCommand::SetFlag { name, value } => {
+                flags.send(ScriptFlagChangedEvent { script, name, value });
+            }
+        }
+    }
+}
@@ -0,0 +1,244 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+
+use crate::input_map::{Action, Binding};
+
+/// Mixer bus volumes (each 0..1) plus subtitle and voice-over options.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioSettings {
+    pub master: f32,
+    pub music: f32,
+    pub sfx: f32,
+    pub voice: f32,
+    pub ambience: f32,
+    pub subtitles: bool,
+    /// Locale code for voice-over clips, e.g. `en` or `de`.
+    pub voice_language: String,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self {
+            master: 0.8,
+            music: 0.7,
+            sfx: 1.0,
+            voice: 1.0,
+            ambience: 0.8,
+            subtitles: true,
+            voice_language: "en".to_string(),
+        }
+    }
+}
+
+/// Language and other display options.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InterfaceSettings {
+    /// Language code for game text, e.g. `en`, `de` or `pt-BR`.
+    pub language: String,
+}
+
+impl Default for InterfaceSettings {
+    fn default() -> Self {
+        Self {
+            language: "en".to_string(),
+        }
+    }
+}
+
+/// Field of view (degrees) and mouse look speed per camera mode, plus
+/// first-person head bob.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CameraSettings {
+    pub third_person_fov: f32,
+    pub first_person_fov: f32,
+    pub third_person_sensitivity: f32,
+    pub first_person_sensitivity: f32,
+    pub head_bob: bool,
+    /// Scales the bob height and sway, 0..1.
+    pub head_bob_intensity: f32,
+}
+
+impl Default for CameraSettings {
+    fn default() -> Self {
+        Self {
+            third_person_fov: 60.0,
+            first_person_fov: 80.0,
+            third_person_sensitivity: 1.0,
+            first_person_sensitivity: 1.0,
+            head_bob: true,
+            head_bob_intensity: 0.5,
+        }
+    }
+}
+
+/// Rebound actions (only those that differ from the defaults) and gamepad
+/// tuning.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InputSettings {
+    pub bindings: BTreeMap<Action, Vec<Binding>>,
+    /// Stick and trigger travel (0..1) ignored as noise.
+    pub gamepad_dead_zone: f32,
+}
+
+impl Default for InputSettings {
+    fn default() -> Self {
+        Self {
+            bindings: BTreeMap::new(),
+            gamepad_dead_zone: 0.15,
+        }
+    }
+}
+
+/// What the game may send home. Everything is off until the player opts in.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PrivacySettings {
+    /// Upload crash reports from `crashes/` on the next start.
+    pub send_crash_reports: bool,
+}
+
+/// Tutorial hints and which ones the player has already been through.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TutorialSettings {
+    pub show_hints: bool,
+    /// Hints and sequences already shown; cleared by "reset tutorials".
+    pub seen: BTreeSet<String>,
+}
+
+impl Default for TutorialSettings {
+    fn default() -> Self {
+        Self {
+            show_hints: true,
+            seen: BTreeSet::new(),
+        }
+    }
+}
+
+/// Combat overlays: aggro rings around hostile NPCs, the warning glow
+/// while something is attacking the player, and lines from NPCs to their
+/// targets.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ThreatDisplaySettings {
+    pub aggro_rings: bool,
+    pub tanking_warning: bool,
+    pub target_lines: bool,
+    /// Only NPCs this close to the player get overlays.
+    pub draw_distance: f32,
+}
+
+impl Default for ThreatDisplaySettings {
+    fn default() -> Self {
+        Self {
+            aggro_rings: true,
+            tanking_warning: true,
+            target_lines: false,
+            draw_distance: 60.0,
+        }
+    }
+}
+
+/// Player-facing options edited from the in-game settings menu. Systems
+/// mutate this resource directly; changes are written back to disk after a
+/// short delay so dragging a slider doesn't write every frame.
+#[derive(Resource, Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UserSettings {
+    pub audio: AudioSettings,
+    pub interface: InterfaceSettings,
+    pub camera: CameraSettings,
+    pub input: InputSettings,
+    pub privacy: PrivacySettings,
+    pub tutorials: TutorialSettings,
+    pub threat_display: ThreatDisplaySettings,
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct SettingsConfig {
+    pub path: PathBuf,
+    pub save_delay_seconds: f32,
+}
+
+impl Default for SettingsConfig {
+    fn default() -> Self {
+        Self {
+            path: PathBuf::from("user_settings.toml"),
+            save_delay_seconds: 1.0,
+        }
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+struct PendingSave {
+    remaining: Option<f32>,
+}
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        let config = app
+            .world()
+            .get_resource::<SettingsConfig>()
+            .cloned()
+            .unwrap_or_default();
+
+        // Loaded during build so other plugins' startup systems see the saved values
+        let settings = load_settings(&config);
+
+        app.insert_resource(config)
+            .insert_resource(settings)
+            .init_resource::<PendingSave>()
+            .add_systems(Update, save_settings_on_change);
+    }
+}
+
+fn load_settings(config: &SettingsConfig) -> UserSettings {
+    let Ok(text) = std::fs::read_to_string(&config.path) else {
+        info!("No user settings at {:?}, using defaults", config.path);
+        return UserSettings::default();
+    };
+    match toml::from_str(&text) {
+        Ok(settings) => settings,
+        Err(e) => {
+            warn!("Invalid user settings {:?}: {}, using defaults", config.path, e);
+            UserSettings::default()
+        }
+    }
+}
+
+fn save_settings_on_change(
+    time: Res<Time>,
+    config: Res<SettingsConfig>,
+    settings: Res<UserSettings>,
+    mut pending: ResMut<PendingSave>,
+) {
+    if settings.is_changed() && !settings.is_added() {
+        pending.remaining = Some(config.save_delay_seconds);
+    }
+
+    let Some(remaining) = pending.remaining.as_mut() else {
+        return;
+    };
+    *remaining -= time.delta_secs();
+    if *remaining > 0.0 {
+        return;
+    }
+    pending.remaining = None;
+
+    match toml::to_string_pretty(&*settings) {
+        Ok(text) => {
+            if let Err(e) = std::fs::write(&config.path, text) {
+                warn!("Failed to save user settings {:?}: {}", config.path, e);
+            }
+        }
+        Err(e) => warn!("Failed to serialize user settings: {}", e),
+    }
+}
@@ -0,0 +1,373 @@
+use bevy::audio::{AudioSinkPlayback, Volume};
+use bevy::prelude::*;
+use rand::Rng;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use super::mixer::{AudioBus, AudioMixer, MixerChannel};
+use crate::content_packs::ContentPacks;
+use crate::triggers::ZoneEnteredEvent;
+use crate::Player;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DayPeriod {
+    #[default]
+    Any,
+    Day,
+    Night,
+}
+
+impl DayPeriod {
+    pub fn from_hour(hour: f32, dawn: f32, dusk: f32) -> Self {
+        let hour = hour.rem_euclid(24.0);
+        if hour >= dawn && hour < dusk {
+            DayPeriod::Day
+        } else {
+            DayPeriod::Night
+        }
+    }
+
+    fn matches(self, current: DayPeriod) -> bool {
+        self == DayPeriod::Any || self == current
+    }
+}
+
+/// Randomized one-shot (a bird call, a distant wolf) placed around the listener.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AmbientEmitterDef {
+    pub clips: Vec<String>,
+    #[serde(default)]
+    pub period: DayPeriod,
+    /// Only plays during this weather; omit for any weather.
+    #[serde(default)]
+    pub weather: Option<String>,
+    #[serde(default = "default_interval_min")]
+    pub interval_min: f32,
+    #[serde(default = "default_interval_max")]
+    pub interval_max: f32,
+    #[serde(default = "default_distance_min")]
+    pub distance_min: f32,
+    #[serde(default = "default_distance_max")]
+    pub distance_max: f32,
+    #[serde(default = "default_ambience_volume")]
+    pub volume: f32,
+}
+
+fn default_interval_min() -> f32 {
+    8.0
+}
+
+fn default_interval_max() -> f32 {
+    20.0
+}
+
+fn default_distance_min() -> f32 {
+    10.0
+}
+
+fn default_distance_max() -> f32 {
+    35.0
+}
+
+fn default_ambience_volume() -> f32 {
+    1.0
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AmbientBedDef {
+    pub path: String,
+    #[serde(default)]
+    pub period: DayPeriod,
+    #[serde(default)]
+    pub weather: Option<String>,
+    #[serde(default = "default_ambience_volume")]
+    pub volume: f32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BiomeAmbienceDef {
+    pub biome: String,
+    /// Trigger-volume zones that use this biome's soundscape.
+    #[serde(default)]
+    pub zones: Vec<String>,
+    #[serde(default)]
+    pub beds: Vec<AmbientBedDef>,
+    #[serde(default)]
+    pub emitters: Vec<AmbientEmitterDef>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AmbienceContentFile {
+    #[serde(default, rename = "biome")]
+    pub biomes: Vec<BiomeAmbienceDef>,
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct AmbienceConfig {
+    pub content_directory: PathBuf,
+    pub crossfade_seconds: f32,
+    pub dawn_hour: f32,
+    pub dusk_hour: f32,
+}
+
+impl Default for AmbienceConfig {
+    fn default() -> Self {
+        Self {
+            content_directory: PathBuf::from("content").join("audio").join("ambience"),
+            crossfade_seconds: 4.0,
+            dawn_hour: 6.0,
+            dusk_hour: 20.0,
+        }
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct AmbienceLibrary {
+    pub biomes: HashMap<String, BiomeAmbienceDef>,
+    pub zone_biomes: HashMap<String, String>,
+}
+
+/// Inputs the soundscape is chosen from. The sky system keeps `hour` in sync
+/// with `TimeOfDay` and the weather system sets `weather`; the biome follows
+/// the player's zone unless set directly.
+#[derive(Resource, Debug, Clone)]
+pub struct AmbienceConditions {
+    pub biome: Option<String>,
+    pub hour: f32,
+    pub weather: Option<String>,
+}
+
+impl Default for AmbienceConditions {
+    fn default() -> Self {
+        Self {
+            biome: None,
+            hour: 12.0,
+            weather: None,
+        }
+    }
+}
+
+#[derive(Component, Debug)]
+pub struct AmbientBed {
+    pub key: String,
+    pub base_volume: f32,
+    pub current_volume: f32,
+    pub fading_out: bool,
+}
+
+#[derive(Resource, Debug, Default)]
+struct EmitterTimers {
+    biome: Option<String>,
+    remaining: Vec<f32>,
+}
+
+pub struct AmbiencePlugin;
+
+impl Plugin for AmbiencePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AmbienceConfig>()
+            .init_resource::<AmbienceLibrary>()
+            .init_resource::<AmbienceConditions>()
+            .init_resource::<EmitterTimers>()
+            .add_systems(Startup, load_ambience_content)
+            .add_systems(
+                Update,
+                (
+                    follow_player_biome,
+                    select_ambient_beds,
+                    fade_ambient_beds,
+                    play_ambient_emitters,
+                )
+                    .chain(),
+            );
+    }
+}
+
+fn load_ambience_content(config: Res<AmbienceConfig>, packs: Res<ContentPacks>, mut library: ResMut<AmbienceLibrary>) {
+    for path in packs.files(&config.content_directory, "toml") {
+        let parsed = std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| toml::from_str::<AmbienceContentFile>(&text).map_err(|e| e.to_string()));
+
+        match parsed {
+            Ok(file) => {
+                for biome in file.biomes {
+                    for zone in &biome.zones {
+                        library.zone_biomes.insert(zone.clone(), biome.biome.clone());
+                    }
+                    library.biomes.insert(biome.biome.clone(), biome);
+                }
+            }
+            Err(e) => warn!("Invalid ambience file {:?}: {}", path, e),
+        }
+    }
+
+    info!("Ambience: {} biomes loaded", library.biomes.len());
+}
+
+fn follow_player_biome(
+    library: Res<AmbienceLibrary>,
+    mut conditions: ResMut<AmbienceConditions>,
+    mut zone_events: EventReader<ZoneEnteredEvent>,
+    players: Query<(), With<Player>>,
+) {
+    for event in zone_events.read() {
+        if !players.contains(event.entity) {
+            continue;
+        }
+        if let Some(biome) = library.zone_biomes.get(&event.zone_id) {
+            if conditions.biome.as_ref() != Some(biome) {
+                conditions.biome = Some(biome.clone());
+            }
+        }
+    }
+}
+
+fn bed_key(biome: &str, bed: &AmbientBedDef) -> String {
+    format!("{}/{}", biome, bed.path)
+}
+
+/// Spawns beds that should be audible and marks the rest for fade-out.
+fn select_ambient_beds(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    config: Res<AmbienceConfig>,
+    library: Res<AmbienceLibrary>,
+    conditions: Res<AmbienceConditions>,
+    mut beds: Query<&mut AmbientBed>,
+) {
+    if !conditions.is_changed() && !library.is_changed() {
+        return;
+    }
+
+    let period = DayPeriod::from_hour(conditions.hour, config.dawn_hour, config.dusk_hour);
+    let wanted: Vec<(String, &AmbientBedDef)> = conditions
+        .biome
+        .as_ref()
+        .and_then(|biome| library.biomes.get(biome))
+        .map(|def| {
+            def.beds
+                .iter()
+                .filter(|bed| bed.period.matches(period))
+                .filter(|bed| bed.weather.is_none() || bed.weather == conditions.weather)
+                .map(|bed| (bed_key(&def.biome, bed), bed))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let mut existing = Vec::new();
+    for mut bed in beds.iter_mut() {
+        let keep = wanted.iter().any(|(key, _)| *key == bed.key);
+        bed.fading_out = !keep;
+        if keep {
+            existing.push(bed.key.clone());
+        }
+    }
+
+    for (key, bed) in wanted {
+        if existing.contains(&key) {
+            continue;
+        }
+        commands.spawn((
+            AudioPlayer::new(asset_server.load(bed.path.clone())),
+            PlaybackSettings::LOOP.with_volume(Volume::new(0.0)),
+            AmbientBed {
+                key: key.clone(),
+                base_volume: bed.volume,
+                current_volume: 0.0,
+                fading_out: false,
+            },
+            Name::new(format!("Ambience: {}", key)),
+        ));
+    }
+}
+
+fn fade_ambient_beds(
+    mut commands: Commands,
+    time: Res<Time>,
+    config: Res<AmbienceConfig>,
+    mixer: Res<AudioMixer>,
+    mut beds: Query<(Entity, &mut AmbientBed, Option<&AudioSink>)>,
+) {
+    let step = time.delta_secs() / config.crossfade_seconds.max(0.01);
+    let bus_volume = mixer.volume(AudioBus::Ambience);
+
+    for (entity, mut bed, sink) in beds.iter_mut() {
+        let target = if bed.fading_out { 0.0 } else { bed.base_volume };
+        bed.current_volume += (target - bed.current_volume).clamp(-step, step);
+
+        if let Some(sink) = sink {
+            sink.set_volume(bed.current_volume * bus_volume);
+        }
+        if bed.fading_out && bed.current_volume <= 0.001 {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+fn play_ambient_emitters(
+    mut commands: Commands,
+    time: Res<Time>,
+    asset_server: Res<AssetServer>,
+    config: Res<AmbienceConfig>,
+    mixer: Res<AudioMixer>,
+    library: Res<AmbienceLibrary>,
+    conditions: Res<AmbienceConditions>,
+    mut timers: ResMut<EmitterTimers>,
+    listeners: Query<&GlobalTransform, With<SpatialListener>>,
+) {
+    let Some(def) = conditions.biome.as_ref().and_then(|b| library.biomes.get(b)) else {
+        return;
+    };
+    let Some(listener) = listeners.iter().next().map(|t| t.translation()) else {
+        return;
+    };
+    let mut rng = rand::thread_rng();
+
+    // New biome: stagger first plays instead of firing everything at once
+    if timers.biome.as_ref() != Some(&def.biome) || timers.remaining.len() != def.emitters.len() {
+        timers.biome = Some(def.biome.clone());
+        timers.remaining = def
+            .emitters
+            .iter()
+            .map(|e| rng.gen_range(0.0..=e.interval_max.max(0.1)))
+            .collect();
+    }
+
+    let period = DayPeriod::from_hour(conditions.hour, config.dawn_hour, config.dusk_hour);
+    let dt = time.delta_secs();
+
+    for (emitter, remaining) in def.emitters.iter().zip(timers.remaining.iter_mut()) {
+        *remaining -= dt;
+        if *remaining > 0.0 {
+            continue;
+        }
+        let (min, max) = (emitter.interval_min.min(emitter.interval_max), emitter.interval_max.max(emitter.interval_min));
+        *remaining = rng.gen_range(min..=max.max(min + 0.01));
+
+        if !emitter.period.matches(period) || emitter.clips.is_empty() {
+            continue;
+        }
+        if emitter.weather.is_some() && emitter.weather != conditions.weather {
+            continue;
+        }
+
+        let angle = rng.gen_range(0.0..std::f32::consts::TAU);
+        let distance = rng.gen_range(emitter.distance_min..=emitter.distance_max.max(emitter.distance_min));
+        let height = rng.gen_range(0.0..6.0);
+        let position = listener + Vec3::new(angle.cos() * distance, height, angle.sin() * distance);
+        let clip = &emitter.clips[rng.gen_range(0..emitter.clips.len())];
+
+        commands.spawn((
+            AudioPlayer::new(asset_server.load(clip.clone())),
+            PlaybackSettings::DESPAWN
+                .with_spatial(true)
+                .with_volume(Volume::new(emitter.volume * mixer.volume(AudioBus::Ambience)))
+                .with_speed(rng.gen_range(0.95..1.05)),
+            MixerChannel::new(AudioBus::Ambience, emitter.volume),
+            Transform::from_translation(position),
+        ));
+    }
+}
@@ -0,0 +1,269 @@
+use bevy::audio::Volume;
+use bevy::prelude::*;
+use rand::Rng;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use super::mixer::{AudioBus, AudioMixer, MixerChannel};
+use crate::content_packs::ContentPacks;
+
+/// Distance falloff for positional cues. Beyond `max_distance` the cue is not
+/// played at all, which keeps distant fights from spawning sinks.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct CueAttenuation {
+    #[serde(default = "default_min_distance")]
+    pub min_distance: f32,
+    #[serde(default = "default_max_distance")]
+    pub max_distance: f32,
+}
+
+fn default_min_distance() -> f32 {
+    2.0
+}
+
+fn default_max_distance() -> f32 {
+    40.0
+}
+
+impl CueAttenuation {
+    pub fn gain(&self, distance: f32) -> f32 {
+        if distance <= self.min_distance {
+            return 1.0;
+        }
+        let range = (self.max_distance - self.min_distance).max(0.01);
+        (1.0 - (distance - self.min_distance) / range).clamp(0.0, 1.0)
+    }
+}
+
+/// One entry of the audio event table. `event` is a namespaced key such as
+/// `ability:fireball:cast`, `ui:button_click` or `monster:wolf:aggro`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SoundCueDef {
+    pub event: String,
+    pub clips: Vec<String>,
+    #[serde(default = "default_cue_bus")]
+    pub bus: AudioBus,
+    #[serde(default = "default_cue_volume")]
+    pub volume: f32,
+    /// Random volume offset applied per play, +/-.
+    #[serde(default)]
+    pub volume_variation: f32,
+    #[serde(default = "default_pitch")]
+    pub pitch_min: f32,
+    #[serde(default = "default_pitch")]
+    pub pitch_max: f32,
+    /// Minimum seconds between plays of this cue from the same source.
+    #[serde(default)]
+    pub cooldown: f32,
+    /// Avoid picking the same clip twice in a row.
+    #[serde(default = "default_true")]
+    pub avoid_repeat: bool,
+    /// Omit for 2D (UI) cues.
+    #[serde(default)]
+    pub attenuation: Option<CueAttenuation>,
+}
+
+fn default_cue_bus() -> AudioBus {
+    AudioBus::Sfx
+}
+
+fn default_cue_volume() -> f32 {
+    1.0
+}
+
+fn default_pitch() -> f32 {
+    1.0
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct SoundCueFile {
+    #[serde(default, rename = "cue")]
+    pub cues: Vec<SoundCueDef>,
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct SoundCueConfig {
+    pub content_directory: PathBuf,
+    /// Cap on simultaneous instances started in one frame, per cue.
+    pub max_per_frame: usize,
+}
+
+impl Default for SoundCueConfig {
+    fn default() -> Self {
+        Self {
+            content_directory: PathBuf::from("content").join("audio").join("events"),
+            max_per_frame: 4,
+        }
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct SoundCueTable {
+    pub cues: HashMap<String, SoundCueDef>,
+    last_played: HashMap<(String, Option<Entity>), f32>,
+    last_clip: HashMap<String, usize>,
+}
+
+/// Gameplay asks for a sound by event key; the table decides what, if anything, plays.
+#[derive(Event, Debug, Clone)]
+pub struct PlaySoundCue {
+    pub event: String,
+    pub position: Option<Vec3>,
+    /// Used for per-source cooldowns, and for the position when `position` is unset.
+    pub source: Option<Entity>,
+}
+
+impl PlaySoundCue {
+    pub fn new(event: impl Into<String>) -> Self {
+        Self {
+            event: event.into(),
+            position: None,
+            source: None,
+        }
+    }
+
+    pub fn ability(ability_id: &str, phase: &str) -> Self {
+        Self::new(format!("ability:{}:{}", ability_id, phase))
+    }
+
+    pub fn ui(action: &str) -> Self {
+        Self::new(format!("ui:{}", action))
+    }
+
+    pub fn monster(monster_type: &str, action: &str) -> Self {
+        Self::new(format!("monster:{}:{}", monster_type, action))
+    }
+
+    pub fn at(mut self, position: Vec3) -> Self {
+        self.position = Some(position);
+        self
+    }
+
+    pub fn with_source(mut self, entity: Entity) -> Self {
+        self.source = Some(entity);
+        self
+    }
+}
+
+pub struct SoundCuePlugin;
+
+impl Plugin for SoundCuePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SoundCueConfig>()
+            .init_resource::<SoundCueTable>()
+            .add_event::<PlaySoundCue>()
+            .add_systems(Startup, load_sound_cues)
+            .add_systems(Update, play_sound_cues);
+    }
+}
+
+fn load_sound_cues(config: Res<SoundCueConfig>, packs: Res<ContentPacks>, mut table: ResMut<SoundCueTable>) {
+    for path in packs.files(&config.content_directory, "toml") {
+        let parsed = std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| toml::from_str::<SoundCueFile>(&text).map_err(|e| e.to_string()));
+
+        match parsed {
+            Ok(file) => {
+                for cue in file.cues {
+                    if cue.clips.is_empty() {
+                        warn!("Audio event '{}' in {:?} has no clips", cue.event, path);
+                        continue;
+                    }
+                    if table.cues.insert(cue.event.clone(), cue).is_some() {
+                        warn!("Audio event redefined in {:?}", path);
+                    }
+                }
+            }
+            Err(e) => warn!("Invalid audio event table {:?}: {}", path, e),
+        }
+    }
+
+    info!("Audio events: {} cues loaded", table.cues.len());
+}
+
+fn play_sound_cues(
+    mut commands: Commands,
+    time: Res<Time>,
+    asset_server: Res<AssetServer>,
+    config: Res<SoundCueConfig>,
+    mixer: Res<AudioMixer>,
+    mut table: ResMut<SoundCueTable>,
+    mut events: EventReader<PlaySoundCue>,
+    transforms: Query<&GlobalTransform>,
+    listeners: Query<&GlobalTransform, With<SpatialListener>>,
+) {
+    let now = time.elapsed_secs();
+    let listener = listeners.iter().next().map(|t| t.translation());
+    let mut started: HashMap<&str, usize> = HashMap::new();
+    let mut rng = rand::thread_rng();
+
+    for event in events.read() {
+        let Some(cue) = table.cues.get(&event.event).cloned() else {
+            debug!("Audio event '{}' has no cue", event.event);
+            continue;
+        };
+
+        let count = started.entry(event.event.as_str()).or_default();
+        if *count >= config.max_per_frame {
+            continue;
+        }
+
+        let cooldown_key = (event.event.clone(), event.source);
+        if cue.cooldown > 0.0 {
+            if let Some(last) = table.last_played.get(&cooldown_key) {
+                if now - last < cue.cooldown {
+                    continue;
+                }
+            }
+        }
+
+        let position = event
+            .position
+            .or_else(|| event.source.and_then(|e| transforms.get(e).ok()).map(|t| t.translation()));
+
+        let mut gain = cue.volume + rng.gen_range(-cue.volume_variation..=cue.volume_variation);
+        if let (Some(attenuation), Some(position), Some(listener)) = (cue.attenuation, position, listener) {
+            let distance = position.distance(listener);
+            if distance > attenuation.max_distance {
+                continue;
+            }
+            gain *= attenuation.gain(distance);
+        }
+        let gain = gain.max(0.0);
+
+        let mut index = rng.gen_range(0..cue.clips.len());
+        if cue.avoid_repeat && cue.clips.len() > 1 && table.last_clip.get(&cue.event) == Some(&index) {
+            index = (index + 1) % cue.clips.len();
+        }
+        let (pitch_min, pitch_max) = (cue.pitch_min.min(cue.pitch_max), cue.pitch_min.max(cue.pitch_max));
+        let pitch = rng.gen_range(pitch_min..=pitch_max);
+
+        let spatial = cue.attenuation.is_some() && position.is_some();
+        let mut entity = commands.spawn((
+            AudioPlayer::new(asset_server.load(cue.clips[index].clone())),
+            PlaybackSettings::DESPAWN
+                .with_spatial(spatial)
+                .with_volume(Volume::new(gain * mixer.volume(cue.bus)))
+                .with_speed(pitch),
+            MixerChannel::new(cue.bus, gain),
+        ));
+        if let Some(position) = position.filter(|_| spatial) {
+            entity.insert(Transform::from_translation(position));
+        }
+
+        *count += 1;
+        table.last_played.insert(cooldown_key, now);
+        table.last_clip.insert(cue.event.clone(), index);
+    }
+
+    // Keep the cooldown map from growing with every short-lived source
+    if table.last_played.len() > 1024 {
+        table.last_played.retain(|_, last| now - *last < 60.0);
+    }
+}
@@ -0,0 +1,437 @@
+use bevy::audio::Volume;
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use super::mixer::{AudioBus, AudioMixer, MixerChannel};
+use crate::content_packs::ContentPacks;
+use crate::engine_fabric::physics::{CharacterController, SurfaceMaterial};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArmorClass {
+    #[default]
+    Cloth,
+    Leather,
+    Mail,
+    Plate,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FootstepKind {
+    Walk,
+    Run,
+    Land,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct SurfaceSoundSet {
+    pub surface: SurfaceMaterial,
+    #[serde(default)]
+    pub walk: Vec<String>,
+    #[serde(default)]
+    pub run: Vec<String>,
+    #[serde(default)]
+    pub land: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ArmorSoundSet {
+    pub armor: ArmorClass,
+    #[serde(default)]
+    pub movement: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct FoleyContentFile {
+    #[serde(default)]
+    pub surfaces: Vec<SurfaceSoundSet>,
+    #[serde(default)]
+    pub armor: Vec<ArmorSoundSet>,
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct FoleyConfig {
+    pub content_path: PathBuf,
+    pub hearing_radius: f32,
+    pub run_speed_threshold: f32,
+    pub pitch_variation: f32,
+    /// Land sounds only play above this vertical speed.
+    pub min_land_speed: f32,
+}
+
+impl Default for FoleyConfig {
+    fn default() -> Self {
+        Self {
+            content_path: PathBuf::from("content").join("audio").join("foley.toml"),
+            hearing_radius: 30.0,
+            run_speed_threshold: 4.5,
+            pitch_variation: 0.08,
+            min_land_speed: 3.0,
+        }
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct FoleyLibrary {
+    pub surfaces: HashMap<SurfaceMaterial, SurfaceSoundSet>,
+    pub armor: HashMap<ArmorClass, ArmorSoundSet>,
+}
+
+/// Terrain splat map sampled when a foot lands on terrain rather than a
+/// tagged prop. The terrain builder fills one cell per splat texel.
+#[derive(Resource, Debug, Default)]
+pub struct TerrainSurfaceMap {
+    pub origin: Vec2,
+    pub cell_size: f32,
+    pub width: usize,
+    pub cells: Vec<SurfaceMaterial>,
+}
+
+impl TerrainSurfaceMap {
+    pub fn sample(&self, position: Vec3) -> Option<SurfaceMaterial> {
+        if self.width == 0 || self.cell_size <= 0.0 {
+            return None;
+        }
+        let local = (Vec2::new(position.x, position.z) - self.origin) / self.cell_size;
+        if local.x < 0.0 || local.y < 0.0 {
+            return None;
+        }
+        let (x, z) = (local.x as usize, local.y as usize);
+        if x >= self.width {
+            return None;
+        }
+        self.cells.get(z * self.width + x).copied()
+    }
+}
+
+/// Characters that make footstep noise.
+#[derive(Component, Debug, Clone)]
+pub struct FoleyEmitter {
+    pub armor: ArmorClass,
+    /// Distance per step for characters without footstep animation events.
+    pub stride_length: f32,
+    pub uses_animation_events: bool,
+    pub distance_since_step: f32,
+    pub last_position: Option<Vec3>,
+}
+
+impl Default for FoleyEmitter {
+    fn default() -> Self {
+        Self {
+            armor: ArmorClass::default(),
+            stride_length: 1.4,
+            uses_animation_events: false,
+            distance_since_step: 0.0,
+            last_position: None,
+        }
+    }
+}
+
+/// Replicated for the local player so nearby clients hear its steps.
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct ReplicateFootsteps;
+
+/// Animation event placed on walk/run clips at each foot plant.
+#[derive(Event, Reflect, Debug, Clone, Copy)]
+pub struct FootstepAnimEvent;
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct FootstepEvent {
+    pub entity: Entity,
+    pub position: Vec3,
+    pub kind: FootstepKind,
+    pub speed: f32,
+}
+
+/// Footstep to send to or received from the network.
+#[derive(Event, Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct NetworkFootstep {
+    pub position: [f32; 3],
+    pub surface: SurfaceMaterial,
+    pub armor: ArmorClass,
+    pub kind: FootstepKind,
+    pub speed: f32,
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct OutgoingFootstep(pub NetworkFootstep);
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct IncomingFootstep(pub NetworkFootstep);
+
+pub struct FoleyPlugin;
+
+impl Plugin for FoleyPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FoleyConfig>()
+            .init_resource::<FoleyLibrary>()
+            .init_resource::<TerrainSurfaceMap>()
+            .add_event::<FootstepEvent>()
+            .add_event::<OutgoingFootstep>()
+            .add_event::<IncomingFootstep>()
+            .add_observer(on_footstep_anim_event)
+            .add_systems(Startup, load_foley_content)
+            .add_systems(Update, (detect_distance_steps, play_footsteps, play_remote_footsteps).chain());
+    }
+}
+
+fn load_foley_content(config: Res<FoleyConfig>, packs: Res<ContentPacks>, mut library: ResMut<FoleyLibrary>) {
+    let Some(path) = packs.resolve(&config.content_path) else {
+        info!("No foley content at {:?}", config.content_path);
+        return;
+    };
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) => {
+            warn!("Failed to read foley file {:?}: {}", path, e);
+            return;
+        }
+    };
+    match toml::from_str::<FoleyContentFile>(&text) {
+        Ok(file) => {
+            library.surfaces = file.surfaces.into_iter().map(|s| (s.surface, s)).collect();
+            library.armor = file.armor.into_iter().map(|a| (a.armor, a)).collect();
+            info!("Foley: {} surface sets, {} armor sets", library.surfaces.len(), library.armor.len());
+        }
+        Err(e) => warn!("Invalid foley file {:?}: {}", path, e),
+    }
+}
+
+/// Animation events fire on the entity holding the `AnimationPlayer`, which
+/// is a descendant of the character root that carries the `FoleyEmitter`.
+fn on_footstep_anim_event(
+    trigger: Trigger<FootstepAnimEvent>,
+    parents: Query<&Parent>,
+    emitters: Query<(&GlobalTransform, Option<&CharacterController>), With<FoleyEmitter>>,
+    config: Res<FoleyConfig>,
+    mut steps: EventWriter<FootstepEvent>,
+) {
+    let mut current = trigger.entity();
+    for _ in 0..16 {
+        if let Ok((transform, controller)) = emitters.get(current) {
+            if controller.is_some_and(|c| !c.ground_info.is_grounded()) {
+                return;
+            }
+            let speed = controller.map_or(0.0, |c| c.get_current_speed());
+            steps.send(FootstepEvent {
+                entity: current,
+                position: transform.translation(),
+                kind: if speed >= config.run_speed_threshold {
+                    FootstepKind::Run
+                } else {
+                    FootstepKind::Walk
+                },
+                speed,
+            });
+            return;
+        }
+        match parents.get(current) {
+            Ok(parent) => current = parent.get(),
+            Err(_) => return,
+        }
+    }
+}
+
+fn detect_distance_steps(
+    time: Res<Time>,
+    config: Res<FoleyConfig>,
+    mut emitters: Query<(Entity, &mut FoleyEmitter, &GlobalTransform, Option<&CharacterController>)>,
+    mut steps: EventWriter<FootstepEvent>,
+) {
+    let dt = time.delta_secs();
+
+    for (entity, mut emitter, transform, controller) in emitters.iter_mut() {
+        let position = transform.translation();
+
+        if let Some(controller) = controller {
+            if let Some(landing_speed) = controller.landed_this_step() {
+                if landing_speed >= config.min_land_speed {
+                    steps.send(FootstepEvent {
+                        entity,
+                        position,
+                        kind: FootstepKind::Land,
+                        speed: landing_speed,
+                    });
+                }
+            }
+            if !controller.ground_info.is_grounded() {
+                emitter.last_position = Some(position);
+                continue;
+            }
+        }
+
+        let Some(last) = emitter.last_position.replace(position) else {
+            continue;
+        };
+        if emitter.uses_animation_events || dt <= 0.0 {
+            continue;
+        }
+
+        let moved = Vec2::new(position.x - last.x, position.z - last.z).length();
+        emitter.distance_since_step += moved;
+        if emitter.distance_since_step < emitter.stride_length {
+            continue;
+        }
+        emitter.distance_since_step = 0.0;
+
+        let speed = moved / dt;
+        steps.send(FootstepEvent {
+            entity,
+            position,
+            kind: if speed >= config.run_speed_threshold {
+                FootstepKind::Run
+            } else {
+                FootstepKind::Walk
+            },
+            speed,
+        });
+    }
+}
+
+/// Ground collider tag first, then the terrain splat map, then dirt.
+fn resolve_surface(
+    rapier_context: &RapierContext,
+    surfaces: &Query<&SurfaceMaterial>,
+    terrain: &TerrainSurfaceMap,
+    entity: Entity,
+    ground: Option<Entity>,
+    position: Vec3,
+) -> SurfaceMaterial {
+    if let Some(material) = ground.and_then(|ground| surfaces.get(ground).ok()) {
+        return *material;
+    }
+
+    let filter = QueryFilter::default().exclude_collider(entity).exclude_rigid_body(entity).exclude_sensors();
+    let hit = rapier_context.cast_ray(position + Vec3::Y * 0.5, Vec3::NEG_Y, 2.0, true, filter);
+
+    hit.and_then(|(collider, _)| surfaces.get(collider).ok().copied())
+        .or_else(|| terrain.sample(position))
+        .unwrap_or(SurfaceMaterial::Dirt)
+}
+
+fn pick_clip(library: &FoleyLibrary, surface: SurfaceMaterial, kind: FootstepKind) -> Option<&String> {
+    let set = library.surfaces.get(&surface)?;
+    let clips = match kind {
+        FootstepKind::Walk => &set.walk,
+        FootstepKind::Run if !set.run.is_empty() => &set.run,
+        FootstepKind::Run => &set.walk,
+        FootstepKind::Land if !set.land.is_empty() => &set.land,
+        FootstepKind::Land => &set.run,
+    };
+    clips.choose(&mut rand::thread_rng())
+}
+
+fn spawn_step_sound(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    library: &FoleyLibrary,
+    config: &FoleyConfig,
+    mixer: &AudioMixer,
+    step: &NetworkFootstep,
+) {
+    let bus_volume = mixer.volume(AudioBus::Sfx);
+    let mut rng = rand::thread_rng();
+    let position = Vec3::from_array(step.position);
+
+    // Faster movement is louder and slightly higher pitched
+    let intensity = match step.kind {
+        FootstepKind::Walk => 0.5,
+        FootstepKind::Run => 0.8,
+        FootstepKind::Land => (step.speed / 10.0).clamp(0.6, 1.0),
+    };
+    let pitch = 1.0 + rng.gen_range(-config.pitch_variation..=config.pitch_variation) + (step.speed * 0.01).min(0.1);
+
+    if let Some(clip) = pick_clip(library, step.surface, step.kind) {
+        commands.spawn((
+            AudioPlayer::new(asset_server.load(clip.clone())),
+            PlaybackSettings::DESPAWN
+                .with_spatial(true)
+                .with_volume(Volume::new(intensity * bus_volume))
+                .with_speed(pitch),
+            MixerChannel::new(AudioBus::Sfx, intensity),
+            Transform::from_translation(position),
+        ));
+    }
+
+    if step.kind != FootstepKind::Walk || step.armor == ArmorClass::Plate || step.armor == ArmorClass::Mail {
+        if let Some(clip) = library.armor.get(&step.armor).and_then(|a| a.movement.choose(&mut rng)) {
+            commands.spawn((
+                AudioPlayer::new(asset_server.load(clip.clone())),
+                PlaybackSettings::DESPAWN
+                    .with_spatial(true)
+                    .with_volume(Volume::new(intensity * 0.6 * bus_volume))
+                    .with_speed(pitch),
+                MixerChannel::new(AudioBus::Sfx, intensity * 0.6),
+                Transform::from_translation(position),
+            ));
+        }
+    }
+}
+
+fn play_footsteps(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    config: Res<FoleyConfig>,
+    mixer: Res<AudioMixer>,
+    library: Res<FoleyLibrary>,
+    terrain: Res<TerrainSurfaceMap>,
+    rapier_context: ReadRapierContext,
+    mut steps: EventReader<FootstepEvent>,
+    emitters: Query<(&FoleyEmitter, Option<&CharacterController>, Has<ReplicateFootsteps>)>,
+    surfaces: Query<&SurfaceMaterial>,
+    mut outgoing: EventWriter<OutgoingFootstep>,
+) {
+    let Ok(rapier_context) = rapier_context.single() else {
+        steps.clear();
+        return;
+    };
+
+    for step in steps.read() {
+        let Ok((emitter, controller, replicate)) = emitters.get(step.entity) else {
+            continue;
+        };
+        let ground = controller.and_then(|c| c.ground_info.ground_entity);
+        let surface = resolve_surface(&rapier_context, &surfaces, &terrain, step.entity, ground, step.position);
+        let footstep = NetworkFootstep {
+            position: step.position.to_array(),
+            surface,
+            armor: emitter.armor,
+            kind: step.kind,
+            speed: step.speed,
+        };
+
+        spawn_step_sound(&mut commands, &asset_server, &library, &config, &mixer, &footstep);
+
+        if replicate {
+            outgoing.send(OutgoingFootstep(footstep));
+        }
+    }
+}
+
+fn play_remote_footsteps(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    config: Res<FoleyConfig>,
+    mixer: Res<AudioMixer>,
+    library: Res<FoleyLibrary>,
+    listeners: Query<&GlobalTransform, With<SpatialListener>>,
+    mut incoming: EventReader<IncomingFootstep>,
+) {
+    let Some(listener) = listeners.iter().next().map(|t| t.translation()) else {
+        incoming.clear();
+        return;
+    };
+    let radius_sq = config.hearing_radius * config.hearing_radius;
+
+    for IncomingFootstep(step) in incoming.read() {
+        if listener.distance_squared(Vec3::from_array(step.position)) > radius_sq {
+            continue;
+        }
+        spawn_step_sound(&mut commands, &asset_server, &library, &config, &mixer, step);
+    }
+}
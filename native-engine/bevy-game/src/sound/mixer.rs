@@ -0,0 +1,256 @@
+use bevy::audio::AudioSinkPlayback;
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::hazards::Breath;
+use crate::settings::UserSettings;
+use crate::Player;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AudioBus {
+    Master,
+    Music,
+    Sfx,
+    Voice,
+    Ambience,
+}
+
+impl AudioBus {
+    pub const ALL: [AudioBus; 5] = [
+        AudioBus::Master,
+        AudioBus::Music,
+        AudioBus::Sfx,
+        AudioBus::Voice,
+        AudioBus::Ambience,
+    ];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Whole-mix states. Each scales the buses so menus, underwater and death
+/// sound different without every emitter knowing about them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum MixerSnapshot {
+    #[default]
+    Gameplay,
+    Menu,
+    Underwater,
+    Death,
+}
+
+impl MixerSnapshot {
+    /// Multiplier per bus, indexed like `AudioBus::ALL`.
+    pub fn bus_scales(self) -> [f32; 5] {
+        match self {
+            MixerSnapshot::Gameplay => [1.0, 1.0, 1.0, 1.0, 1.0],
+            MixerSnapshot::Menu => [1.0, 0.6, 0.3, 1.0, 0.3],
+            MixerSnapshot::Underwater => [1.0, 0.5, 0.35, 0.6, 0.2],
+            MixerSnapshot::Death => [1.0, 0.4, 0.2, 0.8, 0.4],
+        }
+    }
+}
+
+/// Sidechain rule: while anything plays on `trigger`, `target` is pulled down
+/// to `ducked_level`.
+#[derive(Debug, Clone)]
+pub struct DuckingRule {
+    pub trigger: AudioBus,
+    pub target: AudioBus,
+    pub ducked_level: f32,
+    pub attack_seconds: f32,
+    pub release_seconds: f32,
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct MixerConfig {
+    pub ducking: Vec<DuckingRule>,
+    pub snapshot_fade_seconds: f32,
+}
+
+impl Default for MixerConfig {
+    fn default() -> Self {
+        Self {
+            ducking: vec![
+                DuckingRule {
+                    trigger: AudioBus::Voice,
+                    target: AudioBus::Music,
+                    ducked_level: 0.4,
+                    attack_seconds: 0.2,
+                    release_seconds: 1.0,
+                },
+                DuckingRule {
+                    trigger: AudioBus::Voice,
+                    target: AudioBus::Ambience,
+                    ducked_level: 0.7,
+                    attack_seconds: 0.2,
+                    release_seconds: 1.0,
+                },
+            ],
+            snapshot_fade_seconds: 0.75,
+        }
+    }
+}
+
+/// Current mix. User volumes come from `UserSettings`; ducking and snapshot
+/// scales are driven by the mixer systems.
+#[derive(Resource, Debug)]
+pub struct AudioMixer {
+    user_volumes: [f32; 5],
+    duck_levels: [f32; 5],
+    snapshot_scales: [f32; 5],
+    pub snapshot: MixerSnapshot,
+    /// Snapshot requested by gameplay/UI; automatic states (underwater) apply on top of `Gameplay`.
+    pub requested_snapshot: MixerSnapshot,
+}
+
+impl Default for AudioMixer {
+    fn default() -> Self {
+        Self {
+            user_volumes: [1.0; 5],
+            duck_levels: [1.0; 5],
+            snapshot_scales: MixerSnapshot::Gameplay.bus_scales(),
+            snapshot: MixerSnapshot::Gameplay,
+            requested_snapshot: MixerSnapshot::Gameplay,
+        }
+    }
+}
+
+impl AudioMixer {
+    /// Final gain for a bus, including master.
+    pub fn volume(&self, bus: AudioBus) -> f32 {
+        let master = self.bus_gain(AudioBus::Master);
+        if bus == AudioBus::Master {
+            master
+        } else {
+            master * self.bus_gain(bus)
+        }
+    }
+
+    fn bus_gain(&self, bus: AudioBus) -> f32 {
+        let i = bus.index();
+        self.user_volumes[i] * self.duck_levels[i] * self.snapshot_scales[i]
+    }
+
+    pub fn user_volume(&self, bus: AudioBus) -> f32 {
+        self.user_volumes[bus.index()]
+    }
+}
+
+/// Routes a playing sound through a bus. `volume` is the sound's own gain;
+/// the sink is kept at `volume * mixer.volume(bus)`.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct MixerChannel {
+    pub bus: AudioBus,
+    pub volume: f32,
+}
+
+impl MixerChannel {
+    pub fn new(bus: AudioBus, volume: f32) -> Self {
+        Self { bus, volume }
+    }
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct SetMixerSnapshotEvent(pub MixerSnapshot);
+
+pub struct MixerPlugin;
+
+impl Plugin for MixerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MixerConfig>()
+            .init_resource::<AudioMixer>()
+            .add_event::<SetMixerSnapshotEvent>()
+            .add_systems(
+                Update,
+                (
+                    sync_user_volumes,
+                    select_snapshot,
+                    update_ducking,
+                    apply_channel_volumes,
+                )
+                    .chain(),
+            );
+    }
+}
+
+fn sync_user_volumes(settings: Option<Res<UserSettings>>, mut mixer: ResMut<AudioMixer>) {
+    let Some(settings) = settings else {
+        return;
+    };
+    if !settings.is_changed() {
+        return;
+    }
+    let audio = &settings.audio;
+    mixer.user_volumes = [audio.master, audio.music, audio.sfx, audio.voice, audio.ambience]
+        .map(|v| v.clamp(0.0, 1.0));
+}
+
+fn select_snapshot(
+    time: Res<Time>,
+    config: Res<MixerConfig>,
+    mut mixer: ResMut<AudioMixer>,
+    mut events: EventReader<SetMixerSnapshotEvent>,
+    players: Query<&Breath, With<Player>>,
+) {
+    if let Some(SetMixerSnapshotEvent(snapshot)) = events.read().last() {
+        mixer.requested_snapshot = *snapshot;
+    }
+
+    let underwater = players.iter().any(|breath| breath.submerged);
+    let snapshot = match mixer.requested_snapshot {
+        MixerSnapshot::Gameplay if underwater => MixerSnapshot::Underwater,
+        requested => requested,
+    };
+    if snapshot != mixer.snapshot {
+        debug!("Mixer: snapshot {:?} -> {:?}", mixer.snapshot, snapshot);
+        mixer.snapshot = snapshot;
+    }
+
+    let targets = snapshot.bus_scales();
+    let step = time.delta_secs() / config.snapshot_fade_seconds.max(0.01);
+    for (scale, target) in mixer.snapshot_scales.iter_mut().zip(targets) {
+        *scale += (target - *scale).clamp(-step, step);
+    }
+}
+
+fn update_ducking(
+    time: Res<Time>,
+    config: Res<MixerConfig>,
+    mut mixer: ResMut<AudioMixer>,
+    channels: Query<(&MixerChannel, &AudioSink)>,
+) {
+    let mut active = [false; 5];
+    for (channel, sink) in channels.iter() {
+        if !sink.empty() && !sink.is_paused() {
+            active[channel.bus.index()] = true;
+        }
+    }
+
+    let dt = time.delta_secs();
+    let mut targets = [1.0f32; 5];
+    let mut rates = [f32::MAX; 5];
+    for rule in &config.ducking {
+        let target = rule.target.index();
+        if active[rule.trigger.index()] {
+            targets[target] = targets[target].min(rule.ducked_level);
+            rates[target] = rates[target].min(rule.attack_seconds);
+        } else {
+            rates[target] = rates[target].min(rule.release_seconds);
+        }
+    }
+
+    for i in 0..5 {
+        let fade = if rates[i] == f32::MAX { 0.01 } else { rates[i].max(0.01) };
+        let step = dt / fade;
+        mixer.duck_levels[i] += (targets[i] - mixer.duck_levels[i]).clamp(-step, step);
+    }
+}
+
+fn apply_channel_volumes(mixer: Res<AudioMixer>, channels: Query<(&MixerChannel, &AudioSink)>) {
+    for (channel, sink) in channels.iter() {
+        sink.set_volume(channel.volume * mixer.volume(channel.bus));
+    }
+}
@@ -0,0 +1,37 @@
+//! Game-side audio direction layered on top of `audio::AudioPlugin`:
+//! the mixer buses everything routes through, music, biome ambience,
+//! surface-aware foley, dialog voice-over with subtitles, and the
+//! content-driven audio event table that decides what plays when.
+
+use bevy::prelude::*;
+
+use crate::content_packs::ContentPacks;
+
+pub mod ambience;
+pub mod cues;
+pub mod foley;
+pub mod mixer;
+pub mod music;
+pub mod voice;
+
+pub use ambience::*;
+pub use cues::*;
+pub use foley::*;
+pub use mixer::*;
+pub use music::*;
+pub use voice::*;
+
+pub struct SoundPlugin;
+
+impl Plugin for SoundPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ContentPacks>().add_plugins((
+            MixerPlugin,
+            MusicPlugin,
+            AmbiencePlugin,
+            FoleyPlugin,
+            SoundCuePlugin,
+            VoicePlugin,
+        ));
+    }
+}
@@ -0,0 +1,416 @@
+use bevy::audio::{AudioSinkPlayback, Volume};
+use bevy::prelude::*;
+use rand::seq::SliceRandom;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use super::mixer::{AudioBus, AudioMixer, MixerChannel};
+use crate::content_packs::ContentPacks;
+use crate::triggers::ZoneEnteredEvent;
+use crate::Player;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MusicLayer {
+    Exploration,
+    CombatLow,
+    CombatHigh,
+    Boss,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MusicStemDef {
+    pub layer: MusicLayer,
+    pub path: String,
+    #[serde(default = "default_stem_volume")]
+    pub volume: f32,
+}
+
+fn default_stem_volume() -> f32 {
+    1.0
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ZoneMusicDef {
+    pub zone: String,
+    #[serde(default)]
+    pub stems: Vec<MusicStemDef>,
+    /// Exploration tracks played in sequence instead of a looping exploration stem.
+    #[serde(default)]
+    pub playlist: Vec<String>,
+    #[serde(default)]
+    pub shuffle: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct StingerDef {
+    pub id: String,
+    pub path: String,
+    #[serde(default = "default_stinger_duck")]
+    pub duck_seconds: f32,
+}
+
+fn default_stinger_duck() -> f32 {
+    4.0
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct MusicContentFile {
+    #[serde(default)]
+    pub zones: Vec<ZoneMusicDef>,
+    #[serde(default)]
+    pub stingers: Vec<StingerDef>,
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct MusicConfig {
+    pub content_directory: PathBuf,
+    pub crossfade_seconds: f32,
+    pub layer_fade_seconds: f32,
+    pub ducked_volume: f32,
+}
+
+impl Default for MusicConfig {
+    fn default() -> Self {
+        Self {
+            content_directory: PathBuf::from("content").join("music"),
+            crossfade_seconds: 3.0,
+            layer_fade_seconds: 1.5,
+            ducked_volume: 0.35,
+        }
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct MusicLibrary {
+    pub zones: HashMap<String, ZoneMusicDef>,
+    pub stingers: HashMap<String, StingerDef>,
+}
+
+/// Current musical state. Gameplay feeds combat intensity (0..1) from threat
+/// and sets the boss phase; the director turns that into stem volumes.
+#[derive(Resource, Debug, Default)]
+pub struct MusicDirector {
+    pub zone: Option<String>,
+    pub combat_intensity: f32,
+    pub boss_phase: Option<u32>,
+    pub duck_remaining: f32,
+    playlist_order: Vec<String>,
+    playlist_index: usize,
+}
+
+impl MusicDirector {
+    /// Target volume (before stem/master scaling) for a layer given the current state.
+    pub fn layer_weight(&self, layer: MusicLayer) -> f32 {
+        let intensity = self.combat_intensity.clamp(0.0, 1.0);
+        let boss = self.boss_phase.is_some();
+        match layer {
+            MusicLayer::Exploration => {
+                if boss {
+                    0.0
+                } else {
+                    1.0 - intensity * 0.6
+                }
+            }
+            MusicLayer::CombatLow => {
+                if boss {
+                    0.5
+                } else {
+                    (intensity * 2.0).min(1.0)
+                }
+            }
+            MusicLayer::CombatHigh => {
+                if boss {
+                    1.0
+                } else {
+                    ((intensity - 0.5) * 2.0).clamp(0.0, 1.0)
+                }
+            }
+            MusicLayer::Boss => {
+                if boss {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct SetMusicZoneEvent {
+    pub zone: String,
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct MusicIntensityEvent {
+    pub intensity: f32,
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct BossPhaseEvent {
+    /// `None` when the encounter ends.
+    pub phase: Option<u32>,
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct PlayStingerEvent {
+    pub id: String,
+}
+
+#[derive(Component, Debug)]
+pub struct MusicStem {
+    pub zone: String,
+    pub layer: MusicLayer,
+    pub base_volume: f32,
+    pub current_volume: f32,
+    /// Stems from the previous zone fade to zero and are then despawned.
+    pub fading_out: bool,
+    pub playlist_track: bool,
+}
+
+#[derive(Component, Debug)]
+pub struct MusicStinger;
+
+pub struct MusicPlugin;
+
+impl Plugin for MusicPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MusicConfig>()
+            .init_resource::<MusicLibrary>()
+            .init_resource::<MusicDirector>()
+            .add_event::<SetMusicZoneEvent>()
+            .add_event::<MusicIntensityEvent>()
+            .add_event::<BossPhaseEvent>()
+            .add_event::<PlayStingerEvent>()
+            .add_systems(Startup, load_music_content)
+            .add_systems(
+                Update,
+                (
+                    follow_player_zone,
+                    handle_zone_changes,
+                    handle_intensity_events,
+                    play_stingers,
+                    advance_playlist,
+                    update_stem_volumes,
+                )
+                    .chain(),
+            );
+    }
+}
+
+fn load_music_content(config: Res<MusicConfig>, packs: Res<ContentPacks>, mut library: ResMut<MusicLibrary>) {
+    for path in packs.files(&config.content_directory, "toml") {
+        let parsed = std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| toml::from_str::<MusicContentFile>(&text).map_err(|e| e.to_string()));
+
+        match parsed {
+            Ok(file) => {
+                for zone in file.zones {
+                    library.zones.insert(zone.zone.clone(), zone);
+                }
+                for stinger in file.stingers {
+                    library.stingers.insert(stinger.id.clone(), stinger);
+                }
+            }
+            Err(e) => warn!("Invalid music file {:?}: {}", path, e),
+        }
+    }
+
+    info!(
+        "Music: {} zones, {} stingers loaded",
+        library.zones.len(),
+        library.stingers.len()
+    );
+}
+
+fn follow_player_zone(
+    mut zone_events: EventReader<ZoneEnteredEvent>,
+    players: Query<(), With<Player>>,
+    mut music_events: EventWriter<SetMusicZoneEvent>,
+) {
+    for event in zone_events.read() {
+        if players.contains(event.entity) {
+            music_events.send(SetMusicZoneEvent {
+                zone: event.zone_id.clone(),
+            });
+        }
+    }
+}
+
+fn spawn_stem(commands: &mut Commands, asset_server: &AssetServer, zone: &str, layer: MusicLayer, path: &str, base_volume: f32, looping: bool) {
+    let settings = if looping {
+        PlaybackSettings::LOOP
+    } else {
+        PlaybackSettings::DESPAWN
+    };
+    commands.spawn((
+        AudioPlayer::new(asset_server.load(path.to_string())),
+        settings.with_volume(Volume::new(0.0)),
+        MusicStem {
+            zone: zone.to_string(),
+            layer,
+            base_volume,
+            current_volume: 0.0,
+            fading_out: false,
+            playlist_track: !looping,
+        },
+        Name::new(format!("Music: {} {:?}", zone, layer)),
+    ));
+}
+
+fn handle_zone_changes(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    library: Res<MusicLibrary>,
+    mut director: ResMut<MusicDirector>,
+    mut events: EventReader<SetMusicZoneEvent>,
+    mut stems: Query<&mut MusicStem>,
+) {
+    let Some(event) = events.read().last() else {
+        return;
+    };
+    if director.zone.as_deref() == Some(event.zone.as_str()) {
+        return;
+    }
+
+    for mut stem in stems.iter_mut() {
+        stem.fading_out = true;
+    }
+
+    director.zone = Some(event.zone.clone());
+    director.playlist_order.clear();
+    director.playlist_index = 0;
+
+    let Some(zone) = library.zones.get(&event.zone) else {
+        debug!("Music: zone '{}' has no music, fading out", event.zone);
+        return;
+    };
+
+    // All stems start together so layers stay beat-aligned
+    for stem in &zone.stems {
+        if stem.layer == MusicLayer::Exploration && !zone.playlist.is_empty() {
+            continue;
+        }
+        spawn_stem(&mut commands, &asset_server, &zone.zone, stem.layer, &stem.path, stem.volume, true);
+    }
+
+    if !zone.playlist.is_empty() {
+        let mut order = zone.playlist.clone();
+        if zone.shuffle {
+            order.shuffle(&mut rand::thread_rng());
+        }
+        spawn_stem(&mut commands, &asset_server, &zone.zone, MusicLayer::Exploration, &order[0], 1.0, false);
+        director.playlist_order = order;
+        director.playlist_index = 0;
+    }
+
+    info!("Music: entering zone '{}'", event.zone);
+}
+
+fn handle_intensity_events(
+    mut director: ResMut<MusicDirector>,
+    mut intensity_events: EventReader<MusicIntensityEvent>,
+    mut boss_events: EventReader<BossPhaseEvent>,
+) {
+    if let Some(event) = intensity_events.read().last() {
+        director.combat_intensity = event.intensity.clamp(0.0, 1.0);
+    }
+    if let Some(event) = boss_events.read().last() {
+        director.boss_phase = event.phase;
+    }
+}
+
+fn play_stingers(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mixer: Res<AudioMixer>,
+    library: Res<MusicLibrary>,
+    mut director: ResMut<MusicDirector>,
+    mut events: EventReader<PlayStingerEvent>,
+) {
+    for event in events.read() {
+        let Some(stinger) = library.stingers.get(&event.id) else {
+            warn!("Music: unknown stinger '{}'", event.id);
+            continue;
+        };
+        commands.spawn((
+            AudioPlayer::new(asset_server.load(stinger.path.clone())),
+            PlaybackSettings::DESPAWN.with_volume(Volume::new(mixer.volume(AudioBus::Music))),
+            MixerChannel::new(AudioBus::Music, 1.0),
+            MusicStinger,
+        ));
+        director.duck_remaining = director.duck_remaining.max(stinger.duck_seconds);
+    }
+}
+
+fn advance_playlist(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut director: ResMut<MusicDirector>,
+    tracks: Query<(&MusicStem, Option<&AudioSink>)>,
+) {
+    if director.playlist_order.is_empty() {
+        return;
+    }
+    let Some(zone) = director.zone.clone() else {
+        return;
+    };
+
+    // DESPAWN playback removes finished tracks; start the next one when none is left
+    let playing = tracks
+        .iter()
+        .any(|(stem, sink)| stem.playlist_track && !stem.fading_out && sink.is_none_or(|s| !s.empty()));
+    if playing {
+        return;
+    }
+
+    director.playlist_index = (director.playlist_index + 1) % director.playlist_order.len();
+    let path = director.playlist_order[director.playlist_index].clone();
+    spawn_stem(&mut commands, &asset_server, &zone, MusicLayer::Exploration, &path, 1.0, false);
+}
+
+fn update_stem_volumes(
+    mut commands: Commands,
+    time: Res<Time>,
+    config: Res<MusicConfig>,
+    mixer: Res<AudioMixer>,
+    mut director: ResMut<MusicDirector>,
+    mut stems: Query<(Entity, &mut MusicStem, Option<&AudioSink>)>,
+) {
+    let dt = time.delta_secs();
+    if director.duck_remaining > 0.0 {
+        director.duck_remaining -= dt;
+    }
+    let duck = if director.duck_remaining > 0.0 {
+        config.ducked_volume
+    } else {
+        1.0
+    };
+    let bus_volume = mixer.volume(AudioBus::Music);
+
+    for (entity, mut stem, sink) in stems.iter_mut() {
+        let (target, fade_seconds) = if stem.fading_out {
+            (0.0, config.crossfade_seconds)
+        } else {
+            let weight = director.layer_weight(stem.layer);
+            (weight * stem.base_volume * duck, config.layer_fade_seconds)
+        };
+
+        let step = dt / fade_seconds.max(0.01);
+        let delta = (target - stem.current_volume).clamp(-step, step);
+        stem.current_volume += delta;
+
+        // Fades run on the stem's own volume; the bus is applied on top so
+        // settings and snapshot changes take effect immediately
+        if let Some(sink) = sink {
+            sink.set_volume(stem.current_volume * bus_volume);
+        }
+
+        if stem.fading_out && stem.current_volume <= 0.001 {
+            commands.entity(entity).despawn();
+        }
+    }
+}
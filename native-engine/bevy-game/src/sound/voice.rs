@@ -0,0 +1,346 @@
+use bevy::audio::{AudioSinkPlayback, Volume};
+use bevy::prelude::*;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use super::mixer::{AudioBus, AudioMixer, MixerChannel};
+use crate::content_packs::ContentPacks;
+use crate::settings::UserSettings;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SubtitleCue {
+    /// Seconds from the start of the clip.
+    pub at: f32,
+    pub text: String,
+}
+
+/// Voice-over for one dialog line in one locale.
+#[derive(Debug, Clone, Deserialize)]
+pub struct VoiceLineDef {
+    pub id: String,
+    pub clip: String,
+    /// Timed subtitle segments. Without them the dialog text is shown for the whole clip.
+    #[serde(default)]
+    pub subtitles: Vec<SubtitleCue>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct VoiceContentFile {
+    #[serde(default, rename = "line")]
+    pub lines: Vec<VoiceLineDef>,
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct VoiceConfig {
+    /// Holds one directory per locale: `content/audio/voice/<locale>/*.toml`.
+    pub content_directory: PathBuf,
+    pub fallback_locale: String,
+    /// Text-only lines stay up this long per character...
+    pub text_seconds_per_char: f32,
+    /// ...but never shorter than this.
+    pub text_min_seconds: f32,
+}
+
+impl Default for VoiceConfig {
+    fn default() -> Self {
+        Self {
+            content_directory: PathBuf::from("content").join("audio").join("voice"),
+            fallback_locale: "en".to_string(),
+            text_seconds_per_char: 0.06,
+            text_min_seconds: 2.5,
+        }
+    }
+}
+
+/// Voice lines keyed by locale, then line id.
+#[derive(Resource, Debug, Default)]
+pub struct VoiceLibrary {
+    pub locales: HashMap<String, HashMap<String, VoiceLineDef>>,
+}
+
+impl VoiceLibrary {
+    /// Line for the player's language, falling back to the default locale.
+    pub fn find(&self, line_id: &str, locale: &str, fallback: &str) -> Option<&VoiceLineDef> {
+        self.locales
+            .get(locale)
+            .and_then(|lines| lines.get(line_id))
+            .or_else(|| self.locales.get(fallback).and_then(|lines| lines.get(line_id)))
+    }
+}
+
+/// Sent by the dialog system when a line is shown. Any line still playing is
+/// cut off, so advancing the dialog interrupts the previous speaker.
+#[derive(Event, Debug, Clone)]
+pub struct PlayDialogVoiceEvent {
+    pub line_id: String,
+    pub speaker_name: Option<String>,
+    /// Dialog text, shown as the subtitle when there is no voice clip or no timed cues.
+    pub text: String,
+}
+
+/// Dialog closed or skipped: stop the voice and clear the subtitle.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct StopDialogVoiceEvent;
+
+#[derive(Event, Debug, Clone)]
+pub struct DialogVoiceFinishedEvent {
+    pub line_id: String,
+    pub interrupted: bool,
+}
+
+#[derive(Component, Debug)]
+pub struct DialogVoice {
+    pub line_id: String,
+}
+
+/// Line currently being spoken (or shown text-only) and its subtitle timeline.
+#[derive(Resource, Debug, Default)]
+pub struct ActiveDialogVoice {
+    pub line_id: Option<String>,
+    pub speaker_name: Option<String>,
+    pub subtitle: Option<String>,
+    entity: Option<Entity>,
+    cues: Vec<SubtitleCue>,
+    elapsed: f32,
+    /// Set for text-only lines; voiced lines end when the clip does.
+    text_duration: Option<f32>,
+    started: bool,
+}
+
+#[derive(Component)]
+struct SubtitleText;
+
+pub struct VoicePlugin;
+
+impl Plugin for VoicePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<VoiceConfig>()
+            .init_resource::<VoiceLibrary>()
+            .init_resource::<ActiveDialogVoice>()
+            .add_event::<PlayDialogVoiceEvent>()
+            .add_event::<StopDialogVoiceEvent>()
+            .add_event::<DialogVoiceFinishedEvent>()
+            .add_systems(Startup, (load_voice_content, setup_subtitle_ui))
+            .add_systems(
+                Update,
+                (handle_voice_requests, advance_voice_line, update_subtitle_ui).chain(),
+            );
+    }
+}
+
+fn load_voice_content(config: Res<VoiceConfig>, packs: Res<ContentPacks>, mut library: ResMut<VoiceLibrary>) {
+    let mut locales: Vec<String> = packs
+        .directories(&config.content_directory)
+        .iter()
+        .filter_map(|dir| std::fs::read_dir(dir).ok())
+        .flat_map(|entries| entries.flatten())
+        .filter(|entry| entry.path().is_dir())
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .collect();
+    if locales.is_empty() {
+        info!("No voice-over content at {:?}", config.content_directory);
+        return;
+    }
+    locales.sort();
+    locales.dedup();
+
+    for locale in locales {
+        for path in packs.files(&config.content_directory.join(&locale), "toml") {
+            let parsed = std::fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|text| toml::from_str::<VoiceContentFile>(&text).map_err(|e| e.to_string()));
+
+            match parsed {
+                Ok(file) => {
+                    let lines = library.locales.entry(locale.clone()).or_default();
+                    for mut line in file.lines {
+                        line.subtitles.sort_by(|a, b| a.at.total_cmp(&b.at));
+                        lines.insert(line.id.clone(), line);
+                    }
+                }
+                Err(e) => warn!("Invalid voice file {:?}: {}", path, e),
+            }
+        }
+    }
+
+    let total: usize = library.locales.values().map(|l| l.len()).sum();
+    info!("Voice-over: {} lines across {} locales", total, library.locales.len());
+}
+
+fn setup_subtitle_ui(mut commands: Commands) {
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(120.0),
+                width: Val::Percent(100.0),
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            Name::new("Subtitles"),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new(""),
+                TextFont {
+                    font_size: 22.0,
+                    ..default()
+                },
+                TextLayout::new_with_justify(JustifyText::Center),
+                BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.5)),
+                Visibility::Hidden,
+                SubtitleText,
+            ));
+        });
+}
+
+fn stop_active(
+    commands: &mut Commands,
+    active: &mut ActiveDialogVoice,
+    finished: &mut EventWriter<DialogVoiceFinishedEvent>,
+    interrupted: bool,
+) {
+    if let Some(entity) = active.entity.take() {
+        if let Some(mut entity) = commands.get_entity(entity) {
+            entity.despawn();
+        }
+    }
+    if let Some(line_id) = active.line_id.take() {
+        finished.send(DialogVoiceFinishedEvent { line_id, interrupted });
+    }
+    *active = ActiveDialogVoice::default();
+}
+
+fn handle_voice_requests(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    config: Res<VoiceConfig>,
+    library: Res<VoiceLibrary>,
+    mixer: Res<AudioMixer>,
+    settings: Option<Res<UserSettings>>,
+    mut active: ResMut<ActiveDialogVoice>,
+    mut play_events: EventReader<PlayDialogVoiceEvent>,
+    mut stop_events: EventReader<StopDialogVoiceEvent>,
+    mut finished: EventWriter<DialogVoiceFinishedEvent>,
+) {
+    if stop_events.read().last().is_some() {
+        stop_active(&mut commands, &mut active, &mut finished, true);
+    }
+
+    let Some(event) = play_events.read().last() else {
+        return;
+    };
+    stop_active(&mut commands, &mut active, &mut finished, true);
+
+    let locale = settings
+        .as_ref()
+        .map(|s| s.audio.voice_language.as_str())
+        .unwrap_or(config.fallback_locale.as_str());
+
+    active.line_id = Some(event.line_id.clone());
+    active.speaker_name = event.speaker_name.clone();
+
+    match library.find(&event.line_id, locale, &config.fallback_locale) {
+        Some(line) => {
+            let volume = mixer.volume(AudioBus::Voice);
+            let entity = commands
+                .spawn((
+                    AudioPlayer::new(asset_server.load(line.clip.clone())),
+                    PlaybackSettings::DESPAWN.with_volume(Volume::new(volume)),
+                    MixerChannel::new(AudioBus::Voice, 1.0),
+                    DialogVoice {
+                        line_id: line.id.clone(),
+                    },
+                ))
+                .id();
+            active.entity = Some(entity);
+            active.cues = if line.subtitles.is_empty() {
+                vec![SubtitleCue {
+                    at: 0.0,
+                    text: event.text.clone(),
+                }]
+            } else {
+                line.subtitles.clone()
+            };
+        }
+        None => {
+            debug!("Voice-over: no clip for '{}' ({}), text only", event.line_id, locale);
+            let chars = event.text.chars().count() as f32;
+            active.text_duration = Some((chars * config.text_seconds_per_char).max(config.text_min_seconds));
+            active.cues = vec![SubtitleCue {
+                at: 0.0,
+                text: event.text.clone(),
+            }];
+            active.started = true;
+        }
+    }
+}
+
+fn advance_voice_line(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut active: ResMut<ActiveDialogVoice>,
+    voices: Query<Option<&AudioSink>, With<DialogVoice>>,
+    mut finished: EventWriter<DialogVoiceFinishedEvent>,
+) {
+    if active.line_id.is_none() {
+        return;
+    }
+
+    let done = match (active.entity, active.text_duration) {
+        (_, Some(duration)) => active.elapsed >= duration,
+        (Some(entity), None) => match voices.get(entity) {
+            // Subtitles start with the audio, not with the load request
+            Ok(Some(sink)) => {
+                active.started = true;
+                sink.empty()
+            }
+            Ok(None) => false,
+            // DESPAWN playback removed the entity: the clip has ended
+            Err(_) => active.started,
+        },
+        (None, None) => true,
+    };
+
+    if done {
+        active.entity = None;
+        stop_active(&mut commands, &mut active, &mut finished, false);
+        return;
+    }
+
+    if active.started {
+        active.elapsed += time.delta_secs();
+    }
+    let elapsed = active.elapsed;
+    active.subtitle = active
+        .cues
+        .iter()
+        .rev()
+        .find(|cue| cue.at <= elapsed)
+        .map(|cue| cue.text.clone());
+}
+
+fn update_subtitle_ui(
+    active: Res<ActiveDialogVoice>,
+    settings: Option<Res<UserSettings>>,
+    mut texts: Query<(&mut Text, &mut Visibility), With<SubtitleText>>,
+) {
+    if !active.is_changed() {
+        return;
+    }
+    let enabled = settings.is_none_or(|s| s.audio.subtitles);
+
+    for (mut text, mut visibility) in texts.iter_mut() {
+        match active.subtitle.as_ref().filter(|_| enabled) {
+            Some(subtitle) => {
+                text.0 = match &active.speaker_name {
+                    Some(name) => format!("{}: {}", name, subtitle),
+                    None => subtitle.clone(),
+                };
+                *visibility = Visibility::Inherited;
+            }
+            None => *visibility = Visibility::Hidden,
+        }
+    }
+}
@@ -0,0 +1,325 @@
+//! Spawn scaling by player density. Each spawn zone from
+//! `content/spawns/*.toml` is scaled by how many players are in it: crowded
+//! zones respawn faster, keep more monsters alive and grow packs (up to the
+//! caps), solo zones respawn slower, and kill credit switches from first tag
+//! to shared credit once a zone is busy enough that players would otherwise
+//! fight over tags.
+//!
+//! Player counts come from the players interest management knows about: the
+//! local player plus the remote players replicated to this client. A server
+//! that tracks interest cells itself turns off `count_known_players` and
+//! writes [`ZonePlayerCounts`] directly. Counts are smoothed so a group
+//! flying through doesn't swing the spawner.
+
+use bevy::prelude::*;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::authoring::SpawnContentFile;
+use crate::console::{CommandPermission, ConsoleAppExt, ConsoleCommand, ConsoleCommandEvent, ConsoleOutputEvent};
+use crate::content_packs::ContentPacks;
+use crate::{NetworkEntity, Player};
+
+#[derive(Resource, Debug, Clone)]
+pub struct SpawnDensityConfig {
+    pub content_directory: PathBuf,
+    /// Count the local and replicated players each frame. Off when
+    /// something else fills `ZonePlayerCounts`.
+    pub count_known_players: bool,
+    /// Seconds for the smoothed count to close most of the gap to the raw one.
+    pub smoothing_seconds: f32,
+    /// Players at which scaling reaches its caps.
+    pub crowded_players: f32,
+    /// Respawn time multiplier with one player or none.
+    pub solo_respawn_multiplier: f32,
+    /// Respawn time multiplier at `crowded_players`.
+    pub crowded_respawn_multiplier: f32,
+    /// Live spawn multiplier at `crowded_players`.
+    pub max_density_multiplier: f32,
+    /// Extra pack members at `crowded_players`.
+    pub max_pack_bonus: u32,
+    /// Live spawns per zone, whatever the density.
+    pub max_live_per_zone: u32,
+    /// Zones with at least this many players share kill credit.
+    pub shared_credit_players: f32,
+    /// Damage share that earns credit in shared mode.
+    pub shared_credit_min_damage: f32,
+}
+
+impl Default for SpawnDensityConfig {
+    fn default() -> Self {
+        Self {
+            content_directory: PathBuf::from("content").join("spawns"),
+            count_known_players: true,
+            smoothing_seconds: 10.0,
+            crowded_players: 8.0,
+            solo_respawn_multiplier: 1.25,
+            crowded_respawn_multiplier: 0.35,
+            max_density_multiplier: 2.0,
+            max_pack_bonus: 2,
+            max_live_per_zone: 60,
+            shared_credit_players: 4.0,
+            shared_credit_min_damage: 0.1,
+        }
+    }
+}
+
+/// Who gets kill credit (experience, quest progress, loot rights).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KillCreditRule {
+    /// The first player or group to damage the monster.
+    FirstTag,
+    /// Everyone who dealt at least `min_damage` of its health.
+    Shared { min_damage: f32 },
+}
+
+/// How one zone's spawns are scaled right now.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ZoneScaling {
+    /// Smoothed player count.
+    pub players: f32,
+    pub respawn_multiplier: f32,
+    pub density_multiplier: f32,
+    pub pack_bonus: u32,
+    pub credit: KillCreditRule,
+}
+
+impl Default for ZoneScaling {
+    fn default() -> Self {
+        Self {
+            players: 0.0,
+            respawn_multiplier: 1.0,
+            density_multiplier: 1.0,
+            pack_bonus: 0,
+            credit: KillCreditRule::FirstTag,
+        }
+    }
+}
+
+impl ZoneScaling {
+    pub fn respawn_seconds(&self, base: f32) -> f32 {
+        base * self.respawn_multiplier
+    }
+
+    /// Live spawns to keep for a zone expecting `expected` at base density.
+    pub fn live_target(&self, expected: f32, cap: u32) -> u32 {
+        ((expected * self.density_multiplier).round() as u32).min(cap)
+    }
+
+    pub fn pack_size(&self, base: usize) -> usize {
+        if base == 0 {
+            0
+        } else {
+            base + self.pack_bonus as usize
+        }
+    }
+}
+
+/// Spawn zone cells, by grid size, mapped to their zone.
+#[derive(Resource, Debug, Default)]
+pub struct DensityZones {
+    zones: Vec<String>,
+    grids: Vec<(f32, HashMap<[i32; 2], usize>)>,
+}
+
+impl DensityZones {
+    pub fn zone_at(&self, position: Vec3) -> Option<&str> {
+        self.grids.iter().find_map(|(cell_size, cells)| {
+            let cell = [(position.x / cell_size).floor() as i32, (position.z / cell_size).floor() as i32];
+            cells.get(&cell).map(|&index| self.zones[index].as_str())
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.zones.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.zones.is_empty()
+    }
+
+    fn insert(&mut self, id: String, cell_size: f32, cells: &[[i32; 2]]) {
+        let index = self.zones.len();
+        self.zones.push(id);
+        let grid = match self.grids.iter().position(|(size, _)| *size == cell_size) {
+            Some(grid) => grid,
+            None => {
+                self.grids.push((cell_size, HashMap::new()));
+                self.grids.len() - 1
+            }
+        };
+        for cell in cells {
+            self.grids[grid].1.insert(*cell, index);
+        }
+    }
+}
+
+/// Raw player count per zone id, before smoothing.
+#[derive(Resource, Debug, Default)]
+pub struct ZonePlayerCounts {
+    pub counts: HashMap<String, u32>,
+}
+
+/// Current scaling per zone id. Zones without players read as solo.
+#[derive(Resource, Debug, Default)]
+pub struct SpawnScaling {
+    zones: HashMap<String, ZoneScaling>,
+    solo: ZoneScaling,
+}
+
+impl SpawnScaling {
+    pub fn zone(&self, id: &str) -> ZoneScaling {
+        self.zones.get(id).copied().unwrap_or(self.solo)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &ZoneScaling)> {
+        self.zones.iter().map(|(id, scaling)| (id.as_str(), scaling))
+    }
+}
+
+/// A zone's kill credit rule or pack bonus changed.
+#[derive(Event, Debug, Clone)]
+pub struct SpawnScalingChangedEvent {
+    pub zone: String,
+    pub scaling: ZoneScaling,
+}
+
+pub struct SpawnDensityPlugin;
+
+impl Plugin for SpawnDensityPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SpawnDensityConfig>()
+            .init_resource::<DensityZones>()
+            .init_resource::<ZonePlayerCounts>()
+            .init_resource::<SpawnScaling>()
+            .add_event::<SpawnScalingChangedEvent>()
+            .add_console_command(
+                ConsoleCommand::new("spawndensity", "Shows player counts and spawn scaling per zone")
+                    .permission(CommandPermission::Dev),
+            )
+            .add_systems(Startup, load_density_zones)
+            .add_systems(Update, (count_known_players, update_spawn_scaling, spawn_density_command).chain());
+    }
+}
+
+fn load_density_zones(config: Res<SpawnDensityConfig>, packs: Res<ContentPacks>, mut zones: ResMut<DensityZones>) {
+    for path in packs.files(&config.content_directory, "toml") {
+        let parsed = std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| toml::from_str::<SpawnContentFile>(&text).map_err(|e| e.to_string()));
+
+        match parsed {
+            Ok(file) => {
+                for zone in file.zones {
+                    zones.insert(zone.id, file.cell_size, &zone.cells);
+                }
+            }
+            Err(e) => warn!("Invalid spawn file {:?}: {}", path, e),
+        }
+    }
+
+    info!("Spawn density: {} zones", zones.len());
+}
+
+fn count_known_players(
+    config: Res<SpawnDensityConfig>,
+    zones: Res<DensityZones>,
+    mut counts: ResMut<ZonePlayerCounts>,
+    players: Query<&GlobalTransform, With<Player>>,
+    remote: Query<(&GlobalTransform, &NetworkEntity), Without<Player>>,
+) {
+    if !config.count_known_players {
+        return;
+    }
+    counts.counts.clear();
+    let remote_players = remote.iter().filter(|(_, entity)| entity.is_remote).map(|(transform, _)| transform);
+    for transform in players.iter().chain(remote_players) {
+        if let Some(zone) = zones.zone_at(transform.translation()) {
+            *counts.counts.entry(zone.to_string()).or_default() += 1;
+        }
+    }
+}
+
+fn scale_for(players: f32, config: &SpawnDensityConfig) -> ZoneScaling {
+    // 0 at two players, 1 at crowded
+    let crowding = ((players - 2.0) / (config.crowded_players - 2.0).max(1.0)).clamp(0.0, 1.0);
+    let respawn_multiplier = if players < 2.0 {
+        // Solo zones calm down; a second player brings them back to normal
+        config.solo_respawn_multiplier.lerp(1.0, (players - 1.0).clamp(0.0, 1.0))
+    } else {
+        1.0_f32.lerp(config.crowded_respawn_multiplier, crowding)
+    };
+    ZoneScaling {
+        players,
+        respawn_multiplier,
+        density_multiplier: 1.0_f32.lerp(config.max_density_multiplier, crowding),
+        pack_bonus: (config.max_pack_bonus as f32 * crowding).floor() as u32,
+        credit: if players >= config.shared_credit_players {
+            KillCreditRule::Shared { min_damage: config.shared_credit_min_damage }
+        } else {
+            KillCreditRule::FirstTag
+        },
+    }
+}
+
+fn update_spawn_scaling(
+    time: Res<Time>,
+    config: Res<SpawnDensityConfig>,
+    zones: Res<DensityZones>,
+    counts: Res<ZonePlayerCounts>,
+    mut scaling: ResMut<SpawnScaling>,
+    mut changed: EventWriter<SpawnScalingChangedEvent>,
+) {
+    let blend = 1.0 - (-time.delta_secs() * 3.0 / config.smoothing_seconds.max(0.01)).exp();
+    scaling.solo = scale_for(1.0, &config);
+    for zone in &zones.zones {
+        let raw = counts.counts.get(zone).copied().unwrap_or(0) as f32;
+        let previous = scaling.zones.get(zone).copied();
+        let smoothed = previous.map_or(raw, |p| p.players.lerp(raw, blend));
+        // Settle on whole counts so an empty zone ends up exactly solo
+        let players = if (smoothed - raw).abs() < 0.01 { raw } else { smoothed };
+        if previous.is_some_and(|p| p.players == players) {
+            continue;
+        }
+
+        let next = scale_for(players, &config);
+        if previous.is_none_or(|p| p.credit != next.credit || p.pack_bonus != next.pack_bonus) {
+            changed.send(SpawnScalingChangedEvent { zone: zone.clone(), scaling: next });
+        }
+        scaling.zones.insert(zone.clone(), next);
+    }
+}
+
+fn spawn_density_command(
+    config: Res<SpawnDensityConfig>,
+    zones: Res<DensityZones>,
+    scaling: Res<SpawnScaling>,
+    mut events: EventReader<ConsoleCommandEvent>,
+    mut output: EventWriter<ConsoleOutputEvent>,
+) {
+    for event in events.read() {
+        if event.name != "spawndensity" {
+            continue;
+        }
+        let mut busy: Vec<(&str, &ZoneScaling)> = scaling.iter().filter(|(_, s)| s.players >= 0.5).collect();
+        busy.sort_by(|a, b| b.1.players.total_cmp(&a.1.players).then(a.0.cmp(b.0)));
+        let mut lines = vec![format!("{} zones, {} with players", zones.len(), busy.len())];
+        for (id, zone) in busy {
+            let credit = match zone.credit {
+                KillCreditRule::FirstTag => "first tag".to_string(),
+                KillCreditRule::Shared { min_damage } => format!("shared above {:.0}%", min_damage * 100.0),
+            };
+            lines.push(format!(
+                "  {}: {:.1} players, respawn x{:.2}, density x{:.2} (cap {}), packs +{}, {}",
+                id,
+                zone.players,
+                zone.respawn_multiplier,
+                zone.density_multiplier,
+                config.max_live_per_zone,
+                zone.pack_bonus,
+                credit
+            ));
+        }
+        output.send(event.reply(lines.join("\n")));
+    }
+}
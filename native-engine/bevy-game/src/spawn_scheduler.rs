@@ -0,0 +1,319 @@
+//! Budgeted spawning. Work queued here runs a slice at a time, nearest the
+//! player first, so a zone login that queues hundreds of monsters and props
+//! fills in around the player over a few frames instead of stalling one.
+//!
+//! Each frame pending jobs are ranked player-adjacent, then visible (inside
+//! a camera frustum), then distant, oldest first within a class, and run
+//! until the frame's time budget is spent. Jobs insert whole bundles in one
+//! go, and `push_batch` spawns many bundles of one type with a single
+//! `spawn_batch`. Mesh and material creation can be pushed further out with
+//! [`PendingRenderSetup`], so entities exist (and collide) before they cost
+//! GPU uploads.
+
+use bevy::prelude::*;
+use bevy::render::primitives::{Frustum, Sphere};
+use std::collections::HashMap;
+use std::time::Instant;
+
+use crate::metrics::{self, Metrics};
+use crate::Player;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SpawnPriority {
+    PlayerAdjacent,
+    Visible,
+    Distant,
+}
+
+impl SpawnPriority {
+    pub const ALL: [SpawnPriority; 3] = [Self::PlayerAdjacent, Self::Visible, Self::Distant];
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct SpawnSchedulerConfig {
+    /// Milliseconds of spawning per frame. The first job always runs, so a
+    /// slow job delays the queue but never blocks it.
+    pub frame_budget_ms: f32,
+    /// Jobs closer than this to the player are `PlayerAdjacent`.
+    pub adjacent_radius: f32,
+    /// Radius tested against camera frustums for `Visible`.
+    pub visibility_radius: f32,
+    /// Frames a job may wait before it's promoted one class, so distant
+    /// work can't starve behind a steady stream of near spawns.
+    pub promote_after_frames: u32,
+    /// `PendingRenderSetup`s run per frame.
+    pub render_setups_per_frame: usize,
+    /// Bundles per job in `push_batch`.
+    pub batch_size: usize,
+}
+
+impl Default for SpawnSchedulerConfig {
+    fn default() -> Self {
+        Self {
+            frame_budget_ms: 2.0,
+            adjacent_radius: 40.0,
+            visibility_radius: 4.0,
+            promote_after_frames: 120,
+            render_setups_per_frame: 16,
+            batch_size: 64,
+        }
+    }
+}
+
+/// Identifies a queued job, for cancelling it or matching its
+/// [`SpawnJobDoneEvent`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SpawnTicket(pub u64);
+
+type SpawnFn = Box<dyn FnOnce(&mut World) -> Vec<Entity> + Send + Sync>;
+
+struct SpawnJob {
+    ticket: SpawnTicket,
+    kind: String,
+    position: Vec3,
+    queued_frame: u64,
+    run: SpawnFn,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SpawnSchedulerStats {
+    /// Pending jobs per class after the last frame, in `SpawnPriority::ALL` order.
+    pub pending: [usize; 3],
+    pub spawned_last_frame: usize,
+    pub jobs_last_frame: usize,
+    pub ms_last_frame: f32,
+    /// Frames the oldest pending job has waited.
+    pub oldest_wait_frames: u64,
+    pub spawned_by_kind: HashMap<String, u64>,
+}
+
+#[derive(Resource, Default)]
+pub struct SpawnScheduler {
+    jobs: Vec<SpawnJob>,
+    next_ticket: u64,
+    frame: u64,
+    pub stats: SpawnSchedulerStats,
+}
+
+impl SpawnScheduler {
+    /// Queues `spawn`, which should spawn everything for one thing at
+    /// `position` and return the entities it made.
+    pub fn push(
+        &mut self,
+        kind: impl Into<String>,
+        position: Vec3,
+        spawn: impl FnOnce(&mut World) -> Vec<Entity> + Send + Sync + 'static,
+    ) -> SpawnTicket {
+        let ticket = SpawnTicket(self.next_ticket);
+        self.next_ticket += 1;
+        self.jobs.push(SpawnJob {
+            ticket,
+            kind: kind.into(),
+            position,
+            queued_frame: self.frame,
+            run: Box::new(spawn),
+        });
+        ticket
+    }
+
+    /// Queues bundles of one type (trees, rocks, a mob pack), grouped into
+    /// jobs of `batch_size` nearby bundles that each spawn in one batch.
+    pub fn push_batch<B: Bundle>(
+        &mut self,
+        kind: impl Into<String>,
+        batch_size: usize,
+        mut bundles: Vec<(Vec3, B)>,
+    ) -> Vec<SpawnTicket> {
+        let kind = kind.into();
+        // Neighbours share a batch, so a batch's centre is meaningful for ranking
+        bundles.sort_by(|a, b| {
+            let cell = |p: Vec3| ((p.x / 32.0).floor() as i32, (p.z / 32.0).floor() as i32);
+            cell(a.0).cmp(&cell(b.0))
+        });
+        let mut tickets = Vec::new();
+        while !bundles.is_empty() {
+            let rest = bundles.split_off(bundles.len().min(batch_size.max(1)));
+            let chunk = std::mem::replace(&mut bundles, rest);
+            let center = chunk.iter().map(|(p, _)| *p).sum::<Vec3>() / chunk.len() as f32;
+            let items: Vec<B> = chunk.into_iter().map(|(_, bundle)| bundle).collect();
+            tickets.push(self.push(kind.clone(), center, move |world: &mut World| {
+                world.spawn_batch(items).collect()
+            }));
+        }
+        tickets
+    }
+
+    /// Drops a job that hasn't run yet. `false` if it already ran.
+    pub fn cancel(&mut self, ticket: SpawnTicket) -> bool {
+        let before = self.jobs.len();
+        self.jobs.retain(|job| job.ticket != ticket);
+        self.jobs.len() != before
+    }
+
+    pub fn len(&self) -> usize {
+        self.jobs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.jobs.is_empty()
+    }
+}
+
+/// A job finished; `entities` are what it spawned.
+#[derive(Event, Debug, Clone)]
+pub struct SpawnJobDoneEvent {
+    pub ticket: SpawnTicket,
+    pub kind: String,
+    pub entities: Vec<Entity>,
+}
+
+type RenderSetupFn = Box<dyn FnOnce(&mut World, Entity) + Send + Sync>;
+
+/// Mesh/material creation for an entity, run a few per frame after it
+/// spawns. The entity stays hidden until then.
+#[derive(Component)]
+pub struct PendingRenderSetup(Option<RenderSetupFn>);
+
+impl PendingRenderSetup {
+    pub fn new(setup: impl FnOnce(&mut World, Entity) + Send + Sync + 'static) -> (Self, Visibility) {
+        (Self(Some(Box::new(setup))), Visibility::Hidden)
+    }
+}
+
+pub struct SpawnSchedulerPlugin;
+
+impl Plugin for SpawnSchedulerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SpawnSchedulerConfig>()
+            .init_resource::<SpawnScheduler>()
+            .add_event::<SpawnJobDoneEvent>()
+            .add_systems(PostUpdate, (run_spawn_jobs, run_render_setups).chain());
+    }
+}
+
+fn classify(
+    position: Vec3,
+    players: &[Vec3],
+    frustums: &[Frustum],
+    config: &SpawnSchedulerConfig,
+) -> SpawnPriority {
+    let adjacent = config.adjacent_radius * config.adjacent_radius;
+    if players.iter().any(|p| p.distance_squared(position) <= adjacent) {
+        return SpawnPriority::PlayerAdjacent;
+    }
+    let sphere = Sphere {
+        center: position.into(),
+        radius: config.visibility_radius,
+    };
+    if frustums.iter().any(|f| f.intersects_sphere(&sphere, true)) {
+        return SpawnPriority::Visible;
+    }
+    SpawnPriority::Distant
+}
+
+fn promoted(priority: SpawnPriority, waited: u64, config: &SpawnSchedulerConfig) -> SpawnPriority {
+    if config.promote_after_frames == 0 || waited < config.promote_after_frames as u64 {
+        return priority;
+    }
+    match priority {
+        SpawnPriority::Distant => SpawnPriority::Visible,
+        _ => SpawnPriority::PlayerAdjacent,
+    }
+}
+
+fn run_spawn_jobs(world: &mut World) {
+    let config = world.resource::<SpawnSchedulerConfig>().clone();
+    let players: Vec<Vec3> = world
+        .query_filtered::<&Transform, With<Player>>()
+        .iter(world)
+        .map(|t| t.translation)
+        .collect();
+    let frustums: Vec<Frustum> = world
+        .query::<(&Camera, &Frustum)>()
+        .iter(world)
+        .filter(|(camera, _)| camera.is_active)
+        .map(|(_, frustum)| *frustum)
+        .collect();
+
+    // Jobs may queue more work while they run; that lands in a stand-in
+    // with the same frame and tickets, merged back afterwards
+    let mut scheduler = std::mem::take(&mut *world.resource_mut::<SpawnScheduler>());
+    scheduler.frame += 1;
+    let frame = scheduler.frame;
+    {
+        let mut stand_in = world.resource_mut::<SpawnScheduler>();
+        stand_in.frame = frame;
+        stand_in.next_ticket = scheduler.next_ticket;
+    }
+
+    let mut ranked: Vec<(SpawnPriority, SpawnJob)> = scheduler
+        .jobs
+        .drain(..)
+        .map(|job| {
+            let priority = classify(job.position, &players, &frustums, &config);
+            (promoted(priority, frame - job.queued_frame, &config), job)
+        })
+        .collect();
+    // Stable, so queue order holds within a class
+    ranked.sort_by_key(|(priority, job)| (*priority, job.queued_frame));
+
+    let started = Instant::now();
+    let budget = config.frame_budget_ms / 1000.0;
+    let mut done = Vec::new();
+    let mut spawned = 0;
+    let mut pending = ranked.into_iter();
+    for (_, job) in pending.by_ref() {
+        let entities = (job.run)(world);
+        spawned += entities.len();
+        *scheduler.stats.spawned_by_kind.entry(job.kind.clone()).or_default() += entities.len() as u64;
+        done.push(SpawnJobDoneEvent {
+            ticket: job.ticket,
+            kind: job.kind,
+            entities,
+        });
+        if started.elapsed().as_secs_f32() >= budget {
+            break;
+        }
+    }
+
+    let mut counts = [0; 3];
+    let mut oldest = 0;
+    for (priority, job) in pending {
+        counts[priority as usize] += 1;
+        oldest = oldest.max(frame - job.queued_frame);
+        scheduler.jobs.push(job);
+    }
+    scheduler.stats.pending = counts;
+    scheduler.stats.jobs_last_frame = done.len();
+    scheduler.stats.spawned_last_frame = spawned;
+    scheduler.stats.ms_last_frame = started.elapsed().as_secs_f32() * 1000.0;
+    scheduler.stats.oldest_wait_frames = oldest;
+
+    if let Some(metrics) = world.get_resource::<Metrics>() {
+        metrics.set_gauge(metrics::SPAWN_QUEUE_DEPTH, &[], scheduler.jobs.len() as f64);
+    }
+    let mut stand_in = world.resource_mut::<SpawnScheduler>();
+    scheduler.next_ticket = stand_in.next_ticket;
+    scheduler.jobs.append(&mut stand_in.jobs);
+    *world.resource_mut::<SpawnScheduler>() = scheduler;
+    world.send_event_batch(done);
+}
+
+fn run_render_setups(world: &mut World) {
+    let limit = world.resource::<SpawnSchedulerConfig>().render_setups_per_frame;
+    let mut pending = world.query::<(Entity, &mut PendingRenderSetup)>();
+    let setups: Vec<(Entity, RenderSetupFn)> = pending
+        .iter_mut(world)
+        .filter_map(|(entity, mut setup)| setup.0.take().map(|f| (entity, f)))
+        .take(limit)
+        .collect();
+    for (entity, setup) in setups {
+        setup(world, entity);
+        if let Ok(mut entity) = world.get_entity_mut(entity) {
+            entity.remove::<PendingRenderSetup>();
+            if let Some(mut visibility) = entity.get_mut::<Visibility>() {
+                *visibility = Visibility::Inherited;
+            }
+        }
+    }
+}
@@ -0,0 +1,259 @@
+//! Free-fly spectator camera for developers and GMs.
+//!
+//! [`Action::ToggleSpectator`], the `spectate` console command or a
+//! [`SetCameraModeRequest`] for [`CameraMode::Spectator`] detach the camera
+//! from the player; only dev builds (or `--dev`) and sessions the account
+//! system raised to admin in [`ConsolePermissions`] may enter. Spectating
+//! pushes the `FreeCamera` input context, so movement flies the camera
+//! (sprint faster, `FlySlow` slower, the wheel scales the base speed)
+//! instead of the player, and the camera can follow the player or collide
+//! with the world.
+
+use bevy::input::mouse::AccumulatedMouseScroll;
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use crate::camera_modes::{CameraMode, CameraModeState, SetCameraModeRequest, ViewModelCamera};
+use crate::console::{
+    CommandPermission, ConsoleAppExt, ConsoleCommand, ConsoleCommandEvent, ConsoleOutputEvent, ConsolePermissions,
+};
+use crate::input_map::{Action, ActionState, InputContext, InputContexts};
+use crate::Player;
+
+#[derive(Resource, Debug, Clone)]
+pub struct SpectatorConfig {
+    /// Metres per second before modifiers.
+    pub base_speed: f32,
+    pub fast_multiplier: f32,
+    pub slow_multiplier: f32,
+    /// Wheel steps scale the base speed by this much each.
+    pub scroll_step: f32,
+    /// How close the camera gets to geometry with no-clip off.
+    pub collision_radius: f32,
+}
+
+impl Default for SpectatorConfig {
+    fn default() -> Self {
+        Self {
+            base_speed: 20.0,
+            fast_multiplier: 4.0,
+            slow_multiplier: 0.25,
+            scroll_step: 1.25,
+            collision_radius: 0.4,
+        }
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct SpectatorState {
+    pub no_clip: bool,
+    /// Entity the camera moves along with.
+    pub follow: Option<Entity>,
+    /// Multiplies `base_speed`; the wheel changes it.
+    pub speed_scale: f32,
+    /// Mode to return to on leaving.
+    previous_mode: CameraMode,
+    /// Camera position, kept apart from the camera the orbit system drives.
+    position: Option<Vec3>,
+    /// Followed entity's position last frame.
+    anchor: Option<Vec3>,
+}
+
+impl Default for SpectatorState {
+    fn default() -> Self {
+        Self {
+            no_clip: true,
+            follow: None,
+            speed_scale: 1.0,
+            previous_mode: CameraMode::ThirdPerson,
+            position: None,
+            anchor: None,
+        }
+    }
+}
+
+pub struct SpectatorPlugin;
+
+impl Plugin for SpectatorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SpectatorConfig>()
+            .init_resource::<SpectatorState>()
+            .add_console_command(
+                ConsoleCommand::new("spectate", "Detaches the camera for free flight, or reattaches it")
+                    .usage("[noclip|follow]")
+                    .permission(CommandPermission::Dev),
+            )
+            .add_systems(
+                Update,
+                (
+                    toggle_spectator,
+                    fly_spectator
+                        .after(crate::systems::camera::update_camera)
+                        .after(crate::systems::mount::mount_camera_system),
+                )
+                    .chain(),
+            );
+    }
+}
+
+fn toggle_spectator(
+    permissions: Res<ConsolePermissions>,
+    actions: Res<ActionState>,
+    mut contexts: ResMut<InputContexts>,
+    mut camera_mode: ResMut<CameraModeState>,
+    mut spectator: ResMut<SpectatorState>,
+    mut requests: EventReader<SetCameraModeRequest>,
+    mut events: EventReader<ConsoleCommandEvent>,
+    mut output: EventWriter<ConsoleOutputEvent>,
+    players: Query<Entity, With<Player>>,
+    cameras: Query<(&Camera, &Transform), (With<Camera3d>, Without<ViewModelCamera>)>,
+) {
+    let allowed = permissions.local >= CommandPermission::Dev;
+    let spectating = camera_mode.mode == CameraMode::Spectator;
+    let mut enter = None;
+    for request in requests.read() {
+        enter = Some(request.0 == CameraMode::Spectator);
+    }
+    if actions.just_pressed(Action::ToggleSpectator) {
+        enter = Some(!spectating);
+    }
+    for event in events.read() {
+        if event.name != "spectate" {
+            continue;
+        }
+        let result: Result<String, String> = match event.args.first().map(String::as_str) {
+            None => {
+                enter = Some(!spectating);
+                Ok(if spectating { "Camera reattached" } else { "Spectating" }.to_string())
+            }
+            Some(_) if !spectating => Err("not spectating".to_string()),
+            Some("noclip") => {
+                spectator.no_clip = !spectator.no_clip;
+                Ok(format!("No-clip {}", if spectator.no_clip { "on" } else { "off" }))
+            }
+            Some("follow") => {
+                spectator.follow = match spectator.follow {
+                    Some(_) => None,
+                    None => players.get_single().ok(),
+                };
+                spectator.anchor = None;
+                Ok(if spectator.follow.is_some() { "Following the player" } else { "Follow off" }.to_string())
+            }
+            Some(other) => Err(format!("unknown option '{}'", other)),
+        };
+        output.send(match result {
+            Ok(text) => event.reply(text),
+            Err(text) => event.error(format!("{}: {}", event.name, text)),
+        });
+    }
+
+    if actions.just_pressed(Action::ToggleNoClip) {
+        spectator.no_clip = !spectator.no_clip;
+    }
+    if actions.just_pressed(Action::ToggleFollow) {
+        spectator.follow = match spectator.follow {
+            Some(_) => None,
+            None => players.get_single().ok(),
+        };
+        spectator.anchor = None;
+    }
+
+    match enter {
+        Some(true) if !spectating => {
+            if !allowed {
+                warn!("Spectator mode needs a dev build or an admin account");
+                return;
+            }
+            if let Some((_, transform)) = cameras.iter().find(|(camera, _)| camera.order == 0) {
+                let (yaw, pitch, _) = transform.rotation.to_euler(EulerRot::YXZ);
+                camera_mode.yaw = yaw;
+                camera_mode.pitch = pitch;
+            }
+            spectator.previous_mode = camera_mode.mode;
+            spectator.position = None;
+            spectator.follow = None;
+            spectator.anchor = None;
+            camera_mode.mode = CameraMode::Spectator;
+        }
+        Some(false) if spectating => camera_mode.mode = spectator.previous_mode,
+        _ => {}
+    }
+    contexts.set(InputContext::FreeCamera, camera_mode.mode == CameraMode::Spectator);
+}
+
+fn fly_spectator(
+    time: Res<Time>,
+    config: Res<SpectatorConfig>,
+    actions: Res<ActionState>,
+    camera_mode: Res<CameraModeState>,
+    mut spectator: ResMut<SpectatorState>,
+    scroll: Option<Res<AccumulatedMouseScroll>>,
+    rapier_context: ReadRapierContext,
+    followed: Query<&GlobalTransform>,
+    mut cameras: Query<(&Camera, &mut Transform), (With<Camera3d>, Without<ViewModelCamera>)>,
+) {
+    if camera_mode.mode != CameraMode::Spectator {
+        return;
+    }
+    let Some((_, mut transform)) = cameras.iter_mut().find(|(camera, _)| camera.order == 0) else {
+        return;
+    };
+    // The orbit camera moved it this frame; start from where we left it
+    let mut position = spectator.position.unwrap_or(transform.translation);
+
+    let scrolled = scroll.map_or(0.0, |s| s.delta.y);
+    if scrolled != 0.0 {
+        spectator.speed_scale = (spectator.speed_scale * config.scroll_step.powf(scrolled)).clamp(0.05, 20.0);
+    }
+
+    if let Some(target) = spectator.follow {
+        match followed.get(target) {
+            Ok(target_transform) => {
+                let now = target_transform.translation();
+                if let Some(anchor) = spectator.anchor {
+                    position += now - anchor;
+                }
+                spectator.anchor = Some(now);
+            }
+            Err(_) => {
+                spectator.follow = None;
+                spectator.anchor = None;
+            }
+        }
+    }
+
+    let rotation = Quat::from_euler(EulerRot::YXZ, camera_mode.yaw, camera_mode.pitch, 0.0);
+    let input = Vec3::new(
+        actions.axis(Action::MoveLeft, Action::MoveRight),
+        actions.axis(Action::FlyDown, Action::FlyUp),
+        actions.axis(Action::MoveForward, Action::MoveBackward),
+    );
+    let mut speed = config.base_speed * spectator.speed_scale;
+    if actions.pressed(Action::Sprint) {
+        speed *= config.fast_multiplier;
+    }
+    if actions.pressed(Action::FlySlow) {
+        speed *= config.slow_multiplier;
+    }
+    // Horizontal input follows the view, vertical stays world-up
+    let horizontal = rotation * Vec3::new(input.x, 0.0, input.z);
+    let motion = (horizontal + Vec3::Y * input.y).clamp_length_max(1.0) * speed * time.delta_secs();
+
+    let distance = motion.length();
+    if distance > f32::EPSILON {
+        let direction = motion / distance;
+        let blocked = (!spectator.no_clip)
+            .then(|| rapier_context.single().ok())
+            .flatten()
+            .and_then(|context| {
+                let filter = QueryFilter::default().exclude_sensors();
+                context.cast_ray(position, direction, distance + config.collision_radius, true, filter)
+            })
+            .map(|(_, time_of_impact)| (time_of_impact - config.collision_radius).max(0.0));
+        position += direction * blocked.unwrap_or(distance);
+    }
+
+    spectator.position = Some(position);
+    transform.translation = position;
+    transform.rotation = rotation;
+}
@@ -262,4 +262,51 @@ mod stress_tests {
             "Concurrent operations too slow: {:.0}/sec", ops_per_sec);
         println!("✅ PASSED: Concurrent systems performance OK");
     }
+
+    #[test]
+    fn stress_ai_stage_agents() {
+        println!("\n=== AI Stage Stress Test ===");
+        println!("Target: {} agents", STRESS_ENTITY_COUNT);
+
+        use crate::ai_stage::{AiAgent, AiStagePlugin, AiStageStats, AiTarget, PLAYER_FACTION};
+        use bevy::prelude::*;
+
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins).add_plugins(AiStagePlugin);
+        for i in 0..20 {
+            let position = Vec3::new((i % 5) as f32 * 200.0, 0.0, (i / 5) as f32 * 250.0);
+            app.world_mut()
+                .spawn((AiTarget { faction: PLAYER_FACTION }, Transform::from_translation(position)));
+        }
+        for i in 0..STRESS_ENTITY_COUNT {
+            let home = Vec3::new((i % 100) as f32 * 10.0, 0.0, (i / 100) as f32 * 10.0);
+            app.world_mut().spawn((
+                AiAgent { home, ..Default::default() },
+                Transform::from_translation(home),
+            ));
+        }
+
+        // The first frames ask for every path at once; measure steady state
+        for _ in 0..60 {
+            app.update();
+        }
+        let frames = 60;
+        let start = Instant::now();
+        for _ in 0..frames {
+            app.update();
+        }
+        let avg_frame_time = start.elapsed().as_secs_f32() * 1000.0 / frames as f32;
+        let stats = app.world().resource::<AiStageStats>().clone();
+
+        println!("Agents: {}", stats.agents);
+        println!("Average frame time: {:.2}ms", avg_frame_time);
+        println!("Sensed last frame: {}, decisions: {}", stats.sensed, stats.decisions);
+
+        assert_eq!(stats.agents, STRESS_ENTITY_COUNT);
+        // Unoptimized builds run several times slower than release
+        let budget = if cfg!(debug_assertions) { FRAME_BUDGET_MS * 4.0 } else { FRAME_BUDGET_MS * 0.5 };
+        assert!(avg_frame_time < budget,
+            "AI stage exceeds budget: {:.2}ms > {:.2}ms", avg_frame_time, budget);
+        println!("✅ PASSED: AI stage performance OK");
+    }
 }
@@ -0,0 +1,558 @@
+//! Terrain chunk meshing on the async compute pool. Chunk streaming asks
+//! for a chunk at a LOD with [`TerrainMeshJobs::request`]; heightmap
+//! sampling and mesh building then run on worker threads against a snapshot
+//! of the terrain inputs, so a camera sweep across the map no longer costs
+//! main-thread frames.
+//!
+//! A job is cancelled when its chunk is released or remeshed before it
+//! finishes. Finished meshes are applied a few per frame into the chunk's
+//! back buffer and swapped to the front on the next frame, once the render
+//! world has had a frame to upload them; the old mesh keeps drawing until
+//! then, so LOD changes and sculpt edits never leave a hole.
+//!
+//! Once a chunk remeshed for a sculpt edit lands, the minimap tiles over it
+//! are marked dirty ([`MapTileDirtyEvent`]) so they're captured again.
+
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::tasks::{block_on, AsyncComputeTaskPool, Task};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::authoring::terrain_sculpt::{TerrainEditedEvent, TerrainEdits};
+use crate::minimap::{MapTileCoord, MapTileDirtyEvent, MinimapConfig};
+use crate::worldgen::WorldSeed;
+use crate::{LandmarkRegistry, Player, TerrainConfig};
+
+#[derive(Resource, Debug, Clone)]
+pub struct TerrainMeshingConfig {
+    /// Quads per chunk side at LOD 0; each LOD halves it.
+    pub base_resolution: u32,
+    pub min_resolution: u32,
+    /// Jobs running on the compute pool at once.
+    pub max_in_flight: usize,
+    /// Milliseconds per frame spent turning finished jobs into mesh assets.
+    /// The first one always goes, so a large chunk delays but never blocks.
+    pub apply_budget_ms: f32,
+    pub max_applies_per_frame: usize,
+}
+
+impl Default for TerrainMeshingConfig {
+    fn default() -> Self {
+        Self {
+            base_resolution: 64,
+            min_resolution: 4,
+            max_in_flight: 8,
+            apply_budget_ms: 1.0,
+            max_applies_per_frame: 4,
+        }
+    }
+}
+
+impl TerrainMeshingConfig {
+    pub fn resolution(&self, lod: u32) -> u32 {
+        (self.base_resolution >> lod.min(16)).max(self.min_resolution.max(1))
+    }
+}
+
+/// What a job samples. Rebuilt when the terrain config, world seed or
+/// sculpt edits change; jobs hold their own `Arc`, so a rebuild never
+/// touches work in flight.
+struct HeightSource {
+    config: TerrainConfig,
+    landmarks: LandmarkRegistry,
+    edits: Option<TerrainEdits>,
+}
+
+/// A finished chunk. `heights` are the `(resolution + 1)²` vertex heights,
+/// row-major from the chunk's minimum corner.
+#[derive(Debug, Clone)]
+pub struct ChunkMeshData {
+    pub coord: IVec2,
+    pub lod: u32,
+    pub resolution: u32,
+    pub chunk_size: f32,
+    pub heights: Arc<Vec<f32>>,
+    positions: Vec<[f32; 3]>,
+    normals: Vec<[f32; 3]>,
+    uvs: Vec<[f32; 2]>,
+    indices: Vec<u32>,
+    build_ms: f32,
+}
+
+struct MeshJob {
+    lod: u32,
+    cancelled: Arc<AtomicBool>,
+    task: Task<Option<ChunkMeshData>>,
+}
+
+impl MeshJob {
+    /// Dropping the task stops it at its next poll; the flag stops a job
+    /// already sampling on a worker.
+    fn cancel(self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TerrainMeshingStats {
+    pub wanted: usize,
+    pub queued: usize,
+    pub in_flight: usize,
+    pub ready: usize,
+    pub applied_last_frame: usize,
+    pub apply_ms_last_frame: f32,
+    pub completed_total: u64,
+    pub cancelled_total: u64,
+    /// Worker time of the last finished job.
+    pub last_build_ms: f32,
+}
+
+#[derive(Resource, Default)]
+pub struct TerrainMeshJobs {
+    /// Chunks streaming wants drawn, and at which LOD.
+    wanted: HashMap<IVec2, u32>,
+    /// Wanted chunks that need a (new) mesh, oldest first.
+    queued: VecDeque<IVec2>,
+    in_flight: HashMap<IVec2, MeshJob>,
+    ready: VecDeque<ChunkMeshData>,
+    /// Chunks to despawn on the next apply pass.
+    released: Vec<IVec2>,
+    /// Chunks remeshed for an edit; their map tiles are stale once the new
+    /// mesh is applied.
+    edited: HashSet<IVec2>,
+    source: Option<Arc<HeightSource>>,
+    pub stats: TerrainMeshingStats,
+}
+
+impl TerrainMeshJobs {
+    /// Asks for `coord` at `lod`. Free when that's already drawn or building.
+    pub fn request(&mut self, coord: IVec2, lod: u32) {
+        if self.wanted.insert(coord, lod) == Some(lod) {
+            return;
+        }
+        self.remesh(coord);
+    }
+
+    /// Rebuilds a wanted chunk, e.g. after its heights changed.
+    pub fn remesh(&mut self, coord: IVec2) {
+        if !self.wanted.contains_key(&coord) {
+            return;
+        }
+        self.cancel(coord);
+        if !self.queued.contains(&coord) {
+            self.queued.push_back(coord);
+        }
+    }
+
+    /// Stops drawing `coord` and drops any work for it.
+    pub fn release(&mut self, coord: IVec2) {
+        if self.wanted.remove(&coord).is_none() {
+            return;
+        }
+        self.cancel(coord);
+        self.queued.retain(|c| *c != coord);
+        self.edited.remove(&coord);
+        self.released.push(coord);
+    }
+
+    pub fn remesh_all(&mut self) {
+        let coords: Vec<IVec2> = self.wanted.keys().copied().collect();
+        for coord in coords {
+            self.remesh(coord);
+        }
+    }
+
+    pub fn wanted_lod(&self, coord: IVec2) -> Option<u32> {
+        self.wanted.get(&coord).copied()
+    }
+
+    pub fn is_building(&self, coord: IVec2) -> bool {
+        self.in_flight.contains_key(&coord) || self.queued.contains(&coord)
+    }
+
+    fn cancel(&mut self, coord: IVec2) {
+        if let Some(job) = self.in_flight.remove(&coord) {
+            job.cancel();
+            self.stats.cancelled_total += 1;
+        }
+        self.ready.retain(|data| data.coord != coord);
+    }
+}
+
+/// Material for chunks this module spawns. Replace the handle to use the
+/// splat material.
+#[derive(Resource, Debug, Clone)]
+pub struct TerrainMeshMaterial(pub Handle<StandardMaterial>);
+
+/// A streamed terrain chunk. `Mesh3d` is the front buffer; `back` holds a
+/// mesh applied this frame, swapped in on the next.
+#[derive(Component, Debug)]
+pub struct TerrainChunkMesh {
+    pub coord: IVec2,
+    pub lod: u32,
+    back: Option<(Handle<Mesh>, u32)>,
+}
+
+/// A chunk's heights are built; colliders and the height cache can take
+/// them from here instead of sampling the terrain again.
+#[derive(Event, Debug, Clone)]
+pub struct TerrainChunkMeshedEvent {
+    pub coord: IVec2,
+    pub lod: u32,
+    pub resolution: u32,
+    pub chunk_size: f32,
+    pub heights: Arc<Vec<f32>>,
+}
+
+/// A chunk was released and its mesh despawned.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct TerrainChunkReleasedEvent {
+    pub coord: IVec2,
+}
+
+/// The meshing chain; run after it to see this frame's meshed and released
+/// chunks.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TerrainMeshingSet;
+
+pub struct TerrainMeshingPlugin;
+
+impl Plugin for TerrainMeshingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TerrainMeshingConfig>()
+            .init_resource::<TerrainMeshJobs>()
+            .add_event::<TerrainChunkMeshedEvent>()
+            .add_event::<TerrainChunkReleasedEvent>()
+            .add_event::<MapTileDirtyEvent>()
+            .add_systems(Startup, setup_terrain_material)
+            .add_systems(
+                Update,
+                (
+                    refresh_height_source,
+                    remesh_edited_chunks,
+                    start_mesh_jobs,
+                    collect_mesh_jobs,
+                    swap_uploaded_meshes,
+                    apply_ready_meshes,
+                )
+                    .chain()
+                    .in_set(TerrainMeshingSet),
+            );
+    }
+}
+
+fn setup_terrain_material(mut commands: Commands, mut materials: ResMut<Assets<StandardMaterial>>) {
+    let material = materials.add(StandardMaterial {
+        base_color: Color::srgb(0.32, 0.42, 0.22),
+        perceptual_roughness: 0.95,
+        ..default()
+    });
+    commands.insert_resource(TerrainMeshMaterial(material));
+}
+
+fn refresh_height_source(
+    config: Res<TerrainConfig>,
+    landmarks: Res<LandmarkRegistry>,
+    edits: Option<Res<TerrainEdits>>,
+    seed: Option<Res<WorldSeed>>,
+    mut jobs: ResMut<TerrainMeshJobs>,
+) {
+    // Landmarks fill lazily as terrain is sampled, so their change ticks say
+    // nothing; a new seed or config is what reshapes the world
+    let reshaped = config.is_changed() || seed.as_ref().is_some_and(|s| s.is_changed());
+    let edited = edits.as_ref().is_some_and(|e| e.is_changed());
+    if !reshaped && !edited && jobs.source.is_some() {
+        return;
+    }
+    jobs.source = Some(Arc::new(HeightSource {
+        config: config.clone(),
+        landmarks: landmarks.clone(),
+        edits: edits.map(|e| e.clone()),
+    }));
+    // Edits remesh just the chunks they touch, below
+    if reshaped {
+        jobs.remesh_all();
+    }
+}
+
+fn remesh_edited_chunks(
+    mut events: EventReader<TerrainEditedEvent>,
+    config: Res<TerrainConfig>,
+    edits: Option<Res<TerrainEdits>>,
+    mut jobs: ResMut<TerrainMeshJobs>,
+) {
+    let Some(edits) = edits else {
+        events.clear();
+        return;
+    };
+    let mut touched = HashSet::new();
+    for event in events.read().filter(|e| e.height) {
+        touched.extend(world_chunks_for_edits(&edits, config.chunk_size, &event.chunks));
+    }
+    for coord in touched {
+        if jobs.wanted_lod(coord).is_some() {
+            jobs.edited.insert(coord);
+        }
+        jobs.remesh(coord);
+    }
+}
+
+/// Terrain chunks overlapping the given edit-grid chunks, with one edit cell
+/// of margin since normals on a neighbour's edge sample across it.
+pub fn world_chunks_for_edits(edits: &TerrainEdits, chunk_size: f32, edit_chunks: &[IVec2]) -> HashSet<IVec2> {
+    let span = edits.chunk_cells as f32 * edits.cell_size;
+    let margin = Vec2::splat(edits.cell_size);
+    let mut touched = HashSet::new();
+    for chunk in edit_chunks {
+        let min = ((chunk.as_vec2() * span - margin) / chunk_size).floor().as_ivec2();
+        let max = (((chunk.as_vec2() + Vec2::ONE) * span + margin) / chunk_size).floor().as_ivec2();
+        for x in min.x..=max.x {
+            for z in min.y..=max.y {
+                touched.insert(IVec2::new(x, z));
+            }
+        }
+    }
+    touched
+}
+
+fn start_mesh_jobs(
+    config: Res<TerrainMeshingConfig>,
+    terrain: Res<TerrainConfig>,
+    players: Query<&Transform, With<Player>>,
+    mut jobs: ResMut<TerrainMeshJobs>,
+) {
+    let Some(source) = jobs.source.clone() else {
+        return;
+    };
+    let free = config.max_in_flight.saturating_sub(jobs.in_flight.len());
+    if free == 0 || jobs.queued.is_empty() {
+        return;
+    }
+
+    // Nearest the player first; queue order breaks ties
+    let player = players.iter().next().map_or(Vec2::ZERO, |t| t.translation.xz());
+    let chunk_size = terrain.chunk_size;
+    let distance = |coord: IVec2| ((coord.as_vec2() + Vec2::splat(0.5)) * chunk_size).distance_squared(player);
+    let mut queued: Vec<IVec2> = jobs.queued.drain(..).collect();
+    queued.sort_by(|a, b| distance(*a).total_cmp(&distance(*b)));
+
+    let pool = AsyncComputeTaskPool::get();
+    for coord in queued.drain(..free.min(queued.len())) {
+        let Some(lod) = jobs.wanted_lod(coord) else {
+            continue;
+        };
+        let resolution = config.resolution(lod);
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let flag = cancelled.clone();
+        let source = source.clone();
+        let task = pool.spawn(async move { build_chunk_mesh(&source, coord, lod, resolution, &flag) });
+        jobs.in_flight.insert(coord, MeshJob { lod, cancelled, task });
+    }
+    jobs.queued.extend(queued);
+}
+
+/// Samples the chunk with a one-vertex border, so edge normals match the
+/// neighbour's, and builds the vertex data. `None` when cancelled.
+fn build_chunk_mesh(
+    source: &HeightSource,
+    coord: IVec2,
+    lod: u32,
+    resolution: u32,
+    cancelled: &AtomicBool,
+) -> Option<ChunkMeshData> {
+    let started = Instant::now();
+    let chunk_size = source.config.chunk_size;
+    let step = chunk_size / resolution as f32;
+    let origin = coord.as_vec2() * chunk_size;
+    // Sampling may grow the landmark cache, so each job works on its own copy
+    let mut landmarks = source.landmarks.clone();
+
+    let n = resolution as usize + 1;
+    let bordered = n + 2;
+    let mut samples = vec![0.0; bordered * bordered];
+    for z in 0..bordered {
+        if cancelled.load(Ordering::Relaxed) {
+            return None;
+        }
+        for x in 0..bordered {
+            let wx = origin.x + (x as f32 - 1.0) * step;
+            let wz = origin.y + (z as f32 - 1.0) * step;
+            let mut height =
+                crate::systems::terrain::terrain_height_at_with_features(wx, wz, &source.config, &mut landmarks);
+            if let Some(edits) = &source.edits {
+                height += edits.height_delta(wx, wz);
+            }
+            samples[z * bordered + x] = height;
+        }
+    }
+    let sample = |x: usize, z: usize| samples[(z + 1) * bordered + (x + 1)];
+
+    let mut heights = Vec::with_capacity(n * n);
+    let mut positions = Vec::with_capacity(n * n);
+    let mut normals = Vec::with_capacity(n * n);
+    let mut uvs = Vec::with_capacity(n * n);
+    for z in 0..n {
+        for x in 0..n {
+            let height = sample(x, z);
+            heights.push(height);
+            positions.push([x as f32 * step, height, z as f32 * step]);
+            // Central differences over the bordered grid
+            let index = |x: usize, z: usize| samples[z * bordered + x];
+            let dx = index(x + 2, z + 1) - index(x, z + 1);
+            let dz = index(x + 1, z + 2) - index(x + 1, z);
+            normals.push(Vec3::new(-dx, 2.0 * step, -dz).normalize().to_array());
+            uvs.push([x as f32 / resolution as f32, z as f32 / resolution as f32]);
+        }
+    }
+
+    let mut indices = Vec::with_capacity(resolution as usize * resolution as usize * 6);
+    for z in 0..resolution {
+        for x in 0..resolution {
+            let i = z * n as u32 + x;
+            let below = i + n as u32;
+            indices.extend_from_slice(&[i, below, i + 1, i + 1, below, below + 1]);
+        }
+    }
+
+    Some(ChunkMeshData {
+        coord,
+        lod,
+        resolution,
+        chunk_size,
+        heights: Arc::new(heights),
+        positions,
+        normals,
+        uvs,
+        indices,
+        build_ms: started.elapsed().as_secs_f32() * 1000.0,
+    })
+}
+
+fn collect_mesh_jobs(mut jobs: ResMut<TerrainMeshJobs>) {
+    let finished: Vec<IVec2> = jobs
+        .in_flight
+        .iter()
+        .filter(|(_, job)| job.task.is_finished())
+        .map(|(coord, _)| *coord)
+        .collect();
+    for coord in finished {
+        let Some(job) = jobs.in_flight.remove(&coord) else {
+            continue;
+        };
+        let lod = job.lod;
+        let Some(data) = block_on(job.task) else {
+            continue;
+        };
+        // The chunk moved to another LOD while this one built
+        if jobs.wanted_lod(coord) != Some(lod) {
+            continue;
+        }
+        jobs.stats.completed_total += 1;
+        jobs.stats.last_build_ms = data.build_ms;
+        jobs.ready.push_back(data);
+    }
+}
+
+/// Back buffers applied last frame have been uploaded; draw them now and
+/// let the old meshes go.
+fn swap_uploaded_meshes(mut chunks: Query<(&mut TerrainChunkMesh, &mut Mesh3d, &mut Visibility)>) {
+    for (mut chunk, mut mesh, mut visibility) in &mut chunks {
+        let Some((back, lod)) = chunk.back.take() else {
+            continue;
+        };
+        mesh.0 = back;
+        chunk.lod = lod;
+        *visibility = Visibility::Inherited;
+    }
+}
+
+fn apply_ready_meshes(
+    mut commands: Commands,
+    config: Res<TerrainMeshingConfig>,
+    material: Option<Res<TerrainMeshMaterial>>,
+    mut jobs: ResMut<TerrainMeshJobs>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut chunks: Query<(Entity, &mut TerrainChunkMesh)>,
+    minimap: Option<Res<MinimapConfig>>,
+    mut meshed: EventWriter<TerrainChunkMeshedEvent>,
+    mut released: EventWriter<TerrainChunkReleasedEvent>,
+    mut map_dirty: EventWriter<MapTileDirtyEvent>,
+) {
+    let mut by_coord: HashMap<IVec2, Entity> = HashMap::new();
+    for (entity, chunk) in &chunks {
+        by_coord.insert(chunk.coord, entity);
+    }
+    for coord in std::mem::take(&mut jobs.released) {
+        if let Some(entity) = by_coord.remove(&coord) {
+            commands.entity(entity).despawn_recursive();
+        }
+        released.send(TerrainChunkReleasedEvent { coord });
+    }
+
+    let started = Instant::now();
+    let budget = config.apply_budget_ms / 1000.0;
+    let mut applied = 0;
+    while applied < config.max_applies_per_frame.max(1) {
+        let Some(data) = jobs.ready.pop_front() else {
+            break;
+        };
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default());
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, data.positions);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, data.normals);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, data.uvs);
+        mesh.insert_indices(Indices::U32(data.indices));
+        let handle = meshes.add(mesh);
+
+        match by_coord.get(&data.coord).and_then(|e| chunks.get_mut(*e).ok()) {
+            Some((_, mut chunk)) => chunk.back = Some((handle, data.lod)),
+            None => {
+                // Hidden until the swap, like any other back buffer
+                let origin = data.coord.as_vec2() * data.chunk_size;
+                let mut entity = commands.spawn((
+                    Name::new(format!("Terrain Chunk {},{}", data.coord.x, data.coord.y)),
+                    TerrainChunkMesh {
+                        coord: data.coord,
+                        lod: data.lod,
+                        back: Some((handle.clone(), data.lod)),
+                    },
+                    Mesh3d(handle),
+                    Transform::from_xyz(origin.x, 0.0, origin.y),
+                    Visibility::Hidden,
+                ));
+                if let Some(material) = &material {
+                    entity.insert(MeshMaterial3d(material.0.clone()));
+                }
+                by_coord.insert(data.coord, entity.id());
+            }
+        }
+        let edited = jobs.edited.remove(&data.coord);
+        if let Some(minimap) = minimap.as_ref().filter(|_| edited) {
+            let min = data.coord.as_vec2() * data.chunk_size;
+            for coord in MapTileCoord::covering(min, min + Vec2::splat(data.chunk_size), minimap.tile_world_size) {
+                map_dirty.send(MapTileDirtyEvent { coord });
+            }
+        }
+        meshed.send(TerrainChunkMeshedEvent {
+            coord: data.coord,
+            lod: data.lod,
+            resolution: data.resolution,
+            chunk_size: data.chunk_size,
+            heights: data.heights,
+        });
+        applied += 1;
+        if started.elapsed().as_secs_f32() >= budget {
+            break;
+        }
+    }
+
+    jobs.stats.applied_last_frame = applied;
+    jobs.stats.apply_ms_last_frame = started.elapsed().as_secs_f32() * 1000.0;
+    jobs.stats.wanted = jobs.wanted.len();
+    jobs.stats.queued = jobs.queued.len();
+    jobs.stats.in_flight = jobs.in_flight.len();
+    jobs.stats.ready = jobs.ready.len();
+}
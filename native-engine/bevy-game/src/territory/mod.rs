@@ -0,0 +1,657 @@
+//! Guild territory control. Territories and their objectives live in
+//! `content/territory/*.toml`:
+//!
+//! ```toml
+//! [[territory]]
+//! id = "stormpeak_pass"
+//! name = "Stormpeak Pass"
+//! min = [1200.0, -400.0]
+//! max = [1800.0, 200.0]
+//! upkeep = 5000
+//! buffs = { experience = 0.05, gathering_speed = 0.1 }
+//!
+//! [[territory.objective]]
+//! id = "north_tower"
+//! position = [1450.0, 88.0, -210.0]
+//!
+//! [[territory.window]]
+//! day = "saturday"
+//! hour = 20
+//! hours = 2
+//! ```
+//!
+//! A guild takes an unclaimed territory by holding all of its objectives.
+//! Once claimed, objectives can only be taken during the territory's
+//! vulnerability windows (UTC); an attacker that holds them all before the
+//! window closes takes the claim. Claims cost `upkeep` copper from the guild's
+//! upkeep treasury every `upkeep_period_seconds` and lapse when it can't pay.
+//! Members of the owning guild get the territory's buffs while inside it.
+//!
+//! Objectives are captured by standing in them with no other guild present;
+//! a second guild contests and freezes progress. The first capture attempt
+//! by each attacking guild per window sends a [`TerritoryAttackEvent`] for
+//! the server to broadcast.
+
+pub mod state;
+
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::PathBuf;
+
+use crate::console::{
+    ArgCompletion, CommandPermission, ConsoleAppExt, ConsoleCommand, ConsoleCommandEvent, ConsoleOutputEvent,
+};
+use crate::content_packs::ContentPacks;
+use crate::world_clock::WorldClock;
+
+pub use state::{Claim, TerritoryState};
+
+const DAY_SECONDS: u64 = 86_400;
+const WEEK_SECONDS: u64 = 7 * DAY_SECONDS;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Weekday {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+impl Weekday {
+    fn index(self) -> u64 {
+        self as u64
+    }
+}
+
+/// Weekly span during which a claimed territory can be attacked.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VulnerabilityWindow {
+    pub day: Weekday,
+    /// UTC hour the window opens.
+    pub hour: u32,
+    pub hours: u32,
+}
+
+impl VulnerabilityWindow {
+    pub fn is_open(&self, unix_seconds: u64) -> bool {
+        // 1970-01-01 was a Thursday
+        let into_week = (unix_seconds + 3 * DAY_SECONDS) % WEEK_SECONDS;
+        let start = self.day.index() * DAY_SECONDS + self.hour as u64 * 3600;
+        (into_week + WEEK_SECONDS - start % WEEK_SECONDS) % WEEK_SECONDS < self.hours as u64 * 3600
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ObjectiveDef {
+    pub id: String,
+    pub position: Vec3,
+    #[serde(default = "default_objective_radius")]
+    pub radius: f32,
+    /// Seconds one guild needs alone on the objective to take it.
+    #[serde(default = "default_capture_seconds")]
+    pub capture_seconds: f32,
+}
+
+fn default_objective_radius() -> f32 {
+    15.0
+}
+
+fn default_capture_seconds() -> f32 {
+    60.0
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TerritoryDef {
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    /// `[x, z]` corners of the area the territory covers.
+    pub min: [f32; 2],
+    pub max: [f32; 2],
+    /// Copper per upkeep period.
+    #[serde(default)]
+    pub upkeep: u64,
+    /// Bonus per stat for members of the owning guild inside the territory.
+    #[serde(default)]
+    pub buffs: BTreeMap<String, f32>,
+    #[serde(default, rename = "objective")]
+    pub objectives: Vec<ObjectiveDef>,
+    #[serde(default, rename = "window")]
+    pub windows: Vec<VulnerabilityWindow>,
+}
+
+impl TerritoryDef {
+    pub fn contains(&self, position: Vec3) -> bool {
+        (self.min[0]..=self.max[0]).contains(&position.x) && (self.min[1]..=self.max[1]).contains(&position.z)
+    }
+
+    pub fn is_window_open(&self, unix_seconds: u64) -> bool {
+        self.windows.iter().any(|w| w.is_open(unix_seconds))
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TerritoryContentFile {
+    #[serde(default, rename = "territory")]
+    pub territories: Vec<TerritoryDef>,
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct TerritoryConfig {
+    pub content_directory: PathBuf,
+    /// Where claims and treasuries are saved; nothing is saved when `None`.
+    pub state_path: Option<PathBuf>,
+    pub save_interval_seconds: f32,
+    pub upkeep_period_seconds: u64,
+    /// Capture progress lost per second, as a fraction of the capture time,
+    /// while nobody from the capturing guild stands on the objective.
+    pub progress_decay: f32,
+}
+
+impl Default for TerritoryConfig {
+    fn default() -> Self {
+        Self {
+            content_directory: PathBuf::from("content").join("territory"),
+            state_path: Some(PathBuf::from("territory_state.json")),
+            save_interval_seconds: 60.0,
+            upkeep_period_seconds: DAY_SECONDS,
+            progress_decay: 0.5,
+        }
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct TerritoryContent {
+    pub territories: Vec<TerritoryDef>,
+}
+
+impl TerritoryContent {
+    pub fn get(&self, id: &str) -> Option<&TerritoryDef> {
+        self.territories.iter().find(|t| t.id == id)
+    }
+
+    pub fn at(&self, position: Vec3) -> Option<&TerritoryDef> {
+        self.territories.iter().find(|t| t.contains(position))
+    }
+}
+
+/// Guild membership as territory control sees it. The guild system keeps it
+/// on member characters.
+#[derive(Component, Debug, Clone, PartialEq, Eq)]
+pub struct GuildMember {
+    pub guild: String,
+}
+
+/// Territory buffs on a member of the owning guild while inside it; stat
+/// calculation adds `bonuses` by stat name.
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct TerritoryBuffs {
+    pub territory: String,
+    pub bonuses: BTreeMap<String, f32>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ObjectiveState {
+    pub holder: Option<String>,
+    /// Guild building progress towards taking the objective.
+    pub capturing: Option<String>,
+    /// 0 to 1.
+    pub progress: f32,
+    pub contested: bool,
+}
+
+/// Live state of one territory; rebuilt from the claims on restart.
+#[derive(Debug, Clone, Default)]
+pub struct TerritoryRuntime {
+    pub vulnerable: bool,
+    pub objectives: Vec<ObjectiveState>,
+    /// Guilds whose attack has been announced this window.
+    announced: HashSet<String>,
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct TerritoryControl {
+    pub territories: HashMap<String, TerritoryRuntime>,
+}
+
+/// A territory's vulnerability window opened or closed.
+#[derive(Event, Debug, Clone)]
+pub struct VulnerabilityWindowEvent {
+    pub territory: String,
+    pub open: bool,
+}
+
+/// A guild started capturing an objective in a claimed territory. Sent once
+/// per attacking guild per window; the server broadcasts it to everyone.
+#[derive(Event, Debug, Clone)]
+pub struct TerritoryAttackEvent {
+    pub territory: String,
+    pub objective: String,
+    pub attacker: String,
+    pub defender: String,
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct ObjectiveCapturedEvent {
+    pub territory: String,
+    pub objective: String,
+    pub guild: String,
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct TerritoryClaimedEvent {
+    pub territory: String,
+    pub guild: String,
+    pub previous: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReleaseReason {
+    UnpaidUpkeep,
+    Admin,
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct TerritoryReleasedEvent {
+    pub territory: String,
+    pub guild: String,
+    pub reason: ReleaseReason,
+}
+
+/// The guild bank moves copper into a guild's upkeep treasury.
+#[derive(Event, Debug, Clone)]
+pub struct UpkeepDepositRequest {
+    pub guild: String,
+    pub copper: u64,
+}
+
+pub struct TerritoryPlugin;
+
+impl Plugin for TerritoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TerritoryConfig>()
+            .init_resource::<TerritoryContent>()
+            .init_resource::<TerritoryState>()
+            .init_resource::<TerritoryControl>()
+            .init_resource::<WorldClock>()
+            .add_event::<VulnerabilityWindowEvent>()
+            .add_event::<TerritoryAttackEvent>()
+            .add_event::<ObjectiveCapturedEvent>()
+            .add_event::<TerritoryClaimedEvent>()
+            .add_event::<TerritoryReleasedEvent>()
+            .add_event::<UpkeepDepositRequest>()
+            .add_console_command(
+                ConsoleCommand::new("territory", "Lists territories, or hands one to a guild")
+                    .usage("[claim <territory> <guild>|release <territory>|deposit <guild> <copper>]")
+                    .permission(CommandPermission::Admin)
+                    .complete(vec![ArgCompletion::Values(vec![
+                        "claim".to_string(),
+                        "release".to_string(),
+                        "deposit".to_string(),
+                    ])]),
+            )
+            .add_systems(Startup, load_territories)
+            .add_systems(
+                Update,
+                (
+                    take_deposits,
+                    update_windows,
+                    capture_objectives,
+                    charge_upkeep,
+                    apply_territory_buffs,
+                    territory_command,
+                    save_territory_state,
+                )
+                    .chain(),
+            );
+    }
+}
+
+fn load_territories(
+    config: Res<TerritoryConfig>,
+    packs: Res<ContentPacks>,
+    mut content: ResMut<TerritoryContent>,
+    mut state: ResMut<TerritoryState>,
+) {
+    for path in packs.files(&config.content_directory, "toml") {
+        let parsed = std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| toml::from_str::<TerritoryContentFile>(&text).map_err(|e| e.to_string()));
+
+        match parsed {
+            Ok(file) => content.territories.extend(file.territories),
+            Err(e) => warn!("Invalid territory file {:?}: {}", path, e),
+        }
+    }
+
+    if let Some(path) = config.state_path.as_ref().filter(|p| p.exists()) {
+        match TerritoryState::load(path) {
+            Ok(loaded) => *state = loaded,
+            Err(e) => warn!("Invalid territory state {:?}: {}; starting fresh", path, e),
+        }
+    }
+    let unknown: Vec<String> = state.claims.keys().filter(|id| content.get(id).is_none()).cloned().collect();
+    for id in unknown {
+        warn!("Dropping claim on unknown territory '{}'", id);
+        state.release(&id);
+    }
+
+    info!("Territory: {} territories, {} claimed", content.territories.len(), state.claims.len());
+}
+
+fn take_deposits(mut state: ResMut<TerritoryState>, mut deposits: EventReader<UpkeepDepositRequest>) {
+    for deposit in deposits.read() {
+        state.deposit(&deposit.guild, deposit.copper);
+    }
+}
+
+/// Objectives held by the owner, or by nobody for unclaimed territories.
+fn reset_objectives(def: &TerritoryDef, owner: Option<&str>) -> Vec<ObjectiveState> {
+    def.objectives
+        .iter()
+        .map(|_| ObjectiveState { holder: owner.map(str::to_string), ..default() })
+        .collect()
+}
+
+fn update_windows(
+    content: Res<TerritoryContent>,
+    state: Res<TerritoryState>,
+    mut control: ResMut<TerritoryControl>,
+    mut windows: EventWriter<VulnerabilityWindowEvent>,
+    clock: Res<WorldClock>,
+) {
+    let now = clock.now_secs();
+    for def in &content.territories {
+        let owner = state.owner(&def.id);
+        let runtime = control.territories.entry(def.id.clone()).or_insert_with(|| TerritoryRuntime {
+            objectives: reset_objectives(def, owner),
+            ..default()
+        });
+        // Unclaimed land is open to anyone at any time
+        let vulnerable = owner.is_none() || def.is_window_open(now);
+        if vulnerable == runtime.vulnerable {
+            continue;
+        }
+        runtime.vulnerable = vulnerable;
+        if owner.is_some() {
+            windows.send(VulnerabilityWindowEvent { territory: def.id.clone(), open: vulnerable });
+            if !vulnerable {
+                // Whatever the attackers took reverts when the window closes
+                runtime.objectives = reset_objectives(def, owner);
+                runtime.announced.clear();
+            }
+        }
+    }
+}
+
+fn capture_objectives(
+    (time, clock): (Res<Time>, Res<WorldClock>),
+    config: Res<TerritoryConfig>,
+    content: Res<TerritoryContent>,
+    mut state: ResMut<TerritoryState>,
+    mut control: ResMut<TerritoryControl>,
+    members: Query<(&GlobalTransform, &GuildMember)>,
+    (mut attacks, mut captured, mut claimed): (
+        EventWriter<TerritoryAttackEvent>,
+        EventWriter<ObjectiveCapturedEvent>,
+        EventWriter<TerritoryClaimedEvent>,
+    ),
+) {
+    let dt = time.delta_secs();
+    let positions: Vec<(Vec3, &str)> = members.iter().map(|(t, m)| (t.translation(), m.guild.as_str())).collect();
+
+    for def in &content.territories {
+        let Some(runtime) = control.territories.get_mut(&def.id) else {
+            continue;
+        };
+        if !runtime.vulnerable {
+            continue;
+        }
+        let owner = state.owner(&def.id).map(str::to_string);
+
+        for (objective, progress) in def.objectives.iter().zip(runtime.objectives.iter_mut()) {
+            let mut present: Vec<&str> = positions
+                .iter()
+                .filter(|(position, _)| position.distance(objective.position) <= objective.radius)
+                .map(|(_, guild)| *guild)
+                .collect();
+            present.sort_unstable();
+            present.dedup();
+            progress.contested = present.len() > 1;
+            if progress.contested {
+                continue;
+            }
+
+            let step = dt / objective.capture_seconds.max(0.1);
+            match present.first().copied().filter(|&g| progress.holder.as_deref() != Some(g)) {
+                Some(guild) => {
+                    if progress.capturing.as_deref() != Some(guild) {
+                        progress.capturing = Some(guild.to_string());
+                        progress.progress = 0.0;
+                        if let Some(defender) = owner.as_ref().filter(|o| *o != guild) {
+                            if runtime.announced.insert(guild.to_string()) {
+                                info!("{} is attacking {} in {}", guild, objective.id, def.id);
+                                attacks.send(TerritoryAttackEvent {
+                                    territory: def.id.clone(),
+                                    objective: objective.id.clone(),
+                                    attacker: guild.to_string(),
+                                    defender: defender.clone(),
+                                });
+                            }
+                        }
+                    }
+                    progress.progress += step;
+                    if progress.progress >= 1.0 {
+                        progress.holder = Some(guild.to_string());
+                        progress.capturing = None;
+                        progress.progress = 0.0;
+                        captured.send(ObjectiveCapturedEvent {
+                            territory: def.id.clone(),
+                            objective: objective.id.clone(),
+                            guild: guild.to_string(),
+                        });
+                    }
+                }
+                None if progress.capturing.is_some() => {
+                    progress.progress -= step * config.progress_decay;
+                    if progress.progress <= 0.0 {
+                        progress.capturing = None;
+                        progress.progress = 0.0;
+                    }
+                }
+                None => {}
+            }
+        }
+
+        // One guild holding every objective takes the territory
+        let Some(first) = runtime.objectives.first().and_then(|o| o.holder.clone()) else {
+            continue;
+        };
+        let all_held = runtime.objectives.iter().all(|o| o.holder.as_deref() == Some(first.as_str()));
+        if !all_held || owner.as_deref() == Some(first.as_str()) {
+            continue;
+        }
+        info!("{} claimed {}", first, def.id);
+        state.claim(&def.id, &first, clock.now_secs());
+        runtime.vulnerable = def.is_window_open(clock.now_secs());
+        runtime.announced.clear();
+        claimed.send(TerritoryClaimedEvent { territory: def.id.clone(), guild: first, previous: owner });
+    }
+}
+
+fn charge_upkeep(
+    config: Res<TerritoryConfig>,
+    content: Res<TerritoryContent>,
+    mut state: ResMut<TerritoryState>,
+    mut control: ResMut<TerritoryControl>,
+    mut released: EventWriter<TerritoryReleasedEvent>,
+    clock: Res<WorldClock>,
+) {
+    let now = clock.now_secs();
+    let due: Vec<(String, String)> = state
+        .claims
+        .iter()
+        .filter(|(_, claim)| claim.paid_until <= now)
+        .map(|(id, claim)| (id.clone(), claim.guild.clone()))
+        .collect();
+
+    for (id, guild) in due {
+        let upkeep = content.get(&id).map_or(0, |def| def.upkeep);
+        if state.withdraw(&guild, upkeep) {
+            if let Some(claim) = state.claims.get_mut(&id) {
+                // Periods missed while the server was down aren't charged
+                let period = config.upkeep_period_seconds.max(1);
+                let next = claim.paid_until + period;
+                claim.paid_until = if next <= now { now + period } else { next };
+            }
+            state.dirty = true;
+            continue;
+        }
+        warn!("{} couldn't pay {} upkeep for {}; claim lapsed", guild, upkeep, id);
+        state.release(&id);
+        if let (Some(def), Some(runtime)) = (content.get(&id), control.territories.get_mut(&id)) {
+            runtime.objectives = reset_objectives(def, None);
+            runtime.announced.clear();
+        }
+        released.send(TerritoryReleasedEvent { territory: id, guild, reason: ReleaseReason::UnpaidUpkeep });
+    }
+}
+
+fn apply_territory_buffs(
+    mut commands: Commands,
+    content: Res<TerritoryContent>,
+    state: Res<TerritoryState>,
+    members: Query<(Entity, &GlobalTransform, &GuildMember, Option<&TerritoryBuffs>)>,
+) {
+    for (entity, transform, member, current) in &members {
+        let owned = content
+            .at(transform.translation())
+            .filter(|def| !def.buffs.is_empty() && state.owner(&def.id) == Some(member.guild.as_str()));
+        match (owned, current) {
+            (Some(def), Some(buffs)) if buffs.territory == def.id && buffs.bonuses == def.buffs => {}
+            (Some(def), _) => {
+                let buffs = TerritoryBuffs { territory: def.id.clone(), bonuses: def.buffs.clone() };
+                commands.entity(entity).insert(buffs);
+            }
+            (None, Some(_)) => {
+                commands.entity(entity).remove::<TerritoryBuffs>();
+            }
+            (None, None) => {}
+        }
+    }
+}
+
+fn territory_command(
+    content: Res<TerritoryContent>,
+    clock: Res<WorldClock>,
+    mut state: ResMut<TerritoryState>,
+    mut control: ResMut<TerritoryControl>,
+    mut events: EventReader<ConsoleCommandEvent>,
+    mut output: EventWriter<ConsoleOutputEvent>,
+    (mut claimed, mut released): (EventWriter<TerritoryClaimedEvent>, EventWriter<TerritoryReleasedEvent>),
+) {
+    for event in events.read() {
+        if event.name != "territory" {
+            continue;
+        }
+        let args: Vec<&str> = event.args.iter().map(String::as_str).collect();
+        let result = match args.as_slice() {
+            [] => {
+                let mut lines = Vec::new();
+                for def in &content.territories {
+                    let owner = state.owner(&def.id).unwrap_or("unclaimed");
+                    let runtime = control.territories.get(&def.id);
+                    let vulnerable = runtime.is_some_and(|r| r.vulnerable);
+                    lines.push(format!(
+                        "{}: {}, upkeep {}c{}",
+                        def.id,
+                        owner,
+                        def.upkeep,
+                        if vulnerable { ", vulnerable" } else { "" }
+                    ));
+                    let objectives = def.objectives.iter().zip(runtime.map_or(&[][..], |r| r.objectives.as_slice()));
+                    for (objective, progress) in objectives {
+                        let capture = match &progress.capturing {
+                            _ if progress.contested => ", contested".to_string(),
+                            Some(guild) => format!(", {} capturing {:.0}%", guild, progress.progress * 100.0),
+                            None => String::new(),
+                        };
+                        let holder = progress.holder.as_deref().unwrap_or("nobody");
+                        lines.push(format!("  {}: held by {}{}", objective.id, holder, capture));
+                    }
+                }
+                for (guild, copper) in &state.treasuries {
+                    lines.push(format!("{} treasury: {}c", guild, copper));
+                }
+                Ok(if lines.is_empty() { "No territories loaded".to_string() } else { lines.join("\n") })
+            }
+            ["claim", id, guild] => match content.get(id) {
+                Some(def) => {
+                    let previous = state.owner(id).map(str::to_string);
+                    state.claim(id, guild, clock.now_secs());
+                    control.territories.remove(*id);
+                    claimed.send(TerritoryClaimedEvent {
+                        territory: def.id.clone(),
+                        guild: guild.to_string(),
+                        previous,
+                    });
+                    Ok(format!("{} now holds {}", guild, id))
+                }
+                None => Err(format!("unknown territory '{}'", id)),
+            },
+            ["release", id] => match state.release(id) {
+                Some(claim) => {
+                    control.territories.remove(*id);
+                    released.send(TerritoryReleasedEvent {
+                        territory: id.to_string(),
+                        guild: claim.guild.clone(),
+                        reason: ReleaseReason::Admin,
+                    });
+                    Ok(format!("Released {} from {}", id, claim.guild))
+                }
+                None => Err(format!("'{}' isn't claimed", id)),
+            },
+            ["deposit", guild, copper] => match copper.parse::<u64>() {
+                Ok(copper) => {
+                    state.deposit(guild, copper);
+                    Ok(format!("Deposited {}c for {}", copper, guild))
+                }
+                Err(_) => Err(format!("invalid amount '{}'", copper)),
+            },
+            _ => Err("usage: territory [claim <territory> <guild>|release <territory>|deposit <guild> <copper>]"
+                .to_string()),
+        };
+        output.send(match result {
+            Ok(text) => event.reply(text),
+            Err(text) => event.error(format!("{}: {}", event.name, text)),
+        });
+    }
+}
+
+fn save_territory_state(
+    time: Res<Time>,
+    config: Res<TerritoryConfig>,
+    mut state: ResMut<TerritoryState>,
+    mut exits: EventReader<AppExit>,
+    mut since_save: Local<f32>,
+) {
+    let Some(path) = &config.state_path else {
+        return;
+    };
+    *since_save += time.delta_secs();
+    let exiting = exits.read().count() > 0;
+    if !state.dirty || (*since_save < config.save_interval_seconds && !exiting) {
+        return;
+    }
+    *since_save = 0.0;
+    match state.save(path) {
+        Ok(()) => state.dirty = false,
+        Err(e) => warn!("Failed to save territory state {:?}: {}", path, e),
+    }
+}
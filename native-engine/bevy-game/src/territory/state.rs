@@ -0,0 +1,75 @@
+//! Claims and guild treasuries. This is the part of territory control that
+//! outlives a server restart; capture progress on objectives does not.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::persistence;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Claim {
+    pub guild: String,
+    /// Unix seconds.
+    pub claimed_at: u64,
+    /// Upkeep is charged again once this passes.
+    pub paid_until: u64,
+}
+
+#[derive(Resource, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TerritoryState {
+    /// Territory id to its current claim.
+    #[serde(default)]
+    pub claims: BTreeMap<String, Claim>,
+    /// Copper each guild has set aside for upkeep.
+    #[serde(default)]
+    pub treasuries: BTreeMap<String, u64>,
+    /// Changed since the last save.
+    #[serde(skip)]
+    pub dirty: bool,
+}
+
+impl TerritoryState {
+    pub fn owner(&self, territory: &str) -> Option<&str> {
+        self.claims.get(territory).map(|claim| claim.guild.as_str())
+    }
+
+    /// Hands `territory` to `guild`. The first upkeep is due right away.
+    pub fn claim(&mut self, territory: &str, guild: &str, now: u64) {
+        let claim = Claim { guild: guild.to_string(), claimed_at: now, paid_until: now };
+        self.claims.insert(territory.to_string(), claim);
+        self.dirty = true;
+    }
+
+    pub fn release(&mut self, territory: &str) -> Option<Claim> {
+        let claim = self.claims.remove(territory);
+        self.dirty |= claim.is_some();
+        claim
+    }
+
+    pub fn deposit(&mut self, guild: &str, copper: u64) {
+        *self.treasuries.entry(guild.to_string()).or_default() += copper;
+        self.dirty = true;
+    }
+
+    /// Takes `copper` from the guild's treasury if it holds enough.
+    pub fn withdraw(&mut self, guild: &str, copper: u64) -> bool {
+        match self.treasuries.get_mut(guild) {
+            Some(balance) if *balance >= copper => {
+                *balance -= copper;
+                self.dirty = true;
+                true
+            }
+            _ => copper == 0,
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        persistence::load_json(path)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        persistence::save_json(self, path)
+    }
+}
@@ -0,0 +1,166 @@
+//! Combat overlays for players, each toggled in [`ThreatDisplaySettings`]:
+//!
+//! - Aggro rings around hostile NPCs, drawn at the range the AI stage
+//!   actually senses at (level-scaled, see [`AiStageConfig::aggro_range`])
+//!   and colored by the level difference.
+//! - A pulsing red glow around the screen while NPCs are after the player.
+//! - Lines from NPCs to whatever they're chasing or attacking.
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts, EguiPlugin};
+use std::f32::consts::FRAC_PI_2;
+
+use crate::ai_stage::{AiAgent, AiIntent, AiSpatialIndex, AiStageConfig, Perception, PLAYER_FACTION};
+use crate::settings::{ThreatDisplaySettings, UserSettings};
+use crate::Player;
+
+/// Rings fade in from this far outside them to full strength at the edge.
+const RING_FADE_DISTANCE: f32 = 15.0;
+
+/// Hostile NPCs chasing or attacking the local player, counted each frame.
+#[derive(Resource, Debug, Default, PartialEq)]
+pub struct Tanking {
+    pub attackers: usize,
+}
+
+pub struct ThreatDisplayPlugin;
+
+impl Plugin for ThreatDisplayPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugins(EguiPlugin);
+        }
+        app.init_resource::<Tanking>().add_systems(Update, (count_attackers, tanking_warning_ui).chain());
+
+        // Gizmos only exist when rendering
+        if app.is_plugin_added::<bevy::gizmos::GizmoPlugin>() {
+            app.add_systems(Update, draw_threat_gizmos);
+        }
+    }
+}
+
+fn settings_of(settings: &Option<Res<UserSettings>>) -> ThreatDisplaySettings {
+    settings.as_ref().map(|s| s.threat_display.clone()).unwrap_or_default()
+}
+
+fn intent_target(intent: &AiIntent) -> Option<Entity> {
+    match intent {
+        AiIntent::Chase(target) | AiIntent::Attack(target) => Some(*target),
+        AiIntent::Idle | AiIntent::ReturnHome => None,
+    }
+}
+
+/// Classic "con" colors: grey (no threat) through green, yellow and orange
+/// to red for NPCs well above the player.
+fn level_color(agent_level: u32, player_level: Option<u32>) -> Color {
+    let Some(player_level) = player_level.filter(|_| agent_level > 0) else {
+        return Color::srgb(1.0, 0.9, 0.2);
+    };
+    match agent_level as i64 - player_level as i64 {
+        5.. => Color::srgb(0.95, 0.15, 0.1),
+        3..=4 => Color::srgb(1.0, 0.5, 0.1),
+        -2..=2 => Color::srgb(1.0, 0.9, 0.2),
+        -7..=-3 => Color::srgb(0.3, 0.9, 0.3),
+        _ => Color::srgb(0.6, 0.6, 0.6),
+    }
+}
+
+fn count_attackers(
+    mut tanking: ResMut<Tanking>,
+    player: Query<Entity, With<Player>>,
+    agents: Query<(&AiAgent, &AiIntent)>,
+) {
+    let attackers = player.get_single().map_or(0, |player| {
+        agents
+            .iter()
+            .filter(|(agent, intent)| agent.faction != PLAYER_FACTION && intent_target(intent) == Some(player))
+            .count()
+    });
+    tanking.set_if_neq(Tanking { attackers });
+}
+
+fn draw_threat_gizmos(
+    settings: Option<Res<UserSettings>>,
+    config: Res<AiStageConfig>,
+    index: Res<AiSpatialIndex>,
+    player: Query<(Entity, &Transform), With<Player>>,
+    agents: Query<(&AiAgent, &Transform, &Perception, &AiIntent)>,
+    targets: Query<&Transform>,
+    mut gizmos: Gizmos,
+) {
+    let settings = settings_of(&settings);
+    if !settings.aggro_rings && !settings.target_lines {
+        return;
+    }
+    let Ok((player, player_transform)) = player.get_single() else {
+        return;
+    };
+    let player_position = player_transform.translation;
+    let player_level = index.player_level();
+
+    for (agent, transform, perception, intent) in &agents {
+        let position = transform.translation;
+        let distance = position.distance(player_position);
+        if agent.faction == PLAYER_FACTION || distance > settings.draw_distance || perception.beyond_leash {
+            continue;
+        }
+        let target = intent_target(intent);
+
+        if settings.aggro_rings && target.is_none() {
+            let range = config.aggro_range(agent, player_level);
+            // Rings fade in as the player walks up to them
+            let closeness = 1.0 - ((distance - range) / RING_FADE_DISTANCE).clamp(0.0, 1.0);
+            let color = level_color(agent.level, player_level).with_alpha(0.25 + 0.6 * closeness);
+            let ring = Isometry3d::new(position + Vec3::Y * 0.1, Quat::from_rotation_x(FRAC_PI_2));
+            gizmos.circle(ring, range, color);
+        }
+
+        if settings.target_lines {
+            if let Some(target_position) = target.and_then(|t| targets.get(t).ok()).map(|t| t.translation) {
+                let color = if target == Some(player) {
+                    Color::srgb(1.0, 0.2, 0.15)
+                } else {
+                    Color::srgba(1.0, 0.6, 0.2, 0.7)
+                };
+                gizmos.line(position + Vec3::Y * 1.5, target_position + Vec3::Y * 1.5, color);
+            }
+        }
+    }
+}
+
+fn tanking_warning_ui(
+    time: Res<Time>,
+    settings: Option<Res<UserSettings>>,
+    tanking: Res<Tanking>,
+    mut contexts: EguiContexts,
+) {
+    if tanking.attackers == 0 || !settings_of(&settings).tanking_warning {
+        return;
+    }
+    let ctx = contexts.ctx_mut().clone();
+    let screen = ctx.screen_rect();
+    let pulse = 0.6 + 0.4 * (time.elapsed_secs() * 4.0).sin();
+    let painter = ctx.layer_painter(egui::LayerId::new(egui::Order::Background, egui::Id::new("tanking_glow")));
+
+    // Nested strokes, fainter towards the middle, read as a soft glow
+    for step in 0..8 {
+        let inset = step as f32 * 4.0 + 2.0;
+        let alpha = (pulse * 110.0 * (1.0 - step as f32 / 8.0)) as u8;
+        painter.rect_stroke(
+            screen.shrink(inset),
+            0.0,
+            egui::Stroke::new(4.0, egui::Color32::from_rgba_unmultiplied(220, 30, 20, alpha)),
+        );
+    }
+    let text = match tanking.attackers {
+        1 => "You are tanking".to_string(),
+        attackers => format!("You are tanking {} enemies", attackers),
+    };
+    painter.text(
+        egui::pos2(screen.center().x, screen.top() + 48.0),
+        egui::Align2::CENTER_CENTER,
+        text,
+        egui::FontId::proportional(20.0),
+        egui::Color32::from_rgba_unmultiplied(255, 90, 70, (pulse * 255.0) as u8),
+    );
+}
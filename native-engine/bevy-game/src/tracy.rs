@@ -0,0 +1,63 @@
+//! Tracy capture support. Build with `--features tracy` (the launcher's
+//! `--tracy` toggle does this). Every Bevy system shows up as a zone through
+//! `bevy/trace_tracy`; `profile_zone!` marks sections inside the heavy ones,
+//! and `TracyPlugin` adds a simulation frame mark and entity-count plots.
+
+use bevy::prelude::*;
+
+/// Opens a named profiler zone that lasts until the end of the enclosing
+/// block. Compiles to nothing without the `trace` feature.
+#[macro_export]
+macro_rules! profile_zone {
+    ($name:literal) => {
+        #[cfg(feature = "trace")]
+        let _profile_zone = bevy::log::info_span!($name).entered();
+    };
+}
+
+#[cfg(feature = "tracy")]
+pub use capture::TracyPlugin;
+
+#[cfg(feature = "tracy")]
+mod capture {
+    use super::*;
+    use crate::{NetworkEntity, Player};
+    use tracy_client::{frame_name, plot_name, Client};
+
+    pub struct TracyPlugin;
+
+    impl Plugin for TracyPlugin {
+        fn build(&self, app: &mut App) {
+            // The trace_tracy log layer also starts the client, but headless
+            // runs have no LogPlugin, so make sure it's up for plots.
+            let client = Client::start();
+            client.message(concat!("mmo-engine ", env!("CARGO_PKG_VERSION")), 0);
+            info!("Tracy capture enabled; connect the Tracy profiler to this process");
+
+            app.add_systems(Last, (plot_entity_counts, mark_simulation_frame).chain());
+        }
+    }
+
+    fn plot_entity_counts(
+        entities: Query<Entity>,
+        meshes: Query<(), With<Mesh3d>>,
+        players: Query<(), With<Player>>,
+        remote: Query<(), With<NetworkEntity>>,
+    ) {
+        let Some(client) = Client::running() else {
+            return;
+        };
+        client.plot(plot_name!("entities"), entities.iter().len() as f64);
+        client.plot(plot_name!("mesh entities"), meshes.iter().len() as f64);
+        client.plot(plot_name!("players"), players.iter().len() as f64);
+        client.plot(plot_name!("network entities"), remote.iter().len() as f64);
+    }
+
+    /// Bevy marks rendered frames itself; this marks simulation ticks, which
+    /// also covers headless servers.
+    fn mark_simulation_frame() {
+        if let Some(client) = Client::running() {
+            client.secondary_frame_mark(frame_name!("simulation"));
+        }
+    }
+}
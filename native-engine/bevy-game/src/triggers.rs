@@ -0,0 +1,565 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::content_packs::ContentPacks;
+use crate::hazards::{DamageSchool, HazardCause, HazardConfig, HazardDamageEvent, Invulnerable};
+use crate::hot_reload::{ContentChangedEvent, ContentReloadedEvent};
+use crate::Player;
+
+/// Shape of a trigger volume, in the volume's local space.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TriggerShape {
+    Box { half_extents: [f32; 3] },
+    Sphere { radius: f32 },
+    /// Polygon on the local XZ plane extruded between `min_y` and `max_y`.
+    /// Used for irregular zone borders that boxes can't follow.
+    Prism { points: Vec<[f32; 2]>, min_y: f32, max_y: f32 },
+}
+
+impl TriggerShape {
+    pub fn contains_local(&self, p: Vec3) -> bool {
+        match self {
+            TriggerShape::Box { half_extents } => {
+                p.x.abs() <= half_extents[0] && p.y.abs() <= half_extents[1] && p.z.abs() <= half_extents[2]
+            }
+            TriggerShape::Sphere { radius } => p.length_squared() <= radius * radius,
+            TriggerShape::Prism { points, min_y, max_y } => {
+                if p.y < *min_y || p.y > *max_y || points.len() < 3 {
+                    return false;
+                }
+                // Even-odd ray crossing on the XZ plane
+                let mut inside = false;
+                let mut j = points.len() - 1;
+                for i in 0..points.len() {
+                    let (xi, zi) = (points[i][0], points[i][1]);
+                    let (xj, zj) = (points[j][0], points[j][1]);
+                    if (zi > p.z) != (zj > p.z) && p.x < (xj - xi) * (p.z - zi) / (zj - zi) + xi {
+                        inside = !inside;
+                    }
+                    j = i;
+                }
+                inside
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TriggerAction {
+    EnterZone { zone_id: String },
+    StartCutscene { cutscene: String },
+    SpawnAmbush { template: String, count: u32, radius: f32 },
+    EnvironmentalDamage { damage_per_second: f32, school: DamageSchool },
+    /// Free-form hook for quest scripts; only raises [`TriggerEnteredEvent`].
+    Custom { name: String },
+}
+
+/// Authoring description of a trigger volume as stored in content files and
+/// written back by the level editor.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TriggerVolumeDef {
+    pub id: String,
+    pub position: [f32; 3],
+    #[serde(default)]
+    pub yaw_degrees: f32,
+    pub shape: TriggerShape,
+    #[serde(default)]
+    pub actions: Vec<TriggerAction>,
+    #[serde(default)]
+    pub once: bool,
+    #[serde(default = "default_players_only")]
+    pub players_only: bool,
+    #[serde(default)]
+    pub cooldown: f32,
+}
+
+fn default_players_only() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TriggerVolumeFile {
+    #[serde(default)]
+    pub volumes: Vec<TriggerVolumeDef>,
+}
+
+#[derive(Component, Debug, Clone)]
+pub struct TriggerVolume {
+    pub id: String,
+    pub shape: TriggerShape,
+    pub actions: Vec<TriggerAction>,
+    pub once: bool,
+    pub players_only: bool,
+    pub cooldown: f32,
+    pub enabled: bool,
+    /// Content file this volume came from, so editor saves go back to the same file.
+    pub source: Option<PathBuf>,
+}
+
+impl TriggerVolume {
+    pub fn new(id: impl Into<String>, shape: TriggerShape) -> Self {
+        Self {
+            id: id.into(),
+            shape,
+            actions: Vec::new(),
+            once: false,
+            players_only: true,
+            cooldown: 0.0,
+            enabled: true,
+            source: None,
+        }
+    }
+
+    pub fn with_action(mut self, action: TriggerAction) -> Self {
+        self.actions.push(action);
+        self
+    }
+
+    pub fn to_def(&self, transform: &Transform) -> TriggerVolumeDef {
+        let (yaw, _, _) = transform.rotation.to_euler(EulerRot::YXZ);
+        TriggerVolumeDef {
+            id: self.id.clone(),
+            position: transform.translation.to_array(),
+            yaw_degrees: yaw.to_degrees(),
+            shape: self.shape.clone(),
+            actions: self.actions.clone(),
+            once: self.once,
+            players_only: self.players_only,
+            cooldown: self.cooldown,
+        }
+    }
+}
+
+/// Runtime occupancy of a trigger volume.
+#[derive(Component, Debug, Default)]
+pub struct TriggerOccupants {
+    pub inside: HashSet<Entity>,
+    pub fired_once: bool,
+    pub cooldown_remaining: f32,
+    pub damage_tick_timer: f32,
+}
+
+/// Non-player entities that should set off trigger volumes (escort NPCs, mounts).
+#[derive(Component, Debug, Clone, Copy, Default)]
+pub struct TriggerActivator;
+
+#[derive(Event, Debug, Clone)]
+pub struct TriggerEnteredEvent {
+    pub trigger: Entity,
+    pub trigger_id: String,
+    pub entity: Entity,
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct TriggerExitedEvent {
+    pub trigger: Entity,
+    pub trigger_id: String,
+    pub entity: Entity,
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct ZoneEnteredEvent {
+    pub entity: Entity,
+    pub zone_id: String,
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct CutsceneRequestEvent {
+    pub cutscene: String,
+    pub instigator: Entity,
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct AmbushSpawnRequest {
+    pub template: String,
+    pub count: u32,
+    pub center: Vec3,
+    pub radius: f32,
+    pub target: Entity,
+}
+
+/// Ask the trigger system to write all volumes back to their content files.
+#[derive(Event, Debug, Clone, Default)]
+pub struct SaveTriggerVolumesEvent;
+
+#[derive(Resource, Debug, Clone)]
+pub struct TriggerContentConfig {
+    pub directory: PathBuf,
+    pub debug_draw: bool,
+}
+
+impl Default for TriggerContentConfig {
+    fn default() -> Self {
+        Self {
+            directory: PathBuf::from("content").join("triggers"),
+            debug_draw: false,
+        }
+    }
+}
+
+pub struct TriggerVolumePlugin;
+
+impl Plugin for TriggerVolumePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TriggerContentConfig>()
+            .init_resource::<ContentPacks>()
+            .add_event::<TriggerEnteredEvent>()
+            .add_event::<TriggerExitedEvent>()
+            .add_event::<ZoneEnteredEvent>()
+            .add_event::<CutsceneRequestEvent>()
+            .add_event::<AmbushSpawnRequest>()
+            .add_event::<SaveTriggerVolumesEvent>()
+            .add_event::<ContentChangedEvent>()
+            .add_event::<ContentReloadedEvent>()
+            .add_systems(Startup, load_trigger_content)
+            .add_systems(
+                Update,
+                (
+                    reload_trigger_content,
+                    ensure_trigger_occupants,
+                    update_trigger_volumes,
+                    apply_environmental_damage,
+                    save_trigger_volumes,
+                )
+                    .chain(),
+            );
+
+        // Gizmos only exist when rendering
+        if app.is_plugin_added::<bevy::gizmos::GizmoPlugin>() {
+            app.add_systems(Update, draw_trigger_volumes);
+        }
+    }
+}
+
+pub fn spawn_trigger_volume(commands: &mut Commands, def: &TriggerVolumeDef, source: Option<PathBuf>) -> Entity {
+    let transform = Transform::from_translation(Vec3::from_array(def.position))
+        .with_rotation(Quat::from_rotation_y(def.yaw_degrees.to_radians()));
+
+    commands
+        .spawn((
+            TriggerVolume {
+                id: def.id.clone(),
+                shape: def.shape.clone(),
+                actions: def.actions.clone(),
+                once: def.once,
+                players_only: def.players_only,
+                cooldown: def.cooldown,
+                enabled: true,
+                source,
+            },
+            TriggerOccupants::default(),
+            transform,
+            GlobalTransform::default(),
+            Name::new(format!("Trigger: {}", def.id)),
+        ))
+        .id()
+}
+
+fn load_trigger_file(path: &Path) -> Result<TriggerVolumeFile, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("failed to read: {}", e))?;
+    toml::from_str::<TriggerVolumeFile>(&text).map_err(|e| format!("invalid trigger file: {}", e))
+}
+
+fn load_trigger_content(mut commands: Commands, config: Res<TriggerContentConfig>, packs: Res<ContentPacks>) {
+    let mut count = 0;
+    for path in packs.files(&config.directory, "toml") {
+        let file = match load_trigger_file(&path) {
+            Ok(file) => file,
+            Err(e) => {
+                warn!("Trigger content {:?}: {}", path, e);
+                continue;
+            }
+        };
+        for def in &file.volumes {
+            spawn_trigger_volume(&mut commands, def, Some(path.clone()));
+            count += 1;
+        }
+    }
+
+    info!("Loaded {} trigger volumes from {:?}", count, config.directory);
+}
+
+/// Applies an edited trigger file to the live volumes, matching them by id so
+/// occupancy and once/cooldown state carry over.
+fn reload_trigger_content(
+    mut commands: Commands,
+    mut changed: EventReader<ContentChangedEvent>,
+    mut reloaded: EventWriter<ContentReloadedEvent>,
+    config: Res<TriggerContentConfig>,
+    mut volumes: Query<(Entity, &mut TriggerVolume, &mut Transform)>,
+) {
+    for event in changed.read() {
+        let is_trigger_file =
+            event.path.starts_with(&config.directory) && event.path.extension().and_then(|e| e.to_str()) == Some("toml");
+        if !is_trigger_file {
+            continue;
+        }
+        let file = if event.removed {
+            TriggerVolumeFile::default()
+        } else {
+            match load_trigger_file(&event.path) {
+                Ok(file) => file,
+                Err(e) => {
+                    reloaded.send(ContentReloadedEvent::failed(&event.path, e));
+                    continue;
+                }
+            }
+        };
+
+        let mut remaining: HashMap<&str, &TriggerVolumeDef> =
+            file.volumes.iter().map(|def| (def.id.as_str(), def)).collect();
+        let mut removed = 0;
+        for (entity, mut volume, mut transform) in volumes.iter_mut() {
+            if volume.source.as_ref() != Some(&event.path) {
+                continue;
+            }
+            let Some(def) = remaining.remove(volume.id.as_str()) else {
+                commands.entity(entity).despawn_recursive();
+                removed += 1;
+                continue;
+            };
+            volume.shape = def.shape.clone();
+            volume.actions = def.actions.clone();
+            volume.once = def.once;
+            volume.players_only = def.players_only;
+            volume.cooldown = def.cooldown;
+            *transform = Transform::from_translation(Vec3::from_array(def.position))
+                .with_rotation(Quat::from_rotation_y(def.yaw_degrees.to_radians()));
+        }
+        for def in remaining.values() {
+            spawn_trigger_volume(&mut commands, def, Some(event.path.clone()));
+        }
+        reloaded.send(ContentReloadedEvent::applied(
+            &event.path,
+            format!(
+                "{} trigger volumes ({} added, {} removed)",
+                file.volumes.len(),
+                remaining.len(),
+                removed
+            ),
+        ));
+    }
+}
+
+fn ensure_trigger_occupants(
+    mut commands: Commands,
+    volumes: Query<Entity, (With<TriggerVolume>, Without<TriggerOccupants>)>,
+) {
+    for entity in volumes.iter() {
+        commands.entity(entity).insert(TriggerOccupants::default());
+    }
+}
+
+fn update_trigger_volumes(
+    time: Res<Time>,
+    mut volumes: Query<(Entity, &TriggerVolume, &GlobalTransform, &mut TriggerOccupants)>,
+    activators: Query<(Entity, &GlobalTransform, Has<Player>), Or<(With<Player>, With<TriggerActivator>)>>,
+    mut entered: EventWriter<TriggerEnteredEvent>,
+    mut exited: EventWriter<TriggerExitedEvent>,
+    mut zones: EventWriter<ZoneEnteredEvent>,
+    mut cutscenes: EventWriter<CutsceneRequestEvent>,
+    mut ambushes: EventWriter<AmbushSpawnRequest>,
+) {
+    let dt = time.delta_secs();
+
+    for (trigger, volume, transform, mut occupants) in volumes.iter_mut() {
+        if occupants.cooldown_remaining > 0.0 {
+            occupants.cooldown_remaining -= dt;
+        }
+        if !volume.enabled {
+            continue;
+        }
+
+        let to_local = transform.affine().inverse();
+        let mut now_inside = HashSet::new();
+
+        for (entity, activator_transform, is_player) in activators.iter() {
+            if volume.players_only && !is_player {
+                continue;
+            }
+            let local = to_local.transform_point3(activator_transform.translation());
+            if volume.shape.contains_local(local) {
+                now_inside.insert(entity);
+            }
+        }
+
+        for entity in occupants.inside.difference(&now_inside) {
+            exited.send(TriggerExitedEvent {
+                trigger,
+                trigger_id: volume.id.clone(),
+                entity: *entity,
+            });
+        }
+
+        let newcomers: Vec<Entity> = now_inside.difference(&occupants.inside).copied().collect();
+        occupants.inside = now_inside;
+
+        for entity in newcomers {
+            if (volume.once && occupants.fired_once) || occupants.cooldown_remaining > 0.0 {
+                continue;
+            }
+            occupants.fired_once = true;
+            occupants.cooldown_remaining = volume.cooldown;
+
+            entered.send(TriggerEnteredEvent {
+                trigger,
+                trigger_id: volume.id.clone(),
+                entity,
+            });
+
+            for action in &volume.actions {
+                match action {
+                    TriggerAction::EnterZone { zone_id } => {
+                        zones.send(ZoneEnteredEvent {
+                            entity,
+                            zone_id: zone_id.clone(),
+                        });
+                    }
+                    TriggerAction::StartCutscene { cutscene } => {
+                        cutscenes.send(CutsceneRequestEvent {
+                            cutscene: cutscene.clone(),
+                            instigator: entity,
+                        });
+                    }
+                    TriggerAction::SpawnAmbush { template, count, radius } => {
+                        ambushes.send(AmbushSpawnRequest {
+                            template: template.clone(),
+                            count: *count,
+                            center: transform.translation(),
+                            radius: *radius,
+                            target: entity,
+                        });
+                    }
+                    // Applied continuously while inside
+                    TriggerAction::EnvironmentalDamage { .. } | TriggerAction::Custom { .. } => {}
+                }
+            }
+        }
+    }
+}
+
+fn apply_environmental_damage(
+    time: Res<Time>,
+    config: Option<Res<HazardConfig>>,
+    mut volumes: Query<(&TriggerVolume, &mut TriggerOccupants)>,
+    invulnerable: Query<(), With<Invulnerable>>,
+    mut damage: EventWriter<HazardDamageEvent>,
+) {
+    let interval = config.map_or(1.0, |c| c.volume_tick_interval).max(0.05);
+
+    for (volume, mut occupants) in volumes.iter_mut() {
+        if !volume.enabled || occupants.inside.is_empty() {
+            occupants.damage_tick_timer = 0.0;
+            continue;
+        }
+
+        // Damage lands in discrete ticks so the combat log stays readable
+        occupants.damage_tick_timer += time.delta_secs();
+        if occupants.damage_tick_timer < interval {
+            continue;
+        }
+        occupants.damage_tick_timer -= interval;
+
+        for action in &volume.actions {
+            let TriggerAction::EnvironmentalDamage { damage_per_second, school } = action else {
+                continue;
+            };
+            for target in occupants.inside.iter().filter(|t| !invulnerable.contains(**t)) {
+                damage.send(HazardDamageEvent {
+                    target: *target,
+                    amount: damage_per_second * interval,
+                    school: *school,
+                    cause: HazardCause::Volume {
+                        trigger_id: volume.id.clone(),
+                    },
+                });
+            }
+        }
+    }
+}
+
+fn save_trigger_volumes(
+    mut events: EventReader<SaveTriggerVolumesEvent>,
+    config: Res<TriggerContentConfig>,
+    volumes: Query<(&TriggerVolume, &Transform)>,
+) {
+    if events.read().count() == 0 {
+        return;
+    }
+
+    let default_path = config.directory.join("editor.toml");
+    let mut files: HashMap<PathBuf, TriggerVolumeFile> = HashMap::new();
+    for (volume, transform) in volumes.iter() {
+        let path = volume.source.clone().unwrap_or_else(|| default_path.clone());
+        files.entry(path).or_default().volumes.push(volume.to_def(transform));
+    }
+
+    if let Err(e) = std::fs::create_dir_all(&config.directory) {
+        error!("Failed to create trigger directory {:?}: {}", config.directory, e);
+        return;
+    }
+
+    for (path, mut file) in files {
+        file.volumes.sort_by(|a, b| a.id.cmp(&b.id));
+        match toml::to_string_pretty(&file) {
+            Ok(text) => {
+                if let Err(e) = std::fs::write(&path, text) {
+                    error!("Failed to write trigger file {:?}: {}", path, e);
+                } else {
+                    info!("Saved {} trigger volumes to {:?}", file.volumes.len(), path);
+                }
+            }
+            Err(e) => error!("Failed to serialize trigger file {:?}: {}", path, e),
+        }
+    }
+}
+
+fn draw_trigger_volumes(
+    config: Res<TriggerContentConfig>,
+    volumes: Query<(&TriggerVolume, &GlobalTransform, &TriggerOccupants)>,
+    mut gizmos: Gizmos,
+) {
+    if !config.debug_draw {
+        return;
+    }
+
+    for (volume, transform, occupants) in volumes.iter() {
+        let color = if occupants.inside.is_empty() {
+            Color::srgba(0.2, 0.8, 1.0, 0.8)
+        } else {
+            Color::srgba(1.0, 0.4, 0.2, 0.9)
+        };
+        let (_, rotation, translation) = transform.to_scale_rotation_translation();
+
+        match &volume.shape {
+            TriggerShape::Box { half_extents } => {
+                let size = Vec3::from_array(*half_extents) * 2.0;
+                gizmos.cuboid(
+                    Transform::from_translation(translation)
+                        .with_rotation(rotation)
+                        .with_scale(size),
+                    color,
+                );
+            }
+            TriggerShape::Sphere { radius } => {
+                gizmos.sphere(Isometry3d::new(translation, rotation), *radius, color);
+            }
+            TriggerShape::Prism { points, min_y, max_y } => {
+                for (i, a) in points.iter().enumerate() {
+                    let b = points[(i + 1) % points.len()];
+                    let bottom_a = transform.transform_point(Vec3::new(a[0], *min_y, a[1]));
+                    let bottom_b = transform.transform_point(Vec3::new(b[0], *min_y, b[1]));
+                    let top_a = transform.transform_point(Vec3::new(a[0], *max_y, a[1]));
+                    let top_b = transform.transform_point(Vec3::new(b[0], *max_y, b[1]));
+                    gizmos.line(bottom_a, bottom_b, color);
+                    gizmos.line(top_a, top_b, color);
+                    gizmos.line(bottom_a, top_a, color);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,399 @@
+//! New-player onboarding: hint popups the first time something happens,
+//! and scripted starter sequences. Both live in `content/tutorials/*.toml`:
+//!
+//! ```toml
+//! [[hint]]
+//! id = "first_mount"
+//! on = "mount"
+//! title = "Riding"
+//! text = "Hold Jump while mounted to take off."
+//!
+//! [[sequence]]
+//! id = "starter_vale"
+//! on = "zone:starter_vale"
+//! steps = [
+//!     { type = "cutscene", cutscene = "vale_arrival" },
+//!     { type = "hint", hint = "talk_to_npcs" },
+//!     { type = "quest", quest = "first_steps" },
+//!     { type = "wait", on = "quest_complete:first_steps" },
+//!     { type = "hint", hint = "open_world" },
+//! ]
+//! ```
+//!
+//! Hints and sequences key off [`TutorialTriggerEvent`]s. This module raises
+//! `mount`, `zone:<id>`, `trigger:<id>`, `loot`, `gather` and `dungeon`
+//! itself; combat sends `death` and the quest system sends
+//! `quest_accepted:<id>` and `quest_complete:<id>`. Each hint and sequence
+//! runs once, tracked in the tutorial settings until the player resets them.
+
+mod ui;
+
+pub use ui::*;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+
+use crate::console::{
+    ArgCompletion, CommandPermission, ConsoleAppExt, ConsoleCommand, ConsoleCommandEvent, ConsoleOutputEvent,
+};
+use crate::content_packs::ContentPacks;
+use crate::dungeons::DungeonEnteredEvent;
+use crate::gathering::GatherYieldEvent;
+use crate::interactables::LootAwardedEvent;
+use crate::mount_environment::MountEnvironment;
+use crate::settings::UserSettings;
+use crate::triggers::{CutsceneRequestEvent, TriggerEnteredEvent, ZoneEnteredEvent};
+use crate::Player;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HintDef {
+    pub id: String,
+    /// Trigger key that shows it; hints only shown by sequences leave it out.
+    #[serde(default)]
+    pub on: Option<String>,
+    pub title: String,
+    pub text: String,
+    /// Seconds before it dismisses itself.
+    #[serde(default = "default_hint_seconds")]
+    pub seconds: f32,
+}
+
+fn default_hint_seconds() -> f32 {
+    12.0
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TutorialStep {
+    Cutscene { cutscene: String },
+    Hint { hint: String },
+    Quest { quest: String },
+    /// Waits for the trigger key `on`.
+    Wait { on: String },
+    Delay { seconds: f32 },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SequenceDef {
+    pub id: String,
+    /// Trigger key that starts it.
+    pub on: String,
+    #[serde(default)]
+    pub steps: Vec<TutorialStep>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TutorialContentFile {
+    #[serde(default, rename = "hint")]
+    pub hints: Vec<HintDef>,
+    #[serde(default, rename = "sequence")]
+    pub sequences: Vec<SequenceDef>,
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct TutorialConfig {
+    pub content_directory: PathBuf,
+}
+
+impl Default for TutorialConfig {
+    fn default() -> Self {
+        Self {
+            content_directory: PathBuf::from("content").join("tutorials"),
+        }
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct TutorialContent {
+    pub hints: HashMap<String, HintDef>,
+    pub sequences: Vec<SequenceDef>,
+}
+
+/// Something happened to the local player that a hint or sequence may key
+/// off, e.g. `mount` or `quest_complete:first_steps`.
+#[derive(Event, Debug, Clone)]
+pub struct TutorialTriggerEvent {
+    pub key: String,
+}
+
+impl TutorialTriggerEvent {
+    pub fn new(key: impl Into<String>) -> Self {
+        Self { key: key.into() }
+    }
+}
+
+/// A starter sequence hands the player `quest`; the quest system grants it.
+#[derive(Event, Debug, Clone)]
+pub struct TutorialQuestRequest {
+    pub player: Entity,
+    pub quest: String,
+}
+
+/// Forgets every hint and sequence the player has seen, so they run again.
+#[derive(Event, Debug, Clone, Copy, Default)]
+pub struct ResetTutorialsRequest;
+
+/// Hints waiting to be shown, oldest first.
+#[derive(Resource, Debug, Default)]
+pub struct HintQueue {
+    pub pending: VecDeque<String>,
+}
+
+#[derive(Debug, Clone)]
+struct RunningSequence {
+    id: String,
+    step: usize,
+    step_started: f32,
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct RunningSequences {
+    running: Vec<RunningSequence>,
+}
+
+impl RunningSequences {
+    pub fn len(&self) -> usize {
+        self.running.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.running.is_empty()
+    }
+}
+
+fn sequence_key(id: &str) -> String {
+    format!("sequence:{}", id)
+}
+
+pub struct TutorialPlugin;
+
+impl Plugin for TutorialPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TutorialConfig>()
+            .init_resource::<TutorialContent>()
+            .init_resource::<HintQueue>()
+            .init_resource::<RunningSequences>()
+            .add_event::<TutorialTriggerEvent>()
+            .add_event::<TutorialQuestRequest>()
+            .add_event::<ResetTutorialsRequest>()
+            .add_event::<CutsceneRequestEvent>()
+            .add_console_command(
+                ConsoleCommand::new("tutorial", "Fires a tutorial trigger, or forgets every seen tutorial")
+                    .usage("<trigger key|reset>")
+                    .permission(CommandPermission::Dev)
+                    .complete(vec![ArgCompletion::Values(vec!["reset".to_string()])]),
+            )
+            .add_systems(Startup, load_tutorials)
+            .add_systems(
+                Update,
+                (detect_triggers, reset_tutorials, tutorial_command, start_tutorials, run_sequences).chain(),
+            );
+    }
+}
+
+fn load_tutorials(config: Res<TutorialConfig>, packs: Res<ContentPacks>, mut content: ResMut<TutorialContent>) {
+    for path in packs.files(&config.content_directory, "toml") {
+        let parsed = std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| toml::from_str::<TutorialContentFile>(&text).map_err(|e| e.to_string()));
+
+        match parsed {
+            Ok(file) => {
+                for hint in file.hints {
+                    content.hints.insert(hint.id.clone(), hint);
+                }
+                content.sequences.extend(file.sequences);
+            }
+            Err(e) => warn!("Invalid tutorial file {:?}: {}", path, e),
+        }
+    }
+    for sequence in &content.sequences {
+        for step in &sequence.steps {
+            if let TutorialStep::Hint { hint } = step {
+                if !content.hints.contains_key(hint) {
+                    warn!("Tutorial sequence '{}' shows unknown hint '{}'", sequence.id, hint);
+                }
+            }
+        }
+    }
+
+    info!("Tutorials: {} hints, {} sequences loaded", content.hints.len(), content.sequences.len());
+}
+
+/// Turns what the local player does into trigger keys.
+fn detect_triggers(
+    players: Query<Entity, With<Player>>,
+    mounted: Query<(), (Added<MountEnvironment>, With<Player>)>,
+    (mut zones, mut entered): (EventReader<ZoneEnteredEvent>, EventReader<TriggerEnteredEvent>),
+    mut loot: EventReader<LootAwardedEvent>,
+    mut gathered: EventReader<GatherYieldEvent>,
+    mut dungeons: EventReader<DungeonEnteredEvent>,
+    mut triggers: EventWriter<TutorialTriggerEvent>,
+) {
+    let Ok(player) = players.get_single() else {
+        zones.clear();
+        entered.clear();
+        loot.clear();
+        gathered.clear();
+        dungeons.clear();
+        return;
+    };
+    let mut keys = Vec::new();
+    if !mounted.is_empty() {
+        keys.push("mount".to_string());
+    }
+    keys.extend(zones.read().filter(|e| e.entity == player).map(|e| format!("zone:{}", e.zone_id)));
+    keys.extend(entered.read().filter(|e| e.entity == player).map(|e| format!("trigger:{}", e.trigger_id)));
+    if loot.read().filter(|e| e.recipient == player).count() > 0 {
+        keys.push("loot".to_string());
+    }
+    if gathered.read().filter(|e| e.actor == player).count() > 0 {
+        keys.push("gather".to_string());
+    }
+    if dungeons.read().filter(|e| e.character == player).count() > 0 {
+        keys.push("dungeon".to_string());
+    }
+    triggers.send_batch(keys.into_iter().map(TutorialTriggerEvent::new));
+}
+
+fn reset_tutorials(
+    mut requests: EventReader<ResetTutorialsRequest>,
+    settings: Option<ResMut<UserSettings>>,
+    mut queue: ResMut<HintQueue>,
+    mut sequences: ResMut<RunningSequences>,
+) {
+    if requests.read().count() == 0 {
+        return;
+    }
+    if let Some(mut settings) = settings {
+        settings.tutorials.seen.clear();
+    }
+    queue.pending.clear();
+    sequences.running.clear();
+    info!("Tutorials reset");
+}
+
+fn tutorial_command(
+    mut events: EventReader<ConsoleCommandEvent>,
+    mut output: EventWriter<ConsoleOutputEvent>,
+    (mut triggers, mut resets): (EventWriter<TutorialTriggerEvent>, EventWriter<ResetTutorialsRequest>),
+) {
+    for event in events.read() {
+        if event.name != "tutorial" {
+            continue;
+        }
+        let result = match event.args.first().map(String::as_str) {
+            Some("reset") => {
+                resets.send(ResetTutorialsRequest);
+                Ok("Tutorials will run again".to_string())
+            }
+            Some(key) => {
+                triggers.send(TutorialTriggerEvent::new(key));
+                Ok(format!("Fired '{}'", key))
+            }
+            None => Err("expected a trigger key or 'reset'".to_string()),
+        };
+        output.send(match result {
+            Ok(text) => event.reply(text),
+            Err(text) => event.error(format!("{}: {}", event.name, text)),
+        });
+    }
+}
+
+/// Queues first-time hints and starts sequences for this frame's triggers.
+fn start_tutorials(
+    time: Res<Time>,
+    content: Res<TutorialContent>,
+    settings: Option<ResMut<UserSettings>>,
+    mut triggers: EventReader<TutorialTriggerEvent>,
+    mut queue: ResMut<HintQueue>,
+    mut sequences: ResMut<RunningSequences>,
+) {
+    let Some(mut settings) = settings else {
+        triggers.clear();
+        return;
+    };
+    for trigger in triggers.read() {
+        // Checked before inserting so triggers that change nothing don't save the settings
+        if settings.tutorials.show_hints {
+            for hint in content.hints.values().filter(|h| h.on.as_deref() == Some(trigger.key.as_str())) {
+                if !settings.tutorials.seen.contains(&hint.id) {
+                    settings.tutorials.seen.insert(hint.id.clone());
+                    queue.pending.push_back(hint.id.clone());
+                }
+            }
+        }
+        for sequence in content.sequences.iter().filter(|s| s.on == trigger.key) {
+            let key = sequence_key(&sequence.id);
+            if !settings.tutorials.seen.contains(&key) {
+                settings.tutorials.seen.insert(key);
+                info!("Tutorial sequence {} started", sequence.id);
+                sequences.running.push(RunningSequence {
+                    id: sequence.id.clone(),
+                    step: 0,
+                    step_started: time.elapsed_secs(),
+                });
+            }
+        }
+    }
+}
+
+fn run_sequences(
+    time: Res<Time>,
+    content: Res<TutorialContent>,
+    settings: Option<Res<UserSettings>>,
+    players: Query<Entity, With<Player>>,
+    mut triggers: EventReader<TutorialTriggerEvent>,
+    (mut queue, mut sequences): (ResMut<HintQueue>, ResMut<RunningSequences>),
+    (mut cutscenes, mut quests): (EventWriter<CutsceneRequestEvent>, EventWriter<TutorialQuestRequest>),
+) {
+    let fired: Vec<&str> = triggers.read().map(|t| t.key.as_str()).collect();
+    let Ok(player) = players.get_single() else {
+        return;
+    };
+    let now = time.elapsed_secs();
+    let show_hints = settings.is_none_or(|s| s.tutorials.show_hints);
+
+    sequences.running.retain_mut(|running| {
+        let Some(sequence) = content.sequences.iter().find(|s| s.id == running.id) else {
+            return false;
+        };
+        while let Some(step) = sequence.steps.get(running.step) {
+            match step {
+                TutorialStep::Cutscene { cutscene } => {
+                    cutscenes.send(CutsceneRequestEvent {
+                        cutscene: cutscene.clone(),
+                        instigator: player,
+                    });
+                }
+                TutorialStep::Hint { hint } => {
+                    if show_hints {
+                        queue.pending.push_back(hint.clone());
+                    }
+                }
+                TutorialStep::Quest { quest } => {
+                    quests.send(TutorialQuestRequest {
+                        player,
+                        quest: quest.clone(),
+                    });
+                }
+                TutorialStep::Wait { on } => {
+                    if !fired.contains(&on.as_str()) {
+                        return true;
+                    }
+                }
+                TutorialStep::Delay { seconds } => {
+                    if now - running.step_started < *seconds {
+                        return true;
+                    }
+                }
+            }
+            running.step += 1;
+            running.step_started = now;
+        }
+        info!("Tutorial sequence {} finished", sequence.id);
+        false
+    });
+}
@@ -0,0 +1,82 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts, EguiPlugin};
+
+use super::{HintQueue, TutorialContent};
+use crate::settings::UserSettings;
+
+/// The hint on screen and when it went up.
+#[derive(Resource, Debug, Default)]
+pub struct ShownHint {
+    pub hint: Option<String>,
+    pub shown_at: f32,
+}
+
+/// Shows queued tutorial hints one at a time near the top of the screen.
+pub struct TutorialUiPlugin;
+
+impl Plugin for TutorialUiPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugins(EguiPlugin);
+        }
+        app.init_resource::<ShownHint>()
+            .add_systems(Update, (next_hint, hint_popup_ui).chain());
+    }
+}
+
+fn next_hint(
+    time: Res<Time>,
+    content: Res<TutorialContent>,
+    mut queue: ResMut<HintQueue>,
+    mut shown: ResMut<ShownHint>,
+) {
+    let now = time.elapsed_secs();
+    if let Some(hint) = shown.hint.as_ref().and_then(|id| content.hints.get(id)) {
+        if now - shown.shown_at < hint.seconds {
+            return;
+        }
+    }
+    let next = std::iter::from_fn(|| queue.pending.pop_front()).find(|id| content.hints.contains_key(id));
+    if next.is_some() || shown.hint.is_some() {
+        *shown = ShownHint { hint: next, shown_at: now };
+    }
+}
+
+fn hint_popup_ui(
+    mut contexts: EguiContexts,
+    content: Res<TutorialContent>,
+    settings: Option<ResMut<UserSettings>>,
+    mut queue: ResMut<HintQueue>,
+    mut shown: ResMut<ShownHint>,
+) {
+    let Some(hint) = shown.hint.as_ref().and_then(|id| content.hints.get(id)) else {
+        return;
+    };
+    let ctx = contexts.ctx_mut().clone();
+    let (mut dismiss, mut turn_off) = (false, false);
+
+    egui::Window::new(&hint.title)
+        .id(egui::Id::new("tutorial_hint"))
+        .anchor(egui::Align2::CENTER_TOP, [0.0, 80.0])
+        .collapsible(false)
+        .resizable(false)
+        .show(&ctx, |ui| {
+            ui.set_max_width(360.0);
+            ui.label(&hint.text);
+            ui.add_space(6.0);
+            ui.horizontal(|ui| {
+                dismiss = ui.button("Got it").clicked();
+                turn_off = settings.is_some() && ui.small_button("Turn off tips").clicked();
+            });
+        });
+
+    if turn_off {
+        if let Some(mut settings) = settings {
+            settings.tutorials.show_hints = false;
+        }
+        queue.pending.clear();
+    }
+    if dismiss || turn_off {
+        shown.hint = None;
+    }
+}
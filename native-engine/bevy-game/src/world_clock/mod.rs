@@ -0,0 +1,417 @@
+//! The world clock: the server's time, as every client agrees on it.
+//!
+//! [`WorldClock`] is this machine's clock plus an offset measured against the
+//! server. Every `sync_interval_seconds` (every `burst_interval_seconds`
+//! until the first few samples are in) a [`TimeSyncRequest`] goes into
+//! [`TimeSyncOutbox`] for the match socket to send with
+//! [`TIME_SYNC_OP_CODE`]; the server answers with a [`TimeSyncReply`]
+//! carrying its own time. Each round trip gives an offset estimate, and the
+//! one with the shortest round trip of the last few wins, since it was least
+//! delayed one way or the other. Small corrections are slewed in a little at
+//! a time so the clock never jumps or runs backwards; the first sync, and
+//! any error over `snap_seconds`, snaps.
+//!
+//! Everything that has to line up across clients reads the world clock
+//! instead of the system clock: the hour of the day/night cycle (derived
+//! from server time, so all clients see the same sky), the scheduled world
+//! events in [`schedule`], daily and weekly resets, instance lockouts and
+//! territory vulnerability windows. Without a server the offset stays 0
+//! and the world clock is the system clock.
+
+pub mod schedule;
+
+pub use schedule::*;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+use crate::console::{ConsoleAppExt, ConsoleCommand, ConsoleCommandEvent, ConsoleOutputEvent, SetTimeOfDayRequest};
+use crate::day_night::{self, DayNightClock};
+use crate::dungeons::{next_weekly_reset, time_until};
+use crate::territory::Weekday;
+
+/// Match data op code for [`TimeSyncRequest`] and [`TimeSyncReply`].
+pub const TIME_SYNC_OP_CODE: i64 = 22;
+
+const DAY_SECONDS: u64 = 86_400;
+
+/// Asks the server for its time.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TimeSyncRequest {
+    /// Local unix seconds when the request was made; the server echoes it.
+    pub client_sent: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TimeSyncReply {
+    pub client_sent: f64,
+    /// Server unix seconds when it answered.
+    pub server_time: f64,
+}
+
+/// A reply came in; the network layer stamps it with the local time it
+/// arrived.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct TimeSyncReceived {
+    pub reply: TimeSyncReply,
+    pub received_at: f64,
+}
+
+/// Requests for the match socket to send. Only the newest is kept, so
+/// requests made while offline don't pile up.
+#[derive(Resource, Debug, Default)]
+pub struct TimeSyncOutbox {
+    pending: Vec<TimeSyncRequest>,
+}
+
+impl TimeSyncOutbox {
+    pub fn drain(&mut self) -> Vec<TimeSyncRequest> {
+        std::mem::take(&mut self.pending)
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct WorldClockConfig {
+    pub sync_interval_seconds: f32,
+    /// Interval until `burst_samples` replies are in, so a new session
+    /// settles quickly.
+    pub burst_interval_seconds: f32,
+    pub burst_samples: usize,
+    /// Recent samples the best estimate is picked from.
+    pub samples_kept: usize,
+    /// Round trips slower than this are too noisy to use.
+    pub max_round_trip_seconds: f64,
+    /// Errors larger than this are fixed at once instead of slewed.
+    pub snap_seconds: f64,
+    /// Seconds of correction per second while slewing; under 1 so time
+    /// always moves forward.
+    pub slew_rate: f64,
+    /// Game hour at the start of each server day's cycle; shifts the whole
+    /// day/night cycle.
+    pub hour_offset: f32,
+    /// Set the day/night clock from server time (unless the `timescale` or
+    /// `timepause` commands have taken it over).
+    pub drive_day_night: bool,
+    /// Daily reset, UTC.
+    pub daily_reset_hour: u32,
+    /// Weekly reset, UTC.
+    pub weekly_reset_day: Weekday,
+    pub weekly_reset_hour: u32,
+}
+
+impl Default for WorldClockConfig {
+    fn default() -> Self {
+        Self {
+            sync_interval_seconds: 30.0,
+            burst_interval_seconds: 1.0,
+            burst_samples: 5,
+            samples_kept: 8,
+            max_round_trip_seconds: 2.0,
+            snap_seconds: 1.0,
+            slew_rate: 0.05,
+            hour_offset: 0.0,
+            drive_day_night: true,
+            daily_reset_hour: 10,
+            weekly_reset_day: Weekday::Tuesday,
+            weekly_reset_hour: 15,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct SyncSample {
+    round_trip: f64,
+    offset: f64,
+}
+
+#[derive(Resource, Debug, Clone, Default)]
+pub struct WorldClock {
+    /// Server time minus local time, as applied now.
+    offset: f64,
+    /// The offset being slewed towards.
+    target_offset: f64,
+    samples: VecDeque<SyncSample>,
+    synced: bool,
+    since_request: f32,
+    /// Hours the `time` command moved the day/night cycle from server time.
+    hour_shift: f32,
+}
+
+impl WorldClock {
+    /// Server unix seconds.
+    pub fn now(&self) -> f64 {
+        local_unix_seconds() + self.offset
+    }
+
+    /// Whole server unix seconds.
+    pub fn now_secs(&self) -> u64 {
+        self.now().max(0.0) as u64
+    }
+
+    /// Whether a reply from the server has come in yet.
+    pub fn is_synced(&self) -> bool {
+        self.synced
+    }
+
+    /// Seconds added to the local clock to get server time.
+    pub fn offset(&self) -> f64 {
+        self.offset
+    }
+
+    /// Round trip of the sample the offset comes from.
+    pub fn round_trip(&self) -> Option<f64> {
+        self.best().map(|s| s.round_trip)
+    }
+
+    fn best(&self) -> Option<SyncSample> {
+        self.samples.iter().copied().min_by(|a, b| a.round_trip.total_cmp(&b.round_trip))
+    }
+
+    /// Game hour (0..24) for a cycle of `day_length_minutes` real minutes.
+    pub fn hour_of_day(&self, day_length_minutes: f32, config: &WorldClockConfig) -> f32 {
+        let day_seconds = (day_length_minutes.max(0.1) as f64) * 60.0;
+        let into_day = (self.now() / day_seconds).fract() as f32;
+        (into_day * 24.0 + config.hour_offset + self.hour_shift).rem_euclid(24.0)
+    }
+
+    /// Takes in one round trip; returns whether the clock snapped.
+    pub fn add_sample(&mut self, reply: TimeSyncReply, received_at: f64, config: &WorldClockConfig) -> bool {
+        let round_trip = received_at - reply.client_sent;
+        if !(0.0..=config.max_round_trip_seconds).contains(&round_trip) {
+            return false;
+        }
+        // The server answered about halfway through the round trip
+        let offset = reply.server_time - (reply.client_sent + round_trip / 2.0);
+        self.samples.push_back(SyncSample { round_trip, offset });
+        while self.samples.len() > config.samples_kept.max(1) {
+            self.samples.pop_front();
+        }
+        let Some(best) = self.best() else {
+            return false;
+        };
+        self.target_offset = best.offset;
+        let snap = !self.synced || (self.target_offset - self.offset).abs() > config.snap_seconds;
+        if snap {
+            self.offset = self.target_offset;
+        }
+        self.synced = true;
+        snap
+    }
+
+    /// Moves the applied offset towards the target by at most what
+    /// `seconds` of slewing allows.
+    pub fn slew(&mut self, seconds: f64, config: &WorldClockConfig) {
+        let step = config.slew_rate.clamp(0.0, 0.9) * seconds;
+        self.offset += (self.target_offset - self.offset).clamp(-step, step);
+    }
+}
+
+fn local_unix_seconds() -> f64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs_f64())
+        .unwrap_or(0.0)
+}
+
+/// The first time after `after` that is `phase` seconds into a cycle of
+/// `period` seconds counted from the unix epoch.
+pub fn next_occurrence(period: u64, phase: u64, after: u64) -> u64 {
+    let period = period.max(1);
+    let since = (after + period - phase % period) % period;
+    after - since + period
+}
+
+/// Unix seconds of the next daily reset after `now`.
+pub fn next_daily_reset(config: &WorldClockConfig, now: u64) -> u64 {
+    next_occurrence(DAY_SECONDS, config.daily_reset_hour as u64 * 3600, now)
+}
+
+/// Unix seconds of the next weekly reset after `now`.
+pub fn next_weekly_world_reset(config: &WorldClockConfig, now: u64) -> u64 {
+    next_weekly_reset(config.weekly_reset_day, config.weekly_reset_hour, now)
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct DailyResetEvent {
+    /// Server unix seconds of the boundary.
+    pub at: u64,
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct WeeklyResetEvent {
+    pub at: u64,
+}
+
+pub struct WorldClockPlugin;
+
+impl Plugin for WorldClockPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WorldClockConfig>()
+            .init_resource::<WorldClock>()
+            .init_resource::<TimeSyncOutbox>()
+            .init_resource::<DayNightClock>()
+            .add_event::<TimeSyncReceived>()
+            .add_event::<DailyResetEvent>()
+            .add_event::<WeeklyResetEvent>()
+            .add_event::<SetTimeOfDayRequest>()
+            .add_plugins(schedule::WorldSchedulePlugin)
+            .add_console_command(ConsoleCommand::new(
+                "clock",
+                "Shows server time, sync state and the next resets and world events",
+            ))
+            .add_systems(
+                Update,
+                (
+                    (request_time_sync, apply_time_sync, slew_clock, detect_resets).chain(),
+                    drive_day_night.after(day_night::advance_clock).before(day_night::apply_lighting),
+                    clock_command,
+                ),
+            );
+    }
+}
+
+fn request_time_sync(
+    time: Res<Time>,
+    config: Res<WorldClockConfig>,
+    mut clock: ResMut<WorldClock>,
+    mut outbox: ResMut<TimeSyncOutbox>,
+) {
+    clock.since_request += time.delta_secs();
+    let interval = if clock.samples.len() < config.burst_samples {
+        config.burst_interval_seconds
+    } else {
+        config.sync_interval_seconds
+    };
+    if clock.since_request < interval {
+        return;
+    }
+    clock.since_request = 0.0;
+    outbox.pending.clear();
+    outbox.pending.push(TimeSyncRequest {
+        client_sent: local_unix_seconds(),
+    });
+}
+
+fn apply_time_sync(
+    config: Res<WorldClockConfig>,
+    mut clock: ResMut<WorldClock>,
+    mut replies: EventReader<TimeSyncReceived>,
+) {
+    for event in replies.read() {
+        let before = clock.offset;
+        if clock.add_sample(event.reply, event.received_at, &config) {
+            info!(
+                "World clock synced: offset {:+.3}s (was {:+.3}s), round trip {:.0}ms",
+                clock.offset,
+                before,
+                (event.received_at - event.reply.client_sent) * 1000.0
+            );
+        }
+    }
+}
+
+fn slew_clock(time: Res<Time>, config: Res<WorldClockConfig>, mut clock: ResMut<WorldClock>) {
+    clock.slew(time.delta_secs_f64(), &config);
+}
+
+/// Fires reset events as server time crosses the boundaries. Boundaries
+/// passed while the game wasn't running don't fire, and after a clock
+/// correction backwards nothing fires until time catches up again.
+fn detect_resets(
+    config: Res<WorldClockConfig>,
+    clock: Res<WorldClock>,
+    mut last_checked: Local<Option<u64>>,
+    mut daily: EventWriter<DailyResetEvent>,
+    mut weekly: EventWriter<WeeklyResetEvent>,
+) {
+    let now = clock.now_secs();
+    let Some(last) = *last_checked else {
+        *last_checked = Some(now);
+        return;
+    };
+    if now <= last {
+        return;
+    }
+    *last_checked = Some(now);
+    let next_daily = next_daily_reset(&config, last);
+    if next_daily <= now {
+        info!("Daily reset");
+        daily.send(DailyResetEvent { at: next_daily });
+    }
+    let next_weekly = next_weekly_world_reset(&config, last);
+    if next_weekly <= now {
+        info!("Weekly reset");
+        weekly.send(WeeklyResetEvent { at: next_weekly });
+    }
+}
+
+fn drive_day_night(
+    config: Res<WorldClockConfig>,
+    mut clock: ResMut<WorldClock>,
+    mut day_night: ResMut<DayNightClock>,
+    mut time_requests: EventReader<SetTimeOfDayRequest>,
+) {
+    // `time` moves the cycle relative to server time rather than fighting it
+    for request in time_requests.read() {
+        clock.hour_shift = 0.0;
+        let base = clock.hour_of_day(day_night.day_length_minutes, &config);
+        clock.hour_shift = (request.hour - base).rem_euclid(24.0);
+    }
+    let taken_over = day_night.paused || (day_night.speed - 1.0).abs() > f32::EPSILON;
+    if !config.drive_day_night || taken_over {
+        return;
+    }
+    let hour = clock.hour_of_day(day_night.day_length_minutes, &config);
+    if (day_night.hour - hour).abs() > f32::EPSILON {
+        day_night.hour = hour;
+    }
+}
+
+fn clock_command(
+    config: Res<WorldClockConfig>,
+    clock: Res<WorldClock>,
+    day_night: Res<DayNightClock>,
+    schedule: Res<WorldScheduleContent>,
+    mut events: EventReader<ConsoleCommandEvent>,
+    mut output: EventWriter<ConsoleOutputEvent>,
+) {
+    for event in events.read() {
+        if event.name != "clock" {
+            continue;
+        }
+        let now = clock.now_secs();
+        let mut lines = vec![format!(
+            "Server time {:02}:{:02}:{:02} UTC, game hour {:.2}",
+            now % DAY_SECONDS / 3600,
+            now % 3600 / 60,
+            now % 60,
+            day_night.hour
+        )];
+        lines.push(match clock.round_trip() {
+            Some(round_trip) if clock.is_synced() => format!(
+                "Synced: offset {:+.3}s, best round trip {:.0}ms",
+                clock.offset(),
+                round_trip * 1000.0
+            ),
+            _ => "Not synced; using this machine's clock".to_string(),
+        });
+        lines.push(format!(
+            "Daily reset in {}, weekly reset in {}",
+            time_until(next_daily_reset(&config, now), now),
+            time_until(next_weekly_world_reset(&config, now), now)
+        ));
+        for def in &schedule.events {
+            lines.push(format!("  {} in {}", def.id, time_until(def.next_after(now), now)));
+        }
+        output.send(event.reply(lines.join("\n")));
+    }
+}
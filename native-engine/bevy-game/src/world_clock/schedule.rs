@@ -0,0 +1,164 @@
+//! World events on a fixed server-time schedule, from
+//! `content/world_schedule/*.toml`:
+//!
+//! ```toml
+//! [[event]]
+//! id = "elder_drake"
+//! every_minutes = 240
+//! # Minutes into each cycle, counted from midnight UTC
+//! offset_minutes = 30
+//! spawn = { monster = "elder_drake", position = [412.0, 38.0, -96.0], level = 45 }
+//! ```
+//!
+//! Occurrences are fixed points in server time, so every client fires the
+//! same event at the same moment no matter when it started. A
+//! [`WorldScheduleEvent`] goes out for each; events with a `spawn` also send
+//! the spawn requests.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use super::{next_occurrence, WorldClock};
+use crate::console::ConsoleSpawnRequest;
+use crate::content_packs::ContentPacks;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledSpawnDef {
+    pub monster: String,
+    pub position: Vec3,
+    #[serde(default)]
+    pub level: Option<u32>,
+    #[serde(default = "default_spawn_count")]
+    pub count: u32,
+}
+
+fn default_spawn_count() -> u32 {
+    1
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledEventDef {
+    pub id: String,
+    pub every_minutes: u32,
+    #[serde(default)]
+    pub offset_minutes: u32,
+    #[serde(default)]
+    pub spawn: Option<ScheduledSpawnDef>,
+}
+
+impl ScheduledEventDef {
+    /// Server unix seconds of the first occurrence after `after`.
+    pub fn next_after(&self, after: u64) -> u64 {
+        next_occurrence(self.every_minutes.max(1) as u64 * 60, self.offset_minutes as u64 * 60, after)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WorldScheduleFile {
+    #[serde(default, rename = "event")]
+    pub events: Vec<ScheduledEventDef>,
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct WorldScheduleConfig {
+    pub content_directory: PathBuf,
+}
+
+impl Default for WorldScheduleConfig {
+    fn default() -> Self {
+        Self {
+            content_directory: PathBuf::from("content").join("world_schedule"),
+        }
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct WorldScheduleContent {
+    pub events: Vec<ScheduledEventDef>,
+}
+
+/// A scheduled event's time came.
+#[derive(Event, Debug, Clone)]
+pub struct WorldScheduleEvent {
+    pub id: String,
+    /// Server unix seconds it was scheduled for.
+    pub at: u64,
+}
+
+pub struct WorldSchedulePlugin;
+
+impl Plugin for WorldSchedulePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WorldScheduleConfig>()
+            .init_resource::<WorldScheduleContent>()
+            .add_event::<WorldScheduleEvent>()
+            .add_event::<ConsoleSpawnRequest>()
+            .add_systems(Startup, load_world_schedule)
+            .add_systems(Update, run_world_schedule);
+    }
+}
+
+fn load_world_schedule(
+    config: Res<WorldScheduleConfig>,
+    packs: Res<ContentPacks>,
+    mut content: ResMut<WorldScheduleContent>,
+) {
+    for path in packs.files(&config.content_directory, "toml") {
+        let parsed = std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| toml::from_str::<WorldScheduleFile>(&text).map_err(|e| e.to_string()));
+
+        match parsed {
+            Ok(file) => content.events.extend(file.events),
+            Err(e) => warn!("Invalid world schedule file {:?}: {}", path, e),
+        }
+    }
+    for def in content.events.iter().filter(|d| d.every_minutes == 0) {
+        warn!("Scheduled event '{}' has no interval; it runs every minute", def.id);
+    }
+
+    info!("World schedule: {} events loaded", content.events.len());
+}
+
+/// Fires events whose time passed since the last frame. Occurrences missed
+/// while the game wasn't running don't fire, and after a long stall only
+/// the latest missed one does.
+fn run_world_schedule(
+    clock: Res<WorldClock>,
+    content: Res<WorldScheduleContent>,
+    mut last_checked: Local<Option<u64>>,
+    mut fired: EventWriter<WorldScheduleEvent>,
+    mut spawns: EventWriter<ConsoleSpawnRequest>,
+) {
+    let now = clock.now_secs();
+    let Some(last) = *last_checked else {
+        *last_checked = Some(now);
+        return;
+    };
+    // After a correction backwards, wait for time to catch up again
+    if now <= last {
+        return;
+    }
+    *last_checked = Some(now);
+
+    for def in &content.events {
+        let period = def.every_minutes.max(1) as u64 * 60;
+        let next = def.next_after(last);
+        if next > now {
+            continue;
+        }
+        let at = next + (now - next) / period * period;
+        info!("Scheduled event {}", def.id);
+        fired.send(WorldScheduleEvent { id: def.id.clone(), at });
+        if let Some(spawn) = &def.spawn {
+            for i in 0..spawn.count.max(1) {
+                spawns.send(ConsoleSpawnRequest {
+                    monster: spawn.monster.clone(),
+                    level: spawn.level,
+                    position: spawn.position + Vec3::X * i as f32 * 2.0,
+                });
+            }
+        }
+    }
+}
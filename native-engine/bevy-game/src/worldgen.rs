@@ -0,0 +1,237 @@
+//! World seed. Terrain, landmarks, forests and spawn placement all draw from
+//! one `u64`, split into independent streams so adding a landmark doesn't
+//! move every tree. The seed comes from `--seed` (a number or any text),
+//! then `MMO_WORLD_SEED`, then [`DEFAULT_WORLD_SEED`]. Saves store it under
+//! [`SAVE_SEED_KEY`], and the network handshake carries it so every client
+//! builds the server's world.
+
+use bevy::prelude::*;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::console::{ConsoleAppExt, ConsoleCommand, ConsoleCommandEvent, ConsoleOutputEvent};
+
+/// The seed the shipped world was built with.
+pub const DEFAULT_WORLD_SEED: u64 = 0x4D4D_4F52_5047_0001;
+
+/// Save files keep the world seed under this key.
+pub const SAVE_SEED_KEY: &str = "world_seed";
+
+/// Independent random streams. The discriminants are salts: changing one
+/// changes that part of every seeded world, so add variants, don't renumber.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WorldStream {
+    Terrain = 1,
+    Landmarks = 2,
+    Forests = 3,
+    Spawns = 4,
+    Water = 5,
+}
+
+/// SplitMix64 finalizer, as in `SimulationRng::reseed_for_tick`.
+fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// FNV-1a, so text seeds hash the same in every build (unlike `DefaultHasher`).
+fn hash_text(text: &str) -> u64 {
+    text.bytes().fold(0xCBF2_9CE4_8422_2325, |h, b| (h ^ b as u64).wrapping_mul(0x0100_0000_01B3))
+}
+
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorldSeed {
+    pub seed: u64,
+}
+
+impl Default for WorldSeed {
+    fn default() -> Self {
+        Self {
+            seed: DEFAULT_WORLD_SEED,
+        }
+    }
+}
+
+impl WorldSeed {
+    pub fn new(seed: u64) -> Self {
+        Self { seed }
+    }
+
+    /// Numbers are used as-is; anything else is hashed, so `--seed frostvale`
+    /// works as well as `--seed 1234`.
+    pub fn parse(text: &str) -> Self {
+        let text = text.trim();
+        let seed = text
+            .parse::<u64>()
+            .ok()
+            .or_else(|| text.strip_prefix("0x").and_then(|hex| u64::from_str_radix(hex, 16).ok()))
+            .unwrap_or_else(|| hash_text(text));
+        Self { seed }
+    }
+
+    pub fn from_env() -> Self {
+        let args: Vec<String> = std::env::args().collect();
+        args.iter()
+            .position(|a| a == "--seed")
+            .and_then(|i| args.get(i + 1).cloned())
+            .or_else(|| std::env::var("MMO_WORLD_SEED").ok().filter(|v| !v.is_empty()))
+            .map_or_else(Self::default, |text| Self::parse(&text))
+    }
+
+    pub fn stream_seed(&self, stream: WorldStream) -> u64 {
+        mix(self.seed ^ mix(stream as u64))
+    }
+
+    /// Seed for `noise` generators, which take a `u32`.
+    pub fn noise_seed(&self, stream: WorldStream) -> u32 {
+        let s = self.stream_seed(stream);
+        (s ^ (s >> 32)) as u32
+    }
+
+    pub fn rng(&self, stream: WorldStream) -> StdRng {
+        StdRng::seed_from_u64(self.stream_seed(stream))
+    }
+
+    /// RNG for one cell or chunk. Chunks stream in any order, so each gets
+    /// its own generator instead of sharing one.
+    pub fn cell_rng(&self, stream: WorldStream, cell: [i32; 2]) -> StdRng {
+        StdRng::seed_from_u64(self.cell_seed(stream, cell))
+    }
+
+    fn cell_seed(&self, stream: WorldStream, cell: [i32; 2]) -> u64 {
+        let packed = ((cell[0] as u32 as u64) << 32) | cell[1] as u32 as u64;
+        mix(self.stream_seed(stream) ^ mix(packed))
+    }
+
+    /// A value in `0..=1` for `(cell, index)`, for scatter that must come out
+    /// the same without keeping an RNG around.
+    pub fn cell_value(&self, stream: WorldStream, cell: [i32; 2], index: u32) -> f32 {
+        let h = mix(self.cell_seed(stream, cell) ^ index as u64);
+        (h >> 40) as f32 / ((1u64 << 24) - 1) as f32
+    }
+
+    pub fn write_to_save(&self, save: &mut Value) {
+        if let Some(object) = save.as_object_mut() {
+            // A string: JSON numbers lose precision past 2^53 in most readers
+            object.insert(SAVE_SEED_KEY.to_string(), Value::from(self.seed.to_string()));
+        }
+    }
+
+    /// The seed a save was written with; `None` for saves from before seeds
+    /// were recorded, which were all on the default world.
+    pub fn from_save(save: &Value) -> Option<Self> {
+        match save.get(SAVE_SEED_KEY)? {
+            Value::String(text) => text.parse().ok().map(Self::new),
+            value => value.as_u64().map(Self::new),
+        }
+    }
+}
+
+/// What the server tells a joining client about its world. The client
+/// refuses to play on a world it can't reproduce.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorldHandshake {
+    #[serde(with = "seed_string")]
+    pub seed: u64,
+    pub content_version: u32,
+}
+
+mod seed_string {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(seed: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&seed.to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl WorldHandshake {
+    pub fn new(seed: &WorldSeed, content_version: u32) -> Self {
+        Self {
+            seed: seed.seed,
+            content_version,
+        }
+    }
+
+    /// Content must match; the seed is adopted.
+    pub fn check(&self, local_content_version: u32) -> Result<WorldSeed, String> {
+        if self.content_version != local_content_version {
+            return Err(format!(
+                "server content version {} doesn't match installed {}",
+                self.content_version, local_content_version
+            ));
+        }
+        Ok(WorldSeed::new(self.seed))
+    }
+}
+
+/// Sent by the network layer when the server's handshake arrives.
+#[derive(Event, Debug, Clone)]
+pub struct WorldHandshakeEvent(pub WorldHandshake);
+
+/// The world seed changed after startup (server handshake, loaded save).
+/// Generators rebuild everything they made from the old seed.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct WorldSeedChangedEvent {
+    pub previous: WorldSeed,
+    pub seed: WorldSeed,
+}
+
+pub struct WorldSeedPlugin;
+
+impl Plugin for WorldSeedPlugin {
+    fn build(&self, app: &mut App) {
+        let seed = WorldSeed::from_env();
+        info!("World seed: {}", seed.seed);
+        app.insert_resource(seed)
+            .add_event::<WorldHandshakeEvent>()
+            .add_event::<WorldSeedChangedEvent>()
+            .add_console_command(ConsoleCommand::new("seed", "Shows the world seed"))
+            .add_systems(PreUpdate, apply_world_handshake)
+            .add_systems(Update, (report_seed_change, run_seed_command));
+    }
+}
+
+fn apply_world_handshake(
+    mut handshakes: EventReader<WorldHandshakeEvent>,
+    mut seed: ResMut<WorldSeed>,
+    database: Option<Res<crate::database::GameDatabase>>,
+    mut changed: EventWriter<WorldSeedChangedEvent>,
+) {
+    let content_version = database.map_or(1, |db| db.migrations.current_version());
+    for WorldHandshakeEvent(handshake) in handshakes.read() {
+        match handshake.check(content_version) {
+            Ok(server_seed) if server_seed != *seed => {
+                changed.send(WorldSeedChangedEvent {
+                    previous: *seed,
+                    seed: server_seed,
+                });
+                *seed = server_seed;
+            }
+            Ok(_) => {}
+            Err(e) => error!("World handshake rejected: {}", e),
+        }
+    }
+}
+
+fn report_seed_change(mut events: EventReader<WorldSeedChangedEvent>) {
+    for event in events.read() {
+        info!("World seed changed from {} to {}", event.previous.seed, event.seed.seed);
+    }
+}
+
+fn run_seed_command(
+    seed: Res<WorldSeed>,
+    mut events: EventReader<ConsoleCommandEvent>,
+    mut output: EventWriter<ConsoleOutputEvent>,
+) {
+    for event in events.read().filter(|e| e.name == "seed") {
+        output.send(event.reply(format!("World seed {}", seed.seed)));
+    }
+}
@@ -0,0 +1,280 @@
+//! GPU and driver compatibility check, run before the game starts.
+//!
+//! The GPUs come from `vulkaninfo --summary`, which enumerates them the way
+//! the game's renderer will. They're compared against the server's list of
+//! known-bad GPU and driver combinations (`/sync/gpu-compat`, cached in
+//! `gpu_compat.json` for offline runs). A known issue is always reported; if
+//! it names a safer renderer or a quality ceiling and the game has never been
+//! started, those go straight into `game_settings.toml`. After that the
+//! player's own settings are left alone and the launcher only says what to
+//! change.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::process::Command;
+
+use crate::config::Config;
+use crate::game_settings::{GameSettings, QualityPreset, RendererChoice};
+use crate::http;
+use crate::logging;
+
+const CACHE_FILE: &str = "gpu_compat.json";
+
+#[derive(Debug, Clone)]
+pub struct GpuInfo {
+    pub name: String,
+    pub vendor_id: u32,
+    pub device_id: u32,
+    pub device_type: String,
+    /// The driver version as the vendor writes it (`driverInfo`), or
+    /// Vulkan's encoded one if the driver doesn't say.
+    pub driver: String,
+    pub api_version: String,
+}
+
+impl GpuInfo {
+    fn is_discrete(&self) -> bool {
+        self.device_type.contains("DISCRETE")
+    }
+}
+
+/// An entry in the known-issues database. Every field that's set has to
+/// match; driver versions compare number by number.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct KnownIssue {
+    id: String,
+    /// PCI vendor ID, e.g. `0x10de`.
+    #[serde(default)]
+    vendor_id: Option<String>,
+    /// PCI device IDs; empty matches every device from the vendor.
+    #[serde(default)]
+    device_ids: Vec<String>,
+    /// Case-insensitive part of the device name.
+    #[serde(default)]
+    name_contains: Option<String>,
+    /// First affected driver version.
+    #[serde(default)]
+    driver_from: Option<String>,
+    /// First driver version with the fix.
+    #[serde(default)]
+    driver_before: Option<String>,
+    /// Renderer to use instead.
+    #[serde(default)]
+    renderer: Option<RendererChoice>,
+    /// Highest quality preset that works.
+    #[serde(default)]
+    max_quality: Option<QualityPreset>,
+    message: String,
+}
+
+impl KnownIssue {
+    fn matches(&self, gpu: &GpuInfo) -> bool {
+        if self.vendor_id.as_deref().is_some_and(|id| parse_id(id) != Some(gpu.vendor_id)) {
+            return false;
+        }
+        if !self.device_ids.is_empty() && !self.device_ids.iter().any(|id| parse_id(id) == Some(gpu.device_id)) {
+            return false;
+        }
+        if let Some(part) = &self.name_contains {
+            if !gpu.name.to_lowercase().contains(&part.to_lowercase()) {
+                return false;
+            }
+        }
+        let driver = parse_version(&gpu.driver);
+        if self.driver_from.as_deref().is_some_and(|from| driver < parse_version(from)) {
+            return false;
+        }
+        if self.driver_before.as_deref().is_some_and(|before| driver >= parse_version(before)) {
+            return false;
+        }
+        true
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CompatDatabase {
+    issues: Vec<KnownIssue>,
+}
+
+pub struct CompatChecker {
+    config: Config,
+    client: reqwest::Client,
+}
+
+impl CompatChecker {
+    pub fn new(config: Config) -> Result<Self> {
+        let client = http::client_builder(&config)?
+            .timeout(std::time::Duration::from_secs(30))
+            .build()?;
+        Ok(Self { config, client })
+    }
+
+    /// Checks the GPU the game will use. With `apply`, a first launch gets
+    /// the settings the database recommends. Never fails the launch: without
+    /// `vulkaninfo` or the database there's nothing to compare.
+    pub async fn run(&self, apply: bool) -> Result<()> {
+        let gpus = match self.detect_gpus() {
+            Ok(gpus) => gpus,
+            Err(e) => {
+                logging::warn(&format!("Could not list GPUs: {:#} - skipping the compatibility check", e));
+                return Ok(());
+            }
+        };
+        for gpu in &gpus {
+            logging::info(&format!(
+                "GPU: {} ({:04x}:{:04x}), driver {}, Vulkan {}",
+                gpu.name, gpu.vendor_id, gpu.device_id, gpu.driver, gpu.api_version
+            ));
+        }
+        // The game asks for the high-performance adapter
+        let Some(gpu) = gpus.iter().find(|gpu| gpu.is_discrete()).or(gpus.first()) else {
+            logging::warn("No Vulkan GPU found - the game will need the wgpu renderer or a driver update");
+            return Ok(());
+        };
+
+        let Some(database) = self.database().await else {
+            logging::warn("GPU compatibility database unavailable - skipping the check");
+            return Ok(());
+        };
+        let issues: Vec<&KnownIssue> = database.issues.iter().filter(|issue| issue.matches(gpu)).collect();
+        if issues.is_empty() {
+            logging::success(&format!("{} has no known issues", gpu.name));
+            return Ok(());
+        }
+
+        let first_launch = !GameSettings::path(&self.config).exists();
+        let mut settings = GameSettings::load(&self.config).unwrap_or_default();
+        let mut changes = Vec::new();
+        for issue in &issues {
+            logging::warn(&format!(
+                "Known issue {} with {} (driver {}): {}",
+                issue.id, gpu.name, gpu.driver, issue.message
+            ));
+            if let Some(renderer) = issue.renderer {
+                if settings.graphics.renderer != renderer {
+                    settings.graphics.renderer = renderer;
+                    changes.push(format!("renderer {}", renderer));
+                }
+            }
+            if let Some(max) = issue.max_quality {
+                if settings.graphics.quality > max {
+                    settings.graphics.quality = max;
+                    changes.push(format!("quality {}", max));
+                }
+            }
+        }
+        if changes.is_empty() {
+            return Ok(());
+        }
+
+        if apply && first_launch {
+            settings.save(&self.config)?;
+            logging::info(&format!("Starting with {} for this GPU", changes.join(", ")));
+        } else {
+            logging::warn(&format!(
+                "Recommended for this GPU: {} (Game settings in the launcher window, or {})",
+                changes.join(", "),
+                GameSettings::path(&self.config).display()
+            ));
+        }
+        Ok(())
+    }
+
+    fn vulkaninfo(&self) -> Option<PathBuf> {
+        if let Ok(path) = which::which("vulkaninfo") {
+            return Some(path);
+        }
+        let sdk_dirs = std::env::var("VULKAN_SDK").map(PathBuf::from).into_iter();
+        sdk_dirs
+            .chain([self.config.vulkan_sdk_dir()])
+            .flat_map(|sdk| [sdk.join("Bin").join("vulkaninfoSDK.exe"), sdk.join("Bin").join("vulkaninfo.exe")])
+            .find(|path| path.exists())
+    }
+
+    pub fn detect_gpus(&self) -> Result<Vec<GpuInfo>> {
+        let vulkaninfo = self
+            .vulkaninfo()
+            .ok_or_else(|| anyhow::anyhow!("vulkaninfo not found (it comes with the Vulkan SDK)"))?;
+        let output = Command::new(&vulkaninfo)
+            .arg("--summary")
+            .output()
+            .with_context(|| format!("Failed to run {}", vulkaninfo.display()))?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "vulkaninfo failed: {}",
+                String::from_utf8_lossy(&output.stderr).lines().last().unwrap_or("no output")
+            );
+        }
+        Ok(parse_summary(&String::from_utf8_lossy(&output.stdout)))
+    }
+
+    /// The server's database, or the last one fetched if it can't be reached.
+    async fn database(&self) -> Option<CompatDatabase> {
+        let cache = self.config.install_dir.join(CACHE_FILE);
+        match self.fetch_database().await {
+            Ok(database) => {
+                if let Ok(text) = serde_json::to_string_pretty(&database) {
+                    let _ = std::fs::write(&cache, text);
+                }
+                Some(database)
+            }
+            Err(e) => {
+                logging::warn(&format!("Could not fetch the GPU compatibility database: {:#}", e));
+                let text = std::fs::read_to_string(&cache).ok()?;
+                logging::info("Using the cached GPU compatibility database");
+                serde_json::from_str(&text).ok()
+            }
+        }
+    }
+
+    async fn fetch_database(&self) -> Result<CompatDatabase> {
+        let url = self.config.endpoint(["sync", "gpu-compat"])?;
+        let response = self.client.get(url).send().await?;
+        if !response.status().is_success() {
+            anyhow::bail!("HTTP {}", response.status());
+        }
+        Ok(response.json().await?)
+    }
+}
+
+/// The `GPUn:` sections of `vulkaninfo --summary`.
+fn parse_summary(text: &str) -> Vec<GpuInfo> {
+    let mut sections: Vec<Vec<(String, String)>> = Vec::new();
+    for line in text.lines().map(str::trim) {
+        if line.starts_with("GPU") && line.ends_with(':') {
+            sections.push(Vec::new());
+        } else if let (Some(fields), Some((key, value))) = (sections.last_mut(), line.split_once('=')) {
+            fields.push((key.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    sections.iter().filter_map(|fields| gpu_from_fields(fields)).collect()
+}
+
+fn gpu_from_fields(fields: &[(String, String)]) -> Option<GpuInfo> {
+    let get = |key: &str| fields.iter().find(|(k, _)| k == key).map(|(_, v)| v.clone());
+    let driver = get("driverInfo").filter(|info| !info.is_empty()).or_else(|| get("driverVersion"))?;
+    Some(GpuInfo {
+        name: get("deviceName")?,
+        vendor_id: get("vendorID").as_deref().and_then(parse_id).unwrap_or(0),
+        device_id: get("deviceID").as_deref().and_then(parse_id).unwrap_or(0),
+        device_type: get("deviceType").unwrap_or_default(),
+        driver,
+        api_version: get("apiVersion").unwrap_or_default(),
+    })
+}
+
+fn parse_id(id: &str) -> Option<u32> {
+    let id = id.trim();
+    let hex = id.strip_prefix("0x").or_else(|| id.strip_prefix("0X")).unwrap_or(id);
+    u32::from_str_radix(hex, 16).ok()
+}
+
+/// `"551.86"` as `[551, 86]`, for comparing.
+pub fn parse_version(version: &str) -> Vec<u64> {
+    version
+        .split(|c: char| !c.is_ascii_digit())
+        .filter(|part| !part.is_empty())
+        .filter_map(|part| part.parse().ok())
+        .collect()
+}
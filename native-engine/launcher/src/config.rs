@@ -1,15 +1,110 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::logging;
 
 pub const LAUNCHER_VERSION: &str = "1.0.0";
 #[allow(dead_code)]
 pub const SOURCE_VERSION: &str = env!("CARGO_PKG_VERSION");
 pub const DEFAULT_SERVER_URL: &str = "https://aaa-mmorpg-engine-danielbodnar2.replit.app";
 
+/// `config_version` this launcher writes. Bump it when a field is renamed or
+/// changes meaning, and add the step that converts older files to
+/// [`MIGRATIONS`]. New fields only need a serde default.
+const CONFIG_VERSION: u32 = 1;
+
+/// `MIGRATIONS[n]` turns a version `n + 1` config into version `n + 2`.
+/// Files from before versioning count as version 1.
+const MIGRATIONS: &[fn(&mut Map<String, Value>)] = &[];
+
+/// Which builds the server hands out. Each channel gets its own engine
+/// directory, so switching doesn't overwrite another channel's install.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Channel {
+    #[default]
+    Stable,
+    Beta,
+    Nightly,
+}
+
+impl Channel {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Channel::Stable => "stable",
+            Channel::Beta => "beta",
+            Channel::Nightly => "nightly",
+        }
+    }
+}
+
+impl fmt::Display for Channel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Channel {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "stable" => Ok(Channel::Stable),
+            "beta" => Ok(Channel::Beta),
+            "nightly" => Ok(Channel::Nightly),
+            _ => anyhow::bail!("Unknown channel '{}' (expected stable, beta or nightly)", s),
+        }
+    }
+}
+
+/// Whether crash reports the game left behind are sent to the server.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CrashReportConsent {
+    #[default]
+    Ask,
+    Always,
+    Never,
+}
+
+impl fmt::Display for CrashReportConsent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            CrashReportConsent::Ask => "ask",
+            CrashReportConsent::Always => "always",
+            CrashReportConsent::Never => "never",
+        })
+    }
+}
+
+/// How to start the game: `--profile <name>` picks one from
+/// `launch_profiles`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LaunchProfile {
+    /// Arguments for the game, before any given after `--`.
+    pub args: Vec<String>,
+    /// Environment variables set for the game, on top of the launcher's.
+    pub env: BTreeMap<String, String>,
+    /// Where the game runs, relative to the engine directory; empty is the
+    /// engine directory itself.
+    pub working_dir: Option<PathBuf>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
+    /// Format of the file, for [`MIGRATIONS`].
+    #[serde(default = "default_config_version")]
+    pub config_version: u32,
     pub server_url: String,
+    /// Release channel to update the launcher and sync the engine from.
+    #[serde(default)]
+    pub channel: Channel,
     pub install_dir: PathBuf,
     pub o3de_version: String,
     pub vulkan_version: String,
@@ -17,6 +112,106 @@ pub struct Config {
     pub force_rebuild: bool,
     pub skip_update: bool,
     pub verbose: bool,
+    /// Build the game with Tracy capture and open the profiler on launch.
+    #[serde(default)]
+    pub enable_tracy: bool,
+    /// Engine files downloaded at the same time.
+    #[serde(default = "default_download_parallelism")]
+    pub download_parallelism: usize,
+    /// Times a failed file download is retried before the sync fails.
+    #[serde(default = "default_download_retries")]
+    pub download_retries: u32,
+    /// Earlier engine versions kept for `--rollback`.
+    #[serde(default = "default_keep_versions")]
+    pub keep_versions: usize,
+    #[serde(default)]
+    pub crash_reports: CrashReportConsent,
+    /// Download speed cap in KB/s, across all downloads at once; 0 is no limit.
+    #[serde(default)]
+    pub max_download_speed_kbps: u64,
+    /// Proxy for every download, `http://`, `https://` or `socks5://`, with
+    /// `user:password@` if it needs a login. Empty follows the `*_PROXY`
+    /// environment variables; `none` never uses a proxy.
+    #[serde(default)]
+    pub proxy: String,
+    /// Comma-separated hosts that bypass `proxy`; empty uses `NO_PROXY`.
+    #[serde(default)]
+    pub no_proxy: String,
+    /// PEM certificates to trust on top of the system's, for proxies that
+    /// re-sign TLS traffic.
+    #[serde(default)]
+    pub ca_bundle: Option<PathBuf>,
+    /// Free space on the install drive the first build needs, in GB; 0
+    /// skips the check.
+    #[serde(default = "default_min_free_disk_gb")]
+    pub min_free_disk_gb: u64,
+    /// Installed memory the build needs, in GB; 0 skips the check.
+    #[serde(default = "default_min_ram_gb")]
+    pub min_ram_gb: u64,
+    /// CPU threads the build needs; 0 skips the check.
+    #[serde(default = "default_min_cpu_cores")]
+    pub min_cpu_cores: usize,
+    /// Stop when an optional dependency fails to install, as `--require-all`.
+    #[serde(default)]
+    pub require_all_dependencies: bool,
+    /// Install O3DE from the server's prebuilt package for this platform
+    /// and toolchain when there is one, instead of building it from source.
+    #[serde(default = "default_o3de_prebuilt")]
+    pub o3de_prebuilt: bool,
+    /// Wait for the game to exit, keeping its output in `game-output.log`
+    /// and reporting how it ended; off, the launcher exits once it's started.
+    #[serde(default = "default_supervise_game")]
+    pub supervise_game: bool,
+    /// Times a game that crashes is started again.
+    #[serde(default)]
+    pub game_restarts: u32,
+    /// Named ways to start the game, for `--profile`.
+    #[serde(default = "default_launch_profiles")]
+    pub launch_profiles: BTreeMap<String, LaunchProfile>,
+}
+
+fn default_config_version() -> u32 {
+    1
+}
+
+fn default_download_parallelism() -> usize {
+    4
+}
+
+fn default_download_retries() -> u32 {
+    3
+}
+
+fn default_keep_versions() -> usize {
+    3
+}
+
+fn default_min_free_disk_gb() -> u64 {
+    60
+}
+
+fn default_min_ram_gb() -> u64 {
+    8
+}
+
+fn default_min_cpu_cores() -> usize {
+    4
+}
+
+fn default_o3de_prebuilt() -> bool {
+    true
+}
+
+fn default_supervise_game() -> bool {
+    true
+}
+
+fn default_launch_profiles() -> BTreeMap<String, LaunchProfile> {
+    let headless = LaunchProfile {
+        args: ["--headless", "--ticks", "300"].map(String::from).to_vec(),
+        ..LaunchProfile::default()
+    };
+    BTreeMap::from([("headless".to_string(), headless)])
 }
 
 impl Default for Config {
@@ -26,7 +221,9 @@ impl Default for Config {
             .join("AAAEngine");
 
         Self {
+            config_version: CONFIG_VERSION,
             server_url: DEFAULT_SERVER_URL.to_string(),
+            channel: Channel::default(),
             install_dir,
             o3de_version: "2510.1".to_string(),  // GitHub tag format (25.10.1 -> 2510.1)
             vulkan_version: "1.3.290.0".to_string(),
@@ -34,40 +231,275 @@ impl Default for Config {
             force_rebuild: false,
             skip_update: false,
             verbose: false,
+            enable_tracy: false,
+            download_parallelism: default_download_parallelism(),
+            download_retries: default_download_retries(),
+            keep_versions: default_keep_versions(),
+            crash_reports: CrashReportConsent::default(),
+            max_download_speed_kbps: 0,
+            proxy: String::new(),
+            no_proxy: String::new(),
+            ca_bundle: None,
+            min_free_disk_gb: default_min_free_disk_gb(),
+            min_ram_gb: default_min_ram_gb(),
+            min_cpu_cores: default_min_cpu_cores(),
+            require_all_dependencies: false,
+            o3de_prebuilt: default_o3de_prebuilt(),
+            supervise_game: default_supervise_game(),
+            game_restarts: 0,
+            launch_profiles: default_launch_profiles(),
         }
     }
 }
 
 impl Config {
     pub fn load() -> Result<Self> {
+        let mut config = Self::load_saved();
+        if let Some(url) = config.server_url_override() {
+            config.server_url = url;
+        }
+        config.validate();
+        config.save()?;
+        Ok(config)
+    }
+
+    /// The config file as it is, before [`Config::server_url_override`]
+    /// and validation.
+    pub fn load_saved() -> Self {
         let config_path = Self::config_path();
-        
-        let mut config = if config_path.exists() {
-            let content = std::fs::read_to_string(&config_path)?;
-            serde_json::from_str(&content)?
-        } else {
-            Config::default()
+        match std::fs::read_to_string(&config_path) {
+            Ok(content) => Self::parse(&content, &config_path),
+            Err(_) => Config::default(),
+        }
+    }
+
+    /// The server URL that replaces the configured one for this session:
+    /// `AAA_SERVER_URL`, or else `server_url.txt` (written by bootstrap.bat
+    /// so the dev server in use is the one the launcher talks to).
+    pub fn server_url_override(&self) -> Option<String> {
+        let from_env = std::env::var("AAA_SERVER_URL").ok().filter(|url| !url.is_empty());
+        from_env.or_else(|| {
+            let url = std::fs::read_to_string(self.install_dir.join("server_url.txt")).ok()?;
+            Some(url.trim().to_string()).filter(|url| !url.is_empty())
+        })
+    }
+
+    /// Reads the file field by field, so one bad value falls back to its
+    /// default instead of stopping the launcher. A file that isn't JSON at
+    /// all is set aside as `.bad` and replaced with the defaults.
+    fn parse(content: &str, path: &Path) -> Self {
+        let mut fields = match serde_json::from_str::<Value>(content) {
+            Ok(Value::Object(fields)) => fields,
+            result => {
+                let reason = result.err().map_or_else(|| "not a JSON object".to_string(), |e| e.to_string());
+                let backup = path.with_extension("json.bad");
+                let _ = std::fs::copy(path, &backup);
+                logging::warn(&format!(
+                    "{} is unreadable ({}) - using the defaults; the old file is in {}",
+                    path.display(),
+                    reason,
+                    backup.display()
+                ));
+                return Config::default();
+            }
         };
-        
-        // Override server URL from server_url.txt if it exists (written by bootstrap.bat)
-        // This ensures we use the correct dev server URL for the current session
-        let server_url_file = config.install_dir.join("server_url.txt");
-        if let Ok(url) = std::fs::read_to_string(&server_url_file) {
-            let url = url.trim();
-            if !url.is_empty() {
-                config.server_url = url.to_string();
+        Self::migrate(&mut fields);
+
+        let Ok(Value::Object(mut merged)) = serde_json::to_value(Config::default()) else {
+            return Config::default();
+        };
+        for (key, value) in fields {
+            let mut candidate = merged.clone();
+            candidate.insert(key.clone(), value.clone());
+            if serde_json::from_value::<Config>(Value::Object(candidate)).is_ok() {
+                merged.insert(key, value);
+            } else {
+                logging::warn(&format!("Invalid \"{}\": {} in {} - using the default", key, value, path.display()));
             }
         }
-        
-        // Also check environment variable
-        if let Ok(url) = std::env::var("AAA_SERVER_URL") {
-            if !url.is_empty() {
-                config.server_url = url;
+        serde_json::from_value(Value::Object(merged)).unwrap_or_default()
+    }
+
+    fn migrate(fields: &mut Map<String, Value>) {
+        let version = fields
+            .get("config_version")
+            .and_then(Value::as_u64)
+            .map_or(default_config_version(), |v| v as u32);
+        if version > CONFIG_VERSION {
+            logging::warn(&format!(
+                "The launcher config is from a newer launcher (version {}); settings it doesn't know are dropped",
+                version
+            ));
+        }
+        for (step, migration) in MIGRATIONS.iter().enumerate().skip(version.saturating_sub(1) as usize) {
+            migration(fields);
+            logging::info(&format!("Updated the launcher config to version {}", step + 2));
+        }
+        fields.insert("config_version".to_string(), CONFIG_VERSION.into());
+    }
+
+    /// Puts every invalid value back to its default, saying which.
+    pub fn validate(&mut self) {
+        for (_, problem) in self.reset_invalid() {
+            logging::warn(&format!("{} - using the default", problem));
+        }
+    }
+
+    /// Puts every invalid value back to its default. Returns those fields,
+    /// each with what was wrong.
+    fn reset_invalid(&mut self) -> Vec<(&'static str, String)> {
+        let defaults = Config::default();
+        let mut invalid = Vec::new();
+        let mut reset = |field: &'static str, value: &dyn fmt::Display, problem: &str| {
+            invalid.push((field, format!("Invalid {} \"{}\": {}", field, value, problem)));
+        };
+
+        match reqwest::Url::parse(&self.server_url) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") && url.host().is_some() => {
+                // Shown and saved without a trailing slash
+                let trimmed = self.server_url.trim_end_matches('/').len();
+                self.server_url.truncate(trimmed);
+            }
+            Ok(_) => {
+                reset("server_url", &self.server_url, "expected an http or https URL");
+                self.server_url = defaults.server_url.clone();
+            }
+            Err(e) => {
+                reset("server_url", &self.server_url, &e.to_string());
+                self.server_url = defaults.server_url.clone();
             }
         }
-        
-        config.save()?;
-        Ok(config)
+
+        for (field, value, default, parts, example) in [
+            ("o3de_version", &mut self.o3de_version, &defaults.o3de_version, 2, "2510.1"),
+            ("vulkan_version", &mut self.vulkan_version, &defaults.vulkan_version, 4, "1.3.290.0"),
+            ("tracy_version", &mut self.tracy_version, &defaults.tracy_version, 3, "0.11.1"),
+        ] {
+            if !is_version(value, parts) {
+                reset(field, value, &format!("expected a version like {}", example));
+                *value = default.clone();
+            }
+        }
+
+        if !self.install_dir.is_absolute() {
+            reset("install_dir", &self.install_dir.display(), "expected a full path");
+            self.install_dir = defaults.install_dir.clone();
+        } else if let Err(e) = check_writable(&self.install_dir) {
+            reset("install_dir", &self.install_dir.display(), &format!("{:#}", e));
+            self.install_dir = defaults.install_dir.clone();
+        }
+
+        if !(1..=32).contains(&self.download_parallelism) {
+            reset("download_parallelism", &self.download_parallelism, "expected 1 to 32");
+            self.download_parallelism = defaults.download_parallelism;
+        }
+
+        if self.download_retries > 10 {
+            reset("download_retries", &self.download_retries, "expected 0 to 10");
+            self.download_retries = defaults.download_retries;
+        }
+
+        // At 0 the rollback point a sync has just made would be pruned straight away
+        if !(1..=20).contains(&self.keep_versions) {
+            reset("keep_versions", &self.keep_versions, "expected 1 to 20");
+            self.keep_versions = defaults.keep_versions;
+        }
+
+        if self.game_restarts > 10 {
+            reset("game_restarts", &self.game_restarts, "expected 0 to 10");
+            self.game_restarts = defaults.game_restarts;
+        }
+
+        let proxy = self.proxy.trim();
+        if !proxy.is_empty() && proxy != "none" {
+            let scheme = reqwest::Url::parse(proxy).map(|url| url.scheme().to_string());
+            if !scheme.is_ok_and(|scheme| matches!(scheme.as_str(), "http" | "https" | "socks5" | "socks5h")) {
+                reset("proxy", &proxy, "expected an http, https or socks5 URL, or \"none\"");
+                self.proxy = defaults.proxy.clone();
+            }
+        }
+
+        if let Some(bundle) = self.ca_bundle.as_ref().filter(|bundle| !bundle.is_file()) {
+            reset("ca_bundle", &bundle.display(), "no such file");
+            self.ca_bundle = None;
+        }
+
+        self.launch_profiles.retain(|name, profile| {
+            let problem = if name.is_empty() || name.starts_with('-') {
+                Some("a profile name can't be empty or start with -".to_string())
+            } else {
+                let bad_var = profile.env.keys().find(|var| var.is_empty() || var.contains(['=', '\0']));
+                bad_var.map(|var| format!("\"{}\" isn't an environment variable name", var))
+            };
+            if let Some(problem) = &problem {
+                reset("launch_profiles", name, problem);
+            }
+            problem.is_none()
+        });
+        invalid
+    }
+
+    /// Every setting by name, as it's saved.
+    pub fn fields(&self) -> Map<String, Value> {
+        match serde_json::to_value(self) {
+            Ok(Value::Object(fields)) => fields,
+            _ => Map::new(),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Result<Value> {
+        self.fields()
+            .remove(key)
+            .ok_or_else(|| anyhow::anyhow!("Unknown setting \"{}\" - `config list` shows them all", key))
+    }
+
+    /// Changes `key` to `value`. The value is read as JSON first, so
+    /// `true`, `8` and `null` work, and as text otherwise. One that doesn't
+    /// fit the setting or fails validation is an error and changes nothing.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        self.get(key)?;
+        if key == "config_version" {
+            anyhow::bail!("config_version is managed by the launcher");
+        }
+
+        let text = || Value::String(value.to_string());
+        let mut config = match serde_json::from_str(value).map(|json| self.with_field(key, json)) {
+            Ok(Ok(config)) => Ok(config),
+            // JSON of the wrong type for the setting, e.g. a number for a version
+            Ok(Err(json_error)) => self.with_field(key, text()).map_err(|_| json_error),
+            Err(_) => self.with_field(key, text()),
+        }
+        .with_context(|| format!("Invalid {} \"{}\"", key, value))?;
+
+        if let Some((_, problem)) = config.reset_invalid().into_iter().find(|(field, _)| *field == key) {
+            anyhow::bail!("{}", problem);
+        }
+        *self = config;
+        Ok(())
+    }
+
+    /// Puts `key` back to its default.
+    pub fn reset_field(&mut self, key: &str) -> Result<()> {
+        *self = self.with_field(key, Config::default().get(key)?)?;
+        Ok(())
+    }
+
+    /// A copy with `key` set to `value`, if the value fits the setting.
+    fn with_field(&self, key: &str, value: Value) -> serde_json::Result<Config> {
+        let mut fields = self.fields();
+        fields.insert(key.to_string(), value);
+        serde_json::from_value(Value::Object(fields))
+    }
+
+    /// Replaces the config file with the defaults, keeping the old one as
+    /// `.bak`. Returns where the config is.
+    pub fn reset() -> Result<PathBuf> {
+        let path = Self::config_path();
+        if path.exists() {
+            std::fs::copy(&path, path.with_extension("json.bak")).context("Failed to back up the launcher config")?;
+        }
+        Config::default().save()?;
+        Ok(path)
     }
 
     pub fn save(&self) -> Result<()> {
@@ -82,7 +514,7 @@ impl Config {
         Ok(())
     }
 
-    fn config_path() -> PathBuf {
+    pub fn config_path() -> PathBuf {
         dirs::data_local_dir()
             .unwrap_or_else(|| PathBuf::from("C:\\"))
             .join("AAAEngine")
@@ -97,8 +529,24 @@ impl Config {
         self.install_dir.join("o3de")
     }
 
+    /// Where the channel's engine is synced and built. Stable keeps the
+    /// original `engine` directory so existing installs carry on as they are.
     pub fn engine_dir(&self) -> PathBuf {
-        self.install_dir.join("engine")
+        match self.channel {
+            Channel::Stable => self.install_dir.join("engine"),
+            channel => self.install_dir.join(format!("engine-{}", channel)),
+        }
+    }
+
+    /// A server URL for the path `segments` on the configured channel. Each
+    /// segment is percent-encoded, so a `?`, `#` or `/` in a file name stays
+    /// part of that name.
+    pub fn endpoint<'a>(&self, segments: impl IntoIterator<Item = &'a str>) -> Result<reqwest::Url> {
+        let invalid = || anyhow::anyhow!("Invalid server_url \"{}\"", self.server_url);
+        let mut url = reqwest::Url::parse(&self.server_url).map_err(|_| invalid())?;
+        url.path_segments_mut().map_err(|_| invalid())?.pop_if_empty().extend(segments);
+        url.query_pairs_mut().append_pair("channel", self.channel.as_str());
+        Ok(url)
     }
 
     pub fn logs_dir(&self) -> PathBuf {
@@ -112,6 +560,29 @@ impl Config {
     pub fn tracy_dir(&self) -> PathBuf {
         self.deps_dir().join(format!("tracy-{}", self.tracy_version))
     }
+
+    /// Cargo features the game is built with on top of its defaults.
+    pub fn game_features(&self) -> Vec<&'static str> {
+        let mut features = Vec::new();
+        if self.enable_tracy {
+            features.push("tracy");
+        }
+        features
+    }
+}
+
+/// `version` is `parts` numbers separated by dots.
+pub fn is_version(version: &str, parts: usize) -> bool {
+    let numbers: Vec<&str> = version.split('.').collect();
+    numbers.len() == parts && numbers.iter().all(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+}
+
+fn check_writable(dir: &Path) -> Result<()> {
+    std::fs::create_dir_all(dir).context("can't be created")?;
+    let probe = dir.join(".write_test");
+    std::fs::write(&probe, b"").context("isn't writable")?;
+    let _ = std::fs::remove_file(probe);
+    Ok(())
 }
 
 #[allow(dead_code)]
@@ -0,0 +1,171 @@
+//! Crash reports the game left in `<engine>/crashes/`, sent to
+//! `/crash/submit` on the next run if the player agrees.
+//!
+//! The game writes `crash-<time>-<pid>.json` (and a `.dmp` beside it for
+//! native crashes). Sent reports are renamed to `.json.sent`, ones the
+//! player chose not to send to `.json.declined`, so each is only offered
+//! once. `crash_reports` in the config remembers "always" and "never".
+
+use anyhow::{Context, Result};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::config::{Config, CrashReportConsent};
+use crate::http;
+use crate::logging;
+
+pub struct CrashReports {
+    config: Config,
+    dir: PathBuf,
+    client: reqwest::Client,
+}
+
+impl CrashReports {
+    pub fn new(config: Config) -> Result<Self> {
+        let client = http::client_builder(&config)?
+            .timeout(std::time::Duration::from_secs(60))
+            .build()?;
+        Ok(Self {
+            dir: config.engine_dir().join("crashes"),
+            config,
+            client,
+        })
+    }
+
+    /// Reports not sent or declined yet, oldest first.
+    pub fn pending(&self) -> Vec<PathBuf> {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        let mut reports: Vec<PathBuf> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .collect();
+        reports.sort();
+        reports
+    }
+
+    /// Offers to send pending reports. `interactive` is whether the player
+    /// can be asked; if not, and they haven't said "always", the reports wait.
+    pub async fn offer(&self, interactive: bool) -> Result<()> {
+        let reports = self.pending();
+        if reports.is_empty() {
+            return Ok(());
+        }
+        logging::warn(&format!("The game crashed {} time(s) since the last run", reports.len()));
+        for report in &reports {
+            if let Some(summary) = summary(report) {
+                logging::info(&format!("  {}", summary));
+            }
+        }
+
+        let send = match self.config.crash_reports {
+            CrashReportConsent::Always => true,
+            CrashReportConsent::Never => false,
+            CrashReportConsent::Ask if interactive => self.ask()?,
+            CrashReportConsent::Ask => {
+                logging::info(&format!(
+                    "Crash reports are in {}; run the launcher in a console to send them",
+                    self.dir.display()
+                ));
+                return Ok(());
+            }
+        };
+
+        if !send {
+            for report in &reports {
+                let _ = std::fs::rename(report, report.with_extension("json.declined"));
+            }
+            logging::info(&format!("Not sending crash reports; they stay in {}", self.dir.display()));
+            return Ok(());
+        }
+
+        let mut sent = 0;
+        for report in &reports {
+            match self.upload(report).await {
+                Ok(()) => sent += 1,
+                Err(e) => {
+                    // Whatever's left is offered again next time
+                    logging::warn(&format!("Could not send crash report: {:#}", e));
+                    break;
+                }
+            }
+        }
+        if sent > 0 {
+            logging::success(&format!("Sent {} crash report(s) - thank you", sent));
+        }
+        Ok(())
+    }
+
+    /// Asks on the console; "always" and "never" are saved to the config.
+    fn ask(&self) -> Result<bool> {
+        println!();
+        println!("Send the crash reports to the developers? They contain the error, the game's recent");
+        println!("log, and the OS, CPU count, GPU, driver and install path.");
+        print!("[y]es / [n]o / [a]lways / ne[v]er: ");
+        std::io::stdout().flush()?;
+        let mut answer = String::new();
+        std::io::stdin().read_line(&mut answer)?;
+
+        let (send, remember) = match answer.trim().to_ascii_lowercase().as_str() {
+            "y" | "yes" => (true, None),
+            "a" | "always" => (true, Some(CrashReportConsent::Always)),
+            "v" | "never" => (false, Some(CrashReportConsent::Never)),
+            _ => (false, None),
+        };
+        if let Some(consent) = remember {
+            // Saved from a fresh load so this run's overrides don't stick
+            let mut saved = Config::load()?;
+            saved.crash_reports = consent;
+            saved.save()?;
+            logging::info(&format!("Saved \"crash_reports\": \"{}\" to the launcher config", consent));
+        }
+        Ok(send)
+    }
+
+    async fn upload(&self, report: &Path) -> Result<()> {
+        let body = std::fs::read(report).with_context(|| format!("Failed to read {}", report.display()))?;
+        self.post(self.config.endpoint(["crash", "submit"])?, "application/json", body).await?;
+
+        let minidump = report.with_extension("dmp");
+        if minidump.exists() {
+            let stem = report.file_stem().unwrap_or_default().to_string_lossy();
+            let mut url = self.config.endpoint(["crash", "submit", "minidump"])?;
+            url.query_pairs_mut().append_pair("report", &stem);
+            self.post(url, "application/octet-stream", std::fs::read(&minidump)?).await?;
+            let _ = std::fs::rename(&minidump, minidump.with_extension("dmp.sent"));
+        }
+        std::fs::rename(report, report.with_extension("json.sent"))?;
+        Ok(())
+    }
+
+    async fn post(&self, url: reqwest::Url, content_type: &str, body: Vec<u8>) -> Result<()> {
+        let response = self
+            .client
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, content_type)
+            .body(body)
+            .send()
+            .await
+            .context("Failed to reach the crash report server")?;
+        if !response.status().is_success() {
+            anyhow::bail!("Crash report server answered HTTP {}", response.status());
+        }
+        Ok(())
+    }
+}
+
+/// "<time>: <message>" from a report, for listing it.
+fn summary(report: &Path) -> Option<String> {
+    let text = std::fs::read_to_string(report).ok()?;
+    let report: serde_json::Value = serde_json::from_str(&text).ok()?;
+    let message = report.get("message")?.as_str()?.lines().next()?;
+    let time = report
+        .get("time")
+        .and_then(|time| time.as_i64())
+        .and_then(|time| chrono::DateTime::from_timestamp(time, 0))
+        .map(|time| time.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_default();
+    Some(format!("{}: {}", time, message))
+}
@@ -1,10 +1,58 @@
 use anyhow::{Context, Result};
 use serde_json::Value;
+use sha2::{Digest, Sha256};
 use std::path::PathBuf;
 use std::process::Command;
 
 use crate::config::Config;
+use crate::dependency_manifest::DependencyManifest;
+use crate::download_limit::{self, RateLimiter};
+use crate::http;
 use crate::logging;
+use crate::o3de_packages::{self, Toolchain};
+
+/// Where `vswhere.exe` is; it comes with the Visual Studio Installer.
+const VSWHERE_PATHS: &[&str] = &[
+    r"C:\Program Files (x86)\Microsoft Visual Studio\Installer\vswhere.exe",
+    r"C:\Program Files\Microsoft Visual Studio\Installer\vswhere.exe",
+];
+
+/// The Visual Studio Installer, for changing an existing installation.
+const VS_SETUP_PATH: &str = r"C:\Program Files (x86)\Microsoft Visual Studio\Installer\setup.exe";
+
+/// A Visual Studio component the engine build needs.
+struct VsComponent {
+    /// Installed package IDs that count, by prefix: any Windows SDK version will do.
+    prefix: &'static str,
+    /// What to `--add` when none is installed.
+    add: &'static str,
+    description: &'static str,
+}
+
+const VS_COMPONENTS: &[VsComponent] = &[
+    VsComponent {
+        prefix: "Microsoft.VisualStudio.Component.VC.Tools.x86.x64",
+        add: "Microsoft.VisualStudio.Component.VC.Tools.x86.x64",
+        description: "MSVC x64 compiler",
+    },
+    VsComponent {
+        prefix: "Microsoft.VisualStudio.Component.Windows1",
+        add: "Microsoft.VisualStudio.Component.Windows11SDK.22621",
+        description: "Windows SDK",
+    },
+    VsComponent {
+        prefix: "Microsoft.VisualStudio.Component.VC.CMake.Project",
+        add: "Microsoft.VisualStudio.Component.VC.CMake.Project",
+        description: "C++ CMake tools",
+    },
+];
+
+struct VsInstallation {
+    path: PathBuf,
+    version: String,
+    cl_path: Option<PathBuf>,
+    missing: Vec<&'static VsComponent>,
+}
 
 #[derive(Debug, Clone)]
 pub struct DependencyStatus {
@@ -15,35 +63,96 @@ pub struct DependencyStatus {
     pub path: Option<PathBuf>,
 }
 
+/// Dependency names and their IDs in the [`DependencyManifest`].
+const MANIFEST_IDS: &[(&str, &str)] = &[
+    ("Visual Studio Build Tools", "vs_build_tools"),
+    ("Rust", "rust"),
+    ("Vulkan SDK", "vulkan_sdk"),
+    ("Tracy Profiler", "tracy"),
+    ("O3DE SDK", "o3de"),
+    ("CMake", "cmake"),
+];
+
 pub struct DependencyManager {
     config: Config,
+    manifest: DependencyManifest,
+    limiter: RateLimiter,
 }
 
 impl DependencyManager {
-    pub fn new(config: Config) -> Self {
-        Self { config }
+    /// `config` should already have `manifest`'s versions applied.
+    pub fn new(config: Config, manifest: DependencyManifest) -> Self {
+        Self {
+            limiter: RateLimiter::new(&config),
+            config,
+            manifest,
+        }
+    }
+
+    /// Downloads `url` within the download limit.
+    async fn download(&self, client: &reqwest::Client, url: &str) -> Result<Vec<u8>> {
+        let response = client.get(url).send().await?;
+        download_limit::read_body(response, &self.limiter, &indicatif::ProgressBar::hidden()).await
+    }
+
+    /// Downloads dependency `id` from the manifest's URL, or `default_url`,
+    /// and checks it against the manifest's checksum.
+    async fn download_dependency(&self, client: &reqwest::Client, id: &str, default_url: &str) -> Result<Vec<u8>> {
+        let spec = self.manifest.get(id);
+        let url = spec.and_then(|spec| spec.url.as_deref()).unwrap_or(default_url);
+        let bytes = self.download(client, url).await?;
+        if let Some(expected) = spec.and_then(|spec| spec.checksum.as_deref()) {
+            let actual = hex::encode(Sha256::digest(&bytes));
+            if !actual.eq_ignore_ascii_case(expected) {
+                anyhow::bail!("{} doesn't match the dependency manifest's checksum (got {})", url, actual);
+            }
+        }
+        Ok(bytes)
     }
 
     pub fn check_all(&self) -> Vec<DependencyStatus> {
-        vec![
+        let mut deps = vec![
             self.check_vs_build_tools(),
             self.check_rust(),
             self.check_vulkan_sdk(),
             self.check_tracy(),
             self.check_o3de(),
             self.check_cmake(),
-        ]
+        ];
+        for dep in deps.iter_mut().filter(|dep| dep.installed) {
+            if let Some((installed, min)) = self.too_old(dep) {
+                logging::warn(&format!("{} {} is older than the {} the engine needs", dep.name, installed, min));
+                dep.installed = false;
+            }
+        }
+        deps
+    }
+
+    /// `dep`'s installed version and the manifest's `min_version`, if it's
+    /// older. An unreadable version is given the benefit of the doubt.
+    fn too_old<'a>(&'a self, dep: &'a DependencyStatus) -> Option<(&'a str, &'a str)> {
+        let (_, id) = MANIFEST_IDS.iter().find(|(name, _)| *name == dep.name)?;
+        let installed = version_number(dep.version.as_deref()?)?;
+        Some((installed, self.manifest.too_old(id, installed)?))
     }
 
     pub fn check_vs_build_tools(&self) -> DependencyStatus {
         // Use vswhere.exe as the SINGLE SOURCE OF TRUTH for VS detection
         // This is Microsoft's official tool and is always accurate
-        if let Some((path, version)) = self.find_vs_via_vswhere() {
+        if let Some(install) = self.find_vs_via_vswhere() {
+            if !install.missing.is_empty() {
+                logging::warn(&format!(
+                    "Visual Studio {} at {} is missing: {}",
+                    install.version,
+                    install.path.display(),
+                    install.missing.iter().map(|c| c.description).collect::<Vec<_>>().join(", ")
+                ));
+            }
             return DependencyStatus {
                 name: "Visual Studio Build Tools".to_string(),
-                installed: true,
-                version: Some(version),
-                path: Some(path),
+                installed: install.missing.is_empty(),
+                version: Some(install.version),
+                path: Some(install.cl_path.unwrap_or(install.path)),
             };
         }
 
@@ -65,65 +174,62 @@ impl DependencyManager {
         }
     }
 
-    fn find_vs_via_vswhere(&self) -> Option<(PathBuf, String)> {
+    /// The Visual Studio installation to build with: the first one with
+    /// every [`VS_COMPONENTS`] entry, or else the one missing the fewest.
+    fn find_vs_via_vswhere(&self) -> Option<VsInstallation> {
         // vswhere.exe is installed with VS Installer, always at this location
-        let vswhere_paths = [
-            r"C:\Program Files (x86)\Microsoft Visual Studio\Installer\vswhere.exe",
-            r"C:\Program Files\Microsoft Visual Studio\Installer\vswhere.exe",
-        ];
+        let vswhere = VSWHERE_PATHS.iter().map(std::path::Path::new).find(|path| path.exists())?;
 
-        for vswhere in &vswhere_paths {
-            let vswhere_path = std::path::Path::new(vswhere);
-            if !vswhere_path.exists() {
-                continue;
-            }
-
-            // Query for any VS installation with C++ tools
-            let output = Command::new(vswhere)
-                .args([
-                    "-latest",
-                    "-products", "*",
-                    "-requires", "Microsoft.VisualStudio.Component.VC.Tools.x86.x64",
-                    "-format", "json"
-                ])
-                .output()
-                .ok()?;
+        // Every installation with its component list, C++ tools or not, so
+        // one that's missing components can be repaired
+        let output = Command::new(vswhere)
+            .args(["-products", "*", "-include", "packages", "-format", "json"])
+            .output()
+            .ok()?;
+        let installations: Vec<Value> = serde_json::from_slice(&output.stdout).ok()?;
+
+        installations
+            .iter()
+            .filter_map(|install| {
+                let path = install.get("installationPath").and_then(|v| v.as_str()).map(PathBuf::from)?;
+                let version = install
+                    .get("installationVersion")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("2022")
+                    .to_string();
+                let packages: Vec<&str> = install
+                    .get("packages")
+                    .and_then(|v| v.as_array())
+                    .map(|packages| packages.iter().filter_map(|p| p.get("id")?.as_str()).collect())
+                    .unwrap_or_default();
+                let missing = VS_COMPONENTS
+                    .iter()
+                    .filter(|component| !packages.iter().any(|id| id.starts_with(component.prefix)))
+                    .collect();
+                let cl_path = find_cl(&path);
+                Some(VsInstallation {
+                    path,
+                    version,
+                    cl_path,
+                    missing,
+                })
+            })
+            .min_by_key(|install| install.missing.len())
+    }
 
-            let json_str = String::from_utf8_lossy(&output.stdout);
-            
-            // Parse JSON to get installation path and version
-            if let Ok(installations) = serde_json::from_str::<Vec<Value>>(&json_str) {
-                if let Some(install) = installations.first() {
-                    let install_path = install.get("installationPath")
-                        .and_then(|v| v.as_str())
-                        .map(PathBuf::from)?;
-                    
-                    let version = install.get("installationVersion")
-                        .and_then(|v| v.as_str())
-                        .unwrap_or("2022")
-                        .to_string();
-                    
-                    // Find cl.exe in this installation
-                    let vc_tools = install_path.join("VC").join("Tools").join("MSVC");
-                    if vc_tools.exists() {
-                        // Find the actual MSVC version directory and return path to cl.exe
-                        if let Ok(entries) = std::fs::read_dir(&vc_tools) {
-                            for entry in entries.flatten() {
-                                let cl_path = entry.path()
-                                    .join("bin").join("Hostx64").join("x64").join("cl.exe");
-                                if cl_path.exists() {
-                                    // Return the cl.exe path, not just the install root
-                                    return Some((cl_path, version));
-                                }
-                            }
-                        }
-                        // If we can't find cl.exe but the MSVC dir exists, return the install path
-                        return Some((install_path, version));
-                    }
-                }
-            }
-        }
-        None
+    /// The MSVC toolset the engine will be built with, to pick a prebuilt
+    /// O3DE package for.
+    fn msvc_toolchain(&self) -> Option<Toolchain> {
+        let cl_path = self
+            .find_vs_via_vswhere()
+            .and_then(|install| install.cl_path)
+            .or_else(|| which::which("cl.exe").ok())?;
+        // ...\VC\Tools\MSVC\<toolset>\bin\Hostx64\x64\cl.exe
+        let version = cl_path.ancestors().nth(4)?.file_name()?.to_string_lossy().into_owned();
+        Some(Toolchain {
+            name: "msvc".to_string(),
+            version,
+        })
     }
 
     fn get_cl_version(&self) -> Option<String> {
@@ -161,13 +267,13 @@ impl DependencyManager {
         // First check VULKAN_SDK environment variable (set by installer)
         if let Ok(sdk_path) = std::env::var("VULKAN_SDK") {
             let path = PathBuf::from(&sdk_path);
-            if path.exists() {
-                // Extract version from path (e.g., C:\VulkanSDK\1.3.290.0)
-                let version = path.file_name()
-                    .and_then(|n| n.to_str())
-                    .map(|s| s.to_string())
-                    .unwrap_or_else(|| self.config.vulkan_version.clone());
-                    
+            // Extract version from path (e.g., C:\VulkanSDK\1.3.290.0)
+            let version = path.file_name()
+                .and_then(|n| n.to_str())
+                .map(|s| s.to_string())
+                .unwrap_or_else(|| self.config.vulkan_version.clone());
+            // Right after an upgrade VULKAN_SDK still names the old SDK, so it only wins if it's new enough
+            if path.exists() && self.manifest.too_old("vulkan_sdk", &version).is_none() {
                 logging::info(&format!("Found Vulkan SDK at: {}", path.display()));
                 return DependencyStatus {
                     name: "Vulkan SDK".to_string(),
@@ -428,31 +534,64 @@ impl DependencyManager {
         }
     }
 
+    /// Installs what's missing, stopping at the first required dependency
+    /// that fails. Optional ones that fail are skipped with a warning.
     pub async fn install_missing(&self, deps: &[DependencyStatus]) -> Result<()> {
         for dep in deps.iter().filter(|d| !d.installed) {
-            match dep.name.as_str() {
-                "Visual Studio Build Tools" => self.install_vs_build_tools().await?,
-                "Rust" => self.install_rust().await?,
-                "Vulkan SDK" => self.install_vulkan_sdk().await?,
-                "Tracy Profiler" => self.install_tracy().await?,
-                "O3DE SDK" => self.install_o3de().await?,
-                "CMake" => logging::warn("CMake should be installed with VS Build Tools"),
-                _ => logging::warn(&format!("Unknown dependency: {}", dep.name)),
+            let result = match dep.name.as_str() {
+                "Visual Studio Build Tools" => self.install_vs_build_tools().await,
+                "Rust" => self.install_rust().await,
+                "Vulkan SDK" => self.install_vulkan_sdk().await,
+                "Tracy Profiler" => self.install_tracy().await,
+                "O3DE SDK" => self.install_o3de().await,
+                "CMake" => {
+                    logging::warn("CMake should be installed with VS Build Tools");
+                    Ok(())
+                }
+                _ => {
+                    logging::warn(&format!("Unknown dependency: {}", dep.name));
+                    Ok(())
+                }
+            };
+            match result {
+                Ok(()) => {}
+                Err(e) if !self.is_required(dep) => {
+                    logging::warn(&format!("{} failed to install: {:#} - continuing without it", dep.name, e));
+                }
+                Err(e) => return Err(e.context(format!("Failed to install {}", dep.name))),
             }
         }
         Ok(())
     }
 
+    /// Whether the run stops without `dep`. Tracy is only needed for
+    /// `--tracy` builds; `require_all_dependencies` makes everything required.
+    pub fn is_required(&self, dep: &DependencyStatus) -> bool {
+        if self.config.require_all_dependencies {
+            return true;
+        }
+        match dep.name.as_str() {
+            "Tracy Profiler" => self.config.enable_tracy,
+            _ => true,
+        }
+    }
+
     async fn install_vs_build_tools(&self) -> Result<()> {
+        let log_path = self.config.deps_dir().join("vs_install.log");
+        std::fs::create_dir_all(self.config.deps_dir())?;
+
+        // An installation that only lacks some components is modified in
+        // place; installing Build Tools next to it wouldn't fix it
+        if let Some(install) = self.find_vs_via_vswhere().filter(|install| !install.missing.is_empty()) {
+            return self.repair_vs_components(&install, &log_path);
+        }
+
         logging::info("Installing Visual Studio Build Tools 2022...");
         logging::warn("This may take 10-30 minutes on first install");
         logging::warn("An installer window will open - please wait for it to complete");
 
         let installer_url = "https://aka.ms/vs/17/release/vs_buildtools.exe";
         let installer_path = self.config.deps_dir().join("vs_buildtools.exe");
-        let log_path = self.config.deps_dir().join("vs_install.log");
-
-        std::fs::create_dir_all(self.config.deps_dir())?;
 
         // Step 1: Clear any corrupted installer state
         logging::info("Clearing any corrupted installer state...");
@@ -470,11 +609,10 @@ impl DependencyManager {
 
         // Step 2: Download installer
         logging::info("Downloading VS Build Tools installer...");
-        let client = reqwest::Client::builder()
+        let client = http::client_builder(&self.config)?
             .timeout(std::time::Duration::from_secs(300))
             .build()?;
-        let response = client.get(installer_url).send().await?;
-        let bytes = response.bytes().await?;
+        let bytes = self.download_dependency(&client, "vs_build_tools", installer_url).await?;
         std::fs::write(&installer_path, &bytes)?;
         logging::success("Installer downloaded");
 
@@ -482,61 +620,25 @@ impl DependencyManager {
         // Using --passive instead of --quiet so user can see progress
         logging::info("Starting installer (a progress window will appear)...");
         logging::warn("Do NOT close the installer window - wait for it to finish");
-        
-        let ps_script = format!(
-            r#"
-# Run VS Build Tools installer with elevation
-$installerPath = '{}'
-$logPath = '{}'
-
-# Build argument list
-$args = @(
-    '--passive',
-    '--wait', 
-    '--norestart',
-    '--nocache',
-    '--noUpdateInstaller',
-    '--includeRecommended',
-    '--log', $logPath,
-    '--add', 'Microsoft.VisualStudio.Workload.VCTools',
-    '--add', 'Microsoft.VisualStudio.Component.VC.Tools.x86.x64',
-    '--add', 'Microsoft.VisualStudio.Component.Windows11SDK.22621',
-    '--add', 'Microsoft.VisualStudio.Component.VC.CMake.Project'
-)
-
-# Start elevated process and wait
-$psi = New-Object System.Diagnostics.ProcessStartInfo
-$psi.FileName = $installerPath
-$psi.Arguments = $args -join ' '
-$psi.Verb = 'runas'
-$psi.UseShellExecute = $true
-
-try {{
-    $process = [System.Diagnostics.Process]::Start($psi)
-    $process.WaitForExit()
-    $exitCode = $process.ExitCode
-    Write-Output "EXIT_CODE:$exitCode"
-}} catch {{
-    Write-Output "EXIT_CODE:-1"
-    Write-Output "ERROR:$($_.Exception.Message)"
-}}
-"#,
-            installer_path.display().to_string().replace('\\', "\\\\"),
-            log_path.display().to_string().replace('\\', "\\\\")
-        );
-
-        let output = Command::new("powershell")
-            .args(["-NoProfile", "-ExecutionPolicy", "Bypass", "-Command", &ps_script])
-            .output()
-            .context("Failed to run PowerShell")?;
 
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        let exit_code: i32 = stdout
-            .lines()
-            .find(|l| l.starts_with("EXIT_CODE:"))
-            .and_then(|l| l.strip_prefix("EXIT_CODE:"))
-            .and_then(|s| s.parse().ok())
-            .unwrap_or(-1);
+        let mut args: Vec<String> = [
+            "--passive",
+            "--wait",
+            "--norestart",
+            "--nocache",
+            "--noUpdateInstaller",
+            "--includeRecommended",
+            "--add",
+            "Microsoft.VisualStudio.Workload.VCTools",
+        ]
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect();
+        args.extend(["--log".to_string(), log_path.display().to_string()]);
+        for component in VS_COMPONENTS {
+            args.extend(["--add".to_string(), component.add.to_string()]);
+        }
+        let exit_code = run_elevated(&installer_path, &args)?;
 
         // Check exit codes
         match exit_code {
@@ -582,6 +684,52 @@ try {{
         anyhow::bail!("VS Build Tools installation failed (exit code {}). Please try running the installer manually from: {}", exit_code, installer_path.display())
     }
 
+    /// Adds the components `install` is missing with the Visual Studio
+    /// Installer's `modify`, keeping everything else it has.
+    fn repair_vs_components(&self, install: &VsInstallation, log_path: &std::path::Path) -> Result<()> {
+        let names: Vec<&str> = install.missing.iter().map(|c| c.description).collect();
+        logging::info(&format!(
+            "Adding {} to the Visual Studio installation at {}...",
+            names.join(", "),
+            install.path.display()
+        ));
+        logging::warn("An installer window will open - please wait for it to complete");
+
+        let mut args = vec![
+            "modify".to_string(),
+            "--installPath".to_string(),
+            install.path.display().to_string(),
+            "--passive".to_string(),
+            "--wait".to_string(),
+            "--norestart".to_string(),
+            "--log".to_string(),
+            log_path.display().to_string(),
+        ];
+        for component in &install.missing {
+            args.extend(["--add".to_string(), component.add.to_string()]);
+        }
+        let exit_code = run_elevated(std::path::Path::new(VS_SETUP_PATH), &args)?;
+
+        let still_missing = self
+            .find_vs_via_vswhere()
+            .filter(|repaired| repaired.path == install.path)
+            .map_or(install.missing.len(), |repaired| repaired.missing.len());
+        if still_missing == 0 {
+            logging::success(&format!("Added {}", names.join(", ")));
+            if exit_code == 3010 {
+                logging::warn("A system restart is recommended to complete installation");
+            }
+            return Ok(());
+        }
+        anyhow::bail!(
+            "Adding {} to Visual Studio failed (exit code {}). Add them with the Visual Studio Installer \
+             (Modify), or see {}",
+            names.join(", "),
+            exit_code,
+            log_path.display()
+        )
+    }
+
     async fn install_rust(&self) -> Result<()> {
         logging::info("Installing Rust toolchain...");
 
@@ -590,13 +738,13 @@ try {{
 
         std::fs::create_dir_all(self.config.deps_dir())?;
 
-        let client = reqwest::Client::new();
-        let response = client.get(installer_url).send().await?;
-        let bytes = response.bytes().await?;
+        let client = http::client_builder(&self.config)?.build()?;
+        let bytes = self.download_dependency(&client, "rust", installer_url).await?;
         std::fs::write(&installer_path, &bytes)?;
 
+        let toolchain = self.manifest.rust_toolchain();
         let status = Command::new(&installer_path)
-            .args(["-y", "--default-toolchain", "stable"])
+            .args(["-y", "--default-toolchain", toolchain])
             .status()
             .context("Failed to run Rust installer")?;
 
@@ -619,9 +767,8 @@ try {{
 
         std::fs::create_dir_all(self.config.deps_dir())?;
 
-        let client = reqwest::Client::new();
-        let response = client.get(&installer_url).send().await?;
-        let bytes = response.bytes().await?;
+        let client = http::client_builder(&self.config)?.build()?;
+        let bytes = self.download_dependency(&client, "vulkan_sdk", &installer_url).await?;
         std::fs::write(&installer_path, &bytes)?;
 
         let status = Command::new(&installer_path)
@@ -648,9 +795,8 @@ try {{
 
         std::fs::create_dir_all(self.config.deps_dir())?;
 
-        let client = reqwest::Client::new();
-        let response = client.get(&archive_url).send().await?;
-        let bytes = response.bytes().await?;
+        let client = http::client_builder(&self.config)?.build()?;
+        let bytes = self.download_dependency(&client, "tracy", &archive_url).await?;
         std::fs::write(&archive_path, &bytes)?;
 
         let file = std::fs::File::open(&archive_path)?;
@@ -664,6 +810,10 @@ try {{
     }
 
     async fn install_o3de(&self) -> Result<()> {
+        if self.config.o3de_prebuilt && self.install_o3de_prebuilt().await {
+            return Ok(());
+        }
+
         logging::info(&format!("Installing O3DE SDK {} (building from source)...", self.config.o3de_version));
         logging::warn("=".repeat(60).as_str());
         logging::warn("FIRST-TIME BUILD: This will take 60-120 minutes");
@@ -902,14 +1052,108 @@ try {{
         Ok(())
     }
 
+    /// Installs the server's prebuilt O3DE package if it has one for this
+    /// machine. `false` means building from source instead.
+    async fn install_o3de_prebuilt(&self) -> bool {
+        let Some(toolchain) = self.msvc_toolchain() else {
+            logging::info("No MSVC toolset found to pick a prebuilt O3DE package for");
+            return false;
+        };
+        let o3de_dir = self.config.o3de_dir();
+        match o3de_packages::install(&self.config, &self.limiter, &toolchain, &o3de_dir).await {
+            Ok(true) => {
+                let marker_file = self.config.install_dir.join("o3de_version.txt");
+                if let Err(e) = std::fs::write(&marker_file, &self.config.o3de_version) {
+                    logging::warn(&format!("Could not write {}: {}", marker_file.display(), e));
+                }
+                true
+            }
+            Ok(false) => false,
+            Err(e) => {
+                logging::warn(&format!("Prebuilt O3DE package failed: {:#} - building from source instead", e));
+                false
+            }
+        }
+    }
+
     pub fn print_status(&self, deps: &[DependencyStatus]) {
         for dep in deps {
-            if dep.installed {
-                let version = dep.version.as_deref().unwrap_or("unknown");
-                logging::success(&format!("{}: {}", dep.name, version));
+            let name = if self.is_required(dep) {
+                dep.name.clone()
             } else {
-                logging::warn(&format!("{}: NOT INSTALLED", dep.name));
-            }
+                format!("{} (optional)", dep.name)
+            };
+            logging::dependency(&name, dep.installed, dep.version.as_deref());
         }
     }
 }
+
+/// The version in a tool's version output, e.g. `1.82.0` in
+/// `rustc 1.82.0 (f6e511eec 2024-10-15)`.
+fn version_number(text: &str) -> Option<&str> {
+    text.split_whitespace()
+        .find(|word| word.starts_with(|c: char| c.is_ascii_digit()))
+        .map(|word| word.trim_end_matches(|c: char| !c.is_ascii_digit()))
+}
+
+/// Runs `program` elevated (a UAC prompt) through PowerShell and waits for
+/// it. Returns its exit code, or -1 if it didn't start, e.g. because the
+/// prompt was declined.
+fn run_elevated(program: &std::path::Path, args: &[String]) -> Result<i32> {
+    // PowerShell single-quoted strings only escape quotes
+    let quote = |text: &str| format!("'{}'", text.replace('\'', "''"));
+    // Paths with spaces are quoted again for the program's command line
+    let arguments: Vec<String> = args
+        .iter()
+        .map(|arg| {
+            if arg.contains(' ') {
+                quote(&format!("\"{}\"", arg))
+            } else {
+                quote(arg)
+            }
+        })
+        .collect();
+    let ps_script = format!(
+        r#"
+$psi = New-Object System.Diagnostics.ProcessStartInfo
+$psi.FileName = {}
+$psi.Arguments = @({}) -join ' '
+$psi.Verb = 'runas'
+$psi.UseShellExecute = $true
+
+try {{
+    $process = [System.Diagnostics.Process]::Start($psi)
+    $process.WaitForExit()
+    $exitCode = $process.ExitCode
+    Write-Output "EXIT_CODE:$exitCode"
+}} catch {{
+    Write-Output "EXIT_CODE:-1"
+    Write-Output "ERROR:$($_.Exception.Message)"
+}}
+"#,
+        quote(&program.display().to_string()),
+        arguments.join(", ")
+    );
+
+    let output = Command::new("powershell")
+        .args(["-NoProfile", "-ExecutionPolicy", "Bypass", "-Command", &ps_script])
+        .output()
+        .context("Failed to run PowerShell")?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(stdout
+        .lines()
+        .find_map(|l| l.strip_prefix("EXIT_CODE:"))
+        .and_then(|s| s.trim().parse().ok())
+        .unwrap_or(-1))
+}
+
+/// `cl.exe` for x64 in a Visual Studio installation.
+fn find_cl(install_path: &std::path::Path) -> Option<PathBuf> {
+    let vc_tools = install_path.join("VC").join("Tools").join("MSVC");
+    std::fs::read_dir(vc_tools)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path().join("bin").join("Hostx64").join("x64").join("cl.exe"))
+        .find(|cl_path| cl_path.exists())
+}
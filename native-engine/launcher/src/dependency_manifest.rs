@@ -0,0 +1,216 @@
+//! The dependency versions the engine needs, from the server, so upgrading
+//! the Vulkan SDK or O3DE doesn't need a new launcher.
+//!
+//! `/sync/dependencies` (cached in `dependencies.json` for offline runs)
+//! lists them by ID (`vs_build_tools`, `rust`, `vulkan_sdk`, `tracy`,
+//! `o3de`, `cmake`); every field is optional:
+//!
+//! ```json
+//! {
+//!   "dependencies": {
+//!     "vulkan_sdk": {
+//!       "version": "1.3.296.0",
+//!       "min_version": "1.3.290.0",
+//!       "url": "https://sdk.lunarg.com/sdk/download/1.3.296.0/windows/VulkanSDK-1.3.296.0-Installer.exe",
+//!       "checksum": "<sha256 of the download>"
+//!     }
+//!   }
+//! }
+//! ```
+//!
+//! `version` replaces the config's `vulkan_version`, `tracy_version` or
+//! `o3de_version` for the run, and picks the Rust toolchain. An installed
+//! dependency older than `min_version` is installed again. `url` and
+//! `checksum` replace the download the launcher would use. Without a
+//! manifest, the config's versions and the built-in downloads are used as
+//! before.
+//!
+//! Those downloads run as installers, some elevated, so the manifest has to
+//! be signed by a trusted release key: the server publishes the signature
+//! `--gen-manifest --dependencies` writes at `/sync/dependencies.sig`. A
+//! manifest without a valid one is ignored, and the cached copy is checked
+//! again each time it's used.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+use crate::compat::parse_version;
+use crate::config::{self, Config};
+use crate::http;
+use crate::logging;
+use crate::signing::TrustedKeys;
+
+/// The manifest's name, for its signature and the cached copy.
+pub const MANIFEST_FILE: &str = "dependencies.json";
+const SIGNATURE_FILE: &str = "dependencies.json.sig";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DependencySpec {
+    /// The version to install.
+    #[serde(default)]
+    pub version: Option<String>,
+    /// The oldest installed version that will do; any will without it.
+    #[serde(default)]
+    pub min_version: Option<String>,
+    /// Where to download the installer or archive.
+    #[serde(default)]
+    pub url: Option<String>,
+    /// SHA-256 of the download.
+    #[serde(default)]
+    pub checksum: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DependencyManifest {
+    #[serde(default)]
+    pub dependencies: BTreeMap<String, DependencySpec>,
+}
+
+impl DependencyManifest {
+    /// The server's manifest, or the last one fetched if it can't be reached.
+    /// `offline` only reads the cached one. Either has to be signed.
+    pub async fn load(config: &Config, offline: bool) -> Self {
+        let cache = config.install_dir.join(MANIFEST_FILE);
+        let signature_cache = config.install_dir.join(SIGNATURE_FILE);
+        if !offline {
+            match fetch(config).await {
+                // A server without one leaves the config's versions in charge
+                Ok(None) => {
+                    let _ = std::fs::remove_file(&cache);
+                    let _ = std::fs::remove_file(&signature_cache);
+                    return Self::default();
+                }
+                Ok(Some((text, signature))) => match Self::verified(config, &text, &signature) {
+                    Ok(manifest) => {
+                        let _ = std::fs::write(&cache, &text);
+                        let _ = std::fs::write(&signature_cache, &signature);
+                        return manifest;
+                    }
+                    Err(e) => logging::warn(&format!("Ignoring the server's dependency manifest: {:#}", e)),
+                },
+                Err(e) => logging::warn(&format!("Could not fetch the dependency manifest: {:#}", e)),
+            }
+        }
+        let (Ok(text), Ok(signature)) = (std::fs::read(&cache), std::fs::read(&signature_cache)) else {
+            logging::info("Using the dependency versions in the launcher config");
+            return Self::default();
+        };
+        match Self::verified(config, &text, &signature) {
+            Ok(manifest) => {
+                logging::info("Using the cached dependency manifest");
+                manifest
+            }
+            Err(e) => {
+                logging::warn(&format!("Ignoring the cached dependency manifest: {:#}", e));
+                Self::default()
+            }
+        }
+    }
+
+    /// The manifest in `text`, if `signature` (a `.sig` file) vouches for it.
+    fn verified(config: &Config, text: &[u8], signature: &[u8]) -> Result<Self> {
+        TrustedKeys::load(config)?
+            .verify_file(MANIFEST_FILE, text, signature)
+            .context("The dependency manifest isn't signed by a trusted release key")?;
+        serde_json::from_slice(text).context("Invalid dependency manifest")
+    }
+
+    pub fn get(&self, id: &str) -> Option<&DependencySpec> {
+        self.dependencies.get(id)
+    }
+
+    /// Puts the manifest's Vulkan SDK, Tracy and O3DE versions in `config`.
+    pub fn apply(&self, config: &mut Config) {
+        for (id, name, setting, parts) in [
+            ("vulkan_sdk", "Vulkan SDK", &mut config.vulkan_version, 4),
+            ("tracy", "Tracy", &mut config.tracy_version, 3),
+            ("o3de", "O3DE", &mut config.o3de_version, 2),
+        ] {
+            let Some(version) = self.get(id).and_then(|spec| spec.version.as_ref()) else {
+                continue;
+            };
+            if !config::is_version(version, parts) {
+                logging::warn(&format!("Ignoring {} version \"{}\" from the dependency manifest", name, version));
+            } else if version != setting {
+                logging::info(&format!("The server wants {} {} (the config has {})", name, version, setting));
+                *setting = version.clone();
+            }
+        }
+    }
+
+    /// The `min_version` that `installed` falls short of, if any. Versions
+    /// compare number by number.
+    pub fn too_old(&self, id: &str, installed: &str) -> Option<&str> {
+        let min = self.get(id)?.min_version.as_deref()?;
+        (parse_version(installed).cmp(&parse_version(min)) == Ordering::Less).then_some(min)
+    }
+
+    /// The Rust toolchain to install: the manifest's `rust` version if
+    /// rustup would take it as one, stable otherwise.
+    pub fn rust_toolchain(&self) -> &str {
+        match self.get("rust").and_then(|spec| spec.version.as_deref()) {
+            Some(version) if is_toolchain(version) => version,
+            Some(version) => {
+                logging::warn(&format!("Ignoring Rust version \"{}\" from the dependency manifest", version));
+                "stable"
+            }
+            None => "stable",
+        }
+    }
+}
+
+/// A channel (`stable`, `beta`, `nightly`) or release (`1.82`, `1.82.0`),
+/// optionally dated like `nightly-2024-11-01`. Nothing else reaches rustup's
+/// command line.
+fn is_toolchain(name: &str) -> bool {
+    let (channel, date) = match name.split_once('-') {
+        Some((channel, date)) => (channel, Some(date)),
+        None => (name, None),
+    };
+    let channel_ok =
+        matches!(channel, "stable" | "beta" | "nightly") || config::is_version(channel, 2) || config::is_version(channel, 3);
+    let date_ok = date.is_none_or(|date| {
+        let parts: Vec<&str> = date.split('-').collect();
+        parts.len() == 3
+            && parts.iter().zip([4, 2, 2]).all(|(part, len)| part.len() == len && part.chars().all(|c| c.is_ascii_digit()))
+    });
+    channel_ok && date_ok
+}
+
+/// The manifest's text and its signature, or `None` if the server has none.
+async fn fetch(config: &Config) -> Result<Option<(Vec<u8>, Vec<u8>)>> {
+    let client = http::client_builder(config)?
+        .timeout(std::time::Duration::from_secs(30))
+        .build()?;
+    let response = client.get(config.endpoint(["sync", "dependencies"])?).send().await?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    if !response.status().is_success() {
+        anyhow::bail!("HTTP {}", response.status());
+    }
+    let text = response.bytes().await?.to_vec();
+
+    let response = client.get(config.endpoint(["sync", "dependencies.sig"])?).send().await?;
+    if !response.status().is_success() {
+        anyhow::bail!("No signature for it (HTTP {})", response.status());
+    }
+    Ok(Some((text, response.bytes().await?.to_vec())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_toolchain_names_are_taken() {
+        for name in ["stable", "beta", "nightly", "1.82", "1.82.0", "nightly-2024-11-01", "1.82.0-2024-10-17"] {
+            assert!(is_toolchain(name), "{}", name);
+        }
+        for name in ["", "1", "stable --no-modify-path", "nightly-2024-11", "1.82.x", "--help", "stable-x86_64"] {
+            assert!(!is_toolchain(name), "{}", name);
+        }
+    }
+}
@@ -0,0 +1,138 @@
+//! The download speed cap, `max_download_speed_kbps` in the config or
+//! `--limit-rate` for one run. It's in kilobytes per second and shared by
+//! every download the launcher has going at once, so parallel file
+//! downloads split it rather than each getting the full amount.
+
+use anyhow::Result;
+use indicatif::ProgressBar;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+use crate::config::Config;
+
+/// Bytes that can go through at once after an idle spell: one second's worth.
+const BURST_SECONDS: f64 = 1.0;
+
+struct Bucket {
+    /// Goes negative when a chunk takes more than there was; the reader then
+    /// sleeps until it's paid back.
+    available: f64,
+    refilled: Instant,
+}
+
+#[derive(Clone)]
+pub struct RateLimiter {
+    limit: Option<Arc<(f64, Mutex<Bucket>)>>,
+}
+
+impl RateLimiter {
+    pub fn new(config: &Config) -> Self {
+        let limit = (config.max_download_speed_kbps > 0).then(|| {
+            let bytes_per_second = config.max_download_speed_kbps as f64 * 1024.0;
+            let bucket = Bucket {
+                available: bytes_per_second * BURST_SECONDS,
+                refilled: Instant::now(),
+            };
+            Arc::new((bytes_per_second, Mutex::new(bucket)))
+        });
+        Self { limit }
+    }
+
+    /// Waits until `bytes` more are allowed through.
+    pub async fn take(&self, bytes: usize) {
+        let Some(limit) = &self.limit else {
+            return;
+        };
+        let (rate, bucket) = limit.as_ref();
+        let wait = {
+            let mut bucket = bucket.lock().await;
+            let now = Instant::now();
+            let refill = now.duration_since(bucket.refilled).as_secs_f64() * rate;
+            bucket.available = (bucket.available + refill).min(rate * BURST_SECONDS) - bytes as f64;
+            bucket.refilled = now;
+            Duration::from_secs_f64((-bucket.available).max(0.0) / rate)
+        };
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+/// Reads the whole body of `response` within the limit, moving `pb` along.
+pub async fn read_body(mut response: reqwest::Response, limiter: &RateLimiter, pb: &ProgressBar) -> Result<Vec<u8>> {
+    let mut body = Vec::with_capacity(response.content_length().unwrap_or(0) as usize);
+    while let Some(chunk) = response.chunk().await? {
+        limiter.take(chunk.len()).await;
+        body.extend_from_slice(&chunk);
+        pb.inc(chunk.len() as u64);
+    }
+    Ok(body)
+}
+
+/// A `--limit-rate` value in KB/s: `500`, `500k` or `2m`. `0` is no limit.
+pub fn parse_rate(value: &str) -> Result<u64> {
+    let value = value.trim().to_ascii_lowercase();
+    let (number, multiplier) = match value.strip_suffix('m') {
+        Some(number) => (number, 1024),
+        None => (value.strip_suffix('k').unwrap_or(&value), 1),
+    };
+    number
+        .parse::<u64>()
+        .ok()
+        .and_then(|number| number.checked_mul(multiplier))
+        .ok_or_else(|| anyhow::anyhow!("Invalid download limit '{}' (expected KB/s, e.g. 500, 500k or 2m)", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(kbps: u64) -> RateLimiter {
+        RateLimiter::new(&Config {
+            max_download_speed_kbps: kbps,
+            ..Config::default()
+        })
+    }
+
+    #[test]
+    fn parses_rates_in_kilobytes() {
+        assert_eq!(parse_rate("500").unwrap(), 500);
+        assert_eq!(parse_rate("500k").unwrap(), 500);
+        assert_eq!(parse_rate(" 500K ").unwrap(), 500);
+        assert_eq!(parse_rate("2m").unwrap(), 2048);
+        assert_eq!(parse_rate("0").unwrap(), 0);
+        assert_eq!(parse_rate("0m").unwrap(), 0);
+    }
+
+    #[test]
+    fn rejects_what_isnt_a_rate() {
+        for value in ["", "k", "fast", "-5", "1.5m", "5g", "500kb", "18446744073709551615m"] {
+            assert!(parse_rate(value).is_err(), "{}", value);
+        }
+    }
+
+    #[tokio::test]
+    async fn zero_is_no_limit() {
+        let limiter = limiter(0);
+        let started = Instant::now();
+        for _ in 0..100 {
+            limiter.take(10 * 1024 * 1024).await;
+        }
+        assert!(started.elapsed() < Duration::from_millis(100));
+    }
+
+    #[tokio::test]
+    async fn paces_past_the_burst() {
+        // 100 KB/s: the first second's worth goes at once, the next half
+        // second's worth waits for it
+        let limiter = limiter(100);
+        let started = Instant::now();
+        limiter.take(100 * 1024).await;
+        assert!(started.elapsed() < Duration::from_millis(100));
+        limiter.take(50 * 1024).await;
+        let elapsed = started.elapsed();
+        assert!(elapsed >= Duration::from_millis(450), "{:?}", elapsed);
+        assert!(elapsed < Duration::from_millis(900), "{:?}", elapsed);
+    }
+}
@@ -0,0 +1,263 @@
+//! The launcher's end of the log channel to the game it launched.
+//!
+//! Before the game starts the launcher listens on a named pipe (a Unix
+//! socket elsewhere) and passes its name in `AAA_LAUNCHER_PIPE`. The game
+//! connects and writes one JSON message per line: its log lines and, if it
+//! gives up, a `fatal` message saying why. The launcher stays up until the
+//! game exits, copies the lines to `game.log` (and the window, if one is
+//! open) and, when the game dies, says what to do about it.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::process::{Child, ExitStatus};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::sync::mpsc;
+
+use crate::config::Config;
+use crate::logging;
+
+/// Environment variable the game finds the channel in.
+pub const LAUNCHER_PIPE_ENV: &str = "AAA_LAUNCHER_PIPE";
+
+/// How often the game is checked for having exited.
+const EXIT_POLL_INTERVAL: Duration = Duration::from_millis(250);
+/// Game log lines quoted when it exits with an error.
+const TAIL_LINES: usize = 15;
+
+/// One line from the game.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum GameMessage {
+    Log { level: String, target: String, message: String },
+    /// The game is exiting because of `reason`, e.g. `atom_verification_failed`.
+    Fatal { reason: String, message: String },
+}
+
+/// What to tell the player for each `fatal` reason the game sends.
+fn guidance(reason: &str) -> &'static [&'static str] {
+    match reason {
+        "atom_verification_failed" => &[
+            "The Atom renderer didn't start on this machine. To fix it:",
+            "  1. Update your graphics driver; Atom needs Vulkan 1.3",
+            "  2. Run vulkaninfo from the Vulkan SDK and check it lists your GPU",
+            "  3. Set \"force_rebuild\": true in launcher_config.json and run the launcher again,",
+            "     in case the Render Fabric build is out of date",
+        ],
+        "crashed" => &[
+            "The game crashed and saved a crash report.",
+            "The launcher offers to send it to the developers the next time it starts.",
+        ],
+        _ => &["The game reported a fatal error. The lines above and game.log say more."],
+    }
+}
+
+/// How the game ended.
+pub enum GameExit {
+    Closed,
+    /// It exited with an error code or was killed.
+    Crashed(ExitStatus),
+    /// It gave up and said why.
+    Fatal { reason: String, message: String },
+}
+
+impl GameExit {
+    pub fn from_status(status: ExitStatus) -> Self {
+        if status.success() {
+            GameExit::Closed
+        } else {
+            GameExit::Crashed(status)
+        }
+    }
+
+    /// Whether starting the game again might help: not when it stopped
+    /// itself over something like a renderer that won't start.
+    pub fn restartable(&self) -> bool {
+        match self {
+            GameExit::Closed => false,
+            GameExit::Crashed(_) => true,
+            GameExit::Fatal { reason, .. } => reason == "crashed",
+        }
+    }
+
+    pub fn into_result(self) -> Result<()> {
+        match self {
+            GameExit::Closed => Ok(()),
+            GameExit::Crashed(status) => anyhow::bail!("Game exited ({})", status),
+            GameExit::Fatal { reason, message } => anyhow::bail!("Game stopped: {} ({})", message, reason),
+        }
+    }
+}
+
+pub struct GameLink {
+    name: String,
+    messages: mpsc::UnboundedReceiver<String>,
+    reader: tokio::task::JoinHandle<()>,
+    log_path: PathBuf,
+}
+
+impl GameLink {
+    /// Starts listening; the game is told [`GameLink::name`] when it's launched.
+    pub fn listen(config: &Config) -> Result<Self> {
+        let name = pipe_name();
+        let (sender, messages) = mpsc::unbounded_channel();
+        let connection = accept(&name).with_context(|| format!("Failed to listen on {}", name))?;
+        let reader = tokio::spawn(async move {
+            let Ok(stream) = connection.await else {
+                return;
+            };
+            let mut lines = BufReader::new(stream).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(Self {
+            name,
+            messages,
+            reader,
+            log_path: config.logs_dir().join("game.log"),
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Relays the game's log until it exits, with guidance if it reported a
+    /// fatal error or exited with one.
+    pub async fn supervise(mut self, mut game: Child) -> Result<GameExit> {
+        let mut log = std::fs::File::create(&self.log_path)
+            .with_context(|| format!("Failed to create {}", self.log_path.display()))?;
+        let mut tail = VecDeque::new();
+        let mut fatal = None;
+
+        let status = loop {
+            tokio::select! {
+                Some(line) = self.messages.recv() => {
+                    self.handle(&line, &mut log, &mut tail, &mut fatal);
+                }
+                _ = tokio::time::sleep(EXIT_POLL_INTERVAL) => {
+                    if let Some(status) = game.try_wait().context("Failed to check on the game")? {
+                        break status;
+                    }
+                }
+            }
+        };
+
+        // Whatever the game wrote just before exiting
+        tokio::time::sleep(EXIT_POLL_INTERVAL).await;
+        while let Ok(line) = self.messages.try_recv() {
+            self.handle(&line, &mut log, &mut tail, &mut fatal);
+        }
+        self.reader.abort();
+        cleanup(&self.name);
+
+        Ok(self.report(status, fatal, &tail))
+    }
+
+    fn handle(
+        &self,
+        line: &str,
+        log: &mut std::fs::File,
+        tail: &mut VecDeque<String>,
+        fatal: &mut Option<(String, String)>,
+    ) {
+        let text = match serde_json::from_str::<GameMessage>(line) {
+            Ok(GameMessage::Log { level, target, message }) => format!("{:5} {}: {}", level, target, message),
+            Ok(GameMessage::Fatal { reason, message }) => {
+                let text = format!("FATAL {}: {}", reason, message);
+                *fatal = Some((reason, message));
+                text
+            }
+            Err(_) => line.to_string(),
+        };
+        let _ = writeln!(log, "{}", text);
+        // The console already shows the game's own output
+        if logging::attached() {
+            logging::output(&text);
+        }
+        tail.push_back(text);
+        if tail.len() > TAIL_LINES {
+            tail.pop_front();
+        }
+    }
+
+    fn report(&self, status: ExitStatus, fatal: Option<(String, String)>, tail: &VecDeque<String>) -> GameExit {
+        if fatal.is_none() && status.success() {
+            logging::success(&format!("Game closed ({})", status));
+            return GameExit::Closed;
+        }
+
+        logging::error(&format!("The game exited ({})", status));
+        if !logging::attached() && !tail.is_empty() {
+            logging::info("Last game log lines:");
+            for line in tail {
+                logging::output(line);
+            }
+        }
+        if let Some((reason, _)) = &fatal {
+            for line in guidance(reason) {
+                logging::warn(line);
+            }
+        }
+        logging::info(&format!("Full game log: {}", self.log_path.display()));
+
+        match fatal {
+            Some((reason, message)) => GameExit::Fatal { reason, message },
+            None => GameExit::Crashed(status),
+        }
+    }
+}
+
+#[cfg(windows)]
+fn pipe_name() -> String {
+    format!(r"\\.\pipe\aaa-launcher-{}", std::process::id())
+}
+
+#[cfg(not(windows))]
+fn pipe_name() -> String {
+    std::env::temp_dir()
+        .join(format!("aaa-launcher-{}.sock", std::process::id()))
+        .to_string_lossy()
+        .into_owned()
+}
+
+type Stream = Box<dyn AsyncRead + Unpin + Send>;
+type Connection = Pin<Box<dyn Future<Output = io::Result<Stream>> + Send>>;
+
+/// Creates the pipe now and returns a future for the game connecting to it.
+#[cfg(windows)]
+fn accept(name: &str) -> io::Result<Connection> {
+    use tokio::net::windows::named_pipe::ServerOptions;
+
+    let server = ServerOptions::new().first_pipe_instance(true).access_outbound(false).create(name)?;
+    Ok(Box::pin(async move {
+        server.connect().await?;
+        Ok(Box::new(server) as Stream)
+    }))
+}
+
+#[cfg(not(windows))]
+fn accept(name: &str) -> io::Result<Connection> {
+    cleanup(name);
+    let listener = tokio::net::UnixListener::bind(name)?;
+    Ok(Box::pin(async move {
+        let (stream, _) = listener.accept().await?;
+        Ok(Box::new(stream) as Stream)
+    }))
+}
+
+#[cfg(windows)]
+fn cleanup(_name: &str) {}
+
+#[cfg(not(windows))]
+fn cleanup(name: &str) {
+    let _ = std::fs::remove_file(name);
+}
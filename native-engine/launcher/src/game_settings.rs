@@ -0,0 +1,158 @@
+//! `game_settings.toml` in the engine directory: resolution, window mode,
+//! renderer and quality for the game to start with. The game reads it
+//! before opening its window, so a setting that leaves the screen black can
+//! be changed here instead of in the game.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::config::Config;
+
+pub const GAME_SETTINGS_FILE: &str = "game_settings.toml";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DisplayMode {
+    #[default]
+    Windowed,
+    Borderless,
+    Fullscreen,
+}
+
+#[cfg(feature = "gui")]
+impl DisplayMode {
+    pub const ALL: [DisplayMode; 3] = [DisplayMode::Windowed, DisplayMode::Borderless, DisplayMode::Fullscreen];
+}
+
+#[cfg(feature = "gui")]
+impl fmt::Display for DisplayMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DisplayMode::Windowed => write!(f, "Windowed"),
+            DisplayMode::Borderless => write!(f, "Borderless fullscreen"),
+            DisplayMode::Fullscreen => write!(f, "Fullscreen"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RendererChoice {
+    #[default]
+    Auto,
+    Atom,
+    Wgpu,
+}
+
+#[cfg(feature = "gui")]
+impl RendererChoice {
+    pub const ALL: [RendererChoice; 3] = [RendererChoice::Auto, RendererChoice::Atom, RendererChoice::Wgpu];
+}
+
+impl fmt::Display for RendererChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RendererChoice::Auto => write!(f, "Automatic"),
+            RendererChoice::Atom => write!(f, "Atom"),
+            RendererChoice::Wgpu => write!(f, "wgpu (compatibility)"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QualityPreset {
+    Low,
+    Medium,
+    #[default]
+    High,
+    Ultra,
+}
+
+#[cfg(feature = "gui")]
+impl QualityPreset {
+    pub const ALL: [QualityPreset; 4] =
+        [QualityPreset::Low, QualityPreset::Medium, QualityPreset::High, QualityPreset::Ultra];
+}
+
+impl fmt::Display for QualityPreset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DisplaySettings {
+    pub width: u32,
+    pub height: u32,
+    pub mode: DisplayMode,
+    pub vsync: bool,
+}
+
+impl Default for DisplaySettings {
+    fn default() -> Self {
+        Self {
+            width: 1920,
+            height: 1080,
+            mode: DisplayMode::Windowed,
+            vsync: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GraphicsSettings {
+    pub renderer: RendererChoice,
+    pub quality: QualityPreset,
+}
+
+/// Start the game without a window for `ticks` ticks, for testing.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HeadlessSettings {
+    pub enabled: bool,
+    pub ticks: u32,
+}
+
+impl Default for HeadlessSettings {
+    fn default() -> Self {
+        Self { enabled: false, ticks: 100 }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameSettings {
+    pub display: DisplaySettings,
+    pub graphics: GraphicsSettings,
+    pub headless: HeadlessSettings,
+}
+
+impl GameSettings {
+    pub fn path(config: &Config) -> PathBuf {
+        config.engine_dir().join(GAME_SETTINGS_FILE)
+    }
+
+    /// The saved settings, or the defaults if there are none yet.
+    pub fn load(config: &Config) -> Result<Self> {
+        let path = Self::path(config);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let text = std::fs::read_to_string(&path)?;
+        toml::from_str(&text).with_context(|| format!("Invalid game settings file {}", path.display()))
+    }
+
+    pub fn save(&self, config: &Config) -> Result<()> {
+        let path = Self::path(config);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, toml::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
@@ -0,0 +1,116 @@
+//! Starts the game and stays with it until it exits.
+//!
+//! The game's stdout and stderr go to `game-output.log` and the console as
+//! they come (the log it sends over the [`GameLink`] goes to `game.log`),
+//! and how it ended is reported. A game that crashes is started again up to
+//! `game_restarts` times; one that stopped itself with a fatal error isn't,
+//! since it would only stop again. With `supervise_game` off, the launcher
+//! is done as soon as the game is running.
+
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::Child;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::config::{Config, LaunchProfile};
+use crate::game_link::{GameExit, GameLink};
+use crate::logging;
+use crate::orchestrator::BuildOrchestrator;
+
+/// How often a game without the log channel is checked for having exited.
+const EXIT_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+pub async fn run(config: &Config, launch: &LaunchProfile) -> Result<()> {
+    let orchestrator = BuildOrchestrator::new(config.clone());
+    if !config.supervise_game {
+        orchestrator.launch_game(launch, None, false)?;
+        logging::success("Game launched");
+        return Ok(());
+    }
+
+    let output_path = config.logs_dir().join("game-output.log");
+    let output = File::create(&output_path).with_context(|| format!("Failed to create {}", output_path.display()))?;
+    let output = Arc::new(Mutex::new(output));
+
+    let mut restarts = 0;
+    loop {
+        // The game still runs without the log channel, with only its output to go on
+        let link = match GameLink::listen(config) {
+            Ok(link) => Some(link),
+            Err(e) => {
+                logging::warn(&format!("Can't follow the game's log: {:#}", e));
+                None
+            }
+        };
+        let mut game = orchestrator.launch_game(launch, link.as_ref().map(GameLink::name), true)?;
+        logging::success("Game launched");
+        capture_output(&mut game, &output);
+
+        let exit = match link {
+            Some(link) => {
+                logging::info("Following the game's log until it closes...");
+                link.supervise(game).await?
+            }
+            None => {
+                logging::info("Waiting for the game to close...");
+                wait(game).await?
+            }
+        };
+
+        if !exit.restartable() || restarts == config.game_restarts {
+            if !matches!(exit, GameExit::Closed) {
+                logging::info(&format!("Game output: {}", output_path.display()));
+            }
+            return exit.into_result();
+        }
+        restarts += 1;
+
+        // The next run's game.log would replace the one that says why it crashed
+        let game_log = config.logs_dir().join("game.log");
+        let kept = config.logs_dir().join(format!("game-crash-{}.log", restarts));
+        if std::fs::rename(&game_log, &kept).is_ok() {
+            logging::info(&format!("Game log kept as {}", kept.display()));
+        }
+        if let Ok(mut output) = output.lock() {
+            let _ = writeln!(output, "--- Restart {} of {} ---", restarts, config.game_restarts);
+        }
+        logging::warn(&format!("Starting the game again ({} of {})", restarts, config.game_restarts));
+    }
+}
+
+/// Copies the game's stdout and stderr to `log`, and on to the console
+/// where they'd have gone anyway.
+fn capture_output(game: &mut Child, log: &Arc<Mutex<File>>) {
+    let stdout = game.stdout.take().map(|s| Box::new(s) as Box<dyn Read + Send>);
+    let stderr = game.stderr.take().map(|s| Box::new(s) as Box<dyn Read + Send>);
+    for stream in [stdout, stderr].into_iter().flatten() {
+        let log = Arc::clone(log);
+        std::thread::spawn(move || {
+            for line in BufReader::new(stream).lines().map_while(|line| line.ok()) {
+                println!("{}", line);
+                if let Ok(mut log) = log.lock() {
+                    let _ = writeln!(log, "{}", line);
+                }
+            }
+        });
+    }
+}
+
+/// Waits for a game that isn't sending its log, and says how it ended.
+async fn wait(mut game: Child) -> Result<GameExit> {
+    let status = loop {
+        if let Some(status) = game.try_wait().context("Failed to check on the game")? {
+            break status;
+        }
+        tokio::time::sleep(EXIT_POLL_INTERVAL).await;
+    };
+    let exit = GameExit::from_status(status);
+    if matches!(exit, GameExit::Closed) {
+        logging::success(&format!("Game closed ({})", status));
+    } else {
+        logging::error(&format!("The game exited ({})", status));
+    }
+    Ok(exit)
+}
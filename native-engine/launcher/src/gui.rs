@@ -0,0 +1,432 @@
+//! The windowed frontend (`--gui`, and the default when the launcher is
+//! started by double-clicking it on Windows).
+//!
+//! The launcher runs exactly as it does on the console; this window only
+//! listens. [`logging::attach`] hands it every step, dependency, progress bar
+//! and log line, and tools run by the build are piped through the log so
+//! their output shows under "Details" instead of in a console.
+//!
+//! The "Game settings" tab edits `game_settings.toml`, which the game reads
+//! when it starts.
+
+use anyhow::Result;
+use eframe::egui;
+use indicatif::{HumanBytes, ProgressBar};
+use std::future::Future;
+use std::sync::mpsc::{self, Receiver};
+use std::time::Duration;
+
+use crate::config::Config;
+use crate::game_settings::{DisplayMode, GameSettings, QualityPreset, RendererChoice};
+use crate::logging::{self, Level, LogEvent};
+use crate::state_machine::LauncherState;
+
+/// Lines kept under "Details".
+const MAX_LINES: usize = 5000;
+
+/// Offered on the settings tab; any other size can be typed in.
+const RESOLUTIONS: [(u32, u32); 6] = [(1280, 720), (1366, 768), (1600, 900), (1920, 1080), (2560, 1440), (3840, 2160)];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Tab {
+    Setup,
+    GameSettings,
+}
+
+/// `game_settings.toml` as edited on the settings tab.
+struct SettingsTab {
+    config: Config,
+    settings: GameSettings,
+    /// As last loaded or saved, to tell whether there's anything to save.
+    saved: GameSettings,
+    status: Option<(Level, String)>,
+}
+
+impl SettingsTab {
+    fn new(config: Config) -> Self {
+        let (settings, status) = match GameSettings::load(&config) {
+            Ok(settings) => (settings, None),
+            Err(e) => (GameSettings::default(), Some((Level::Warn, format!("{:#} - showing the defaults", e)))),
+        };
+        Self {
+            config,
+            saved: settings.clone(),
+            settings,
+            status,
+        }
+    }
+
+    fn save(&mut self) {
+        self.status = Some(match self.settings.save(&self.config) {
+            Ok(()) => {
+                self.saved = self.settings.clone();
+                (Level::Success, "Saved. The game uses these the next time it starts.".to_string())
+            }
+            Err(e) => (Level::Error, format!("{:#}", e)),
+        });
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui) {
+        let settings = &mut self.settings;
+        ui.heading("Display");
+        egui::Grid::new("display").num_columns(2).spacing([12.0, 6.0]).show(ui, |ui| {
+            ui.label("Resolution");
+            ui.horizontal(|ui| {
+                let current = format!("{} x {}", settings.display.width, settings.display.height);
+                egui::ComboBox::from_id_salt("resolution").selected_text(current).show_ui(ui, |ui| {
+                    for (width, height) in RESOLUTIONS {
+                        let selected = (settings.display.width, settings.display.height) == (width, height);
+                        if ui.selectable_label(selected, format!("{} x {}", width, height)).clicked() {
+                            settings.display.width = width;
+                            settings.display.height = height;
+                        }
+                    }
+                });
+                ui.add(egui::DragValue::new(&mut settings.display.width).range(640..=7680));
+                ui.label("x");
+                ui.add(egui::DragValue::new(&mut settings.display.height).range(360..=4320));
+            });
+            ui.end_row();
+
+            ui.label("Window");
+            egui::ComboBox::from_id_salt("mode")
+                .selected_text(settings.display.mode.to_string())
+                .show_ui(ui, |ui| {
+                    for mode in DisplayMode::ALL {
+                        ui.selectable_value(&mut settings.display.mode, mode, mode.to_string());
+                    }
+                });
+            ui.end_row();
+
+            ui.label("");
+            ui.checkbox(&mut settings.display.vsync, "Vertical sync");
+            ui.end_row();
+        });
+        if settings.display.mode == DisplayMode::Fullscreen {
+            ui.weak("A black screen in fullscreen usually means the monitor doesn't take this resolution; \
+                     try borderless fullscreen.");
+        }
+
+        ui.add_space(12.0);
+        ui.heading("Graphics");
+        egui::Grid::new("graphics").num_columns(2).spacing([12.0, 6.0]).show(ui, |ui| {
+            ui.label("Renderer");
+            egui::ComboBox::from_id_salt("renderer")
+                .selected_text(settings.graphics.renderer.to_string())
+                .show_ui(ui, |ui| {
+                    for renderer in RendererChoice::ALL {
+                        ui.selectable_value(&mut settings.graphics.renderer, renderer, renderer.to_string());
+                    }
+                });
+            ui.end_row();
+
+            ui.label("Quality");
+            egui::ComboBox::from_id_salt("quality")
+                .selected_text(settings.graphics.quality.to_string())
+                .show_ui(ui, |ui| {
+                    for quality in QualityPreset::ALL {
+                        ui.selectable_value(&mut settings.graphics.quality, quality, quality.to_string());
+                    }
+                });
+            ui.end_row();
+        });
+
+        ui.add_space(12.0);
+        egui::CollapsingHeader::new("Testing").default_open(settings.headless.enabled).show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut settings.headless.enabled, "Start without a window for");
+                let ticks = egui::DragValue::new(&mut settings.headless.ticks).range(1..=1_000_000);
+                ui.add_enabled(settings.headless.enabled, ticks);
+                ui.label("ticks");
+            });
+        });
+
+        ui.add_space(12.0);
+        ui.horizontal(|ui| {
+            let changed = self.settings != self.saved;
+            if ui.add_enabled(changed, egui::Button::new("Save")).clicked() {
+                self.save();
+            }
+            if ui.button("Reset to defaults").clicked() {
+                self.settings = GameSettings::default();
+            }
+            if changed {
+                ui.weak("Unsaved changes");
+            }
+        });
+        if let Some((level, text)) = &self.status {
+            ui.colored_label(LauncherApp::color(*level), text);
+        }
+        ui.weak(GameSettings::path(&self.config).display().to_string());
+    }
+}
+
+struct Dependency {
+    name: String,
+    installed: bool,
+    version: Option<String>,
+}
+
+struct LauncherApp {
+    events: Receiver<LogEvent>,
+    steps: Vec<LauncherState>,
+    step: u8,
+    /// Steps in the run, as the state machine counts them.
+    step_total: u8,
+    step_message: String,
+    dependencies: Vec<Dependency>,
+    progress: Vec<ProgressBar>,
+    lines: Vec<(Level, String)>,
+    /// The last warning or error, shown under the heading.
+    last_problem: Option<(Level, String)>,
+    finished: Option<std::result::Result<(), String>>,
+    tab: Tab,
+    /// Missing if the launcher config couldn't be read.
+    settings: Option<SettingsTab>,
+}
+
+impl LauncherApp {
+    fn new(events: Receiver<LogEvent>, config: Option<Config>) -> Self {
+        let mut steps = Vec::new();
+        let mut state = LauncherState::Init;
+        while let Some(next) = state.next() {
+            if next != LauncherState::Complete {
+                steps.push(next);
+            }
+            state = next;
+        }
+        Self {
+            events,
+            step_total: steps.len() as u8,
+            steps,
+            step: 0,
+            step_message: LauncherState::Init.to_string(),
+            dependencies: Vec::new(),
+            progress: Vec::new(),
+            lines: Vec::new(),
+            last_problem: None,
+            finished: None,
+            tab: Tab::Setup,
+            settings: config.map(SettingsTab::new),
+        }
+    }
+
+    fn receive(&mut self) {
+        while let Ok(event) = self.events.try_recv() {
+            match event {
+                LogEvent::Step { number, total, message } => {
+                    self.step = number;
+                    self.step_total = total;
+                    self.step_message = message;
+                }
+                LogEvent::Line(level, text) => {
+                    if matches!(level, Level::Warn | Level::Error) && !text.trim().is_empty() {
+                        self.last_problem = Some((level, text.clone()));
+                    }
+                    self.lines.push((level, text));
+                    if self.lines.len() > MAX_LINES {
+                        self.lines.drain(..self.lines.len() - MAX_LINES);
+                    }
+                }
+                LogEvent::Dependency { name, installed, version } => {
+                    let dependency = Dependency { name, installed, version };
+                    match self.dependencies.iter_mut().find(|d| d.name == dependency.name) {
+                        Some(existing) => *existing = dependency,
+                        None => self.dependencies.push(dependency),
+                    }
+                }
+                LogEvent::Progress(pb) => self.progress.push(pb),
+                LogEvent::Finished(result) => self.finished = Some(result),
+            }
+        }
+        self.progress.retain(|pb| !pb.is_finished());
+    }
+
+    fn color(level: Level) -> egui::Color32 {
+        match level {
+            Level::Success => egui::Color32::from_rgb(90, 200, 120),
+            Level::Warn => egui::Color32::from_rgb(230, 180, 60),
+            Level::Error => egui::Color32::from_rgb(230, 80, 80),
+            Level::Info | Level::Download | Level::Output => egui::Color32::GRAY,
+        }
+    }
+
+    fn steps_ui(&self, ui: &mut egui::Ui) {
+        let failed = matches!(self.finished, Some(Err(_)));
+        let done = matches!(self.finished, Some(Ok(())));
+        for state in &self.steps {
+            let number = state.step_number();
+            let (mark, color) = if done || number < self.step {
+                ("✔", Self::color(Level::Success))
+            } else if number == self.step && failed {
+                ("✖", Self::color(Level::Error))
+            } else if number == self.step {
+                ("▶", egui::Color32::WHITE)
+            } else {
+                ("○", egui::Color32::DARK_GRAY)
+            };
+            ui.horizontal(|ui| {
+                ui.colored_label(color, mark);
+                let text = egui::RichText::new(state.to_string()).color(color);
+                ui.label(if number == self.step { text.strong() } else { text });
+            });
+        }
+    }
+
+    fn dependencies_ui(&self, ui: &mut egui::Ui) {
+        egui::Grid::new("dependencies").striped(true).show(ui, |ui| {
+            for dependency in &self.dependencies {
+                ui.label(&dependency.name);
+                if dependency.installed {
+                    let version = dependency.version.as_deref().unwrap_or("installed");
+                    ui.colored_label(Self::color(Level::Success), version);
+                } else {
+                    ui.colored_label(Self::color(Level::Warn), "missing");
+                }
+                ui.end_row();
+            }
+        });
+    }
+
+    fn progress_ui(&self, ui: &mut egui::Ui) {
+        for pb in &self.progress {
+            let position = pb.position();
+            let bar = match pb.length().filter(|length| *length > 0) {
+                Some(length) => egui::ProgressBar::new(position as f32 / length as f32)
+                    .text(format!("{} / {}", HumanBytes(position), HumanBytes(length))),
+                None => egui::ProgressBar::new(0.0).text(HumanBytes(position).to_string()).animate(true),
+            };
+            ui.add(bar);
+        }
+    }
+
+    fn details_ui(&self, ui: &mut egui::Ui) {
+        let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+        egui::ScrollArea::vertical()
+            .stick_to_bottom(true)
+            .auto_shrink([false, false])
+            .show_rows(ui, row_height, self.lines.len(), |ui, rows| {
+                for (level, text) in &self.lines[rows] {
+                    ui.label(egui::RichText::new(text).monospace().color(Self::color(*level)));
+                }
+            });
+    }
+}
+
+impl eframe::App for LauncherApp {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.receive();
+
+        egui::TopBottomPanel::bottom("status").show(ctx, |ui| {
+            ui.add_space(6.0);
+            match &self.finished {
+                None => {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        if self.step > 0 {
+                            ui.weak(format!("Step {}/{}", self.step, self.step_total));
+                        }
+                        ui.label(&self.step_message);
+                    });
+                }
+                Some(Ok(())) => {
+                    ui.horizontal(|ui| {
+                        ui.colored_label(Self::color(Level::Success), "All done.");
+                        if ui.button("Close").clicked() {
+                            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                        }
+                    });
+                }
+                Some(Err(error)) => {
+                    ui.colored_label(Self::color(Level::Error), format!("Something went wrong: {}", error));
+                    ui.horizontal(|ui| {
+                        ui.weak("Details has the full log.");
+                        if ui.button("Close").clicked() {
+                            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                        }
+                    });
+                }
+            }
+            ui.add_space(6.0);
+        });
+
+        egui::SidePanel::left("steps").resizable(false).show(ctx, |ui| {
+            ui.heading("Setup");
+            ui.add_space(8.0);
+            self.steps_ui(ui);
+            if !self.dependencies.is_empty() {
+                ui.add_space(12.0);
+                ui.heading("Dependencies");
+                ui.add_space(4.0);
+                self.dependencies_ui(ui);
+            }
+        });
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            if self.settings.is_some() {
+                ui.horizontal(|ui| {
+                    ui.selectable_value(&mut self.tab, Tab::Setup, "Setup");
+                    ui.selectable_value(&mut self.tab, Tab::GameSettings, "Game settings");
+                });
+                ui.separator();
+            }
+            match (self.tab, &mut self.settings) {
+                (Tab::GameSettings, Some(settings)) => {
+                    egui::ScrollArea::vertical().show(ui, |ui| settings.ui(ui));
+                }
+                _ => {
+                    ui.heading(&self.step_message);
+                    if let Some((level, text)) = &self.last_problem {
+                        ui.colored_label(Self::color(*level), text);
+                    }
+                    ui.add_space(8.0);
+                    self.progress_ui(ui);
+                    ui.add_space(8.0);
+                    egui::CollapsingHeader::new("Details").default_open(false).show(ui, |ui| self.details_ui(ui));
+                }
+            }
+        });
+
+        // Progress moves without any input, so keep redrawing
+        ctx.request_repaint_after(Duration::from_millis(100));
+    }
+}
+
+/// Runs `work` behind the window until the window is closed. The result is
+/// `work`'s if it finished by then. With `config`, the window also has the
+/// game settings tab.
+pub async fn run<F>(config: Option<Config>, work: F) -> Result<()>
+where
+    F: Future<Output = Result<()>> + Send + 'static,
+{
+    let (sender, events) = mpsc::channel();
+    logging::attach(sender.clone());
+
+    let task = tokio::spawn(async move {
+        let result = work.await;
+        let outcome = result.as_ref().map(|_| ()).map_err(|e| format!("{:#}", e));
+        let _ = sender.send(LogEvent::Finished(outcome));
+        result
+    });
+
+    let options = eframe::NativeOptions {
+        viewport: egui::ViewportBuilder::default()
+            .with_title("AAA MMORPG Engine Launcher")
+            .with_inner_size([820.0, 540.0])
+            .with_min_inner_size([560.0, 360.0]),
+        ..Default::default()
+    };
+    eframe::run_native(
+        "AAA MMORPG Engine Launcher",
+        options,
+        Box::new(|_cc| Ok(Box::new(LauncherApp::new(events, config)))),
+    )
+    .map_err(|e| anyhow::anyhow!("Could not open the launcher window: {}", e))?;
+
+    if task.is_finished() {
+        task.await?
+    } else {
+        // Closed mid-run; the state machine picks up from this step next time
+        Ok(())
+    }
+}
@@ -0,0 +1,44 @@
+//! What every HTTP client the launcher makes has in common: the proxy and
+//! the extra CA certificates from the config.
+//!
+//! With no `proxy` set, reqwest already follows `HTTP_PROXY`, `HTTPS_PROXY`,
+//! `ALL_PROXY` and `NO_PROXY` (and the system proxy on Windows), so most
+//! corporate setups work without touching the config. `proxy: "none"`
+//! ignores all of those.
+
+use anyhow::{Context, Result};
+
+use crate::config::Config;
+
+/// A client builder with the config's network settings; callers add their
+/// own timeouts.
+pub fn client_builder(config: &Config) -> Result<reqwest::ClientBuilder> {
+    let mut builder = reqwest::Client::builder();
+
+    match config.proxy.trim() {
+        "" => {}
+        "none" => builder = builder.no_proxy(),
+        url => {
+            let no_proxy = match config.no_proxy.trim() {
+                "" => reqwest::NoProxy::from_env(),
+                hosts => reqwest::NoProxy::from_string(hosts),
+            };
+            let proxy = reqwest::Proxy::all(url).with_context(|| format!("Invalid proxy {}", url))?;
+            builder = builder.proxy(proxy.no_proxy(no_proxy));
+        }
+    }
+
+    if let Some(bundle) = &config.ca_bundle {
+        let pem = std::fs::read(bundle).with_context(|| format!("Failed to read CA bundle {}", bundle.display()))?;
+        let certificates = reqwest::Certificate::from_pem_bundle(&pem)
+            .with_context(|| format!("Invalid CA bundle {}", bundle.display()))?;
+        if certificates.is_empty() {
+            anyhow::bail!("CA bundle {} has no certificates", bundle.display());
+        }
+        for certificate in certificates {
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+
+    Ok(builder)
+}
@@ -0,0 +1,192 @@
+//! One launcher at a time per install directory.
+//!
+//! Two launchers syncing at once overwrite each other's state file and
+//! downloads. The first one to start holds an OS lock on `launcher.lock`
+//! until it exits; the OS drops the lock if it crashes, so a dead launcher
+//! never blocks the next one. `launcher.pid` says who holds it, for the
+//! message the second one shows. `--force` stops a launcher that's still
+//! running but stuck, and takes over once it's gone. It only stops a pid
+//! that's running the launcher's own executable, so a stale `launcher.pid`
+//! whose pid was reused never gets another program killed.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions, TryLockError};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::config::Config;
+use crate::logging;
+
+const LOCK_FILE: &str = "launcher.lock";
+const OWNER_FILE: &str = "launcher.pid";
+
+/// How long `--force` waits for the old launcher to let go.
+const TAKEOVER_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Owner {
+    pid: u32,
+    started_at: String,
+    version: String,
+}
+
+/// Held for as long as the launcher runs; dropping it releases the lock.
+pub struct InstanceLock {
+    _file: File,
+    owner_path: PathBuf,
+}
+
+impl InstanceLock {
+    /// Takes the lock, or fails naming the launcher that has it. With
+    /// `force`, that launcher is stopped first.
+    pub fn acquire(config: &Config, force: bool) -> Result<Self> {
+        std::fs::create_dir_all(&config.install_dir)?;
+        let lock_path = config.install_dir.join(LOCK_FILE);
+        let owner_path = config.install_dir.join(OWNER_FILE);
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)
+            .with_context(|| format!("Failed to open {}", lock_path.display()))?;
+
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                // Read only once the lock is known to be held: it's the holder's
+                let owner = read_owner(&owner_path);
+                let described = describe(owner.as_ref());
+                if !force {
+                    anyhow::bail!(
+                        "Another launcher is already running{} - close it first, or run with --force if it's stuck",
+                        described
+                    );
+                }
+                let Some(owner) = owner.filter(|owner| is_launcher(owner.pid)) else {
+                    anyhow::bail!(
+                        "Another process holds {}{}, but it isn't a launcher this one can stop - close it first",
+                        lock_path.display(),
+                        described
+                    );
+                };
+                logging::warn(&format!("Stopping the launcher that holds the lock{}", described));
+                terminate(owner.pid)?;
+                wait_for_lock(&file)?;
+            }
+            Err(TryLockError::Error(e)) => {
+                return Err(e).with_context(|| format!("Failed to lock {}", lock_path.display()));
+            }
+        }
+
+        let owner = Owner {
+            pid: std::process::id(),
+            started_at: chrono::Local::now().to_rfc3339(),
+            version: crate::config::LAUNCHER_VERSION.to_string(),
+        };
+        std::fs::write(&owner_path, serde_json::to_string_pretty(&owner)?)?;
+
+        Ok(Self { _file: file, owner_path })
+    }
+}
+
+impl Drop for InstanceLock {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.owner_path);
+    }
+}
+
+fn read_owner(path: &Path) -> Option<Owner> {
+    serde_json::from_str(&std::fs::read_to_string(path).ok()?).ok()
+}
+
+fn describe(owner: Option<&Owner>) -> String {
+    match owner {
+        Some(owner) => format!(" (pid {}, v{}, started {})", owner.pid, owner.version, owner.started_at),
+        None => String::new(),
+    }
+}
+
+fn wait_for_lock(file: &File) -> Result<()> {
+    let deadline = Instant::now() + TAKEOVER_TIMEOUT;
+    loop {
+        match file.try_lock() {
+            Ok(()) => return Ok(()),
+            Err(TryLockError::WouldBlock) if Instant::now() < deadline => {
+                std::thread::sleep(Duration::from_millis(200));
+            }
+            Err(TryLockError::WouldBlock) => {
+                anyhow::bail!("The other launcher still holds the lock after {:?}", TAKEOVER_TIMEOUT)
+            }
+            Err(TryLockError::Error(e)) => return Err(e.into()),
+        }
+    }
+}
+
+/// The file name of this launcher's executable.
+fn launcher_exe_name() -> Option<std::ffi::OsString> {
+    std::env::current_exe().ok()?.file_name().map(Into::into)
+}
+
+/// Whether `pid` is running the same executable as this launcher.
+#[cfg(windows)]
+fn is_launcher(pid: u32) -> bool {
+    let Some(name) = launcher_exe_name() else {
+        return false;
+    };
+    let Ok(output) = std::process::Command::new("tasklist")
+        .args(["/FI", &format!("PID eq {}", pid), "/FO", "CSV", "/NH"])
+        .output()
+    else {
+        return false;
+    };
+    // "aaa-launcher.exe","1234","Console","1","12,345 K"
+    let listing = String::from_utf8_lossy(&output.stdout);
+    let image = listing.lines().next().and_then(|line| line.split(',').next()).map(|field| field.trim_matches('"'));
+    image.is_some_and(|image| name.to_str().is_some_and(|name| image.eq_ignore_ascii_case(name)))
+}
+
+#[cfg(not(windows))]
+fn is_launcher(pid: u32) -> bool {
+    let running = std::fs::read_link(format!("/proc/{}/exe", pid)).ok();
+    let running = running.as_deref().and_then(Path::file_name);
+    running.is_some() && running == launcher_exe_name().as_deref()
+}
+
+#[cfg(windows)]
+fn terminate(pid: u32) -> Result<()> {
+    let status = std::process::Command::new("taskkill")
+        .args(["/PID", &pid.to_string(), "/F"])
+        .output()
+        .context("Failed to run taskkill")?
+        .status;
+    if !status.success() {
+        logging::warn(&format!("taskkill couldn't stop pid {}; waiting for the lock anyway", pid));
+    }
+    Ok(())
+}
+
+#[cfg(not(windows))]
+fn terminate(pid: u32) -> Result<()> {
+    let pid = libc::pid_t::try_from(pid).context("Invalid pid")?;
+    if unsafe { libc::kill(pid, libc::SIGTERM) } != 0 {
+        logging::warn(&format!(
+            "Couldn't stop pid {}: {}; waiting for the lock anyway",
+            pid,
+            std::io::Error::last_os_error()
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_launcher_processes_count_as_launchers() {
+        assert!(is_launcher(std::process::id()));
+        assert!(!is_launcher(1));
+        assert!(!is_launcher(u32::MAX));
+    }
+}
@@ -0,0 +1,107 @@
+//! `--install-from`: installing and updating from a local copy of what the
+//! server publishes, for machines without internet access.
+//!
+//! The source is a `manifest.json` as `--gen-manifest` writes it, plus the
+//! engine files: either under `files/` beside it (the layout of
+//! `/sync/file/<path>`) or in the full archive, `full.zip`. It can be given
+//! as that directory or as the `.zip` with the manifest next to it. Only
+//! files that differ from the manifest are copied, the same as an online
+//! sync. A `manifest.json.sig` is checked against the trusted release keys;
+//! an unsigned manifest is used with a warning.
+
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::logging;
+use crate::signing::{self, KeySignature, TrustedKeys};
+use crate::sync::FileManifest;
+
+const MANIFEST_FILE: &str = "manifest.json";
+const ARCHIVE_FILE: &str = "full.zip";
+
+pub struct LocalSource {
+    manifest_path: PathBuf,
+    files_dir: Option<PathBuf>,
+    archive: Option<zip::ZipArchive<File>>,
+}
+
+impl LocalSource {
+    pub fn open(path: &Path) -> Result<Self> {
+        let (dir, archive_path) = if path.is_dir() {
+            (path.to_path_buf(), path.join(ARCHIVE_FILE))
+        } else if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("zip")) {
+            (path.parent().unwrap_or(Path::new(".")).to_path_buf(), path.to_path_buf())
+        } else {
+            anyhow::bail!("{} is neither a directory nor a .zip archive", path.display());
+        };
+
+        let manifest_path = dir.join(MANIFEST_FILE);
+        if !manifest_path.exists() {
+            anyhow::bail!("No {} in {} - generate one with --gen-manifest", MANIFEST_FILE, dir.display());
+        }
+        let files_dir = Some(dir.join("files")).filter(|files| files.is_dir());
+        let archive = if archive_path.exists() {
+            let file =
+                File::open(&archive_path).with_context(|| format!("Failed to open {}", archive_path.display()))?;
+            Some(zip::ZipArchive::new(file).with_context(|| format!("Invalid archive {}", archive_path.display()))?)
+        } else {
+            None
+        };
+        if files_dir.is_none() && archive.is_none() {
+            anyhow::bail!("{} has neither a files/ directory nor {}", dir.display(), ARCHIVE_FILE);
+        }
+
+        Ok(Self {
+            manifest_path,
+            files_dir,
+            archive,
+        })
+    }
+
+    pub fn describe(&self) -> String {
+        self.manifest_path.parent().unwrap_or(Path::new(".")).display().to_string()
+    }
+
+    /// The manifest, once its signature checks out.
+    pub fn manifest(&self, config: &Config) -> Result<FileManifest> {
+        let text = std::fs::read(&self.manifest_path)
+            .with_context(|| format!("Failed to read {}", self.manifest_path.display()))?;
+
+        let signature_path = self.manifest_path.with_extension("json.sig");
+        if signature_path.exists() {
+            let signature: KeySignature = serde_json::from_str(&std::fs::read_to_string(&signature_path)?)
+                .with_context(|| format!("Invalid {}", signature_path.display()))?;
+            TrustedKeys::load(config)?
+                .verify(&signing::file_message(MANIFEST_FILE, &text), &signature.signature)
+                .with_context(|| format!("{} isn't signed by a trusted release key", self.manifest_path.display()))?;
+            logging::success("Manifest signature verified");
+        } else {
+            logging::warn(&format!(
+                "{} is unsigned - only install from a source you trust",
+                self.manifest_path.display()
+            ));
+        }
+
+        serde_json::from_slice(&text).with_context(|| format!("Invalid {}", self.manifest_path.display()))
+    }
+
+    /// The contents of `file_path` (as the manifest names it).
+    pub fn read(&mut self, file_path: &str) -> Result<Vec<u8>> {
+        if let Some(path) = self.files_dir.as_ref().map(|dir| dir.join(file_path)).filter(|path| path.exists()) {
+            return std::fs::read(&path).with_context(|| format!("Failed to read {}", path.display()));
+        }
+        let archive = self
+            .archive
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("{} is missing from the source", file_path))?;
+        let mut entry = archive
+            .by_name(file_path)
+            .with_context(|| format!("{} is missing from {}", file_path, ARCHIVE_FILE))?;
+        let mut bytes = Vec::with_capacity(entry.size() as usize);
+        entry.read_to_end(&mut bytes)?;
+        Ok(bytes)
+    }
+}
@@ -1,7 +1,9 @@
 use anyhow::Result;
 use console::{style, Emoji};
-use indicatif::{ProgressBar, ProgressStyle};
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
 use std::path::Path;
+use std::sync::mpsc::Sender;
+use std::sync::Mutex;
 use tracing_subscriber::{fmt, prelude::*, EnvFilter};
 
 static ROCKET: Emoji<'_, '_> = Emoji("🚀 ", "");
@@ -11,6 +13,58 @@ static WARN: Emoji<'_, '_> = Emoji("⚠️  ", "[WARN] ");
 static GEAR: Emoji<'_, '_> = Emoji("⚙️  ", "[...] ");
 static DOWNLOAD: Emoji<'_, '_> = Emoji("📥 ", "[DL] ");
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Info,
+    Success,
+    Warn,
+    Error,
+    Download,
+    /// A line of output from a build tool or installer.
+    Output,
+}
+
+/// Everything printed, as events for a frontend to show. The console
+/// output is unchanged while a frontend is attached.
+#[derive(Debug)]
+#[cfg_attr(not(feature = "gui"), allow(dead_code))]
+pub enum LogEvent {
+    Step { number: u8, total: u8, message: String },
+    Line(Level, String),
+    Dependency { name: String, installed: bool, version: Option<String> },
+    /// A new progress bar; the frontend reads its position as it moves.
+    Progress(ProgressBar),
+    /// The launcher run ended, with the error if it failed.
+    Finished(std::result::Result<(), String>),
+}
+
+static FRONTEND: Mutex<Option<Sender<LogEvent>>> = Mutex::new(None);
+
+/// Sends every log event to `sender` from now on.
+#[cfg_attr(not(feature = "gui"), allow(dead_code))]
+pub fn attach(sender: Sender<LogEvent>) {
+    if let Ok(mut frontend) = FRONTEND.lock() {
+        *frontend = Some(sender);
+    }
+}
+
+/// Whether a frontend is showing the log.
+pub fn attached() -> bool {
+    FRONTEND.lock().is_ok_and(|frontend| frontend.is_some())
+}
+
+pub fn emit(event: LogEvent) {
+    if let Ok(frontend) = FRONTEND.lock() {
+        if let Some(sender) = frontend.as_ref() {
+            let _ = sender.send(event);
+        }
+    }
+}
+
+fn line(level: Level, message: &str) {
+    emit(LogEvent::Line(level, message.to_string()));
+}
+
 pub fn init(logs_dir: &Path, verbose: bool) -> Result<()> {
     std::fs::create_dir_all(logs_dir)?;
     
@@ -61,36 +115,72 @@ pub fn step(number: u8, total: u8, message: &str) {
         GEAR,
         style(message).bold()
     );
+    emit(LogEvent::Step {
+        number,
+        total,
+        message: message.to_string(),
+    });
 }
 
 pub fn success(message: &str) {
     println!("       {}{}", CHECK, style(message).green());
+    line(Level::Success, message);
 }
 
 pub fn error(message: &str) {
     println!("       {}{}", CROSS, style(message).red());
+    line(Level::Error, message);
 }
 
 pub fn warn(message: &str) {
     println!("       {}{}", WARN, style(message).yellow());
+    line(Level::Warn, message);
 }
 
 pub fn info(message: &str) {
     println!("       {}", style(message).dim());
+    line(Level::Info, message);
 }
 
 pub fn download(message: &str) {
     println!("       {}{}", DOWNLOAD, message);
+    line(Level::Download, message);
+}
+
+/// A line of output from a tool the launcher runs.
+pub fn output(message: &str) {
+    println!("{}", message);
+    line(Level::Output, message);
+}
+
+/// One row of the dependency audit.
+pub fn dependency(name: &str, installed: bool, version: Option<&str>) {
+    if installed {
+        success(&format!("{}: {}", name, version.unwrap_or("unknown")));
+    } else {
+        warn(&format!("{}: NOT INSTALLED", name));
+    }
+    emit(LogEvent::Dependency {
+        name: name.to_string(),
+        installed,
+        version: version.map(str::to_string),
+    });
 }
 
 pub fn progress_bar(len: u64) -> ProgressBar {
-    let pb = ProgressBar::new(len);
+    // The frontend draws it instead
+    let pb = if attached() {
+        ProgressBar::with_draw_target(Some(len), ProgressDrawTarget::hidden())
+    } else {
+        ProgressBar::new(len)
+    };
     pb.set_style(
         ProgressStyle::default_bar()
             .template("       [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({eta})")
             .unwrap()
             .progress_chars("█▓░"),
     );
+    emit(LogEvent::Progress(pb.clone()));
     pb
 }
 
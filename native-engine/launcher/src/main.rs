@@ -1,20 +1,48 @@
+mod compat;
 mod config;
+mod crash_reports;
 mod dependencies;
+mod dependency_manifest;
+mod download_limit;
+mod game_link;
+mod game_settings;
+mod game_supervisor;
+mod http;
+mod instance_lock;
+#[cfg(feature = "gui")]
+mod gui;
+mod local_source;
 mod logging;
+mod manifest_gen;
+mod o3de_packages;
 mod orchestrator;
+mod packs;
+mod patch;
+mod preflight;
+mod signing;
 mod state_machine;
 mod sync;
 mod updater;
+mod versions;
 
 use anyhow::Result;
 use state_machine::{LauncherState, StateMachine};
-use std::io::Write;
+use std::io::{IsTerminal, Write};
+use std::path::Path;
 
-use crate::config::Config;
+use crate::compat::CompatChecker;
+use crate::config::{Config, LaunchProfile};
+use crate::crash_reports::CrashReports;
+use crate::instance_lock::InstanceLock;
 use crate::dependencies::DependencyManager;
+use crate::dependency_manifest::DependencyManifest;
+use crate::local_source::LocalSource;
+use crate::manifest_gen::ManifestOptions;
 use crate::orchestrator::BuildOrchestrator;
+use crate::packs::PackManager;
 use crate::sync::SyncManager;
 use crate::updater::Updater;
+use crate::versions::VersionStore;
 
 struct Args {
     help: bool,
@@ -22,16 +50,73 @@ struct Args {
     dry_run: bool,
     verbose: bool,
     skip_elevation: bool,
+    tracy: bool,
+    list_packs: bool,
+    enable_pack: Option<String>,
+    disable_pack: Option<String>,
+    gui: bool,
+    smoke_test: bool,
+    channel: Option<String>,
+    rollback: bool,
+    rollback_to: Option<String>,
+    verify: bool,
+    repair: bool,
+    force: bool,
+    limit_rate: Option<String>,
+    install_from: Option<String>,
+    require_all: bool,
+    profile: Option<String>,
+    /// Everything after `--`, for the game.
+    game_args: Vec<String>,
+    reset_config: bool,
+    /// What follows `config`, for the `config` subcommands.
+    config_command: Option<Vec<String>>,
+    gen_manifest: Option<String>,
+    manifest_version: Option<String>,
+    out: Option<String>,
+    signing_key: Option<String>,
+    dependencies: Option<String>,
 }
 
 fn parse_args() -> Args {
-    let args: Vec<String> = std::env::args().collect();
+    let mut args: Vec<String> = std::env::args().collect();
+    let game_args = match args.iter().position(|a| a == "--") {
+        Some(i) => args.split_off(i).into_iter().skip(1).collect(),
+        None => Vec::new(),
+    };
+    let value_of = |name: &str| args.iter().position(|a| a == name).and_then(|i| args.get(i + 1)).cloned();
     Args {
         help: args.iter().any(|a| a == "--help" || a == "-h"),
         version: args.iter().any(|a| a == "--version" || a == "-V"),
         dry_run: args.iter().any(|a| a == "--dry-run" || a == "--test"),
         verbose: args.iter().any(|a| a == "--verbose" || a == "-v"),
         skip_elevation: args.iter().any(|a| a == "--skip-elevation"),
+        tracy: args.iter().any(|a| a == "--tracy"),
+        list_packs: args.iter().any(|a| a == "--list-packs"),
+        enable_pack: value_of("--enable-pack"),
+        disable_pack: value_of("--disable-pack"),
+        gui: cfg!(feature = "gui")
+            && (args.iter().any(|a| a == "--gui")
+                || (!args.iter().any(|a| a == "--console") && started_from_explorer())),
+        smoke_test: args.iter().any(|a| a == "--smoke-test"),
+        channel: value_of("--channel"),
+        rollback: args.iter().any(|a| a == "--rollback"),
+        rollback_to: value_of("--rollback").filter(|v| !v.starts_with('-')),
+        verify: args.iter().any(|a| a == "--verify"),
+        repair: args.iter().any(|a| a == "--repair"),
+        force: args.iter().any(|a| a == "--force"),
+        limit_rate: value_of("--limit-rate"),
+        install_from: value_of("--install-from"),
+        require_all: args.iter().any(|a| a == "--require-all"),
+        profile: value_of("--profile"),
+        game_args,
+        reset_config: args.iter().any(|a| a == "--reset-config"),
+        config_command: (args.get(1).map(String::as_str) == Some("config")).then(|| args[2..].to_vec()),
+        gen_manifest: value_of("--gen-manifest"),
+        manifest_version: value_of("--manifest-version"),
+        out: value_of("--out"),
+        signing_key: value_of("--signing-key"),
+        dependencies: value_of("--dependencies"),
     }
 }
 
@@ -39,7 +124,8 @@ fn print_help() {
     println!("AAA MMORPG Engine Launcher v{}", config::LAUNCHER_VERSION);
     println!();
     println!("USAGE:");
-    println!("    aaa-launcher.exe [OPTIONS]");
+    println!("    aaa-launcher.exe [OPTIONS] [-- <game arguments>]");
+    println!("    aaa-launcher.exe config <list | get <key> | set <key> <value> | reset [key]>");
     println!();
     println!("OPTIONS:");
     println!("    -h, --help           Show this help message");
@@ -47,6 +133,37 @@ fn print_help() {
     println!("    -v, --verbose        Enable verbose logging");
     println!("    --dry-run            Test mode (check deps, don't build)");
     println!("    --skip-elevation     Don't request admin rights");
+    println!("    --tracy              Build with Tracy capture and open the profiler");
+    println!("    --smoke-test         After building, run the game headless instead of launching it");
+    println!("    --require-all        Stop if an optional dependency (e.g. Tracy) fails to install");
+    println!("    --profile <name>     Start the game with a launch profile from the config, e.g. headless");
+    println!("    -- <arguments>       Pass everything after -- to the game");
+    println!("    --channel <name>     Use the stable, beta or nightly channel for this run");
+    println!("    --limit-rate <KB/s>  Cap download speed for this run, e.g. 500k or 2m (0 = no limit)");
+    println!("    --install-from <path> Install or update from a local manifest directory or archive, offline");
+    println!("    --list-packs         List installed content packs and whether they load");
+    println!("    --enable-pack <id>   Enable a content pack");
+    println!("    --disable-pack <id>  Disable a content pack");
+    println!("    --rollback [version] Restore the previous engine version, or an earlier kept one");
+    println!("    --verify             Check the engine files against the server's manifest");
+    println!("    --repair             With --verify, download the corrupted and missing files again");
+    println!("    --force              Stop a stuck launcher that's holding the install directory");
+    println!("    --reset-config       Put the launcher config back to the defaults (the old one is kept as .bak)");
+    println!("    --gui                Show progress in a window (default when double-clicked)");
+    println!("    --console            Stay in the console, e.g. for CI");
+    println!();
+    println!("CONFIG:");
+    println!("    config list          Show every launcher setting");
+    println!("    config get <key>     Show one setting");
+    println!("    config set <key> <value>");
+    println!("                         Change a setting; a value that isn't valid for it is rejected");
+    println!("    config reset [key]   Put one setting, or all of them, back to the default");
+    println!();
+    println!("SERVER OPERATORS:");
+    println!("    --gen-manifest <dir> --manifest-version <version> [--out <dir>] [--signing-key <file>]");
+    println!("                         Write manifest.json and packs.json for an engine directory, signed");
+    println!("                         with the release key in <file>, to --out (default: current directory)");
+    println!("    --dependencies <file> With --gen-manifest, publish that dependency manifest alongside, signed");
     println!();
 }
 
@@ -90,7 +207,7 @@ fn is_elevated() -> bool {
 }
 
 #[cfg(windows)]
-fn request_elevation() -> bool {
+fn request_elevation(launch: &Args) -> bool {
     use std::os::windows::ffi::OsStrExt;
     use std::ffi::OsStr;
     use std::iter::once;
@@ -100,7 +217,8 @@ fn request_elevation() -> bool {
         Err(_) => return false,
     };
     
-    let exe_wide: Vec<u16> = OsStr::new(exe_path.to_str().unwrap_or(""))
+    let exe_wide: Vec<u16> = exe_path
+        .as_os_str()
         .encode_wide()
         .chain(once(0))
         .collect();
@@ -110,7 +228,9 @@ fn request_elevation() -> bool {
         .chain(once(0))
         .collect();
     
-    let args: Vec<u16> = OsStr::new("--skip-elevation")
+    // The elevated copy gets a console of its own, so the GUI choice is passed on
+    let args = elevated_command_line(launch.gui, std::env::args().skip(1));
+    let args: Vec<u16> = OsStr::new(&args)
         .encode_wide()
         .chain(once(0))
         .collect();
@@ -129,13 +249,75 @@ fn request_elevation() -> bool {
     }
 }
 
+/// The command line for the elevated copy: this run's arguments as given,
+/// with `--skip-elevation` and the GUI choice in front of them.
+#[cfg(any(windows, test))]
+fn elevated_command_line(gui: bool, args: impl Iterator<Item = String>) -> String {
+    let mut forwarded = vec![
+        "--skip-elevation".to_string(),
+        if gui { "--gui" } else { "--console" }.to_string(),
+    ];
+    let mut game_args = false;
+    for arg in args {
+        game_args |= arg == "--";
+        if game_args || !matches!(arg.as_str(), "--skip-elevation" | "--gui" | "--console") {
+            forwarded.push(arg);
+        }
+    }
+    forwarded.iter().map(|arg| quote_windows_arg(arg)).collect::<Vec<_>>().join(" ")
+}
+
+/// Quotes `arg` so Windows' command-line parsing (`CommandLineToArgvW`)
+/// gives it back unchanged: backslashes only double before a quote.
+#[cfg(any(windows, test))]
+fn quote_windows_arg(arg: &str) -> String {
+    if !arg.is_empty() && !arg.contains([' ', '\t', '\n', '\x0b', '"']) {
+        return arg.to_string();
+    }
+    let mut quoted = String::from('"');
+    let mut backslashes = 0;
+    for c in arg.chars() {
+        match c {
+            '\\' => backslashes += 1,
+            '"' => {
+                quoted.extend(std::iter::repeat_n('\\', backslashes * 2 + 1));
+                quoted.push('"');
+                backslashes = 0;
+            }
+            _ => {
+                quoted.extend(std::iter::repeat_n('\\', backslashes));
+                quoted.push(c);
+                backslashes = 0;
+            }
+        }
+    }
+    // The closing quote mustn't be escaped by a trailing backslash
+    quoted.extend(std::iter::repeat_n('\\', backslashes * 2));
+    quoted.push('"');
+    quoted
+}
+
 #[cfg(not(windows))]
 fn is_elevated() -> bool {
     unsafe { libc::geteuid() == 0 }
 }
 
 #[cfg(not(windows))]
-fn request_elevation() -> bool {
+fn request_elevation(_launch: &Args) -> bool {
+    false
+}
+
+/// Whether the launcher owns its console, which is how Windows starts a
+/// console program that was double-clicked.
+#[cfg(windows)]
+fn started_from_explorer() -> bool {
+    let mut processes = [0u32; 2];
+    let count = unsafe { winapi::um::wincon::GetConsoleProcessList(processes.as_mut_ptr(), processes.len() as u32) };
+    count == 1
+}
+
+#[cfg(not(windows))]
+fn started_from_explorer() -> bool {
     false
 }
 
@@ -160,6 +342,57 @@ async fn main() {
         return;
     }
     
+    if let Some(source) = &args.gen_manifest {
+        if let Err(e) = run_gen_manifest_command(&args, source) {
+            eprintln!("ERROR: {:#}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if let Some(words) = &args.config_command {
+        if let Err(e) = run_config_command(words) {
+            eprintln!("ERROR: {:#}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if args.reset_config {
+        match Config::reset() {
+            Ok(path) => logging::success(&format!("Reset {} to the defaults", path.display())),
+            Err(e) => {
+                eprintln!("ERROR: {:#}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if args.rollback {
+        if let Err(e) = run_rollback_command(&args) {
+            eprintln!("ERROR: {:#}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if args.verify || args.repair {
+        if let Err(e) = run_verify_command(&args).await {
+            eprintln!("ERROR: {:#}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if args.list_packs || args.enable_pack.is_some() || args.disable_pack.is_some() {
+        if let Err(e) = run_pack_command(&args) {
+            eprintln!("ERROR: {:#}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    
     // Early logging to console before config is loaded
     println!();
     println!("AAA MMORPG Engine Launcher v{}", config::LAUNCHER_VERSION);
@@ -173,7 +406,7 @@ async fn main() {
         println!("(Required for installing Vulkan SDK and VS Build Tools)");
         println!();
         
-        if request_elevation() {
+        if request_elevation(&args) {
             println!("Elevated process started. This window will close.");
             std::thread::sleep(std::time::Duration::from_secs(2));
             return;
@@ -184,11 +417,29 @@ async fn main() {
         }
     }
     
+    #[cfg(feature = "gui")]
+    if args.gui {
+        // The window replaces the console
+        #[cfg(windows)]
+        unsafe {
+            winapi::um::wincon::FreeConsole();
+        }
+        let config = load_config(&args).ok();
+        if gui::run(config, run(args)).await.is_err() {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // Smoke tests run unattended, e.g. in CI
+    let interactive = !args.smoke_test;
     match run(args).await {
         Ok(()) => {
             println!();
             println!("Launcher completed successfully.");
-            wait_for_enter();
+            if interactive {
+                wait_for_enter();
+            }
         }
         Err(e) => {
             eprintln!();
@@ -196,16 +447,36 @@ async fn main() {
             eprintln!("ERROR: {:#}", e);
             eprintln!("=====================================");
             eprintln!();
-            wait_for_enter();
+            if interactive {
+                wait_for_enter();
+            }
             std::process::exit(1);
         }
     }
 }
 
-async fn run(args: Args) -> Result<()> {
+/// The saved config with `--channel` and `--limit-rate` applied for this run.
+fn load_config(args: &Args) -> Result<Config> {
     let mut config = Config::load()?;
+    if let Some(channel) = &args.channel {
+        config.channel = channel.parse()?;
+    }
+    if let Some(rate) = &args.limit_rate {
+        config.max_download_speed_kbps = download_limit::parse_rate(rate)?;
+    }
+    Ok(config)
+}
+
+async fn run(args: Args) -> Result<()> {
+    let mut config = load_config(&args)?;
     config.verbose = args.verbose;
+    config.enable_tracy |= args.tracy;
+    config.require_all_dependencies |= args.require_all;
+    // Checked before the steps start, so a typo doesn't wait for the build
+    let launch = launch_profile(&config, &args)?;
     
+    let _lock = InstanceLock::acquire(&config, args.force)?;
+
     // Create directories first so logging can work
     std::fs::create_dir_all(&config.install_dir)?;
     std::fs::create_dir_all(&config.logs_dir())?;
@@ -213,11 +484,34 @@ async fn run(args: Args) -> Result<()> {
     logging::init(&config.logs_dir(), config.verbose)?;
     logging::header();
     
+    // Opened before the steps start, so a wrong path fails straight away
+    let mut source = args.install_from.as_deref().map(|path| LocalSource::open(Path::new(path))).transpose()?;
+
     println!("Install directory: {}", config.install_dir.display());
-    println!("Server: {}", config.server_url);
+    match &source {
+        Some(source) => println!("Installing from: {}", source.describe()),
+        None => println!("Server: {}", config.server_url),
+    }
+    println!("Channel: {}", config.channel);
+    if let Some(profile) = &args.profile {
+        println!("Launch profile: {}", profile);
+    }
+    if config.max_download_speed_kbps > 0 {
+        println!("Download limit: {} KB/s", config.max_download_speed_kbps);
+    }
     println!("Log directory: {}", config.logs_dir().display());
     println!();
 
+    // Asked before the steps start, so the answer isn't buried in their output
+    let interactive = !args.gui && !args.smoke_test && std::io::stdin().is_terminal();
+    if let Err(e) = CrashReports::new(config.clone())?.offer(interactive).await {
+        logging::warn(&format!("Could not check for crash reports: {:#}", e));
+    }
+
+    // Applied before the steps, so a resumed run builds with the same versions it installed
+    let dependencies = DependencyManifest::load(&config, source.is_some()).await;
+    dependencies.apply(&mut config);
+
     let mut state_machine = StateMachine::new(&config.install_dir)?;
 
     if state_machine.current() == LauncherState::Complete {
@@ -233,14 +527,19 @@ async fn run(args: Args) -> Result<()> {
 
         let result = match current_state {
             LauncherState::Init => run_init(&config).await,
+            LauncherState::SelfUpdate if source.is_some() => {
+                logging::info("Installing from a local source: not checking for launcher updates");
+                Ok(())
+            }
             LauncherState::SelfUpdate => run_self_update(&config).await,
-            LauncherState::DependencyAudit => run_dependency_audit(&config, args.dry_run).await,
+            LauncherState::Preflight => preflight::run(&config, !args.dry_run),
+            LauncherState::DependencyAudit => run_dependency_audit(&config, &dependencies, args.dry_run).await,
             LauncherState::Sync => {
                 if args.dry_run {
                     logging::info("Dry-run mode: skipping sync");
                     Ok(())
                 } else {
-                    run_sync(&config).await
+                    run_sync(&config, source.as_mut()).await
                 }
             }
             LauncherState::Build => {
@@ -248,15 +547,20 @@ async fn run(args: Args) -> Result<()> {
                     logging::info("Dry-run mode: skipping build");
                     Ok(())
                 } else {
-                    run_build(&config).await
+                    run_build(&config, args.smoke_test).await
                 }
             }
+            // Settings are only written on a real run
+            LauncherState::Compatibility => CompatChecker::new(config.clone())?.run(!args.dry_run).await,
             LauncherState::Launch => {
                 if args.dry_run {
                     logging::info("Dry-run mode: skipping launch");
                     Ok(())
+                } else if args.smoke_test {
+                    logging::info("Smoke test mode: not launching the game");
+                    Ok(())
                 } else {
-                    run_launch(&config).await
+                    run_launch(&config, &launch).await
                 }
             }
             LauncherState::Complete => break,
@@ -293,9 +597,141 @@ async fn run(args: Args) -> Result<()> {
     Ok(())
 }
 
+/// `config ...`: shows and changes the saved launcher config.
+fn run_config_command(words: &[String]) -> Result<()> {
+    let words: Vec<&str> = words.iter().map(String::as_str).collect();
+    let mut config = Config::load_saved();
+    config.validate();
+
+    match words.as_slice() {
+        [] | ["list"] => {
+            println!("{}", Config::config_path().display());
+            for (key, value) in config.fields() {
+                println!("    {} = {}", key, value);
+            }
+            if let Some(url) = config.server_url_override() {
+                logging::info(&format!("AAA_SERVER_URL or server_url.txt replaces server_url with {}", url));
+            }
+        }
+        ["get", key] => match config.get(key)? {
+            serde_json::Value::String(text) => println!("{}", text),
+            value => println!("{}", value),
+        },
+        ["set", key, value] => {
+            config.set(key, value)?;
+            config.save()?;
+            logging::success(&format!("Set {} to {}", key, config.get(key)?));
+        }
+        ["reset"] => {
+            let path = Config::reset()?;
+            logging::success(&format!("Reset {} to the defaults", path.display()));
+        }
+        ["reset", key] => {
+            config.reset_field(key)?;
+            config.save()?;
+            logging::success(&format!("Reset {} to {}", key, config.get(key)?));
+        }
+        _ => anyhow::bail!("Usage: aaa-launcher config <list | get <key> | set <key> <value> | reset [key]>"),
+    }
+    Ok(())
+}
+
+fn run_gen_manifest_command(args: &Args, source: &str) -> Result<()> {
+    let version = args
+        .manifest_version
+        .clone()
+        .ok_or_else(|| anyhow::anyhow!("--gen-manifest needs --manifest-version <version>"))?;
+    manifest_gen::generate(&ManifestOptions {
+        source: source.into(),
+        version,
+        out: args.out.as_deref().unwrap_or(".").into(),
+        signing_key: args.signing_key.as_ref().map(Into::into),
+        dependencies: args.dependencies.as_ref().map(Into::into),
+    })
+}
+
+fn run_pack_command(args: &Args) -> Result<()> {
+    let config = load_config(args)?;
+    let packs = PackManager::new(&config);
+    if let Some(id) = &args.enable_pack {
+        packs.set_enabled(id, true)?;
+    }
+    if let Some(id) = &args.disable_pack {
+        packs.set_enabled(id, false)?;
+    }
+    packs.list()
+}
+
+/// Restores a kept engine version without contacting the server.
+fn run_rollback_command(args: &Args) -> Result<()> {
+    let config = load_config(args)?;
+    let _lock = InstanceLock::acquire(&config, args.force)?;
+    let sync_manager = SyncManager::new(config.clone())?;
+    let versions = VersionStore::new(&config);
+    let Some(current) = sync_manager.local_version() else {
+        versions.list();
+        anyhow::bail!("No synced engine version in {}", config.engine_dir().display());
+    };
+
+    let restored = match versions.rollback(&current, args.rollback_to.as_deref()) {
+        Ok(restored) => restored,
+        Err(e) => {
+            versions.list();
+            return Err(e);
+        }
+    };
+    sync_manager.save_local_version(&restored)?;
+    println!("Rolled back from {} to {}.", current, restored);
+    println!("The launcher stays on {} until the server has a version newer than {}.", restored, current);
+    Ok(())
+}
+
+async fn run_verify_command(args: &Args) -> Result<()> {
+    let config = load_config(args)?;
+    let _lock = InstanceLock::acquire(&config, args.force)?;
+    let engine_dir = config.engine_dir();
+    if !engine_dir.exists() {
+        anyhow::bail!("No engine in {} - run the launcher to install it", engine_dir.display());
+    }
+    let sync_manager = SyncManager::new(config.clone())?;
+
+    let server_version = sync_manager.check_server().await?;
+    let local_version = sync_manager.local_version();
+    let outdated = local_version.as_deref() != Some(server_version.as_str());
+    if outdated {
+        logging::warn(&format!(
+            "The engine is at {} and the server at {}; files the update changes show as corrupted",
+            local_version.as_deref().unwrap_or("an unknown version"),
+            server_version
+        ));
+    }
+
+    let manifest = sync_manager.get_manifest().await?;
+    let report = sync_manager.verify_files(&manifest)?;
+    report.print();
+    if report.bad_files() == 0 {
+        logging::success("All engine files match the manifest");
+        return Ok(());
+    }
+    if !args.repair {
+        anyhow::bail!("{} files need repair; run with --verify --repair to download them again", report.bad_files());
+    }
+    if outdated {
+        // Repairing would leave a mix of both versions
+        anyhow::bail!("Not repairing an outdated engine - run the launcher to update it, which replaces bad files too");
+    }
+
+    let repaired = sync_manager.repair(&manifest, &report).await?;
+    logging::success(&format!("Repaired {} files", repaired));
+    if !report.extra.is_empty() {
+        logging::info("Extra files were left in place");
+    }
+    Ok(())
+}
+
 async fn run_init(config: &Config) -> Result<()> {
     logging::info(&format!("Install directory: {}", config.install_dir.display()));
-    logging::info(&format!("Server: {}", config.server_url));
+    logging::info(&format!("Server: {} ({} channel)", config.server_url, config.channel));
     
     std::fs::create_dir_all(&config.install_dir)?;
     std::fs::create_dir_all(&config.deps_dir())?;
@@ -317,7 +753,7 @@ async fn run_self_update(config: &Config) -> Result<()> {
         Some(update_info) => {
             let temp_path = config.install_dir.join("launcher_update.exe");
             
-            updater.download_and_verify(&temp_path, &update_info.checksum).await?;
+            updater.download_and_verify(&temp_path, &update_info).await?;
             
             let current_exe = std::env::current_exe()?;
             Updater::apply_update(&temp_path, &current_exe)?;
@@ -332,8 +768,8 @@ async fn run_self_update(config: &Config) -> Result<()> {
     Ok(())
 }
 
-async fn run_dependency_audit(config: &Config, dry_run: bool) -> Result<()> {
-    let dep_manager = DependencyManager::new(config.clone());
+async fn run_dependency_audit(config: &Config, manifest: &DependencyManifest, dry_run: bool) -> Result<()> {
+    let dep_manager = DependencyManager::new(config.clone(), manifest.clone());
     let deps = dep_manager.check_all();
 
     dep_manager.print_status(&deps);
@@ -358,7 +794,10 @@ async fn run_dependency_audit(config: &Config, dry_run: bool) -> Result<()> {
             dep_manager.install_missing(&deps).await?;
             
             let recheck = dep_manager.check_all();
-            let still_missing: Vec<_> = recheck.iter().filter(|d| !d.installed).collect();
+            let (still_missing, optional): (Vec<_>, Vec<_>) = recheck
+                .iter()
+                .filter(|d| !d.installed)
+                .partition(|d| dep_manager.is_required(d));
             
             if !still_missing.is_empty() {
                 anyhow::bail!(
@@ -367,38 +806,81 @@ async fn run_dependency_audit(config: &Config, dry_run: bool) -> Result<()> {
                 );
             }
             
-            logging::success("All dependencies installed");
+            if optional.is_empty() {
+                logging::success("All dependencies installed");
+            } else {
+                logging::success("All required dependencies installed");
+                logging::warn(&format!(
+                    "Continuing without {} (optional; run with --require-all to stop instead)",
+                    optional.iter().map(|d| d.name.as_str()).collect::<Vec<_>>().join(", ")
+                ));
+            }
         }
     }
 
     Ok(())
 }
 
-async fn run_sync(config: &Config) -> Result<()> {
+async fn run_sync(config: &Config, source: Option<&mut LocalSource>) -> Result<()> {
     let sync_manager = SyncManager::new(config.clone())?;
     
-    let _server_version = sync_manager.check_server().await?;
+    let source = source.map(|source| source.manifest(config).map(|manifest| (source, manifest))).transpose()?;
+    let server_version = match &source {
+        Some((_, manifest)) => manifest.version.clone(),
+        None => sync_manager.check_server().await?,
+    };
+    let versions = VersionStore::new(config);
+
+    if versions.skipped_version().as_deref() == Some(server_version.as_str()) {
+        logging::warn(&format!(
+            "Staying on {}: {} was rolled back; it's synced again once the server has a newer version",
+            sync_manager.local_version().unwrap_or_else(|| "the restored version".to_string()),
+            server_version
+        ));
+        return Ok(());
+    }
     
     let engine_dir = config.engine_dir();
-    if !engine_dir.exists() || std::fs::read_dir(&engine_dir)?.count() == 0 {
-        logging::info("No local files - downloading full archive");
-        sync_manager.download_full_archive().await?;
-    } else {
-        match sync_manager.get_manifest().await {
-            Ok(manifest) => {
-                sync_manager.sync_files(&manifest).await?;
+    let installed = engine_dir.exists() && std::fs::read_dir(&engine_dir)?.count() > 0;
+    let local_version = sync_manager.local_version();
+    if installed {
+        if let Some(local) = local_version.as_deref().filter(|local| *local != server_version) {
+            match versions.begin(local) {
+                Ok(point) => sync_manager.set_rollback_point(point),
+                Err(e) => logging::warn(&format!("Not keeping {} for rollback: {:#}", local, e)),
             }
-            Err(e) => {
-                logging::warn(&format!("Could not get manifest: {} - using full sync", e));
-                sync_manager.download_full_archive().await?;
+        }
+    }
+
+    if let Some((source, manifest)) = source {
+        sync_manager.copy_files(source, &manifest)?;
+    } else {
+        // Everything that lands in the engine directory is checked against
+        // this, so there's no syncing without it
+        let manifest = sync_manager.get_manifest().await?;
+        if !installed {
+            logging::info("No local files - downloading full archive");
+            sync_manager.download_full_archive(&manifest).await?;
+        } else {
+            // Patch forward from the last synced version when the server has a chain;
+            // the manifest check afterwards fixes up anything the patches missed
+            if let Some(local) = local_version.filter(|local| *local != server_version) {
+                if let Err(e) = sync_manager.apply_patch_chain(&local, &server_version).await {
+                    logging::warn(&format!("Patching failed: {:#} - checking files one by one", e));
+                }
             }
+            sync_manager.sync_files(&manifest).await?;
         }
     }
 
+    sync_manager.save_local_version(&server_version)?;
+    if let Some(point) = sync_manager.take_rollback_point() {
+        point.commit(&versions, &server_version)?;
+    }
     Ok(())
 }
 
-async fn run_build(config: &Config) -> Result<()> {
+async fn run_build(config: &Config, smoke_test: bool) -> Result<()> {
     let orchestrator = BuildOrchestrator::new(config.clone());
     
     if orchestrator.needs_rebuild()? {
@@ -411,14 +893,59 @@ async fn run_build(config: &Config) -> Result<()> {
     // Build Render Fabric and run validation tests
     orchestrator.build_render_fabric()?;
     orchestrator.run_validation_tests()?;
+    orchestrator.validate_content()?;
+
+    if smoke_test {
+        orchestrator.run_smoke_test()?;
+    }
 
     Ok(())
 }
 
-async fn run_launch(config: &Config) -> Result<()> {
-    let orchestrator = BuildOrchestrator::new(config.clone());
-    orchestrator.launch_game()?;
-    
-    logging::success("Game launched");
-    Ok(())
+/// How to start the game: the `--profile` one, plus the arguments after `--`.
+fn launch_profile(config: &Config, args: &Args) -> Result<LaunchProfile> {
+    let mut profile = match &args.profile {
+        Some(name) => config.launch_profiles.get(name).cloned().ok_or_else(|| {
+            let names: Vec<&str> = config.launch_profiles.keys().map(String::as_str).collect();
+            anyhow::anyhow!("No launch profile \"{}\" in the config (it has: {})", name, names.join(", "))
+        })?,
+        None => LaunchProfile::default(),
+    };
+    profile.args.extend(args.game_args.iter().cloned());
+    Ok(profile)
+}
+
+async fn run_launch(config: &Config, launch: &LaunchProfile) -> Result<()> {
+    game_supervisor::run(config, launch).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn windows_arguments_survive_quoting() {
+        let cases = [
+            ("plain", "plain"),
+            ("", "\"\""),
+            ("two words", "\"two words\""),
+            ("say \"hi\"", "\"say \\\"hi\\\"\""),
+            ("C:\\Program Files\\", "\"C:\\Program Files\\\\\""),
+            ("a\\\\\"b", "\"a\\\\\\\\\\\"b\""),
+            ("no\\space", "no\\space"),
+        ];
+        for (arg, quoted) in cases {
+            assert_eq!(quote_windows_arg(arg), quoted, "{}", arg);
+        }
+    }
+
+    #[test]
+    fn elevated_copy_gets_every_argument() {
+        let args = ["--tracy", "-v", "--dry-run", "--gui", "--profile", "raid night", "--", "--gui", "x y"];
+        let line = elevated_command_line(false, args.iter().map(|a| a.to_string()));
+        assert_eq!(
+            line,
+            "--skip-elevation --console --tracy -v --dry-run --profile \"raid night\" -- --gui \"x y\""
+        );
+    }
 }
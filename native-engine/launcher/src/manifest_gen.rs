@@ -0,0 +1,197 @@
+//! `--gen-manifest`: what the server publishes for an engine version, built
+//! from an engine directory so operators don't write manifests by hand.
+//!
+//! These files go to the output directory:
+//! - `manifest.json`, the [`FileManifest`] served at `/sync/manifest`, with
+//!   each file's SHA-256 and the SHA-256 of every [`CHUNK_SIZE`] block of it;
+//! - `packs.json`, each content pack under `packs/`: its `pack.toml` fields
+//!   plus its files, hashed the same way;
+//! - with `--dependencies`, that file as `dependencies.json`, for
+//!   `/sync/dependencies`;
+//! - with a release key, a `.sig` next to each, the key's signature over the
+//!   file exactly as written.
+//!
+//! The engine walk skips what the launcher never syncs (build output,
+//! launcher state, packs), the same entries `--verify` ignores.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::dependency_manifest::{self, DependencyManifest};
+use crate::logging;
+use crate::signing::{self, ReleaseKey};
+use crate::sync::{self, FileInfo, FileManifest};
+
+/// Block size for per-chunk hashes.
+const CHUNK_SIZE: u64 = 1024 * 1024;
+
+pub struct ManifestOptions {
+    /// The engine directory to describe.
+    pub source: PathBuf,
+    pub version: String,
+    pub out: PathBuf,
+    pub signing_key: Option<PathBuf>,
+    /// A dependency manifest to publish alongside.
+    pub dependencies: Option<PathBuf>,
+}
+
+#[derive(Debug, Serialize)]
+struct PackIndex {
+    version: String,
+    packs: Vec<PackDefinition>,
+}
+
+#[derive(Debug, Serialize)]
+struct PackDefinition {
+    /// Everything in `pack.toml`, `id` included.
+    #[serde(flatten)]
+    manifest: toml::Table,
+    /// Paths relative to the pack's directory.
+    files: BTreeMap<String, FileInfo>,
+}
+
+pub fn generate(options: &ManifestOptions) -> Result<()> {
+    if !options.source.is_dir() {
+        anyhow::bail!("{} isn't a directory", options.source.display());
+    }
+    // Load the key and dependencies first so a bad one fails before the hashing
+    let key = options.signing_key.as_deref().map(ReleaseKey::load).transpose()?;
+    let dependencies = options.dependencies.as_deref().map(load_dependencies).transpose()?;
+
+    logging::info(&format!("Hashing {} for version {}", options.source.display(), options.version));
+    let manifest = FileManifest {
+        version: options.version.clone(),
+        chunk_size: Some(CHUNK_SIZE),
+        files: hash_tree(&options.source)?,
+    };
+    let total: u64 = manifest.files.values().map(|info| info.size).sum();
+    logging::info(&format!("{} files, {:.1} MB", manifest.files.len(), total as f64 / 1_048_576.0));
+
+    let packs = PackIndex {
+        version: options.version.clone(),
+        packs: pack_definitions(&options.source.join("packs"))?,
+    };
+    for pack in &packs.packs {
+        logging::info(&format!("Pack {}: {} files", pack_id(&pack.manifest), pack.files.len()));
+    }
+
+    std::fs::create_dir_all(&options.out)?;
+    write(&options.out, "manifest.json", &manifest, key.as_ref())?;
+    write(&options.out, "packs.json", &packs, key.as_ref())?;
+    if let Some(dependencies) = &dependencies {
+        write(&options.out, dependency_manifest::MANIFEST_FILE, dependencies, key.as_ref())?;
+    }
+
+    match &key {
+        Some(key) if key.is_built_in() => {
+            logging::success(&format!("Signed with the built-in release key {}", signing::key_id(&key.public_key())));
+        }
+        Some(key) => {
+            logging::success(&format!("Signed with {}", key.public_key()));
+            logging::warn("That isn't the built-in release key; launchers trust it once a keys manifest lists it");
+        }
+        None => logging::warn("No --signing-key - the manifest is unsigned"),
+    }
+    logging::success(&format!("Wrote the manifest for {} to {}", options.version, options.out.display()));
+    Ok(())
+}
+
+fn load_dependencies(path: &Path) -> Result<DependencyManifest> {
+    let text = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    serde_json::from_str(&text).with_context(|| format!("Invalid {}", path.display()))
+}
+
+/// Writes `value` as `name` in `out`, and its signature as `name.sig`.
+fn write(out: &Path, name: &str, value: &impl Serialize, key: Option<&ReleaseKey>) -> Result<()> {
+    let text = serde_json::to_string_pretty(value)?;
+    let path = out.join(name);
+    std::fs::write(&path, &text).with_context(|| format!("Failed to write {}", path.display()))?;
+    if let Some(key) = key {
+        let signature = key.sign(&signing::file_message(name, text.as_bytes()));
+        std::fs::write(out.join(format!("{}.sig", name)), serde_json::to_string_pretty(&signature)?)?;
+    }
+    Ok(())
+}
+
+/// Every file under `root` the launcher syncs, by `/`-separated path.
+fn hash_tree(root: &Path) -> Result<BTreeMap<String, FileInfo>> {
+    let mut paths = Vec::new();
+    sync::collect_files(root, Path::new(""), &mut paths)?;
+    let sizes: Vec<u64> = paths
+        .iter()
+        .map(|path| std::fs::metadata(root.join(path)).map(|m| m.len()))
+        .collect::<std::io::Result<_>>()?;
+
+    let pb = logging::progress_bar(sizes.iter().sum());
+    let mut files = BTreeMap::new();
+    for path in paths {
+        let info = hash_file(&root.join(&path))?;
+        pb.inc(info.size);
+        files.insert(path.to_string_lossy().replace('\\', "/"), info);
+    }
+    pb.finish_and_clear();
+    Ok(files)
+}
+
+fn hash_file(path: &Path) -> Result<FileInfo> {
+    let mut file = std::fs::File::open(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let mut whole = Sha256::new();
+    let mut chunks = Vec::new();
+    let mut size = 0;
+    let mut buffer = vec![0; CHUNK_SIZE as usize];
+    loop {
+        // Fill the whole block so chunk boundaries don't depend on read sizes
+        let mut filled = 0;
+        while filled < buffer.len() {
+            match file.read(&mut buffer[filled..])? {
+                0 => break,
+                read => filled += read,
+            }
+        }
+        if filled == 0 {
+            break;
+        }
+        whole.update(&buffer[..filled]);
+        chunks.push(hex::encode(Sha256::digest(&buffer[..filled])));
+        size += filled as u64;
+        if filled < buffer.len() {
+            break;
+        }
+    }
+    Ok(FileInfo {
+        checksum: hex::encode(whole.finalize()),
+        size,
+        chunks,
+    })
+}
+
+/// `packs/<id>/` directories with a `pack.toml`, by id.
+fn pack_definitions(packs_dir: &Path) -> Result<Vec<PackDefinition>> {
+    let Ok(entries) = std::fs::read_dir(packs_dir) else {
+        return Ok(Vec::new());
+    };
+    let mut packs = Vec::new();
+    for entry in entries.flatten().filter(|entry| entry.path().join("pack.toml").exists()) {
+        let manifest_path = entry.path().join("pack.toml");
+        let text = std::fs::read_to_string(&manifest_path)?;
+        let manifest: toml::Table =
+            toml::from_str(&text).with_context(|| format!("Invalid {}", manifest_path.display()))?;
+        if !manifest.get("id").is_some_and(toml::Value::is_str) {
+            anyhow::bail!("{} has no id", manifest_path.display());
+        }
+        packs.push(PackDefinition {
+            manifest,
+            files: hash_tree(&entry.path())?,
+        });
+    }
+    packs.sort_by(|a, b| pack_id(&a.manifest).cmp(pack_id(&b.manifest)));
+    Ok(packs)
+}
+
+fn pack_id(manifest: &toml::Table) -> &str {
+    manifest.get("id").and_then(toml::Value::as_str).unwrap_or_default()
+}
@@ -0,0 +1,276 @@
+//! Prebuilt O3DE libraries, so a first install doesn't spend 60-120 minutes
+//! building AzCore and Atom from source.
+//!
+//! `/sync/o3de/<o3de_version>/packages` lists the bundles the server has
+//! (404 when it has none), each built for one platform and toolchain:
+//!
+//! ```json
+//! {
+//!   "packages": [
+//!     {
+//!       "id": "o3de-2510.1-windows-msvc",
+//!       "platform": "windows-x86_64",
+//!       "toolchain": "msvc",
+//!       "min_toolset": "14.40",
+//!       "file": "o3de-2510.1-windows-msvc.zip",
+//!       "size": 1073741824,
+//!       "checksum": "<sha256 of the zip>"
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! The list is only used if `/sync/o3de/<o3de_version>/packages.sig` holds
+//! a trusted release key's signature over it as [`INDEX_FILE`]: it's what
+//! vouches for the zip's checksum, and the libraries end up linked into the
+//! game. `file` has to be a plain file name, as it's saved under `deps/`.
+//!
+//! The zip, served at `/sync/o3de/<o3de_version>/<file>`, is an O3DE
+//! install tree (`lib/profile/AzCore.lib`, `include/`, `cmake/`, ...) with
+//! a `manifest.json` from `--gen-manifest` at the top. Every file is checked
+//! against that manifest before the tree replaces `o3de/install`, where the
+//! dependency check and the engine build look for a built O3DE. When no
+//! package matches, or the download fails, the launcher builds from source
+//! as before.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::cmp::Ordering;
+use std::path::{Component, Path};
+
+use crate::compat::parse_version;
+use crate::config::Config;
+use crate::download_limit::RateLimiter;
+use crate::http;
+use crate::logging;
+use crate::signing::TrustedKeys;
+use crate::sync::{self, FileInfo, FileManifest, SyncManager};
+
+/// The name the package list's signature covers.
+const INDEX_FILE: &str = "packages.json";
+
+/// The manifest at the top of a package.
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// Mismatched files listed before the rest are counted.
+const REPORT_LIMIT: usize = 10;
+
+/// The compiler the engine will be built with; a package's libraries have
+/// to link with it.
+#[derive(Debug, Clone)]
+pub struct Toolchain {
+    /// `msvc`, ...
+    pub name: String,
+    /// The toolset version, e.g. `14.40.33807` for MSVC.
+    pub version: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PackageIndex {
+    #[serde(default)]
+    packages: Vec<Package>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Package {
+    id: String,
+    /// `<os>-<arch>` as Rust names them, e.g. `windows-x86_64`.
+    platform: String,
+    toolchain: String,
+    /// Oldest toolset that can link the libraries; static libraries built
+    /// with a newer MSVC don't link with an older one.
+    #[serde(default)]
+    min_toolset: Option<String>,
+    file: String,
+    size: u64,
+    checksum: String,
+}
+
+impl Package {
+    fn matches(&self, platform: &str, toolchain: &Toolchain) -> bool {
+        self.platform == platform
+            && self.toolchain.eq_ignore_ascii_case(&toolchain.name)
+            && self.min_toolset.as_deref().is_none_or(|min| {
+                parse_version(&toolchain.version).cmp(&parse_version(min)) != Ordering::Less
+            })
+    }
+}
+
+/// This machine, as packages name platforms.
+pub fn platform() -> String {
+    format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH)
+}
+
+/// Installs the prebuilt package for this O3DE version, platform and
+/// `toolchain` into `o3de_dir/install`. Returns `false` if the server has
+/// none that fits, so the caller builds from source.
+pub async fn install(config: &Config, limiter: &RateLimiter, toolchain: &Toolchain, o3de_dir: &Path) -> Result<bool> {
+    let client = http::client_builder(config)?
+        .timeout(std::time::Duration::from_secs(600))
+        .connect_timeout(std::time::Duration::from_secs(30))
+        .build()?;
+    let version = config.o3de_version.as_str();
+
+    let url = config.endpoint(["sync", "o3de", version, "packages"])?;
+    let response = client.get(url).send().await.context("Failed to ask for prebuilt O3DE packages")?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        logging::info(&format!("The server has no prebuilt packages for O3DE {}", version));
+        return Ok(false);
+    }
+    if !response.status().is_success() {
+        anyhow::bail!("Prebuilt package list: HTTP {}", response.status());
+    }
+    let text = response.bytes().await.context("Failed to fetch the prebuilt package list")?;
+    let response = client
+        .get(config.endpoint(["sync", "o3de", version, "packages.sig"])?)
+        .send()
+        .await
+        .context("Failed to fetch the prebuilt package list's signature")?;
+    if !response.status().is_success() {
+        anyhow::bail!("No signature for the prebuilt package list (HTTP {})", response.status());
+    }
+    let signature = response.bytes().await?;
+    TrustedKeys::load(config)?
+        .verify_file(INDEX_FILE, &text, &signature)
+        .context("The prebuilt package list isn't signed by a trusted release key")?;
+    let index: PackageIndex = serde_json::from_slice(&text).context("Invalid prebuilt package list")?;
+
+    let platform = platform();
+    let Some(package) = index
+        .packages
+        .iter()
+        .filter(|package| package.matches(&platform, toolchain))
+        .max_by_key(|package| package.min_toolset.as_deref().map(parse_version))
+    else {
+        let available: Vec<String> = index
+            .packages
+            .iter()
+            .map(|package| match &package.min_toolset {
+                Some(min) => format!("{} {} {}+", package.platform, package.toolchain, min),
+                None => format!("{} {}", package.platform, package.toolchain),
+            })
+            .collect();
+        logging::info(&format!(
+            "No prebuilt O3DE {} package for {} with {} {} (the server has: {})",
+            version,
+            platform,
+            toolchain.name,
+            toolchain.version,
+            if available.is_empty() { "none".to_string() } else { available.join(", ") }
+        ));
+        return Ok(false);
+    };
+
+    logging::info(&format!(
+        "Downloading prebuilt O3DE {} ({}, {:.1} GB)...",
+        version,
+        package.id,
+        package.size as f64 / (1024.0 * 1024.0 * 1024.0)
+    ));
+    let file = file_name(&package.file)?;
+    std::fs::create_dir_all(config.deps_dir())?;
+    let archive_path = config.deps_dir().join(file);
+    let info = FileInfo {
+        checksum: package.checksum.clone(),
+        size: package.size,
+        chunks: Vec::new(),
+    };
+    let url = config.endpoint(["sync", "o3de", version, file])?;
+    let pb = logging::progress_bar(package.size);
+    let download = sync::fetch_file(&client, &url, &archive_path, &info, config.download_retries, limiter, &pb).await;
+    pb.finish_and_clear();
+    download.with_context(|| format!("Failed to download {}", file))?;
+    logging::success("Package downloaded");
+
+    let install_dir = o3de_dir.join("install");
+    let staging = o3de_dir.join("install.partial");
+    if staging.exists() {
+        std::fs::remove_dir_all(&staging).with_context(|| format!("Failed to clear {}", staging.display()))?;
+    }
+    std::fs::create_dir_all(&staging)?;
+
+    logging::info("Extracting and checking the package...");
+    let unpacked = unpack(&archive_path, &staging, version);
+    if let Err(e) = unpacked {
+        let _ = std::fs::remove_dir_all(&staging);
+        // A bad archive would only pass its checksum again
+        let _ = std::fs::remove_file(&archive_path);
+        return Err(e);
+    }
+
+    if install_dir.exists() {
+        std::fs::remove_dir_all(&install_dir)
+            .with_context(|| format!("Failed to remove the old {}", install_dir.display()))?;
+    }
+    std::fs::rename(&staging, &install_dir)
+        .with_context(|| format!("Failed to move the package into {}", install_dir.display()))?;
+    let _ = std::fs::remove_file(&archive_path);
+
+    logging::success(&format!("Prebuilt O3DE {} installed in {}", version, install_dir.display()));
+    Ok(true)
+}
+
+/// `file` from the package list, if it's a plain file name that stays in
+/// the directory it's joined to.
+fn file_name(file: &str) -> Result<&str> {
+    let mut components = Path::new(file).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(_)), None) => Ok(file),
+        _ => anyhow::bail!("Refusing package file name {:?}: it has to be a plain file name", file),
+    }
+}
+
+/// Extracts `archive` into `dir` and checks every file against the
+/// package's manifest.
+fn unpack(archive: &Path, dir: &Path, version: &str) -> Result<()> {
+    let file = std::fs::File::open(archive)?;
+    let mut zip = zip::ZipArchive::new(file).context("The package isn't a zip archive")?;
+    zip.extract(dir).context("Failed to extract the package")?;
+
+    let manifest_path = dir.join(MANIFEST_FILE);
+    let text = std::fs::read_to_string(&manifest_path).context("The package has no manifest.json")?;
+    let manifest: FileManifest = serde_json::from_str(&text).context("The package's manifest.json is invalid")?;
+    if manifest.version != version {
+        anyhow::bail!("The package is for O3DE {}, not {}", manifest.version, version);
+    }
+
+    let mut bad = Vec::new();
+    for (path, info) in &manifest.files {
+        let local = dir.join(path);
+        let matches = std::fs::metadata(&local).is_ok_and(|meta| meta.len() == info.size)
+            && SyncManager::calculate_checksum(&local).is_ok_and(|checksum| checksum == info.checksum);
+        if !matches {
+            bad.push(path.as_str());
+        }
+    }
+    if !bad.is_empty() {
+        let mut listed = bad.iter().take(REPORT_LIMIT).copied().collect::<Vec<_>>().join(", ");
+        if bad.len() > REPORT_LIMIT {
+            listed.push_str(&format!(" and {} more", bad.len() - REPORT_LIMIT));
+        }
+        anyhow::bail!("{} file(s) in the package don't match its manifest: {}", bad.len(), listed);
+    }
+
+    if !dir.join("lib").join("profile").join("AzCore.lib").exists() {
+        anyhow::bail!("The package has no lib/profile/AzCore.lib");
+    }
+    let _ = std::fs::remove_file(&manifest_path);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn package_files_are_plain_file_names() {
+        assert_eq!(file_name("o3de-2510.1-windows-msvc.zip").unwrap(), "o3de-2510.1-windows-msvc.zip");
+        for file in ["", ".", "..", "../launcher.exe", "sub/o3de.zip", "./o3de.zip", "/tmp/o3de.zip"] {
+            assert!(file_name(file).is_err(), "{} was accepted", file);
+        }
+        #[cfg(windows)]
+        for file in ["C:\\o3de.zip", "C:o3de.zip", "..\\o3de.zip", "\\\\server\\share\\o3de.zip"] {
+            assert!(file_name(file).is_err(), "{} was accepted", file);
+        }
+    }
+}
@@ -1,9 +1,73 @@
 use anyhow::{Context, Result};
-use std::process::{Command, Stdio};
-
-use crate::config::Config;
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+use crate::config::{Config, LaunchProfile};
+use crate::game_link::LAUNCHER_PIPE_ENV;
 use crate::logging;
 
+/// Ticks the game simulates for `--smoke-test`.
+const SMOKE_TEST_TICKS: u32 = 300;
+/// A smoke test still running after this long has hung.
+const SMOKE_TEST_TIMEOUT: Duration = Duration::from_secs(300);
+/// Lines of game output quoted when the smoke test fails.
+const SMOKE_TEST_TAIL: usize = 25;
+
+/// What the game printed during the smoke test.
+#[derive(Debug, Default)]
+struct SmokeOutput {
+    passed: bool,
+    last_tick: Option<u32>,
+    /// `key=value` pairs from the `HEADLESS STATS` line.
+    stats: Vec<(String, String)>,
+    panic: Option<String>,
+    tail: VecDeque<String>,
+}
+
+impl SmokeOutput {
+    fn read(&mut self, line: String) {
+        if line.contains("HEADLESS TEST PASSED") {
+            self.passed = true;
+        }
+        if let Some(stats) = line.split("HEADLESS STATS ").nth(1) {
+            self.stats = stats
+                .split_whitespace()
+                .filter_map(|pair| pair.split_once('='))
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect();
+        }
+        // "Tick 20/300 - Delta: ..."
+        let tick = line.split("Tick ").nth(1).and_then(|rest| rest.split('/').next());
+        if let Some(tick) = tick.and_then(|tick| tick.parse().ok()) {
+            self.last_tick = Some(tick);
+        }
+        if self.panic.is_none() && line.contains("panicked at") {
+            self.panic = Some(line.trim().to_string());
+        }
+        self.tail.push_back(line);
+        if self.tail.len() > SMOKE_TEST_TAIL {
+            self.tail.pop_front();
+        }
+    }
+
+    fn stat(&self, key: &str) -> &str {
+        self.stats.iter().find(|(k, _)| k == key).map_or("?", |(_, value)| value.as_str())
+    }
+
+    fn summary(&self) -> String {
+        format!(
+            "{} ticks, {} entities, {} ms per tick on average, {} ms slowest",
+            self.stat("ticks"),
+            self.stat("entities"),
+            self.stat("avg_tick_ms"),
+            self.stat("max_tick_ms")
+        )
+    }
+}
+
 pub struct BuildOrchestrator {
     config: Config,
 }
@@ -38,12 +102,12 @@ impl BuildOrchestrator {
         cmd.env("O3DE_HOME", self.config.o3de_dir());
         cmd.env("VULKAN_SDK", self.config.vulkan_sdk_dir());
         cmd.env("TRACY_DIR", self.config.tracy_dir());
+        // Extra cargo features for the game build, comma separated
+        cmd.env("AAA_CARGO_FEATURES", self.config.game_features().join(","));
 
         cmd.current_dir(&engine_dir);
-        cmd.stdout(Stdio::inherit());
-        cmd.stderr(Stdio::inherit());
 
-        let status = cmd.status().context("Failed to run build orchestrator")?;
+        let status = run_streamed(&mut cmd).context("Failed to run build orchestrator")?;
 
         if !status.success() {
             anyhow::bail!("Build failed with exit code: {:?}", status.code());
@@ -77,7 +141,7 @@ impl BuildOrchestrator {
         }
 
         let cached_version = std::fs::read_to_string(&version_file)?;
-        let current_version = self.get_source_version()?;
+        let current_version = self.build_fingerprint()?;
 
         Ok(cached_version.trim() != current_version.trim())
     }
@@ -93,10 +157,21 @@ impl BuildOrchestrator {
         }
     }
 
+    /// Source version plus enabled features, so toggling a feature rebuilds.
+    fn build_fingerprint(&self) -> Result<String> {
+        let version = self.get_source_version()?;
+        let features = self.config.game_features();
+        if features.is_empty() {
+            Ok(version)
+        } else {
+            Ok(format!("{}+{}", version.trim(), features.join("+")))
+        }
+    }
+
     pub fn save_build_version(&self) -> Result<()> {
         let engine_dir = self.config.engine_dir();
         let version_file = engine_dir.join(".build_version");
-        let version = self.get_source_version()?;
+        let version = self.build_fingerprint()?;
         
         std::fs::write(version_file, version)?;
         
@@ -109,29 +184,196 @@ impl BuildOrchestrator {
         Ok(())
     }
 
-    pub fn launch_game(&self) -> Result<()> {
-        let engine_dir = self.config.engine_dir();
-        let game_exe = engine_dir
+    /// What a finished build leaves in the engine directory, relative to
+    /// it: the version it was built from, the marker and the game.
+    pub fn build_outputs() -> Vec<std::path::PathBuf> {
+        let release = std::path::Path::new("target").join("release");
+        vec![".build_version".into(), release.join(".build_complete"), release.join("aaa-mmorpg.exe")]
+    }
+
+    fn game_exe(&self) -> std::path::PathBuf {
+        self.config
+            .engine_dir()
             .join("target")
             .join("release")
-            .join("aaa-mmorpg.exe");
+            .join("aaa-mmorpg.exe")
+    }
+
+    /// Starts the game as `profile` says, telling it where to send its log
+    /// if `link` is set. With `capture`, its stdout and stderr are piped
+    /// back to the launcher.
+    pub fn launch_game(&self, profile: &LaunchProfile, link: Option<&str>, capture: bool) -> Result<Child> {
+        let engine_dir = self.config.engine_dir();
+        let game_exe = self.game_exe();
 
         if !game_exe.exists() {
             anyhow::bail!("Game executable not found at: {}", game_exe.display());
         }
 
-        logging::info("Launching game...");
+        if profile.args.is_empty() {
+            logging::info("Launching game...");
+        } else {
+            logging::info(&format!("Launching game with: {}", profile.args.join(" ")));
+        }
 
-        Command::new(&game_exe)
-            .current_dir(&engine_dir)
+        let working_dir = match &profile.working_dir {
+            Some(dir) => engine_dir.join(dir),
+            None => engine_dir,
+        };
+        let mut cmd = Command::new(&game_exe);
+        cmd.args(&profile.args)
+            .current_dir(&working_dir)
             .env("O3DE_HOME", self.config.o3de_dir())
             .env("VULKAN_SDK", self.config.vulkan_sdk_dir())
+            .envs(&profile.env);
+        if let Some(link) = link {
+            cmd.env(LAUNCHER_PIPE_ENV, link);
+        }
+        if capture {
+            cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+        }
+        let game = cmd
             .spawn()
-            .context("Failed to launch game")?;
+            .with_context(|| format!("Failed to launch game in {}", working_dir.display()))?;
+
+        if self.config.enable_tracy {
+            self.open_tracy();
+        }
+
+        Ok(game)
+    }
+
+    /// Starts the Tracy profiler GUI; the game's capture client waits for it.
+    fn open_tracy(&self) {
+        let tracy_exe = self.config.tracy_dir().join("Tracy.exe");
+        if !tracy_exe.exists() {
+            logging::warn(&format!(
+                "Tracy capture enabled but {} not found - start Tracy manually and connect to localhost",
+                tracy_exe.display()
+            ));
+            return;
+        }
+        match Command::new(&tracy_exe).args(["-a", "127.0.0.1"]).spawn() {
+            Ok(_) => logging::info("Tracy profiler started"),
+            Err(e) => logging::warn(&format!("Failed to start Tracy: {}", e)),
+        }
+    }
+
+    /// Runs the game's headless `--validate-content` check over content/ and assets/.
+    pub fn validate_content(&self) -> Result<()> {
+        let engine_dir = self.config.engine_dir();
+        let game_exe = self.game_exe();
+
+        if !game_exe.exists() {
+            anyhow::bail!("Game executable not found at: {}", game_exe.display());
+        }
+
+        logging::info("Validating game content...");
+
+        let mut cmd = Command::new(&game_exe);
+        cmd.arg("--validate-content").current_dir(&engine_dir);
+        let status = run_streamed(&mut cmd).context("Failed to run content validation")?;
+
+        if status.success() {
+            logging::success("Content validation passed");
+        } else {
+            logging::warn(&format!("Content validation completed with exit code: {:?}", status.code()));
+        }
 
         Ok(())
     }
 
+    /// Runs the game headless for a few hundred ticks and fails unless it
+    /// reports that it simulated them. Its whole output goes to
+    /// `smoke-test.log`; failures quote the end of it.
+    pub fn run_smoke_test(&self) -> Result<()> {
+        let game_exe = self.game_exe();
+        if !game_exe.exists() {
+            anyhow::bail!("Game executable not found at: {}", game_exe.display());
+        }
+
+        logging::info(&format!("Smoke test: running the game headless for {} ticks...", SMOKE_TEST_TICKS));
+
+        let log_path = self.config.logs_dir().join("smoke-test.log");
+        let mut log = std::fs::File::create(&log_path)
+            .with_context(|| format!("Failed to create {}", log_path.display()))?;
+
+        let mut child = Command::new(&game_exe)
+            .args(["--headless", "--ticks", &SMOKE_TEST_TICKS.to_string()])
+            .current_dir(self.config.engine_dir())
+            .env("O3DE_HOME", self.config.o3de_dir())
+            .env("VULKAN_SDK", self.config.vulkan_sdk_dir())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("Failed to start the game for the smoke test")?;
+
+        // Both streams, a line at a time, so a hung game can still be timed out
+        let (sender, lines) = mpsc::channel();
+        let stdout = child.stdout.take().map(|s| Box::new(s) as Box<dyn Read + Send>);
+        let stderr = child.stderr.take().map(|s| Box::new(s) as Box<dyn Read + Send>);
+        for stream in [stdout, stderr].into_iter().flatten() {
+            let sender = sender.clone();
+            std::thread::spawn(move || {
+                for line in BufReader::new(stream).lines().map_while(|line| line.ok()) {
+                    if sender.send(line).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(sender);
+
+        let deadline = Instant::now() + SMOKE_TEST_TIMEOUT;
+        let mut output = SmokeOutput::default();
+        let mut timed_out = false;
+        loop {
+            match lines.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok(line) => {
+                    let _ = writeln!(log, "{}", line);
+                    if self.config.verbose {
+                        logging::output(&line);
+                    }
+                    output.read(line);
+                }
+                Err(RecvTimeoutError::Disconnected) => break,
+                Err(RecvTimeoutError::Timeout) => {
+                    timed_out = true;
+                    let _ = child.kill();
+                    break;
+                }
+            }
+        }
+        let status = child.wait().context("Failed to wait for the smoke test")?;
+
+        if output.passed && status.success() && !timed_out {
+            logging::success(&format!("Smoke test passed: {}", output.summary()));
+            return Ok(());
+        }
+
+        let reason = if timed_out {
+            format!("still running after {}s", SMOKE_TEST_TIMEOUT.as_secs())
+        } else if let Some(panic) = &output.panic {
+            format!("the game panicked ({})", panic)
+        } else if !status.success() {
+            format!("the game exited with code {:?}", status.code())
+        } else {
+            "the game exited without reporting HEADLESS TEST PASSED".to_string()
+        };
+        logging::error(&format!("Smoke test failed: {}", reason));
+        match output.last_tick {
+            Some(tick) => logging::error(&format!("Last tick reached: {}/{}", tick, SMOKE_TEST_TICKS)),
+            None => logging::error("The game didn't reach its first tick report"),
+        }
+        if !self.config.verbose && !output.tail.is_empty() {
+            logging::info("Last game output:");
+            for line in &output.tail {
+                logging::output(line);
+            }
+        }
+        anyhow::bail!("Game smoke test failed: {} (full output in {})", reason, log_path.display())
+    }
+
     pub fn build_render_fabric(&self) -> Result<()> {
         let engine_dir = self.config.engine_dir();
         let atom_bridge_dir = engine_dir.join("atom-bridge").join("cpp");
@@ -149,10 +391,8 @@ impl BuildOrchestrator {
         ]);
         cmake_configure.current_dir(&build_dir);
         cmake_configure.env("VULKAN_SDK", self.config.vulkan_sdk_dir());
-        cmake_configure.stdout(Stdio::inherit());
-        cmake_configure.stderr(Stdio::inherit());
 
-        let status = cmake_configure.status().context("Failed to run cmake configure")?;
+        let status = run_streamed(&mut cmake_configure).context("Failed to run cmake configure")?;
         if !status.success() {
             anyhow::bail!("CMake configure failed");
         }
@@ -160,10 +400,8 @@ impl BuildOrchestrator {
         let mut cmake_build = Command::new("cmake");
         cmake_build.args(["--build", ".", "--config", "Release", "-j"]);
         cmake_build.current_dir(&build_dir);
-        cmake_build.stdout(Stdio::inherit());
-        cmake_build.stderr(Stdio::inherit());
 
-        let status = cmake_build.status().context("Failed to run cmake build")?;
+        let status = run_streamed(&mut cmake_build).context("Failed to run cmake build")?;
         if !status.success() {
             anyhow::bail!("CMake build failed");
         }
@@ -203,10 +441,8 @@ impl BuildOrchestrator {
 
         let mut cmd = Command::new(test_exe);
         cmd.env("VULKAN_SDK", self.config.vulkan_sdk_dir());
-        cmd.stdout(Stdio::inherit());
-        cmd.stderr(Stdio::inherit());
 
-        let status = cmd.status().context("Failed to run validation test")?;
+        let status = run_streamed(&mut cmd).context("Failed to run validation test")?;
 
         if status.success() {
             logging::success("All validation tests PASSED - Frame graph barriers are Vulkan-compliant!");
@@ -217,3 +453,31 @@ impl BuildOrchestrator {
         Ok(())
     }
 }
+
+/// Runs `cmd` to completion. Its output goes straight to the console, or
+/// through the log line by line while the GUI is showing it.
+fn run_streamed(cmd: &mut Command) -> std::io::Result<ExitStatus> {
+    if !logging::attached() {
+        cmd.stdout(Stdio::inherit());
+        cmd.stderr(Stdio::inherit());
+        return cmd.status();
+    }
+
+    cmd.stdout(Stdio::piped());
+    cmd.stderr(Stdio::piped());
+    let mut child = cmd.spawn()?;
+    let stderr = child.stderr.take().map(|stderr| std::thread::spawn(move || forward_lines(stderr)));
+    if let Some(stdout) = child.stdout.take() {
+        forward_lines(stdout);
+    }
+    if let Some(stderr) = stderr {
+        let _ = stderr.join();
+    }
+    child.wait()
+}
+
+fn forward_lines(stream: impl Read) {
+    for line in BufReader::new(stream).lines().map_while(|line| line.ok()) {
+        logging::output(&line);
+    }
+}
@@ -0,0 +1,131 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::config::Config;
+
+/// The game's own content; always loaded first and can't be disabled.
+const CORE_PACK: &str = "core";
+
+/// The parts of `packs/<id>/pack.toml` the launcher shows.
+#[derive(Debug, Deserialize)]
+struct PackManifest {
+    id: String,
+    name: String,
+    version: String,
+    #[serde(default)]
+    description: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct LoadOrderEntry {
+    id: String,
+    #[serde(default = "enabled_by_default")]
+    enabled: bool,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+/// `packs/load_order.toml`, read by the game at startup.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct LoadOrderFile {
+    #[serde(default, rename = "pack")]
+    packs: Vec<LoadOrderEntry>,
+}
+
+pub struct PackManager {
+    packs_dir: PathBuf,
+}
+
+impl PackManager {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            packs_dir: config.engine_dir().join("packs"),
+        }
+    }
+
+    fn load_order_path(&self) -> PathBuf {
+        self.packs_dir.join("load_order.toml")
+    }
+
+    fn installed(&self) -> Vec<PackManifest> {
+        let Ok(entries) = std::fs::read_dir(&self.packs_dir) else {
+            return Vec::new();
+        };
+        let mut manifests: Vec<PackManifest> = entries
+            .flatten()
+            .filter_map(|entry| std::fs::read_to_string(entry.path().join("pack.toml")).ok())
+            .filter_map(|text| toml::from_str(&text).ok())
+            .collect();
+        manifests.sort_by(|a, b| a.id.cmp(&b.id));
+        manifests
+    }
+
+    fn load_order(&self) -> Result<LoadOrderFile> {
+        let path = self.load_order_path();
+        if !path.exists() {
+            return Ok(LoadOrderFile::default());
+        }
+        let text = std::fs::read_to_string(&path)?;
+        toml::from_str(&text).with_context(|| format!("Invalid load order file {}", path.display()))
+    }
+
+    /// Prints installed packs in load order; packs the load order doesn't
+    /// mention load last, enabled.
+    pub fn list(&self) -> Result<()> {
+        let installed = self.installed();
+        let load_order = self.load_order()?;
+
+        println!("Content packs in {}:", self.packs_dir.display());
+        println!("  [x] {:<24} (built in)", CORE_PACK);
+        let listed = load_order
+            .packs
+            .iter()
+            .filter_map(|entry| installed.iter().find(|m| m.id == entry.id).map(|m| (m, entry.enabled)));
+        let unlisted = installed
+            .iter()
+            .filter(|m| !load_order.packs.iter().any(|e| e.id == m.id))
+            .map(|m| (m, true));
+        for (manifest, enabled) in listed.chain(unlisted) {
+            println!(
+                "  [{}] {:<24} {} {}",
+                if enabled { "x" } else { " " },
+                manifest.id,
+                manifest.version,
+                manifest.name
+            );
+            if !manifest.description.is_empty() {
+                println!("      {}", manifest.description);
+            }
+        }
+        if installed.is_empty() {
+            println!("  (no mods installed)");
+        }
+        Ok(())
+    }
+
+    pub fn set_enabled(&self, id: &str, enabled: bool) -> Result<()> {
+        if id == CORE_PACK {
+            anyhow::bail!("The core pack is always loaded");
+        }
+        if !self.installed().iter().any(|m| m.id == id) {
+            anyhow::bail!("No content pack '{}' in {}", id, self.packs_dir.display());
+        }
+
+        let mut load_order = self.load_order()?;
+        match load_order.packs.iter_mut().find(|e| e.id == id) {
+            Some(entry) => entry.enabled = enabled,
+            None => load_order.packs.push(LoadOrderEntry {
+                id: id.to_string(),
+                enabled,
+            }),
+        }
+
+        std::fs::create_dir_all(&self.packs_dir)?;
+        std::fs::write(self.load_order_path(), toml::to_string_pretty(&load_order)?)?;
+        println!("{} content pack '{}'", if enabled { "Enabled" } else { "Disabled" }, id);
+        Ok(())
+    }
+}
@@ -0,0 +1,130 @@
+//! Binary patches between engine versions.
+//!
+//! `/sync/patch/<from>/<to>` answers with a [`PatchManifest`] (404 when there
+//! is no way from `from` to `to`). Each changed file is rebuilt from
+//! [`PatchOp`]s: block copies out of the old file and literal bytes out of
+//! the patch's data blob at `/sync/patch/<from>/<to>/data`, so only changed
+//! blocks are downloaded. Without a direct patch the server answers with the
+//! first hop of a chain, whose `to` is an intermediate version; the launcher
+//! applies hops until it reaches the version it asked for.
+//!
+//! The manifest's checksums are what the patched files are checked against,
+//! so it has to be signed like `manifest.json`: `/sync/patch/<from>/<to>.sig`
+//! holds a release key's signature over it as [`MANIFEST_FILE`], and a patch
+//! without a valid one isn't applied.
+
+use anyhow::Result;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use crate::sync::SyncManager;
+
+/// The name a patch manifest's signature covers.
+pub const MANIFEST_FILE: &str = "patch.json";
+
+#[derive(Debug, Deserialize)]
+pub struct PatchManifest {
+    pub from: String,
+    /// The version this patch produces.
+    pub to: String,
+    /// Changed and added files. Files not listed are unchanged.
+    #[serde(default)]
+    pub files: HashMap<String, FilePatch>,
+    #[serde(default)]
+    pub removed: Vec<String>,
+    /// Bytes in the data blob; zero when every op is a copy.
+    #[serde(default)]
+    pub data_size: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FilePatch {
+    /// Checksum of the file the ops copy from; `None` for a new file.
+    #[serde(default)]
+    pub base_checksum: Option<String>,
+    /// Checksum and size of the patched file.
+    pub checksum: String,
+    pub size: u64,
+    pub ops: Vec<PatchOp>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PatchOp {
+    /// Bytes from the old file.
+    Copy { offset: u64, length: u64 },
+    /// Bytes from the data blob.
+    Data { offset: u64, length: u64 },
+}
+
+/// Builds the patched file from `base` (the old file) and `data` (the
+/// patch's data blob), checking the result against the patch's checksum.
+pub fn apply(base: &[u8], data: &[u8], patch: &FilePatch) -> Result<Vec<u8>> {
+    let mut output = Vec::with_capacity(patch.size as usize);
+    for op in &patch.ops {
+        let (source, name, offset, length) = match *op {
+            PatchOp::Copy { offset, length } => (base, "old file", offset, length),
+            PatchOp::Data { offset, length } => (data, "patch data", offset, length),
+        };
+        let bytes = usize::try_from(offset)
+            .ok()
+            .zip(usize::try_from(length).ok())
+            .and_then(|(start, length)| source.get(start..start.checked_add(length)?));
+        let Some(bytes) = bytes else {
+            anyhow::bail!("{} bytes at {} are past the end of the {} ({} bytes)", length, offset, name, source.len());
+        };
+        output.extend_from_slice(bytes);
+    }
+
+    if output.len() as u64 != patch.size {
+        anyhow::bail!("patched file is {} bytes, expected {}", output.len(), patch.size);
+    }
+    let checksum = SyncManager::checksum_bytes(&output);
+    if checksum != patch.checksum {
+        anyhow::bail!("patched file checksum {} doesn't match {}", checksum, patch.checksum);
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file_patch(ops: Vec<PatchOp>, expected: &[u8]) -> FilePatch {
+        FilePatch {
+            base_checksum: None,
+            checksum: SyncManager::checksum_bytes(expected),
+            size: expected.len() as u64,
+            ops,
+        }
+    }
+
+    #[test]
+    fn rebuilds_a_file_from_copies_and_data() {
+        let patch = file_patch(
+            vec![
+                PatchOp::Copy { offset: 0, length: 6 },
+                PatchOp::Data { offset: 2, length: 5 },
+                PatchOp::Copy { offset: 5, length: 6 },
+            ],
+            b"hello brave world",
+        );
+        assert_eq!(apply(b"hello world", b"..brave", &patch).unwrap(), b"hello brave world");
+    }
+
+    #[test]
+    fn rejects_ops_past_the_end() {
+        let patch = file_patch(vec![PatchOp::Copy { offset: 4, length: 8 }], b"whatever");
+        assert!(apply(b"short", b"", &patch).is_err());
+        let patch = file_patch(vec![PatchOp::Data { offset: u64::MAX, length: 2 }], b"xx");
+        assert!(apply(b"", b"xx", &patch).is_err());
+    }
+
+    #[test]
+    fn rejects_a_result_that_doesnt_match() {
+        let mut patch = file_patch(vec![PatchOp::Copy { offset: 0, length: 5 }], b"hello");
+        assert!(apply(b"jello", b"", &patch).is_err());
+        patch.size = 4;
+        assert!(apply(b"hello", b"", &patch).is_err());
+    }
+}
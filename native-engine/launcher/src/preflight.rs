@@ -0,0 +1,153 @@
+//! Checks the machine can take the install before anything is downloaded.
+//!
+//! The engine's source build needs about 60 GB and a fair amount of memory,
+//! and without this the launcher only found out when the build died halfway.
+//! The minimums are in the config (`min_free_disk_gb`, `min_ram_gb`,
+//! `min_cpu_cores`; 0 skips a check). Once the engine has been built the
+//! space is mostly taken already, so an update only needs a quarter of
+//! `min_free_disk_gb` free.
+
+use anyhow::Result;
+use std::path::Path;
+
+use crate::config::Config;
+use crate::logging;
+
+const GB: u64 = 1024 * 1024 * 1024;
+
+/// Share of `min_free_disk_gb` an already built engine needs for updates.
+const UPDATE_DISK_DIVISOR: u64 = 4;
+
+/// Checks free disk space, RAM and CPU cores. With `strict`, anything short
+/// fails the run; otherwise (`--dry-run`) it's only reported.
+pub fn run(config: &Config, strict: bool) -> Result<()> {
+    let mut problems = Vec::new();
+
+    let built = config.engine_dir().join(".build_version").exists();
+    let needed_disk = if built {
+        config.min_free_disk_gb.div_ceil(UPDATE_DISK_DIVISOR)
+    } else {
+        config.min_free_disk_gb
+    };
+    if needed_disk > 0 {
+        match free_disk_space(&config.install_dir) {
+            Ok(free) => {
+                logging::info(&format!(
+                    "Free space on the install drive: {:.1} GB (need {} GB)",
+                    free as f64 / GB as f64,
+                    needed_disk
+                ));
+                if free < needed_disk * GB {
+                    problems.push(format!(
+                        "Only {:.1} GB free on the drive with {} - the engine build needs {} GB. \
+                         Free up space or move install_dir in the launcher config to another drive",
+                        free as f64 / GB as f64,
+                        config.install_dir.display(),
+                        needed_disk
+                    ));
+                }
+            }
+            Err(e) => logging::warn(&format!("Could not check free disk space: {:#}", e)),
+        }
+    }
+
+    if config.min_ram_gb > 0 {
+        match total_memory() {
+            Ok(memory) => {
+                logging::info(&format!(
+                    "Memory: {:.1} GB (need {} GB)",
+                    memory as f64 / GB as f64,
+                    config.min_ram_gb
+                ));
+                // Firmware and integrated graphics keep a little back from the installed amount
+                if memory < config.min_ram_gb * GB * 15 / 16 {
+                    problems.push(format!(
+                        "{:.1} GB of memory - the engine build needs {} GB",
+                        memory as f64 / GB as f64,
+                        config.min_ram_gb
+                    ));
+                }
+            }
+            Err(e) => logging::warn(&format!("Could not check memory: {:#}", e)),
+        }
+    }
+
+    if config.min_cpu_cores > 0 {
+        let cores = std::thread::available_parallelism().map_or(1, |cores| cores.get());
+        logging::info(&format!("CPU threads: {} (need {})", cores, config.min_cpu_cores));
+        if cores < config.min_cpu_cores {
+            problems.push(format!("{} CPU threads - the engine build needs {}", cores, config.min_cpu_cores));
+        }
+    }
+
+    if problems.is_empty() {
+        logging::success("This machine meets the requirements");
+        return Ok(());
+    }
+    for problem in &problems {
+        logging::error(problem);
+    }
+    if !strict {
+        logging::info("Dry-run mode: continuing anyway");
+        return Ok(());
+    }
+    anyhow::bail!(
+        "This machine doesn't meet the requirements ({} problem(s) above). \
+         The minimums are min_free_disk_gb, min_ram_gb and min_cpu_cores in the launcher config",
+        problems.len()
+    )
+}
+
+#[cfg(windows)]
+fn free_disk_space(path: &Path) -> Result<u64> {
+    use std::os::windows::ffi::OsStrExt;
+
+    let wide: Vec<u16> = path.as_os_str().encode_wide().chain(std::iter::once(0)).collect();
+    let mut free: winapi::um::winnt::ULARGE_INTEGER = unsafe { std::mem::zeroed() };
+    let ok = unsafe {
+        winapi::um::fileapi::GetDiskFreeSpaceExW(
+            wide.as_ptr(),
+            &mut free,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        )
+    };
+    if ok == 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    Ok(unsafe { *free.QuadPart() })
+}
+
+#[cfg(not(windows))]
+fn free_disk_space(path: &Path) -> Result<u64> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = std::ffi::CString::new(path.as_os_str().as_bytes())?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    Ok(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(windows)]
+fn total_memory() -> Result<u64> {
+    use winapi::um::sysinfoapi::{GlobalMemoryStatusEx, MEMORYSTATUSEX};
+
+    let mut status: MEMORYSTATUSEX = unsafe { std::mem::zeroed() };
+    status.dwLength = std::mem::size_of::<MEMORYSTATUSEX>() as u32;
+    if unsafe { GlobalMemoryStatusEx(&mut status) } == 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    Ok(status.ullTotalPhys)
+}
+
+#[cfg(not(windows))]
+fn total_memory() -> Result<u64> {
+    let pages = unsafe { libc::sysconf(libc::_SC_PHYS_PAGES) };
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    if pages <= 0 || page_size <= 0 {
+        anyhow::bail!("sysconf doesn't report physical memory");
+    }
+    Ok(pages as u64 * page_size as u64)
+}
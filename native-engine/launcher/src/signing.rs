@@ -0,0 +1,359 @@
+//! Ed25519 signatures on launcher updates.
+//!
+//! A checksum from the update server only shows the download wasn't
+//! corrupted; whoever controls the server controls both. An update is
+//! applied only if it's also signed by a key the launcher already trusts:
+//! the one built into it, or one it was handed by a keys manifest signed by
+//! a key it trusted before. That manifest is how the key is rotated: the
+//! server publishes the new key list, signed with the old key, and the
+//! launcher adds it to the chain in `update_keys.json`. Every load replays
+//! that chain from the built-in key, so editing the file trusts nothing.
+//!
+//! Keys and signatures are hex. An update's signature is over
+//! [`update_message`], a file `--gen-manifest` signs over [`file_message`].
+
+use anyhow::{Context, Result};
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::logging;
+
+/// The release key's public half, hex. Move to a new key with a keys
+/// manifest rather than by changing this, or installed launchers won't
+/// follow.
+const BUILT_IN_KEY: &str = include_str!("../release-key.pub");
+
+const KEYS_FILE: &str = "update_keys.json";
+
+/// The keys manifest as served: `body` is the JSON text of a [`KeysBody`],
+/// signed as is so nothing depends on how it's re-serialized.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedKeys {
+    body: String,
+    signatures: Vec<KeySignature>,
+}
+
+/// A signature and the public key that made it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeySignature {
+    pub key: String,
+    pub signature: String,
+}
+
+/// `update_keys.json`: every keys manifest accepted, oldest first.
+#[derive(Debug, Serialize, Deserialize)]
+struct KeyChain {
+    manifests: Vec<SignedKeys>,
+}
+
+#[derive(Debug, Deserialize)]
+struct KeysBody {
+    /// Only a higher serial replaces the keys, so an old manifest can't be
+    /// served again to bring back a retired key.
+    serial: u64,
+    keys: Vec<String>,
+}
+
+/// The message an update's signature covers: the version and the SHA-256
+/// of the binary, so a signed binary can't be passed off as another version.
+pub fn update_message(version: &str, sha256: &str) -> Vec<u8> {
+    format!("aaa-launcher-update\n{}\n{}\n", version, sha256.to_ascii_lowercase()).into_bytes()
+}
+
+/// The message a published file's signature covers: its name and contents,
+/// so the signature of one file can't vouch for another.
+pub fn file_message(name: &str, contents: &[u8]) -> Vec<u8> {
+    let mut message = format!("aaa-engine-file\n{}\n", name).into_bytes();
+    message.extend_from_slice(contents);
+    message
+}
+
+/// The private half of a release key, for signing what the server
+/// publishes. The key file holds the 32-byte seed as hex, e.g. from
+/// `openssl rand -hex 32`.
+pub struct ReleaseKey(SigningKey);
+
+impl ReleaseKey {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let seed: [u8; 32] = hex::decode(text.trim())
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| anyhow::anyhow!("{} isn't a 32-byte hex key", path.display()))?;
+        Ok(Self(SigningKey::from_bytes(&seed)))
+    }
+
+    /// The public half, hex, as a keys manifest lists it.
+    pub fn public_key(&self) -> String {
+        hex::encode(self.0.verifying_key().to_bytes())
+    }
+
+    /// Whether launchers trust this key without a keys manifest.
+    pub fn is_built_in(&self) -> bool {
+        self.public_key().eq_ignore_ascii_case(BUILT_IN_KEY.trim())
+    }
+
+    pub fn sign(&self, message: &[u8]) -> KeySignature {
+        KeySignature {
+            key: self.public_key(),
+            signature: hex::encode(self.0.sign(message).to_bytes()),
+        }
+    }
+}
+
+/// The keys updates are checked against.
+pub struct TrustedKeys {
+    serial: u64,
+    keys: Vec<VerifyingKey>,
+    /// The manifests that got from the built-in key to `keys`.
+    chain: Vec<SignedKeys>,
+    path: PathBuf,
+}
+
+impl TrustedKeys {
+    /// The built-in key, rotated by each manifest kept in `update_keys.json`.
+    pub fn load(config: &Config) -> Result<Self> {
+        let root = parse_key(BUILT_IN_KEY).context("Built-in update key is invalid")?;
+        Self::replay(root, config.install_dir.join(KEYS_FILE))
+    }
+
+    /// `root`, rotated by the chain at `path`. Each manifest has to be signed
+    /// by the keys before it, as when it was first accepted.
+    fn replay(root: VerifyingKey, path: PathBuf) -> Result<Self> {
+        let mut trusted = Self {
+            serial: 0,
+            keys: vec![root],
+            chain: Vec::new(),
+            path,
+        };
+        if !trusted.path.exists() {
+            return Ok(trusted);
+        }
+        let text = std::fs::read_to_string(&trusted.path)?;
+        let chain: KeyChain =
+            serde_json::from_str(&text).with_context(|| format!("Invalid {}", trusted.path.display()))?;
+        for signed in chain.manifests {
+            let accepted = trusted
+                .accept(&signed)
+                .with_context(|| format!("{} doesn't follow from the built-in key", trusted.path.display()))?;
+            if accepted.is_none() {
+                anyhow::bail!("{} lists update keys out of order", trusted.path.display());
+            }
+            trusted.chain.push(signed);
+        }
+        Ok(trusted)
+    }
+
+    /// Takes the keys in `signed` if one of the current keys signed it and
+    /// it's newer, and keeps it for next time. A manifest no trusted key
+    /// signed is an error: someone other than the key holder wrote it.
+    pub fn rotate(&mut self, signed: SignedKeys) -> Result<()> {
+        let current = self.serial;
+        let offered = parse_body(&signed.body)?.serial;
+        let Some(signer) = self
+            .accept(&signed)
+            .context("Update keys from the server can't be trusted - refusing to update")?
+        else {
+            if offered < current {
+                logging::warn(&format!(
+                    "Server offered update keys #{}, older than #{} - keeping the current keys",
+                    offered, current
+                ));
+            }
+            return Ok(());
+        };
+
+        self.chain.push(signed);
+        let chain = KeyChain {
+            manifests: self.chain.clone(),
+        };
+        std::fs::write(&self.path, serde_json::to_string_pretty(&chain)?)
+            .with_context(|| format!("Failed to write {}", self.path.display()))?;
+        logging::info(&format!(
+            "Update keys rotated to #{} ({} keys, signed by {})",
+            self.serial,
+            self.keys.len(),
+            signer
+        ));
+        Ok(())
+    }
+
+    /// Moves to the keys in `signed`, returning who signed it, or `None` if
+    /// it isn't newer than the current ones.
+    fn accept(&mut self, signed: &SignedKeys) -> Result<Option<String>> {
+        let Some(signer) = signed
+            .signatures
+            .iter()
+            .find(|s| self.verify_with(&s.key, signed.body.as_bytes(), &s.signature))
+        else {
+            anyhow::bail!("Update keys aren't signed by a trusted key");
+        };
+        let body = parse_body(&signed.body)?;
+        if body.serial <= self.serial {
+            return Ok(None);
+        }
+        let keys = parse_keys(&body.keys)?;
+        if keys.is_empty() {
+            anyhow::bail!("Update keys #{} list no keys", body.serial);
+        }
+        self.serial = body.serial;
+        self.keys = keys;
+        Ok(Some(key_id(&signer.key).to_string()))
+    }
+
+    /// Checks `signature` on `message` against every trusted key.
+    pub fn verify(&self, message: &[u8], signature: &str) -> Result<()> {
+        let signature = parse_signature(signature)?;
+        if self.keys.iter().any(|key| key.verify_strict(message, &signature).is_ok()) {
+            Ok(())
+        } else {
+            anyhow::bail!("Signature doesn't match any trusted update key")
+        }
+    }
+
+    /// Checks a `.sig` file, as `--gen-manifest` writes it, on the contents
+    /// of the file published as `name`.
+    pub fn verify_file(&self, name: &str, contents: &[u8], signature: &[u8]) -> Result<()> {
+        let signature: KeySignature = serde_json::from_slice(signature).context("Invalid signature file")?;
+        self.verify(&file_message(name, contents), &signature.signature)
+    }
+
+    /// Whether `key` is trusted and signed `message`.
+    fn verify_with(&self, key: &str, message: &[u8], signature: &str) -> bool {
+        let (Ok(key), Ok(signature)) = (parse_key(key), parse_signature(signature)) else {
+            return false;
+        };
+        self.keys.contains(&key) && key.verify_strict(message, &signature).is_ok()
+    }
+}
+
+fn parse_body(body: &str) -> Result<KeysBody> {
+    serde_json::from_str(body).context("Invalid update keys manifest")
+}
+
+fn parse_keys(keys: &[String]) -> Result<Vec<VerifyingKey>> {
+    keys.iter().map(|key| parse_key(key)).collect()
+}
+
+fn parse_key(key: &str) -> Result<VerifyingKey> {
+    let bytes: [u8; 32] = hex::decode(key.trim())
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| anyhow::anyhow!("Invalid update key {}", key))?;
+    VerifyingKey::from_bytes(&bytes).with_context(|| format!("Invalid update key {}", key))
+}
+
+fn parse_signature(signature: &str) -> Result<Signature> {
+    let bytes = hex::decode(signature.trim()).context("Invalid signature")?;
+    Signature::from_slice(&bytes).context("Invalid signature")
+}
+
+/// A key's first 8 bytes, for logs.
+pub fn key_id(key: &str) -> &str {
+    key.get(..16).unwrap_or(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(seed: u8) -> SigningKey {
+        SigningKey::from_bytes(&[seed; 32])
+    }
+
+    fn public(key: &SigningKey) -> String {
+        hex::encode(key.verifying_key().to_bytes())
+    }
+
+    fn sign(key: &SigningKey, message: &[u8]) -> String {
+        hex::encode(key.sign(message).to_bytes())
+    }
+
+    fn keys_manifest(serial: u64, keys: &[&SigningKey], signer: &SigningKey) -> SignedKeys {
+        let keys: Vec<String> = keys.iter().map(|k| format!("\"{}\"", public(k))).collect();
+        let body = format!("{{\"serial\": {}, \"keys\": [{}]}}", serial, keys.join(", "));
+        SignedKeys {
+            signatures: vec![KeySignature {
+                key: public(signer),
+                signature: sign(signer, body.as_bytes()),
+            }],
+            body,
+        }
+    }
+
+    fn trusted(root: &SigningKey, dir: &Path) -> Result<TrustedKeys> {
+        TrustedKeys::replay(root.verifying_key(), dir.join(KEYS_FILE))
+    }
+
+    #[test]
+    fn the_built_in_key_is_a_real_key() {
+        parse_key(BUILT_IN_KEY).unwrap();
+    }
+
+    #[test]
+    fn verifies_only_trusted_signatures() {
+        let dir = tempfile::tempdir().unwrap();
+        let keys = trusted(&key(1), dir.path()).unwrap();
+        let message = update_message("1.2.0", "ABCD");
+
+        keys.verify(&message, &sign(&key(1), &message)).unwrap();
+        assert!(keys.verify(&message, &sign(&key(2), &message)).is_err());
+        assert!(keys.verify(&update_message("1.2.1", "abcd"), &sign(&key(1), &message)).is_err());
+        assert!(keys.verify(&message, "not hex").is_err());
+    }
+
+    #[test]
+    fn rotation_moves_trust_and_survives_a_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut keys = trusted(&key(1), dir.path()).unwrap();
+        keys.rotate(keys_manifest(1, &[&key(2)], &key(1))).unwrap();
+        keys.rotate(keys_manifest(2, &[&key(3)], &key(2))).unwrap();
+
+        let message = file_message("manifest.json", b"{}");
+        for keys in [keys, trusted(&key(1), dir.path()).unwrap()] {
+            assert_eq!(keys.serial, 2);
+            keys.verify(&message, &sign(&key(3), &message)).unwrap();
+            assert!(keys.verify(&message, &sign(&key(1), &message)).is_err());
+            assert!(keys.verify(&message, &sign(&key(2), &message)).is_err());
+        }
+    }
+
+    #[test]
+    fn rejects_rotations_no_trusted_key_signed() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut keys = trusted(&key(1), dir.path()).unwrap();
+
+        assert!(keys.rotate(keys_manifest(1, &[&key(9)], &key(9))).is_err());
+        assert!(keys.rotate(keys_manifest(1, &[], &key(1))).is_err());
+        let mut tampered = keys_manifest(1, &[&key(2)], &key(1));
+        tampered.body = tampered.body.replace(&public(&key(2)), &public(&key(9)));
+        assert!(keys.rotate(tampered).is_err());
+        assert_eq!(keys.serial, 0);
+        assert!(!dir.path().join(KEYS_FILE).exists());
+
+        // An older manifest is ignored rather than brought back
+        keys.rotate(keys_manifest(2, &[&key(2)], &key(1))).unwrap();
+        keys.rotate(keys_manifest(1, &[&key(9)], &key(2))).unwrap();
+        assert_eq!(keys.serial, 2);
+    }
+
+    #[test]
+    fn rejects_an_edited_keys_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut keys = trusted(&key(1), dir.path()).unwrap();
+        keys.rotate(keys_manifest(1, &[&key(2)], &key(1))).unwrap();
+
+        let path = dir.path().join(KEYS_FILE);
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::write(&path, text.replace(&public(&key(2)), &public(&key(9)))).unwrap();
+        assert!(trusted(&key(1), dir.path()).is_err());
+
+        let forged = KeyChain {
+            manifests: vec![keys_manifest(1, &[&key(9)], &key(9))],
+        };
+        std::fs::write(&path, serde_json::to_string(&forged).unwrap()).unwrap();
+        assert!(trusted(&key(1), dir.path()).is_err());
+    }
+}
@@ -5,9 +5,11 @@ use std::fmt;
 pub enum LauncherState {
     Init,
     SelfUpdate,
+    Preflight,
     DependencyAudit,
     Sync,
     Build,
+    Compatibility,
     Launch,
     Complete,
     Failed,
@@ -18,9 +20,11 @@ impl fmt::Display for LauncherState {
         match self {
             LauncherState::Init => write!(f, "Initializing"),
             LauncherState::SelfUpdate => write!(f, "Checking for Updates"),
+            LauncherState::Preflight => write!(f, "Checking System Requirements"),
             LauncherState::DependencyAudit => write!(f, "Verifying Dependencies"),
             LauncherState::Sync => write!(f, "Syncing Files"),
             LauncherState::Build => write!(f, "Building Engine"),
+            LauncherState::Compatibility => write!(f, "Checking GPU Compatibility"),
             LauncherState::Launch => write!(f, "Launching Game"),
             LauncherState::Complete => write!(f, "Complete"),
             LauncherState::Failed => write!(f, "Failed"),
@@ -32,10 +36,12 @@ impl LauncherState {
     pub fn next(self) -> Option<LauncherState> {
         match self {
             LauncherState::Init => Some(LauncherState::SelfUpdate),
-            LauncherState::SelfUpdate => Some(LauncherState::DependencyAudit),
+            LauncherState::SelfUpdate => Some(LauncherState::Preflight),
+            LauncherState::Preflight => Some(LauncherState::DependencyAudit),
             LauncherState::DependencyAudit => Some(LauncherState::Sync),
             LauncherState::Sync => Some(LauncherState::Build),
-            LauncherState::Build => Some(LauncherState::Launch),
+            LauncherState::Build => Some(LauncherState::Compatibility),
+            LauncherState::Compatibility => Some(LauncherState::Launch),
             LauncherState::Launch => Some(LauncherState::Complete),
             LauncherState::Complete => None,
             LauncherState::Failed => None,
@@ -46,17 +52,19 @@ impl LauncherState {
         match self {
             LauncherState::Init => 0,
             LauncherState::SelfUpdate => 1,
-            LauncherState::DependencyAudit => 2,
-            LauncherState::Sync => 3,
-            LauncherState::Build => 4,
-            LauncherState::Launch => 5,
-            LauncherState::Complete => 6,
+            LauncherState::Preflight => 2,
+            LauncherState::DependencyAudit => 3,
+            LauncherState::Sync => 4,
+            LauncherState::Build => 5,
+            LauncherState::Compatibility => 6,
+            LauncherState::Launch => 7,
+            LauncherState::Complete => 8,
             LauncherState::Failed => 0,
         }
     }
 
     pub fn total_steps() -> u8 {
-        6
+        8
     }
 }
 
@@ -84,9 +92,11 @@ impl StateMachine {
         match state_str {
             "Init" => Some(LauncherState::Init),
             "SelfUpdate" => Some(LauncherState::SelfUpdate),
+            "Preflight" => Some(LauncherState::Preflight),
             "DependencyAudit" => Some(LauncherState::DependencyAudit),
             "Sync" => Some(LauncherState::Sync),
             "Build" => Some(LauncherState::Build),
+            "Compatibility" => Some(LauncherState::Compatibility),
             "Launch" => Some(LauncherState::Launch),
             "Complete" => Some(LauncherState::Complete),
             "Failed" => Some(LauncherState::Failed),
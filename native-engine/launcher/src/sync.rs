@@ -1,22 +1,95 @@
 use anyhow::{Context, Result};
+use indicatif::ProgressBar;
 use sha2::{Digest, Sha256};
-use std::collections::HashMap;
-use std::path::{Path, PathBuf};
+use std::collections::{BTreeMap, HashSet};
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
 
 use crate::config::Config;
+use crate::download_limit::{self, RateLimiter};
+use crate::http;
+use crate::local_source::LocalSource;
 use crate::logging;
-
-#[derive(Debug, serde::Deserialize)]
+use crate::patch::{self, PatchManifest};
+use crate::signing::TrustedKeys;
+use crate::versions::RollbackPoint;
+
+/// Longest patch chain followed before giving up on patching.
+const MAX_PATCH_HOPS: usize = 16;
+
+/// Longest wait between download retries.
+const MAX_RETRY_DELAY_SECS: u64 = 30;
+
+/// Engine directory entries that never come from the server: build output,
+/// launcher state and the player's own files. `--verify` doesn't call them extra.
+const UNSYNCED: &[&str] = &[
+    "target",
+    "build",
+    "packs",
+    ".sync_version",
+    ".build_version",
+    ".skip_version",
+    "game_settings.toml",
+];
+
+/// Files listed per kind of problem before the rest are counted.
+const REPORT_LIMIT: usize = 20;
+
+/// The name the manifest's signature covers, as `--gen-manifest` writes it.
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// `/sync/manifest`, as written by `--gen-manifest`.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub struct FileManifest {
-    #[allow(dead_code)]
     pub version: String,
-    pub files: HashMap<String, FileInfo>,
+    /// Bytes per entry in each file's `chunks`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chunk_size: Option<u64>,
+    pub files: BTreeMap<String, FileInfo>,
 }
 
-#[derive(Debug, serde::Deserialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct FileInfo {
     pub checksum: String,
     pub size: u64,
+    /// SHA-256 of each `chunk_size` block of the file, in order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub chunks: Vec<String>,
+}
+
+/// What `--verify` found, as engine-relative paths.
+#[derive(Debug, Default)]
+pub struct VerifyReport {
+    pub checked: usize,
+    pub corrupted: Vec<String>,
+    pub missing: Vec<String>,
+    pub extra: Vec<String>,
+}
+
+impl VerifyReport {
+    /// Files a repair would download again.
+    pub fn bad_files(&self) -> usize {
+        self.corrupted.len() + self.missing.len()
+    }
+
+    pub fn print(&self) {
+        println!("Checked {} files", self.checked);
+        for (label, files) in [("Corrupted", &self.corrupted), ("Missing", &self.missing), ("Extra", &self.extra)] {
+            if files.is_empty() {
+                continue;
+            }
+            println!("{} ({}):", label, files.len());
+            for file in files.iter().take(REPORT_LIMIT) {
+                println!("  {}", file);
+            }
+            if files.len() > REPORT_LIMIT {
+                println!("  ... and {} more", files.len() - REPORT_LIMIT);
+            }
+        }
+    }
 }
 
 #[derive(Debug, serde::Deserialize)]
@@ -27,24 +100,60 @@ struct VersionResponse {
 pub struct SyncManager {
     config: Config,
     client: reqwest::Client,
+    limiter: RateLimiter,
+    /// Keeps what the sync overwrites, when the sync changes version.
+    rollback: Mutex<Option<RollbackPoint>>,
 }
 
 impl SyncManager {
     pub fn new(config: Config) -> Result<Self> {
-        let client = reqwest::Client::builder()
+        let client = http::client_builder(&config)?
             .timeout(std::time::Duration::from_secs(600))  // 10 minutes for large downloads
             .connect_timeout(std::time::Duration::from_secs(30))
             .build()?;
 
-        Ok(Self { config, client })
+        Ok(Self {
+            limiter: RateLimiter::new(&config),
+            config,
+            client,
+            rollback: Mutex::new(None),
+        })
+    }
+
+    /// Copies every file aside before it's overwritten or removed, until
+    /// [`SyncManager::take_rollback_point`].
+    pub fn set_rollback_point(&self, point: RollbackPoint) {
+        if let Ok(mut rollback) = self.rollback.lock() {
+            *rollback = Some(point);
+        }
+    }
+
+    pub fn take_rollback_point(&self) -> Option<RollbackPoint> {
+        self.rollback.lock().ok()?.take()
+    }
+
+    /// Keeps `file_path` for the rollback point. If it can't be kept the
+    /// sync goes on without one, since a partial one can't restore anything.
+    fn keep_for_rollback(&self, file_path: &str) {
+        let Ok(mut rollback) = self.rollback.lock() else {
+            return;
+        };
+        let Some(point) = rollback.as_mut() else {
+            return;
+        };
+        let kept = Self::normalize_path_for_platform(file_path).and_then(|path| point.keep(&path));
+        if let Err(e) = kept {
+            logging::warn(&format!("Not keeping this version for rollback: {:#}", e));
+            *rollback = None;
+        }
     }
 
     pub async fn check_server(&self) -> Result<String> {
-        let url = format!("{}/sync/version", self.config.server_url);
+        let url = self.config.endpoint(["sync", "version"])?;
         
         let response = self
             .client
-            .get(&url)
+            .get(url)
             .send()
             .await
             .context("Failed to connect to server")?;
@@ -62,70 +171,434 @@ impl SyncManager {
         Ok(version_info.version)
     }
 
+    /// The server's manifest, once `/sync/manifest.sig` shows a trusted
+    /// release key signed it. It decides what lands in the engine directory,
+    /// so an unsigned one isn't used.
     pub async fn get_manifest(&self) -> Result<FileManifest> {
-        let url = format!("{}/sync/manifest", self.config.server_url);
-        
+        let text = self.fetch_bytes(&["sync", "manifest"]).await.context("Failed to fetch manifest")?;
+        let signature = self
+            .fetch_bytes(&["sync", "manifest.sig"])
+            .await
+            .context("Failed to fetch the manifest's signature")?;
+        TrustedKeys::load(&self.config)?
+            .verify_file(MANIFEST_FILE, &text, &signature)
+            .context("The server's manifest isn't signed by a trusted release key")?;
+        logging::success("Manifest signature verified");
+
+        serde_json::from_slice(&text).context("Failed to parse manifest")
+    }
+
+    async fn fetch_bytes(&self, segments: &[&str]) -> Result<Vec<u8>> {
+        let response = self.client.get(self.config.endpoint(segments.iter().copied())?).send().await?;
+        if !response.status().is_success() {
+            anyhow::bail!("Server returned error: {}", response.status());
+        }
+        Ok(response.bytes().await?.to_vec())
+    }
+
+    pub fn calculate_checksum(path: &Path) -> Result<String> {
+        let bytes = std::fs::read(path)?;
+        Ok(Self::checksum_bytes(&bytes))
+    }
+
+    pub fn checksum_bytes(bytes: &[u8]) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(bytes);
+        hex::encode(hasher.finalize())
+    }
+
+    fn local_version_path(&self) -> PathBuf {
+        self.config.engine_dir().join(".sync_version")
+    }
+
+    /// The server version the engine directory was last synced to.
+    pub fn local_version(&self) -> Option<String> {
+        let version = std::fs::read_to_string(self.local_version_path()).ok()?;
+        let version = version.trim();
+        (!version.is_empty()).then(|| version.to_string())
+    }
+
+    pub fn save_local_version(&self, version: &str) -> Result<()> {
+        std::fs::write(self.local_version_path(), version)?;
+        Ok(())
+    }
+
+    /// Patches the engine directory from version `from` to `to`, one hop at
+    /// a time. `Ok(false)` when the server has no patch chain between them.
+    /// The local version is saved after every hop, so an interrupted chain
+    /// resumes where it stopped.
+    pub async fn apply_patch_chain(&self, from: &str, to: &str) -> Result<bool> {
+        let mut current = from.to_string();
+        let mut downloaded = 0u64;
+        let mut hops = 0;
+
+        while current != to {
+            if hops == MAX_PATCH_HOPS {
+                anyhow::bail!("Patch chain from {} to {} is longer than {} steps", from, to, MAX_PATCH_HOPS);
+            }
+            let Some(manifest) = self.get_patch(&current, to).await? else {
+                if hops == 0 {
+                    return Ok(false);
+                }
+                anyhow::bail!("Patch chain from {} to {} stops at {}", from, to, current);
+            };
+            if manifest.from != current || manifest.to == current {
+                anyhow::bail!(
+                    "Server sent a patch from {} to {} when asked for {} to {}",
+                    manifest.from,
+                    manifest.to,
+                    current,
+                    to
+                );
+            }
+
+            logging::info(&format!("Patching {} -> {}", manifest.from, manifest.to));
+            downloaded += self.apply_patch(&manifest).await?;
+            self.save_local_version(&manifest.to)?;
+            current = manifest.to;
+            hops += 1;
+        }
+
+        logging::success(&format!(
+            "Patched {} -> {} in {} step(s), {} downloaded",
+            from,
+            to,
+            hops,
+            indicatif::HumanBytes(downloaded)
+        ));
+        Ok(true)
+    }
+
+    /// The first hop from `from` towards `to`, once `.sig` beside it shows a
+    /// trusted release key signed it: its checksums are all that vouch for
+    /// the files it writes and removes.
+    async fn get_patch(&self, from: &str, to: &str) -> Result<Option<PatchManifest>> {
+        let url = self.config.endpoint(["sync", "patch", from, to])?;
+
         let response = self
             .client
-            .get(&url)
+            .get(url)
             .send()
             .await
-            .context("Failed to fetch manifest")?;
+            .context("Failed to fetch patch")?;
 
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
         if !response.status().is_success() {
-            anyhow::bail!("Failed to get manifest: {}", response.status());
+            anyhow::bail!("Failed to get patch {} -> {}: {}", from, to, response.status());
         }
 
-        let manifest: FileManifest = response
-            .json()
+        let text = response.bytes().await.context("Failed to fetch patch")?;
+        let signature = self
+            .fetch_bytes(&["sync", "patch", from, &format!("{}.sig", to)])
             .await
-            .context("Failed to parse manifest")?;
+            .context("Failed to fetch the patch's signature")?;
+        TrustedKeys::load(&self.config)?
+            .verify_file(patch::MANIFEST_FILE, &text, &signature)
+            .context("The patch isn't signed by a trusted release key")?;
 
-        Ok(manifest)
+        let manifest = serde_json::from_slice(&text).context("Failed to parse patch manifest")?;
+        Ok(Some(manifest))
     }
 
-    pub fn calculate_checksum(path: &Path) -> Result<String> {
-        let bytes = std::fs::read(path)?;
-        let mut hasher = Sha256::new();
-        hasher.update(&bytes);
-        Ok(hex::encode(hasher.finalize()))
+    /// Applies one hop and returns the bytes it downloaded. A file that
+    /// isn't the version the patch was made against is downloaded whole.
+    async fn apply_patch(&self, manifest: &PatchManifest) -> Result<u64> {
+        let data = if manifest.data_size > 0 {
+            self.download_patch_data(manifest).await?
+        } else {
+            Vec::new()
+        };
+        let mut downloaded = data.len() as u64;
+        let engine_dir = self.config.engine_dir();
+        // All of them before anything is touched, so a bad manifest can't leave half a patch
+        for file_path in manifest.files.keys().chain(&manifest.removed) {
+            Self::normalize_path_for_platform(file_path)?;
+        }
+
+        for (file_path, file_patch) in &manifest.files {
+            let local_path = engine_dir.join(Self::normalize_path_for_platform(file_path)?);
+            self.keep_for_rollback(file_path);
+            let base = match &file_patch.base_checksum {
+                None => Some(Vec::new()),
+                Some(expected) => std::fs::read(&local_path)
+                    .ok()
+                    .filter(|bytes| Self::checksum_bytes(bytes) == *expected),
+            };
+            let patched = match base {
+                Some(base) => patch::apply(&base, &data, file_patch),
+                None => Err(anyhow::anyhow!("local file doesn't match the patch base")),
+            };
+
+            match patched {
+                Ok(bytes) => {
+                    if let Some(parent) = local_path.parent() {
+                        std::fs::create_dir_all(parent)?;
+                    }
+                    // Write beside the file and swap it in, so a crash never leaves half a file
+                    let mut temp_name = local_path.file_name().unwrap_or_default().to_os_string();
+                    temp_name.push(".patch-tmp");
+                    let temp_path = local_path.with_file_name(temp_name);
+                    std::fs::write(&temp_path, &bytes)?;
+                    std::fs::rename(&temp_path, &local_path)?;
+                }
+                Err(e) => {
+                    logging::warn(&format!("{}: {} - downloading it whole", file_path, e));
+                    let info = FileInfo {
+                        checksum: file_patch.checksum.clone(),
+                        size: file_patch.size,
+                        chunks: Vec::new(),
+                    };
+                    self.download_file(file_path, &local_path, &info).await?;
+                    downloaded += file_patch.size;
+                }
+            }
+        }
+
+        for file_path in &manifest.removed {
+            let local_path = engine_dir.join(Self::normalize_path_for_platform(file_path)?);
+            self.keep_for_rollback(file_path);
+            match std::fs::remove_file(&local_path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                    logging::warn(&format!("Could not remove {}: {}", file_path, e));
+                }
+                _ => {}
+            }
+        }
+
+        Ok(downloaded)
     }
 
+    async fn download_patch_data(&self, manifest: &PatchManifest) -> Result<Vec<u8>> {
+        let url = self
+            .config
+            .endpoint(["sync", "patch", manifest.from.as_str(), manifest.to.as_str(), "data"])?;
+
+        let response = self
+            .client
+            .get(url)
+            .send()
+            .await
+            .context("Failed to download patch data")?;
+
+        if !response.status().is_success() {
+            anyhow::bail!("Patch data download failed: {}", response.status());
+        }
+
+        let pb = logging::progress_bar(manifest.data_size);
+        let bytes = download_limit::read_body(response, &self.limiter, &pb).await?;
+        pb.finish_and_clear();
+
+        if bytes.len() as u64 != manifest.data_size {
+            anyhow::bail!("Patch data is {} bytes, expected {}", bytes.len(), manifest.data_size);
+        }
+        Ok(bytes)
+    }
+
+    /// Downloads every file that's missing or out of date, up to
+    /// `download_parallelism` at a time, behind one progress bar. A file
+    /// that still fails after its retries doesn't stop the others; the sync
+    /// fails once they're done, and their partial downloads resume next time.
     pub async fn sync_files(&self, manifest: &FileManifest) -> Result<u64> {
+        let pending = self.outdated_files(manifest)?;
+        if pending.is_empty() {
+            logging::success("All files up to date");
+            return Ok(0);
+        }
+        self.download_files(pending).await
+    }
+
+    /// [`SyncManager::sync_files`] for `--install-from`: the files come from
+    /// `source` instead of the server.
+    pub fn copy_files(&self, source: &mut LocalSource, manifest: &FileManifest) -> Result<u64> {
+        let pending = self.outdated_files(manifest)?;
+        if pending.is_empty() {
+            logging::success("All files up to date");
+            return Ok(0);
+        }
+        let total_bytes: u64 = pending.iter().map(|(_, _, info)| info.size).sum();
+        logging::info(&format!(
+            "Copying {} files ({}) from {}",
+            pending.len(),
+            indicatif::HumanBytes(total_bytes),
+            source.describe()
+        ));
+
+        let pb = logging::progress_bar(total_bytes);
+        let mut copied = 0u64;
+        let mut failed = Vec::new();
+        for (file_path, local_path, info) in &pending {
+            match copy_file(source, file_path, local_path, info) {
+                Ok(()) => copied += 1,
+                Err(e) => {
+                    pb.suspend(|| logging::error(&format!("{}: {:#}", file_path, e)));
+                    failed.push(file_path.as_str());
+                }
+            }
+            pb.inc(info.size);
+        }
+        pb.finish_and_clear();
+
+        if !failed.is_empty() {
+            anyhow::bail!("{} of {} files could not be copied: {}", failed.len(), pending.len(), failed.join(", "));
+        }
+        logging::success(&format!("Copied {} files", copied));
+        Ok(copied)
+    }
+
+    /// Files in `manifest` that are missing or differ locally, kept for the
+    /// rollback point as they're found.
+    fn outdated_files(&self, manifest: &FileManifest) -> Result<Vec<(String, PathBuf, FileInfo)>> {
         let engine_dir = self.config.engine_dir();
         std::fs::create_dir_all(&engine_dir)?;
 
-        let mut synced_count = 0u64;
-
+        let mut pending = Vec::new();
         for (file_path, info) in &manifest.files {
-            let native_path = Self::normalize_path_for_platform(file_path);
+            let native_path = Self::normalize_path_for_platform(file_path)?;
             let local_path = engine_dir.join(&native_path);
-            let needs_sync = self.file_needs_sync(&local_path, info)?;
+            if self.file_needs_sync(&local_path, info)? {
+                self.keep_for_rollback(file_path);
+                pending.push((file_path.clone(), local_path, info.clone()));
+            }
+        }
+        Ok(pending)
+    }
 
-            if needs_sync {
-                self.download_file(file_path, &local_path, info).await?;
-                synced_count += 1;
+    /// Downloads the corrupted and missing files in `report` again, and
+    /// nothing else.
+    pub async fn repair(&self, manifest: &FileManifest, report: &VerifyReport) -> Result<u64> {
+        let engine_dir = self.config.engine_dir();
+        let pending = report
+            .corrupted
+            .iter()
+            .chain(&report.missing)
+            .filter_map(|file_path| {
+                let info = manifest.files.get(file_path)?.clone();
+                let local_path = engine_dir.join(Self::normalize_path_for_platform(file_path).ok()?);
+                Some((file_path.clone(), local_path, info))
+            })
+            .collect();
+        self.download_files(pending).await
+    }
+
+    /// Checks every file in `manifest` against its checksum and looks for
+    /// files the manifest doesn't have.
+    pub fn verify_files(&self, manifest: &FileManifest) -> Result<VerifyReport> {
+        let engine_dir = self.config.engine_dir();
+        let mut report = VerifyReport::default();
+        let mut files: Vec<(&String, &FileInfo)> = manifest.files.iter().collect();
+        files.sort_by_key(|(file_path, _)| *file_path);
+
+        let pb = logging::progress_bar(files.iter().map(|(_, info)| info.size).sum());
+        for (file_path, info) in files {
+            let local_path = engine_dir.join(Self::normalize_path_for_platform(file_path)?);
+            if !local_path.exists() {
+                report.missing.push(file_path.clone());
+            } else if self.file_needs_sync(&local_path, info)? {
+                report.corrupted.push(file_path.clone());
             }
+            report.checked += 1;
+            pb.inc(info.size);
         }
+        pb.finish_and_clear();
 
-        if synced_count > 0 {
-            logging::success(&format!("Synced {} files", synced_count));
-        } else {
-            logging::success("All files up to date");
+        let expected: HashSet<PathBuf> = manifest
+            .files
+            .keys()
+            .map(|file_path| Self::normalize_path_for_platform(file_path))
+            .collect::<Result<_>>()?;
+        let mut local = Vec::new();
+        collect_files(&engine_dir, Path::new(""), &mut local)?;
+        report.extra = local
+            .into_iter()
+            .filter(|file| !expected.contains(file))
+            .map(|file| file.to_string_lossy().replace('\\', "/"))
+            .collect();
+        report.extra.sort();
+        Ok(report)
+    }
+
+    async fn download_files(&self, pending: Vec<(String, PathBuf, FileInfo)>) -> Result<u64> {
+        if pending.is_empty() {
+            return Ok(0);
+        }
+        let total_files = pending.len();
+        let total_bytes: u64 = pending.iter().map(|(_, _, info)| info.size).sum();
+        let parallelism = self.config.download_parallelism.clamp(1, total_files);
+        logging::download(&format!(
+            "Downloading {} files ({}), {} at a time",
+            total_files,
+            indicatif::HumanBytes(total_bytes),
+            parallelism
+        ));
+
+        let pb = logging::progress_bar(total_bytes);
+        let mut pending = pending.into_iter();
+        let mut tasks = tokio::task::JoinSet::new();
+        let mut synced_count = 0u64;
+        let mut failed = Vec::new();
+
+        loop {
+            while tasks.len() < parallelism {
+                let Some((file_path, local_path, info)) = pending.next() else {
+                    break;
+                };
+                let client = self.client.clone();
+                let url = self.file_url(&file_path)?;
+                let retries = self.config.download_retries;
+                let limiter = self.limiter.clone();
+                let pb = pb.clone();
+                tasks.spawn(async move {
+                    let result = fetch_file(&client, &url, &local_path, &info, retries, &limiter, &pb).await;
+                    (file_path, result)
+                });
+            }
+            let Some(joined) = tasks.join_next().await else {
+                break;
+            };
+            match joined? {
+                (_, Ok(())) => synced_count += 1,
+                (file_path, Err(e)) => {
+                    pb.suspend(|| logging::error(&format!("{}: {:#}", file_path, e)));
+                    failed.push(file_path);
+                }
+            }
         }
+        pb.finish_and_clear();
 
+        if !failed.is_empty() {
+            anyhow::bail!(
+                "{} of {} files failed to download: {}",
+                failed.len(),
+                total_files,
+                failed.join(", ")
+            );
+        }
+
+        logging::success(&format!("Synced {} files", synced_count));
         Ok(synced_count)
     }
 
-    fn normalize_path_for_platform(path: &str) -> PathBuf {
+    fn file_url(&self, remote_path: &str) -> Result<reqwest::Url> {
+        self.config.endpoint(["sync", "file"].into_iter().chain(remote_path.split('/')))
+    }
+
+    /// `path` from a manifest as a path under the engine directory. One
+    /// that could leave it (`..`, absolute, a drive or UNC prefix) is refused.
+    fn normalize_path_for_platform(path: &str) -> Result<PathBuf> {
         #[cfg(windows)]
-        {
-            PathBuf::from(path.replace('/', "\\"))
-        }
+        let native = PathBuf::from(path.replace('/', "\\"));
         #[cfg(not(windows))]
-        {
-            PathBuf::from(path)
+        let native = PathBuf::from(path);
+
+        let escapes = native
+            .components()
+            .any(|component| matches!(component, Component::ParentDir | Component::RootDir | Component::Prefix(_)));
+        if escapes || native.as_os_str().is_empty() {
+            anyhow::bail!("Refusing manifest path outside the engine directory: {}", path);
         }
+        Ok(native)
     }
 
     fn file_needs_sync(&self, local_path: &Path, info: &FileInfo) -> Result<bool> {
@@ -148,89 +621,330 @@ impl SyncManager {
         local_path: &Path,
         info: &FileInfo,
     ) -> Result<()> {
-        let url = format!("{}/sync/file/{}", self.config.server_url, remote_path);
+        logging::download(&format!("Downloading {}", remote_path));
+        let url = self.file_url(remote_path)?;
+        let retries = self.config.download_retries;
+        fetch_file(&self.client, &url, local_path, info, retries, &self.limiter, &ProgressBar::hidden())
+            .await
+            .with_context(|| format!("Failed to download {}", remote_path))
+    }
 
-        if let Some(parent) = local_path.parent() {
-            std::fs::create_dir_all(parent)?;
-        }
+    /// Replaces the engine directory with the server's full archive. Only
+    /// the files `manifest` lists are taken from it, each checked against
+    /// the manifest in a staging directory before anything is replaced.
+    pub async fn download_full_archive(&self, manifest: &FileManifest) -> Result<()> {
+        let url = self.config.endpoint(["sync", "full.zip"])?;
+        let archive_path = self.config.install_dir.join("engine.zip");
+        let engine_dir = self.config.engine_dir();
+        let staging = engine_dir.with_extension("partial");
 
-        logging::download(&format!("Downloading {}", remote_path));
+        logging::info("Downloading full engine archive...");
 
         let response = self
             .client
-            .get(&url)
+            .get(url)
             .send()
             .await
-            .context("Download request failed")?;
+            .context("Failed to download archive")?;
 
         if !response.status().is_success() {
-            anyhow::bail!("Failed to download {}: {}", remote_path, response.status());
+            anyhow::bail!("Archive download failed: {}", response.status());
         }
 
-        let bytes = response.bytes().await?;
+        let total_size = response.content_length().unwrap_or(0);
+        let pb = logging::progress_bar(total_size);
 
-        let mut hasher = Sha256::new();
-        hasher.update(&bytes);
-        let checksum = hex::encode(hasher.finalize());
+        let bytes = download_limit::read_body(response, &self.limiter, &pb).await?;
+        pb.finish_and_clear();
 
-        if checksum != info.checksum {
-            anyhow::bail!(
-                "Checksum mismatch for {}: expected {}, got {}",
-                remote_path,
-                info.checksum,
-                checksum
-            );
+        std::fs::write(&archive_path, &bytes)?;
+
+        logging::info("Extracting and checking archive...");
+        if staging.exists() {
+            std::fs::remove_dir_all(&staging).with_context(|| format!("Failed to clear {}", staging.display()))?;
+        }
+        let extracted = extract_archive(&archive_path, &staging, manifest);
+        let _ = std::fs::remove_file(&archive_path);
+        if let Err(e) = extracted {
+            let _ = std::fs::remove_dir_all(&staging);
+            return Err(e);
         }
 
-        std::fs::write(local_path, &bytes)?;
+        // ALWAYS clear the engine cache before moving in fresh code
+        // This ensures we never run stale/outdated builds
+        if engine_dir.exists() {
+            logging::info("Clearing cached engine files...");
+            std::fs::remove_dir_all(&engine_dir).context("Could not clear cache")?;
+        }
+        std::fs::rename(&staging, &engine_dir)
+            .with_context(|| format!("Failed to move the engine files into {}", engine_dir.display()))?;
+
+        logging::success("Engine files extracted");
         Ok(())
     }
+}
 
-    pub async fn download_full_archive(&self) -> Result<()> {
-        let url = format!("{}/sync/full.zip", self.config.server_url);
-        let archive_path = self.config.install_dir.join("engine.zip");
-        let engine_dir = self.config.engine_dir();
+/// Extracts the files `manifest` lists from `archive` into `dir`, each
+/// only if it matches its checksum. Anything else in the archive is left out.
+fn extract_archive(archive: &Path, dir: &Path, manifest: &FileManifest) -> Result<()> {
+    let file = std::fs::File::open(archive)?;
+    let mut archive = zip::ZipArchive::new(file).context("The archive isn't a zip archive")?;
+    for (file_path, info) in &manifest.files {
+        let local_path = dir.join(SyncManager::normalize_path_for_platform(file_path)?);
+        let mut entry = archive
+            .by_name(file_path)
+            .with_context(|| format!("{} is missing from the archive", file_path))?;
+        let mut bytes = Vec::with_capacity(entry.size() as usize);
+        entry.read_to_end(&mut bytes)?;
+        if bytes.len() as u64 != info.size || SyncManager::checksum_bytes(&bytes) != info.checksum {
+            anyhow::bail!("{} in the archive doesn't match the manifest", file_path);
+        }
+        if let Some(parent) = local_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&local_path, &bytes)?;
+    }
+    Ok(())
+}
 
-        // ALWAYS clear the engine cache before downloading fresh code
-        // This ensures we never run stale/outdated builds
-        if engine_dir.exists() {
-            logging::info("Clearing cached engine files...");
-            if let Err(e) = std::fs::remove_dir_all(&engine_dir) {
-                logging::warn(&format!("Could not clear cache: {} - continuing anyway", e));
-            } else {
-                logging::success("Cache cleared");
+/// Every file under `dir`, relative to the engine directory, except the
+/// [`UNSYNCED`] ones and partial downloads.
+pub fn collect_files(dir: &Path, relative: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in std::fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if UNSYNCED.contains(&name.as_ref()) || name.ends_with(".part") {
+            continue;
+        }
+        let path = relative.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            collect_files(&entry.path(), &path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Writes `file_path` from `source` to `local_path` if it matches `info`.
+fn copy_file(source: &mut LocalSource, file_path: &str, local_path: &Path, info: &FileInfo) -> Result<()> {
+    let bytes = source.read(file_path)?;
+    if bytes.len() as u64 != info.size || SyncManager::checksum_bytes(&bytes) != info.checksum {
+        anyhow::bail!("the copy in {} doesn't match the manifest", source.describe());
+    }
+    if let Some(parent) = local_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut part_name = local_path.file_name().unwrap_or_default().to_os_string();
+    part_name.push(".part");
+    let part_path = local_path.with_file_name(part_name);
+    std::fs::write(&part_path, &bytes)?;
+    std::fs::rename(&part_path, local_path)?;
+    Ok(())
+}
+
+/// Downloads `url` to `local_path` through a `.part` file beside it. An
+/// interrupted download picks up from the end of the part file with a Range
+/// request, and a failed attempt is retried up to `retries` times with
+/// exponential backoff. The file only replaces `local_path` once its
+/// checksum matches. Bytes received are added to `pb`.
+pub async fn fetch_file(
+    client: &reqwest::Client,
+    url: &reqwest::Url,
+    local_path: &Path,
+    info: &FileInfo,
+    retries: u32,
+    limiter: &RateLimiter,
+    pb: &ProgressBar,
+) -> Result<()> {
+    if let Some(parent) = local_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut part_name = local_path.file_name().unwrap_or_default().to_os_string();
+    part_name.push(".part");
+    let part_path = local_path.with_file_name(part_name);
+
+    // This file's share of `pb`, so a restart can take it back
+    let mut counted = 0u64;
+    let mut attempt = 0;
+    loop {
+        match fetch_attempt(client, url, &part_path, info, limiter, pb, &mut counted).await {
+            Ok(()) => break,
+            Err(e) if attempt < retries => {
+                attempt += 1;
+                let backoff = 1u64.checked_shl(attempt).unwrap_or(u64::MAX);
+                let delay = Duration::from_secs(backoff.min(MAX_RETRY_DELAY_SECS));
+                pb.suspend(|| {
+                    logging::warn(&format!(
+                        "{} - retry {}/{} in {}s",
+                        e,
+                        attempt,
+                        retries,
+                        delay.as_secs()
+                    ))
+                });
+                tokio::time::sleep(delay).await;
             }
+            Err(e) => return Err(e),
         }
+    }
 
-        logging::info("Downloading full engine archive...");
+    std::fs::rename(&part_path, local_path)?;
+    Ok(())
+}
 
-        let response = self
-            .client
-            .get(&url)
-            .send()
-            .await
-            .context("Failed to download archive")?;
+async fn fetch_attempt(
+    client: &reqwest::Client,
+    url: &reqwest::Url,
+    part_path: &Path,
+    info: &FileInfo,
+    limiter: &RateLimiter,
+    pb: &ProgressBar,
+    counted: &mut u64,
+) -> Result<()> {
+    let mut offset = std::fs::metadata(part_path).map(|m| m.len()).unwrap_or(0);
+    if offset > info.size {
+        std::fs::remove_file(part_path)?;
+        offset = 0;
+    }
 
-        if !response.status().is_success() {
-            anyhow::bail!("Archive download failed: {}", response.status());
+    if offset < info.size {
+        let mut request = client.get(url.clone());
+        if offset > 0 {
+            request = request.header(reqwest::header::RANGE, format!("bytes={}-", offset));
+        }
+        let mut response = request.send().await.context("Download request failed")?;
+
+        let status = response.status();
+        // Servers that ignore Range answer 200 with the whole file
+        let resumed = status == reqwest::StatusCode::PARTIAL_CONTENT
+            && response
+                .headers()
+                .get(reqwest::header::CONTENT_RANGE)
+                .and_then(|range| range.to_str().ok())
+                .is_some_and(|range| range.starts_with(&format!("bytes {}-", offset)));
+        if !status.is_success() {
+            if status == reqwest::StatusCode::RANGE_NOT_SATISFIABLE {
+                std::fs::remove_file(part_path)?;
+            }
+            anyhow::bail!("Server returned {}", status);
+        }
+        if !resumed {
+            offset = 0;
         }
 
-        let total_size = response.content_length().unwrap_or(0);
-        let pb = logging::progress_bar(total_size);
+        // Make this file's share of the bar match what's on disk
+        if offset >= *counted {
+            pb.inc(offset - *counted);
+        } else {
+            pb.set_position(pb.position().saturating_sub(*counted - offset));
+        }
+        *counted = offset;
 
-        let bytes = response.bytes().await?;
-        pb.finish_and_clear();
+        let mut options = tokio::fs::OpenOptions::new();
+        if resumed {
+            options.append(true);
+        } else {
+            options.write(true).create(true).truncate(true);
+        }
+        let mut file = options.open(part_path).await?;
+        while let Some(chunk) = response.chunk().await? {
+            limiter.take(chunk.len()).await;
+            file.write_all(&chunk).await?;
+            pb.inc(chunk.len() as u64);
+            *counted += chunk.len() as u64;
+        }
+        file.flush().await?;
+    }
 
-        std::fs::write(&archive_path, &bytes)?;
+    let checksum = SyncManager::calculate_checksum(part_path)?;
+    if checksum != info.checksum {
+        std::fs::remove_file(part_path)?;
+        anyhow::bail!("Checksum mismatch: expected {}, got {}", info.checksum, checksum);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn zip_with(path: &Path, files: &[(&str, &[u8])]) {
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(path).unwrap());
+        for (name, contents) in files {
+            zip.start_file(*name, zip::write::FileOptions::default()).unwrap();
+            zip.write_all(contents).unwrap();
+        }
+        zip.finish().unwrap();
+    }
 
-        logging::info("Extracting archive...");
-        let file = std::fs::File::open(&archive_path)?;
-        let mut archive = zip::ZipArchive::new(file)?;
-        archive.extract(self.config.engine_dir())?;
+    fn manifest_of(files: &[(&str, &[u8])]) -> FileManifest {
+        let files = files
+            .iter()
+            .map(|(name, contents)| {
+                let info = FileInfo {
+                    checksum: SyncManager::checksum_bytes(contents),
+                    size: contents.len() as u64,
+                    chunks: Vec::new(),
+                };
+                (name.to_string(), info)
+            })
+            .collect();
+        FileManifest {
+            version: "1.0.0".to_string(),
+            chunk_size: None,
+            files,
+        }
+    }
 
-        std::fs::remove_file(&archive_path)?;
+    #[test]
+    fn extracts_only_what_the_manifest_vouches_for() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path().join("full.zip");
+        let manifest = manifest_of(&[("bin/game.exe", b"game"), ("assets/a.pak", b"pak")]);
+
+        zip_with(&archive, &[("bin/game.exe", b"game"), ("assets/a.pak", b"pak"), ("bin/extra.dll", b"x")]);
+        let out = dir.path().join("ok");
+        extract_archive(&archive, &out, &manifest).unwrap();
+        assert_eq!(std::fs::read(out.join("bin").join("game.exe")).unwrap(), b"game");
+        assert!(!out.join("bin").join("extra.dll").exists());
+
+        zip_with(&archive, &[("bin/game.exe", b"evil"), ("assets/a.pak", b"pak")]);
+        assert!(extract_archive(&archive, &dir.path().join("tampered"), &manifest).is_err());
+        zip_with(&archive, &[("bin/game.exe", b"game")]);
+        assert!(extract_archive(&archive, &dir.path().join("missing"), &manifest).is_err());
+    }
 
-        logging::success("Engine files extracted");
-        Ok(())
+    #[test]
+    fn file_urls_keep_odd_names_in_their_path_segment() {
+        let config = Config {
+            server_url: "https://example.com/api".to_string(),
+            channel: crate::config::Channel::Beta,
+            ..Config::default()
+        };
+        let sync = SyncManager::new(config).unwrap();
+        assert_eq!(
+            sync.file_url("assets/50% off #1?.pak").unwrap().as_str(),
+            "https://example.com/api/sync/file/assets/50%25%20off%20%231%3F.pak?channel=beta"
+        );
+    }
+
+    #[test]
+    fn manifest_paths_stay_in_the_engine_directory() {
+        assert_eq!(
+            SyncManager::normalize_path_for_platform("bin/game.exe").unwrap(),
+            Path::new("bin").join("game.exe")
+        );
+        assert!(SyncManager::normalize_path_for_platform("./assets/a.pak").is_ok());
+
+        for path in ["../launcher.exe", "assets/../../outside", "/etc/passwd", ""] {
+            assert!(SyncManager::normalize_path_for_platform(path).is_err(), "{} was accepted", path);
+        }
+        #[cfg(windows)]
+        for path in ["C:\\Windows\\System32\\x.dll", "C:x.dll", "\\\\server\\share\\x", "..\\x"] {
+            assert!(SyncManager::normalize_path_for_platform(path).is_err(), "{} was accepted", path);
+        }
     }
 }
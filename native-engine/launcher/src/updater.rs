@@ -3,19 +3,23 @@ use sha2::{Digest, Sha256};
 use std::path::Path;
 
 use crate::config::Config;
+use crate::http;
 use crate::logging;
+use crate::signing::{self, SignedKeys, TrustedKeys};
 
 #[derive(Debug, Clone)]
 pub struct UpdateInfo {
-    #[allow(dead_code)]
     pub version: String,
     pub checksum: String,
+    /// Ed25519 signature over [`signing::update_message`].
+    pub signature: String,
 }
 
 #[derive(Debug, serde::Deserialize)]
 struct VersionResponse {
     version: String,
     checksum: Option<String>,
+    signature: Option<String>,
 }
 
 pub struct Updater {
@@ -25,7 +29,7 @@ pub struct Updater {
 
 impl Updater {
     pub fn new(config: Config) -> Result<Self> {
-        let client = reqwest::Client::builder()
+        let client = http::client_builder(&config)?
             .timeout(std::time::Duration::from_secs(30))
             .build()?;
 
@@ -33,11 +37,11 @@ impl Updater {
     }
 
     pub async fn check_for_update(&self) -> Result<Option<UpdateInfo>> {
-        let url = format!("{}/sync/launcher-version", self.config.server_url);
+        let url = self.config.endpoint(["sync", "launcher-version"])?;
         
         let response = self
             .client
-            .get(&url)
+            .get(url)
             .send()
             .await
             .context("Failed to connect to update server")?;
@@ -58,6 +62,9 @@ impl Updater {
             let checksum = version_info.checksum.ok_or_else(|| {
                 anyhow::anyhow!("Server did not provide checksum for update - refusing to update")
             })?;
+            let signature = version_info.signature.ok_or_else(|| {
+                anyhow::anyhow!("Server did not provide a signature for update - refusing to update")
+            })?;
             
             logging::info(&format!(
                 "Update available: {} -> {}",
@@ -67,6 +74,7 @@ impl Updater {
             Ok(Some(UpdateInfo {
                 version: version_info.version,
                 checksum,
+                signature,
             }))
         } else {
             logging::success("Launcher is up to date");
@@ -75,13 +83,13 @@ impl Updater {
     }
 
     pub async fn download_update(&self, temp_path: &Path) -> Result<()> {
-        let url = format!("{}/sync/launcher-binary", self.config.server_url);
+        let url = self.config.endpoint(["sync", "launcher-binary"])?;
         
         logging::download("Downloading launcher update...");
         
         let response = self
             .client
-            .get(&url)
+            .get(url)
             .send()
             .await
             .context("Failed to download update")?;
@@ -111,22 +119,59 @@ impl Updater {
         Ok(result == expected)
     }
 
-    pub async fn download_and_verify(&self, temp_path: &Path, expected_checksum: &str) -> Result<()> {
+    /// The trusted update keys, rotated first if the server has a newer
+    /// signed key list. A server without one keeps the current keys.
+    async fn trusted_keys(&self) -> Result<TrustedKeys> {
+        let mut keys = TrustedKeys::load(&self.config)?;
+
+        let url = self.config.endpoint(["sync", "launcher-keys"])?;
+        let response = self
+            .client
+            .get(url)
+            .send()
+            .await
+            .context("Failed to fetch update keys")?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(keys);
+        }
+        if !response.status().is_success() {
+            anyhow::bail!("Update keys request failed: HTTP {}", response.status());
+        }
+        let signed: SignedKeys = response.json().await.context("Failed to parse update keys")?;
+        keys.rotate(signed)?;
+        Ok(keys)
+    }
+
+    pub async fn download_and_verify(&self, temp_path: &Path, update: &UpdateInfo) -> Result<()> {
+        let keys = self.trusted_keys().await?;
         self.download_update(temp_path).await?;
         
         logging::info("Verifying update checksum...");
         
-        if !Self::verify_checksum(temp_path, expected_checksum)? {
+        if !Self::verify_checksum(temp_path, &update.checksum)? {
             if temp_path.exists() {
                 let _ = std::fs::remove_file(temp_path);
             }
             anyhow::bail!(
                 "Checksum verification failed! Update file may be corrupted or tampered with. Expected: {}",
-                expected_checksum
+                update.checksum
             );
         }
         
         logging::success("Checksum verified");
+
+        logging::info("Verifying update signature...");
+
+        let message = signing::update_message(&update.version, &update.checksum);
+        if let Err(e) = keys.verify(&message, &update.signature) {
+            let _ = std::fs::remove_file(temp_path);
+            return Err(e.context(format!(
+                "Update {} is not signed by the launcher's release key - refusing to apply it",
+                update.version
+            )));
+        }
+
+        logging::success("Signature verified");
         Ok(())
     }
 
@@ -0,0 +1,262 @@
+//! Rollback points for the engine directory, in `versions/<channel>/`.
+//!
+//! Before a sync moves the engine to a new version, every file it's about
+//! to overwrite or delete is copied aside, along with the build marker and
+//! game executable. That's enough to put the old version back without the
+//! server: `--rollback` restores the copies, deletes the files the update
+//! added and marks the build as current again. The last `keep_versions`
+//! rollback points are kept.
+//!
+//! A version that was rolled back from isn't synced again; the launcher
+//! stays on the old one until the server offers something newer.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use crate::config::Config;
+use crate::logging;
+use crate::orchestrator::BuildOrchestrator;
+
+/// Where the rollback point being written lives until its sync finishes.
+const PENDING_DIR: &str = ".pending";
+/// In the engine directory: the version rolled back from, not to be synced again.
+const SKIP_VERSION_FILE: &str = ".skip_version";
+
+/// `snapshot.json` in a rollback point.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Snapshot {
+    /// The version this rollback point restores.
+    version: String,
+    /// The version the sync moved on to.
+    replaced_by: String,
+    saved_at: String,
+    /// Engine files as they were at `version`, copied under `files/`.
+    saved: Vec<String>,
+    /// Files the sync created, deleted again on rollback.
+    added: Vec<String>,
+    /// Build outputs copied under `build/`; the rest are removed on rollback.
+    build: Vec<String>,
+}
+
+pub struct VersionStore {
+    dir: PathBuf,
+    engine_dir: PathBuf,
+    keep: usize,
+}
+
+impl VersionStore {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            dir: config.install_dir.join("versions").join(config.channel.as_str()),
+            engine_dir: config.engine_dir(),
+            keep: config.keep_versions,
+        }
+    }
+
+    /// The version rolled back from, if there's been a rollback.
+    pub fn skipped_version(&self) -> Option<String> {
+        let version = std::fs::read_to_string(self.engine_dir.join(SKIP_VERSION_FILE)).ok()?;
+        let version = version.trim();
+        (!version.is_empty()).then(|| version.to_string())
+    }
+
+    /// Starts a rollback point for the engine as it is at `version`.
+    pub fn begin(&self, version: &str) -> Result<RollbackPoint> {
+        let dir = self.dir.join(PENDING_DIR);
+        if dir.exists() {
+            std::fs::remove_dir_all(&dir).with_context(|| format!("Failed to clear {}", dir.display()))?;
+        }
+        std::fs::create_dir_all(dir.join("files"))?;
+
+        let mut build = Vec::new();
+        for output in BuildOrchestrator::build_outputs() {
+            let source = self.engine_dir.join(&output);
+            if source.exists() {
+                copy_file(&source, &dir.join("build").join(&output))?;
+                build.push(output.to_string_lossy().into_owned());
+            }
+        }
+
+        Ok(RollbackPoint {
+            dir,
+            engine_dir: self.engine_dir.clone(),
+            snapshot: Snapshot {
+                version: version.to_string(),
+                replaced_by: String::new(),
+                saved_at: String::new(),
+                saved: Vec::new(),
+                added: Vec::new(),
+                build,
+            },
+            seen: HashSet::new(),
+        })
+    }
+
+    fn snapshots(&self) -> Vec<(PathBuf, Snapshot)> {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        let mut snapshots: Vec<(PathBuf, Snapshot)> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.file_name().is_some_and(|name| name != PENDING_DIR))
+            .filter_map(|path| {
+                let text = std::fs::read_to_string(path.join("snapshot.json")).ok()?;
+                Some((path, serde_json::from_str(&text).ok()?))
+            })
+            .collect();
+        // Newest first
+        snapshots.sort_by(|(_, a), (_, b)| b.saved_at.cmp(&a.saved_at));
+        snapshots
+    }
+
+    fn prune(&self) {
+        for (path, snapshot) in self.snapshots().into_iter().skip(self.keep) {
+            if let Err(e) = std::fs::remove_dir_all(&path) {
+                logging::warn(&format!("Could not remove rollback point {}: {}", snapshot.version, e));
+            }
+        }
+    }
+
+    /// Prints the versions there are rollback points for.
+    pub fn list(&self) {
+        let snapshots = self.snapshots();
+        if snapshots.is_empty() {
+            println!("No rollback points in {}", self.dir.display());
+            return;
+        }
+        println!("Rollback points in {}:", self.dir.display());
+        for (_, snapshot) in snapshots {
+            println!("  {:<16} replaced by {} on {}", snapshot.version, snapshot.replaced_by, snapshot.saved_at);
+        }
+    }
+
+    /// Puts the engine back from `current` to `target`, or to the version
+    /// before `current`. Returns the version restored, for the caller to
+    /// record as synced.
+    pub fn rollback(&self, current: &str, target: Option<&str>) -> Result<String> {
+        let mut snapshots = self.snapshots();
+        let mut version = current.to_string();
+        let mut chain = Vec::new();
+        loop {
+            let Some(index) = snapshots.iter().position(|(_, s)| s.replaced_by == version) else {
+                let available: Vec<&str> = snapshots.iter().map(|(_, s)| s.version.as_str()).collect();
+                match target {
+                    Some(target) => anyhow::bail!(
+                        "No way back from {} to {}; rollback points: {}",
+                        current,
+                        target,
+                        if available.is_empty() { "none".to_string() } else { available.join(", ") }
+                    ),
+                    None => anyhow::bail!("No rollback point for the version before {}", current),
+                }
+            };
+            let (path, snapshot) = snapshots.remove(index);
+            version = snapshot.version.clone();
+            chain.push((path, snapshot));
+            if target.is_none_or(|target| target == version) {
+                break;
+            }
+        }
+
+        for (path, snapshot) in &chain {
+            logging::info(&format!("Restoring {} ({} files)", snapshot.version, snapshot.saved.len()));
+            self.restore(path, snapshot)?;
+            std::fs::remove_dir_all(path)?;
+        }
+
+        std::fs::write(self.engine_dir.join(SKIP_VERSION_FILE), current)?;
+        Ok(version)
+    }
+
+    fn restore(&self, path: &Path, snapshot: &Snapshot) -> Result<()> {
+        for file in &snapshot.added {
+            match std::fs::remove_file(self.engine_dir.join(file)) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                    anyhow::bail!("Could not remove {}: {}", file, e);
+                }
+                _ => {}
+            }
+        }
+        for file in &snapshot.saved {
+            copy_file(&path.join("files").join(file), &self.engine_dir.join(file))?;
+        }
+
+        // A build restored with its marker is used as is; without one it's rebuilt
+        for output in BuildOrchestrator::build_outputs() {
+            let name = output.to_string_lossy();
+            let local = self.engine_dir.join(&output);
+            if snapshot.build.iter().any(|b| *b == name) {
+                copy_file(&path.join("build").join(&output), &local)?;
+            } else if local.exists() {
+                std::fs::remove_file(&local)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A rollback point being filled in as a sync changes files.
+pub struct RollbackPoint {
+    dir: PathBuf,
+    engine_dir: PathBuf,
+    snapshot: Snapshot,
+    seen: HashSet<String>,
+}
+
+impl RollbackPoint {
+    /// Keeps the current copy of `file` (an engine-relative path) before the
+    /// sync overwrites or deletes it. Only the first call per file counts.
+    pub fn keep(&mut self, file: &Path) -> Result<()> {
+        let name = file.to_string_lossy().into_owned();
+        if !self.seen.insert(name.clone()) {
+            return Ok(());
+        }
+        let local = self.engine_dir.join(file);
+        if local.exists() {
+            copy_file(&local, &self.dir.join("files").join(file))?;
+            self.snapshot.saved.push(name);
+        } else {
+            self.snapshot.added.push(name);
+        }
+        Ok(())
+    }
+
+    /// Files the sync finished at `version`: stores the rollback point and
+    /// drops the oldest ones past the limit.
+    pub fn commit(mut self, store: &VersionStore, version: &str) -> Result<()> {
+        self.snapshot.replaced_by = version.to_string();
+        self.snapshot.saved_at = chrono::Utc::now().to_rfc3339();
+        std::fs::write(self.dir.join("snapshot.json"), serde_json::to_string_pretty(&self.snapshot)?)?;
+
+        let target = store.dir.join(dir_name(&self.snapshot.version));
+        if target.exists() {
+            std::fs::remove_dir_all(&target)?;
+        }
+        std::fs::rename(&self.dir, &target)?;
+        // Syncing again means the version is wanted after all
+        let _ = std::fs::remove_file(self.engine_dir.join(SKIP_VERSION_FILE));
+
+        store.prune();
+        logging::info(&format!("Kept {} for --rollback", self.snapshot.version));
+        Ok(())
+    }
+}
+
+/// A version as a directory name.
+fn dir_name(version: &str) -> String {
+    version
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_') { c } else { '_' })
+        .collect()
+}
+
+fn copy_file(from: &Path, to: &Path) -> Result<()> {
+    if let Some(parent) = to.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::copy(from, to).with_context(|| format!("Failed to copy {} to {}", from.display(), to.display()))?;
+    Ok(())
+}